@@ -1,8 +1,10 @@
 use crate::error::Result;
 
 use super::*;
+use fragment::Reassembler;
 
 use util::conn::conn_bridge::*;
+use util::conn::conn_pipe::pipe;
 use util::conn::*;
 
 use tokio::io::AsyncReadExt;
@@ -38,6 +40,12 @@ async fn create_new_association_pair(
             max_receive_buffer_size: 0,
             max_message_size: 0,
             name: "client".to_owned(),
+            max_association_buffered_amount: 0,
+            buffer_full_policy: Default::default(),
+            max_retransmissions: 0,
+            num_outbound_streams: 0,
+            num_inbound_streams: 0,
+            clock: None,
         })
         .await;
 
@@ -54,6 +62,12 @@ async fn create_new_association_pair(
             max_receive_buffer_size: 0,
             max_message_size: 0,
             name: "server".to_owned(),
+            max_association_buffered_amount: 0,
+            buffer_full_policy: Default::default(),
+            max_retransmissions: 0,
+            num_outbound_streams: 0,
+            num_inbound_streams: 0,
+            clock: None,
         })
         .await;
 
@@ -153,6 +167,13 @@ async fn close_association_pair(
     drop(closed_tx);
 }
 
+/// receive_ack handles a DATA_CHANNEL_ACK as if it was read from the remote, so that the
+/// reliability parameters of a channel opened by `dc` are in force without reading from it.
+async fn receive_ack(dc: &DataChannel) -> Result<()> {
+    let ack = Message::DataChannelAck(DataChannelAck {}).marshal()?;
+    dc.handle_dcep(&mut ack.clone()).await
+}
+
 //use std::io::Write;
 
 async fn pr_ordered_unordered_test(channel_type: ChannelType, is_ordered: bool) -> Result<()> {
@@ -195,7 +216,7 @@ async fn pr_ordered_unordered_test(channel_type: ChannelType, is_ordered: bool)
     assert_eq!(dc0.config, cfg, "local config should match");
     assert_eq!(dc1.config, cfg, "remote config should match");
 
-    dc0.commit_reliability_params();
+    receive_ack(&dc0).await?;
     dc1.commit_reliability_params();
 
     sbuf[0..4].copy_from_slice(&1u32.to_be_bytes());
@@ -345,7 +366,7 @@ async fn test_data_channel_channel_type_reliable_unordered() -> Result<()> {
     assert_eq!(dc0.config, cfg, "local config should match");
     assert_eq!(dc1.config, cfg, "remote config should match");
 
-    dc0.commit_reliability_params();
+    receive_ack(&dc0).await?;
     dc1.commit_reliability_params();
 
     sbuf[0..4].copy_from_slice(&1u32.to_be_bytes());
@@ -415,6 +436,118 @@ async fn test_data_channel_channel_type_partial_reliable_timed_unordered() -> Re
     pr_ordered_unordered_test(ChannelType::PartialReliableTimedUnordered, false).await
 }
 
+#[tokio::test]
+async fn test_data_channel_ordered_until_ack() -> Result<()> {
+    let (br, ca, cb) = Bridge::new(0, None, None);
+
+    let (a0, a1) = create_new_association_pair(&br, Arc::new(ca), Arc::new(cb)).await?;
+
+    let cfg = Config {
+        channel_type: ChannelType::PartialReliableRexmitUnordered,
+        reliability_parameter: 0,
+        label: "data".to_string(),
+        ..Default::default()
+    };
+
+    let dc0 = DataChannel::dial(&a0, 100, cfg.clone()).await?;
+    assert!(dc0.is_ordered(), "must be ordered until the ACK");
+    assert_eq!((ReliabilityType::Rexmit, 0), dc0.reliability());
+    bridge_process_at_least_one(&br).await;
+
+    let existing_data_channels: Vec<DataChannel> = Vec::new();
+    let dc1 = DataChannel::accept(&a1, Config::default(), &existing_data_channels).await?;
+    assert!(!dc1.is_ordered(), "the remote must be unordered once open");
+    assert_eq!((ReliabilityType::Rexmit, 0), dc1.reliability());
+    bridge_process_at_least_one(&br).await;
+
+    dc1.write(&Bytes::from_static(b"hello")).await?;
+
+    // Reads the ACK, then the message
+    let (done_tx, mut done_rx) = mpsc::channel(1);
+    let reader = dc0.clone();
+    tokio::spawn(async move {
+        let mut rbuf = vec![0u8; 1500];
+        let n = reader.read(&mut rbuf).await?;
+        let _ = done_tx.send(Bytes::copy_from_slice(&rbuf[..n])).await;
+
+        Result::<()>::Ok(())
+    });
+    let received = loop {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        while br.tick().await > 0 {}
+        if let Ok(received) = done_rx.try_recv() {
+            break received;
+        }
+    };
+    assert_eq!(&received[..], b"hello");
+    assert!(!dc0.is_ordered(), "must be unordered after the ACK");
+    assert_eq!((ReliabilityType::Rexmit, 0), dc0.reliability());
+
+    dc0.close().await?;
+    dc1.close().await?;
+    bridge_process_at_least_one(&br).await;
+
+    close_association_pair(&br, a0, a1).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_data_channel_negotiated_unreliable_unordered() -> Result<()> {
+    let mut sbuf = vec![0u8; 1000];
+    let mut rbuf = vec![0u8; 1500];
+
+    let (br, ca, cb) = Bridge::new(0, None, None);
+
+    let (a0, a1) = create_new_association_pair(&br, Arc::new(ca), Arc::new(cb)).await?;
+
+    let cfg = Config {
+        channel_type: ChannelType::PartialReliableRexmitUnordered,
+        reliability_parameter: 0,
+        negotiated: true,
+        label: "data".to_string(),
+        ..Default::default()
+    };
+
+    // Usable right away, nothing is sent to open a negotiated channel
+    let dc0 = DataChannel::dial(&a0, 100, cfg.clone()).await?;
+    let dc1 = DataChannel::dial(&a1, 100, cfg.clone()).await?;
+    for dc in [&dc0, &dc1] {
+        assert!(!dc.is_ordered(), "should be unordered");
+        assert_eq!((ReliabilityType::Rexmit, 0), dc.reliability());
+    }
+
+    for i in 1..=3u32 {
+        sbuf[0..4].copy_from_slice(&i.to_be_bytes());
+        let n = dc0.write(&Bytes::from(sbuf.clone())).await?;
+        assert_eq!(sbuf.len(), n, "data length should match");
+    }
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    br.drop_offset(0, 0, 1).await; // drop the first message on the wire
+    br.reorder(0).await;
+    bridge_process_at_least_one(&br).await;
+
+    // The lost message is skipped, the others are delivered as they arrive
+    for expected in [3u32, 2] {
+        let n = dc1.read(&mut rbuf[..]).await?;
+        assert_eq!(sbuf.len(), n, "data length should match");
+        assert_eq!(
+            expected,
+            u32::from_be_bytes([rbuf[0], rbuf[1], rbuf[2], rbuf[3]]),
+            "data should match"
+        );
+    }
+
+    dc0.close().await?;
+    dc1.close().await?;
+    bridge_process_at_least_one(&br).await;
+
+    close_association_pair(&br, a0, a1).await;
+
+    Ok(())
+}
+
 //TODO: remove this conditional test
 #[cfg(not(any(target_os = "macos", target_os = "windows")))]
 #[tokio::test]
@@ -671,3 +804,493 @@ async fn test_poll_data_channel() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_config_validate_label_and_protocol_length() -> Result<()> {
+    for len in [0, 65535] {
+        Config {
+            label: "l".repeat(len),
+            protocol: "p".repeat(len),
+            ..Default::default()
+        }
+        .validate()?;
+    }
+
+    let err = Config {
+        label: "l".repeat(65536),
+        ..Default::default()
+    }
+    .validate()
+    .expect_err("expected label too long error");
+    assert_eq!(err, Error::LabelTooLong(65536));
+
+    let err = Config {
+        protocol: "p".repeat(65536),
+        ..Default::default()
+    }
+    .validate()
+    .expect_err("expected protocol too long error");
+    assert_eq!(err, Error::ProtocolTooLong(65536));
+
+    // The prefix announcing chunked messages counts against the limit
+    let err = Config {
+        protocol: "p".repeat(65535),
+        chunked: true,
+        ..Default::default()
+    }
+    .validate()
+    .expect_err("expected protocol too long error");
+    assert_eq!(
+        err,
+        Error::ProtocolTooLong(65535 + CHUNKED_PROTOCOL_PREFIX.len())
+    );
+
+    assert!(ConfigBuilder::default()
+        .label("l".repeat(65536))
+        .build()
+        .is_err());
+    assert!(ConfigBuilder::default()
+        .label(String::new())
+        .build()
+        .is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn test_string_from_utf8_lossy() {
+    assert_eq!(string_from_utf8_lossy(b"data".to_vec()), "data");
+    assert_eq!(string_from_utf8_lossy(vec![]), "");
+    assert_eq!(
+        string_from_utf8_lossy(vec![b'a', 0xc3, 0x28, b'b']),
+        "a\u{FFFD}(b"
+    );
+}
+
+#[tokio::test]
+async fn test_data_channel_accept_invalid_utf8_label() -> Result<()> {
+    let (br, ca, cb) = Bridge::new(0, None, None);
+
+    let (a0, a1) = create_new_association_pair(&br, Arc::new(ca), Arc::new(cb)).await?;
+
+    let msg = Message::DataChannelOpen(DataChannelOpen {
+        channel_type: ChannelType::Reliable,
+        priority: 0,
+        reliability_parameter: 0,
+        label: vec![b'a', 0xc3, 0x28, b'b'],
+        protocol: vec![0xff],
+    })
+    .marshal()?;
+    let s0 = a0
+        .open_stream(100, PayloadProtocolIdentifier::Binary)
+        .await?;
+    s0.write_sctp(&msg, PayloadProtocolIdentifier::Dcep).await?;
+    bridge_process_at_least_one(&br).await;
+
+    let existing_data_channels: Vec<DataChannel> = Vec::new();
+    let dc1 = DataChannel::accept(&a1, Config::default(), &existing_data_channels).await?;
+    bridge_process_at_least_one(&br).await;
+
+    assert_eq!(dc1.config.label, "a\u{FFFD}(b");
+    assert_eq!(dc1.config.protocol, "\u{FFFD}");
+
+    dc1.close().await?;
+    bridge_process_at_least_one(&br).await;
+
+    close_association_pair(&br, a0, a1).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_data_channel_empty_label() -> Result<()> {
+    let (br, ca, cb) = Bridge::new(0, None, None);
+
+    let (a0, a1) = create_new_association_pair(&br, Arc::new(ca), Arc::new(cb)).await?;
+
+    let cfg = Config::default();
+    let dc0 = DataChannel::dial(&a0, 100, cfg.clone()).await?;
+    bridge_process_at_least_one(&br).await;
+
+    let existing_data_channels: Vec<DataChannel> = Vec::new();
+    let dc1 = DataChannel::accept(&a1, Config::default(), &existing_data_channels).await?;
+    bridge_process_at_least_one(&br).await;
+
+    assert_eq!(dc0.config, cfg, "local config should match");
+    assert_eq!(dc1.config, cfg, "remote config should match");
+
+    dc0.close().await?;
+    dc1.close().await?;
+    bridge_process_at_least_one(&br).await;
+
+    close_association_pair(&br, a0, a1).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_data_channel_priority() -> Result<()> {
+    let (br, ca, cb) = Bridge::new(0, None, None);
+
+    let (a0, a1) = create_new_association_pair(&br, Arc::new(ca), Arc::new(cb)).await?;
+
+    let cfg = Config {
+        priority: CHANNEL_PRIORITY_HIGH,
+        label: "data".to_owned(),
+        ..Default::default()
+    };
+    let dc0 = DataChannel::dial(&a0, 100, cfg.clone()).await?;
+    bridge_process_at_least_one(&br).await;
+
+    let existing_data_channels: Vec<DataChannel> = Vec::new();
+    let dc1 = DataChannel::accept(&a1, Config::default(), &existing_data_channels).await?;
+    bridge_process_at_least_one(&br).await;
+
+    assert_eq!(
+        dc0.priority(),
+        CHANNEL_PRIORITY_HIGH,
+        "local priority should match"
+    );
+    assert_eq!(
+        dc1.priority(),
+        CHANNEL_PRIORITY_HIGH,
+        "remote priority should match"
+    );
+    assert_eq!(dc0.stream.priority(), CHANNEL_PRIORITY_HIGH);
+    assert_eq!(dc1.stream.priority(), CHANNEL_PRIORITY_HIGH);
+
+    dc0.close().await?;
+    dc1.close().await?;
+    bridge_process_at_least_one(&br).await;
+
+    close_association_pair(&br, a0, a1).await;
+
+    Ok(())
+}
+
+fn chunked_message(len: usize, seed: u8) -> Bytes {
+    (0..len)
+        .map(|i| (i % 251) as u8 ^ seed)
+        .collect::<Vec<u8>>()
+        .into()
+}
+
+#[test]
+fn test_fragment_boundaries() -> Result<()> {
+    for (len, count) in [(1, 1), (16376, 1), (16377, 2), (16384, 2), (16385, 2)] {
+        let data = chunked_message(len, 0);
+        let fragments = fragment::fragment(7, &data)?;
+        assert_eq!(fragments.len(), count, "fragments of {} bytes", len);
+        assert!(fragments
+            .iter()
+            .all(|f| f.len() <= fragment::MAX_FRAGMENT_SIZE));
+
+        let mut reassembler = Reassembler::default();
+        let mut message = None;
+        for f in &fragments {
+            assert!(message.is_none());
+            message = reassembler.push(f)?;
+        }
+        assert_eq!(message, Some(data));
+    }
+
+    let err = fragment::fragment(
+        0,
+        &Bytes::from(vec![0; fragment::MAX_CHUNKED_MESSAGE_SIZE + 1]),
+    )
+    .expect_err("expected message too large error");
+    assert_eq!(
+        err,
+        Error::ChunkedMessageTooLarge(fragment::MAX_CHUNKED_MESSAGE_SIZE + 1)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_reassembler_reordered_fragments() -> Result<()> {
+    let large = chunked_message(10 * 1024 * 1024, 1);
+    let small = chunked_message(16385, 2);
+    let mut large_fragments = fragment::fragment(u32::MAX, &large)?;
+    let small_fragments = fragment::fragment(0, &small)?;
+
+    // The fragments of the large message arrive backwards, interleaved with the ones of the
+    // small message and with a duplicate.
+    large_fragments.reverse();
+    let mut reassembler = Reassembler::default();
+    assert_eq!(reassembler.push(&large_fragments[0])?, None);
+    assert_eq!(reassembler.push(&small_fragments[1])?, None);
+    assert_eq!(reassembler.push(&large_fragments[0])?, None);
+    assert_eq!(reassembler.push(&small_fragments[0])?, Some(small));
+    let last = large_fragments.pop().unwrap();
+    for f in &large_fragments[1..] {
+        assert_eq!(reassembler.push(f)?, None);
+    }
+    assert_eq!(reassembler.push(&last)?, Some(large));
+
+    assert_eq!(
+        reassembler.push(&[0, 0, 0, 1]),
+        Err(Error::UnexpectedEndOfBuffer {
+            expected: 8,
+            actual: 4
+        })
+    );
+    assert_eq!(
+        reassembler.push(&[0, 0, 0, 1, 0, 2, 0, 2, 0]),
+        Err(Error::ErrInvalidFragment)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_data_channel_chunked_reordered() -> Result<()> {
+    let (br, ca, cb) = Bridge::new(0, None, None);
+
+    let (a0, a1) = create_new_association_pair(&br, Arc::new(ca), Arc::new(cb)).await?;
+
+    let cfg = Config {
+        channel_type: ChannelType::ReliableUnordered,
+        label: "data".to_owned(),
+        protocol: "proto".to_owned(),
+        chunked: true,
+        ..Default::default()
+    };
+    let dc0 = DataChannel::dial(&a0, 100, cfg.clone()).await?;
+    bridge_process_at_least_one(&br).await;
+
+    let existing_data_channels: Vec<DataChannel> = Vec::new();
+    let dc1 = DataChannel::accept(&a1, Config::default(), &existing_data_channels).await?;
+    bridge_process_at_least_one(&br).await;
+
+    assert_eq!(dc0.config, cfg, "local config should match");
+    assert_eq!(dc1.config, cfg, "remote config should match");
+
+    receive_ack(&dc0).await?;
+    dc1.commit_reliability_params();
+
+    let messages = vec![chunked_message(16384, 1), chunked_message(16385, 2)];
+    for message in &messages {
+        let n = dc0.write(message).await?;
+        assert_eq!(message.len(), n, "data length should match");
+    }
+
+    let (done_tx, mut done_rx) = mpsc::channel(1);
+    tokio::spawn(async move {
+        let mut rbuf = vec![0u8; 65536];
+        let mut received = vec![];
+        for _ in 0..2 {
+            let n = dc1.read(&mut rbuf).await?;
+            received.push(Bytes::copy_from_slice(&rbuf[..n]));
+        }
+        let _ = done_tx.send(received).await;
+
+        Result::<()>::Ok(())
+    });
+
+    // Reverse the packets on the wire, the second fragments complete before the first ones
+    let mut received = loop {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        br.reorder(0).await;
+        while br.tick().await > 0 {}
+        if let Ok(received) = done_rx.try_recv() {
+            break received;
+        }
+    };
+    received.sort_by_key(|message| message.len());
+    assert_eq!(received, messages);
+
+    dc0.close().await?;
+    bridge_process_at_least_one(&br).await;
+
+    close_association_pair(&br, a0, a1).await;
+
+    Ok(())
+}
+
+async fn create_pipe_association_pair() -> Result<(Arc<Association>, Arc<Association>)> {
+    let (ca, cb) = pipe();
+    let config = |net_conn: Arc<dyn Conn + Send + Sync>, name: &str| sctp::association::Config {
+        net_conn,
+        max_receive_buffer_size: 0,
+        max_message_size: 0,
+        name: name.to_owned(),
+        max_association_buffered_amount: 0,
+        buffer_full_policy: Default::default(),
+        max_retransmissions: 0,
+        num_outbound_streams: 0,
+        num_inbound_streams: 0,
+        clock: None,
+    };
+
+    let client = tokio::spawn(Association::client(config(Arc::new(ca), "client")));
+    let server = Association::server(config(Arc::new(cb), "server")).await?;
+    let client = client.await.map_err(|err| Error::new(err.to_string()))??;
+
+    Ok((Arc::new(client), Arc::new(server)))
+}
+
+#[tokio::test]
+async fn test_data_channel_chunked_large_message() -> Result<()> {
+    let (a0, a1) = create_pipe_association_pair().await?;
+
+    let cfg = Config {
+        label: "data".to_owned(),
+        chunked: true,
+        ..Default::default()
+    };
+    let dc0 = DataChannel::dial(&a0, 100, cfg.clone()).await?;
+    let existing_data_channels: Vec<DataChannel> = Vec::new();
+    let dc1 = DataChannel::accept(&a1, Config::default(), &existing_data_channels).await?;
+    assert_eq!(dc1.config, cfg, "remote config should match");
+
+    let message = chunked_message(1024 * 1024, 3);
+    let n = dc0.write(&message).await?;
+    assert_eq!(message.len(), n, "data length should match");
+    // Strings aren't chunked
+    dc0.write_data_channel(&Bytes::from_static(b"done"), true)
+        .await?;
+
+    let mut rbuf = vec![0u8; fragment::MAX_CHUNKED_MESSAGE_SIZE];
+    let (n, is_string) = dc1.read_data_channel(&mut rbuf).await?;
+    assert!(!is_string);
+    assert_eq!(&rbuf[..n], &message[..], "data should match");
+    assert_eq!(dc1.bytes_received(), message.len());
+    assert_eq!(dc1.messages_received(), 1);
+
+    let (n, is_string) = dc1.read_data_channel(&mut rbuf).await?;
+    assert!(is_string);
+    assert_eq!(&rbuf[..n], b"done");
+
+    // Without chunking messages are limited by the max message size of the association
+    let dc2 = DataChannel::dial(
+        &a0,
+        102,
+        Config {
+            label: "data".to_owned(),
+            ..Default::default()
+        },
+    )
+    .await?;
+    let err = dc2
+        .write(&chunked_message(65537, 4))
+        .await
+        .expect_err("expected message too large error");
+    assert_eq!(err, Error::Sctp(sctp::Error::ErrOutboundPacketTooLarge));
+
+    dc0.close().await?;
+    dc2.close().await?;
+    a0.close().await?;
+    a1.close().await?;
+
+    Ok(())
+}
+
+async fn tunnel(
+    writer: &mut PollDataChannel,
+    reader: &mut PollDataChannel,
+    seed: u8,
+) -> Result<()> {
+    let message = chunked_message(100_000, seed);
+    let messages_sent = writer.messages_sent();
+
+    let (write_result, received) = tokio::join!(
+        async {
+            writer.write_all(&message).await?;
+            writer.flush().await
+        },
+        async {
+            let mut received = vec![];
+            let mut rbuf = [0u8; 1000];
+            while received.len() < message.len() {
+                let n = reader.read(&mut rbuf).await?;
+                if n == 0 {
+                    break;
+                }
+                received.extend_from_slice(&rbuf[..n]);
+            }
+            std::io::Result::Ok(received)
+        }
+    );
+    write_result.map_err(|e| Error::new(e.to_string()))?;
+    let received = received.map_err(|e| Error::new(e.to_string()))?;
+
+    assert_eq!(received, message, "data should match");
+    // The write was split into messages of the max message size
+    assert_eq!(writer.messages_sent() - messages_sent, 25);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_poll_data_channel_large_writes_and_short_reads() -> Result<()> {
+    let (a0, a1) = create_pipe_association_pair().await?;
+    a0.set_max_message_size(4096);
+    a1.set_max_message_size(4096);
+
+    let cfg = Config {
+        label: "data".to_owned(),
+        ..Default::default()
+    };
+    let dc0 = Arc::new(DataChannel::dial(&a0, 100, cfg).await?);
+    let existing_data_channels: Vec<DataChannel> = Vec::new();
+    let dc1 = Arc::new(DataChannel::accept(&a1, Config::default(), &existing_data_channels).await?);
+
+    // Messages are larger than the buffers of the readers, but fit the read buffers
+    let mut poll_dc0 = PollDataChannel::new(dc0);
+    poll_dc0.set_read_buf_capacity(4096);
+    let mut poll_dc1 = PollDataChannel::new(dc1);
+
+    tunnel(&mut poll_dc0, &mut poll_dc1, 1).await?;
+    tunnel(&mut poll_dc1, &mut poll_dc0, 2).await?;
+
+    poll_dc0.into_inner().close().await?;
+    poll_dc1.into_inner().close().await?;
+    a0.close().await?;
+    a1.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_poll_data_channel_reads_messages_larger_than_read_buf() -> Result<()> {
+    let (a0, a1) = create_pipe_association_pair().await?;
+
+    let cfg = Config {
+        label: "data".to_owned(),
+        ..Default::default()
+    };
+    let dc0 = Arc::new(DataChannel::dial(&a0, 100, cfg).await?);
+    let existing_data_channels: Vec<DataChannel> = Vec::new();
+    let dc1 = Arc::new(DataChannel::accept(&a1, Config::default(), &existing_data_channels).await?);
+
+    let mut poll_dc1 = PollDataChannel::new(Arc::clone(&dc1));
+    poll_dc1.set_read_buf_capacity(1024);
+
+    let large: Vec<u8> = (0..8192).map(|i| i as u8).collect();
+    dc0.write(&Bytes::from(large.clone())).await?;
+    dc0.write(&Bytes::from_static(b"next")).await?;
+
+    // The large message is handed out over several reads, then the next one follows
+    let mut received = vec![0u8; large.len()];
+    poll_dc1
+        .read_exact(&mut received)
+        .await
+        .map_err(|e| Error::new(e.to_string()))?;
+    assert_eq!(received, large, "data should match");
+
+    let mut next = vec![0u8; 16];
+    let n = poll_dc1
+        .read(&mut next)
+        .await
+        .map_err(|e| Error::new(e.to_string()))?;
+    assert_eq!(&next[..n], b"next");
+    assert_eq!(dc1.messages_received(), 2);
+
+    dc0.close().await?;
+    poll_dc1.into_inner().close().await?;
+    a0.close().await?;
+    a1.close().await?;
+
+    Ok(())
+}
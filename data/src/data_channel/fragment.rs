@@ -0,0 +1,134 @@
+use crate::error::{Error, Result};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+/// Put in front of the protocol of the DATA_CHANNEL_OPEN message of a chunked channel, so that
+/// the remote reassembles its messages. Endpoints which don't know about it see a different
+/// protocol, chunked channels are only meant to be opened between endpoints using this crate.
+pub(crate) const CHUNKED_PROTOCOL_PREFIX: &str = "webrtc-rs-chunked/";
+
+/// Fragments start with the message id (u32), the fragment index (u16) and the number of
+/// fragments of the message (u16).
+const FRAGMENT_HEADER_LEN: usize = 8;
+
+/// Fragments, header included, fit in the 16 KiB older endpoints are able to reassemble.
+pub const MAX_FRAGMENT_SIZE: usize = 16384;
+
+const MAX_FRAGMENT_PAYLOAD: usize = MAX_FRAGMENT_SIZE - FRAGMENT_HEADER_LEN;
+
+/// Largest binary message sent or reassembled by a chunked channel.
+pub const MAX_CHUNKED_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Number of partially received messages kept. Only partially reliable channels lose
+/// fragments for good, the oldest message is dropped when yet another one starts.
+const MAX_PENDING_MESSAGES: usize = 16;
+
+/// fragment splits `data` into fragments of at most MAX_FRAGMENT_SIZE bytes, each sent as its own
+/// SCTP message. `data` must not be empty.
+pub(crate) fn fragment(message_id: u32, data: &Bytes) -> Result<Vec<Bytes>> {
+    if data.len() > MAX_CHUNKED_MESSAGE_SIZE {
+        return Err(Error::ChunkedMessageTooLarge(data.len()));
+    }
+
+    let count = (data.len() - 1) / MAX_FRAGMENT_PAYLOAD + 1;
+    let fragments = data
+        .chunks(MAX_FRAGMENT_PAYLOAD)
+        .enumerate()
+        .map(|(index, payload)| {
+            let mut b = BytesMut::with_capacity(FRAGMENT_HEADER_LEN + payload.len());
+            b.put_u32(message_id);
+            b.put_u16(index as u16);
+            b.put_u16(count as u16);
+            b.extend_from_slice(payload);
+            b.freeze()
+        })
+        .collect();
+
+    Ok(fragments)
+}
+
+#[derive(Debug)]
+struct PendingMessage {
+    message_id: u32,
+    fragments: Vec<Option<Bytes>>,
+    missing: usize,
+    size: usize,
+}
+
+/// Reassembler puts received fragments back together. Fragments of a message may arrive in any
+/// order and interleaved with the fragments of other messages on unordered channels.
+#[derive(Debug, Default)]
+pub(crate) struct Reassembler {
+    pending: Vec<PendingMessage>,
+}
+
+impl Reassembler {
+    /// push records a received fragment and returns the message it completes, if any.
+    pub(crate) fn push(&mut self, mut fragment: &[u8]) -> Result<Option<Bytes>> {
+        if fragment.len() < FRAGMENT_HEADER_LEN {
+            return Err(Error::UnexpectedEndOfBuffer {
+                expected: FRAGMENT_HEADER_LEN,
+                actual: fragment.len(),
+            });
+        }
+
+        let message_id = fragment.get_u32();
+        let index = fragment.get_u16() as usize;
+        let count = fragment.get_u16() as usize;
+        if index >= count || count > MAX_CHUNKED_MESSAGE_SIZE / MAX_FRAGMENT_PAYLOAD + 1 {
+            return Err(Error::ErrInvalidFragment);
+        }
+
+        if count == 1 {
+            return Ok(Some(Bytes::copy_from_slice(fragment)));
+        }
+
+        let pos = match self
+            .pending
+            .iter()
+            .position(|message| message.message_id == message_id)
+        {
+            Some(pos) => pos,
+            None => {
+                if self.pending.len() == MAX_PENDING_MESSAGES {
+                    let dropped = self.pending.remove(0);
+                    log::debug!("Dropping incomplete chunked message {}", dropped.message_id);
+                }
+                self.pending.push(PendingMessage {
+                    message_id,
+                    fragments: vec![None; count],
+                    missing: count,
+                    size: 0,
+                });
+                self.pending.len() - 1
+            }
+        };
+
+        let message = &mut self.pending[pos];
+        if message.fragments.len() != count {
+            self.pending.remove(pos);
+            return Err(Error::ErrInvalidFragment);
+        }
+        if message.fragments[index].is_none() {
+            message.size += fragment.len();
+            if message.size > MAX_CHUNKED_MESSAGE_SIZE {
+                let size = message.size;
+                self.pending.remove(pos);
+                return Err(Error::ChunkedMessageTooLarge(size));
+            }
+            message.fragments[index] = Some(Bytes::copy_from_slice(fragment));
+            message.missing -= 1;
+        }
+        if message.missing > 0 {
+            return Ok(None);
+        }
+
+        let message = self.pending.remove(pos);
+        let mut data = BytesMut::with_capacity(message.size);
+        for payload in message.fragments.into_iter().flatten() {
+            data.extend_from_slice(&payload);
+        }
+
+        Ok(Some(data.freeze()))
+    }
+}
@@ -1,15 +1,22 @@
 #[cfg(test)]
 mod data_channel_test;
 
+pub mod fragment;
+
 use crate::error::Result;
 use crate::{
-    error::Error, message::message_channel_ack::*, message::message_channel_open::*, message::*,
+    data_channel::fragment::{Reassembler, CHUNKED_PROTOCOL_PREFIX},
+    error::Error,
+    message::message_channel_ack::*,
+    message::message_channel_open::*,
+    message::*,
 };
 
 use sctp::{
     association::Association, chunk::chunk_payload_data::PayloadProtocolIdentifier, stream::*,
 };
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::Mutex;
 use util::marshal::*;
 
 use bytes::{Buf, Bytes};
@@ -20,7 +27,7 @@ use std::future::Future;
 use std::io;
 use std::net::Shutdown;
 use std::pin::Pin;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
@@ -28,6 +35,7 @@ const RECEIVE_MTU: usize = 8192;
 
 /// Config is used to configure the data channel.
 #[derive(Eq, PartialEq, Default, Clone, Debug, Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
 pub struct Config {
     #[builder(default)]
     pub channel_type: ChannelType,
@@ -41,6 +49,51 @@ pub struct Config {
     pub label: String,
     #[builder(default)]
     pub protocol: String,
+    /// chunked splits binary messages into fragments of at most 16 KiB, which are reassembled by
+    /// the remote, see [`fragment`]. Both ends must use this crate. It is announced in the
+    /// DATA_CHANNEL_OPEN message, negotiated channels must enable it on both ends.
+    #[builder(default)]
+    pub chunked: bool,
+}
+
+impl Config {
+    /// validate checks that the label and protocol fit in a DATA_CHANNEL_OPEN message.
+    pub fn validate(&self) -> Result<()> {
+        if self.label.len() > MAX_LABEL_AND_PROTOCOL_LEN {
+            return Err(Error::LabelTooLong(self.label.len()));
+        }
+        let protocol_len = if self.chunked && !self.negotiated {
+            CHUNKED_PROTOCOL_PREFIX.len() + self.protocol.len()
+        } else {
+            self.protocol.len()
+        };
+        if protocol_len > MAX_LABEL_AND_PROTOCOL_LEN {
+            return Err(Error::ProtocolTooLong(protocol_len));
+        }
+
+        Ok(())
+    }
+}
+
+impl ConfigBuilder {
+    fn validate(&self) -> std::result::Result<(), String> {
+        let config = Config {
+            label: self.label.clone().unwrap_or_default(),
+            protocol: self.protocol.clone().unwrap_or_default(),
+            chunked: self.chunked.unwrap_or_default(),
+            negotiated: self.negotiated.unwrap_or_default(),
+            ..Default::default()
+        };
+
+        config.validate().map_err(|err| err.to_string())
+    }
+}
+
+/// Decodes a label or protocol received from the remote, replacing invalid UTF-8 sequences with
+/// U+FFFD like browsers do instead of rejecting the channel.
+fn string_from_utf8_lossy(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes)
+        .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned())
 }
 
 /// DataChannel represents a data channel
@@ -54,10 +107,23 @@ pub struct DataChannel {
     messages_received: Arc<AtomicUsize>,
     bytes_sent: Arc<AtomicUsize>,
     bytes_received: Arc<AtomicUsize>,
+
+    // chunked mode
+    next_message_id: Arc<AtomicU32>,
+    reassembler: Arc<Mutex<Reassembler>>,
+
+    // set by the opener until the DATA_CHANNEL_ACK or any other message is received
+    awaiting_ack: Arc<AtomicBool>,
 }
 
 impl DataChannel {
+    /// new creates a data channel over `stream`. The priority of the stream is set from
+    /// `config.priority` unless it's 0, which is treated as "normal" like an unset priority.
     pub fn new(stream: Arc<Stream>, config: Config) -> Self {
+        if config.priority != 0 {
+            stream.set_priority(config.priority);
+        }
+
         Self {
             config,
             stream,
@@ -106,21 +172,43 @@ impl DataChannel {
         Self::server(stream, config).await
     }
 
-    /// Client opens a data channel over an SCTP stream
+    /// Client opens a data channel over an SCTP stream.
+    ///
+    /// Negotiated channels are usable right away with the reliability parameters of `config`.
+    /// Otherwise a DATA_CHANNEL_OPEN message is sent and, as required by RFC 8832 Section 6.6,
+    /// messages are sent ordered until the DATA_CHANNEL_ACK or any other message is received,
+    /// their partial reliability policy is applied from the start.
     pub async fn client(stream: Arc<Stream>, config: Config) -> Result<Self> {
-        if !config.negotiated {
-            let msg = Message::DataChannelOpen(DataChannelOpen {
-                channel_type: config.channel_type,
-                priority: config.priority,
-                reliability_parameter: config.reliability_parameter,
-                label: config.label.bytes().collect(),
-                protocol: config.protocol.bytes().collect(),
-            })
-            .marshal()?;
-
-            stream.write_sctp(&msg, PayloadProtocolIdentifier::Dcep)?;
+        config.validate()?;
+
+        if config.negotiated {
+            let data_channel = DataChannel::new(stream, config);
+            data_channel.commit_reliability_params();
+            return Ok(data_channel);
         }
-        Ok(DataChannel::new(stream, config))
+
+        let protocol = if config.chunked {
+            format!("{}{}", CHUNKED_PROTOCOL_PREFIX, config.protocol)
+        } else {
+            config.protocol.clone()
+        };
+        let msg = Message::DataChannelOpen(DataChannelOpen {
+            channel_type: config.channel_type,
+            priority: config.priority,
+            reliability_parameter: config.reliability_parameter,
+            label: config.label.bytes().collect(),
+            protocol: protocol.into_bytes(),
+        })
+        .marshal()?;
+
+        stream
+            .write_sctp(&msg, PayloadProtocolIdentifier::Dcep)
+            .await?;
+
+        let data_channel = DataChannel::new(stream, config);
+        data_channel.awaiting_ack.store(true, Ordering::SeqCst);
+        data_channel.commit_reliability_params();
+        Ok(data_channel)
     }
 
     /// Server accepts a data channel over an SCTP stream
@@ -140,8 +228,12 @@ impl DataChannel {
             config.channel_type = dco.channel_type;
             config.priority = dco.priority;
             config.reliability_parameter = dco.reliability_parameter;
-            config.label = String::from_utf8(dco.label)?;
-            config.protocol = String::from_utf8(dco.protocol)?;
+            config.label = string_from_utf8_lossy(dco.label);
+            config.protocol = string_from_utf8_lossy(dco.protocol);
+            if let Some(protocol) = config.protocol.strip_prefix(CHUNKED_PROTOCOL_PREFIX) {
+                config.protocol = protocol.to_owned();
+                config.chunked = true;
+            }
         } else {
             return Err(Error::InvalidMessageType(msg.message_type() as u8));
         };
@@ -164,13 +256,36 @@ impl DataChannel {
     /// ReadDataChannel reads a packet of len(p) bytes. It returns the number of bytes read and
     /// `true` if the data read is a string.
     ///
+    /// Chunked channels return binary messages once all their fragments were received, `p` must
+    /// be large enough for the whole message.
+    ///
     /// See [`sctp::stream::Stream::read_sctp`].
     pub async fn read_data_channel(&self, buf: &mut [u8]) -> Result<(usize, bool)> {
+        self.read_message(MessageBuf::Slice(buf)).await
+    }
+
+    /// read_data_channel_to_vec reads like read_data_channel, but grows buf to fit the message
+    /// instead of failing with ErrShortBuffer.
+    async fn read_data_channel_to_vec(&self, buf: &mut Vec<u8>) -> Result<(usize, bool)> {
+        self.read_message(MessageBuf::Vec(buf)).await
+    }
+
+    async fn read_message(&self, mut buf: MessageBuf<'_>) -> Result<(usize, bool)> {
         loop {
             //TODO: add handling of cancel read_data_channel
-            let (mut n, ppi) = match self.stream.read_sctp(buf).await {
+            let result = match &mut buf {
+                MessageBuf::Slice(buf) => self.stream.read_sctp(buf).await,
+                MessageBuf::Vec(buf) => self.stream.read_sctp_to_vec(buf).await,
+            };
+            let (mut n, ppi) = match result {
                 Ok((0, PayloadProtocolIdentifier::Unknown)) => {
-                    // The incoming stream was reset or the reading half was shutdown
+                    // The incoming stream was reset or the reading half was shutdown. When the
+                    // peer closed the channel, the outgoing stream is reset too (RFC 8831 Sec 6.7).
+                    if self.stream.is_incoming_reset() {
+                        if let Err(err) = self.stream.shutdown(Shutdown::Both).await {
+                            log::debug!("Failed to reset the outgoing stream: {}", err);
+                        }
+                    }
                     return Ok((0, false));
                 }
                 Ok((n, ppi)) => (n, ppi),
@@ -184,7 +299,7 @@ impl DataChannel {
             let mut is_string = false;
             match ppi {
                 PayloadProtocolIdentifier::Dcep => {
-                    let mut data = &buf[..n];
+                    let mut data = &buf.as_mut_slice()[..n];
                     match self.handle_dcep(&mut data).await {
                         Ok(()) => {}
                         Err(err) => {
@@ -196,9 +311,31 @@ impl DataChannel {
                 PayloadProtocolIdentifier::String | PayloadProtocolIdentifier::StringEmpty => {
                     is_string = true;
                 }
+                PayloadProtocolIdentifier::Binary if self.config.chunked => {
+                    let result = {
+                        let mut reassembler = self.reassembler.lock().await;
+                        reassembler.push(&buf.as_mut_slice()[..n])
+                    };
+                    let message = match result {
+                        Ok(Some(message)) => message,
+                        Ok(None) => continue,
+                        Err(err) => {
+                            log::warn!("Failed to reassemble chunked message: {}", err);
+                            continue;
+                        }
+                    };
+                    buf.fit(message.len())?;
+                    buf.as_mut_slice()[..message.len()].copy_from_slice(&message);
+                    n = message.len();
+                }
                 _ => {}
             };
 
+            // Any message received on the channel means the remote processed DATA_CHANNEL_OPEN
+            if self.awaiting_ack.swap(false, Ordering::SeqCst) {
+                self.commit_reliability_params();
+            }
+
             match ppi {
                 PayloadProtocolIdentifier::StringEmpty | PayloadProtocolIdentifier::BinaryEmpty => {
                     n = 0;
@@ -238,6 +375,27 @@ impl DataChannel {
         self.stream.stream_identifier()
     }
 
+    /// priority returns the priority of the data channel, as sent in or received with the
+    /// DATA_CHANNEL_OPEN message.
+    pub fn priority(&self) -> u16 {
+        self.config.priority
+    }
+
+    /// is_ordered returns whether messages are currently sent with ordered delivery. Unordered
+    /// channels opened with DATA_CHANNEL_OPEN only switch to unordered delivery once the remote
+    /// acknowledged the channel.
+    pub fn is_ordered(&self) -> bool {
+        let (unordered, _, _) = self.stream.reliability_params();
+        !unordered
+    }
+
+    /// reliability returns the partial reliability policy messages are currently sent with and
+    /// its parameter, the max number of retransmissions or the max lifetime in milliseconds.
+    pub fn reliability(&self) -> (ReliabilityType, u32) {
+        let (_, reliability_type, reliability_parameter) = self.stream.reliability_params();
+        (reliability_type, reliability_parameter)
+    }
+
     async fn handle_dcep<B>(&self, data: &mut B) -> Result<()>
     where
         B: Buf,
@@ -253,6 +411,7 @@ impl DataChannel {
             }
             Message::DataChannelAck(_) => {
                 log::debug!("Received DATA_CHANNEL_ACK");
+                self.awaiting_ack.store(false, Ordering::SeqCst);
                 self.commit_reliability_params();
             }
         };
@@ -265,7 +424,8 @@ impl DataChannel {
         self.write_data_channel(data, false).await
     }
 
-    /// WriteDataChannel writes len(p) bytes from p
+    /// WriteDataChannel writes len(p) bytes from p. Chunked channels split binary messages into
+    /// fragments, messages of other channels must fit in the max message size of the association.
     pub async fn write_data_channel(&self, data: &Bytes, is_string: bool) -> Result<usize> {
         let data_len = data.len();
 
@@ -284,10 +444,20 @@ impl DataChannel {
         };
 
         let n = if data_len == 0 {
-            let _ = self.stream.write_sctp(&Bytes::from_static(&[0]), ppi)?;
+            let _ = self
+                .stream
+                .write_sctp(&Bytes::from_static(&[0]), ppi)
+                .await?;
             0
+        } else if self.config.chunked && !is_string {
+            let message_id = self.next_message_id.fetch_add(1, Ordering::SeqCst);
+            for fragment in fragment::fragment(message_id, data)? {
+                self.stream.write_sctp(&fragment, ppi).await?;
+            }
+            self.bytes_sent.fetch_add(data_len, Ordering::SeqCst);
+            data_len
         } else {
-            let n = self.stream.write_sctp(data, ppi)?;
+            let n = self.stream.write_sctp(data, ppi).await?;
             self.bytes_sent.fetch_add(n, Ordering::SeqCst);
             n
         };
@@ -300,7 +470,8 @@ impl DataChannel {
         let ack = Message::DataChannelAck(DataChannelAck {}).marshal()?;
         Ok(self
             .stream
-            .write_sctp(&ack, PayloadProtocolIdentifier::Dcep)?)
+            .write_sctp(&ack, PayloadProtocolIdentifier::Dcep)
+            .await?)
     }
 
     /// Close closes the DataChannel and the underlying SCTP stream.
@@ -343,6 +514,8 @@ impl DataChannel {
         self.stream.on_buffered_amount_low(f)
     }
 
+    /// commit_reliability_params applies the reliability parameters of the config to the stream.
+    /// Unordered delivery is held back while the DATA_CHANNEL_ACK is awaited.
     fn commit_reliability_params(&self) {
         let (unordered, reliability_type) = match self.config.channel_type {
             ChannelType::Reliable => (false, ReliabilityType::Reliable),
@@ -354,24 +527,58 @@ impl DataChannel {
         };
 
         self.stream.set_reliability_params(
-            unordered,
+            unordered && !self.awaiting_ack.load(Ordering::SeqCst),
             reliability_type,
             self.config.reliability_parameter,
         );
     }
 }
 
-/// Default capacity of the temporary read buffer used by [`PollStream`].
-const DEFAULT_READ_BUF_SIZE: usize = 8192;
+/// The buffer [`DataChannel`] reads a message into.
+enum MessageBuf<'a> {
+    /// A buffer of the caller, too short ones fail the read with ErrShortBuffer.
+    Slice(&'a mut [u8]),
+    /// A buffer grown to fit the message.
+    Vec(&'a mut Vec<u8>),
+}
 
-/// State of the read `Future` in [`PollStream`].
+impl MessageBuf<'_> {
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        match self {
+            MessageBuf::Slice(buf) => buf,
+            MessageBuf::Vec(buf) => buf.as_mut_slice(),
+        }
+    }
+
+    /// Makes room for a message of len bytes.
+    fn fit(&mut self, len: usize) -> Result<()> {
+        match self {
+            MessageBuf::Slice(buf) if buf.len() < len => {
+                Err(Error::Sctp(sctp::Error::ErrShortBuffer))
+            }
+            MessageBuf::Slice(_) => Ok(()),
+            MessageBuf::Vec(buf) => {
+                if buf.len() < len {
+                    buf.resize(len, 0);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Default capacity of the read buffer used by [`PollDataChannel`]. It's the max-message-size a
+/// peer assumes when none is signaled (RFC 8841 Section 6.1), so any message it sends fits.
+const DEFAULT_READ_BUF_SIZE: usize = 65536;
+
+/// State of the read `Future` in [`PollDataChannel`].
 enum ReadFut {
     /// Nothing in progress.
     Idle,
     /// Reading data from the underlying stream.
     Reading(Pin<Box<dyn Future<Output = Result<Vec<u8>>> + Send>>),
-    /// Finished reading, but there's unread data in the temporary buffer.
-    RemainingData(Vec<u8>),
+    /// Finished reading, but there's unread data in the read buffer from the given position.
+    RemainingData(Vec<u8>, usize),
 }
 
 impl ReadFut {
@@ -391,12 +598,15 @@ impl ReadFut {
 /// A wrapper around around [`DataChannel`], which implements [`AsyncRead`] and
 /// [`AsyncWrite`].
 ///
-/// Both `poll_read` and `poll_write` calls allocate temporary buffers, which results in an
-/// additional overhead.
+/// Messages are read whole into a read buffer, which is reused across reads, and handed out over
+/// as many `poll_read` calls as the caller's buffer requires. Writes are split into messages of
+/// at most the max message size of the stream. `poll_write` copies the data into a temporary
+/// buffer, which results in an additional overhead.
 pub struct PollDataChannel {
     data_channel: Arc<DataChannel>,
 
     read_fut: ReadFut,
+    read_buf: Vec<u8>,
     write_fut: Option<Pin<Box<dyn Future<Output = Result<usize>> + Send>>>,
     shutdown_fut: Option<Pin<Box<dyn Future<Output = Result<()>> + Send>>>,
 
@@ -420,6 +630,7 @@ impl PollDataChannel {
         Self {
             data_channel,
             read_fut: ReadFut::Idle,
+            read_buf: vec![],
             write_fut: None,
             shutdown_fut: None,
             read_buf_cap: DEFAULT_READ_BUF_SIZE,
@@ -473,10 +684,22 @@ impl PollDataChannel {
         self.data_channel.buffered_amount_low_threshold()
     }
 
-    /// Set the capacity of the temporary read buffer (default: 8192).
+    /// Set the capacity of the read buffer (default: 65536). The buffer grows to fit a larger
+    /// message for as long as it is read. The caller's buffers passed to `poll_read` can be of
+    /// any size.
     pub fn set_read_buf_capacity(&mut self, capacity: usize) {
         self.read_buf_cap = capacity
     }
+
+    /// max_write_len returns how much of `len` bytes fits into a single message.
+    fn max_write_len(&self, len: usize) -> usize {
+        let max_message_size = self.data_channel.stream.max_message_size() as usize;
+        if max_message_size == 0 {
+            len
+        } else {
+            std::cmp::min(len, max_message_size)
+        }
+    }
 }
 
 impl AsyncRead for PollDataChannel {
@@ -491,28 +714,37 @@ impl AsyncRead for PollDataChannel {
 
         let fut = match self.read_fut {
             ReadFut::Idle => {
-                // read into a temporary buffer because `buf` has an unonymous lifetime, which can
+                // read into the read buffer because `buf` has an unonymous lifetime, which can
                 // be shorter than the lifetime of `read_fut`.
                 let data_channel = self.data_channel.clone();
-                let mut temp_buf = vec![0; self.read_buf_cap];
+                let mut read_buf = std::mem::take(&mut self.read_buf);
+                let read_buf_cap = self.read_buf_cap;
+                read_buf.resize(read_buf_cap, 0);
+                // A buffer grown for a larger message is given back, it may have been a one-off
+                read_buf.shrink_to(read_buf_cap);
                 self.read_fut = ReadFut::Reading(Box::pin(async move {
-                    data_channel.read(temp_buf.as_mut_slice()).await.map(|n| {
-                        temp_buf.truncate(n);
-                        temp_buf
-                    })
+                    data_channel
+                        .read_data_channel_to_vec(&mut read_buf)
+                        .await
+                        .map(|(n, _)| {
+                            read_buf.truncate(n);
+                            read_buf
+                        })
                 }));
                 self.read_fut.get_reading_mut()
             }
             ReadFut::Reading(ref mut fut) => fut,
-            ReadFut::RemainingData(ref mut data) => {
-                let remaining = buf.remaining();
-                let len = std::cmp::min(data.len(), remaining);
-                buf.put_slice(&data[..len]);
-                if data.len() > remaining {
-                    // ReadFut remains to be RemainingData
-                    data.drain(..len);
-                } else {
-                    self.read_fut = ReadFut::Idle;
+            ReadFut::RemainingData(ref data, ref mut pos) => {
+                let len = std::cmp::min(data.len() - *pos, buf.remaining());
+                buf.put_slice(&data[*pos..*pos + len]);
+                *pos += len;
+                if *pos == data.len() {
+                    // Keep the buffer for the next read
+                    if let ReadFut::RemainingData(data, _) =
+                        std::mem::replace(&mut self.read_fut, ReadFut::Idle)
+                    {
+                        self.read_buf = data;
+                    }
                 }
                 return Poll::Ready(Ok(()));
             }
@@ -533,15 +765,14 @@ impl AsyncRead for PollDataChannel {
                     self.read_fut = ReadFut::Idle;
                     return Poll::Ready(Err(e.into()));
                 }
-                Poll::Ready(Ok(mut temp_buf)) => {
-                    let remaining = buf.remaining();
-                    let len = std::cmp::min(temp_buf.len(), remaining);
-                    buf.put_slice(&temp_buf[..len]);
-                    if temp_buf.len() > remaining {
-                        temp_buf.drain(..len);
-                        self.read_fut = ReadFut::RemainingData(temp_buf);
+                Poll::Ready(Ok(read_buf)) => {
+                    let len = std::cmp::min(read_buf.len(), buf.remaining());
+                    buf.put_slice(&read_buf[..len]);
+                    if len < read_buf.len() {
+                        self.read_fut = ReadFut::RemainingData(read_buf, len);
                     } else {
                         self.read_fut = ReadFut::Idle;
+                        self.read_buf = read_buf;
                     }
                     return Poll::Ready(Ok(()));
                 }
@@ -560,6 +791,9 @@ impl AsyncWrite for PollDataChannel {
             return Poll::Ready(Ok(0));
         }
 
+        // Larger writes would fail, the rest is written by the next call
+        let buf = &buf[..self.max_write_len(buf.len())];
+
         if let Some(fut) = self.write_fut.as_mut() {
             match fut.as_mut().poll(cx) {
                 Poll::Pending => Poll::Pending,
@@ -19,6 +19,14 @@ pub enum Error {
     InvalidPayloadProtocolIdentifier(u8),
     #[error("Stream closed")]
     ErrStreamClosed,
+    #[error("DataChannel label is {0} bytes long, the limit is 65535 bytes")]
+    LabelTooLong(usize),
+    #[error("DataChannel protocol is {0} bytes long, the limit is 65535 bytes")]
+    ProtocolTooLong(usize),
+    #[error("Chunked DataChannel message is {0} bytes long, the limit is 16777216 bytes")]
+    ChunkedMessageTooLarge(usize),
+    #[error("Malformed fragment of a chunked DataChannel message")]
+    ErrInvalidFragment,
 
     #[error("{0}")]
     Util(#[from] util::Error),
@@ -119,6 +119,9 @@ impl Unmarshal for ChannelType {
 
 const CHANNEL_OPEN_HEADER_LEN: usize = 11;
 
+/// The maximum length in bytes of the label and of the protocol, both lengths are 16 bit fields.
+pub const MAX_LABEL_AND_PROTOCOL_LEN: usize = u16::MAX as usize;
+
 /// The data-part of an data-channel OPEN message without the message type.
 ///
 /// # Memory layout
@@ -162,6 +165,13 @@ impl MarshalSize for DataChannelOpen {
 
 impl Marshal for DataChannelOpen {
     fn marshal_to(&self, mut buf: &mut [u8]) -> Result<usize> {
+        if self.label.len() > MAX_LABEL_AND_PROTOCOL_LEN {
+            return Err(Error::LabelTooLong(self.label.len()).into());
+        }
+        if self.protocol.len() > MAX_LABEL_AND_PROTOCOL_LEN {
+            return Err(Error::ProtocolTooLong(self.protocol.len()).into());
+        }
+
         let required_len = self.marshal_size();
         if buf.remaining_mut() < required_len {
             return Err(Error::UnexpectedEndOfBuffer {
@@ -437,4 +447,90 @@ mod tests {
         assert_eq!(&bytes[..], &MARSHALED_BYTES);
         Ok(())
     }
+
+    #[test]
+    fn test_channel_open_priority_round_trip() -> Result<()> {
+        for priority in [
+            0,
+            1,
+            CHANNEL_PRIORITY_BELOW_NORMAL,
+            CHANNEL_PRIORITY_NORMAL,
+            CHANNEL_PRIORITY_HIGH,
+            CHANNEL_PRIORITY_EXTRA_HIGH,
+            3893,
+            u16::MAX,
+        ] {
+            let channel_open = DataChannelOpen {
+                channel_type: ChannelType::Reliable,
+                priority,
+                reliability_parameter: 0,
+                label: b"label".to_vec(),
+                protocol: vec![],
+            };
+            let mut bytes = channel_open.marshal()?;
+            assert_eq!(&bytes[1..3], &priority.to_be_bytes());
+
+            let decoded = DataChannelOpen::unmarshal(&mut bytes)?;
+            assert_eq!(decoded.priority, priority);
+        }
+
+        Ok(())
+    }
+
+    fn channel_open_with_lengths(label_len: usize, protocol_len: usize) -> DataChannelOpen {
+        DataChannelOpen {
+            channel_type: ChannelType::Reliable,
+            priority: 0,
+            reliability_parameter: 0,
+            label: vec![b'l'; label_len],
+            protocol: vec![b'p'; protocol_len],
+        }
+    }
+
+    #[test]
+    fn test_channel_open_label_and_protocol_boundaries() -> Result<()> {
+        for (label_len, protocol_len) in [(0, 0), (65535, 0), (0, 65535), (65535, 65535)] {
+            let channel_open = channel_open_with_lengths(label_len, protocol_len);
+            let mut bytes = channel_open.marshal()?;
+            assert_eq!(bytes.len(), 11 + label_len + protocol_len);
+
+            let decoded = DataChannelOpen::unmarshal(&mut bytes)?;
+            assert_eq!(decoded, channel_open);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_channel_open_marshal_too_long() {
+        let err = channel_open_with_lengths(65536, 0)
+            .marshal()
+            .expect_err("expected label too long error");
+        assert_eq!(Error::LabelTooLong(65536), err);
+
+        let err = channel_open_with_lengths(0, 65536)
+            .marshal()
+            .expect_err("expected protocol too long error");
+        assert_eq!(Error::ProtocolTooLong(65536), err);
+    }
+
+    #[test]
+    fn test_channel_open_unmarshal_invalid_utf8() -> Result<()> {
+        let mut bytes = Bytes::from_static(&[
+            0x00, // channel type
+            0x00, 0x00, // priority
+            0x00, 0x00, 0x00, 0x00, // reliability parameter
+            0x00, 0x02, // label length
+            0x00, 0x01, // protocol length
+            0xc3, 0x28, // label, invalid 2 octet sequence
+            0xff, // protocol, invalid octet
+        ]);
+
+        // The payload is not interpreted here, the data channel decodes it lossily.
+        let channel_open = DataChannelOpen::unmarshal(&mut bytes)?;
+        assert_eq!(channel_open.label, vec![0xc3, 0x28]);
+        assert_eq!(channel_open.protocol, vec![0xff]);
+
+        Ok(())
+    }
 }
@@ -78,6 +78,7 @@ impl CipherSuite for CipherSuiteAes256CbcSha {
                 &keys.client_mac_key,
                 &keys.server_write_key,
                 &keys.server_mac_key,
+                MacHash::Sha1,
             )?);
         } else {
             self.cbc = Some(CryptoCbc::new(
@@ -85,6 +86,7 @@ impl CipherSuite for CipherSuiteAes256CbcSha {
                 &keys.server_mac_key,
                 &keys.client_write_key,
                 &keys.client_mac_key,
+                MacHash::Sha1,
             )?);
         }
 
@@ -0,0 +1,102 @@
+use super::*;
+use crate::crypto::crypto_cbc::*;
+use crate::prf::*;
+
+#[derive(Clone, Default)]
+pub struct CipherSuiteTlsEcdhePskWithAes128CbcSha256 {
+    cbc: Option<CryptoCbc>,
+}
+
+impl CipherSuiteTlsEcdhePskWithAes128CbcSha256 {
+    const PRF_MAC_LEN: usize = 32;
+    const PRF_KEY_LEN: usize = 16;
+    const PRF_IV_LEN: usize = 16;
+}
+
+impl CipherSuite for CipherSuiteTlsEcdhePskWithAes128CbcSha256 {
+    fn to_string(&self) -> String {
+        "TLS_ECDHE_PSK_WITH_AES_128_CBC_SHA256".to_owned()
+    }
+
+    fn id(&self) -> CipherSuiteId {
+        CipherSuiteId::Tls_Ecdhe_Psk_With_Aes_128_Cbc_Sha256
+    }
+
+    fn certificate_type(&self) -> ClientCertificateType {
+        ClientCertificateType::Unsupported
+    }
+
+    fn hash_func(&self) -> CipherSuiteHash {
+        CipherSuiteHash::Sha256
+    }
+
+    fn is_psk(&self) -> bool {
+        true
+    }
+
+    fn key_exchange_algorithm(&self) -> KeyExchangeAlgorithm {
+        KeyExchangeAlgorithm::EcdhePsk
+    }
+
+    fn is_initialized(&self) -> bool {
+        self.cbc.is_some()
+    }
+
+    fn init(
+        &mut self,
+        master_secret: &[u8],
+        client_random: &[u8],
+        server_random: &[u8],
+        is_client: bool,
+    ) -> Result<()> {
+        let keys = prf_encryption_keys(
+            master_secret,
+            client_random,
+            server_random,
+            CipherSuiteTlsEcdhePskWithAes128CbcSha256::PRF_MAC_LEN,
+            CipherSuiteTlsEcdhePskWithAes128CbcSha256::PRF_KEY_LEN,
+            CipherSuiteTlsEcdhePskWithAes128CbcSha256::PRF_IV_LEN,
+            self.hash_func(),
+        )?;
+
+        if is_client {
+            self.cbc = Some(CryptoCbc::new(
+                &keys.client_write_key,
+                &keys.client_mac_key,
+                &keys.server_write_key,
+                &keys.server_mac_key,
+                MacHash::Sha256,
+            )?);
+        } else {
+            self.cbc = Some(CryptoCbc::new(
+                &keys.server_write_key,
+                &keys.server_mac_key,
+                &keys.client_write_key,
+                &keys.client_mac_key,
+                MacHash::Sha256,
+            )?);
+        }
+
+        Ok(())
+    }
+
+    fn encrypt(&self, pkt_rlh: &RecordLayerHeader, raw: &[u8]) -> Result<Vec<u8>> {
+        if let Some(cbc) = &self.cbc {
+            cbc.encrypt(pkt_rlh, raw)
+        } else {
+            Err(Error::Other(
+                "CipherSuite has not been initialized, unable to encrypt".to_owned(),
+            ))
+        }
+    }
+
+    fn decrypt(&self, input: &[u8]) -> Result<Vec<u8>> {
+        if let Some(cbc) = &self.cbc {
+            cbc.decrypt(input)
+        } else {
+            Err(Error::Other(
+                "CipherSuite has not been initialized, unable to decrypt".to_owned(),
+            ))
+        }
+    }
+}
@@ -3,6 +3,7 @@ pub mod cipher_suite_aes_128_gcm_sha256;
 pub mod cipher_suite_aes_256_cbc_sha;
 pub mod cipher_suite_tls_ecdhe_ecdsa_with_aes_128_ccm;
 pub mod cipher_suite_tls_ecdhe_ecdsa_with_aes_128_ccm8;
+pub mod cipher_suite_tls_ecdhe_psk_with_aes_128_cbc_sha256;
 pub mod cipher_suite_tls_psk_with_aes_128_ccm;
 pub mod cipher_suite_tls_psk_with_aes_128_ccm8;
 pub mod cipher_suite_tls_psk_with_aes_128_gcm_sha256;
@@ -18,6 +19,7 @@ use cipher_suite_aes_128_gcm_sha256::*;
 use cipher_suite_aes_256_cbc_sha::*;
 use cipher_suite_tls_ecdhe_ecdsa_with_aes_128_ccm::*;
 use cipher_suite_tls_ecdhe_ecdsa_with_aes_128_ccm8::*;
+use cipher_suite_tls_ecdhe_psk_with_aes_128_cbc_sha256::*;
 use cipher_suite_tls_psk_with_aes_128_ccm::*;
 use cipher_suite_tls_psk_with_aes_128_ccm8::*;
 use cipher_suite_tls_psk_with_aes_128_gcm_sha256::*;
@@ -43,6 +45,8 @@ pub enum CipherSuiteId {
     Tls_Psk_With_Aes_128_Ccm_8 = 0xc0a8,
     Tls_Psk_With_Aes_128_Gcm_Sha256 = 0x00a8,
 
+    Tls_Ecdhe_Psk_With_Aes_128_Cbc_Sha256 = 0xc037,
+
     Unsupported,
 }
 
@@ -72,6 +76,9 @@ impl fmt::Display for CipherSuiteId {
             CipherSuiteId::Tls_Psk_With_Aes_128_Gcm_Sha256 => {
                 write!(f, "TLS_PSK_WITH_AES_128_GCM_SHA256")
             }
+            CipherSuiteId::Tls_Ecdhe_Psk_With_Aes_128_Cbc_Sha256 => {
+                write!(f, "TLS_ECDHE_PSK_WITH_AES_128_CBC_SHA256")
+            }
             _ => write!(f, "Unsupported CipherSuiteID"),
         }
     }
@@ -96,6 +103,8 @@ impl From<u16> for CipherSuiteId {
             0xc0a8 => CipherSuiteId::Tls_Psk_With_Aes_128_Ccm_8,
             0x00a8 => CipherSuiteId::Tls_Psk_With_Aes_128_Gcm_Sha256,
 
+            0xc037 => CipherSuiteId::Tls_Ecdhe_Psk_With_Aes_128_Cbc_Sha256,
+
             _ => CipherSuiteId::Unsupported,
         }
     }
@@ -106,6 +115,24 @@ pub enum CipherSuiteHash {
     Sha256,
 }
 
+/// KeyExchangeAlgorithm tells how the pre-master secret of a CipherSuite is established.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyExchangeAlgorithm {
+    /// Ephemeral ECDH authenticated by certificates
+    Ecdhe,
+    /// Pre-shared key only, RFC 4279
+    Psk,
+    /// Ephemeral ECDH authenticated by a pre-shared key, RFC 5489
+    EcdhePsk,
+}
+
+impl KeyExchangeAlgorithm {
+    /// has_ecdhe returns true if an ephemeral ECDH key exchange takes place.
+    pub fn has_ecdhe(&self) -> bool {
+        *self != KeyExchangeAlgorithm::Psk
+    }
+}
+
 impl CipherSuiteHash {
     pub(crate) fn size(&self) -> usize {
         match *self {
@@ -120,6 +147,13 @@ pub trait CipherSuite {
     fn certificate_type(&self) -> ClientCertificateType;
     fn hash_func(&self) -> CipherSuiteHash;
     fn is_psk(&self) -> bool;
+    fn key_exchange_algorithm(&self) -> KeyExchangeAlgorithm {
+        if self.is_psk() {
+            KeyExchangeAlgorithm::Psk
+        } else {
+            KeyExchangeAlgorithm::Ecdhe
+        }
+    }
     fn is_initialized(&self) -> bool;
 
     // Generate the internal encryption state
@@ -167,6 +201,9 @@ pub fn cipher_suite_for_id(id: CipherSuiteId) -> Result<Box<dyn CipherSuite + Se
         CipherSuiteId::Tls_Psk_With_Aes_128_Gcm_Sha256 => {
             Ok(Box::new(CipherSuiteTlsPskWithAes128GcmSha256::default()))
         }
+        CipherSuiteId::Tls_Ecdhe_Psk_With_Aes_128_Cbc_Sha256 => Ok(Box::new(
+            CipherSuiteTlsEcdhePskWithAes128CbcSha256::default(),
+        )),
         _ => Err(Error::ErrInvalidCipherSuite),
     }
 }
@@ -192,6 +229,7 @@ fn all_cipher_suites() -> Vec<Box<dyn CipherSuite + Send + Sync>> {
         Box::new(new_cipher_suite_tls_psk_with_aes_128_ccm()),
         Box::new(new_cipher_suite_tls_psk_with_aes_128_ccm8()),
         Box::new(CipherSuiteTlsPskWithAes128GcmSha256::default()),
+        Box::new(CipherSuiteTlsEcdhePskWithAes128CbcSha256::default()),
     ]
 }
 
@@ -1,9 +1,10 @@
 use crate::cipher_suite::*;
 use crate::crypto::*;
+use crate::curve::named_curve::{parse_named_curves, NamedCurve};
 use crate::error::*;
 use crate::extension::extension_use_srtp::SrtpProtectionProfile;
 use crate::handshaker::VerifyPeerCertificateFn;
-use crate::signature_hash_algorithm::SignatureScheme;
+use crate::signature_hash_algorithm::{parse_signature_schemes, SignatureScheme};
 
 use std::sync::Arc;
 use tokio::time::Duration;
@@ -22,8 +23,16 @@ pub struct Config {
     pub cipher_suites: Vec<CipherSuiteId>,
 
     /// signature_schemes contains the signature and hash schemes that the peer requests to verify.
+    /// If signature_schemes is empty, a default list is used. Otherwise each certificate must
+    /// have a private key that can sign with one of the schemes.
     pub signature_schemes: Vec<SignatureScheme>,
 
+    /// elliptic_curves are the curves supported for the ECDHE key exchange, in order of
+    /// preference. Clients offer them in the supported_groups extension, servers select the
+    /// first curve of this list that the client offered.
+    /// If elliptic_curves is empty, a default list of X25519, P-256 and P-384 is used
+    pub elliptic_curves: Vec<NamedCurve>,
+
     /// srtp_protection_profiles are the supported protection profiles
     /// Clients will send this via use_srtp and assert that the server properly responds
     /// Servers will assert that clients send one of these profiles and will respond as needed
@@ -107,6 +116,7 @@ impl Default for Config {
             certificates: vec![],
             cipher_suites: vec![],
             signature_schemes: vec![],
+            elliptic_curves: vec![],
             srtp_protection_profiles: vec![],
             client_auth: ClientAuthType::default(),
             extended_master_secret: ExtendedMasterSecretType::default(),
@@ -195,5 +205,20 @@ pub(crate) fn validate_config(is_client: bool, config: &Config) -> Result<()> {
         config.psk.is_some(),
     )?;
 
+    parse_named_curves(&config.elliptic_curves)?;
+
+    if !config.signature_schemes.is_empty() {
+        let sigs: Vec<u16> = config.signature_schemes.iter().map(|x| *x as u16).collect();
+        let signature_schemes = parse_signature_schemes(&sigs, config.insecure_hashes)?;
+        for cert in &config.certificates {
+            if !signature_schemes
+                .iter()
+                .any(|ss| ss.is_compatible(&cert.private_key))
+            {
+                return Err(Error::ErrNoSignatureSchemeForCertificate);
+            }
+        }
+    }
+
     Ok(())
 }
@@ -3,6 +3,7 @@ use crate::cipher_suite::cipher_suite_aes_128_gcm_sha256::*;
 use crate::cipher_suite::*;
 use crate::compression_methods::*;
 use crate::crypto::*;
+use crate::curve::named_curve::*;
 use crate::curve::*;
 use crate::error::*;
 use crate::extension::extension_supported_elliptic_curves::*;
@@ -438,6 +439,7 @@ async fn test_export_keying_material() -> Result<()> {
         handshake_completed_successfully: Arc::new(AtomicBool::new(false)),
         connection_closed_by_user: false,
         closed: AtomicBool::new(false),
+        tasks: TaskGroup::new(),
         current_flight: Box::new(Flight0 {}) as Box<dyn Flight + Send + Sync>,
         flights: None,
         cfg: HandshakeConfig::default(),
@@ -449,6 +451,7 @@ async fn test_export_keying_material() -> Result<()> {
         handshake_done_tx: None,
 
         reader_close_tx: Mutex::new(None),
+        replay_detector: Arc::new(SyncMutex::new(vec![])),
     };
 
     c.set_local_epoch(0);
@@ -535,11 +538,26 @@ async fn test_psk() -> Result<()> {
         (
             "Server identity specified",
             Some("Test Identity".as_bytes().to_vec()),
+            CipherSuiteId::Tls_Psk_With_Aes_128_Ccm_8,
+        ),
+        (
+            "Server identity nil",
+            None,
+            CipherSuiteId::Tls_Psk_With_Aes_128_Ccm_8,
+        ),
+        (
+            "ECDHE_PSK server identity specified",
+            Some("Test Identity".as_bytes().to_vec()),
+            CipherSuiteId::Tls_Ecdhe_Psk_With_Aes_128_Cbc_Sha256,
+        ),
+        (
+            "ECDHE_PSK server identity nil",
+            None,
+            CipherSuiteId::Tls_Ecdhe_Psk_With_Aes_128_Cbc_Sha256,
         ),
-        ("Server identity nil", None),
     ];
 
-    for (name, server_identity) in tests {
+    for (name, server_identity, cipher_suite) in tests {
         let client_identity = "Client Identity".as_bytes();
         let (client_res_tx, mut client_res_rx) = mpsc::channel(1);
 
@@ -548,7 +566,7 @@ async fn test_psk() -> Result<()> {
             let conf = Config {
                 psk: Some(Arc::new(psk_callback_client)),
                 psk_identity_hint: Some(client_identity.to_vec()),
-                cipher_suites: vec![CipherSuiteId::Tls_Psk_With_Aes_128_Ccm_8],
+                cipher_suites: vec![cipher_suite],
                 ..Default::default()
             };
 
@@ -559,12 +577,22 @@ async fn test_psk() -> Result<()> {
         let config = Config {
             psk: Some(Arc::new(psk_callback_server)),
             psk_identity_hint: server_identity,
-            cipher_suites: vec![CipherSuiteId::Tls_Psk_With_Aes_128_Ccm_8],
+            cipher_suites: vec![cipher_suite],
             ..Default::default()
         };
 
         let server = create_test_server(Arc::new(cb), config, false).await?;
 
+        let actual_cipher_suite = server.selected_cipher_suite().await;
+        assert_eq!(
+            actual_cipher_suite,
+            Some(cipher_suite),
+            "TestPSK: Server CipherSuite Mismatch '{}': expected({}) actual({:?})",
+            name,
+            cipher_suite,
+            actual_cipher_suite,
+        );
+
         let actual_psk_identity_hint = &server.connection_state().await.identity_hint;
         assert_eq!(
             actual_psk_identity_hint, client_identity,
@@ -590,6 +618,65 @@ async fn test_psk() -> Result<()> {
     Ok(())
 }
 
+fn psk_callback_server_known_identity(identity: &[u8]) -> Result<Vec<u8>> {
+    if identity != "Client Identity".as_bytes() {
+        return Err(Error::Other(ERR_PSK_REJECTED.to_owned()));
+    }
+    Ok(vec![0xAB, 0xC1, 0x23])
+}
+
+#[tokio::test]
+async fn test_psk_identity_mismatch() -> Result<()> {
+    for cipher_suite in [
+        CipherSuiteId::Tls_Psk_With_Aes_128_Ccm_8,
+        CipherSuiteId::Tls_Ecdhe_Psk_With_Aes_128_Cbc_Sha256,
+    ] {
+        let (client_res_tx, mut client_res_rx) = mpsc::channel(1);
+
+        let (ca, cb) = pipe();
+        tokio::spawn(async move {
+            let conf = Config {
+                psk: Some(Arc::new(psk_callback_client)),
+                psk_identity_hint: Some("Unknown Identity".as_bytes().to_vec()),
+                cipher_suites: vec![cipher_suite],
+                ..Default::default()
+            };
+
+            let result = create_test_client(Arc::new(ca), conf, false).await;
+            let _ = client_res_tx.send(result).await;
+        });
+
+        let config = Config {
+            psk: Some(Arc::new(psk_callback_server_known_identity)),
+            psk_identity_hint: Some("Test Identity".as_bytes().to_vec()),
+            cipher_suites: vec![cipher_suite],
+            ..Default::default()
+        };
+
+        if let Err(server_err) = create_test_server(Arc::new(cb), config, false).await {
+            assert!(
+                server_err.to_string().contains(ERR_PSK_REJECTED),
+                "TestPSKIdentityMismatch {}: Server error exp({}) failed({})",
+                cipher_suite,
+                ERR_PSK_REJECTED,
+                server_err,
+            );
+        } else {
+            panic!("{}: Expected server error, but got OK", cipher_suite);
+        }
+
+        if let Some(result) = client_res_rx.recv().await {
+            assert!(
+                result.is_err(),
+                "{}: Expected client error, but got OK",
+                cipher_suite
+            );
+        }
+    }
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_psk_hint_fail() -> Result<()> {
     /*env_logger::Builder::new()
@@ -1688,6 +1775,214 @@ async fn test_cipher_suite_configuration() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_elliptic_curve_configuration() -> Result<()> {
+    let tests = vec![
+        (
+            "No curves specified",
+            vec![],
+            vec![],
+            None,
+            None,
+            Some(NamedCurve::X25519),
+        ),
+        (
+            "Invalid curve",
+            vec![NamedCurve::Unsupported],
+            vec![NamedCurve::Unsupported],
+            Some(Error::ErrInvalidNamedCurve),
+            Some(Error::ErrInvalidNamedCurve),
+            None,
+        ),
+        (
+            "X25519 only",
+            vec![NamedCurve::X25519],
+            vec![NamedCurve::X25519],
+            None,
+            None,
+            Some(NamedCurve::X25519),
+        ),
+        (
+            "P-256 only",
+            vec![NamedCurve::P256],
+            vec![NamedCurve::P256],
+            None,
+            None,
+            Some(NamedCurve::P256),
+        ),
+        (
+            "P-384 only",
+            vec![NamedCurve::P384],
+            vec![NamedCurve::P384],
+            None,
+            None,
+            Some(NamedCurve::P384),
+        ),
+        (
+            "Server restricted to X25519",
+            vec![],
+            vec![NamedCurve::X25519],
+            None,
+            None,
+            Some(NamedCurve::X25519),
+        ),
+        (
+            "Client restricted to P-256",
+            vec![NamedCurve::P256],
+            vec![],
+            None,
+            None,
+            Some(NamedCurve::P256),
+        ),
+        (
+            "Server order takes precedence",
+            vec![NamedCurve::X25519, NamedCurve::P256],
+            vec![NamedCurve::P256, NamedCurve::X25519],
+            None,
+            None,
+            Some(NamedCurve::P256),
+        ),
+        (
+            "Server defaults take precedence",
+            vec![NamedCurve::P384, NamedCurve::P256, NamedCurve::X25519],
+            vec![],
+            None,
+            None,
+            Some(NamedCurve::X25519),
+        ),
+        (
+            "No common curve",
+            vec![NamedCurve::X25519],
+            vec![NamedCurve::P256, NamedCurve::P384],
+            Some(Error::ErrAlertFatalOrClose),
+            Some(Error::ErrNoCommonEllipticCurve),
+            None,
+        ),
+    ];
+
+    for (
+        name,
+        client_elliptic_curves,
+        server_elliptic_curves,
+        want_client_error,
+        want_server_error,
+        want_named_curve,
+    ) in tests
+    {
+        let (client_res_tx, mut client_res_rx) = mpsc::channel(1);
+        let (ca, cb) = pipe();
+        tokio::spawn(async move {
+            let conf = Config {
+                elliptic_curves: client_elliptic_curves,
+                ..Default::default()
+            };
+
+            let result = create_test_client(Arc::new(ca), conf, true).await;
+            let _ = client_res_tx.send(result).await;
+        });
+
+        let config = Config {
+            elliptic_curves: server_elliptic_curves,
+            ..Default::default()
+        };
+
+        let server_result = create_test_server(Arc::new(cb), config, true).await;
+        let client_result = client_res_rx.recv().await.unwrap();
+
+        for (role, result, want_error) in [
+            ("client", client_result, want_client_error),
+            ("server", server_result, want_server_error),
+        ] {
+            match (result, want_error) {
+                (Err(err), Some(expected_err)) => assert_eq!(
+                    err.to_string(),
+                    expected_err.to_string(),
+                    "{} {}: unexpected error",
+                    name,
+                    role
+                ),
+                (Ok(_), Some(expected_err)) => {
+                    panic!(
+                        "{} {}: expected error {}, but got ok",
+                        name, role, expected_err
+                    )
+                }
+                (Err(err), None) => panic!("{} {}: expected ok, but got {}", name, role, err),
+                (Ok(conn), None) => {
+                    assert_eq!(
+                        Some(conn.state.named_curve),
+                        want_named_curve,
+                        "{} {}: negotiated curve",
+                        name,
+                        role
+                    );
+                    let _ = conn.close().await;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_signature_scheme_configuration() -> Result<()> {
+    // The certificate must be able to sign with one of the configured schemes
+    let (ca, _cb) = pipe();
+    let result = create_test_client(
+        Arc::new(ca),
+        Config {
+            signature_schemes: vec![SignatureScheme::Ed25519],
+            ..Default::default()
+        },
+        true,
+    )
+    .await;
+    match result {
+        Err(err) => assert_eq!(err, Error::ErrNoSignatureSchemeForCertificate),
+        Ok(_) => panic!("expected ErrNoSignatureSchemeForCertificate, but got ok"),
+    }
+
+    // Ed25519 certificates restricted to Ed25519 signatures on both ends
+    let (client_res_tx, mut client_res_rx) = mpsc::channel(1);
+    let (ca, cb) = pipe();
+    tokio::spawn(async move {
+        let conf = Config {
+            certificates: vec![Certificate::generate_self_signed_with_alg(
+                vec!["localhost".to_owned()],
+                &rcgen::PKCS_ED25519,
+            )
+            .unwrap()],
+            signature_schemes: vec![SignatureScheme::Ed25519],
+            elliptic_curves: vec![NamedCurve::X25519],
+            ..Default::default()
+        };
+
+        let result = create_test_client(Arc::new(ca), conf, false).await;
+        let _ = client_res_tx.send(result).await;
+    });
+
+    let config = Config {
+        certificates: vec![Certificate::generate_self_signed_with_alg(
+            vec!["localhost".to_owned()],
+            &rcgen::PKCS_ED25519,
+        )?],
+        signature_schemes: vec![SignatureScheme::Ed25519],
+        elliptic_curves: vec![NamedCurve::X25519],
+        ..Default::default()
+    };
+    let server = create_test_server(Arc::new(cb), config, false).await?;
+    let client = client_res_rx.recv().await.unwrap()?;
+
+    assert_eq!(server.state.named_curve, NamedCurve::X25519);
+    assert_eq!(client.state.named_curve, NamedCurve::X25519);
+
+    client.close().await?;
+    server.close().await?;
+
+    Ok(())
+}
+
 fn psk_callback(_b: &[u8]) -> Result<Vec<u8>> {
     Ok(vec![0x00, 0x01, 0x02])
 }
@@ -2226,6 +2521,7 @@ async fn test_protocol_version_validation() -> Result<()> {
                                 signature: SignatureAlgorithm::Ecdsa,
                             },
                             signature: vec![0; 64],
+                            key_exchange_algorithm: KeyExchangeAlgorithm::Ecdhe,
                         },
                     ));
                     handshake.handshake_header.message_sequence = 3;
@@ -2538,3 +2834,138 @@ async fn test_renegotation_info() -> Result<()> {
 
     Ok(())
 }
+
+/// RecordingConn records the packets sent through the connection it wraps.
+#[cfg(feature = "dangerous_state_export")]
+struct RecordingConn {
+    conn: Arc<dyn util::Conn + Send + Sync>,
+    sent: SyncMutex<Vec<Vec<u8>>>,
+}
+
+#[cfg(feature = "dangerous_state_export")]
+#[async_trait]
+impl util::Conn for RecordingConn {
+    async fn connect(&self, addr: SocketAddr) -> UtilResult<()> {
+        self.conn.connect(addr).await
+    }
+    async fn recv(&self, buf: &mut [u8]) -> UtilResult<usize> {
+        self.conn.recv(buf).await
+    }
+    async fn recv_from(&self, buf: &mut [u8]) -> UtilResult<(usize, SocketAddr)> {
+        self.conn.recv_from(buf).await
+    }
+    async fn send(&self, buf: &[u8]) -> UtilResult<usize> {
+        self.sent.lock().push(buf.to_vec());
+        self.conn.send(buf).await
+    }
+    async fn send_to(&self, buf: &[u8], target: SocketAddr) -> UtilResult<usize> {
+        self.conn.send_to(buf, target).await
+    }
+    fn local_addr(&self) -> UtilResult<SocketAddr> {
+        self.conn.local_addr()
+    }
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        self.conn.remote_addr()
+    }
+    async fn close(&self) -> UtilResult<()> {
+        self.conn.close().await
+    }
+}
+
+#[cfg(feature = "dangerous_state_export")]
+async fn read_string(c: &DTLSConn) -> Result<String> {
+    let mut buf = vec![0u8; 100];
+    let n = c.read(&mut buf, Some(Duration::from_secs(5))).await?;
+    Ok(String::from_utf8(buf[..n].to_vec()).unwrap())
+}
+
+#[cfg(feature = "dangerous_state_export")]
+#[tokio::test]
+async fn test_export_and_resume_state() -> Result<()> {
+    let (ua, ub) = pipe();
+    let ca = Arc::new(RecordingConn {
+        conn: Arc::new(ua),
+        sent: SyncMutex::new(vec![]),
+    });
+    let cb: Arc<dyn util::Conn + Send + Sync> = Arc::new(ub);
+    let (client, server) = pipe_conn(
+        Arc::clone(&ca) as Arc<dyn util::Conn + Send + Sync>,
+        Arc::clone(&cb),
+    )
+    .await?;
+
+    client.write(b"before", None).await?;
+    assert_eq!(read_string(&server).await?, "before");
+    let replayed = ca.sent.lock().last().cloned().unwrap();
+    server.write(b"hello", None).await?;
+    assert_eq!(read_string(&client).await?, "hello");
+
+    let srtp_keys = server
+        .connection_state()
+        .await
+        .export_keying_material("EXTRACTOR-dtls_srtp", &[], 30)
+        .await?;
+
+    // The original connection is stopped, without notifying the client, and dropped
+    let exported = tokio::spawn(async move {
+        let state = server.export_state().await?;
+        assert!(!state.is_client());
+        assert_eq!(
+            server.write(b"stopped", None).await,
+            Err(Error::ErrConnClosed)
+        );
+        assert_eq!(
+            server.export_state().await.err(),
+            Some(Error::ErrConnClosed)
+        );
+        state.marshal_binary()
+    })
+    .await
+    .unwrap()?;
+
+    let server = tokio::spawn(async move {
+        let state = ExportedState::unmarshal_binary(&exported)?;
+        DTLSConn::resume_from_state(
+            state,
+            cb,
+            Config {
+                certificates: vec![Certificate::generate_self_signed(vec![
+                    "localhost".to_owned()
+                ])?],
+                ..Default::default()
+            },
+        )
+        .await
+    })
+    .await
+    .unwrap()?;
+
+    assert_eq!(
+        server.selected_srtpprotection_profile(),
+        SrtpProtectionProfile::Srtp_Aes128_Cm_Hmac_Sha1_80
+    );
+    assert_eq!(
+        server
+            .connection_state()
+            .await
+            .export_keying_material("EXTRACTOR-dtls_srtp", &[], 30)
+            .await?,
+        srtp_keys
+    );
+
+    // A record received before the export is still detected as replayed
+    ca.conn.send(&replayed).await?;
+
+    for i in 0..3 {
+        let message = format!("after {}", i);
+        client.write(message.as_bytes(), None).await?;
+        assert_eq!(read_string(&server).await?, message);
+        server.write(message.as_bytes(), None).await?;
+        assert_eq!(read_string(&client).await?, message);
+    }
+
+    client.close().await?;
+    server.close().await?;
+
+    Ok(())
+}
@@ -1,12 +1,17 @@
 #[cfg(test)]
 mod conn_test;
+#[cfg(feature = "dangerous_state_export")]
+mod state_export;
+
+#[cfg(feature = "dangerous_state_export")]
+pub use state_export::ExportedState;
 
 use crate::alert::*;
 use crate::application_data::*;
 use crate::cipher_suite::*;
 use crate::config::*;
 use crate::content::*;
-use crate::curve::named_curve::NamedCurve;
+use crate::curve::named_curve::parse_named_curves;
 use crate::error::*;
 use crate::extension::extension_use_srtp::*;
 use crate::flight::flight0::*;
@@ -24,7 +29,7 @@ use crate::record_layer::*;
 use crate::signature_hash_algorithm::parse_signature_schemes;
 use crate::state::*;
 
-use util::{replay_detector::*, Conn};
+use util::{replay_detector::*, sync::Mutex as SyncMutex, sync::TaskGroup, Conn};
 
 use async_trait::async_trait;
 use log::*;
@@ -38,7 +43,6 @@ use tokio::time::Duration;
 
 pub(crate) const INITIAL_TICKER_INTERVAL: Duration = Duration::from_secs(1);
 pub(crate) const COOKIE_LENGTH: usize = 20;
-pub(crate) const DEFAULT_NAMED_CURVE: NamedCurve = NamedCurve::X25519;
 pub(crate) const INBOUND_BUFFER_SIZE: usize = 8192;
 // Default replay protection window is specified by RFC 6347 Section 4.1.2.6
 pub(crate) const DEFAULT_REPLAY_PROTECTION_WINDOW: usize = 64;
@@ -55,7 +59,7 @@ type PacketSendRequest = (Vec<Packet>, Option<mpsc::Sender<Result<()>>>);
 struct ConnReaderContext {
     is_client: bool,
     replay_protection_window: usize,
-    replay_detector: Vec<Box<dyn ReplayDetector + Send>>,
+    replay_detector: Arc<SyncMutex<Vec<SlidingWindowDetector>>>,
     decrypted_tx: mpsc::Sender<Result<Vec<u8>>>,
     encrypted_packets: Vec<Vec<u8>>,
     fragment_buffer: FragmentBuffer,
@@ -78,7 +82,8 @@ pub struct DTLSConn {
     connection_closed_by_user: bool,
     // closeLock              sync.Mutex
     closed: AtomicBool, //  *closer.Closer
-    //handshakeLoopsFinished sync.WaitGroup
+    // the read and write loops, stopped when the connection is closed
+    tasks: TaskGroup,
 
     //readDeadline  :deadline.Deadline,
     //writeDeadline :deadline.Deadline,
@@ -101,6 +106,8 @@ pub struct DTLSConn {
     pub(crate) handshake_done_tx: Option<mpsc::Sender<()>>,
 
     reader_close_tx: Mutex<Option<mpsc::Sender<()>>>,
+    // the replay windows of the remote epochs, shared with the read loop
+    replay_detector: Arc<SyncMutex<Vec<SlidingWindowDetector>>>,
 }
 
 type UtilResult<T> = std::result::Result<T, util::Error>;
@@ -142,10 +149,22 @@ impl Conn for DTLSConn {
 
 impl DTLSConn {
     pub async fn new(
+        conn: Arc<dyn Conn + Send + Sync>,
+        config: Config,
+        is_client: bool,
+        initial_state: Option<State>,
+    ) -> Result<Self> {
+        DTLSConn::new_with_replay_windows(conn, config, is_client, initial_state, vec![]).await
+    }
+
+    /// new_with_replay_windows creates the connection like `new`, with the sequence numbers
+    /// already accepted from the remote in each epoch, see `SlidingWindowDetector::accepted`.
+    pub(crate) async fn new_with_replay_windows(
         conn: Arc<dyn Conn + Send + Sync>,
         mut config: Config,
         is_client: bool,
         initial_state: Option<State>,
+        replay_windows: Vec<Vec<u64>>,
     ) -> Result<Self> {
         validate_config(is_client, &config)?;
 
@@ -160,6 +179,7 @@ impl DTLSConn {
 
         let sigs: Vec<u16> = config.signature_schemes.iter().map(|x| *x as u16).collect();
         let local_signature_schemes = parse_signature_schemes(&sigs, config.insecure_hashes)?;
+        let local_elliptic_curves = parse_named_curves(&config.elliptic_curves)?;
 
         let retransmit_interval = if config.flight_interval != Duration::from_secs(0) {
             config.flight_interval
@@ -186,6 +206,20 @@ impl DTLSConn {
         } else {
             config.replay_protection_window
         };
+        let replay_detector: Vec<SlidingWindowDetector> = replay_windows
+            .iter()
+            .map(|accepted| {
+                let mut detector =
+                    SlidingWindowDetector::new(replay_protection_window, MAX_SEQUENCE_NUMBER);
+                for &seq in accepted {
+                    if detector.check(seq) {
+                        detector.accept();
+                    }
+                }
+                detector
+            })
+            .collect();
+        let replay_detector = Arc::new(SyncMutex::new(replay_detector));
 
         let mut server_name = config.server_name.clone();
 
@@ -204,6 +238,7 @@ impl DTLSConn {
             local_psk_identity_hint: config.psk_identity_hint.take(),
             local_cipher_suites,
             local_signature_schemes,
+            local_elliptic_curves,
             extended_master_secret: config.extended_master_secret,
             local_srtp_protection_profiles: config.srtp_protection_profiles.clone(),
             server_name,
@@ -286,12 +321,14 @@ impl DTLSConn {
             handle_queue_tx,
             handshake_done_tx: Some(handshake_done_tx),
             reader_close_tx: Mutex::new(Some(reader_close_tx)),
+            tasks: TaskGroup::new(),
+            replay_detector: Arc::clone(&replay_detector),
         };
 
         let cipher_suite1 = Arc::clone(&c.state.cipher_suite);
         let sequence_number = Arc::clone(&c.state.local_sequence_number);
 
-        tokio::spawn(async move {
+        c.tasks.spawn(async move {
             loop {
                 let rx = packet_rx.recv().await;
                 if let Some(r) = rx {
@@ -322,12 +359,12 @@ impl DTLSConn {
         let remote_epoch = Arc::clone(&c.state.remote_epoch);
         let cipher_suite2 = Arc::clone(&c.state.cipher_suite);
 
-        tokio::spawn(async move {
+        c.tasks.spawn(async move {
             let mut buf = vec![0u8; INBOUND_BUFFER_SIZE];
             let mut ctx = ConnReaderContext {
                 is_client,
                 replay_protection_window,
-                replay_detector: vec![],
+                replay_detector,
                 decrypted_tx,
                 encrypted_packets: vec![],
                 fragment_buffer: FragmentBuffer::new(),
@@ -379,7 +416,10 @@ impl DTLSConn {
         });
 
         // Do handshake
-        c.handshake(initial_fsm_state).await?;
+        if let Err(err) = c.handshake(initial_fsm_state).await {
+            c.tasks.cancel();
+            return Err(err);
+        }
 
         trace!("Handshake Completed");
 
@@ -476,6 +516,11 @@ impl DTLSConn {
                 reader_close_tx.take();
             }
             self.conn.close().await?;
+
+            // Both loops have nothing left to do once the connection is closed, the write loop
+            // would only stop once the connection is dropped.
+            self.tasks.cancel();
+            self.tasks.wait().await;
         }
 
         Ok(())
@@ -492,6 +537,12 @@ impl DTLSConn {
         self.state.srtp_protection_profile
     }
 
+    /// selected_cipher_suite returns the negotiated cipher suite, if the handshake chose one
+    pub async fn selected_cipher_suite(&self) -> Option<CipherSuiteId> {
+        let cipher_suite = self.state.cipher_suite.lock().await;
+        cipher_suite.as_ref().map(|cs| cs.id())
+    }
+
     pub(crate) async fn notify(&self, level: AlertLevel, desc: AlertDescription) -> Result<()> {
         self.write_packets(vec![Packet {
             record: RecordLayer::new(
@@ -920,15 +971,16 @@ impl DTLSConn {
         }
 
         // Anti-replay protection
-        while ctx.replay_detector.len() <= h.epoch as usize {
-            ctx.replay_detector
-                .push(Box::new(SlidingWindowDetector::new(
+        let ok = {
+            let mut replay_detector = ctx.replay_detector.lock();
+            while replay_detector.len() <= h.epoch as usize {
+                replay_detector.push(SlidingWindowDetector::new(
                     ctx.replay_protection_window,
                     MAX_SEQUENCE_NUMBER,
-                )));
-        }
-
-        let ok = ctx.replay_detector[h.epoch as usize].check(h.sequence_number);
+                ));
+            }
+            replay_detector[h.epoch as usize].check(h.sequence_number)
+        };
         if !ok {
             debug!(
                 "{}: discarded duplicated packet (epoch: {}, seq: {})",
@@ -984,20 +1036,31 @@ impl DTLSConn {
             }
         };
         if is_handshake {
-            ctx.replay_detector[h.epoch as usize].accept();
+            ctx.replay_detector.lock()[h.epoch as usize].accept();
             while let Ok((out, epoch)) = ctx.fragment_buffer.pop() {
                 //log::debug!("Extension Debug: out.len()={}", out.len());
                 let mut reader = BufReader::new(out.as_slice());
-                let raw_handshake = match Handshake::unmarshal(&mut reader) {
-                    Ok(rh) => {
+                // Key exchange bodies can not be parsed until the negotiated cipher
+                // suite is known, so only their header is checked here.
+                let parsed = HandshakeHeader::unmarshal(&mut reader).and_then(|hh| {
+                    match hh.handshake_type {
+                        HandshakeType::ServerKeyExchange | HandshakeType::ClientKeyExchange => {
+                            Ok(hh)
+                        }
+                        _ => Handshake::unmarshal(&mut BufReader::new(out.as_slice()))
+                            .map(|rh| rh.handshake_header),
+                    }
+                });
+                let handshake_header = match parsed {
+                    Ok(hh) => {
                         trace!(
                             "Recv [handshake:{}] -> {} (epoch: {}, seq: {})",
                             srv_cli_str(ctx.is_client),
-                            rh.handshake_header.handshake_type.to_string(),
+                            hh.handshake_type,
                             h.epoch,
-                            rh.handshake_header.message_sequence
+                            hh.message_sequence
                         );
-                        rh
+                        hh
                     }
                     Err(err) => {
                         debug!(
@@ -1013,8 +1076,8 @@ impl DTLSConn {
                     .push(
                         out,
                         epoch,
-                        raw_handshake.handshake_header.message_sequence,
-                        raw_handshake.handshake_header.handshake_type,
+                        handshake_header.message_sequence,
+                        handshake_header.handshake_type,
                         !ctx.is_client,
                     )
                     .await;
@@ -1048,7 +1111,7 @@ impl DTLSConn {
                         alert_description: AlertDescription::CloseNotify,
                     };
                 }
-                ctx.replay_detector[h.epoch as usize].accept();
+                ctx.replay_detector.lock()[h.epoch as usize].accept();
                 return (
                     false,
                     Some(a),
@@ -1087,7 +1150,7 @@ impl DTLSConn {
 
                 if epoch + 1 == new_remote_epoch {
                     ctx.remote_epoch.store(new_remote_epoch, Ordering::SeqCst);
-                    ctx.replay_detector[h.epoch as usize].accept();
+                    ctx.replay_detector.lock()[h.epoch as usize].accept();
                 }
             }
             Content::ApplicationData(a) => {
@@ -1102,7 +1165,7 @@ impl DTLSConn {
                     );
                 }
 
-                ctx.replay_detector[h.epoch as usize].accept();
+                ctx.replay_detector.lock()[h.epoch as usize].accept();
 
                 let _ = ctx.decrypted_tx.send(Ok(a.data)).await;
                 //TODO
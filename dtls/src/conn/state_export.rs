@@ -0,0 +1,98 @@
+use super::*;
+
+use serde::{Deserialize, Serialize};
+
+/// ExportedState is a snapshot of an established connection taken by `DTLSConn::export_state`,
+/// from which `DTLSConn::resume_from_state` carries on with the connection, e.g. in another
+/// process. It holds the master secret of the connection: whoever gets hold of it can decrypt
+/// and forge the traffic of the connection, as well as the SRTP traffic keyed from it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExportedState {
+    is_client: bool,
+    /// The state as serialized by `State::marshal_binary`
+    state: Vec<u8>,
+    /// The sequence numbers accepted from the remote within the replay window, per epoch
+    replay_windows: Vec<Vec<u64>>,
+}
+
+impl ExportedState {
+    /// is_client returns whether the connection was the DTLS client.
+    pub fn is_client(&self) -> bool {
+        self.is_client
+    }
+
+    /// marshal_binary serializes the snapshot.
+    pub fn marshal_binary(&self) -> Result<Vec<u8>> {
+        bincode::serialize(self).map_err(|err| Error::Other(err.to_string()))
+    }
+
+    /// unmarshal_binary deserializes a snapshot serialized by `marshal_binary`.
+    pub fn unmarshal_binary(data: &[u8]) -> Result<Self> {
+        bincode::deserialize(data).map_err(|err| Error::Other(err.to_string()))
+    }
+}
+
+impl DTLSConn {
+    /// export_state stops the connection and returns a snapshot of it, to hand it over to
+    /// another process with `resume_from_state`. The snapshot holds the negotiated cipher suite
+    /// and SRTP protection profile, the master secret and randoms the keys derive from, the
+    /// epochs, the local sequence number and the remote replay windows. Connection IDs are not
+    /// supported. SRTP sessions keyed from the connection are handed over separately, with the
+    /// rollover counters of `srtp::context::Context::get_roc`.
+    ///
+    /// The connection stops reading and writing without notifying the peer and leaves the
+    /// underlying connection open, for the resumed connection to use it. The sequence numbers
+    /// move to the snapshot: it must be resumed only once, reusing them would replay nonces.
+    pub async fn export_state(&self) -> Result<ExportedState> {
+        if !self.is_handshake_completed_successfully() {
+            return Err(Error::ErrHandshakeInProgress);
+        }
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return Err(Error::ErrConnClosed);
+        }
+
+        {
+            let mut reader_close_tx = self.reader_close_tx.lock().await;
+            reader_close_tx.take();
+        }
+        // Nothing is sent or accepted past this point, the snapshot is final
+        self.tasks.cancel();
+        self.tasks.wait().await;
+
+        let state = self.state.marshal_binary().await?;
+        let replay_windows = self
+            .replay_detector
+            .lock()
+            .iter()
+            .map(|detector| detector.accepted())
+            .collect();
+
+        Ok(ExportedState {
+            is_client: self.state.is_client,
+            state,
+            replay_windows,
+        })
+    }
+
+    /// resume_from_state carries on with a connection exported by `export_state` over conn,
+    /// without a new handshake. config must be valid for the role of the connection, as for
+    /// `new`, and provides the settings which aren't part of the snapshot, e.g. the MTU or the
+    /// replay protection window.
+    pub async fn resume_from_state(
+        state: ExportedState,
+        conn: Arc<dyn Conn + Send + Sync>,
+        config: Config,
+    ) -> Result<Self> {
+        let mut initial_state = State::default();
+        initial_state.unmarshal_binary(&state.state).await?;
+
+        DTLSConn::new_with_replay_windows(
+            conn,
+            config,
+            state.is_client,
+            Some(initial_state),
+            state.replay_windows,
+        )
+        .await
+    }
+}
@@ -16,13 +16,14 @@ use crate::error::*;
 use crate::prf::*;
 use crate::record_layer::record_layer_header::*;
 
-use aes::Aes256;
+use aes::{Aes128, Aes256};
 use block_modes::BlockModeError;
 use block_modes::{BlockMode, Cbc};
 use rand::Rng;
 use subtle::ConstantTimeEq;
 
 use super::padding::DtlsPadding;
+type Aes128Cbc = Cbc<Aes128, DtlsPadding>;
 type Aes256Cbc = Cbc<Aes256, DtlsPadding>;
 
 // State needed to handle encrypted input/output
@@ -32,17 +33,20 @@ pub struct CryptoCbc {
     remote_key: Vec<u8>,
     write_mac: Vec<u8>,
     read_mac: Vec<u8>,
+    mac_hash: MacHash,
 }
 
 impl CryptoCbc {
     const BLOCK_SIZE: usize = 16;
-    const MAC_SIZE: usize = 20;
+    const AES_128_KEY_SIZE: usize = 16;
 
+    /// new creates the CBC state, AES-128 or AES-256 is picked from the key length.
     pub fn new(
         local_key: &[u8],
         local_mac: &[u8],
         remote_key: &[u8],
         remote_mac: &[u8],
+        mac_hash: MacHash,
     ) -> Result<Self> {
         Ok(CryptoCbc {
             local_key: local_key.to_vec(),
@@ -50,6 +54,8 @@ impl CryptoCbc {
 
             remote_key: remote_key.to_vec(),
             read_mac: remote_mac.to_vec(),
+
+            mac_hash,
         })
     }
 
@@ -67,14 +73,18 @@ impl CryptoCbc {
             h.protocol_version,
             &payload,
             &self.write_mac,
+            self.mac_hash,
         )?;
         payload.extend_from_slice(&mac);
 
         let mut iv: Vec<u8> = vec![0; Self::BLOCK_SIZE];
         rand::thread_rng().fill(iv.as_mut_slice());
 
-        let write_cbc = Aes256Cbc::new_var(&self.local_key, &iv)?;
-        let encrypted = write_cbc.encrypt_vec(&payload);
+        let encrypted = if self.local_key.len() == Self::AES_128_KEY_SIZE {
+            Aes128Cbc::new_var(&self.local_key, &iv)?.encrypt_vec(&payload)
+        } else {
+            Aes256Cbc::new_var(&self.local_key, &iv)?.encrypt_vec(&payload)
+        };
 
         // Prepend unencrypte header with encrypted payload
         let mut r = vec![];
@@ -102,12 +112,18 @@ impl CryptoCbc {
         let body = &body[Self::BLOCK_SIZE..];
         //TODO: add body.len() check
 
-        let read_cbc = Aes256Cbc::new_var(&self.remote_key, iv)?;
-
-        let decrypted = read_cbc.decrypt_vec(body)?;
+        let decrypted = if self.remote_key.len() == Self::AES_128_KEY_SIZE {
+            Aes128Cbc::new_var(&self.remote_key, iv)?.decrypt_vec(body)?
+        } else {
+            Aes256Cbc::new_var(&self.remote_key, iv)?.decrypt_vec(body)?
+        };
 
-        let recv_mac = &decrypted[decrypted.len() - Self::MAC_SIZE..];
-        let decrypted = &decrypted[0..decrypted.len() - Self::MAC_SIZE];
+        let mac_size = self.mac_hash.size();
+        if decrypted.len() < mac_size {
+            return Err(BlockModeError.into());
+        }
+        let recv_mac = &decrypted[decrypted.len() - mac_size..];
+        let decrypted = &decrypted[0..decrypted.len() - mac_size];
         let mac = prf_mac(
             h.epoch,
             h.sequence_number,
@@ -115,6 +131,7 @@ impl CryptoCbc {
             h.protocol_version,
             decrypted,
             &self.read_mac,
+            self.mac_hash,
         )?;
 
         if recv_mac.ct_eq(&mac).not().into() {
@@ -71,6 +71,32 @@ fn elliptic_curve_keypair(curve: NamedCurve) -> Result<NamedCurveKeypair> {
     })
 }
 
+/// default_named_curves are the curves offered for ECDHE when Config::elliptic_curves is empty,
+/// in order of preference.
+pub(crate) fn default_named_curves() -> Vec<NamedCurve> {
+    vec![NamedCurve::X25519, NamedCurve::P256, NamedCurve::P384]
+}
+
+/// parse_named_curves validates the configured curves, it returns the default curves
+/// if none are configured.
+pub(crate) fn parse_named_curves(curves: &[NamedCurve]) -> Result<Vec<NamedCurve>> {
+    if curves.is_empty() {
+        return Ok(default_named_curves());
+    }
+
+    let mut out: Vec<NamedCurve> = vec![];
+    for curve in curves {
+        if *curve == NamedCurve::Unsupported {
+            return Err(Error::ErrInvalidNamedCurve);
+        }
+        if !out.contains(curve) {
+            out.push(*curve);
+        }
+    }
+
+    Ok(out)
+}
+
 impl NamedCurve {
     pub fn generate_keypair(&self) -> Result<NamedCurveKeypair> {
         match *self {
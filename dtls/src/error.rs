@@ -37,6 +37,8 @@ pub enum Error {
     ErrCipherSuiteNoIntersection,
     #[error("server hello can not be created without a cipher suite")]
     ErrCipherSuiteUnset,
+    #[error("key exchange messages can not be parsed without a cipher suite")]
+    ErrKeyExchangeAlgorithmUnset,
     #[error("client sent certificate but did not verify it")]
     ErrClientCertificateNotVerified,
     #[error("server required client verification, but got none")]
@@ -95,6 +97,14 @@ pub enum Error {
     ErrNoConfigProvided,
     #[error("client requested zero or more elliptic curves that are not supported by the server")]
     ErrNoSupportedEllipticCurves,
+    #[error("none of the elliptic curves offered by the client is enabled on the server")]
+    ErrNoCommonEllipticCurve,
+    #[error("server selected an elliptic curve the client didn't offer")]
+    ErrUnofferedEllipticCurve,
+    #[error(
+        "none of the configured signature schemes can be used with the certificate's private key"
+    )]
+    ErrNoSignatureSchemeForCertificate,
     #[error("unsupported protocol version")]
     ErrUnsupportedProtocolVersion,
     #[error("Certificate and PSK provided")]
@@ -34,6 +34,7 @@ impl Flight for Flight0 {
         let (seq, msgs) = match cache
             .full_pull_map(
                 0,
+                None,
                 &[HandshakeCachePullRule {
                     typ: HandshakeType::ClientHello,
                     epoch: cfg.initial_epoch,
@@ -110,7 +111,23 @@ impl Flight for Flight0 {
                                 Some(Error::ErrNoSupportedEllipticCurves),
                             ));
                         }
-                        state.named_curve = e.elliptic_curves[0];
+                        // The server's order of preference takes precedence
+                        state.named_curve = match cfg
+                            .local_elliptic_curves
+                            .iter()
+                            .find(|curve| e.elliptic_curves.contains(curve))
+                        {
+                            Some(curve) => *curve,
+                            None => {
+                                return Err((
+                                    Some(Alert {
+                                        alert_level: AlertLevel::Fatal,
+                                        alert_description: AlertDescription::HandshakeFailure,
+                                    }),
+                                    Some(Error::ErrNoCommonEllipticCurve),
+                                ));
+                            }
+                        };
                     }
                     Extension::UseSrtp(e) => {
                         if let Ok(profile) = find_matching_srtp_profile(
@@ -183,7 +200,7 @@ impl Flight for Flight0 {
         &self,
         state: &mut State,
         _cache: &HandshakeCache,
-        _cfg: &HandshakeConfig,
+        cfg: &HandshakeConfig,
     ) -> Result<Vec<Packet>, (Option<Alert>, Option<Error>)> {
         // Initialize
         state.cookie = vec![0; COOKIE_LENGTH];
@@ -194,7 +211,7 @@ impl Flight for Flight0 {
         state.local_epoch.store(zero_epoch, Ordering::SeqCst);
         state.remote_epoch.store(zero_epoch, Ordering::SeqCst);
 
-        state.named_curve = DEFAULT_NAMED_CURVE;
+        state.named_curve = cfg.local_elliptic_curves[0];
         state.local_random.populate();
 
         Ok(vec![])
@@ -2,9 +2,7 @@ use super::flight3::*;
 use super::*;
 use crate::compression_methods::*;
 use crate::config::*;
-use crate::conn::*;
 use crate::content::*;
-use crate::curve::named_curve::*;
 use crate::error::Error;
 use crate::extension::extension_server_name::*;
 use crate::extension::extension_supported_elliptic_curves::*;
@@ -46,6 +44,7 @@ impl Flight for Flight1 {
         let (seq, msgs) = match cache
             .full_pull_map(
                 state.handshake_recv_sequence,
+                None,
                 &[
                     HandshakeCachePullRule {
                         typ: HandshakeType::HelloVerifyRequest,
@@ -125,7 +124,7 @@ impl Flight for Flight1 {
         state.local_epoch.store(zero_epoch, Ordering::SeqCst);
         state.remote_epoch.store(zero_epoch, Ordering::SeqCst);
 
-        state.named_curve = DEFAULT_NAMED_CURVE;
+        state.named_curve = cfg.local_elliptic_curves[0];
         state.cookie = vec![];
         state.local_random.populate();
 
@@ -138,10 +137,10 @@ impl Flight for Flight1 {
             }),
         ];
 
-        if cfg.local_psk_callback.is_none() {
+        if cfg.has_ecdhe_cipher_suite() {
             extensions.extend_from_slice(&[
                 Extension::SupportedEllipticCurves(ExtensionSupportedEllipticCurves {
-                    elliptic_curves: vec![NamedCurve::P256, NamedCurve::X25519, NamedCurve::P384],
+                    elliptic_curves: cfg.local_elliptic_curves.clone(),
                 }),
                 Extension::SupportedPointFormats(ExtensionSupportedPointFormats {
                     point_formats: vec![ELLIPTIC_CURVE_POINT_FORMAT_UNCOMPRESSED],
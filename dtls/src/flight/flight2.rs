@@ -35,6 +35,7 @@ impl Flight for Flight2 {
         let (seq, msgs) = match cache
             .full_pull_map(
                 state.handshake_recv_sequence,
+                None,
                 &[HandshakeCachePullRule {
                     typ: HandshakeType::ClientHello,
                     epoch: cfg.initial_epoch,
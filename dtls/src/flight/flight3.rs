@@ -3,7 +3,6 @@ use super::*;
 use crate::compression_methods::*;
 use crate::config::*;
 use crate::content::*;
-use crate::curve::named_curve::*;
 use crate::error::Error;
 use crate::extension::extension_server_name::*;
 use crate::extension::extension_supported_elliptic_curves::*;
@@ -19,7 +18,9 @@ use crate::record_layer::record_layer_header::*;
 use crate::record_layer::*;
 
 use crate::cipher_suite::cipher_suite_for_id;
-use crate::prf::{prf_pre_master_secret, prf_psk_pre_master_secret};
+use crate::prf::{
+    prf_ecdhe_psk_pre_master_secret, prf_pre_master_secret, prf_psk_pre_master_secret,
+};
 use crate::{find_matching_cipher_suite, find_matching_srtp_profile};
 
 use crate::extension::renegotiation_info::ExtensionRenegotiationInfo;
@@ -51,6 +52,7 @@ impl Flight for Flight3 {
         if let Ok((seq, msgs)) = cache
             .full_pull_map(
                 state.handshake_recv_sequence,
+                None,
                 &[HandshakeCachePullRule {
                     typ: HandshakeType::HelloVerifyRequest,
                     epoch: cfg.initial_epoch,
@@ -98,6 +100,7 @@ impl Flight for Flight3 {
             cache
                 .full_pull_map(
                     state.handshake_recv_sequence,
+                    None,
                     &[
                         HandshakeCachePullRule {
                             typ: HandshakeType::ServerHello,
@@ -124,6 +127,7 @@ impl Flight for Flight3 {
             cache
                 .full_pull_map(
                     state.handshake_recv_sequence,
+                    None,
                     &[
                         HandshakeCachePullRule {
                             typ: HandshakeType::ServerHello,
@@ -358,10 +362,10 @@ impl Flight for Flight3 {
             }),
         ];
 
-        if cfg.local_psk_callback.is_none() {
+        if cfg.has_ecdhe_cipher_suite() {
             extensions.extend_from_slice(&[
                 Extension::SupportedEllipticCurves(ExtensionSupportedEllipticCurves {
-                    elliptic_curves: vec![NamedCurve::P256, NamedCurve::X25519, NamedCurve::P384],
+                    elliptic_curves: cfg.local_elliptic_curves.clone(),
                 }),
                 Extension::SupportedPointFormats(ExtensionSupportedPointFormats {
                     point_formats: vec![ELLIPTIC_CURVE_POINT_FORMAT_UNCOMPRESSED],
@@ -431,8 +435,70 @@ pub(crate) fn handle_server_key_exchange(
         };
 
         state.identity_hint = h.identity_hint.clone();
-        state.pre_master_secret = prf_psk_pre_master_secret(&psk);
+
+        // A ServerKeyExchange carrying an ECDH public key means ECDHE_PSK was
+        // negotiated, so the psk is mixed with an ephemeral ECDH secret.
+        //
+        // https://tools.ietf.org/html/rfc5489#section-2
+        if h.public_key.is_empty() {
+            state.pre_master_secret = prf_psk_pre_master_secret(&psk);
+        } else {
+            if !cfg.local_elliptic_curves.contains(&h.named_curve) {
+                return Err((
+                    Some(Alert {
+                        alert_level: AlertLevel::Fatal,
+                        alert_description: AlertDescription::IllegalParameter,
+                    }),
+                    Some(Error::ErrUnofferedEllipticCurve),
+                ));
+            }
+            state.named_curve = h.named_curve;
+
+            let local_keypair = match h.named_curve.generate_keypair() {
+                Ok(local_keypair) => local_keypair,
+                Err(err) => {
+                    return Err((
+                        Some(Alert {
+                            alert_level: AlertLevel::Fatal,
+                            alert_description: AlertDescription::InternalError,
+                        }),
+                        Some(err),
+                    ))
+                }
+            };
+
+            state.pre_master_secret = match prf_ecdhe_psk_pre_master_secret(
+                &psk,
+                &h.public_key,
+                &local_keypair.private_key,
+                local_keypair.curve,
+            ) {
+                Ok(pre_master_secret) => pre_master_secret,
+                Err(err) => {
+                    return Err((
+                        Some(Alert {
+                            alert_level: AlertLevel::Fatal,
+                            alert_description: AlertDescription::InternalError,
+                        }),
+                        Some(err),
+                    ))
+                }
+            };
+
+            state.local_keypair = Some(local_keypair);
+        }
     } else {
+        if !cfg.local_elliptic_curves.contains(&h.named_curve) {
+            return Err((
+                Some(Alert {
+                    alert_level: AlertLevel::Fatal,
+                    alert_description: AlertDescription::IllegalParameter,
+                }),
+                Some(Error::ErrUnofferedEllipticCurve),
+            ));
+        }
+        state.named_curve = h.named_curve;
+
         let local_keypair = match h.named_curve.generate_keypair() {
             Ok(local_keypair) => local_keypair,
             Err(err) => {
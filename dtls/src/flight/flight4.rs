@@ -49,9 +49,14 @@ impl Flight for Flight4 {
         cache: &HandshakeCache,
         cfg: &HandshakeConfig,
     ) -> Result<Box<dyn Flight + Send + Sync>, (Option<Alert>, Option<Error>)> {
+        let key_exchange_algorithm = {
+            let cipher_suite = state.cipher_suite.lock().await;
+            cipher_suite.as_ref().map(|c| c.key_exchange_algorithm())
+        };
         let (seq, msgs) = match cache
             .full_pull_map(
                 state.handshake_recv_sequence,
+                key_exchange_algorithm,
                 &[
                     HandshakeCachePullRule {
                         typ: HandshakeType::Certificate,
@@ -303,7 +308,39 @@ impl Flight for Flight4 {
                         };
 
                         state.identity_hint = client_key_exchange.identity_hint.clone();
-                        pre_master_secret = prf_psk_pre_master_secret(&psk);
+                        if cipher_suite.key_exchange_algorithm() == KeyExchangeAlgorithm::EcdhePsk {
+                            let local_keypair = match &state.local_keypair {
+                                Some(local_keypair) => local_keypair,
+                                None => {
+                                    return Err((
+                                        Some(Alert {
+                                            alert_level: AlertLevel::Fatal,
+                                            alert_description: AlertDescription::InternalError,
+                                        }),
+                                        Some(Error::ErrInvalidClientKeyExchange),
+                                    ))
+                                }
+                            };
+                            pre_master_secret = match prf_ecdhe_psk_pre_master_secret(
+                                &psk,
+                                &client_key_exchange.public_key,
+                                &local_keypair.private_key,
+                                local_keypair.curve,
+                            ) {
+                                Ok(pre_master_secret) => pre_master_secret,
+                                Err(err) => {
+                                    return Err((
+                                        Some(Alert {
+                                            alert_level: AlertLevel::Fatal,
+                                            alert_description: AlertDescription::IllegalParameter,
+                                        }),
+                                        Some(err),
+                                    ))
+                                }
+                            };
+                        } else {
+                            pre_master_secret = prf_psk_pre_master_secret(&psk);
+                        }
                     } else if let Some(local_keypair) = &state.local_keypair {
                         pre_master_secret = match prf_pre_master_secret(
                             &client_key_exchange.public_key,
@@ -410,6 +447,7 @@ impl Flight for Flight4 {
         let (seq, msgs) = match cache
             .full_pull_map(
                 seq,
+                None,
                 &[HandshakeCachePullRule {
                     typ: HandshakeType::Finished,
                     epoch: cfg.initial_epoch + 1,
@@ -500,6 +538,15 @@ impl Flight for Flight4 {
         _cache: &HandshakeCache,
         cfg: &HandshakeConfig,
     ) -> Result<Vec<Packet>, (Option<Alert>, Option<Error>)> {
+        let (cipher_suite_id, key_exchange_algorithm) = {
+            let cipher_suite = state.cipher_suite.lock().await;
+            if let Some(cipher_suite) = &*cipher_suite {
+                (cipher_suite.id(), cipher_suite.key_exchange_algorithm())
+            } else {
+                (CipherSuiteId::Unsupported, KeyExchangeAlgorithm::Ecdhe)
+            }
+        };
+
         let mut extensions = vec![Extension::RenegotiationInfo(ExtensionRenegotiationInfo {
             renegotiated_connection: 0,
         })];
@@ -518,10 +565,10 @@ impl Flight for Flight4 {
             }));
         }
 
-        if cfg.local_psk_callback.is_none() {
+        if key_exchange_algorithm.has_ecdhe() {
             extensions.extend_from_slice(&[
                 Extension::SupportedEllipticCurves(ExtensionSupportedEllipticCurves {
-                    elliptic_curves: vec![NamedCurve::P256, NamedCurve::X25519, NamedCurve::P384],
+                    elliptic_curves: cfg.local_elliptic_curves.clone(),
                 }),
                 Extension::SupportedPointFormats(ExtensionSupportedPointFormats {
                     point_formats: vec![ELLIPTIC_CURVE_POINT_FORMAT_UNCOMPRESSED],
@@ -537,14 +584,7 @@ impl Flight for Flight4 {
                     HandshakeMessageServerHello {
                         version: PROTOCOL_VERSION1_2,
                         random: state.local_random.clone(),
-                        cipher_suite: {
-                            let cipher_suite = state.cipher_suite.lock().await;
-                            if let Some(cipher_suite) = &*cipher_suite {
-                                cipher_suite.id()
-                            } else {
-                                CipherSuiteId::Unsupported
-                            }
-                        },
+                        cipher_suite: cipher_suite_id,
                         compression_method: default_compression_methods().ids[0],
                         extensions,
                     },
@@ -651,6 +691,7 @@ impl Flight for Flight4 {
                                     signature: signature_hash_algo.signature,
                                 },
                                 signature: state.local_key_signature.clone(),
+                                key_exchange_algorithm,
                             },
                         ))),
                     ),
@@ -678,6 +719,38 @@ impl Flight for Flight4 {
                     reset_local_sequence_number: false,
                 });
             }
+        } else if key_exchange_algorithm == KeyExchangeAlgorithm::EcdhePsk {
+            // With ECDHE_PSK the ServerKeyExchange is mandatory: it carries the
+            // optional PSK identity hint followed by the unsigned ECDH parameters.
+            //
+            // https://tools.ietf.org/html/rfc5489#section-2
+            if let Some(local_keypair) = &state.local_keypair {
+                pkts.push(Packet {
+                    record: RecordLayer::new(
+                        PROTOCOL_VERSION1_2,
+                        0,
+                        Content::Handshake(Handshake::new(HandshakeMessage::ServerKeyExchange(
+                            HandshakeMessageServerKeyExchange {
+                                identity_hint: cfg
+                                    .local_psk_identity_hint
+                                    .clone()
+                                    .unwrap_or_default(),
+                                elliptic_curve_type: EllipticCurveType::NamedCurve,
+                                named_curve: state.named_curve,
+                                public_key: local_keypair.public_key.clone(),
+                                algorithm: SignatureHashAlgorithm {
+                                    hash: HashAlgorithm::Unsupported,
+                                    signature: SignatureAlgorithm::Unsupported,
+                                },
+                                signature: vec![],
+                                key_exchange_algorithm,
+                            },
+                        ))),
+                    ),
+                    should_encrypt: false,
+                    reset_local_sequence_number: false,
+                });
+            }
         } else if let Some(local_psk_identity_hint) = &cfg.local_psk_identity_hint {
             // To help the client in selecting which identity to use, the server
             // can provide a "PSK identity hint" in the ServerKeyExchange message.
@@ -699,6 +772,7 @@ impl Flight for Flight4 {
                                 signature: SignatureAlgorithm::Unsupported,
                             },
                             signature: vec![],
+                            key_exchange_algorithm,
                         },
                     ))),
                 ),
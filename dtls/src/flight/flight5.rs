@@ -46,6 +46,7 @@ impl Flight for Flight5 {
         let (_seq, msgs) = match cache
             .full_pull_map(
                 state.handshake_recv_sequence,
+                None,
                 &[HandshakeCachePullRule {
                     typ: HandshakeType::Finished,
                     epoch: cfg.initial_epoch + 1,
@@ -196,6 +197,22 @@ impl Flight for Flight5 {
             None
         };
 
+        let key_exchange_algorithm = {
+            let cipher_suite = state.cipher_suite.lock().await;
+            match &*cipher_suite {
+                Some(cipher_suite) => cipher_suite.key_exchange_algorithm(),
+                None => {
+                    return Err((
+                        Some(Alert {
+                            alert_level: AlertLevel::Fatal,
+                            alert_description: AlertDescription::InternalError,
+                        }),
+                        Some(Error::ErrCipherSuiteUnset),
+                    ))
+                }
+            }
+        };
+
         let mut pkts = vec![];
 
         if state.remote_requested_certificate {
@@ -221,13 +238,20 @@ impl Flight for Flight5 {
         let mut client_key_exchange = HandshakeMessageClientKeyExchange {
             identity_hint: vec![],
             public_key: vec![],
+            key_exchange_algorithm,
         };
         if cfg.local_psk_callback.is_none() {
             if let Some(local_keypair) = &state.local_keypair {
                 client_key_exchange.public_key = local_keypair.public_key.clone();
             }
-        } else if let Some(local_psk_identity_hint) = &cfg.local_psk_identity_hint {
-            client_key_exchange.identity_hint = local_psk_identity_hint.clone();
+        } else {
+            if let Some(local_psk_identity_hint) = &cfg.local_psk_identity_hint {
+                client_key_exchange.identity_hint = local_psk_identity_hint.clone();
+            }
+            // With ECDHE_PSK the client sends its ECDH public key along with the identity
+            if let Some(local_keypair) = &state.local_keypair {
+                client_key_exchange.public_key = local_keypair.public_key.clone();
+            }
         }
 
         pkts.push(Packet {
@@ -261,6 +285,7 @@ impl Flight for Flight5 {
                 signature: SignatureAlgorithm::Unsupported,
             },
             signature: vec![],
+            key_exchange_algorithm,
         };
 
         // handshakeMessageServerKeyExchange is optional for PSK
@@ -271,7 +296,10 @@ impl Flight for Flight5 {
             }
         } else {
             let mut reader = BufReader::new(server_key_exchange_data.as_slice());
-            let raw_handshake = match Handshake::unmarshal(&mut reader) {
+            let raw_handshake = match Handshake::unmarshal_with_key_exchange_algorithm(
+                &mut reader,
+                Some(key_exchange_algorithm),
+            ) {
                 Ok(h) => h,
                 Err(err) => {
                     return Err((
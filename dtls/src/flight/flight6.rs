@@ -34,6 +34,7 @@ impl Flight for Flight6 {
         let (_, msgs) = match cache
             .full_pull_map(
                 state.handshake_recv_sequence - 1,
+                None,
                 &[HandshakeCachePullRule {
                     typ: HandshakeType::Finished,
                     epoch: cfg.initial_epoch + 1,
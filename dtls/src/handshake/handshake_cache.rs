@@ -97,9 +97,12 @@ impl HandshakeCache {
     }
 
     // full_pull_map pulls all handshakes between rules[0] to rules[len(rules)-1] as map.
+    // Key exchange messages are parsed for key_exchange_algorithm, or for the cipher suite
+    // of a ServerHello pulled before them.
     pub(crate) async fn full_pull_map(
         &self,
         start_seq: isize,
+        mut key_exchange_algorithm: Option<KeyExchangeAlgorithm>,
         rules: &[HandshakeCachePullRule],
     ) -> Result<(isize, HashMap<HandshakeType, HandshakeMessage>)> {
         let cache = self.cache.lock().await;
@@ -134,7 +137,15 @@ impl HandshakeCache {
             let t = r.typ;
             if let Some(i) = ci.get(&t) {
                 let mut reader = BufReader::new(i.data.as_slice());
-                let raw_handshake = Handshake::unmarshal(&mut reader)?;
+                let raw_handshake = Handshake::unmarshal_with_key_exchange_algorithm(
+                    &mut reader,
+                    key_exchange_algorithm,
+                )?;
+                if let HandshakeMessage::ServerHello(h) = &raw_handshake.handshake_message {
+                    if let Ok(cipher_suite) = cipher_suite_for_id(h.cipher_suite) {
+                        key_exchange_algorithm = Some(cipher_suite.key_exchange_algorithm());
+                    }
+                }
                 if seq as u16 != raw_handshake.handshake_header.message_sequence {
                     // There is a gap. Some messages are not arrived.
                     return Err(Error::Other(
@@ -2,14 +2,21 @@
 mod handshake_message_client_key_exchange_test;
 
 use super::*;
+use crate::cipher_suite::KeyExchangeAlgorithm;
 
 use byteorder::{BigEndian, WriteBytesExt};
 use std::io::{Read, Write};
 
+// Structure supports ECDH, PSK and ECDHE_PSK. An ECDHE_PSK message carries both
+// the PSK identity and the ECDH public key. The encoding can't be told apart from
+// the bytes alone, so it follows the key exchange algorithm of the cipher suite.
+//
+// https://tools.ietf.org/html/rfc5489#section-2
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct HandshakeMessageClientKeyExchange {
     pub(crate) identity_hint: Vec<u8>,
     pub(crate) public_key: Vec<u8>,
+    pub(crate) key_exchange_algorithm: KeyExchangeAlgorithm,
 }
 
 impl HandshakeMessageClientKeyExchange {
@@ -18,52 +25,76 @@ impl HandshakeMessageClientKeyExchange {
     }
 
     pub fn size(&self) -> usize {
-        if !self.public_key.is_empty() {
-            1 + self.public_key.len()
-        } else {
-            2 + self.identity_hint.len()
+        match self.key_exchange_algorithm {
+            KeyExchangeAlgorithm::Ecdhe => 1 + self.public_key.len(),
+            KeyExchangeAlgorithm::Psk => 2 + self.identity_hint.len(),
+            KeyExchangeAlgorithm::EcdhePsk => {
+                2 + self.identity_hint.len() + 1 + self.public_key.len()
+            }
         }
     }
 
     pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<()> {
-        if (!self.identity_hint.is_empty() && !self.public_key.is_empty())
-            || (self.identity_hint.is_empty() && self.public_key.is_empty())
-        {
+        if self.key_exchange_algorithm.has_ecdhe() && self.public_key.is_empty() {
             return Err(Error::ErrInvalidClientKeyExchange);
         }
 
-        if !self.public_key.is_empty() {
-            writer.write_u8(self.public_key.len() as u8)?;
-            writer.write_all(&self.public_key)?;
-        } else {
+        // The psk_identity is always present with PSK key exchanges, even when empty
+        if self.key_exchange_algorithm != KeyExchangeAlgorithm::Ecdhe {
             writer.write_u16::<BigEndian>(self.identity_hint.len() as u16)?;
             writer.write_all(&self.identity_hint)?;
         }
+        if self.key_exchange_algorithm.has_ecdhe() {
+            writer.write_u8(self.public_key.len() as u8)?;
+            writer.write_all(&self.public_key)?;
+        }
 
         Ok(writer.flush()?)
     }
 
-    pub fn unmarshal<R: Read>(reader: &mut R) -> Result<Self> {
+    pub fn unmarshal<R: Read>(
+        reader: &mut R,
+        key_exchange_algorithm: KeyExchangeAlgorithm,
+    ) -> Result<Self> {
         let mut data = vec![];
         reader.read_to_end(&mut data)?;
 
-        // If parsed as PSK return early and only populate PSK Identity Hint
-        let psk_length = ((data[0] as u16) << 8) | data[1] as u16;
-        if data.len() == psk_length as usize + 2 {
-            return Ok(HandshakeMessageClientKeyExchange {
-                identity_hint: data[2..].to_vec(),
-                public_key: vec![],
-            });
+        let mut offset = 0;
+        let mut identity_hint = vec![];
+        if key_exchange_algorithm != KeyExchangeAlgorithm::Ecdhe {
+            if data.len() < 2 {
+                return Err(Error::ErrBufferTooSmall);
+            }
+            let identity_length = ((data[0] as usize) << 8) | data[1] as usize;
+            offset = 2 + identity_length;
+            if data.len() < offset {
+                return Err(Error::ErrBufferTooSmall);
+            }
+            identity_hint = data[2..offset].to_vec();
+        }
+
+        let mut public_key = vec![];
+        if key_exchange_algorithm.has_ecdhe() {
+            if data.len() <= offset {
+                return Err(Error::ErrBufferTooSmall);
+            }
+            let public_key_length = data[offset] as usize;
+            offset += 1;
+            if data.len() < offset + public_key_length {
+                return Err(Error::ErrBufferTooSmall);
+            }
+            public_key = data[offset..offset + public_key_length].to_vec();
+            offset += public_key_length;
         }
 
-        let public_key_length = data[0] as usize;
-        if data.len() != public_key_length + 1 {
-            return Err(Error::ErrBufferTooSmall);
+        if data.len() != offset {
+            return Err(Error::ErrLengthMismatch);
         }
 
         Ok(HandshakeMessageClientKeyExchange {
-            identity_hint: vec![],
-            public_key: data[1..].to_vec(),
+            identity_hint,
+            public_key,
+            key_exchange_algorithm,
         })
     }
 }
@@ -12,10 +12,11 @@ fn test_handshake_message_client_key_exchange() -> Result<()> {
     let parsed_client_key_exchange = HandshakeMessageClientKeyExchange {
         identity_hint: vec![],
         public_key: raw_client_key_exchange[1..].to_vec(),
+        key_exchange_algorithm: KeyExchangeAlgorithm::Ecdhe,
     };
 
     let mut reader = BufReader::new(raw_client_key_exchange.as_slice());
-    let c = HandshakeMessageClientKeyExchange::unmarshal(&mut reader)?;
+    let c = HandshakeMessageClientKeyExchange::unmarshal(&mut reader, KeyExchangeAlgorithm::Ecdhe)?;
     assert_eq!(
         c, parsed_client_key_exchange,
         "parsedCertificateRequest unmarshal: got {:?}, want {:?}",
@@ -35,3 +36,86 @@ fn test_handshake_message_client_key_exchange() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_handshake_message_client_key_exchange_ecdhe_psk() -> Result<()> {
+    let raw_client_key_exchange = vec![
+        0x00, 0x04, 0x61, 0x62, 0x63, 0x64, 0x20, 0x26, 0x78, 0x4a, 0x78, 0x70, 0xc1, 0xf9, 0x71,
+        0xea, 0x50, 0x4a, 0xb5, 0xbb, 0x00, 0x76, 0x02, 0x05, 0xda, 0xf7, 0xd0, 0x3f, 0xe3, 0xf7,
+        0x4e, 0x8a, 0x14, 0x6f, 0xb7, 0xe0, 0xc0, 0xff, 0x54,
+    ];
+    let parsed_client_key_exchange = HandshakeMessageClientKeyExchange {
+        identity_hint: vec![0x61, 0x62, 0x63, 0x64],
+        public_key: raw_client_key_exchange[7..].to_vec(),
+        key_exchange_algorithm: KeyExchangeAlgorithm::EcdhePsk,
+    };
+
+    let mut reader = BufReader::new(raw_client_key_exchange.as_slice());
+    let c =
+        HandshakeMessageClientKeyExchange::unmarshal(&mut reader, KeyExchangeAlgorithm::EcdhePsk)?;
+    assert_eq!(
+        c, parsed_client_key_exchange,
+        "handshakeMessageClientKeyExchange unmarshal: got {:?}, want {:?}",
+        c, parsed_client_key_exchange
+    );
+
+    let mut raw = vec![];
+    {
+        let mut writer = BufWriter::<&mut Vec<u8>>::new(raw.as_mut());
+        c.marshal(&mut writer)?;
+    }
+    assert_eq!(
+        raw, raw_client_key_exchange,
+        "handshakeMessageClientKeyExchange marshal: got {:?}, want {:?}",
+        raw, raw_client_key_exchange
+    );
+    assert_eq!(c.size(), raw_client_key_exchange.len());
+
+    Ok(())
+}
+
+#[test]
+fn test_handshake_message_client_key_exchange_ecdhe_psk_empty_identity() -> Result<()> {
+    // The identity length is always present with ECDHE_PSK, even when empty
+    let raw_client_key_exchange = vec![
+        0x00, 0x00, 0x20, 0x26, 0x78, 0x4a, 0x78, 0x70, 0xc1, 0xf9, 0x71, 0xea, 0x50, 0x4a, 0xb5,
+        0xbb, 0x00, 0x76, 0x02, 0x05, 0xda, 0xf7, 0xd0, 0x3f, 0xe3, 0xf7, 0x4e, 0x8a, 0x14, 0x6f,
+        0xb7, 0xe0, 0xc0, 0xff, 0x54,
+    ];
+    let c = HandshakeMessageClientKeyExchange {
+        identity_hint: vec![],
+        public_key: raw_client_key_exchange[3..].to_vec(),
+        key_exchange_algorithm: KeyExchangeAlgorithm::EcdhePsk,
+    };
+
+    let mut raw = vec![];
+    {
+        let mut writer = BufWriter::<&mut Vec<u8>>::new(raw.as_mut());
+        c.marshal(&mut writer)?;
+    }
+    assert_eq!(
+        raw, raw_client_key_exchange,
+        "handshakeMessageClientKeyExchange marshal: got {:?}, want {:?}",
+        raw, raw_client_key_exchange
+    );
+    assert_eq!(c.size(), raw_client_key_exchange.len());
+
+    let mut reader = BufReader::new(raw_client_key_exchange.as_slice());
+    let parsed =
+        HandshakeMessageClientKeyExchange::unmarshal(&mut reader, KeyExchangeAlgorithm::EcdhePsk)?;
+    assert_eq!(
+        parsed, c,
+        "handshakeMessageClientKeyExchange unmarshal: got {:?}, want {:?}",
+        parsed, c
+    );
+
+    // The same bytes are not a valid plain ECDHE message
+    let mut reader = BufReader::new(raw_client_key_exchange.as_slice());
+    assert!(
+        HandshakeMessageClientKeyExchange::unmarshal(&mut reader, KeyExchangeAlgorithm::Ecdhe)
+            .is_err(),
+        "expected ECDHE parse of an ECDHE_PSK message to fail"
+    );
+
+    Ok(())
+}
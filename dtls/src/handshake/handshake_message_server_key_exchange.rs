@@ -2,6 +2,7 @@
 mod handshake_message_server_key_exchange_test;
 
 use super::*;
+use crate::cipher_suite::KeyExchangeAlgorithm;
 use crate::curve::named_curve::*;
 use crate::curve::*;
 use crate::signature_hash_algorithm::*;
@@ -9,7 +10,11 @@ use crate::signature_hash_algorithm::*;
 use byteorder::{BigEndian, WriteBytesExt};
 use std::io::{Read, Write};
 
-// Structure supports ECDH and PSK
+// Structure supports ECDH, PSK and ECDHE_PSK. An ECDHE_PSK message carries the
+// identity hint followed by the ECDH parameters without a signature. The encoding
+// follows the key exchange algorithm of the cipher suite.
+//
+// https://tools.ietf.org/html/rfc5489#section-2
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct HandshakeMessageServerKeyExchange {
     pub(crate) identity_hint: Vec<u8>,
@@ -19,6 +24,8 @@ pub struct HandshakeMessageServerKeyExchange {
     pub(crate) public_key: Vec<u8>,
     pub(crate) algorithm: SignatureHashAlgorithm,
     pub(crate) signature: Vec<u8>,
+
+    pub(crate) key_exchange_algorithm: KeyExchangeAlgorithm,
 }
 
 impl HandshakeMessageServerKeyExchange {
@@ -27,17 +34,23 @@ impl HandshakeMessageServerKeyExchange {
     }
 
     pub fn size(&self) -> usize {
-        if !self.identity_hint.is_empty() {
-            2 + self.identity_hint.len()
-        } else {
-            1 + 2 + 1 + self.public_key.len() + 2 + 2 + self.signature.len()
+        match self.key_exchange_algorithm {
+            KeyExchangeAlgorithm::Ecdhe => {
+                1 + 2 + 1 + self.public_key.len() + 2 + 2 + self.signature.len()
+            }
+            KeyExchangeAlgorithm::Psk => 2 + self.identity_hint.len(),
+            KeyExchangeAlgorithm::EcdhePsk => {
+                2 + self.identity_hint.len() + 1 + 2 + 1 + self.public_key.len()
+            }
         }
     }
 
     pub fn marshal<W: Write>(&self, writer: &mut W) -> Result<()> {
-        if !self.identity_hint.is_empty() {
+        if self.key_exchange_algorithm != KeyExchangeAlgorithm::Ecdhe {
             writer.write_u16::<BigEndian>(self.identity_hint.len() as u16)?;
             writer.write_all(&self.identity_hint)?;
+        }
+        if !self.key_exchange_algorithm.has_ecdhe() {
             return Ok(writer.flush()?);
         }
 
@@ -47,24 +60,44 @@ impl HandshakeMessageServerKeyExchange {
         writer.write_u8(self.public_key.len() as u8)?;
         writer.write_all(&self.public_key)?;
 
-        writer.write_u8(self.algorithm.hash as u8)?;
-        writer.write_u8(self.algorithm.signature as u8)?;
+        // ECDHE_PSK parameters are authenticated by the psk instead of a signature
+        if self.key_exchange_algorithm == KeyExchangeAlgorithm::Ecdhe {
+            writer.write_u8(self.algorithm.hash as u8)?;
+            writer.write_u8(self.algorithm.signature as u8)?;
 
-        writer.write_u16::<BigEndian>(self.signature.len() as u16)?;
-        writer.write_all(&self.signature)?;
+            writer.write_u16::<BigEndian>(self.signature.len() as u16)?;
+            writer.write_all(&self.signature)?;
+        }
 
         Ok(writer.flush()?)
     }
 
-    pub fn unmarshal<R: Read>(reader: &mut R) -> Result<Self> {
+    pub fn unmarshal<R: Read>(
+        reader: &mut R,
+        key_exchange_algorithm: KeyExchangeAlgorithm,
+    ) -> Result<Self> {
         let mut data = vec![];
         reader.read_to_end(&mut data)?;
 
-        // If parsed as PSK return early and only populate PSK Identity Hint
-        let psk_length = ((data[0] as u16) << 8) | data[1] as u16;
-        if data.len() == psk_length as usize + 2 {
+        let mut identity_hint = vec![];
+        if key_exchange_algorithm != KeyExchangeAlgorithm::Ecdhe {
+            if data.len() < 2 {
+                return Err(Error::ErrBufferTooSmall);
+            }
+            let hint_length = ((data[0] as usize) << 8) | data[1] as usize;
+            if data.len() < 2 + hint_length {
+                return Err(Error::ErrBufferTooSmall);
+            }
+            identity_hint = data[2..2 + hint_length].to_vec();
+            data.drain(..2 + hint_length);
+        }
+
+        if !key_exchange_algorithm.has_ecdhe() {
+            if !data.is_empty() {
+                return Err(Error::ErrLengthMismatch);
+            }
             return Ok(HandshakeMessageServerKeyExchange {
-                identity_hint: data[2..].to_vec(),
+                identity_hint,
 
                 elliptic_curve_type: EllipticCurveType::Unsupported,
                 named_curve: NamedCurve::Unsupported,
@@ -74,9 +107,14 @@ impl HandshakeMessageServerKeyExchange {
                     signature: SignatureAlgorithm::Unsupported,
                 },
                 signature: vec![],
+
+                key_exchange_algorithm,
             });
         }
 
+        if data.is_empty() {
+            return Err(Error::ErrBufferTooSmall);
+        }
         let elliptic_curve_type = data[0].into();
         if data[1..].len() < 2 {
             return Err(Error::ErrBufferTooSmall);
@@ -93,6 +131,27 @@ impl HandshakeMessageServerKeyExchange {
             return Err(Error::ErrBufferTooSmall);
         }
         let public_key = data[4..offset].to_vec();
+
+        if key_exchange_algorithm == KeyExchangeAlgorithm::EcdhePsk {
+            if data.len() != offset {
+                return Err(Error::ErrLengthMismatch);
+            }
+            return Ok(HandshakeMessageServerKeyExchange {
+                identity_hint,
+
+                elliptic_curve_type,
+                named_curve,
+                public_key,
+                algorithm: SignatureHashAlgorithm {
+                    hash: HashAlgorithm::Unsupported,
+                    signature: SignatureAlgorithm::Unsupported,
+                },
+                signature: vec![],
+
+                key_exchange_algorithm,
+            });
+        }
+
         if data.len() <= offset {
             return Err(Error::ErrBufferTooSmall);
         }
@@ -127,6 +186,8 @@ impl HandshakeMessageServerKeyExchange {
                 signature: signature_algorithm,
             },
             signature,
+
+            key_exchange_algorithm,
         })
     }
 }
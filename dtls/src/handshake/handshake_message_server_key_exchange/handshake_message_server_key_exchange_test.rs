@@ -27,10 +27,11 @@ fn test_handshake_message_server_key_exchange() -> Result<()> {
         },
 
         signature: raw_server_key_exchange[73..144].to_vec(),
+        key_exchange_algorithm: KeyExchangeAlgorithm::Ecdhe,
     };
 
     let mut reader = BufReader::new(raw_server_key_exchange.as_slice());
-    let c = HandshakeMessageServerKeyExchange::unmarshal(&mut reader)?;
+    let c = HandshakeMessageServerKeyExchange::unmarshal(&mut reader, KeyExchangeAlgorithm::Ecdhe)?;
     assert_eq!(
         c, parsed_server_key_exchange,
         "handshakeMessageServerKeyExchange unmarshal: got {:?}, want {:?}",
@@ -50,3 +51,47 @@ fn test_handshake_message_server_key_exchange() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_handshake_message_server_key_exchange_ecdhe_psk() -> Result<()> {
+    let raw_server_key_exchange = vec![
+        0x00, 0x04, 0x68, 0x69, 0x6e, 0x74, 0x03, 0x00, 0x1d, 0x20, 0x26, 0x78, 0x4a, 0x78, 0x70,
+        0xc1, 0xf9, 0x71, 0xea, 0x50, 0x4a, 0xb5, 0xbb, 0x00, 0x76, 0x02, 0x05, 0xda, 0xf7, 0xd0,
+        0x3f, 0xe3, 0xf7, 0x4e, 0x8a, 0x14, 0x6f, 0xb7, 0xe0, 0xc0, 0xff, 0x54,
+    ];
+    let parsed_server_key_exchange = HandshakeMessageServerKeyExchange {
+        identity_hint: vec![0x68, 0x69, 0x6e, 0x74],
+        elliptic_curve_type: EllipticCurveType::NamedCurve,
+        named_curve: NamedCurve::X25519,
+        public_key: raw_server_key_exchange[10..].to_vec(),
+        algorithm: SignatureHashAlgorithm {
+            hash: HashAlgorithm::Unsupported,
+            signature: SignatureAlgorithm::Unsupported,
+        },
+        signature: vec![],
+        key_exchange_algorithm: KeyExchangeAlgorithm::EcdhePsk,
+    };
+
+    let mut reader = BufReader::new(raw_server_key_exchange.as_slice());
+    let c =
+        HandshakeMessageServerKeyExchange::unmarshal(&mut reader, KeyExchangeAlgorithm::EcdhePsk)?;
+    assert_eq!(
+        c, parsed_server_key_exchange,
+        "handshakeMessageServerKeyExchange unmarshal: got {:?}, want {:?}",
+        c, parsed_server_key_exchange
+    );
+
+    let mut raw = vec![];
+    {
+        let mut writer = BufWriter::<&mut Vec<u8>>::new(raw.as_mut());
+        c.marshal(&mut writer)?;
+    }
+    assert_eq!(
+        raw, raw_server_key_exchange,
+        "handshakeMessageServerKeyExchange marshal: got {:?}, want {:?}",
+        raw, raw_server_key_exchange
+    );
+    assert_eq!(c.size(), raw_server_key_exchange.len());
+
+    Ok(())
+}
@@ -18,6 +18,7 @@ mod handshake_test;
 use std::fmt;
 use std::io::{Read, Write};
 
+use super::cipher_suite::KeyExchangeAlgorithm;
 use super::content::*;
 use super::error::*;
 
@@ -198,7 +199,18 @@ impl Handshake {
         Ok(())
     }
 
+    /// Parses a handshake message. Key exchange messages can't be parsed without knowing the
+    /// key exchange algorithm, see [`Handshake::unmarshal_with_key_exchange_algorithm`].
     pub fn unmarshal<R: Read>(reader: &mut R) -> Result<Self> {
+        Self::unmarshal_with_key_exchange_algorithm(reader, None)
+    }
+
+    /// Parses a handshake message whose key exchange messages, if any, are encoded for
+    /// key_exchange_algorithm, the one of the negotiated cipher suite.
+    pub fn unmarshal_with_key_exchange_algorithm<R: Read>(
+        reader: &mut R,
+        key_exchange_algorithm: Option<KeyExchangeAlgorithm>,
+    ) -> Result<Self> {
         let handshake_header = HandshakeHeader::unmarshal(reader)?;
 
         let handshake_message = match handshake_header.handshake_type {
@@ -214,9 +226,12 @@ impl Handshake {
             HandshakeType::Certificate => {
                 HandshakeMessage::Certificate(HandshakeMessageCertificate::unmarshal(reader)?)
             }
-            HandshakeType::ServerKeyExchange => HandshakeMessage::ServerKeyExchange(
-                HandshakeMessageServerKeyExchange::unmarshal(reader)?,
-            ),
+            HandshakeType::ServerKeyExchange => {
+                HandshakeMessage::ServerKeyExchange(HandshakeMessageServerKeyExchange::unmarshal(
+                    reader,
+                    key_exchange_algorithm.ok_or(Error::ErrKeyExchangeAlgorithmUnset)?,
+                )?)
+            }
             HandshakeType::CertificateRequest => HandshakeMessage::CertificateRequest(
                 HandshakeMessageCertificateRequest::unmarshal(reader)?,
             ),
@@ -226,9 +241,12 @@ impl Handshake {
             HandshakeType::CertificateVerify => HandshakeMessage::CertificateVerify(
                 HandshakeMessageCertificateVerify::unmarshal(reader)?,
             ),
-            HandshakeType::ClientKeyExchange => HandshakeMessage::ClientKeyExchange(
-                HandshakeMessageClientKeyExchange::unmarshal(reader)?,
-            ),
+            HandshakeType::ClientKeyExchange => {
+                HandshakeMessage::ClientKeyExchange(HandshakeMessageClientKeyExchange::unmarshal(
+                    reader,
+                    key_exchange_algorithm.ok_or(Error::ErrKeyExchangeAlgorithmUnset)?,
+                )?)
+            }
             HandshakeType::Finished => {
                 HandshakeMessage::Finished(HandshakeMessageFinished::unmarshal(reader)?)
             }
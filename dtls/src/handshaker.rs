@@ -3,6 +3,7 @@ use crate::config::*;
 use crate::conn::*;
 use crate::content::*;
 use crate::crypto::*;
+use crate::curve::named_curve::*;
 use crate::error::*;
 use crate::extension::extension_use_srtp::*;
 use crate::signature_hash_algorithm::*;
@@ -78,6 +79,7 @@ pub(crate) struct HandshakeConfig {
     pub(crate) local_psk_identity_hint: Option<Vec<u8>>,
     pub(crate) local_cipher_suites: Vec<CipherSuiteId>, // Available CipherSuites
     pub(crate) local_signature_schemes: Vec<SignatureHashAlgorithm>, // Available signature schemes
+    pub(crate) local_elliptic_curves: Vec<NamedCurve>, // Available ECDHE curves, in order of preference
     pub(crate) extended_master_secret: ExtendedMasterSecretType, // Policy for the Extended Master Support extension
     pub(crate) local_srtp_protection_profiles: Vec<SrtpProtectionProfile>, // Available SRTPProtectionProfiles, if empty no SRTP support
     pub(crate) server_name: String,
@@ -103,6 +105,7 @@ impl Default for HandshakeConfig {
             local_psk_identity_hint: None,
             local_cipher_suites: vec![],
             local_signature_schemes: vec![],
+            local_elliptic_curves: default_named_curves(),
             extended_master_secret: ExtendedMasterSecretType::Disable,
             local_srtp_protection_profiles: vec![],
             server_name: String::new(),
@@ -122,6 +125,18 @@ impl Default for HandshakeConfig {
 }
 
 impl HandshakeConfig {
+    // has_ecdhe_cipher_suite returns true if any of the local CipherSuites
+    // requires an ephemeral ECDH key exchange, in which case the elliptic
+    // curve extensions have to be advertised.
+    pub(crate) fn has_ecdhe_cipher_suite(&self) -> bool {
+        self.local_psk_callback.is_none()
+            || self.local_cipher_suites.iter().any(|id| {
+                cipher_suite_for_id(*id)
+                    .map(|c| c.key_exchange_algorithm().has_ecdhe())
+                    .unwrap_or(false)
+            })
+    }
+
     pub(crate) fn get_certificate(&self, server_name: &str) -> Result<Certificate> {
         //TODO
         /*if self.name_to_certificate.is_empty() {
@@ -18,6 +18,22 @@ use crate::curve::named_curve::*;
 use crate::error::*;
 use crate::record_layer::record_layer_header::ProtocolVersion;
 
+/// MacHash is the hash function of the HMAC protecting the records of CBC cipher suites.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MacHash {
+    Sha1,
+    Sha256,
+}
+
+impl MacHash {
+    pub(crate) fn size(&self) -> usize {
+        match *self {
+            MacHash::Sha1 => 20,
+            MacHash::Sha256 => 32,
+        }
+    }
+}
+
 pub(crate) const PRF_MASTER_SECRET_LABEL: &str = "master secret";
 pub(crate) const PRF_EXTENDED_MASTER_SECRET_LABEL: &str = "extended master secret";
 pub(crate) const PRF_KEY_EXPANSION_LABEL: &str = "key expansion";
@@ -69,6 +85,27 @@ pub(crate) fn prf_psk_pre_master_secret(psk: &[u8]) -> Vec<u8> {
     out
 }
 
+// The premaster secret of ECDHE_PSK cipher suites is formed from the shared secret of the
+// ECDH exchange (other_secret) and the PSK, both prefixed by their uint16 length.
+//
+// https://tools.ietf.org/html/rfc5489#section-2
+pub(crate) fn prf_ecdhe_psk_pre_master_secret(
+    psk: &[u8],
+    public_key: &[u8],
+    private_key: &NamedCurvePrivateKey,
+    curve: NamedCurve,
+) -> Result<Vec<u8>> {
+    let other_secret = prf_pre_master_secret(public_key, private_key, curve)?;
+
+    let mut out = Vec::with_capacity(2 + other_secret.len() + 2 + psk.len());
+    out.extend_from_slice(&(other_secret.len() as u16).to_be_bytes());
+    out.extend_from_slice(&other_secret);
+    out.extend_from_slice(&(psk.len() as u16).to_be_bytes());
+    out.extend_from_slice(psk);
+
+    Ok(out)
+}
+
 pub(crate) fn prf_pre_master_secret(
     public_key: &[u8],
     private_key: &NamedCurvePrivateKey,
@@ -297,9 +334,8 @@ pub(crate) fn prf_mac(
     protocol_version: ProtocolVersion,
     payload: &[u8],
     key: &[u8],
+    h: MacHash,
 ) -> Result<Vec<u8>> {
-    let mut hmac = HmacSha1::new_varkey(key).map_err(|e| Error::Other(e.to_string()))?;
-
     let mut msg = vec![0u8; 13];
     msg[..2].copy_from_slice(&epoch.to_be_bytes());
     msg[2..8].copy_from_slice(&sequence_number.to_be_bytes()[2..]);
@@ -308,9 +344,18 @@ pub(crate) fn prf_mac(
     msg[10] = protocol_version.minor;
     msg[11..].copy_from_slice(&(payload.len() as u16).to_be_bytes());
 
-    hmac.update(&msg);
-    hmac.update(payload);
-    let result = hmac.finalize();
-
-    Ok(result.into_bytes().to_vec())
+    match h {
+        MacHash::Sha1 => {
+            let mut hmac = HmacSha1::new_varkey(key).map_err(|e| Error::Other(e.to_string()))?;
+            hmac.update(&msg);
+            hmac.update(payload);
+            Ok(hmac.finalize().into_bytes().to_vec())
+        }
+        MacHash::Sha256 => {
+            let mut hmac = HmacSha256::new_varkey(key).map_err(|e| Error::Other(e.to_string()))?;
+            hmac.update(&msg);
+            hmac.update(payload);
+            Ok(hmac.finalize().into_bytes().to_vec())
+        }
+    }
 }
@@ -33,6 +33,40 @@ fn test_pre_master_secret() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_ecdhe_psk_pre_master_secret() -> Result<()> {
+    let private_key: [u8; 32] = [
+        0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x2b, 0x2c, 0x2d, 0x2e,
+        0x2f, 0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x3b, 0x3c, 0x3d,
+        0x3e, 0x3f,
+    ];
+    let private_key =
+        NamedCurvePrivateKey::StaticSecretX25519(x25519_dalek::StaticSecret::from(private_key));
+    let public_key = [
+        0x9f, 0xd7, 0xad, 0x6d, 0xcf, 0xf4, 0x29, 0x8d, 0xd3, 0xf9, 0x6d, 0x5b, 0x1b, 0x2a, 0xf9,
+        0x10, 0xa0, 0x53, 0x5b, 0x14, 0x88, 0xd7, 0xf8, 0xfa, 0xbb, 0x34, 0x9a, 0x98, 0x28, 0x80,
+        0xb6, 0x15,
+    ];
+    let psk = [0xAB, 0xC1, 0x23];
+
+    let expected_pre_master_secret = vec![
+        0x00, 0x20, 0xdf, 0x4a, 0x29, 0x1b, 0xaa, 0x1e, 0xb7, 0xcf, 0xa6, 0x93, 0x4b, 0x29, 0xb4,
+        0x74, 0xba, 0xad, 0x26, 0x97, 0xe2, 0x9f, 0x1f, 0x92, 0x0d, 0xcc, 0x77, 0xc8, 0xa0, 0xa0,
+        0x88, 0x44, 0x76, 0x24, 0x00, 0x03, 0xAB, 0xC1, 0x23,
+    ];
+
+    let pre_master_secret =
+        prf_ecdhe_psk_pre_master_secret(&psk, &public_key, &private_key, NamedCurve::X25519)?;
+
+    assert_eq!(
+        expected_pre_master_secret, pre_master_secret,
+        "PremasterSecret exp: {:?} actual: {:?}",
+        expected_pre_master_secret, pre_master_secret
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_master_secret() -> Result<()> {
     let pre_master_secret = vec![
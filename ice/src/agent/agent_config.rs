@@ -2,6 +2,7 @@ use super::*;
 use crate::error::*;
 use crate::mdns::*;
 use crate::network_type::*;
+use crate::resolver::Resolver;
 use crate::udp_network::UDPNetwork;
 use crate::url::*;
 
@@ -16,6 +17,12 @@ pub(crate) const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_millis(200);
 /// The interval used to keep candidates alive.
 pub(crate) const DEFAULT_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(2);
 
+/// The interval used to keep valid but unselected candidate pairs alive.
+pub(crate) const DEFAULT_BACKUP_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Max backup keepalives in a row that may fail before the pair is pruned.
+pub(crate) const DEFAULT_MAX_BACKUP_KEEPALIVE_FAILURES: u16 = 3;
+
 /// The default time till an Agent transitions disconnected.
 pub(crate) const DEFAULT_DISCONNECTED_TIMEOUT: Duration = Duration::from_secs(5);
 
@@ -43,6 +50,9 @@ pub(crate) const MAX_BUFFER_SIZE: usize = 1000 * 1000; // 1MB
 /// Wait time before binding requests can be deleted.
 pub(crate) const MAX_BINDING_REQUEST_TIMEOUT: Duration = Duration::from_millis(4000);
 
+/// Max time closing an agent waits for its tasks to stop before they are cancelled.
+pub(crate) const TASKS_CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
 pub(crate) fn default_candidate_types() -> Vec<CandidateType> {
     vec![
         CandidateType::Host,
@@ -53,6 +63,7 @@ pub(crate) fn default_candidate_types() -> Vec<CandidateType> {
 
 pub type InterfaceFilterFn = Box<dyn (Fn(&str) -> bool) + Send + Sync>;
 pub type IpFilterFn = Box<dyn (Fn(IpAddr) -> bool) + Send + Sync>;
+pub type NetworkCostFn = Box<dyn (Fn(&str, IpAddr) -> u16) + Send + Sync>;
 
 /// Collects the arguments to `ice::Agent` construction into a single structure, for
 /// future-proofness of the interface.
@@ -95,17 +106,61 @@ pub struct AgentConfig {
     /// A keepalive interval of 0 means we never send keepalive packets
     pub keepalive_interval: Option<Duration>,
 
+    /// Determines how often valid candidate pairs other than the selected one are kept alive, so
+    /// that their NAT bindings are still open if the agent has to fail over to them. When this is
+    /// nil, it defaults to 10 seconds. An interval of 0 disables backup keepalives.
+    pub backup_keepalive_interval: Option<Duration>,
+
+    /// Sends STUN Binding requests instead of Binding indications as backup keepalives. Requests
+    /// cost a response from the remote agent but let unanswered pairs be detected and pruned.
+    pub backup_keepalive_requests: bool,
+
+    /// The number of backup keepalives in a row that may fail (or, with
+    /// backup_keepalive_requests, go unanswered) before the pair is marked as failed. Defaults
+    /// to 3 when this is nil.
+    pub max_backup_keepalive_failures: Option<u16>,
+
     /// An optional configuration for disabling or enabling support for specific network types.
     pub network_types: Vec<NetworkType>,
 
+    /// Gathers active TCP host candidates, which connect to the passive TCP candidates of the
+    /// remote agent, in addition to passive ones. Only used if network_types has a TCP type.
+    pub tcp_active_candidates: bool,
+
+    /// Allows gathering relay candidates from `turn:` URLs with `transport=tcp`, the agent then
+    /// reaches the TURN server over TCP. Relayed traffic between the server and the remote agent
+    /// still uses UDP.
+    pub turn_over_tcp: bool,
+
+    /// Looks up the host names of the STUN and TURN servers of urls. Defaults to the resolver
+    /// of the system, or the one of the virtual network, when this is nil.
+    pub resolver: Option<Arc<dyn Resolver + Send + Sync>>,
+
+    /// How long the lookup of the host name of a STUN or TURN server may take before the server
+    /// is given up, without delaying the candidates of other servers. Defaults to 5 seconds when
+    /// this is nil.
+    pub dns_timeout: Option<Duration>,
+
     /// An optional configuration for disabling or enabling support for specific candidate types.
     pub candidate_types: Vec<CandidateType>,
 
+    /// An optional allow list of remote candidate types. Remote candidates of any other type,
+    /// including peer-reflexive candidates learned from inbound checks, are ignored and never
+    /// paired. An empty list accepts every type. Peer-reflexive candidates learned from checks
+    /// arriving on a local relay candidate are always kept, but only paired with relay candidates.
+    pub remote_candidate_types: Vec<CandidateType>,
+
     //LoggerFactory logging.LoggerFactory
     /// Controls how often our internal task loop runs when in the connecting state.
     /// Only useful for testing.
     pub check_interval: Duration,
 
+    /// The minimum time Ta between two connectivity checks or gathering transactions of the agent
+    /// (RFC 8445 Section 14). Defaults to 50 ms when this is nil, an interval of 0 disables
+    /// pacing. Agents sharing a UDP mux are additionally paced by the mux, see
+    /// `UDPMuxParams::with_pacing_interval`.
+    pub pacing_interval: Option<Duration>,
+
     /// The max amount of binding requests the agent will send over a candidate pair for validation
     /// or nomination, if after max_binding_requests the candidate is yet to answer a binding
     /// request or a nomination we set the pair as failed.
@@ -116,6 +171,22 @@ pub struct AgentConfig {
     /// lite agents do not perform connectivity check and only provide host candidates.
     pub lite: bool,
 
+    /// Allows the controlling agent to nominate a better candidate pair after a pair was
+    /// selected. It is only used if the remote agent supports renomination as well, see
+    /// `Agent::set_remote_options`.
+    pub renomination: bool,
+
+    /// How long a valid pair has to stay better than the selected pair before it is
+    /// renominated, so that a path recovering only briefly doesn't make the selected pair flap.
+    /// Defaults to 0 when this is nil.
+    pub renomination_hold_time: Option<Duration>,
+
+    /// Prefers candidate pairs with a lower network cost, see `Candidate::network_cost`, over
+    /// pairs of the same priority when nominating and renominating. With renomination, the
+    /// controlling agent keeps checking pairs after selection so that a cheaper path can be
+    /// renominated once it validates.
+    pub prefer_low_cost: bool,
+
     /// It is used along with nat1to1ips to specify which candidate type the 1:1 NAT IP addresses
     /// should be mapped to. If unspecified or CandidateTypeHost, nat1to1ips are used to replace
     /// host candidate IPs. If CandidateTypeServerReflexive, it will insert a srflx candidate (as
@@ -149,6 +220,11 @@ pub struct AgentConfig {
     /// the ips which are used to gather ICE candidates.
     pub ip_filter: Arc<Option<IpFilterFn>>,
 
+    /// A function returning the network cost of the host candidates gathered on an interface,
+    /// given its name and the IP of the candidate. It's signaled to the remote agent as the
+    /// `network-cost` candidate extension. Candidates gathered with a UDP mux have no cost.
+    pub network_cost: Arc<Option<NetworkCostFn>>,
+
     /// Controls if self-signed certificates are accepted when connecting to TURN servers via TLS or
     /// DTLS.
     pub insecure_skip_verify: bool,
@@ -205,6 +281,20 @@ impl AgentConfig {
             a.keepalive_interval = DEFAULT_KEEPALIVE_INTERVAL;
         }
 
+        if let Some(backup_keepalive_interval) = self.backup_keepalive_interval {
+            a.backup_keepalive_interval = backup_keepalive_interval;
+        } else {
+            a.backup_keepalive_interval = DEFAULT_BACKUP_KEEPALIVE_INTERVAL;
+        }
+
+        if let Some(max_backup_keepalive_failures) = self.max_backup_keepalive_failures {
+            a.max_backup_keepalive_failures = max_backup_keepalive_failures;
+        } else {
+            a.max_backup_keepalive_failures = DEFAULT_MAX_BACKUP_KEEPALIVE_FAILURES;
+        }
+
+        a.pacer = Pacer::new(self.pacing_interval.unwrap_or(DEFAULT_PACING_INTERVAL));
+
         if self.check_interval == Duration::from_secs(0) {
             a.check_interval = DEFAULT_CHECK_INTERVAL;
         } else {
@@ -1,6 +1,8 @@
 use super::*;
 use crate::error::*;
 use crate::network_type::*;
+use crate::resolver::*;
+use crate::tcp_packet_conn::{TcpFraming, TcpPacketConn};
 use crate::udp_network::UDPNetwork;
 use crate::url::{ProtoType, SchemeType, Url};
 use crate::util::*;
@@ -12,18 +14,39 @@ use crate::candidate::candidate_host::CandidateHostConfig;
 use crate::candidate::candidate_relay::CandidateRelayConfig;
 use crate::candidate::candidate_server_reflexive::CandidateServerReflexiveConfig;
 use crate::candidate::*;
-use std::net::{Ipv4Addr, Ipv6Addr};
-use std::str::FromStr;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::sync::Arc;
 use waitgroup::WaitGroup;
 
 const STUN_GATHER_TIMEOUT: Duration = Duration::from_secs(5);
 
+/// The error code of a `CandidateError` for a server that couldn't be resolved or reached, the
+/// STUN error codes are below 700.
+pub const CANDIDATE_ERROR_CODE_UNREACHABLE: u16 = 701;
+
+/// Describes why no candidate could be gathered from a STUN or TURN server, like the
+/// RTCPeerConnectionIceErrorEvent of the WebRTC API.
+#[derive(Debug, Clone)]
+pub struct CandidateError {
+    /// The URL of the server.
+    pub url: Url,
+    /// `CANDIDATE_ERROR_CODE_UNREACHABLE` if the host name of the server couldn't be resolved,
+    /// or none of its addresses could be used.
+    pub error_code: u16,
+    /// Describes the error.
+    pub error_text: String,
+}
+
 pub(crate) struct GatherCandidatesInternalParams {
     pub(crate) udp_network: UDPNetwork,
     pub(crate) candidate_types: Vec<CandidateType>,
     pub(crate) urls: Vec<Url>,
     pub(crate) network_types: Vec<NetworkType>,
+    pub(crate) tcp_active_candidates: bool,
+    pub(crate) turn_over_tcp: bool,
+    pub(crate) resolver: Arc<dyn Resolver + Send + Sync>,
+    pub(crate) dns_timeout: Duration,
     pub(crate) mdns_mode: MulticastDnsMode,
     pub(crate) mdns_name: String,
     pub(crate) net: Arc<Net>,
@@ -47,6 +70,18 @@ struct GatherCandidatesLocalParams {
     agent_internal: Arc<AgentInternal>,
 }
 
+struct GatherCandidatesLocalTcpParams {
+    network_types: Vec<NetworkType>,
+    tcp_active_candidates: bool,
+    mdns_mode: MulticastDnsMode,
+    mdns_name: String,
+    interface_filter: Arc<Option<InterfaceFilterFn>>,
+    ip_filter: Arc<Option<IpFilterFn>>,
+    ext_ip_mapper: Arc<Option<ExternalIpMapper>>,
+    net: Arc<Net>,
+    agent_internal: Arc<AgentInternal>,
+}
+
 struct GatherCandidatesLocalUDPMuxParams {
     network_types: Vec<NetworkType>,
     interface_filter: Arc<Option<InterfaceFilterFn>>,
@@ -71,10 +106,22 @@ struct GatherCandidatesSrflxParams {
     network_types: Vec<NetworkType>,
     port_max: u16,
     port_min: u16,
+    resolver: Arc<dyn Resolver + Send + Sync>,
+    dns_timeout: Duration,
     net: Arc<Net>,
     agent_internal: Arc<AgentInternal>,
 }
 
+pub(crate) struct GatherCandidatesRelayParams {
+    pub(crate) urls: Vec<Url>,
+    pub(crate) network_types: Vec<NetworkType>,
+    pub(crate) turn_over_tcp: bool,
+    pub(crate) resolver: Arc<dyn Resolver + Send + Sync>,
+    pub(crate) dns_timeout: Duration,
+    pub(crate) net: Arc<Net>,
+    pub(crate) agent_internal: Arc<AgentInternal>,
+}
+
 impl Agent {
     pub(crate) async fn gather_candidates_internal(params: GatherCandidatesInternalParams) {
         Self::set_gathering_state(
@@ -102,11 +149,32 @@ impl Agent {
                     };
 
                     let w = wg.worker();
-                    tokio::spawn(async move {
+                    params.agent_internal.tasks.spawn(async move {
                         let _d = w;
 
                         Self::gather_candidates_local(local_params).await;
                     });
+
+                    if params.network_types.iter().any(|n| n.is_tcp()) {
+                        let tcp_params = GatherCandidatesLocalTcpParams {
+                            network_types: params.network_types.clone(),
+                            tcp_active_candidates: params.tcp_active_candidates,
+                            mdns_mode: params.mdns_mode,
+                            mdns_name: params.mdns_name.clone(),
+                            interface_filter: Arc::clone(&params.interface_filter),
+                            ip_filter: Arc::clone(&params.ip_filter),
+                            ext_ip_mapper: Arc::clone(&params.ext_ip_mapper),
+                            net: Arc::clone(&params.net),
+                            agent_internal: Arc::clone(&params.agent_internal),
+                        };
+
+                        let w = wg.worker();
+                        params.agent_internal.tasks.spawn(async move {
+                            let _d = w;
+
+                            Self::gather_candidates_local_tcp(tcp_params).await;
+                        });
+                    }
                 }
                 CandidateType::ServerReflexive => {
                     let ephemeral_config = match &params.udp_network {
@@ -120,11 +188,13 @@ impl Agent {
                         network_types: params.network_types.clone(),
                         port_max: ephemeral_config.port_max(),
                         port_min: ephemeral_config.port_min(),
+                        resolver: Arc::clone(&params.resolver),
+                        dns_timeout: params.dns_timeout,
                         net: Arc::clone(&params.net),
                         agent_internal: Arc::clone(&params.agent_internal),
                     };
                     let w1 = wg.worker();
-                    tokio::spawn(async move {
+                    params.agent_internal.tasks.spawn(async move {
                         let _d = w1;
 
                         Self::gather_candidates_srflx(srflx_params).await;
@@ -140,7 +210,7 @@ impl Agent {
                                 agent_internal: Arc::clone(&params.agent_internal),
                             };
                             let w2 = wg.worker();
-                            tokio::spawn(async move {
+                            params.agent_internal.tasks.spawn(async move {
                                 let _d = w2;
 
                                 Self::gather_candidates_srflx_mapped(srflx_mapped_params).await;
@@ -149,14 +219,20 @@ impl Agent {
                     }
                 }
                 CandidateType::Relay => {
-                    let urls = params.urls.clone();
-                    let net = Arc::clone(&params.net);
-                    let agent_internal = Arc::clone(&params.agent_internal);
+                    let relay_params = GatherCandidatesRelayParams {
+                        urls: params.urls.clone(),
+                        network_types: params.network_types.clone(),
+                        turn_over_tcp: params.turn_over_tcp,
+                        resolver: Arc::clone(&params.resolver),
+                        dns_timeout: params.dns_timeout,
+                        net: Arc::clone(&params.net),
+                        agent_internal: Arc::clone(&params.agent_internal),
+                    };
                     let w = wg.worker();
-                    tokio::spawn(async move {
+                    params.agent_internal.tasks.spawn(async move {
                         let _d = w;
 
-                        Self::gather_candidates_relay(urls, net, agent_internal).await;
+                        Self::gather_candidates_relay(relay_params).await;
                     });
                 }
                 _ => {}
@@ -225,53 +301,27 @@ impl Agent {
             return;
         }
 
-        let ips = local_interfaces(&net, &interface_filter, &ip_filter, &network_types).await;
-        for ip in ips {
-            let mut mapped_ip = ip;
-
-            if mdns_mode != MulticastDnsMode::QueryAndGather && ext_ip_mapper.is_some() {
-                if let Some(ext_ip_mapper2) = ext_ip_mapper.as_ref() {
-                    if ext_ip_mapper2.candidate_type == CandidateType::Host {
-                        if let Ok(mi) = ext_ip_mapper2.find_external_ip(&ip.to_string()) {
-                            mapped_ip = mi;
-                        } else {
-                            log::warn!(
-                                "[{}]: 1:1 NAT mapping is enabled but no external IP is found for {}",
-                                agent_internal.get_name(),
-                                ip
-                            );
-                        }
-                    }
-                }
-            }
+        // Filter out non UDP network types, TCP candidates are gathered on their own
+        let udp_network_types: Vec<_> = network_types.into_iter().filter(|n| n.is_udp()).collect();
 
-            let address = if mdns_mode == MulticastDnsMode::QueryAndGather {
-                mdns_name.clone()
-            } else {
-                mapped_ip.to_string()
-            };
+        let addrs =
+            local_interface_addrs(&net, &interface_filter, &ip_filter, &udp_network_types).await;
+        for InterfaceAddr {
+            ip,
+            interface,
+            network_id,
+        } in addrs
+        {
+            let (mapped_ip, address) = Self::host_candidate_address(
+                ip,
+                mdns_mode,
+                &mdns_name,
+                &ext_ip_mapper,
+                &agent_internal,
+            );
 
-            //TODO: for network in networks
             let network = UDP.to_owned();
             if let UDPNetwork::Ephemeral(ephemeral_config) = &udp_network {
-                /*TODO:switch network {
-                case tcp:
-                    // Handle ICE TCP passive mode
-
-                    a.log.Debugf("GetConn by ufrag: %s\n", a.localUfrag)
-                    conn, err = a.tcpMux.GetConnByUfrag(a.localUfrag)
-                    if err != nil {
-                        if !errors.Is(err, ErrTCPMuxNotInitialized) {
-                            a.log.Warnf("error getting tcp conn by ufrag: %s %s %s\n", network, ip, a.localUfrag)
-                        }
-                        continue
-                    }
-                    port = conn.LocalAddr().(*net.TCPAddr).Port
-                    tcpType = TCPTypePassive
-                    // is there a way to verify that the listen address is even
-                    // accessible from the current interface.
-                case udp:*/
-
                 let conn: Arc<dyn Conn + Send + Sync> = match listen_udp_in_port_range(
                     &net,
                     ephemeral_config.port_max(),
@@ -307,64 +357,212 @@ impl Agent {
 
                 let host_config = CandidateHostConfig {
                     base_config: CandidateBaseConfig {
-                        network: network.clone(),
+                        network,
                         address,
                         port,
                         component: COMPONENT_RTP,
+                        network_id,
+                        network_cost: agent_internal.host_network_cost(&interface, ip),
                         conn: Some(conn),
                         ..CandidateBaseConfig::default()
                     },
                     ..CandidateHostConfig::default()
                 };
 
-                let candidate: Arc<dyn Candidate + Send + Sync> =
-                    match host_config.new_candidate_host() {
-                        Ok(candidate) => {
-                            if mdns_mode == MulticastDnsMode::QueryAndGather {
-                                if let Err(err) = candidate.set_ip(&ip) {
-                                    log::warn!(
-                                        "[{}]: Failed to create host candidate: {} {} {}: {:?}",
-                                        agent_internal.get_name(),
-                                        network,
-                                        mapped_ip,
-                                        port,
-                                        err
-                                    );
-                                    continue;
-                                }
-                            }
-                            Arc::new(candidate)
-                        }
-                        Err(err) => {
-                            log::warn!(
-                                "[{}]: Failed to create host candidate: {} {} {}: {}",
-                                agent_internal.get_name(),
-                                network,
-                                mapped_ip,
-                                port,
-                                err
-                            );
-                            continue;
-                        }
-                    };
+                Self::add_host_candidate(host_config, ip, mapped_ip, mdns_mode, &agent_internal)
+                    .await;
+            }
+        }
+    }
 
-                {
-                    if let Err(err) = agent_internal.add_candidate(&candidate).await {
-                        if let Err(close_err) = candidate.close().await {
-                            log::warn!(
-                                "[{}]: Failed to close candidate: {}",
-                                agent_internal.get_name(),
-                                close_err
-                            );
-                        }
+    /// Gathers a passive TCP host candidate per local IP, and an active one as well if
+    /// tcp_active_candidates is set, see RFC 6544. Packets are framed as in RFC 4571.
+    async fn gather_candidates_local_tcp(params: GatherCandidatesLocalTcpParams) {
+        let GatherCandidatesLocalTcpParams {
+            network_types,
+            tcp_active_candidates,
+            mdns_mode,
+            mdns_name,
+            interface_filter,
+            ip_filter,
+            ext_ip_mapper,
+            net,
+            agent_internal,
+        } = params;
+
+        if net.is_virtual() {
+            log::warn!(
+                "[{}]: vnet does not support TCP, no TCP candidates gathered",
+                agent_internal.get_name()
+            );
+            return;
+        }
+
+        let tcp_network_types: Vec<_> = network_types.into_iter().filter(|n| n.is_tcp()).collect();
+
+        let addrs =
+            local_interface_addrs(&net, &interface_filter, &ip_filter, &tcp_network_types).await;
+        for InterfaceAddr {
+            ip,
+            interface,
+            network_id,
+        } in addrs
+        {
+            let (mapped_ip, address) = Self::host_candidate_address(
+                ip,
+                mdns_mode,
+                &mdns_name,
+                &ext_ip_mapper,
+                &agent_internal,
+            );
+
+            let passive =
+                match TcpPacketConn::listen(SocketAddr::new(ip, 0), TcpFraming::Rfc4571).await {
+                    Ok(conn) => conn,
+                    Err(err) => {
                         log::warn!(
-                            "[{}]: Failed to append to localCandidates and run onCandidateHdlr: {}",
+                            "[{}]: could not listen {} {}: {}",
+                            agent_internal.get_name(),
+                            TCP,
+                            ip,
+                            err
+                        );
+                        continue;
+                    }
+                };
+            // The local address of a listening conn is always known
+            let port = passive.local_addr().map_or(0, |addr| addr.port());
+
+            let mut conns: Vec<(Arc<dyn Conn + Send + Sync>, u16, TcpType)> =
+                vec![(Arc::new(passive), port, TcpType::Passive)];
+            if tcp_active_candidates {
+                // Active candidates use the discard port, see RFC 6544 Section 4.5
+                conns.push((
+                    Arc::new(TcpPacketConn::dialer(ip, TcpFraming::Rfc4571)),
+                    9,
+                    TcpType::Active,
+                ));
+            }
+
+            for (conn, port, tcp_type) in conns {
+                let host_config = CandidateHostConfig {
+                    base_config: CandidateBaseConfig {
+                        network: TCP.to_owned(),
+                        address: address.clone(),
+                        port,
+                        component: COMPONENT_RTP,
+                        network_id,
+                        network_cost: agent_internal.host_network_cost(&interface, ip),
+                        conn: Some(conn),
+                        ..CandidateBaseConfig::default()
+                    },
+                    tcp_type,
+                };
+
+                Self::add_host_candidate(host_config, ip, mapped_ip, mdns_mode, &agent_internal)
+                    .await;
+            }
+        }
+    }
+
+    /// host_candidate_address returns the IP a host candidate on ip is reachable at, with 1:1
+    /// NAT mapping, and the address it's announced with, which is hidden with mDNS.
+    fn host_candidate_address(
+        ip: IpAddr,
+        mdns_mode: MulticastDnsMode,
+        mdns_name: &str,
+        ext_ip_mapper: &Option<ExternalIpMapper>,
+        agent_internal: &Arc<AgentInternal>,
+    ) -> (IpAddr, String) {
+        let mut mapped_ip = ip;
+
+        if mdns_mode != MulticastDnsMode::QueryAndGather {
+            if let Some(ext_ip_mapper2) = ext_ip_mapper {
+                if ext_ip_mapper2.candidate_type == CandidateType::Host {
+                    if let Ok(mi) = ext_ip_mapper2.find_external_ip(&ip.to_string()) {
+                        mapped_ip = mi;
+                    } else {
+                        log::warn!(
+                            "[{}]: 1:1 NAT mapping is enabled but no external IP is found for {}",
                             agent_internal.get_name(),
+                            ip
+                        );
+                    }
+                }
+            }
+        }
+
+        let address = if mdns_mode == MulticastDnsMode::QueryAndGather {
+            mdns_name.to_owned()
+        } else {
+            mapped_ip.to_string()
+        };
+
+        (mapped_ip, address)
+    }
+
+    /// add_host_candidate creates the host candidate on ip and adds it to the local
+    /// candidates, or closes its conn if that fails.
+    async fn add_host_candidate(
+        host_config: CandidateHostConfig,
+        ip: IpAddr,
+        mapped_ip: IpAddr,
+        mdns_mode: MulticastDnsMode,
+        agent_internal: &Arc<AgentInternal>,
+    ) {
+        let network = host_config.base_config.network.clone();
+        let port = host_config.base_config.port;
+        let conn = host_config.base_config.conn.clone();
+
+        let candidate: Arc<dyn Candidate + Send + Sync> = match host_config.new_candidate_host() {
+            Ok(candidate) => {
+                if mdns_mode == MulticastDnsMode::QueryAndGather {
+                    if let Err(err) = candidate.set_ip(&ip) {
+                        log::warn!(
+                            "[{}]: Failed to create host candidate: {} {} {}: {:?}",
+                            agent_internal.get_name(),
+                            network,
+                            mapped_ip,
+                            port,
                             err
                         );
+                        if let Some(conn) = conn {
+                            let _ = conn.close().await;
+                        }
+                        return;
                     }
                 }
+                Arc::new(candidate)
             }
+            Err(err) => {
+                log::warn!(
+                    "[{}]: Failed to create host candidate: {} {} {}: {}",
+                    agent_internal.get_name(),
+                    network,
+                    mapped_ip,
+                    port,
+                    err
+                );
+                if let Some(conn) = conn {
+                    let _ = conn.close().await;
+                }
+                return;
+            }
+        };
+
+        if let Err(err) = agent_internal.add_candidate(&candidate).await {
+            if let Err(close_err) = candidate.close().await {
+                log::warn!(
+                    "[{}]: Failed to close candidate: {}",
+                    agent_internal.get_name(),
+                    close_err
+                );
+            }
+            log::warn!(
+                "[{}]: Failed to append to localCandidates and run onCandidateHdlr: {}",
+                agent_internal.get_name(),
+                err
+            );
         }
     }
 
@@ -472,7 +670,7 @@ impl Agent {
             let ext_ip_mapper2 = Arc::clone(&ext_ip_mapper);
 
             let w = wg.worker();
-            tokio::spawn(async move {
+            agent_internal.tasks.spawn(async move {
                 let _d = w;
 
                 let conn: Arc<dyn Conn + Send + Sync> = match listen_udp_in_port_range(
@@ -523,12 +721,24 @@ impl Agent {
                     }
                 };
 
+                // The base is the local address mapped to mapped_ip, if only one is
+                let (network_id, network_cost) =
+                    Self::base_network(&net2, &agent_internal2, |ip| {
+                        ip.is_ipv4() == mapped_ip.is_ipv4()
+                            && (*ext_ip_mapper2).as_ref().map_or(false, |mapper| {
+                                mapper.find_external_ip(&ip.to_string()).ok() == Some(mapped_ip)
+                            })
+                    })
+                    .await;
+
                 let srflx_config = CandidateServerReflexiveConfig {
                     base_config: CandidateBaseConfig {
                         network: network.clone(),
                         address: mapped_ip.to_string(),
                         port: laddr.port(),
                         component: COMPONENT_RTP,
+                        network_id,
+                        network_cost,
                         conn: Some(conn),
                         ..CandidateBaseConfig::default()
                     },
@@ -582,142 +792,207 @@ impl Agent {
             network_types,
             port_max,
             port_min,
+            resolver,
+            dns_timeout,
             net,
             agent_internal,
         } = params;
 
-        let wg = WaitGroup::new();
-        for network_type in network_types {
-            if network_type.is_tcp() {
-                continue;
-            }
-
-            for url in &urls {
-                let network = network_type.to_string();
-                let is_ipv4 = network_type.is_ipv4();
-                let url = url.clone();
-                let net2 = Arc::clone(&net);
-                let agent_internal2 = Arc::clone(&agent_internal);
+        let network_types: Vec<NetworkType> =
+            network_types.into_iter().filter(|n| !n.is_tcp()).collect();
+        if network_types.is_empty() {
+            return;
+        }
 
-                let w = wg.worker();
-                tokio::spawn(async move {
-                    let _d = w;
+        let wg = WaitGroup::new();
+        for url in urls {
+            let network_types = network_types.clone();
+            let resolver = Arc::clone(&resolver);
+            let net2 = Arc::clone(&net);
+            let agent_internal2 = Arc::clone(&agent_internal);
 
-                    let host_port = format!("{}:{}", url.host, url.port);
-                    let server_addr = match net2.resolve_addr(is_ipv4, &host_port).await {
-                        Ok(addr) => addr,
-                        Err(err) => {
-                            log::warn!(
-                                "[{}]: failed to resolve stun host: {}: {}",
-                                agent_internal2.get_name(),
-                                host_port,
-                                err
-                            );
-                            return Ok(());
-                        }
-                    };
+            let w = wg.worker();
+            agent_internal.tasks.spawn(async move {
+                let _d = w;
 
-                    let conn: Arc<dyn Conn + Send + Sync> = match listen_udp_in_port_range(
-                        &net2,
-                        port_max,
-                        port_min,
-                        if is_ipv4 {
-                            SocketAddr::new(Ipv4Addr::new(0, 0, 0, 0).into(), 0)
-                        } else {
-                            SocketAddr::new(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0).into(), 0)
-                        },
-                    )
-                    .await
-                    {
-                        Ok(conn) => conn,
-                        Err(err) => {
-                            log::warn!(
-                                "[{}]: Failed to listen for {}: {}",
-                                agent_internal2.get_name(),
-                                server_addr,
-                                err
-                            );
-                            return Ok(());
-                        }
-                    };
+                // Each server is resolved once for all network types, and on its own so that a
+                // slow lookup doesn't hold back the other servers.
+                let server_addrs = match resolve_server(
+                    &resolver,
+                    dns_timeout,
+                    &url.host,
+                    url.port,
+                    &network_types,
+                )
+                .await
+                {
+                    Ok(server_addrs) => server_addrs,
+                    Err(err) => {
+                        log::warn!(
+                            "[{}]: failed to resolve stun host: {}: {}",
+                            agent_internal2.get_name(),
+                            url,
+                            err
+                        );
+                        agent_internal2.candidate_error(&url, &err).await;
+                        return;
+                    }
+                };
 
-                    let xoraddr =
-                        match get_xormapped_addr(&conn, server_addr, STUN_GATHER_TIMEOUT).await {
-                            Ok(xoraddr) => xoraddr,
-                            Err(err) => {
-                                log::warn!(
-                                    "[{}]: could not get server reflexive address {} {}: {}",
-                                    agent_internal2.get_name(),
-                                    network,
-                                    url,
-                                    err
-                                );
-                                return Ok(());
-                            }
-                        };
+                let wg = WaitGroup::new();
+                for network_type in network_types {
+                    let server_addrs: Vec<SocketAddr> = server_addrs
+                        .iter()
+                        .filter(|addr| addr.is_ipv4() == network_type.is_ipv4())
+                        .copied()
+                        .collect();
+                    if server_addrs.is_empty() {
+                        continue;
+                    }
 
-                    let (ip, port) = (xoraddr.ip, xoraddr.port);
+                    let url = url.clone();
+                    let net3 = Arc::clone(&net2);
+                    let agent_internal3 = Arc::clone(&agent_internal2);
 
-                    let laddr = conn.local_addr()?;
-                    let srflx_config = CandidateServerReflexiveConfig {
-                        base_config: CandidateBaseConfig {
-                            network: network.clone(),
-                            address: ip.to_string(),
-                            port,
-                            component: COMPONENT_RTP,
-                            conn: Some(conn),
-                            ..CandidateBaseConfig::default()
-                        },
-                        rel_addr: laddr.ip().to_string(),
-                        rel_port: laddr.port(),
-                    };
+                    let w = wg.worker();
+                    agent_internal2.tasks.spawn(async move {
+                        let _d = w;
 
-                    let candidate: Arc<dyn Candidate + Send + Sync> =
-                        match srflx_config.new_candidate_server_reflexive() {
-                            Ok(candidate) => Arc::new(candidate),
-                            Err(err) => {
-                                log::warn!(
-                                "[{}]: Failed to create server reflexive candidate: {} {} {}: {:?}",
-                                agent_internal2.get_name(),
-                                network,
-                                ip,
-                                port,
-                                err
-                            );
-                                return Ok(());
+                        let candidate = Self::race_connection_attempts(
+                            &agent_internal3,
+                            server_addrs,
+                            |server_addr| {
+                                Self::gather_srflx_from(
+                                    network_type,
+                                    server_addr,
+                                    port_max,
+                                    port_min,
+                                    Arc::clone(&net3),
+                                    Arc::clone(&agent_internal3),
+                                )
+                            },
+                        )
+                        .await;
+
+                        match candidate {
+                            Some(candidate) => {
+                                agent_internal3.add_gathered_candidate(candidate).await
                             }
-                        };
-
-                    {
-                        if let Err(err) = agent_internal2.add_candidate(&candidate).await {
-                            if let Err(close_err) = candidate.close().await {
-                                log::warn!(
-                                    "[{}]: Failed to close candidate: {}",
-                                    agent_internal2.get_name(),
-                                    close_err
-                                );
+                            None => {
+                                agent_internal3
+                                    .candidate_error(&url, &Error::ErrServerUnreachable)
+                                    .await
                             }
-                            log::warn!(
-                                "[{}]: Failed to append to localCandidates and run onCandidateHdlr: {}",
-                                agent_internal2.get_name(),
-                                err
-                            );
                         }
-                    }
-
-                    Result::<()>::Ok(())
-                });
-            }
+                    });
+                }
+                wg.wait().await;
+            });
         }
 
         wg.wait().await;
     }
 
-    pub(crate) async fn gather_candidates_relay(
-        urls: Vec<Url>,
+    /// Returns a server reflexive candidate of network_type from the STUN server at
+    /// server_addr.
+    async fn gather_srflx_from(
+        network_type: NetworkType,
+        server_addr: SocketAddr,
+        port_max: u16,
+        port_min: u16,
         net: Arc<Net>,
         agent_internal: Arc<AgentInternal>,
-    ) {
+    ) -> Option<Arc<dyn Candidate + Send + Sync>> {
+        let network = network_type.to_string();
+
+        let conn: Arc<dyn Conn + Send + Sync> = match listen_udp_in_port_range(
+            &net,
+            port_max,
+            port_min,
+            if network_type.is_ipv4() {
+                SocketAddr::new(Ipv4Addr::new(0, 0, 0, 0).into(), 0)
+            } else {
+                SocketAddr::new(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0).into(), 0)
+            },
+        )
+        .await
+        {
+            Ok(conn) => conn,
+            Err(err) => {
+                log::warn!(
+                    "[{}]: Failed to listen for {}: {}",
+                    agent_internal.get_name(),
+                    server_addr,
+                    err
+                );
+                return None;
+            }
+        };
+
+        agent_internal.pace(false).await;
+        let xoraddr = match get_xormapped_addr(&conn, server_addr, STUN_GATHER_TIMEOUT).await {
+            Ok(xoraddr) => xoraddr,
+            Err(err) => {
+                log::warn!(
+                    "[{}]: could not get server reflexive address {} {}: {}",
+                    agent_internal.get_name(),
+                    network,
+                    server_addr,
+                    err
+                );
+                let _ = conn.close().await;
+                return None;
+            }
+        };
+
+        let (ip, port) = (xoraddr.ip, xoraddr.port);
+
+        let laddr = conn.local_addr().ok()?;
+        let base_ip = Self::source_ip(&net, laddr.ip(), server_addr).await;
+        let (network_id, network_cost) =
+            Self::base_network(&net, &agent_internal, |ip| Some(ip) == base_ip).await;
+        let srflx_config = CandidateServerReflexiveConfig {
+            base_config: CandidateBaseConfig {
+                network: network.clone(),
+                address: ip.to_string(),
+                port,
+                component: COMPONENT_RTP,
+                network_id,
+                network_cost,
+                conn: Some(conn),
+                ..CandidateBaseConfig::default()
+            },
+            rel_addr: laddr.ip().to_string(),
+            rel_port: laddr.port(),
+        };
+
+        match srflx_config.new_candidate_server_reflexive() {
+            Ok(candidate) => Some(Arc::new(candidate)),
+            Err(err) => {
+                log::warn!(
+                    "[{}]: Failed to create server reflexive candidate: {} {} {}: {:?}",
+                    agent_internal.get_name(),
+                    network,
+                    ip,
+                    port,
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    pub(crate) async fn gather_candidates_relay(params: GatherCandidatesRelayParams) {
+        let GatherCandidatesRelayParams {
+            urls,
+            network_types,
+            turn_over_tcp,
+            resolver,
+            dns_timeout,
+            net,
+            agent_internal,
+        } = params;
+
         let wg = WaitGroup::new();
 
         for url in urls {
@@ -741,147 +1016,358 @@ impl Agent {
                 return;
             }
 
-            let network = NetworkType::Udp4.to_string();
+            let network_types = network_types.clone();
+            let resolver = Arc::clone(&resolver);
             let net2 = Arc::clone(&net);
             let agent_internal2 = Arc::clone(&agent_internal);
 
             let w = wg.worker();
-            tokio::spawn(async move {
+            agent_internal.tasks.spawn(async move {
                 let _d = w;
 
-                let turn_server_addr = format!("{}:{}", url.host, url.port);
-
-                let (loc_conn, rel_addr, rel_port) =
-                    if url.proto == ProtoType::Udp && url.scheme == SchemeType::Turn {
-                        let loc_conn = match net2.bind(SocketAddr::from_str("0.0.0.0:0")?).await {
-                            Ok(c) => c,
-                            Err(err) => {
-                                log::warn!(
-                                    "[{}]: Failed to listen due to error: {}",
-                                    agent_internal2.get_name(),
-                                    err
-                                );
-                                return Ok(());
-                            }
-                        };
-
-                        let local_addr = loc_conn.local_addr()?;
-                        let rel_addr = local_addr.ip().to_string();
-                        let rel_port = local_addr.port();
-                        (loc_conn, rel_addr, rel_port)
-                    /*TODO: case url.proto == ProtoType::UDP && url.scheme == SchemeType::TURNS{
-                    case a.proxyDialer != nil && url.Proto == ProtoTypeTCP && (url.Scheme == SchemeTypeTURN || url.Scheme == SchemeTypeTURNS):
-                    case url.Proto == ProtoTypeTCP && url.Scheme == SchemeTypeTURN:
-                    case url.Proto == ProtoTypeTCP && url.Scheme == SchemeTypeTURNS:*/
-                    } else {
-                        log::warn!(
-                            "[{}]: Unable to handle URL in gather_candidates_relay {}",
-                            agent_internal2.get_name(),
-                            url
-                        );
-                        return Ok(());
-                    };
+                if url.scheme == SchemeType::Turns {
+                    log::warn!(
+                        "[{}]: Unable to handle URL in gather_candidates_relay {}",
+                        agent_internal2.get_name(),
+                        url
+                    );
+                    agent_internal2
+                        .candidate_error(&url, &Error::ErrTurnsUnsupported)
+                        .await;
+                    return;
+                }
+                let is_udp = url.proto == ProtoType::Udp;
+                let is_tcp = url.proto == ProtoType::Tcp && turn_over_tcp;
+                if !is_udp && !is_tcp {
+                    log::warn!(
+                        "[{}]: Unable to handle URL in gather_candidates_relay {}",
+                        agent_internal2.get_name(),
+                        url
+                    );
+                    return;
+                }
 
-                let cfg = turn::client::ClientConfig {
-                    stun_serv_addr: String::new(),
-                    turn_serv_addr: turn_server_addr.clone(),
-                    username: url.username,
-                    password: url.password,
-                    realm: String::new(),
-                    software: String::new(),
-                    rto_in_ms: 0,
-                    conn: loc_conn,
-                    vnet: Some(Arc::clone(&net2)),
-                };
-                let client = match turn::client::Client::new(cfg).await {
-                    Ok(client) => Arc::new(client),
+                let server_addrs = match resolve_server(
+                    &resolver,
+                    dns_timeout,
+                    &url.host,
+                    url.port,
+                    &network_types,
+                )
+                .await
+                {
+                    Ok(server_addrs) => server_addrs,
                     Err(err) => {
                         log::warn!(
-                            "[{}]: Failed to build new turn.Client {} {}\n",
+                            "[{}]: Failed to resolve {}: {}",
                             agent_internal2.get_name(),
-                            turn_server_addr,
+                            url,
                             err
                         );
-                        return Ok(());
+                        agent_internal2.candidate_error(&url, &err).await;
+                        return;
                     }
                 };
-                if let Err(err) = client.listen().await {
-                    let _ = client.close().await;
+
+                let candidate =
+                    Self::race_connection_attempts(&agent_internal2, server_addrs, |server_addr| {
+                        Self::gather_relay_from(
+                            url.clone(),
+                            server_addr,
+                            Arc::clone(&net2),
+                            Arc::clone(&agent_internal2),
+                        )
+                    })
+                    .await;
+
+                match candidate {
+                    Some(candidate) => agent_internal2.add_gathered_candidate(candidate).await,
+                    None => {
+                        agent_internal2
+                            .candidate_error(&url, &Error::ErrServerUnreachable)
+                            .await
+                    }
+                }
+            });
+        }
+
+        wg.wait().await;
+    }
+
+    /// Returns a relay candidate allocated on the TURN server of url at server_addr.
+    async fn gather_relay_from(
+        url: Url,
+        server_addr: SocketAddr,
+        net: Arc<Net>,
+        agent_internal: Arc<AgentInternal>,
+    ) -> Option<Arc<dyn Candidate + Send + Sync>> {
+        let local_ip = if server_addr.is_ipv4() {
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        } else {
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+        };
+
+        let loc_conn: Arc<dyn Conn + Send + Sync> = if url.proto == ProtoType::Udp {
+            match net.bind(SocketAddr::new(local_ip, 0)).await {
+                Ok(c) => c,
+                Err(err) => {
                     log::warn!(
-                        "[{}]: Failed to listen on turn.Client {} {}",
-                        agent_internal2.get_name(),
-                        turn_server_addr,
+                        "[{}]: Failed to listen due to error: {}",
+                        agent_internal.get_name(),
                         err
                     );
-                    return Ok(());
+                    return None;
                 }
+            }
+        } else {
+            // The allocation stays bound to this connection
+            match TcpPacketConn::dial_with_net(&net, local_ip, server_addr, TcpFraming::Stun).await
+            {
+                Ok(c) => Arc::new(c),
+                Err(err) => {
+                    log::warn!(
+                        "[{}]: Failed to connect to {} due to error: {}",
+                        agent_internal.get_name(),
+                        server_addr,
+                        err
+                    );
+                    return None;
+                }
+            }
+        };
 
-                let relay_conn = match client.allocate().await {
-                    Ok(conn) => conn,
-                    Err(err) => {
-                        let _ = client.close().await;
-                        log::warn!(
-                            "[{}]: Failed to allocate on turn.Client {} {}",
-                            agent_internal2.get_name(),
-                            turn_server_addr,
-                            err
-                        );
-                        return Ok(());
-                    }
-                };
+        let local_addr = loc_conn.local_addr().ok()?;
+        let rel_addr = local_addr.ip().to_string();
+        let rel_port = local_addr.port();
+        let base_ip = Self::source_ip(&net, local_addr.ip(), server_addr).await;
+        let (network_id, network_cost) =
+            Self::base_network(&net, &agent_internal, |ip| Some(ip) == base_ip).await;
+
+        // The TURN client is handed the resolved address so it doesn't resolve it differently.
+        let turn_server_addr = server_addr.to_string();
+        let cfg = turn::client::ClientConfig {
+            stun_serv_addr: String::new(),
+            turn_serv_addr: turn_server_addr.clone(),
+            username: url.username,
+            password: url.password,
+            realm: String::new(),
+            software: String::new(),
+            rto_in_ms: 0,
+            conn: loc_conn,
+            vnet: Some(Arc::clone(&net)),
+        };
+        let client = match turn::client::Client::new(cfg).await {
+            Ok(client) => Arc::new(client),
+            Err(err) => {
+                log::warn!(
+                    "[{}]: Failed to build new turn.Client {} {}\n",
+                    agent_internal.get_name(),
+                    turn_server_addr,
+                    err
+                );
+                return None;
+            }
+        };
+        if let Err(err) = client.listen().await {
+            let _ = client.close().await;
+            log::warn!(
+                "[{}]: Failed to listen on turn.Client {} {}",
+                agent_internal.get_name(),
+                turn_server_addr,
+                err
+            );
+            return None;
+        }
 
-                let raddr = relay_conn.local_addr()?;
-                let relay_config = CandidateRelayConfig {
-                    base_config: CandidateBaseConfig {
-                        network: network.clone(),
-                        address: raddr.ip().to_string(),
-                        port: raddr.port(),
-                        component: COMPONENT_RTP,
-                        conn: Some(Arc::new(relay_conn)),
-                        ..CandidateBaseConfig::default()
-                    },
-                    rel_addr,
-                    rel_port,
-                    relay_client: Some(Arc::clone(&client)),
-                };
+        agent_internal.pace(false).await;
+        let relay_conn = match client.allocate().await {
+            Ok(conn) => conn,
+            Err(err) => {
+                let _ = client.close().await;
+                log::warn!(
+                    "[{}]: Failed to allocate on turn.Client {} {}",
+                    agent_internal.get_name(),
+                    turn_server_addr,
+                    err
+                );
+                return None;
+            }
+        };
 
-                let candidate: Arc<dyn Candidate + Send + Sync> =
-                    match relay_config.new_candidate_relay() {
-                        Ok(candidate) => Arc::new(candidate),
-                        Err(err) => {
-                            let _ = client.close().await;
-                            log::warn!(
-                                "[{}]: Failed to create relay candidate: {} {}: {}",
-                                agent_internal2.get_name(),
-                                network,
-                                raddr,
-                                err
-                            );
-                            return Ok(());
-                        }
-                    };
+        let raddr = match relay_conn.local_addr() {
+            Ok(raddr) => raddr,
+            Err(_) => {
+                let _ = client.close().await;
+                return None;
+            }
+        };
+        // The relayed address can be of another family than the server address (RFC 6156)
+        let network = if raddr.is_ipv4() {
+            NetworkType::Udp4
+        } else {
+            NetworkType::Udp6
+        }
+        .to_string();
+        let relay_config = CandidateRelayConfig {
+            base_config: CandidateBaseConfig {
+                network: network.clone(),
+                address: raddr.ip().to_string(),
+                port: raddr.port(),
+                component: COMPONENT_RTP,
+                network_id,
+                network_cost,
+                conn: Some(Arc::new(relay_conn)),
+                ..CandidateBaseConfig::default()
+            },
+            rel_addr,
+            rel_port,
+            relay_client: Some(Arc::clone(&client)),
+        };
 
-                {
-                    if let Err(err) = agent_internal2.add_candidate(&candidate).await {
-                        if let Err(close_err) = candidate.close().await {
-                            log::warn!(
-                                "[{}]: Failed to close candidate: {}",
-                                agent_internal2.get_name(),
-                                close_err
-                            );
-                        }
-                        log::warn!(
-                            "[{}]: Failed to append to localCandidates and run onCandidateHdlr: {}",
-                            agent_internal2.get_name(),
-                            err
-                        );
+        match relay_config.new_candidate_relay() {
+            Ok(candidate) => Some(Arc::new(candidate)),
+            Err(err) => {
+                let _ = client.close().await;
+                log::warn!(
+                    "[{}]: Failed to create relay candidate: {} {}: {}",
+                    agent_internal.get_name(),
+                    network,
+                    raddr,
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    /// Returns the network id and cost of the local interface address of a server reflexive or
+    /// relay candidate's base, which the candidate inherits. Zeros are returned unless exactly
+    /// one address is_base.
+    async fn base_network<F>(
+        net: &Arc<Net>,
+        agent_internal: &AgentInternal,
+        is_base: F,
+    ) -> (u16, u16)
+    where
+        F: Fn(IpAddr) -> bool,
+    {
+        let network_types = [NetworkType::Udp4, NetworkType::Udp6];
+        let mut bases = local_interface_addrs(net, &None, &None, &network_types)
+            .await
+            .into_iter()
+            .filter(|addr| is_base(addr.ip));
+        match (bases.next(), bases.next()) {
+            (Some(base), None) => (
+                base.network_id,
+                agent_internal.host_network_cost(&base.interface, base.ip),
+            ),
+            _ => (0, 0),
+        }
+    }
+
+    /// Returns the address packets from a socket bound to local_ip to dst are sent from, which
+    /// is looked up if local_ip is unspecified.
+    async fn source_ip(net: &Arc<Net>, local_ip: IpAddr, dst: SocketAddr) -> Option<IpAddr> {
+        if !local_ip.is_unspecified() {
+            return Some(local_ip);
+        }
+        // Connecting a UDP socket only looks up the route, nothing is sent
+        let conn = net.dail(dst.is_ipv4(), &dst.to_string()).await.ok()?;
+        let local_addr = conn.local_addr();
+        let _ = conn.close().await;
+        local_addr.ok().map(|addr| addr.ip())
+    }
+
+    /// Tries to gather a candidate from the addresses of a server in their order, like Happy
+    /// Eyeballs (RFC 8305): an attempt with the next address starts once the previous attempt
+    /// failed or after CONNECTION_ATTEMPT_DELAY, whichever comes first. The candidate of the
+    /// first successful attempt is returned, the ones of later successful attempts are closed.
+    async fn race_connection_attempts<F, Fut>(
+        agent_internal: &Arc<AgentInternal>,
+        server_addrs: Vec<SocketAddr>,
+        attempt: F,
+    ) -> Option<Arc<dyn Candidate + Send + Sync>>
+    where
+        F: Fn(SocketAddr) -> Fut,
+        Fut: Future<Output = Option<Arc<dyn Candidate + Send + Sync>>> + Send + 'static,
+    {
+        let (result_tx, mut result_rx) = mpsc::channel(server_addrs.len().max(1));
+        let mut server_addrs = server_addrs.into_iter().peekable();
+        let mut pending = 0usize;
+
+        let winner = loop {
+            if let Some(server_addr) = server_addrs.next() {
+                let result_tx = result_tx.clone();
+                let attempt = attempt(server_addr);
+                agent_internal.tasks.spawn(async move {
+                    let _ = result_tx.send(attempt.await).await;
+                });
+                pending += 1;
+            } else if pending == 0 {
+                break None;
+            }
+
+            let delay = tokio::time::sleep(CONNECTION_ATTEMPT_DELAY);
+            tokio::pin!(delay);
+            let has_next = server_addrs.peek().is_some();
+            tokio::select! {
+                result = result_rx.recv() => {
+                    pending -= 1;
+                    if let Some(Some(candidate)) = result {
+                        break Some(candidate);
                     }
                 }
+                _ = &mut delay, if has_next => {}
+            }
+        };
 
-                Result::<()>::Ok(())
+        if winner.is_some() && pending > 0 {
+            drop(result_tx);
+            let name = agent_internal.get_name().to_owned();
+            agent_internal.tasks.spawn(async move {
+                while let Some(result) = result_rx.recv().await {
+                    if let Some(candidate) = result {
+                        if let Err(err) = candidate.close().await {
+                            log::warn!("[{}]: Failed to close candidate: {}", name, err);
+                        }
+                    }
+                }
             });
         }
 
-        wg.wait().await;
+        winner
+    }
+}
+
+impl AgentInternal {
+    /// Adds a candidate gathered from a STUN or TURN server, closing it if that fails.
+    async fn add_gathered_candidate(self: &Arc<Self>, candidate: Arc<dyn Candidate + Send + Sync>) {
+        if let Err(err) = self.add_candidate(&candidate).await {
+            if let Err(close_err) = candidate.close().await {
+                log::warn!(
+                    "[{}]: Failed to close candidate: {}",
+                    self.get_name(),
+                    close_err
+                );
+            }
+            log::warn!(
+                "[{}]: Failed to append to localCandidates and run onCandidateHdlr: {}",
+                self.get_name(),
+                err
+            );
+        }
+    }
+
+    /// Fires the on_candidate_error handler for a server no candidate was gathered from.
+    async fn candidate_error(&self, url: &Url, err: &Error) {
+        if let Some(handler) = &*self.on_candidate_error_hdlr.load() {
+            let mut f = handler.lock().await;
+            f(CandidateError {
+                url: url.clone(),
+                error_code: CANDIDATE_ERROR_CODE_UNREACHABLE,
+                error_text: err.to_string(),
+            })
+            .await;
+        }
     }
 }
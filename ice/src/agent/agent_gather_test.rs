@@ -1,10 +1,14 @@
+use super::agent_gather::*;
 use super::agent_vnet_test::*;
 use super::*;
+use crate::candidate::candidate_relay_test::OptimisticAuthHandler;
+use crate::resolver::resolver_test::TestResolver;
 use crate::udp_mux::{UDPMuxDefault, UDPMuxParams};
 use crate::util::*;
 
 use ipnet::IpNet;
 use std::str::FromStr;
+use std::time::Duration;
 use tokio::net::UdpSocket;
 use util::vnet::*;
 
@@ -310,15 +314,84 @@ async fn test_vnet_gather_with_nat_1to1_as_srflx_candidates() -> Result<()> {
     }
 
     assert!(candi_host.is_some(), "should not be nil");
-    assert_eq!("10.0.0.1", candi_host.unwrap().address(), "should match");
+    let candi_host = candi_host.unwrap();
+    assert_eq!("10.0.0.1", candi_host.address(), "should match");
     assert!(candi_srflx.is_some(), "should not be nil");
-    assert_eq!("1.2.3.4", candi_srflx.unwrap().address(), "should match");
+    let candi_srflx = candi_srflx.unwrap();
+    assert_eq!("1.2.3.4", candi_srflx.address(), "should match");
+    assert_ne!(candi_srflx.network_id(), 0);
+    assert_eq!(
+        candi_srflx.network_id(),
+        candi_host.network_id(),
+        "the srflx candidate should be on the network of its base"
+    );
 
     a.close().await?;
 
     Ok(())
 }
 
+#[tokio::test]
+async fn test_vnet_gather_srflx_and_relay_inherit_network_of_base() -> Result<()> {
+    let v = build_simple_vnet(nat::NatType::default(), nat::NatType::default()).await?;
+
+    let a = Agent::new(AgentConfig {
+        network_types: vec![NetworkType::Udp4],
+        urls: vec![
+            Url {
+                scheme: SchemeType::Stun,
+                host: VNET_STUN_SERVER_IP.to_owned(),
+                port: VNET_STUN_SERVER_PORT,
+                ..Default::default()
+            },
+            Url {
+                scheme: SchemeType::Turn,
+                host: VNET_STUN_SERVER_IP.to_owned(),
+                port: VNET_STUN_SERVER_PORT,
+                username: "user".to_owned(),
+                password: "pass".to_owned(),
+                proto: ProtoType::Udp,
+            },
+        ],
+        candidate_types: vec![
+            CandidateType::Host,
+            CandidateType::ServerReflexive,
+            CandidateType::Relay,
+        ],
+        multicast_dns_mode: MulticastDnsMode::Disabled,
+        net: Some(Arc::clone(&v.net0)),
+        network_cost: Arc::new(Some(Box::new(|_: &str, _: IpAddr| 900))),
+        ..Default::default()
+    })
+    .await?;
+    let (mut candidate_rx, _error_rx) = gathering_events(&a);
+
+    a.gather_candidates()?;
+
+    let mut types = vec![];
+    while let Some(candidate) = candidate_rx.recv().await.unwrap() {
+        // eth0 comes after lo
+        assert_eq!(candidate.network_id(), 2, "{}", candidate);
+        assert_eq!(candidate.network_cost(), 900, "{}", candidate);
+        types.push(candidate.candidate_type());
+    }
+    types.sort_by_key(|t| *t as u8);
+    types.dedup();
+    assert_eq!(
+        types,
+        vec![
+            CandidateType::Host,
+            CandidateType::ServerReflexive,
+            CandidateType::Relay
+        ]
+    );
+
+    a.close().await?;
+    v.close().await?;
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_vnet_gather_with_interface_filter() -> Result<()> {
     let r = Arc::new(Mutex::new(router::Router::new(router::RouterConfig {
@@ -408,11 +481,15 @@ async fn test_vnet_gather_turn_connection_leak() -> Result<()> {
 
     {
         let agent_internal = Arc::clone(&a_agent.internal);
-        Agent::gather_candidates_relay(
-            vec![turn_server_url.clone()],
-            Arc::clone(&v.net0),
+        Agent::gather_candidates_relay(GatherCandidatesRelayParams {
+            urls: vec![turn_server_url.clone()],
+            network_types: supported_network_types(),
+            turn_over_tcp: false,
+            resolver: Arc::clone(&a_agent.resolver),
+            dns_timeout: a_agent.dns_timeout,
+            net: Arc::clone(&v.net0),
             agent_internal,
-        )
+        })
         .await;
     }
 
@@ -488,3 +565,188 @@ async fn test_vnet_gather_muxed_udp() -> Result<()> {
 
     Ok(())
 }
+
+/// Returns the events of the gathering of agent: the candidates, None once gathering is done, and
+/// the candidate errors.
+fn gathering_events(
+    agent: &Agent,
+) -> (
+    mpsc::UnboundedReceiver<Option<Arc<dyn Candidate + Send + Sync>>>,
+    mpsc::UnboundedReceiver<CandidateError>,
+) {
+    let (candidate_tx, candidate_rx) = mpsc::unbounded_channel();
+    agent.on_candidate(Box::new(
+        move |c: Option<Arc<dyn Candidate + Send + Sync>>| {
+            let _ = candidate_tx.send(c);
+            Box::pin(async {})
+        },
+    ));
+
+    let (error_tx, error_rx) = mpsc::unbounded_channel();
+    agent.on_candidate_error(Box::new(move |err: CandidateError| {
+        let _ = error_tx.send(err);
+        Box::pin(async {})
+    }));
+
+    (candidate_rx, error_rx)
+}
+
+#[tokio::test]
+async fn test_gather_slow_dns_does_not_delay_other_servers() -> Result<()> {
+    let server_listener = Arc::new(UdpSocket::bind("127.0.0.1:0").await?);
+    let server_port = server_listener.local_addr()?.port();
+    let server = turn::server::Server::new(turn::server::config::ServerConfig {
+        realm: "webrtc.rs".to_owned(),
+        auth_handler: Arc::new(OptimisticAuthHandler {}),
+        conn_configs: vec![turn::server::config::ConnConfig {
+            conn: server_listener,
+            relay_addr_generator: Box::new(turn::relay::relay_none::RelayAddressGeneratorNone {
+                address: "127.0.0.1".to_owned(),
+                net: Arc::new(net::Net::new(None)),
+            }),
+        }],
+        channel_bind_timeout: Duration::from_secs(0),
+    })
+    .await?;
+
+    let dns_timeout = Duration::from_secs(1);
+    let resolver = TestResolver::default()
+        .with_host("slow.test", Duration::from_secs(10), &["127.0.0.1"])
+        .with_host("fast.test", Duration::from_millis(0), &["127.0.0.1"]);
+    let stun_url = |host: &str| Url {
+        scheme: SchemeType::Stun,
+        host: host.to_owned(),
+        port: server_port,
+        ..Default::default()
+    };
+
+    let a = Agent::new(AgentConfig {
+        network_types: vec![NetworkType::Udp4],
+        urls: vec![stun_url("slow.test"), stun_url("fast.test")],
+        candidate_types: vec![CandidateType::ServerReflexive],
+        multicast_dns_mode: MulticastDnsMode::Disabled,
+        resolver: Some(Arc::new(resolver)),
+        dns_timeout: Some(dns_timeout),
+        ..Default::default()
+    })
+    .await?;
+    let (mut candidate_rx, mut error_rx) = gathering_events(&a);
+
+    let start = Instant::now();
+    a.gather_candidates()?;
+
+    let candidate = candidate_rx.recv().await.unwrap();
+    assert!(
+        start.elapsed() < dns_timeout,
+        "the candidate of fast.test should not wait for slow.test"
+    );
+    assert_eq!(
+        candidate.unwrap().candidate_type(),
+        CandidateType::ServerReflexive
+    );
+
+    let err = error_rx.recv().await.unwrap();
+    assert_eq!(err.url.host, "slow.test");
+    assert_eq!(err.error_code, CANDIDATE_ERROR_CODE_UNREACHABLE);
+    assert_eq!(err.error_text, Error::ErrDnsTimeout.to_string());
+
+    assert!(candidate_rx.recv().await.unwrap().is_none());
+    assert!(start.elapsed() < Duration::from_secs(10));
+
+    a.close().await?;
+    server.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_gather_relay_reports_turns_unsupported() -> Result<()> {
+    let a = Agent::new(AgentConfig {
+        network_types: supported_network_types(),
+        urls: vec![Url {
+            scheme: SchemeType::Turns,
+            host: "127.0.0.1".to_owned(),
+            username: "username".to_owned(),
+            password: "password".to_owned(),
+            port: 5349,
+            proto: ProtoType::Tcp,
+        }],
+        candidate_types: vec![CandidateType::Relay],
+        multicast_dns_mode: MulticastDnsMode::Disabled,
+        ..Default::default()
+    })
+    .await?;
+    let (mut candidate_rx, mut error_rx) = gathering_events(&a);
+
+    a.gather_candidates()?;
+
+    let err = error_rx.recv().await.unwrap();
+    assert_eq!(err.url.scheme, SchemeType::Turns);
+    assert_eq!(err.error_code, CANDIDATE_ERROR_CODE_UNREACHABLE);
+    assert_eq!(err.error_text, Error::ErrTurnsUnsupported.to_string());
+    assert!(candidate_rx.recv().await.unwrap().is_none());
+
+    a.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_gather_relay_ipv6_only_turn_server() -> Result<()> {
+    let server_listener = Arc::new(UdpSocket::bind("[::1]:0").await?);
+    let server_port = server_listener.local_addr()?.port();
+    let server = turn::server::Server::new(turn::server::config::ServerConfig {
+        realm: "webrtc.rs".to_owned(),
+        auth_handler: Arc::new(OptimisticAuthHandler {}),
+        conn_configs: vec![turn::server::config::ConnConfig {
+            conn: server_listener,
+            relay_addr_generator: Box::new(turn::relay::relay_none::RelayAddressGeneratorNone {
+                address: "127.0.0.1".to_owned(),
+                net: Arc::new(net::Net::new(None)),
+            }),
+        }],
+        channel_bind_timeout: Duration::from_secs(0),
+    })
+    .await?;
+
+    // turn.test only has an AAAA record
+    let resolver =
+        TestResolver::default().with_host("turn.test", Duration::from_millis(0), &["::1"]);
+    let a = Agent::new(AgentConfig {
+        network_types: supported_network_types(),
+        urls: vec![Url {
+            scheme: SchemeType::Turn,
+            host: "turn.test".to_owned(),
+            username: "username".to_owned(),
+            password: "password".to_owned(),
+            port: server_port,
+            proto: ProtoType::Udp,
+        }],
+        candidate_types: vec![CandidateType::Relay],
+        multicast_dns_mode: MulticastDnsMode::Disabled,
+        resolver: Some(Arc::new(resolver)),
+        ..Default::default()
+    })
+    .await?;
+    let (mut candidate_rx, mut error_rx) = gathering_events(&a);
+
+    a.gather_candidates()?;
+
+    let candidate = candidate_rx.recv().await.unwrap().unwrap();
+    assert_eq!(candidate.candidate_type(), CandidateType::Relay);
+    assert_eq!(candidate.address(), "127.0.0.1");
+    let related_address = candidate.related_address().unwrap();
+    assert!(
+        IpAddr::from_str(&related_address.address)?.is_ipv6(),
+        "the TURN server should be reached over IPv6: {}",
+        related_address
+    );
+
+    assert!(candidate_rx.recv().await.unwrap().is_none());
+    assert!(error_rx.try_recv().is_err());
+
+    a.close().await?;
+    server.close().await?;
+
+    Ok(())
+}
@@ -4,12 +4,20 @@ use crate::candidate::candidate_base::CandidateBaseConfig;
 use crate::candidate::candidate_peer_reflexive::CandidatePeerReflexiveConfig;
 use crate::util::*;
 use arc_swap::ArcSwapOption;
+use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, AtomicU64};
+use stun::error_code::{ErrorCodeAttribute, CODE_BAD_REQUEST};
 use util::sync::Mutex as SyncMutex;
+use util::sync::TaskGroup;
 
 pub type ChanCandidateTx =
     Arc<Mutex<Option<mpsc::Sender<Option<Arc<dyn Candidate + Send + Sync>>>>>>;
 
+pub(crate) type CandidatePairKey = (
+    Arc<dyn Candidate + Send + Sync>,
+    Arc<dyn Candidate + Send + Sync>,
+);
+
 #[derive(Default)]
 pub(crate) struct UfragPwd {
     pub(crate) local_ufrag: String,
@@ -31,13 +39,15 @@ pub struct AgentInternal {
         Mutex<Option<(mpsc::Receiver<()>, mpsc::Receiver<bool>)>>,
 
     pub(crate) chan_candidate_tx: ChanCandidateTx,
-    pub(crate) chan_candidate_pair_tx: Mutex<Option<mpsc::Sender<()>>>,
+    pub(crate) chan_candidate_pair_tx: Mutex<Option<mpsc::Sender<SelectedCandidatePairChange>>>,
+    pub(crate) selected_pair_changes: SyncMutex<VecDeque<SelectedCandidatePairChange>>,
     pub(crate) chan_state_tx: Mutex<Option<mpsc::Sender<ConnectionState>>>,
 
     pub(crate) on_connection_state_change_hdlr: ArcSwapOption<Mutex<OnConnectionStateChangeHdlrFn>>,
     pub(crate) on_selected_candidate_pair_change_hdlr:
         ArcSwapOption<Mutex<OnSelectedCandidatePairChangeHdlrFn>>,
     pub(crate) on_candidate_hdlr: ArcSwapOption<Mutex<OnCandidateHdlrFn>>,
+    pub(crate) on_candidate_error_hdlr: ArcSwapOption<Mutex<OnCandidateErrorHdlrFn>>,
 
     pub(crate) tie_breaker: AtomicU64,
     pub(crate) is_controlling: AtomicBool,
@@ -46,6 +56,17 @@ pub struct AgentInternal {
     pub(crate) start_time: SyncMutex<Instant>,
     pub(crate) nominated_pair: Mutex<Option<Arc<CandidatePair>>>,
 
+    pub(crate) renomination: bool,
+    pub(crate) renomination_hold_time: Duration,
+    // The pair better than the selected one and since when, renominated after the hold time
+    pub(crate) renomination_candidate: SyncMutex<Option<(Arc<CandidatePair>, Instant)>>,
+    pub(crate) network_cost: Arc<Option<NetworkCostFn>>,
+    pub(crate) remote_options: SyncMutex<IceOptions>,
+    // The remote agent won't signal more candidates, either because it sent
+    // end-of-candidates or because it doesn't trickle
+    pub(crate) remote_candidates_complete: AtomicBool,
+    pub(crate) gathering_state: Arc<AtomicU8>, //GatheringState,
+
     pub(crate) connection_state: AtomicU8, //ConnectionState,
 
     pub(crate) started_ch_tx: Mutex<Option<broadcast::Sender<()>>>,
@@ -61,6 +82,24 @@ pub struct AgentInternal {
 
     pub(crate) agent_conn: Arc<AgentConn>,
 
+    // Tasks of the agent, waited for when it is closed
+    pub(crate) tasks: TaskGroup,
+
+    // Paces the checks and gathering transactions of the agent, and of all the agents sharing
+    // its UDP mux
+    pub(crate) pacer: Pacer,
+    pub(crate) mux_pacer: Option<Arc<Pacer>>,
+    // Checks triggered by inbound binding requests, sent ahead of the ordinary checks
+    pub(crate) triggered_checks: SyncMutex<VecDeque<CandidatePairKey>>,
+    pub(crate) triggered_check_tx: SyncMutex<Option<mpsc::Sender<()>>>,
+    pub(crate) triggered_check_rx: Mutex<Option<mpsc::Receiver<()>>>,
+
+    // Remote candidate types we are willing to pair with, empty means all types
+    pub(crate) remote_candidate_types: SyncMutex<Vec<CandidateType>>,
+    // Number of remote candidates dropped because of remote_candidate_types
+    pub(crate) filtered_remote_candidates: AtomicU64,
+    pub(crate) rejected_binding_requests: SyncMutex<RejectedBindingRequestStats>,
+
     // the following variables won't be changed after init_with_defaults()
     pub(crate) insecure_skip_verify: bool,
     pub(crate) max_binding_requests: u16,
@@ -77,6 +116,13 @@ pub struct AgentInternal {
     // How often should we send keepalive packets?
     // 0 means never
     pub(crate) keepalive_interval: Duration,
+    // How often should we keep valid but unselected pairs alive?
+    // 0 means never
+    pub(crate) backup_keepalive_interval: Duration,
+    // Use binding requests rather than indications as backup keepalives
+    pub(crate) backup_keepalive_requests: bool,
+    // How many backup keepalives in a row may fail before the pair is pruned
+    pub(crate) max_backup_keepalive_failures: u16,
     // How often should we run our internal taskLoop to check for state changes when connecting
     pub(crate) check_interval: Duration,
 }
@@ -89,6 +135,7 @@ impl AgentInternal {
         let (on_connected_tx, on_connected_rx) = mpsc::channel(1);
         let (done_tx, done_rx) = mpsc::channel(1);
         let (force_candidate_contact_tx, force_candidate_contact_rx) = mpsc::channel(1);
+        let (triggered_check_tx, triggered_check_rx) = mpsc::channel(1);
         let (started_ch_tx, _) = broadcast::channel(1);
 
         let ai = AgentInternal {
@@ -104,11 +151,13 @@ impl AgentInternal {
 
             chan_candidate_tx: Arc::new(Mutex::new(Some(chan_candidate_tx))),
             chan_candidate_pair_tx: Mutex::new(Some(chan_candidate_pair_tx)),
+            selected_pair_changes: SyncMutex::new(VecDeque::new()),
             chan_state_tx: Mutex::new(Some(chan_state_tx)),
 
             on_connection_state_change_hdlr: ArcSwapOption::empty(),
             on_selected_candidate_pair_change_hdlr: ArcSwapOption::empty(),
             on_candidate_hdlr: ArcSwapOption::empty(),
+            on_candidate_error_hdlr: ArcSwapOption::empty(),
 
             tie_breaker: AtomicU64::new(rand::random::<u64>()),
             is_controlling: AtomicBool::new(config.is_controlling),
@@ -117,6 +166,16 @@ impl AgentInternal {
             start_time: SyncMutex::new(Instant::now()),
             nominated_pair: Mutex::new(None),
 
+            renomination: config.renomination,
+            renomination_hold_time: config
+                .renomination_hold_time
+                .unwrap_or_else(|| Duration::from_secs(0)),
+            renomination_candidate: SyncMutex::new(None),
+            network_cost: Arc::clone(&config.network_cost),
+            remote_options: SyncMutex::new(IceOptions::default()),
+            remote_candidates_complete: AtomicBool::new(false),
+            gathering_state: Arc::new(AtomicU8::new(0)), //GatheringState::New,
+
             connection_state: AtomicU8::new(ConnectionState::New as u8),
 
             insecure_skip_verify: config.insecure_skip_verify,
@@ -142,6 +201,12 @@ impl AgentInternal {
             // 0 means never
             keepalive_interval: Duration::from_secs(0),
 
+            // How often should we keep valid but unselected pairs alive?
+            // 0 means never
+            backup_keepalive_interval: Duration::from_secs(0),
+            backup_keepalive_requests: config.backup_keepalive_requests,
+            max_backup_keepalive_failures: 0,
+
             // How often should we run our internal taskLoop to check for state changes when connecting
             check_interval: Duration::from_secs(0),
 
@@ -154,7 +219,22 @@ impl AgentInternal {
             pending_binding_requests: Mutex::new(vec![]),
 
             // AgentConn
-            agent_conn: Arc::new(AgentConn::new()),
+            agent_conn: Arc::new(AgentConn::new(config.prefer_low_cost)),
+            tasks: TaskGroup::new(),
+
+            // Set by init_with_defaults()
+            pacer: Pacer::new(Duration::from_secs(0)),
+            mux_pacer: match &config.udp_network {
+                UDPNetwork::Muxed(udp_mux) => udp_mux.pacer(),
+                _ => None,
+            },
+            triggered_checks: SyncMutex::new(VecDeque::new()),
+            triggered_check_tx: SyncMutex::new(Some(triggered_check_tx)),
+            triggered_check_rx: Mutex::new(Some(triggered_check_rx)),
+
+            remote_candidate_types: SyncMutex::new(config.remote_candidate_types.clone()),
+            filtered_remote_candidates: AtomicU64::new(0),
+            rejected_binding_requests: SyncMutex::new(RejectedBindingRequestStats::default()),
         };
 
         let chan_receivers = ChanReceivers {
@@ -229,6 +309,21 @@ impl AgentInternal {
                 *last_connection_state = self.connection_state.load(Ordering::SeqCst).into();
                 return;
             }
+
+            // No more candidates are coming from either side and every pair failed, so
+            // there is nothing left to wait for
+            if self.remote_candidates_complete.load(Ordering::SeqCst)
+                && self.gathering_state.load(Ordering::SeqCst) != GatheringState::Gathering as u8
+                && self.all_candidate_pairs_failed().await
+            {
+                log::debug!(
+                    "[{}]: all candidate pairs failed and no more candidates are expected",
+                    self.get_name()
+                );
+                self.update_connection_state(ConnectionState::Failed).await;
+                *last_connection_state = self.connection_state.load(Ordering::SeqCst).into();
+                return;
+            }
         }
 
         self.contact_candidates().await;
@@ -247,6 +342,10 @@ impl AgentInternal {
             self.failed_timeout,
         );
 
+        if let Some(triggered_check_rx) = self.triggered_check_rx.lock().await.take() {
+            self.start_triggered_checks(triggered_check_rx);
+        }
+
         let done_and_force_candidate_contact_rx = {
             let mut done_and_force_candidate_contact_rx =
                 self.done_and_force_candidate_contact_rx.lock().await;
@@ -257,7 +356,7 @@ impl AgentInternal {
             done_and_force_candidate_contact_rx
         {
             let ai = Arc::clone(self);
-            tokio::spawn(async move {
+            self.tasks.spawn(async move {
                 loop {
                     let mut interval = DEFAULT_CHECK_INTERVAL;
 
@@ -326,16 +425,58 @@ impl AgentInternal {
         }
     }
 
+    /// set_selected_pair selects a nominated pair, or clears the selected pair for an ICE
+    /// restart.
     pub(crate) async fn set_selected_pair(&self, p: Option<Arc<CandidatePair>>) {
+        let reason = if self.agent_conn.get_selected_pair().is_some() {
+            SelectedCandidatePairChangeReason::Renomination
+        } else if self.selected_pair_changes.lock().is_empty() {
+            SelectedCandidatePairChangeReason::InitialNomination
+        } else {
+            SelectedCandidatePairChangeReason::Restart
+        };
+        self.change_selected_pair(p, reason).await;
+    }
+
+    pub(crate) async fn change_selected_pair(
+        &self,
+        p: Option<Arc<CandidatePair>>,
+        reason: SelectedCandidatePairChangeReason,
+    ) {
         log::trace!(
-            "[{}]: Set selected candidate pair: {:?}",
+            "[{}]: Set selected candidate pair: {:?}, reason: {}",
             self.get_name(),
-            p
+            p,
+            reason
         );
 
         if let Some(p) = p {
             p.nominated.store(true, Ordering::SeqCst);
-            self.agent_conn.selected_pair.store(Some(p));
+            let previous = self
+                .agent_conn
+                .selected_pair
+                .swap(Some(Arc::clone(&p)))
+                .or_else(|| {
+                    // The pair selected before an ICE restart
+                    self.selected_pair_changes
+                        .lock()
+                        .back()
+                        .map(|change| Arc::clone(&change.current))
+                });
+
+            let change = SelectedCandidatePairChange {
+                previous,
+                current: p,
+                reason,
+                timestamp: SystemTime::now(),
+            };
+            {
+                let mut changes = self.selected_pair_changes.lock();
+                if changes.len() >= MAX_SELECTED_CANDIDATE_PAIR_CHANGES {
+                    changes.pop_front();
+                }
+                changes.push_back(change.clone());
+            }
 
             self.update_connection_state(ConnectionState::Connected)
                 .await;
@@ -344,7 +485,7 @@ impl AgentInternal {
             {
                 let chan_candidate_pair_tx = self.chan_candidate_pair_tx.lock().await;
                 if let Some(tx) = &*chan_candidate_pair_tx {
-                    let _ = tx.send(()).await;
+                    let _ = tx.send(change).await;
                 }
             }
 
@@ -401,10 +542,90 @@ impl AgentInternal {
         }
 
         for (local, remote) in pairs {
+            self.pace(false).await;
             self.ping_candidate(&local, &remote).await;
         }
     }
 
+    /// Waits until a new STUN transaction can be started without exceeding the pacing interval
+    /// Ta of the agent, and of the UDP mux if the agent shares one with other agents. Triggered
+    /// checks are served before the ordinary checks and gathering transactions.
+    pub(crate) async fn pace(&self, triggered: bool) {
+        let pacers = std::iter::once(&self.pacer).chain(self.mux_pacer.as_deref());
+        for pacer in pacers {
+            if triggered {
+                pacer.wait_triggered().await;
+            } else {
+                pacer.wait().await;
+            }
+        }
+    }
+
+    /// Queues a triggered check on the pair (RFC 8445 Section 7.3.1.4), unless it is queued
+    /// already. Triggered checks are sent by their own task so that the inbound binding request
+    /// is answered right away.
+    pub(crate) fn enqueue_triggered_check(
+        &self,
+        local: &Arc<dyn Candidate + Send + Sync>,
+        remote: &Arc<dyn Candidate + Send + Sync>,
+    ) {
+        {
+            let mut triggered_checks = self.triggered_checks.lock();
+            if triggered_checks
+                .iter()
+                .any(|(l, r)| l.equal(&**local) && r.equal(&**remote))
+            {
+                return;
+            }
+            triggered_checks.push_back((Arc::clone(local), Arc::clone(remote)));
+        }
+
+        if let Some(triggered_check_tx) = &*self.triggered_check_tx.lock() {
+            let _ = triggered_check_tx.try_send(());
+        }
+    }
+
+    pub(crate) fn start_triggered_checks(
+        self: &Arc<Self>,
+        mut triggered_check_rx: mpsc::Receiver<()>,
+    ) {
+        let ai = Arc::clone(self);
+        self.tasks.spawn(async move {
+            // Stops once the agent is closed and the sender is dropped
+            while triggered_check_rx.recv().await.is_some() {
+                loop {
+                    let pair = ai.triggered_checks.lock().pop_front();
+                    let (local, remote) = match pair {
+                        Some(pair) => pair,
+                        None => break,
+                    };
+                    ai.pace(true).await;
+                    ai.ping_candidate(&local, &remote).await;
+                }
+            }
+        });
+    }
+
+    async fn all_candidate_pairs_failed(&self) -> bool {
+        let checklist = self.agent_conn.checklist.lock().await;
+        !checklist.is_empty()
+            && checklist
+                .iter()
+                .all(|p| p.state.load(Ordering::SeqCst) == CandidatePairState::Failed as u8)
+    }
+
+    /// host_network_cost returns the network cost of a host candidate on ip of interface.
+    pub(crate) fn host_network_cost(&self, interface: &str, ip: IpAddr) -> u16 {
+        (*self.network_cost)
+            .as_ref()
+            .map_or(0, |network_cost| network_cost(interface, ip))
+    }
+
+    /// renomination_enabled returns true if both agents support renomination.
+    pub(crate) fn renomination_enabled(&self) -> bool {
+        self.renomination && self.remote_options.lock().renomination
+    }
+
     pub(crate) async fn add_pair(
         &self,
         local: Arc<dyn Candidate + Send + Sync>,
@@ -441,8 +662,13 @@ impl AgentInternal {
             (*selected_pair).as_ref().map_or_else(
                 || (false, Duration::from_secs(0)),
                 |selected_pair| {
+                    // Prefer what was received on the pair itself, the remote candidate may
+                    // also be reached through a backup pair.
+                    let last_received = selected_pair
+                        .last_received()
+                        .unwrap_or_else(|| selected_pair.remote.last_received());
                     let disconnected_time = SystemTime::now()
-                        .duration_since(selected_pair.remote.last_received())
+                        .duration_since(last_received)
                         .unwrap_or_else(|_| Duration::from_secs(0));
                     (true, disconnected_time)
                 },
@@ -463,8 +689,15 @@ impl AgentInternal {
             } else if self.disconnected_timeout != Duration::from_secs(0)
                 && disconnected_time > self.disconnected_timeout
             {
-                self.update_connection_state(ConnectionState::Disconnected)
-                    .await;
+                // Consent on the selected pair is lost, move over to a backup pair that is
+                // still warm before giving up on the connection.
+                if self.fail_over_selected_pair().await {
+                    self.update_connection_state(ConnectionState::Connected)
+                        .await;
+                } else {
+                    self.update_connection_state(ConnectionState::Disconnected)
+                        .await;
+                }
             } else {
                 self.update_connection_state(ConnectionState::Connected)
                     .await;
@@ -478,25 +711,18 @@ impl AgentInternal {
     /// if no packet has been sent on that pair in the last keepaliveInterval.
     /// Note: the caller should hold the agent lock.
     pub(crate) async fn check_keepalive(&self) {
-        let (local, remote) = {
-            let selected_pair = self.agent_conn.selected_pair.load();
-            (*selected_pair)
-                .as_ref()
-                .map_or((None, None), |selected_pair| {
-                    (
-                        Some(selected_pair.local.clone()),
-                        Some(selected_pair.remote.clone()),
-                    )
-                })
-        };
-
-        if let (Some(local), Some(remote)) = (local, remote) {
+        if let Some(selected_pair) = self.agent_conn.get_selected_pair() {
+            let (local, remote) = (&selected_pair.local, &selected_pair.remote);
             let last_sent = SystemTime::now()
                 .duration_since(local.last_sent())
                 .unwrap_or_else(|_| Duration::from_secs(0));
 
             let last_received = SystemTime::now()
-                .duration_since(remote.last_received())
+                .duration_since(
+                    selected_pair
+                        .last_received()
+                        .unwrap_or_else(|| remote.last_received()),
+                )
                 .unwrap_or_else(|_| Duration::from_secs(0));
 
             if (self.keepalive_interval != Duration::from_secs(0))
@@ -505,8 +731,169 @@ impl AgentInternal {
             {
                 // we use binding request instead of indication to support refresh consent schemas
                 // see https://tools.ietf.org/html/rfc7675
-                self.ping_candidate(&local, &remote).await;
+                self.ping_candidate(local, remote).await;
+                selected_pair
+                    .consent_requests_sent
+                    .fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// Sends STUN Binding Indications (or requests, if configured) to every valid pair other than
+    /// the selected one, if no keepalive has been sent on that pair in the last
+    /// backupKeepaliveInterval. Pairs whose keepalives keep failing are marked as failed.
+    /// Note: the caller should hold the agent lock.
+    pub(crate) async fn check_backup_keepalive(&self) {
+        if self.backup_keepalive_interval == Duration::from_secs(0) {
+            return;
+        }
+
+        let selected_pair = self.agent_conn.get_selected_pair();
+        let now = SystemTime::now();
+
+        let pairs: Vec<Arc<CandidatePair>> = {
+            let checklist = self.agent_conn.checklist.lock().await;
+            checklist
+                .iter()
+                .filter(|p| {
+                    p.state.load(Ordering::SeqCst) == CandidatePairState::Succeeded as u8
+                        && selected_pair.as_ref().map_or(true, |s| **s != ***p)
+                        && p.last_keepalive().map_or(true, |t| {
+                            now.duration_since(t)
+                                .unwrap_or_else(|_| Duration::from_secs(0))
+                                >= self.backup_keepalive_interval
+                        })
+                })
+                .cloned()
+                .collect()
+        };
+
+        for p in pairs {
+            // A request is answered on the same pair, so nothing received since the previous
+            // keepalive means it went unanswered. Indications are never answered.
+            let unanswered = self.backup_keepalive_requests
+                && match (p.last_keepalive(), p.last_received()) {
+                    (Some(sent), Some(received)) => received < sent,
+                    (Some(_), None) => true,
+                    _ => false,
+                };
+
+            let sent = if self.backup_keepalive_requests {
+                self.ping_candidate(&p.local, &p.remote).await;
+                true
+            } else {
+                self.send_binding_indication(&p.local, &p.remote).await
+            };
+            p.set_last_keepalive(now);
+
+            if sent && !unanswered {
+                p.failed_keepalives.store(0, Ordering::SeqCst);
+            } else if p.failed_keepalives.fetch_add(1, Ordering::SeqCst) + 1
+                >= self.max_backup_keepalive_failures
+            {
+                log::debug!(
+                    "[{}]: backup keepalives keep failing for pair {}, marking it as failed",
+                    self.get_name(),
+                    p
+                );
+                p.state
+                    .store(CandidatePairState::Failed as u8, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// Selects the highest priority valid pair, other than the selected one, whose NAT bindings
+    /// are still open: it answered a keepalive request recently, or, as indications are never
+    /// answered, was sent an indication recently. Returns false if there is no such pair.
+    async fn fail_over_selected_pair(&self) -> bool {
+        if self.backup_keepalive_interval == Duration::from_secs(0) {
+            return false;
+        }
+
+        let selected_pair = self.agent_conn.get_selected_pair();
+        let now = SystemTime::now();
+        let max_idle = self.backup_keepalive_interval + self.disconnected_timeout;
+
+        let backup = {
+            let checklist = self.agent_conn.checklist.lock().await;
+            checklist
+                .iter()
+                .filter(|p| {
+                    p.state.load(Ordering::SeqCst) == CandidatePairState::Succeeded as u8
+                        && selected_pair.as_ref().map_or(true, |s| **s != ***p)
+                        && if self.backup_keepalive_requests {
+                            p.last_received()
+                        } else {
+                            p.last_received().max(p.last_keepalive())
+                        }
+                        .map_or(false, |t| {
+                            now.duration_since(t)
+                                .unwrap_or_else(|_| Duration::from_secs(0))
+                                <= max_idle
+                        })
+                })
+                .max_by_key(|p| p.priority())
+                .cloned()
+        };
+
+        if let Some(backup) = backup {
+            log::info!(
+                "[{}]: selected pair lost consent, failing over to {}",
+                self.get_name(),
+                backup
+            );
+            // The pair that lost consent must not be picked again as a backup, otherwise two
+            // pairs that only see backup keepalives would take turns being selected forever.
+            if let Some(selected_pair) = &selected_pair {
+                selected_pair
+                    .state
+                    .store(CandidatePairState::Failed as u8, Ordering::SeqCst);
             }
+            // From now on consent is checked on the backup, which gets a disconnected_timeout
+            // to answer the keepalive requests sent to the selected pair.
+            backup.set_last_received(now);
+            self.change_selected_pair(
+                Some(backup),
+                SelectedCandidatePairChangeReason::ConsentLossFailover,
+            )
+            .await;
+            true
+        } else {
+            false
+        }
+    }
+
+    async fn send_binding_indication(
+        &self,
+        local: &Arc<dyn Candidate + Send + Sync>,
+        remote: &Arc<dyn Candidate + Send + Sync>,
+    ) -> bool {
+        let mut msg = Message::new();
+        if let Err(err) = msg.build(&[
+            Box::new(MessageType::new(METHOD_BINDING, CLASS_INDICATION)),
+            Box::new(TransactionId::new()),
+            Box::new(FINGERPRINT),
+        ]) {
+            log::error!("{}", err);
+            return false;
+        }
+
+        log::trace!(
+            "[{}]: keepalive STUN from {} to {}",
+            self.get_name(),
+            local,
+            remote
+        );
+
+        if let Err(err) = local.write_to(&msg.raw, &**remote).await {
+            log::trace!(
+                "[{}]: failed to send STUN message: {}",
+                self.get_name(),
+                err
+            );
+            false
+        } else {
+            true
         }
     }
 
@@ -514,8 +901,44 @@ impl AgentInternal {
         let _ = self.force_candidate_contact_tx.try_send(true);
     }
 
+    /// Returns true and records it in the stats if remote candidate c is not allowed by
+    /// remote_candidate_types.
+    pub(crate) fn filter_remote_candidate(&self, c: &Arc<dyn Candidate + Send + Sync>) -> bool {
+        {
+            let remote_candidate_types = self.remote_candidate_types.lock();
+            if remote_candidate_types.is_empty()
+                || contains_candidate_type(c.candidate_type(), &remote_candidate_types)
+            {
+                return false;
+            }
+        }
+
+        log::debug!(
+            "[{}]: ignoring remote candidate of filtered type: {}",
+            self.get_name(),
+            c
+        );
+        self.filtered_remote_candidates
+            .fetch_add(1, Ordering::SeqCst);
+        true
+    }
+
     /// Assumes you are holding the lock (must be execute using a.run).
     pub(crate) async fn add_remote_candidate(&self, c: &Arc<dyn Candidate + Send + Sync>) {
+        if self.filter_remote_candidate(c) {
+            return;
+        }
+
+        self.insert_remote_candidate(c, None).await;
+    }
+
+    /// Adds remote candidate c without checking remote_candidate_types and pairs it with the
+    /// local candidates of local_type, or all of them if None.
+    async fn insert_remote_candidate(
+        &self,
+        c: &Arc<dyn Candidate + Send + Sync>,
+        local_type: Option<CandidateType>,
+    ) {
         let network_type = c.network_type();
 
         {
@@ -544,7 +967,12 @@ impl AgentInternal {
         }
 
         for cand in local_cands {
-            self.add_pair(cand, c.clone()).await;
+            if local_type.map_or(false, |t| cand.candidate_type() != t) {
+                continue;
+            }
+            if cand.tcp_type().can_pair_with(c.tcp_type()) {
+                self.add_pair(cand, c.clone()).await;
+            }
         }
 
         self.request_connectivity_check();
@@ -596,7 +1024,9 @@ impl AgentInternal {
         }
 
         for cand in remote_cands {
-            self.add_pair(c.clone(), cand).await;
+            if c.tcp_type().can_pair_with(cand.tcp_type()) {
+                self.add_pair(c.clone(), cand).await;
+            }
         }
 
         self.request_connectivity_check();
@@ -623,6 +1053,8 @@ impl AgentInternal {
             let mut started_ch_tx = self.started_ch_tx.lock().await;
             started_ch_tx.take();
         }
+        self.triggered_check_tx.lock().take();
+        self.triggered_checks.lock().clear();
 
         self.agent_conn.buffer.close().await;
 
@@ -728,6 +1160,9 @@ impl AgentInternal {
                 is_use_candidate: m.contains(ATTR_USE_CANDIDATE),
             });
         }
+        if let Some(p) = self.find_pair(local, remote).await {
+            p.requests_sent.fetch_add(1, Ordering::SeqCst);
+        }
 
         self.send_stun(m, local, remote).await;
     }
@@ -842,6 +1277,12 @@ impl AgentInternal {
             return;
         }
 
+        // Requests are authenticated and checked before looking at the role attributes, so that
+        // malformed requests are answered instead of being taken for a role conflict
+        if m.typ.class == CLASS_REQUEST && !self.validate_binding_request(m, local, remote).await {
+            return;
+        }
+
         if self.is_controlling.load(Ordering::SeqCst) {
             if m.contains(ATTR_ICE_CONTROLLING) {
                 log::debug!(
@@ -894,33 +1335,9 @@ impl AgentInternal {
                 return;
             }
         } else if m.typ.class == CLASS_REQUEST {
-            {
-                let ufrag_pwd = self.ufrag_pwd.lock().await;
-                let username =
-                    ufrag_pwd.local_ufrag.clone() + ":" + ufrag_pwd.remote_ufrag.as_str();
-                if let Err(err) = assert_inbound_username(m, &username) {
-                    log::warn!(
-                        "[{}]: discard message from ({}), {}",
-                        self.get_name(),
-                        remote,
-                        err
-                    );
-                    return;
-                } else if let Err(err) =
-                    assert_inbound_message_integrity(m, ufrag_pwd.local_pwd.as_bytes())
-                {
-                    log::warn!(
-                        "[{}]: discard message from ({}), {}",
-                        self.get_name(),
-                        remote,
-                        err
-                    );
-                    return;
-                }
-            }
-
             if remote_candidate.is_none() {
-                let (ip, port, network_type) = (remote.ip(), remote.port(), NetworkType::Udp4);
+                // Reached through the conn of the local candidate, so over the same network
+                let (ip, port, network_type) = (remote.ip(), remote.port(), local.network_type());
 
                 let prflx_candidate_config = CandidatePeerReflexiveConfig {
                     base_config: CandidateBaseConfig {
@@ -934,26 +1351,36 @@ impl AgentInternal {
                     rel_port: 0,
                 };
 
-                match prflx_candidate_config.new_candidate_peer_reflexive() {
-                    Ok(prflx_candidate) => remote_candidate = Some(Arc::new(prflx_candidate)),
-                    Err(err) => {
-                        log::error!(
-                            "[{}]: Failed to create new remote prflx candidate ({})",
-                            self.get_name(),
-                            err
-                        );
-                        return;
-                    }
-                };
+                let prflx_candidate: Arc<dyn Candidate + Send + Sync> =
+                    match prflx_candidate_config.new_candidate_peer_reflexive() {
+                        Ok(prflx_candidate) => Arc::new(prflx_candidate),
+                        Err(err) => {
+                            log::error!(
+                                "[{}]: Failed to create new remote prflx candidate ({})",
+                                self.get_name(),
+                                err
+                            );
+                            return;
+                        }
+                    };
 
                 log::debug!(
                     "[{}]: adding a new peer-reflexive candidate: {} ",
                     self.get_name(),
                     remote
                 );
-                if let Some(rc) = &remote_candidate {
-                    self.add_remote_candidate(rc).await;
+                if local.candidate_type() == CandidateType::Relay {
+                    // The check came in through our relay, so answering it only ever uses
+                    // relayed paths on our side, whatever the allow list says.
+                    self.insert_remote_candidate(&prflx_candidate, Some(CandidateType::Relay))
+                        .await;
+                } else if self.filter_remote_candidate(&prflx_candidate) {
+                    // Don't answer checks from peers we are not allowed to pair with.
+                    return;
+                } else {
+                    self.insert_remote_candidate(&prflx_candidate, None).await;
                 }
+                remote_candidate = Some(prflx_candidate);
             }
 
             log::trace!(
@@ -970,6 +1397,103 @@ impl AgentInternal {
 
         if let Some(rc) = remote_candidate {
             rc.seen(false);
+            // Indications carry no MESSAGE-INTEGRITY, so anyone could spoof them to keep a dead
+            // pair looking alive. Only authenticated requests and responses count as consent.
+            if m.typ.class != CLASS_INDICATION {
+                if let Some(p) = self.find_pair(local, &rc).await {
+                    p.set_last_received(SystemTime::now());
+                }
+            }
+        }
+    }
+
+    /// Authenticates an inbound connectivity check and checks its ICE attributes, returning false
+    /// if it is rejected. Unauthenticated requests are discarded and malformed ones answered with
+    /// 400 (Bad Request).
+    async fn validate_binding_request(
+        &self,
+        m: &mut Message,
+        local: &Arc<dyn Candidate + Send + Sync>,
+        remote: SocketAddr,
+    ) -> bool {
+        let local_pwd = {
+            let ufrag_pwd = self.ufrag_pwd.lock().await;
+            let username = ufrag_pwd.local_ufrag.clone() + ":" + ufrag_pwd.remote_ufrag.as_str();
+            let result = assert_inbound_username(m, &username)
+                .and_then(|_| assert_inbound_message_integrity(m, ufrag_pwd.local_pwd.as_bytes()));
+            if let Err(err) = result {
+                log::warn!(
+                    "[{}]: discard message from ({}), {}",
+                    self.get_name(),
+                    remote,
+                    err
+                );
+                self.rejected_binding_requests.lock().unauthenticated += 1;
+                return false;
+            }
+            ufrag_pwd.local_pwd.clone()
+        };
+
+        if let Err(err) = assert_inbound_binding_request(m) {
+            log::warn!(
+                "[{}]: reject binding request from ({}), {}",
+                self.get_name(),
+                remote,
+                err
+            );
+            {
+                let mut rejected = self.rejected_binding_requests.lock();
+                match err {
+                    Error::ErrMissingPriority => rejected.missing_priority += 1,
+                    Error::ErrMissingRole => rejected.missing_role += 1,
+                    Error::ErrConflictingRoles => rejected.conflicting_roles += 1,
+                    _ => rejected.use_candidate_from_controlled += 1,
+                }
+            }
+            self.send_bad_request(m, local, remote, &err, local_pwd)
+                .await;
+            return false;
+        }
+
+        true
+    }
+
+    /// Answers a request with 400 (Bad Request), giving the error as reason.
+    async fn send_bad_request(
+        &self,
+        m: &Message,
+        local: &Arc<dyn Candidate + Send + Sync>,
+        remote: SocketAddr,
+        err: &Error,
+        local_pwd: String,
+    ) {
+        let mut out = Message::new();
+        let result = out.build(&[
+            Box::new(m.clone()),
+            Box::new(BINDING_ERROR),
+            Box::new(ErrorCodeAttribute {
+                code: CODE_BAD_REQUEST,
+                reason: err.to_string().into_bytes(),
+            }),
+            Box::new(MessageIntegrity::new_short_term_integrity(local_pwd)),
+            Box::new(FINGERPRINT),
+        ]);
+
+        if let Err(err) = result {
+            log::warn!(
+                "[{}]: Failed to build error response to: {} error: {}",
+                self.get_name(),
+                remote,
+                err
+            );
+        } else if let Some(conn) = local.get_conn() {
+            if let Err(err) = conn.send_to(&out.raw, remote).await {
+                log::trace!(
+                    "[{}]: failed to send STUN message: {}",
+                    self.get_name(),
+                    err
+                );
+            }
         }
     }
 
@@ -984,6 +1508,11 @@ impl AgentInternal {
             .await
             .map_or(false, |remote_candidate| {
                 remote_candidate.seen(false);
+                if let Some(p) = self.agent_conn.get_selected_pair() {
+                    if p.local.equal(&**local) && p.remote.equal(&*remote_candidate) {
+                        p.set_last_received(SystemTime::now());
+                    }
+                }
                 true
             })
     }
@@ -1039,7 +1568,7 @@ impl AgentInternal {
             let conn = Arc::clone(conn);
             let addr = candidate.addr();
             let ai = Arc::clone(self);
-            tokio::spawn(async move {
+            self.tasks.spawn(async move {
                 let _ = ai
                     .recv_loop(cand, closed_ch_rx, initialized_ch, conn, addr)
                     .await;
@@ -1053,25 +1582,22 @@ impl AgentInternal {
         self: &Arc<Self>,
         mut chan_state_rx: mpsc::Receiver<ConnectionState>,
         mut chan_candidate_rx: mpsc::Receiver<Option<Arc<dyn Candidate + Send + Sync>>>,
-        mut chan_candidate_pair_rx: mpsc::Receiver<()>,
+        mut chan_candidate_pair_rx: mpsc::Receiver<SelectedCandidatePairChange>,
     ) {
         let ai = Arc::clone(self);
-        tokio::spawn(async move {
+        self.tasks.spawn(async move {
             // CandidatePair and ConnectionState are usually changed at once.
             // Blocking one by the other one causes deadlock.
-            while chan_candidate_pair_rx.recv().await.is_some() {
-                if let (Some(cb), Some(p)) = (
-                    &*ai.on_selected_candidate_pair_change_hdlr.load(),
-                    &*ai.agent_conn.selected_pair.load(),
-                ) {
+            while let Some(change) = chan_candidate_pair_rx.recv().await {
+                if let Some(cb) = &*ai.on_selected_candidate_pair_change_hdlr.load() {
                     let mut f = cb.lock().await;
-                    f(&p.local, &p.remote).await;
+                    f(&change).await;
                 }
             }
         });
 
         let ai = Arc::clone(self);
-        tokio::spawn(async move {
+        self.tasks.spawn(async move {
             loop {
                 tokio::select! {
                     opt_state = chan_state_rx.recv() => {
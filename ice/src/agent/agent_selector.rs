@@ -4,7 +4,7 @@ use crate::control::*;
 use crate::priority::*;
 use crate::use_candidate::*;
 
-use stun::{agent::*, attributes::*, fingerprint::*, integrity::*, message::*, textattrs::*};
+use stun::{attributes::*, integrity::*, message::*};
 
 use async_trait::async_trait;
 use std::net::SocketAddr;
@@ -61,6 +61,59 @@ trait ControlledSelector {
 }
 
 impl AgentInternal {
+    /// renominate_pair nominates the best valid pair if it is better than the selected pair, see
+    /// `AgentConn::is_better_pair`, and stayed so for the renomination hold time. It must only be
+    /// used if both agents support renomination.
+    pub(crate) async fn renominate_pair(&self) {
+        let selected_pair = match self.agent_conn.get_selected_pair() {
+            Some(p) => p,
+            None => return,
+        };
+        let best_pair = match self.agent_conn.get_best_valid_candidate_pair().await {
+            Some(p) => p,
+            None => return,
+        };
+
+        if best_pair == selected_pair
+            || !self.agent_conn.is_better_pair(&best_pair, &selected_pair)
+            || !self.is_nominatable(&best_pair.local)
+            || !self.is_nominatable(&best_pair.remote)
+        {
+            *self.renomination_candidate.lock() = None;
+            return;
+        }
+
+        if self.renomination_hold_time > Duration::from_secs(0) {
+            let now = Instant::now();
+            let mut renomination_candidate = self.renomination_candidate.lock();
+            match &*renomination_candidate {
+                Some((p, since)) if *p == best_pair => {
+                    if now.duration_since(*since) < self.renomination_hold_time {
+                        return;
+                    }
+                }
+                _ => {
+                    *renomination_candidate = Some((Arc::clone(&best_pair), now));
+                    return;
+                }
+            }
+            *renomination_candidate = None;
+        }
+
+        log::trace!(
+            "[{}]: renominating ({}, {})",
+            self.get_name(),
+            best_pair.local,
+            best_pair.remote
+        );
+        best_pair.nominated.store(true, Ordering::SeqCst);
+        {
+            let mut nominated_pair = self.nominated_pair.lock().await;
+            *nominated_pair = Some(best_pair);
+        }
+        self.nominate_pair().await;
+    }
+
     fn is_nominatable(&self, c: &Arc<dyn Candidate + Send + Sync>) -> bool {
         let start_time = *self.start_time.lock();
         match c.candidate_type() {
@@ -111,38 +164,37 @@ impl AgentInternal {
                 // agent MUST NOT include the USE-CANDIDATE attribute in a Binding
                 // request.
 
-                let (msg, result) = {
+                let result = {
                     let ufrag_pwd = self.ufrag_pwd.lock().await;
                     let username =
                         ufrag_pwd.remote_ufrag.clone() + ":" + ufrag_pwd.local_ufrag.as_str();
-                    let mut msg = Message::new();
-                    let result = msg.build(&[
-                        Box::new(BINDING_REQUEST),
-                        Box::new(TransactionId::new()),
-                        Box::new(Username::new(ATTR_USERNAME, username)),
-                        Box::new(UseCandidateAttr::default()),
-                        Box::new(AttrControlling(self.tie_breaker.load(Ordering::SeqCst))),
-                        Box::new(PriorityAttr(pair.local.priority())),
-                        Box::new(MessageIntegrity::new_short_term_integrity(
+                    Message::binding_request()
+                        .with_username(username)
+                        .with(UseCandidateAttr)
+                        .with(AttrControlling(self.tie_breaker.load(Ordering::SeqCst)))
+                        .with(PriorityAttr::for_check_from(&*pair.local))
+                        .with_integrity(MessageIntegrity::new_short_term_integrity(
                             ufrag_pwd.remote_pwd.clone(),
-                        )),
-                        Box::new(FINGERPRINT),
-                    ]);
-                    (msg, result)
+                        ))
+                        .with_fingerprint()
+                        .build()
                 };
 
-                if let Err(err) = result {
-                    log::error!("{}", err);
-                    None
-                } else {
-                    log::trace!(
-                        "ping STUN (nominate candidate pair from {} to {}",
-                        pair.local,
-                        pair.remote
-                    );
-                    let local = pair.local.clone();
-                    let remote = pair.remote.clone();
-                    Some((msg, local, remote))
+                match result {
+                    Ok(msg) => {
+                        log::trace!(
+                            "ping STUN (nominate candidate pair from {} to {}",
+                            pair.local,
+                            pair.remote
+                        );
+                        let local = pair.local.clone();
+                        let remote = pair.remote.clone();
+                        Some((msg, local, remote))
+                    }
+                    Err(err) => {
+                        log::error!("{}", err);
+                        None
+                    }
                 }
             } else {
                 None
@@ -236,6 +288,15 @@ impl ControllingSelector for AgentInternal {
             if self.validate_selected_pair().await {
                 log::trace!("[{}]: checking keepalive", self.get_name());
                 self.check_keepalive().await;
+                self.check_backup_keepalive().await;
+                if self.renomination_enabled() {
+                    if self.agent_conn.prefer_low_cost {
+                        // Pairs formed after the selection, e.g. on a network that came up, are
+                        // still checked, they may be renominated for their lower cost
+                        self.ping_all_candidates().await;
+                    }
+                    self.renominate_pair().await;
+                }
             }
         } else if nominated_pair_is_some {
             self.nominate_pair().await;
@@ -273,28 +334,23 @@ impl ControllingSelector for AgentInternal {
         local: &Arc<dyn Candidate + Send + Sync>,
         remote: &Arc<dyn Candidate + Send + Sync>,
     ) {
-        let (msg, result) = {
+        let result = {
             let ufrag_pwd = self.ufrag_pwd.lock().await;
             let username = ufrag_pwd.remote_ufrag.clone() + ":" + ufrag_pwd.local_ufrag.as_str();
-            let mut msg = Message::new();
-            let result = msg.build(&[
-                Box::new(BINDING_REQUEST),
-                Box::new(TransactionId::new()),
-                Box::new(Username::new(ATTR_USERNAME, username)),
-                Box::new(AttrControlling(self.tie_breaker.load(Ordering::SeqCst))),
-                Box::new(PriorityAttr(local.priority())),
-                Box::new(MessageIntegrity::new_short_term_integrity(
+            Message::binding_request()
+                .with_username(username)
+                .with(AttrControlling(self.tie_breaker.load(Ordering::SeqCst)))
+                .with(PriorityAttr::for_check_from(&**local))
+                .with_integrity(MessageIntegrity::new_short_term_integrity(
                     ufrag_pwd.remote_pwd.clone(),
-                )),
-                Box::new(FINGERPRINT),
-            ]);
-            (msg, result)
+                ))
+                .with_fingerprint()
+                .build()
         };
 
-        if let Err(err) = result {
-            log::error!("{}", err);
-        } else {
-            self.send_binding_request(&msg, local, remote).await;
+        match result {
+            Ok(msg) => self.send_binding_request(&msg, local, remote).await,
+            Err(err) => log::error!("{}", err),
         }
     }
 
@@ -323,6 +379,7 @@ impl ControllingSelector for AgentInternal {
             let selected_pair_is_none = self.agent_conn.get_selected_pair().is_none();
 
             if let Some(p) = self.find_pair(local, remote).await {
+                p.add_round_trip_time(pending_request.timestamp.elapsed());
                 p.state
                     .store(CandidatePairState::Succeeded as u8, Ordering::SeqCst);
                 log::trace!(
@@ -332,8 +389,16 @@ impl ControllingSelector for AgentInternal {
                     pending_request.is_use_candidate,
                     selected_pair_is_none
                 );
-                if pending_request.is_use_candidate && selected_pair_is_none {
-                    self.set_selected_pair(Some(Arc::clone(&p))).await;
+                if pending_request.is_use_candidate
+                    && (selected_pair_is_none || self.renomination_enabled())
+                {
+                    let is_selected = self
+                        .agent_conn
+                        .get_selected_pair()
+                        .map_or(false, |selected| selected == p);
+                    if !is_selected {
+                        self.set_selected_pair(Some(Arc::clone(&p))).await;
+                    }
                 }
             } else {
                 // This shouldn't happen
@@ -414,6 +479,7 @@ impl ControlledSelector for AgentInternal {
             if self.validate_selected_pair().await {
                 log::trace!("[{}]: checking keepalive", self.get_name());
                 self.check_keepalive().await;
+                self.check_backup_keepalive().await;
             }
         } else {
             self.ping_all_candidates().await;
@@ -425,28 +491,23 @@ impl ControlledSelector for AgentInternal {
         local: &Arc<dyn Candidate + Send + Sync>,
         remote: &Arc<dyn Candidate + Send + Sync>,
     ) {
-        let (msg, result) = {
+        let result = {
             let ufrag_pwd = self.ufrag_pwd.lock().await;
             let username = ufrag_pwd.remote_ufrag.clone() + ":" + ufrag_pwd.local_ufrag.as_str();
-            let mut msg = Message::new();
-            let result = msg.build(&[
-                Box::new(BINDING_REQUEST),
-                Box::new(TransactionId::new()),
-                Box::new(Username::new(ATTR_USERNAME, username)),
-                Box::new(AttrControlled(self.tie_breaker.load(Ordering::SeqCst))),
-                Box::new(PriorityAttr(local.priority())),
-                Box::new(MessageIntegrity::new_short_term_integrity(
+            Message::binding_request()
+                .with_username(username)
+                .with(AttrControlled(self.tie_breaker.load(Ordering::SeqCst)))
+                .with(PriorityAttr::for_check_from(&**local))
+                .with_integrity(MessageIntegrity::new_short_term_integrity(
                     ufrag_pwd.remote_pwd.clone(),
-                )),
-                Box::new(FINGERPRINT),
-            ]);
-            (msg, result)
+                ))
+                .with_fingerprint()
+                .build()
         };
 
-        if let Err(err) = result {
-            log::error!("{}", err);
-        } else {
-            self.send_binding_request(&msg, local, remote).await;
+        match result {
+            Ok(msg) => self.send_binding_request(&msg, local, remote).await,
+            Err(err) => log::error!("{}", err),
         }
     }
 
@@ -480,6 +541,7 @@ impl ControlledSelector for AgentInternal {
             );
 
             if let Some(p) = self.find_pair(local, remote).await {
+                p.add_round_trip_time(pending_request.timestamp.elapsed());
                 p.state
                     .store(CandidatePairState::Succeeded as u8, Ordering::SeqCst);
                 log::trace!("Found valid candidate pair: {}", p);
@@ -516,8 +578,15 @@ impl ControlledSelector for AgentInternal {
                     // previously sent by this pair produced a successful response and
                     // generated a valid pair (Section 7.2.5.3.2).  The agent sets the
                     // nominated flag value of the valid pair to true.
-                    if self.agent_conn.get_selected_pair().is_none() {
-                        self.set_selected_pair(Some(Arc::clone(&p))).await;
+                    // A later nomination of another pair is only followed if both
+                    // agents support renomination.
+                    match self.agent_conn.get_selected_pair() {
+                        None => self.set_selected_pair(Some(Arc::clone(&p))).await,
+                        Some(selected) if selected != p && self.renomination_enabled() => {
+                            log::trace!("[{}]: pair renominated: {}", self.get_name(), p);
+                            self.set_selected_pair(Some(Arc::clone(&p))).await;
+                        }
+                        _ => {}
                     }
                     self.send_binding_success(m, local, remote).await;
                 } else {
@@ -529,11 +598,11 @@ impl ControlledSelector for AgentInternal {
                     // MUST remove the candidate pair from the valid list, set the
                     // candidate pair state to Failed, and set the checklist state to
                     // Failed.
-                    self.ping_candidate(local, remote).await;
+                    self.enqueue_triggered_check(local, remote);
                 }
             } else {
                 self.send_binding_success(m, local, remote).await;
-                self.ping_candidate(local, remote).await;
+                self.enqueue_triggered_check(local, remote);
             }
         }
     }
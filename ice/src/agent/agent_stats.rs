@@ -1,10 +1,16 @@
-use crate::candidate::{CandidatePairState, CandidateType};
+use crate::candidate::{CandidatePair, CandidatePairState, CandidateType};
 
 use crate::agent::agent_internal::AgentInternal;
 use crate::network_type::NetworkType;
+use std::fmt;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::SystemTime;
 use tokio::time::Instant;
 
+/// The number of selected candidate pair changes the agent keeps in its history.
+pub const MAX_SELECTED_CANDIDATE_PAIR_CHANGES: usize = 16;
+
 /// Contains ICE candidate pair statistics.
 pub struct CandidatePairStats {
     /// The timestamp associated with this struct.
@@ -19,6 +25,10 @@ pub struct CandidatePairStats {
     /// The state of the checklist for the local and remote candidates in a pair.
     pub state: CandidatePairState,
 
+    /// The network of the local candidate, which tells the transport protocol (UDP or TCP)
+    /// the pair sends over.
+    pub network_type: NetworkType,
+
     /// It is true when this valid pair that should be used for media,
     /// if it is the highest-priority one amongst those whose nominated flag is set.
     pub nominated: bool,
@@ -110,6 +120,10 @@ pub struct CandidatePairStats {
 
     /// The timestamp at which the latest valid STUN binding response expired.
     pub consent_expired_timestamp: Instant,
+
+    /// The time at which the last backup keepalive was sent on this candidate pair, if any. Only
+    /// valid pairs other than the selected one are kept alive this way.
+    pub last_keepalive_timestamp: Option<SystemTime>,
 }
 
 impl Default for CandidatePairStats {
@@ -119,6 +133,7 @@ impl Default for CandidatePairStats {
             local_candidate_id: String::new(),
             remote_candidate_id: String::new(),
             state: CandidatePairState::default(),
+            network_type: NetworkType::Unspecified,
             nominated: false,
             packets_sent: 0,
             packets_received: 0,
@@ -142,6 +157,7 @@ impl Default for CandidatePairStats {
             retransmissions_sent: 0,
             consent_requests_sent: 0,
             consent_expired_timestamp: Instant::now(),
+            last_keepalive_timestamp: None,
         }
     }
 }
@@ -211,6 +227,69 @@ impl Default for CandidateStats {
     }
 }
 
+/// Contains the number of inbound connectivity check requests the agent rejected, by reason.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RejectedBindingRequestStats {
+    /// Requests with a wrong USERNAME or MESSAGE-INTEGRITY, discarded without a response.
+    pub unauthenticated: u64,
+
+    /// Requests without a valid PRIORITY, answered with 400 (Bad Request).
+    pub missing_priority: u64,
+
+    /// Requests with neither ICE-CONTROLLING nor ICE-CONTROLLED, answered with 400 (Bad Request).
+    pub missing_role: u64,
+
+    /// Requests with both ICE-CONTROLLING and ICE-CONTROLLED, answered with 400 (Bad Request).
+    pub conflicting_roles: u64,
+
+    /// Requests with USE-CANDIDATE from the controlled agent, answered with 400 (Bad Request).
+    pub use_candidate_from_controlled: u64,
+}
+
+/// Tells why the selected candidate pair changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectedCandidatePairChangeReason {
+    /// The first pair was nominated.
+    InitialNomination,
+    /// A better pair was nominated while another one was selected, see
+    /// `AgentConfig::renomination`.
+    Renomination,
+    /// The selected pair lost consent and a backup pair took over, see
+    /// `AgentConfig::backup_keepalive_interval`.
+    ConsentLossFailover,
+    /// A pair was nominated after an ICE restart.
+    Restart,
+}
+
+impl fmt::Display for SelectedCandidatePairChangeReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match *self {
+            SelectedCandidatePairChangeReason::InitialNomination => "initial-nomination",
+            SelectedCandidatePairChangeReason::Renomination => "renomination",
+            SelectedCandidatePairChangeReason::ConsentLossFailover => "consent-loss-failover",
+            SelectedCandidatePairChangeReason::Restart => "restart",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Describes a change of the selected candidate pair.
+#[derive(Debug, Clone)]
+pub struct SelectedCandidatePairChange {
+    /// The pair selected before, None for the initial nomination. After an ICE restart it is the
+    /// pair selected before the restart.
+    pub previous: Option<Arc<CandidatePair>>,
+
+    /// The pair selected now.
+    pub current: Arc<CandidatePair>,
+
+    /// Why the selected pair changed.
+    pub reason: SelectedCandidatePairChangeReason,
+
+    /// When the selected pair changed.
+    pub timestamp: SystemTime,
+}
+
 impl AgentInternal {
     /// Returns a list of candidate pair stats.
     pub(crate) async fn get_candidate_pairs_stats(&self) -> Vec<CandidatePairStats> {
@@ -222,7 +301,16 @@ impl AgentInternal {
                 local_candidate_id: cp.local.id(),
                 remote_candidate_id: cp.remote.id(),
                 state: cp.state.load(Ordering::SeqCst).into(),
+                network_type: cp.local.network_type(),
                 nominated: cp.nominated.load(Ordering::SeqCst),
+                total_round_trip_time: cp.total_round_trip_time().as_secs_f64(),
+                current_round_trip_time: cp
+                    .current_round_trip_time()
+                    .map_or(0.0, |rtt| rtt.as_secs_f64()),
+                requests_sent: cp.requests_sent(),
+                responses_received: cp.responses_received(),
+                consent_requests_sent: cp.consent_requests_sent.load(Ordering::SeqCst),
+                last_keepalive_timestamp: cp.last_keepalive(),
                 ..CandidatePairStats::default()
             };
             res.push(stat);
@@ -5,8 +5,10 @@ use crate::candidate::candidate_host::*;
 use crate::candidate::candidate_peer_reflexive::*;
 use crate::candidate::candidate_relay::*;
 use crate::candidate::candidate_server_reflexive::*;
-use crate::control::AttrControlling;
+use crate::control::{AttrControlled, AttrControlling};
+use crate::options::IceOptions;
 use crate::priority::PriorityAttr;
+use crate::udp_mux::{UDPMuxDefault, UDPMuxParams};
 use crate::use_candidate::UseCandidateAttr;
 
 use crate::agent::agent_transport_test::pipe;
@@ -14,8 +16,10 @@ use async_trait::async_trait;
 use std::net::Ipv4Addr;
 use std::ops::Sub;
 use std::str::FromStr;
+use stun::error_code::{ErrorCodeAttribute, CODE_BAD_REQUEST};
 use stun::message::*;
 use stun::textattrs::Username;
+use tokio::net::UdpSocket;
 use util::{vnet::*, Conn};
 use waitgroup::{WaitGroup, Worker};
 
@@ -190,7 +194,7 @@ async fn test_on_selected_candidate_pair_change() -> Result<()> {
     let a = Agent::new(AgentConfig::default()).await?;
     let (callback_called_tx, mut callback_called_rx) = mpsc::channel::<()>(1);
     let callback_called_tx = Arc::new(Mutex::new(Some(callback_called_tx)));
-    let cb: OnSelectedCandidatePairChangeHdlrFn = Box::new(move |_, _| {
+    let cb: OnSelectedCandidatePairChangeHdlrFn = Box::new(move |_| {
         let callback_called_tx_clone = Arc::clone(&callback_called_tx);
         Box::pin(async move {
             let mut tx = callback_called_tx_clone.lock().await;
@@ -240,6 +244,88 @@ async fn test_on_selected_candidate_pair_change() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_selected_candidate_pair_change_reasons() -> Result<()> {
+    let a = Agent::new(AgentConfig::default()).await?;
+    let (changes_tx, mut changes_rx) = mpsc::unbounded_channel();
+    a.on_selected_candidate_pair_change(Box::new(move |change: &SelectedCandidatePairChange| {
+        let _ = changes_tx.send(change.clone());
+        Box::pin(async {})
+    }));
+
+    let new_pair = |port: u16| -> Result<Arc<CandidatePair>> {
+        let local = CandidateHostConfig {
+            base_config: CandidateBaseConfig {
+                network: "udp".to_owned(),
+                address: "192.168.1.1".to_owned(),
+                port,
+                component: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .new_candidate_host()?;
+        let remote = CandidateHostConfig {
+            base_config: CandidateBaseConfig {
+                network: "udp".to_owned(),
+                address: "192.168.1.2".to_owned(),
+                port,
+                component: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .new_candidate_host()?;
+        Ok(Arc::new(CandidatePair::new(
+            Arc::new(local),
+            Arc::new(remote),
+            true,
+        )))
+    };
+    let (p0, p1, p2) = (new_pair(1000)?, new_pair(1001)?, new_pair(1002)?);
+
+    a.internal.set_selected_pair(Some(Arc::clone(&p0))).await;
+    a.internal.set_selected_pair(Some(Arc::clone(&p1))).await;
+    // An ICE restart clears the selected pair
+    a.internal.set_selected_pair(None).await;
+    a.internal.set_selected_pair(Some(Arc::clone(&p2))).await;
+
+    let mut changes = vec![];
+    for _ in 0..3 {
+        changes.push(changes_rx.recv().await.unwrap());
+    }
+    let summary: Vec<(Option<u16>, u16, SelectedCandidatePairChangeReason)> = changes
+        .iter()
+        .map(|change| {
+            (
+                change.previous.as_ref().map(|p| p.local.port()),
+                change.current.local.port(),
+                change.reason,
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (
+                None,
+                1000,
+                SelectedCandidatePairChangeReason::InitialNomination
+            ),
+            (
+                Some(1000),
+                1001,
+                SelectedCandidatePairChangeReason::Renomination
+            ),
+            (Some(1001), 1002, SelectedCandidatePairChangeReason::Restart),
+        ]
+    );
+    assert_eq!(a.get_selected_candidate_pair_changes().len(), 3);
+
+    a.close().await?;
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_handle_peer_reflexive_udp_pflx_candidate() -> Result<()> {
     let a = Agent::new(AgentConfig::default()).await?;
@@ -323,25 +409,12 @@ async fn test_handle_peer_reflexive_udp_pflx_candidate() -> Result<()> {
 }
 
 #[tokio::test]
-async fn test_handle_peer_reflexive_unknown_remote() -> Result<()> {
-    let a = Agent::new(AgentConfig::default()).await?;
-
-    let mut tid = TransactionId::default();
-    tid.0[..3].copy_from_slice("ABC".as_bytes());
-
-    let remote_pwd = {
-        {
-            let mut pending_binding_requests = a.internal.pending_binding_requests.lock().await;
-            *pending_binding_requests = vec![BindingRequest {
-                timestamp: Instant::now(),
-                transaction_id: tid,
-                destination: SocketAddr::from_str("0.0.0.0:0")?,
-                is_use_candidate: false,
-            }];
-        }
-        let ufrag_pwd = a.internal.ufrag_pwd.lock().await;
-        ufrag_pwd.remote_pwd.clone()
-    };
+async fn test_remote_candidate_types_filter() -> Result<()> {
+    let a = Agent::new(AgentConfig {
+        remote_candidate_types: vec![CandidateType::Relay],
+        ..Default::default()
+    })
+    .await?;
 
     let host_config = CandidateHostConfig {
         base_config: CandidateBaseConfig {
@@ -354,102 +427,444 @@ async fn test_handle_peer_reflexive_unknown_remote() -> Result<()> {
         },
         ..Default::default()
     };
-
     let local: Arc<dyn Candidate + Send + Sync> = Arc::new(host_config.new_candidate_host()?);
-    let remote = SocketAddr::from_str("172.17.0.3:999")?;
+    {
+        let mut local_candidates = a.internal.local_candidates.lock().await;
+        local_candidates.insert(local.network_type(), vec![local.clone()]);
+    }
+
+    let host_remote: Arc<dyn Candidate + Send + Sync> = Arc::new(
+        CandidateHostConfig {
+            base_config: CandidateBaseConfig {
+                network: "udp".to_owned(),
+                address: "192.168.0.3".to_owned(),
+                port: 888,
+                component: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .new_candidate_host()?,
+    );
+    a.internal.add_remote_candidate(&host_remote).await;
 
+    // Inbound checks from an unknown address would create a prflx candidate, which is filtered too.
+    let (username, local_pwd, tie_breaker) = {
+        let ufrag_pwd = a.internal.ufrag_pwd.lock().await;
+        (
+            ufrag_pwd.local_ufrag.to_owned() + ":" + ufrag_pwd.remote_ufrag.as_str(),
+            ufrag_pwd.local_pwd.clone(),
+            a.internal.tie_breaker.load(Ordering::SeqCst),
+        )
+    };
     let mut msg = Message::new();
     msg.build(&[
-        Box::new(BINDING_SUCCESS),
-        Box::new(tid),
-        Box::new(MessageIntegrity::new_short_term_integrity(remote_pwd)),
+        Box::new(BINDING_REQUEST),
+        Box::new(TransactionId::new()),
+        Box::new(Username::new(ATTR_USERNAME, username)),
+        Box::new(UseCandidateAttr::new()),
+        Box::new(AttrControlling(tie_breaker)),
+        Box::new(PriorityAttr(local.priority())),
+        Box::new(MessageIntegrity::new_short_term_integrity(local_pwd)),
         Box::new(FINGERPRINT),
     ])?;
+    a.internal
+        .handle_inbound(&mut msg, &local, SocketAddr::from_str("172.17.0.3:999")?)
+        .await;
 
     {
-        a.internal.handle_inbound(&mut msg, &local, remote).await;
-
         let remote_candidates = a.internal.remote_candidates.lock().await;
-        assert_eq!(
-            remote_candidates.len(),
-            0,
-            "unknown remote was able to create a candidate"
+        assert!(
+            remote_candidates.is_empty(),
+            "non-relay remote candidates must be ignored"
         );
+        let checklist = a.internal.agent_conn.checklist.lock().await;
+        assert!(checklist.is_empty(), "no pair should have been formed");
     }
+    assert_eq!(a.get_filtered_remote_candidates(), 2);
 
-    let _ = a.close().await?;
+    let relay_remote: Arc<dyn Candidate + Send + Sync> = Arc::new(
+        CandidateRelayConfig {
+            base_config: CandidateBaseConfig {
+                network: "udp".to_owned(),
+                address: "1.2.3.4".to_owned(),
+                port: 12340,
+                component: 1,
+                ..Default::default()
+            },
+            rel_addr: "4.3.2.1".to_owned(),
+            rel_port: 43210,
+            ..Default::default()
+        }
+        .new_candidate_relay()?,
+    );
+    a.internal.add_remote_candidate(&relay_remote).await;
+
+    {
+        let checklist = a.internal.agent_conn.checklist.lock().await;
+        assert_eq!(checklist.len(), 1, "relay remote candidate must be paired");
+    }
+    assert_eq!(a.get_filtered_remote_candidates(), 2);
+
+    a.close().await?;
     Ok(())
 }
 
-//use std::io::Write;
-
-// Assert that Agent on startup sends message, and doesn't wait for connectivityTicker to fire
 #[tokio::test]
-async fn test_connectivity_on_startup() -> Result<()> {
-    /*env_logger::Builder::new()
-    .format(|buf, record| {
-        writeln!(
-            buf,
-            "{}:{} [{}] {} - {}",
-            record.file().unwrap_or("unknown"),
-            record.line().unwrap_or(0),
-            record.level(),
-            chrono::Local::now().format("%H:%M:%S.%6f"),
-            record.args()
-        )
+async fn test_remote_candidate_types_keep_prflx_from_relay() -> Result<()> {
+    let a = Agent::new(AgentConfig {
+        remote_candidate_types: vec![CandidateType::Relay],
+        ..Default::default()
     })
-    .filter(None, log::LevelFilter::Trace)
-    .init();*/
+    .await?;
 
-    // Create a network with two interfaces
-    let wan = Arc::new(Mutex::new(router::Router::new(router::RouterConfig {
-        cidr: "0.0.0.0/0".to_owned(),
-        ..Default::default()
-    })?));
+    let host_local: Arc<dyn Candidate + Send + Sync> = Arc::new(
+        CandidateHostConfig {
+            base_config: CandidateBaseConfig {
+                network: "udp".to_owned(),
+                address: "192.168.0.2".to_owned(),
+                port: 777,
+                component: 1,
+                conn: Some(Arc::new(MockConn {})),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .new_candidate_host()?,
+    );
+    let relay_local: Arc<dyn Candidate + Send + Sync> = Arc::new(
+        CandidateRelayConfig {
+            base_config: CandidateBaseConfig {
+                network: "udp".to_owned(),
+                address: "1.2.3.4".to_owned(),
+                port: 12340,
+                component: 1,
+                conn: Some(Arc::new(MockConn {})),
+                ..Default::default()
+            },
+            rel_addr: "4.3.2.1".to_owned(),
+            rel_port: 43210,
+            ..Default::default()
+        }
+        .new_candidate_relay()?,
+    );
+    {
+        let mut local_candidates = a.internal.local_candidates.lock().await;
+        local_candidates.insert(
+            relay_local.network_type(),
+            vec![host_local.clone(), relay_local.clone()],
+        );
+    }
 
-    let net0 = Arc::new(net::Net::new(Some(net::NetConfig {
-        static_ips: vec!["192.168.0.1".to_owned()],
-        ..Default::default()
-    })));
-    let net1 = Arc::new(net::Net::new(Some(net::NetConfig {
-        static_ips: vec!["192.168.0.2".to_owned()],
-        ..Default::default()
-    })));
+    // The peer's check reaches us through our relay from an address it never signaled.
+    let (username, local_pwd, tie_breaker) = {
+        let ufrag_pwd = a.internal.ufrag_pwd.lock().await;
+        (
+            ufrag_pwd.local_ufrag.to_owned() + ":" + ufrag_pwd.remote_ufrag.as_str(),
+            ufrag_pwd.local_pwd.clone(),
+            a.internal.tie_breaker.load(Ordering::SeqCst),
+        )
+    };
+    let mut msg = Message::new();
+    msg.build(&[
+        Box::new(BINDING_REQUEST),
+        Box::new(TransactionId::new()),
+        Box::new(Username::new(ATTR_USERNAME, username)),
+        Box::new(UseCandidateAttr::new()),
+        Box::new(AttrControlling(tie_breaker)),
+        Box::new(PriorityAttr(relay_local.priority())),
+        Box::new(MessageIntegrity::new_short_term_integrity(local_pwd)),
+        Box::new(FINGERPRINT),
+    ])?;
+    a.internal
+        .handle_inbound(
+            &mut msg,
+            &relay_local,
+            SocketAddr::from_str("172.17.0.3:999")?,
+        )
+        .await;
 
-    connect_net2router(&net0, &wan).await?;
-    connect_net2router(&net1, &wan).await?;
-    start_router(&wan).await?;
+    {
+        let remote_candidates = a.internal.remote_candidates.lock().await;
+        let cands = remote_candidates
+            .get(&relay_local.network_type())
+            .expect("prflx candidate learned over the relay must be kept");
+        assert_eq!(cands.len(), 1);
+        assert_eq!(cands[0].candidate_type(), CandidateType::PeerReflexive);
 
-    let (a_notifier, mut a_connected) = on_connected();
-    let (b_notifier, mut b_connected) = on_connected();
+        let checklist = a.internal.agent_conn.checklist.lock().await;
+        assert_eq!(
+            checklist.len(),
+            1,
+            "prflx must only be paired with the relay"
+        );
+        assert_eq!(checklist[0].local.candidate_type(), CandidateType::Relay);
+    }
+    assert_eq!(a.get_filtered_remote_candidates(), 0);
 
-    let keepalive_interval = Some(Duration::from_secs(3600)); //time.Hour
-    let check_interval = Duration::from_secs(3600); //time.Hour
-    let cfg0 = AgentConfig {
-        network_types: supported_network_types(),
-        multicast_dns_mode: MulticastDnsMode::Disabled,
-        net: Some(net0),
+    a.close().await?;
+    Ok(())
+}
 
-        keepalive_interval,
-        check_interval,
-        ..Default::default()
-    };
+#[tokio::test]
+async fn test_set_candidate_types() -> Result<()> {
+    let a = Agent::new(AgentConfig::default()).await?;
 
-    let a_agent = Arc::new(Agent::new(cfg0).await?);
-    a_agent.on_connection_state_change(a_notifier);
+    a.set_candidate_types(vec![CandidateType::Relay], vec![CandidateType::Relay])?;
+    assert_eq!(*a.candidate_types.lock(), vec![CandidateType::Relay]);
 
-    let cfg1 = AgentConfig {
-        network_types: supported_network_types(),
-        multicast_dns_mode: MulticastDnsMode::Disabled,
-        net: Some(net1),
+    let host_remote: Arc<dyn Candidate + Send + Sync> = Arc::new(
+        CandidateHostConfig {
+            base_config: CandidateBaseConfig {
+                network: "udp".to_owned(),
+                address: "192.168.0.3".to_owned(),
+                port: 888,
+                component: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .new_candidate_host()?,
+    );
+    a.internal.add_remote_candidate(&host_remote).await;
+    assert_eq!(a.get_filtered_remote_candidates(), 1);
+
+    // Empty lists go back to the defaults.
+    a.set_candidate_types(vec![], vec![])?;
+    assert_eq!(*a.candidate_types.lock(), default_candidate_types());
+    a.internal.add_remote_candidate(&host_remote).await;
+    assert_eq!(a.get_filtered_remote_candidates(), 1);
+    a.close().await?;
 
-        keepalive_interval,
-        check_interval,
+    let lite = Agent::new(AgentConfig {
+        lite: true,
+        candidate_types: vec![CandidateType::Host],
         ..Default::default()
-    };
-
-    let b_agent = Arc::new(Agent::new(cfg1).await?);
-    b_agent.on_connection_state_change(b_notifier);
+    })
+    .await?;
+    assert_eq!(
+        lite.set_candidate_types(vec![CandidateType::Relay], vec![])
+            .expect_err("lite agents only gather host candidates"),
+        Error::ErrLiteUsingNonHostCandidates
+    );
+    assert_eq!(*lite.candidate_types.lock(), vec![CandidateType::Host]);
+    lite.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_binding_indication_is_not_consent() -> Result<()> {
+    let a = Agent::new(AgentConfig::default()).await?;
+
+    let local: Arc<dyn Candidate + Send + Sync> = Arc::new(
+        CandidateHostConfig {
+            base_config: CandidateBaseConfig {
+                network: "udp".to_owned(),
+                address: "192.168.0.2".to_owned(),
+                port: 777,
+                component: 1,
+                conn: Some(Arc::new(MockConn {})),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .new_candidate_host()?,
+    );
+    {
+        let mut local_candidates = a.internal.local_candidates.lock().await;
+        local_candidates.insert(local.network_type(), vec![local.clone()]);
+    }
+    let remote: Arc<dyn Candidate + Send + Sync> = Arc::new(
+        CandidateHostConfig {
+            base_config: CandidateBaseConfig {
+                network: "udp".to_owned(),
+                address: "192.168.0.3".to_owned(),
+                port: 888,
+                component: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .new_candidate_host()?,
+    );
+    a.internal.add_remote_candidate(&remote).await;
+    let remote_addr = SocketAddr::from_str("192.168.0.3:888")?;
+    let pair = a
+        .internal
+        .find_pair(&local, &remote)
+        .await
+        .expect("the candidates must be paired");
+
+    let mut msg = Message::new();
+    msg.build(&[
+        Box::new(MessageType::new(METHOD_BINDING, CLASS_INDICATION)),
+        Box::new(TransactionId::new()),
+        Box::new(FINGERPRINT),
+    ])?;
+    a.internal
+        .handle_inbound(&mut msg, &local, remote_addr)
+        .await;
+    assert!(
+        pair.last_received().is_none(),
+        "an unauthenticated indication must not refresh the pair"
+    );
+
+    let (username, local_pwd, tie_breaker) = {
+        let ufrag_pwd = a.internal.ufrag_pwd.lock().await;
+        (
+            ufrag_pwd.local_ufrag.to_owned() + ":" + ufrag_pwd.remote_ufrag.as_str(),
+            ufrag_pwd.local_pwd.clone(),
+            a.internal.tie_breaker.load(Ordering::SeqCst),
+        )
+    };
+    let mut msg = Message::new();
+    msg.build(&[
+        Box::new(BINDING_REQUEST),
+        Box::new(TransactionId::new()),
+        Box::new(Username::new(ATTR_USERNAME, username)),
+        Box::new(AttrControlling(tie_breaker)),
+        Box::new(PriorityAttr(local.priority())),
+        Box::new(MessageIntegrity::new_short_term_integrity(local_pwd)),
+        Box::new(FINGERPRINT),
+    ])?;
+    a.internal
+        .handle_inbound(&mut msg, &local, remote_addr)
+        .await;
+    assert!(pair.last_received().is_some());
+
+    a.close().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_handle_peer_reflexive_unknown_remote() -> Result<()> {
+    let a = Agent::new(AgentConfig::default()).await?;
+
+    let mut tid = TransactionId::default();
+    tid.0[..3].copy_from_slice("ABC".as_bytes());
+
+    let remote_pwd = {
+        {
+            let mut pending_binding_requests = a.internal.pending_binding_requests.lock().await;
+            *pending_binding_requests = vec![BindingRequest {
+                timestamp: Instant::now(),
+                transaction_id: tid,
+                destination: SocketAddr::from_str("0.0.0.0:0")?,
+                is_use_candidate: false,
+            }];
+        }
+        let ufrag_pwd = a.internal.ufrag_pwd.lock().await;
+        ufrag_pwd.remote_pwd.clone()
+    };
+
+    let host_config = CandidateHostConfig {
+        base_config: CandidateBaseConfig {
+            network: "udp".to_owned(),
+            address: "192.168.0.2".to_owned(),
+            port: 777,
+            component: 1,
+            conn: Some(Arc::new(MockConn {})),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let local: Arc<dyn Candidate + Send + Sync> = Arc::new(host_config.new_candidate_host()?);
+    let remote = SocketAddr::from_str("172.17.0.3:999")?;
+
+    let mut msg = Message::new();
+    msg.build(&[
+        Box::new(BINDING_SUCCESS),
+        Box::new(tid),
+        Box::new(MessageIntegrity::new_short_term_integrity(remote_pwd)),
+        Box::new(FINGERPRINT),
+    ])?;
+
+    {
+        a.internal.handle_inbound(&mut msg, &local, remote).await;
+
+        let remote_candidates = a.internal.remote_candidates.lock().await;
+        assert_eq!(
+            remote_candidates.len(),
+            0,
+            "unknown remote was able to create a candidate"
+        );
+    }
+
+    let _ = a.close().await?;
+    Ok(())
+}
+
+//use std::io::Write;
+
+// Assert that Agent on startup sends message, and doesn't wait for connectivityTicker to fire
+#[tokio::test]
+async fn test_connectivity_on_startup() -> Result<()> {
+    /*env_logger::Builder::new()
+    .format(|buf, record| {
+        writeln!(
+            buf,
+            "{}:{} [{}] {} - {}",
+            record.file().unwrap_or("unknown"),
+            record.line().unwrap_or(0),
+            record.level(),
+            chrono::Local::now().format("%H:%M:%S.%6f"),
+            record.args()
+        )
+    })
+    .filter(None, log::LevelFilter::Trace)
+    .init();*/
+
+    // Create a network with two interfaces
+    let wan = Arc::new(Mutex::new(router::Router::new(router::RouterConfig {
+        cidr: "0.0.0.0/0".to_owned(),
+        ..Default::default()
+    })?));
+
+    let net0 = Arc::new(net::Net::new(Some(net::NetConfig {
+        static_ips: vec!["192.168.0.1".to_owned()],
+        ..Default::default()
+    })));
+    let net1 = Arc::new(net::Net::new(Some(net::NetConfig {
+        static_ips: vec!["192.168.0.2".to_owned()],
+        ..Default::default()
+    })));
+
+    connect_net2router(&net0, &wan).await?;
+    connect_net2router(&net1, &wan).await?;
+    start_router(&wan).await?;
+
+    let (a_notifier, mut a_connected) = on_connected();
+    let (b_notifier, mut b_connected) = on_connected();
+
+    let keepalive_interval = Some(Duration::from_secs(3600)); //time.Hour
+    let check_interval = Duration::from_secs(3600); //time.Hour
+    let cfg0 = AgentConfig {
+        network_types: supported_network_types(),
+        multicast_dns_mode: MulticastDnsMode::Disabled,
+        net: Some(net0),
+
+        keepalive_interval,
+        check_interval,
+        ..Default::default()
+    };
+
+    let a_agent = Arc::new(Agent::new(cfg0).await?);
+    a_agent.on_connection_state_change(a_notifier);
+
+    let cfg1 = AgentConfig {
+        network_types: supported_network_types(),
+        multicast_dns_mode: MulticastDnsMode::Disabled,
+        net: Some(net1),
+
+        keepalive_interval,
+        check_interval,
+        ..Default::default()
+    };
+
+    let b_agent = Arc::new(Agent::new(cfg1).await?);
+    b_agent.on_connection_state_change(b_notifier);
 
     // Manual signaling
     let (a_ufrag, a_pwd) = a_agent.get_local_user_credentials().await;
@@ -621,6 +1036,8 @@ fn build_msg(c: MessageClass, username: String, key: String) -> Result<Message>
         Box::new(MessageType::new(METHOD_BINDING, c)),
         Box::new(TransactionId::new()),
         Box::new(Username::new(ATTR_USERNAME, username)),
+        Box::new(AttrControlling(0)),
+        Box::new(PriorityAttr(0)),
         Box::new(MessageIntegrity::new_short_term_integrity(key)),
         Box::new(FINGERPRINT),
     ])?;
@@ -813,6 +1230,8 @@ async fn test_inbound_validity() -> Result<()> {
                 Box::new(BINDING_REQUEST),
                 Box::new(TransactionId::new()),
                 Box::new(Username::new(ATTR_USERNAME, username)),
+                Box::new(AttrControlling(0)),
+                Box::new(PriorityAttr(0)),
                 Box::new(MessageIntegrity::new_short_term_integrity(local_pwd)),
             ])?;
 
@@ -2091,15 +2510,13 @@ async fn test_run_task_in_selected_candidate_pair_change_callback() -> Result<()
 
     let (is_tested_tx, mut is_tested_rx) = mpsc::channel::<()>(1);
     let is_tested_tx = Arc::new(Mutex::new(Some(is_tested_tx)));
-    a_agent.on_selected_candidate_pair_change(Box::new(
-        move |_: &Arc<dyn Candidate + Send + Sync>, _: &Arc<dyn Candidate + Send + Sync>| {
-            let is_tested_tx_clone = Arc::clone(&is_tested_tx);
-            Box::pin(async move {
-                let mut tx = is_tested_tx_clone.lock().await;
-                tx.take();
-            })
-        },
-    ));
+    a_agent.on_selected_candidate_pair_change(Box::new(move |_: &SelectedCandidatePairChange| {
+        let is_tested_tx_clone = Arc::clone(&is_tested_tx);
+        Box::pin(async move {
+            let mut tx = is_tested_tx_clone.lock().await;
+            tx.take();
+        })
+    }));
 
     let (is_complete_tx, mut is_complete_rx) = mpsc::channel::<()>(1);
     let is_complete_tx = Arc::new(Mutex::new(Some(is_complete_tx)));
@@ -2200,3 +2617,601 @@ async fn test_lite_lifecycle() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_invalid_binding_requests_rejected() -> Result<()> {
+    let a = Agent::new(AgentConfig::default()).await?;
+
+    let local_conn = Arc::new(UdpSocket::bind("127.0.0.1:0").await?);
+    let local_port = local_conn.local_addr()?.port();
+    let local: Arc<dyn Candidate + Send + Sync> = Arc::new(
+        CandidateHostConfig {
+            base_config: CandidateBaseConfig {
+                network: "udp".to_owned(),
+                address: "127.0.0.1".to_owned(),
+                port: local_port,
+                component: 1,
+                conn: Some(local_conn),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .new_candidate_host()?,
+    );
+    {
+        let mut local_candidates = a.internal.local_candidates.lock().await;
+        local_candidates.insert(local.network_type(), vec![local.clone()]);
+    }
+
+    let remote_conn = UdpSocket::bind("127.0.0.1:0").await?;
+    let remote = remote_conn.local_addr()?;
+
+    let (username, local_pwd, tie_breaker) = {
+        let ufrag_pwd = a.internal.ufrag_pwd.lock().await;
+        (
+            ufrag_pwd.local_ufrag.to_owned() + ":" + ufrag_pwd.remote_ufrag.as_str(),
+            ufrag_pwd.local_pwd.clone(),
+            a.internal.tie_breaker.load(Ordering::SeqCst),
+        )
+    };
+    let request = |attrs: Vec<Box<dyn Setter>>, pwd: &str| -> Result<Message> {
+        let mut setters: Vec<Box<dyn Setter>> = vec![
+            Box::new(BINDING_REQUEST),
+            Box::new(TransactionId::new()),
+            Box::new(Username::new(ATTR_USERNAME, username.clone())),
+        ];
+        setters.extend(attrs);
+        setters.push(Box::new(MessageIntegrity::new_short_term_integrity(
+            pwd.to_owned(),
+        )));
+        setters.push(Box::new(FINGERPRINT));
+
+        let mut msg = Message::new();
+        msg.build(&setters)?;
+        Ok(msg)
+    };
+    let priority = PriorityAttr(local.priority());
+
+    let invalid_requests: Vec<Vec<Box<dyn Setter>>> = vec![
+        vec![Box::new(AttrControlling(tie_breaker))],
+        vec![Box::new(priority)],
+        vec![
+            Box::new(AttrControlling(tie_breaker)),
+            Box::new(AttrControlled(tie_breaker)),
+            Box::new(priority),
+        ],
+        vec![
+            Box::new(UseCandidateAttr::new()),
+            Box::new(AttrControlled(tie_breaker)),
+            Box::new(priority),
+        ],
+    ];
+    let mut buf = vec![0u8; 1500];
+    for attrs in invalid_requests {
+        let mut msg = request(attrs, &local_pwd)?;
+        a.internal.handle_inbound(&mut msg, &local, remote).await;
+
+        let (n, _) = tokio::time::timeout(Duration::from_secs(1), remote_conn.recv_from(&mut buf))
+            .await
+            .expect("no response to invalid request")?;
+        let mut response = Message::new();
+        response.write(&buf[..n])?;
+        assert_eq!(response.typ, BINDING_ERROR);
+        assert_eq!(response.transaction_id, msg.transaction_id);
+        let mut error_code = ErrorCodeAttribute::default();
+        error_code.get_from(&response)?;
+        assert!(error_code.code == CODE_BAD_REQUEST, "{}", error_code);
+    }
+
+    // A valid request with a wrong password is discarded without response
+    let mut msg = request(
+        vec![Box::new(AttrControlling(tie_breaker)), Box::new(priority)],
+        "wrong password",
+    )?;
+    a.internal.handle_inbound(&mut msg, &local, remote).await;
+
+    {
+        let remote_candidates = a.internal.remote_candidates.lock().await;
+        assert!(remote_candidates.is_empty(), "no prflx candidate expected");
+        let checklist = a.internal.agent_conn.checklist.lock().await;
+        assert!(checklist.is_empty(), "no pair should have been formed");
+    }
+    assert_eq!(
+        a.get_rejected_binding_request_stats(),
+        RejectedBindingRequestStats {
+            unauthenticated: 1,
+            missing_priority: 1,
+            missing_role: 1,
+            conflicting_roles: 1,
+            use_candidate_from_controlled: 1,
+        }
+    );
+
+    // Valid requests are still answered
+    let mut msg = request(
+        vec![Box::new(AttrControlling(tie_breaker)), Box::new(priority)],
+        &local_pwd,
+    )?;
+    a.internal.handle_inbound(&mut msg, &local, remote).await;
+    let (n, _) = tokio::time::timeout(Duration::from_secs(1), remote_conn.recv_from(&mut buf))
+        .await
+        .expect("no response to valid request")?;
+    let mut response = Message::new();
+    response.write(&buf[..n])?;
+    assert_eq!(response.typ, BINDING_SUCCESS);
+    assert_eq!(response.transaction_id, msg.transaction_id);
+
+    a.close().await?;
+    Ok(())
+}
+
+async fn renomination_test_pairs(a: &Agent) -> Result<(Arc<CandidatePair>, Arc<CandidatePair>)> {
+    let local: Arc<dyn Candidate + Send + Sync> = Arc::new(
+        CandidateHostConfig {
+            base_config: CandidateBaseConfig {
+                network: "udp".to_owned(),
+                address: "192.168.0.2".to_owned(),
+                port: 777,
+                component: 1,
+                conn: Some(Arc::new(MockConn {})),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .new_candidate_host()?,
+    );
+    let relay_remote: Arc<dyn Candidate + Send + Sync> = Arc::new(
+        CandidateRelayConfig {
+            base_config: CandidateBaseConfig {
+                network: "udp".to_owned(),
+                address: "1.2.3.4".to_owned(),
+                port: 12340,
+                component: 1,
+                ..Default::default()
+            },
+            rel_addr: "4.3.2.1".to_owned(),
+            rel_port: 43210,
+            ..Default::default()
+        }
+        .new_candidate_relay()?,
+    );
+    let host_remote: Arc<dyn Candidate + Send + Sync> = Arc::new(
+        CandidateHostConfig {
+            base_config: CandidateBaseConfig {
+                network: "udp".to_owned(),
+                address: "192.168.0.3".to_owned(),
+                port: 888,
+                component: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .new_candidate_host()?,
+    );
+
+    a.internal
+        .add_pair(Arc::clone(&local), Arc::clone(&relay_remote))
+        .await;
+    a.internal
+        .add_pair(local.clone(), host_remote.clone())
+        .await;
+
+    let relay_pair = a.internal.find_pair(&local, &relay_remote).await.unwrap();
+    let host_pair = a.internal.find_pair(&local, &host_remote).await.unwrap();
+    for p in [&relay_pair, &host_pair] {
+        p.state
+            .store(CandidatePairState::Succeeded as u8, Ordering::SeqCst);
+    }
+    assert!(host_pair.priority() > relay_pair.priority());
+
+    // The lower priority pair was selected first
+    a.internal
+        .set_selected_pair(Some(Arc::clone(&relay_pair)))
+        .await;
+
+    Ok((relay_pair, host_pair))
+}
+
+#[tokio::test]
+async fn test_renomination_requires_mutual_support() -> Result<()> {
+    for (local, remote) in [(true, true), (true, false), (false, true)] {
+        let a = Agent::new(AgentConfig {
+            renomination: local,
+            ..Default::default()
+        })
+        .await?;
+        assert_eq!(a.get_local_options().renomination, local);
+
+        a.set_remote_options(IceOptions {
+            trickle: true,
+            renomination: remote,
+            ice2: false,
+        });
+        assert_eq!(a.internal.renomination_enabled(), local && remote);
+
+        // Restart forgets the options of the previous remote
+        a.restart(String::new(), String::new()).await?;
+        assert_eq!(a.get_remote_options(), IceOptions::default());
+        assert!(!a.internal.renomination_enabled());
+
+        a.close().await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_controlling_renomination() -> Result<()> {
+    for (remote_renomination, want_renominated) in [(true, true), (false, false)] {
+        let a = Agent::new(AgentConfig {
+            is_controlling: true,
+            renomination: true,
+            ..Default::default()
+        })
+        .await?;
+        a.set_remote_options(IceOptions {
+            trickle: true,
+            renomination: remote_renomination,
+            ice2: false,
+        });
+
+        let (_relay_pair, host_pair) = renomination_test_pairs(&a).await?;
+        {
+            let mut nominated_pair = a.internal.nominated_pair.lock().await;
+            *nominated_pair = None;
+        }
+
+        if a.internal.renomination_enabled() {
+            a.internal.renominate_pair().await;
+        }
+
+        let nominated_pair = a.internal.nominated_pair.lock().await.clone();
+        assert_eq!(
+            nominated_pair.map_or(false, |p| p == host_pair),
+            want_renominated,
+            "remote renomination support: {}",
+            remote_renomination
+        );
+
+        a.close().await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_controlled_renomination() -> Result<()> {
+    for (remote_renomination, want_renominated) in [(true, true), (false, false)] {
+        let a = Agent::new(AgentConfig {
+            renomination: true,
+            ..Default::default()
+        })
+        .await?;
+        a.set_remote_options(IceOptions {
+            trickle: true,
+            renomination: remote_renomination,
+            ice2: false,
+        });
+
+        let (relay_pair, host_pair) = renomination_test_pairs(&a).await?;
+
+        let mut msg = Message::new();
+        msg.build(&[
+            Box::new(BINDING_REQUEST),
+            Box::new(TransactionId::new()),
+            Box::new(UseCandidateAttr::new()),
+            Box::new(AttrControlling(0)),
+        ])?;
+        a.internal
+            .handle_binding_request(&msg, &host_pair.local, &host_pair.remote)
+            .await;
+
+        let selected_pair = a.internal.agent_conn.get_selected_pair().unwrap();
+        if want_renominated {
+            assert!(
+                selected_pair == host_pair,
+                "expected the pair to be renominated"
+            );
+        } else {
+            assert!(
+                selected_pair == relay_pair,
+                "expected the first nomination to stick"
+            );
+        }
+
+        a.close().await?;
+    }
+
+    Ok(())
+}
+
+// A remote agent that doesn't trickle signaled all of its candidates, so once every pair
+// failed the agent fails without waiting for the failed timeout.
+#[tokio::test]
+async fn test_non_trickle_remote_fails_when_all_pairs_failed() -> Result<()> {
+    for (remote_trickle, want_failed) in [(false, true), (true, false)] {
+        let a = Agent::new(AgentConfig {
+            network_types: vec![NetworkType::Udp4],
+            candidate_types: vec![CandidateType::Host],
+            multicast_dns_mode: MulticastDnsMode::Disabled,
+            max_binding_requests: Some(1),
+            check_interval: Duration::from_millis(20),
+            disconnected_timeout: Some(Duration::from_secs(30)),
+            failed_timeout: Some(Duration::from_secs(30)),
+            ..Default::default()
+        })
+        .await?;
+
+        let (failed_tx, mut failed_rx) = mpsc::channel::<()>(1);
+        let failed_tx = Arc::new(Mutex::new(Some(failed_tx)));
+        a.on_connection_state_change(Box::new(move |c: ConnectionState| {
+            let failed_tx = Arc::clone(&failed_tx);
+            Box::pin(async move {
+                if c == ConnectionState::Failed {
+                    failed_tx.lock().await.take();
+                }
+            })
+        }));
+
+        let (gathered_tx, mut gathered_rx) = mpsc::channel::<()>(1);
+        let gathered_tx = Arc::new(Mutex::new(Some(gathered_tx)));
+        a.on_candidate(Box::new(
+            move |c: Option<Arc<dyn Candidate + Send + Sync>>| {
+                let gathered_tx = Arc::clone(&gathered_tx);
+                Box::pin(async move {
+                    if c.is_none() {
+                        gathered_tx.lock().await.take();
+                    }
+                })
+            },
+        ));
+        a.gather_candidates()?;
+        let _ = gathered_rx.recv().await;
+
+        a.set_remote_options(IceOptions {
+            trickle: remote_trickle,
+            ..Default::default()
+        });
+
+        // Nothing answers on the TEST-NET-1 address
+        let remote: Arc<dyn Candidate + Send + Sync> = Arc::new(
+            CandidateHostConfig {
+                base_config: CandidateBaseConfig {
+                    network: "udp".to_owned(),
+                    address: "192.0.2.1".to_owned(),
+                    port: 9,
+                    component: 1,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+            .new_candidate_host()?,
+        );
+        a.add_remote_candidate(&remote)?;
+
+        let a = Arc::new(a);
+        let agent = Arc::clone(&a);
+        tokio::spawn(async move {
+            let (_cancel_tx, cancel_rx) = mpsc::channel(1);
+            let _ = agent
+                .dial(cancel_rx, "remoteufrag".to_owned(), "remotepwd".to_owned())
+                .await;
+        });
+
+        let failed = tokio::time::timeout(Duration::from_secs(3), failed_rx.recv())
+            .await
+            .is_ok();
+        assert_eq!(failed, want_failed, "remote trickle: {}", remote_trickle);
+
+        a.close().await?;
+    }
+
+    Ok(())
+}
+
+fn new_mock_host_candidate(port: u16) -> Result<Arc<dyn Candidate + Send + Sync>> {
+    let host_config = CandidateHostConfig {
+        base_config: CandidateBaseConfig {
+            network: "udp".to_owned(),
+            address: "192.168.0.2".to_owned(),
+            port,
+            component: 1,
+            conn: Some(Arc::new(MockConn {})),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    Ok(Arc::new(host_config.new_candidate_host()?))
+}
+
+/// assert_paced checks that the binding requests sent by the agents are spaced by at least ta
+async fn assert_paced(agents: &[&Agent], ta: Duration, expected_requests: usize) {
+    let mut timestamps = vec![];
+    for a in agents {
+        let pending_binding_requests = a.internal.pending_binding_requests.lock().await;
+        timestamps.extend(pending_binding_requests.iter().map(|r| r.timestamp));
+    }
+    timestamps.sort();
+
+    assert_eq!(timestamps.len(), expected_requests);
+    for w in timestamps.windows(2) {
+        assert!(w[1] - w[0] >= ta, "checks sent {:?} apart", w[1] - w[0]);
+    }
+}
+
+#[tokio::test]
+async fn test_pacing_interval_config() -> Result<()> {
+    let a = Agent::new(AgentConfig::default()).await?;
+    assert_eq!(a.internal.pacer.interval(), DEFAULT_PACING_INTERVAL);
+    assert!(a.internal.mux_pacer.is_none());
+    a.close().await?;
+
+    let a = Agent::new(AgentConfig {
+        pacing_interval: Some(Duration::from_secs(0)),
+        ..Default::default()
+    })
+    .await?;
+    assert_eq!(a.internal.pacer.interval(), Duration::from_secs(0));
+    a.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_connectivity_checks_are_paced() -> Result<()> {
+    const TA: Duration = Duration::from_millis(20);
+
+    let a = Agent::new(AgentConfig {
+        pacing_interval: Some(TA),
+        ..Default::default()
+    })
+    .await?;
+
+    let local = new_mock_host_candidate(777)?;
+    for port in 1000..1004 {
+        a.internal
+            .add_pair(Arc::clone(&local), new_mock_host_candidate(port)?)
+            .await;
+    }
+
+    let start = Instant::now();
+    a.internal.ping_all_candidates().await;
+    assert_eq!(
+        start.elapsed(),
+        TA * 3,
+        "checks must not wait longer than Ta"
+    );
+    assert_paced(&[&a], TA, 4).await;
+
+    a.close().await?;
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_triggered_checks_are_queued_and_paced() -> Result<()> {
+    const TA: Duration = Duration::from_millis(20);
+
+    let a = Agent::new(AgentConfig {
+        pacing_interval: Some(TA),
+        ..Default::default()
+    })
+    .await?;
+
+    let local = new_mock_host_candidate(777)?;
+    let (remote1, remote2) = (
+        new_mock_host_candidate(1000)?,
+        new_mock_host_candidate(1001)?,
+    );
+    a.internal.enqueue_triggered_check(&local, &remote1);
+    a.internal.enqueue_triggered_check(&local, &remote2);
+    // A pair already waiting for its triggered check is not queued twice
+    a.internal.enqueue_triggered_check(&local, &remote1);
+    assert_eq!(a.internal.triggered_checks.lock().len(), 2);
+
+    let triggered_check_rx = a.internal.triggered_check_rx.lock().await.take().unwrap();
+    a.internal.start_triggered_checks(triggered_check_rx);
+    tokio::time::sleep(TA * 5).await;
+
+    assert!(a.internal.triggered_checks.lock().is_empty());
+    assert_paced(&[&a], TA, 2).await;
+
+    a.close().await?;
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_pacing_shared_by_udp_mux() -> Result<()> {
+    const TA: Duration = Duration::from_millis(20);
+
+    let udp_socket = UdpSocket::bind("127.0.0.1:0").await?;
+    let udp_mux = UDPMuxDefault::new(UDPMuxParams::new(udp_socket).with_pacing_interval(TA));
+
+    let mut agents = vec![];
+    for _ in 0..2 {
+        let a = Agent::new(AgentConfig {
+            udp_network: UDPNetwork::Muxed(Arc::clone(&udp_mux) as Arc<dyn UDPMux + Send + Sync>),
+            pacing_interval: Some(TA),
+            ..Default::default()
+        })
+        .await?;
+        assert!(a.internal.mux_pacer.is_some());
+
+        let local = new_mock_host_candidate(777)?;
+        for port in 1000..1002 {
+            a.internal
+                .add_pair(Arc::clone(&local), new_mock_host_candidate(port)?)
+                .await;
+        }
+        agents.push(a);
+    }
+
+    // Each agent alone would only wait for its own checks
+    let start = Instant::now();
+    tokio::join!(
+        agents[0].internal.ping_all_candidates(),
+        agents[1].internal.ping_all_candidates()
+    );
+    assert_eq!(start.elapsed(), TA * 3);
+    assert_paced(&[&agents[0], &agents[1]], TA, 4).await;
+
+    for a in agents {
+        a.close().await?;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_connectivity_over_tcp() -> Result<()> {
+    // vnet has no TCP, the agents use the real network
+    let new_agent = |tcp_active_candidates: bool| {
+        Agent::new(AgentConfig {
+            network_types: vec![NetworkType::Tcp4],
+            candidate_types: vec![CandidateType::Host],
+            tcp_active_candidates,
+            multicast_dns_mode: MulticastDnsMode::Disabled,
+            ..Default::default()
+        })
+    };
+
+    let a_agent = Arc::new(new_agent(false).await?);
+    let (a_notifier, mut a_connected) = on_connected();
+    a_agent.on_connection_state_change(a_notifier);
+
+    let b_agent = Arc::new(new_agent(true).await?);
+    let (b_notifier, mut b_connected) = on_connected();
+    b_agent.on_connection_state_change(b_notifier);
+
+    let (a_conn, b_conn) = connect_with_vnet(&a_agent, &b_agent).await?;
+    let _ = a_connected.recv().await;
+    let _ = b_connected.recv().await;
+
+    for c in b_agent.get_local_candidates().await? {
+        assert_eq!(c.network_type(), NetworkType::Tcp4);
+        assert!(c.tcp_type() == TcpType::Active || c.tcp_type() == TcpType::Passive);
+    }
+
+    // b connected from its active candidate to the passive one of a
+    let b_pair = b_agent.get_selected_candidate_pair().unwrap();
+    assert_eq!(b_pair.local.tcp_type(), TcpType::Active);
+    assert_eq!(b_pair.remote.tcp_type(), TcpType::Passive);
+    let a_pair = a_agent.get_selected_candidate_pair().unwrap();
+    assert_eq!(a_pair.local.tcp_type(), TcpType::Passive);
+    assert_eq!(a_pair.remote.candidate_type(), CandidateType::PeerReflexive);
+    assert_eq!(a_pair.remote.network_type(), NetworkType::Tcp4);
+
+    let stats = b_agent.get_candidate_pairs_stats().await;
+    assert!(stats
+        .iter()
+        .any(|s| s.nominated && s.network_type == NetworkType::Tcp4));
+
+    b_conn.send(b"over tcp").await?;
+    let mut buf = vec![0u8; 100];
+    let n = a_conn.recv(&mut buf).await?;
+    assert_eq!(&buf[..n], b"over tcp");
+
+    a_agent.close().await?;
+    b_agent.close().await?;
+
+    Ok(())
+}
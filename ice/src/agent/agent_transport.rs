@@ -88,10 +88,11 @@ pub(crate) struct AgentConn {
     pub(crate) bytes_received: AtomicUsize,
     pub(crate) bytes_sent: AtomicUsize,
     pub(crate) done: AtomicBool,
+    pub(crate) prefer_low_cost: bool,
 }
 
 impl AgentConn {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(prefer_low_cost: bool) -> Self {
         Self {
             selected_pair: ArcSwapOption::empty(),
             checklist: Mutex::new(vec![]),
@@ -102,12 +103,22 @@ impl AgentConn {
             bytes_received: AtomicUsize::new(0),
             bytes_sent: AtomicUsize::new(0),
             done: AtomicBool::new(false),
+            prefer_low_cost,
         }
     }
     pub(crate) fn get_selected_pair(&self) -> Option<Arc<CandidatePair>> {
         self.selected_pair.load().clone()
     }
 
+    /// is_better_pair returns true if p is preferred over other: if it has a higher priority, or
+    /// with prefer_low_cost, the same priority and a lower network cost.
+    pub(crate) fn is_better_pair(&self, p: &CandidatePair, other: &CandidatePair) -> bool {
+        if p.priority() != other.priority() || !self.prefer_low_cost {
+            return p.priority() > other.priority();
+        }
+        p.network_cost() < other.network_cost()
+    }
+
     pub(crate) async fn get_best_available_candidate_pair(&self) -> Option<Arc<CandidatePair>> {
         let mut best: Option<&Arc<CandidatePair>> = None;
 
@@ -118,7 +129,7 @@ impl AgentConn {
             }
 
             if let Some(b) = &mut best {
-                if b.priority() < p.priority() {
+                if self.is_better_pair(p, b) {
                     *b = p;
                 }
             } else {
@@ -139,7 +150,7 @@ impl AgentConn {
             }
 
             if let Some(b) = &mut best {
-                if b.priority() < p.priority() {
+                if self.is_better_pair(p, b) {
                     *b = p;
                 }
             } else {
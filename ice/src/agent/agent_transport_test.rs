@@ -2,6 +2,9 @@ use super::agent_vnet_test::*;
 use super::*;
 
 use crate::agent::agent_transport::AgentConn;
+use crate::candidate::candidate_base::CandidateBaseConfig;
+use crate::candidate::candidate_host::CandidateHostConfig;
+use crate::candidate::candidate_relay::CandidateRelayConfig;
 use util::{vnet::*, Conn};
 use waitgroup::WaitGroup;
 
@@ -69,7 +72,7 @@ async fn test_remote_local_addr() -> Result<()> {
 
     //"Disconnected Returns nil"
     {
-        let disconnected_conn = AgentConn::new();
+        let disconnected_conn = AgentConn::new(false);
         let result = disconnected_conn.local_addr();
         assert!(result.is_err(), "Disconnected Returns nil");
     }
@@ -130,3 +133,76 @@ async fn test_conn_stats() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_is_better_pair_breaks_ties_by_network_cost() -> Result<()> {
+    let pair = |remote_address: &str, network_cost: u16| -> Result<CandidatePair> {
+        let local = CandidateHostConfig {
+            base_config: CandidateBaseConfig {
+                network: "udp".to_owned(),
+                address: "192.168.0.1".to_owned(),
+                component: COMPONENT_RTP,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .new_candidate_host()?;
+        let remote = CandidateHostConfig {
+            base_config: CandidateBaseConfig {
+                network: "udp".to_owned(),
+                address: remote_address.to_owned(),
+                component: COMPONENT_RTP,
+                network_cost,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .new_candidate_host()?;
+        Ok(CandidatePair::new(Arc::new(local), Arc::new(remote), true))
+    };
+    let wifi = pair("192.168.0.2", 10)?;
+    let cellular = pair("192.168.0.3", 900)?;
+    assert_eq!(wifi.priority(), cellular.priority());
+
+    let conn = AgentConn::new(true);
+    assert!(conn.is_better_pair(&wifi, &cellular));
+    assert!(!conn.is_better_pair(&cellular, &wifi));
+
+    // Without prefer_low_cost, or with different priorities, the cost doesn't matter
+    let conn = AgentConn::new(false);
+    assert!(!conn.is_better_pair(&wifi, &cellular));
+    assert!(!conn.is_better_pair(&cellular, &wifi));
+
+    let relay = CandidatePair::new(
+        Arc::new(
+            CandidateRelayConfig {
+                base_config: CandidateBaseConfig {
+                    network: "udp".to_owned(),
+                    address: "192.168.0.1".to_owned(),
+                    component: COMPONENT_RTP,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+            .new_candidate_relay()?,
+        ),
+        Arc::new(
+            CandidateHostConfig {
+                base_config: CandidateBaseConfig {
+                    network: "udp".to_owned(),
+                    address: "192.168.0.4".to_owned(),
+                    component: COMPONENT_RTP,
+                    network_cost: 10,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+            .new_candidate_host()?,
+        ),
+        true,
+    );
+    let conn = AgentConn::new(true);
+    assert!(conn.is_better_pair(&cellular, &relay));
+
+    Ok(())
+}
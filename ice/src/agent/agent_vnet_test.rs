@@ -1,6 +1,7 @@
 use super::*;
 
 use crate::candidate::candidate_base::unmarshal_candidate;
+use crate::tcp_packet_conn::{TcpFraming, TcpPacketConn};
 use async_trait::async_trait;
 use std::net::{IpAddr, Ipv4Addr};
 use std::result::Result;
@@ -281,6 +282,7 @@ pub(crate) async fn connect_with_vnet(
 pub(crate) struct AgentTestConfig {
     pub(crate) urls: Vec<Url>,
     pub(crate) nat_1to1_ip_candidate_type: CandidateType,
+    pub(crate) turn_over_tcp: bool,
 }
 
 pub(crate) async fn pipe_with_vnet(
@@ -288,6 +290,16 @@ pub(crate) async fn pipe_with_vnet(
     a0test_config: AgentTestConfig,
     a1test_config: AgentTestConfig,
 ) -> Result<(Arc<impl Conn>, Arc<impl Conn>), Error> {
+    let (_a_agent, _b_agent, a_conn, b_conn) =
+        pipe_agents_with_vnet(v, a0test_config, a1test_config).await?;
+    Ok((a_conn, b_conn))
+}
+
+pub(crate) async fn pipe_agents_with_vnet(
+    v: &VNet,
+    a0test_config: AgentTestConfig,
+    a1test_config: AgentTestConfig,
+) -> Result<(Arc<Agent>, Arc<Agent>, Arc<impl Conn>, Arc<impl Conn>), Error> {
     let (a_notifier, mut a_connected) = on_connected();
     let (b_notifier, mut b_connected) = on_connected();
 
@@ -303,6 +315,7 @@ pub(crate) async fn pipe_with_vnet(
         multicast_dns_mode: MulticastDnsMode::Disabled,
         nat_1to1_ips,
         nat_1to1_ip_candidate_type: a0test_config.nat_1to1_ip_candidate_type,
+        turn_over_tcp: a0test_config.turn_over_tcp,
         net: Some(Arc::clone(&v.net0)),
         ..Default::default()
     };
@@ -321,6 +334,7 @@ pub(crate) async fn pipe_with_vnet(
         multicast_dns_mode: MulticastDnsMode::Disabled,
         nat_1to1_ips,
         nat_1to1_ip_candidate_type: a1test_config.nat_1to1_ip_candidate_type,
+        turn_over_tcp: a1test_config.turn_over_tcp,
         net: Some(Arc::clone(&v.net1)),
         ..Default::default()
     };
@@ -335,7 +349,13 @@ pub(crate) async fn pipe_with_vnet(
     let _ = a_connected.recv().await;
     let _ = b_connected.recv().await;
 
-    Ok((a_conn, b_conn))
+    Ok((a_agent, b_agent, a_conn, b_conn))
+}
+
+pub(crate) fn selected_candidate_types(agent: &Agent) -> Option<(CandidateType, CandidateType)> {
+    agent
+        .get_selected_candidate_pair()
+        .map(|p| (p.local.candidate_type(), p.remote.candidate_type()))
 }
 
 pub(crate) fn on_connected() -> (OnConnectionStateChangeHdlrFn, mpsc::Receiver<()>) {
@@ -661,6 +681,7 @@ async fn test_connectivity_vnet_1to1_nat_with_host_candidate_vs_symmetric_nats()
     let a0test_config = AgentTestConfig {
         urls: vec![],
         nat_1to1_ip_candidate_type: CandidateType::Host, // Use 1:1 NAT IP as a host candidate
+        ..Default::default()
     };
     let a1test_config = AgentTestConfig {
         urls: vec![],
@@ -714,6 +735,7 @@ async fn test_connectivity_vnet_1to1_nat_with_srflx_candidate_vs_symmetric_nats(
     let a0test_config = AgentTestConfig {
         urls: vec![],
         nat_1to1_ip_candidate_type: CandidateType::ServerReflexive, // Use 1:1 NAT IP as a srflx candidate
+        ..Default::default()
     };
     let a1test_config = AgentTestConfig {
         urls: vec![],
@@ -729,6 +751,222 @@ async fn test_connectivity_vnet_1to1_nat_with_srflx_candidate_vs_symmetric_nats(
     Ok(())
 }
 
+#[tokio::test]
+async fn test_connectivity_vnet_full_cone_nats_connect_directly() -> Result<(), Error> {
+    let stun_server_url = Url {
+        scheme: SchemeType::Stun,
+        host: VNET_STUN_SERVER_IP.to_owned(),
+        port: VNET_STUN_SERVER_PORT,
+        proto: ProtoType::Udp,
+        ..Default::default()
+    };
+
+    let turn_server_url = Url {
+        scheme: SchemeType::Turn,
+        host: VNET_STUN_SERVER_IP.to_owned(),
+        port: VNET_STUN_SERVER_PORT,
+        username: "user".to_owned(),
+        password: "pass".to_owned(),
+        proto: ProtoType::Udp,
+    };
+
+    // Both agents can relay, but cone NATs must not need it
+    let v = build_vnet(nat::NatType::full_cone(), nat::NatType::full_cone()).await?;
+
+    let a0test_config = AgentTestConfig {
+        urls: vec![stun_server_url.clone(), turn_server_url.clone()],
+        ..Default::default()
+    };
+    let a1test_config = AgentTestConfig {
+        urls: vec![stun_server_url.clone(), turn_server_url.clone()],
+        ..Default::default()
+    };
+    let (a_agent, b_agent, _ca, _cb) =
+        pipe_agents_with_vnet(&v, a0test_config, a1test_config).await?;
+
+    for agent in [&a_agent, &b_agent] {
+        let (local, remote) = selected_candidate_types(agent).ok_or(Error::ErrNoCandidatePairs)?;
+        assert_ne!(local, CandidateType::Relay, "should connect directly");
+        assert_ne!(remote, CandidateType::Relay, "should connect directly");
+    }
+
+    a_agent.close().await?;
+    b_agent.close().await?;
+    v.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_connectivity_vnet_symmetric_nats_require_relay() -> Result<(), Error> {
+    let stun_server_url = Url {
+        scheme: SchemeType::Stun,
+        host: VNET_STUN_SERVER_IP.to_owned(),
+        port: VNET_STUN_SERVER_PORT,
+        proto: ProtoType::Udp,
+        ..Default::default()
+    };
+
+    let turn_server_url = Url {
+        scheme: SchemeType::Turn,
+        host: VNET_STUN_SERVER_IP.to_owned(),
+        port: VNET_STUN_SERVER_PORT,
+        username: "user".to_owned(),
+        password: "pass".to_owned(),
+        proto: ProtoType::Udp,
+    };
+
+    // The srflx candidates of Agent1 are useless behind a symmetric NAT, so the only working
+    // path goes through the relay of Agent0
+    let v = build_vnet(nat::NatType::symmetric(), nat::NatType::symmetric()).await?;
+
+    let a0test_config = AgentTestConfig {
+        urls: vec![stun_server_url.clone(), turn_server_url.clone()],
+        ..Default::default()
+    };
+    let a1test_config = AgentTestConfig {
+        urls: vec![stun_server_url.clone()],
+        ..Default::default()
+    };
+    let (a_agent, b_agent, _ca, _cb) =
+        pipe_agents_with_vnet(&v, a0test_config, a1test_config).await?;
+
+    let (local, _) = selected_candidate_types(&a_agent).ok_or(Error::ErrNoCandidatePairs)?;
+    assert_eq!(local, CandidateType::Relay, "Agent0 should use its relay");
+    let (_, remote) = selected_candidate_types(&b_agent).ok_or(Error::ErrNoCandidatePairs)?;
+    assert_eq!(remote, CandidateType::Relay, "Agent1 should use the relay");
+
+    a_agent.close().await?;
+    b_agent.close().await?;
+    v.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_connectivity_vnet_relay_over_tcp_with_udp_blocked() -> Result<(), Error> {
+    const TURN_TCP_SERVER_IP: &str = "1.2.3.5";
+
+    let v = build_vnet(nat::NatType::full_cone(), nat::NatType::full_cone()).await?;
+
+    // Agent0 can't send or receive any UDP, only TCP leaves its LAN
+    {
+        let w = v.wan.lock().await;
+        w.add_chunk_filter(Box::new(|c: &(dyn Chunk + Send + Sync)| {
+            let global_ip = IpAddr::from_str(VNET_GLOBAL_IPA).unwrap();
+            c.network() != "udp"
+                || (c.get_source_ip() != global_ip && c.get_destination_ip() != global_ip)
+        }))
+        .await;
+    }
+
+    // A TURN server accepting TCP connections, packets are relayed over UDP
+    let tnet = Arc::new(net::Net::new(Some(net::NetConfig {
+        static_ip: TURN_TCP_SERVER_IP.to_owned(),
+        ..Default::default()
+    })));
+    connect_net2router(&tnet, &v.wan).await?;
+    let server_addr =
+        SocketAddr::from_str(&format!("{}:{}", TURN_TCP_SERVER_IP, VNET_STUN_SERVER_PORT))?;
+    let server_listener =
+        TcpPacketConn::listen_with_net(&tnet, server_addr, TcpFraming::Stun).await?;
+    let server = turn::server::Server::new(turn::server::config::ServerConfig {
+        conn_configs: vec![turn::server::config::ConnConfig {
+            conn: Arc::new(server_listener),
+            relay_addr_generator: Box::new(
+                turn::relay::relay_static::RelayAddressGeneratorStatic {
+                    relay_address: IpAddr::from_str(TURN_TCP_SERVER_IP)?,
+                    address: "0.0.0.0".to_owned(),
+                    net: tnet,
+                },
+            ),
+        }],
+        realm: "webrtc.rs".to_owned(),
+        auth_handler: Arc::new(TestAuthHandler::new()),
+        channel_bind_timeout: Duration::from_secs(0),
+    })
+    .await?;
+
+    let a0test_config = AgentTestConfig {
+        urls: vec![Url {
+            scheme: SchemeType::Turn,
+            host: TURN_TCP_SERVER_IP.to_owned(),
+            port: VNET_STUN_SERVER_PORT,
+            username: "user".to_owned(),
+            password: "pass".to_owned(),
+            proto: ProtoType::Tcp,
+        }],
+        turn_over_tcp: true,
+        ..Default::default()
+    };
+    let a1test_config = AgentTestConfig {
+        urls: vec![Url {
+            scheme: SchemeType::Stun,
+            host: VNET_STUN_SERVER_IP.to_owned(),
+            port: VNET_STUN_SERVER_PORT,
+            proto: ProtoType::Udp,
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let (a_agent, b_agent, _ca, _cb) =
+        pipe_agents_with_vnet(&v, a0test_config, a1test_config).await?;
+
+    let (local, _) = selected_candidate_types(&a_agent).ok_or(Error::ErrNoCandidatePairs)?;
+    assert_eq!(local, CandidateType::Relay, "Agent0 should use its relay");
+    let (_, remote) = selected_candidate_types(&b_agent).ok_or(Error::ErrNoCandidatePairs)?;
+    assert_eq!(remote, CandidateType::Relay, "Agent1 should use the relay");
+
+    a_agent.close().await?;
+    b_agent.close().await?;
+    server.close().await?;
+    v.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_connectivity_vnet_port_restricted_nat_with_prflx_candidate() -> Result<(), Error> {
+    let stun_server_url = Url {
+        scheme: SchemeType::Stun,
+        host: VNET_STUN_SERVER_IP.to_owned(),
+        port: VNET_STUN_SERVER_PORT,
+        proto: ProtoType::Udp,
+        ..Default::default()
+    };
+
+    // Agent0 is behind a port-restricted cone NAT and only knows its host candidate, so
+    // Agent1 learns its mapped address as a peer-reflexive candidate from the first check
+    let v = build_vnet(
+        nat::NatType::port_restricted_cone(),
+        nat::NatType::full_cone(),
+    )
+    .await?;
+
+    let a0test_config = AgentTestConfig {
+        urls: vec![],
+        ..Default::default()
+    };
+    let a1test_config = AgentTestConfig {
+        urls: vec![stun_server_url.clone()],
+        ..Default::default()
+    };
+    let (a_agent, b_agent, _ca, _cb) =
+        pipe_agents_with_vnet(&v, a0test_config, a1test_config).await?;
+
+    let (local, remote) = selected_candidate_types(&a_agent).ok_or(Error::ErrNoCandidatePairs)?;
+    assert_eq!(local, CandidateType::Host, "should match");
+    assert_eq!(remote, CandidateType::ServerReflexive, "should match");
+    let (_, remote) = selected_candidate_types(&b_agent).ok_or(Error::ErrNoCandidatePairs)?;
+    assert_eq!(remote, CandidateType::PeerReflexive, "should match");
+
+    a_agent.close().await?;
+    b_agent.close().await?;
+    v.close().await?;
+
+    Ok(())
+}
+
 async fn block_until_state_seen(
     expected_state: ConnectionState,
     state_queue: &mut mpsc::Receiver<ConnectionState>,
@@ -1011,3 +1249,464 @@ async fn test_write_use_valid_pair() -> Result<(), Error> {
 
     Ok(())
 }
+
+// test_backup_pair_failover asserts that valid pairs other than the selected one are kept alive
+// through a NAT that expires idle bindings, so the agent moves over to one of them as soon as the
+// selected path is cut instead of going to disconnected.
+#[tokio::test]
+async fn test_backup_pair_failover() -> Result<(), Error> {
+    const LOCAL_IPA0: &str = "192.168.0.1";
+    const LOCAL_IPA1: &str = "192.168.0.2";
+
+    let wan = Arc::new(Mutex::new(router::Router::new(router::RouterConfig {
+        cidr: "0.0.0.0/0".to_owned(),
+        ..Default::default()
+    })?));
+
+    // LAN with a NAT that forgets bindings left idle for 30 seconds
+    let lan = router::Router::new(router::RouterConfig {
+        static_ips: vec![VNET_GLOBAL_IPA.to_owned()],
+        cidr: format!("{}/{}", VNET_LOCAL_IPA, VNET_LOCAL_SUBNET_MASK_A),
+        nat_type: Some(nat::NatType {
+            mapping_behavior: nat::EndpointDependencyType::EndpointIndependent,
+            filtering_behavior: nat::EndpointDependencyType::EndpointIndependent,
+            mapping_life_time: Duration::from_secs(30),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })?;
+
+    // Holds the local address whose traffic is dropped, 0 lets everything through
+    let cut_ip = Arc::new(AtomicU64::new(0));
+    let cut_ip2 = Arc::clone(&cut_ip);
+    lan.add_chunk_filter(Box::new(move |c: &(dyn Chunk + Send + Sync)| -> bool {
+        let cut = cut_ip2.load(Ordering::SeqCst);
+        ![c.get_source_ip(), c.get_destination_ip()]
+            .iter()
+            .any(|ip| matches!(ip, IpAddr::V4(ip) if u64::from(u32::from(*ip)) == cut))
+    }))
+    .await;
+    let lan = Arc::new(Mutex::new(lan));
+
+    // The agent behind the NAT has two paths to the remote agent
+    let net0 = Arc::new(net::Net::new(Some(net::NetConfig {
+        static_ips: vec![LOCAL_IPA0.to_owned(), LOCAL_IPA1.to_owned()],
+        ..Default::default()
+    })));
+    let net1 = Arc::new(net::Net::new(Some(net::NetConfig {
+        static_ips: vec![VNET_GLOBAL_IPB.to_owned()],
+        ..Default::default()
+    })));
+
+    connect_net2router(&net0, &lan).await?;
+    connect_router2router(&lan, &wan).await?;
+    connect_net2router(&net1, &wan).await?;
+    start_router(&wan).await?;
+
+    let backup_keepalive_interval = Duration::from_secs(10);
+
+    let a_agent = Arc::new(
+        Agent::new(AgentConfig {
+            network_types: supported_network_types(),
+            multicast_dns_mode: MulticastDnsMode::Disabled,
+            net: Some(Arc::clone(&net0)),
+            backup_keepalive_interval: Some(backup_keepalive_interval),
+            ..Default::default()
+        })
+        .await?,
+    );
+
+    let b_agent = Arc::new(
+        Agent::new(AgentConfig {
+            network_types: supported_network_types(),
+            multicast_dns_mode: MulticastDnsMode::Disabled,
+            net: Some(Arc::clone(&net1)),
+            backup_keepalive_interval: Some(backup_keepalive_interval),
+            ..Default::default()
+        })
+        .await?,
+    );
+
+    let (a_state_changes_tx, mut a_state_changes_rx) = mpsc::channel::<ConnectionState>(100);
+    let a_state_changes_tx = Arc::new(a_state_changes_tx);
+    a_agent.on_connection_state_change(Box::new(move |c: ConnectionState| {
+        let a_state_changes_tx_clone = Arc::clone(&a_state_changes_tx);
+        Box::pin(async move {
+            let _ = a_state_changes_tx_clone.try_send(c);
+        })
+    }));
+
+    let (a_pair_changes_tx, mut a_pair_changes_rx) = mpsc::unbounded_channel();
+    a_agent.on_selected_candidate_pair_change(Box::new(
+        move |change: &SelectedCandidatePairChange| {
+            let _ = a_pair_changes_tx.send(change.clone());
+            Box::pin(async {})
+        },
+    ));
+
+    let (a_conn, b_conn) = connect_with_vnet(&a_agent, &b_agent).await?;
+    block_until_state_seen(ConnectionState::Connected, &mut a_state_changes_rx).await;
+
+    // Stay idle on the backup path for twice the NAT binding lifetime
+    tokio::time::sleep(Duration::from_secs(60)).await;
+
+    let selected_ip = a_agent
+        .internal
+        .agent_conn
+        .get_selected_pair()
+        .map(|p| p.local.address())
+        .expect("a pair should be selected");
+
+    let backup_stats = a_agent
+        .get_candidate_pairs_stats()
+        .await
+        .into_iter()
+        .find(|s| s.state == CandidatePairState::Succeeded && !s.nominated)
+        .expect("a backup pair should still be valid");
+    let last_keepalive = backup_stats
+        .last_keepalive_timestamp
+        .expect("the backup pair should have been kept alive");
+    assert!(
+        SystemTime::now()
+            .duration_since(last_keepalive)
+            .unwrap_or_else(|_| Duration::from_secs(0))
+            <= backup_keepalive_interval + Duration::from_secs(2),
+        "the backup pair should be kept alive every interval"
+    );
+
+    // Cut the selected path
+    let ip = Ipv4Addr::from_str(&selected_ip)?;
+    cut_ip.store(u64::from(u32::from(ip)), Ordering::SeqCst);
+
+    let mut failed_over = false;
+    for _ in 0..100 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        if let Some(p) = a_agent.internal.agent_conn.get_selected_pair() {
+            if p.local.address() != selected_ip {
+                failed_over = true;
+                break;
+            }
+        }
+    }
+    assert!(failed_over, "should fail over to the backup pair");
+
+    while let Ok(state) = a_state_changes_rx.try_recv() {
+        assert_ne!(
+            state,
+            ConnectionState::Disconnected,
+            "should not go to disconnected while a backup pair is warm"
+        );
+    }
+
+    let test_message = "Test Message";
+    a_conn.send(test_message.as_bytes()).await?;
+    let mut read_buf = vec![0u8; test_message.len()];
+    let n = tokio::time::timeout(Duration::from_secs(5), b_conn.recv(&mut read_buf))
+        .await
+        .expect("should receive over the backup pair")?;
+    assert_eq!(&read_buf[..n], test_message.as_bytes(), "should match");
+
+    let mut changes = vec![];
+    while let Ok(change) = a_pair_changes_rx.try_recv() {
+        changes.push(change);
+    }
+    let reasons: Vec<SelectedCandidatePairChangeReason> =
+        changes.iter().map(|change| change.reason).collect();
+    assert_eq!(
+        reasons,
+        vec![
+            SelectedCandidatePairChangeReason::InitialNomination,
+            SelectedCandidatePairChangeReason::ConsentLossFailover
+        ]
+    );
+    assert!(changes[0].previous.is_none());
+    assert_eq!(changes[0].current.local.address(), selected_ip);
+    let previous = changes[1]
+        .previous
+        .as_ref()
+        .expect("the failover should report the pair that lost consent");
+    assert_eq!(previous.local.address(), selected_ip);
+    assert_ne!(changes[1].current.local.address(), selected_ip);
+    assert!(changes[0].timestamp <= changes[1].timestamp);
+
+    let history: Vec<SelectedCandidatePairChangeReason> = a_agent
+        .get_selected_candidate_pair_changes()
+        .iter()
+        .map(|change| change.reason)
+        .collect();
+    assert_eq!(history, reasons, "the history should match the events");
+
+    {
+        let mut w = wan.lock().await;
+        w.stop().await?;
+    }
+
+    a_agent.close().await?;
+    b_agent.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_consent_checks_measure_round_trip_time() -> Result<(), Error> {
+    // Every packet is held for 80ms in each direction
+    let wan = Arc::new(Mutex::new(router::Router::new(router::RouterConfig {
+        cidr: "0.0.0.0/0".to_owned(),
+        min_delay: Duration::from_millis(80),
+        ..Default::default()
+    })?));
+
+    let net0 = Arc::new(net::Net::new(Some(net::NetConfig {
+        static_ips: vec!["192.168.0.1".to_owned()],
+        ..Default::default()
+    })));
+    let net1 = Arc::new(net::Net::new(Some(net::NetConfig {
+        static_ips: vec!["192.168.0.2".to_owned()],
+        ..Default::default()
+    })));
+
+    connect_net2router(&net0, &wan).await?;
+    connect_net2router(&net1, &wan).await?;
+    start_router(&wan).await?;
+
+    let keepalive_interval = Duration::from_millis(500);
+
+    let controlling_agent = Arc::new(
+        Agent::new(AgentConfig {
+            network_types: supported_network_types(),
+            multicast_dns_mode: MulticastDnsMode::Disabled,
+            net: Some(Arc::clone(&net0)),
+            keepalive_interval: Some(keepalive_interval),
+            ..Default::default()
+        })
+        .await?,
+    );
+
+    let controlled_agent = Arc::new(
+        Agent::new(AgentConfig {
+            network_types: supported_network_types(),
+            multicast_dns_mode: MulticastDnsMode::Disabled,
+            net: Some(Arc::clone(&net1)),
+            keepalive_interval: Some(keepalive_interval),
+            ..Default::default()
+        })
+        .await?,
+    );
+
+    connect_with_vnet(&controlling_agent, &controlled_agent).await?;
+
+    let selected_pair = controlling_agent
+        .get_selected_candidate_pair()
+        .expect("a pair should be selected");
+    let responses_at_selection = selected_pair.responses_received();
+
+    // Let a few consent checks go by on the idle connection
+    tokio::time::sleep(keepalive_interval * 6).await;
+
+    let rtt = selected_pair
+        .current_round_trip_time()
+        .expect("the consent checks should measure the round trip time");
+    assert!(
+        (Duration::from_millis(150)..=Duration::from_millis(170)).contains(&rtt),
+        "round trip time {:?} should be about twice the one-way delay",
+        rtt
+    );
+    assert!(
+        selected_pair.responses_received() >= responses_at_selection + 2,
+        "consent checks should keep being answered"
+    );
+
+    let stats = controlling_agent
+        .get_candidate_pairs_stats()
+        .await
+        .into_iter()
+        .find(|s| s.nominated)
+        .expect("the selected pair should have stats");
+    assert!(stats.consent_requests_sent >= 2);
+    assert!(stats.requests_sent >= stats.responses_received);
+    assert_eq!(stats.responses_received, selected_pair.responses_received());
+    assert!((0.150..=0.170).contains(&stats.current_round_trip_time));
+    let average_rtt = stats.total_round_trip_time / stats.responses_received as f64;
+    assert!((0.150..=0.170).contains(&average_rtt));
+
+    {
+        let mut w = wan.lock().await;
+        w.stop().await?;
+    }
+
+    controlling_agent.close().await?;
+    controlled_agent.close().await?;
+
+    Ok(())
+}
+
+// test_renomination_prefers_low_cost_pair asserts that the controlling agent moves from a
+// cellular path to a Wi-Fi one of the remote agent which validates later, once it stayed valid for
+// the renomination hold time, going by the network cost the remote candidates are signaled with.
+#[tokio::test]
+async fn test_renomination_prefers_low_cost_pair() -> Result<(), Error> {
+    const CELLULAR_IP: &str = "28.1.1.1";
+    const WIFI_IP: &str = "28.1.1.2";
+
+    let wan = Arc::new(Mutex::new(router::Router::new(router::RouterConfig {
+        cidr: "0.0.0.0/0".to_owned(),
+        ..Default::default()
+    })?));
+    let net0 = Arc::new(net::Net::new(Some(net::NetConfig {
+        static_ips: vec![VNET_GLOBAL_IPA.to_owned()],
+        ..Default::default()
+    })));
+    let net1 = Arc::new(net::Net::new(Some(net::NetConfig {
+        static_ips: vec![CELLULAR_IP.to_owned(), WIFI_IP.to_owned()],
+        ..Default::default()
+    })));
+    connect_net2router(&net0, &wan).await?;
+    connect_net2router(&net1, &wan).await?;
+    start_router(&wan).await?;
+
+    let hold_time = Duration::from_secs(1);
+    let controlling = Arc::new(
+        Agent::new(AgentConfig {
+            network_types: supported_network_types(),
+            multicast_dns_mode: MulticastDnsMode::Disabled,
+            net: Some(Arc::clone(&net0)),
+            renomination: true,
+            renomination_hold_time: Some(hold_time),
+            prefer_low_cost: true,
+            ..Default::default()
+        })
+        .await?,
+    );
+    let wifi_ip = IpAddr::from_str(WIFI_IP)?;
+    let controlled = Arc::new(
+        Agent::new(AgentConfig {
+            network_types: supported_network_types(),
+            multicast_dns_mode: MulticastDnsMode::Disabled,
+            net: Some(Arc::clone(&net1)),
+            renomination: true,
+            network_cost: Arc::new(Some(Box::new(
+                move |_: &str, ip: IpAddr| {
+                    if ip == wifi_ip {
+                        10
+                    } else {
+                        900
+                    }
+                },
+            ))),
+            ..Default::default()
+        })
+        .await?,
+    );
+    let options = IceOptions {
+        trickle: true,
+        renomination: true,
+        ice2: false,
+    };
+    controlling.set_remote_options(options);
+    controlled.set_remote_options(options);
+
+    // Only the cellular candidate of the controlled agent is known at first
+    for agent in [&controlling, &controlled].iter() {
+        let (done_tx, mut done_rx) = mpsc::channel::<()>(1);
+        let done_tx = Arc::new(Mutex::new(Some(done_tx)));
+        agent.on_candidate(Box::new(
+            move |candidate: Option<Arc<dyn Candidate + Send + Sync>>| {
+                let done_tx = Arc::clone(&done_tx);
+                Box::pin(async move {
+                    if candidate.is_none() {
+                        done_tx.lock().await.take();
+                    }
+                })
+            },
+        ));
+        agent.gather_candidates()?;
+        let _ = done_rx.recv().await;
+    }
+    for c in controlling.get_local_candidates().await? {
+        let c: Arc<dyn Candidate + Send + Sync> = Arc::new(unmarshal_candidate(&c.marshal())?);
+        controlled.add_remote_candidate(&c)?;
+    }
+    let mut remote_candidates = vec![];
+    for c in controlled.get_local_candidates().await? {
+        let c: Arc<dyn Candidate + Send + Sync> = Arc::new(unmarshal_candidate(&c.marshal())?);
+        assert_eq!(
+            c.network_cost(),
+            if c.address() == WIFI_IP { 10 } else { 900 }
+        );
+        remote_candidates.push(c);
+    }
+    let (wifi, cellular): (Vec<_>, Vec<_>) = remote_candidates
+        .into_iter()
+        .partition(|c| c.address() == WIFI_IP);
+    for c in &cellular {
+        controlling.add_remote_candidate(c)?;
+    }
+
+    let (controlling_ufrag, controlling_pwd) = controlling.get_local_user_credentials().await;
+    let (controlled_ufrag, controlled_pwd) = controlled.get_local_user_credentials().await;
+    let controlled2 = Arc::clone(&controlled);
+    tokio::spawn(async move {
+        let (_cancel_tx, cancel_rx) = mpsc::channel(1);
+        controlled2
+            .accept(cancel_rx, controlling_ufrag, controlling_pwd)
+            .await
+    });
+    let (_cancel_tx, cancel_rx) = mpsc::channel(1);
+    let _conn = controlling
+        .dial(cancel_rx, controlled_ufrag, controlled_pwd)
+        .await?;
+
+    let selected_remote = |agent: &Agent| {
+        agent
+            .get_selected_candidate_pair()
+            .map(|p| p.remote.address())
+    };
+    assert_eq!(selected_remote(&controlling).as_deref(), Some(CELLULAR_IP));
+
+    // The Wi-Fi path comes up
+    let added = Instant::now();
+    for c in &wifi {
+        controlling.add_remote_candidate(c)?;
+    }
+
+    let mut renominated = None;
+    for _ in 0..100 {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        if selected_remote(&controlling).as_deref() == Some(WIFI_IP) {
+            renominated = Some(added.elapsed());
+            break;
+        }
+    }
+    let renominated = renominated.expect("should renominate the low cost pair");
+    assert!(
+        renominated >= hold_time,
+        "renominated after {:?}, before the hold time",
+        renominated
+    );
+
+    // The controlled agent follows the nomination
+    let mut followed = false;
+    for _ in 0..40 {
+        if controlled
+            .get_selected_candidate_pair()
+            .map_or(false, |p| p.local.address() == WIFI_IP)
+        {
+            followed = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(
+        followed,
+        "the controlled agent should select the low cost pair"
+    );
+
+    {
+        let mut w = wan.lock().await;
+        w.stop().await?;
+    }
+    controlling.close().await?;
+    controlled.close().await?;
+
+    Ok(())
+}
@@ -19,6 +19,9 @@ use crate::error::*;
 use crate::external_ip_mapper::*;
 use crate::mdns::*;
 use crate::network_type::*;
+use crate::options::IceOptions;
+use crate::pacer::*;
+use crate::resolver::*;
 use crate::state::*;
 use crate::udp_mux::UDPMux;
 use crate::udp_network::UDPNetwork;
@@ -29,11 +32,11 @@ use agent_stats::*;
 
 use mdns::conn::*;
 use std::collections::HashMap;
-use std::net::{Ipv4Addr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use stun::{agent::*, attributes::*, fingerprint::*, integrity::*, message::*, xoraddr::*};
-use util::{vnet::net::*, Buffer};
+use util::{sync::Mutex as SyncMutex, vnet::net::*, Buffer};
 
-use crate::agent::agent_gather::GatherCandidatesInternalParams;
+use crate::agent::agent_gather::{CandidateError, GatherCandidatesInternalParams};
 use crate::rand::*;
 use crate::tcp_type::TcpType;
 use std::future::Future;
@@ -69,10 +72,7 @@ pub type OnConnectionStateChangeHdlrFn = Box<
         + Sync,
 >;
 pub type OnSelectedCandidatePairChangeHdlrFn = Box<
-    dyn (FnMut(
-            &Arc<dyn Candidate + Send + Sync>,
-            &Arc<dyn Candidate + Send + Sync>,
-        ) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>)
+    dyn (FnMut(&SelectedCandidatePairChange) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>)
         + Send
         + Sync,
 >;
@@ -83,12 +83,15 @@ pub type OnCandidateHdlrFn = Box<
         + Send
         + Sync,
 >;
+pub type OnCandidateErrorHdlrFn = Box<
+    dyn (FnMut(CandidateError) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>) + Send + Sync,
+>;
 pub type GatherCandidateCancelFn = Box<dyn Fn() + Send + Sync>;
 
 struct ChanReceivers {
     chan_state_rx: mpsc::Receiver<ConnectionState>,
     chan_candidate_rx: mpsc::Receiver<Option<Arc<dyn Candidate + Send + Sync>>>,
-    chan_candidate_pair_rx: mpsc::Receiver<()>,
+    chan_candidate_pair_rx: mpsc::Receiver<SelectedCandidatePairChange>,
 }
 
 /// Represents the ICE agent.
@@ -106,9 +109,13 @@ pub struct Agent {
     // 1:1 D-NAT IP address mapping
     pub(crate) ext_ip_mapper: Arc<Option<ExternalIpMapper>>,
     pub(crate) gathering_state: Arc<AtomicU8>, //GatheringState,
-    pub(crate) candidate_types: Vec<CandidateType>,
+    pub(crate) candidate_types: SyncMutex<Vec<CandidateType>>,
     pub(crate) urls: Vec<Url>,
     pub(crate) network_types: Vec<NetworkType>,
+    pub(crate) tcp_active_candidates: bool,
+    pub(crate) turn_over_tcp: bool,
+    pub(crate) resolver: Arc<dyn Resolver + Send + Sync>,
+    pub(crate) dns_timeout: Duration,
 
     pub(crate) gather_candidate_cancel: Option<GatherCandidateCancelFn>,
 }
@@ -192,6 +199,12 @@ impl Agent {
             Arc::new(Net::new(None))
         };
 
+        let resolver = config
+            .resolver
+            .clone()
+            .unwrap_or_else(|| Arc::new(NetResolver::new(Arc::clone(&net))));
+
+        let gathering_state = Arc::clone(&ai.gathering_state);
         let agent = Self {
             udp_network: config.udp_network,
             internal: Arc::new(ai),
@@ -202,10 +215,14 @@ impl Agent {
             mdns_conn,
             net,
             ext_ip_mapper: Arc::new(ext_ip_mapper),
-            gathering_state: Arc::new(AtomicU8::new(0)), //GatheringState::New,
-            candidate_types,
+            gathering_state,
+            candidate_types: SyncMutex::new(candidate_types),
             urls: config.urls.clone(),
             network_types: config.network_types.clone(),
+            tcp_active_candidates: config.tcp_active_candidates,
+            turn_over_tcp: config.turn_over_tcp,
+            resolver,
+            dns_timeout: config.dns_timeout.unwrap_or(DEFAULT_DNS_TIMEOUT),
 
             gather_candidate_cancel: None, //TODO: add cancel
         };
@@ -234,6 +251,65 @@ impl Agent {
         self.internal.agent_conn.bytes_sent()
     }
 
+    /// Returns the number of remote candidates that were ignored because their type is not in
+    /// `AgentConfig::remote_candidate_types`.
+    pub fn get_filtered_remote_candidates(&self) -> u64 {
+        self.internal
+            .filtered_remote_candidates
+            .load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of inbound connectivity checks that were rejected, by reason.
+    pub fn get_rejected_binding_request_stats(&self) -> RejectedBindingRequestStats {
+        *self.internal.rejected_binding_requests.lock()
+    }
+
+    /// Replaces the types of local candidates to gather and the allow list of remote candidate
+    /// types, see `AgentConfig::candidate_types` and `AgentConfig::remote_candidate_types`.
+    /// Remote candidates are filtered with the new list right away, while local candidates of the
+    /// new types are only gathered on the next `gather_candidates`, i.e. after a restart.
+    pub fn set_candidate_types(
+        &self,
+        candidate_types: Vec<CandidateType>,
+        remote_candidate_types: Vec<CandidateType>,
+    ) -> Result<()> {
+        let candidate_types = if candidate_types.is_empty() {
+            default_candidate_types()
+        } else {
+            candidate_types
+        };
+
+        if self.internal.lite.load(Ordering::SeqCst)
+            && (candidate_types.len() != 1 || candidate_types[0] != CandidateType::Host)
+        {
+            return Err(Error::ErrLiteUsingNonHostCandidates);
+        }
+
+        if !self.urls.is_empty()
+            && !contains_candidate_type(CandidateType::ServerReflexive, &candidate_types)
+            && !contains_candidate_type(CandidateType::Relay, &candidate_types)
+        {
+            return Err(Error::ErrUselessUrlsProvided);
+        }
+
+        if let Some(ext_ip_mapper) = &*self.ext_ip_mapper {
+            if ext_ip_mapper.candidate_type == CandidateType::Host
+                && !contains_candidate_type(CandidateType::Host, &candidate_types)
+            {
+                return Err(Error::ErrIneffectiveNat1to1IpMappingHost);
+            }
+            if ext_ip_mapper.candidate_type == CandidateType::ServerReflexive
+                && !contains_candidate_type(CandidateType::ServerReflexive, &candidate_types)
+            {
+                return Err(Error::ErrIneffectiveNat1to1IpMappingSrflx);
+            }
+        }
+
+        *self.candidate_types.lock() = candidate_types;
+        *self.internal.remote_candidate_types.lock() = remote_candidate_types;
+        Ok(())
+    }
+
     /// Sets a handler that is fired when the connection state changes.
     pub fn on_connection_state_change(&self, f: OnConnectionStateChangeHdlrFn) {
         self.internal
@@ -241,7 +317,19 @@ impl Agent {
             .store(Some(Arc::new(Mutex::new(f))))
     }
 
-    /// Sets a handler that is fired when the final candidate pair is selected.
+    /// Returns the latest changes of the selected candidate pair, oldest first. At most
+    /// `MAX_SELECTED_CANDIDATE_PAIR_CHANGES` are kept.
+    pub fn get_selected_candidate_pair_changes(&self) -> Vec<SelectedCandidatePairChange> {
+        self.internal
+            .selected_pair_changes
+            .lock()
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Sets a handler that is fired when the selected candidate pair changes, with the previous
+    /// and the new pair and the reason of the change.
     pub fn on_selected_candidate_pair_change(&self, f: OnSelectedCandidatePairChangeHdlrFn) {
         self.internal
             .on_selected_candidate_pair_change_hdlr
@@ -256,6 +344,14 @@ impl Agent {
             .store(Some(Arc::new(Mutex::new(f))));
     }
 
+    /// Sets a handler that is fired when candidates can't be gathered from a STUN or TURN
+    /// server, e.g. because its host name can't be resolved or none of its addresses responds.
+    pub fn on_candidate_error(&self, f: OnCandidateErrorHdlrFn) {
+        self.internal
+            .on_candidate_error_hdlr
+            .store(Some(Arc::new(Mutex::new(f))));
+    }
+
     /// Adds a new remote candidate.
     pub fn add_remote_candidate(&self, c: &Arc<dyn Candidate + Send + Sync>) -> Result<()> {
         // cannot check for network yet because it might not be applied
@@ -284,7 +380,7 @@ impl Agent {
             let ai = Arc::clone(&self.internal);
             let host_candidate = Arc::clone(c);
             let mdns_conn = self.mdns_conn.clone();
-            tokio::spawn(async move {
+            self.internal.tasks.spawn(async move {
                 if let Some(mdns_conn) = mdns_conn {
                     if let Ok(candidate) =
                         Self::resolve_and_add_multicast_candidate(mdns_conn, host_candidate).await
@@ -296,7 +392,7 @@ impl Agent {
         } else {
             let ai = Arc::clone(&self.internal);
             let candidate = Arc::clone(c);
-            tokio::spawn(async move {
+            self.internal.tasks.spawn(async move {
                 ai.add_remote_candidate(&candidate).await;
             });
         }
@@ -332,6 +428,39 @@ impl Agent {
         (ufrag_pwd.remote_ufrag.clone(), ufrag_pwd.remote_pwd.clone())
     }
 
+    /// Returns the ICE options supported by the local agent, to be signaled in a=ice-options.
+    pub fn get_local_options(&self) -> IceOptions {
+        IceOptions {
+            trickle: true,
+            renomination: self.internal.renomination,
+            ice2: false,
+        }
+    }
+
+    /// Returns the ICE options last signaled by the remote agent.
+    pub fn get_remote_options(&self) -> IceOptions {
+        *self.internal.remote_options.lock()
+    }
+
+    /// Sets the ICE options signaled by the remote agent, they are reset by `restart`.
+    /// Renomination is only used if both agents support it, and the candidates of a
+    /// remote agent that doesn't trickle are complete, as if it had signaled end-of-candidates.
+    pub fn set_remote_options(&self, options: IceOptions) {
+        *self.internal.remote_options.lock() = options;
+        if !options.trickle {
+            self.set_remote_end_of_candidates();
+        }
+    }
+
+    /// Signals that the remote agent won't send more candidates. Once local gathering is
+    /// complete too, the agent fails as soon as every candidate pair failed, rather than
+    /// waiting for the failed timeout.
+    pub fn set_remote_end_of_candidates(&self) {
+        self.internal
+            .remote_candidates_complete
+            .store(true, Ordering::SeqCst);
+    }
+
     /// Cleans up the Agent.
     pub async fn close(&self) -> Result<()> {
         if let Some(gather_candidate_cancel) = &self.gather_candidate_cancel {
@@ -344,7 +473,19 @@ impl Agent {
         }
 
         //FIXME: deadlock here
-        self.internal.close().await
+        self.internal.close().await?;
+
+        // The tasks of the agent stop once it is closed, wait for them to release their sockets
+        let cancelled = self.internal.tasks.close(TASKS_CLOSE_TIMEOUT).await;
+        if cancelled > 0 {
+            log::debug!(
+                "[{}]: cancelled {} tasks which didn't stop on close",
+                self.internal.get_name(),
+                cancelled
+            );
+        }
+
+        Ok(())
     }
 
     /// Returns the selected pair or nil if there is none
@@ -406,6 +547,10 @@ impl Agent {
             ufrag_pwd.remote_ufrag = String::new();
             ufrag_pwd.remote_pwd = String::new();
         }
+        *self.internal.remote_options.lock() = IceOptions::default();
+        self.internal
+            .remote_candidates_complete
+            .store(false, Ordering::SeqCst);
         {
             let mut pending_binding_requests = self.internal.pending_binding_requests.lock().await;
             *pending_binding_requests = vec![];
@@ -415,6 +560,7 @@ impl Agent {
             let mut checklist = self.internal.agent_conn.checklist.lock().await;
             *checklist = vec![];
         }
+        self.internal.triggered_checks.lock().clear();
 
         self.internal.set_selected_pair(None).await;
         self.internal.delete_all_candidates().await;
@@ -449,9 +595,13 @@ impl Agent {
 
         let params = GatherCandidatesInternalParams {
             udp_network: self.udp_network.clone(),
-            candidate_types: self.candidate_types.clone(),
+            candidate_types: self.candidate_types.lock().clone(),
             urls: self.urls.clone(),
             network_types: self.network_types.clone(),
+            tcp_active_candidates: self.tcp_active_candidates,
+            turn_over_tcp: self.turn_over_tcp,
+            resolver: Arc::clone(&self.resolver),
+            dns_timeout: self.dns_timeout,
             mdns_mode: self.mdns_mode,
             mdns_name: self.mdns_name.clone(),
             net: Arc::clone(&self.net),
@@ -462,7 +612,7 @@ impl Agent {
             gathering_state: Arc::clone(&self.gathering_state),
             chan_candidate_tx: Arc::clone(&self.internal.chan_candidate_tx),
         };
-        tokio::spawn(async move {
+        self.internal.tasks.spawn(async move {
             Self::gather_candidates_internal(params).await;
         });
 
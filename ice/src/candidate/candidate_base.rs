@@ -29,6 +29,12 @@ pub struct CandidateBaseConfig {
     pub component: u16,
     pub priority: u32,
     pub foundation: String,
+    /// Identifies the network interface of the candidate, 0 if unknown. Signaled as the
+    /// `network-id` extension.
+    pub network_id: u16,
+    /// The cost of sending over the network of the candidate, 0 if unknown. Signaled as the
+    /// `network-cost` extension.
+    pub network_cost: u16,
     pub conn: Option<Arc<dyn util::Conn + Send + Sync>>,
     pub initialized_ch: Option<broadcast::Receiver<()>>,
 }
@@ -43,6 +49,8 @@ pub struct CandidateBase {
     pub(crate) port: u16,
     pub(crate) related_address: Option<CandidateRelatedAddress>,
     pub(crate) tcp_type: TcpType,
+    pub(crate) network_id: u16,
+    pub(crate) network_cost: u16,
 
     pub(crate) resolved_addr: SyncMutex<SocketAddr>,
 
@@ -73,6 +81,8 @@ impl Default for CandidateBase {
             port: 0,
             related_address: None,
             tcp_type: TcpType::default(),
+            network_id: 0,
+            network_cost: 0,
 
             resolved_addr: SyncMutex::new(SocketAddr::new(IpAddr::from([0, 0, 0, 0]), 0)),
 
@@ -205,6 +215,14 @@ impl Candidate for CandidateBase {
         self.tcp_type
     }
 
+    fn network_id(&self) -> u16 {
+        self.network_id
+    }
+
+    fn network_cost(&self) -> u16 {
+        self.network_cost
+    }
+
     /// Returns the string representation of the ICECandidate.
     fn marshal(&self) -> String {
         let mut val = format!(
@@ -218,10 +236,7 @@ impl Candidate for CandidateBase {
             self.candidate_type()
         );
 
-        if self.tcp_type != TcpType::Unspecified {
-            val += format!(" tcptype {}", self.tcp_type()).as_str();
-        }
-
+        // The related address comes before the extensions, see RFC 5245 Section 15.1
         if let Some(related_address) = self.related_address() {
             val += format!(
                 " raddr {} rport {}",
@@ -230,6 +245,18 @@ impl Candidate for CandidateBase {
             .as_str();
         }
 
+        if self.tcp_type != TcpType::Unspecified {
+            val += format!(" tcptype {}", self.tcp_type()).as_str();
+        }
+
+        if self.network_id != 0 {
+            val += format!(" network-id {}", self.network_id).as_str();
+        }
+
+        if self.network_cost != 0 {
+            val += format!(" network-cost {}", self.network_cost).as_str();
+        }
+
         val
     }
 
@@ -423,32 +450,57 @@ pub fn unmarshal_candidate(raw: &str) -> Result<impl Candidate> {
     let mut rel_addr = String::new();
     let mut rel_port = 0;
     let mut tcp_type = TcpType::Unspecified;
-
-    if split.len() > 8 {
-        let split2 = &split[8..];
-
-        if split2[0] == "raddr" {
-            if split2.len() < 4 {
-                return Err(Error::Other(format!(
-                    "{:?}: incorrect length",
-                    Error::ErrParseRelatedAddr
-                )));
+    let mut network_id = 0;
+    let mut network_cost = 0;
+
+    // Extensions are key/value pairs in any order, such as "raddr", "rport" and "tcptype"
+    let mut extensions = split[8..].iter();
+    while let Some(key) = extensions.next() {
+        match *key {
+            "raddr" => {
+                // RelatedAddress, followed by the RelatedPort
+                let value = extensions.next().ok_or_else(|| {
+                    Error::Other(format!(
+                        "{:?}: incorrect length",
+                        Error::ErrParseRelatedAddr
+                    ))
+                })?;
+                rel_addr = (*value).to_owned();
             }
-
-            // RelatedAddress
-            rel_addr = split2[1].to_owned();
-
-            // RelatedPort
-            rel_port = split2[3].parse()?;
-        } else if split2[0] == "tcptype" {
-            if split2.len() < 2 {
-                return Err(Error::Other(format!(
-                    "{:?}: incorrect length",
-                    Error::ErrParseType
-                )));
+            "rport" => {
+                let value = extensions.next().ok_or_else(|| {
+                    Error::Other(format!(
+                        "{:?}: incorrect length",
+                        Error::ErrParseRelatedAddr
+                    ))
+                })?;
+                rel_port = value.parse()?;
+            }
+            "tcptype" => {
+                let value = extensions.next().ok_or_else(|| {
+                    Error::Other(format!("{:?}: incorrect length", Error::ErrParseType))
+                })?;
+                tcp_type = TcpType::from(*value);
+            }
+            "network-id" => {
+                let value = extensions.next().ok_or_else(|| {
+                    Error::Other(format!("{:?}: incorrect length", Error::ErrParseNetworkId))
+                })?;
+                network_id = value.parse()?;
+            }
+            "network-cost" => {
+                let value = extensions.next().ok_or_else(|| {
+                    Error::Other(format!(
+                        "{:?}: incorrect length",
+                        Error::ErrParseNetworkCost
+                    ))
+                })?;
+                network_cost = value.parse()?;
+            }
+            // unknown extensions are ignored, along with their value
+            _ => {
+                extensions.next();
             }
-
-            tcp_type = TcpType::from(split2[1]);
         }
     }
 
@@ -462,6 +514,8 @@ pub fn unmarshal_candidate(raw: &str) -> Result<impl Candidate> {
                     component,
                     priority,
                     foundation,
+                    network_id,
+                    network_cost,
                     ..CandidateBaseConfig::default()
                 },
                 tcp_type,
@@ -477,6 +531,8 @@ pub fn unmarshal_candidate(raw: &str) -> Result<impl Candidate> {
                     component,
                     priority,
                     foundation,
+                    network_id,
+                    network_cost,
                     ..CandidateBaseConfig::default()
                 },
                 rel_addr,
@@ -493,6 +549,8 @@ pub fn unmarshal_candidate(raw: &str) -> Result<impl Candidate> {
                     component,
                     priority,
                     foundation,
+                    network_id,
+                    network_cost,
                     ..CandidateBaseConfig::default()
                 },
                 rel_addr,
@@ -510,6 +568,8 @@ pub fn unmarshal_candidate(raw: &str) -> Result<impl Candidate> {
                     component,
                     priority,
                     foundation,
+                    network_id,
+                    network_cost,
                     ..CandidateBaseConfig::default()
                 },
                 rel_addr,
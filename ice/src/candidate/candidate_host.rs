@@ -29,6 +29,8 @@ impl CandidateHostConfig {
             tcp_type: self.tcp_type,
             foundation_override: self.base_config.foundation,
             priority_override: self.base_config.priority,
+            network_id: self.base_config.network_id,
+            network_cost: self.base_config.network_cost,
             network: self.base_config.network,
             network_type: AtomicU8::new(NetworkType::Udp4 as u8),
             conn: self.base_config.conn,
@@ -155,3 +155,66 @@ fn test_candidate_pair_equality() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_candidate_pair_round_trip_time() -> Result<()> {
+    let pair = CandidatePair::new(
+        Arc::new(host_candidate()?),
+        Arc::new(srflx_candidate()?),
+        true,
+    );
+    assert_eq!(pair.current_round_trip_time(), None);
+
+    pair.add_round_trip_time(Duration::from_millis(100));
+    pair.add_round_trip_time(Duration::from_millis(50));
+
+    assert_eq!(
+        pair.current_round_trip_time(),
+        Some(Duration::from_millis(50))
+    );
+    assert_eq!(pair.total_round_trip_time(), Duration::from_millis(150));
+    assert_eq!(pair.responses_received(), 2);
+
+    Ok(())
+}
+
+fn host_candidate_with_cost(network_cost: u16) -> Result<CandidateBase> {
+    CandidateHostConfig {
+        base_config: CandidateBaseConfig {
+            network: "udp".to_owned(),
+            address: "0.0.0.0".to_owned(),
+            component: COMPONENT_RTP,
+            network_cost,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+    .new_candidate_host()
+}
+
+#[test]
+fn test_candidate_pair_network_cost() -> Result<()> {
+    let pair = CandidatePair::new(
+        Arc::new(host_candidate_with_cost(10)?),
+        Arc::new(host_candidate_with_cost(10)?),
+        true,
+    );
+    assert_eq!(pair.network_cost(), 20);
+
+    // A remote candidate without network cost may be on a cellular network
+    let pair = CandidatePair::new(
+        Arc::new(host_candidate_with_cost(10)?),
+        Arc::new(host_candidate_with_cost(0)?),
+        true,
+    );
+    assert_eq!(pair.network_cost(), 10 + u32::from(NETWORK_COST_HIGH));
+
+    let pair = CandidatePair::new(
+        Arc::new(host_candidate_with_cost(0)?),
+        Arc::new(host_candidate_with_cost(10)?),
+        true,
+    );
+    assert_eq!(pair.network_cost(), 10);
+
+    Ok(())
+}
@@ -40,6 +40,8 @@ impl CandidatePeerReflexiveConfig {
             component: AtomicU16::new(self.base_config.component),
             foundation_override: self.base_config.foundation,
             priority_override: self.base_config.priority,
+            network_id: self.base_config.network_id,
+            network_cost: self.base_config.network_cost,
             related_address: Some(CandidateRelatedAddress {
                 address: self.rel_addr,
                 port: self.rel_port,
@@ -4,12 +4,16 @@ use crate::agent::agent_config::AgentConfig;
 use crate::agent::agent_vnet_test::{connect_with_vnet, on_connected};
 use crate::agent::Agent;
 use crate::error::Error;
+use crate::tcp_packet_conn::{TcpFraming, TcpPacketConn};
 use crate::url::{ProtoType, SchemeType, Url};
 
 use std::result::Result;
+use std::str::FromStr;
 use std::time::Duration;
 use tokio::net::UdpSocket;
+use tokio::sync::{mpsc, Mutex};
 use turn::auth::AuthHandler;
+use util::Conn;
 
 pub(crate) struct OptimisticAuthHandler;
 
@@ -111,3 +115,84 @@ async fn test_relay_only_connection() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_relay_only_connection_over_tcp() -> Result<(), Error> {
+    // The TURN server accepts TCP connections, packets are relayed over UDP
+    let server_listener =
+        TcpPacketConn::listen(SocketAddr::from_str("127.0.0.1:0")?, TcpFraming::Stun).await?;
+    let server_port = server_listener.local_addr()?.port();
+
+    let server = turn::server::Server::new(turn::server::config::ServerConfig {
+        realm: "webrtc.rs".to_owned(),
+        auth_handler: Arc::new(OptimisticAuthHandler {}),
+        conn_configs: vec![turn::server::config::ConnConfig {
+            conn: Arc::new(server_listener),
+            relay_addr_generator: Box::new(turn::relay::relay_none::RelayAddressGeneratorNone {
+                address: "127.0.0.1".to_owned(),
+                net: Arc::new(util::vnet::net::Net::new(None)),
+            }),
+        }],
+        channel_bind_timeout: Duration::from_secs(0),
+    })
+    .await?;
+
+    let new_agent = |turn_over_tcp: bool| {
+        Agent::new(AgentConfig {
+            network_types: supported_network_types(),
+            urls: vec![Url {
+                scheme: SchemeType::Turn,
+                host: "127.0.0.1".to_owned(),
+                username: "username".to_owned(),
+                password: "password".to_owned(),
+                port: server_port,
+                proto: ProtoType::Tcp,
+            }],
+            candidate_types: vec![CandidateType::Relay],
+            turn_over_tcp,
+            ..Default::default()
+        })
+    };
+
+    // Without turn_over_tcp, TCP TURN URLs are skipped
+    let agent = new_agent(false).await?;
+    let (done_tx, mut done_rx) = mpsc::channel::<()>(1);
+    let done_tx = Arc::new(Mutex::new(Some(done_tx)));
+    agent.on_candidate(Box::new(
+        move |c: Option<Arc<dyn Candidate + Send + Sync>>| {
+            let done_tx = Arc::clone(&done_tx);
+            Box::pin(async move {
+                if c.is_none() {
+                    done_tx.lock().await.take();
+                }
+            })
+        },
+    ));
+    agent.gather_candidates()?;
+    let _ = done_rx.recv().await;
+    assert!(agent.get_local_candidates().await?.is_empty());
+    agent.close().await?;
+
+    let a_agent = Arc::new(new_agent(true).await?);
+    let (a_notifier, mut a_connected) = on_connected();
+    a_agent.on_connection_state_change(a_notifier);
+
+    let b_agent = Arc::new(new_agent(true).await?);
+    let (b_notifier, mut b_connected) = on_connected();
+    b_agent.on_connection_state_change(b_notifier);
+
+    connect_with_vnet(&a_agent, &b_agent).await?;
+
+    let _ = a_connected.recv().await;
+    let _ = b_connected.recv().await;
+
+    let local = a_agent.get_local_candidates().await?;
+    assert_eq!(local.len(), 1);
+    assert_eq!(local[0].candidate_type(), CandidateType::Relay);
+
+    a_agent.close().await?;
+    b_agent.close().await?;
+    server.close().await?;
+
+    Ok(())
+}
@@ -410,3 +410,51 @@ fn test_candidate_marshal() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_candidate_unmarshal_extensions() -> Result<()> {
+    let tests = vec![
+        (
+            "1052353102 1 tcp 2128609279 192.168.0.196 9 typ host generation 0 tcptype active network-id 1",
+            "1052353102 1 tcp 2128609279 192.168.0.196 9 typ host tcptype active network-id 1",
+        ),
+        (
+            "1052353102 1 udp 2122260223 192.168.0.196 60542 typ host generation 0 ufrag x network-cost 900 network-id 3",
+            "1052353102 1 udp 2122260223 192.168.0.196 60542 typ host network-id 3 network-cost 900",
+        ),
+        (
+            "647372371 1 udp 1694498815 191.228.238.68 53991 typ srflx raddr 192.168.0.274 rport 53991 generation 0",
+            "647372371 1 udp 1694498815 191.228.238.68 53991 typ srflx raddr 192.168.0.274 rport 53991",
+        ),
+        (
+            "647372371 1 udp 1694498815 191.228.238.68 53991 typ srflx rport 53991 raddr 192.168.0.274",
+            "647372371 1 udp 1694498815 191.228.238.68 53991 typ srflx raddr 192.168.0.274 rport 53991",
+        ),
+    ];
+
+    for (raw, marshaled) in tests {
+        let candidate = unmarshal_candidate(raw)?;
+        assert_eq!(candidate.marshal(), marshaled, "{}", raw);
+    }
+
+    assert!(
+        unmarshal_candidate("1052353102 1 tcp 2128609279 192.168.0.196 9 typ host tcptype")
+            .is_err()
+    );
+    assert!(unmarshal_candidate(
+        "1052353102 1 udp 2122260223 192.168.0.196 60542 typ host network-cost 70000"
+    )
+    .is_err());
+    assert!(unmarshal_candidate(
+        "1052353102 1 udp 2122260223 192.168.0.196 60542 typ host network-id"
+    )
+    .is_err());
+
+    let candidate = unmarshal_candidate(
+        "1052353102 1 udp 2122260223 192.168.0.196 60542 typ host network-id 2 network-cost 10",
+    )?;
+    assert_eq!(candidate.network_id(), 2);
+    assert_eq!(candidate.network_cost(), 10);
+
+    Ok(())
+}
@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod candidate_pair_test;
 #[cfg(test)]
-mod candidate_relay_test;
+pub(crate) mod candidate_relay_test;
 #[cfg(test)]
 mod candidate_server_reflexive_test;
 #[cfg(test)]
@@ -22,13 +22,16 @@ use async_trait::async_trait;
 use serde::Serialize;
 use std::fmt;
 use std::net::{IpAddr, SocketAddr};
-use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU8, Ordering};
+use std::ops::Add;
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU64, AtomicU8, Ordering};
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::{broadcast, Mutex};
 
 pub(crate) const RECEIVE_MTU: usize = 8192;
 pub(crate) const DEFAULT_LOCAL_PREFERENCE: u16 = 65535;
+/// The network cost browsers signal for cellular networks.
+pub(crate) const NETWORK_COST_HIGH: u16 = 900;
 
 /// Indicates that the candidate is used for RTP.
 pub(crate) const COMPONENT_RTP: u16 = 1;
@@ -72,6 +75,12 @@ pub trait Candidate: fmt::Display {
     fn candidate_type(&self) -> CandidateType;
     fn tcp_type(&self) -> TcpType;
 
+    /// Identifies the network interface the candidate was gathered on, 0 if unknown.
+    fn network_id(&self) -> u16;
+    /// The cost of sending over the network of the candidate, 0 if unknown. Browsers use 10 for
+    /// Ethernet and Wi-Fi and 900 for cellular networks.
+    fn network_cost(&self) -> u16;
+
     fn marshal(&self) -> String;
 
     fn addr(&self) -> SocketAddr;
@@ -232,6 +241,22 @@ pub struct CandidatePair {
     pub(crate) binding_request_count: AtomicU16,
     pub(crate) state: AtomicU8, // convert it to CandidatePairState,
     pub(crate) nominated: AtomicBool,
+    // unix nanos of the last backup keepalive sent on this pair, 0 means never
+    pub(crate) last_keepalive: AtomicU64,
+    // unix nanos of the last STUN message received on this pair, 0 means never
+    pub(crate) last_received: AtomicU64,
+    // backup keepalives in a row that failed to send or went unanswered
+    pub(crate) failed_keepalives: AtomicU16,
+    // STUN binding requests sent on this pair, including consent checks
+    pub(crate) requests_sent: AtomicU64,
+    // STUN binding requests sent on this pair to refresh consent while it was selected
+    pub(crate) consent_requests_sent: AtomicU64,
+    // STUN binding success responses received on this pair
+    pub(crate) responses_received: AtomicU64,
+    // nanos of the latest round trip time measurement, 0 means none yet
+    pub(crate) current_round_trip_time: AtomicU64,
+    // nanos of all round trip time measurements added up
+    pub(crate) total_round_trip_time: AtomicU64,
 }
 
 impl Default for CandidatePair {
@@ -243,6 +268,14 @@ impl Default for CandidatePair {
             state: AtomicU8::new(CandidatePairState::Waiting as u8),
             binding_request_count: AtomicU16::new(0),
             nominated: AtomicBool::new(false),
+            last_keepalive: AtomicU64::new(0),
+            last_received: AtomicU64::new(0),
+            failed_keepalives: AtomicU16::new(0),
+            requests_sent: AtomicU64::new(0),
+            consent_requests_sent: AtomicU64::new(0),
+            responses_received: AtomicU64::new(0),
+            current_round_trip_time: AtomicU64::new(0),
+            total_round_trip_time: AtomicU64::new(0),
         }
     }
 }
@@ -295,6 +328,14 @@ impl CandidatePair {
             state: AtomicU8::new(CandidatePairState::Waiting as u8),
             binding_request_count: AtomicU16::new(0),
             nominated: AtomicBool::new(false),
+            last_keepalive: AtomicU64::new(0),
+            last_received: AtomicU64::new(0),
+            failed_keepalives: AtomicU16::new(0),
+            requests_sent: AtomicU64::new(0),
+            consent_requests_sent: AtomicU64::new(0),
+            responses_received: AtomicU64::new(0),
+            current_round_trip_time: AtomicU64::new(0),
+            total_round_trip_time: AtomicU64::new(0),
         }
     }
 
@@ -317,7 +358,84 @@ impl CandidatePair {
             + u64::from(g > d)
     }
 
+    /// Returns the network costs of the local and the remote candidate added up. A remote
+    /// candidate signaled without network cost counts as a high cost one, it may well be on a
+    /// cellular network.
+    pub fn network_cost(&self) -> u32 {
+        let remote_cost = match self.remote.network_cost() {
+            0 => NETWORK_COST_HIGH,
+            cost => cost,
+        };
+        u32::from(self.local.network_cost()) + u32::from(remote_cost)
+    }
+
     pub async fn write(&self, b: &[u8]) -> Result<usize> {
         self.local.write_to(b, &*self.remote).await
     }
+
+    /// Returns the time the last backup keepalive was sent on this pair, if any.
+    pub fn last_keepalive(&self) -> Option<SystemTime> {
+        match self.last_keepalive.load(Ordering::SeqCst) {
+            0 => None,
+            n => Some(UNIX_EPOCH.add(Duration::from_nanos(n))),
+        }
+    }
+
+    /// Returns the time a STUN message was last received on this pair, if any.
+    pub fn last_received(&self) -> Option<SystemTime> {
+        match self.last_received.load(Ordering::SeqCst) {
+            0 => None,
+            n => Some(UNIX_EPOCH.add(Duration::from_nanos(n))),
+        }
+    }
+
+    /// Returns the number of STUN binding requests sent on this pair, including those sent to
+    /// refresh consent.
+    pub fn requests_sent(&self) -> u64 {
+        self.requests_sent.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of STUN binding success responses received on this pair.
+    pub fn responses_received(&self) -> u64 {
+        self.responses_received.load(Ordering::SeqCst)
+    }
+
+    /// Returns the latest round trip time measured by a STUN binding request on this pair, if
+    /// any. It keeps being updated by the consent checks once the pair is selected.
+    pub fn current_round_trip_time(&self) -> Option<Duration> {
+        match self.current_round_trip_time.load(Ordering::SeqCst) {
+            0 => None,
+            n => Some(Duration::from_nanos(n)),
+        }
+    }
+
+    /// Returns the sum of all round trip times measured on this pair. Divided by
+    /// `responses_received` it gives the average round trip time.
+    pub fn total_round_trip_time(&self) -> Duration {
+        Duration::from_nanos(self.total_round_trip_time.load(Ordering::SeqCst))
+    }
+
+    /// Records a binding success response which answered a request sent `rtt` ago.
+    pub(crate) fn add_round_trip_time(&self, rtt: Duration) {
+        #[allow(clippy::cast_possible_truncation)]
+        let nanos = (rtt.as_nanos() as u64).max(1);
+        self.current_round_trip_time.store(nanos, Ordering::SeqCst);
+        self.total_round_trip_time
+            .fetch_add(nanos, Ordering::SeqCst);
+        self.responses_received.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn set_last_keepalive(&self, t: SystemTime) {
+        if let Ok(d) = t.duration_since(UNIX_EPOCH) {
+            self.last_keepalive
+                .store(d.as_nanos() as u64, Ordering::SeqCst);
+        }
+    }
+
+    pub(crate) fn set_last_received(&self, t: SystemTime) {
+        if let Ok(d) = t.duration_since(UNIX_EPOCH) {
+            self.last_received
+                .store(d.as_nanos() as u64, Ordering::SeqCst);
+        }
+    }
 }
@@ -161,6 +161,10 @@ pub enum Error {
     ErrParseRelatedAddr,
     #[error("could not parse type")]
     ErrParseType,
+    #[error("could not parse network id")]
+    ErrParseNetworkId,
+    #[error("could not parse network cost")]
+    ErrParseNetworkCost,
     #[error("unknown candidate type")]
     ErrUnknownCandidateType,
     #[error("failed to get XOR-MAPPED-ADDRESS response")]
@@ -185,6 +189,14 @@ pub enum Error {
     ErrUnknownRole,
     #[error("username mismatch")]
     ErrMismatchUsername,
+    #[error("binding request without a valid PRIORITY")]
+    ErrMissingPriority,
+    #[error("binding request without ICE-CONTROLLING or ICE-CONTROLLED")]
+    ErrMissingRole,
+    #[error("binding request with both ICE-CONTROLLING and ICE-CONTROLLED")]
+    ErrConflictingRoles,
+    #[error("binding request with USE-CANDIDATE from the controlled agent")]
+    ErrUseCandidateFromControlled,
     #[error("the ICE conn can't write STUN messages")]
     ErrIceWriteStunMessage,
     #[error("invalid url")]
@@ -193,6 +205,14 @@ pub enum Error {
     ErrUrlParse,
     #[error("Candidate IP could not be found")]
     ErrCandidateIpNotFound,
+    #[error("DNS lookup timed out")]
+    ErrDnsTimeout,
+    #[error("the server has no address of the network types")]
+    ErrNoServerAddress,
+    #[error("no address of the server could be reached")]
+    ErrServerUnreachable,
+    #[error("TURN over TLS (turns:) is not supported")]
+    ErrTurnsUnsupported,
 
     #[error("parse int: {0}")]
     ParseInt(#[from] ParseIntError),
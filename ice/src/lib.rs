@@ -8,10 +8,14 @@ mod error;
 pub mod external_ip_mapper;
 pub mod mdns;
 pub mod network_type;
+pub mod options;
+pub mod pacer;
 pub mod priority;
 pub mod rand;
+pub mod resolver;
 pub mod state;
 pub mod stats;
+pub mod tcp_packet_conn;
 pub mod tcp_type;
 pub mod udp_mux;
 pub mod udp_network;
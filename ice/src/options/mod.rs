@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod options_test;
+
+use std::fmt;
+
+pub const ICE_OPTION_TRICKLE: &str = "trickle";
+pub const ICE_OPTION_RENOMINATION: &str = "renomination";
+pub const ICE_OPTION_ICE2: &str = "ice2";
+
+/// IceOptions are the ICE extensions an agent supports, as signaled in the SDP
+/// a=ice-options attribute.
+/// https://datatracker.ietf.org/doc/html/rfc8839#section-5.6
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IceOptions {
+    /// The agent trickles its candidates (RFC 8838), so the candidates in a
+    /// session description may not be complete.
+    pub trickle: bool,
+    /// The controlling agent may nominate a different pair after a pair was
+    /// selected.
+    pub renomination: bool,
+    /// The agent implements RFC 8445.
+    pub ice2: bool,
+}
+
+// from parses the space separated option tags of an a=ice-options value,
+// unknown tags are ignored.
+impl From<&str> for IceOptions {
+    fn from(raw: &str) -> Self {
+        let mut options = Self::default();
+        for tag in raw.split_whitespace() {
+            match tag {
+                ICE_OPTION_TRICKLE => options.trickle = true,
+                ICE_OPTION_RENOMINATION => options.renomination = true,
+                ICE_OPTION_ICE2 => options.ice2 = true,
+                _ => {}
+            }
+        }
+        options
+    }
+}
+
+impl fmt::Display for IceOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tags = vec![];
+        if self.trickle {
+            tags.push(ICE_OPTION_TRICKLE);
+        }
+        if self.renomination {
+            tags.push(ICE_OPTION_RENOMINATION);
+        }
+        if self.ice2 {
+            tags.push(ICE_OPTION_ICE2);
+        }
+        write!(f, "{}", tags.join(" "))
+    }
+}
+
+impl IceOptions {
+    /// is_empty returns true if no option is set, the a=ice-options attribute
+    /// is omitted in that case.
+    pub fn is_empty(&self) -> bool {
+        !self.trickle && !self.renomination && !self.ice2
+    }
+}
@@ -0,0 +1,63 @@
+use super::*;
+
+#[test]
+fn test_ice_options_parse() {
+    let tests = vec![
+        ("", IceOptions::default()),
+        (
+            "trickle",
+            IceOptions {
+                trickle: true,
+                ..Default::default()
+            },
+        ),
+        (
+            "trickle renomination",
+            IceOptions {
+                trickle: true,
+                renomination: true,
+                ice2: false,
+            },
+        ),
+        (
+            " ice2  google-ice trickle ",
+            IceOptions {
+                trickle: true,
+                renomination: false,
+                ice2: true,
+            },
+        ),
+    ];
+
+    for (raw, want) in tests {
+        assert_eq!(IceOptions::from(raw), want, "parsing {:?}", raw);
+    }
+}
+
+#[test]
+fn test_ice_options_string() {
+    let tests = vec![
+        (IceOptions::default(), ""),
+        (
+            IceOptions {
+                trickle: true,
+                ..Default::default()
+            },
+            "trickle",
+        ),
+        (
+            IceOptions {
+                trickle: true,
+                renomination: true,
+                ice2: true,
+            },
+            "trickle renomination ice2",
+        ),
+    ];
+
+    for (options, want) in tests {
+        assert_eq!(options.to_string(), want);
+        assert_eq!(options.is_empty(), want.is_empty());
+        assert_eq!(IceOptions::from(want), options);
+    }
+}
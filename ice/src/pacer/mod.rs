@@ -0,0 +1,140 @@
+#[cfg(test)]
+mod pacer_test;
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use util::sync::Mutex;
+
+/// The default pacing interval Ta between two transactions, see RFC 8445 Section 14.2.
+pub const DEFAULT_PACING_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Pacer spaces out STUN transactions, i.e. connectivity checks and gathering requests, by at
+/// least the pacing interval Ta (RFC 8445 Section 14). Transactions are started in the order they
+/// asked for a slot, except that triggered checks go before all ordinary transactions.
+///
+/// A pacer can be shared, e.g. by the agents of a UDP mux, to bound the rate of all of them.
+pub struct Pacer {
+    interval: Duration,
+    state: Mutex<PacerState>,
+    notify: Notify,
+}
+
+#[derive(Default)]
+struct PacerState {
+    /// The earliest time the next transaction can start
+    next_slot: Option<Instant>,
+    next_ticket: u64,
+    triggered: VecDeque<u64>,
+    ordinary: VecDeque<u64>,
+}
+
+impl PacerState {
+    fn head(&self) -> Option<u64> {
+        self.triggered
+            .front()
+            .or_else(|| self.ordinary.front())
+            .copied()
+    }
+}
+
+/// Ticket keeps the place of a waiting transaction in the queue, which it leaves when dropped
+struct Ticket<'a> {
+    pacer: &'a Pacer,
+    id: u64,
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        let mut state = self.pacer.state.lock();
+        state.triggered.retain(|id| *id != self.id);
+        state.ordinary.retain(|id| *id != self.id);
+        drop(state);
+
+        self.pacer.notify.notify_waiters();
+    }
+}
+
+impl Pacer {
+    /// new returns a pacer spacing transactions by interval. An interval of 0 disables pacing.
+    pub fn new(interval: Duration) -> Self {
+        Pacer {
+            interval,
+            state: Mutex::new(PacerState::default()),
+            notify: Notify::new(),
+        }
+    }
+
+    /// interval returns the pacing interval Ta.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// wait returns once an ordinary transaction can be started.
+    pub async fn wait(&self) {
+        self.wait_for_slot(false).await
+    }
+
+    /// wait_triggered returns once a triggered check can be sent. It is served before all
+    /// ordinary transactions waiting for the pacer, but still no sooner than Ta after the
+    /// previous transaction.
+    pub async fn wait_triggered(&self) {
+        self.wait_for_slot(true).await
+    }
+
+    async fn wait_for_slot(&self, triggered: bool) {
+        if self.interval == Duration::from_secs(0) {
+            return;
+        }
+
+        let ticket = {
+            let mut state = self.state.lock();
+            let id = state.next_ticket;
+            state.next_ticket += 1;
+            if triggered {
+                state.triggered.push_back(id);
+            } else {
+                state.ordinary.push_back(id);
+            }
+            Ticket { pacer: self, id }
+        };
+        // A new head of the queue has to be waited for by the current one
+        self.notify.notify_waiters();
+
+        loop {
+            // Created before the state is looked at, so that no change is missed
+            let notified = self.notify.notified();
+
+            let deadline = {
+                let mut state = self.state.lock();
+                if state.head() != Some(ticket.id) {
+                    None
+                } else {
+                    let now = Instant::now();
+                    match state.next_slot {
+                        Some(next_slot) if next_slot > now => Some(next_slot),
+                        _ => {
+                            state.next_slot = Some(now + self.interval);
+                            break;
+                        }
+                    }
+                }
+            };
+
+            match deadline {
+                Some(deadline) => {
+                    tokio::select! {
+                        _ = tokio::time::sleep_until(deadline) => {}
+                        _ = notified => {}
+                    }
+                }
+                None => notified.await,
+            }
+        }
+
+        // Leaving the queue lets the next transaction wait for its slot
+        drop(ticket);
+    }
+}
@@ -0,0 +1,109 @@
+use super::*;
+
+use std::sync::Arc;
+
+const TA: Duration = Duration::from_millis(50);
+
+type Log = Arc<Mutex<Vec<(&'static str, Instant)>>>;
+
+fn spawn_waiter(pacer: &Arc<Pacer>, log: &Log, name: &'static str, triggered: bool) {
+    let (pacer, log) = (Arc::clone(pacer), Arc::clone(log));
+    tokio::spawn(async move {
+        if triggered {
+            pacer.wait_triggered().await;
+        } else {
+            pacer.wait().await;
+        }
+        log.lock().push((name, Instant::now()));
+    });
+}
+
+fn assert_spacing(log: &[(&'static str, Instant)]) {
+    for w in log.windows(2) {
+        let gap = w[1].1 - w[0].1;
+        assert!(
+            gap >= TA,
+            "{} started {:?} after {}, less than Ta",
+            w[1].0,
+            gap,
+            w[0].0
+        );
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_pacer_spaces_transactions() {
+    let pacer = Arc::new(Pacer::new(TA));
+    let log: Log = Arc::new(Mutex::new(vec![]));
+
+    let start = Instant::now();
+    for name in ["a", "b", "c", "d"] {
+        spawn_waiter(&pacer, &log, name, false);
+    }
+    tokio::time::sleep(TA * 10).await;
+
+    let log = log.lock().clone();
+    let names: Vec<&str> = log.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, vec!["a", "b", "c", "d"]);
+    assert_eq!(log[0].1, start, "the first transaction must not wait");
+    assert_spacing(&log);
+    assert_eq!(
+        log[3].1 - start,
+        TA * 3,
+        "transactions must not wait longer than Ta"
+    );
+
+    // Once the pacer is idle for Ta, the next transaction starts right away
+    let now = Instant::now();
+    pacer.wait().await;
+    assert_eq!(Instant::now(), now);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_pacer_triggered_checks_jump_the_queue() {
+    let pacer = Arc::new(Pacer::new(TA));
+    let log: Log = Arc::new(Mutex::new(vec![]));
+
+    for name in ["a", "b", "c"] {
+        spawn_waiter(&pacer, &log, name, false);
+    }
+    // "a" starts right away, "b" waits for its slot
+    tokio::time::sleep(TA / 2).await;
+    spawn_waiter(&pacer, &log, "t1", true);
+    spawn_waiter(&pacer, &log, "t2", true);
+    tokio::time::sleep(TA * 10).await;
+
+    let log = log.lock().clone();
+    let names: Vec<&str> = log.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, vec!["a", "t1", "t2", "b", "c"]);
+    assert_spacing(&log);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_pacer_cancelled_wait_leaves_queue() {
+    let pacer = Arc::new(Pacer::new(TA));
+    let log: Log = Arc::new(Mutex::new(vec![]));
+
+    let start = Instant::now();
+    pacer.wait().await;
+    // Gives up before its slot, the transaction after it must not wait for it
+    let _ = tokio::time::timeout(TA / 5, pacer.wait()).await;
+    spawn_waiter(&pacer, &log, "a", false);
+    tokio::time::sleep(TA * 10).await;
+
+    let log = log.lock().clone();
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].1 - start, TA);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_pacer_disabled() {
+    let pacer = Pacer::new(Duration::from_secs(0));
+
+    let now = Instant::now();
+    for _ in 0..10 {
+        pacer.wait().await;
+        pacer.wait_triggered().await;
+    }
+    assert_eq!(Instant::now(), now);
+}
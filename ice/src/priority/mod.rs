@@ -1,6 +1,8 @@
 #[cfg(test)]
 mod priority_test;
 
+use crate::candidate::{Candidate, CandidateType};
+
 use stun::attributes::ATTR_PRIORITY;
 use stun::checks::*;
 use stun::message::*;
@@ -22,8 +24,18 @@ impl Setter for PriorityAttr {
 }
 
 impl PriorityAttr {
+    /// Returns the PRIORITY of a connectivity check sent from `local`, the priority a
+    /// peer-reflexive candidate learned from it would have (RFC 8445 section 7.1.1). It differs
+    /// from the priority of `local` only in the type preference.
+    pub fn for_check_from(local: &(dyn Candidate + Send + Sync)) -> Self {
+        let type_preference = u32::from(CandidateType::PeerReflexive.preference()) << 24;
+        PriorityAttr(type_preference | (local.priority() & 0x00FF_FFFF))
+    }
+}
+
+impl Getter for PriorityAttr {
     /// Decodes PRIORITY attribute from message.
-    pub fn get_from(&mut self, m: &Message) -> Result<(), stun::Error> {
+    fn get_from(&mut self, m: &Message) -> Result<(), stun::Error> {
         let v = m.get(ATTR_PRIORITY)?;
 
         check_size(ATTR_PRIORITY, v.len(), PRIORITY_SIZE)?;
@@ -1,4 +1,6 @@
 use super::*;
+use crate::candidate::candidate_base::CandidateBaseConfig;
+use crate::candidate::candidate_host::CandidateHostConfig;
 use crate::error::Result;
 
 #[test]
@@ -37,3 +39,27 @@ fn test_priority_get_from() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_priority_for_check_from() -> Result<()> {
+    let host = CandidateHostConfig {
+        base_config: CandidateBaseConfig {
+            network: "udp".to_owned(),
+            address: "192.168.0.2".to_owned(),
+            port: 777,
+            component: 1,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+    .new_candidate_host()?;
+
+    // Same local preference and component, type preference of a peer-reflexive candidate
+    assert_eq!(host.priority(), (126 << 24) + (65535 << 8) + 255);
+    assert_eq!(
+        PriorityAttr::for_check_from(&host),
+        PriorityAttr((110 << 24) + (65535 << 8) + 255)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,116 @@
+#[cfg(test)]
+pub(crate) mod resolver_test;
+
+use crate::error::*;
+use crate::network_type::NetworkType;
+
+use async_trait::async_trait;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use util::vnet::net::Net;
+
+/// The default time the DNS lookup of a STUN or TURN server may take.
+pub const DEFAULT_DNS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The time to wait for an attempt to reach an address of a server before an attempt with the
+/// next address is started, see RFC 8305 Section 5.
+pub(crate) const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Resolver looks up the IP addresses of the STUN and TURN servers given by host name.
+#[async_trait]
+pub trait Resolver {
+    /// Returns the IPv4 and IPv6 addresses of host.
+    async fn lookup_ip(&self, host: &str) -> Result<Vec<IpAddr>>;
+}
+
+/// NetResolver resolves host names with the resolver of the system, or with the one of the
+/// virtual network.
+pub struct NetResolver {
+    net: Arc<Net>,
+}
+
+impl NetResolver {
+    pub fn new(net: Arc<Net>) -> Self {
+        NetResolver { net }
+    }
+}
+
+#[async_trait]
+impl Resolver for NetResolver {
+    async fn lookup_ip(&self, host: &str) -> Result<Vec<IpAddr>> {
+        if !self.net.is_virtual() {
+            let addrs = tokio::net::lookup_host((host, 0)).await?;
+            return Ok(addrs.map(|addr| addr.ip()).collect());
+        }
+
+        let host_port = format!("{}:0", host);
+        let mut ips = vec![];
+        for use_ipv4 in [true, false] {
+            if let Ok(addr) = self.net.resolve_addr(use_ipv4, &host_port).await {
+                ips.push(addr.ip());
+            }
+        }
+        Ok(ips)
+    }
+}
+
+/// resolve_server returns the addresses of a STUN or TURN server in the order they should be
+/// tried, see `order_addresses`. The resolver isn't asked for IP addresses, and the lookup of
+/// host names fails with `Error::ErrDnsTimeout` after dns_timeout.
+pub(crate) async fn resolve_server(
+    resolver: &Arc<dyn Resolver + Send + Sync>,
+    dns_timeout: Duration,
+    host: &str,
+    port: u16,
+    network_types: &[NetworkType],
+) -> Result<Vec<SocketAddr>> {
+    let ips = if let Ok(ip) = host.parse::<IpAddr>() {
+        vec![ip]
+    } else {
+        match tokio::time::timeout(dns_timeout, resolver.lookup_ip(host)).await {
+            Ok(ips) => ips?,
+            Err(_) => return Err(Error::ErrDnsTimeout),
+        }
+    };
+
+    let ips = order_addresses(ips, network_types);
+    if ips.is_empty() {
+        return Err(Error::ErrNoServerAddress);
+    }
+
+    Ok(ips
+        .into_iter()
+        .map(|ip| SocketAddr::new(ip, port))
+        .collect())
+}
+
+/// order_addresses keeps the addresses of the IP families of network_types, and interleaves
+/// them starting with the family of the first network type, as in RFC 8305 Section 4. Both
+/// families are used if network_types is empty, IPv4 first.
+pub(crate) fn order_addresses(ips: Vec<IpAddr>, network_types: &[NetworkType]) -> Vec<IpAddr> {
+    let mut families: Vec<bool> = vec![];
+    for network_type in network_types {
+        if !families.contains(&network_type.is_ipv4()) {
+            families.push(network_type.is_ipv4());
+        }
+    }
+    if families.is_empty() {
+        families = vec![true, false];
+    }
+
+    let (preferred, other): (Vec<IpAddr>, Vec<IpAddr>) = ips
+        .into_iter()
+        .filter(|ip| families.contains(&ip.is_ipv4()))
+        .partition(|ip| ip.is_ipv4() == families[0]);
+
+    let mut ordered = Vec::with_capacity(preferred.len() + other.len());
+    let (mut preferred, mut other) = (preferred.into_iter(), other.into_iter());
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => break,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+    ordered
+}
@@ -0,0 +1,95 @@
+use super::*;
+
+use std::collections::HashMap;
+
+/// TestResolver answers lookups from a fixed table after the delay of the host, so that slow
+/// and failing DNS can be simulated.
+#[derive(Default)]
+pub(crate) struct TestResolver {
+    hosts: HashMap<String, (Duration, Vec<IpAddr>)>,
+}
+
+impl TestResolver {
+    pub(crate) fn with_host(mut self, host: &str, delay: Duration, ips: &[&str]) -> Self {
+        let ips = ips.iter().map(|ip| ip.parse().unwrap()).collect();
+        self.hosts.insert(host.to_owned(), (delay, ips));
+        self
+    }
+}
+
+#[async_trait]
+impl Resolver for TestResolver {
+    async fn lookup_ip(&self, host: &str) -> Result<Vec<IpAddr>> {
+        match self.hosts.get(host) {
+            Some((delay, ips)) => {
+                tokio::time::sleep(*delay).await;
+                Ok(ips.clone())
+            }
+            None => Err(Error::Other(format!("{} not found", host))),
+        }
+    }
+}
+
+fn ips(ips: &[&str]) -> Vec<IpAddr> {
+    ips.iter().map(|ip| ip.parse().unwrap()).collect()
+}
+
+#[test]
+fn test_order_addresses() {
+    let dual_stack = ips(&["10.0.0.1", "10.0.0.2", "10.0.0.3", "fd00::1", "fd00::2"]);
+
+    // Interleaved starting with the family of the first network type
+    assert_eq!(
+        order_addresses(dual_stack.clone(), &[NetworkType::Udp6, NetworkType::Udp4]),
+        ips(&["fd00::1", "10.0.0.1", "fd00::2", "10.0.0.2", "10.0.0.3"])
+    );
+    assert_eq!(
+        order_addresses(dual_stack.clone(), &[]),
+        ips(&["10.0.0.1", "fd00::1", "10.0.0.2", "fd00::2", "10.0.0.3"])
+    );
+
+    // Families without a network type are left out
+    assert_eq!(
+        order_addresses(dual_stack.clone(), &[NetworkType::Udp6, NetworkType::Tcp6]),
+        ips(&["fd00::1", "fd00::2"])
+    );
+    assert!(order_addresses(ips(&["fd00::1"]), &[NetworkType::Udp4]).is_empty());
+}
+
+#[tokio::test]
+async fn test_resolve_server() -> Result<()> {
+    let resolver: Arc<dyn Resolver + Send + Sync> = Arc::new(
+        TestResolver::default()
+            .with_host("ipv6.test", Duration::from_millis(0), &["fd00::1"])
+            .with_host("slow.test", Duration::from_secs(10), &["10.0.0.1"]),
+    );
+    let network_types = [NetworkType::Udp4, NetworkType::Udp6];
+    let timeout = Duration::from_millis(100);
+
+    assert_eq!(
+        resolve_server(&resolver, timeout, "ipv6.test", 3478, &network_types).await?,
+        vec!["[fd00::1]:3478".parse().unwrap()]
+    );
+
+    // IP addresses aren't looked up
+    assert_eq!(
+        resolve_server(&resolver, timeout, "10.0.0.2", 3478, &network_types).await?,
+        vec!["10.0.0.2:3478".parse().unwrap()]
+    );
+
+    assert_eq!(
+        resolve_server(&resolver, timeout, "slow.test", 3478, &network_types).await,
+        Err(Error::ErrDnsTimeout)
+    );
+    assert_eq!(
+        resolve_server(&resolver, timeout, "ipv6.test", 3478, &[NetworkType::Udp4]).await,
+        Err(Error::ErrNoServerAddress)
+    );
+    assert!(
+        resolve_server(&resolver, timeout, "unknown.test", 3478, &network_types)
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,567 @@
+#[cfg(test)]
+mod tcp_packet_conn_test;
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use stun::message::is_message;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, WriteHalf};
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::Duration;
+use util::sync::{Mutex as SyncMutex, TaskGroup};
+use util::vnet::net::Net;
+use util::vnet::tcp::TcpListener as VirtualTcpListener;
+use util::{Conn, Error};
+
+/// How long connecting to a remote address may take.
+const DIAL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The number of accepted streams that haven't sent a STUN message yet, more are closed.
+const MAX_PENDING_STREAMS: usize = 64;
+
+/// How long an accepted stream may take to send its first packet, a STUN message.
+const PENDING_STREAM_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The number of packets received and not read yet, the streams aren't read meanwhile.
+const RECV_QUEUE_SIZE: usize = 64;
+
+/// The length of a STUN message header, see RFC 5389 Section 6.
+const STUN_HEADER_SIZE: usize = 20;
+
+/// The length of a TURN ChannelData message header, see RFC 5766 Section 11.4.
+const CHANNEL_DATA_HEADER_SIZE: usize = 4;
+
+type ConnResult<T> = Result<T, util::Error>;
+
+/// A packet received and the address it was received from.
+type Received = (Vec<u8>, SocketAddr);
+
+/// Stream is a TCP stream of the system or of a virtual network.
+trait Stream: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> Stream for T {}
+
+type StreamWriter = Arc<Mutex<WriteHalf<Box<dyn Stream>>>>;
+
+/// Listener accepts the TCP streams of the system or of a virtual network.
+enum Listener {
+    System(TcpListener),
+    Virtual(VirtualTcpListener),
+}
+
+impl Listener {
+    async fn accept(&self) -> io::Result<(Box<dyn Stream>, SocketAddr)> {
+        match self {
+            Listener::System(listener) => {
+                let (stream, remote) = listener.accept().await?;
+                Ok((Box::new(nodelay(stream, remote)), remote))
+            }
+            Listener::Virtual(listener) => {
+                let (stream, remote) = listener.accept().await.map_err(into_io_error)?;
+                Ok((Box::new(stream), remote))
+            }
+        }
+    }
+}
+
+/// nodelay disables Nagle's algorithm for the stream with remote, packets are sent right away.
+fn nodelay(stream: TcpStream, remote: SocketAddr) -> TcpStream {
+    if let Err(err) = stream.set_nodelay(true) {
+        log::debug!(
+            "failed to disable Nagle's algorithm for {}: {}",
+            remote,
+            err
+        );
+    }
+    stream
+}
+
+fn into_io_error(err: Error) -> io::Error {
+    match err {
+        Error::Io(err) => err.0,
+        err => io::Error::new(io::ErrorKind::Other, err),
+    }
+}
+
+/// TcpFraming tells how the packets of a [`TcpPacketConn`] are delimited on its streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TcpFraming {
+    /// Each packet is preceded by its length on 2 bytes, see RFC 4571. Used by ICE-TCP
+    /// (RFC 6544).
+    Rfc4571,
+    /// STUN and TURN ChannelData messages, delimited by their own length field, see RFC 5766
+    /// Section 11.5. Used to reach TURN servers over TCP.
+    Stun,
+}
+
+impl TcpFraming {
+    /// encode returns the bytes written to a stream to send buf.
+    fn encode(self, buf: &[u8]) -> ConnResult<Vec<u8>> {
+        match self {
+            TcpFraming::Rfc4571 => {
+                if buf.len() > u16::MAX as usize {
+                    return Err(Error::ErrPacketTooBig);
+                }
+                let mut framed = Vec::with_capacity(2 + buf.len());
+                framed.extend_from_slice(&(buf.len() as u16).to_be_bytes());
+                framed.extend_from_slice(buf);
+                Ok(framed)
+            }
+            // ChannelData messages are already padded to 4 bytes by the TURN client
+            TcpFraming::Stun => Ok(buf.to_vec()),
+        }
+    }
+
+    /// read_packet reads the next packet of a stream.
+    async fn read_packet<R: AsyncRead + Unpin>(self, r: &mut R) -> io::Result<Vec<u8>> {
+        match self {
+            TcpFraming::Rfc4571 => {
+                let mut len = [0u8; 2];
+                r.read_exact(&mut len).await?;
+                let mut packet = vec![0u8; u16::from_be_bytes(len) as usize];
+                r.read_exact(&mut packet).await?;
+                Ok(packet)
+            }
+            TcpFraming::Stun => {
+                let mut header = [0u8; CHANNEL_DATA_HEADER_SIZE];
+                r.read_exact(&mut header).await?;
+                let len = u16::from_be_bytes([header[2], header[3]]) as usize;
+                // The first two bits of STUN messages are 0, channel numbers start with 01
+                let size = if header[0] & 0xC0 == 0x40 {
+                    // ChannelData over streams is padded to 4 bytes
+                    CHANNEL_DATA_HEADER_SIZE + ((len + 3) & !3)
+                } else {
+                    STUN_HEADER_SIZE + len
+                };
+                let mut packet = vec![0u8; size];
+                packet[..CHANNEL_DATA_HEADER_SIZE].copy_from_slice(&header);
+                r.read_exact(&mut packet[CHANNEL_DATA_HEADER_SIZE..])
+                    .await?;
+                Ok(packet)
+            }
+        }
+    }
+}
+
+/// TcpPacketConn carries packets over TCP streams, one per remote address, so it can be used
+/// like a UDP socket: `recv_from` returns the packets of all its streams and `send_to` sends
+/// on the stream of the target.
+///
+/// A listening conn accepts the streams, as passive ICE-TCP candidates do. An accepted stream
+/// is only used once its first packet turned out to be a STUN message, streams that don't send
+/// one in time, or in excess of the ones waiting for it, are closed. A dialing conn
+/// connects to the addresses it sends to, as active ICE-TCP candidates do. Packets sent to an
+/// address while its stream is being connected are dropped, like datagrams would be lost.
+///
+/// The `_with_net` constructors use the TCP streams of a virtual network instead of the system
+/// ones when the net is virtual.
+pub struct TcpPacketConn {
+    inner: Arc<TcpPacketConnInner>,
+    recv_rx: Mutex<mpsc::Receiver<Received>>,
+    local_addr: SocketAddr,
+    remote_addr: SyncMutex<Option<SocketAddr>>,
+}
+
+struct TcpPacketConnInner {
+    framing: TcpFraming,
+    local_ip: IpAddr,
+    dial: bool,
+    // set for a virtual network only
+    vnet: Option<Arc<Net>>,
+    streams: SyncMutex<HashMap<SocketAddr, StreamWriter>>,
+    dialing: SyncMutex<HashSet<SocketAddr>>,
+    // accepted streams waiting for their first packet
+    pending: AtomicUsize,
+    // taken on close, so recv_from fails once the tasks holding a clone stopped
+    recv_tx: SyncMutex<Option<mpsc::Sender<Received>>>,
+    tasks: TaskGroup,
+}
+
+impl TcpPacketConn {
+    fn new(
+        framing: TcpFraming,
+        local_addr: SocketAddr,
+        dial: bool,
+        vnet: Option<Arc<Net>>,
+    ) -> Self {
+        let (recv_tx, recv_rx) = mpsc::channel(RECV_QUEUE_SIZE);
+        TcpPacketConn {
+            inner: Arc::new(TcpPacketConnInner {
+                framing,
+                local_ip: local_addr.ip(),
+                dial,
+                vnet,
+                streams: SyncMutex::new(HashMap::new()),
+                dialing: SyncMutex::new(HashSet::new()),
+                pending: AtomicUsize::new(0),
+                recv_tx: SyncMutex::new(Some(recv_tx)),
+                tasks: TaskGroup::new(),
+            }),
+            recv_rx: Mutex::new(recv_rx),
+            local_addr,
+            remote_addr: SyncMutex::new(None),
+        }
+    }
+
+    /// listen returns a conn accepting the streams connected to addr.
+    pub async fn listen(addr: SocketAddr, framing: TcpFraming) -> io::Result<Self> {
+        TcpPacketConn::listen_with_limits(
+            None,
+            addr,
+            framing,
+            MAX_PENDING_STREAMS,
+            PENDING_STREAM_TIMEOUT,
+        )
+        .await
+    }
+
+    /// listen_with_net returns a conn accepting the streams of net connected to addr.
+    pub async fn listen_with_net(
+        net: &Arc<Net>,
+        addr: SocketAddr,
+        framing: TcpFraming,
+    ) -> io::Result<Self> {
+        TcpPacketConn::listen_with_limits(
+            virtual_net(net),
+            addr,
+            framing,
+            MAX_PENDING_STREAMS,
+            PENDING_STREAM_TIMEOUT,
+        )
+        .await
+    }
+
+    async fn listen_with_limits(
+        vnet: Option<Arc<Net>>,
+        addr: SocketAddr,
+        framing: TcpFraming,
+        max_pending: usize,
+        pending_timeout: Duration,
+    ) -> io::Result<Self> {
+        let listener = match &vnet {
+            Some(net) => Listener::Virtual(net.listen_tcp(addr).await.map_err(into_io_error)?),
+            None => Listener::System(TcpListener::bind(addr).await?),
+        };
+        let local_addr = match &listener {
+            Listener::System(listener) => listener.local_addr()?,
+            Listener::Virtual(listener) => listener.local_addr(),
+        };
+        let conn = TcpPacketConn::new(framing, local_addr, false, vnet);
+
+        let inner = Arc::clone(&conn.inner);
+        conn.inner.tasks.spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, remote)) => {
+                        if inner.pending.load(Ordering::SeqCst) >= max_pending {
+                            log::debug!(
+                                "closing the TCP connection from {}, too many are pending",
+                                remote
+                            );
+                            continue;
+                        }
+                        inner.accept_stream(stream, remote, pending_timeout);
+                    }
+                    Err(err) => {
+                        log::warn!("failed to accept a TCP connection: {}", err);
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(conn)
+    }
+
+    /// dialer returns a conn connecting from local_ip to the addresses it sends to. An
+    /// unspecified local_ip lets the system pick the local address.
+    pub fn dialer(local_ip: IpAddr, framing: TcpFraming) -> Self {
+        TcpPacketConn::new(framing, SocketAddr::new(local_ip, 0), true, None)
+    }
+
+    /// dialer_with_net returns a conn connecting over net from local_ip to the addresses it
+    /// sends to.
+    pub fn dialer_with_net(net: &Arc<Net>, local_ip: IpAddr, framing: TcpFraming) -> Self {
+        TcpPacketConn::new(
+            framing,
+            SocketAddr::new(local_ip, 0),
+            true,
+            virtual_net(net),
+        )
+    }
+
+    /// dial returns a conn connected to remote, from local_ip unless it's unspecified.
+    pub async fn dial(
+        local_ip: IpAddr,
+        remote: SocketAddr,
+        framing: TcpFraming,
+    ) -> io::Result<Self> {
+        TcpPacketConn::dial_from(None, local_ip, remote, framing).await
+    }
+
+    /// dial_with_net returns a conn connected over net to remote, from local_ip unless it's
+    /// unspecified.
+    pub async fn dial_with_net(
+        net: &Arc<Net>,
+        local_ip: IpAddr,
+        remote: SocketAddr,
+        framing: TcpFraming,
+    ) -> io::Result<Self> {
+        TcpPacketConn::dial_from(virtual_net(net), local_ip, remote, framing).await
+    }
+
+    async fn dial_from(
+        vnet: Option<Arc<Net>>,
+        local_ip: IpAddr,
+        remote: SocketAddr,
+        framing: TcpFraming,
+    ) -> io::Result<Self> {
+        let mut conn = TcpPacketConn::new(framing, SocketAddr::new(local_ip, 0), true, vnet);
+        conn.local_addr = conn.inner.connect(remote).await?;
+        *conn.remote_addr.lock() = Some(remote);
+        Ok(conn)
+    }
+
+    /// remote_addrs returns the addresses the conn has a stream with.
+    pub fn remote_addrs(&self) -> Vec<SocketAddr> {
+        self.inner.streams.lock().keys().copied().collect()
+    }
+}
+
+/// virtual_net returns net if it's a virtual network, the system streams are used otherwise.
+fn virtual_net(net: &Arc<Net>) -> Option<Arc<Net>> {
+    if net.is_virtual() {
+        Some(Arc::clone(net))
+    } else {
+        None
+    }
+}
+
+impl TcpPacketConnInner {
+    /// accept_stream adds the stream accepted from remote once its first packet, read within
+    /// timeout, turns out to be a STUN message, and closes it otherwise.
+    fn accept_stream(
+        self: &Arc<Self>,
+        mut stream: Box<dyn Stream>,
+        remote: SocketAddr,
+        timeout: Duration,
+    ) {
+        self.pending.fetch_add(1, Ordering::SeqCst);
+
+        let inner = Arc::clone(self);
+        self.tasks.spawn(async move {
+            let result =
+                tokio::time::timeout(timeout, inner.framing.read_packet(&mut stream)).await;
+            inner.pending.fetch_sub(1, Ordering::SeqCst);
+            match result {
+                Ok(Ok(packet)) if is_message(&packet) => {
+                    inner.add_stream(stream, remote, Some(packet))
+                }
+                Ok(Ok(_)) => log::debug!(
+                    "closing the TCP connection from {}, it didn't start with STUN",
+                    remote
+                ),
+                Ok(Err(err)) => log::trace!("TCP connection with {} ended: {}", remote, err),
+                Err(_) => log::debug!(
+                    "closing the TCP connection from {}, it sent no STUN in time",
+                    remote
+                ),
+            }
+        });
+    }
+
+    /// add_stream starts reading the packets of the stream with remote, first is a packet
+    /// already read from it.
+    fn add_stream(
+        self: &Arc<Self>,
+        stream: Box<dyn Stream>,
+        remote: SocketAddr,
+        first: Option<Vec<u8>>,
+    ) {
+        let recv_tx = match &*self.recv_tx.lock() {
+            Some(recv_tx) => recv_tx.clone(),
+            None => return,
+        };
+
+        let (mut reader, writer) = tokio::io::split(stream);
+        let writer = Arc::new(Mutex::new(writer));
+        self.streams.lock().insert(remote, Arc::clone(&writer));
+
+        let inner = Arc::clone(self);
+        self.tasks.spawn(async move {
+            let mut first = first;
+            loop {
+                let packet = match first.take() {
+                    Some(packet) => Ok(packet),
+                    None => inner.framing.read_packet(&mut reader).await,
+                };
+                match packet {
+                    Ok(packet) => {
+                        if recv_tx.send((packet, remote)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        log::trace!("TCP connection with {} ended: {}", remote, err);
+                        break;
+                    }
+                }
+            }
+
+            // unless it was replaced by a newer stream
+            let mut streams = inner.streams.lock();
+            if streams
+                .get(&remote)
+                .map_or(false, |w| Arc::ptr_eq(w, &writer))
+            {
+                streams.remove(&remote);
+            }
+        });
+    }
+
+    /// connect connects to remote and returns the local address of the stream.
+    async fn connect(self: &Arc<Self>, remote: SocketAddr) -> io::Result<SocketAddr> {
+        if let Some(net) = &self.vnet {
+            // a virtual network times out by itself
+            let stream = net
+                .dial_tcp(self.local_ip, remote)
+                .await
+                .map_err(into_io_error)?;
+            let local_addr = stream.local_addr();
+            self.add_stream(Box::new(stream), remote, None);
+            return Ok(local_addr);
+        }
+
+        let socket = if remote.is_ipv4() {
+            TcpSocket::new_v4()?
+        } else {
+            TcpSocket::new_v6()?
+        };
+        if !self.local_ip.is_unspecified() {
+            socket.bind(SocketAddr::new(self.local_ip, 0))?;
+        }
+
+        let stream = tokio::time::timeout(DIAL_TIMEOUT, socket.connect(remote))
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "TCP connect timed out"))??;
+        let local_addr = stream.local_addr()?;
+        self.add_stream(Box::new(nodelay(stream, remote)), remote, None);
+
+        Ok(local_addr)
+    }
+
+    /// connect_and_send connects to remote in the background and sends the framed packet once
+    /// connected, unless a connection to remote is already being made.
+    fn connect_and_send(self: &Arc<Self>, remote: SocketAddr, framed: Vec<u8>) {
+        if !self.dialing.lock().insert(remote) {
+            return;
+        }
+
+        let inner = Arc::clone(self);
+        self.tasks.spawn(async move {
+            let result = inner.connect(remote).await;
+            inner.dialing.lock().remove(&remote);
+            if let Err(err) = result {
+                log::debug!("failed to connect to {}: {}", remote, err);
+                return;
+            }
+
+            let writer = inner.streams.lock().get(&remote).cloned();
+            if let Some(writer) = writer {
+                let _ = writer.lock().await.write_all(&framed).await;
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl Conn for TcpPacketConn {
+    async fn connect(&self, addr: SocketAddr) -> ConnResult<()> {
+        let connected = self.inner.streams.lock().contains_key(&addr);
+        if !connected {
+            self.inner.connect(addr).await?;
+        }
+        *self.remote_addr.lock() = Some(addr);
+        Ok(())
+    }
+
+    async fn recv(&self, buf: &mut [u8]) -> ConnResult<usize> {
+        let (n, _) = self.recv_from(buf).await?;
+        Ok(n)
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> ConnResult<(usize, SocketAddr)> {
+        let mut recv_rx = self.recv_rx.lock().await;
+        match recv_rx.recv().await {
+            Some((packet, remote)) => {
+                // truncated like a datagram read into a short buffer
+                let n = std::cmp::min(packet.len(), buf.len());
+                buf[..n].copy_from_slice(&packet[..n]);
+                Ok((n, remote))
+            }
+            None => Err(Error::ErrUseClosedNetworkConn),
+        }
+    }
+
+    async fn send(&self, buf: &[u8]) -> ConnResult<usize> {
+        let remote_addr = *self.remote_addr.lock();
+        match remote_addr {
+            Some(remote_addr) => self.send_to(buf, remote_addr).await,
+            None => Err(io::Error::new(io::ErrorKind::NotConnected, "not connected").into()),
+        }
+    }
+
+    async fn send_to(&self, buf: &[u8], target: SocketAddr) -> ConnResult<usize> {
+        if self.inner.tasks.is_cancelled() {
+            return Err(Error::ErrUseClosedNetworkConn);
+        }
+        let framed = self.inner.framing.encode(buf)?;
+
+        let writer = self.inner.streams.lock().get(&target).cloned();
+        match writer {
+            Some(writer) => {
+                let result = writer.lock().await.write_all(&framed).await;
+                if let Err(err) = result {
+                    let mut streams = self.inner.streams.lock();
+                    if streams
+                        .get(&target)
+                        .map_or(false, |w| Arc::ptr_eq(w, &writer))
+                    {
+                        streams.remove(&target);
+                    }
+                    return Err(err.into());
+                }
+            }
+            None if self.inner.dial => self.inner.connect_and_send(target, framed),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotConnected,
+                    format!("no TCP connection with {}", target),
+                )
+                .into())
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn local_addr(&self) -> ConnResult<SocketAddr> {
+        Ok(self.local_addr)
+    }
+
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        *self.remote_addr.lock()
+    }
+
+    async fn close(&self) -> ConnResult<()> {
+        self.inner.recv_tx.lock().take();
+        self.inner.tasks.cancel();
+        self.inner.streams.lock().clear();
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+use super::*;
+use crate::error::Result;
+
+use std::net::Ipv4Addr;
+use stun::message::{Message, BINDING_REQUEST};
+use tokio::time::timeout;
+use turn::proto::chandata::ChannelData;
+use turn::proto::channum::ChannelNumber;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+async fn recv(conn: &TcpPacketConn) -> Result<(Vec<u8>, SocketAddr)> {
+    let mut buf = vec![0u8; 1500];
+    let (n, addr) = timeout(TIMEOUT, conn.recv_from(&mut buf))
+        .await
+        .expect("timed out waiting for a packet")?;
+    buf.truncate(n);
+    Ok((buf, addr))
+}
+
+#[tokio::test]
+async fn test_tcp_packet_conn_rfc4571() -> Result<()> {
+    let passive = TcpPacketConn::listen(SocketAddr::new(LOCALHOST, 0), TcpFraming::Rfc4571).await?;
+    let passive_addr = passive.local_addr()?;
+    let active = TcpPacketConn::dialer(LOCALHOST, TcpFraming::Rfc4571);
+
+    // Nothing to reply to before the active side connected
+    assert!(passive
+        .send_to(b"early", SocketAddr::new(LOCALHOST, 9))
+        .await
+        .is_err());
+
+    // The first packets are sent once connected, a packet sent meanwhile is dropped
+    let mut request = Message::new();
+    request.build(&[Box::new(BINDING_REQUEST)])?;
+    let mut received = None;
+    for _ in 0..50 {
+        active.send_to(&request.raw, passive_addr).await?;
+        let mut buf = vec![0u8; 1500];
+        if let Ok(result) = timeout(Duration::from_millis(100), passive.recv_from(&mut buf)).await {
+            let (n, addr) = result?;
+            received = Some((buf[..n].to_vec(), addr));
+            break;
+        }
+    }
+    let (packet, active_addr) = received.expect("no packet received");
+    assert_eq!(packet, request.raw);
+    assert_eq!(passive.remote_addrs(), vec![active_addr]);
+
+    // Packets keep their boundaries, including empty and large ones
+    let large = vec![7u8; 1400];
+    for p in [&b""[..], &large[..], &b"x"[..]].iter() {
+        passive.send_to(p, active_addr).await?;
+    }
+    for p in [&b""[..], &large[..], &b"x"[..]].iter() {
+        let (packet, addr) = recv(&active).await?;
+        assert_eq!(&packet[..], *p);
+        assert_eq!(addr, passive_addr);
+    }
+
+    assert!(matches!(
+        active.send_to(&vec![0u8; 70000], passive_addr).await,
+        Err(Error::ErrPacketTooBig)
+    ));
+
+    active.close().await?;
+    passive.close().await?;
+    assert!(matches!(
+        passive.recv_from(&mut [0u8; 10]).await,
+        Err(Error::ErrUseClosedNetworkConn)
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tcp_packet_conn_stun_framing() -> Result<()> {
+    let server = TcpPacketConn::listen(SocketAddr::new(LOCALHOST, 0), TcpFraming::Stun).await?;
+    let server_addr = server.local_addr()?;
+    let client = TcpPacketConn::dial(LOCALHOST, server_addr, TcpFraming::Stun).await?;
+    assert_eq!(client.remote_addr(), Some(server_addr));
+
+    let mut request = Message::new();
+    request.build(&[Box::new(BINDING_REQUEST)])?;
+
+    // A ChannelData message with a length that isn't a multiple of 4, padded when encoded
+    let mut channel_data = ChannelData {
+        data: vec![1, 2, 3, 4, 5],
+        number: ChannelNumber(0x4001),
+        ..Default::default()
+    };
+    channel_data.encode();
+    assert_eq!(channel_data.raw.len(), 12);
+
+    client.send(&request.raw).await?;
+    client.send(&channel_data.raw).await?;
+    client.send(&request.raw).await?;
+
+    let (packet, client_addr) = recv(&server).await?;
+    assert_eq!(packet, request.raw);
+    let (packet, _) = recv(&server).await?;
+    assert_eq!(packet, channel_data.raw);
+    let (packet, _) = recv(&server).await?;
+    assert_eq!(packet, request.raw);
+
+    server.send_to(&request.raw, client_addr).await?;
+    let (packet, addr) = recv(&client).await?;
+    assert_eq!(packet, request.raw);
+    assert_eq!(addr, server_addr);
+
+    client.close().await?;
+    server.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tcp_packet_conn_closes_pending_streams() -> Result<()> {
+    let pending_timeout = Duration::from_millis(200);
+    let passive = TcpPacketConn::listen_with_limits(
+        None,
+        SocketAddr::new(LOCALHOST, 0),
+        TcpFraming::Rfc4571,
+        2,
+        pending_timeout,
+    )
+    .await?;
+    let passive_addr = passive.local_addr()?;
+
+    // closed reports whether the passive side closed the stream within the timeout
+    async fn closed(stream: &mut TcpStream, within: Duration) -> bool {
+        let mut buf = [0u8; 16];
+        matches!(
+            timeout(within, stream.read(&mut buf)).await,
+            Ok(Ok(0)) | Ok(Err(_))
+        )
+    }
+
+    // Two silent streams are pending, a third one is closed right away
+    let mut silent1 = TcpStream::connect(passive_addr).await?;
+    let mut silent2 = TcpStream::connect(passive_addr).await?;
+    let mut excess = TcpStream::connect(passive_addr).await?;
+    assert!(closed(&mut excess, TIMEOUT).await);
+    assert!(!closed(&mut silent1, Duration::from_millis(50)).await);
+
+    // and the pending ones once they didn't send a STUN message in time
+    assert!(closed(&mut silent1, TIMEOUT).await);
+    assert!(closed(&mut silent2, TIMEOUT).await);
+
+    // A stream that doesn't start with a STUN message is closed
+    let mut garbage = TcpStream::connect(passive_addr).await?;
+    garbage
+        .write_all(&TcpFraming::Rfc4571.encode(b"hello")?)
+        .await?;
+    assert!(closed(&mut garbage, TIMEOUT).await);
+    assert!(passive.remote_addrs().is_empty());
+
+    // One that does is used
+    let mut request = Message::new();
+    request.build(&[Box::new(BINDING_REQUEST)])?;
+    let mut valid = TcpStream::connect(passive_addr).await?;
+    valid
+        .write_all(&TcpFraming::Rfc4571.encode(&request.raw)?)
+        .await?;
+    valid
+        .write_all(&TcpFraming::Rfc4571.encode(b"hello")?)
+        .await?;
+    let (packet, valid_addr) = recv(&passive).await?;
+    assert_eq!(packet, request.raw);
+    assert_eq!(valid_addr, valid.local_addr()?);
+    let (packet, _) = recv(&passive).await?;
+    assert_eq!(packet, b"hello");
+    assert_eq!(passive.remote_addrs(), vec![valid_addr]);
+    assert!(!closed(&mut valid, pending_timeout * 2).await);
+
+    passive.close().await?;
+
+    Ok(())
+}
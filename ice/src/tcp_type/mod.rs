@@ -41,6 +41,20 @@ impl fmt::Display for TcpType {
     }
 }
 
+impl TcpType {
+    /// Returns true if a local candidate of this type can be paired with a remote candidate of
+    /// the given type, see RFC 6544 Section 6.2. A remote candidate of unspecified type is a
+    /// peer-reflexive candidate learned from a connection accepted by a passive candidate.
+    #[must_use]
+    pub fn can_pair_with(self, remote: Self) -> bool {
+        match self {
+            Self::Unspecified | Self::SimultaneousOpen => true,
+            Self::Active => remote == Self::Passive || remote == Self::SimultaneousOpen,
+            Self::Passive => remote != Self::Passive,
+        }
+    }
+}
+
 impl Default for TcpType {
     fn default() -> Self {
         Self::Unspecified
@@ -16,3 +16,31 @@ fn test_tcp_type() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_tcp_type_can_pair_with() -> Result<()> {
+    let tests = vec![
+        (TcpType::Active, TcpType::Active, false),
+        (TcpType::Active, TcpType::Passive, true),
+        (TcpType::Active, TcpType::SimultaneousOpen, true),
+        (TcpType::Active, TcpType::Unspecified, false),
+        (TcpType::Passive, TcpType::Active, true),
+        (TcpType::Passive, TcpType::Passive, false),
+        (TcpType::Passive, TcpType::SimultaneousOpen, true),
+        (TcpType::Passive, TcpType::Unspecified, true),
+        (TcpType::SimultaneousOpen, TcpType::Passive, true),
+        (TcpType::Unspecified, TcpType::Unspecified, true),
+    ];
+
+    for (local, remote, expected) in tests {
+        assert_eq!(
+            local.can_pair_with(remote),
+            expected,
+            "{} with {}",
+            local,
+            remote
+        );
+    }
+
+    Ok(())
+}
@@ -1,6 +1,8 @@
-use std::{collections::HashMap, io::ErrorKind, net::SocketAddr, sync::Arc, sync::Weak};
+use std::{
+    collections::HashMap, io::ErrorKind, net::SocketAddr, sync::Arc, sync::Weak, time::Duration,
+};
 
-use util::{sync::RwLock, Conn, Error};
+use util::{sync::RwLock, sync::TaskGroup, Conn, Error};
 
 use async_trait::async_trait;
 
@@ -19,7 +21,9 @@ use stun::{
     message::{is_message as is_stun_message, Message as STUNMessage},
 };
 
+use crate::agent::agent_config::TASKS_CLOSE_TIMEOUT;
 use crate::candidate::RECEIVE_MTU;
+use crate::pacer::{Pacer, DEFAULT_PACING_INTERVAL};
 
 /// Normalize a target socket addr for sending over a given local socket addr. This is useful when
 /// a dual stack socket is used, in which case an IPv4 target needs to be mapped to an IPv6
@@ -46,10 +50,17 @@ pub trait UDPMux {
 
     /// Remove the underlying connection for a given ufrag.
     async fn remove_conn_by_ufrag(&self, ufrag: &str);
+
+    /// Get the pacer shared by the agents using the mux, which bounds the rate of the connectivity
+    /// checks and gathering transactions sent from the socket.
+    fn pacer(&self) -> Option<Arc<Pacer>> {
+        None
+    }
 }
 
 pub struct UDPMuxParams {
     conn: Box<dyn Conn + Send + Sync>,
+    pacing_interval: Duration,
 }
 
 impl UDPMuxParams {
@@ -59,8 +70,16 @@ impl UDPMuxParams {
     {
         Self {
             conn: Box::new(conn),
+            pacing_interval: DEFAULT_PACING_INTERVAL,
         }
     }
+
+    /// Sets the minimum time between two STUN transactions of all the agents using the mux,
+    /// 50 ms by default. An interval of 0 leaves the pacing to each agent.
+    pub fn with_pacing_interval(mut self, interval: Duration) -> Self {
+        self.pacing_interval = interval;
+        self
+    }
 }
 
 pub struct UDPMuxDefault {
@@ -79,18 +98,27 @@ pub struct UDPMuxDefault {
 
     /// Close reciever
     closed_watch_rx: watch::Receiver<()>,
+
+    /// Paces the STUN transactions of the agents using the mux
+    pacer: Arc<Pacer>,
+
+    /// The read loop and the tasks removing closed connections, waited for on close
+    tasks: TaskGroup,
 }
 
 impl UDPMuxDefault {
     pub fn new(params: UDPMuxParams) -> Arc<Self> {
         let (closed_watch_tx, closed_watch_rx) = watch::channel(());
 
+        let pacer = Arc::new(Pacer::new(params.pacing_interval));
         let mux = Arc::new(Self {
             params,
             conns: Mutex::default(),
             address_map: RwLock::default(),
             closed_watch_tx: Mutex::new(Some(closed_watch_tx)),
             closed_watch_rx: closed_watch_rx.clone(),
+            pacer,
+            tasks: TaskGroup::new(),
         });
 
         let cloned_mux = Arc::clone(&mux);
@@ -161,7 +189,8 @@ impl UDPMuxDefault {
     }
 
     fn start_conn_worker(self: Arc<Self>, mut closed_watch_rx: watch::Receiver<()>) {
-        tokio::spawn(async move {
+        let tasks = self.tasks.clone();
+        tasks.spawn(async move {
             let mut buffer = [0u8; RECEIVE_MTU];
 
             loop {
@@ -249,6 +278,13 @@ impl UDPMux for UDPMuxDefault {
                 // avoid a retain cycle due to the use of [`std::sync::Arc`] on both sides.
                 let _ = std::mem::take(&mut (*address_map));
             }
+
+            // The read loop stops on the close signal, the other tasks once their
+            // connection is closed
+            let cancelled = self.tasks.close(TASKS_CLOSE_TIMEOUT).await;
+            if cancelled > 0 {
+                log::debug!("cancelled {} UDP mux tasks on close", cancelled);
+            }
         }
 
         Ok(())
@@ -271,7 +307,7 @@ impl UDPMux for UDPMuxDefault {
             let mut close_rx = muxed_conn.close_rx();
             let cloned_self = Arc::clone(&self);
             let cloned_ufrag = ufrag.to_string();
-            tokio::spawn(async move {
+            self.tasks.spawn(async move {
                 let _ = close_rx.changed().await;
 
                 // Arc needed
@@ -301,6 +337,10 @@ impl UDPMux for UDPMuxDefault {
             }
         }
     }
+
+    fn pacer(&self) -> Option<Arc<Pacer>> {
+        Some(Arc::clone(&self.pacer))
+    }
 }
 
 #[async_trait]
@@ -4,8 +4,10 @@ mod util_test;
 use crate::agent::agent_config::{InterfaceFilterFn, IpFilterFn};
 use crate::error::*;
 use crate::network_type::*;
+use crate::priority::PriorityAttr;
 
 use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use stun::{agent::*, attributes::*, integrity::*, message::*, textattrs::*, xoraddr::*};
@@ -42,6 +44,27 @@ pub fn assert_inbound_message_integrity(m: &mut Message, key: &[u8]) -> Result<(
     Ok(message_integrity_attr.check(m)?)
 }
 
+/// Checks the ICE attributes of a connectivity check request (RFC 8445 section 7.1): it must
+/// carry a PRIORITY, exactly one of ICE-CONTROLLING and ICE-CONTROLLED, and USE-CANDIDATE only
+/// when sent by the controlling agent.
+pub fn assert_inbound_binding_request(m: &Message) -> Result<()> {
+    if PriorityAttr::default().get_from(m).is_err() {
+        return Err(Error::ErrMissingPriority);
+    }
+
+    match (
+        m.contains(ATTR_ICE_CONTROLLING),
+        m.contains(ATTR_ICE_CONTROLLED),
+    ) {
+        (false, false) => Err(Error::ErrMissingRole),
+        (true, true) => Err(Error::ErrConflictingRoles),
+        (false, true) if m.contains(ATTR_USE_CANDIDATE) => {
+            Err(Error::ErrUseCandidateFromControlled)
+        }
+        _ => Ok(()),
+    }
+}
+
 /// Initiates a stun requests to `server_addr` using conn, reads the response and returns the
 /// `XORMappedAddress` returned by the stun server.
 /// Adapted from stun v0.2.
@@ -93,7 +116,31 @@ pub async fn local_interfaces(
     ip_filter: &Option<IpFilterFn>,
     network_types: &[NetworkType],
 ) -> HashSet<IpAddr> {
+    local_interface_addrs(vnet, interface_filter, ip_filter, network_types)
+        .await
+        .into_iter()
+        .map(|addr| addr.ip)
+        .collect()
+}
+
+/// An address of a local network interface.
+pub(crate) struct InterfaceAddr {
+    pub(crate) ip: IpAddr,
+    pub(crate) interface: String,
+    /// The position of the interface among all interfaces, starting at 1
+    pub(crate) network_id: u16,
+}
+
+/// local_interface_addrs returns the addresses `local_interfaces` returns along with the
+/// interface they belong to.
+pub(crate) async fn local_interface_addrs(
+    vnet: &Arc<Net>,
+    interface_filter: &Option<InterfaceFilterFn>,
+    ip_filter: &Option<IpFilterFn>,
+    network_types: &[NetworkType],
+) -> Vec<InterfaceAddr> {
     let mut ips = HashSet::new();
+    let mut addrs = vec![];
     let interfaces = vnet.get_interfaces().await;
 
     let (mut ipv4requested, mut ipv6requested) = (false, false);
@@ -106,7 +153,7 @@ pub async fn local_interfaces(
         }
     }
 
-    for iface in interfaces {
+    for (i, iface) in interfaces.iter().enumerate() {
         if let Some(filter) = interface_filter {
             if !filter(iface.name()) {
                 continue;
@@ -122,13 +169,18 @@ pub async fn local_interfaces(
                     .as_ref()
                     .map(|filter| filter(ipaddr))
                     .unwrap_or(true)
+                && ips.insert(ipaddr)
             {
-                ips.insert(ipaddr);
+                addrs.push(InterfaceAddr {
+                    ip: ipaddr,
+                    interface: iface.name().to_owned(),
+                    network_id: u16::try_from(i + 1).unwrap_or(u16::MAX),
+                });
             }
         }
     }
 
-    ips
+    addrs
 }
 
 pub async fn listen_udp_in_port_range(
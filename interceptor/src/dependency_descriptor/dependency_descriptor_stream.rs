@@ -0,0 +1,96 @@
+use super::*;
+
+use rtp::extension::dependency_descriptor_extension::{
+    DependencyDescriptorExtension, FrameDependencyStructure,
+};
+use tokio::sync::Mutex;
+use util::Unmarshal;
+
+#[derive(Default)]
+struct StreamState {
+    structure: Option<FrameDependencyStructure>,
+    active_decode_targets: Option<u32>,
+}
+
+pub(super) struct DependencyDescriptorStream {
+    parent_rtp_reader: Arc<dyn RTPReader + Send + Sync>,
+    hdr_ext_id: u8,
+    state: Mutex<StreamState>,
+}
+
+impl DependencyDescriptorStream {
+    pub(super) fn new(parent_rtp_reader: Arc<dyn RTPReader + Send + Sync>, hdr_ext_id: u8) -> Self {
+        DependencyDescriptorStream {
+            parent_rtp_reader,
+            hdr_ext_id,
+            state: Mutex::new(StreamState::default()),
+        }
+    }
+
+    async fn process(&self, mut ext: &[u8]) -> Option<DependencyDescriptorInfo> {
+        let mut state = self.state.lock().await;
+
+        let dd = match DependencyDescriptorExtension::unmarshal_with_structure(
+            &mut ext,
+            state.structure.as_ref(),
+        ) {
+            Ok(dd) => dd,
+            Err(err) => {
+                log::debug!("ignoring dependency descriptor: {}", err);
+                return None;
+            }
+        };
+
+        if let Some(structure) = &dd.attached_structure {
+            // a new structure activates all of its decode targets
+            state.structure = Some(structure.clone());
+            state.active_decode_targets = None;
+        }
+        if let Some(bitmask) = dd.active_decode_targets_bitmask {
+            state.active_decode_targets = Some(bitmask);
+        }
+
+        let mut info = DependencyDescriptorInfo {
+            start_of_frame: dd.start_of_frame,
+            end_of_frame: dd.end_of_frame,
+            frame_number: dd.frame_number,
+            template_id: dd.frame_dependency_template_id,
+            ..Default::default()
+        };
+        if let Some(structure) = &state.structure {
+            if let Ok(frame) = dd.frame_dependency(structure) {
+                info.spatial_id = Some(frame.spatial_id);
+                info.temporal_id = Some(frame.temporal_id);
+            }
+            let all_decode_targets = u64::MAX
+                .checked_shr(64 - structure.decode_target_count as u32)
+                .unwrap_or(0);
+            info.active_decode_targets = Some(
+                state
+                    .active_decode_targets
+                    .unwrap_or(all_decode_targets as u32),
+            );
+        }
+
+        Some(info)
+    }
+}
+
+#[async_trait]
+impl RTPReader for DependencyDescriptorStream {
+    /// read a rtp packet
+    async fn read(&self, buf: &mut [u8], attributes: &Attributes) -> Result<(usize, Attributes)> {
+        let (n, mut attr) = self.parent_rtp_reader.read(buf, attributes).await?;
+
+        let mut b = &buf[..n];
+        let p = rtp::packet::Packet::unmarshal(&mut b)?;
+
+        if let Some(ext) = p.header.get_extension(self.hdr_ext_id) {
+            if let Some(info) = self.process(&ext).await {
+                info.to_attributes(&mut attr);
+            }
+        }
+
+        Ok((n, attr))
+    }
+}
@@ -0,0 +1,151 @@
+use super::*;
+use crate::stream_info::RTPHeaderExtension;
+use bytes::Bytes;
+use rtp::extension::dependency_descriptor_extension::{
+    DecodeTargetIndication, DependencyDescriptorExtension, FrameDependencyStructure,
+    FrameDependencyTemplate,
+};
+use std::collections::VecDeque;
+use tokio::sync::Mutex;
+use util::Marshal;
+
+struct PacketQueue(Mutex<VecDeque<Bytes>>);
+
+#[async_trait]
+impl RTPReader for PacketQueue {
+    async fn read(&self, buf: &mut [u8], a: &Attributes) -> Result<(usize, Attributes)> {
+        let raw = self.0.lock().await.pop_front().ok_or(Error::ErrIoEOF)?;
+        buf[..raw.len()].copy_from_slice(&raw);
+        Ok((raw.len(), a.clone()))
+    }
+}
+
+fn l1t2_structure() -> FrameDependencyStructure {
+    use DecodeTargetIndication::*;
+
+    FrameDependencyStructure {
+        decode_target_count: 2,
+        templates: vec![
+            FrameDependencyTemplate {
+                spatial_id: 0,
+                temporal_id: 0,
+                decode_target_indications: vec![Switch, Switch],
+                ..Default::default()
+            },
+            FrameDependencyTemplate {
+                spatial_id: 0,
+                temporal_id: 1,
+                decode_target_indications: vec![NotPresent, Discardable],
+                frame_diffs: vec![1],
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+fn packet(dd: Option<&DependencyDescriptorExtension>) -> Result<Bytes> {
+    let mut header = rtp::header::Header {
+        ssrc: 1,
+        ..Default::default()
+    };
+    if let Some(dd) = dd {
+        header.set_extension(5, dd.marshal()?)?;
+    }
+    Ok(rtp::packet::Packet {
+        header,
+        payload: Bytes::from_static(&[0x01]),
+    }
+    .marshal()?)
+}
+
+#[tokio::test]
+async fn test_dependency_descriptor_interceptor() -> Result<()> {
+    let icpr = DependencyDescriptorInterceptor::builder().build("")?;
+
+    let key_frame = DependencyDescriptorExtension {
+        start_of_frame: true,
+        end_of_frame: true,
+        frame_number: 1,
+        attached_structure: Some(l1t2_structure()),
+        ..Default::default()
+    };
+    let delta_frame = DependencyDescriptorExtension {
+        start_of_frame: true,
+        end_of_frame: false,
+        frame_dependency_template_id: 1,
+        frame_number: 2,
+        ..Default::default()
+    };
+    let queue = PacketQueue(Mutex::new(VecDeque::from(vec![
+        // refers to a structure which hasn't been received
+        packet(Some(&delta_frame))?,
+        packet(Some(&key_frame))?,
+        packet(Some(&delta_frame))?,
+        packet(None)?,
+    ])));
+
+    let reader = icpr
+        .bind_remote_stream(
+            &StreamInfo {
+                ssrc: 1,
+                rtp_header_extensions: vec![RTPHeaderExtension {
+                    uri: DEPENDENCY_DESCRIPTOR_URI.to_owned(),
+                    id: 5,
+                }],
+                ..Default::default()
+            },
+            Arc::new(queue),
+        )
+        .await;
+
+    let mut buf = vec![0u8; 1500];
+    let a = Attributes::new();
+
+    let (_, attr) = reader.read(&mut buf, &a).await?;
+    assert_eq!(
+        DependencyDescriptorInfo::from_attributes(&attr),
+        Some(DependencyDescriptorInfo {
+            start_of_frame: true,
+            end_of_frame: false,
+            frame_number: 2,
+            template_id: 1,
+            ..Default::default()
+        })
+    );
+
+    let (_, attr) = reader.read(&mut buf, &a).await?;
+    assert_eq!(
+        DependencyDescriptorInfo::from_attributes(&attr),
+        Some(DependencyDescriptorInfo {
+            start_of_frame: true,
+            end_of_frame: true,
+            frame_number: 1,
+            template_id: 0,
+            spatial_id: Some(0),
+            temporal_id: Some(0),
+            active_decode_targets: Some(0b11),
+        })
+    );
+
+    let (_, attr) = reader.read(&mut buf, &a).await?;
+    assert_eq!(
+        DependencyDescriptorInfo::from_attributes(&attr),
+        Some(DependencyDescriptorInfo {
+            start_of_frame: true,
+            end_of_frame: false,
+            frame_number: 2,
+            template_id: 1,
+            spatial_id: Some(0),
+            temporal_id: Some(1),
+            active_decode_targets: Some(0b11),
+        })
+    );
+
+    let (_, attr) = reader.read(&mut buf, &a).await?;
+    assert_eq!(DependencyDescriptorInfo::from_attributes(&attr), None);
+
+    icpr.close().await?;
+
+    Ok(())
+}
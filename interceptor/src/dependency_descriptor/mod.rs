@@ -0,0 +1,159 @@
+mod dependency_descriptor_stream;
+#[cfg(test)]
+mod dependency_descriptor_test;
+
+use crate::*;
+use dependency_descriptor_stream::DependencyDescriptorStream;
+use sdp::extmap::DEPENDENCY_DESCRIPTOR_URI;
+
+// Attribute keys set on every RTP packet carrying a dependency descriptor
+const ATTR_BASE: usize = 0xDD00;
+pub const ATTR_FRAME_NUMBER: usize = ATTR_BASE + 1;
+pub const ATTR_START_OF_FRAME: usize = ATTR_BASE + 2;
+pub const ATTR_END_OF_FRAME: usize = ATTR_BASE + 3;
+pub const ATTR_TEMPLATE_ID: usize = ATTR_BASE + 4;
+/// Only set once the template dependency structure of the stream is known
+pub const ATTR_SPATIAL_ID: usize = ATTR_BASE + 5;
+/// Only set once the template dependency structure of the stream is known
+pub const ATTR_TEMPORAL_ID: usize = ATTR_BASE + 6;
+/// Only set once the template dependency structure of the stream is known
+pub const ATTR_ACTIVE_DECODE_TARGETS: usize = ATTR_BASE + 7;
+
+/// DependencyDescriptorInfo is the part of the dependency descriptor of a packet exposed
+/// through its attributes.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct DependencyDescriptorInfo {
+    pub start_of_frame: bool,
+    pub end_of_frame: bool,
+    pub frame_number: u16,
+    pub template_id: u8,
+    pub spatial_id: Option<u8>,
+    pub temporal_id: Option<u8>,
+    /// Bitmask of the decode targets currently sent by the remote
+    pub active_decode_targets: Option<u32>,
+}
+
+impl DependencyDescriptorInfo {
+    /// from_attributes returns the dependency descriptor stored in the attributes returned by
+    /// `read_rtp`, or None if the packet didn't carry one.
+    pub fn from_attributes(attributes: &Attributes) -> Option<Self> {
+        let frame_number = *attributes.get(&ATTR_FRAME_NUMBER)?;
+        Some(DependencyDescriptorInfo {
+            start_of_frame: attributes.get(&ATTR_START_OF_FRAME) == Some(&1),
+            end_of_frame: attributes.get(&ATTR_END_OF_FRAME) == Some(&1),
+            frame_number: frame_number as u16,
+            template_id: attributes
+                .get(&ATTR_TEMPLATE_ID)
+                .copied()
+                .unwrap_or_default() as u8,
+            spatial_id: attributes.get(&ATTR_SPATIAL_ID).map(|v| *v as u8),
+            temporal_id: attributes.get(&ATTR_TEMPORAL_ID).map(|v| *v as u8),
+            active_decode_targets: attributes
+                .get(&ATTR_ACTIVE_DECODE_TARGETS)
+                .map(|v| *v as u32),
+        })
+    }
+
+    pub(crate) fn to_attributes(self, attributes: &mut Attributes) {
+        attributes.insert(ATTR_FRAME_NUMBER, self.frame_number as usize);
+        attributes.insert(ATTR_START_OF_FRAME, self.start_of_frame as usize);
+        attributes.insert(ATTR_END_OF_FRAME, self.end_of_frame as usize);
+        attributes.insert(ATTR_TEMPLATE_ID, self.template_id as usize);
+        if let Some(spatial_id) = self.spatial_id {
+            attributes.insert(ATTR_SPATIAL_ID, spatial_id as usize);
+        }
+        if let Some(temporal_id) = self.temporal_id {
+            attributes.insert(ATTR_TEMPORAL_ID, temporal_id as usize);
+        }
+        if let Some(active_decode_targets) = self.active_decode_targets {
+            attributes.insert(ATTR_ACTIVE_DECODE_TARGETS, active_decode_targets as usize);
+        }
+    }
+}
+
+/// DependencyDescriptorBuilder is a InterceptorBuilder for a DependencyDescriptorInterceptor
+#[derive(Default)]
+pub struct DependencyDescriptorBuilder;
+
+impl InterceptorBuilder for DependencyDescriptorBuilder {
+    fn build(&self, _id: &str) -> Result<Arc<dyn Interceptor + Send + Sync>> {
+        Ok(Arc::new(DependencyDescriptorInterceptor {}))
+    }
+}
+
+/// DependencyDescriptorInterceptor parses the AV1 Dependency Descriptor header extension of
+/// incoming RTP packets and exposes it through the packet attributes, see
+/// `DependencyDescriptorInfo::from_attributes`. The template dependency structure is kept per
+/// stream so descriptors referring to an earlier structure can be interpreted.
+pub struct DependencyDescriptorInterceptor {}
+
+impl DependencyDescriptorInterceptor {
+    /// builder returns a new DependencyDescriptorBuilder.
+    pub fn builder() -> DependencyDescriptorBuilder {
+        DependencyDescriptorBuilder
+    }
+}
+
+#[async_trait]
+impl Interceptor for DependencyDescriptorInterceptor {
+    /// bind_rtcp_reader lets you modify any incoming RTCP packets. It is called once per sender/receiver, however this might
+    /// change in the future. The returned method will be called once per packet batch.
+    async fn bind_rtcp_reader(
+        &self,
+        reader: Arc<dyn RTCPReader + Send + Sync>,
+    ) -> Arc<dyn RTCPReader + Send + Sync> {
+        reader
+    }
+
+    /// bind_rtcp_writer lets you modify any outgoing RTCP packets. It is called once per PeerConnection. The returned method
+    /// will be called once per packet batch.
+    async fn bind_rtcp_writer(
+        &self,
+        writer: Arc<dyn RTCPWriter + Send + Sync>,
+    ) -> Arc<dyn RTCPWriter + Send + Sync> {
+        writer
+    }
+
+    /// bind_local_stream lets you modify any outgoing RTP packets. It is called once for per LocalStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_local_stream(
+        &self,
+        _info: &StreamInfo,
+        writer: Arc<dyn RTPWriter + Send + Sync>,
+    ) -> Arc<dyn RTPWriter + Send + Sync> {
+        writer
+    }
+
+    /// unbind_local_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_local_stream(&self, _info: &StreamInfo) {}
+
+    /// bind_remote_stream lets you modify any incoming RTP packets. It is called once for per RemoteStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_remote_stream(
+        &self,
+        info: &StreamInfo,
+        reader: Arc<dyn RTPReader + Send + Sync>,
+    ) -> Arc<dyn RTPReader + Send + Sync> {
+        let mut hdr_ext_id = 0u8;
+        for e in &info.rtp_header_extensions {
+            if e.uri == DEPENDENCY_DESCRIPTOR_URI {
+                hdr_ext_id = e.id as u8;
+                break;
+            }
+        }
+        if hdr_ext_id == 0 {
+            // Don't try to read header extension if ID is 0, because 0 is an invalid extension ID
+            return reader;
+        }
+
+        Arc::new(DependencyDescriptorStream::new(reader, hdr_ext_id))
+    }
+
+    /// unbind_remote_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_remote_stream(&self, _info: &StreamInfo) {}
+
+    /// close closes the Interceptor, cleaning up any data if necessary.
+    async fn close(&self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,365 @@
+use super::writer::{
+    rotated_path, IPV4_UDP_HEADER_LEN, LOCAL_IP, PCAP_FILE_HEADER_LEN, PCAP_RECORD_HEADER_LEN,
+    PORT, REMOTE_IP, RTPDUMP_FILE_HEADER_LEN, RTPDUMP_PACKET_HEADER_LEN,
+};
+use super::*;
+use crate::mock::mock_stream::MockStream;
+use crate::mock::mock_time::MockTime;
+
+use rtcp::receiver_report::ReceiverReport;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const START: Duration = Duration::from_secs(1_600_000_000);
+const INTERVAL: Duration = Duration::from_millis(20);
+
+/// temp_path returns a path in the temporary directory unique to the test.
+fn temp_path(name: &str) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .subsec_nanos();
+    std::env::temp_dir()
+        .join(format!("dump-{}-{}-{}", name, std::process::id(), nanos))
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn rtp_packet(sequence_number: u16, payload_len: usize) -> rtp::packet::Packet {
+    rtp::packet::Packet {
+        header: rtp::header::Header {
+            version: 2,
+            sequence_number,
+            ssrc: 1,
+            ..Default::default()
+        },
+        payload: Bytes::from(vec![sequence_number as u8; payload_len]),
+    }
+}
+
+fn rtcp_packets() -> Vec<Box<dyn rtcp::packet::Packet + Send + Sync>> {
+    vec![Box::new(ReceiverReport {
+        ssrc: 2,
+        ..Default::default()
+    })]
+}
+
+/// session writes and receives a few packets through the interceptor, advancing the clock by
+/// INTERVAL after each of them.
+async fn session(icpr: &Arc<DumpInterceptor>, time: &MockTime) -> Result<()> {
+    let stream = MockStream::new(
+        &StreamInfo {
+            ssrc: 1,
+            ..Default::default()
+        },
+        Arc::clone(icpr) as Arc<dyn Interceptor + Send + Sync>,
+    )
+    .await;
+
+    for i in 0..3u16 {
+        stream.write_rtp(&rtp_packet(i, 100)).await?;
+        time.advance(INTERVAL);
+        stream.receive_rtp(rtp_packet(100 + i, 200)).await;
+        stream.read_rtp().await.unwrap()?;
+        time.advance(INTERVAL);
+    }
+    stream.write_rtcp(&rtcp_packets()).await?;
+    time.advance(INTERVAL);
+    stream.receive_rtcp(rtcp_packets()).await;
+    stream.read_rtcp().await.unwrap()?;
+
+    stream.close().await
+}
+
+fn mock_time() -> Arc<MockTime> {
+    let time = Arc::new(MockTime::default());
+    time.set_now(UNIX_EPOCH + START);
+    time
+}
+
+fn u16_be(b: &[u8]) -> u16 {
+    u16::from_be_bytes([b[0], b[1]])
+}
+
+fn u32_be(b: &[u8]) -> u32 {
+    u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+}
+
+fn u32_le(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+/// RtpDumpPacket is a packet parsed from an rtpdump file.
+struct RtpDumpPacket {
+    rtp_len: u16,
+    offset: u32,
+    data: Vec<u8>,
+}
+
+fn parse_rtpdump(b: &[u8]) -> (Duration, Vec<RtpDumpPacket>) {
+    let first_line = format!("#!rtpplay1.0 {}/{}\n", REMOTE_IP, PORT);
+    assert!(b.starts_with(first_line.as_bytes()));
+    let mut b = &b[first_line.len()..];
+
+    let start = Duration::new(u32_be(&b[0..]) as u64, u32_be(&b[4..]) * 1000);
+    assert_eq!(u32_be(&b[8..]), u32::from(REMOTE_IP));
+    assert_eq!(u16_be(&b[12..]), PORT);
+    b = &b[RTPDUMP_FILE_HEADER_LEN..];
+
+    let mut packets = vec![];
+    while !b.is_empty() {
+        let len = u16_be(&b[0..]) as usize;
+        packets.push(RtpDumpPacket {
+            rtp_len: u16_be(&b[2..]),
+            offset: u32_be(&b[4..]),
+            data: b[RTPDUMP_PACKET_HEADER_LEN..len].to_vec(),
+        });
+        b = &b[len..];
+    }
+    (start, packets)
+}
+
+/// PcapPacket is a packet parsed from a pcap file.
+struct PcapPacket {
+    time: Duration,
+    src: Ipv4Addr,
+    dst: Ipv4Addr,
+    data: Vec<u8>,
+}
+
+fn parse_pcap(b: &[u8]) -> Vec<PcapPacket> {
+    assert_eq!(u32_le(&b[0..]), 0xa1b2_c3d4);
+    assert_eq!(&b[4..8], &[2, 0, 4, 0]);
+    assert_eq!(u32_le(&b[20..]), 101);
+    let mut b = &b[PCAP_FILE_HEADER_LEN..];
+
+    let mut packets = vec![];
+    while !b.is_empty() {
+        let time = Duration::new(u32_le(&b[0..]) as u64, u32_le(&b[4..]) * 1000);
+        let len = u32_le(&b[8..]) as usize;
+        assert_eq!(u32_le(&b[12..]), len as u32);
+        let ip = &b[PCAP_RECORD_HEADER_LEN..PCAP_RECORD_HEADER_LEN + len];
+
+        assert_eq!(ip[0], 0x45);
+        assert_eq!(u16_be(&ip[2..]) as usize, len);
+        assert_eq!(ip[9], 17);
+        let sum = ip[..20].chunks(2).map(|w| u16_be(w) as u32).sum::<u32>();
+        assert_eq!((sum & 0xffff) + (sum >> 16), 0xffff, "invalid ip checksum");
+        assert_eq!(u16_be(&ip[20..]), PORT);
+        assert_eq!(u16_be(&ip[22..]), PORT);
+        assert_eq!(u16_be(&ip[24..]) as usize, len - 20);
+
+        packets.push(PcapPacket {
+            time,
+            src: Ipv4Addr::new(ip[12], ip[13], ip[14], ip[15]),
+            dst: Ipv4Addr::new(ip[16], ip[17], ip[18], ip[19]),
+            data: ip[IPV4_UDP_HEADER_LEN..].to_vec(),
+        });
+        b = &b[PCAP_RECORD_HEADER_LEN + len..];
+    }
+    packets
+}
+
+fn remove_dump(path: &Path, max_files: usize) {
+    let _ = std::fs::remove_file(path);
+    for i in 1..max_files {
+        let _ = std::fs::remove_file(rotated_path(path, i));
+    }
+}
+
+#[tokio::test]
+async fn test_dump_rtpdump() -> Result<()> {
+    let time = mock_time();
+    let template = temp_path("{id}");
+    let icpr = DumpBuilder::with_file(template.clone())
+        .with_time_source(Arc::clone(&time) as SharedTimeSource)
+        .build_dump("rtpdump")?;
+
+    session(&icpr, &time).await?;
+    assert_eq!(icpr.dumped_packets(), 8);
+    assert_eq!(icpr.dropped_packets(), 0);
+    assert!(!icpr.is_stopped());
+
+    let path = PathBuf::from(template.replace("{id}", "rtpdump"));
+    let b = std::fs::read(&path).unwrap();
+    remove_dump(&path, 1);
+
+    let (start, packets) = parse_rtpdump(&b);
+    assert_eq!(start, START);
+    assert_eq!(packets.len(), 8);
+
+    for (i, p) in packets[..6].iter().enumerate() {
+        let (sequence_number, payload_len) = if i % 2 == 0 {
+            (i as u16 / 2, 100)
+        } else {
+            (100 + i as u16 / 2, 200)
+        };
+        let expected = rtp_packet(sequence_number, payload_len).marshal()?;
+        assert_eq!(p.data, expected);
+        assert_eq!(p.rtp_len as usize, expected.len());
+        assert_eq!(p.offset, i as u32 * 20);
+    }
+
+    // The RTCP packets have no RTP length
+    let rtcp = rtcp::packet::marshal(&rtcp_packets())?;
+    for (i, p) in packets[6..].iter().enumerate() {
+        assert_eq!(p.data, rtcp);
+        assert_eq!(p.rtp_len, 0);
+        assert_eq!(p.offset, (6 + i as u32) * 20);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dump_pcap() -> Result<()> {
+    let time = mock_time();
+    let path = temp_path("pcap");
+    let icpr = DumpBuilder::with_file(path.clone())
+        .with_format(DumpFormat::Pcap)
+        .with_rtcp(false)
+        .with_time_source(Arc::clone(&time) as SharedTimeSource)
+        .build_dump("")?;
+
+    session(&icpr, &time).await?;
+    assert_eq!(icpr.dumped_packets(), 6);
+
+    let path = PathBuf::from(path);
+    let b = std::fs::read(&path).unwrap();
+    remove_dump(&path, 1);
+
+    let packets = parse_pcap(&b);
+    assert_eq!(packets.len(), 6);
+    for (i, p) in packets.iter().enumerate() {
+        assert_eq!(p.time, START + INTERVAL * i as u32);
+        let (sequence_number, payload_len, src, dst) = if i % 2 == 0 {
+            (i as u16 / 2, 100, LOCAL_IP, REMOTE_IP)
+        } else {
+            (100 + i as u16 / 2, 200, REMOTE_IP, LOCAL_IP)
+        };
+        assert_eq!(p.data, rtp_packet(sequence_number, payload_len).marshal()?);
+        assert_eq!((p.src, p.dst), (src, dst));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dump_rotation_and_cap() -> Result<()> {
+    let time = mock_time();
+    let path = temp_path("rotation");
+    let record_len = (PCAP_RECORD_HEADER_LEN + IPV4_UDP_HEADER_LEN + 12 + 100) as u64;
+    // Two packets per file, and five packets overall
+    let icpr = DumpBuilder::with_file(path.clone())
+        .with_format(DumpFormat::Pcap)
+        .with_directions(false, true)
+        .with_rotation(PCAP_FILE_HEADER_LEN as u64 + 2 * record_len, 2)
+        .with_max_bytes(3 * PCAP_FILE_HEADER_LEN as u64 + 5 * record_len)
+        .with_time_source(Arc::clone(&time) as SharedTimeSource)
+        .build_dump("")?;
+
+    let stream = MockStream::new(
+        &StreamInfo {
+            ssrc: 1,
+            ..Default::default()
+        },
+        Arc::clone(&icpr) as Arc<dyn Interceptor + Send + Sync>,
+    )
+    .await;
+    for i in 0..8u16 {
+        stream.write_rtp(&rtp_packet(i, 100)).await?;
+        time.advance(INTERVAL);
+        // Let the writer keep up, so that the cap is reached rather than the queue full
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(icpr.is_stopped());
+    stream.close().await?;
+
+    let path = PathBuf::from(path);
+    let current = parse_pcap(&std::fs::read(&path).unwrap());
+    let previous = parse_pcap(&std::fs::read(rotated_path(&path, 1)).unwrap());
+    let removed = std::fs::metadata(rotated_path(&path, 2)).is_err();
+    remove_dump(&path, 3);
+    assert!(removed, "only two files should be kept");
+
+    // The first file was removed, the last one holds the packet before the cap
+    let sequence_numbers = |packets: &[PcapPacket]| {
+        packets
+            .iter()
+            .map(|p| u16_be(&p.data[2..]))
+            .collect::<Vec<u16>>()
+    };
+    assert_eq!(sequence_numbers(&previous), vec![2, 3]);
+    assert_eq!(sequence_numbers(&current), vec![4]);
+
+    Ok(())
+}
+
+/// write_rtp writes a packet through a stream of the interceptor and closes it.
+async fn write_rtp(icpr: &Arc<DumpInterceptor>, pkt: &rtp::packet::Packet) -> Result<()> {
+    let stream = MockStream::new(
+        &StreamInfo {
+            ssrc: 1,
+            ..Default::default()
+        },
+        Arc::clone(icpr) as Arc<dyn Interceptor + Send + Sync>,
+    )
+    .await;
+    stream.write_rtp(pkt).await?;
+    stream.close().await
+}
+
+#[tokio::test]
+async fn test_dump_writer() -> Result<()> {
+    let (client, mut server) = tokio::io::duplex(64 * 1024);
+    let builder = DumpBuilder::with_writer(client);
+
+    // The interceptors built share the writer
+    let first = builder.build_dump("first")?;
+    let second = builder.build_dump("second")?;
+    write_rtp(&first, &rtp_packet(1, 10)).await?;
+    write_rtp(&second, &rtp_packet(2, 10)).await?;
+
+    // The writer is shut down once the interceptors are closed and the builder is dropped
+    drop(builder);
+    let mut b = vec![];
+    tokio::io::AsyncReadExt::read_to_end(&mut server, &mut b)
+        .await
+        .unwrap();
+    let (_, packets) = parse_rtpdump(&b);
+    assert_eq!(packets.len(), 2);
+    assert_eq!(packets[0].data, rtp_packet(1, 10).marshal()?);
+    assert_eq!(packets[1].data, rtp_packet(2, 10).marshal()?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dump_shared_file() -> Result<()> {
+    let path = temp_path("shared");
+    let builder = DumpBuilder::with_file(path.clone());
+
+    // Without "{id}" in the path, the interceptors write to the same file
+    let first = builder.build_dump("first")?;
+    let second = builder.build_dump("second")?;
+    write_rtp(&first, &rtp_packet(1, 10)).await?;
+    write_rtp(&second, &rtp_packet(2, 10)).await?;
+    // A closed interceptor doesn't write to it anymore
+    write_rtp(&first, &rtp_packet(3, 10)).await?;
+    assert_eq!(first.dropped_packets(), 1);
+
+    // The packets are written once an interceptor is closed, the others keep writing
+    let path = PathBuf::from(path);
+    let b = std::fs::read(&path).unwrap();
+    remove_dump(&path, 1);
+
+    let (_, packets) = parse_rtpdump(&b);
+    assert_eq!(packets.len(), 2);
+    assert_eq!(packets[0].data, rtp_packet(1, 10).marshal()?);
+    assert_eq!(packets[1].data, rtp_packet(2, 10).marshal()?);
+    assert_eq!(first.dumped_packets(), 1);
+    assert_eq!(second.dumped_packets(), 1);
+
+    Ok(())
+}
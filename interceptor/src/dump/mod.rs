@@ -0,0 +1,496 @@
+#[cfg(test)]
+mod dump_test;
+mod writer;
+
+use crate::time_source::{system_time_source, SharedTimeSource};
+use crate::*;
+
+use bytes::Bytes;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::AsyncWrite;
+use tokio::sync::{mpsc, oneshot};
+use util::sync::{Mutex, TaskGroup};
+use util::Marshal;
+use writer::{DumpSink, DumpWriter, Queued, Record};
+
+/// Default hard cap of the bytes written by a DumpInterceptor, see `DumpBuilder::with_max_bytes`.
+pub const DEFAULT_MAX_BYTES: u64 = 1 << 30;
+
+/// Number of packets queued for the writer before new ones are dropped.
+const QUEUE_SIZE: usize = 1024;
+
+/// How long closing the interceptor waits for the queued packets to be written.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// DumpFormat is the file format written by a DumpInterceptor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// The format of the rtpdump/rtpplay tools (rtptools). It has no notion of direction, all
+    /// packets appear as sent from the same source, so capture one direction per file to tell
+    /// them apart.
+    RtpDump,
+    /// A pcap capture of raw IPv4 packets, each packet wrapped in a fake IPv4 and UDP header.
+    /// Outbound packets are sent from `10.0.0.1:5000` to `10.0.0.2:5000` and inbound ones the
+    /// other way round, so that the usual tools can follow both directions of the session.
+    Pcap,
+}
+
+impl Default for DumpFormat {
+    fn default() -> Self {
+        DumpFormat::RtpDump
+    }
+}
+
+/// DumpDirection is the direction of a dumped packet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DumpDirection {
+    /// Packets read from the remote peer.
+    Inbound,
+    /// Packets written to the remote peer.
+    Outbound,
+}
+
+enum DumpTarget {
+    /// A file path, "{id}" is replaced by the id the interceptor is built with.
+    File(String),
+    /// A writer, or a file path without "{id}", written by all the interceptors built.
+    Shared(Mutex<SharedDump>),
+}
+
+/// SharedDump is a dump written by all the interceptors built, the queue of its writer is
+/// created with the first one.
+struct SharedDump {
+    sink: Option<DumpSink>,
+    queue: Option<(Arc<DumpQueue>, mpsc::Sender<Queued>)>,
+}
+
+impl DumpTarget {
+    fn shared(sink: DumpSink) -> Self {
+        DumpTarget::Shared(Mutex::new(SharedDump {
+            sink: Some(sink),
+            queue: None,
+        }))
+    }
+}
+
+/// DumpBuilder is a InterceptorBuilder for a DumpInterceptor.
+pub struct DumpBuilder {
+    target: DumpTarget,
+    format: DumpFormat,
+    inbound: bool,
+    outbound: bool,
+    rtp: bool,
+    rtcp: bool,
+    rotation: Option<(u64, usize)>,
+    max_bytes: u64,
+    now: Option<SharedTimeSource>,
+}
+
+impl DumpBuilder {
+    fn new(target: DumpTarget) -> Self {
+        DumpBuilder {
+            target,
+            format: DumpFormat::default(),
+            inbound: true,
+            outbound: true,
+            rtp: true,
+            rtcp: true,
+            rotation: None,
+            max_bytes: DEFAULT_MAX_BYTES,
+            now: None,
+        }
+    }
+
+    /// with_file returns a DumpBuilder writing to the file at path, created or truncated when the
+    /// first packet is dumped. "{id}" in the path is replaced by the id the interceptor is built
+    /// with, e.g. the id of the PeerConnection, so that every interceptor gets its own file.
+    /// Without it, the packets of all the interceptors built are written to the same file.
+    pub fn with_file(path: impl Into<String>) -> Self {
+        let path = path.into();
+        if path.contains("{id}") {
+            DumpBuilder::new(DumpTarget::File(path))
+        } else {
+            DumpBuilder::new(DumpTarget::shared(DumpSink::File(PathBuf::from(path))))
+        }
+    }
+
+    /// with_writer returns a DumpBuilder writing to writer. The packets of all the interceptors
+    /// built are written to it, the writer is shut down once they are all closed and the
+    /// builder is dropped.
+    pub fn with_writer(writer: impl AsyncWrite + Send + Unpin + 'static) -> Self {
+        DumpBuilder::new(DumpTarget::shared(DumpSink::Writer(Box::new(writer))))
+    }
+
+    /// with_format sets the format of the dump, rtpdump by default.
+    pub fn with_format(mut self, format: DumpFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// with_directions sets whether inbound and outbound packets are dumped, both by default.
+    pub fn with_directions(mut self, inbound: bool, outbound: bool) -> Self {
+        self.inbound = inbound;
+        self.outbound = outbound;
+        self
+    }
+
+    /// with_rtp sets whether RTP packets are dumped, true by default.
+    pub fn with_rtp(mut self, rtp: bool) -> Self {
+        self.rtp = rtp;
+        self
+    }
+
+    /// with_rtcp sets whether RTCP packets are dumped, true by default.
+    pub fn with_rtcp(mut self, rtcp: bool) -> Self {
+        self.rtcp = rtcp;
+        self
+    }
+
+    /// with_rotation starts a new file once the current one would grow beyond max_file_size
+    /// bytes. The previous files are renamed with the suffixes ".1", ".2"... from the most recent
+    /// one, and only max_files files are kept, including the current one. Rotation only applies
+    /// to dumps written to a file.
+    pub fn with_rotation(mut self, max_file_size: u64, max_files: usize) -> Self {
+        self.rotation = Some((max_file_size, max_files.max(1)));
+        self
+    }
+
+    /// with_max_bytes sets the total number of bytes the interceptor writes, across all its
+    /// files, before it stops dumping. It defaults to `DEFAULT_MAX_BYTES`.
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// with_time_source sets the clock used to timestamp the dumped packets.
+    pub fn with_time_source(mut self, time_source: SharedTimeSource) -> Self {
+        self.now = Some(time_source);
+        self
+    }
+
+    /// build_dump constructs the DumpInterceptor like `build`, keeping its type so that its
+    /// counters can be read.
+    pub fn build_dump(&self, id: &str) -> Result<Arc<DumpInterceptor>> {
+        let (queue, records_tx, shared) = match &self.target {
+            DumpTarget::File(path) => {
+                let sink = DumpSink::File(PathBuf::from(path.replace("{id}", id)));
+                let (queue, records_tx) = self.new_queue(sink);
+                (queue, records_tx, false)
+            }
+            DumpTarget::Shared(dump) => {
+                let mut dump = dump.lock();
+                if let Some(sink) = dump.sink.take() {
+                    dump.queue = Some(self.new_queue(sink));
+                }
+                match &dump.queue {
+                    Some((queue, records_tx)) => (Arc::clone(queue), records_tx.clone(), true),
+                    None => return Err(Error::Other("the dump has no writer".to_owned())),
+                }
+            }
+        };
+
+        Ok(Arc::new(DumpInterceptor {
+            internal: Arc::new(DumpInternal {
+                inbound: self.inbound,
+                outbound: self.outbound,
+                rtp: self.rtp,
+                rtcp: self.rtcp,
+                now: self.now.clone().unwrap_or_else(system_time_source),
+                records_tx: Mutex::new(Some(records_tx)),
+                queue,
+                shared,
+                dumped: AtomicU64::new(0),
+                dropped: AtomicU64::new(0),
+            }),
+        }))
+    }
+
+    fn new_queue(&self, sink: DumpSink) -> (Arc<DumpQueue>, mpsc::Sender<Queued>) {
+        let (records_tx, records_rx) = mpsc::channel(QUEUE_SIZE);
+        let stopped = Arc::new(AtomicBool::new(false));
+        let queue = Arc::new(DumpQueue {
+            writer: Mutex::new(Some(DumpWriter::new(
+                sink,
+                self.format,
+                self.rotation,
+                self.max_bytes,
+                records_rx,
+                Arc::clone(&stopped),
+            ))),
+            stopped,
+            tasks: TaskGroup::new(),
+        });
+        (queue, records_tx)
+    }
+}
+
+impl InterceptorBuilder for DumpBuilder {
+    fn build(&self, id: &str) -> Result<Arc<dyn Interceptor + Send + Sync>> {
+        Ok(self.build_dump(id)?)
+    }
+}
+
+struct DumpInternal {
+    inbound: bool,
+    outbound: bool,
+    rtp: bool,
+    rtcp: bool,
+    now: SharedTimeSource,
+    records_tx: Mutex<Option<mpsc::Sender<Queued>>>,
+    queue: Arc<DumpQueue>,
+    /// Whether other interceptors write to the same dump
+    shared: bool,
+    dumped: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// DumpQueue is the writer of a dump, and its state shared by the interceptors writing to it.
+struct DumpQueue {
+    /// The writer, until it runs on its own task with the first dumped packet
+    writer: Mutex<Option<DumpWriter>>,
+    /// Set by the writer once it can't write anymore
+    stopped: Arc<AtomicBool>,
+    tasks: TaskGroup,
+}
+
+impl DumpInternal {
+    fn dumps(&self, direction: DumpDirection, rtcp: bool) -> bool {
+        let direction = match direction {
+            DumpDirection::Inbound => self.inbound,
+            DumpDirection::Outbound => self.outbound,
+        };
+        let kind = if rtcp { self.rtcp } else { self.rtp };
+        direction && kind && !self.queue.stopped.load(Ordering::SeqCst)
+    }
+
+    /// dump queues a packet for the writer, dropping it if the writer is behind.
+    fn dump(&self, direction: DumpDirection, rtcp: bool, data: Bytes) {
+        if let Some(writer) = self.queue.writer.lock().take() {
+            self.queue.tasks.spawn(writer.run());
+        }
+
+        let record = Record {
+            time: self.now.now(),
+            direction,
+            rtcp,
+            data,
+        };
+        let queued = match &*self.records_tx.lock() {
+            Some(records_tx) => records_tx.try_send(Queued::Record(record)).is_ok(),
+            None => false,
+        };
+        if queued {
+            self.dumped.fetch_add(1, Ordering::SeqCst);
+        } else {
+            self.dropped.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// DumpInterceptor writes the RTP and RTCP packets going through it to a file, or any writer,
+/// in the rtpdump or pcap format for offline analysis. The packets are dumped as they are
+/// exchanged with the peer, unencrypted, whatever their payload. They are written on a
+/// separate task, and dropped rather than slowing down the session if it falls behind.
+pub struct DumpInterceptor {
+    internal: Arc<DumpInternal>,
+}
+
+impl DumpInterceptor {
+    /// builder returns a new DumpBuilder writing to the file at path, see
+    /// `DumpBuilder::with_file`.
+    pub fn builder(path: impl Into<String>) -> DumpBuilder {
+        DumpBuilder::with_file(path)
+    }
+
+    /// dumped_packets returns the number of packets queued to be written.
+    pub fn dumped_packets(&self) -> u64 {
+        self.internal.dumped.load(Ordering::SeqCst)
+    }
+
+    /// dropped_packets returns the number of packets which were not dumped because the writer
+    /// was behind or the interceptor was closed.
+    pub fn dropped_packets(&self) -> u64 {
+        self.internal.dropped.load(Ordering::SeqCst)
+    }
+
+    /// is_stopped returns true once the interceptor stopped dumping, because it reached its
+    /// hard cap or failed to write.
+    pub fn is_stopped(&self) -> bool {
+        self.internal.queue.stopped.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl Interceptor for DumpInterceptor {
+    /// bind_rtcp_reader lets you modify any incoming RTCP packets. It is called once per sender/receiver, however this might
+    /// change in the future. The returned method will be called once per packet batch.
+    async fn bind_rtcp_reader(
+        &self,
+        reader: Arc<dyn RTCPReader + Send + Sync>,
+    ) -> Arc<dyn RTCPReader + Send + Sync> {
+        Arc::new(DumpRtcpReader {
+            internal: Arc::clone(&self.internal),
+            parent_rtcp_reader: reader,
+        })
+    }
+
+    /// bind_rtcp_writer lets you modify any outgoing RTCP packets. It is called once per PeerConnection. The returned method
+    /// will be called once per packet batch.
+    async fn bind_rtcp_writer(
+        &self,
+        writer: Arc<dyn RTCPWriter + Send + Sync>,
+    ) -> Arc<dyn RTCPWriter + Send + Sync> {
+        Arc::new(DumpRtcpWriter {
+            internal: Arc::clone(&self.internal),
+            next_rtcp_writer: writer,
+        })
+    }
+
+    /// bind_local_stream lets you modify any outgoing RTP packets. It is called once for per LocalStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_local_stream(
+        &self,
+        _info: &StreamInfo,
+        writer: Arc<dyn RTPWriter + Send + Sync>,
+    ) -> Arc<dyn RTPWriter + Send + Sync> {
+        Arc::new(DumpRtpWriter {
+            internal: Arc::clone(&self.internal),
+            next_rtp_writer: writer,
+        })
+    }
+
+    /// unbind_local_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_local_stream(&self, _info: &StreamInfo) {}
+
+    /// bind_remote_stream lets you modify any incoming RTP packets. It is called once for per RemoteStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_remote_stream(
+        &self,
+        _info: &StreamInfo,
+        reader: Arc<dyn RTPReader + Send + Sync>,
+    ) -> Arc<dyn RTPReader + Send + Sync> {
+        Arc::new(DumpRtpReader {
+            internal: Arc::clone(&self.internal),
+            parent_rtp_reader: reader,
+        })
+    }
+
+    /// unbind_remote_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_remote_stream(&self, _info: &StreamInfo) {}
+
+    /// close closes the Interceptor, cleaning up any data if necessary.
+    async fn close(&self) -> Result<()> {
+        let records_tx = self.internal.records_tx.lock().take();
+        let queue = &self.internal.queue;
+        if !self.internal.shared {
+            // Closing the queue lets the writer flush the queued packets and finish.
+            drop(records_tx);
+            let cancelled = queue.tasks.close(CLOSE_TIMEOUT).await;
+            if cancelled > 0 {
+                log::warn!("the packet dump wasn't fully written before closing");
+            }
+        } else if let Some(records_tx) = records_tx {
+            // The other interceptors keep writing, only wait for the packets queued so far.
+            let running = queue.writer.lock().is_none();
+            if running {
+                let (flushed_tx, flushed_rx) = oneshot::channel();
+                let flushed = tokio::time::timeout(CLOSE_TIMEOUT, async {
+                    records_tx.send(Queued::Flush(flushed_tx)).await.is_ok()
+                        && flushed_rx.await.is_ok()
+                })
+                .await
+                .unwrap_or(false);
+                if !flushed && !queue.stopped.load(Ordering::SeqCst) {
+                    log::warn!("the packet dump wasn't fully written before closing");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct DumpRtpReader {
+    internal: Arc<DumpInternal>,
+    parent_rtp_reader: Arc<dyn RTPReader + Send + Sync>,
+}
+
+#[async_trait]
+impl RTPReader for DumpRtpReader {
+    /// read a rtp packet
+    async fn read(&self, buf: &mut [u8], attributes: &Attributes) -> Result<(usize, Attributes)> {
+        let (n, attr) = self.parent_rtp_reader.read(buf, attributes).await?;
+        if self.internal.dumps(DumpDirection::Inbound, false) {
+            self.internal.dump(
+                DumpDirection::Inbound,
+                false,
+                Bytes::copy_from_slice(&buf[..n]),
+            );
+        }
+        Ok((n, attr))
+    }
+}
+
+struct DumpRtpWriter {
+    internal: Arc<DumpInternal>,
+    next_rtp_writer: Arc<dyn RTPWriter + Send + Sync>,
+}
+
+#[async_trait]
+impl RTPWriter for DumpRtpWriter {
+    /// write a rtp packet
+    async fn write(&self, pkt: &rtp::packet::Packet, attributes: &Attributes) -> Result<usize> {
+        if self.internal.dumps(DumpDirection::Outbound, false) {
+            match pkt.marshal() {
+                Ok(data) => self.internal.dump(DumpDirection::Outbound, false, data),
+                Err(err) => log::debug!("not dumping rtp packet: {}", err),
+            }
+        }
+        self.next_rtp_writer.write(pkt, attributes).await
+    }
+}
+
+struct DumpRtcpReader {
+    internal: Arc<DumpInternal>,
+    parent_rtcp_reader: Arc<dyn RTCPReader + Send + Sync>,
+}
+
+#[async_trait]
+impl RTCPReader for DumpRtcpReader {
+    /// read a batch of rtcp packets
+    async fn read(&self, buf: &mut [u8], attributes: &Attributes) -> Result<(usize, Attributes)> {
+        let (n, attr) = self.parent_rtcp_reader.read(buf, attributes).await?;
+        if self.internal.dumps(DumpDirection::Inbound, true) {
+            self.internal.dump(
+                DumpDirection::Inbound,
+                true,
+                Bytes::copy_from_slice(&buf[..n]),
+            );
+        }
+        Ok((n, attr))
+    }
+}
+
+struct DumpRtcpWriter {
+    internal: Arc<DumpInternal>,
+    next_rtcp_writer: Arc<dyn RTCPWriter + Send + Sync>,
+}
+
+#[async_trait]
+impl RTCPWriter for DumpRtcpWriter {
+    /// write a batch of rtcp packets
+    async fn write(
+        &self,
+        pkts: &[Box<dyn rtcp::packet::Packet + Send + Sync>],
+        attributes: &Attributes,
+    ) -> Result<usize> {
+        if self.internal.dumps(DumpDirection::Outbound, true) {
+            match rtcp::packet::marshal(pkts) {
+                Ok(data) => self.internal.dump(DumpDirection::Outbound, true, data),
+                Err(err) => log::debug!("not dumping rtcp packets: {}", err),
+            }
+        }
+        self.next_rtcp_writer.write(pkts, attributes).await
+    }
+}
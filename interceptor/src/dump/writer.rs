@@ -0,0 +1,356 @@
+use super::{DumpDirection, DumpFormat};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use std::io;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+use tokio::sync::{mpsc, oneshot};
+
+/// Address of the local end of the session in pcap dumps.
+pub(crate) const LOCAL_IP: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+/// Address of the remote end of the session in pcap dumps, and the source of rtpdump dumps.
+pub(crate) const REMOTE_IP: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 2);
+/// UDP port of both ends of the session.
+pub(crate) const PORT: u16 = 5000;
+
+pub(crate) const RTPDUMP_FILE_HEADER_LEN: usize = 16;
+pub(crate) const RTPDUMP_PACKET_HEADER_LEN: usize = 8;
+pub(crate) const PCAP_FILE_HEADER_LEN: usize = 24;
+pub(crate) const PCAP_RECORD_HEADER_LEN: usize = 16;
+pub(crate) const IPV4_UDP_HEADER_LEN: usize = 28;
+
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+const PCAP_SNAPLEN: u32 = 65535;
+/// LINKTYPE_RAW, packets start with their IP header.
+const PCAP_LINKTYPE_RAW: u32 = 101;
+
+/// Largest packet which fits in the length fields of both formats.
+const MAX_PACKET_LEN: usize = u16::MAX as usize - IPV4_UDP_HEADER_LEN;
+
+/// Record is a packet queued to be dumped.
+pub(crate) struct Record {
+    pub(crate) time: SystemTime,
+    pub(crate) direction: DumpDirection,
+    pub(crate) rtcp: bool,
+    pub(crate) data: Bytes,
+}
+
+/// Queued is what the interceptors queue for the writer.
+pub(crate) enum Queued {
+    Record(Record),
+    /// Flush asks the writer to flush what it wrote so far, it's answered once done.
+    Flush(oneshot::Sender<()>),
+}
+
+pub(crate) enum DumpSink {
+    File(PathBuf),
+    Writer(Box<dyn AsyncWrite + Send + Unpin>),
+}
+
+/// DumpWriter writes the queued records, until the queue is closed or it can't write anymore.
+pub(crate) struct DumpWriter {
+    format: DumpFormat,
+    path: Option<PathBuf>,
+    writer: Option<Box<dyn AsyncWrite + Send + Unpin>>,
+    rotation: Option<(u64, usize)>,
+    max_bytes: u64,
+    records_rx: mpsc::Receiver<Queued>,
+    stopped: Arc<AtomicBool>,
+
+    out: Option<BufWriter<Box<dyn AsyncWrite + Send + Unpin>>>,
+    /// Time of the first packet of the current file
+    start: SystemTime,
+    file_bytes: u64,
+    file_packets: u64,
+    total_bytes: u64,
+    ip_id: u16,
+}
+
+impl DumpWriter {
+    pub(crate) fn new(
+        sink: DumpSink,
+        format: DumpFormat,
+        rotation: Option<(u64, usize)>,
+        max_bytes: u64,
+        records_rx: mpsc::Receiver<Queued>,
+        stopped: Arc<AtomicBool>,
+    ) -> Self {
+        let (path, writer) = match sink {
+            DumpSink::File(path) => (Some(path), None),
+            DumpSink::Writer(writer) => (None, Some(writer)),
+        };
+        DumpWriter {
+            format,
+            path,
+            writer,
+            rotation,
+            max_bytes,
+            records_rx,
+            stopped,
+            out: None,
+            start: UNIX_EPOCH,
+            file_bytes: 0,
+            file_packets: 0,
+            total_bytes: 0,
+            ip_id: 0,
+        }
+    }
+
+    pub(crate) async fn run(mut self) {
+        while let Some(queued) = self.records_rx.recv().await {
+            let record = match queued {
+                Queued::Record(record) => record,
+                Queued::Flush(flushed) => {
+                    if let Some(out) = &mut self.out {
+                        if let Err(err) = out.flush().await {
+                            log::warn!("failed to flush the packet dump: {}", err);
+                        }
+                    }
+                    let _ = flushed.send(());
+                    continue;
+                }
+            };
+            match self.write(&record).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    log::warn!(
+                        "stopped dumping packets after {} bytes, the dump is full",
+                        self.total_bytes
+                    );
+                    self.stopped.store(true, Ordering::SeqCst);
+                    break;
+                }
+                Err(err) => {
+                    log::warn!("stopped dumping packets: {}", err);
+                    self.stopped.store(true, Ordering::SeqCst);
+                    break;
+                }
+            }
+        }
+
+        if let Some(mut out) = self.out.take() {
+            if let Err(err) = out.shutdown().await {
+                log::warn!("failed to flush the packet dump: {}", err);
+            }
+        }
+    }
+
+    /// write writes a record, opening or rotating the file first if needed. It returns false once
+    /// the dump reached its hard cap.
+    async fn write(&mut self, record: &Record) -> io::Result<bool> {
+        if record.data.len() > MAX_PACKET_LEN {
+            log::debug!("not dumping packet of {} bytes", record.data.len());
+            return Ok(true);
+        }
+        let packet_len = self.format.record_len(record.data.len()) as u64;
+
+        let rotate = match (self.rotation, &self.path) {
+            (Some((max_file_size, _)), Some(_)) => {
+                self.file_packets > 0 && self.file_bytes + packet_len > max_file_size
+            }
+            _ => false,
+        };
+        let header_len = if self.out.is_none() || rotate {
+            self.format.file_header_len() as u64
+        } else {
+            0
+        };
+        if self.total_bytes + header_len + packet_len > self.max_bytes {
+            return Ok(false);
+        }
+
+        if rotate {
+            self.rotate().await?;
+        }
+        if self.out.is_none() {
+            self.open(record.time).await?;
+        }
+
+        let packet = self.encode(record);
+        if let Some(out) = &mut self.out {
+            out.write_all(&packet).await?;
+        }
+        self.file_bytes += packet.len() as u64;
+        self.file_packets += 1;
+        self.total_bytes += packet.len() as u64;
+
+        Ok(true)
+    }
+
+    /// open opens the file, or takes the writer, and writes the file header.
+    async fn open(&mut self, start: SystemTime) -> io::Result<()> {
+        let out: Box<dyn AsyncWrite + Send + Unpin> = if let Some(path) = &self.path {
+            Box::new(tokio::fs::File::create(path).await?)
+        } else if let Some(writer) = self.writer.take() {
+            writer
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "the writer of the dump is closed",
+            ));
+        };
+        let mut out = BufWriter::new(out);
+
+        let header = self.format.file_header(start);
+        out.write_all(&header).await?;
+        self.out = Some(out);
+        self.start = start;
+        self.file_bytes = header.len() as u64;
+        self.file_packets = 0;
+        self.total_bytes += header.len() as u64;
+
+        Ok(())
+    }
+
+    /// rotate closes the current file and renames the previous ones, removing the oldest one.
+    async fn rotate(&mut self) -> io::Result<()> {
+        if let Some(mut out) = self.out.take() {
+            out.shutdown().await?;
+        }
+        let (path, max_files) = match (&self.path, self.rotation) {
+            (Some(path), Some((_, max_files))) => (path, max_files),
+            _ => return Ok(()),
+        };
+        if max_files <= 1 {
+            // The file is truncated when opened again
+            return Ok(());
+        }
+
+        ignore_not_found(tokio::fs::remove_file(rotated_path(path, max_files - 1)).await)?;
+        for i in (1..max_files - 1).rev() {
+            ignore_not_found(
+                tokio::fs::rename(rotated_path(path, i), rotated_path(path, i + 1)).await,
+            )?;
+        }
+        tokio::fs::rename(path, rotated_path(path, 1)).await
+    }
+
+    fn encode(&mut self, record: &Record) -> Bytes {
+        let data = &record.data;
+        let mut b = BytesMut::with_capacity(self.format.record_len(data.len()));
+        match self.format {
+            DumpFormat::RtpDump => {
+                let offset = record
+                    .time
+                    .duration_since(self.start)
+                    .unwrap_or_default()
+                    .as_millis();
+                b.put_u16((RTPDUMP_PACKET_HEADER_LEN + data.len()) as u16);
+                b.put_u16(if record.rtcp { 0 } else { data.len() as u16 });
+                b.put_u32(offset as u32);
+            }
+            DumpFormat::Pcap => {
+                let ts = since_epoch(record.time);
+                let len = (IPV4_UDP_HEADER_LEN + data.len()) as u32;
+                b.put_u32_le(ts.as_secs() as u32);
+                b.put_u32_le(ts.subsec_micros());
+                b.put_u32_le(len);
+                b.put_u32_le(len);
+
+                let (src, dst) = match record.direction {
+                    DumpDirection::Outbound => (LOCAL_IP, REMOTE_IP),
+                    DumpDirection::Inbound => (REMOTE_IP, LOCAL_IP),
+                };
+                b.put_slice(&ipv4_header(src, dst, self.ip_id, len as u16));
+                self.ip_id = self.ip_id.wrapping_add(1);
+
+                // The UDP checksum is optional over IPv4
+                b.put_u16(PORT);
+                b.put_u16(PORT);
+                b.put_u16((8 + data.len()) as u16);
+                b.put_u16(0);
+            }
+        }
+        b.put_slice(data);
+        b.freeze()
+    }
+}
+
+impl DumpFormat {
+    fn file_header_len(&self) -> usize {
+        match self {
+            DumpFormat::RtpDump => rtpdump_first_line().len() + RTPDUMP_FILE_HEADER_LEN,
+            DumpFormat::Pcap => PCAP_FILE_HEADER_LEN,
+        }
+    }
+
+    fn file_header(&self, start: SystemTime) -> Bytes {
+        let mut b = BytesMut::with_capacity(self.file_header_len());
+        match self {
+            DumpFormat::RtpDump => {
+                let start = since_epoch(start);
+                b.put_slice(rtpdump_first_line().as_bytes());
+                b.put_u32(start.as_secs() as u32);
+                b.put_u32(start.subsec_micros());
+                b.put_u32(u32::from(REMOTE_IP));
+                b.put_u16(PORT);
+                b.put_u16(0);
+            }
+            DumpFormat::Pcap => {
+                b.put_u32_le(PCAP_MAGIC);
+                b.put_u16_le(2);
+                b.put_u16_le(4);
+                b.put_i32_le(0);
+                b.put_u32_le(0);
+                b.put_u32_le(PCAP_SNAPLEN);
+                b.put_u32_le(PCAP_LINKTYPE_RAW);
+            }
+        }
+        b.freeze()
+    }
+
+    fn record_len(&self, data_len: usize) -> usize {
+        match self {
+            DumpFormat::RtpDump => RTPDUMP_PACKET_HEADER_LEN + data_len,
+            DumpFormat::Pcap => PCAP_RECORD_HEADER_LEN + IPV4_UDP_HEADER_LEN + data_len,
+        }
+    }
+}
+
+fn rtpdump_first_line() -> String {
+    format!("#!rtpplay1.0 {}/{}\n", REMOTE_IP, PORT)
+}
+
+fn since_epoch(time: SystemTime) -> Duration {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+fn ipv4_header(src: Ipv4Addr, dst: Ipv4Addr, id: u16, total_len: u16) -> [u8; 20] {
+    let mut h = [0u8; 20];
+    h[0] = 0x45;
+    h[2..4].copy_from_slice(&total_len.to_be_bytes());
+    h[4..6].copy_from_slice(&id.to_be_bytes());
+    // Don't fragment
+    h[6] = 0x40;
+    h[8] = 64;
+    h[9] = 17;
+    h[12..16].copy_from_slice(&src.octets());
+    h[16..20].copy_from_slice(&dst.octets());
+
+    let mut sum = h
+        .chunks(2)
+        .map(|w| u32::from(u16::from_be_bytes([w[0], w[1]])))
+        .sum::<u32>();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    h[10..12].copy_from_slice(&(!(sum as u16)).to_be_bytes());
+    h
+}
+
+pub(crate) fn rotated_path(path: &Path, i: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{}", i));
+    PathBuf::from(rotated)
+}
+
+fn ignore_not_found(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
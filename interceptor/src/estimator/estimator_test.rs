@@ -0,0 +1,155 @@
+use super::*;
+
+const PACKET_SIZE: usize = 1250;
+
+/// feed records packets of PACKET_SIZE bytes sent at `bitrate` during `duration` from
+/// `start`, each one arriving `delay(i)` after it was sent or lost if that is None, and updates
+/// the estimator every 50ms. It returns the last estimate.
+fn feed(
+    estimator: &mut DelayLossEstimator,
+    start: Duration,
+    duration: Duration,
+    bitrate: u64,
+    with_send_time: bool,
+    mut delay: impl FnMut(u32) -> Option<Duration>,
+) -> u64 {
+    let interval = Duration::from_secs_f64(PACKET_SIZE as f64 * 8.0 / bitrate as f64);
+    let update_interval = Duration::from_millis(50);
+    let mut next_update = start + update_interval;
+    let mut lost_before = 0;
+    let mut estimate = 0;
+
+    let mut i = 0;
+    loop {
+        let send_time = start + interval * i;
+        if send_time >= start + duration {
+            break;
+        }
+        let delay = delay(i);
+        let arrival_time = send_time + delay.unwrap_or_default();
+        while next_update <= arrival_time {
+            estimate = estimator.update(next_update);
+            next_update += update_interval;
+        }
+
+        if delay.is_some() {
+            estimator.on_packet(&PacketResult {
+                arrival_time,
+                send_time: with_send_time.then(|| send_time),
+                size: PACKET_SIZE,
+                lost_before,
+            });
+            lost_before = 0;
+        } else {
+            lost_before += 1;
+        }
+        i += 1;
+    }
+
+    estimate
+}
+
+#[test]
+fn test_delay_loss_estimator_follows_uncongested_bitrate() {
+    let mut estimator = DelayLossEstimator::default();
+
+    let estimate = feed(
+        &mut estimator,
+        Duration::ZERO,
+        Duration::from_secs(5),
+        1_000_000,
+        true,
+        |_| Some(Duration::from_millis(20)),
+    );
+
+    // Between the incoming bitrate and 1.5 times that
+    assert!(
+        (1_000_000..=1_500_000).contains(&estimate),
+        "estimate {}",
+        estimate
+    );
+    assert_eq!(Duration::ZERO, estimator.queuing_delay());
+}
+
+#[test]
+fn test_delay_loss_estimator_decreases_on_growing_delay() {
+    let mut estimator = DelayLossEstimator::default();
+
+    feed(
+        &mut estimator,
+        Duration::ZERO,
+        Duration::from_secs(5),
+        1_000_000,
+        true,
+        |_| Some(Duration::from_millis(20)),
+    );
+
+    // Queuing delay grows by 1ms per packet
+    let estimate = feed(
+        &mut estimator,
+        Duration::from_secs(5),
+        Duration::from_millis(500),
+        1_000_000,
+        true,
+        |i| Some(Duration::from_millis(20 + i as u64)),
+    );
+
+    assert!(estimator.queuing_delay() > OVERUSE_QUEUING_DELAY);
+    assert!(estimate <= 850_000, "estimate {}", estimate);
+}
+
+#[test]
+fn test_delay_loss_estimator_decreases_on_loss() {
+    let mut estimator = DelayLossEstimator::default();
+
+    feed(
+        &mut estimator,
+        Duration::ZERO,
+        Duration::from_secs(5),
+        1_000_000,
+        false,
+        |_| Some(Duration::from_millis(20)),
+    );
+
+    // Every third packet is lost, without send times
+    let estimate = feed(
+        &mut estimator,
+        Duration::from_secs(5),
+        Duration::from_secs(2),
+        1_000_000,
+        false,
+        |i| (i % 3 != 0).then(|| Duration::from_millis(20)),
+    );
+
+    // The incoming bitrate is 2/3 of the sent one, lowered by half the loss rate
+    assert!(
+        (500_000..=600_000).contains(&estimate),
+        "estimate {}",
+        estimate
+    );
+}
+
+#[test]
+fn test_delay_loss_estimator_bounds() {
+    let mut estimator = DelayLossEstimator::new(300_000, 100_000, 400_000);
+
+    let estimate = feed(
+        &mut estimator,
+        Duration::ZERO,
+        Duration::from_secs(2),
+        1_000_000,
+        true,
+        |_| Some(Duration::from_millis(20)),
+    );
+    assert_eq!(400_000, estimate);
+
+    let estimate = feed(
+        &mut estimator,
+        Duration::from_secs(2),
+        Duration::from_secs(3),
+        50_000,
+        true,
+        |_| Some(Duration::from_millis(20)),
+    );
+    assert_eq!(100_000, estimate);
+}
@@ -0,0 +1,202 @@
+#[cfg(test)]
+mod estimator_test;
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// PacketResult describes a packet received by the remote or local end, as fed to an
+/// [`Estimator`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct PacketResult {
+    /// When the packet arrived, relative to an arbitrary but fixed origin.
+    pub arrival_time: Duration,
+    /// When the packet was sent according to the clock of the sender, e.g. from the
+    /// abs-send-time header extension. Only differences between send times are meaningful.
+    pub send_time: Option<Duration>,
+    /// Size of the packet in bytes.
+    pub size: usize,
+    /// Number of packets of the same stream found missing right before this one.
+    pub lost_before: u64,
+}
+
+/// Estimator estimates the bandwidth available on the path packets take from their sender to
+/// their receiver. Interceptors generating bandwidth feedback, e.g. REMB, own an Estimator and can
+/// be configured with any implementation.
+pub trait Estimator {
+    /// on_packet records a received packet.
+    fn on_packet(&mut self, packet: &PacketResult);
+
+    /// update evaluates the packets recorded since the last update and returns the current
+    /// estimate in bits per second. `now` is on the same clock as the arrival times.
+    fn update(&mut self, now: Duration) -> u64;
+}
+
+/// Incoming bitrate is measured over this window
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+/// The base one way delay is the minimum seen in this window, so that clock drift
+/// between sender and receiver isn't mistaken for queuing
+const BASE_DELAY_WINDOW: Duration = Duration::from_secs(10);
+/// Queuing delay above which the path is considered overused
+const OVERUSE_QUEUING_DELAY: Duration = Duration::from_millis(20);
+/// On overuse the estimate drops to this share of the incoming bitrate
+const BETA: f64 = 0.85;
+/// Multiplicative increase per second while the path isn't congested
+const INCREASE_PER_SECOND: f64 = 1.08;
+/// The estimate never exceeds the incoming bitrate by more than this factor
+const MAX_INCOMING_RATIO: f64 = 1.5;
+/// Loss rates below this let the estimate grow
+const LOW_LOSS: f64 = 0.02;
+/// Loss rates above this decrease the estimate
+const HIGH_LOSS: f64 = 0.1;
+
+/// DelayLossEstimator is a simple AIMD estimator driven by queuing delay, loss and the incoming
+/// bitrate.
+///
+/// The queuing delay is the one way delay of a packet above the smallest one seen recently. It
+/// is only known for packets carrying a send time, without them the estimate follows the incoming
+/// bitrate and the loss rate only. When the queuing delay exceeds 20ms the estimate drops to 85%
+/// of the incoming bitrate, more than 10% loss lowers it in proportion to the loss. Otherwise it
+/// grows by 8% per second, staying between the incoming bitrate and 1.5 times that.
+pub struct DelayLossEstimator {
+    min_bitrate: u64,
+    max_bitrate: u64,
+    estimate: f64,
+
+    /// (arrival time, size) of the packets received within the rate window
+    received: VecDeque<(Duration, usize)>,
+    received_bytes: usize,
+    first_arrival: Option<Duration>,
+
+    /// (arrival time, one way delay in microseconds) candidates for the base delay, increasing
+    /// in both fields
+    base_delays: VecDeque<(Duration, i64)>,
+    queuing_delay: Duration,
+
+    received_since_update: u64,
+    lost_since_update: u64,
+    last_update: Option<Duration>,
+}
+
+impl DelayLossEstimator {
+    /// new creates a DelayLossEstimator starting at `initial_bitrate` and bounded by
+    /// `min_bitrate` and `max_bitrate`, all in bits per second.
+    pub fn new(initial_bitrate: u64, min_bitrate: u64, max_bitrate: u64) -> Self {
+        DelayLossEstimator {
+            min_bitrate,
+            max_bitrate,
+            estimate: initial_bitrate.clamp(min_bitrate, max_bitrate) as f64,
+            received: VecDeque::new(),
+            received_bytes: 0,
+            first_arrival: None,
+            base_delays: VecDeque::new(),
+            queuing_delay: Duration::ZERO,
+            received_since_update: 0,
+            lost_since_update: 0,
+            last_update: None,
+        }
+    }
+
+    /// queuing_delay returns the queuing delay of the latest packet with a send time.
+    pub fn queuing_delay(&self) -> Duration {
+        self.queuing_delay
+    }
+
+    fn incoming_bitrate(&mut self, now: Duration) -> f64 {
+        while let Some((arrival, size)) = self.received.front() {
+            if now.saturating_sub(*arrival) < RATE_WINDOW {
+                break;
+            }
+            self.received_bytes -= size;
+            self.received.pop_front();
+        }
+
+        let elapsed = match self.first_arrival {
+            Some(first_arrival) => now.saturating_sub(first_arrival).min(RATE_WINDOW),
+            None => return 0.0,
+        };
+        if elapsed.is_zero() {
+            return 0.0;
+        }
+
+        self.received_bytes as f64 * 8.0 / elapsed.as_secs_f64()
+    }
+
+    fn update_queuing_delay(&mut self, arrival_time: Duration, send_time: Duration) {
+        let delay = arrival_time.as_micros() as i64 - send_time.as_micros() as i64;
+
+        while let Some((_, base_delay)) = self.base_delays.back() {
+            if *base_delay < delay {
+                break;
+            }
+            self.base_delays.pop_back();
+        }
+        self.base_delays.push_back((arrival_time, delay));
+        while let Some((arrival, _)) = self.base_delays.front() {
+            if arrival_time.saturating_sub(*arrival) <= BASE_DELAY_WINDOW {
+                break;
+            }
+            self.base_delays.pop_front();
+        }
+
+        let base_delay = self.base_delays.front().map(|(_, d)| *d).unwrap_or(delay);
+        self.queuing_delay = Duration::from_micros((delay - base_delay).max(0) as u64);
+    }
+}
+
+impl Default for DelayLossEstimator {
+    /// default starts at 300kbps and is bounded by 30kbps and 100Mbps.
+    fn default() -> Self {
+        DelayLossEstimator::new(300_000, 30_000, 100_000_000)
+    }
+}
+
+impl Estimator for DelayLossEstimator {
+    fn on_packet(&mut self, packet: &PacketResult) {
+        if self.first_arrival.is_none() {
+            self.first_arrival = Some(packet.arrival_time);
+        }
+        self.received.push_back((packet.arrival_time, packet.size));
+        self.received_bytes += packet.size;
+
+        self.received_since_update += 1;
+        self.lost_since_update += packet.lost_before;
+
+        if let Some(send_time) = packet.send_time {
+            self.update_queuing_delay(packet.arrival_time, send_time);
+        }
+    }
+
+    fn update(&mut self, now: Duration) -> u64 {
+        let elapsed = self
+            .last_update
+            .map(|last_update| now.saturating_sub(last_update))
+            .unwrap_or_default();
+        self.last_update = Some(now);
+
+        let incoming = self.incoming_bitrate(now);
+        let expected = self.received_since_update + self.lost_since_update;
+        let loss = if expected > 0 {
+            self.lost_since_update as f64 / expected as f64
+        } else {
+            0.0
+        };
+        self.received_since_update = 0;
+        self.lost_since_update = 0;
+
+        if incoming > 0.0 {
+            if self.queuing_delay > OVERUSE_QUEUING_DELAY {
+                self.estimate = self.estimate.min(BETA * incoming);
+            } else if loss > HIGH_LOSS {
+                self.estimate = self.estimate.min(incoming * (1.0 - 0.5 * loss));
+            } else if loss < LOW_LOSS {
+                let increased = self.estimate * INCREASE_PER_SECOND.powf(elapsed.as_secs_f64());
+                self.estimate = increased.max(incoming).min(MAX_INCOMING_RATIO * incoming);
+            }
+        }
+
+        self.estimate = self
+            .estimate
+            .clamp(self.min_bitrate as f64, self.max_bitrate as f64);
+        self.estimate as u64
+    }
+}
@@ -7,22 +7,35 @@ use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
 use stream_info::StreamInfo;
 
 pub mod chain;
+pub mod dependency_descriptor;
+pub mod dump;
 mod error;
+pub mod estimator;
 pub mod mock;
 pub mod nack;
 pub mod noop;
 pub mod registry;
+pub mod remb;
 pub mod report;
+pub mod rtcp_scheduler;
 pub mod stats;
 pub mod stream_info;
 pub mod stream_reader;
+pub mod time_source;
 pub mod twcc;
+pub mod video_orientation;
 
 pub use error::Error;
+use rtcp_scheduler::RtcpSchedulerConfig;
+pub use time_source::{SharedTimeSource, SystemTimeSource, TimeSource};
+
+/// Interceptors wait this long for their tasks to finish when closed, before cancelling them
+pub(crate) const TASKS_CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
 
 /// Attributes are a generic key/value store used by interceptors
 pub type Attributes = HashMap<usize, usize>;
@@ -30,6 +43,11 @@ pub type Attributes = HashMap<usize, usize>;
 /// InterceptorBuilder provides an interface for constructing interceptors
 pub trait InterceptorBuilder {
     fn build(&self, id: &str) -> Result<Arc<dyn Interceptor + Send + Sync>>;
+
+    /// set_default_rtcp_scheduler_config sets the RTCP timing of the session for the interceptors
+    /// built from now on, unless the builder was given its own config. Builders of interceptors
+    /// that don't schedule RTCP ignore it.
+    fn set_default_rtcp_scheduler_config(&mut self, _config: RtcpSchedulerConfig) {}
 }
 
 /// Interceptor can be used to add functionality to you PeerConnections by modifying any incoming/outgoing rtp/rtcp
@@ -163,10 +163,20 @@ impl MockStream {
 
     /// write_rtp writes an rtp packet to the stream, using the interceptor
     pub async fn write_rtp(&self, pkt: &rtp::packet::Packet) -> Result<usize> {
-        let a = Attributes::new();
+        self.write_rtp_with_attributes(pkt, &Attributes::new())
+            .await
+    }
+
+    /// write_rtp_with_attributes writes an rtp packet with the given attributes to the stream,
+    /// using the interceptor
+    pub async fn write_rtp_with_attributes(
+        &self,
+        pkt: &rtp::packet::Packet,
+        a: &Attributes,
+    ) -> Result<usize> {
         let rtp_writer = self.rtp_writer.lock().await;
         if let Some(writer) = &*rtp_writer {
-            writer.write(pkt, &a).await
+            writer.write(pkt, a).await
         } else {
             Err(Error::Other("invalid rtp_writer".to_owned()))
         }
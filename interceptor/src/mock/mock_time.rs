@@ -1,22 +1,35 @@
 use std::time::{Duration, SystemTime};
+use tokio::time::Instant;
 
 use util::sync::Mutex;
 
+use crate::time_source::TimeSource;
+
 /// MockTime is a helper to replace SystemTime::now() for testing purposes.
+///
+/// Wrap it in an `Arc` and hand it to the interceptor builders (e.g.
+/// `ReportBuilder::with_time_source`) to control the clock they observe.
+///
+/// Its monotonic clock starts at the instant the MockTime was created and only moves with
+/// `advance`, `set_now` steps the wall clock alone like setting the system clock would.
 pub struct MockTime {
     cur_now: Mutex<SystemTime>,
+    start: Instant,
+    elapsed: Mutex<Duration>,
 }
 
 impl Default for MockTime {
     fn default() -> Self {
         MockTime {
             cur_now: Mutex::new(SystemTime::UNIX_EPOCH),
+            start: Instant::now(),
+            elapsed: Mutex::new(Duration::ZERO),
         }
     }
 }
 
 impl MockTime {
-    /// set_now sets the current time.
+    /// set_now sets the current wall clock time, leaving the monotonic clock where it is.
     pub fn set_now(&self, now: SystemTime) {
         let mut cur_now = self.cur_now.lock();
         *cur_now = now;
@@ -28,9 +41,63 @@ impl MockTime {
         *cur_now
     }
 
+    /// instant returns the current time of the monotonic clock.
+    pub fn instant(&self) -> Instant {
+        self.start + *self.elapsed.lock()
+    }
+
     /// advance advances duration d
-    pub fn advance(&mut self, d: Duration) {
+    pub fn advance(&self, d: Duration) {
         let mut cur_now = self.cur_now.lock();
         *cur_now = cur_now.checked_add(d).unwrap_or(*cur_now);
+        *self.elapsed.lock() += d;
+    }
+}
+
+impl TimeSource for MockTime {
+    fn now(&self) -> SystemTime {
+        MockTime::now(self)
+    }
+
+    fn instant(&self) -> Instant {
+        MockTime::instant(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_mock_time_as_time_source() {
+        let mt = Arc::new(MockTime::default());
+        let ts: Arc<dyn TimeSource + Send + Sync> = mt.clone();
+
+        assert_eq!(ts.now(), SystemTime::UNIX_EPOCH);
+
+        mt.advance(Duration::from_secs(5));
+        assert_eq!(ts.now(), SystemTime::UNIX_EPOCH + Duration::from_secs(5));
+
+        let t = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        mt.set_now(t);
+        assert_eq!(ts.now(), t);
+    }
+
+    #[test]
+    fn test_mock_time_instant_is_monotonic() {
+        let mt = Arc::new(MockTime::default());
+        let ts: Arc<dyn TimeSource + Send + Sync> = mt.clone();
+        let start = ts.instant();
+
+        mt.advance(Duration::from_secs(5));
+        assert_eq!(ts.instant() - start, Duration::from_secs(5));
+
+        // stepping the wall clock leaves the monotonic clock alone
+        mt.set_now(SystemTime::UNIX_EPOCH);
+        assert_eq!(ts.now(), SystemTime::UNIX_EPOCH);
+        assert_eq!(ts.instant() - start, Duration::from_secs(5));
+        mt.set_now(SystemTime::UNIX_EPOCH + Duration::from_secs(3_600));
+        assert_eq!(ts.instant() - start, Duration::from_secs(5));
     }
 }
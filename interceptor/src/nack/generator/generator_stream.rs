@@ -1,125 +1,66 @@
 use super::*;
 
-use crate::nack::UINT16SIZE_HALF;
-
+use rtp::seqnum::{ExtendedSeqnum, SeqnumWindow};
 use util::sync::Mutex;
 use util::Unmarshal;
 
 struct GeneratorStreamInternal {
-    packets: Vec<u64>,
-    size: u16,
-    end: u16,
-    started: bool,
-    last_consecutive: u16,
+    seqnum: ExtendedSeqnum,
+    received: SeqnumWindow,
+    last_consecutive: u64,
 }
 
 impl GeneratorStreamInternal {
     fn new(log2_size_minus_6: u8) -> Self {
         GeneratorStreamInternal {
-            packets: vec![0u64; 1 << log2_size_minus_6],
-            size: 1 << (log2_size_minus_6 + 6),
-            end: 0,
-            started: false,
+            seqnum: ExtendedSeqnum::new(),
+            received: SeqnumWindow::new(1 << (log2_size_minus_6 + 6)),
             last_consecutive: 0,
         }
     }
 
     fn add(&mut self, seq: u16) {
-        if !self.started {
-            self.set_received(seq);
-            self.end = seq;
-            self.started = true;
+        let started = self.seqnum.highest().is_some();
+        let seq = match self.seqnum.update(seq) {
+            Some(seq) => seq,
+            None => return,
+        };
+        if !started {
             self.last_consecutive = seq;
-            return;
         }
 
-        let last_consecutive_plus1 = self.last_consecutive.wrapping_add(1);
-        let diff = seq.wrapping_sub(self.end);
-        if diff == 0 {
-            return;
-        } else if diff < UINT16SIZE_HALF {
-            // this means a positive diff, in other words seq > end (with counting for rollovers)
-            let mut i = self.end.wrapping_add(1);
-            while i != seq {
-                // clear packets between end and seq (these may contain packets from a "size" ago)
-                self.del_received(i);
-                i = i.wrapping_add(1);
-            }
-            self.end = seq;
-
-            let seq_sub_last_consecutive = seq.wrapping_sub(self.last_consecutive);
-            if last_consecutive_plus1 == seq {
-                self.last_consecutive = seq;
-            } else if seq_sub_last_consecutive > self.size {
-                let diff = seq.wrapping_sub(self.size);
-                self.last_consecutive = diff;
-                self.fix_last_consecutive(); // there might be valid packets at the beginning of the buffer now
-            }
-        } else if last_consecutive_plus1 == seq {
-            // negative diff, seq < end (with counting for rollovers)
-            self.last_consecutive = seq;
-            self.fix_last_consecutive(); // there might be other valid packets after seq
+        self.received.insert(seq);
+        if self.last_consecutive + self.received.size() < seq {
+            // the packets after last_consecutive are out of the buffer now
+            self.last_consecutive = seq - self.received.size();
         }
-
-        self.set_received(seq);
+        // there might be valid packets after last_consecutive now
+        self.fix_last_consecutive();
     }
 
     fn get(&self, seq: u16) -> bool {
-        let diff = self.end.wrapping_sub(seq);
-        if diff >= UINT16SIZE_HALF {
-            return false;
-        }
-
-        if diff >= self.size {
-            return false;
-        }
-
-        self.get_received(seq)
+        self.seqnum
+            .extend(seq)
+            .map_or(false, |seq| self.received.contains(seq))
     }
 
     fn missing_seq_numbers(&self, skip_last_n: u16) -> Vec<u16> {
-        let until = self.end.wrapping_sub(skip_last_n);
-        let diff = until.wrapping_sub(self.last_consecutive);
-        if diff >= UINT16SIZE_HALF {
-            // until < s.last_consecutive (counting for rollover)
-            return vec![];
-        }
-
-        let mut missing_packet_seq_nums = vec![];
-        let mut i = self.last_consecutive.wrapping_add(1);
-        let util_plus1 = until.wrapping_add(1);
-        while i != util_plus1 {
-            if !self.get_received(i) {
-                missing_packet_seq_nums.push(i);
-            }
-            i = i.wrapping_add(1);
-        }
-
-        missing_packet_seq_nums
-    }
-
-    fn set_received(&mut self, seq: u16) {
-        let pos = (seq % self.size) as usize;
-        self.packets[pos / 64] |= 1u64 << (pos % 64);
-    }
-
-    fn del_received(&mut self, seq: u16) {
-        let pos = (seq % self.size) as usize;
-        self.packets[pos / 64] &= u64::MAX ^ (1u64 << (pos % 64));
-    }
-
-    fn get_received(&self, seq: u16) -> bool {
-        let pos = (seq % self.size) as usize;
-        (self.packets[pos / 64] & (1u64 << (pos % 64))) != 0
+        let until = match self.seqnum.highest() {
+            Some(highest) => highest.saturating_sub(skip_last_n as u64),
+            None => return vec![],
+        };
+
+        (self.last_consecutive + 1..=until)
+            .filter(|&seq| !self.received.contains(seq))
+            .map(|seq| seq as u16)
+            .collect()
     }
 
     fn fix_last_consecutive(&mut self) {
-        let mut i = self.last_consecutive.wrapping_add(1);
-        while i != self.end.wrapping_add(1) && self.get_received(i) {
-            // find all consecutive packets
-            i = i.wrapping_add(1);
+        // find all consecutive packets
+        while self.received.contains(self.last_consecutive + 1) {
+            self.last_consecutive += 1;
         }
-        self.last_consecutive = i.wrapping_sub(1);
     }
 }
 
@@ -234,7 +175,10 @@ mod test {
 
             let assert_last_consecutive = |rl: &GeneratorStreamInternal, last_consecutive: u16| {
                 let want = last_consecutive.wrapping_add(start);
-                assert_eq!(rl.last_consecutive, want, "invalid last_consecutive want");
+                assert_eq!(
+                    rl.last_consecutive as u16, want,
+                    "invalid last_consecutive want"
+                );
             };
 
             add(&mut rl, &[0]);
@@ -310,5 +254,13 @@ mod test {
         rl.add(65534);
         rl.add(0);
         rl.add(65535);
+
+        let mut rl = GeneratorStreamInternal::new(1);
+        // A packet reordered from before the first one across the wraparound is ignored.
+        rl.add(1);
+        rl.add(65535);
+        rl.add(3);
+        assert!(!rl.get(65535));
+        assert_eq!(rl.missing_seq_numbers(0), vec![2]);
     }
 }
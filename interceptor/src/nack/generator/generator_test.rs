@@ -64,3 +64,67 @@ async fn test_generator_interceptor() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(start_paused = true)]
+async fn test_generator_interceptor_early_feedback_budget() -> Result<()> {
+    const INTERVAL: Duration = Duration::from_millis(10);
+    let config = RtcpSchedulerConfig {
+        min_interval: Duration::from_secs(0),
+        trr_int: Duration::from_secs(60),
+        ..RtcpSchedulerConfig::with_session_bandwidth(100_000)
+    };
+    let regular_interval = RtcpScheduler::new(config).regular_interval();
+    let icpr: Arc<dyn Interceptor + Send + Sync> = Generator::builder()
+        .with_log2_size_minus_6(0)
+        .with_interval(INTERVAL)
+        .with_rtcp_scheduler_config(config)
+        .build("")?;
+
+    let stream = MockStream::new(
+        &StreamInfo {
+            ssrc: 1,
+            rtcp_feedback: vec![RTCPFeedback {
+                typ: "nack".to_owned(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+        icpr,
+    )
+    .await;
+
+    for seq_num in [10, 12] {
+        stream
+            .receive_rtp(rtp::packet::Packet {
+                header: rtp::header::Header {
+                    sequence_number: seq_num,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .await;
+        let _ = timeout_or_fail(Duration::from_millis(10), stream.read_rtp()).await;
+    }
+
+    // The budget allows one nack per regular RTCP interval however many ticks pass, trr_int
+    // only holds back regular reports.
+    timeout_or_fail(Duration::from_millis(100), stream.written_rtcp())
+        .await
+        .expect("Write rtcp");
+    tokio::time::sleep(regular_interval / 2).await;
+    assert!(stream.last_written_rtcp().await.is_none());
+
+    let r = timeout_or_fail(regular_interval, stream.written_rtcp())
+        .await
+        .expect("Write rtcp");
+    let p = r[0]
+        .as_any()
+        .downcast_ref::<TransportLayerNack>()
+        .expect("single packet RTCP Compound Packet expected");
+    assert_eq!(11, p.nacks[0].packet_id);
+    assert!(regular_interval * 2 < config.trr_int);
+
+    stream.close().await?;
+
+    Ok(())
+}
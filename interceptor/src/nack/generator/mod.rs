@@ -7,9 +7,10 @@ use generator_stream::GeneratorStream;
 use crate::error::{Error, Result};
 use crate::stream_info::StreamInfo;
 use crate::{Attributes, Interceptor, RTCPReader, RTPReader, RTPWriter};
-use crate::{InterceptorBuilder, RTCPWriter};
+use crate::{InterceptorBuilder, RTCPWriter, TASKS_CLOSE_TIMEOUT};
 
 use crate::nack::stream_support_nack;
+use crate::rtcp_scheduler::{RtcpScheduler, RtcpSchedulerConfig};
 
 use async_trait::async_trait;
 use rtcp::transport_feedbacks::transport_layer_nack::{
@@ -19,7 +20,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{mpsc, Mutex};
-use waitgroup::WaitGroup;
+use util::sync::TaskGroup;
 
 /// GeneratorBuilder can be used to configure Generator Interceptor
 #[derive(Default)]
@@ -27,6 +28,7 @@ pub struct GeneratorBuilder {
     log2_size_minus_6: Option<u8>,
     skip_last_n: Option<u16>,
     interval: Option<Duration>,
+    scheduler_config: Option<RtcpSchedulerConfig>,
 }
 
 impl GeneratorBuilder {
@@ -49,6 +51,14 @@ impl GeneratorBuilder {
         self.interval = Some(interval);
         self
     }
+
+    /// with_rtcp_scheduler_config limits nacks to the early feedback budget of RFC 4585, at most one
+    /// nack batch is sent per regular RTCP interval computed from the config, regardless of
+    /// `trr_int`. Missing packets are kept and requested once the budget allows it.
+    pub fn with_rtcp_scheduler_config(mut self, config: RtcpSchedulerConfig) -> GeneratorBuilder {
+        self.scheduler_config = Some(config);
+        self
+    }
 }
 
 impl InterceptorBuilder for GeneratorBuilder {
@@ -71,21 +81,27 @@ impl InterceptorBuilder for GeneratorBuilder {
                 } else {
                     Duration::from_millis(100)
                 },
+                scheduler: self.scheduler_config.map(RtcpScheduler::new),
 
                 streams: Mutex::new(HashMap::new()),
                 close_rx: Mutex::new(Some(close_rx)),
             }),
 
-            wg: Mutex::new(Some(WaitGroup::new())),
+            tasks: TaskGroup::new(),
             close_tx: Mutex::new(Some(close_tx)),
         }))
     }
+
+    fn set_default_rtcp_scheduler_config(&mut self, config: RtcpSchedulerConfig) {
+        self.scheduler_config.get_or_insert(config);
+    }
 }
 
 struct GeneratorInternal {
     log2_size_minus_6: u8,
     skip_last_n: u16,
     interval: Duration,
+    scheduler: Option<RtcpScheduler>,
 
     streams: Mutex<HashMap<u32, Arc<GeneratorStream>>>,
     close_rx: Mutex<Option<mpsc::Receiver<()>>>,
@@ -95,7 +111,7 @@ struct GeneratorInternal {
 pub struct Generator {
     internal: Arc<GeneratorInternal>,
 
-    pub(crate) wg: Mutex<Option<WaitGroup>>,
+    pub(crate) tasks: TaskGroup,
     pub(crate) close_tx: Mutex<Option<mpsc::Sender<()>>>,
 }
 
@@ -124,9 +140,27 @@ impl Generator {
             }
         };
 
+        // The generator sends no regular reports, so it re-arms its early feedback budget itself
+        // each regular RTCP interval.
+        let regular_interval = internal
+            .scheduler
+            .as_ref()
+            .map(|scheduler| scheduler.regular_interval())
+            .unwrap_or_default();
+        let rearm = tokio::time::sleep(regular_interval);
+        tokio::pin!(rearm);
+
         let sender_ssrc = rand::random::<u32>();
         loop {
             tokio::select! {
+                _ = &mut rearm, if internal.scheduler.is_some() => {
+                    if let Some(scheduler) = &internal.scheduler {
+                        scheduler.on_regular_interval();
+                        rearm
+                            .as_mut()
+                            .reset(tokio::time::Instant::now() + scheduler.regular_interval());
+                    }
+                }
                 _ = ticker.tick() =>{
                     let nacks = {
                         let mut nacks = vec![];
@@ -146,6 +180,14 @@ impl Generator {
                         nacks
                     };
 
+                    if !nacks.is_empty() {
+                        if let Some(scheduler) = &internal.scheduler {
+                            if !scheduler.try_early_feedback() {
+                                continue;
+                            }
+                        }
+                    }
+
                     let a = Attributes::new();
                     for nack in nacks{
                         if let Err(err) = rtcp_writer.write(&[Box::new(nack)], &a).await{
@@ -182,14 +224,9 @@ impl Interceptor for Generator {
             return writer;
         }
 
-        let mut w = {
-            let wait_group = self.wg.lock().await;
-            wait_group.as_ref().map(|wg| wg.worker())
-        };
         let writer2 = Arc::clone(&writer);
         let internal = Arc::clone(&self.internal);
-        tokio::spawn(async move {
-            let _d = w.take();
+        self.tasks.spawn(async move {
             if let Err(err) = Generator::run(writer2, internal).await {
                 log::warn!("bind_rtcp_writer NACK Generator::run got error: {}", err);
             }
@@ -247,12 +284,7 @@ impl Interceptor for Generator {
             close_tx.take();
         }
 
-        {
-            let mut wait_group = self.wg.lock().await;
-            if let Some(wg) = wait_group.take() {
-                wg.wait().await;
-            }
-        }
+        self.tasks.close(TASKS_CLOSE_TIMEOUT).await;
 
         Ok(())
     }
@@ -3,7 +3,9 @@ use crate::stream_info::StreamInfo;
 pub mod generator;
 pub mod responder;
 
-const UINT16SIZE_HALF: u16 = 1 << 15;
+/// Attribute key set by the responder on the packets it resends in response to a NACK, so that
+/// interceptors further down the chain can tell retransmissions apart.
+pub const ATTR_RETRANSMISSION: usize = 0x4E41;
 
 fn stream_support_nack(info: &StreamInfo) -> bool {
     for fb in &info.rtcp_feedback {
@@ -5,11 +5,12 @@ mod responder_test;
 use crate::stream_info::StreamInfo;
 use crate::{
     Attributes, Interceptor, InterceptorBuilder, RTCPReader, RTCPWriter, RTPReader, RTPWriter,
+    TASKS_CLOSE_TIMEOUT,
 };
 use responder_stream::ResponderStream;
 
 use crate::error::Result;
-use crate::nack::stream_support_nack;
+use crate::nack::{stream_support_nack, ATTR_RETRANSMISSION};
 
 use async_trait::async_trait;
 use rtcp::transport_feedbacks::transport_layer_nack::TransportLayerNack;
@@ -18,6 +19,7 @@ use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use util::sync::TaskGroup;
 
 /// GeneratorBuilder can be used to configure Responder Interceptor
 #[derive(Default)]
@@ -32,11 +34,11 @@ impl ResponderBuilder {
         self.log2_size = Some(log2_size);
         self
     }
-}
 
-impl InterceptorBuilder for ResponderBuilder {
-    fn build(&self, _id: &str) -> Result<Arc<dyn Interceptor + Send + Sync>> {
-        Ok(Arc::new(Responder {
+    /// build_responder constructs the Responder like `build`, keeping its type so that its
+    /// counters can be read with [`Responder::retransmissions`].
+    pub fn build_responder(&self) -> Arc<Responder> {
+        Arc::new(Responder {
             internal: Arc::new(ResponderInternal {
                 log2_size: if let Some(log2_size) = self.log2_size {
                     log2_size
@@ -44,14 +46,22 @@ impl InterceptorBuilder for ResponderBuilder {
                     13 // 8192 = 1 << 13
                 },
                 streams: Arc::new(Mutex::new(HashMap::new())),
+                tasks: TaskGroup::new(),
             }),
-        }))
+        })
+    }
+}
+
+impl InterceptorBuilder for ResponderBuilder {
+    fn build(&self, _id: &str) -> Result<Arc<dyn Interceptor + Send + Sync>> {
+        Ok(self.build_responder())
     }
 }
 
 pub struct ResponderInternal {
     log2_size: u8,
     streams: Arc<Mutex<HashMap<u32, Arc<ResponderStream>>>>,
+    tasks: TaskGroup,
 }
 
 impl ResponderInternal {
@@ -75,9 +85,13 @@ impl ResponderInternal {
                     let stream3 = Arc::clone(&stream2);
                     Box::pin(async move {
                         if let Some(p) = stream3.get(seq).await {
-                            let a = Attributes::new();
-                            if let Err(err) = stream3.next_rtp_writer.write(&p, &a).await {
-                                log::warn!("failed resending nacked packet: {}", err);
+                            let mut a = Attributes::new();
+                            a.insert(ATTR_RETRANSMISSION, 1);
+                            match stream3.next_rtp_writer.write(&p, &a).await {
+                                Ok(_) => stream3.record_retransmission(&p),
+                                Err(err) => {
+                                    log::warn!("failed resending nacked packet: {}", err)
+                                }
                             }
                         }
 
@@ -106,7 +120,7 @@ impl RTCPReader for ResponderRtcpReader {
             if let Some(nack) = p.as_any().downcast_ref::<TransportLayerNack>() {
                 let nack = nack.clone();
                 let streams = Arc::clone(&self.internal.streams);
-                tokio::spawn(async move {
+                self.internal.tasks.spawn(async move {
                     ResponderInternal::resend_packets(streams, nack).await;
                 });
             }
@@ -126,6 +140,13 @@ impl Responder {
     pub fn builder() -> ResponderBuilder {
         ResponderBuilder::default()
     }
+
+    /// retransmissions returns the number of packets resent for the local stream `ssrc` and
+    /// their payload bytes, or None if the stream isn't bound or doesn't support NACK.
+    pub async fn retransmissions(&self, ssrc: u32) -> Option<(u64, u64)> {
+        let streams = self.internal.streams.lock().await;
+        streams.get(&ssrc).map(|stream| stream.retransmissions())
+    }
 }
 
 #[async_trait]
@@ -192,6 +213,8 @@ impl Interceptor for Responder {
 
     /// close closes the Interceptor, cleaning up any data if necessary.
     async fn close(&self) -> Result<()> {
+        self.internal.tasks.close(TASKS_CLOSE_TIMEOUT).await;
+
         Ok(())
     }
 }
@@ -1,8 +1,9 @@
 use crate::error::Result;
-use crate::nack::UINT16SIZE_HALF;
 use crate::{Attributes, RTPWriter};
 
 use async_trait::async_trait;
+use rtp::seqnum;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -32,10 +33,10 @@ impl ResponderStreamInternal {
             return;
         }
 
-        let diff = seq.wrapping_sub(self.last_added);
+        let diff = seqnum::diff(seq, self.last_added);
         if diff == 0 {
             return;
-        } else if diff < UINT16SIZE_HALF {
+        } else if diff > 0 {
             let mut i = self.last_added.wrapping_add(1);
             while i != seq {
                 self.packets[(i % self.size) as usize] = None;
@@ -48,12 +49,8 @@ impl ResponderStreamInternal {
     }
 
     fn get(&self, seq: u16) -> Option<&rtp::packet::Packet> {
-        let diff = self.last_added.wrapping_sub(seq);
-        if diff >= UINT16SIZE_HALF {
-            return None;
-        }
-
-        if diff >= self.size {
+        let diff = seqnum::diff(self.last_added, seq);
+        if diff < 0 || diff >= self.size as i32 {
             return None;
         }
 
@@ -64,6 +61,9 @@ impl ResponderStreamInternal {
 pub(super) struct ResponderStream {
     internal: Mutex<ResponderStreamInternal>,
     pub(super) next_rtp_writer: Arc<dyn RTPWriter + Send + Sync>,
+
+    retransmitted_packets: AtomicU64,
+    retransmitted_bytes: AtomicU64,
 }
 
 impl ResponderStream {
@@ -71,9 +71,24 @@ impl ResponderStream {
         ResponderStream {
             internal: Mutex::new(ResponderStreamInternal::new(log2_size)),
             next_rtp_writer: writer,
+            retransmitted_packets: AtomicU64::new(0),
+            retransmitted_bytes: AtomicU64::new(0),
         }
     }
 
+    pub(super) fn record_retransmission(&self, pkt: &rtp::packet::Packet) {
+        self.retransmitted_packets.fetch_add(1, Ordering::SeqCst);
+        self.retransmitted_bytes
+            .fetch_add(pkt.payload.len() as u64, Ordering::SeqCst);
+    }
+
+    pub(super) fn retransmissions(&self) -> (u64, u64) {
+        (
+            self.retransmitted_packets.load(Ordering::SeqCst),
+            self.retransmitted_bytes.load(Ordering::SeqCst),
+        )
+    }
+
     async fn add(&self, pkt: &rtp::packet::Packet) {
         let mut internal = self.internal.lock().await;
         internal.add(pkt);
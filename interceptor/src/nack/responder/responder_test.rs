@@ -2,14 +2,15 @@ use super::*;
 use crate::mock::mock_stream::MockStream;
 use crate::stream_info::RTCPFeedback;
 use crate::test::timeout_or_fail;
+use bytes::Bytes;
 use tokio::time::Duration;
 
 use rtcp::transport_feedbacks::transport_layer_nack::{NackPair, TransportLayerNack};
 
 #[tokio::test]
 async fn test_responder_interceptor() -> Result<()> {
-    let icpr: Arc<dyn Interceptor + Send + Sync> =
-        Responder::builder().with_log2_size(3).build("")?;
+    let responder = Responder::builder().with_log2_size(3).build_responder();
+    let icpr: Arc<dyn Interceptor + Send + Sync> = responder.clone();
 
     let stream = MockStream::new(
         &StreamInfo {
@@ -31,7 +32,7 @@ async fn test_responder_interceptor() -> Result<()> {
                     sequence_number: seq_num,
                     ..Default::default()
                 },
-                ..Default::default()
+                payload: Bytes::from(vec![0u8; seq_num as usize]),
             })
             .await?;
 
@@ -74,6 +75,13 @@ async fn test_responder_interceptor() -> Result<()> {
     let result = tokio::time::timeout(Duration::from_millis(10), stream.written_rtp()).await;
     assert!(result.is_err(), "no more rtp packets expected");
 
+    assert_eq!(
+        Some((3, 11 + 12 + 15)),
+        responder.retransmissions(1).await,
+        "the resent packets and their payload bytes should be counted"
+    );
+    assert_eq!(None, responder.retransmissions(2).await);
+
     stream.close().await?;
 
     Ok(())
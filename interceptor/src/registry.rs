@@ -1,6 +1,7 @@
 use crate::chain::Chain;
 use crate::error::Result;
 use crate::noop::NoOp;
+use crate::rtcp_scheduler::RtcpSchedulerConfig;
 use crate::{Interceptor, InterceptorBuilder};
 
 use std::sync::Arc;
@@ -21,6 +22,14 @@ impl Registry {
         self.builders.push(builder);
     }
 
+    /// set_rtcp_scheduler_config sets the RTCP timing of the session on the builders already
+    /// added, see [`InterceptorBuilder::set_default_rtcp_scheduler_config`].
+    pub fn set_rtcp_scheduler_config(&mut self, config: RtcpSchedulerConfig) {
+        for builder in &mut self.builders {
+            builder.set_default_rtcp_scheduler_config(config);
+        }
+    }
+
     /// build constructs a single Interceptor from an InterceptorRegistry
     pub fn build(&self, id: &str) -> Result<Arc<dyn Interceptor + Send + Sync>> {
         if self.builders.is_empty() {
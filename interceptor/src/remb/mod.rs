@@ -0,0 +1,12 @@
+pub mod receiver;
+
+use crate::stream_info::StreamInfo;
+
+pub(crate) const ABS_SEND_TIME_URI: &str =
+    "http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time";
+
+fn stream_support_remb(info: &StreamInfo) -> bool {
+    info.rtcp_feedback
+        .iter()
+        .any(|fb| fb.typ == "goog-remb" && fb.parameter.is_empty())
+}
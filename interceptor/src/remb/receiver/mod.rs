@@ -0,0 +1,392 @@
+mod receiver_stream;
+#[cfg(test)]
+mod receiver_test;
+
+use crate::estimator::{DelayLossEstimator, Estimator, PacketResult};
+use crate::remb::{stream_support_remb, ABS_SEND_TIME_URI};
+use crate::time_source::{system_time_source, SharedTimeSource};
+use crate::*;
+use receiver_stream::ReceiverStream;
+
+use rtcp::payload_feedbacks::receiver_estimated_maximum_bitrate::ReceiverEstimatedMaximumBitrate;
+use rtp::seqnum;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::{Instant, MissedTickBehavior};
+use util::sync::TaskGroup;
+
+/// EstimatorFactory creates the estimator of each interceptor built by a [`ReceiverBuilder`]
+pub type EstimatorFactory = Box<dyn (Fn() -> Box<dyn Estimator + Send + Sync>) + Send + Sync>;
+
+/// The estimate is re-evaluated this often, to send a REMB early when it dropped
+const UPDATE_INTERVAL: Duration = Duration::from_millis(50);
+/// A REMB is sent before the interval elapsed when the estimate fell below this share of the
+/// last one sent
+const DECREASE_RATIO: f64 = 0.97;
+
+/// ReceiverBuilder is a InterceptorBuilder for a Receiver
+#[derive(Default)]
+pub struct ReceiverBuilder {
+    interval: Option<Duration>,
+    estimator_factory: Option<EstimatorFactory>,
+    time_source: Option<SharedTimeSource>,
+}
+
+impl ReceiverBuilder {
+    /// with_interval sets how often REMB packets are sent, defaults to one second. A decrease of
+    /// the estimate is reported right away.
+    pub fn with_interval(mut self, interval: Duration) -> ReceiverBuilder {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// with_estimator sets how the estimators are created, defaults to
+    /// [`DelayLossEstimator::default`].
+    pub fn with_estimator(mut self, estimator_factory: EstimatorFactory) -> ReceiverBuilder {
+        self.estimator_factory = Some(estimator_factory);
+        self
+    }
+
+    /// with_time_source sets the clock the arrival times of packets are read from, with
+    /// [`TimeSource::instant`](crate::time_source::TimeSource::instant).
+    pub fn with_time_source(mut self, time_source: SharedTimeSource) -> ReceiverBuilder {
+        self.time_source = Some(time_source);
+        self
+    }
+}
+
+impl InterceptorBuilder for ReceiverBuilder {
+    fn build(&self, _id: &str) -> Result<Arc<dyn Interceptor + Send + Sync>> {
+        let estimator = if let Some(estimator_factory) = &self.estimator_factory {
+            estimator_factory()
+        } else {
+            Box::new(DelayLossEstimator::default())
+        };
+
+        let (close_tx, close_rx) = mpsc::channel(1);
+        let (packet_chan_tx, packet_chan_rx) = mpsc::channel(1);
+        let now = self.time_source.clone().unwrap_or_else(system_time_source);
+        Ok(Arc::new(Receiver {
+            internal: Arc::new(ReceiverInternal {
+                interval: if let Some(interval) = &self.interval {
+                    *interval
+                } else {
+                    Duration::from_secs(1)
+                },
+                estimator: Mutex::new(estimator),
+                packet_chan_rx: Mutex::new(Some(packet_chan_rx)),
+                streams: Mutex::new(HashMap::new()),
+                close_rx: Mutex::new(Some(close_rx)),
+            }),
+            start_time: now.instant(),
+            now,
+            packet_chan_tx,
+            tasks: TaskGroup::new(),
+            close_tx: Mutex::new(Some(close_tx)),
+        }))
+    }
+}
+
+struct Packet {
+    ssrc: u32,
+    sequence_number: u16,
+    arrival_time: Duration,
+    /// The 24 bit abs-send-time of the packet
+    abs_send_time: Option<u32>,
+    size: usize,
+}
+
+/// AbsSendTimeUnwrapper turns the 24 bit abs-send-time, which wraps every 64 seconds, into a
+/// continuous send time.
+#[derive(Default)]
+struct AbsSendTimeUnwrapper {
+    last: Option<u32>,
+    cycles: u64,
+}
+
+impl AbsSendTimeUnwrapper {
+    const CYCLE: u64 = 1 << 24;
+
+    fn unwrap(&mut self, abs_send_time: u32) -> Duration {
+        let mut cycles = self.cycles;
+        if let Some(last) = self.last {
+            let delta = abs_send_time.wrapping_sub(last) & 0xFFFFFF;
+            if delta < 1 << 23 {
+                // sent after the last packet
+                if abs_send_time < last {
+                    cycles += Self::CYCLE;
+                }
+                self.cycles = cycles;
+                self.last = Some(abs_send_time);
+            } else if abs_send_time > last {
+                // reordered from before the last wrap
+                cycles = cycles.saturating_sub(Self::CYCLE);
+            }
+        } else {
+            self.last = Some(abs_send_time);
+        }
+
+        // 6.18 fixed point seconds
+        let units = cycles + abs_send_time as u64;
+        Duration::from_nanos(units * 1_000_000_000 / (1 << 18))
+    }
+}
+
+struct ReceiverInternal {
+    interval: Duration,
+    estimator: Mutex<Box<dyn Estimator + Send + Sync>>,
+    packet_chan_rx: Mutex<Option<mpsc::Receiver<Packet>>>,
+    streams: Mutex<HashMap<u32, Arc<ReceiverStream>>>,
+    close_rx: Mutex<Option<mpsc::Receiver<()>>>,
+}
+
+/// Receiver estimates the bandwidth available for the incoming streams and reports it to the
+/// remote with Receiver Estimated Maximum Bitrate packets, as specified in
+/// https://datatracker.ietf.org/doc/html/draft-alvestrand-rmcat-remb-03
+///
+/// Only streams which negotiated `goog-remb` feedback are taken into account. The send times
+/// of packets are read from the abs-send-time header extension when it was negotiated.
+pub struct Receiver {
+    internal: Arc<ReceiverInternal>,
+
+    start_time: Instant,
+    now: SharedTimeSource,
+    packet_chan_tx: mpsc::Sender<Packet>,
+
+    tasks: TaskGroup,
+    close_tx: Mutex<Option<mpsc::Sender<()>>>,
+}
+
+impl Receiver {
+    /// builder returns a new ReceiverBuilder.
+    pub fn builder() -> ReceiverBuilder {
+        ReceiverBuilder::default()
+    }
+
+    async fn is_closed(&self) -> bool {
+        let close_tx = self.close_tx.lock().await;
+        close_tx.is_none()
+    }
+
+    async fn send_remb(
+        rtcp_writer: &Arc<dyn RTCPWriter + Send + Sync>,
+        internal: &ReceiverInternal,
+        sender_ssrc: u32,
+        bitrate: u64,
+    ) {
+        let ssrcs = {
+            let streams = internal.streams.lock().await;
+            let mut ssrcs: Vec<u32> = streams.keys().copied().collect();
+            ssrcs.sort_unstable();
+            ssrcs
+        };
+
+        let pkts: Vec<Box<dyn rtcp::packet::Packet + Send + Sync>> =
+            vec![Box::new(ReceiverEstimatedMaximumBitrate {
+                sender_ssrc,
+                bitrate: bitrate as f32,
+                ssrcs,
+            })];
+
+        if let Err(err) = rtcp_writer.write(&pkts, &Attributes::new()).await {
+            log::error!("rtcp_writer.write got err: {}", err);
+        }
+    }
+
+    async fn run(
+        rtcp_writer: Arc<dyn RTCPWriter + Send + Sync>,
+        internal: Arc<ReceiverInternal>,
+        start_time: Instant,
+        now: SharedTimeSource,
+    ) -> Result<()> {
+        let mut close_rx = {
+            let mut close_rx = internal.close_rx.lock().await;
+            if let Some(close_rx) = close_rx.take() {
+                close_rx
+            } else {
+                return Err(Error::ErrInvalidCloseRx);
+            }
+        };
+        let mut packet_chan_rx = {
+            let mut packet_chan_rx = internal.packet_chan_rx.lock().await;
+            if let Some(packet_chan_rx) = packet_chan_rx.take() {
+                packet_chan_rx
+            } else {
+                return Err(Error::ErrInvalidPacketRx);
+            }
+        };
+
+        let sender_ssrc = rand::random::<u32>();
+        let mut last_sequence_numbers = HashMap::new();
+        let mut abs_send_time_unwrapper = AbsSendTimeUnwrapper::default();
+        let mut estimate = None;
+        let mut last_sent: Option<u64> = None;
+
+        let mut update_ticker = tokio::time::interval(UPDATE_INTERVAL);
+        update_ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut ticker = tokio::time::interval(internal.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            tokio::select! {
+                _ = close_rx.recv() =>{
+                    return Ok(());
+                }
+                p = packet_chan_rx.recv() => {
+                    if let Some(p) = p {
+                        let lost_before = match last_sequence_numbers.get(&p.ssrc) {
+                            Some(last) => {
+                                let delta = seqnum::diff(p.sequence_number, *last);
+                                if delta <= 0 {
+                                    // duplicated or reordered
+                                    None
+                                } else {
+                                    Some(delta as u64 - 1)
+                                }
+                            }
+                            None => Some(0),
+                        };
+                        if lost_before.is_some() {
+                            last_sequence_numbers.insert(p.ssrc, p.sequence_number);
+                        }
+
+                        let mut estimator = internal.estimator.lock().await;
+                        estimator.on_packet(&PacketResult {
+                            arrival_time: p.arrival_time,
+                            send_time: p
+                                .abs_send_time
+                                .map(|t| abs_send_time_unwrapper.unwrap(t)),
+                            size: p.size,
+                            lost_before: lost_before.unwrap_or_default(),
+                        });
+                    }
+                }
+                _ = update_ticker.tick() =>{
+                    if last_sequence_numbers.is_empty() {
+                        continue;
+                    }
+
+                    let bitrate = {
+                        let mut estimator = internal.estimator.lock().await;
+                        estimator.update(now.instant() - start_time)
+                    };
+                    estimate = Some(bitrate);
+
+                    if let Some(last_bitrate) = last_sent {
+                        if (bitrate as f64) < last_bitrate as f64 * DECREASE_RATIO {
+                            Receiver::send_remb(&rtcp_writer, &internal, sender_ssrc, bitrate).await;
+                            ticker.reset();
+                            last_sent = Some(bitrate);
+                        }
+                    }
+                }
+                _ = ticker.tick() =>{
+                    if let Some(bitrate) = estimate {
+                        Receiver::send_remb(&rtcp_writer, &internal, sender_ssrc, bitrate).await;
+                        last_sent = Some(bitrate);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Interceptor for Receiver {
+    /// bind_rtcp_reader lets you modify any incoming RTCP packets. It is called once per sender/receiver, however this might
+    /// change in the future. The returned method will be called once per packet batch.
+    async fn bind_rtcp_reader(
+        &self,
+        reader: Arc<dyn RTCPReader + Send + Sync>,
+    ) -> Arc<dyn RTCPReader + Send + Sync> {
+        reader
+    }
+
+    /// bind_rtcp_writer lets you modify any outgoing RTCP packets. It is called once per PeerConnection. The returned method
+    /// will be called once per packet batch.
+    async fn bind_rtcp_writer(
+        &self,
+        writer: Arc<dyn RTCPWriter + Send + Sync>,
+    ) -> Arc<dyn RTCPWriter + Send + Sync> {
+        if self.is_closed().await {
+            return writer;
+        }
+
+        let writer2 = Arc::clone(&writer);
+        let internal = Arc::clone(&self.internal);
+        let start_time = self.start_time;
+        let now = Arc::clone(&self.now);
+        self.tasks.spawn(async move {
+            if let Err(err) = Receiver::run(writer2, internal, start_time, now).await {
+                log::warn!("bind_rtcp_writer REMB Receiver::run got error: {}", err);
+            }
+        });
+
+        writer
+    }
+
+    /// bind_local_stream lets you modify any outgoing RTP packets. It is called once for per LocalStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_local_stream(
+        &self,
+        _info: &StreamInfo,
+        writer: Arc<dyn RTPWriter + Send + Sync>,
+    ) -> Arc<dyn RTPWriter + Send + Sync> {
+        writer
+    }
+
+    /// unbind_local_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_local_stream(&self, _info: &StreamInfo) {}
+
+    /// bind_remote_stream lets you modify any incoming RTP packets. It is called once for per RemoteStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_remote_stream(
+        &self,
+        info: &StreamInfo,
+        reader: Arc<dyn RTPReader + Send + Sync>,
+    ) -> Arc<dyn RTPReader + Send + Sync> {
+        if !stream_support_remb(info) {
+            return reader;
+        }
+
+        let hdr_ext_id = info
+            .rtp_header_extensions
+            .iter()
+            .find(|e| e.uri == ABS_SEND_TIME_URI)
+            .map(|e| e.id as u8)
+            .unwrap_or_default();
+
+        let stream = Arc::new(ReceiverStream::new(
+            reader,
+            hdr_ext_id,
+            info.ssrc,
+            self.packet_chan_tx.clone(),
+            self.start_time,
+            Arc::clone(&self.now),
+        ));
+
+        {
+            let mut streams = self.internal.streams.lock().await;
+            streams.insert(info.ssrc, Arc::clone(&stream));
+        }
+
+        stream
+    }
+
+    /// unbind_remote_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_remote_stream(&self, info: &StreamInfo) {
+        let mut streams = self.internal.streams.lock().await;
+        streams.remove(&info.ssrc);
+    }
+
+    /// close closes the Interceptor, cleaning up any data if necessary.
+    async fn close(&self) -> Result<()> {
+        {
+            let mut close_tx = self.close_tx.lock().await;
+            close_tx.take();
+        }
+
+        self.tasks.close(TASKS_CLOSE_TIMEOUT).await;
+
+        Ok(())
+    }
+}
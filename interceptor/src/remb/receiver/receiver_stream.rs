@@ -0,0 +1,67 @@
+use super::*;
+
+use rtp::extension::abs_send_time_extension::AbsSendTimeExtension;
+use util::Unmarshal;
+
+pub(super) struct ReceiverStream {
+    parent_rtp_reader: Arc<dyn RTPReader + Send + Sync>,
+    /// ID of the abs-send-time header extension, 0 when it isn't negotiated
+    hdr_ext_id: u8,
+    ssrc: u32,
+    packet_chan_tx: mpsc::Sender<Packet>,
+    start_time: Instant,
+    now: SharedTimeSource,
+}
+
+impl ReceiverStream {
+    pub(super) fn new(
+        parent_rtp_reader: Arc<dyn RTPReader + Send + Sync>,
+        hdr_ext_id: u8,
+        ssrc: u32,
+        packet_chan_tx: mpsc::Sender<Packet>,
+        start_time: Instant,
+        now: SharedTimeSource,
+    ) -> Self {
+        ReceiverStream {
+            parent_rtp_reader,
+            hdr_ext_id,
+            ssrc,
+            packet_chan_tx,
+            start_time,
+            now,
+        }
+    }
+}
+
+#[async_trait]
+impl RTPReader for ReceiverStream {
+    /// read a rtp packet
+    async fn read(&self, buf: &mut [u8], attributes: &Attributes) -> Result<(usize, Attributes)> {
+        let (n, attr) = self.parent_rtp_reader.read(buf, attributes).await?;
+
+        let mut b = &buf[..n];
+        let header = rtp::header::Header::unmarshal(&mut b)?;
+
+        let abs_send_time = if self.hdr_ext_id != 0 {
+            match header.get_extension(self.hdr_ext_id) {
+                Some(mut ext) => Some(AbsSendTimeExtension::unmarshal(&mut ext)?.timestamp as u32),
+                None => None,
+            }
+        } else {
+            None
+        };
+
+        let _ = self
+            .packet_chan_tx
+            .send(Packet {
+                ssrc: self.ssrc,
+                sequence_number: header.sequence_number,
+                arrival_time: self.now.instant() - self.start_time,
+                abs_send_time,
+                size: n,
+            })
+            .await;
+
+        Ok((n, attr))
+    }
+}
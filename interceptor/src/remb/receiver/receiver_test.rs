@@ -0,0 +1,491 @@
+use super::*;
+use crate::mock::mock_stream::MockStream;
+use crate::mock::mock_time::MockTime;
+use crate::stream_info::{RTCPFeedback, RTPHeaderExtension};
+use bytes::Bytes;
+use rtp::extension::abs_send_time_extension::AbsSendTimeExtension;
+use std::net::SocketAddr;
+use util::vnet::chunk::Chunk;
+use util::vnet::net::{Net, NetConfig};
+use util::vnet::router::{Router, RouterConfig};
+use util::{Marshal, Unmarshal};
+
+fn remb_stream_info(ssrc: u32) -> StreamInfo {
+    StreamInfo {
+        ssrc,
+        rtp_header_extensions: vec![RTPHeaderExtension {
+            uri: ABS_SEND_TIME_URI.to_owned(),
+            id: 2,
+        }],
+        rtcp_feedback: vec![RTCPFeedback {
+            typ: "goog-remb".to_owned(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+fn abs_send_time(send_time: Duration) -> u32 {
+    ((send_time.as_nanos() << 18) / 1_000_000_000) as u32 & 0xFFFFFF
+}
+
+fn rtp_packet(
+    sequence_number: u16,
+    send_time: Duration,
+    size: usize,
+) -> Result<rtp::packet::Packet> {
+    let mut header = rtp::header::Header {
+        ssrc: 1,
+        sequence_number,
+        ..Default::default()
+    };
+    header.set_extension(
+        2,
+        AbsSendTimeExtension {
+            timestamp: abs_send_time(send_time) as u64,
+        }
+        .marshal()?,
+    )?;
+
+    Ok(rtp::packet::Packet {
+        header,
+        payload: Bytes::from(vec![0u8; size]),
+    })
+}
+
+async fn advance(mt: &MockTime, d: Duration) {
+    mt.advance(d);
+    tokio::time::advance(d).await;
+}
+
+fn remb_bitrate(pkts: &[Box<dyn rtcp::packet::Packet + Send + Sync>]) -> Option<u64> {
+    pkts.iter().find_map(|p| {
+        p.as_any()
+            .downcast_ref::<ReceiverEstimatedMaximumBitrate>()
+            .map(|remb| remb.bitrate as u64)
+    })
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_remb_receiver_before_any_packets() -> Result<()> {
+    let icpr = Receiver::builder().build("")?;
+    let stream = MockStream::new(&remb_stream_info(1), icpr).await;
+
+    tokio::time::advance(Duration::from_secs(3)).await;
+    tokio::task::yield_now().await;
+
+    assert!(
+        stream.last_written_rtcp().await.is_none(),
+        "Should not have sent a REMB before receiving the first RTP packets"
+    );
+
+    stream.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_remb_receiver_without_remb_feedback() -> Result<()> {
+    let icpr = Receiver::builder().build("")?;
+    let stream = MockStream::new(
+        &StreamInfo {
+            ssrc: 1,
+            ..Default::default()
+        },
+        icpr,
+    )
+    .await;
+
+    for i in 0..10 {
+        tokio::time::advance(Duration::from_millis(10)).await;
+        stream
+            .receive_rtp(rtp_packet(i, Duration::from_millis(10 * i as u64), 1000)?)
+            .await;
+        stream.read_rtp().await;
+    }
+    tokio::time::advance(Duration::from_secs(2)).await;
+    tokio::task::yield_now().await;
+
+    assert!(stream.last_written_rtcp().await.is_none());
+
+    stream.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_remb_receiver_sends_estimate() -> Result<()> {
+    let mt = Arc::new(MockTime::default());
+    let icpr = Receiver::builder()
+        .with_interval(Duration::from_millis(500))
+        .with_time_source(Arc::clone(&mt) as SharedTimeSource)
+        .build("")?;
+    let stream = MockStream::new(&remb_stream_info(1), icpr).await;
+
+    // 1Mbps
+    for i in 0..100 {
+        advance(&mt, Duration::from_millis(10)).await;
+        stream
+            .receive_rtp(rtp_packet(i, Duration::from_millis(10 * i as u64), 1238)?)
+            .await;
+        stream.read_rtp().await;
+    }
+
+    let pkts = stream.written_rtcp().await.unwrap();
+    let remb = pkts[0]
+        .as_any()
+        .downcast_ref::<ReceiverEstimatedMaximumBitrate>()
+        .expect("should have sent a REMB");
+    assert_eq!(vec![1], remb.ssrcs);
+    assert!(
+        (1_000_000.0..=1_500_000.0).contains(&remb.bitrate),
+        "bitrate {}",
+        remb.bitrate
+    );
+
+    stream.close().await?;
+
+    Ok(())
+}
+
+/// ArrivalRecorder is an Estimator keeping the arrival times of the packets it is given.
+struct ArrivalRecorder(Arc<std::sync::Mutex<Vec<Duration>>>);
+
+impl Estimator for ArrivalRecorder {
+    fn on_packet(&mut self, packet: &PacketResult) {
+        self.0.lock().unwrap().push(packet.arrival_time);
+    }
+
+    fn update(&mut self, _now: Duration) -> u64 {
+        1_000_000
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_remb_receiver_ignores_wall_clock_steps() -> Result<()> {
+    let mt = Arc::new(MockTime::default());
+    mt.set_now(std::time::UNIX_EPOCH + Duration::from_secs(7_200));
+    let arrivals = Arc::new(std::sync::Mutex::new(vec![]));
+    let recorded = Arc::clone(&arrivals);
+    let icpr = Receiver::builder()
+        .with_estimator(Box::new(move || {
+            Box::new(ArrivalRecorder(Arc::clone(&recorded)))
+        }))
+        .with_time_source(Arc::clone(&mt) as SharedTimeSource)
+        .build("")?;
+    let stream = MockStream::new(&remb_stream_info(1), icpr).await;
+
+    // The wall clock steps back by an hour and then forward by two between packets, which must
+    // not show in their arrival times.
+    for i in 0..3u16 {
+        match i {
+            1 => mt.set_now(mt.now() - Duration::from_secs(3_600)),
+            2 => mt.set_now(mt.now() + Duration::from_secs(7_200)),
+            _ => {}
+        }
+        advance(&mt, Duration::from_millis(10)).await;
+        stream
+            .receive_rtp(rtp_packet(i, Duration::from_millis(10 * i as u64), 1238)?)
+            .await;
+        stream.read_rtp().await;
+        tokio::task::yield_now().await;
+    }
+
+    assert_eq!(
+        *arrivals.lock().unwrap(),
+        vec![
+            Duration::from_millis(10),
+            Duration::from_millis(20),
+            Duration::from_millis(30)
+        ]
+    );
+
+    stream.close().await?;
+
+    Ok(())
+}
+
+/// A sender keeps sending 2Mbps through a bottleneck whose capacity drops from 4Mbps to 1Mbps
+/// after 5 seconds. The bottleneck queues up to 250ms of packets and drops the rest.
+#[tokio::test(start_paused = true)]
+async fn test_remb_receiver_tracks_bandwidth_step_down() -> Result<()> {
+    const PACKET_SIZE: usize = 1250;
+    const SEND_BITRATE: f64 = 2_000_000.0;
+    const STEP_DOWN_AT: Duration = Duration::from_secs(5);
+    const CAPACITY_AFTER_STEP_DOWN: f64 = 1_000_000.0;
+    const PROPAGATION_DELAY: Duration = Duration::from_millis(20);
+    const MAX_QUEUING_DELAY: Duration = Duration::from_millis(250);
+    // abs-send-time wraps 64 seconds after its origin
+    const SENDER_CLOCK_OFFSET: Duration = Duration::from_secs(62);
+
+    let mt = Arc::new(MockTime::default());
+    let icpr = Receiver::builder()
+        .with_interval(Duration::from_millis(500))
+        .with_time_source(Arc::clone(&mt) as SharedTimeSource)
+        .build("")?;
+    let stream = MockStream::new(&remb_stream_info(1), icpr).await;
+    let start = mt.now();
+
+    let send_interval = Duration::from_secs_f64(PACKET_SIZE as f64 * 8.0 / SEND_BITRATE);
+    let mut link_free_at = Duration::ZERO;
+    let mut sequence_number = 65500u16;
+    let mut rembs = vec![];
+    let mut i = 0;
+    loop {
+        let send_time = send_interval * i;
+        if send_time >= Duration::from_secs(10) {
+            break;
+        }
+        i += 1;
+        sequence_number = sequence_number.wrapping_add(1);
+
+        let capacity = if send_time < STEP_DOWN_AT {
+            2.0 * SEND_BITRATE
+        } else {
+            CAPACITY_AFTER_STEP_DOWN
+        };
+        let departure = link_free_at.max(send_time);
+        if departure - send_time > MAX_QUEUING_DELAY {
+            continue;
+        }
+        link_free_at = departure + Duration::from_secs_f64(PACKET_SIZE as f64 * 8.0 / capacity);
+        let arrival = link_free_at + PROPAGATION_DELAY;
+
+        let now = mt.now().duration_since(start).unwrap_or_default();
+        advance(&mt, arrival.saturating_sub(now)).await;
+
+        stream
+            .receive_rtp(rtp_packet(
+                sequence_number,
+                SENDER_CLOCK_OFFSET + send_time,
+                PACKET_SIZE - 12 - 8,
+            )?)
+            .await;
+        stream.read_rtp().await;
+
+        if let Some(bitrate) = stream
+            .last_written_rtcp()
+            .await
+            .and_then(|pkts| remb_bitrate(&pkts))
+        {
+            rembs.push((arrival, bitrate));
+        }
+    }
+
+    let before: Vec<u64> = rembs
+        .iter()
+        .filter(|(t, _)| *t > Duration::from_secs(2) && *t < STEP_DOWN_AT)
+        .map(|(_, b)| *b)
+        .collect();
+    assert!(!before.is_empty());
+    assert!(
+        before.iter().all(|b| *b as f64 >= SEND_BITRATE),
+        "estimates before the step down: {:?}",
+        before
+    );
+
+    // Within 3 seconds the estimate settles close to the new capacity
+    let after: Vec<u64> = rembs
+        .iter()
+        .filter(|(t, _)| *t > STEP_DOWN_AT + Duration::from_secs(3))
+        .map(|(_, b)| *b)
+        .collect();
+    assert!(!after.is_empty());
+    assert!(
+        after.iter().all(
+            |b| (0.5 * CAPACITY_AFTER_STEP_DOWN..=1.2 * CAPACITY_AFTER_STEP_DOWN)
+                .contains(&(*b as f64))
+        ),
+        "estimates after the step down: {:?}",
+        after
+    );
+
+    stream.close().await?;
+
+    Ok(())
+}
+
+/// Token bucket policing a vnet link, whose rate drops from 4Mbps to 1Mbps after `step_down_at`
+struct Policer {
+    start: tokio::time::Instant,
+    step_down_at: Duration,
+    tokens: f64,
+    last: Duration,
+}
+
+impl Policer {
+    const BUCKET_SIZE: f64 = 12_500.0;
+
+    fn admit(&mut self, size: usize) -> bool {
+        let now = self.start.elapsed();
+        let capacity = if now < self.step_down_at {
+            4_000_000.0
+        } else {
+            1_000_000.0
+        };
+        self.tokens =
+            (self.tokens + (now - self.last).as_secs_f64() * capacity / 8.0).min(Self::BUCKET_SIZE);
+        self.last = now;
+
+        if self.tokens < size as f64 {
+            return false;
+        }
+        self.tokens -= size as f64;
+        true
+    }
+}
+
+/// A sender keeps sending 2Mbps of RTP over a virtual network whose router polices the link to
+/// 4Mbps, then 1Mbps after 5 seconds. The receiver reads the packets off its UDP socket.
+#[tokio::test(start_paused = true)]
+async fn test_remb_receiver_tracks_bandwidth_step_down_over_vnet() -> Result<()> {
+    const PACKET_SIZE: usize = 1250;
+    const SEND_BITRATE: f64 = 2_000_000.0;
+    const STEP_DOWN_AT: Duration = Duration::from_secs(5);
+    const CAPACITY_AFTER_STEP_DOWN: f64 = 1_000_000.0;
+
+    let start = tokio::time::Instant::now();
+
+    let wan = Arc::new(Mutex::new(Router::new(RouterConfig {
+        cidr: "1.2.3.0/24".to_owned(),
+        ..Default::default()
+    })?));
+    let policer = std::sync::Mutex::new(Policer {
+        start,
+        step_down_at: STEP_DOWN_AT,
+        tokens: Policer::BUCKET_SIZE,
+        last: Duration::ZERO,
+    });
+    {
+        let w = wan.lock().await;
+        w.add_chunk_filter(Box::new(move |c: &(dyn Chunk + Send + Sync)| {
+            policer.lock().unwrap().admit(c.user_data().len())
+        }))
+        .await;
+    }
+
+    let mut conns = vec![];
+    for ip in ["1.2.3.4", "1.2.3.5"] {
+        let net = Net::new(Some(NetConfig {
+            static_ips: vec![ip.to_owned()],
+            ..Default::default()
+        }));
+        let nic = net.get_nic()?;
+        {
+            let mut w = wan.lock().await;
+            w.add_net(Arc::clone(&nic)).await?;
+        }
+        {
+            let n = nic.lock().await;
+            n.set_router(Arc::clone(&wan)).await?;
+        }
+        let addr: SocketAddr = format!("{}:5000", ip).parse().unwrap();
+        conns.push(net.bind(addr).await?);
+    }
+    let (sender, receiver) = (Arc::clone(&conns[0]), Arc::clone(&conns[1]));
+    {
+        let mut w = wan.lock().await;
+        w.start().await?;
+    }
+
+    let icpr = Receiver::builder()
+        .with_interval(Duration::from_millis(500))
+        .build("")?;
+    let stream = MockStream::new(&remb_stream_info(1), icpr).await;
+
+    let reading = {
+        let stream = Arc::clone(&stream);
+        let receiver = Arc::clone(&receiver);
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 1500];
+            while let Ok((n, _)) = receiver.recv_from(&mut buf).await {
+                let mut b = &buf[..n];
+                if let Ok(pkt) = rtp::packet::Packet::unmarshal(&mut b) {
+                    stream.receive_rtp(pkt).await;
+                    stream.read_rtp().await;
+                }
+            }
+        })
+    };
+    let rembs = Arc::new(Mutex::new(vec![]));
+    let collecting = {
+        let stream = Arc::clone(&stream);
+        let rembs = Arc::clone(&rembs);
+        tokio::spawn(async move {
+            while let Some(pkts) = stream.written_rtcp().await {
+                if let Some(bitrate) = remb_bitrate(&pkts) {
+                    rembs.lock().await.push((start.elapsed(), bitrate));
+                }
+            }
+        })
+    };
+
+    let send_interval = Duration::from_secs_f64(PACKET_SIZE as f64 * 8.0 / SEND_BITRATE);
+    let receiver_addr = receiver.local_addr()?;
+    let mut i = 0;
+    loop {
+        let send_time = send_interval * i;
+        if send_time >= Duration::from_secs(10) {
+            break;
+        }
+        tokio::time::sleep_until(start + send_time).await;
+
+        let pkt = rtp_packet(i as u16, send_time, PACKET_SIZE - 12 - 8)?;
+        sender.send_to(&pkt.marshal()?, receiver_addr).await?;
+        i += 1;
+    }
+
+    let rembs = rembs.lock().await.clone();
+    let before: Vec<u64> = rembs
+        .iter()
+        .filter(|(t, _)| *t > Duration::from_secs(2) && *t < STEP_DOWN_AT)
+        .map(|(_, b)| *b)
+        .collect();
+    assert!(!before.is_empty());
+    assert!(
+        before.iter().all(|b| *b as f64 >= SEND_BITRATE),
+        "estimates before the step down: {:?}",
+        before
+    );
+
+    // Within 3 seconds the estimate settles close to the new capacity
+    let after: Vec<u64> = rembs
+        .iter()
+        .filter(|(t, _)| *t > STEP_DOWN_AT + Duration::from_secs(3))
+        .map(|(_, b)| *b)
+        .collect();
+    assert!(!after.is_empty());
+    assert!(
+        after.iter().all(
+            |b| (0.5 * CAPACITY_AFTER_STEP_DOWN..=1.2 * CAPACITY_AFTER_STEP_DOWN)
+                .contains(&(*b as f64))
+        ),
+        "estimates after the step down: {:?}",
+        after
+    );
+
+    collecting.abort();
+    receiver.close().await?;
+    let _ = reading.await;
+    sender.close().await?;
+    {
+        let mut w = wan.lock().await;
+        w.stop().await?;
+    }
+    stream.close().await?;
+
+    Ok(())
+}
+
+#[test]
+fn test_abs_send_time_unwrapper() {
+    let mut unwrapper = AbsSendTimeUnwrapper::default();
+
+    let cycle = Duration::from_secs(64);
+    assert_eq!(Duration::from_secs(63), unwrapper.unwrap(63 << 18));
+    // Wraps around
+    assert_eq!(cycle + Duration::from_secs(1), unwrapper.unwrap(1 << 18));
+    // Reordered from before the wrap
+    assert_eq!(Duration::from_secs(63), unwrapper.unwrap(63 << 18));
+    assert_eq!(cycle + Duration::from_secs(2), unwrapper.unwrap(2 << 18));
+}
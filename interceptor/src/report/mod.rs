@@ -2,12 +2,14 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use tokio::sync::{mpsc, Mutex};
-use waitgroup::WaitGroup;
+use util::sync::TaskGroup;
 
 pub mod receiver;
 pub mod sender;
 
 use crate::error::Result;
+use crate::rtcp_scheduler::{RtcpScheduler, RtcpSchedulerConfig};
+use crate::time_source::{system_time_source, SharedTimeSource};
 use crate::{Interceptor, InterceptorBuilder};
 use receiver::{ReceiverReport, ReceiverReportInternal};
 use sender::{SenderReport, SenderReportInternal};
@@ -19,7 +21,8 @@ type FnTimeGen = Arc<dyn Fn() -> SystemTime + Sync + 'static + Send>;
 pub struct ReportBuilder {
     is_rr: bool,
     interval: Option<Duration>,
-    now: Option<FnTimeGen>,
+    now: Option<SharedTimeSource>,
+    scheduler_config: Option<RtcpSchedulerConfig>,
 }
 
 impl ReportBuilder {
@@ -29,12 +32,29 @@ impl ReportBuilder {
         self
     }
 
+    /// with_rtcp_scheduler_config makes the interceptor derive the report interval from the session
+    /// bandwidth and the number of streams following RFC 3550, instead of using a fixed interval.
+    pub fn with_rtcp_scheduler_config(mut self, config: RtcpSchedulerConfig) -> ReportBuilder {
+        self.scheduler_config = Some(config);
+        self
+    }
+
     /// with_now_fn sets an alternative for the time.Now function.
     pub fn with_now_fn(mut self, now: FnTimeGen) -> ReportBuilder {
-        self.now = Some(now);
+        self.now = Some(Arc::new(move || now()));
         self
     }
 
+    /// with_time_source sets the clock used to stamp and process reports.
+    pub fn with_time_source(mut self, time_source: SharedTimeSource) -> ReportBuilder {
+        self.now = Some(time_source);
+        self
+    }
+
+    fn time_source(&self) -> SharedTimeSource {
+        self.now.clone().unwrap_or_else(system_time_source)
+    }
+
     fn build_rr(&self) -> ReceiverReport {
         let (close_tx, close_rx) = mpsc::channel(1);
         ReceiverReport {
@@ -44,12 +64,13 @@ impl ReportBuilder {
                 } else {
                     Duration::from_secs(1)
                 },
-                now: self.now.clone(),
+                now: self.time_source(),
+                scheduler: self.scheduler_config.map(RtcpScheduler::new),
                 streams: Mutex::new(HashMap::new()),
                 close_rx: Mutex::new(Some(close_rx)),
             }),
 
-            wg: Mutex::new(Some(WaitGroup::new())),
+            tasks: TaskGroup::new(),
             close_tx: Mutex::new(Some(close_tx)),
         }
     }
@@ -63,17 +84,30 @@ impl ReportBuilder {
                 } else {
                     Duration::from_secs(1)
                 },
-                now: self.now.clone(),
+                now: self.time_source(),
+                scheduler: self.scheduler_config.map(RtcpScheduler::new),
                 streams: Mutex::new(HashMap::new()),
                 close_rx: Mutex::new(Some(close_rx)),
             }),
 
-            wg: Mutex::new(Some(WaitGroup::new())),
+            tasks: TaskGroup::new(),
             close_tx: Mutex::new(Some(close_tx)),
         }
     }
 }
 
+/// Waits until the next report is due, using the scheduler if there is one.
+async fn wait_next_report(ticker: &mut tokio::time::Interval, scheduler: Option<&RtcpScheduler>) {
+    if let Some(scheduler) = scheduler {
+        tokio::time::sleep(scheduler.next_interval()).await;
+    } else {
+        ticker.tick().await;
+    }
+}
+
+/// Size of the UDP and IPv4 headers, accounted in the average RTCP packet size.
+const RTCP_LOWER_LAYER_OVERHEAD: usize = 28;
+
 impl InterceptorBuilder for ReportBuilder {
     fn build(&self, _id: &str) -> Result<Arc<dyn Interceptor + Send + Sync>> {
         if self.is_rr {
@@ -82,4 +116,8 @@ impl InterceptorBuilder for ReportBuilder {
             Ok(Arc::new(self.build_sr()))
         }
     }
+
+    fn set_default_rtcp_scheduler_config(&mut self, config: RtcpSchedulerConfig) {
+        self.scheduler_config.get_or_insert(config);
+    }
 }
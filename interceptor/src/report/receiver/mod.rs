@@ -8,13 +8,15 @@ use crate::*;
 use receiver_stream::ReceiverStream;
 
 use std::collections::HashMap;
-use std::time::{Duration, SystemTime};
+use std::time::Duration;
 use tokio::sync::{mpsc, Mutex};
-use waitgroup::WaitGroup;
+use util::sync::TaskGroup;
+use util::MarshalSize;
 
 pub(crate) struct ReceiverReportInternal {
     pub(crate) interval: Duration,
-    pub(crate) now: Option<FnTimeGen>,
+    pub(crate) now: SharedTimeSource,
+    pub(crate) scheduler: Option<RtcpScheduler>,
     pub(crate) streams: Mutex<HashMap<u32, Arc<ReceiverStream>>>,
     pub(crate) close_rx: Mutex<Option<mpsc::Receiver<()>>>,
 }
@@ -32,11 +34,7 @@ impl RTCPReader for ReceiverReportRtcpReader {
         let mut b = &buf[..n];
         let pkts = rtcp::packet::unmarshal(&mut b)?;
 
-        let now = if let Some(f) = &self.internal.now {
-            f()
-        } else {
-            SystemTime::now()
-        };
+        let now = self.internal.now.now();
 
         for p in &pkts {
             if let Some(sr) = p
@@ -61,7 +59,7 @@ impl RTCPReader for ReceiverReportRtcpReader {
 pub struct ReceiverReport {
     pub(crate) internal: Arc<ReceiverReportInternal>,
 
-    pub(crate) wg: Mutex<Option<WaitGroup>>,
+    pub(crate) tasks: TaskGroup,
     pub(crate) close_tx: Mutex<Option<mpsc::Sender<()>>>,
 }
 
@@ -95,20 +93,22 @@ impl ReceiverReport {
 
         loop {
             tokio::select! {
-                _ = ticker.tick() =>{
+                _ = wait_next_report(&mut ticker, internal.scheduler.as_ref()) =>{
                     // TODO(cancel safety): This branch isn't cancel safe
 
-                    let now = if let Some(f) = &internal.now {
-                        f()
-                    } else {
-                        SystemTime::now()
-                    };
+                    let now = internal.now.now();
                     let streams:Vec<Arc<ReceiverStream>> = {
                         let m = internal.streams.lock().await;
                         m.values().cloned().collect()
                     };
+                    if let Some(scheduler) = &internal.scheduler {
+                        scheduler.set_participants(streams.len() + 1, streams.len(), false);
+                    }
                     for stream in streams {
                         let pkt = stream.generate_report(now);
+                        if let Some(scheduler) = &internal.scheduler {
+                            scheduler.on_report_sent(pkt.marshal_size() + RTCP_LOWER_LAYER_OVERHEAD);
+                        }
 
                         let a = Attributes::new();
                         if let Err(err) = rtcp_writer.write(&[Box::new(pkt)], &a).await{
@@ -148,14 +148,9 @@ impl Interceptor for ReceiverReport {
             return writer;
         }
 
-        let mut w = {
-            let wait_group = self.wg.lock().await;
-            wait_group.as_ref().map(|wg| wg.worker())
-        };
         let writer2 = Arc::clone(&writer);
         let internal = Arc::clone(&self.internal);
-        tokio::spawn(async move {
-            let _d = w.take();
+        self.tasks.spawn(async move {
             if let Err(err) = ReceiverReport::run(writer2, internal).await {
                 log::warn!("bind_rtcp_writer ReceiverReport::run got error: {}", err);
             }
@@ -211,12 +206,7 @@ impl Interceptor for ReceiverReport {
             close_tx.take();
         }
 
-        {
-            let mut wait_group = self.wg.lock().await;
-            if let Some(wg) = wait_group.take() {
-                wg.wait().await;
-            }
-        }
+        self.tasks.close(TASKS_CLOSE_TIMEOUT).await;
 
         Ok(())
     }
@@ -2,6 +2,7 @@ use super::*;
 use crate::{Attributes, RTPReader};
 
 use async_trait::async_trait;
+use rtp::seqnum::{ExtendedSeqnum, SeqnumWindow};
 use std::time::SystemTime;
 use util::sync::Mutex;
 use util::Unmarshal;
@@ -11,11 +12,10 @@ struct ReceiverStreamInternal {
     receiver_ssrc: u32,
     clock_rate: f64,
 
-    packets: Vec<u64>,
-    started: bool,
-    seq_num_cycles: u16,
-    last_seq_num: i32,
-    last_report_seq_num: i32,
+    seqnum: ExtendedSeqnum,
+    received: SeqnumWindow,
+    /// the first sequence number the next report covers
+    next_report_seq_num: u64,
     last_rtp_time_rtp: u32,
     last_rtp_time_time: SystemTime,
     jitter: f64,
@@ -25,47 +25,17 @@ struct ReceiverStreamInternal {
 }
 
 impl ReceiverStreamInternal {
-    fn set_received(&mut self, seq: u16) {
-        let pos = (seq as usize) % self.packets.len();
-        self.packets[pos / 64] |= 1 << (pos % 64);
-    }
-
-    fn del_received(&mut self, seq: u16) {
-        let pos = (seq as usize) % self.packets.len();
-        self.packets[pos / 64] &= u64::MAX ^ (1u64 << (pos % 64));
-    }
-
-    fn get_received(&self, seq: u16) -> bool {
-        let pos = (seq as usize) % self.packets.len();
-        (self.packets[pos / 64] & (1 << (pos % 64))) != 0
-    }
-
     fn process_rtp(&mut self, now: SystemTime, pkt: &rtp::packet::Packet) {
-        if !self.started {
-            // first frame
-            self.started = true;
-            self.set_received(pkt.header.sequence_number);
-            self.last_seq_num = pkt.header.sequence_number as i32;
-            self.last_report_seq_num = pkt.header.sequence_number as i32 - 1;
-        } else {
-            // following frames
-            self.set_received(pkt.header.sequence_number);
-
-            let diff = pkt.header.sequence_number as i32 - self.last_seq_num as i32;
-            if !(-0x0FFF..=0).contains(&diff) {
-                // overflow
-                if diff < -0x0FFF {
-                    self.seq_num_cycles += 1;
-                }
-
-                // set missing packets as missing
-                for i in self.last_seq_num + 1..pkt.header.sequence_number as i32 {
-                    self.del_received(i as u16);
-                }
-
-                self.last_seq_num = pkt.header.sequence_number as i32;
+        let started = self.seqnum.highest().is_some();
+        if let Some(seq) = self.seqnum.update(pkt.header.sequence_number) {
+            if !started {
+                // first frame
+                self.next_report_seq_num = seq;
             }
+            self.received.insert(seq);
+        }
 
+        if started {
             // compute jitter
             // https://tools.ietf.org/html/rfc3550#page-39
             let d = now
@@ -87,21 +57,17 @@ impl ReceiverStreamInternal {
     }
 
     fn generate_report(&mut self, now: SystemTime) -> rtcp::receiver_report::ReceiverReport {
-        let total_since_report = (self.last_seq_num - self.last_report_seq_num) as u16;
-        let mut total_lost_since_report = {
-            if self.last_seq_num == self.last_report_seq_num {
-                0
-            } else {
-                let mut ret = 0u32;
-                let mut i = (self.last_report_seq_num + 1) as u16;
-                while i != self.last_seq_num as u16 {
-                    if !self.get_received(i) {
-                        ret += 1;
-                    }
-                    i = i.wrapping_add(1);
-                }
-                ret
-            }
+        let last_seq_num = self.seqnum.highest().unwrap_or_default();
+        let (total_since_report, mut total_lost_since_report) = if self.seqnum.highest().is_some() {
+            let lost = (self.next_report_seq_num..last_seq_num)
+                .filter(|&seq| !self.received.contains(seq))
+                .count();
+            (
+                (last_seq_num + 1 - self.next_report_seq_num) as u16,
+                lost as u32,
+            )
+        } else {
+            (0, 0)
         };
 
         self.total_lost += total_lost_since_report;
@@ -118,8 +84,7 @@ impl ReceiverStreamInternal {
             ssrc: self.receiver_ssrc,
             reports: vec![rtcp::reception_report::ReceptionReport {
                 ssrc: self.ssrc,
-                last_sequence_number: (self.seq_num_cycles as u32) << 16
-                    | (self.last_seq_num as u32),
+                last_sequence_number: last_seq_num as u32,
                 last_sender_report: self.last_sender_report,
                 fraction_lost: ((total_lost_since_report * 256) as f64 / total_since_report as f64)
                     as u8,
@@ -139,7 +104,7 @@ impl ReceiverStreamInternal {
             ..Default::default()
         };
 
-        self.last_report_seq_num = self.last_seq_num;
+        self.next_report_seq_num = last_seq_num + 1;
 
         r
     }
@@ -147,7 +112,7 @@ impl ReceiverStreamInternal {
 
 pub(crate) struct ReceiverStream {
     parent_rtp_reader: Arc<dyn RTPReader + Send + Sync>,
-    now: Option<FnTimeGen>,
+    now: SharedTimeSource,
 
     internal: Mutex<ReceiverStreamInternal>,
 }
@@ -157,7 +122,7 @@ impl ReceiverStream {
         ssrc: u32,
         clock_rate: u32,
         reader: Arc<dyn RTPReader + Send + Sync>,
-        now: Option<FnTimeGen>,
+        now: SharedTimeSource,
     ) -> Self {
         let receiver_ssrc = rand::random::<u32>();
         ReceiverStream {
@@ -169,11 +134,9 @@ impl ReceiverStream {
                 receiver_ssrc,
                 clock_rate: clock_rate as f64,
 
-                packets: vec![0u64; 128],
-                started: false,
-                seq_num_cycles: 0,
-                last_seq_num: 0,
-                last_report_seq_num: 0,
+                seqnum: ExtendedSeqnum::new(),
+                received: SeqnumWindow::new(128 * 64),
+                next_report_seq_num: 0,
                 last_rtp_time_rtp: 0,
                 last_rtp_time_time: SystemTime::UNIX_EPOCH,
                 jitter: 0.0,
@@ -213,11 +176,7 @@ impl RTPReader for ReceiverStream {
 
         let mut b = &buf[..n];
         let pkt = rtp::packet::Packet::unmarshal(&mut b)?;
-        let now = if let Some(f) = &self.now {
-            f()
-        } else {
-            SystemTime::now()
-        };
+        let now = self.now.now();
         self.process_rtp(now, &pkt);
 
         Ok((n, attr))
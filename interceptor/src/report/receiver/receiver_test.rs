@@ -1,6 +1,7 @@
 use super::*;
 use crate::mock::mock_stream::MockStream;
 use crate::mock::mock_time::MockTime;
+use crate::registry::Registry;
 //use bytes::Bytes;
 use chrono::prelude::*;
 use rtp::extension::abs_send_time_extension::unix2ntp;
@@ -763,3 +764,80 @@ async fn test_receiver_interceptor_delay() -> Result<()> {
     stream.close().await?;
     Ok(())
 }
+
+#[tokio::test(start_paused = true)]
+async fn test_receiver_interceptor_rtcp_scheduler() -> Result<()> {
+    let icpr: Arc<dyn Interceptor + Send + Sync> = ReceiverReport::builder()
+        .with_interval(Duration::from_millis(50))
+        .with_rtcp_scheduler_config(RtcpSchedulerConfig {
+            min_interval: Duration::from_secs(2),
+            ..Default::default()
+        })
+        .build("")?;
+
+    let stream = MockStream::new(
+        &StreamInfo {
+            ssrc: 123456,
+            clock_rate: 90000,
+            ..Default::default()
+        },
+        icpr,
+    )
+    .await;
+
+    // The first report is sent after half the minimum interval, randomized by
+    // [0.5, 1.5] / 1.21828, so between ~410ms and ~1231ms. The fixed 50ms interval is not used.
+    // Let the report loop arm its timer before moving the clock.
+    tokio::task::yield_now().await;
+    tokio::time::advance(Duration::from_millis(400)).await;
+    tokio::task::yield_now().await;
+    assert!(stream.last_written_rtcp().await.is_none());
+
+    tokio::time::advance(Duration::from_millis(900)).await;
+    tokio::task::yield_now().await;
+    let pkts = stream.last_written_rtcp().await.unwrap();
+    assert!(pkts[0]
+        .as_any()
+        .downcast_ref::<rtcp::receiver_report::ReceiverReport>()
+        .is_some());
+
+    stream.close().await?;
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_receiver_interceptor_registry_rtcp_scheduler() -> Result<()> {
+    let mut registry = Registry::new();
+    registry.add(Box::new(
+        ReceiverReport::builder().with_interval(Duration::from_millis(50)),
+    ));
+    registry.set_rtcp_scheduler_config(RtcpSchedulerConfig {
+        min_interval: Duration::from_secs(2),
+        ..Default::default()
+    });
+    let icpr = registry.build("")?;
+
+    let stream = MockStream::new(
+        &StreamInfo {
+            ssrc: 123456,
+            clock_rate: 90000,
+            ..Default::default()
+        },
+        icpr,
+    )
+    .await;
+
+    // The config of the registry replaces the fixed 50ms interval, the first report is due
+    // between ~410ms and ~1231ms.
+    tokio::task::yield_now().await;
+    tokio::time::advance(Duration::from_millis(400)).await;
+    tokio::task::yield_now().await;
+    assert!(stream.last_written_rtcp().await.is_none());
+
+    tokio::time::advance(Duration::from_millis(900)).await;
+    tokio::task::yield_now().await;
+    assert!(stream.last_written_rtcp().await.is_some());
+
+    stream.close().await?;
+    Ok(())
+}
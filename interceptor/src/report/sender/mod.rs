@@ -8,13 +8,15 @@ use crate::*;
 use sender_stream::SenderStream;
 
 use std::collections::HashMap;
-use std::time::{Duration, SystemTime};
+use std::time::Duration;
 use tokio::sync::{mpsc, Mutex};
-use waitgroup::WaitGroup;
+use util::sync::TaskGroup;
+use util::MarshalSize;
 
 pub(crate) struct SenderReportInternal {
     pub(crate) interval: Duration,
-    pub(crate) now: Option<FnTimeGen>,
+    pub(crate) now: SharedTimeSource,
+    pub(crate) scheduler: Option<RtcpScheduler>,
     pub(crate) streams: Mutex<HashMap<u32, Arc<SenderStream>>>,
     pub(crate) close_rx: Mutex<Option<mpsc::Receiver<()>>>,
 }
@@ -23,7 +25,7 @@ pub(crate) struct SenderReportInternal {
 pub struct SenderReport {
     pub(crate) internal: Arc<SenderReportInternal>,
 
-    pub(crate) wg: Mutex<Option<WaitGroup>>,
+    pub(crate) tasks: TaskGroup,
     pub(crate) close_tx: Mutex<Option<mpsc::Sender<()>>>,
 }
 
@@ -57,22 +59,30 @@ impl SenderReport {
 
         loop {
             tokio::select! {
-                _ = ticker.tick() =>{
+                _ = wait_next_report(&mut ticker, internal.scheduler.as_ref()) =>{
                     // TODO(cancel safety): This branch isn't cancel safe
-                    let now = if let Some(f) = &internal.now {
-                        f()
-                    } else {
-                        SystemTime::now()
-                    };
+                    let now = internal.now.now();
                     let streams:Vec<Arc<SenderStream>> = {
                         let m = internal.streams.lock().await;
                         m.values().cloned().collect()
                     };
+                    if let Some(scheduler) = &internal.scheduler {
+                        scheduler.set_participants(streams.len() + 1, streams.len(), !streams.is_empty());
+                    }
                     for stream in streams {
                         let pkt = stream.generate_report(now).await;
+                        let mut size = pkt.marshal_size();
+                        let mut pkts: Vec<Box<dyn rtcp::packet::Packet + Send + Sync>> = vec![Box::new(pkt)];
+                        if let Some(sdes) = stream.source_description() {
+                            size += sdes.marshal_size();
+                            pkts.push(Box::new(sdes.clone()));
+                        }
+                        if let Some(scheduler) = &internal.scheduler {
+                            scheduler.on_report_sent(size + RTCP_LOWER_LAYER_OVERHEAD);
+                        }
 
                         let a = Attributes::new();
-                        if let Err(err) = rtcp_writer.write(&[Box::new(pkt)], &a).await{
+                        if let Err(err) = rtcp_writer.write(&pkts, &a).await{
                             log::warn!("failed sending: {}", err);
                         }
                     }
@@ -106,14 +116,9 @@ impl Interceptor for SenderReport {
             return writer;
         }
 
-        let mut w = {
-            let wait_group = self.wg.lock().await;
-            wait_group.as_ref().map(|wg| wg.worker())
-        };
         let writer2 = Arc::clone(&writer);
         let internal = Arc::clone(&self.internal);
-        tokio::spawn(async move {
-            let _d = w.take();
+        self.tasks.spawn(async move {
             if let Err(err) = SenderReport::run(writer2, internal).await {
                 log::warn!("bind_rtcp_writer Generator::run got error: {}", err);
             }
@@ -129,12 +134,7 @@ impl Interceptor for SenderReport {
         info: &StreamInfo,
         writer: Arc<dyn RTPWriter + Send + Sync>,
     ) -> Arc<dyn RTPWriter + Send + Sync> {
-        let stream = Arc::new(SenderStream::new(
-            info.ssrc,
-            info.clock_rate,
-            writer,
-            self.internal.now.clone(),
-        ));
+        let stream = Arc::new(SenderStream::new(info, writer, self.internal.now.clone()));
         {
             let mut streams = self.internal.streams.lock().await;
             streams.insert(info.ssrc, Arc::clone(&stream));
@@ -169,12 +169,7 @@ impl Interceptor for SenderReport {
             close_tx.take();
         }
 
-        {
-            let mut wait_group = self.wg.lock().await;
-            if let Some(wg) = wait_group.take() {
-                wg.wait().await;
-            }
-        }
+        self.tasks.close(TASKS_CLOSE_TIMEOUT).await;
 
         Ok(())
     }
@@ -2,6 +2,10 @@ use super::*;
 use crate::{Attributes, RTPWriter};
 
 use async_trait::async_trait;
+use bytes::Bytes;
+use rtcp::source_description::{
+    SdesType, SourceDescription, SourceDescriptionChunk, SourceDescriptionItem,
+};
 use rtp::extension::abs_send_time_extension::unix2ntp;
 use std::convert::TryInto;
 use std::sync::Arc;
@@ -47,25 +51,26 @@ impl SenderStreamInternal {
 
 pub(crate) struct SenderStream {
     next_rtp_writer: Arc<dyn RTPWriter + Send + Sync>,
-    now: Option<FnTimeGen>,
+    now: SharedTimeSource,
+    source_description: Option<SourceDescription>,
 
     internal: Mutex<SenderStreamInternal>,
 }
 
 impl SenderStream {
     pub(crate) fn new(
-        ssrc: u32,
-        clock_rate: u32,
+        info: &StreamInfo,
         writer: Arc<dyn RTPWriter + Send + Sync>,
-        now: Option<FnTimeGen>,
+        now: SharedTimeSource,
     ) -> Self {
         SenderStream {
             next_rtp_writer: writer,
             now,
+            source_description: source_description(info),
 
             internal: Mutex::new(SenderStreamInternal {
-                ssrc,
-                clock_rate: clock_rate as f64,
+                ssrc: info.ssrc,
+                clock_rate: info.clock_rate as f64,
                 last_rtp_time_rtp: 0,
                 last_rtp_time_time: SystemTime::UNIX_EPOCH,
                 counters: Default::default(),
@@ -85,6 +90,39 @@ impl SenderStream {
         let mut internal = self.internal.lock().await;
         internal.generate_report(now)
     }
+
+    /// source_description returns the SDES sent along with the reports of the stream, if it has
+    /// a MID or RID to tell.
+    pub(crate) fn source_description(&self) -> Option<&SourceDescription> {
+        self.source_description.as_ref()
+    }
+}
+
+/// source_description builds the SDES carrying the MID and RtpStreamId of a stream, so a
+/// receiver can associate its SSRC when RTP packets arrive without header extensions.
+fn source_description(info: &StreamInfo) -> Option<SourceDescription> {
+    let mut items = vec![];
+    for (sdes_type, text) in [
+        (SdesType::SdesMid, &info.mid),
+        (SdesType::SdesRtpStreamId, &info.rid),
+    ] {
+        if !text.is_empty() {
+            items.push(SourceDescriptionItem {
+                sdes_type,
+                text: Bytes::from(text.clone()),
+            });
+        }
+    }
+    if items.is_empty() {
+        return None;
+    }
+
+    Some(SourceDescription {
+        chunks: vec![SourceDescriptionChunk {
+            source: info.ssrc,
+            items,
+        }],
+    })
 }
 
 /// RTPWriter is used by Interceptor.bind_local_stream.
@@ -92,11 +130,7 @@ impl SenderStream {
 impl RTPWriter for SenderStream {
     /// write a rtp packet
     async fn write(&self, pkt: &rtp::packet::Packet, a: &Attributes) -> Result<usize> {
-        let now = if let Some(f) = &self.now {
-            f()
-        } else {
-            SystemTime::now()
-        };
+        let now = self.now.now();
         self.process_rtp(now, pkt).await;
 
         self.next_rtp_writer.write(pkt, a).await
@@ -257,3 +257,55 @@ async fn test_stream_octet_counter_saturates_u32_from_usize() -> Result<()> {
     assert_eq!(0xffffffff_u32, counters.octet_count());
     Ok(())
 }
+
+#[tokio::test]
+async fn test_sender_interceptor_source_description() -> Result<()> {
+    let icpr: Arc<dyn Interceptor + Send + Sync> = SenderReport::builder()
+        .with_interval(Duration::from_millis(50))
+        .build("")?;
+
+    let stream = MockStream::new(
+        &StreamInfo {
+            ssrc: 123456,
+            clock_rate: 90000,
+            mid: "0".to_owned(),
+            rid: "hi".to_owned(),
+            ..Default::default()
+        },
+        icpr,
+    )
+    .await;
+
+    let pkts = stream.written_rtcp().await.unwrap();
+    assert_eq!(pkts.len(), 2, "the SDES should follow the report");
+    assert!(pkts[0]
+        .as_any()
+        .downcast_ref::<rtcp::sender_report::SenderReport>()
+        .is_some());
+    let sdes = pkts[1]
+        .as_any()
+        .downcast_ref::<rtcp::source_description::SourceDescription>()
+        .expect("should be an SDES");
+    assert_eq!(
+        sdes,
+        &rtcp::source_description::SourceDescription {
+            chunks: vec![rtcp::source_description::SourceDescriptionChunk {
+                source: 123456,
+                items: vec![
+                    rtcp::source_description::SourceDescriptionItem {
+                        sdes_type: rtcp::source_description::SdesType::SdesMid,
+                        text: Bytes::from_static(b"0"),
+                    },
+                    rtcp::source_description::SourceDescriptionItem {
+                        sdes_type: rtcp::source_description::SdesType::SdesRtpStreamId,
+                        text: Bytes::from_static(b"hi"),
+                    },
+                ],
+            }],
+        }
+    );
+
+    stream.close().await?;
+
+    Ok(())
+}
@@ -0,0 +1,285 @@
+use std::time::Duration;
+
+use util::sync::Mutex;
+
+/// Fraction of the session bandwidth RTCP may use, see RFC 3550 section 6.2.
+pub const DEFAULT_RTCP_BANDWIDTH_FRACTION: f64 = 0.05;
+
+/// Minimum interval between regular RTCP reports, see RFC 3550 section 6.2.
+pub const DEFAULT_RTCP_MIN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Compensation for the "timer reconsideration" algorithm converging to a value below the
+/// intended average, e - 3/2. See RFC 3550 appendix A.7.
+const COMPENSATION: f64 = std::f64::consts::E - 1.5;
+
+/// Size in bytes of a compound RTCP packet (SR/RR + SDES) plus UDP/IP overhead used before any
+/// report has been sent.
+const INITIAL_AVG_RTCP_SIZE: f64 = 128.0;
+
+/// RtcpSchedulerConfig configures the RTCP transmission interval computation.
+#[derive(Debug, Clone, Copy)]
+pub struct RtcpSchedulerConfig {
+    /// Session bandwidth in bits per second, e.g. from the `b=AS` SDP attribute.
+    pub session_bandwidth: u32,
+    /// Share of the session bandwidth reserved for RTCP.
+    pub rtcp_fraction: f64,
+    /// Minimum interval between regular reports once the session has started. Half of it is
+    /// used before the first report has been sent. RFC 4585 (AVPF) sessions typically set this to
+    /// zero and rely on `trr_int` instead.
+    pub min_interval: Duration,
+    /// Minimum interval between regular reports, as negotiated by the `trr-int` parameter of
+    /// RFC 4585. Applied after the bandwidth based computation.
+    pub trr_int: Duration,
+}
+
+impl Default for RtcpSchedulerConfig {
+    fn default() -> Self {
+        RtcpSchedulerConfig {
+            session_bandwidth: 0,
+            rtcp_fraction: DEFAULT_RTCP_BANDWIDTH_FRACTION,
+            min_interval: DEFAULT_RTCP_MIN_INTERVAL,
+            trr_int: Duration::from_secs(0),
+        }
+    }
+}
+
+impl RtcpSchedulerConfig {
+    /// with_session_bandwidth returns a config using the RFC 3550 defaults for the given session
+    /// bandwidth in bits per second.
+    pub fn with_session_bandwidth(session_bandwidth: u32) -> Self {
+        RtcpSchedulerConfig {
+            session_bandwidth,
+            ..Default::default()
+        }
+    }
+}
+
+struct RtcpSchedulerInternal {
+    members: usize,
+    senders: usize,
+    we_sent: bool,
+    initial: bool,
+    avg_rtcp_size: f64,
+    allow_early: bool,
+}
+
+/// RtcpScheduler computes when RTCP packets may be sent following the timing rules of
+/// RFC 3550 section 6.3 and the early feedback rules of RFC 4585 section 3.5.
+pub struct RtcpScheduler {
+    config: RtcpSchedulerConfig,
+    internal: Mutex<RtcpSchedulerInternal>,
+}
+
+impl RtcpScheduler {
+    pub fn new(config: RtcpSchedulerConfig) -> Self {
+        RtcpScheduler {
+            config,
+            internal: Mutex::new(RtcpSchedulerInternal {
+                members: 2,
+                senders: 0,
+                we_sent: false,
+                initial: true,
+                avg_rtcp_size: INITIAL_AVG_RTCP_SIZE,
+                allow_early: true,
+            }),
+        }
+    }
+
+    /// config returns the configuration of the scheduler.
+    pub fn config(&self) -> &RtcpSchedulerConfig {
+        &self.config
+    }
+
+    /// set_participants updates the number of session members and of those how many are
+    /// senders, `we_sent` tells whether we sent RTP since the last report.
+    pub fn set_participants(&self, members: usize, senders: usize, we_sent: bool) {
+        let mut internal = self.internal.lock();
+        internal.members = members.max(1);
+        internal.senders = senders.min(internal.members);
+        internal.we_sent = we_sent;
+    }
+
+    /// on_report_sent must be called for every regular compound RTCP packet sent, `size` being its
+    /// size in bytes including lower layer headers. It re-arms the early feedback budget.
+    pub fn on_report_sent(&self, size: usize) {
+        let mut internal = self.internal.lock();
+        internal.avg_rtcp_size = size as f64 / 16.0 + internal.avg_rtcp_size * 15.0 / 16.0;
+        internal.initial = false;
+        internal.allow_early = true;
+    }
+
+    /// on_regular_interval must be called when a regular RTCP interval, see `regular_interval`,
+    /// elapsed without a report being sent, e.g. because `trr_int` suppressed it or because the
+    /// caller only sends feedback. It re-arms the early feedback budget like `on_report_sent`.
+    pub fn on_regular_interval(&self) {
+        let mut internal = self.internal.lock();
+        internal.allow_early = true;
+    }
+
+    /// interval returns the deterministic RTCP interval, before randomization, at which regular
+    /// reports are sent. It is `regular_interval` floored by `trr_int`.
+    pub fn interval(&self) -> Duration {
+        self.regular_interval().max(self.config.trr_int)
+    }
+
+    /// regular_interval returns the deterministic RTCP interval computed by the rules of
+    /// RFC 3550, without the `trr_int` floor. It falls back to `min_interval` when no session
+    /// bandwidth is configured. The early feedback budget is re-armed at this interval, see
+    /// RFC 4585 section 3.5.3.
+    pub fn regular_interval(&self) -> Duration {
+        let internal = self.internal.lock();
+
+        let mut min_interval = self.config.min_interval.as_secs_f64();
+        if internal.initial {
+            min_interval /= 2.0;
+        }
+
+        let mut t = min_interval;
+        let rtcp_bw = self.config.session_bandwidth as f64 * self.config.rtcp_fraction / 8.0;
+        if rtcp_bw > 0.0 {
+            // Dedicate 1/4 of the RTCP bandwidth to senders when they are few, so that their
+            // reports, containing the timing information, come in often enough.
+            let (rtcp_bw, n) = if (internal.senders as f64) <= internal.members as f64 * 0.25 {
+                if internal.we_sent {
+                    (rtcp_bw * 0.25, internal.senders)
+                } else {
+                    (rtcp_bw * 0.75, internal.members - internal.senders)
+                }
+            } else {
+                (rtcp_bw, internal.members)
+            };
+
+            t = t.max(internal.avg_rtcp_size * n.max(1) as f64 / rtcp_bw);
+        }
+
+        Duration::from_secs_f64(t)
+    }
+
+    /// next_interval returns the time to wait before the next regular report, randomized to avoid
+    /// synchronization between participants.
+    pub fn next_interval(&self) -> Duration {
+        let t = self.interval().as_secs_f64();
+        let t = t * (rand::random::<f64>() + 0.5) / COMPENSATION;
+
+        Duration::from_secs_f64(t).max(self.config.trr_int)
+    }
+
+    /// try_early_feedback reports whether an early feedback packet (NACK, PLI, ...) may be sent
+    /// now. At most one early packet is allowed per regular RTCP interval, a successful call
+    /// consumes that budget until it is re-armed by `on_report_sent` or `on_regular_interval`.
+    pub fn try_early_feedback(&self) -> bool {
+        let mut internal = self.internal.lock();
+        let allowed = internal.allow_early;
+        internal.allow_early = false;
+
+        allowed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rtcp_scheduler_without_bandwidth() {
+        let s = RtcpScheduler::new(RtcpSchedulerConfig::default());
+        assert_eq!(s.interval(), Duration::from_millis(2500));
+
+        s.on_report_sent(100);
+        assert_eq!(s.interval(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_rtcp_scheduler_adapts_to_members() {
+        let s = RtcpScheduler::new(RtcpSchedulerConfig {
+            min_interval: Duration::from_secs(0),
+            // 5% of 160 kbps = 1000 bytes/s of RTCP
+            ..RtcpSchedulerConfig::with_session_bandwidth(160_000)
+        });
+        for _ in 0..200 {
+            s.on_report_sent(100);
+        }
+
+        s.set_participants(2, 2, true);
+        let two = s.interval();
+        assert!(
+            (two.as_secs_f64() - 0.2).abs() < 0.01,
+            "unexpected interval {:?}",
+            two
+        );
+
+        s.set_participants(20, 20, true);
+        let twenty = s.interval();
+        assert!(
+            (twenty.as_secs_f64() - 2.0).abs() < 0.1,
+            "unexpected interval {:?}",
+            twenty
+        );
+
+        // A receiver among 100 members where only 2 send shares 75% of the bandwidth with 98
+        // other receivers.
+        s.set_participants(100, 2, false);
+        let receiver = s.interval();
+        assert!(
+            (receiver.as_secs_f64() - 98.0 * 100.0 / 750.0).abs() < 0.5,
+            "unexpected interval {:?}",
+            receiver
+        );
+
+        // While the sender shares 25% with one other sender.
+        s.set_participants(100, 2, true);
+        let sender = s.interval();
+        assert!(sender < receiver);
+    }
+
+    #[test]
+    fn test_rtcp_scheduler_trr_int_floor() {
+        let s = RtcpScheduler::new(RtcpSchedulerConfig {
+            min_interval: Duration::from_secs(0),
+            trr_int: Duration::from_secs(1),
+            ..RtcpSchedulerConfig::with_session_bandwidth(10_000_000)
+        });
+        s.set_participants(2, 2, true);
+
+        assert_eq!(s.interval(), Duration::from_secs(1));
+        for _ in 0..10 {
+            assert!(s.next_interval() >= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn test_rtcp_scheduler_early_feedback_is_rate_bounded() {
+        let s = RtcpScheduler::new(RtcpSchedulerConfig {
+            min_interval: Duration::from_secs(0),
+            trr_int: Duration::from_millis(500),
+            ..RtcpSchedulerConfig::with_session_bandwidth(10_000_000)
+        });
+
+        assert!(s.try_early_feedback());
+        assert!(!s.try_early_feedback());
+        assert!(!s.try_early_feedback());
+
+        s.on_report_sent(100);
+        assert!(s.try_early_feedback());
+        assert!(!s.try_early_feedback());
+    }
+
+    #[test]
+    fn test_rtcp_scheduler_early_feedback_ignores_trr_int() {
+        let s = RtcpScheduler::new(RtcpSchedulerConfig {
+            min_interval: Duration::from_secs(0),
+            trr_int: Duration::from_secs(60),
+            ..RtcpSchedulerConfig::with_session_bandwidth(1_000_000)
+        });
+
+        // Regular reports are held back by trr_int, the early feedback budget isn't.
+        assert_eq!(s.interval(), Duration::from_secs(60));
+        assert!(s.regular_interval() < Duration::from_secs(1));
+
+        for _ in 0..3 {
+            assert!(s.try_early_feedback());
+            assert!(!s.try_early_feedback());
+            s.on_regular_interval();
+        }
+    }
+}
@@ -15,12 +15,16 @@ use rtp::extension::abs_send_time_extension::unix2ntp;
 use tokio::sync::{mpsc, oneshot};
 use tokio::time::Duration;
 
-use util::sync::Mutex;
+use util::sync::{Mutex, TaskGroup};
 use util::{MarshalSize, Unmarshal};
 
 use crate::error::Result;
+use crate::nack::ATTR_RETRANSMISSION;
 use crate::stream_info::StreamInfo;
-use crate::{Attributes, Interceptor, RTCPReader, RTCPWriter, RTPReader, RTPWriter};
+use crate::time_source::{system_time_source, SharedTimeSource};
+use crate::{
+    Attributes, Interceptor, RTCPReader, RTCPWriter, RTPReader, RTPWriter, TASKS_CLOSE_TIMEOUT,
+};
 
 #[derive(Debug)]
 enum Message {
@@ -53,6 +57,8 @@ enum StatsUpdate {
         header_bytes: u64,
         payload_bytes: u64,
         last_packet_timestamp: SystemTime,
+        /// Whether the packet was resent in response to a NACK.
+        retransmission: bool,
     },
     /// Stats collected from received RTCP packets.
     InboundRTCP {
@@ -91,37 +97,33 @@ pub struct StatsInterceptor {
     tx: mpsc::Sender<Message>,
 
     id: String,
-    now_gen: Arc<dyn Fn() -> SystemTime + Send + Sync>,
+    now_gen: SharedTimeSource,
+
+    tasks: TaskGroup,
+    close_tx: Mutex<Option<mpsc::Sender<()>>>,
 }
 
 impl StatsInterceptor {
     pub fn new(id: String) -> Self {
-        let (tx, rx) = mpsc::channel(100);
-
-        tokio::spawn(run_stats_reducer(rx));
-
-        Self {
-            id,
-            recv_streams: Default::default(),
-            send_streams: Default::default(),
-            tx,
-            now_gen: Arc::new(SystemTime::now),
-        }
+        Self::with_time_source(id, system_time_source())
     }
 
-    fn with_time_gen<F>(id: String, now_gen: F) -> Self
-    where
-        F: Fn() -> SystemTime + Send + Sync + 'static,
-    {
+    /// with_time_source creates a StatsInterceptor that reads the current time from `time_source`
+    /// instead of the system clock.
+    pub fn with_time_source(id: String, time_source: SharedTimeSource) -> Self {
         let (tx, rx) = mpsc::channel(100);
-        tokio::spawn(run_stats_reducer(rx));
+        let (close_tx, close_rx) = mpsc::channel(1);
+        let tasks = TaskGroup::new();
+        tasks.spawn(run_stats_reducer(rx, close_rx));
 
         Self {
             id,
             recv_streams: Default::default(),
             send_streams: Default::default(),
             tx,
-            now_gen: Arc::new(now_gen),
+            now_gen: time_source,
+            tasks,
+            close_tx: Mutex::new(Some(close_tx)),
         }
     }
 
@@ -170,12 +172,13 @@ impl StatsInterceptor {
     }
 }
 
-async fn run_stats_reducer(mut rx: mpsc::Receiver<Message>) {
+async fn run_stats_reducer(mut rx: mpsc::Receiver<Message>, mut close_rx: mpsc::Receiver<()>) {
     let mut ssrc_stats: StatsContainer = Default::default();
     let mut cleanup_ticker = tokio::time::interval(Duration::from_secs(10));
 
     loop {
         tokio::select! {
+            _ = close_rx.recv() => break,
             maybe_msg = rx.recv() => {
                 let msg = match maybe_msg {
                     Some(m) => m,
@@ -233,11 +236,15 @@ fn handle_stats_update(ssrc_stats: &mut StatsContainer, ssrc: u32, update: Stats
             header_bytes,
             payload_bytes,
             last_packet_timestamp,
+            retransmission,
         } => {
             let stats = ssrc_stats.get_or_create_outbound_stream_stats(ssrc);
             stats
                 .rtp_stats
                 .update(header_bytes, payload_bytes, packets, last_packet_timestamp);
+            if retransmission {
+                stats.record_retransmission(packets, payload_bytes);
+            }
             stats.mark_updated();
         }
         StatsUpdate::InboundRTCP {
@@ -308,9 +315,13 @@ impl Interceptor for StatsInterceptor {
     ) -> Arc<dyn RTPReader + Send + Sync> {
         let mut lock = self.recv_streams.lock();
 
-        let e = lock
-            .entry(info.ssrc)
-            .or_insert_with(|| Arc::new(RTPReadRecorder::new(reader, self.tx.clone())));
+        let e = lock.entry(info.ssrc).or_insert_with(|| {
+            Arc::new(RTPReadRecorder::new(
+                reader,
+                self.tx.clone(),
+                self.now_gen.clone(),
+            ))
+        });
 
         e.clone()
     }
@@ -331,9 +342,13 @@ impl Interceptor for StatsInterceptor {
     ) -> Arc<dyn RTPWriter + Send + Sync> {
         let mut lock = self.send_streams.lock();
 
-        let e = lock
-            .entry(info.ssrc)
-            .or_insert_with(|| Arc::new(RTPWriteRecorder::new(writer, self.tx.clone())));
+        let e = lock.entry(info.ssrc).or_insert_with(|| {
+            Arc::new(RTPWriteRecorder::new(
+                writer,
+                self.tx.clone(),
+                self.now_gen.clone(),
+            ))
+        });
 
         e.clone()
     }
@@ -346,6 +361,10 @@ impl Interceptor for StatsInterceptor {
     }
 
     async fn close(&self) -> Result<()> {
+        self.close_tx.lock().take();
+
+        self.tasks.close(TASKS_CLOSE_TIMEOUT).await;
+
         Ok(())
     }
 
@@ -360,7 +379,7 @@ impl Interceptor for StatsInterceptor {
         Arc::new(RTCPWriteInterceptor {
             rtcp_writer: writer,
             tx: self.tx.clone(),
-            now_gen: move || now(),
+            now_gen: move || now.now(),
         })
     }
 
@@ -375,7 +394,7 @@ impl Interceptor for StatsInterceptor {
         Arc::new(RTCPReadInterceptor {
             rtcp_reader: reader,
             tx: self.tx.clone(),
-            now_gen: move || now(),
+            now_gen: move || now.now(),
         })
     }
 }
@@ -702,11 +721,20 @@ where
 pub struct RTPReadRecorder {
     rtp_reader: Arc<dyn RTPReader + Send + Sync>,
     tx: mpsc::Sender<Message>,
+    now_gen: SharedTimeSource,
 }
 
 impl RTPReadRecorder {
-    fn new(rtp_reader: Arc<dyn RTPReader + Send + Sync>, tx: mpsc::Sender<Message>) -> Self {
-        Self { rtp_reader, tx }
+    fn new(
+        rtp_reader: Arc<dyn RTPReader + Send + Sync>,
+        tx: mpsc::Sender<Message>,
+        now_gen: SharedTimeSource,
+    ) -> Self {
+        Self {
+            rtp_reader,
+            tx,
+            now_gen,
+        }
     }
 }
 
@@ -733,7 +761,7 @@ impl RTPReader for RTPReadRecorder {
                     packets: 1,
                     header_bytes: (bytes_read - packet.payload.len()) as u64,
                     payload_bytes: packet.payload.len() as u64,
-                    last_packet_timestamp: SystemTime::now(),
+                    last_packet_timestamp: self.now_gen.now(),
                 },
             })
             .await;
@@ -745,11 +773,20 @@ impl RTPReader for RTPReadRecorder {
 pub struct RTPWriteRecorder {
     rtp_writer: Arc<dyn RTPWriter + Send + Sync>,
     tx: mpsc::Sender<Message>,
+    now_gen: SharedTimeSource,
 }
 
 impl RTPWriteRecorder {
-    fn new(rtp_writer: Arc<dyn RTPWriter + Send + Sync>, tx: mpsc::Sender<Message>) -> Self {
-        Self { rtp_writer, tx }
+    fn new(
+        rtp_writer: Arc<dyn RTPWriter + Send + Sync>,
+        tx: mpsc::Sender<Message>,
+        now_gen: SharedTimeSource,
+    ) -> Self {
+        Self {
+            rtp_writer,
+            tx,
+            now_gen,
+        }
     }
 }
 
@@ -773,7 +810,8 @@ impl RTPWriter for RTPWriteRecorder {
                     packets: 1,
                     header_bytes: pkt.header.marshal_size() as u64,
                     payload_bytes: pkt.payload.len() as u64,
-                    last_packet_timestamp: SystemTime::now(),
+                    last_packet_timestamp: self.now_gen.now(),
+                    retransmission: attributes.contains_key(&ATTR_RETRANSMISSION),
                 },
             })
             .await;
@@ -847,7 +885,9 @@ mod test {
 
     use crate::error::Result;
     use crate::mock::mock_stream::MockStream;
+    use crate::nack::ATTR_RETRANSMISSION;
     use crate::stream_info::StreamInfo;
+    use crate::Attributes;
 
     use super::StatsInterceptor;
 
@@ -927,12 +967,79 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_stats_interceptor_close() -> Result<()> {
+        let icpr: Arc<_> = Arc::new(StatsInterceptor::new("Hello".to_owned()));
+
+        let stream = MockStream::new(
+            &StreamInfo {
+                ssrc: 123456,
+                ..Default::default()
+            },
+            icpr.clone(),
+        )
+        .await;
+        assert_eq!(icpr.tasks.len(), 1);
+
+        // The recorders of the stream still hold senders to the reducer, close stops it anyway
+        tokio::time::timeout(Duration::from_millis(100), stream.close())
+            .await
+            .expect("close should not wait for the tasks close timeout")?;
+        assert!(icpr.tasks.is_empty());
+        assert!(icpr.fetch_inbound_stats(vec![123456]).await.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stats_interceptor_rtp_retransmission() -> Result<()> {
+        let icpr: Arc<_> = Arc::new(StatsInterceptor::new("Hello".to_owned()));
+
+        let send_stream = MockStream::new(
+            &StreamInfo {
+                ssrc: 234567,
+                ..Default::default()
+            },
+            icpr.clone(),
+        )
+        .await;
+
+        let pkt = rtp::packet::Packet {
+            header: rtp::header::Header {
+                ssrc: 234567,
+                ..Default::default()
+            },
+            payload: Bytes::from_static(b"\xde\xad\xbe\xef"),
+        };
+        send_stream.write_rtp(&pkt).await?;
+
+        let mut attributes = Attributes::new();
+        attributes.insert(ATTR_RETRANSMISSION, 1);
+        send_stream
+            .write_rtp_with_attributes(&pkt, &attributes)
+            .await?;
+
+        let snapshots = icpr.fetch_outbound_stats(vec![234567]).await;
+        let send_snapshot = snapshots[0]
+            .as_ref()
+            .expect("Stats should exist for ssrc: 234567");
+        assert_eq!(send_snapshot.packets_sent(), 2);
+        assert_eq!(send_snapshot.payload_bytes_sent(), 8);
+        assert_eq!(send_snapshot.retransmitted_packets_sent(), 1);
+        assert_eq!(send_snapshot.retransmitted_bytes_sent(), 4);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_stats_interceptor_rtcp() -> Result<()> {
-        let icpr: Arc<_> = Arc::new(StatsInterceptor::with_time_gen("Hello".to_owned(), || {
-            // 10 Nov 1995 11:33:36.5 UTC
-            SystemTime::UNIX_EPOCH + Duration::from_secs_f64(816003216.5)
-        }));
+        let icpr: Arc<_> = Arc::new(StatsInterceptor::with_time_source(
+            "Hello".to_owned(),
+            Arc::new(|| {
+                // 10 Nov 1995 11:33:36.5 UTC
+                SystemTime::UNIX_EPOCH + Duration::from_secs_f64(816003216.5)
+            }),
+        ));
 
         let recv_stream = MockStream::new(
             &StreamInfo {
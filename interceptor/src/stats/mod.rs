@@ -248,6 +248,12 @@ mod outbound {
 
         /// The latest fraction lost value from RR.
         remote_fraction_lost: Option<u8>,
+
+        /// The number of packets resent in response to a NACK, included in `rtp_stats`.
+        retransmitted_packets_sent: u64,
+
+        /// The payload bytes of the packets resent in response to a NACK.
+        retransmitted_bytes_sent: u64,
     }
 
     impl Default for StreamStats {
@@ -256,6 +262,8 @@ mod outbound {
                 rtp_stats: RTPStats::default(),
                 rtcp_stats: RTCPStats::default(),
                 last_update: Instant::now(),
+                retransmitted_packets_sent: 0,
+                retransmitted_bytes_sent: 0,
                 initial_outbound_ext_seq_num: None,
                 remote_packets_received: 0,
                 remote_total_lost: 0,
@@ -331,6 +339,11 @@ mod outbound {
         pub(super) fn update_remote_total_lost(&mut self, lost: u32) {
             self.remote_total_lost = lost;
         }
+
+        pub(super) fn record_retransmission(&mut self, packets: u64, payload_bytes: u64) {
+            self.retransmitted_packets_sent += packets;
+            self.retransmitted_bytes_sent += payload_bytes;
+        }
     }
 
     /// A point in time snapshot of the stream stats for an outbound RTP stream.
@@ -364,6 +377,12 @@ mod outbound {
         /// The fraction of packets lost reported for this stream.
         /// Calculated as defined in [RFC3550](https://www.rfc-editor.org/rfc/rfc3550) section 6.4.1 and Appendix A.3.
         remote_fraction_lost: Option<f64>,
+
+        /// The number of packets resent in response to a NACK.
+        retransmitted_packets_sent: u64,
+
+        /// The payload bytes of the packets resent in response to a NACK.
+        retransmitted_bytes_sent: u64,
     }
 
     impl StatsSnapshot {
@@ -395,6 +414,17 @@ mod outbound {
             self.rtcp_stats.pli_count
         }
 
+        /// Packets resent in response to a NACK, they are also counted by `packets_sent`.
+        pub fn retransmitted_packets_sent(&self) -> u64 {
+            self.retransmitted_packets_sent
+        }
+
+        /// Payload bytes of the packets resent in response to a NACK, they are also counted by
+        /// `payload_bytes_sent`.
+        pub fn retransmitted_bytes_sent(&self) -> u64 {
+            self.retransmitted_bytes_sent
+        }
+
         /// Packets received on the remote side.
         pub fn remote_packets_received(&self) -> u64 {
             self.remote_packets_received
@@ -446,6 +476,8 @@ mod outbound {
                 remote_fraction_lost: stream_stats
                     .remote_fraction_lost
                     .map(|fraction| (fraction as f64) / (u8::MAX as f64)),
+                retransmitted_packets_sent: stream_stats.retransmitted_packets_sent,
+                retransmitted_bytes_sent: stream_stats.retransmitted_bytes_sent,
             }
         }
     }
@@ -20,6 +20,10 @@ pub struct StreamInfo {
     pub channels: u16,
     pub sdp_fmtp_line: String,
     pub rtcp_feedback: Vec<RTCPFeedback>,
+    /// mid is the MID of the transceiver of the stream, if it has one.
+    pub mid: String,
+    /// rid is the RtpStreamId of the stream, if it is a simulcast layer.
+    pub rid: String,
 }
 
 /// RTCPFeedback signals the connection to use additional RTCP packet types.
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::time::Instant;
+
+/// TimeSource is the clock used by interceptors that need the current wall clock time, e.g. to
+/// stamp reports or compute round trip times, and a monotonic clock to measure the time between
+/// events, e.g. packet arrivals. It can be replaced to make tests deterministic, see
+/// [`crate::mock::mock_time::MockTime`].
+pub trait TimeSource {
+    /// now returns the current wall clock time, which may step when the system clock is set.
+    fn now(&self) -> SystemTime;
+
+    /// instant returns the current time of a monotonic clock. Durations between events must be
+    /// measured with it rather than with `now`, which may step backwards or forwards.
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// SystemTimeSource is a TimeSource backed by [`SystemTime::now`] and tokio's [`Instant::now`].
+#[derive(Default, Debug, Copy, Clone)]
+pub struct SystemTimeSource;
+
+impl TimeSource for SystemTimeSource {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+impl<F> TimeSource for F
+where
+    F: Fn() -> SystemTime,
+{
+    fn now(&self) -> SystemTime {
+        self()
+    }
+}
+
+/// SharedTimeSource is a TimeSource that can be shared between interceptors and their streams.
+pub type SharedTimeSource = Arc<dyn TimeSource + Send + Sync>;
+
+/// system_time_source returns a SharedTimeSource backed by [`SystemTime::now`] and tokio's
+/// [`Instant::now`].
+pub fn system_time_source() -> SharedTimeSource {
+    Arc::new(SystemTimeSource)
+}
@@ -4,12 +4,20 @@ mod twcc_test;
 pub mod receiver;
 pub mod sender;
 
+use crate::stream_info::StreamInfo;
 use rtcp::transport_feedbacks::transport_layer_cc::{
     PacketStatusChunk, RecvDelta, RunLengthChunk, StatusChunkTypeTcc, StatusVectorChunk,
     SymbolSizeTypeTcc, SymbolTypeTcc, TransportLayerCc,
 };
+use rtp::seqnum::ExtendedSeqnum;
 use std::cmp::Ordering;
 
+fn stream_support_transport_cc(info: &StreamInfo) -> bool {
+    info.rtcp_feedback
+        .iter()
+        .any(|fb| fb.typ == "transport-cc" && fb.parameter.is_empty())
+}
+
 #[derive(Default, Debug, PartialEq, Clone)]
 struct PktInfo {
     sequence_number: u32,
@@ -23,8 +31,7 @@ struct PktInfo {
 pub struct Recorder {
     received_packets: Vec<PktInfo>,
 
-    cycles: u32,
-    last_sequence_number: u16,
+    seqnum: ExtendedSeqnum,
 
     sender_ssrc: u32,
     media_ssrc: u32,
@@ -44,14 +51,13 @@ impl Recorder {
     /// record marks a packet with media_ssrc and a transport wide sequence number sequence_number as received at arrival_time.
     pub fn record(&mut self, media_ssrc: u32, sequence_number: u16, arrival_time: i64) {
         self.media_ssrc = media_ssrc;
-        if sequence_number < 0x0fff && self.last_sequence_number > 0xf000 {
-            self.cycles += 1 << 16;
+        // packets reordered from before the first one across the wraparound are dropped
+        if let Some(sequence_number) = self.seqnum.update(sequence_number) {
+            self.received_packets.push(PktInfo {
+                sequence_number: sequence_number as u32,
+                arrival_time,
+            });
         }
-        self.received_packets.push(PktInfo {
-            sequence_number: self.cycles | sequence_number as u32,
-            arrival_time,
-        });
-        self.last_sequence_number = sequence_number;
     }
 
     /// build_feedback_packet creates a new RTCP packet containing a TWCC feedback report.
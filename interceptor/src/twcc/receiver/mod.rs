@@ -2,22 +2,24 @@ mod receiver_stream;
 #[cfg(test)]
 mod receiver_test;
 
+use crate::time_source::{system_time_source, SharedTimeSource};
 use crate::twcc::sender::TRANSPORT_CC_URI;
-use crate::twcc::Recorder;
+use crate::twcc::{stream_support_transport_cc, Recorder};
 use crate::*;
 use receiver_stream::ReceiverStream;
 
 use rtp::extension::transport_cc_extension::TransportCcExtension;
 use std::time::Duration;
 use tokio::sync::{mpsc, Mutex};
-use tokio::time::MissedTickBehavior;
+use tokio::time::{Instant, MissedTickBehavior};
+use util::sync::TaskGroup;
 use util::Unmarshal;
-use waitgroup::WaitGroup;
 
 /// ReceiverBuilder is a InterceptorBuilder for a SenderInterceptor
 #[derive(Default)]
 pub struct ReceiverBuilder {
     interval: Option<Duration>,
+    time_source: Option<SharedTimeSource>,
 }
 
 impl ReceiverBuilder {
@@ -26,12 +28,20 @@ impl ReceiverBuilder {
         self.interval = Some(interval);
         self
     }
+
+    /// with_time_source sets the clock the arrival times of packets are read from, with
+    /// [`TimeSource::instant`](crate::time_source::TimeSource::instant).
+    pub fn with_time_source(mut self, time_source: SharedTimeSource) -> ReceiverBuilder {
+        self.time_source = Some(time_source);
+        self
+    }
 }
 
 impl InterceptorBuilder for ReceiverBuilder {
     fn build(&self, _id: &str) -> Result<Arc<dyn Interceptor + Send + Sync>> {
         let (close_tx, close_rx) = mpsc::channel(1);
         let (packet_chan_tx, packet_chan_rx) = mpsc::channel(1);
+        let now = self.time_source.clone().unwrap_or_else(system_time_source);
         Ok(Arc::new(Receiver {
             internal: Arc::new(ReceiverInternal {
                 interval: if let Some(interval) = &self.interval {
@@ -44,9 +54,10 @@ impl InterceptorBuilder for ReceiverBuilder {
                 streams: Mutex::new(HashMap::new()),
                 close_rx: Mutex::new(Some(close_rx)),
             }),
-            start_time: tokio::time::Instant::now(),
+            start_time: now.instant(),
+            now,
             packet_chan_tx,
-            wg: Mutex::new(Some(WaitGroup::new())),
+            tasks: TaskGroup::new(),
             close_tx: Mutex::new(Some(close_tx)),
         }))
     }
@@ -72,11 +83,11 @@ struct ReceiverInternal {
 pub struct Receiver {
     internal: Arc<ReceiverInternal>,
 
-    // we use tokio's Instant because it makes testing easier via `tokio::time::advance`.
-    start_time: tokio::time::Instant,
+    start_time: Instant,
+    now: SharedTimeSource,
     packet_chan_tx: mpsc::Sender<Packet>,
 
-    wg: Mutex<Option<WaitGroup>>,
+    tasks: TaskGroup,
     close_tx: Mutex<Option<mpsc::Sender<()>>>,
 }
 
@@ -172,14 +183,9 @@ impl Interceptor for Receiver {
             *recorder = Recorder::new(rand::random::<u32>());
         }
 
-        let mut w = {
-            let wait_group = self.wg.lock().await;
-            wait_group.as_ref().map(|wg| wg.worker())
-        };
         let writer2 = Arc::clone(&writer);
         let internal = Arc::clone(&self.internal);
-        tokio::spawn(async move {
-            let _d = w.take();
+        self.tasks.spawn(async move {
             if let Err(err) = Receiver::run(writer2, internal).await {
                 log::warn!("bind_rtcp_writer TWCC Sender::run got error: {}", err);
             }
@@ -208,6 +214,11 @@ impl Interceptor for Receiver {
         info: &StreamInfo,
         reader: Arc<dyn RTPReader + Send + Sync>,
     ) -> Arc<dyn RTPReader + Send + Sync> {
+        if !stream_support_transport_cc(info) {
+            // The remote doesn't expect transport wide feedback for this stream
+            return reader;
+        }
+
         let mut hdr_ext_id = 0u8;
         for e in &info.rtp_header_extensions {
             if e.uri == TRANSPORT_CC_URI {
@@ -226,6 +237,7 @@ impl Interceptor for Receiver {
             info.ssrc,
             self.packet_chan_tx.clone(),
             self.start_time,
+            Arc::clone(&self.now),
         ));
 
         {
@@ -249,12 +261,7 @@ impl Interceptor for Receiver {
             close_tx.take();
         }
 
-        {
-            let mut wait_group = self.wg.lock().await;
-            if let Some(wg) = wait_group.take() {
-                wg.wait().await;
-            }
-        }
+        self.tasks.close(TASKS_CLOSE_TIMEOUT).await;
 
         Ok(())
     }
@@ -5,8 +5,8 @@ pub(super) struct ReceiverStream {
     hdr_ext_id: u8,
     ssrc: u32,
     packet_chan_tx: mpsc::Sender<Packet>,
-    // we use tokio's Instant because it makes testing easier via `tokio::time::advance`.
-    start_time: tokio::time::Instant,
+    start_time: Instant,
+    now: SharedTimeSource,
 }
 
 impl ReceiverStream {
@@ -15,7 +15,8 @@ impl ReceiverStream {
         hdr_ext_id: u8,
         ssrc: u32,
         packet_chan_tx: mpsc::Sender<Packet>,
-        start_time: tokio::time::Instant,
+        start_time: Instant,
+        now: SharedTimeSource,
     ) -> Self {
         ReceiverStream {
             parent_rtp_reader,
@@ -23,6 +24,7 @@ impl ReceiverStream {
             ssrc,
             packet_chan_tx,
             start_time,
+            now,
         }
     }
 }
@@ -44,8 +46,7 @@ impl RTPReader for ReceiverStream {
                 .send(Packet {
                     hdr: p.header,
                     sequence_number: tcc_ext.transport_sequence,
-                    arrival_time: (tokio::time::Instant::now() - self.start_time).as_micros()
-                        as i64,
+                    arrival_time: (self.now.instant() - self.start_time).as_micros() as i64,
                     ssrc: self.ssrc,
                 })
                 .await;
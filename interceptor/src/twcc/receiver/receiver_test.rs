@@ -1,6 +1,7 @@
 use super::*;
 use crate::mock::mock_stream::MockStream;
-use crate::stream_info::RTPHeaderExtension;
+use crate::mock::mock_time::MockTime;
+use crate::stream_info::{RTCPFeedback, RTPHeaderExtension};
 use rtcp::transport_feedbacks::transport_layer_cc::{
     PacketStatusChunk, RunLengthChunk, StatusChunkTypeTcc, StatusVectorChunk, SymbolSizeTypeTcc,
     SymbolTypeTcc, TransportLayerCc,
@@ -20,6 +21,10 @@ async fn test_twcc_receiver_interceptor_before_any_packets() -> Result<()> {
                 id: 1,
                 ..Default::default()
             }],
+            rtcp_feedback: vec![RTCPFeedback {
+                typ: "transport-cc".to_owned(),
+                ..Default::default()
+            }],
             ..Default::default()
         },
         icpr,
@@ -53,6 +58,10 @@ async fn test_twcc_receiver_interceptor_after_rtp_packets() -> Result<()> {
                 id: 1,
                 ..Default::default()
             }],
+            rtcp_feedback: vec![RTCPFeedback {
+                typ: "transport-cc".to_owned(),
+                ..Default::default()
+            }],
             ..Default::default()
         },
         icpr,
@@ -96,9 +105,59 @@ async fn test_twcc_receiver_interceptor_after_rtp_packets() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_twcc_receiver_interceptor_without_transport_cc_feedback() -> Result<()> {
+    let builder = Receiver::builder();
+    let icpr = builder.build("")?;
+
+    let stream = MockStream::new(
+        &StreamInfo {
+            ssrc: 1,
+            rtp_header_extensions: vec![RTPHeaderExtension {
+                uri: TRANSPORT_CC_URI.to_owned(),
+                id: 1,
+            }],
+            ..Default::default()
+        },
+        icpr,
+    )
+    .await;
+
+    for i in 0..10 {
+        let mut hdr = rtp::header::Header::default();
+        let tcc = TransportCcExtension {
+            transport_sequence: i,
+        }
+        .marshal()?;
+        hdr.set_extension(1, tcc)?;
+        stream
+            .receive_rtp(rtp::packet::Packet {
+                header: hdr,
+                ..Default::default()
+            })
+            .await;
+    }
+
+    tokio::select! {
+        pkts = stream.written_rtcp() => {
+            assert!(pkts.map(|p| p.is_empty()).unwrap_or(true), "Should not have sent feedback which wasn't negotiated")
+        }
+        _ = tokio::time::sleep(Duration::from_millis(300)) => {
+            // All good
+        }
+    }
+
+    stream.close().await?;
+
+    Ok(())
+}
+
 #[tokio::test(start_paused = true)]
 async fn test_twcc_receiver_interceptor_different_delays_between_rtp_packets() -> Result<()> {
-    let builder = Receiver::builder().with_interval(Duration::from_millis(500));
+    let mt = Arc::new(MockTime::default());
+    let builder = Receiver::builder()
+        .with_interval(Duration::from_millis(500))
+        .with_time_source(Arc::clone(&mt) as SharedTimeSource);
     let icpr = builder.build("")?;
 
     let stream = MockStream::new(
@@ -109,6 +168,10 @@ async fn test_twcc_receiver_interceptor_different_delays_between_rtp_packets() -
                 id: 1,
                 ..Default::default()
             }],
+            rtcp_feedback: vec![RTCPFeedback {
+                typ: "transport-cc".to_owned(),
+                ..Default::default()
+            }],
             ..Default::default()
         },
         icpr,
@@ -117,6 +180,7 @@ async fn test_twcc_receiver_interceptor_different_delays_between_rtp_packets() -
 
     let delays = vec![0, 10, 100, 200];
     for (i, d) in delays.iter().enumerate() {
+        mt.advance(Duration::from_millis(*d));
         tokio::time::advance(Duration::from_millis(*d)).await;
 
         let mut hdr = rtp::header::Header::default();
@@ -168,9 +232,81 @@ async fn test_twcc_receiver_interceptor_different_delays_between_rtp_packets() -
     Ok(())
 }
 
+#[tokio::test(start_paused = true)]
+async fn test_twcc_receiver_interceptor_wall_clock_steps() -> Result<()> {
+    let mt = Arc::new(MockTime::default());
+    let builder = Receiver::builder()
+        .with_interval(Duration::from_millis(500))
+        .with_time_source(Arc::clone(&mt) as SharedTimeSource);
+    let icpr = builder.build("")?;
+
+    let stream = MockStream::new(
+        &StreamInfo {
+            ssrc: 1,
+            rtp_header_extensions: vec![RTPHeaderExtension {
+                uri: TRANSPORT_CC_URI.to_owned(),
+                id: 1,
+            }],
+            rtcp_feedback: vec![RTCPFeedback {
+                typ: "transport-cc".to_owned(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+        icpr,
+    )
+    .await;
+
+    // The wall clock steps back and then forward by an hour between packets, which must not
+    // show in their arrival deltas.
+    let steps = [(7_200, 0), (3_600, 10), (10_800, 20)];
+    for (i, (wall_clock, d)) in steps.iter().enumerate() {
+        mt.set_now(std::time::UNIX_EPOCH + Duration::from_secs(*wall_clock));
+        mt.advance(Duration::from_millis(*d));
+        tokio::time::advance(Duration::from_millis(*d)).await;
+
+        let mut hdr = rtp::header::Header::default();
+        let tcc = TransportCcExtension {
+            transport_sequence: i as u16,
+        }
+        .marshal()?;
+
+        hdr.set_extension(1, tcc)?;
+        stream
+            .receive_rtp(rtp::packet::Packet {
+                header: hdr,
+                ..Default::default()
+            })
+            .await;
+
+        // Yield so this packet can be processed
+        tokio::task::yield_now().await;
+    }
+
+    // Force a packet to be generated
+    tokio::time::advance(Duration::from_millis(2001)).await;
+    tokio::task::yield_now().await;
+
+    let pkts = stream.written_rtcp().await.unwrap();
+    assert_eq!(pkts.len(), 1);
+    let cc = pkts[0]
+        .as_any()
+        .downcast_ref::<TransportLayerCc>()
+        .expect("should be a TransportLayerCc");
+    let deltas: Vec<i64> = cc.recv_deltas.iter().map(|d| d.delta).collect();
+    assert_eq!(deltas, vec![0, 10_000, 20_000]);
+
+    stream.close().await?;
+
+    Ok(())
+}
+
 #[tokio::test(start_paused = true)]
 async fn test_twcc_receiver_interceptor_packet_loss() -> Result<()> {
-    let builder = Receiver::builder().with_interval(Duration::from_secs(2));
+    let mt = Arc::new(MockTime::default());
+    let builder = Receiver::builder()
+        .with_interval(Duration::from_secs(2))
+        .with_time_source(Arc::clone(&mt) as SharedTimeSource);
     let icpr = builder.build("")?;
 
     let stream = MockStream::new(
@@ -181,6 +317,10 @@ async fn test_twcc_receiver_interceptor_packet_loss() -> Result<()> {
                 id: 1,
                 ..Default::default()
             }],
+            rtcp_feedback: vec![RTCPFeedback {
+                typ: "transport-cc".to_owned(),
+                ..Default::default()
+            }],
             ..Default::default()
         },
         icpr,
@@ -198,6 +338,7 @@ async fn test_twcc_receiver_interceptor_packet_loss() -> Result<()> {
     ];
 
     for (i, d) in sequence_number_to_delay {
+        mt.advance(Duration::from_millis(*d));
         tokio::time::advance(Duration::from_millis(*d)).await;
         let mut hdr = rtp::header::Header::default();
         let tcc = TransportCcExtension {
@@ -288,6 +429,10 @@ async fn test_twcc_receiver_interceptor_overflow() -> Result<()> {
                 id: 1,
                 ..Default::default()
             }],
+            rtcp_feedback: vec![RTCPFeedback {
+                typ: "transport-cc".to_owned(),
+                ..Default::default()
+            }],
             ..Default::default()
         },
         icpr,
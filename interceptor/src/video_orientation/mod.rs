@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod video_orientation_test;
+
+use crate::*;
+
+use rtp::extension::video_orientation_extension::VideoOrientationExtension;
+use util::sync::Mutex;
+use util::{Marshal, Unmarshal};
+
+pub(crate) const VIDEO_ORIENTATION_URI: &str = "urn:3gpp:video-orientation";
+
+/// Attribute key of the most recent video orientation (CVO) of the stream, set on every RTP packet
+/// read once the remote has sent one. The value is the byte of the header extension.
+pub const ATTR_VIDEO_ORIENTATION: usize = 0xC700;
+
+/// video_orientation_from_attributes returns the video orientation stored in the attributes
+/// returned by `read_rtp`, or None if the remote didn't send one yet.
+pub fn video_orientation_from_attributes(
+    attributes: &Attributes,
+) -> Option<VideoOrientationExtension> {
+    let b = *attributes.get(&ATTR_VIDEO_ORIENTATION)? as u8;
+    VideoOrientationExtension::unmarshal(&mut &[b][..]).ok()
+}
+
+/// VideoOrientationBuilder is a InterceptorBuilder for a VideoOrientationInterceptor
+#[derive(Default)]
+pub struct VideoOrientationBuilder;
+
+impl InterceptorBuilder for VideoOrientationBuilder {
+    fn build(&self, _id: &str) -> Result<Arc<dyn Interceptor + Send + Sync>> {
+        Ok(Arc::new(VideoOrientationInterceptor {}))
+    }
+}
+
+/// VideoOrientationInterceptor parses the video orientation (CVO) header extension of incoming
+/// RTP packets, see 3GPP TS 26.114 Section 7.4.5. Senders only need to send it on some packets,
+/// e.g. the last packet of key frames and when the orientation changes, so the most recent value
+/// of the stream is exposed through the attributes of every packet, see
+/// `video_orientation_from_attributes`.
+pub struct VideoOrientationInterceptor {}
+
+impl VideoOrientationInterceptor {
+    /// builder returns a new VideoOrientationBuilder.
+    pub fn builder() -> VideoOrientationBuilder {
+        VideoOrientationBuilder
+    }
+}
+
+#[async_trait]
+impl Interceptor for VideoOrientationInterceptor {
+    /// bind_rtcp_reader lets you modify any incoming RTCP packets. It is called once per sender/receiver, however this might
+    /// change in the future. The returned method will be called once per packet batch.
+    async fn bind_rtcp_reader(
+        &self,
+        reader: Arc<dyn RTCPReader + Send + Sync>,
+    ) -> Arc<dyn RTCPReader + Send + Sync> {
+        reader
+    }
+
+    /// bind_rtcp_writer lets you modify any outgoing RTCP packets. It is called once per PeerConnection. The returned method
+    /// will be called once per packet batch.
+    async fn bind_rtcp_writer(
+        &self,
+        writer: Arc<dyn RTCPWriter + Send + Sync>,
+    ) -> Arc<dyn RTCPWriter + Send + Sync> {
+        writer
+    }
+
+    /// bind_local_stream lets you modify any outgoing RTP packets. It is called once for per LocalStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_local_stream(
+        &self,
+        _info: &StreamInfo,
+        writer: Arc<dyn RTPWriter + Send + Sync>,
+    ) -> Arc<dyn RTPWriter + Send + Sync> {
+        writer
+    }
+
+    /// unbind_local_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_local_stream(&self, _info: &StreamInfo) {}
+
+    /// bind_remote_stream lets you modify any incoming RTP packets. It is called once for per RemoteStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_remote_stream(
+        &self,
+        info: &StreamInfo,
+        reader: Arc<dyn RTPReader + Send + Sync>,
+    ) -> Arc<dyn RTPReader + Send + Sync> {
+        let mut hdr_ext_id = 0u8;
+        for e in &info.rtp_header_extensions {
+            if e.uri == VIDEO_ORIENTATION_URI {
+                hdr_ext_id = e.id as u8;
+                break;
+            }
+        }
+        if hdr_ext_id == 0 {
+            // Don't try to read header extension if ID is 0, because 0 is an invalid extension ID
+            return reader;
+        }
+
+        Arc::new(VideoOrientationStream {
+            parent_rtp_reader: reader,
+            hdr_ext_id,
+            orientation: Mutex::new(None),
+        })
+    }
+
+    /// unbind_remote_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_remote_stream(&self, _info: &StreamInfo) {}
+
+    /// close closes the Interceptor, cleaning up any data if necessary.
+    async fn close(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct VideoOrientationStream {
+    parent_rtp_reader: Arc<dyn RTPReader + Send + Sync>,
+    hdr_ext_id: u8,
+    /// The most recent orientation received on the stream
+    orientation: Mutex<Option<VideoOrientationExtension>>,
+}
+
+#[async_trait]
+impl RTPReader for VideoOrientationStream {
+    /// read a rtp packet
+    async fn read(&self, buf: &mut [u8], attributes: &Attributes) -> Result<(usize, Attributes)> {
+        let (n, mut attr) = self.parent_rtp_reader.read(buf, attributes).await?;
+
+        let mut b = &buf[..n];
+        let p = rtp::packet::Packet::unmarshal(&mut b)?;
+
+        let mut orientation = self.orientation.lock();
+        if let Some(mut ext) = p.header.get_extension(self.hdr_ext_id) {
+            match VideoOrientationExtension::unmarshal(&mut ext) {
+                Ok(ext) => *orientation = Some(ext),
+                Err(err) => log::debug!("ignoring video orientation: {}", err),
+            }
+        }
+        if let Some(ext) = *orientation {
+            if let Ok(b) = ext.marshal() {
+                attr.insert(ATTR_VIDEO_ORIENTATION, b[0] as usize);
+            }
+        }
+
+        Ok((n, attr))
+    }
+}
@@ -0,0 +1,87 @@
+use super::*;
+use crate::stream_info::RTPHeaderExtension;
+use bytes::Bytes;
+use rtp::extension::video_orientation_extension::{CameraDirection, VideoRotation};
+use std::collections::VecDeque;
+
+struct PacketQueue(Mutex<VecDeque<Bytes>>);
+
+#[async_trait]
+impl RTPReader for PacketQueue {
+    async fn read(&self, buf: &mut [u8], a: &Attributes) -> Result<(usize, Attributes)> {
+        let raw = self.0.lock().pop_front().ok_or(Error::ErrIoEOF)?;
+        buf[..raw.len()].copy_from_slice(&raw);
+        Ok((raw.len(), a.clone()))
+    }
+}
+
+fn packet(orientation: Option<VideoOrientationExtension>) -> Result<Bytes> {
+    let mut header = rtp::header::Header {
+        ssrc: 1,
+        ..Default::default()
+    };
+    if let Some(orientation) = orientation {
+        header.set_extension(3, orientation.marshal()?)?;
+    }
+    Ok(rtp::packet::Packet {
+        header,
+        payload: Bytes::from_static(&[0x01]),
+    }
+    .marshal()?)
+}
+
+#[tokio::test]
+async fn test_video_orientation_interceptor() -> Result<()> {
+    let icpr = VideoOrientationInterceptor::builder().build("")?;
+
+    let rotated = VideoOrientationExtension {
+        direction: CameraDirection::Front,
+        flip: false,
+        rotation: VideoRotation::Degree90,
+    };
+    let flipped = VideoOrientationExtension {
+        direction: CameraDirection::Back,
+        flip: true,
+        rotation: VideoRotation::Degree270,
+    };
+    let queue = PacketQueue(Mutex::new(VecDeque::from(vec![
+        packet(None)?,
+        packet(Some(rotated))?,
+        packet(None)?,
+        packet(Some(flipped))?,
+        packet(None)?,
+    ])));
+
+    let reader = icpr
+        .bind_remote_stream(
+            &StreamInfo {
+                ssrc: 1,
+                rtp_header_extensions: vec![RTPHeaderExtension {
+                    uri: VIDEO_ORIENTATION_URI.to_owned(),
+                    id: 3,
+                }],
+                ..Default::default()
+            },
+            Arc::new(queue),
+        )
+        .await;
+
+    let mut buf = vec![0u8; 1500];
+    let a = Attributes::new();
+
+    // Nothing is known before the first orientation, later packets carry the most recent one
+    for expected in [
+        None,
+        Some(rotated),
+        Some(rotated),
+        Some(flipped),
+        Some(flipped),
+    ] {
+        let (_, attr) = reader.read(&mut buf, &a).await?;
+        assert_eq!(video_orientation_from_attributes(&attr), expected);
+    }
+
+    icpr.close().await?;
+
+    Ok(())
+}
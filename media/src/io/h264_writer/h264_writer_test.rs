@@ -2,36 +2,6 @@ use super::*;
 use bytes::Bytes;
 use std::io::Cursor;
 
-#[test]
-fn test_is_key_frame() -> Result<()> {
-    let tests = vec![
-        (
-            "When given a non-keyframe; it should return false",
-            vec![0x27, 0x90, 0x90],
-            false,
-        ),
-        (
-            "When given a SPS packetized with STAP-A;; it should return true",
-            vec![
-                0x38, 0x00, 0x03, 0x27, 0x90, 0x90, 0x00, 0x05, 0x28, 0x90, 0x90, 0x90, 0x90,
-            ],
-            true,
-        ),
-        (
-            "When given a SPS with no packetization; it should return true",
-            vec![0x27, 0x90, 0x90, 0x00],
-            true,
-        ),
-    ];
-
-    for (name, payload, want) in tests {
-        let got = is_key_frame(&payload);
-        assert_eq!(want, got, "{} failed", name);
-    }
-
-    Ok(())
-}
-
 #[test]
 fn test_write_rtp() -> Result<()> {
     let tests = vec![
@@ -44,7 +14,7 @@ fn test_write_rtp() -> Result<()> {
         ),
         (
             "When no keyframe is defined; it should discard the packet",
-            vec![0x25, 0x90, 0x90],
+            vec![0x21, 0x90, 0x90],
             false,
             vec![],
             false,
@@ -123,3 +93,28 @@ fn test_write_rtp_fu() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_async_h264_writer_write_sample_and_close() -> Result<()> {
+    let non_key_frame = Bytes::from_static(&[0x00, 0x00, 0x00, 0x01, 0x21, 0x90, 0x90]);
+    let key_frame = Bytes::from_static(&[
+        0x00, 0x00, 0x00, 0x01, 0x27, 0x90, 0x90, 0x00, 0x00, 0x00, 0x01, 0x25, 0x90,
+    ]);
+
+    let mut writer = AsyncH264Writer::new(Vec::<u8>::new());
+    for data in [&non_key_frame, &key_frame, &non_key_frame] {
+        writer
+            .write_sample(&Sample {
+                data: data.clone(),
+                ..Default::default()
+            })
+            .await?;
+    }
+    writer.close().await?;
+
+    let mut want = key_frame.to_vec();
+    want.extend_from_slice(&non_key_frame);
+    assert_eq!(writer.into_inner(), want);
+
+    Ok(())
+}
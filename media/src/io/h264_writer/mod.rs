@@ -1,26 +1,24 @@
 #[cfg(test)]
 mod h264_writer_test;
 
-use crate::error::Result;
-use crate::io::Writer;
+use crate::error::{Error, Result};
+use crate::io::{AsyncSampleWriter, Writer};
+use crate::Sample;
 
-use rtp::codecs::h264::H264Packet;
+use async_trait::async_trait;
+
+use rtp::codecs::h264::{self, H264Packet};
 use rtp::packetizer::Depacketizer;
 use std::io::{Seek, Write};
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
 
-const NALU_TTYPE_STAP_A: u32 = 24;
 const NALU_TTYPE_SPS: u32 = 7;
 const NALU_TYPE_BITMASK: u32 = 0x1F;
 
-fn is_key_frame(data: &[u8]) -> bool {
-    if data.len() < 4 {
-        false
-    } else {
-        let word = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
-        let nalu_type = (word >> 24) & NALU_TYPE_BITMASK;
-        (nalu_type == NALU_TTYPE_STAP_A && (word & NALU_TYPE_BITMASK) == NALU_TTYPE_SPS)
-            || (nalu_type == NALU_TTYPE_SPS)
-    }
+/// has_sps reports whether an Annex B bitstream contains a sequence parameter set
+fn has_sps(data: &[u8]) -> bool {
+    data.windows(4)
+        .any(|w| w[..3] == [0, 0, 1] && (w[3] as u32 & NALU_TYPE_BITMASK) == NALU_TTYPE_SPS)
 }
 
 /// H264Writer is used to take RTP packets, parse them and
@@ -53,7 +51,7 @@ impl<W: Write + Seek> Writer for H264Writer<W> {
         }
 
         if !self.has_key_frame {
-            self.has_key_frame = is_key_frame(&packet.payload);
+            self.has_key_frame = h264::is_keyframe(&packet.payload);
             if !self.has_key_frame {
                 // key frame not defined yet. discarding packet
                 return Ok(());
@@ -80,3 +78,66 @@ impl<W: Write + Seek> Writer for H264Writer<W> {
         Ok(())
     }
 }
+
+/// AsyncH264Writer is used to take H264 samples in Annex B format and write them
+/// to a `tokio::io::AsyncWrite`, such as a file or a socket.
+/// Samples are discarded until one carrying a sequence parameter set is seen.
+pub struct AsyncH264Writer<W: AsyncWrite + Unpin + Send> {
+    writer: BufWriter<W>,
+    has_key_frame: bool,
+    closed: bool,
+}
+
+impl<W: AsyncWrite + Unpin + Send> AsyncH264Writer<W> {
+    // new initializes a new async H264 writer with an AsyncWrite output
+    pub fn new(writer: W) -> Self {
+        AsyncH264Writer {
+            writer: BufWriter::new(writer),
+            has_key_frame: false,
+            closed: false,
+        }
+    }
+
+    /// into_inner returns the underlying writer, any unflushed data is lost
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+}
+
+#[async_trait]
+impl<W: AsyncWrite + Unpin + Send> AsyncSampleWriter for AsyncH264Writer<W> {
+    /// write_sample writes the Annex B bitstream of the sample
+    async fn write_sample(&mut self, sample: &Sample) -> Result<()> {
+        if self.closed {
+            return Err(Error::ErrFileNotOpened);
+        }
+
+        if !self.has_key_frame {
+            self.has_key_frame = has_sps(&sample.data);
+            if !self.has_key_frame {
+                // key frame not defined yet. discarding sample
+                return Ok(());
+            }
+        }
+
+        self.writer.write_all(&sample.data).await?;
+
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.writer.flush().await?;
+        Ok(())
+    }
+
+    /// close flushes and shuts down the underlying writer
+    async fn close(&mut self) -> Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.closed = true;
+
+        self.writer.shutdown().await?;
+        Ok(())
+    }
+}
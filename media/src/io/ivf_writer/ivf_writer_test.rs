@@ -188,3 +188,65 @@ fn test_ivf_writer_add_packet_and_close() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_async_ivf_writer_write_sample_and_close() -> Result<()> {
+    let header = IVFFileHeader {
+        signature: *b"DKIF",
+        version: 0,
+        header_size: 32,
+        four_cc: *b"VP80",
+        width: 640,
+        height: 480,
+        timebase_denominator: 30,
+        timebase_numerator: 1,
+        num_frames: 0,
+        unused: 0,
+    };
+
+    // A key frame followed by two inter frames
+    let frames = vec![
+        Bytes::from_static(&[0x10, 0x02, 0x00, 0x9d, 0x01, 0x2a, 0x80, 0x02, 0xe0, 0x01]),
+        Bytes::from_static(&[0x31, 0x01, 0x00, 0xaa]),
+        Bytes::from_static(&[0x31, 0x01, 0x00, 0xbb, 0xcc]),
+    ];
+
+    let mut writer = AsyncIVFWriter::new(Vec::<u8>::new(), &header).await?;
+    for data in &frames {
+        writer
+            .write_sample(&Sample {
+                data: data.clone(),
+                ..Default::default()
+            })
+            .await?;
+    }
+    // Empty samples are skipped
+    writer.write_sample(&Sample::default()).await?;
+    assert_eq!(writer.frame_count(), 3);
+
+    writer.close().await?;
+    writer.close().await?;
+    assert_eq!(
+        writer
+            .write_sample(&Sample {
+                data: frames[0].clone(),
+                ..Default::default()
+            })
+            .await,
+        Err(Error::ErrFileNotOpened)
+    );
+
+    let output = writer.into_inner();
+    let (mut reader, got_header) = crate::io::ivf_reader::IVFReader::new(Cursor::new(output))?;
+    assert_eq!(got_header, header);
+
+    for (pts, data) in frames.iter().enumerate() {
+        let (frame, frame_header) = reader.parse_next_frame()?;
+        assert_eq!(frame_header.frame_size as usize, data.len());
+        assert_eq!(frame_header.timestamp, pts as u64);
+        assert_eq!(&frame[..], &data[..]);
+    }
+    assert!(reader.parse_next_frame().is_err(), "expected end of file");
+
+    Ok(())
+}
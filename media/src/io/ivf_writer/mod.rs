@@ -1,14 +1,18 @@
 #[cfg(test)]
 mod ivf_writer_test;
 
-use crate::error::Result;
-use crate::io::ivf_reader::IVFFileHeader;
-use crate::io::Writer;
+use crate::error::{Error, Result};
+use crate::io::ivf_reader::{IVFFileHeader, IVF_FILE_HEADER_SIZE, IVF_FRAME_HEADER_SIZE};
+use crate::io::{AsyncSampleWriter, Writer};
+use crate::Sample;
+
+use async_trait::async_trait;
 
 use byteorder::{LittleEndian, WriteBytesExt};
-use bytes::{Bytes, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 use rtp::packetizer::Depacketizer;
 use std::io::{Seek, SeekFrom, Write};
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
 
 /// IVFWriter is used to take RTP packets and write them to an IVF on disk
 pub struct IVFWriter<W: Write + Seek> {
@@ -36,23 +40,34 @@ impl<W: Write + Seek> IVFWriter<W> {
     }
 
     fn write_header(&mut self, header: &IVFFileHeader) -> Result<()> {
-        self.writer.write_all(&header.signature)?; // DKIF
-        self.writer.write_u16::<LittleEndian>(header.version)?; // version
-        self.writer.write_u16::<LittleEndian>(header.header_size)?; // Header size
-        self.writer.write_all(&header.four_cc)?; // FOURCC
-        self.writer.write_u16::<LittleEndian>(header.width)?; // Width in pixels
-        self.writer.write_u16::<LittleEndian>(header.height)?; // Height in pixels
-        self.writer
-            .write_u32::<LittleEndian>(header.timebase_denominator)?; // Framerate denominator
-        self.writer
-            .write_u32::<LittleEndian>(header.timebase_numerator)?; // Framerate numerator
-        self.writer.write_u32::<LittleEndian>(header.num_frames)?; // Frame count, will be updated on first Close() call
-        self.writer.write_u32::<LittleEndian>(header.unused)?; // Unused
+        self.writer.write_all(&marshal_header(header))?;
 
         Ok(())
     }
 }
 
+fn marshal_header(header: &IVFFileHeader) -> Bytes {
+    let mut buf = BytesMut::with_capacity(IVF_FILE_HEADER_SIZE);
+    buf.put_slice(&header.signature); // DKIF
+    buf.put_u16_le(header.version); // version
+    buf.put_u16_le(header.header_size); // Header size
+    buf.put_slice(&header.four_cc); // FOURCC
+    buf.put_u16_le(header.width); // Width in pixels
+    buf.put_u16_le(header.height); // Height in pixels
+    buf.put_u32_le(header.timebase_denominator); // Framerate denominator
+    buf.put_u32_le(header.timebase_numerator); // Framerate numerator
+    buf.put_u32_le(header.num_frames); // Frame count, will be updated on first Close() call
+    buf.put_u32_le(header.unused); // Unused
+    buf.freeze()
+}
+
+fn marshal_frame_header(frame_length: usize, pts: u64) -> Bytes {
+    let mut buf = BytesMut::with_capacity(IVF_FRAME_HEADER_SIZE);
+    buf.put_u32_le(frame_length as u32); // Frame length
+    buf.put_u64_le(pts); // PTS
+    buf.freeze()
+}
+
 impl<W: Write + Seek> Writer for IVFWriter<W> {
     /// write_rtp adds a new packet and writes the appropriate headers for it
     fn write_rtp(&mut self, packet: &rtp::packet::Packet) -> Result<()> {
@@ -94,8 +109,8 @@ impl<W: Write + Seek> Writer for IVFWriter<W> {
             return Ok(());
         }
 
-        self.writer.write_u32::<LittleEndian>(frame_length as u32)?; // Frame length
-        self.writer.write_u64::<LittleEndian>(self.count)?; // PTS
+        self.writer
+            .write_all(&marshal_frame_header(frame_length, self.count))?;
         self.count += 1;
 
         let frame_content = if let Some(current_frame) = self.current_frame.take() {
@@ -119,3 +134,76 @@ impl<W: Write + Seek> Writer for IVFWriter<W> {
         Ok(())
     }
 }
+
+/// AsyncIVFWriter is used to take samples and write them to an IVF over a
+/// `tokio::io::AsyncWrite`, such as a file or a socket.
+///
+/// Since the output may not be seekable, the frame count in the file header is
+/// written as given in the header passed to `new` and isn't updated on close.
+pub struct AsyncIVFWriter<W: AsyncWrite + Unpin + Send> {
+    writer: BufWriter<W>,
+    count: u64,
+    closed: bool,
+}
+
+impl<W: AsyncWrite + Unpin + Send> AsyncIVFWriter<W> {
+    /// new initialize a new async IVF writer and writes the file header
+    pub async fn new(writer: W, header: &IVFFileHeader) -> Result<Self> {
+        let mut w = AsyncIVFWriter {
+            writer: BufWriter::new(writer),
+            count: 0,
+            closed: false,
+        };
+
+        w.writer.write_all(&marshal_header(header)).await?;
+
+        Ok(w)
+    }
+
+    /// frame_count returns the number of frames written so far
+    pub fn frame_count(&self) -> u64 {
+        self.count
+    }
+
+    /// into_inner returns the underlying writer, any unflushed data is lost
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+}
+
+#[async_trait]
+impl<W: AsyncWrite + Unpin + Send> AsyncSampleWriter for AsyncIVFWriter<W> {
+    /// write_sample writes the sample as a single frame
+    async fn write_sample(&mut self, sample: &Sample) -> Result<()> {
+        if self.closed {
+            return Err(Error::ErrFileNotOpened);
+        }
+        if sample.data.is_empty() {
+            return Ok(());
+        }
+
+        self.writer
+            .write_all(&marshal_frame_header(sample.data.len(), self.count))
+            .await?;
+        self.writer.write_all(&sample.data).await?;
+        self.count += 1;
+
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.writer.flush().await?;
+        Ok(())
+    }
+
+    /// close flushes the recording and shuts down the writer
+    async fn close(&mut self) -> Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.closed = true;
+
+        self.writer.shutdown().await?;
+        Ok(())
+    }
+}
@@ -1,6 +1,9 @@
 pub mod h264_reader;
 pub mod h264_writer;
 use crate::error::Result;
+use crate::Sample;
+
+use async_trait::async_trait;
 
 pub mod ivf_reader;
 pub mod ivf_writer;
@@ -19,3 +22,19 @@ pub trait Writer {
     // Note: close implementation must be idempotent
     fn close(&mut self) -> Result<()>;
 }
+
+/// AsyncSampleWriter is the async counterpart of [`Writer`]: it writes assembled
+/// samples to a `tokio::io::AsyncWrite`, e.g. a file or a socket.
+///
+/// Writes are buffered, call `flush` to push them to the underlying writer and
+/// `close` to finalize the media.
+#[async_trait]
+pub trait AsyncSampleWriter {
+    /// Add a sample to the media
+    async fn write_sample(&mut self, sample: &Sample) -> Result<()>;
+    /// Write all buffered data to the underlying writer
+    async fn flush(&mut self) -> Result<()>;
+    /// close the media, flushing and shutting down the underlying writer
+    /// Note: close implementation must be idempotent
+    async fn close(&mut self) -> Result<()>;
+}
@@ -61,3 +61,44 @@ fn test_ogg_writer_add_packet_and_close() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_async_ogg_writer_write_sample_and_close() -> Result<()> {
+    let mut writer = AsyncOggWriter::new(Vec::<u8>::new(), 48000, 2).await?;
+    for i in 0..3u32 {
+        writer
+            .write_sample(&Sample {
+                data: Bytes::from(vec![0x98, 0x36, i as u8]),
+                packet_timestamp: 1000 + i * 960,
+                ..Default::default()
+            })
+            .await?;
+    }
+    writer.flush().await?;
+    writer.close().await?;
+    writer.close().await?;
+
+    let output = writer.into_inner();
+    let (mut reader, header) = OggReader::new(Cursor::new(output), true)?;
+    assert_eq!(header.sample_rate, 48000);
+    assert_eq!(header.channels, 2);
+
+    // Comment header
+    reader.parse_next_page()?;
+
+    let mut granule_position = 1;
+    for i in 0..3u8 {
+        let (payload, page_header) = reader.parse_next_page()?;
+        assert_eq!(&payload[..], &[0x98, 0x36, i]);
+        assert_eq!(page_header.granule_position, granule_position);
+        granule_position += 960;
+    }
+
+    // End of stream
+    let (payload, page_header) = reader.parse_next_page()?;
+    assert_eq!(&payload[..], &[0x98, 0x36, 2]);
+    assert_eq!(page_header.granule_position, granule_position - 960);
+    assert!(reader.parse_next_page().is_err(), "expected end of stream");
+
+    Ok(())
+}
@@ -8,20 +8,58 @@ pub mod sample_sequence_location;
 use std::time::{Duration, SystemTime};
 
 use bytes::Bytes;
-use rtp::{packet::Packet, packetizer::Depacketizer};
+use rtp::extension::video_orientation_extension::VideoOrientationExtension;
+use rtp::{packet::Packet, packetizer::Depacketizer, seqnum};
+use util::Unmarshal;
 
 use crate::Sample;
 
 use self::sample_sequence_location::{Comparison, SampleSequenceLocation};
 
+/// Describes the RTP packets a [`Sample`] was built from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SampleMeta {
+    /// The RTP timestamp shared by all packets of the sample.
+    pub packet_timestamp: u32,
+
+    /// The sequence number of the first packet of the sample.
+    pub first_sequence_number: u16,
+
+    /// The sequence number of the last packet of the sample.
+    pub last_sequence_number: u16,
+
+    /// The number of packets the sample was built from.
+    pub packet_count: u16,
+
+    /// The time at which the last of the sample's packets arrived, i.e. when the sample became
+    /// complete.
+    pub last_arrival_time: SystemTime,
+
+    /// The number of packets dropped since the previous sample, see
+    /// [`Sample::prev_dropped_packets`].
+    pub prev_dropped_packets: u16,
+
+    /// The number of padding packets dropped since the previous sample, see
+    /// [`Sample::prev_padding_packets`].
+    pub prev_padding_packets: u16,
+}
+
+/// A packet waiting in the buffer of a [`SampleBuilder`].
+#[derive(Debug, Clone, PartialEq)]
+struct BufferedPacket {
+    packet: Packet,
+    /// when the packet was received
+    arrival_time: SystemTime,
+}
+
 /// SampleBuilder buffers packets until media frames are complete.
 pub struct SampleBuilder<T: Depacketizer> {
     /// how many packets to wait until we get a valid Sample
     max_late: u16,
     /// max timestamp between old and new timestamps before dropping packets
     max_late_timestamp: u32,
-    buffer: Vec<Option<Packet>>,
-    prepared_samples: Vec<Option<Sample>>,
+    buffer: Vec<Option<BufferedPacket>>,
+    prepared_samples: Vec<Option<(Sample, SampleMeta)>>,
     last_sample_timestamp: Option<u32>,
 
     /// Interface that allows us to take RTP packets to samples
@@ -45,6 +83,12 @@ pub struct SampleBuilder<T: Depacketizer> {
     /// number of padding packets detected and dropped. This number will be a subset of
     /// `droppped_packets`
     padding_packets: u16,
+
+    /// id of the video orientation header extension, if it was negotiated
+    video_orientation_extension_id: Option<u8>,
+
+    /// the most recent video orientation received
+    orientation: Option<VideoOrientationExtension>,
 }
 
 impl<T: Depacketizer> SampleBuilder<T> {
@@ -68,6 +112,8 @@ impl<T: Depacketizer> SampleBuilder<T> {
             prepared: SampleSequenceLocation::new(),
             dropped_packets: 0,
             padding_packets: 0,
+            video_orientation_extension_id: None,
+            orientation: None,
         }
     }
 
@@ -77,6 +123,17 @@ impl<T: Depacketizer> SampleBuilder<T> {
         self
     }
 
+    /// Reads the video orientation (CVO) header extension with the given id from the packets, see
+    /// [`Sample::orientation`]. Senders only need to send it on some packets, e.g. the last packet
+    /// of key frames and when the orientation changes, so every sample gets the most recent one
+    /// received up to its last packet.
+    ///
+    /// The id is the one negotiated for `urn:3gpp:video-orientation`.
+    pub fn with_video_orientation_extension_id(mut self, id: u8) -> Self {
+        self.video_orientation_extension_id = Some(id);
+        self
+    }
+
     fn too_old(&self, location: &SampleSequenceLocation) -> bool {
         if self.max_late_timestamp == 0 {
             return false;
@@ -87,7 +144,7 @@ impl<T: Depacketizer> SampleBuilder<T> {
 
         let mut i = location.head;
         while i != location.tail {
-            if let Some(ref packet) = self.buffer[i as usize] {
+            if let Some(packet) = self.packet(i) {
                 found_head = Some(packet.header.timestamp);
                 break;
             }
@@ -100,7 +157,7 @@ impl<T: Depacketizer> SampleBuilder<T> {
 
         let mut i = location.tail.wrapping_sub(1);
         while i != location.head {
-            if let Some(ref packet) = self.buffer[i as usize] {
+            if let Some(packet) = self.packet(i) {
                 found_tail = Some(packet.header.timestamp);
                 break;
             }
@@ -119,15 +176,15 @@ impl<T: Depacketizer> SampleBuilder<T> {
         if location.empty() {
             None
         } else {
-            Some(
-                (self.buffer[location.head as usize])
-                    .as_ref()?
-                    .header
-                    .timestamp,
-            )
+            Some(self.packet(location.head)?.header.timestamp)
         }
     }
 
+    /// Returns the packet buffered at sequence number i
+    fn packet(&self, i: u16) -> Option<&Packet> {
+        self.buffer[i as usize].as_ref().map(|b| &b.packet)
+    }
+
     fn release_packet(&mut self, i: u16) {
         self.buffer[i as usize] = None;
     }
@@ -198,8 +255,17 @@ impl<T: Depacketizer> SampleBuilder<T> {
     /// Push does not copy the input. If you wish to reuse
     /// this memory make sure to copy before calling push
     pub fn push(&mut self, p: Packet) {
+        self.push_with_arrival_time(p, SystemTime::now());
+    }
+
+    /// Adds an RTP Packet to self's buffer, recording `arrival_time` as the time it was received
+    /// rather than the time of the call. See [`SampleMeta::last_arrival_time`].
+    pub fn push_with_arrival_time(&mut self, p: Packet, arrival_time: SystemTime) {
         let sequence_number = p.header.sequence_number;
-        self.buffer[sequence_number as usize] = Some(p);
+        self.buffer[sequence_number as usize] = Some(BufferedPacket {
+            packet: p,
+            arrival_time,
+        });
         match self.filled.compare(sequence_number) {
             Comparison::Void => {
                 self.filled.head = sequence_number;
@@ -240,7 +306,7 @@ impl<T: Depacketizer> SampleBuilder<T> {
         let mut i = self.active.head;
         // `self.active` isn't modified in the loop, fetch the timestamp once and cache it.
         let head_timestamp = self.fetch_timestamp(&self.active);
-        while let Some(ref packet) = self.buffer[i as usize] {
+        while let Some(packet) = self.packet(i) {
             if self.active.compare(i) == Comparison::After {
                 break;
             }
@@ -288,7 +354,7 @@ impl<T: Depacketizer> SampleBuilder<T> {
 
         // scan for any packet after the current and use that time stamp as the diff point
         for i in consume.tail..self.active.tail {
-            if let Some(ref packet) = self.buffer[i as usize] {
+            if let Some(packet) = self.packet(i) {
                 after_timestamp = packet.header.timestamp;
                 break;
             }
@@ -296,16 +362,17 @@ impl<T: Depacketizer> SampleBuilder<T> {
 
         // prior to decoding all the packets, check if this packet
         // would end being disposed anyway
-        let head_payload = self.buffer[consume.head as usize]
-            .as_ref()
+        let head_payload = self
+            .packet(consume.head)
             .map(|p| &p.payload)
             .ok_or(BuildError::GapInSegment)?;
         if !self.depacketizer.is_partition_head(head_payload) {
             // libWebRTC will sometimes send several empty padding packets to smooth out send
             // rate. These packets don't carry any media payloads.
-            let is_padding = consume.range(&self.buffer).all(|p| {
-                p.map(|p| {
-                    self.last_sample_timestamp == Some(p.header.timestamp) && p.payload.is_empty()
+            let is_padding = consume.range(&self.buffer).all(|b| {
+                b.map(|b| {
+                    self.last_sample_timestamp == Some(b.packet.header.timestamp)
+                        && b.packet.payload.is_empty()
                 })
                 .unwrap_or(false)
             });
@@ -326,12 +393,29 @@ impl<T: Depacketizer> SampleBuilder<T> {
 
         // merge all the buffers into a sample
         let mut data: Vec<u8> = Vec::new();
+        let mut last_arrival_time: Option<SystemTime> = None;
         let mut i = consume.head;
         while i != consume.tail {
-            let payload = self.buffer[i as usize]
+            let BufferedPacket {
+                packet,
+                arrival_time,
+            } = self.buffer[i as usize]
                 .as_ref()
-                .map(|p| &p.payload)
                 .ok_or(BuildError::GapInSegment)?;
+            let payload = &packet.payload;
+
+            if let Some(mut ext) = self
+                .video_orientation_extension_id
+                .and_then(|id| packet.header.get_extension(id))
+            {
+                if let Ok(orientation) = VideoOrientationExtension::unmarshal(&mut ext) {
+                    self.orientation = Some(orientation);
+                }
+            }
+
+            if last_arrival_time.map_or(true, |t| t < *arrival_time) {
+                last_arrival_time = Some(*arrival_time);
+            }
 
             let p = self
                 .depacketizer
@@ -350,13 +434,24 @@ impl<T: Depacketizer> SampleBuilder<T> {
             packet_timestamp: sample_timestamp,
             prev_dropped_packets: self.dropped_packets,
             prev_padding_packets: self.padding_packets,
+            orientation: self.orientation,
+        };
+
+        let meta = SampleMeta {
+            packet_timestamp: sample_timestamp,
+            first_sequence_number: consume.head,
+            last_sequence_number: consume.tail.wrapping_sub(1),
+            packet_count: consume.count(),
+            last_arrival_time: last_arrival_time.unwrap_or(sample.timestamp),
+            prev_dropped_packets: self.dropped_packets,
+            prev_padding_packets: self.padding_packets,
         };
 
         self.dropped_packets = 0;
         self.padding_packets = 0;
         self.last_sample_timestamp = Some(sample_timestamp);
 
-        self.prepared_samples[self.prepared.tail as usize] = Some(sample);
+        self.prepared_samples[self.prepared.tail as usize] = Some((sample, meta));
         self.prepared.tail = self.prepared.tail.wrapping_add(1);
 
         self.purge_consumed_location(&consume, true);
@@ -368,6 +463,13 @@ impl<T: Depacketizer> SampleBuilder<T> {
     /// Compiles pushed RTP packets into media samples and then
     /// returns the next valid sample (or None if no sample is compiled).
     pub fn pop(&mut self) -> Option<Sample> {
+        self.pop_with_meta().map(|(sample, _)| sample)
+    }
+
+    /// Compiles pushed RTP packets into media samples and then
+    /// returns the next valid sample along with a description of the RTP packets it was built
+    /// from (or `None` if no sample is compiled).
+    pub fn pop_with_meta(&mut self) -> Option<(Sample, SampleMeta)> {
         let _ = self.build_sample(false);
 
         if self.prepared.empty() {
@@ -395,21 +497,8 @@ impl<T: Depacketizer> SampleBuilder<T> {
 }
 
 /// Computes the distance between two sequence numbers
-/*pub(crate) fn seqnum_distance(head: u16, tail: u16) -> u16 {
-    if head > tail {
-        head.wrapping_add(tail)
-    } else {
-        tail - head
-    }
-}*/
-
 pub(crate) fn seqnum_distance(x: u16, y: u16) -> u16 {
-    let diff = x.wrapping_sub(y);
-    if diff > 0xFFFF / 2 {
-        0xFFFF - diff + 1
-    } else {
-        diff
-    }
+    seqnum::diff(x, y).unsigned_abs() as u16
 }
 
 #[derive(Debug)]
@@ -1435,14 +1435,14 @@ fn test_sample_builder_clean_reference() {
         for i in 0..3 {
             assert_eq!(
                 None,
-                s.buffer[seq_start.wrapping_add(i) as usize],
+                s.packet(seq_start.wrapping_add(i)),
                 "Old packet ({}) is not unreferenced (seq_start: {}, max_late: 10, pushed: 12)",
                 i,
                 seq_start
             );
         }
-        assert_eq!(Some(pkt4), s.buffer[seq_start.wrapping_add(14) as usize]);
-        assert_eq!(Some(pkt5), s.buffer[seq_start.wrapping_add(12) as usize]);
+        assert_eq!(Some(&pkt4), s.packet(seq_start.wrapping_add(14)));
+        assert_eq!(Some(&pkt5), s.packet(seq_start.wrapping_add(12)));
     }
 }
 
@@ -1496,3 +1496,199 @@ fn test_sample_builder_data() {
     // only the last packet should be dropped
     assert_eq!(j, 0x1FFFF);
 }
+
+#[test]
+fn test_pop_with_meta() {
+    let base = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+    let mut s = SampleBuilder::new(50, FakeDepacketizer::new(), 1);
+
+    // (sequence number, timestamp, marker, arrival offset in ms)
+    for (sequence_number, timestamp, marker, arrival) in [
+        (100, 10, false, 5),
+        (101, 10, false, 9),
+        (102, 10, true, 7),
+        (103, 20, true, 12),
+        (104, 30, false, 20),
+    ] {
+        s.push_with_arrival_time(
+            Packet {
+                header: Header {
+                    sequence_number,
+                    timestamp,
+                    marker,
+                    ..Default::default()
+                },
+                payload: Bytes::copy_from_slice(&[sequence_number as u8]),
+            },
+            base + Duration::from_millis(arrival),
+        );
+    }
+
+    let (sample, meta) = s.pop_with_meta().expect("should build the first sample");
+    assert_eq!(sample.data, Bytes::from_static(&[100, 101, 102]));
+    assert_eq!(
+        meta,
+        SampleMeta {
+            packet_timestamp: 10,
+            first_sequence_number: 100,
+            last_sequence_number: 102,
+            packet_count: 3,
+            last_arrival_time: base + Duration::from_millis(9),
+            prev_dropped_packets: 0,
+            prev_padding_packets: 0,
+        }
+    );
+
+    let (sample, meta) = s.pop_with_meta().expect("should build the second sample");
+    assert_eq!(sample.data, Bytes::from_static(&[103]));
+    assert_eq!(
+        meta,
+        SampleMeta {
+            packet_timestamp: 20,
+            first_sequence_number: 103,
+            last_sequence_number: 103,
+            packet_count: 1,
+            last_arrival_time: base + Duration::from_millis(12),
+            prev_dropped_packets: 0,
+            prev_padding_packets: 0,
+        }
+    );
+
+    assert!(s.pop_with_meta().is_none());
+}
+
+#[test]
+fn test_pop_with_meta_after_loss() {
+    let base = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+    let d = FakeDepacketizer {
+        head_checker: true,
+        head_bytes: vec![bytes!(0x01)],
+    };
+    let mut s = SampleBuilder::new(4, d, 1);
+
+    // The second frame (302..=304) loses 303 and is skipped
+    for (sequence_number, timestamp, marker, payload) in [
+        (300, 10, false, 0x01),
+        (301, 10, true, 0x02),
+        (302, 20, false, 0x01),
+        (304, 20, true, 0x02),
+        (305, 30, false, 0x01),
+        (306, 30, true, 0x02),
+        (307, 40, false, 0x01),
+    ] {
+        s.push_with_arrival_time(
+            Packet {
+                header: Header {
+                    sequence_number,
+                    timestamp,
+                    marker,
+                    ..Default::default()
+                },
+                payload: Bytes::copy_from_slice(&[payload]),
+            },
+            base + Duration::from_millis(u64::from(sequence_number)),
+        );
+    }
+
+    let (sample, meta) = s.pop_with_meta().expect("should build the first sample");
+    assert_eq!(sample.data, Bytes::from_static(&[0x01, 0x02]));
+    assert_eq!(
+        meta,
+        SampleMeta {
+            packet_timestamp: 10,
+            first_sequence_number: 300,
+            last_sequence_number: 301,
+            packet_count: 2,
+            last_arrival_time: base + Duration::from_millis(301),
+            prev_dropped_packets: 0,
+            prev_padding_packets: 0,
+        }
+    );
+
+    let (sample, meta) = s.pop_with_meta().expect("should build the third sample");
+    assert_eq!(sample.data, Bytes::from_static(&[0x01, 0x02]));
+    assert_eq!(sample.prev_dropped_packets, meta.prev_dropped_packets);
+    assert_eq!(
+        meta,
+        SampleMeta {
+            packet_timestamp: 30,
+            first_sequence_number: 305,
+            last_sequence_number: 306,
+            packet_count: 2,
+            last_arrival_time: base + Duration::from_millis(306),
+            // 302, the missing 303 and the orphaned tail 304
+            prev_dropped_packets: 3,
+            prev_padding_packets: 0,
+        }
+    );
+
+    assert!(s.pop_with_meta().is_none());
+}
+
+#[test]
+fn test_sample_builder_video_orientation() {
+    use rtp::extension::video_orientation_extension::{CameraDirection, VideoRotation};
+    use util::Marshal;
+
+    let rotation = |rotation, flip| VideoOrientationExtension {
+        direction: CameraDirection::Front,
+        flip,
+        rotation,
+    };
+    let mut s =
+        SampleBuilder::new(50, FakeDepacketizer::new(), 1).with_video_orientation_extension_id(4);
+
+    // (sequence number, timestamp, marker, orientation), the orientation is only sent on the
+    // last packet of some frames and the last packet of the third frame arrives early
+    let packets = [
+        (200, 10, false, None),
+        (
+            201,
+            10,
+            true,
+            Some(rotation(VideoRotation::Degree90, false)),
+        ),
+        (
+            205,
+            30,
+            true,
+            Some(rotation(VideoRotation::Degree180, true)),
+        ),
+        (202, 20, false, None),
+        (203, 20, true, None),
+        (204, 30, false, None),
+        (206, 40, true, None),
+        (207, 50, true, None),
+    ];
+    for (sequence_number, timestamp, marker, orientation) in packets {
+        let mut header = Header {
+            sequence_number,
+            timestamp,
+            marker,
+            ..Default::default()
+        };
+        if let Some(orientation) = orientation {
+            header
+                .set_extension(4, orientation.marshal().unwrap())
+                .unwrap();
+        }
+        s.push(Packet {
+            header,
+            payload: Bytes::copy_from_slice(&[sequence_number as u8]),
+        });
+    }
+
+    let mut orientations = vec![];
+    while let Some(sample) = s.pop() {
+        orientations.push((sample.packet_timestamp, sample.orientation));
+    }
+    assert_eq!(
+        orientations,
+        vec![
+            (10, Some(rotation(VideoRotation::Degree90, false))),
+            (20, Some(rotation(VideoRotation::Degree90, false))),
+            (30, Some(rotation(VideoRotation::Degree180, true))),
+            (40, Some(rotation(VideoRotation::Degree180, true))),
+        ]
+    );
+}
@@ -1,4 +1,5 @@
 use super::seqnum_distance;
+use rtp::seqnum;
 
 #[derive(Debug, PartialEq)]
 pub(crate) enum Comparison {
@@ -54,20 +55,17 @@ impl SampleSequenceLocation {
         seqnum_distance(self.head, self.tail)
     }
 
+    /// compare tells where pos is relative to the sequence, in the order of `seqnum::is_newer`.
     pub(crate) fn compare(&self, pos: u16) -> Comparison {
         if self.head == self.tail {
             return Comparison::Void;
         }
-        if self.head < self.tail {
-            if self.head <= pos && pos < self.tail {
-                return Comparison::Inside;
-            }
-        } else if self.head <= pos || pos < self.tail {
-            return Comparison::Inside;
-        }
-        if self.head.wrapping_sub(pos) <= pos.wrapping_sub(self.tail) {
+        if seqnum::is_newer(self.head, pos) {
             return Comparison::Before;
         }
+        if seqnum::is_newer(self.tail, pos) {
+            return Comparison::Inside;
+        }
         Comparison::After
     }
 
@@ -43,3 +43,24 @@ fn test_sample_sequence_location_range() {
         [Some(65533), None, Some(65535), Some(0), None, Some(2)]
     );
 }
+
+#[test]
+fn test_sample_sequence_location_compare_half_way() {
+    // Positions are ordered against head and tail as sequence numbers, so a position is before
+    // the sequence if it precedes head by less than half of the sequence number space.
+    let s = SampleSequenceLocation {
+        head: 100,
+        tail: 200,
+    };
+    assert_eq!(Comparison::Before, s.compare(100u16.wrapping_sub(0x7fff)));
+    assert_eq!(Comparison::After, s.compare(100u16.wrapping_sub(0x8001)));
+    assert_eq!(Comparison::After, s.compare(200 + 0x7f00));
+
+    let s = SampleSequenceLocation {
+        head: 0xfff0,
+        tail: 0x0010,
+    };
+    assert_eq!(Comparison::Before, s.compare(0xfff0 - 0x7fff));
+    assert_eq!(Comparison::After, s.compare(0xfff0 - 0x8001));
+    assert_eq!(Comparison::Inside, s.compare(0x000f));
+}
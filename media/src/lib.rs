@@ -10,6 +10,7 @@ pub mod video;
 pub use error::Error;
 
 use bytes::Bytes;
+use rtp::extension::video_orientation_extension::VideoOrientationExtension;
 use std::time::{Duration, SystemTime};
 
 /// A Sample contains encoded media and timing information
@@ -60,13 +61,23 @@ pub struct Sample {
     /// #   duration: Duration::from_secs(0),
     /// #   packet_timestamp: 0,
     /// #   prev_dropped_packets: 10,
-    /// #   prev_padding_packets: 15
+    /// #   prev_padding_packets: 15,
+    /// #   orientation: None,
     /// # };
     /// #
     /// let adjusted_dropped =
     /// sample.prev_dropped_packets.saturating_sub(sample.prev_padding_packets);
     /// ```
     pub prev_padding_packets: u16,
+
+    /// The orientation of the video, i.e. its rotation and whether it is mirrored, which the
+    /// receiver should apply when rendering it.
+    ///
+    /// When building samples it is the most recent video orientation (CVO) header extension
+    /// received, see [`io::sample_builder::SampleBuilder::with_video_orientation_extension_id`].
+    /// When writing samples it is sent on the last packet of the sample, if the extension was
+    /// negotiated.
+    pub orientation: Option<VideoOrientationExtension>,
 }
 
 impl Default for Sample {
@@ -78,6 +89,7 @@ impl Default for Sample {
             packet_timestamp: 0,
             prev_dropped_packets: 0,
             prev_padding_packets: 0,
+            orientation: None,
         }
     }
 }
@@ -105,6 +117,9 @@ impl PartialEq for Sample {
         if self.prev_padding_packets != other.prev_padding_packets {
             equal = false;
         }
+        if self.orientation != other.orientation {
+            equal = false;
+        }
 
         equal
     }
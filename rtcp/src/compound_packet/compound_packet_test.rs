@@ -1,6 +1,7 @@
 use super::*;
 use crate::goodbye::Goodbye;
 use crate::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use crate::reception_report::ReceptionReport;
 
 // An RTCP packet from a packet dump
 const REAL_PACKET: [u8; 116] = [
@@ -32,6 +33,34 @@ const REAL_PACKET: [u8; 116] = [
     0x90, 0x2f, 0x9e, 0x2e, // media=0x902f9e2e
 ];
 
+// A sender report and its SDES as sent for a simulcast layer, with the layout of browser
+// compound packets and the MID and RtpStreamId items of RFC 8852 after the CNAME
+const SIMULCAST_LAYER_PACKET: [u8; 88] = [
+    // Sender Report (offset=0)
+    0x81, 0xc8, 0x0, 0xc, // v=2, p=0, count=1, SR, len=12
+    0x5d, 0x3a, 0x8c, 0x21, // ssrc=0x5d3a8c21
+    0xe8, 0xa1, 0xb2, 0xc3, // ntp=0xe8a1b2c34d5e6f70
+    0x4d, 0x5e, 0x6f, 0x70, //
+    0x12, 0x34, 0x56, 0x78, // rtp=0x12345678
+    0x0, 0x0, 0x4, 0xb4, // packet count=1204
+    0x0, 0xf, 0x72, 0x79, // octet count=1012345
+    0x9f, 0x21, 0xa3, 0xb4, // ssrc=0x9f21a3b4
+    0x0, 0x0, 0x0, 0x0, // fracLost=0, totalLost=0
+    0x0, 0x1, 0x12, 0x34, // lastSeq=0x11234
+    0x0, 0x0, 0x0, 0x12, // jitter=18
+    0xb2, 0xc3, 0x4d, 0x5e, // lsr=0xb2c34d5e
+    0x0, 0x1, 0x0, 0x0, // delay=65536
+    // Source Description (offset=52)
+    0x81, 0xca, 0x0, 0x8, // v=2, p=0, count=1, SDES, len=8
+    0x5d, 0x3a, 0x8c, 0x21, // ssrc=0x5d3a8c21
+    0x1, 0x10, // CNAME, len=16
+    0x50, 0x74, 0x34, 0x5a, 0x73, 0x47, 0x5a, 0x76, 0x33, 0x6d, 0x47, 0x58, 0x31, 0x61, 0x37,
+    0x6f, // text="Pt4ZsGZv3mGX1a7o"
+    0xf, 0x1, 0x30, // MID, len=1, text="0"
+    0xc, 0x1, 0x68, // RtpStreamId, len=1, text="h"
+    0x0, 0x0, 0x0, 0x0, // END + padding
+];
+
 #[test]
 fn test_read_eof() {
     let mut short_header = Bytes::from_static(&[
@@ -88,6 +117,81 @@ fn test_bad_compound() {
     }
 }
 
+#[test]
+fn test_simulcast_layer_packet() {
+    let mut data = Bytes::from_static(&SIMULCAST_LAYER_PACKET);
+    let compound = CompoundPacket(unmarshal(&mut data).expect("Error unmarshalling packet"));
+    compound
+        .validate()
+        .expect("should be a valid compound packet");
+    assert_eq!(
+        compound.cname().expect("should have a CNAME"),
+        "Pt4ZsGZv3mGX1a7o"
+    );
+    assert_eq!(compound.0.len(), 2);
+
+    let sr = compound.0[0]
+        .as_any()
+        .downcast_ref::<SenderReport>()
+        .expect("Unmarshal(simulcast layer), want SenderReport");
+    assert_eq!(
+        sr,
+        &SenderReport {
+            ssrc: 0x5d3a8c21,
+            ntp_time: 0xe8a1b2c34d5e6f70,
+            rtp_time: 0x12345678,
+            packet_count: 1204,
+            octet_count: 1012345,
+            reports: vec![ReceptionReport {
+                ssrc: 0x9f21a3b4,
+                fraction_lost: 0,
+                total_lost: 0,
+                last_sequence_number: 0x11234,
+                jitter: 18,
+                last_sender_report: 0xb2c34d5e,
+                delay: 65536,
+            }],
+            ..Default::default()
+        }
+    );
+
+    let sdes = compound.0[1]
+        .as_any()
+        .downcast_ref::<SourceDescription>()
+        .expect("Unmarshal(simulcast layer), want SourceDescription");
+    assert_eq!(
+        sdes,
+        &SourceDescription {
+            chunks: vec![SourceDescriptionChunk {
+                source: 0x5d3a8c21,
+                items: vec![
+                    SourceDescriptionItem {
+                        sdes_type: SdesType::SdesCname,
+                        text: Bytes::from_static(b"Pt4ZsGZv3mGX1a7o"),
+                    },
+                    SourceDescriptionItem {
+                        sdes_type: SdesType::SdesMid,
+                        text: Bytes::from_static(b"0"),
+                    },
+                    SourceDescriptionItem {
+                        sdes_type: SdesType::SdesRtpStreamId,
+                        text: Bytes::from_static(b"h"),
+                    },
+                ],
+            }],
+        }
+    );
+
+    let mut raw = compound.marshal().expect("Error marshalling packet");
+    let pkts = unmarshal(&mut raw).expect("Error unmarshalling packet");
+    assert_eq!(pkts.len(), 2);
+    assert_eq!(pkts[0].as_any().downcast_ref::<SenderReport>(), Some(sr));
+    assert_eq!(
+        pkts[1].as_any().downcast_ref::<SourceDescription>(),
+        Some(sdes)
+    );
+}
+
 #[test]
 fn test_valid_packet() {
     let cname = SourceDescription {
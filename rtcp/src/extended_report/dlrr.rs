@@ -4,6 +4,7 @@ const DLRR_REPORT_LENGTH: u16 = 12;
 
 /// DLRRReport encodes a single report inside a DLRRReportBlock.
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DLRRReport {
     pub ssrc: u32,
     pub last_rr: u32,
@@ -35,6 +36,7 @@ impl fmt::Display for DLRRReport {
 /// :                               ...                             :   2
 /// +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DLRRReportBlock {
     pub reports: Vec<DLRRReport>,
 }
@@ -33,6 +33,7 @@ const XR_HEADER_LENGTH: usize = 4;
 /// BlockType specifies the type of report in a report block
 /// Extended Report block types from RFC 3611.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BlockType {
     Unknown = 0,
     LossRLE = 1,               // RFC 3611, section 4.1
@@ -94,6 +95,7 @@ pub type TypeSpecificField = u8;
 /// blocks, these values will not be accurate until the corresponding
 /// packet is marshaled.
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XRHeader {
     pub block_type: BlockType,
     pub type_specific: TypeSpecificField,
@@ -4,6 +4,7 @@ const RLE_REPORT_BLOCK_MIN_LENGTH: u16 = 8;
 
 /// ChunkType enumerates the three kinds of chunks described in RFC 3611 section 4.1.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ChunkType {
     RunLength = 0,
     BitVector = 1,
@@ -37,6 +38,7 @@ pub enum ChunkType {
 ///  |0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0|
 ///  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Chunk(pub u16);
 
 impl fmt::Display for Chunk {
@@ -103,6 +105,7 @@ impl Chunk {
 /// |          chunk n-1            |             chunk n           |
 /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RLEReportBlock {
     //not included in marshal/unmarshal
     pub is_loss_rle: bool,
@@ -15,6 +15,7 @@ const RRT_REPORT_BLOCK_LENGTH: u16 = 8;
 /// |             NTP timestamp, least significant word             |
 /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ReceiverReferenceTimeReportBlock {
     pub ntp_timestamp: u64,
 }
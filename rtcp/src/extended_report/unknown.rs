@@ -3,7 +3,9 @@ use super::*;
 /// UnknownReportBlock is used to store bytes for any report block
 /// that has an unknown Report Block Type.
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnknownReportBlock {
+    #[cfg_attr(feature = "serde", serde(with = "util::serde_hex"))]
     pub bytes: Bytes,
 }
 
@@ -12,10 +12,12 @@ type Result<T> = std::result::Result<T, util::Error>;
 
 /// The Goodbye packet indicates that one or more sources are no longer active.
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Goodbye {
     /// The SSRC/CSRC identifiers that are no longer active
     pub sources: Vec<u32>,
     /// Optional text indicating the reason for leaving, e.g., "camera malfunction" or "RTP loop detected"
+    #[cfg_attr(feature = "serde", serde(with = "util::serde_hex"))]
     pub reason: Bytes,
 }
 
@@ -7,6 +7,7 @@ use bytes::{Buf, BufMut};
 /// RTCP packet types registered with IANA. See: https://www.iana.org/assignments/rtp-parameters/rtp-parameters.xhtml#rtp-parameters-4
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PacketType {
     Unsupported = 0,
     SenderReport = 200,              // RFC 3550, 6.4.1
@@ -89,6 +90,7 @@ pub const SDES_MAX_OCTET_COUNT: usize = (1 << 8) - 1;
 
 /// A Header is the common header shared by all RTCP packets
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Header {
     /// If the padding bit is set, this individual RTCP packet contains
     /// some additional padding octets at the end which are not part of
@@ -268,4 +268,57 @@ mod test {
 
         Ok(())
     }
+
+    #[cfg(feature = "serde")]
+    fn serde_round_trip<T>(packet: &(dyn Packet + Send + Sync)) -> serde_json::Value
+    where
+        T: Packet + serde::Serialize + serde::de::DeserializeOwned + PartialEq + 'static,
+    {
+        let packet = packet
+            .as_any()
+            .downcast_ref::<T>()
+            .expect("unexpected packet type");
+        let json = serde_json::to_value(packet).unwrap();
+        let parsed: T = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(&parsed, packet, "serde round trip of {}", json);
+        assert_eq!(parsed.marshal().unwrap(), packet.marshal().unwrap());
+        json
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_packet_serde_round_trip() -> Result<()> {
+        let mut data = Bytes::from_static(&[
+            // Receiver Report
+            0x81, 0xc9, 0x0, 0x7, 0x90, 0x2f, 0x9e, 0x2e, 0xbc, 0x5e, 0x9a, 0x40, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x46, 0xe1, 0x0, 0x0, 0x1, 0x11, 0x9, 0xf3, 0x64, 0x32, 0x0, 0x2, 0x4a,
+            0x79, // Source Description, CNAME "abcd"
+            0x81, 0xca, 0x0, 0x3, 0x90, 0x2f, 0x9e, 0x2e, 0x1, 0x4, 0x61, 0x62, 0x63, 0x64, 0x0,
+            0x0, // Goodbye with reason "x"
+            0x81, 0xcb, 0x0, 0x2, 0x90, 0x2f, 0x9e, 0x2e, 0x1, 0x78, 0x0, 0x0,
+            // Picture Loss Indication
+            0x81, 0xce, 0x0, 0x2, 0x90, 0x2f, 0x9e, 0x2e, 0x90, 0x2f, 0x9e, 0x2e,
+            // Transport Layer NACK
+            0x81, 0xcd, 0x0, 0x3, 0x90, 0x2f, 0x9e, 0x2e, 0x90, 0x2f, 0x9e, 0x2e, 0x0, 0x0a, 0x0,
+            0x5, // Transport Layer CC
+            143, 205, 0, 6, 65, 227, 184, 49, 118, 243, 78, 96, 42, 63, 0, 5, 12, 162, 166, 0, 32,
+            5, 200, 4, 0, 4, 0, 0, // unknown Transport Specific Feedback
+            0x87, 0xcd, 0x0, 0x1, 0x1, 0x2, 0x3, 0x4,
+        ]);
+        let packets = unmarshal(&mut data)?;
+        assert_eq!(packets.len(), 7);
+
+        serde_round_trip::<ReceiverReport>(packets[0].as_ref());
+        let json = serde_round_trip::<SourceDescription>(packets[1].as_ref());
+        assert_eq!(json["chunks"][0]["items"][0]["text"], "61626364");
+        let json = serde_round_trip::<Goodbye>(packets[2].as_ref());
+        assert_eq!(json["reason"], "78");
+        serde_round_trip::<PictureLossIndication>(packets[3].as_ref());
+        serde_round_trip::<TransportLayerNack>(packets[4].as_ref());
+        serde_round_trip::<TransportLayerCc>(packets[5].as_ref());
+        let json = serde_round_trip::<RawPacket>(packets[6].as_ref());
+        assert_eq!(json, "87cd000101020304");
+
+        Ok(())
+    }
 }
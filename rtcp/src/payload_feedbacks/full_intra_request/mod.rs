@@ -12,6 +12,7 @@ type Result<T> = std::result::Result<T, util::Error>;
 
 /// A FIREntry is a (ssrc, seqno) pair, as carried by FullIntraRequest.
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FirEntry {
     pub ssrc: u32,
     pub sequence_number: u8,
@@ -21,6 +22,7 @@ pub struct FirEntry {
 /// in a video stream.  See RFC 5104 Section 3.5.1.  This is not for loss
 /// recovery, which should use PictureLossIndication (PLI) instead.
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FullIntraRequest {
     pub sender_ssrc: u32,
     pub media_ssrc: u32,
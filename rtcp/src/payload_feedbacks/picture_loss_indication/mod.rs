@@ -14,6 +14,7 @@ const PLI_LENGTH: usize = 2;
 
 /// The PictureLossIndication packet informs the encoder about the loss of an undefined amount of coded video data belonging to one or more pictures
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PictureLossIndication {
     /// SSRC of sender
     pub sender_ssrc: u32,
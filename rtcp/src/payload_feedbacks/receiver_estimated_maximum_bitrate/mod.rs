@@ -13,6 +13,7 @@ type Result<T> = std::result::Result<T, util::Error>;
 /// ReceiverEstimatedMaximumBitrate contains the receiver's estimated maximum bitrate.
 /// see: https://tools.ietf.org/html/draft-alvestrand-rmcat-remb-03
 #[derive(Debug, PartialEq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ReceiverEstimatedMaximumBitrate {
     /// SSRC of sender
     pub sender_ssrc: u32,
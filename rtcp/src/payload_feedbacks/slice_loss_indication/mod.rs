@@ -16,6 +16,7 @@ const SLI_OFFSET: usize = 8;
 /// SLIEntry represents a single entry to the SLI packet's
 /// list of lost slices.
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SliEntry {
     /// ID of first lost slice
     pub first: u16,
@@ -27,6 +28,7 @@ pub struct SliEntry {
 
 /// The SliceLossIndication packet informs the encoder about the loss of a picture slice
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SliceLossIndication {
     /// SSRC of sender
     pub sender_ssrc: u32,
@@ -9,7 +9,8 @@ use std::fmt;
 /// RawPacket represents an unparsed RTCP packet. It's returned by Unmarshal when
 /// a packet with an unknown type is encountered.
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
-pub struct RawPacket(pub Bytes);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RawPacket(#[cfg_attr(feature = "serde", serde(with = "util::serde_hex"))] pub Bytes);
 
 impl fmt::Display for RawPacket {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
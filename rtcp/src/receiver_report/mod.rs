@@ -15,6 +15,7 @@ pub(super) const RR_REPORT_OFFSET: usize = RR_SSRC_OFFSET + SSRC_LENGTH;
 
 /// A ReceiverReport (RR) packet provides reception quality feedback for an RTP stream
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ReceiverReport {
     /// The synchronization source identifier for the originator of this RR packet.
     pub ssrc: u32,
@@ -25,6 +26,7 @@ pub struct ReceiverReport {
     pub reports: Vec<ReceptionReport>,
     /// Extension contains additional, payload-specific information that needs to
     /// be reported regularly about the receiver.
+    #[cfg_attr(feature = "serde", serde(with = "util::serde_hex"))]
     pub profile_extensions: Bytes,
 }
 
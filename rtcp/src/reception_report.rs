@@ -16,6 +16,7 @@ pub(crate) const DELAY_OFFSET: usize = 20;
 /// A ReceptionReport block conveys statistics on the reception of RTP packets
 /// from a single synchronization source.
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ReceptionReport {
     /// The SSRC identifier of the source to which the information in this
     /// reception report block pertains.
@@ -25,6 +25,7 @@ pub(crate) const SR_OCTET_COUNT_LENGTH: usize = 4;
 
 /// A SenderReport (SR) packet provides reception quality feedback for an RTP stream
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SenderReport {
     /// The synchronization source identifier for the originator of this SR packet.
     pub ssrc: u32,
@@ -56,6 +57,7 @@ pub struct SenderReport {
 
     /// ProfileExtensions contains additional, payload-specific information that needs to
     /// be reported regularly about the sender.
+    #[cfg_attr(feature = "serde", serde(with = "util::serde_hex"))]
     pub profile_extensions: Bytes,
 }
 
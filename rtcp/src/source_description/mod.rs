@@ -22,16 +22,24 @@ const SDES_TEXT_OFFSET: usize = 2;
 /// RTP SDES item types registered with IANA. See: https://www.iana.org/assignments/rtp-parameters/rtp-parameters.xhtml#rtp-parameters-5
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SdesType {
-    SdesEnd = 0,      // end of SDES list                RFC 3550, 6.5
-    SdesCname = 1,    // canonical name                  RFC 3550, 6.5.1
-    SdesName = 2,     // user name                       RFC 3550, 6.5.2
-    SdesEmail = 3,    // user's electronic mail address  RFC 3550, 6.5.3
-    SdesPhone = 4,    // user's phone number             RFC 3550, 6.5.4
-    SdesLocation = 5, // geographic user location        RFC 3550, 6.5.5
-    SdesTool = 6,     // name of application or tool     RFC 3550, 6.5.6
-    SdesNote = 7,     // notice about the source         RFC 3550, 6.5.7
-    SdesPrivate = 8,  // private extensions              RFC 3550, 6.5.8  (not implemented)
+    SdesEnd = 0,                  // end of SDES list               RFC 3550, 6.5
+    SdesCname = 1,                // canonical name                 RFC 3550, 6.5.1
+    SdesName = 2,                 // user name                      RFC 3550, 6.5.2
+    SdesEmail = 3,                // user's electronic mail address RFC 3550, 6.5.3
+    SdesPhone = 4,                // user's phone number            RFC 3550, 6.5.4
+    SdesLocation = 5,             // geographic user location       RFC 3550, 6.5.5
+    SdesTool = 6,                 // name of application or tool    RFC 3550, 6.5.6
+    SdesNote = 7,                 // notice about the source        RFC 3550, 6.5.7
+    SdesPrivate = 8,              // private extensions             RFC 3550, 6.5.8 (unsupported)
+    SdesH323Caddr = 9,            // H.323 callable address         ITU-T H.225.0
+    SdesApsi = 10,                // application specific id        RFC 6776
+    SdesRgrp = 11,                // reporting group id             RFC 8861
+    SdesRtpStreamId = 12,         // RTP stream id                  RFC 8852
+    SdesRepairedRtpStreamId = 13, // repaired RTP stream id         RFC 8852
+    SdesCcid = 14,                // CLUE capture id                RFC 8849
+    SdesMid = 15,                 // media id                       RFC 8843
 }
 
 impl Default for SdesType {
@@ -52,6 +60,13 @@ impl fmt::Display for SdesType {
             SdesType::SdesTool => "TOOL",
             SdesType::SdesNote => "NOTE",
             SdesType::SdesPrivate => "PRIV",
+            SdesType::SdesH323Caddr => "H323-CADDR",
+            SdesType::SdesApsi => "APSI",
+            SdesType::SdesRgrp => "RGRP",
+            SdesType::SdesRtpStreamId => "RtpStreamId",
+            SdesType::SdesRepairedRtpStreamId => "RepairedRtpStreamId",
+            SdesType::SdesCcid => "CCID",
+            SdesType::SdesMid => "MID",
         };
         write!(f, "{}", s)
     }
@@ -68,6 +83,13 @@ impl From<u8> for SdesType {
             6 => SdesType::SdesTool,
             7 => SdesType::SdesNote,
             8 => SdesType::SdesPrivate,
+            9 => SdesType::SdesH323Caddr,
+            10 => SdesType::SdesApsi,
+            11 => SdesType::SdesRgrp,
+            12 => SdesType::SdesRtpStreamId,
+            13 => SdesType::SdesRepairedRtpStreamId,
+            14 => SdesType::SdesCcid,
+            15 => SdesType::SdesMid,
             _ => SdesType::SdesEnd,
         }
     }
@@ -75,6 +97,7 @@ impl From<u8> for SdesType {
 
 /// A SourceDescriptionChunk contains items describing a single RTP source
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SourceDescriptionChunk {
     /// The source (ssrc) or contributing source (csrc) identifier this packet describes
     pub source: u32,
@@ -177,12 +200,14 @@ impl Unmarshal for SourceDescriptionChunk {
 
 /// A SourceDescriptionItem is a part of a SourceDescription that describes a stream.
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SourceDescriptionItem {
     /// The type identifier for this item. eg, SDESCNAME for canonical name description.
     ///
     /// Type zero or SDESEnd is interpreted as the end of an item list and cannot be used.
     pub sdes_type: SdesType,
     /// Text is a unicode text blob associated with the item. Its meaning varies based on the item's Type.
+    #[cfg_attr(feature = "serde", serde(with = "util::serde_hex"))]
     pub text: Bytes,
 }
 
@@ -275,6 +300,7 @@ impl Unmarshal for SourceDescriptionItem {
 
 /// A SourceDescription (SDES) packet describes the sources in an RTP stream.
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SourceDescription {
     pub chunks: Vec<SourceDescriptionChunk>,
 }
@@ -191,6 +191,58 @@ fn test_source_description_unmarshal() {
             },
             None,
         ),
+        (
+            "rid, repaired rid and mid",
+            Bytes::from_static(&[
+                // v=2, p=0, count=2, SDES, len=40
+                0x82, 0xca, 0x00, 0x09, // ssrc=0x2f5a3bc1
+                0x2f, 0x5a, 0x3b, 0xc1, // CNAME, len=9, content=user@host
+                0x01, 0x09, 0x75, 0x73, 0x65, 0x72, 0x40, 0x68, 0x6f, 0x73, 0x74,
+                // MID, len=1, content=0
+                0x0f, 0x01, 0x30, // RtpStreamId, len=1, content=h
+                0x0c, 0x01, 0x68, // END + padding
+                0x00, 0x00, 0x00, // ssrc=0x2f5a3bc2
+                0x2f, 0x5a, 0x3b, 0xc2, // MID, len=1, content=0
+                0x0f, 0x01, 0x30, // RepairedRtpStreamId, len=1, content=h
+                0x0d, 0x01, 0x68, // END + padding
+                0x00, 0x00,
+            ]),
+            SourceDescription {
+                chunks: vec![
+                    SourceDescriptionChunk {
+                        source: 0x2f5a3bc1,
+                        items: vec![
+                            SourceDescriptionItem {
+                                sdes_type: SdesType::SdesCname,
+                                text: Bytes::from_static(b"user@host"),
+                            },
+                            SourceDescriptionItem {
+                                sdes_type: SdesType::SdesMid,
+                                text: Bytes::from_static(b"0"),
+                            },
+                            SourceDescriptionItem {
+                                sdes_type: SdesType::SdesRtpStreamId,
+                                text: Bytes::from_static(b"h"),
+                            },
+                        ],
+                    },
+                    SourceDescriptionChunk {
+                        source: 0x2f5a3bc2,
+                        items: vec![
+                            SourceDescriptionItem {
+                                sdes_type: SdesType::SdesMid,
+                                text: Bytes::from_static(b"0"),
+                            },
+                            SourceDescriptionItem {
+                                sdes_type: SdesType::SdesRepairedRtpStreamId,
+                                text: Bytes::from_static(b"h"),
+                            },
+                        ],
+                    },
+                ],
+            },
+            None,
+        ),
     ];
 
     for (name, mut data, want, want_error) in tests {
@@ -326,6 +378,29 @@ fn test_source_description_roundtrip() {
             },
             Some(Error::SdesTextTooLong),
         ),
+        (
+            "rid and mid items",
+            SourceDescription {
+                chunks: vec![SourceDescriptionChunk {
+                    source: 1,
+                    items: vec![
+                        SourceDescriptionItem {
+                            sdes_type: SdesType::SdesMid,
+                            text: Bytes::from_static(b"audio"),
+                        },
+                        SourceDescriptionItem {
+                            sdes_type: SdesType::SdesRtpStreamId,
+                            text: Bytes::from_static(b"hi"),
+                        },
+                        SourceDescriptionItem {
+                            sdes_type: SdesType::SdesRepairedRtpStreamId,
+                            text: Bytes::from_static(b"lo"),
+                        },
+                    ],
+                }],
+            },
+            None,
+        ),
         (
             "count overflow",
             SourceDescription {
@@ -16,6 +16,7 @@ const RRR_MEDIA_OFFSET: usize = 4;
 
 /// The RapidResynchronizationRequest packet informs the encoder about the loss of an undefined amount of coded video data belonging to one or more pictures
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RapidResynchronizationRequest {
     /// SSRC of sender
     pub sender_ssrc: u32,
@@ -41,6 +41,7 @@ type Result<T> = std::result::Result<T, util::Error>;
 /// type of packet status chunk
 #[derive(PartialEq, Eq, Debug, Clone)]
 #[repr(u16)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StatusChunkTypeTcc {
     RunLengthChunk = 0,
     StatusVectorChunk = 1,
@@ -49,6 +50,7 @@ pub enum StatusChunkTypeTcc {
 /// type of packet status symbol and recv delta
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
 #[repr(u16)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SymbolTypeTcc {
     /// https://tools.ietf.org/html/draft-holmer-rmcat-transport-wide-cc-extensions-01#section-3.1.1
     PacketNotReceived = 0,
@@ -64,6 +66,7 @@ pub enum SymbolTypeTcc {
 /// for status vector chunk
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
 #[repr(u16)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SymbolSizeTypeTcc {
     /// https://tools.ietf.org/html/draft-holmer-rmcat-transport-wide-cc-extensions-01#section-3.1.4
     OneBit = 0,
@@ -120,6 +123,7 @@ impl Default for SymbolTypeTcc {
 /// PacketStatusChunk has two kinds:
 /// RunLengthChunk and StatusVectorChunk
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PacketStatusChunk {
     RunLengthChunk(RunLengthChunk),
     StatusVectorChunk(StatusVectorChunk),
@@ -151,6 +155,7 @@ impl Marshal for PacketStatusChunk {
 /// |T| S |       Run Length        |
 /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RunLengthChunk {
     /// T = TypeTCCRunLengthChunk
     pub type_tcc: StatusChunkTypeTcc,
@@ -224,6 +229,7 @@ impl Unmarshal for RunLengthChunk {
 /// |T|S|       symbol list         |
 /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StatusVectorChunk {
     /// T = TypeTCCRunLengthChunk
     pub type_tcc: StatusChunkTypeTcc,
@@ -321,6 +327,7 @@ impl Unmarshal for StatusVectorChunk {
 /// big delta is 2 bytes: [-8192.0, 8191.75]ms = [-8192000, 8191750]us = [-32768, 32767]*250us
 /// https://tools.ietf.org/html/draft-holmer-rmcat-transport-wide-cc-extensions-01#section-3.1.5
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RecvDelta {
     pub type_tcc_packet: SymbolTypeTcc,
     /// us
@@ -442,6 +449,7 @@ const PACKET_STATUS_CHUNK_LENGTH: usize = 2;
 /// TransportLayerCC for sender-BWE
 /// https://tools.ietf.org/html/draft-holmer-rmcat-transport-wide-cc-extensions-01#page-5
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TransportLayerCc {
     /// SSRC of sender
     pub sender_ssrc: u32,
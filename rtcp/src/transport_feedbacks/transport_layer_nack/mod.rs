@@ -17,6 +17,7 @@ type PacketBitmap = u16;
 /// NackPair is a wire-representation of a collection of
 /// Lost RTP packets
 #[derive(Debug, PartialEq, Eq, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NackPair {
     /// ID of lost packets
     pub packet_id: u16,
@@ -96,6 +97,7 @@ const NACK_OFFSET: usize = 8;
 // IETF RFC 4585, Section 6.2.1
 // https://tools.ietf.org/html/rfc4585#section-6.2.1
 #[derive(Debug, PartialEq, Eq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TransportLayerNack {
     /// SSRC of sender
     pub sender_ssrc: u32,
@@ -0,0 +1,17 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use bytes::Bytes;
+use rtp::extension::dependency_descriptor_extension::DependencyDescriptorExtension;
+use util::marshal::{Marshal, Unmarshal};
+
+fuzz_target!(|data: &[u8]| {
+    let mut bytes = Bytes::from(data.to_vec());
+    if let Ok(dd) = DependencyDescriptorExtension::unmarshal(&mut bytes) {
+        // whatever parses must marshal again
+        let _ = dd.marshal();
+        if let Some(structure) = &dd.attached_structure {
+            let _ = dd.frame_dependency(structure);
+        }
+    }
+});
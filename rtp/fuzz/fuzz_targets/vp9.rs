@@ -0,0 +1,13 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use bytes::Bytes;
+use rtp::codecs::vp9::Vp9Packet;
+use rtp::packetizer::Depacketizer;
+
+fuzz_target!(|data: &[u8]| {
+    let bytes = Bytes::from(data.to_vec());
+    let mut packet = Vp9Packet::default();
+    let _ = packet.depacketize(&bytes);
+    packet.is_partition_head(&bytes);
+});
@@ -258,3 +258,32 @@ fn test_h264_payloader_payload_sps_and_pps_handling() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_h264_is_keyframe() {
+    let cases: Vec<(&str, &[u8], bool)> = vec![
+        ("SPS", &[0x27, 0x90, 0x90, 0x00], true),
+        ("IDR", &[0x65, 0xAA], true),
+        ("non-IDR slice", &[0x41, 0xAA], false),
+        (
+            "STAP-A with an SPS",
+            &[
+                0x38, 0x00, 0x03, 0x27, 0x90, 0x90, 0x00, 0x05, 0x28, 0x90, 0x90, 0x90, 0x90,
+            ],
+            true,
+        ),
+        (
+            "STAP-A with an SPS after an AUD",
+            &[0x78, 0x00, 0x01, 0x09, 0x00, 0x01, 0x67],
+            true,
+        ),
+        ("STAP-A with an AUD", &[0x78, 0x00, 0x01, 0x09], false),
+        ("first fragment of an IDR", &[0x7C, 0x85, 0xAA], true),
+        ("middle fragment of an IDR", &[0x7C, 0x05, 0xAA], false),
+        ("empty", &[], false),
+    ];
+
+    for (name, payload, want) in cases {
+        assert_eq!(is_keyframe(payload), want, "{}", name);
+    }
+}
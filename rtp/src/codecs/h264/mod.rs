@@ -15,6 +15,7 @@ pub struct H264Payloader {
     pps_nalu: Option<Bytes>,
 }
 
+pub const IDR_NALU_TYPE: u8 = 5;
 pub const STAPA_NALU_TYPE: u8 = 24;
 pub const FUA_NALU_TYPE: u8 = 28;
 pub const FUB_NALU_TYPE: u8 = 29;
@@ -205,6 +206,37 @@ pub struct H264Packet {
     fua_buffer: Option<BytesMut>,
 }
 
+/// is_keyframe tells whether the H264 RTP payload starts a keyframe, that is whether it carries
+/// an SPS or an IDR slice, alone, aggregated in a STAP-A or as the first fragment of a FU-A.
+pub fn is_keyframe(payload: &[u8]) -> bool {
+    let is_keyframe_nalu = |b: u8| {
+        let nalu_type = b & NALU_TYPE_BITMASK;
+        nalu_type == IDR_NALU_TYPE || nalu_type == SPS_NALU_TYPE
+    };
+
+    match payload.first().map(|b| b & NALU_TYPE_BITMASK) {
+        Some(STAPA_NALU_TYPE) => {
+            let mut offset = STAPA_HEADER_SIZE;
+            while let (Some(hi), Some(lo)) = (payload.get(offset), payload.get(offset + 1)) {
+                let size = u16::from_be_bytes([*hi, *lo]) as usize;
+                if payload
+                    .get(offset + STAPA_NALU_LENGTH_SIZE)
+                    .map_or(false, |b| is_keyframe_nalu(*b))
+                {
+                    return true;
+                }
+                offset += STAPA_NALU_LENGTH_SIZE + size;
+            }
+            false
+        }
+        Some(FUA_NALU_TYPE) => payload
+            .get(1)
+            .map_or(false, |b| b & FU_START_BITMASK != 0 && is_keyframe_nalu(*b)),
+        Some(_) => payload.first().map_or(false, |b| is_keyframe_nalu(*b)),
+        None => false,
+    }
+}
+
 impl Depacketizer for H264Packet {
     /// depacketize parses the passed byte slice and stores the result in the H264Packet this method is called upon
     fn depacketize(&mut self, packet: &Bytes) -> Result<Bytes> {
@@ -4,9 +4,10 @@ mod vp8_test;
 use crate::{
     error::{Error, Result},
     packetizer::{Depacketizer, Payloader},
+    util::bits::BitReader,
 };
 
-use bytes::{Buf, BufMut, Bytes, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 
 pub const VP8_HEADER_SIZE: usize = 1;
 
@@ -146,6 +147,16 @@ pub struct Vp8Packet {
     pub key_idx: u8,
 }
 
+/// is_keyframe tells whether the VP8 RTP payload starts a keyframe, that is the first partition
+/// of a frame whose frame header has the P bit cleared.
+pub fn is_keyframe(payload: &Bytes) -> bool {
+    let mut vp8 = Vp8Packet::default();
+    match vp8.depacketize(payload) {
+        Ok(frame) => vp8.s == 1 && vp8.pid == 0 && frame.first().map_or(false, |b| b & 0x01 == 0),
+        Err(_) => false,
+    }
+}
+
 impl Depacketizer for Vp8Packet {
     /// depacketize parses the passed byte slice and stores the result in the VP8Packet this method is called upon
     fn depacketize(&mut self, packet: &Bytes) -> Result<Bytes> {
@@ -168,11 +179,9 @@ impl Depacketizer for Vp8Packet {
         //T/K:|tid|Y| KEYIDX  | (OPTIONAL)
         //    +-+-+-+-+-+-+-+-+
 
-        let reader = &mut packet.clone();
-        let mut payload_index = 0;
+        let mut reader = BitReader::new(packet);
 
-        let mut b = reader.get_u8();
-        payload_index += 1;
+        let mut b = reader.read_u8()?;
 
         self.x = (b & 0x80) >> 7;
         self.n = (b & 0x20) >> 5;
@@ -180,8 +189,7 @@ impl Depacketizer for Vp8Packet {
         self.pid = b & 0x07;
 
         if self.x == 1 {
-            b = reader.get_u8();
-            payload_index += 1;
+            b = reader.read_u8()?;
             self.i = (b & 0x80) >> 7;
             self.l = (b & 0x40) >> 6;
             self.t = (b & 0x20) >> 5;
@@ -189,33 +197,30 @@ impl Depacketizer for Vp8Packet {
         }
 
         if self.i == 1 {
-            b = reader.get_u8();
-            payload_index += 1;
+            b = reader.read_u8()?;
             // PID present?
             if b & 0x80 > 0 {
                 // M == 1, PID is 16bit
-                self.picture_id = (((b & 0x7f) as u16) << 8) | (reader.get_u8() as u16);
-                payload_index += 1;
+                self.picture_id = (((b & 0x7f) as u16) << 8) | (reader.read_u8()? as u16);
             } else {
                 self.picture_id = b as u16;
             }
         }
 
-        if payload_index >= payload_len {
+        if reader.remaining() == 0 {
             return Err(Error::ErrShortPacket);
         }
 
         if self.l == 1 {
-            self.tl0_pic_idx = reader.get_u8();
-            payload_index += 1;
+            self.tl0_pic_idx = reader.read_u8()?;
         }
 
-        if payload_index >= payload_len {
+        if reader.remaining() == 0 {
             return Err(Error::ErrShortPacket);
         }
 
         if self.t == 1 || self.k == 1 {
-            let b = reader.get_u8();
+            let b = reader.read_u8()?;
             if self.t == 1 {
                 self.tid = b >> 6;
                 self.y = (b >> 5) & 0x1;
@@ -223,23 +228,18 @@ impl Depacketizer for Vp8Packet {
             if self.k == 1 {
                 self.key_idx = b & 0x1F;
             }
-            payload_index += 1;
         }
 
-        if payload_index >= packet.len() {
+        if reader.remaining() == 0 {
             return Err(Error::ErrShortPacket);
         }
 
-        Ok(packet.slice(payload_index..))
+        Ok(packet.slice(reader.byte_position()..))
     }
 
     /// is_partition_head checks whether if this is a head of the VP8 partition
     fn is_partition_head(&self, payload: &Bytes) -> bool {
-        if payload.is_empty() {
-            false
-        } else {
-            (payload[0] & 0x10) != 0
-        }
+        payload.first().map_or(false, |b| (b & 0x10) != 0)
     }
 
     fn is_partition_tail(&self, marker: bool, _payload: &Bytes) -> bool {
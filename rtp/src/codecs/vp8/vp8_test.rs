@@ -227,3 +227,144 @@ fn test_vp8_partition_head_checker_is_partition_head() -> Result<()> {
 
     Ok(())
 }
+
+// legacy_depacketize is the byte oriented parser depacketize used before moving to BitReader,
+// kept to check that both agree.
+fn legacy_depacketize(pck: &mut Vp8Packet, packet: &Bytes) -> Result<Bytes> {
+    use bytes::Buf;
+
+    let payload_len = packet.len();
+    if payload_len < 4 {
+        return Err(Error::ErrShortPacket);
+    }
+
+    let reader = &mut packet.clone();
+    let mut payload_index = 0;
+
+    let mut b = reader.get_u8();
+    payload_index += 1;
+
+    pck.x = (b & 0x80) >> 7;
+    pck.n = (b & 0x20) >> 5;
+    pck.s = (b & 0x10) >> 4;
+    pck.pid = b & 0x07;
+
+    if pck.x == 1 {
+        b = reader.get_u8();
+        payload_index += 1;
+        pck.i = (b & 0x80) >> 7;
+        pck.l = (b & 0x40) >> 6;
+        pck.t = (b & 0x20) >> 5;
+        pck.k = (b & 0x10) >> 4;
+    }
+
+    if pck.i == 1 {
+        b = reader.get_u8();
+        payload_index += 1;
+        if b & 0x80 > 0 {
+            pck.picture_id = (((b & 0x7f) as u16) << 8) | (reader.get_u8() as u16);
+            payload_index += 1;
+        } else {
+            pck.picture_id = b as u16;
+        }
+    }
+
+    if payload_index >= payload_len {
+        return Err(Error::ErrShortPacket);
+    }
+
+    if pck.l == 1 {
+        pck.tl0_pic_idx = reader.get_u8();
+        payload_index += 1;
+    }
+
+    if payload_index >= payload_len {
+        return Err(Error::ErrShortPacket);
+    }
+
+    if pck.t == 1 || pck.k == 1 {
+        let b = reader.get_u8();
+        if pck.t == 1 {
+            pck.tid = b >> 6;
+            pck.y = (b >> 5) & 0x1;
+        }
+        if pck.k == 1 {
+            pck.key_idx = b & 0x1F;
+        }
+        payload_index += 1;
+    }
+
+    if payload_index >= packet.len() {
+        return Err(Error::ErrShortPacket);
+    }
+
+    Ok(packet.slice(payload_index..))
+}
+
+fn random_vp8_packet(rng: &mut impl rand::Rng) -> Bytes {
+    let mut raw = vec![rng.gen::<u8>() | 0x80, rng.gen::<u8>() & 0xF0];
+    if raw[1] & 0x80 != 0 {
+        let picture_id = rng.gen::<u8>();
+        raw.push(picture_id);
+        if picture_id & 0x80 != 0 {
+            raw.push(rng.gen());
+        }
+    }
+    if raw[1] & 0x40 != 0 {
+        raw.push(rng.gen());
+    }
+    if raw[1] & 0x30 != 0 {
+        raw.push(rng.gen());
+    }
+    for _ in 0..rng.gen_range(0..4) {
+        raw.push(rng.gen());
+    }
+    // also cut some of the packets short
+    if rng.gen_bool(0.2) {
+        raw.truncate(rng.gen_range(0..=raw.len()));
+    }
+    Bytes::from(raw)
+}
+
+#[test]
+fn test_vp8_depacketize_matches_legacy_parser() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0x5650_3800);
+    for _ in 0..10_000 {
+        let packet = random_vp8_packet(&mut rng);
+
+        let (mut pck, mut legacy) = (Vp8Packet::default(), Vp8Packet::default());
+        let result = pck.depacketize(&packet);
+        assert_eq!(
+            result,
+            legacy_depacketize(&mut legacy, &packet),
+            "{:02x?}",
+            &packet[..]
+        );
+        if result.is_ok() {
+            assert_eq!(pck, legacy, "{:02x?}", &packet[..]);
+        }
+    }
+}
+
+#[test]
+fn test_vp8_is_keyframe() {
+    let cases: Vec<(&[u8], bool)> = vec![
+        (&[0x10, 0x00, 0x9d, 0x01], true),
+        // P bit of the frame header set
+        (&[0x10, 0x01, 0x9d, 0x01], false),
+        // not the first partition of the frame
+        (&[0x00, 0x00, 0x9d, 0x01], false),
+        (&[0x10], false),
+    ];
+
+    for (payload, want) in cases {
+        assert_eq!(
+            is_keyframe(&Bytes::copy_from_slice(payload)),
+            want,
+            "{:02x?}",
+            payload
+        );
+    }
+}
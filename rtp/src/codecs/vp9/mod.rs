@@ -4,9 +4,10 @@ mod vp9_test;
 use crate::{
     error::{Error, Result},
     packetizer::{Depacketizer, Payloader},
+    util::bits::BitReader,
 };
 
-use bytes::{Buf, BufMut, Bytes, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 use std::fmt;
 use std::sync::Arc;
 
@@ -200,6 +201,13 @@ pub struct Vp9Packet {
     pub pgpdiff: Vec<Vec<u8>>,
 }
 
+/// is_keyframe tells whether the VP9 RTP payload starts a keyframe, that is the beginning of a
+/// frame which isn't inter-picture predicted.
+pub fn is_keyframe(payload: &Bytes) -> bool {
+    let mut vp9 = Vp9Packet::default();
+    vp9.depacketize(payload).is_ok() && vp9.b && !vp9.p
+}
+
 impl Depacketizer for Vp9Packet {
     /// depacketize parses the passed byte slice and stores the result in the Vp9Packet this method is called upon
     fn depacketize(&mut self, packet: &Bytes) -> Result<Bytes> {
@@ -207,8 +215,8 @@ impl Depacketizer for Vp9Packet {
             return Err(Error::ErrShortPacket);
         }
 
-        let reader = &mut packet.clone();
-        let b = reader.get_u8();
+        let mut reader = BitReader::new(packet);
+        let b = reader.read_u8()?;
 
         self.i = (b & 0x80) != 0;
         self.p = (b & 0x40) != 0;
@@ -219,34 +227,28 @@ impl Depacketizer for Vp9Packet {
         self.v = (b & 0x02) != 0;
         self.z = (b & 0x01) != 0;
 
-        let mut payload_index = 1;
-
         if self.i {
-            payload_index = self.parse_picture_id(reader, payload_index)?;
+            self.parse_picture_id(&mut reader)?;
         }
 
         if self.l {
-            payload_index = self.parse_layer_info(reader, payload_index)?;
+            self.parse_layer_info(&mut reader)?;
         }
 
         if self.f && self.p {
-            payload_index = self.parse_ref_indices(reader, payload_index)?;
+            self.parse_ref_indices(&mut reader)?;
         }
 
         if self.v {
-            payload_index = self.parse_ssdata(reader, payload_index)?;
+            self.parse_ssdata(&mut reader)?;
         }
 
-        Ok(packet.slice(payload_index..))
+        Ok(packet.slice(reader.byte_position()..))
     }
 
     /// is_partition_head checks whether if this is a head of the VP9 partition
     fn is_partition_head(&self, payload: &Bytes) -> bool {
-        if payload.is_empty() {
-            false
-        } else {
-            (payload[0] & 0x08) != 0
-        }
+        payload.first().map_or(false, |b| (b & 0x08) != 0)
     }
 
     fn is_partition_tail(&self, marker: bool, _payload: &Bytes) -> bool {
@@ -263,42 +265,26 @@ impl Vp9Packet {
     // M:   | EXTENDED PID  |
     //      +-+-+-+-+-+-+-+-+
     //
-    fn parse_picture_id(
-        &mut self,
-        reader: &mut dyn Buf,
-        mut payload_index: usize,
-    ) -> Result<usize> {
-        if reader.remaining() == 0 {
-            return Err(Error::ErrShortPacket);
-        }
-        let b = reader.get_u8();
-        payload_index += 1;
+    fn parse_picture_id(&mut self, reader: &mut BitReader<'_>) -> Result<()> {
+        let b = reader.read_u8()?;
         // PID present?
         if (b & 0x80) != 0 {
-            if reader.remaining() == 0 {
-                return Err(Error::ErrShortPacket);
-            }
             // M == 1, PID is 15bit
-            self.picture_id = (((b & 0x7f) as u16) << 8) | (reader.get_u8() as u16);
-            payload_index += 1;
+            self.picture_id = (((b & 0x7f) as u16) << 8) | (reader.read_u8()? as u16);
         } else {
             self.picture_id = (b & 0x7F) as u16;
         }
 
-        Ok(payload_index)
+        Ok(())
     }
 
-    fn parse_layer_info(
-        &mut self,
-        reader: &mut dyn Buf,
-        mut payload_index: usize,
-    ) -> Result<usize> {
-        payload_index = self.parse_layer_info_common(reader, payload_index)?;
+    fn parse_layer_info(&mut self, reader: &mut BitReader<'_>) -> Result<()> {
+        self.parse_layer_info_common(reader)?;
 
         if self.f {
-            Ok(payload_index)
+            Ok(())
         } else {
-            self.parse_layer_info_non_flexible_mode(reader, payload_index)
+            self.parse_layer_info_non_flexible_mode(reader)
         }
     }
 
@@ -308,16 +294,8 @@ impl Vp9Packet {
     // L:   |  T  |U|  S  |D|
     //      +-+-+-+-+-+-+-+-+
     //
-    fn parse_layer_info_common(
-        &mut self,
-        reader: &mut dyn Buf,
-        mut payload_index: usize,
-    ) -> Result<usize> {
-        if reader.remaining() == 0 {
-            return Err(Error::ErrShortPacket);
-        }
-        let b = reader.get_u8();
-        payload_index += 1;
+    fn parse_layer_info_common(&mut self, reader: &mut BitReader<'_>) -> Result<()> {
+        let b = reader.read_u8()?;
 
         self.tid = b >> 5;
         self.u = b & 0x10 != 0;
@@ -327,7 +305,7 @@ impl Vp9Packet {
         if self.sid >= MAX_SPATIAL_LAYERS {
             Err(Error::ErrTooManySpatialLayers)
         } else {
-            Ok(payload_index)
+            Ok(())
         }
     }
 
@@ -339,17 +317,9 @@ impl Vp9Packet {
     //      |   tl0picidx   |
     //      +-+-+-+-+-+-+-+-+
     //
-    fn parse_layer_info_non_flexible_mode(
-        &mut self,
-        reader: &mut dyn Buf,
-        mut payload_index: usize,
-    ) -> Result<usize> {
-        if reader.remaining() == 0 {
-            return Err(Error::ErrShortPacket);
-        }
-        self.tl0picidx = reader.get_u8();
-        payload_index += 1;
-        Ok(payload_index)
+    fn parse_layer_info_non_flexible_mode(&mut self, reader: &mut BitReader<'_>) -> Result<()> {
+        self.tl0picidx = reader.read_u8()?;
+        Ok(())
     }
 
     // Reference indices:
@@ -359,18 +329,10 @@ impl Vp9Packet {
     //      +-+-+-+-+-+-+-+-+                    N=1: An additional P_DIFF follows
     //                                                current P_DIFF.
     //
-    fn parse_ref_indices(
-        &mut self,
-        reader: &mut dyn Buf,
-        mut payload_index: usize,
-    ) -> Result<usize> {
+    fn parse_ref_indices(&mut self, reader: &mut BitReader<'_>) -> Result<()> {
         let mut b = 1u8;
         while (b & 0x1) != 0 {
-            if reader.remaining() == 0 {
-                return Err(Error::ErrShortPacket);
-            }
-            b = reader.get_u8();
-            payload_index += 1;
+            b = reader.read_u8()?;
 
             self.pdiff.push(b >> 1);
             if self.pdiff.len() >= MAX_VP9REF_PICS {
@@ -378,7 +340,7 @@ impl Vp9Packet {
             }
         }
 
-        Ok(payload_index)
+        Ok(())
     }
 
     // Scalability structure (SS):
@@ -401,13 +363,8 @@ impl Vp9Packet {
     //      |    P_DIFF     | (OPTIONAL)    . R times    .
     //      +-+-+-+-+-+-+-+-+              -|           -|
     //
-    fn parse_ssdata(&mut self, reader: &mut dyn Buf, mut payload_index: usize) -> Result<usize> {
-        if reader.remaining() == 0 {
-            return Err(Error::ErrShortPacket);
-        }
-
-        let b = reader.get_u8();
-        payload_index += 1;
+    fn parse_ssdata(&mut self, reader: &mut BitReader<'_>) -> Result<()> {
+        let b = reader.read_u8()?;
 
         self.ns = b >> 5;
         self.y = b & 0x10 != 0;
@@ -417,52 +374,40 @@ impl Vp9Packet {
         self.ng = 0;
 
         if self.y {
-            if reader.remaining() < 4 * ns {
+            if reader.remaining() < 4 * 8 * ns {
                 return Err(Error::ErrShortPacket);
             }
 
             self.width = vec![0u16; ns];
             self.height = vec![0u16; ns];
-            for i in 0..ns {
-                self.width[i] = reader.get_u16();
-                self.height[i] = reader.get_u16();
+            for (width, height) in self.width.iter_mut().zip(self.height.iter_mut()) {
+                *width = reader.read_u16()?;
+                *height = reader.read_u16()?;
             }
-            payload_index += 4 * ns;
         }
 
         if self.g {
-            if reader.remaining() == 0 {
-                return Err(Error::ErrShortPacket);
-            }
-
-            self.ng = reader.get_u8();
-            payload_index += 1;
+            self.ng = reader.read_u8()?;
         }
 
-        for i in 0..self.ng as usize {
-            if reader.remaining() == 0 {
-                return Err(Error::ErrShortPacket);
-            }
-            let b = reader.get_u8();
-            payload_index += 1;
+        for _ in 0..self.ng {
+            let b = reader.read_u8()?;
 
             self.pgtid.push(b >> 5);
             self.pgu.push(b & 0x10 != 0);
 
             let r = ((b >> 2) & 0x3) as usize;
-            if reader.remaining() < r {
+            if reader.remaining() < 8 * r {
                 return Err(Error::ErrShortPacket);
             }
 
-            self.pgpdiff.push(vec![]);
+            let mut pdiffs = Vec::with_capacity(r);
             for _ in 0..r {
-                let b = reader.get_u8();
-                payload_index += 1;
-
-                self.pgpdiff[i].push(b);
+                pdiffs.push(reader.read_u8()?);
             }
+            self.pgpdiff.push(pdiffs);
         }
 
-        Ok(payload_index)
+        Ok(())
     }
 }
@@ -364,3 +364,248 @@ fn test_vp9_partition_head_checker_is_partition_head() -> Result<()> {
 
     Ok(())
 }
+
+// legacy_depacketize is the byte oriented parser depacketize used before moving to BitReader,
+// kept to check that both agree.
+fn legacy_depacketize(pck: &mut Vp9Packet, packet: &Bytes) -> Result<Bytes> {
+    use bytes::Buf;
+
+    if packet.is_empty() {
+        return Err(Error::ErrShortPacket);
+    }
+
+    let reader = &mut packet.clone();
+    let b = reader.get_u8();
+
+    pck.i = (b & 0x80) != 0;
+    pck.p = (b & 0x40) != 0;
+    pck.l = (b & 0x20) != 0;
+    pck.f = (b & 0x10) != 0;
+    pck.b = (b & 0x08) != 0;
+    pck.e = (b & 0x04) != 0;
+    pck.v = (b & 0x02) != 0;
+    pck.z = (b & 0x01) != 0;
+
+    let mut payload_index = 1;
+
+    if pck.i {
+        if reader.remaining() == 0 {
+            return Err(Error::ErrShortPacket);
+        }
+        let b = reader.get_u8();
+        payload_index += 1;
+        if (b & 0x80) != 0 {
+            if reader.remaining() == 0 {
+                return Err(Error::ErrShortPacket);
+            }
+            pck.picture_id = (((b & 0x7f) as u16) << 8) | (reader.get_u8() as u16);
+            payload_index += 1;
+        } else {
+            pck.picture_id = (b & 0x7F) as u16;
+        }
+    }
+
+    if pck.l {
+        if reader.remaining() == 0 {
+            return Err(Error::ErrShortPacket);
+        }
+        let b = reader.get_u8();
+        payload_index += 1;
+
+        pck.tid = b >> 5;
+        pck.u = b & 0x10 != 0;
+        pck.sid = (b >> 1) & 0x7;
+        pck.d = b & 0x01 != 0;
+
+        if pck.sid >= MAX_SPATIAL_LAYERS {
+            return Err(Error::ErrTooManySpatialLayers);
+        }
+
+        if !pck.f {
+            if reader.remaining() == 0 {
+                return Err(Error::ErrShortPacket);
+            }
+            pck.tl0picidx = reader.get_u8();
+            payload_index += 1;
+        }
+    }
+
+    if pck.f && pck.p {
+        let mut b = 1u8;
+        while (b & 0x1) != 0 {
+            if reader.remaining() == 0 {
+                return Err(Error::ErrShortPacket);
+            }
+            b = reader.get_u8();
+            payload_index += 1;
+
+            pck.pdiff.push(b >> 1);
+            if pck.pdiff.len() >= MAX_VP9REF_PICS {
+                return Err(Error::ErrTooManyPDiff);
+            }
+        }
+    }
+
+    if pck.v {
+        if reader.remaining() == 0 {
+            return Err(Error::ErrShortPacket);
+        }
+
+        let b = reader.get_u8();
+        payload_index += 1;
+
+        pck.ns = b >> 5;
+        pck.y = b & 0x10 != 0;
+        pck.g = (b >> 1) & 0x7 != 0;
+
+        let ns = (pck.ns + 1) as usize;
+        pck.ng = 0;
+
+        if pck.y {
+            if reader.remaining() < 4 * ns {
+                return Err(Error::ErrShortPacket);
+            }
+
+            pck.width = vec![0u16; ns];
+            pck.height = vec![0u16; ns];
+            for i in 0..ns {
+                pck.width[i] = reader.get_u16();
+                pck.height[i] = reader.get_u16();
+            }
+            payload_index += 4 * ns;
+        }
+
+        if pck.g {
+            if reader.remaining() == 0 {
+                return Err(Error::ErrShortPacket);
+            }
+
+            pck.ng = reader.get_u8();
+            payload_index += 1;
+        }
+
+        for i in 0..pck.ng as usize {
+            if reader.remaining() == 0 {
+                return Err(Error::ErrShortPacket);
+            }
+            let b = reader.get_u8();
+            payload_index += 1;
+
+            pck.pgtid.push(b >> 5);
+            pck.pgu.push(b & 0x10 != 0);
+
+            let r = ((b >> 2) & 0x3) as usize;
+            if reader.remaining() < r {
+                return Err(Error::ErrShortPacket);
+            }
+
+            pck.pgpdiff.push(vec![]);
+            for _ in 0..r {
+                let b = reader.get_u8();
+                payload_index += 1;
+
+                pck.pgpdiff[i].push(b);
+            }
+        }
+    }
+
+    Ok(packet.slice(payload_index..))
+}
+
+fn random_vp9_packet(rng: &mut impl rand::Rng) -> Bytes {
+    let flags = rng.gen::<u8>();
+    let mut raw = vec![flags];
+    if flags & 0x80 != 0 {
+        let picture_id = rng.gen::<u8>();
+        raw.push(picture_id);
+        if picture_id & 0x80 != 0 {
+            raw.push(rng.gen());
+        }
+    }
+    if flags & 0x20 != 0 {
+        // keep the spatial layer valid most of the time
+        raw.push(rng.gen::<u8>() & if rng.gen_bool(0.9) { 0xF7 } else { 0xFF });
+        if flags & 0x10 == 0 {
+            raw.push(rng.gen());
+        }
+    }
+    if flags & 0x50 == 0x50 {
+        for i in 0..rng.gen_range(1..=3) {
+            let more = if i < 2 && rng.gen_bool(0.5) { 1 } else { 0 };
+            raw.push((rng.gen::<u8>() & 0xFE) | more);
+            if more == 0 {
+                break;
+            }
+        }
+    }
+    if flags & 0x02 != 0 {
+        let ss = rng.gen::<u8>();
+        raw.push(ss);
+        if ss & 0x10 != 0 {
+            for _ in 0..4 * ((ss >> 5) as usize + 1) {
+                raw.push(rng.gen());
+            }
+        }
+        if ss & 0x0E != 0 {
+            let ng = rng.gen_range(0..4u8);
+            raw.push(ng);
+            for _ in 0..ng {
+                let group = rng.gen::<u8>();
+                raw.push(group);
+                for _ in 0..(group >> 2) & 0x3 {
+                    raw.push(rng.gen());
+                }
+            }
+        }
+    }
+    for _ in 0..rng.gen_range(0..4) {
+        raw.push(rng.gen());
+    }
+    // also cut some of the packets short
+    if rng.gen_bool(0.2) {
+        raw.truncate(rng.gen_range(0..=raw.len()));
+    }
+    Bytes::from(raw)
+}
+
+#[test]
+fn test_vp9_depacketize_matches_legacy_parser() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0x5650_3900);
+    for _ in 0..10_000 {
+        let packet = random_vp9_packet(&mut rng);
+
+        let (mut pck, mut legacy) = (Vp9Packet::default(), Vp9Packet::default());
+        let result = pck.depacketize(&packet);
+        assert_eq!(
+            result,
+            legacy_depacketize(&mut legacy, &packet),
+            "{:02x?}",
+            &packet[..]
+        );
+        if result.is_ok() {
+            assert_eq!(pck, legacy, "{:02x?}", &packet[..]);
+        }
+    }
+}
+
+#[test]
+fn test_vp9_is_keyframe() {
+    let cases: Vec<(&[u8], bool)> = vec![
+        // B set, P cleared
+        (&[0x08, 0xAA], true),
+        (&[0x48, 0xAA], false),
+        (&[0x00, 0xAA], false),
+        (&[], false),
+    ];
+
+    for (payload, want) in cases {
+        assert_eq!(
+            is_keyframe(&Bytes::copy_from_slice(payload)),
+            want,
+            "{:02x?}",
+            payload
+        );
+    }
+}
@@ -31,6 +31,16 @@ pub enum Error {
 
     #[error("packet is not large enough")]
     ErrShortPacket,
+    #[error("too many CSRCs, at most 15 are allowed")]
+    ErrTooManyCsrc,
+    #[error("dependency descriptor requires a template dependency structure")]
+    ErrDependencyDescriptorStructureMissing,
+    #[error("invalid template dependency structure")]
+    ErrDependencyDescriptorInvalidStructure,
+    #[error("dependency descriptor refers to an unknown frame dependency template")]
+    ErrDependencyDescriptorInvalidTemplateId,
+    #[error("value does not fit in the bit field")]
+    ErrBitFieldOverflow,
     #[error("invalid nil packet")]
     ErrNilPacket,
     #[error("too many PDiff")]
@@ -0,0 +1,83 @@
+use super::*;
+use crate::error::Result;
+use bytes::{Bytes, BytesMut};
+
+#[test]
+fn test_csrc_audio_level_extension_round_trip() -> Result<()> {
+    let raw = Bytes::from_static(&[0x08, 0x7F, 0x00]);
+    let buf = &mut raw.clone();
+    let a1 = CsrcAudioLevelExtension::unmarshal(buf)?;
+    let a2 = CsrcAudioLevelExtension {
+        levels: vec![8, 127, 0],
+    };
+    assert_eq!(a1, a2);
+
+    let mut dst = BytesMut::with_capacity(a2.marshal_size());
+    dst.resize(a2.marshal_size(), 0);
+    a2.marshal_to(&mut dst)?;
+    assert_eq!(raw, dst.freeze());
+
+    Ok(())
+}
+
+#[test]
+fn test_csrc_audio_level_extension_level_overflow() -> Result<()> {
+    let a = CsrcAudioLevelExtension {
+        levels: vec![1, 128],
+    };
+
+    let mut dst = BytesMut::with_capacity(a.marshal_size());
+    dst.resize(a.marshal_size(), 0);
+    let result = a.marshal_to(&mut dst);
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_csrc_audio_level_extension_too_many_levels() -> Result<()> {
+    let mut buf = &vec![0u8; CSRC_MAX_COUNT + 1][..];
+    let result = CsrcAudioLevelExtension::unmarshal(&mut buf);
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_csrc_audio_levels() -> Result<()> {
+    let first = CsrcAudioLevelExtension {
+        levels: vec![30, 10],
+    };
+    let second = CsrcAudioLevelExtension {
+        levels: vec![20, 50],
+    };
+
+    let (csrc, ext) = merge_csrc_audio_levels(
+        first
+            .entries(&[1, 2])
+            .into_iter()
+            .chain(second.entries(&[3, 1]))
+            .chain(std::iter::once(CsrcAudioLevel { csrc: 4, level: 5 })),
+    );
+
+    assert_eq!(csrc, vec![4, 2, 3, 1]);
+    assert_eq!(ext.levels, vec![5, 10, 20, 30]);
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_csrc_audio_levels_keeps_loudest() -> Result<()> {
+    let entries = (0..20u32).map(|i| CsrcAudioLevel {
+        csrc: i,
+        level: 100 - i as u8,
+    });
+
+    let (csrc, ext) = merge_csrc_audio_levels(entries);
+
+    assert_eq!(csrc.len(), CSRC_MAX_COUNT);
+    assert_eq!(csrc, (5..20u32).rev().collect::<Vec<_>>());
+    assert_eq!(ext.levels, (81..=95u8).collect::<Vec<_>>());
+
+    Ok(())
+}
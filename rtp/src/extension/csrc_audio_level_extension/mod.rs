@@ -0,0 +1,118 @@
+#[cfg(test)]
+mod csrc_audio_level_extension_test;
+
+use crate::error::Error;
+use crate::header::CSRC_MAX_COUNT;
+use serde::{Deserialize, Serialize};
+use util::marshal::{Marshal, MarshalSize, Unmarshal};
+
+use bytes::{Buf, BufMut};
+
+/// CsrcAudioLevelExtension is a extension payload format described in
+/// https://tools.ietf.org/html/rfc6465
+///
+/// It carries one level per contributing source, in the same order as the CSRC list of
+/// the packet.
+///
+/// One byte format:
+/// 0                   1                   2                   3
+/// 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  ID   | len=2 |0|   level 1   |0|   level 2   |0|   level 3   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(PartialEq, Eq, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CsrcAudioLevelExtension {
+    pub levels: Vec<u8>,
+}
+
+/// CsrcAudioLevel is the audio level of a single contributing source.
+#[derive(PartialEq, Eq, Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct CsrcAudioLevel {
+    pub csrc: u32,
+    pub level: u8,
+}
+
+impl CsrcAudioLevelExtension {
+    /// entries pairs the levels with `csrc`, the CSRC list of the packet carrying the extension.
+    /// Levels or CSRCs without a counterpart are ignored.
+    pub fn entries(&self, csrc: &[u32]) -> Vec<CsrcAudioLevel> {
+        csrc.iter()
+            .zip(self.levels.iter())
+            .map(|(&csrc, &level)| CsrcAudioLevel { csrc, level })
+            .collect()
+    }
+}
+
+/// merge_csrc_audio_levels merges the audio level entries of all sources contributing to a
+/// mixed packet into its CSRC list and the matching CsrcAudioLevelExtension.
+///
+/// A CSRC reported more than once keeps its loudest level. Entries are ordered from the
+/// loudest to the quietest source (the level is in -dBov, so lower is louder) and only the
+/// 15 loudest sources are kept, as that is all the CC field can carry.
+pub fn merge_csrc_audio_levels<I>(entries: I) -> (Vec<u32>, CsrcAudioLevelExtension)
+where
+    I: IntoIterator<Item = CsrcAudioLevel>,
+{
+    let mut merged: Vec<CsrcAudioLevel> = vec![];
+    for entry in entries {
+        if let Some(existing) = merged.iter_mut().find(|e| e.csrc == entry.csrc) {
+            existing.level = existing.level.min(entry.level);
+        } else {
+            merged.push(entry);
+        }
+    }
+
+    merged.sort_by_key(|e| e.level);
+    merged.truncate(CSRC_MAX_COUNT);
+
+    let csrc = merged.iter().map(|e| e.csrc).collect();
+    let levels = merged.iter().map(|e| e.level).collect();
+    (csrc, CsrcAudioLevelExtension { levels })
+}
+
+impl Unmarshal for CsrcAudioLevelExtension {
+    /// Unmarshal parses the passed byte slice and stores the result in the members
+    fn unmarshal<B>(raw_packet: &mut B) -> Result<Self, util::Error>
+    where
+        Self: Sized,
+        B: Buf,
+    {
+        if raw_packet.remaining() > CSRC_MAX_COUNT {
+            return Err(Error::ErrTooManyCsrc.into());
+        }
+
+        let mut levels = Vec::with_capacity(raw_packet.remaining());
+        while raw_packet.has_remaining() {
+            levels.push(raw_packet.get_u8() & 0x7F);
+        }
+
+        Ok(CsrcAudioLevelExtension { levels })
+    }
+}
+
+impl MarshalSize for CsrcAudioLevelExtension {
+    /// MarshalSize returns the size of the CsrcAudioLevelExtension once marshaled.
+    fn marshal_size(&self) -> usize {
+        self.levels.len()
+    }
+}
+
+impl Marshal for CsrcAudioLevelExtension {
+    /// MarshalTo serializes the members to buffer
+    fn marshal_to(&self, mut buf: &mut [u8]) -> Result<usize, util::Error> {
+        if self.levels.len() > CSRC_MAX_COUNT {
+            return Err(Error::ErrTooManyCsrc.into());
+        }
+        if buf.remaining_mut() < self.marshal_size() {
+            return Err(Error::ErrBufferTooSmall.into());
+        }
+        for &level in &self.levels {
+            if level > 127 {
+                return Err(Error::AudioLevelOverflow.into());
+            }
+            buf.put_u8(level);
+        }
+
+        Ok(self.levels.len())
+    }
+}
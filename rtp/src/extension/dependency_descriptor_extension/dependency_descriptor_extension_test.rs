@@ -0,0 +1,416 @@
+use super::*;
+use crate::error::Result;
+use bytes::{Bytes, BytesMut};
+
+// The vectors below are built by hand from the bitstream syntax in appendix A of the AV1 RTP
+// specification, for an L1T2 stream (one spatial layer, two temporal layers) at 640x360.
+
+// Key frame carrying the template dependency structure.
+const L1T2_KEY_FRAME: &[u8] = &[
+    0xC0, 0x00, 0x01, 0x80, 0x01, 0x1E, 0xA8, 0x51, 0x41, 0x01, 0x0C, 0x09, 0xFC, 0x05, 0x9C,
+];
+
+// Delta frame on template 2 with the active decode targets and custom frame diffs.
+const L1T2_DELTA_FRAME: &[u8] = &[0xC2, 0x00, 0x02, 0x52, 0x90];
+
+fn l1t2_structure() -> FrameDependencyStructure {
+    use DecodeTargetIndication::*;
+
+    FrameDependencyStructure {
+        template_id_offset: 0,
+        decode_target_count: 2,
+        chain_count: 1,
+        decode_target_protected_by_chain: vec![0, 0],
+        resolutions: vec![RenderResolution {
+            width: 640,
+            height: 360,
+        }],
+        templates: vec![
+            FrameDependencyTemplate {
+                spatial_id: 0,
+                temporal_id: 0,
+                decode_target_indications: vec![Switch, Switch],
+                frame_diffs: vec![],
+                chain_diffs: vec![0],
+            },
+            FrameDependencyTemplate {
+                spatial_id: 0,
+                temporal_id: 0,
+                decode_target_indications: vec![Switch, Switch],
+                frame_diffs: vec![2],
+                chain_diffs: vec![2],
+            },
+            FrameDependencyTemplate {
+                spatial_id: 0,
+                temporal_id: 1,
+                decode_target_indications: vec![NotPresent, Discardable],
+                frame_diffs: vec![1],
+                chain_diffs: vec![1],
+            },
+        ],
+    }
+}
+
+// The L1T3 and L3T3 vectors carry the template structures libwebrtc (and so Chrome) attaches
+// for those scalability modes, encoded from the same bitstream syntax with an encoder written
+// independently of this crate. They are not packets captured from Chrome.
+//
+// TODO: replace them with the descriptors of AV1 SVC packets captured from Chrome, e.g. with
+// the L1T3 and L3T3 `scalabilityMode`s of `RTCRtpSender::setParameters`, noting the Chrome
+// version they were captured with.
+
+// L1T3 key frame at 1280x720 with the structure attached.
+const L1T3_KEY_FRAME: &[u8] = &[
+    0xC0, 0x00, 0x64, 0x80, 0x02, 0x14, 0xEA, 0xA8, 0x60, 0x41, 0x4D, 0x14, 0x10, 0x20, 0x84, 0x27,
+    0x04, 0xFF, 0x02, 0xCF,
+];
+
+// L1T3 delta frames on the first T2 template and the T1 template.
+const L1T3_T2_FRAME: &[u8] = &[0xC3, 0x00, 0x65];
+const L1T3_T1_FRAME: &[u8] = &[0xC2, 0x00, 0x66];
+
+// First packet of an L3T3 key frame at 320x180, 640x360 and 1280x720, with the structure
+// attached at template id offset 15.
+const L3T3_KEY_FRAME: &[u8] = &[
+    0x90, 0x00, 0xC8, 0x81, 0xE8, 0x14, 0x85, 0x21, 0x4E, 0xAF, 0xFF, 0xAA, 0xAA, 0x86, 0x3C, 0xF0,
+    0x43, 0x0C, 0x10, 0xC3, 0x02, 0xAF, 0xC0, 0xAA, 0xA0, 0x06, 0x3C, 0x00, 0x43, 0x00, 0x10, 0xC0,
+    0x02, 0xA0, 0x00, 0xA8, 0x00, 0x06, 0x00, 0x00, 0x40, 0x00, 0x1D, 0x95, 0x49, 0x26, 0xE0, 0x82,
+    0xB0, 0x4A, 0x09, 0x41, 0xB8, 0x20, 0xAC, 0x12, 0x82, 0x50, 0x31, 0x57, 0xF9, 0x74, 0x00, 0x0C,
+    0xA8, 0x64, 0x33, 0x0E, 0x22, 0x22, 0x22, 0xEC, 0xA8, 0x65, 0x53, 0x04, 0x22, 0x42, 0x30, 0xEC,
+    0xA8, 0x77, 0x53, 0x01, 0x3F, 0x00, 0xB3, 0x02, 0x7F, 0x01, 0x67, 0x04, 0xFF, 0x02, 0xCF,
+];
+
+// L3T3 delta frame on the first S1T2 template.
+const L3T3_S1T2_FRAME: &[u8] = &[0xD7, 0x00, 0xCB];
+
+// L3T3 S0T0 delta frame after the top spatial layer was switched off.
+const L3T3_S0T0_FRAME_WITHOUT_S2: &[u8] = &[0xCF, 0x00, 0xD4, 0x40, 0xFC];
+
+// Builds a template the way libwebrtc's scalability structures spell them, with one character
+// per decode target: '-' not present, 'D' discardable, 'S' switch and 'R' required.
+fn template(
+    spatial_id: u8,
+    temporal_id: u8,
+    dtis: &str,
+    frame_diffs: &[u16],
+    chain_diffs: &[u8],
+) -> FrameDependencyTemplate {
+    use DecodeTargetIndication::*;
+
+    FrameDependencyTemplate {
+        spatial_id,
+        temporal_id,
+        decode_target_indications: dtis
+            .chars()
+            .map(|c| match c {
+                'D' => Discardable,
+                'S' => Switch,
+                'R' => Required,
+                _ => NotPresent,
+            })
+            .collect(),
+        frame_diffs: frame_diffs.to_vec(),
+        chain_diffs: chain_diffs.to_vec(),
+    }
+}
+
+fn l1t3_structure() -> FrameDependencyStructure {
+    FrameDependencyStructure {
+        template_id_offset: 0,
+        decode_target_count: 3,
+        chain_count: 1,
+        decode_target_protected_by_chain: vec![0, 0, 0],
+        resolutions: vec![RenderResolution {
+            width: 1280,
+            height: 720,
+        }],
+        templates: vec![
+            template(0, 0, "SSS", &[], &[0]),
+            template(0, 0, "SSS", &[4], &[4]),
+            template(0, 1, "-DS", &[2], &[2]),
+            template(0, 2, "--D", &[1], &[1]),
+            template(0, 2, "--D", &[1], &[3]),
+        ],
+    }
+}
+
+fn l3t3_structure() -> FrameDependencyStructure {
+    FrameDependencyStructure {
+        template_id_offset: 15,
+        decode_target_count: 9,
+        chain_count: 3,
+        decode_target_protected_by_chain: vec![0, 0, 0, 1, 1, 1, 2, 2, 2],
+        resolutions: vec![
+            RenderResolution {
+                width: 320,
+                height: 180,
+            },
+            RenderResolution {
+                width: 640,
+                height: 360,
+            },
+            RenderResolution {
+                width: 1280,
+                height: 720,
+            },
+        ],
+        templates: vec![
+            template(0, 0, "SSSRRRRRR", &[12], &[12, 11, 10]),
+            template(0, 0, "SSSSSSSSS", &[], &[0, 0, 0]),
+            template(0, 1, "-DS-RR-RR", &[6], &[6, 5, 4]),
+            template(0, 2, "--D--R--R", &[3], &[3, 2, 1]),
+            template(0, 2, "--D--R--R", &[3], &[9, 8, 7]),
+            template(1, 0, "---SSSRRR", &[12, 1], &[1, 1, 1]),
+            template(1, 0, "---SSSSSS", &[1], &[1, 1, 1]),
+            template(1, 1, "----DS-RR", &[6, 1], &[7, 6, 5]),
+            template(1, 2, "-----D--R", &[3, 1], &[4, 3, 2]),
+            template(1, 2, "-----D--R", &[3, 1], &[10, 9, 8]),
+            template(2, 0, "------SSS", &[12, 1], &[2, 1, 1]),
+            template(2, 0, "------SSS", &[1], &[2, 1, 1]),
+            template(2, 1, "-------DS", &[6, 1], &[8, 7, 6]),
+            template(2, 2, "--------D", &[3, 1], &[5, 4, 3]),
+            template(2, 2, "--------D", &[3, 1], &[11, 10, 9]),
+        ],
+    }
+}
+
+fn marshal(dd: &DependencyDescriptorExtension) -> Result<Bytes> {
+    let mut dst = BytesMut::with_capacity(dd.marshal_size());
+    dst.resize(dd.marshal_size(), 0);
+    dd.marshal_to(&mut dst)?;
+    Ok(dst.freeze())
+}
+
+#[test]
+fn test_dependency_descriptor_mandatory_fields() -> Result<()> {
+    let raw = Bytes::from_static(&[0xC5, 0x12, 0x34]);
+    let buf = &mut raw.clone();
+    let d1 = DependencyDescriptorExtension::unmarshal(buf)?;
+    let d2 = DependencyDescriptorExtension {
+        start_of_frame: true,
+        end_of_frame: true,
+        frame_dependency_template_id: 5,
+        frame_number: 0x1234,
+        ..Default::default()
+    };
+    assert_eq!(d1, d2);
+    assert_eq!(raw, marshal(&d2)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_dependency_descriptor_too_small() -> Result<()> {
+    let raw = Bytes::from_static(&[0xC5, 0x12]);
+    let buf = &mut raw.clone();
+    let result = DependencyDescriptorExtension::unmarshal(buf);
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_dependency_descriptor_attached_structure() -> Result<()> {
+    let raw = Bytes::from_static(L1T2_KEY_FRAME);
+    let buf = &mut raw.clone();
+    let d1 = DependencyDescriptorExtension::unmarshal(buf)?;
+    let d2 = DependencyDescriptorExtension {
+        start_of_frame: true,
+        end_of_frame: true,
+        frame_dependency_template_id: 0,
+        frame_number: 1,
+        attached_structure: Some(l1t2_structure()),
+        ..Default::default()
+    };
+    assert_eq!(d1, d2);
+    assert_eq!(raw, marshal(&d2)?);
+
+    let frame = d1.frame_dependency(&FrameDependencyStructure::default())?;
+    assert_eq!(frame, l1t2_structure().templates[0]);
+
+    Ok(())
+}
+
+#[test]
+fn test_dependency_descriptor_extended_fields_with_structure() -> Result<()> {
+    let structure = l1t2_structure();
+
+    let raw = Bytes::from_static(L1T2_DELTA_FRAME);
+    let buf = &mut raw.clone();
+    let d = DependencyDescriptorExtension::unmarshal_with_structure(buf, Some(&structure))?;
+    assert_eq!(
+        d,
+        DependencyDescriptorExtension {
+            start_of_frame: true,
+            end_of_frame: true,
+            frame_dependency_template_id: 2,
+            frame_number: 2,
+            active_decode_targets_bitmask: Some(0b01),
+            custom_fdiffs: Some(vec![3]),
+            ..Default::default()
+        }
+    );
+
+    let frame = d.frame_dependency(&structure)?;
+    assert_eq!(frame.spatial_id, 0);
+    assert_eq!(frame.temporal_id, 1);
+    assert_eq!(frame.frame_diffs, vec![3]);
+    assert_eq!(frame.chain_diffs, vec![1]);
+
+    Ok(())
+}
+
+#[test]
+fn test_dependency_descriptor_extended_fields_without_structure() -> Result<()> {
+    let raw = Bytes::from_static(L1T2_DELTA_FRAME);
+    let buf = &mut raw.clone();
+    let result = DependencyDescriptorExtension::unmarshal(buf);
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_dependency_descriptor_invalid_template_id() -> Result<()> {
+    let structure = l1t2_structure();
+
+    // template 7 is not part of the structure
+    let raw = Bytes::from_static(&[0xC7, 0x00, 0x03]);
+    let buf = &mut raw.clone();
+    let result = DependencyDescriptorExtension::unmarshal_with_structure(buf, Some(&structure));
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_dependency_descriptor_custom_fields_round_trip() -> Result<()> {
+    use DecodeTargetIndication::*;
+
+    let structure = l1t2_structure();
+    let d1 = DependencyDescriptorExtension {
+        start_of_frame: true,
+        end_of_frame: false,
+        frame_dependency_template_id: 1,
+        frame_number: 0xFFFF,
+        attached_structure: Some(structure.clone()),
+        active_decode_targets_bitmask: Some(0b11),
+        custom_dtis: Some(vec![Required, Discardable]),
+        custom_fdiffs: Some(vec![1, 17, 300]),
+        custom_chains: Some(vec![4]),
+    };
+
+    let raw = marshal(&d1)?;
+    let buf = &mut raw.clone();
+    let d2 = DependencyDescriptorExtension::unmarshal(buf)?;
+    assert_eq!(d1, d2);
+
+    Ok(())
+}
+
+#[test]
+fn test_dependency_descriptor_invalid_structure() -> Result<()> {
+    let mut structure = l1t2_structure();
+    structure.templates[1].decode_target_indications.pop();
+
+    let d = DependencyDescriptorExtension {
+        attached_structure: Some(structure),
+        ..Default::default()
+    };
+    let mut dst = BytesMut::with_capacity(32);
+    dst.resize(32, 0);
+    let result = d.marshal_to(&mut dst);
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_dependency_descriptor_l1t3() -> Result<()> {
+    let raw = Bytes::from_static(L1T3_KEY_FRAME);
+    let buf = &mut raw.clone();
+    let key = DependencyDescriptorExtension::unmarshal(buf)?;
+    assert_eq!(
+        key,
+        DependencyDescriptorExtension {
+            start_of_frame: true,
+            end_of_frame: true,
+            frame_dependency_template_id: 0,
+            frame_number: 100,
+            attached_structure: Some(l1t3_structure()),
+            ..Default::default()
+        }
+    );
+    assert_eq!(raw, marshal(&key)?);
+
+    let structure = key.attached_structure.unwrap();
+    for (raw, frame_number, expected) in [
+        (L1T3_T2_FRAME, 101, template(0, 2, "--D", &[1], &[1])),
+        (L1T3_T1_FRAME, 102, template(0, 1, "-DS", &[2], &[2])),
+    ] {
+        let raw = Bytes::from_static(raw);
+        let buf = &mut raw.clone();
+        let d = DependencyDescriptorExtension::unmarshal_with_structure(buf, Some(&structure))?;
+        assert_eq!(d.frame_number, frame_number);
+        assert_eq!(d.frame_dependency(&structure)?, expected);
+        assert_eq!(raw, marshal(&d)?);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_dependency_descriptor_l3t3() -> Result<()> {
+    let raw = Bytes::from_static(L3T3_KEY_FRAME);
+    let buf = &mut raw.clone();
+    let key = DependencyDescriptorExtension::unmarshal(buf)?;
+    assert_eq!(
+        key,
+        DependencyDescriptorExtension {
+            start_of_frame: true,
+            end_of_frame: false,
+            frame_dependency_template_id: 16,
+            frame_number: 200,
+            attached_structure: Some(l3t3_structure()),
+            ..Default::default()
+        }
+    );
+    assert_eq!(raw, marshal(&key)?);
+
+    let structure = key.attached_structure.clone().unwrap();
+    assert_eq!(
+        key.frame_dependency(&structure)?,
+        template(0, 0, "SSSSSSSSS", &[], &[0, 0, 0])
+    );
+
+    let raw = Bytes::from_static(L3T3_S1T2_FRAME);
+    let buf = &mut raw.clone();
+    let d = DependencyDescriptorExtension::unmarshal_with_structure(buf, Some(&structure))?;
+    assert_eq!(d.frame_number, 203);
+    assert_eq!(
+        d.frame_dependency(&structure)?,
+        template(1, 2, "-----D--R", &[3, 1], &[4, 3, 2])
+    );
+    assert_eq!(raw, marshal(&d)?);
+
+    let raw = Bytes::from_static(L3T3_S0T0_FRAME_WITHOUT_S2);
+    let buf = &mut raw.clone();
+    let d = DependencyDescriptorExtension::unmarshal_with_structure(buf, Some(&structure))?;
+    assert_eq!(
+        d,
+        DependencyDescriptorExtension {
+            start_of_frame: true,
+            end_of_frame: true,
+            frame_dependency_template_id: 15,
+            frame_number: 212,
+            active_decode_targets_bitmask: Some(0b000111111),
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        d.frame_dependency(&structure)?,
+        template(0, 0, "SSSRRRRRR", &[12], &[12, 11, 10])
+    );
+
+    Ok(())
+}
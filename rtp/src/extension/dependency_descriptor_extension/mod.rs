@@ -0,0 +1,555 @@
+#[cfg(test)]
+mod dependency_descriptor_extension_test;
+
+use crate::error::Error;
+use crate::util::bits::{BitReader, BitWriter};
+use serde::{Deserialize, Serialize};
+use util::marshal::{Marshal, MarshalSize, Unmarshal};
+
+use bytes::{Buf, BufMut};
+
+// MANDATORY_FIELDS_SIZE is the size of start_of_frame, end_of_frame,
+// frame_dependency_template_id and frame_number
+pub const MANDATORY_FIELDS_SIZE: usize = 3;
+// MAX_TEMPLATES is the number of distinct frame_dependency_template_id values
+pub const MAX_TEMPLATES: usize = 64;
+// MAX_DECODE_TARGETS is the largest DtCnt that can be signaled
+pub const MAX_DECODE_TARGETS: usize = 32;
+
+/// DecodeTargetIndication tells how a frame relates to a decode target, see table A.1 of the
+/// AV1 RTP specification.
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum DecodeTargetIndication {
+    /// The frame is not associated with the decode target
+    NotPresent = 0,
+    /// The frame is not needed by any later frame of the decode target
+    Discardable = 1,
+    /// A receiver can switch to the decode target starting with this frame
+    Switch = 2,
+    /// The frame is needed to decode the decode target
+    Required = 3,
+}
+
+impl Default for DecodeTargetIndication {
+    fn default() -> Self {
+        DecodeTargetIndication::NotPresent
+    }
+}
+
+impl From<u32> for DecodeTargetIndication {
+    fn from(v: u32) -> Self {
+        match v & 0x3 {
+            1 => DecodeTargetIndication::Discardable,
+            2 => DecodeTargetIndication::Switch,
+            3 => DecodeTargetIndication::Required,
+            _ => DecodeTargetIndication::NotPresent,
+        }
+    }
+}
+
+/// FrameDependencyTemplate describes the layer and dependencies shared by the frames that refer
+/// to it. It is also used to describe the resolved dependencies of a single frame.
+#[derive(PartialEq, Eq, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FrameDependencyTemplate {
+    pub spatial_id: u8,
+    pub temporal_id: u8,
+    /// One indication per decode target
+    pub decode_target_indications: Vec<DecodeTargetIndication>,
+    /// Differences between this frame number and the frame numbers it depends on
+    pub frame_diffs: Vec<u16>,
+    /// One difference per chain to the previous frame in that chain, 0 if there is none
+    pub chain_diffs: Vec<u8>,
+}
+
+/// RenderResolution is the resolution of a spatial layer.
+#[derive(PartialEq, Eq, Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct RenderResolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// FrameDependencyStructure is the template dependency structure, sent with key frames and
+/// kept by the receiver to interpret the descriptors of the frames that follow.
+#[derive(PartialEq, Eq, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FrameDependencyStructure {
+    pub template_id_offset: u8,
+    pub decode_target_count: u8,
+    pub chain_count: u8,
+    /// For every decode target, the chain protecting it. Empty if chain_count is 0
+    pub decode_target_protected_by_chain: Vec<u8>,
+    /// One resolution per spatial layer, or none
+    pub resolutions: Vec<RenderResolution>,
+    pub templates: Vec<FrameDependencyTemplate>,
+}
+
+/// DependencyDescriptorExtension is the AV1 Dependency Descriptor header extension described in
+/// https://aomediacodec.github.io/av1-rtp-spec/#dependency-descriptor-rtp-header-extension
+///
+/// Only the mandatory fields are always present:
+/// 0                   1                   2
+/// 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |S|E|  template |         frame_number          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// The extended fields are only interpretable with the template dependency structure, either
+/// attached to the same packet or received earlier, see `unmarshal_with_structure`.
+#[derive(PartialEq, Eq, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DependencyDescriptorExtension {
+    pub start_of_frame: bool,
+    pub end_of_frame: bool,
+    pub frame_dependency_template_id: u8,
+    pub frame_number: u16,
+    pub attached_structure: Option<FrameDependencyStructure>,
+    /// Bitmask of the active decode targets, only set when it is signaled in the packet. All
+    /// decode targets are active after a structure is attached. Marshaling it needs either the
+    /// attached structure or the custom DTIs to know the number of decode targets.
+    pub active_decode_targets_bitmask: Option<u32>,
+    pub custom_dtis: Option<Vec<DecodeTargetIndication>>,
+    pub custom_fdiffs: Option<Vec<u16>>,
+    pub custom_chains: Option<Vec<u8>>,
+}
+
+impl DependencyDescriptorExtension {
+    /// unmarshal_with_structure parses a dependency descriptor whose extended fields refer to
+    /// `structure`, the latest structure received on the stream. A structure attached to the
+    /// descriptor itself takes precedence.
+    pub fn unmarshal_with_structure<B>(
+        raw_packet: &mut B,
+        structure: Option<&FrameDependencyStructure>,
+    ) -> Result<Self, Error>
+    where
+        B: Buf,
+    {
+        let size = raw_packet.remaining();
+        if size < MANDATORY_FIELDS_SIZE {
+            return Err(Error::ErrBufferTooSmall);
+        }
+        let raw = raw_packet.copy_to_bytes(size);
+        let mut r = BitReader::new(&raw);
+
+        Self::read(&mut r, size, structure).map_err(|err| match err {
+            Error::ErrShortPacket => Error::ErrBufferTooSmall,
+            err => err,
+        })
+    }
+
+    fn read(
+        r: &mut BitReader<'_>,
+        size: usize,
+        structure: Option<&FrameDependencyStructure>,
+    ) -> Result<Self, Error> {
+        let mut dd = DependencyDescriptorExtension {
+            start_of_frame: r.read_bool()?,
+            end_of_frame: r.read_bool()?,
+            frame_dependency_template_id: r.read_bits(6)? as u8,
+            frame_number: r.read_bits(16)? as u16,
+            ..Default::default()
+        };
+
+        if size == MANDATORY_FIELDS_SIZE {
+            if let Some(structure) = structure {
+                dd.template_index(structure)?;
+            }
+            return Ok(dd);
+        }
+
+        let structure_present = r.read_bool()?;
+        let active_decode_targets_present = r.read_bool()?;
+        let custom_dtis = r.read_bool()?;
+        let custom_fdiffs = r.read_bool()?;
+        let custom_chains = r.read_bool()?;
+
+        if structure_present {
+            dd.attached_structure = Some(read_structure(r)?);
+        }
+        let structure = match (&dd.attached_structure, structure) {
+            (Some(attached), _) => Some(attached),
+            (None, Some(structure)) => Some(structure),
+            (None, None) => None,
+        };
+
+        if structure.is_none() && (active_decode_targets_present || custom_dtis || custom_chains) {
+            return Err(Error::ErrDependencyDescriptorStructureMissing);
+        }
+
+        if let Some(structure) = structure {
+            if active_decode_targets_present {
+                dd.active_decode_targets_bitmask =
+                    Some(r.read_bits(structure.decode_target_count as usize)?);
+            }
+            dd.template_index(structure)?;
+        }
+
+        if custom_dtis {
+            let decode_target_count = structure.map_or(0, |s| s.decode_target_count);
+            let mut dtis = Vec::with_capacity(decode_target_count as usize);
+            for _ in 0..decode_target_count {
+                dtis.push(DecodeTargetIndication::from(r.read_bits(2)?));
+            }
+            dd.custom_dtis = Some(dtis);
+        }
+        if custom_fdiffs {
+            let mut fdiffs = vec![];
+            loop {
+                let next_fdiff_size = r.read_bits(2)? as usize;
+                if next_fdiff_size == 0 {
+                    break;
+                }
+                fdiffs.push(r.read_bits(4 * next_fdiff_size)? as u16 + 1);
+            }
+            dd.custom_fdiffs = Some(fdiffs);
+        }
+        if custom_chains {
+            let chain_count = structure.map_or(0, |s| s.chain_count);
+            let mut chains = Vec::with_capacity(chain_count as usize);
+            for _ in 0..chain_count {
+                chains.push(r.read_bits(8)? as u8);
+            }
+            dd.custom_chains = Some(chains);
+        }
+
+        // the rest is zero padding
+        Ok(dd)
+    }
+
+    /// frame_dependency returns the layer and dependencies of the frame, taken from its
+    /// template in `structure` and overridden by the custom fields of the descriptor.
+    pub fn frame_dependency(
+        &self,
+        structure: &FrameDependencyStructure,
+    ) -> Result<FrameDependencyTemplate, Error> {
+        let structure = self.attached_structure.as_ref().unwrap_or(structure);
+        let template = structure
+            .templates
+            .get(self.template_index(structure)?)
+            .ok_or(Error::ErrDependencyDescriptorInvalidTemplateId)?;
+
+        Ok(FrameDependencyTemplate {
+            spatial_id: template.spatial_id,
+            temporal_id: template.temporal_id,
+            decode_target_indications: self
+                .custom_dtis
+                .clone()
+                .unwrap_or_else(|| template.decode_target_indications.clone()),
+            frame_diffs: self
+                .custom_fdiffs
+                .clone()
+                .unwrap_or_else(|| template.frame_diffs.clone()),
+            chain_diffs: self
+                .custom_chains
+                .clone()
+                .unwrap_or_else(|| template.chain_diffs.clone()),
+        })
+    }
+
+    fn template_index(&self, structure: &FrameDependencyStructure) -> Result<usize, Error> {
+        let index = (self.frame_dependency_template_id as usize + MAX_TEMPLATES
+            - structure.template_id_offset as usize)
+            % MAX_TEMPLATES;
+        if index < structure.templates.len() {
+            Ok(index)
+        } else {
+            Err(Error::ErrDependencyDescriptorInvalidTemplateId)
+        }
+    }
+
+    fn has_extended_fields(&self) -> bool {
+        self.attached_structure.is_some()
+            || self.active_decode_targets_bitmask.is_some()
+            || self.custom_dtis.is_some()
+            || self.custom_fdiffs.is_some()
+            || self.custom_chains.is_some()
+    }
+
+    fn write(&self, w: &mut BitWriter) -> Result<(), Error> {
+        self.write_fields(w).map_err(|err| match err {
+            Error::ErrBitFieldOverflow => Error::ErrDependencyDescriptorInvalidStructure,
+            err => err,
+        })
+    }
+
+    fn write_fields(&self, w: &mut BitWriter) -> Result<(), Error> {
+        w.write_bool(self.start_of_frame);
+        w.write_bool(self.end_of_frame);
+        w.write_bits(self.frame_dependency_template_id as u32, 6)?;
+        w.write_bits(self.frame_number as u32, 16)?;
+
+        if !self.has_extended_fields() {
+            return Ok(());
+        }
+
+        w.write_bool(self.attached_structure.is_some());
+        w.write_bool(self.active_decode_targets_bitmask.is_some());
+        w.write_bool(self.custom_dtis.is_some());
+        w.write_bool(self.custom_fdiffs.is_some());
+        w.write_bool(self.custom_chains.is_some());
+
+        if let Some(structure) = &self.attached_structure {
+            write_structure(w, structure)?;
+        }
+        if let Some(bitmask) = self.active_decode_targets_bitmask {
+            let decode_target_count = match (&self.attached_structure, &self.custom_dtis) {
+                (Some(structure), _) => structure.decode_target_count as usize,
+                (None, Some(dtis)) => dtis.len(),
+                (None, None) => return Err(Error::ErrDependencyDescriptorStructureMissing),
+            };
+            w.write_bits(bitmask, decode_target_count)?;
+        }
+        if let Some(dtis) = &self.custom_dtis {
+            for dti in dtis {
+                w.write_bits(*dti as u32, 2)?;
+            }
+        }
+        if let Some(fdiffs) = &self.custom_fdiffs {
+            for fdiff in fdiffs {
+                let fdiff_minus_one = fdiff
+                    .checked_sub(1)
+                    .ok_or(Error::ErrDependencyDescriptorInvalidStructure)?
+                    as u32;
+                let next_fdiff_size = match fdiff_minus_one {
+                    0..=0xF => 1,
+                    0x10..=0xFF => 2,
+                    0x100..=0xFFF => 3,
+                    _ => return Err(Error::ErrDependencyDescriptorInvalidStructure),
+                };
+                w.write_bits(next_fdiff_size as u32, 2)?;
+                w.write_bits(fdiff_minus_one, 4 * next_fdiff_size)?;
+            }
+            w.write_bits(0, 2)?;
+        }
+        if let Some(chains) = &self.custom_chains {
+            for chain in chains {
+                w.write_bits(*chain as u32, 8)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn read_structure(r: &mut BitReader<'_>) -> Result<FrameDependencyStructure, Error> {
+    let template_id_offset = r.read_bits(6)? as u8;
+    let decode_target_count = r.read_bits(5)? as u8 + 1;
+
+    // template_layers
+    let mut templates = vec![];
+    let (mut spatial_id, mut temporal_id) = (0u8, 0u8);
+    loop {
+        if templates.len() == MAX_TEMPLATES {
+            return Err(Error::ErrDependencyDescriptorInvalidStructure);
+        }
+        templates.push(FrameDependencyTemplate {
+            spatial_id,
+            temporal_id,
+            ..Default::default()
+        });
+        match r.read_bits(2)? {
+            0 => {}
+            1 => temporal_id += 1,
+            2 => {
+                temporal_id = 0;
+                spatial_id += 1;
+            }
+            _ => break,
+        }
+    }
+
+    // template_dtis
+    for template in &mut templates {
+        for _ in 0..decode_target_count {
+            template
+                .decode_target_indications
+                .push(DecodeTargetIndication::from(r.read_bits(2)?));
+        }
+    }
+
+    // template_fdiffs
+    for template in &mut templates {
+        while r.read_bool()? {
+            template.frame_diffs.push(r.read_bits(4)? as u16 + 1);
+        }
+    }
+
+    // template_chains
+    let chain_count = r.read_ns(decode_target_count as u32 + 1)? as u8;
+    let mut decode_target_protected_by_chain = vec![];
+    if chain_count > 0 {
+        for _ in 0..decode_target_count {
+            decode_target_protected_by_chain.push(r.read_ns(chain_count as u32)? as u8);
+        }
+        for template in &mut templates {
+            for _ in 0..chain_count {
+                template.chain_diffs.push(r.read_bits(4)? as u8);
+            }
+        }
+    }
+
+    // render_resolutions
+    let mut resolutions = vec![];
+    if r.read_bool()? {
+        for _ in 0..=spatial_id {
+            resolutions.push(RenderResolution {
+                width: r.read_bits(16)? + 1,
+                height: r.read_bits(16)? + 1,
+            });
+        }
+    }
+
+    Ok(FrameDependencyStructure {
+        template_id_offset,
+        decode_target_count,
+        chain_count,
+        decode_target_protected_by_chain,
+        resolutions,
+        templates,
+    })
+}
+
+fn write_structure(w: &mut BitWriter, structure: &FrameDependencyStructure) -> Result<(), Error> {
+    let decode_target_count = structure.decode_target_count as usize;
+    let chain_count = structure.chain_count as usize;
+    if !(1..=MAX_DECODE_TARGETS).contains(&decode_target_count)
+        || chain_count > decode_target_count
+        || structure.templates.is_empty()
+        || structure.templates.len() > MAX_TEMPLATES
+    {
+        return Err(Error::ErrDependencyDescriptorInvalidStructure);
+    }
+
+    w.write_bits(structure.template_id_offset as u32, 6)?;
+    w.write_bits(decode_target_count as u32 - 1, 5)?;
+
+    // template_layers, the templates are sorted by spatial and then temporal id
+    let first = structure
+        .templates
+        .first()
+        .ok_or(Error::ErrDependencyDescriptorInvalidStructure)?;
+    if first.spatial_id != 0 || first.temporal_id != 0 {
+        return Err(Error::ErrDependencyDescriptorInvalidStructure);
+    }
+    for (i, template) in structure.templates.iter().enumerate() {
+        let next_layer_idc = match structure.templates.get(i + 1) {
+            None => 3,
+            Some(next)
+                if next.spatial_id == template.spatial_id
+                    && next.temporal_id == template.temporal_id =>
+            {
+                0
+            }
+            Some(next)
+                if next.spatial_id == template.spatial_id
+                    && next.temporal_id == template.temporal_id + 1 =>
+            {
+                1
+            }
+            Some(next) if next.spatial_id == template.spatial_id + 1 && next.temporal_id == 0 => 2,
+            Some(_) => return Err(Error::ErrDependencyDescriptorInvalidStructure),
+        };
+        w.write_bits(next_layer_idc, 2)?;
+    }
+
+    // template_dtis
+    for template in &structure.templates {
+        if template.decode_target_indications.len() != decode_target_count {
+            return Err(Error::ErrDependencyDescriptorInvalidStructure);
+        }
+        for dti in &template.decode_target_indications {
+            w.write_bits(*dti as u32, 2)?;
+        }
+    }
+
+    // template_fdiffs
+    for template in &structure.templates {
+        for fdiff in &template.frame_diffs {
+            if !(1..=16).contains(fdiff) {
+                return Err(Error::ErrDependencyDescriptorInvalidStructure);
+            }
+            w.write_bool(true);
+            w.write_bits(*fdiff as u32 - 1, 4)?;
+        }
+        w.write_bool(false);
+    }
+
+    // template_chains
+    w.write_ns(chain_count as u32, decode_target_count as u32 + 1)?;
+    if chain_count > 0 {
+        if structure.decode_target_protected_by_chain.len() != decode_target_count {
+            return Err(Error::ErrDependencyDescriptorInvalidStructure);
+        }
+        for chain in &structure.decode_target_protected_by_chain {
+            w.write_ns(*chain as u32, chain_count as u32)?;
+        }
+        for template in &structure.templates {
+            if template.chain_diffs.len() != chain_count {
+                return Err(Error::ErrDependencyDescriptorInvalidStructure);
+            }
+            for chain_diff in &template.chain_diffs {
+                w.write_bits(*chain_diff as u32, 4)?;
+            }
+        }
+    }
+
+    // render_resolutions
+    w.write_bool(!structure.resolutions.is_empty());
+    if !structure.resolutions.is_empty() {
+        let max_spatial_id = structure
+            .templates
+            .last()
+            .map_or(0, |template| template.spatial_id);
+        if structure.resolutions.len() != max_spatial_id as usize + 1 {
+            return Err(Error::ErrDependencyDescriptorInvalidStructure);
+        }
+        for resolution in &structure.resolutions {
+            if resolution.width == 0 || resolution.height == 0 {
+                return Err(Error::ErrDependencyDescriptorInvalidStructure);
+            }
+            w.write_bits(resolution.width - 1, 16)?;
+            w.write_bits(resolution.height - 1, 16)?;
+        }
+    }
+
+    Ok(())
+}
+
+impl Unmarshal for DependencyDescriptorExtension {
+    /// Unmarshal parses the passed byte slice and stores the result in the members. Descriptors
+    /// whose extended fields need a structure received in an earlier packet fail to parse, use
+    /// `unmarshal_with_structure` for those.
+    fn unmarshal<B>(raw_packet: &mut B) -> Result<Self, util::Error>
+    where
+        Self: Sized,
+        B: Buf,
+    {
+        Ok(DependencyDescriptorExtension::unmarshal_with_structure(
+            raw_packet, None,
+        )?)
+    }
+}
+
+impl MarshalSize for DependencyDescriptorExtension {
+    /// MarshalSize returns the size of the DependencyDescriptorExtension once marshaled.
+    fn marshal_size(&self) -> usize {
+        let mut w = BitWriter::default();
+        match self.write(&mut w) {
+            Ok(()) => w.bytes().len(),
+            Err(_) => 0,
+        }
+    }
+}
+
+impl Marshal for DependencyDescriptorExtension {
+    /// MarshalTo serializes the members to buffer
+    fn marshal_to(&self, mut buf: &mut [u8]) -> Result<usize, util::Error> {
+        let mut w = BitWriter::default();
+        self.write(&mut w)?;
+
+        let raw = w.bytes();
+        if buf.remaining_mut() < raw.len() {
+            return Err(Error::ErrBufferTooSmall.into());
+        }
+        buf.put(raw);
+
+        Ok(raw.len())
+    }
+}
@@ -5,6 +5,8 @@ use util::{Marshal, MarshalSize};
 
 pub mod abs_send_time_extension;
 pub mod audio_level_extension;
+pub mod csrc_audio_level_extension;
+pub mod dependency_descriptor_extension;
 pub mod transport_cc_extension;
 pub mod video_orientation_extension;
 
@@ -14,6 +16,7 @@ pub enum HeaderExtension {
     AudioLevel(audio_level_extension::AudioLevelExtension),
     TransportCc(transport_cc_extension::TransportCcExtension),
     VideoOrientation(video_orientation_extension::VideoOrientationExtension),
+    DependencyDescriptor(dependency_descriptor_extension::DependencyDescriptorExtension),
 
     /// A custom extension
     Custom {
@@ -33,6 +36,10 @@ impl HeaderExtension {
                 "http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01".into()
             }
             VideoOrientation(_) => "urn:3gpp:video-orientation".into(),
+            DependencyDescriptor(_) => {
+                "https://aomediacodec.github.io/av1-rtp-spec/#dependency-descriptor-rtp-header-extension"
+                    .into()
+            }
             Custom { uri, .. } => uri.clone(),
         }
     }
@@ -44,6 +51,7 @@ impl HeaderExtension {
             (AudioLevel(_), AudioLevel(_)) => true,
             (TransportCc(_), TransportCc(_)) => true,
             (VideoOrientation(_), VideoOrientation(_)) => true,
+            (DependencyDescriptor(_), DependencyDescriptor(_)) => true,
             (Custom { uri, .. }, Custom { uri: other_uri, .. }) => uri == other_uri,
             _ => false,
         }
@@ -58,6 +66,7 @@ impl MarshalSize for HeaderExtension {
             AudioLevel(ext) => ext.marshal_size(),
             TransportCc(ext) => ext.marshal_size(),
             VideoOrientation(ext) => ext.marshal_size(),
+            DependencyDescriptor(ext) => ext.marshal_size(),
             Custom { extension: ext, .. } => ext.marshal_size(),
         }
     }
@@ -71,6 +80,7 @@ impl Marshal for HeaderExtension {
             AudioLevel(ext) => ext.marshal_to(buf),
             TransportCc(ext) => ext.marshal_to(buf),
             VideoOrientation(ext) => ext.marshal_to(buf),
+            DependencyDescriptor(ext) => ext.marshal_to(buf),
             Custom { extension: ext, .. } => ext.marshal_to(buf),
         }
     }
@@ -85,6 +95,7 @@ impl fmt::Debug for HeaderExtension {
             AudioLevel(ext) => f.debug_tuple("AudioLevel").field(ext).finish(),
             TransportCc(ext) => f.debug_tuple("TransportCc").field(ext).finish(),
             VideoOrientation(ext) => f.debug_tuple("VideoOrientation").field(ext).finish(),
+            DependencyDescriptor(ext) => f.debug_tuple("DependencyDescriptor").field(ext).finish(),
             Custom { uri, extension: _ } => f.debug_struct("Custom").field("uri", uri).finish(),
         }
     }
@@ -25,16 +25,20 @@ pub const SSRC_OFFSET: usize = 8;
 pub const SSRC_LENGTH: usize = 4;
 pub const CSRC_OFFSET: usize = 12;
 pub const CSRC_LENGTH: usize = 4;
+pub const CSRC_MAX_COUNT: usize = 15;
 
 #[derive(Debug, Eq, PartialEq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Extension {
     pub id: u8,
+    #[cfg_attr(feature = "serde", serde(with = "util::serde_hex"))]
     pub payload: Bytes,
 }
 
 /// Header represents an RTP packet header
 /// NOTE: PayloadOffset is populated by Marshal/Unmarshal and should not be modified
 #[derive(Debug, Eq, PartialEq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Header {
     pub version: u8,
     pub padding: bool,
@@ -232,6 +236,9 @@ impl Marshal for Header {
         if remaining_before < self.marshal_size() {
             return Err(Error::ErrBufferTooSmall.into());
         }
+        if self.csrc.len() > CSRC_MAX_COUNT {
+            return Err(Error::ErrTooManyCsrc.into());
+        }
 
         // The first byte contains the version, padding bit, extension bit, and csrc size
         let mut b0 = (self.version << VERSION_SHIFT) | self.csrc.len() as u8;
@@ -387,6 +394,16 @@ impl Header {
         Ok(())
     }
 
+    /// set_csrc replaces the contributing source (CSRC) list, at most 15 entries fit in the
+    /// 4-bit CC field
+    pub fn set_csrc(&mut self, csrc: Vec<u32>) -> Result<(), Error> {
+        if csrc.len() > CSRC_MAX_COUNT {
+            return Err(Error::ErrTooManyCsrc);
+        }
+        self.csrc = csrc;
+        Ok(())
+    }
+
     /// returns an extension id array
     pub fn get_extension_ids(&self) -> Vec<u8> {
         if self.extension {
@@ -7,6 +7,8 @@ pub mod extension;
 pub mod header;
 pub mod packet;
 pub mod packetizer;
+pub mod seqnum;
 pub mod sequence;
+pub mod util;
 
 pub use error::Error;
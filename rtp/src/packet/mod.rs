@@ -10,8 +10,10 @@ use std::fmt;
 /// Packet represents an RTP Packet
 /// NOTE: Raw is populated by Marshal/Unmarshal and should not be modified
 #[derive(Debug, Eq, PartialEq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Packet {
     pub header: Header,
+    #[cfg_attr(feature = "serde", serde(with = "util::serde_hex"))]
     pub payload: Bytes,
 }
 
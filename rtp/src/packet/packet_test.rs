@@ -1249,3 +1249,101 @@ fn test_round_trip() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_csrc_round_trip() -> Result<()> {
+    for csrc_count in [0usize, 1, CSRC_MAX_COUNT] {
+        let csrc: Vec<u32> = (0..csrc_count as u32).map(|i| 0x1000 + i).collect();
+        let mut header = Header {
+            version: 2,
+            padding: true,
+            marker: true,
+            payload_type: 111,
+            sequence_number: 27023,
+            timestamp: 3653407706,
+            ssrc: 476325762,
+            ..Default::default()
+        };
+        header.set_csrc(csrc.clone())?;
+        header.set_extension(1, Bytes::from_static(&[0x88]))?;
+        header.set_extension(2, Bytes::from(vec![0x7F; csrc_count.max(1)]))?;
+        let packet = Packet {
+            header,
+            payload: Bytes::from_static(&[0x98, 0x36, 0xbe, 0x88, 0x9e]),
+        };
+
+        let raw = packet.marshal()?;
+        assert_eq!(raw.len(), packet.marshal_size(), "wrong marshal size");
+        assert_eq!(
+            (raw[0] & CC_MASK) as usize,
+            csrc_count,
+            "wrong CC field for {} CSRCs",
+            csrc_count
+        );
+        assert_eq!(
+            raw[0] >> PADDING_SHIFT & PADDING_MASK,
+            1,
+            "padding bit must not be clobbered by the CC field"
+        );
+        assert_eq!(
+            raw[0] >> EXTENSION_SHIFT & EXTENSION_MASK,
+            1,
+            "extension bit must not be clobbered by the CC field"
+        );
+
+        let buf = &mut raw.clone();
+        let parsed = Packet::unmarshal(buf)?;
+        assert_eq!(parsed, packet, "round trip with {} CSRCs", csrc_count);
+        assert_eq!(parsed.header.csrc, csrc);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_too_many_csrc() -> Result<()> {
+    let mut header = Header::default();
+    let result = header.set_csrc(vec![0; CSRC_MAX_COUNT + 1]);
+    assert_eq!(result, Err(Error::ErrTooManyCsrc));
+    assert!(header.csrc.is_empty());
+
+    let packet = Packet {
+        header: Header {
+            version: 2,
+            csrc: vec![0; CSRC_MAX_COUNT + 1],
+            ..Default::default()
+        },
+        payload: Bytes::from_static(&[0x98, 0x36, 0xbe, 0x88]),
+    };
+    let result = packet.marshal();
+    assert!(
+        result.is_err(),
+        "Marshal did not error on packet with too many CSRCs"
+    );
+    if let Err(err) = result {
+        assert_eq!(Error::ErrTooManyCsrc, err);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trip() -> Result<()> {
+    let raw_pkt = Bytes::from_static(&[
+        0x90, 0xe0, 0x69, 0x8f, 0xd9, 0xc2, 0x93, 0xda, 0x1c, 0x64, 0x27, 0x82, 0x00, 0x01, 0x00,
+        0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0x98, 0x36, 0xbe, 0x88, 0x9e,
+    ]);
+    let packet = Packet::unmarshal(&mut raw_pkt.clone())?;
+
+    let json = serde_json::to_value(&packet).unwrap();
+    assert_eq!(json["header"]["sequence_number"], 27023);
+    assert_eq!(json["header"]["extensions"][0]["payload"], "ffffffff");
+    assert_eq!(json["payload"], "9836be889e");
+
+    let parsed: Packet = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed, packet);
+    assert_eq!(parsed.marshal()?, raw_pkt);
+
+    Ok(())
+}
@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod packetizer_test;
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::{extension::abs_send_time_extension::*, header::*, packet::*, sequence::*};
 use util::marshal::{Marshal, MarshalSize};
 
@@ -29,7 +29,17 @@ impl Clone for Box<dyn Payloader + Send + Sync> {
 #[async_trait]
 pub trait Packetizer: fmt::Debug {
     fn enable_abs_send_time(&mut self, value: u8);
+    /// set_csrc sets the contributing sources (CSRC) added to every packet, e.g. by a mixer
+    fn set_csrc(&mut self, csrc: Vec<u32>) -> Result<()>;
     async fn packetize(&mut self, payload: &Bytes, samples: u32) -> Result<Vec<Packet>>;
+    /// packetize_with_csrc packetizes like `packetize`, but uses `csrc` as the contributing
+    /// sources of the produced packets instead of the ones set with `set_csrc`
+    async fn packetize_with_csrc(
+        &mut self,
+        payload: &Bytes,
+        samples: u32,
+        csrc: &[u32],
+    ) -> Result<Vec<Packet>>;
     fn skip_samples(&mut self, skipped_samples: u32);
     fn clone_to(&self) -> Box<dyn Packetizer + Send + Sync>;
 }
@@ -65,6 +75,7 @@ pub(crate) struct PacketizerImpl {
     pub(crate) mtu: usize,
     pub(crate) payload_type: u8,
     pub(crate) ssrc: u32,
+    pub(crate) csrc: Vec<u32>,
     pub(crate) payloader: Box<dyn Payloader + Send + Sync>,
     pub(crate) sequencer: Box<dyn Sequencer + Send + Sync>,
     pub(crate) timestamp: u32,
@@ -79,6 +90,7 @@ impl fmt::Debug for PacketizerImpl {
             .field("mtu", &self.mtu)
             .field("payload_type", &self.payload_type)
             .field("ssrc", &self.ssrc)
+            .field("csrc", &self.csrc)
             .field("timestamp", &self.timestamp)
             .field("clock_rate", &self.clock_rate)
             .field("abs_send_time", &self.abs_send_time)
@@ -98,6 +110,7 @@ pub fn new_packetizer(
         mtu,
         payload_type,
         ssrc,
+        csrc: vec![],
         payloader,
         sequencer,
         timestamp: rand::random::<u32>(),
@@ -113,8 +126,34 @@ impl Packetizer for PacketizerImpl {
         self.abs_send_time = value
     }
 
+    fn set_csrc(&mut self, csrc: Vec<u32>) -> Result<()> {
+        if csrc.len() > CSRC_MAX_COUNT {
+            return Err(Error::ErrTooManyCsrc);
+        }
+        self.csrc = csrc;
+        Ok(())
+    }
+
     async fn packetize(&mut self, payload: &Bytes, samples: u32) -> Result<Vec<Packet>> {
-        let payloads = self.payloader.payload(self.mtu - 12, payload)?;
+        let csrc = self.csrc.clone();
+        self.packetize_with_csrc(payload, samples, &csrc).await
+    }
+
+    async fn packetize_with_csrc(
+        &mut self,
+        payload: &Bytes,
+        samples: u32,
+        csrc: &[u32],
+    ) -> Result<Vec<Packet>> {
+        if csrc.len() > CSRC_MAX_COUNT {
+            return Err(Error::ErrTooManyCsrc);
+        }
+
+        // the CSRC list is part of the fixed header, so it reduces the room left for payload
+        let header_size = CSRC_OFFSET + csrc.len() * CSRC_LENGTH;
+        let payloads = self
+            .payloader
+            .payload(self.mtu.saturating_sub(header_size), payload)?;
         let payloads_len = payloads.len();
         let mut packets = Vec::with_capacity(payloads_len);
         for (i, payload) in payloads.into_iter().enumerate() {
@@ -128,6 +167,7 @@ impl Packetizer for PacketizerImpl {
                     sequence_number: self.sequencer.next_sequence_number(),
                     timestamp: self.timestamp, //TODO: Figure out how to do timestamps
                     ssrc: self.ssrc,
+                    csrc: csrc.to_vec(),
                     ..Default::default()
                 },
                 payload,
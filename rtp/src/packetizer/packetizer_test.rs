@@ -53,6 +53,7 @@ async fn test_packetizer_abs_send_time() -> Result<()> {
         mtu: 100,
         payload_type: 98,
         ssrc: 0x1234ABCD,
+        csrc: vec![],
         payloader: g722,
         sequencer,
         timestamp: 45678,
@@ -110,3 +111,41 @@ async fn test_packetizer_timestamp_rollover_does_not_panic() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_packetizer_csrc() -> Result<()> {
+    let g722 = Box::new(g7xx::G722Payloader {});
+    let seq = Box::new(new_fixed_sequencer(1234));
+
+    let payload = Bytes::from_static(&[0; 128]);
+    let mut packetizer = new_packetizer(100, 98, 0x1234ABCD, g722, seq, 90000);
+    packetizer.set_csrc(vec![1, 2])?;
+
+    let packets = packetizer.packetize(&payload, 2000).await?;
+    assert_eq!(packets.len(), 2);
+    for packet in &packets {
+        assert_eq!(packet.header.csrc, vec![1, 2]);
+        assert!(packet.marshal_size() <= 100, "packet exceeds the mtu");
+    }
+
+    // the per packet override wins over the packetizer CSRCs
+    let packets = packetizer.packetize_with_csrc(&payload, 2000, &[3]).await?;
+    for packet in &packets {
+        assert_eq!(packet.header.csrc, vec![3]);
+    }
+
+    let packets = packetizer.packetize(&payload, 2000).await?;
+    for packet in &packets {
+        assert_eq!(packet.header.csrc, vec![1, 2]);
+    }
+
+    let result = packetizer.set_csrc(vec![0; CSRC_MAX_COUNT + 1]);
+    assert_eq!(result, Err(Error::ErrTooManyCsrc));
+
+    let result = packetizer
+        .packetize_with_csrc(&payload, 2000, &[0; CSRC_MAX_COUNT + 1])
+        .await;
+    assert_eq!(result, Err(Error::ErrTooManyCsrc));
+
+    Ok(())
+}
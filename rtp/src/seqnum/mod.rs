@@ -0,0 +1,180 @@
+#[cfg(test)]
+mod seqnum_test;
+
+use std::cmp::Ordering;
+
+const HALF: u16 = 1 << 15;
+
+/// is_newer returns true if the sequence number a was sent after b. It is, if a follows b by
+/// less than half of the sequence number space, counting across the wraparound. When a and b
+/// are exactly half of it apart, the larger one is newer, so that of two different sequence
+/// numbers exactly one is newer than the other.
+pub fn is_newer(a: u16, b: u16) -> bool {
+    diff(a, b) > 0
+}
+
+/// diff returns the number of sequence numbers from b to a, counting across the wraparound.
+/// It is in -32768..=32768, and positive if a is newer than b as in `is_newer`.
+pub fn diff(a: u16, b: u16) -> i32 {
+    let d = a.wrapping_sub(b);
+    match d.cmp(&HALF) {
+        Ordering::Less => d as i32,
+        Ordering::Greater => d as i32 - (1 << 16),
+        Ordering::Equal if a > b => HALF as i32,
+        Ordering::Equal => -(HALF as i32),
+    }
+}
+
+/// ExtendedSeqnum extends 16 bit sequence numbers to 64 bit ones that don't wrap, by tracking
+/// how often the sequence numbers rolled over (the rollover counter, ROC). Each sequence
+/// number is extended to the value closest to the highest one seen so far, as in the index
+/// estimation of RFC 3711 Section 3.3.1.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtendedSeqnum {
+    highest: u64,
+    started: bool,
+}
+
+impl ExtendedSeqnum {
+    pub fn new() -> Self {
+        ExtendedSeqnum::default()
+    }
+
+    /// extend returns the extended sequence number of seq, without recording it. The first
+    /// sequence number is extended with the rollover counter, 0 unless set otherwise. Returns
+    /// None if seq is older than the highest sequence number, but would precede extended
+    /// sequence number 0.
+    pub fn extend(&self, seq: u16) -> Option<u64> {
+        if !self.started {
+            return Some(self.highest & !0xFFFF | seq as u64);
+        }
+
+        let d = diff(seq, self.highest as u16);
+        if d >= 0 {
+            self.highest.checked_add(d as u64)
+        } else {
+            self.highest.checked_sub(d.unsigned_abs() as u64)
+        }
+    }
+
+    /// update returns the extended sequence number of seq, like `extend`, and records it as
+    /// the highest one if it is newer.
+    pub fn update(&mut self, seq: u16) -> Option<u64> {
+        let extended = self.extend(seq)?;
+        if !self.started || extended > self.highest {
+            self.highest = extended;
+            self.started = true;
+        }
+        Some(extended)
+    }
+
+    /// highest returns the highest extended sequence number recorded by `update`.
+    pub fn highest(&self) -> Option<u64> {
+        if self.started {
+            Some(self.highest)
+        } else {
+            None
+        }
+    }
+
+    /// roll_over_count returns the rollover counter of the highest sequence number.
+    pub fn roll_over_count(&self) -> u64 {
+        self.highest >> 16
+    }
+
+    /// set_roll_over_count sets the rollover counter of the highest sequence number, or of the
+    /// first one if none was recorded yet.
+    pub fn set_roll_over_count(&mut self, roc: u64) {
+        self.highest = roc << 16 | (self.highest & 0xFFFF);
+    }
+}
+
+/// SeqnumWindow records which of the most recent extended sequence numbers were received, to
+/// find lost packets or to detect replayed ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeqnumWindow {
+    bits: Vec<u64>,
+    highest: Option<u64>,
+}
+
+impl SeqnumWindow {
+    /// new creates a window of the size most recent sequence numbers, rounded up to a multiple
+    /// of 64.
+    pub fn new(size: usize) -> Self {
+        SeqnumWindow {
+            bits: vec![0; (size.max(1) + 63) / 64],
+            highest: None,
+        }
+    }
+
+    /// size returns how many sequence numbers the window holds.
+    pub fn size(&self) -> u64 {
+        self.bits.len() as u64 * 64
+    }
+
+    /// highest returns the highest sequence number inserted.
+    pub fn highest(&self) -> Option<u64> {
+        self.highest
+    }
+
+    /// is_too_old returns true if seq fell out of the window.
+    pub fn is_too_old(&self, seq: u64) -> bool {
+        self.highest
+            .map_or(false, |highest| seq.saturating_add(self.size()) <= highest)
+    }
+
+    /// contains returns true if seq was inserted and is still inside the window.
+    pub fn contains(&self, seq: u64) -> bool {
+        match self.highest {
+            Some(highest) if seq <= highest && !self.is_too_old(seq) => self.bit(seq),
+            _ => false,
+        }
+    }
+
+    /// insert records seq as received. A sequence number higher than the highest one moves the
+    /// window forward, and the ones skipped are not received. Returns false if seq was already
+    /// inserted or is too old.
+    pub fn insert(&mut self, seq: u64) -> bool {
+        match self.highest {
+            Some(highest) if seq <= highest => {
+                if self.is_too_old(seq) || self.bit(seq) {
+                    return false;
+                }
+            }
+            Some(highest) => {
+                if seq - highest >= self.size() {
+                    self.bits.iter_mut().for_each(|b| *b = 0);
+                } else {
+                    for skipped in highest + 1..seq {
+                        self.clear_bit(skipped);
+                    }
+                }
+                self.highest = Some(seq);
+            }
+            None => self.highest = Some(seq),
+        }
+
+        self.set_bit(seq);
+        true
+    }
+
+    fn position(&self, seq: u64) -> (usize, u64) {
+        let pos = seq % self.size();
+        ((pos / 64) as usize, 1 << (pos % 64))
+    }
+
+    fn bit(&self, seq: u64) -> bool {
+        let (i, mask) = self.position(seq);
+        self.bits[i] & mask != 0
+    }
+
+    fn set_bit(&mut self, seq: u64) {
+        let (i, mask) = self.position(seq);
+        self.bits[i] |= mask;
+    }
+
+    fn clear_bit(&mut self, seq: u64) {
+        let (i, mask) = self.position(seq);
+        self.bits[i] &= !mask;
+    }
+}
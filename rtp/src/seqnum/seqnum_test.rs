@@ -0,0 +1,158 @@
+use super::*;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+
+// The properties are checked against a model of the sequence numbers as unbounded integers,
+// of which the 16 bit sequence numbers are the residues.
+
+const SEEDS: u64 = 16;
+
+fn residue(n: i128) -> u16 {
+    n.rem_euclid(1 << 16) as u16
+}
+
+#[test]
+fn test_diff_and_is_newer() {
+    for seed in 0..SEEDS {
+        let mut rng = StdRng::seed_from_u64(seed);
+        for _ in 0..10_000 {
+            let b: i128 = rng.gen_range(0..1 << 40);
+            let a = b + rng.gen_range(-(1 << 15) + 1..1 << 15);
+            let (sa, sb) = (residue(a), residue(b));
+
+            assert_eq!(diff(sa, sb), (a - b) as i32, "diff({}, {})", sa, sb);
+            assert_eq!(is_newer(sa, sb), a > b, "is_newer({}, {})", sa, sb);
+            assert_eq!(is_newer(sb, sa), b > a, "is_newer({}, {})", sb, sa);
+        }
+    }
+
+    // Half of the sequence number space apart, the larger one is newer.
+    for a in (0..=u16::MAX).step_by(251) {
+        let b = a.wrapping_add(1 << 15);
+        assert_ne!(is_newer(a, b), is_newer(b, a));
+        assert_eq!(is_newer(a, b), a > b);
+        assert_eq!(diff(a, b), -diff(b, a));
+        assert_eq!(diff(a, b).abs(), 1 << 15);
+    }
+
+    for a in [0, 1, 0x7FFF, 0x8000, 0xFFFE, 0xFFFF] {
+        assert_eq!(diff(a, a), 0);
+        assert!(!is_newer(a, a));
+        assert!(is_newer(a.wrapping_add(1), a));
+        assert!(!is_newer(a, a.wrapping_add(1)));
+    }
+}
+
+#[test]
+fn test_extended_seqnum() {
+    for seed in 0..SEEDS {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let roc: u64 = rng.gen_range(0..1 << 20);
+        let first = (roc << 16) as i128 + rng.gen_range(0..1 << 16);
+
+        let mut e = ExtendedSeqnum::new();
+        e.set_roll_over_count(roc);
+        assert_eq!(e.highest(), None);
+        assert_eq!(e.update(residue(first)), Some(first as u64));
+
+        let mut highest = first;
+        for _ in 0..20_000 {
+            // Mostly in order with some loss, sometimes reordered by up to a few thousand.
+            let step = if rng.gen_bool(0.9) {
+                rng.gen_range(1..1000)
+            } else {
+                rng.gen_range(-3000..=0)
+            };
+            let n = (highest + step).max(0);
+
+            let before = e;
+            assert_eq!(
+                e.extend(residue(n)),
+                Some(n as u64),
+                "extend({})",
+                residue(n)
+            );
+            assert_eq!(e, before, "extend must not record");
+
+            assert_eq!(
+                e.update(residue(n)),
+                Some(n as u64),
+                "update({})",
+                residue(n)
+            );
+            highest = highest.max(n);
+            assert_eq!(e.highest(), Some(highest as u64));
+            assert_eq!(e.roll_over_count(), (highest >> 16) as u64);
+        }
+        assert!(highest - first > 10 << 16, "must wrap several times");
+    }
+}
+
+#[test]
+fn test_extended_seqnum_bounds() {
+    // Older than the first sequence number across the wraparound, below extended 0.
+    let mut e = ExtendedSeqnum::new();
+    assert_eq!(e.update(5), Some(5));
+    assert_eq!(e.extend(0xFFFF), None);
+    assert_eq!(e.update(0xFFFF), None);
+    assert_eq!(e.highest(), Some(5));
+    assert_eq!(e.update(0), Some(0));
+    assert_eq!(e.update(0x8004), Some(0x8004));
+
+    // The rollover counter set applies to the first sequence number.
+    let mut e = ExtendedSeqnum::new();
+    e.set_roll_over_count(3);
+    assert_eq!(e.extend(0xFFF0), Some(3 << 16 | 0xFFF0));
+    assert_eq!(e.update(0xFFF0), Some(3 << 16 | 0xFFF0));
+    assert_eq!(e.update(2), Some(4 << 16 | 2));
+    assert_eq!(e.update(0xFFFF), Some(3 << 16 | 0xFFFF));
+    assert_eq!(e.roll_over_count(), 4);
+
+    e.set_roll_over_count(7);
+    assert_eq!(e.highest(), Some(7 << 16 | 2));
+    assert_eq!(e.update(3), Some(7 << 16 | 3));
+}
+
+#[test]
+fn test_seqnum_window() {
+    for seed in 0..SEEDS {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let size = [1, 64, 100, 128, 1024][seed as usize % 5];
+        let mut w = SeqnumWindow::new(size);
+        let window = w.size() as i128;
+        assert!(window >= size as i128 && window % 64 == 0);
+
+        let mut received = HashSet::new();
+        let mut highest: Option<i128> = None;
+        let mut next: i128 = rng.gen_range(0..1 << 40);
+        for _ in 0..2_000 {
+            let n = if rng.gen_bool(0.2) {
+                // A duplicate or a late packet, possibly out of the window.
+                next - rng.gen_range(0..2 * window + 2)
+            } else {
+                next += rng.gen_range(1..=window / 4 + 3);
+                if rng.gen_bool(0.02) {
+                    next += rng.gen_range(0..2 * window);
+                }
+                next
+            };
+            let n = n.max(0);
+
+            let too_old = highest.map_or(false, |h| n + window <= h);
+            assert_eq!(w.is_too_old(n as u64), too_old, "is_too_old({})", n);
+            let want_inserted = !too_old && !received.contains(&n);
+            assert_eq!(w.insert(n as u64), want_inserted, "insert({})", n);
+            received.insert(n);
+            let h = highest.map_or(n, |h| h.max(n));
+            highest = Some(h);
+            assert_eq!(w.highest(), Some(h as u64));
+
+            for m in (h - window - 2).max(0)..=h + 2 {
+                let want = m <= h && m > h - window && received.contains(&m);
+                assert_eq!(w.contains(m as u64), want, "contains({}), highest {}", m, h);
+            }
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use super::*;
+
+#[test]
+fn test_bit_reader() -> Result<()> {
+    let raw = [0b1010_0110, 0xFF, 0x01];
+    let mut r = BitReader::new(&raw);
+
+    assert!(r.read_bool()?);
+    assert!(!r.read_bool()?);
+    assert_eq!(r.read_bits(3)?, 0b100);
+    assert_eq!(r.position(), 5);
+    assert_eq!(r.byte_position(), 1);
+    assert_eq!(r.read_bits(0)?, 0);
+    assert_eq!(r.read_bits(11)?, 0b110_1111_1111);
+    assert_eq!(r.remaining(), 8);
+    assert_eq!(r.read_u8()?, 0x01);
+    assert_eq!(r.remaining(), 0);
+    assert_eq!(r.byte_position(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_bit_reader_truncated() -> Result<()> {
+    let raw = [0xAB, 0xCD];
+    let mut r = BitReader::new(&raw);
+
+    r.skip_bits(4)?;
+    assert_eq!(r.read_u16(), Err(Error::ErrShortPacket));
+    // a failed read doesn't consume anything
+    assert_eq!(r.position(), 4);
+    assert_eq!(r.read_bits(12)?, 0xBCD);
+    assert_eq!(r.read_bool(), Err(Error::ErrShortPacket));
+    assert_eq!(r.skip_bits(1), Err(Error::ErrShortPacket));
+    assert_eq!(r.read_bits(33), Err(Error::ErrBitFieldOverflow));
+
+    let mut r = BitReader::new(&[]);
+    assert_eq!(r.read_u8(), Err(Error::ErrShortPacket));
+    assert_eq!(r.remaining(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_bit_writer() -> Result<()> {
+    let mut w = BitWriter::new();
+
+    w.write_bool(true);
+    w.write_bits(0b0100, 4)?;
+    assert_eq!(w.position(), 5);
+    assert_eq!(w.bytes(), &[0b1010_0000]);
+    w.write_u16(0xFFFF);
+    w.write_u8(0x01);
+    assert_eq!(w.bytes(), &[0b1010_0111, 0xFF, 0b1111_1000, 0b0000_1000]);
+
+    assert_eq!(w.write_bits(4, 2), Err(Error::ErrBitFieldOverflow));
+    assert_eq!(w.write_bits(0, 33), Err(Error::ErrBitFieldOverflow));
+    assert_eq!(w.write_ns(3, 3), Err(Error::ErrBitFieldOverflow));
+    assert_eq!(w.position(), 29);
+
+    w.write_bits(u32::MAX, 32)?;
+    assert_eq!(w.position(), 61);
+
+    Ok(())
+}
+
+#[test]
+fn test_bits_round_trip_ns() -> Result<()> {
+    for n in 1..=64 {
+        let mut w = BitWriter::new();
+        for v in 0..n {
+            w.write_ns(v, n)?;
+        }
+        let raw = w.into_bytes();
+
+        let mut r = BitReader::new(&raw);
+        for v in 0..n {
+            assert_eq!(r.read_ns(n)?, v);
+        }
+    }
+
+    Ok(())
+}
+
+// LegacyBitReader is the reader the dependency descriptor parser used before BitReader, kept to
+// check that both agree.
+struct LegacyBitReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> LegacyBitReader<'a> {
+    fn read_bits(&mut self, n: usize) -> Result<u32> {
+        if self.pos + n > self.buf.len() * 8 {
+            return Err(Error::ErrBufferTooSmall);
+        }
+        let mut v = 0u32;
+        for _ in 0..n {
+            let bit = (self.buf[self.pos / 8] >> (7 - self.pos % 8)) & 1;
+            v = (v << 1) | bit as u32;
+            self.pos += 1;
+        }
+        Ok(v)
+    }
+
+    fn read_ns(&mut self, n: u32) -> Result<u32> {
+        let w = 32 - n.leading_zeros() as usize;
+        let m = (1u32 << w) - n;
+        let v = self.read_bits(w - 1)?;
+        if v < m {
+            return Ok(v);
+        }
+        let extra_bit = self.read_bits(1)?;
+        Ok((v << 1) - m + extra_bit)
+    }
+}
+
+#[test]
+fn test_bit_reader_matches_legacy_reader() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0x6269_7473);
+    for _ in 0..1_000 {
+        let raw: Vec<u8> = (0..rng.gen_range(0..16)).map(|_| rng.gen()).collect();
+        let mut r = BitReader::new(&raw);
+        let mut legacy = LegacyBitReader { buf: &raw, pos: 0 };
+
+        loop {
+            let (result, legacy_result) = if rng.gen_bool(0.8) {
+                let n = rng.gen_range(0..=32);
+                (r.read_bits(n), legacy.read_bits(n))
+            } else {
+                let n = rng.gen_range(1..=33);
+                (r.read_ns(n), legacy.read_ns(n))
+            };
+            match (result, legacy_result) {
+                (Ok(v), Ok(legacy_v)) => {
+                    assert_eq!(v, legacy_v, "{:02x?}", raw);
+                    assert_eq!(r.position(), legacy.pos, "{:02x?}", raw);
+                }
+                (Err(err), Err(_)) => {
+                    assert_eq!(err, Error::ErrShortPacket);
+                    break;
+                }
+                (result, legacy_result) => {
+                    panic!("{:02x?}: {:?} != {:?}", raw, result, legacy_result)
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+#[cfg(test)]
+mod bits_test;
+
+use crate::error::{Error, Result};
+
+/// BitReader reads MSB first bit fields from a byte slice. Reading past the end of the slice
+/// fails with `Error::ErrShortPacket` and leaves the reader where it was.
+#[derive(Debug, Clone)]
+pub struct BitReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        BitReader { buf, pos: 0 }
+    }
+
+    /// position returns the number of bits read so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// byte_position returns the number of bytes touched so far, a partially read byte counts
+    /// as read.
+    pub fn byte_position(&self) -> usize {
+        (self.pos + 7) / 8
+    }
+
+    /// remaining returns the number of bits left to read.
+    pub fn remaining(&self) -> usize {
+        self.buf.len() * 8 - self.pos
+    }
+
+    /// read_bits reads `n` bits, at most 32, as an unsigned value.
+    pub fn read_bits(&mut self, n: usize) -> Result<u32> {
+        if n > 32 {
+            return Err(Error::ErrBitFieldOverflow);
+        }
+        if n > self.remaining() {
+            return Err(Error::ErrShortPacket);
+        }
+        let mut v = 0u32;
+        for _ in 0..n {
+            let byte = self.buf.get(self.pos / 8).ok_or(Error::ErrShortPacket)?;
+            v = (v << 1) | ((byte >> (7 - self.pos % 8)) & 1) as u32;
+            self.pos += 1;
+        }
+        Ok(v)
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool> {
+        Ok(self.read_bits(1)? == 1)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bits(8)? as u8)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16> {
+        Ok(self.read_bits(16)? as u16)
+    }
+
+    /// read_ns reads a non-symmetric unsigned value in the range [0, n), as defined by the AV1
+    /// bitstream specification.
+    pub fn read_ns(&mut self, n: u32) -> Result<u32> {
+        if n == 0 {
+            return Err(Error::ErrBitFieldOverflow);
+        }
+        let w = 32 - n.leading_zeros() as usize;
+        let m = ((1u64 << w) - n as u64) as u32;
+        let start = self.pos;
+        let v = self.read_bits(w - 1)?;
+        if v < m {
+            return Ok(v);
+        }
+        let extra_bit = match self.read_bits(1) {
+            Ok(extra_bit) => extra_bit,
+            Err(err) => {
+                self.pos = start;
+                return Err(err);
+            }
+        };
+        Ok((v << 1) - m + extra_bit)
+    }
+
+    /// skip_bits advances the reader by `n` bits.
+    pub fn skip_bits(&mut self, n: usize) -> Result<()> {
+        if n > self.remaining() {
+            return Err(Error::ErrShortPacket);
+        }
+        self.pos += n;
+        Ok(())
+    }
+}
+
+/// BitWriter writes MSB first bit fields, growing its buffer as needed. The last byte is zero
+/// padded.
+#[derive(Debug, Default, Clone)]
+pub struct BitWriter {
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        BitWriter::default()
+    }
+
+    /// position returns the number of bits written so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+
+    /// write_bits writes the `n` low bits of `v`, at most 32. Values that don't fit in `n` bits
+    /// fail with `Error::ErrBitFieldOverflow`.
+    pub fn write_bits(&mut self, v: u32, n: usize) -> Result<()> {
+        if n > 32 || (n < 32 && v >> n != 0) {
+            return Err(Error::ErrBitFieldOverflow);
+        }
+        for i in (0..n).rev() {
+            if self.pos % 8 == 0 {
+                self.buf.push(0);
+            }
+            if let Some(last) = self.buf.last_mut() {
+                *last |= (((v >> i) & 1) as u8) << (7 - self.pos % 8);
+            }
+            self.pos += 1;
+        }
+        Ok(())
+    }
+
+    pub fn write_bool(&mut self, v: bool) {
+        // a single bit always fits
+        let _ = self.write_bits(v as u32, 1);
+    }
+
+    pub fn write_u8(&mut self, v: u8) {
+        let _ = self.write_bits(v as u32, 8);
+    }
+
+    pub fn write_u16(&mut self, v: u16) {
+        let _ = self.write_bits(v as u32, 16);
+    }
+
+    /// write_ns writes `v` as a non-symmetric unsigned value in the range [0, n).
+    pub fn write_ns(&mut self, v: u32, n: u32) -> Result<()> {
+        if v >= n {
+            return Err(Error::ErrBitFieldOverflow);
+        }
+        let w = 32 - n.leading_zeros() as usize;
+        let m = ((1u64 << w) - n as u64) as u32;
+        if v < m {
+            self.write_bits(v, w - 1)
+        } else {
+            self.write_bits((v + m) >> 1, w - 1)?;
+            self.write_bits((v + m) & 1, 1)
+        }
+    }
+}
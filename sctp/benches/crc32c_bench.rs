@@ -0,0 +1,27 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::Rng;
+use webrtc_sctp::crc32c;
+
+fn benchmark_crc32c(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+
+    let mut group = c.benchmark_group("crc32c");
+    for size in [64usize, 256, 1200, 4096, 65536] {
+        let mut data = vec![0u8; size];
+        rng.fill(&mut data[..]);
+
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("checksum", size), &data, |b, data| {
+            b.iter(|| crc32c::checksum(&[data]))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("checksum_table", size),
+            &data,
+            |b, data| b.iter(|| crc32c::checksum_table(&[data])),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_crc32c);
+criterion_main!(benches);
@@ -0,0 +1,100 @@
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+use tokio::net::UdpSocket;
+use tokio::runtime::Runtime;
+use webrtc_sctp::association::{Association, Config};
+use webrtc_sctp::chunk::chunk_payload_data::PayloadProtocolIdentifier;
+use webrtc_sctp::stream::{Stream, StreamIo};
+
+const MESSAGE: &[u8] = b"small data channel message";
+
+async fn create_stream_pair() -> (Association, Association, Arc<Stream>, Arc<Stream>) {
+    let server_conn = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+    let client_conn = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+    server_conn
+        .connect(client_conn.local_addr().unwrap())
+        .await
+        .unwrap();
+    client_conn
+        .connect(server_conn.local_addr().unwrap())
+        .await
+        .unwrap();
+
+    let server = tokio::spawn(Association::server(Config {
+        net_conn: server_conn,
+        max_receive_buffer_size: 0,
+        max_message_size: 0,
+        name: "server".to_owned(),
+        max_association_buffered_amount: 0,
+        buffer_full_policy: Default::default(),
+        max_retransmissions: 0,
+        num_outbound_streams: 0,
+        num_inbound_streams: 0,
+        clock: None,
+    }));
+    let client = Association::client(Config {
+        net_conn: client_conn,
+        max_receive_buffer_size: 0,
+        max_message_size: 0,
+        name: "client".to_owned(),
+        max_association_buffered_amount: 0,
+        buffer_full_policy: Default::default(),
+        max_retransmissions: 0,
+        num_outbound_streams: 0,
+        num_inbound_streams: 0,
+        clock: None,
+    })
+    .await
+    .unwrap();
+    let server = server.await.unwrap().unwrap();
+
+    let writer = client
+        .open_stream(0, PayloadProtocolIdentifier::Binary)
+        .await
+        .unwrap();
+    // The peer only learns about the stream with its first message.
+    writer.write(&Bytes::from_static(MESSAGE)).await.unwrap();
+    let reader = server.accept_stream().await.unwrap();
+    let mut buf = vec![0; 1024];
+    reader.read(&mut buf).await.unwrap();
+
+    (client, server, writer, reader)
+}
+
+fn benchmark_stream(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let (client, server, writer, reader) = rt.block_on(create_stream_pair());
+    let message = Bytes::from_static(MESSAGE);
+
+    c.bench_function("Stream::read", |b| {
+        let mut buf = vec![0; 1024];
+        b.iter(|| {
+            rt.block_on(async {
+                writer.write(&message).await.unwrap();
+                reader.read(&mut buf).await.unwrap();
+            })
+        })
+    });
+
+    c.bench_function("StreamIo::poll_read", |b| {
+        let mut stream_io = StreamIo::new(reader.clone());
+        let mut buf = vec![0; 1024];
+        b.iter(|| {
+            rt.block_on(async {
+                writer.write(&message).await.unwrap();
+                let n = stream_io.read(&mut buf).await.unwrap();
+                assert_eq!(n, MESSAGE.len());
+            })
+        })
+    });
+
+    rt.block_on(async {
+        client.close().await.unwrap();
+        server.close().await.unwrap();
+    });
+}
+
+criterion_group!(benches, benchmark_stream);
+criterion_main!(benches);
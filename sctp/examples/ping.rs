@@ -67,6 +67,12 @@ async fn main() -> Result<(), Error> {
         max_receive_buffer_size: 0,
         max_message_size: 0,
         name: "client".to_owned(),
+        max_association_buffered_amount: 0,
+        buffer_full_policy: Default::default(),
+        max_retransmissions: 0,
+        num_outbound_streams: 0,
+        num_inbound_streams: 0,
+        clock: None,
     };
     let a = Association::client(config).await?;
     println!("created a client");
@@ -83,7 +89,7 @@ async fn main() -> Result<(), Error> {
         while ping_seq_num < 10 {
             let ping_msg = format!("ping {}", ping_seq_num);
             println!("sent: {}", ping_msg);
-            stream_tx.write(&Bytes::from(ping_msg))?;
+            stream_tx.write(&Bytes::from(ping_msg)).await?;
 
             ping_seq_num += 1;
         }
@@ -66,6 +66,12 @@ async fn main() -> Result<(), Error> {
         max_receive_buffer_size: 0,
         max_message_size: 0,
         name: "server".to_owned(),
+        max_association_buffered_amount: 0,
+        buffer_full_policy: Default::default(),
+        max_retransmissions: 0,
+        num_outbound_streams: 0,
+        num_inbound_streams: 0,
+        clock: None,
     };
     let a = Association::server(config).await?;
     println!("created a server");
@@ -86,7 +92,7 @@ async fn main() -> Result<(), Error> {
 
             let pong_msg = format!("pong [{}]", ping_msg);
             println!("sent: {}", pong_msg);
-            stream2.write(&Bytes::from(pong_msg))?;
+            stream2.write(&Bytes::from(pong_msg)).await?;
 
             tokio::time::sleep(Duration::from_secs(1)).await;
         }
@@ -0,0 +1,132 @@
+use crate::error::{Error, Result};
+use crate::stream::{wake_all, OnBufferedAmountLowFn};
+
+use arc_swap::ArcSwapOption;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::Waker;
+use tokio::sync::Mutex;
+use util::sync::Mutex as SyncMutex;
+
+/// BufferFullPolicy selects what happens to a write that would exceed
+/// `Config::max_association_buffered_amount`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BufferFullPolicy {
+    /// The write fails with `Error::ErrAssociationBufferFull`.
+    Reject,
+    /// The write waits until enough buffered data has been acknowledged, or until the
+    /// stream is shut down for writing.
+    Block,
+}
+
+impl Default for BufferFullPolicy {
+    fn default() -> Self {
+        BufferFullPolicy::Reject
+    }
+}
+
+/// AssociationBuffer tracks the outgoing data buffered by all streams of an association,
+/// i.e. data in the pending queue plus data sent but not acknowledged yet.
+#[derive(Default)]
+pub(crate) struct AssociationBuffer {
+    /// 0 means no limit
+    max_buffered_amount: usize,
+    policy: BufferFullPolicy,
+    buffered_amount: AtomicUsize,
+    buffered_amount_low: AtomicUsize,
+    on_buffered_amount_low: ArcSwapOption<Mutex<OnBufferedAmountLowFn>>,
+    pub(crate) write_wakers: SyncMutex<Vec<Waker>>,
+}
+
+impl AssociationBuffer {
+    pub(crate) fn new(max_buffered_amount: usize, policy: BufferFullPolicy) -> Self {
+        AssociationBuffer {
+            max_buffered_amount,
+            policy,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn policy(&self) -> BufferFullPolicy {
+        self.policy
+    }
+
+    pub(crate) fn buffered_amount(&self) -> usize {
+        self.buffered_amount.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn buffered_amount_low_threshold(&self) -> usize {
+        self.buffered_amount_low.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn set_buffered_amount_low_threshold(&self, th: usize) {
+        self.buffered_amount_low.store(th, Ordering::SeqCst);
+    }
+
+    pub(crate) fn on_buffered_amount_low(&self, f: OnBufferedAmountLowFn) {
+        self.on_buffered_amount_low
+            .store(Some(Arc::new(Mutex::new(f))));
+    }
+
+    /// is_full returns true if buffering `n_bytes` more would exceed the limit. A write into
+    /// an empty buffer is always admitted so messages larger than the limit can still be sent.
+    pub(crate) fn is_full(&self, n_bytes: usize) -> bool {
+        let buffered_amount = self.buffered_amount();
+        self.max_buffered_amount != 0
+            && buffered_amount != 0
+            && buffered_amount + n_bytes > self.max_buffered_amount
+    }
+
+    /// reserve accounts for `n_bytes` of newly buffered data, failing if that would exceed
+    /// the limit.
+    pub(crate) fn reserve(&self, n_bytes: usize) -> Result<()> {
+        let result = self.buffered_amount.fetch_update(
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+            |buffered_amount| {
+                if self.max_buffered_amount != 0
+                    && buffered_amount != 0
+                    && buffered_amount + n_bytes > self.max_buffered_amount
+                {
+                    None
+                } else {
+                    Some(buffered_amount + n_bytes)
+                }
+            },
+        );
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(_) => Err(Error::ErrAssociationBufferFull),
+        }
+    }
+
+    /// release accounts for `n_bytes` of buffered data having been delivered or dropped, and
+    /// wakes up writers waiting for room. Returns true if the buffered amount dropped to or
+    /// below the low threshold, see [`AssociationBuffer::fire_on_buffered_amount_low`].
+    pub(crate) fn release(&self, n_bytes: usize) -> bool {
+        if n_bytes == 0 {
+            return false;
+        }
+
+        let from_amount = self
+            .buffered_amount
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |buffered_amount| {
+                Some(buffered_amount.saturating_sub(n_bytes))
+            })
+            .unwrap_or_default();
+        let new_amount = from_amount.saturating_sub(n_bytes);
+
+        wake_all(&self.write_wakers);
+
+        let buffered_amount_low = self.buffered_amount_low_threshold();
+        from_amount > buffered_amount_low && new_amount <= buffered_amount_low
+    }
+
+    pub(crate) async fn fire_on_buffered_amount_low(&self) {
+        if let Some(handler) = &*self.on_buffered_amount_low.load() {
+            let mut f = handler.lock().await;
+            f().await;
+        }
+    }
+}
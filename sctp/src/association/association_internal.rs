@@ -8,6 +8,14 @@ use crate::param::param_unrecognized::ParamUnrecognized;
 use async_trait::async_trait;
 use std::sync::atomic::AtomicBool;
 
+/// An add streams request sent to the peer.
+pub(crate) struct AddStreamsRequest {
+    incoming: bool,
+    count: u16,
+    /// Resolved with the response of the peer, for the Add Outgoing Streams request
+    done: Option<oneshot::Sender<Result<()>>>,
+}
+
 #[derive(Default)]
 pub struct AssociationInternal {
     pub(crate) name: String,
@@ -17,11 +25,11 @@ pub struct AssociationInternal {
     pub(crate) will_send_shutdown: Arc<AtomicBool>,
     awake_write_loop_ch: Option<Arc<mpsc::Sender<()>>>,
 
-    peer_verification_tag: u32,
+    pub(crate) peer_verification_tag: u32,
     pub(crate) my_verification_tag: u32,
 
     pub(crate) my_next_tsn: u32, // nextTSN
-    peer_last_tsn: u32,          // lastRcvdTSN
+    peer_last_tsn: u64,          // lastRcvdTSN, extended to 64 bits not to wrap around
     min_tsn2measure_rtt: u32,    // for RTT measurement
     will_send_forward_tsn: bool,
     will_retransmit_fast: bool,
@@ -34,16 +42,23 @@ pub struct AssociationInternal {
     my_next_rsn: u32,
     reconfigs: HashMap<u32, ChunkReconfig>,
     reconfig_requests: HashMap<u32, ParamOutgoingResetRequest>,
+    /// The rsn expected for the next reconfiguration request of the peer, older ones are duplicates
+    peer_next_rsn: Option<u32>,
+    /// Our add streams requests waiting for the response of the peer, by rsn
+    add_streams_requests: HashMap<u32, AddStreamsRequest>,
+    /// Results of the last add streams requests of the peer, by rsn
+    peer_add_streams_results: HashMap<u32, ReconfigResult>,
 
     // Non-RFC internal data
     source_port: u16,
     destination_port: u16,
-    pub(crate) my_max_num_inbound_streams: u16,
-    pub(crate) my_max_num_outbound_streams: u16,
+    pub(crate) my_max_num_inbound_streams: Arc<AtomicU16>,
+    pub(crate) my_max_num_outbound_streams: Arc<AtomicU16>,
     my_cookie: Option<ParamStateCookie>,
     payload_queue: PayloadQueue,
     inflight_queue: PayloadQueue,
     pending_queue: Arc<PendingQueue>,
+    pub(crate) association_buffer: Arc<AssociationBuffer>,
     control_queue: ControlQueue,
     pub(crate) mtu: u32,
     max_payload_size: u32, // max DATA chunk payload size
@@ -59,6 +74,9 @@ pub struct AssociationInternal {
     partial_bytes_acked: u32,
     pub(crate) in_fast_recovery: bool,
     fast_recover_exit_point: u32,
+    // SACKs since the Cumulative TSN Ack Point last advanced that reported new gaps, each
+    // allowing to send an MTU beyond cwnd, so small windows get the SACKs a fast retransmit needs
+    limited_transmit_sacks: u32,
 
     // RTX & Ack timer
     pub(crate) rto_mgr: RtoManager,
@@ -82,15 +100,24 @@ pub struct AssociationInternal {
     // local error
     silent_error: Option<Error>,
 
+    pub(crate) close_reason: Option<CloseReason>,
+
     // per inbound packet context
     delayed_ack_triggered: bool,
     immediate_ack_triggered: bool,
 
     pub(crate) stats: Arc<AssociationStats>,
+    pub(crate) clock: SharedClock,
     ack_state: AckState,
     pub(crate) ack_mode: AckMode, // for testing
 }
 
+/// initial_peer_last_tsn returns the extended TSN before the initial TSN of the peer. The
+/// extended TSNs start one wraparound in, so that there is one before an initial TSN of 0.
+fn initial_peer_last_tsn(initial_tsn: u32) -> u64 {
+    ((1 << 32) | initial_tsn as u64) - 1
+}
+
 impl AssociationInternal {
     pub(crate) fn new(
         config: Config,
@@ -122,12 +149,28 @@ impl AssociationInternal {
             max_receive_buffer_size,
             max_message_size: Arc::new(AtomicU32::new(max_message_size)),
 
-            my_max_num_outbound_streams: u16::MAX,
-            my_max_num_inbound_streams: u16::MAX,
+            my_max_num_outbound_streams: Arc::new(AtomicU16::new(
+                if config.num_outbound_streams == 0 {
+                    u16::MAX
+                } else {
+                    config.num_outbound_streams
+                },
+            )),
+            my_max_num_inbound_streams: Arc::new(AtomicU16::new(
+                if config.num_inbound_streams == 0 {
+                    u16::MAX
+                } else {
+                    config.num_inbound_streams
+                },
+            )),
             payload_queue: PayloadQueue::new(Arc::new(AtomicUsize::new(0))),
             inflight_queue: PayloadQueue::new(Arc::clone(&inflight_queue_length)),
             inflight_queue_length,
             pending_queue: Arc::new(PendingQueue::new()),
+            association_buffer: Arc::new(AssociationBuffer::new(
+                config.max_association_buffered_amount,
+                config.buffer_full_policy,
+            )),
             control_queue: ControlQueue::new(),
             mtu: INITIAL_MTU,
             max_payload_size: INITIAL_MTU - (COMMON_HEADER_SIZE + DATA_CHUNK_HEADER_SIZE),
@@ -140,6 +183,9 @@ impl AssociationInternal {
             streams: HashMap::new(),
             reconfigs: HashMap::new(),
             reconfig_requests: HashMap::new(),
+            peer_next_rsn: None,
+            add_streams_requests: HashMap::new(),
+            peer_add_streams_results: HashMap::new(),
             accept_ch_tx: Some(accept_ch_tx),
             close_loop_ch_tx: Some(close_loop_ch_tx),
             handshake_completed_ch_tx: Some(handshake_completed_ch_tx),
@@ -147,6 +193,7 @@ impl AssociationInternal {
             advanced_peer_tsn_ack_point: tsn - 1,
             silent_error: Some(Error::ErrSilentlyDiscard),
             stats: Arc::new(AssociationStats::default()),
+            clock: config.clock.map(SharedClock).unwrap_or_default(),
             awake_write_loop_ch: Some(awake_write_loop_ch),
             ..Default::default()
         };
@@ -211,14 +258,36 @@ impl AssociationInternal {
         }
     }
 
+    /// set_close_reason records why the association is about to be closed, unless a reason was
+    /// recorded already.
+    pub(crate) fn set_close_reason(&mut self, reason: CloseReason) {
+        if self.close_reason.is_none() && self.get_state() != AssociationState::Closed {
+            self.close_reason = Some(reason);
+        }
+    }
+
     pub(crate) async fn close(&mut self) -> Result<()> {
         if self.get_state() != AssociationState::Closed {
+            self.set_close_reason(CloseReason::Local);
             self.set_state(AssociationState::Closed);
 
-            log::debug!("[{}] closing association..", self.name);
+            log::debug!(
+                "[{}] closing association: {}",
+                self.name,
+                self.close_reason.unwrap_or(CloseReason::Local)
+            );
 
             self.close_all_timers().await;
 
+            // fail a handshake that is still in progress, e.g. after an ABORT from the peer
+            self.handshake_completed_ch_tx.take();
+
+            // end accept_stream
+            self.accept_ch_tx.take();
+
+            // fail the add streams requests still waiting for the peer
+            self.add_streams_requests.clear();
+
             // awake read/write_loop to exit
             self.close_loop_ch_tx.take();
 
@@ -257,6 +326,36 @@ impl AssociationInternal {
                 self.name,
                 self.stats.get_num_fast_retrans()
             );
+            log::debug!(
+                "[{}] stats nFastRecoveries: {}",
+                self.name,
+                self.stats.get_num_fast_recoveries()
+            );
+            log::debug!(
+                "[{}] stats nTimeoutRetrans: {}",
+                self.name,
+                self.stats.get_num_timeout_retrans()
+            );
+            log::debug!(
+                "[{}] stats nDupTSNs (in) : {}",
+                self.name,
+                self.stats.get_num_dup_tsns()
+            );
+            log::debug!(
+                "[{}] stats nOutOfWindowDATAs (in) : {}",
+                self.name,
+                self.stats.get_num_out_of_window_datas()
+            );
+            log::debug!(
+                "[{}] stats nStaleSACKs (in) : {}",
+                self.name,
+                self.stats.get_num_stale_sacks()
+            );
+            log::debug!(
+                "[{}] stats bufferedAmount: {}",
+                self.name,
+                self.association_buffer.buffered_amount()
+            );
         }
 
         Ok(())
@@ -297,10 +396,23 @@ impl AssociationInternal {
         let s = self.streams.remove(&stream_identifier);
         if let Some(s) = s {
             // NOTE: shutdown is not used here because it resets the stream.
-            if !s.read_shutdown.swap(true, Ordering::SeqCst) {
-                s.read_notifier.notify_waiters();
+            s.shutdown_read_half();
+            s.shutdown_write_half();
+            s.release_association_buffer();
+        }
+    }
+
+    /// unregister_if_reset un-registers a stream once it was closed and both of its directions
+    /// were reset, letting its reader read what is left before EOF.
+    async fn unregister_if_reset(&mut self, stream_identifier: u16) {
+        let is_reset = self
+            .streams
+            .get(&stream_identifier)
+            .map_or(false, |s| s.is_reset());
+        if is_reset {
+            if let Some(s) = self.streams.remove(&stream_identifier) {
+                s.close_after_reset().await;
             }
-            s.write_shutdown.store(true, Ordering::SeqCst);
         }
     }
 
@@ -390,12 +502,12 @@ impl AssociationInternal {
 
             if !sis_to_reset.is_empty() {
                 let rsn = self.generate_next_rsn();
-                let tsn = self.my_next_tsn - 1;
+                let tsn = self.my_next_tsn.wrapping_sub(1);
                 log::debug!(
                     "[{}] sending RECONFIG: rsn={} tsn={} streams={:?}",
                     self.name,
                     rsn,
-                    self.my_next_tsn - 1,
+                    self.my_next_tsn.wrapping_sub(1),
                     sis_to_reset
                 );
 
@@ -443,9 +555,13 @@ impl AssociationInternal {
 
             let mut i = 0;
             loop {
-                let tsn = self.cumulative_tsn_ack_point + i + 1;
+                let tsn = self.cumulative_tsn_ack_point.wrapping_add(i + 1);
                 if let Some(c) = self.inflight_queue.get_mut(tsn) {
-                    if c.acked || c.abandoned() || c.nsent > 1 || c.miss_indicator < 3 {
+                    if c.acked
+                        || c.abandoned()
+                        || c.nsent > 1
+                        || c.miss_indicator < FAST_RETRANSMIT_THRESHOLD
+                    {
                         i += 1;
                         continue;
                     }
@@ -668,6 +784,18 @@ impl AssociationInternal {
         // responding, the endpoint MUST send the INIT ACK back to the same
         // address that the original INIT (sent by this endpoint) was sent.
 
+        // When both endpoints open the association at the same time, each one receives the INIT
+        // of the other while in COOKIE-WAIT or COOKIE-ECHOED. Both answer with an INIT ACK
+        // carrying their existing tag and cookie, so the two handshakes collapse into a single
+        // association once the cookies have been echoed.
+        if state == AssociationState::Established && i.initiate_tag == self.peer_verification_tag {
+            // A duplicate of the INIT this association was set up with, e.g. a retransmission
+            // from a simultaneous open that crossed our INIT ACK. The peer is past COOKIE-WAIT
+            // and would discard any INIT ACK, so there is nothing to answer.
+            log::debug!("[{}] discarding duplicate INIT", self.name);
+            return Ok(vec![]);
+        }
+
         if state != AssociationState::Closed
             && state != AssociationState::CookieWait
             && state != AssociationState::CookieEchoed
@@ -678,10 +806,7 @@ impl AssociationInternal {
         }
 
         // Should we be setting any of these permanently until we've ACKed further?
-        self.my_max_num_inbound_streams =
-            std::cmp::min(i.num_inbound_streams, self.my_max_num_inbound_streams);
-        self.my_max_num_outbound_streams =
-            std::cmp::min(i.num_outbound_streams, self.my_max_num_outbound_streams);
+        self.negotiate_num_streams(i);
         self.peer_verification_tag = i.initiate_tag;
         self.source_port = p.destination_port;
         self.destination_port = p.source_port;
@@ -690,11 +815,7 @@ impl AssociationInternal {
         // is set initially by taking the peer's initial TSN,
         // received in the INIT or INIT ACK chunk, and
         // subtracting one from it.
-        self.peer_last_tsn = if i.initial_tsn == 0 {
-            u32::MAX
-        } else {
-            i.initial_tsn - 1
-        };
+        self.peer_last_tsn = initial_peer_last_tsn(i.initial_tsn);
 
         for param in &i.params {
             if let Some(v) = param.as_any().downcast_ref::<ParamSupportedExtensions>() {
@@ -741,8 +862,8 @@ impl AssociationInternal {
         let mut init_ack = ChunkInit {
             is_ack: true,
             initial_tsn: self.my_next_tsn,
-            num_outbound_streams: self.my_max_num_outbound_streams,
-            num_inbound_streams: self.my_max_num_inbound_streams,
+            num_outbound_streams: self.my_max_num_outbound_streams.load(Ordering::SeqCst),
+            num_inbound_streams: self.my_max_num_inbound_streams.load(Ordering::SeqCst),
             initiate_tag: self.my_verification_tag,
             advertised_receiver_window_credit: self.max_receive_buffer_size,
             params: unrecognized_params_from_init,
@@ -776,16 +897,9 @@ impl AssociationInternal {
             return Ok(vec![]);
         }
 
-        self.my_max_num_inbound_streams =
-            std::cmp::min(i.num_inbound_streams, self.my_max_num_inbound_streams);
-        self.my_max_num_outbound_streams =
-            std::cmp::min(i.num_outbound_streams, self.my_max_num_outbound_streams);
+        self.negotiate_num_streams(i);
         self.peer_verification_tag = i.initiate_tag;
-        self.peer_last_tsn = if i.initial_tsn == 0 {
-            u32::MAX
-        } else {
-            i.initial_tsn - 1
-        };
+        self.peer_last_tsn = initial_peer_last_tsn(i.initial_tsn);
         if self.source_port != p.destination_port || self.destination_port != p.source_port {
             log::warn!("[{}] handle_init_ack: port mismatch", self.name);
             return Ok(vec![]);
@@ -955,20 +1069,39 @@ impl AssociationInternal {
         );
         self.stats.inc_datas();
 
-        let can_push = self.payload_queue.can_push(d, self.peer_last_tsn);
+        let tsn = unwrap_tsn(self.peer_last_tsn, d.tsn);
+        let can_push = if tsn <= self.peer_last_tsn || self.payload_queue.get(d.tsn).is_some() {
+            self.payload_queue.push_duplicate(d.tsn);
+            self.stats.inc_dup_tsns();
+            false
+        } else if tsn - self.peer_last_tsn > RECEIVE_TSN_WINDOW {
+            log::debug!(
+                "[{}] dropping DATA with tsn={} outside the receive window, peer_last_tsn={}",
+                self.name,
+                d.tsn,
+                self.peer_last_tsn as u32
+            );
+            self.stats.inc_out_of_window_datas();
+            false
+        } else {
+            true
+        };
+
         let mut stream_handle_data = false;
         if can_push {
             if let Some(_s) = self.get_or_create_stream(d.stream_identifier) {
                 if self.get_my_receiver_window_credit().await > 0 {
                     // Pass the new chunk to stream level as soon as it arrives
-                    self.payload_queue.push(d.clone(), self.peer_last_tsn);
+                    self.payload_queue
+                        .push(d.clone(), self.peer_last_tsn as u32);
                     stream_handle_data = true;
                 } else {
                     // Receive buffer is full
                     if let Some(last_tsn) = self.payload_queue.get_last_tsn_received() {
                         if sna32lt(d.tsn, *last_tsn) {
                             log::debug!("[{}] receive buffer full, but accepted as this is a missing chunk with tsn={} ssn={}", self.name, d.tsn, d.stream_sequence_number);
-                            self.payload_queue.push(d.clone(), self.peer_last_tsn);
+                            self.payload_queue
+                                .push(d.clone(), self.peer_last_tsn as u32);
                             stream_handle_data = true; //s.handle_data(d.clone());
                         }
                     } else {
@@ -997,10 +1130,11 @@ impl AssociationInternal {
         }
 
         self.handle_peer_last_tsn_and_acknowledgement(immediate_sack)
+            .await
     }
 
     /// A common routine for handle_data and handle_forward_tsn routines
-    fn handle_peer_last_tsn_and_acknowledgement(
+    async fn handle_peer_last_tsn_and_acknowledgement(
         &mut self,
         sack_immediately: bool,
     ) -> Result<Vec<Packet>> {
@@ -1013,15 +1147,40 @@ impl AssociationInternal {
         //   if possible
         // Meaning, if peer_last_tsn+1 points to a chunk that is received,
         // advance peer_last_tsn until peer_last_tsn+1 points to unreceived chunk.
-        log::debug!("[{}] peer_last_tsn = {}", self.name, self.peer_last_tsn);
-        while self.payload_queue.pop(self.peer_last_tsn + 1).is_some() {
+        log::debug!(
+            "[{}] peer_last_tsn = {}",
+            self.name,
+            self.peer_last_tsn as u32
+        );
+        while self
+            .payload_queue
+            .pop((self.peer_last_tsn + 1) as u32)
+            .is_some()
+        {
             self.peer_last_tsn += 1;
-            log::debug!("[{}] peer_last_tsn = {}", self.name, self.peer_last_tsn);
+            log::debug!(
+                "[{}] peer_last_tsn = {}",
+                self.name,
+                self.peer_last_tsn as u32
+            );
 
-            let rst_reqs: Vec<ParamOutgoingResetRequest> =
+            let mut rst_reqs: Vec<ParamOutgoingResetRequest> =
                 self.reconfig_requests.values().cloned().collect();
+            // Resets of the same stream have to be performed in the order they were requested
+            rst_reqs.sort_by(|a, b| {
+                if a.reconfig_request_sequence_number == b.reconfig_request_sequence_number {
+                    std::cmp::Ordering::Equal
+                } else if sna32lt(
+                    a.reconfig_request_sequence_number,
+                    b.reconfig_request_sequence_number,
+                ) {
+                    std::cmp::Ordering::Less
+                } else {
+                    std::cmp::Ordering::Greater
+                }
+            });
             for rst_req in rst_reqs {
-                let resp = self.reset_streams_if_any(&rst_req);
+                let resp = self.reset_streams_if_any(&rst_req).await;
                 log::debug!("[{}] RESET RESPONSE: {}", self.name, resp);
                 reply.push(resp);
             }
@@ -1033,7 +1192,7 @@ impl AssociationInternal {
                 "[{}] packetloss: {}",
                 self.name,
                 self.payload_queue
-                    .get_gap_ack_blocks_string(self.peer_last_tsn)
+                    .get_gap_ack_blocks_string(self.peer_last_tsn as u32)
             );
         }
 
@@ -1073,11 +1232,21 @@ impl AssociationInternal {
         stream_identifier: u16,
         default_payload_type: PayloadProtocolIdentifier,
     ) -> Result<Arc<Stream>> {
-        if self.streams.contains_key(&stream_identifier) {
-            return Err(Error::ErrStreamAlreadyExist);
+        if stream_identifier >= self.my_max_num_outbound_streams.load(Ordering::SeqCst) {
+            return Err(Error::ErrStreamIdentifierOutOfRange);
         }
 
+        // A closed stream whose reset is still in progress is taken over by the new one
+        let old = match self.streams.get(&stream_identifier) {
+            Some(s) if s.closing.load(Ordering::SeqCst) => self.streams.remove(&stream_identifier),
+            Some(_) => return Err(Error::ErrStreamAlreadyExist),
+            None => None,
+        };
+
         if let Some(s) = self.create_stream(stream_identifier, false) {
+            if let Some(old) = old {
+                s.take_over(&old);
+            }
             s.set_default_payload_type(default_payload_type);
             Ok(Arc::clone(&s))
         } else {
@@ -1090,11 +1259,14 @@ impl AssociationInternal {
         let s = Arc::new(Stream::new(
             format!("{}:{}", stream_identifier, self.name),
             stream_identifier,
-            self.max_payload_size,
-            Arc::clone(&self.max_message_size),
-            Arc::clone(&self.state),
-            self.awake_write_loop_ch.clone(),
-            Arc::clone(&self.pending_queue),
+            StreamConfig {
+                max_payload_size: self.max_payload_size,
+                max_message_size: Arc::clone(&self.max_message_size),
+                state: Arc::clone(&self.state),
+                awake_write_loop_ch: self.awake_write_loop_ch.clone(),
+                pending_queue: Arc::clone(&self.pending_queue),
+                association_buffer: Arc::clone(&self.association_buffer),
+            },
         ));
 
         if accept {
@@ -1123,10 +1295,19 @@ impl AssociationInternal {
 
     /// get_or_create_stream gets or creates a stream. The caller should hold the lock.
     fn get_or_create_stream(&mut self, stream_identifier: u16) -> Option<Arc<Stream>> {
-        if self.streams.contains_key(&stream_identifier) {
-            self.streams.get(&stream_identifier).cloned()
-        } else {
-            self.create_stream(stream_identifier, true)
+        match self.streams.get(&stream_identifier) {
+            // Once both sides reset a closed stream, data is for a new one reopened by the peer,
+            // even if the response to our reset request is still to come.
+            Some(s)
+                if s.closing.load(Ordering::SeqCst) && s.incoming_reset.load(Ordering::SeqCst) =>
+            {
+                let old = self.streams.remove(&stream_identifier)?;
+                let s = self.create_stream(stream_identifier, true)?;
+                s.take_over(&old);
+                Some(s)
+            }
+            Some(s) => Some(Arc::clone(s)),
+            None => self.create_stream(stream_identifier, true),
         }
     }
 
@@ -1139,7 +1320,7 @@ impl AssociationInternal {
         // New ack point, so pop all ACKed packets from inflight_queue
         // We add 1 because the "currentAckPoint" has already been popped from the inflight queue
         // For the first SACK we take care of this by setting the ackpoint to cumAck - 1
-        let mut i = self.cumulative_tsn_ack_point + 1;
+        let mut i = self.cumulative_tsn_ack_point.wrapping_add(1);
         //log::debug!("[{}] i={} d={}", self.name, i, d.cumulative_tsn_ack);
         while sna32lte(i, d.cumulative_tsn_ack) {
             if let Some(c) = self.inflight_queue.pop(i) {
@@ -1149,7 +1330,7 @@ impl AssociationInternal {
                     //        with the earliest outstanding TSN for that address, restart the
                     //        T3-rtx timer for that address with its current RTO (if there is
                     //        still outstanding data on that address).
-                    if i == self.cumulative_tsn_ack_point + 1 {
+                    if i == self.cumulative_tsn_ack_point.wrapping_add(1) {
                         // T3 timer needs to be reset. Stop it for now.
                         if let Some(t3rtx) = &self.t3rtx {
                             t3rtx.stop().await;
@@ -1176,10 +1357,7 @@ impl AssociationInternal {
                     //        chunk or for a later instance)
                     if c.nsent == 1 && sna32gte(c.tsn, self.min_tsn2measure_rtt) {
                         self.min_tsn2measure_rtt = self.my_next_tsn;
-                        let rtt = match SystemTime::now().duration_since(c.since) {
-                            Ok(rtt) => rtt,
-                            Err(_) => return Err(Error::ErrInvalidSystemTime),
-                        };
+                        let rtt = self.clock.now().duration_since(c.since);
                         let srtt = self.rto_mgr.set_new_rtt(rtt.as_millis() as u64);
                         log::trace!(
                             "[{}] SACK: measured-rtt={} srtt={} new-rto={}",
@@ -1199,7 +1377,7 @@ impl AssociationInternal {
                 return Err(Error::ErrInflightQueueTsnPop);
             }
 
-            i += 1;
+            i = i.wrapping_add(1);
         }
 
         let mut htna = d.cumulative_tsn_ack;
@@ -1207,7 +1385,7 @@ impl AssociationInternal {
         // Mark selectively acknowledged chunks as "acked"
         for g in &d.gap_ack_blocks {
             for i in g.start..=g.end {
-                let tsn = d.cumulative_tsn_ack + i as u32;
+                let tsn = d.cumulative_tsn_ack.wrapping_add(i as u32);
 
                 let (is_existed, is_acked) = if let Some(c) = self.inflight_queue.get(tsn) {
                     (true, c.acked)
@@ -1233,10 +1411,7 @@ impl AssociationInternal {
 
                         if c.nsent == 1 {
                             self.min_tsn2measure_rtt = self.my_next_tsn;
-                            let rtt = match SystemTime::now().duration_since(c.since) {
-                                Ok(rtt) => rtt,
-                                Err(_) => return Err(Error::ErrInvalidSystemTime),
-                            };
+                            let rtt = self.clock.now().duration_since(c.since);
                             let srtt = self.rto_mgr.set_new_rtt(rtt.as_millis() as u64);
                             log::trace!(
                                 "[{}] SACK: measured-rtt={} srtt={} new-rto={}",
@@ -1363,15 +1538,17 @@ impl AssociationInternal {
                 htna
             } else {
                 // b) increment for all TSNs reported missing
-                cum_tsn_ack_point + (self.inflight_queue.len() as u32) + 1
+                cum_tsn_ack_point.wrapping_add(self.inflight_queue.len() as u32 + 1)
             };
 
-            let mut tsn = cum_tsn_ack_point + 1;
+            let mut tsn = cum_tsn_ack_point.wrapping_add(1);
             while sna32lt(tsn, max_tsn) {
                 if let Some(c) = self.inflight_queue.get_mut(tsn) {
-                    if !c.acked && !c.abandoned() && c.miss_indicator < 3 {
+                    if !c.acked && !c.abandoned() && c.miss_indicator < FAST_RETRANSMIT_THRESHOLD {
                         c.miss_indicator += 1;
-                        if c.miss_indicator == 3 && !self.in_fast_recovery {
+                        if c.miss_indicator == FAST_RETRANSMIT_THRESHOLD && !self.in_fast_recovery {
+                            // Only the first loss of a window reduces cwnd, the others are
+                            // recovered in the same Fast Recovery.
                             // 2)  If not in Fast Recovery, adjust the ssthresh and cwnd of the
                             //     destination address(es) to which the missing DATA chunks were
                             //     last sent, according to the formula described in Section 7.2.3.
@@ -1381,6 +1558,7 @@ impl AssociationInternal {
                             self.cwnd = self.ssthresh;
                             self.partial_bytes_acked = 0;
                             self.will_retransmit_fast = true;
+                            self.stats.inc_fast_recoveries();
 
                             log::trace!(
                                 "[{}] updated cwnd={} ssthresh={} inflight={} (FR)",
@@ -1395,7 +1573,7 @@ impl AssociationInternal {
                     return Err(Error::ErrTsnRequestNotExist);
                 }
 
-                tsn += 1;
+                tsn = tsn.wrapping_add(1);
             }
         }
 
@@ -1406,6 +1584,31 @@ impl AssociationInternal {
         Ok(())
     }
 
+    /// update_limited_transmit allows to send new data beyond cwnd for the first SACKs that
+    /// report new gaps without advancing the Cumulative TSN Ack Point, as limited transmit
+    /// (RFC 3042) does for duplicate ACKs. Otherwise a window too small to get the SACKs a fast
+    /// retransmit needs would wait for the T3-rtx timer. cwnd itself is left unchanged.
+    fn update_limited_transmit(
+        &mut self,
+        cum_tsn_ack_point: u32,
+        htna: u32,
+        cum_tsn_ack_point_advanced: bool,
+    ) {
+        if cum_tsn_ack_point_advanced || self.in_fast_recovery {
+            self.limited_transmit_sacks = 0;
+        } else if sna32gt(htna, cum_tsn_ack_point)
+            && self.limited_transmit_sacks < LIMITED_TRANSMIT_MAX_SACKS
+        {
+            self.limited_transmit_sacks += 1;
+            log::trace!(
+                "[{}] limited transmit: {} extra MTU(s) beyond cwnd={}",
+                self.name,
+                self.limited_transmit_sacks,
+                self.cwnd
+            );
+        }
+    }
+
     async fn handle_sack(&mut self, d: &ChunkSelectiveAck) -> Result<Vec<Packet>> {
         log::trace!(
             "[{}] {}, SACK: cumTSN={} a_rwnd={}",
@@ -1439,6 +1642,7 @@ impl AssociationInternal {
                 d.cumulative_tsn_ack,
                 self.cumulative_tsn_ack_point
             );
+            self.stats.inc_stale_sacks();
 
             return Ok(vec![]);
         }
@@ -1488,6 +1692,7 @@ impl AssociationInternal {
         }
 
         self.process_fast_retransmission(d.cumulative_tsn_ack, htna, cum_tsn_ack_point_advanced)?;
+        self.update_limited_transmit(d.cumulative_tsn_ack, htna, cum_tsn_ack_point_advanced);
 
         if self.use_forward_tsn {
             // RFC 3758 Sec 3.5 C1
@@ -1499,13 +1704,13 @@ impl AssociationInternal {
             }
 
             // RFC 3758 Sec 3.5 C2
-            let mut i = self.advanced_peer_tsn_ack_point + 1;
+            let mut i = self.advanced_peer_tsn_ack_point.wrapping_add(1);
             while let Some(c) = self.inflight_queue.get(i) {
                 if !c.abandoned() {
                     break;
                 }
                 self.advanced_peer_tsn_ack_point = i;
-                i += 1;
+                i = i.wrapping_add(1);
             }
 
             // RFC 3758 Sec 3.5 C3
@@ -1565,6 +1770,8 @@ impl AssociationInternal {
         let state = self.get_state();
 
         if state == AssociationState::Established {
+            self.set_close_reason(CloseReason::ShutdownReceived);
+
             if !self.inflight_queue.is_empty() {
                 self.set_state(AssociationState::ShutdownReceived);
             } else {
@@ -1617,7 +1824,7 @@ impl AssociationInternal {
     fn create_forward_tsn(&self) -> ChunkForwardTsn {
         // RFC 3758 Sec 3.5 C4
         let mut stream_map: HashMap<u16, u16> = HashMap::new(); // to report only once per SI
-        let mut i = self.cumulative_tsn_ack_point + 1;
+        let mut i = self.cumulative_tsn_ack_point.wrapping_add(1);
         while sna32lte(i, self.advanced_peer_tsn_ack_point) {
             if let Some(c) = self.inflight_queue.get(i) {
                 if let Some(ssn) = stream_map.get(&c.stream_identifier) {
@@ -1632,7 +1839,7 @@ impl AssociationInternal {
                 break;
             }
 
-            i += 1;
+            i = i.wrapping_add(1);
         }
 
         let mut fwd_tsn = ChunkForwardTsn {
@@ -1721,9 +1928,10 @@ impl AssociationInternal {
             "[{}] should send ack? newCumTSN={} peer_last_tsn={}",
             self.name,
             c.new_cumulative_tsn,
-            self.peer_last_tsn
+            self.peer_last_tsn as u32
         );
-        if sna32lte(c.new_cumulative_tsn, self.peer_last_tsn) {
+        let new_cumulative_tsn = unwrap_tsn(self.peer_last_tsn, c.new_cumulative_tsn);
+        if new_cumulative_tsn <= self.peer_last_tsn {
             log::trace!("[{}] sending ack on Forward TSN", self.name);
             self.ack_state = AckState::Immediate;
             if let Some(ack_timer) = &mut self.ack_timer {
@@ -1744,10 +1952,8 @@ impl AssociationInternal {
         //   chunk,
 
         // Advance peer_last_tsn
-        while sna32lt(self.peer_last_tsn, c.new_cumulative_tsn) {
-            self.payload_queue.pop(self.peer_last_tsn + 1); // may not exist
-            self.peer_last_tsn += 1;
-        }
+        self.payload_queue.pop_until(c.new_cumulative_tsn);
+        self.peer_last_tsn = new_cumulative_tsn;
 
         // Report new peer_last_tsn value and abandoned largest SSN value to
         // corresponding streams so that the abandoned chunks can be removed
@@ -1768,7 +1974,178 @@ impl AssociationInternal {
                 .await;
         }
 
-        self.handle_peer_last_tsn_and_acknowledgement(false)
+        self.handle_peer_last_tsn_and_acknowledgement(false).await
+    }
+
+    /// negotiate_num_streams limits our outbound streams to the inbound streams of the peer and
+    /// the other way around (RFC 4960 sec 5.1.1).
+    fn negotiate_num_streams(&mut self, i: &ChunkInit) {
+        // Should we be setting any of these permanently until we've ACKed further?
+        let outbound = std::cmp::min(
+            i.num_inbound_streams,
+            self.my_max_num_outbound_streams.load(Ordering::SeqCst),
+        );
+        let inbound = std::cmp::min(
+            i.num_outbound_streams,
+            self.my_max_num_inbound_streams.load(Ordering::SeqCst),
+        );
+        self.my_max_num_outbound_streams
+            .store(outbound, Ordering::SeqCst);
+        self.my_max_num_inbound_streams
+            .store(inbound, Ordering::SeqCst);
+    }
+
+    /// add_outbound_streams sends an Add Outgoing Streams request for `count` streams, bundled
+    /// with an Add Incoming Streams request when the peer couldn't answer on all of them. The
+    /// returned receiver resolves once the peer answered the former.
+    pub(crate) async fn add_outbound_streams(
+        &mut self,
+        count: u16,
+    ) -> Result<oneshot::Receiver<Result<()>>> {
+        if self.get_state() != AssociationState::Established {
+            return Err(Error::ErrAddStreamsStateNotEstablished);
+        }
+
+        let (done_tx, done_rx) = oneshot::channel();
+        let outgoing_rsn = self.generate_next_rsn();
+        log::debug!(
+            "[{}] sending RECONFIG: rsn={} add {} streams",
+            self.name,
+            outgoing_rsn,
+            count
+        );
+        self.add_streams_requests.insert(
+            outgoing_rsn,
+            AddStreamsRequest {
+                incoming: false,
+                count,
+                done: Some(done_tx),
+            },
+        );
+
+        // Streams are used in both directions, e.g. by data channels, so the peer should be able
+        // to send on the new streams as well
+        let num_outbound =
+            u32::from(self.my_max_num_outbound_streams.load(Ordering::SeqCst)) + u32::from(count);
+        let num_inbound = u32::from(self.my_max_num_inbound_streams.load(Ordering::SeqCst));
+        let param_b: Option<Box<dyn Param + Send + Sync>> = if num_inbound < num_outbound {
+            let incoming_count = std::cmp::min(num_outbound - num_inbound, u32::from(u16::MAX));
+            let incoming_rsn = self.generate_next_rsn();
+            self.add_streams_requests.insert(
+                incoming_rsn,
+                AddStreamsRequest {
+                    incoming: true,
+                    count: incoming_count as u16,
+                    done: None,
+                },
+            );
+            Some(Box::new(ParamAddStreamsRequest {
+                incoming: true,
+                reconfig_request_sequence_number: incoming_rsn,
+                number_of_new_streams: incoming_count as u16,
+            }))
+        } else {
+            None
+        };
+
+        let c = ChunkReconfig {
+            param_a: Some(Box::new(ParamAddStreamsRequest {
+                incoming: false,
+                reconfig_request_sequence_number: outgoing_rsn,
+                number_of_new_streams: count,
+            })),
+            param_b,
+        };
+        self.reconfigs.insert(outgoing_rsn, c.clone()); // store in the map for retransmission
+        self.control_queue
+            .push_back(self.create_packet(vec![Box::new(c)]));
+        if let Some(treconfig) = &self.treconfig {
+            treconfig.start(self.rto_mgr.get_rto()).await;
+        }
+        self.awake_write_loop();
+
+        Ok(done_rx)
+    }
+
+    /// handle_add_streams_request adds the streams requested by the peer, as long as the
+    /// number of streams stays below 65536.
+    fn handle_add_streams_request(&mut self, p: &ParamAddStreamsRequest) -> Packet {
+        let rsn = p.reconfig_request_sequence_number;
+        if let Some(expected) = self.peer_next_rsn {
+            if sna32lt(rsn, expected) {
+                // A retransmission, answered with the result of the original request
+                let result = self
+                    .peer_add_streams_results
+                    .get(&rsn)
+                    .copied()
+                    .unwrap_or(ReconfigResult::ErrorBadSequenceNumber);
+                return self.create_reconfig_response(rsn, result);
+            }
+        }
+        self.peer_next_rsn = Some(rsn.wrapping_add(1));
+
+        // The peer adding incoming streams adds to our outbound ones
+        let streams = if p.incoming {
+            &self.my_max_num_outbound_streams
+        } else {
+            &self.my_max_num_inbound_streams
+        };
+        let result = match streams
+            .load(Ordering::SeqCst)
+            .checked_add(p.number_of_new_streams)
+        {
+            Some(num_streams) => {
+                streams.store(num_streams, Ordering::SeqCst);
+                log::debug!(
+                    "[{}] peer added {} streams, {} {} streams",
+                    self.name,
+                    p.number_of_new_streams,
+                    num_streams,
+                    if p.incoming { "outbound" } else { "inbound" }
+                );
+                ReconfigResult::SuccessPerformed
+            }
+            None => ReconfigResult::Denied,
+        };
+
+        self.peer_add_streams_results.insert(rsn, result);
+        self.peer_add_streams_results
+            .retain(|r, _| rsn.wrapping_sub(*r) < PEER_ADD_STREAMS_RESULTS_KEPT);
+
+        self.create_reconfig_response(rsn, result)
+    }
+
+    /// handle_add_streams_response applies the answer of the peer to one of our add streams
+    /// requests.
+    fn handle_add_streams_response(&mut self, req: AddStreamsRequest, result: ReconfigResult) {
+        let added = matches!(
+            result,
+            ReconfigResult::SuccessPerformed | ReconfigResult::SuccessNop
+        );
+        if added {
+            let streams = if req.incoming {
+                &self.my_max_num_inbound_streams
+            } else {
+                &self.my_max_num_outbound_streams
+            };
+            let num_streams = streams.load(Ordering::SeqCst).saturating_add(req.count);
+            streams.store(num_streams, Ordering::SeqCst);
+        } else {
+            log::warn!(
+                "[{}] peer refused to add {} streams: {}",
+                self.name,
+                req.count,
+                result
+            );
+        }
+
+        if let Some(done) = req.done {
+            let _ = done.send(if added {
+                Ok(())
+            } else {
+                Err(Error::ErrAddStreamsRefused)
+            });
+        }
     }
 
     fn send_reset_request(&mut self, stream_identifier: u16) -> Result<()> {
@@ -1799,11 +2176,52 @@ impl AssociationInternal {
         raw: &Box<dyn Param + Send + Sync>,
     ) -> Result<Option<Packet>> {
         if let Some(p) = raw.as_any().downcast_ref::<ParamOutgoingResetRequest>() {
-            self.reconfig_requests
-                .insert(p.reconfig_request_sequence_number, p.clone());
-            Ok(Some(self.reset_streams_if_any(p)))
+            let rsn = p.reconfig_request_sequence_number;
+            if let Some(expected) = self.peer_next_rsn {
+                if sna32lt(rsn, expected) {
+                    // A retransmission of a request already seen, which is answered without
+                    // performing it again (RFC 6525 Sec 5.2.1)
+                    let result = if self.reconfig_requests.contains_key(&rsn) {
+                        ReconfigResult::InProgress
+                    } else {
+                        ReconfigResult::SuccessPerformed
+                    };
+                    return Ok(Some(self.create_reconfig_response(rsn, result)));
+                }
+            }
+            self.peer_next_rsn = Some(rsn.wrapping_add(1));
+
+            self.reconfig_requests.insert(rsn, p.clone());
+            Ok(Some(self.reset_streams_if_any(p).await))
+        } else if let Some(p) = raw.as_any().downcast_ref::<ParamAddStreamsRequest>() {
+            Ok(Some(self.handle_add_streams_request(p)))
         } else if let Some(p) = raw.as_any().downcast_ref::<ParamReconfigResponse>() {
-            self.reconfigs.remove(&p.reconfig_response_sequence_number);
+            let rsn = p.reconfig_response_sequence_number;
+            match p.result {
+                // The request is retransmitted until the peer performed it
+                ReconfigResult::InProgress | ReconfigResult::ErrorBadSequenceNumber => {}
+                _ => {
+                    if let Some(c) = self.reconfigs.remove(&rsn) {
+                        let stream_identifiers = c
+                            .param_a
+                            .as_ref()
+                            .and_then(|param| {
+                                param.as_any().downcast_ref::<ParamOutgoingResetRequest>()
+                            })
+                            .map(|req| req.stream_identifiers.clone())
+                            .unwrap_or_default();
+                        for id in stream_identifiers {
+                            if let Some(s) = self.streams.get(&id) {
+                                s.handle_outgoing_reset_done();
+                            }
+                            self.unregister_if_reset(id).await;
+                        }
+                    }
+                    if let Some(req) = self.add_streams_requests.remove(&rsn) {
+                        self.handle_add_streams_response(req, p.result);
+                    }
+                }
+            }
             if self.reconfigs.is_empty() {
                 if let Some(treconfig) = &self.treconfig {
                     treconfig.stop().await;
@@ -1815,20 +2233,20 @@ impl AssociationInternal {
         }
     }
 
-    fn reset_streams_if_any(&mut self, p: &ParamOutgoingResetRequest) -> Packet {
+    async fn reset_streams_if_any(&mut self, p: &ParamOutgoingResetRequest) -> Packet {
         let mut result = ReconfigResult::SuccessPerformed;
-        if sna32lte(p.sender_last_tsn, self.peer_last_tsn) {
+        if unwrap_tsn(self.peer_last_tsn, p.sender_last_tsn) <= self.peer_last_tsn {
             log::debug!(
                 "[{}] resetStream(): senderLastTSN={} <= peer_last_tsn={}",
                 self.name,
                 p.sender_last_tsn,
-                self.peer_last_tsn
+                self.peer_last_tsn as u32
             );
             for id in &p.stream_identifiers {
                 if let Some(s) = self.streams.get(id) {
-                    let stream_identifier = s.stream_identifier;
-                    self.unregister_stream(stream_identifier);
+                    s.handle_incoming_reset().await;
                 }
+                self.unregister_if_reset(*id).await;
             }
             self.reconfig_requests
                 .remove(&p.reconfig_request_sequence_number);
@@ -1837,14 +2255,18 @@ impl AssociationInternal {
                 "[{}] resetStream(): senderLastTSN={} > peer_last_tsn={}",
                 self.name,
                 p.sender_last_tsn,
-                self.peer_last_tsn
+                self.peer_last_tsn as u32
             );
             result = ReconfigResult::InProgress;
         }
 
+        self.create_reconfig_response(p.reconfig_request_sequence_number, result)
+    }
+
+    fn create_reconfig_response(&self, rsn: u32, result: ReconfigResult) -> Packet {
         self.create_packet(vec![Box::new(ChunkReconfig {
             param_a: Some(Box::new(ParamReconfigResponse {
-                reconfig_response_sequence_number: p.reconfig_request_sequence_number,
+                reconfig_response_sequence_number: rsn,
                 result,
             })),
             param_b: None,
@@ -1866,7 +2288,7 @@ impl AssociationInternal {
             // Assign TSN
             c.tsn = self.generate_next_tsn();
 
-            c.since = SystemTime::now(); // use to calculate RTT and also for maxPacketLifeTime
+            c.since = self.clock.now(); // use to calculate RTT and also for maxPacketLifeTime
             c.nsent = 1; // being sent for the first time
 
             self.check_partial_reliability_status(&c);
@@ -1929,7 +2351,8 @@ impl AssociationInternal {
                 continue;
             }
 
-            if self.inflight_queue.get_num_bytes() + data_len > self.cwnd as usize {
+            let cwnd = self.cwnd + self.limited_transmit_sacks * self.mtu;
+            if self.inflight_queue.get_num_bytes() + data_len > cwnd as usize {
                 break; // would exceed cwnd
             }
 
@@ -2026,17 +2449,16 @@ impl AssociationInternal {
                     );
                 }
             } else if reliability_type == ReliabilityType::Timed {
-                if let Ok(elapsed) = SystemTime::now().duration_since(c.since) {
-                    if elapsed.as_millis() as u32 >= reliability_value {
-                        c.set_abandoned(true);
-                        log::trace!(
-                            "[{}] marked as abandoned: tsn={} ppi={} (timed: {:?})",
-                            self.name,
-                            c.tsn,
-                            c.payload_type,
-                            elapsed
-                        );
-                    }
+                let elapsed = self.clock.now().duration_since(c.since);
+                if elapsed.as_millis() as u32 >= reliability_value {
+                    c.set_abandoned(true);
+                    log::trace!(
+                        "[{}] marked as abandoned: tsn={} ppi={} (timed: {:?})",
+                        self.name,
+                        c.tsn,
+                        c.payload_type,
+                        elapsed
+                    );
                 }
             }
         } else {
@@ -2053,7 +2475,7 @@ impl AssociationInternal {
         let mut done = false;
         let mut i = 0;
         while !done {
-            let tsn = self.cumulative_tsn_ack_point + i + 1;
+            let tsn = self.cumulative_tsn_ack_point.wrapping_add(i + 1);
             if let Some(c) = self.inflight_queue.get_mut(tsn) {
                 if !c.retransmit {
                     i += 1;
@@ -2071,6 +2493,7 @@ impl AssociationInternal {
                 // t3-rtx timer fires
                 c.retransmit = false;
                 bytes_to_send += c.user_data.len();
+                self.stats.inc_timeout_retrans();
 
                 c.nsent += 1;
             } else {
@@ -2099,7 +2522,7 @@ impl AssociationInternal {
     /// generate_next_tsn returns the my_next_tsn and increases it. The caller should hold the lock.
     fn generate_next_tsn(&mut self) -> u32 {
         let tsn = self.my_next_tsn;
-        self.my_next_tsn += 1;
+        self.my_next_tsn = self.my_next_tsn.wrapping_add(1);
         tsn
     }
 
@@ -2112,9 +2535,11 @@ impl AssociationInternal {
 
     async fn create_selective_ack_chunk(&mut self) -> ChunkSelectiveAck {
         ChunkSelectiveAck {
-            cumulative_tsn_ack: self.peer_last_tsn,
+            cumulative_tsn_ack: self.peer_last_tsn as u32,
             advertised_receiver_window_credit: self.get_my_receiver_window_credit().await,
-            gap_ack_blocks: self.payload_queue.get_gap_ack_blocks(self.peer_last_tsn),
+            gap_ack_blocks: self
+                .payload_queue
+                .get_gap_ack_blocks(self.peer_last_tsn as u32),
             duplicate_tsn: self.payload_queue.pop_duplicates(),
         }
     }
@@ -2158,9 +2583,12 @@ impl AssociationInternal {
             } else {
                 self.handle_init(p, c).await?
             }
-        } else if chunk_any.downcast_ref::<ChunkAbort>().is_some()
-            || chunk_any.downcast_ref::<ChunkError>().is_some()
-        {
+        } else if let Some(c) = chunk_any.downcast_ref::<ChunkAbort>() {
+            self.set_close_reason(CloseReason::PeerAbort {
+                cause: c.error_causes.first().map(|cause| cause.code.0),
+            });
+            return Err(Error::ErrChunk);
+        } else if chunk_any.downcast_ref::<ChunkError>().is_some() {
             return Err(Error::ErrChunk);
         } else if let Some(c) = chunk_any.downcast_ref::<ChunkHeartbeat>() {
             self.handle_heartbeat(c).await?
@@ -2338,6 +2766,9 @@ impl RtxTimerObserver for AssociationInternal {
 
                 self.ssthresh = std::cmp::max(self.cwnd / 2, 4 * self.mtu);
                 self.cwnd = self.mtu;
+                // The timeout takes over the recovery of the window
+                self.in_fast_recovery = false;
+                self.limited_transmit_sacks = 0;
                 log::trace!(
                     "[{}] updated cwnd={} ssthresh={} inflight={} (RTO)",
                     self.name,
@@ -2352,13 +2783,13 @@ impl RtxTimerObserver for AssociationInternal {
                 //  the procedures outlined in C2 - C5.
                 if self.use_forward_tsn {
                     // RFC 3758 Sec 3.5 C2
-                    let mut i = self.advanced_peer_tsn_ack_point + 1;
+                    let mut i = self.advanced_peer_tsn_ack_point.wrapping_add(1);
                     while let Some(c) = self.inflight_queue.get(i) {
                         if !c.abandoned() {
                             break;
                         }
                         self.advanced_peer_tsn_ack_point = i;
-                        i += 1;
+                        i = i.wrapping_add(1);
                     }
 
                     // RFC 3758 Sec 3.5 C3
@@ -2398,20 +2829,26 @@ impl RtxTimerObserver for AssociationInternal {
 
     async fn on_retransmission_failure(&mut self, id: RtxTimerId) {
         match id {
-            RtxTimerId::T1Init => {
-                log::error!("[{}] retransmission failure: T1-init", self.name);
+            RtxTimerId::T1Init | RtxTimerId::T1Cookie => {
+                log::error!(
+                    "[{}] retransmission failure: {}",
+                    self.name,
+                    if id == RtxTimerId::T1Init {
+                        "T1-init"
+                    } else {
+                        "T1-cookie"
+                    }
+                );
+                self.set_close_reason(CloseReason::HandshakeTimeout);
                 if let Some(handshake_completed_ch) = &self.handshake_completed_ch_tx {
                     let _ = handshake_completed_ch
-                        .send(Some(Error::ErrHandshakeInitAck))
+                        .send(Some(Error::ErrAssociationClosed(
+                            CloseReason::HandshakeTimeout,
+                        )))
                         .await;
                 }
-            }
-            RtxTimerId::T1Cookie => {
-                log::error!("[{}] retransmission failure: T1-cookie", self.name);
-                if let Some(handshake_completed_ch) = &self.handshake_completed_ch_tx {
-                    let _ = handshake_completed_ch
-                        .send(Some(Error::ErrHandshakeCookieEcho))
-                        .await;
+                if let Err(err) = self.close().await {
+                    log::warn!("[{}] failed to close association: {:?}", self.name, err);
                 }
             }
 
@@ -2420,11 +2857,13 @@ impl RtxTimerObserver for AssociationInternal {
             }
 
             RtxTimerId::T3RTX => {
-                // T3-rtx timer will not fail by design
-                // Justifications:
-                //  * ICE would fail if the connectivity is lost
-                //  * WebRTC spec is not clear how this incident should be reported to ULP
+                // T3-rtx timer only fails with Config::max_retransmissions set, by default ICE
+                // is left to detect that connectivity is lost.
                 log::error!("[{}] retransmission failure: T3-rtx (DATA)", self.name);
+                self.set_close_reason(CloseReason::MaxRetransmissionsExceeded);
+                if let Err(err) = self.close().await {
+                    log::warn!("[{}] failed to close association: {:?}", self.name, err);
+                }
             }
             _ => {}
         }
@@ -1,4 +1,6 @@
 use super::*;
+use crate::chunk::chunk_selective_ack::GapAckBlock;
+use crate::clock::ManualClock;
 use std::io;
 use std::net::SocketAddr;
 
@@ -163,7 +165,7 @@ async fn test_handle_forward_tsn_forward_3unreceived_chunks() -> Result<()> {
     let prev_tsn = a.peer_last_tsn;
 
     let fwdtsn = ChunkForwardTsn {
-        new_cumulative_tsn: a.peer_last_tsn + 3,
+        new_cumulative_tsn: a.peer_last_tsn as u32 + 3,
         streams: vec![ChunkForwardTsnStream {
             identifier: 0,
             sequence: 0,
@@ -201,17 +203,17 @@ async fn test_handle_forward_tsn_forward_1for1_missing() -> Result<()> {
         ChunkPayloadData {
             beginning_fragment: true,
             ending_fragment: true,
-            tsn: a.peer_last_tsn + 2,
+            tsn: a.peer_last_tsn as u32 + 2,
             stream_identifier: 0,
             stream_sequence_number: 1,
             user_data: Bytes::from_static(b"ABC"),
             ..Default::default()
         },
-        a.peer_last_tsn,
+        a.peer_last_tsn as u32,
     );
 
     let fwdtsn = ChunkForwardTsn {
-        new_cumulative_tsn: a.peer_last_tsn + 1,
+        new_cumulative_tsn: a.peer_last_tsn as u32 + 1,
         streams: vec![ChunkForwardTsnStream {
             identifier: 0,
             sequence: 1,
@@ -249,17 +251,17 @@ async fn test_handle_forward_tsn_forward_1for2_missing() -> Result<()> {
         ChunkPayloadData {
             beginning_fragment: true,
             ending_fragment: true,
-            tsn: a.peer_last_tsn + 3,
+            tsn: a.peer_last_tsn as u32 + 3,
             stream_identifier: 0,
             stream_sequence_number: 1,
             user_data: Bytes::from_static(b"ABC"),
             ..Default::default()
         },
-        a.peer_last_tsn,
+        a.peer_last_tsn as u32,
     );
 
     let fwdtsn = ChunkForwardTsn {
-        new_cumulative_tsn: a.peer_last_tsn + 1,
+        new_cumulative_tsn: a.peer_last_tsn as u32 + 1,
         streams: vec![ChunkForwardTsnStream {
             identifier: 0,
             sequence: 1,
@@ -291,7 +293,7 @@ async fn test_handle_forward_tsn_dup_forward_tsn_chunk_should_generate_sack() ->
     let prev_tsn = a.peer_last_tsn;
 
     let fwdtsn = ChunkForwardTsn {
-        new_cumulative_tsn: a.peer_last_tsn,
+        new_cumulative_tsn: a.peer_last_tsn as u32,
         streams: vec![ChunkForwardTsnStream {
             identifier: 0,
             sequence: 1,
@@ -335,7 +337,7 @@ async fn test_assoc_create_new_stream() -> Result<()> {
     let to_be_ignored = ChunkPayloadData {
         beginning_fragment: true,
         ending_fragment: true,
-        tsn: a.peer_last_tsn + 1,
+        tsn: a.peer_last_tsn as u32 + 1,
         stream_identifier: new_si,
         user_data: Bytes::from_static(b"ABC"),
         ..Default::default()
@@ -353,6 +355,12 @@ async fn handle_init_test(name: &str, initial_state: AssociationState, expect_er
         max_receive_buffer_size: 0,
         max_message_size: 0,
         name: "client".to_owned(),
+        max_association_buffered_amount: 0,
+        buffer_full_policy: Default::default(),
+        max_retransmissions: 0,
+        num_outbound_streams: 0,
+        num_inbound_streams: 0,
+        clock: None,
     });
     a.set_state(initial_state);
     let pkt = Packet {
@@ -383,12 +391,23 @@ async fn handle_init_test(name: &str, initial_state: AssociationState, expect_er
         } else {
             init.initial_tsn - 1
         },
-        a.peer_last_tsn,
+        a.peer_last_tsn as u32,
+        "{} should match",
+        name
+    );
+    // our outbound streams are limited by the inbound streams of the peer and vice versa
+    assert_eq!(
+        1002,
+        a.my_max_num_outbound_streams.load(Ordering::SeqCst),
+        "{} should match",
+        name
+    );
+    assert_eq!(
+        1001,
+        a.my_max_num_inbound_streams.load(Ordering::SeqCst),
         "{} should match",
         name
     );
-    assert_eq!(1001, a.my_max_num_outbound_streams, "{} should match", name);
-    assert_eq!(1002, a.my_max_num_inbound_streams, "{} should match", name);
     assert_eq!(5678, a.peer_verification_tag, "{} should match", name);
     assert_eq!(pkt.source_port, a.destination_port, "{} should match", name);
     assert_eq!(pkt.destination_port, a.source_port, "{} should match", name);
@@ -437,13 +456,19 @@ async fn test_assoc_handle_init() -> Result<()> {
     Ok(())
 }
 
-#[test]
-fn test_assoc_max_message_size_default() -> Result<()> {
+#[tokio::test]
+async fn test_assoc_max_message_size_default() -> Result<()> {
     let mut a = create_association_internal(Config {
         net_conn: Arc::new(DumbConn {}),
         max_receive_buffer_size: 0,
         max_message_size: 0,
         name: "client".to_owned(),
+        max_association_buffered_amount: 0,
+        buffer_full_policy: Default::default(),
+        max_retransmissions: 0,
+        num_outbound_streams: 0,
+        num_inbound_streams: 0,
+        clock: None,
     });
     assert_eq!(
         65536,
@@ -458,7 +483,7 @@ fn test_assoc_max_message_size_default() -> Result<()> {
         let p = Bytes::from(vec![0u8; 65537]);
         let ppi = PayloadProtocolIdentifier::from(s.default_payload_type.load(Ordering::SeqCst));
 
-        if let Err(err) = s.write_sctp(&p.slice(..65536), ppi) {
+        if let Err(err) = s.write_sctp(&p.slice(..65536), ppi).await {
             assert_ne!(
                 Error::ErrOutboundPacketTooLarge,
                 err,
@@ -468,7 +493,7 @@ fn test_assoc_max_message_size_default() -> Result<()> {
             assert!(false, "should be error");
         }
 
-        if let Err(err) = s.write_sctp(&p.slice(..65537), ppi) {
+        if let Err(err) = s.write_sctp(&p.slice(..65537), ppi).await {
             assert_eq!(
                 Error::ErrOutboundPacketTooLarge,
                 err,
@@ -482,13 +507,19 @@ fn test_assoc_max_message_size_default() -> Result<()> {
     Ok(())
 }
 
-#[test]
-fn test_assoc_max_message_size_explicit() -> Result<()> {
+#[tokio::test]
+async fn test_assoc_max_message_size_explicit() -> Result<()> {
     let mut a = create_association_internal(Config {
         net_conn: Arc::new(DumbConn {}),
         max_receive_buffer_size: 0,
         max_message_size: 30000,
         name: "client".to_owned(),
+        max_association_buffered_amount: 0,
+        buffer_full_policy: Default::default(),
+        max_retransmissions: 0,
+        num_outbound_streams: 0,
+        num_inbound_streams: 0,
+        clock: None,
     });
 
     assert_eq!(
@@ -504,7 +535,7 @@ fn test_assoc_max_message_size_explicit() -> Result<()> {
         let p = Bytes::from(vec![0u8; 30001]);
         let ppi = PayloadProtocolIdentifier::from(s.default_payload_type.load(Ordering::SeqCst));
 
-        if let Err(err) = s.write_sctp(&p.slice(..30000), ppi) {
+        if let Err(err) = s.write_sctp(&p.slice(..30000), ppi).await {
             assert_ne!(
                 Error::ErrOutboundPacketTooLarge,
                 err,
@@ -514,7 +545,7 @@ fn test_assoc_max_message_size_explicit() -> Result<()> {
             assert!(false, "should be error");
         }
 
-        if let Err(err) = s.write_sctp(&p.slice(..30001), ppi) {
+        if let Err(err) = s.write_sctp(&p.slice(..30001), ppi).await {
             assert_eq!(
                 Error::ErrOutboundPacketTooLarge,
                 err,
@@ -527,3 +558,422 @@ fn test_assoc_max_message_size_explicit() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_assoc_handshake_timeout_close_reason() -> Result<()> {
+    let (close_loop_ch_tx, _close_loop_ch_rx) = broadcast::channel(1);
+    let (accept_ch_tx, _accept_ch_rx) = mpsc::channel(1);
+    let (handshake_completed_ch_tx, mut handshake_completed_ch_rx) = mpsc::channel(1);
+    let (awake_write_loop_ch_tx, _awake_write_loop_ch_rx) = mpsc::channel(1);
+    let mut a = AssociationInternal::new(
+        Config {
+            net_conn: Arc::new(DumbConn {}),
+            max_receive_buffer_size: 0,
+            max_message_size: 0,
+            name: "client".to_owned(),
+            max_association_buffered_amount: 0,
+            buffer_full_policy: Default::default(),
+            max_retransmissions: 0,
+            num_outbound_streams: 0,
+            num_inbound_streams: 0,
+            clock: None,
+        },
+        close_loop_ch_tx,
+        accept_ch_tx,
+        handshake_completed_ch_tx,
+        Arc::new(awake_write_loop_ch_tx),
+    );
+    a.set_state(AssociationState::CookieWait);
+
+    a.on_retransmission_failure(RtxTimerId::T1Init).await;
+
+    assert_eq!(AssociationState::Closed, a.get_state());
+    assert_eq!(Some(CloseReason::HandshakeTimeout), a.close_reason);
+    assert_eq!(
+        Some(Some(Error::ErrAssociationClosed(
+            CloseReason::HandshakeTimeout
+        ))),
+        handshake_completed_ch_rx.recv().await,
+        "the handshake must fail with the close reason"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_assoc_max_retransmissions_close_reason() -> Result<()> {
+    let mut a = create_association_internal(Config {
+        net_conn: Arc::new(DumbConn {}),
+        max_receive_buffer_size: 0,
+        max_message_size: 0,
+        name: "client".to_owned(),
+        max_association_buffered_amount: 0,
+        buffer_full_policy: Default::default(),
+        max_retransmissions: 1,
+        num_outbound_streams: 0,
+        num_inbound_streams: 0,
+        clock: None,
+    });
+    a.set_state(AssociationState::Established);
+
+    a.on_retransmission_failure(RtxTimerId::T3RTX).await;
+
+    assert_eq!(AssociationState::Closed, a.get_state());
+    assert_eq!(
+        Some(CloseReason::MaxRetransmissionsExceeded),
+        a.close_reason
+    );
+
+    // the first reason is kept
+    a.set_close_reason(CloseReason::Local);
+    assert_eq!(
+        Some(CloseReason::MaxRetransmissionsExceeded),
+        a.close_reason
+    );
+
+    Ok(())
+}
+
+/// create_sending_association returns an established association with DATA chunks of TSN 10
+/// to 10 + n - 1 in flight, each carrying size bytes.
+fn create_sending_association(n: u32, size: usize) -> AssociationInternal {
+    create_sending_association_from(10, n, size)
+}
+
+/// create_sending_association_from returns an established association with n DATA chunks in
+/// flight from TSN first on, each carrying size bytes.
+fn create_sending_association_from(first: u32, n: u32, size: usize) -> AssociationInternal {
+    let mut a = create_association_internal(Config {
+        net_conn: Arc::new(DumbConn {}),
+        max_receive_buffer_size: 0,
+        max_message_size: 0,
+        name: "client".to_owned(),
+        max_association_buffered_amount: 0,
+        buffer_full_policy: Default::default(),
+        max_retransmissions: 0,
+        num_outbound_streams: 0,
+        num_inbound_streams: 0,
+        clock: None,
+    });
+    a.set_state(AssociationState::Established);
+    a.cumulative_tsn_ack_point = first.wrapping_sub(1);
+    a.my_next_tsn = first.wrapping_add(n);
+    a.cwnd = 10 * a.mtu;
+    a.ssthresh = 10 * a.mtu;
+    for i in 0..n {
+        a.inflight_queue.push_no_check(ChunkPayloadData {
+            beginning_fragment: true,
+            ending_fragment: true,
+            tsn: first.wrapping_add(i),
+            stream_identifier: 1,
+            user_data: Bytes::from(vec![0u8; size]),
+            nsent: 1,
+            ..Default::default()
+        });
+    }
+    a
+}
+
+/// sack returns a SACK acknowledging the TSNs from 10 to 10 + acked - 1 but the lost ones.
+fn sack(lost: &[u32], acked: u32) -> ChunkSelectiveAck {
+    let mut cumulative_tsn_ack = 9;
+    while cumulative_tsn_ack < 9 + acked && !lost.contains(&(cumulative_tsn_ack + 1)) {
+        cumulative_tsn_ack += 1;
+    }
+
+    let mut gap_ack_blocks: Vec<GapAckBlock> = vec![];
+    for tsn in cumulative_tsn_ack + 1..10 + acked {
+        if lost.contains(&tsn) {
+            continue;
+        }
+        let offset = (tsn - cumulative_tsn_ack) as u16;
+        match gap_ack_blocks.last_mut() {
+            Some(block) if block.end + 1 == offset => block.end = offset,
+            _ => gap_ack_blocks.push(GapAckBlock {
+                start: offset,
+                end: offset,
+            }),
+        }
+    }
+
+    ChunkSelectiveAck {
+        cumulative_tsn_ack,
+        advertised_receiver_window_credit: 1024 * 1024,
+        gap_ack_blocks,
+        duplicate_tsn: vec![],
+    }
+}
+
+#[tokio::test]
+async fn test_assoc_fast_retransmit_after_three_miss_indications() -> Result<()> {
+    let mut a = create_sending_association(8, 100);
+    let cwnd = a.cwnd;
+
+    for acked in 2..4 {
+        a.handle_sack(&sack(&[10], acked)).await?;
+        assert!(!a.in_fast_recovery, "{} miss indication(s) only", acked - 1);
+        assert_eq!(cwnd, a.cwnd, "cwnd must not be reduced yet");
+        assert!(a
+            .gather_outbound_fast_retransmission_packets(vec![])
+            .is_empty());
+    }
+
+    a.handle_sack(&sack(&[10], 4)).await?;
+    assert!(a.in_fast_recovery);
+    assert_eq!(cwnd / 2, a.cwnd);
+    assert_eq!(
+        1,
+        a.gather_outbound_fast_retransmission_packets(vec![]).len()
+    );
+    assert_eq!(1, a.stats.get_num_fast_retrans());
+    assert_eq!(1, a.stats.get_num_fast_recoveries());
+
+    // More SACKs for the same loss neither retransmit nor reduce cwnd again
+    a.handle_sack(&sack(&[10], 5)).await?;
+    a.handle_sack(&sack(&[10], 6)).await?;
+    assert_eq!(cwnd / 2, a.cwnd);
+    assert!(a
+        .gather_outbound_fast_retransmission_packets(vec![])
+        .is_empty());
+    assert_eq!(1, a.stats.get_num_fast_retrans());
+    assert_eq!(1, a.stats.get_num_fast_recoveries());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_assoc_fast_recovery_one_reduction_per_window() -> Result<()> {
+    let mut a = create_sending_association(8, 100);
+    let cwnd = a.cwnd;
+
+    for acked in 4..7 {
+        a.handle_sack(&sack(&[10, 12], acked)).await?;
+    }
+    assert!(a.in_fast_recovery);
+    assert_eq!(cwnd / 2, a.cwnd, "two losses of a window reduce cwnd once");
+    assert_eq!(1, a.stats.get_num_fast_recoveries());
+
+    // Both losses are retransmitted in the same packet
+    assert_eq!(
+        1,
+        a.gather_outbound_fast_retransmission_packets(vec![]).len()
+    );
+    assert_eq!(2, a.stats.get_num_fast_retrans());
+
+    // Fast Recovery ends once the window is acknowledged
+    a.handle_sack(&sack(&[], 8)).await?;
+    assert!(!a.in_fast_recovery);
+    assert_eq!(1, a.stats.get_num_fast_recoveries());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_assoc_limited_transmit() -> Result<()> {
+    let mut a = create_sending_association(4, 1000);
+    a.cwnd = 4000;
+    a.ssthresh = 4000;
+    for _ in 0..3 {
+        a.pending_queue.push(ChunkPayloadData {
+            beginning_fragment: true,
+            ending_fragment: true,
+            stream_identifier: 1,
+            user_data: Bytes::from(vec![0u8; 1000]),
+            ..Default::default()
+        });
+    }
+
+    // The first SACK reporting a gap allows an MTU beyond cwnd
+    a.handle_sack(&sack(&[10], 2)).await?;
+    assert_eq!(1, a.limited_transmit_sacks);
+    let (chunks, _) = a.pop_pending_data_chunks_to_send();
+    assert_eq!(2, chunks.len(), "one chunk within cwnd and one beyond");
+    assert_eq!(4000, a.cwnd, "cwnd must be left unchanged");
+
+    // The third one starts Fast Recovery instead
+    a.handle_sack(&sack(&[10], 3)).await?;
+    assert_eq!(2, a.limited_transmit_sacks);
+    a.handle_sack(&sack(&[10], 4)).await?;
+    assert!(a.in_fast_recovery);
+    assert_eq!(0, a.limited_transmit_sacks);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_assoc_t3_timeout_ends_fast_recovery() -> Result<()> {
+    let mut a = create_sending_association(8, 100);
+
+    for acked in 2..5 {
+        a.handle_sack(&sack(&[10], acked)).await?;
+    }
+    assert!(a.in_fast_recovery);
+    a.gather_outbound_fast_retransmission_packets(vec![]);
+
+    a.on_retransmission_timeout(RtxTimerId::T3RTX, 1).await;
+    assert!(!a.in_fast_recovery);
+    assert_eq!(a.mtu, a.cwnd);
+    assert_eq!(1, a.stats.get_num_t3timeouts());
+
+    // The timeout retransmits all the TSNs outstanding, the fast retransmitted one included
+    assert_eq!(1, a.get_data_packets_to_retransmit().len());
+    assert_eq!(5, a.stats.get_num_timeout_retrans());
+    assert_eq!(1, a.stats.get_num_fast_retrans());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_assoc_send_across_tsn_wraparound() -> Result<()> {
+    let first = u32::MAX - 3;
+    let mut a = create_sending_association_from(first, 8, 100);
+
+    // Acks the first two TSNs, and u32::MAX and 0 with a gap ack block
+    a.handle_sack(&ChunkSelectiveAck {
+        cumulative_tsn_ack: first.wrapping_add(1),
+        advertised_receiver_window_credit: 1024 * 1024,
+        gap_ack_blocks: vec![GapAckBlock { start: 2, end: 3 }],
+        duplicate_tsn: vec![],
+    })
+    .await?;
+    assert_eq!(first.wrapping_add(1), a.cumulative_tsn_ack_point);
+    assert_eq!(6, a.inflight_queue.len());
+    for (tsn, acked) in [
+        (u32::MAX - 1, false),
+        (u32::MAX, true),
+        (0, true),
+        (1, false),
+    ] {
+        assert_eq!(
+            Some(acked),
+            a.inflight_queue.get(tsn).map(|c| c.acked),
+            "tsn={}",
+            tsn
+        );
+    }
+
+    let cumulative_ack = |cumulative_tsn_ack| ChunkSelectiveAck {
+        cumulative_tsn_ack,
+        ..sack(&[], 0)
+    };
+
+    // SACKs behind the ones before are dropped
+    a.handle_sack(&cumulative_ack(first)).await?;
+    a.handle_sack(&cumulative_ack(first.wrapping_sub(100)))
+        .await?;
+    assert_eq!(first.wrapping_add(1), a.cumulative_tsn_ack_point);
+    assert_eq!(2, a.stats.get_num_stale_sacks());
+
+    a.handle_sack(&cumulative_ack(first.wrapping_add(7)))
+        .await?;
+    assert_eq!(3, a.cumulative_tsn_ack_point);
+    assert!(a.inflight_queue.is_empty());
+
+    Ok(())
+}
+
+/// The receiving side of a long session, in which the TSNs of the peer wrap around several
+/// times. Each round receives a burst of reordered DATA chunks, then the peer abandons a
+/// quarter of the TSN space with a FORWARD TSN chunk.
+#[tokio::test]
+async fn test_assoc_receive_across_tsn_wraparound() -> Result<()> {
+    const ROUNDS: u32 = 20;
+    const BURST: u32 = 16;
+    const ABANDONED: u32 = (1 << 30) + 12345;
+
+    // No timer fires while time stands still
+    let clock = Arc::new(ManualClock::new());
+    let mut a = create_association_internal(Config {
+        net_conn: Arc::new(DumbConn {}),
+        max_receive_buffer_size: 0,
+        max_message_size: 0,
+        name: "server".to_owned(),
+        max_association_buffered_amount: 0,
+        buffer_full_policy: Default::default(),
+        max_retransmissions: 0,
+        num_outbound_streams: 0,
+        num_inbound_streams: 0,
+        clock: Some(Arc::clone(&clock) as Arc<dyn Clock>),
+    });
+    a.set_state(AssociationState::Established);
+    a.use_forward_tsn = true;
+    a.peer_last_tsn = initial_peer_last_tsn(u32::MAX - BURST / 2);
+    let start = a.peer_last_tsn;
+    let s = a.create_stream(0, false).expect("stream 0");
+
+    let mut ssn = 0u16;
+    for round in 0..ROUNDS {
+        let cumulative_tsn = a.peer_last_tsn as u32;
+        let chunks: Vec<ChunkPayloadData> = (1..=BURST)
+            .map(|i| ChunkPayloadData {
+                beginning_fragment: true,
+                ending_fragment: true,
+                tsn: cumulative_tsn.wrapping_add(i),
+                stream_identifier: 0,
+                stream_sequence_number: ssn.wrapping_add(i as u16 - 1),
+                user_data: Bytes::from(format!("{}:{}", round, i)),
+                ..Default::default()
+            })
+            .collect();
+
+        // All but the first chunk in reverse order, a duplicate and one too far ahead
+        for c in chunks[1..].iter().rev() {
+            a.handle_data(c).await?;
+        }
+        a.handle_data(&chunks[BURST as usize - 1]).await?;
+        a.handle_data(&ChunkPayloadData {
+            tsn: cumulative_tsn.wrapping_add(RECEIVE_TSN_WINDOW as u32 + 1),
+            ..chunks[0].clone()
+        })
+        .await?;
+
+        let sack = a.create_selective_ack_chunk().await;
+        assert_eq!(cumulative_tsn, sack.cumulative_tsn_ack, "round {}", round);
+        let blocks: Vec<(u16, u16)> = sack
+            .gap_ack_blocks
+            .iter()
+            .map(|b| (b.start, b.end))
+            .collect();
+        assert_eq!(vec![(2, BURST as u16)], blocks, "round {}", round);
+        assert_eq!(vec![cumulative_tsn.wrapping_add(BURST)], sack.duplicate_tsn);
+
+        // The first chunk completes the burst, and arrives again
+        a.handle_data(&chunks[0]).await?;
+        a.handle_data(&chunks[0]).await?;
+        let sack = a.create_selective_ack_chunk().await;
+        assert_eq!(cumulative_tsn.wrapping_add(BURST), sack.cumulative_tsn_ack);
+        assert!(sack.gap_ack_blocks.is_empty());
+        assert_eq!(vec![cumulative_tsn.wrapping_add(1)], sack.duplicate_tsn);
+
+        let mut buf = vec![0u8; 64];
+        for i in 1..=BURST {
+            let n = s.read(&mut buf).await?;
+            assert_eq!(format!("{}:{}", round, i).as_bytes(), &buf[..n]);
+        }
+        ssn = ssn.wrapping_add(BURST as u16);
+
+        a.handle_forward_tsn(&ChunkForwardTsn {
+            new_cumulative_tsn: cumulative_tsn.wrapping_add(BURST + ABANDONED),
+            streams: vec![],
+        })
+        .await?;
+        assert_eq!(
+            cumulative_tsn.wrapping_add(BURST + ABANDONED),
+            a.create_selective_ack_chunk().await.cumulative_tsn_ack
+        );
+    }
+
+    assert_eq!(
+        start + ROUNDS as u64 * (BURST + ABANDONED) as u64,
+        a.peer_last_tsn
+    );
+    assert!(
+        a.peer_last_tsn - start > 4 << 32,
+        "must wrap around several times"
+    );
+    assert_eq!(2 * ROUNDS as u64, a.stats.get_num_dup_tsns());
+    assert_eq!(ROUNDS as u64, a.stats.get_num_out_of_window_datas());
+    assert_eq!(Duration::ZERO, clock.elapsed());
+
+    Ok(())
+}
@@ -7,6 +7,11 @@ pub(crate) struct AssociationStats {
     n_t3timeouts: AtomicU64,
     n_ack_timeouts: AtomicU64,
     n_fast_retrans: AtomicU64,
+    n_fast_recoveries: AtomicU64,
+    n_timeout_retrans: AtomicU64,
+    n_dup_tsns: AtomicU64,
+    n_out_of_window_datas: AtomicU64,
+    n_stale_sacks: AtomicU64,
 }
 
 impl AssociationStats {
@@ -50,11 +55,56 @@ impl AssociationStats {
         self.n_fast_retrans.load(Ordering::SeqCst)
     }
 
+    pub(crate) fn inc_fast_recoveries(&self) {
+        self.n_fast_recoveries.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn get_num_fast_recoveries(&self) -> u64 {
+        self.n_fast_recoveries.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn inc_timeout_retrans(&self) {
+        self.n_timeout_retrans.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn get_num_timeout_retrans(&self) -> u64 {
+        self.n_timeout_retrans.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn inc_dup_tsns(&self) {
+        self.n_dup_tsns.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn get_num_dup_tsns(&self) -> u64 {
+        self.n_dup_tsns.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn inc_out_of_window_datas(&self) {
+        self.n_out_of_window_datas.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn get_num_out_of_window_datas(&self) -> u64 {
+        self.n_out_of_window_datas.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn inc_stale_sacks(&self) {
+        self.n_stale_sacks.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn get_num_stale_sacks(&self) -> u64 {
+        self.n_stale_sacks.load(Ordering::SeqCst)
+    }
+
     pub(crate) fn reset(&self) {
         self.n_datas.store(0, Ordering::SeqCst);
         self.n_sacks.store(0, Ordering::SeqCst);
         self.n_t3timeouts.store(0, Ordering::SeqCst);
         self.n_ack_timeouts.store(0, Ordering::SeqCst);
         self.n_fast_retrans.store(0, Ordering::SeqCst);
+        self.n_fast_recoveries.store(0, Ordering::SeqCst);
+        self.n_timeout_retrans.store(0, Ordering::SeqCst);
+        self.n_dup_tsns.store(0, Ordering::SeqCst);
+        self.n_out_of_window_datas.store(0, Ordering::SeqCst);
+        self.n_stale_sacks.store(0, Ordering::SeqCst);
     }
 }
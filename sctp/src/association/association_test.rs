@@ -7,11 +7,14 @@ use std::io;
 use std::net::Shutdown;
 use std::net::SocketAddr;
 use std::str::FromStr;
+use std::sync::atomic::AtomicU64;
 use std::time::Duration;
+use tokio::io::AsyncWriteExt;
 use tokio::net::UdpSocket;
 use util::conn::conn_bridge::*;
 use util::conn::conn_pipe::pipe;
 use util::conn::*;
+use util::vnet;
 
 async fn create_new_association_pair(
     br: &Arc<Bridge>,
@@ -32,6 +35,12 @@ async fn create_new_association_pair(
             max_receive_buffer_size: recv_buf_size,
             max_message_size: 0,
             name: "client".to_owned(),
+            max_association_buffered_amount: 0,
+            buffer_full_policy: Default::default(),
+            max_retransmissions: 0,
+            num_outbound_streams: 0,
+            num_inbound_streams: 0,
+            clock: None,
         })
         .await;
 
@@ -48,6 +57,12 @@ async fn create_new_association_pair(
             max_receive_buffer_size: recv_buf_size,
             max_message_size: 0,
             name: "server".to_owned(),
+            max_association_buffered_amount: 0,
+            buffer_full_policy: Default::default(),
+            max_retransmissions: 0,
+            num_outbound_streams: 0,
+            num_inbound_streams: 0,
+            clock: None,
         })
         .await;
 
@@ -185,7 +200,9 @@ async fn establish_session_pair(
     let s0 = client
         .open_stream(si, PayloadProtocolIdentifier::Binary)
         .await?;
-    let _ = s0.write_sctp(&hello_msg, PayloadProtocolIdentifier::Dcep)?;
+    let _ = s0
+        .write_sctp(&hello_msg, PayloadProtocolIdentifier::Dcep)
+        .await?;
 
     flush_buffers(br, client, server).await;
 
@@ -251,7 +268,9 @@ async fn test_assoc_reliable_simple() -> Result<()> {
         assert_eq!(0, a.buffered_amount(), "incorrect bufferedAmount");
     }
 
-    let n = s0.write_sctp(&MSG, PayloadProtocolIdentifier::Binary)?;
+    let n = s0
+        .write_sctp(&MSG, PayloadProtocolIdentifier::Binary)
+        .await?;
     assert_eq!(MSG.len(), n, "unexpected length of received data");
     {
         let a = a0.association_internal.lock().await;
@@ -325,17 +344,21 @@ async fn test_assoc_reliable_ordered_reordered() -> Result<()> {
     }
 
     sbuf[0..4].copy_from_slice(&0u32.to_be_bytes());
-    let n = s0.write_sctp(
-        &Bytes::from(sbuf.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbuf.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbuf.len(), n, "unexpected length of received data");
 
     sbuf[0..4].copy_from_slice(&1u32.to_be_bytes());
-    let n = s0.write_sctp(
-        &Bytes::from(sbuf.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbuf.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbuf.len(), n, "unexpected length of received data");
 
     tokio::time::sleep(Duration::from_millis(10)).await;
@@ -413,10 +436,12 @@ async fn test_assoc_reliable_ordered_fragmented_then_defragmented() -> Result<()
     s0.set_reliability_params(false, ReliabilityType::Reliable, 0);
     s1.set_reliability_params(false, ReliabilityType::Reliable, 0);
 
-    let n = s0.write_sctp(
-        &Bytes::from(sbufl.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbufl.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbufl.len(), n, "unexpected length of received data");
 
     flush_buffers(&br, &a0, &a1).await;
@@ -478,10 +503,12 @@ async fn test_assoc_reliable_unordered_fragmented_then_defragmented() -> Result<
     s0.set_reliability_params(true, ReliabilityType::Reliable, 0);
     s1.set_reliability_params(true, ReliabilityType::Reliable, 0);
 
-    let n = s0.write_sctp(
-        &Bytes::from(sbufl.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbufl.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbufl.len(), n, "unexpected length of received data");
 
     flush_buffers(&br, &a0, &a1).await;
@@ -546,17 +573,21 @@ async fn test_assoc_reliable_unordered_ordered() -> Result<()> {
     br.reorder_next_nwrites(0, 2);
 
     sbuf[0..4].copy_from_slice(&0u32.to_be_bytes());
-    let n = s0.write_sctp(
-        &Bytes::from(sbuf.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbuf.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbuf.len(), n, "unexpected length of received data");
 
     sbuf[0..4].copy_from_slice(&1u32.to_be_bytes());
-    let n = s0.write_sctp(
-        &Bytes::from(sbuf.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbuf.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbuf.len(), n, "unexpected length of received data");
 
     flush_buffers(&br, &a0, &a1).await;
@@ -630,10 +661,14 @@ async fn test_assoc_reliable_retransmission() -> Result<()> {
 
     let (s0, s1) = establish_session_pair(&br, &a0, &mut a1, SI).await?;
 
-    let n = s0.write_sctp(&MSG1, PayloadProtocolIdentifier::Binary)?;
+    let n = s0
+        .write_sctp(&MSG1, PayloadProtocolIdentifier::Binary)
+        .await?;
     assert_eq!(MSG1.len(), n, "unexpected length of received data");
 
-    let n = s0.write_sctp(&MSG2, PayloadProtocolIdentifier::Binary)?;
+    let n = s0
+        .write_sctp(&MSG2, PayloadProtocolIdentifier::Binary)
+        .await?;
     assert_eq!(MSG2.len(), n, "unexpected length of received data");
 
     tokio::time::sleep(Duration::from_millis(10)).await;
@@ -704,7 +739,9 @@ async fn test_assoc_reliable_short_buffer() -> Result<()> {
         assert_eq!(0, a.buffered_amount(), "incorrect bufferedAmount");
     }
 
-    let n = s0.write_sctp(&MSG, PayloadProtocolIdentifier::Binary)?;
+    let n = s0
+        .write_sctp(&MSG, PayloadProtocolIdentifier::Binary)
+        .await?;
     assert_eq!(MSG.len(), n, "unexpected length of received data");
     {
         let a = a0.association_internal.lock().await;
@@ -779,17 +816,21 @@ async fn test_assoc_unreliable_rexmit_ordered_no_fragment() -> Result<()> {
     br.drop_next_nwrites(0, 1); // drop the first packet (second one should be sacked)
 
     sbuf[0..4].copy_from_slice(&0u32.to_be_bytes());
-    let n = s0.write_sctp(
-        &Bytes::from(sbuf.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbuf.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbuf.len(), n, "unexpected length of received data");
 
     sbuf[0..4].copy_from_slice(&1u32.to_be_bytes());
-    let n = s0.write_sctp(
-        &Bytes::from(sbuf.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbuf.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbuf.len(), n, "unexpected length of received data");
 
     log::debug!("flush_buffers");
@@ -865,17 +906,21 @@ async fn test_assoc_unreliable_rexmit_ordered_fragment() -> Result<()> {
     br.drop_next_nwrites(0, 1); // drop the first packet (second one should be sacked)
 
     sbuf[0..4].copy_from_slice(&0u32.to_be_bytes());
-    let n = s0.write_sctp(
-        &Bytes::from(sbuf.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbuf.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbuf.len(), n, "unexpected length of received data");
 
     sbuf[0..4].copy_from_slice(&1u32.to_be_bytes());
-    let n = s0.write_sctp(
-        &Bytes::from(sbuf.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbuf.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbuf.len(), n, "unexpected length of received data");
 
     //log::debug!("flush_buffers");
@@ -946,17 +991,21 @@ async fn test_assoc_unreliable_rexmit_unordered_no_fragment() -> Result<()> {
     br.drop_next_nwrites(0, 1); // drop the first packet (second one should be sacked)
 
     sbuf[0..4].copy_from_slice(&0u32.to_be_bytes());
-    let n = s0.write_sctp(
-        &Bytes::from(sbuf.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbuf.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbuf.len(), n, "unexpected length of received data");
 
     sbuf[0..4].copy_from_slice(&1u32.to_be_bytes());
-    let n = s0.write_sctp(
-        &Bytes::from(sbuf.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbuf.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbuf.len(), n, "unexpected length of received data");
 
     //log::debug!("flush_buffers");
@@ -1028,17 +1077,21 @@ async fn test_assoc_unreliable_rexmit_unordered_fragment() -> Result<()> {
     s1.set_reliability_params(true, ReliabilityType::Rexmit, 0); // doesn't matter
 
     sbuf[0..4].copy_from_slice(&0u32.to_be_bytes());
-    let n = s0.write_sctp(
-        &Bytes::from(sbuf.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbuf.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbuf.len(), n, "unexpected length of received data");
 
     sbuf[0..4].copy_from_slice(&1u32.to_be_bytes());
-    let n = s0.write_sctp(
-        &Bytes::from(sbuf.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbuf.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbuf.len(), n, "unexpected length of received data");
 
     //log::debug!("flush_buffers");
@@ -1121,17 +1174,21 @@ async fn test_assoc_unreliable_rexmit_timed_ordered() -> Result<()> {
     br.drop_next_nwrites(0, 1); // drop the first packet (second one should be sacked)
 
     sbuf[0..4].copy_from_slice(&0u32.to_be_bytes());
-    let n = s0.write_sctp(
-        &Bytes::from(sbuf.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbuf.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbuf.len(), n, "unexpected length of received data");
 
     sbuf[0..4].copy_from_slice(&1u32.to_be_bytes());
-    let n = s0.write_sctp(
-        &Bytes::from(sbuf.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbuf.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbuf.len(), n, "unexpected length of received data");
 
     //log::debug!("flush_buffers");
@@ -1202,17 +1259,21 @@ async fn test_assoc_unreliable_rexmit_timed_unordered() -> Result<()> {
     br.drop_next_nwrites(0, 1); // drop the first packet (second one should be sacked)
 
     sbuf[0..4].copy_from_slice(&0u32.to_be_bytes());
-    let n = s0.write_sctp(
-        &Bytes::from(sbuf.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbuf.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbuf.len(), n, "unexpected length of received data");
 
     sbuf[0..4].copy_from_slice(&1u32.to_be_bytes());
-    let n = s0.write_sctp(
-        &Bytes::from(sbuf.clone()),
-        PayloadProtocolIdentifier::Binary,
-    )?;
+    let n = s0
+        .write_sctp(
+            &Bytes::from(sbuf.clone()),
+            PayloadProtocolIdentifier::Binary,
+        )
+        .await?;
     assert_eq!(sbuf.len(), n, "unexpected length of received data");
 
     //log::debug!("flush_buffers");
@@ -1297,10 +1358,12 @@ async fn test_assoc_congestion_control_fast_retransmission() -> Result<()> {
 
     for i in 0..4u32 {
         sbuf[0..4].copy_from_slice(&i.to_be_bytes());
-        let n = s0.write_sctp(
-            &Bytes::from(sbuf.clone()),
-            PayloadProtocolIdentifier::Binary,
-        )?;
+        let n = s0
+            .write_sctp(
+                &Bytes::from(sbuf.clone()),
+                PayloadProtocolIdentifier::Binary,
+            )
+            .await?;
         assert_eq!(sbuf.len(), n, "unexpected length of received data");
     }
 
@@ -1400,10 +1463,12 @@ async fn test_assoc_congestion_control_congestion_avoidance() -> Result<()> {
 
     for i in 0..N_PACKETS_TO_SEND {
         sbuf[0..4].copy_from_slice(&i.to_be_bytes());
-        let n = s0.write_sctp(
-            &Bytes::from(sbuf.clone()),
-            PayloadProtocolIdentifier::Binary,
-        )?;
+        let n = s0
+            .write_sctp(
+                &Bytes::from(sbuf.clone()),
+                PayloadProtocolIdentifier::Binary,
+            )
+            .await?;
         assert_eq!(sbuf.len(), n, "unexpected length of received data");
     }
 
@@ -1533,10 +1598,12 @@ async fn test_assoc_congestion_control_slow_reader() -> Result<()> {
 
     for i in 0..N_PACKETS_TO_SEND {
         sbuf[0..4].copy_from_slice(&i.to_be_bytes());
-        let n = s0.write_sctp(
-            &Bytes::from(sbuf.clone()),
-            PayloadProtocolIdentifier::Binary,
-        )?;
+        let n = s0
+            .write_sctp(
+                &Bytes::from(sbuf.clone()),
+                PayloadProtocolIdentifier::Binary,
+            )
+            .await?;
         assert_eq!(sbuf.len(), n, "unexpected length of received data");
     }
 
@@ -1768,7 +1835,9 @@ async fn test_assoc_reset_close_one_way() -> Result<()> {
         assert_eq!(0, a.buffered_amount(), "incorrect bufferedAmount");
     }
 
-    let n = s0.write_sctp(&MSG, PayloadProtocolIdentifier::Binary)?;
+    let n = s0
+        .write_sctp(&MSG, PayloadProtocolIdentifier::Binary)
+        .await?;
     assert_eq!(MSG.len(), n, "unexpected length of received data");
     {
         let a = a0.association_internal.lock().await;
@@ -1866,7 +1935,9 @@ async fn test_assoc_reset_close_both_ways() -> Result<()> {
         assert_eq!(0, a.buffered_amount(), "incorrect bufferedAmount");
     }
 
-    let n = s0.write_sctp(&MSG, PayloadProtocolIdentifier::Binary)?;
+    let n = s0
+        .write_sctp(&MSG, PayloadProtocolIdentifier::Binary)
+        .await?;
     assert_eq!(MSG.len(), n, "unexpected length of received data");
     {
         let a = a0.association_internal.lock().await;
@@ -2037,12 +2108,212 @@ async fn test_assoc_abort() -> Result<()> {
     // The receiving association should be closed because it got an ABORT
     assert_eq!(AssociationState::Established, a0.get_state());
     assert_eq!(AssociationState::Closed, a1.get_state());
+    assert_eq!(None, a0.close_reason().await);
+    assert_eq!(
+        Some(CloseReason::PeerAbort {
+            cause: Some(PROTOCOL_VIOLATION.0)
+        }),
+        a1.close_reason().await,
+        "the ABORT must be reported with its error cause"
+    );
 
     close_association_pair(&br, a0, a1).await;
 
     Ok(())
 }
 
+#[tokio::test]
+async fn test_assoc_user_initiated_abort() -> Result<()> {
+    const SI: u16 = 1;
+    let (br, ca, cb) = Bridge::new(0, None, None);
+
+    let (a0, mut a1) =
+        create_new_association_pair(&br, Arc::new(ca), Arc::new(cb), AckMode::NoDelay, 0).await?;
+
+    let (_s0, _s1) = establish_session_pair(&br, &a0, &mut a1, SI).await?;
+
+    a0.abort("bye").await?;
+
+    flush_buffers(&br, &a0, &a1).await;
+
+    // There is a little delay before changing the state to closed
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    assert_eq!(AssociationState::Closed, a0.get_state());
+    assert_eq!(AssociationState::Closed, a1.get_state());
+    assert_eq!(Some(CloseReason::Local), a0.close_reason().await);
+    assert_eq!(
+        Some(CloseReason::PeerAbort {
+            cause: Some(USER_INITIATED_ABORT.0)
+        }),
+        a1.close_reason().await,
+        "the peer must see a user initiated abort"
+    );
+    assert!(
+        a1.accept_stream().await.is_none(),
+        "accept_stream must end once the association is closed"
+    );
+
+    close_association_pair(&br, a0, a1).await;
+
+    Ok(())
+}
+
+async fn create_simultaneous_open_pair(
+    ca: Arc<dyn Conn + Send + Sync>,
+    cb: Arc<dyn Conn + Send + Sync>,
+) -> Result<(Association, Association)> {
+    let new_client = |net_conn, name: &str| {
+        Association::client(Config {
+            net_conn,
+            max_receive_buffer_size: 0,
+            max_message_size: 0,
+            name: name.to_owned(),
+            max_association_buffered_amount: 0,
+            buffer_full_policy: Default::default(),
+            max_retransmissions: 0,
+            num_outbound_streams: 0,
+            num_inbound_streams: 0,
+            clock: None,
+        })
+    };
+
+    // Both ends send an INIT before either has seen the INIT of the other
+    let a0 = tokio::spawn(new_client(ca, "a0"));
+    let a1 = tokio::spawn(new_client(cb, "a1"));
+
+    // The collision must resolve before the first INIT retransmission
+    let timeout = Duration::from_millis(RTO_INITIAL);
+    let a0 = tokio::time::timeout(timeout, a0).await;
+    let a1 = tokio::time::timeout(timeout, a1).await;
+    match (a0, a1) {
+        (Ok(Ok(a0)), Ok(Ok(a1))) => Ok((a0?, a1?)),
+        _ => Err(Error::Other("simultaneous open timed out".to_owned())),
+    }
+}
+
+#[tokio::test]
+async fn test_assoc_simultaneous_open() -> Result<()> {
+    const SI: u16 = 1;
+    let (ca, cb) = pipe();
+
+    let (a0, a1) = create_simultaneous_open_pair(Arc::new(ca), Arc::new(cb)).await?;
+
+    // Both ends settled on the verification tag the other one sent in its INIT
+    {
+        let ai0 = a0.association_internal.lock().await;
+        let ai1 = a1.association_internal.lock().await;
+        assert_eq!(ai0.peer_verification_tag, ai1.my_verification_tag);
+        assert_eq!(ai1.peer_verification_tag, ai0.my_verification_tag);
+    }
+    assert_eq!(AssociationState::Established, a0.get_state());
+    assert_eq!(AssociationState::Established, a1.get_state());
+
+    let s0 = a0
+        .open_stream(SI, PayloadProtocolIdentifier::Binary)
+        .await?;
+    let s1 = a1
+        .open_stream(SI, PayloadProtocolIdentifier::Binary)
+        .await?;
+
+    let test_data = Bytes::from_static(b"simultaneous");
+    s0.write(&test_data).await?;
+    let mut buf = vec![0u8; 32];
+    let n = s1.read(&mut buf).await?;
+    assert_eq!(&test_data, &buf[..n]);
+
+    s1.write(&test_data).await?;
+    let n = s0.read(&mut buf).await?;
+    assert_eq!(&test_data, &buf[..n]);
+
+    a0.close().await?;
+    a1.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_assoc_simultaneous_open_duplicate_init() -> Result<()> {
+    let (ca, cb) = pipe();
+
+    let (a0, a1) = create_simultaneous_open_pair(Arc::new(ca), Arc::new(cb)).await?;
+
+    // A retransmission of the INIT of a0 reaching a1 after the handshake completed
+    let packet = {
+        let ai0 = a0.association_internal.lock().await;
+        let mut init = ChunkInit {
+            initial_tsn: ai0.my_next_tsn,
+            num_outbound_streams: u16::MAX,
+            num_inbound_streams: u16::MAX,
+            initiate_tag: ai0.my_verification_tag,
+            advertised_receiver_window_credit: ai0.max_receive_buffer_size,
+            ..Default::default()
+        };
+        init.set_supported_extensions();
+        Packet {
+            source_port: 5000,
+            destination_port: 5000,
+            verification_tag: 0,
+            chunks: vec![Box::new(init)],
+        }
+        .marshal()?
+    };
+
+    {
+        let mut ai1 = a1.association_internal.lock().await;
+        ai1.handle_inbound(&packet).await?;
+    }
+    assert_eq!(AssociationState::Established, a1.get_state());
+
+    a0.close().await?;
+    a1.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_assoc_abort_during_handshake() -> Result<()> {
+    let (ca, cb) = pipe();
+
+    let client = tokio::spawn(Association::client(Config {
+        net_conn: Arc::new(ca),
+        max_receive_buffer_size: 0,
+        max_message_size: 0,
+        name: "client".to_owned(),
+        max_association_buffered_amount: 0,
+        buffer_full_policy: Default::default(),
+        max_retransmissions: 0,
+        num_outbound_streams: 0,
+        num_inbound_streams: 0,
+        clock: None,
+    }));
+
+    // Answer the INIT with an ABORT
+    let mut buf = vec![0u8; RECEIVE_MTU];
+    cb.recv(&mut buf).await.unwrap();
+    let abort = Packet {
+        source_port: 5000,
+        destination_port: 5000,
+        verification_tag: 0,
+        chunks: vec![Box::new(ChunkAbort::default())],
+    }
+    .marshal()?;
+    cb.send(&abort).await.unwrap();
+
+    match tokio::time::timeout(Duration::from_millis(RTO_INITIAL), client).await {
+        Ok(Ok(result)) => assert_eq!(
+            Some(Error::ErrAssociationClosed(CloseReason::PeerAbort {
+                cause: None
+            })),
+            result.err(),
+            "handshake must fail once the peer aborted it"
+        ),
+        _ => panic!("handshake didn't fail after an ABORT"),
+    }
+
+    Ok(())
+}
+
 struct FakeEchoConn {
     wr_tx: Mutex<mpsc::Sender<Vec<u8>>>,
     rd_rx: Mutex<mpsc::Receiver<Vec<u8>>>,
@@ -2150,6 +2421,12 @@ async fn test_stats() -> Result<()> {
         max_receive_buffer_size: 0,
         max_message_size: 0,
         name: "client".to_owned(),
+        max_association_buffered_amount: 0,
+        buffer_full_policy: Default::default(),
+        max_retransmissions: 0,
+        num_outbound_streams: 0,
+        num_inbound_streams: 0,
+        clock: None,
     })
     .await?;
 
@@ -2167,6 +2444,16 @@ async fn test_stats() -> Result<()> {
 }
 
 async fn create_assocs() -> Result<(Association, Association)> {
+    create_assocs_with_buffer_limit(0, BufferFullPolicy::Reject, 0).await
+}
+
+/// create_assocs_with_buffer_limit creates a client limited to `max_association_buffered_amount`
+/// and a server with a receive buffer of `server_recv_buf_size`.
+async fn create_assocs_with_buffer_limit(
+    max_association_buffered_amount: usize,
+    buffer_full_policy: BufferFullPolicy,
+    server_recv_buf_size: u32,
+) -> Result<(Association, Association)> {
     let addr1 = SocketAddr::from_str("0.0.0.0:0").unwrap();
     let addr2 = SocketAddr::from_str("0.0.0.0:0").unwrap();
 
@@ -2185,6 +2472,12 @@ async fn create_assocs() -> Result<(Association, Association)> {
             max_receive_buffer_size: 0,
             max_message_size: 0,
             name: "client".to_owned(),
+            max_association_buffered_amount,
+            buffer_full_policy,
+            max_retransmissions: 0,
+            num_outbound_streams: 0,
+            num_inbound_streams: 0,
+            clock: None,
         })
         .await?;
 
@@ -2196,9 +2489,15 @@ async fn create_assocs() -> Result<(Association, Association)> {
     tokio::spawn(async move {
         let a = Association::server(Config {
             net_conn: Arc::new(udp2),
-            max_receive_buffer_size: 0,
+            max_receive_buffer_size: server_recv_buf_size,
             max_message_size: 0,
             name: "server".to_owned(),
+            max_association_buffered_amount: 0,
+            buffer_full_policy: Default::default(),
+            max_retransmissions: 0,
+            num_outbound_streams: 0,
+            num_inbound_streams: 0,
+            clock: None,
         })
         .await?;
 
@@ -2261,7 +2560,7 @@ async fn test_association_shutdown() -> Result<()> {
 
     let test_data = Bytes::from_static(b"test");
 
-    let n = s11.write(&test_data)?;
+    let n = s11.write(&test_data).await?;
     assert_eq!(test_data.len(), n);
 
     let mut buf = vec![0u8; test_data.len()];
@@ -2322,7 +2621,7 @@ async fn test_association_shutdown_during_write() -> Result<()> {
     let ss21 = Arc::clone(&s21);
     tokio::spawn(async move {
         let mut i = 0;
-        while ss21.write(&Bytes::from(vec![i])).is_ok() {
+        while ss21.write(&Bytes::from(vec![i])).await.is_ok() {
             if i == 255 {
                 i = 0;
             } else {
@@ -2339,7 +2638,7 @@ async fn test_association_shutdown_during_write() -> Result<()> {
 
     let test_data = Bytes::from_static(b"test");
 
-    let n = s11.write(&test_data)?;
+    let n = s11.write(&test_data).await?;
     assert_eq!(test_data.len(), n);
 
     let mut buf = vec![0u8; test_data.len()];
@@ -2383,6 +2682,175 @@ async fn test_association_shutdown_during_write() -> Result<()> {
     Ok(())
 }
 
+const BUFFER_LIMIT_NUM_STREAMS: u16 = 10;
+const BUFFER_LIMIT_MESSAGE_SIZE: usize = 1000;
+const BUFFER_LIMIT: usize = 20 * BUFFER_LIMIT_MESSAGE_SIZE;
+
+/// open_buffer_limit_streams opens the same streams on both associations, so the server
+/// doesn't need to accept them.
+async fn open_buffer_limit_streams(
+    a1: &Association,
+    a2: &Association,
+) -> Result<(Vec<Arc<Stream>>, Vec<Arc<Stream>>)> {
+    let mut client_streams = vec![];
+    let mut server_streams = vec![];
+    for si in 1..=BUFFER_LIMIT_NUM_STREAMS {
+        client_streams.push(
+            a1.open_stream(si, PayloadProtocolIdentifier::Binary)
+                .await?,
+        );
+        server_streams.push(
+            a2.open_stream(si, PayloadProtocolIdentifier::Binary)
+                .await?,
+        );
+    }
+    Ok((client_streams, server_streams))
+}
+
+/// fill_association_buffer writes to the streams in turn until the association buffer
+/// limit rejects or holds back a write, and waits until data stops being acknowledged.
+async fn fill_association_buffer(a: &Association, streams: &[Arc<Stream>]) {
+    let data = Bytes::from(vec![0u8; BUFFER_LIMIT_MESSAGE_SIZE]);
+    loop {
+        let mut i = 0;
+        loop {
+            let write = streams[i % streams.len()].write(&data);
+            match tokio::time::timeout(Duration::from_millis(100), write).await {
+                Ok(Ok(_)) => i += 1,
+                Ok(Err(err)) => {
+                    assert_eq!(Error::ErrAssociationBufferFull, err);
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+
+        // Stop once the peer's receive window is closed and no more data is acknowledged
+        let buffered_amount = a.buffered_amount();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        if i == 0 && buffered_amount == a.buffered_amount() {
+            break;
+        }
+    }
+}
+
+/// start_slow_readers reads from every stream with a pause between reads.
+fn start_slow_readers(streams: &[Arc<Stream>]) {
+    for s in streams {
+        let s = Arc::clone(s);
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; BUFFER_LIMIT_MESSAGE_SIZE];
+            while let Ok(n) = s.read(&mut buf).await {
+                if n == 0 {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        });
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+#[tokio::test]
+async fn test_association_buffer_limit_reject() -> Result<()> {
+    let (a1, a2) = create_assocs_with_buffer_limit(
+        BUFFER_LIMIT,
+        BufferFullPolicy::Reject,
+        4 * BUFFER_LIMIT_MESSAGE_SIZE as u32,
+    )
+    .await?;
+    let (client_streams, server_streams) = open_buffer_limit_streams(&a1, &a2).await?;
+
+    fill_association_buffer(&a1, &client_streams).await;
+
+    let buffered_amount = a1.buffered_amount();
+    assert!(buffered_amount > 0);
+    assert!(buffered_amount <= BUFFER_LIMIT);
+    assert_eq!(
+        buffered_amount,
+        client_streams.iter().map(|s| s.buffered_amount()).sum()
+    );
+
+    // The limit is shared, every stream is rejected regardless of its own buffered amount
+    let data = Bytes::from(vec![0u8; BUFFER_LIMIT_MESSAGE_SIZE]);
+    for s in &client_streams {
+        assert_eq!(Err(Error::ErrAssociationBufferFull), s.write(&data).await);
+    }
+
+    let (low_tx, mut low_rx) = mpsc::channel::<()>(1);
+    a1.set_buffered_amount_low_threshold(BUFFER_LIMIT / 2);
+    a1.on_buffered_amount_low(Box::new(move || {
+        let low_tx = low_tx.clone();
+        Box::pin(async move {
+            let _ = low_tx.try_send(());
+        })
+    }));
+
+    start_slow_readers(&server_streams);
+
+    let result = tokio::time::timeout(Duration::from_secs(10), low_rx.recv()).await;
+    assert!(result.is_ok(), "association buffered amount low not called");
+    assert!(a1.buffered_amount() <= BUFFER_LIMIT / 2);
+
+    for s in &client_streams {
+        assert_eq!(Ok(BUFFER_LIMIT_MESSAGE_SIZE), s.write(&data).await);
+    }
+
+    a1.close().await?;
+    a2.close().await?;
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+#[tokio::test]
+async fn test_association_buffer_limit_block() -> Result<()> {
+    let (a1, a2) = create_assocs_with_buffer_limit(
+        BUFFER_LIMIT,
+        BufferFullPolicy::Block,
+        4 * BUFFER_LIMIT_MESSAGE_SIZE as u32,
+    )
+    .await?;
+    let (client_streams, server_streams) = open_buffer_limit_streams(&a1, &a2).await?;
+
+    fill_association_buffer(&a1, &client_streams).await;
+
+    // Stream::write and StreamIo wait for room instead of failing
+    let data = vec![0u8; BUFFER_LIMIT_MESSAGE_SIZE];
+    let bytes = Bytes::from(data.clone());
+    let result =
+        tokio::time::timeout(Duration::from_millis(200), client_streams[1].write(&bytes)).await;
+    assert!(
+        result.is_err(),
+        "Stream::write should wait for the association buffer"
+    );
+    let mut stream_io = StreamIo::new(Arc::clone(&client_streams[0]));
+    let result = tokio::time::timeout(Duration::from_millis(200), stream_io.write(&data)).await;
+    assert!(
+        result.is_err(),
+        "write should wait for the association buffer"
+    );
+
+    start_slow_readers(&server_streams);
+
+    let result = tokio::time::timeout(Duration::from_secs(10), stream_io.write(&data)).await;
+    assert!(
+        matches!(result, Ok(Ok(BUFFER_LIMIT_MESSAGE_SIZE))),
+        "write should complete once the association buffer drained"
+    );
+    let result =
+        tokio::time::timeout(Duration::from_secs(10), client_streams[1].write(&bytes)).await;
+    assert!(
+        matches!(result, Ok(Ok(BUFFER_LIMIT_MESSAGE_SIZE))),
+        "Stream::write should complete once the association buffer drained"
+    );
+
+    a1.close().await?;
+    a2.close().await?;
+
+    Ok(())
+}
+
 //use std::io::Write;
 
 #[tokio::test]
@@ -2545,6 +3013,12 @@ async fn test_association_handle_packet_before_init() -> Result<()> {
                 max_message_size: 0,
                 max_receive_buffer_size: 0,
                 name: "client".to_owned(),
+                max_association_buffered_amount: 0,
+                buffer_full_policy: Default::default(),
+                max_retransmissions: 0,
+                num_outbound_streams: 0,
+                num_inbound_streams: 0,
+                clock: None,
             },
             true,
         )
@@ -2563,3 +3037,429 @@ async fn test_association_handle_packet_before_init() -> Result<()> {
 
     Ok(())
 }
+
+/// read_flow reads the messages of a stream up to EOF.
+async fn read_flow(s: &Stream) -> Result<Vec<Bytes>> {
+    let mut msgs = vec![];
+    let mut buf = vec![0u8; 1024];
+    loop {
+        match tokio::time::timeout(Duration::from_secs(5), s.read_sctp(&mut buf)).await {
+            Ok(Ok((0, PayloadProtocolIdentifier::Unknown))) => return Ok(msgs),
+            Ok(Ok((n, _))) => msgs.push(Bytes::copy_from_slice(&buf[..n])),
+            Ok(Err(err)) => return Err(err),
+            Err(_) => return Err(Error::Other("timed out reading the stream".to_owned())),
+        }
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+#[tokio::test]
+async fn test_assoc_reset_sequence() -> Result<()> {
+    const SI: u16 = 1;
+
+    let (a1, a2) = create_assocs().await?;
+
+    let s11 = a1
+        .open_stream(SI, PayloadProtocolIdentifier::Binary)
+        .await?;
+    s11.write(&Bytes::from_static(b"old-0")).await?;
+    s11.write(&Bytes::from_static(b"old-1")).await?;
+    s11.reset_sequence()?;
+    // Held back until the peer performed the reset
+    s11.write(&Bytes::from_static(b"new-0")).await?;
+
+    let s21 = a2.accept_stream().await.unwrap();
+    assert_eq!(
+        read_flow(&s21).await?,
+        vec![Bytes::from_static(b"old-0"), Bytes::from_static(b"old-1")]
+    );
+    assert!(s21.is_incoming_reset());
+
+    s11.write(&Bytes::from_static(b"new-1")).await?;
+    s11.shutdown(Shutdown::Both).await?;
+    assert_eq!(
+        read_flow(&s21).await?,
+        vec![Bytes::from_static(b"new-0"), Bytes::from_static(b"new-1")]
+    );
+
+    a1.close().await?;
+    a2.close().await?;
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+#[tokio::test]
+async fn test_assoc_reopen_closing_stream() -> Result<()> {
+    const SI: u16 = 50;
+    const ROUNDS: usize = 50;
+    const MSGS: usize = 5;
+
+    let (a1, a2) = create_assocs().await?;
+    let a2 = Arc::new(a2);
+
+    let server = {
+        let a2 = Arc::clone(&a2);
+        tokio::spawn(async move {
+            for round in 0..ROUNDS {
+                let s = tokio::time::timeout(Duration::from_secs(5), a2.accept_stream())
+                    .await
+                    .map_err(|_| Error::Other(format!("timed out accepting round {}", round)))?
+                    .unwrap();
+                assert_eq!(s.stream_identifier(), SI);
+
+                let msgs = read_flow(&s).await?;
+                assert_eq!(msgs.len(), MSGS, "round {}", round);
+                for msg in msgs {
+                    assert!(
+                        msg.starts_with(format!("{}:", round).as_bytes()),
+                        "round {} got {:?}",
+                        round,
+                        msg
+                    );
+                }
+
+                s.shutdown(Shutdown::Both).await?;
+            }
+            Result::<()>::Ok(())
+        })
+    };
+
+    for round in 0..ROUNDS {
+        // Reopened right after it was closed, before its resets completed
+        let s = a1
+            .open_stream(SI, PayloadProtocolIdentifier::Binary)
+            .await?;
+        for i in 0..MSGS {
+            s.write(&Bytes::from(format!("{}:{}", round, i))).await?;
+        }
+        s.shutdown(Shutdown::Both).await?;
+    }
+
+    server.await.unwrap()?;
+
+    a1.close().await?;
+    a2.close().await?;
+
+    Ok(())
+}
+
+/// create_lossy_vnet_assocs connects a client and a server over a virtual network whose router
+/// delays packets by delay and, once loss_enabled is set, drops loss_percent of them. The
+/// losses come from a seeded generator so runs are reproducible.
+async fn create_lossy_vnet_assocs(
+    delay: Duration,
+    loss_percent: u64,
+    loss_enabled: Arc<AtomicBool>,
+) -> Result<(Association, Association, Arc<Mutex<vnet::router::Router>>)> {
+    let vnet_err = |err: util::Error| Error::Other(err.to_string());
+
+    let wan = Arc::new(Mutex::new(
+        vnet::router::Router::new(vnet::router::RouterConfig {
+            cidr: "1.2.3.0/24".to_owned(),
+            min_delay: delay,
+            ..Default::default()
+        })
+        .map_err(vnet_err)?,
+    ));
+
+    let mut conns = vec![];
+    for ip in ["1.2.3.4", "1.2.3.5"].iter() {
+        let net = Arc::new(vnet::net::Net::new(Some(vnet::net::NetConfig {
+            static_ips: vec![(*ip).to_owned()],
+            ..Default::default()
+        })));
+        let nic = net.get_nic().map_err(vnet_err)?;
+        wan.lock()
+            .await
+            .add_net(Arc::clone(&nic))
+            .await
+            .map_err(vnet_err)?;
+        nic.lock()
+            .await
+            .set_router(Arc::clone(&wan))
+            .await
+            .map_err(vnet_err)?;
+        conns.push(
+            net.bind(SocketAddr::from_str(&format!("{}:5000", ip)).unwrap())
+                .await
+                .map_err(vnet_err)?,
+        );
+    }
+    conns[0]
+        .connect(SocketAddr::from_str("1.2.3.5:5000").unwrap())
+        .await
+        .map_err(vnet_err)?;
+    conns[1]
+        .connect(SocketAddr::from_str("1.2.3.4:5000").unwrap())
+        .await
+        .map_err(vnet_err)?;
+
+    {
+        let seed = AtomicU64::new(0x2545_f491_4f6c_dd1d);
+        let w = wan.lock().await;
+        w.add_chunk_filter(Box::new(
+            move |_c: &(dyn vnet::chunk::Chunk + Send + Sync)| {
+                if !loss_enabled.load(Ordering::SeqCst) {
+                    return true;
+                }
+                // xorshift64
+                let mut x = seed.load(Ordering::SeqCst);
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                seed.store(x, Ordering::SeqCst);
+                x % 100 >= loss_percent
+            },
+        ))
+        .await;
+    }
+    wan.lock().await.start().await.map_err(vnet_err)?;
+
+    let config = |net_conn: Arc<dyn Conn + Send + Sync>, name: &str| Config {
+        net_conn,
+        max_receive_buffer_size: 0,
+        max_message_size: 0,
+        name: name.to_owned(),
+        max_association_buffered_amount: 0,
+        buffer_full_policy: Default::default(),
+        max_retransmissions: 0,
+        num_outbound_streams: 0,
+        num_inbound_streams: 0,
+        clock: None,
+    };
+    let server_conn = conns.pop().unwrap();
+    let client_conn = conns.pop().unwrap();
+    let (a1, a2) = tokio::try_join!(
+        Association::client(config(client_conn, "client")),
+        Association::server(config(server_conn, "server")),
+    )?;
+
+    Ok((a1, a2, wan))
+}
+
+#[tokio::test]
+async fn test_assoc_loss_recovery_on_lossy_link() -> Result<()> {
+    const SI: u16 = 1;
+    const MSG_SIZE: usize = 1000;
+    const N_MSGS: usize = 1000;
+
+    let loss_enabled = Arc::new(AtomicBool::new(false));
+    let (a1, a2, wan) =
+        create_lossy_vnet_assocs(Duration::from_millis(10), 1, Arc::clone(&loss_enabled)).await?;
+
+    let s1 = a1
+        .open_stream(SI, PayloadProtocolIdentifier::Binary)
+        .await?;
+    // The first message opens the stream on the server side
+    s1.write(&Bytes::from(vec![0u8; MSG_SIZE])).await?;
+    let s2 = tokio::time::timeout(Duration::from_secs(5), a2.accept_stream())
+        .await
+        .map_err(|_| Error::Other("timed out accepting the stream".to_owned()))?
+        .unwrap();
+
+    loss_enabled.store(true, Ordering::SeqCst);
+    for i in 1..N_MSGS {
+        s1.write(&Bytes::from(vec![i as u8; MSG_SIZE])).await?;
+    }
+
+    let mut buf = vec![0u8; MSG_SIZE];
+    for i in 0..N_MSGS {
+        let n = tokio::time::timeout(Duration::from_secs(30), s2.read(&mut buf))
+            .await
+            .map_err(|_| Error::Other(format!("timed out reading message {}", i)))??;
+        assert_eq!(MSG_SIZE, n);
+        assert!(buf.iter().all(|b| *b == i as u8), "message {} corrupted", i);
+    }
+
+    log::debug!(
+        "fast retransmissions: {}, fast recoveries: {}, timeout retransmissions: {}, T3 timeouts: {}",
+        a1.fast_retransmissions(),
+        a1.fast_recoveries(),
+        a1.timeout_retransmissions(),
+        a1.t3_timeouts()
+    );
+    // Most losses are recovered by fast retransmit rather than by the retransmission timer
+    assert!(
+        a1.fast_retransmissions() > 0,
+        "losses must be fast retransmitted"
+    );
+    assert!(a1.fast_recoveries() > 0);
+    assert!(a1.fast_recoveries() <= a1.fast_retransmissions());
+    assert!(
+        a1.t3_timeouts() < a1.fast_recoveries(),
+        "T3-rtx timeouts {} must be fewer than fast recoveries {}",
+        a1.t3_timeouts(),
+        a1.fast_recoveries()
+    );
+
+    a1.close().await?;
+    a2.close().await?;
+    wan.lock()
+        .await
+        .stop()
+        .await
+        .map_err(|err| Error::Other(err.to_string()))?;
+
+    Ok(())
+}
+
+async fn create_assocs_with_num_streams(
+    client_num_streams: (u16, u16),
+    server_num_streams: (u16, u16),
+) -> Result<(Association, Association)> {
+    let (ca, cb) = pipe();
+    let config =
+        |net_conn: Arc<dyn Conn + Send + Sync>, name: &str, num_streams: (u16, u16)| Config {
+            net_conn,
+            max_receive_buffer_size: 0,
+            max_message_size: 0,
+            name: name.to_owned(),
+            max_association_buffered_amount: 0,
+            buffer_full_policy: Default::default(),
+            max_retransmissions: 0,
+            num_outbound_streams: num_streams.0,
+            num_inbound_streams: num_streams.1,
+            clock: None,
+        };
+
+    Ok(tokio::try_join!(
+        Association::client(config(Arc::new(ca), "client", client_num_streams)),
+        Association::server(config(Arc::new(cb), "server", server_num_streams)),
+    )?)
+}
+
+#[tokio::test]
+async fn test_assoc_num_streams_negotiation() -> Result<()> {
+    let (a0, a1) = create_assocs_with_num_streams((4, 0), (0, 2)).await?;
+
+    // The outbound streams of each end are limited by the inbound streams of the other one
+    assert_eq!(2, a0.max_outbound_streams());
+    assert_eq!(u16::MAX, a0.max_inbound_streams());
+    assert_eq!(u16::MAX, a1.max_outbound_streams());
+    assert_eq!(2, a1.max_inbound_streams());
+
+    a0.open_stream(1, PayloadProtocolIdentifier::Binary).await?;
+    let result = a0.open_stream(2, PayloadProtocolIdentifier::Binary).await;
+    assert_eq!(Some(Error::ErrStreamIdentifierOutOfRange), result.err());
+
+    a0.close().await?;
+    a1.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_assoc_add_outbound_streams() -> Result<()> {
+    const SI: u16 = 9;
+    let (a0, a1) = create_assocs_with_num_streams((4, 4), (4, 4)).await?;
+
+    assert_eq!(4, a0.max_outbound_streams());
+    let result = a0.open_stream(SI, PayloadProtocolIdentifier::Binary).await;
+    assert_eq!(Some(Error::ErrStreamIdentifierOutOfRange), result.err());
+
+    a0.add_outbound_streams(6).await?;
+
+    // Both directions grew, so the server can answer on the new stream
+    assert_eq!(10, a0.max_outbound_streams());
+    assert_eq!(10, a0.max_inbound_streams());
+    assert_eq!(10, a1.max_outbound_streams());
+    assert_eq!(10, a1.max_inbound_streams());
+
+    let s0 = a0
+        .open_stream(SI, PayloadProtocolIdentifier::Binary)
+        .await?;
+    s0.write(&Bytes::from_static(b"ping")).await?;
+    let s1 = a1.accept_stream().await.unwrap();
+    assert_eq!(SI, s1.stream_identifier());
+
+    let mut buf = vec![0u8; 32];
+    let n = s1.read(&mut buf).await?;
+    assert_eq!(b"ping", &buf[..n]);
+    s1.write(&Bytes::from_static(b"pong")).await?;
+    let n = s0.read(&mut buf).await?;
+    assert_eq!(b"pong", &buf[..n]);
+
+    a0.close().await?;
+    a1.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_assoc_add_outbound_streams_refused() -> Result<()> {
+    let (a0, a1) = create_assocs_with_num_streams((0, 0), (0, 0)).await?;
+
+    // The server can't have more than 65535 inbound streams
+    let result = a0.add_outbound_streams(1).await;
+    assert_eq!(Some(Error::ErrAddStreamsRefused), result.err());
+    assert_eq!(u16::MAX, a0.max_outbound_streams());
+    assert_eq!(u16::MAX, a1.max_inbound_streams());
+
+    a0.close().await?;
+    a1.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_assoc_handshake_timeout_with_manual_clock() -> Result<()> {
+    use crate::clock::ManualClock;
+
+    // The bridge is never ticked, so the INIT goes unanswered
+    let (_br, ca, _cb) = Bridge::new(0, None, None);
+    let clock = Arc::new(ManualClock::new());
+
+    let client = tokio::spawn(Association::client(Config {
+        net_conn: Arc::new(ca),
+        max_receive_buffer_size: 0,
+        max_message_size: 0,
+        name: "client".to_owned(),
+        max_association_buffered_amount: 0,
+        buffer_full_policy: Default::default(),
+        max_retransmissions: 2,
+        num_outbound_streams: 0,
+        num_inbound_streams: 0,
+        clock: Some(clock.clone()),
+    }));
+
+    // RTO(msec) Total(msec)
+    //  3000        3000    1st RTO
+    //  6000        9000    2nd RTO (== max_retransmissions)
+    // 12000       21000    Failure
+    let advance = |millis: u64| {
+        let clock = Arc::clone(&clock);
+        async move {
+            for _ in 0..millis / 100 {
+                clock.advance(Duration::from_millis(100));
+                for _ in 0..10 {
+                    tokio::task::yield_now().await;
+                }
+            }
+        }
+    };
+
+    // Let the client send its INIT before time moves
+    for _ in 0..10 {
+        tokio::task::yield_now().await;
+    }
+
+    advance(20900).await;
+    assert!(
+        !client.is_finished(),
+        "the handshake must not time out early"
+    );
+
+    advance(100).await;
+    let result = tokio::time::timeout(Duration::from_secs(1), client)
+        .await
+        .expect("the handshake must time out at 21 sec")
+        .unwrap();
+    assert_eq!(
+        result.err(),
+        Some(Error::ErrAssociationClosed(CloseReason::HandshakeTimeout))
+    );
+    assert_eq!(Duration::from_millis(21000), clock.elapsed());
+
+    Ok(())
+}
@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod association_test;
 
+pub(crate) mod association_buffer;
 mod association_internal;
 mod association_stats;
 
@@ -20,9 +21,11 @@ use crate::chunk::chunk_shutdown_ack::ChunkShutdownAck;
 use crate::chunk::chunk_shutdown_complete::ChunkShutdownComplete;
 use crate::chunk::chunk_type::*;
 use crate::chunk::Chunk;
+use crate::clock::{Clock, SharedClock};
 use crate::error::{Error, Result};
 use crate::error_cause::*;
 use crate::packet::Packet;
+use crate::param::param_add_streams_request::ParamAddStreamsRequest;
 use crate::param::param_heartbeat_info::ParamHeartbeatInfo;
 use crate::param::param_outgoing_reset_request::ParamOutgoingResetRequest;
 use crate::param::param_reconfig_response::{ParamReconfigResponse, ReconfigResult};
@@ -37,6 +40,8 @@ use crate::timer::ack_timer::*;
 use crate::timer::rtx_timer::*;
 use crate::util::*;
 
+use association_buffer::AssociationBuffer;
+pub use association_buffer::BufferFullPolicy;
 use association_internal::*;
 use association_stats::*;
 
@@ -44,10 +49,11 @@ use bytes::Bytes;
 use rand::random;
 use std::collections::{HashMap, VecDeque};
 use std::fmt;
-use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, AtomicU8, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::SystemTime;
-use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio::sync::{broadcast, mpsc, oneshot, Mutex};
+use tokio::time::Duration;
+use util::sync::TaskGroup;
 use util::Conn;
 
 pub(crate) const RECEIVE_MTU: usize = 8192;
@@ -59,8 +65,20 @@ pub(crate) const COMMON_HEADER_SIZE: u32 = 12;
 pub(crate) const DATA_CHUNK_HEADER_SIZE: u32 = 16;
 pub(crate) const DEFAULT_MAX_MESSAGE_SIZE: u32 = 65536;
 
+/// SACKs reporting a DATA chunk missing before it's fast retransmitted (RFC 4960 sec 7.2.4)
+pub(crate) const FAST_RETRANSMIT_THRESHOLD: u32 = 3;
+/// SACKs reporting new gaps that each allow an extra MTU beyond cwnd (limited transmit, RFC 3042)
+pub(crate) const LIMITED_TRANSMIT_MAX_SACKS: u32 = 2;
+/// TSNs a received DATA chunk may be ahead of the cumulative TSN, as far as gap ack blocks
+/// reach. DATA chunks further ahead are outside the receive window and dropped.
+pub(crate) const RECEIVE_TSN_WINDOW: u64 = u16::MAX as u64;
+
 /// other constants
 pub(crate) const ACCEPT_CH_SIZE: usize = 16;
+/// results of the add streams requests of the peer kept to answer their retransmissions
+pub(crate) const PEER_ADD_STREAMS_RESULTS_KEPT: u32 = 16;
+/// max time closing an association waits for its read and write loops to stop
+pub(crate) const CLOSE_LOOPS_TIMEOUT: Duration = Duration::from_secs(1);
 
 /// association state enums
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -106,6 +124,46 @@ impl fmt::Display for AssociationState {
     }
 }
 
+/// CloseReason tells why an association was closed, see [`Association::close_reason`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CloseReason {
+    /// close or shutdown was called on this end.
+    Local,
+    /// The INIT or the COOKIE ECHO went unanswered.
+    HandshakeTimeout,
+    /// The peer sent an ABORT. `cause` is the code of its first error cause, if it had one.
+    PeerAbort { cause: Option<u16> },
+    /// Outstanding DATA was retransmitted more than `Config::max_retransmissions` times in a
+    /// row without being acknowledged.
+    MaxRetransmissionsExceeded,
+    /// The peer shut the association down gracefully.
+    ShutdownReceived,
+    /// A packet of the peer couldn't be handled.
+    ProtocolViolation,
+    /// Reading from or writing to the underlying connection failed.
+    ConnectionLost,
+}
+
+impl fmt::Display for CloseReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CloseReason::Local => write!(f, "closed locally"),
+            CloseReason::HandshakeTimeout => write!(f, "handshake timed out"),
+            CloseReason::PeerAbort { cause: Some(cause) } => {
+                write!(f, "aborted by peer: {}", ErrorCauseCode(cause))
+            }
+            CloseReason::PeerAbort { cause: None } => write!(f, "aborted by peer"),
+            CloseReason::MaxRetransmissionsExceeded => {
+                write!(f, "maximum retransmissions exceeded")
+            }
+            CloseReason::ShutdownReceived => write!(f, "shut down by peer"),
+            CloseReason::ProtocolViolation => write!(f, "protocol violation"),
+            CloseReason::ConnectionLost => write!(f, "connection lost"),
+        }
+    }
+}
+
 /// retransmission timer IDs
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub(crate) enum RtxTimerId {
@@ -191,6 +249,24 @@ pub struct Config {
     pub max_receive_buffer_size: u32,
     pub max_message_size: u32,
     pub name: String,
+    /// Limit of outgoing data buffered across all streams of the association, 0 means no
+    /// limit. Data stays buffered until the peer acknowledges it.
+    pub max_association_buffered_amount: usize,
+    /// What writes exceeding `max_association_buffered_amount` do.
+    pub buffer_full_policy: BufferFullPolicy,
+    /// Retransmissions in a row after which the association is given up: an unanswered INIT or
+    /// COOKIE ECHO closes it with `CloseReason::HandshakeTimeout`, unacknowledged DATA with
+    /// `CloseReason::MaxRetransmissionsExceeded`. 0 keeps the defaults of 8 handshake
+    /// retransmissions and retransmitting DATA forever.
+    pub max_retransmissions: usize,
+    /// Number of outbound streams advertised in the INIT, 0 means 65535. The peer may allow
+    /// less, see `Association::max_outbound_streams`.
+    pub num_outbound_streams: u16,
+    /// Number of inbound streams advertised in the INIT, 0 means 65535.
+    pub num_inbound_streams: u16,
+    /// Source of time of the retransmission and delayed ack timers and of round trip time
+    /// measurements. None uses the time of the tokio runtime, see [`crate::clock::TokioClock`].
+    pub clock: Option<Arc<dyn Clock>>,
 }
 
 ///Association represents an SCTP association
@@ -222,6 +298,14 @@ pub struct Association {
     net_conn: Arc<dyn Conn + Send + Sync>,
     bytes_received: Arc<AtomicUsize>,
     bytes_sent: Arc<AtomicUsize>,
+    association_buffer: Arc<AssociationBuffer>,
+    stats: Arc<AssociationStats>,
+    num_outbound_streams: Arc<AtomicU16>,
+    num_inbound_streams: Arc<AtomicU16>,
+    // add streams requests are sent one at a time
+    add_streams_mu: Mutex<()>,
+    // the read and write loops
+    tasks: TaskGroup,
 
     pub(crate) association_internal: Arc<Mutex<AssociationInternal>>,
 }
@@ -229,31 +313,30 @@ pub struct Association {
 impl Association {
     /// server accepts a SCTP stream over a conn
     pub async fn server(config: Config) -> Result<Self> {
-        let (a, mut handshake_completed_ch_rx) = Association::new(config, false).await?;
-
-        if let Some(err_opt) = handshake_completed_ch_rx.recv().await {
-            if let Some(err) = err_opt {
-                Err(err)
-            } else {
-                Ok(a)
-            }
-        } else {
-            Err(Error::ErrAssociationHandshakeClosed)
-        }
+        let (a, handshake_completed_ch_rx) = Association::new(config, false).await?;
+        a.wait_handshake(handshake_completed_ch_rx).await?;
+        Ok(a)
     }
 
     /// Client opens a SCTP stream over a conn
     pub async fn client(config: Config) -> Result<Self> {
-        let (a, mut handshake_completed_ch_rx) = Association::new(config, true).await?;
+        let (a, handshake_completed_ch_rx) = Association::new(config, true).await?;
+        a.wait_handshake(handshake_completed_ch_rx).await?;
+        Ok(a)
+    }
 
-        if let Some(err_opt) = handshake_completed_ch_rx.recv().await {
-            if let Some(err) = err_opt {
-                Err(err)
-            } else {
-                Ok(a)
-            }
-        } else {
-            Err(Error::ErrAssociationHandshakeClosed)
+    async fn wait_handshake(
+        &self,
+        mut handshake_completed_ch_rx: mpsc::Receiver<Option<Error>>,
+    ) -> Result<()> {
+        match handshake_completed_ch_rx.recv().await {
+            Some(None) => Ok(()),
+            Some(Some(err)) => Err(err),
+            // The association was closed before the handshake completed
+            None => match self.close_reason().await {
+                Some(CloseReason::Local) | None => Err(Error::ErrAssociationHandshakeClosed),
+                Some(reason) => Err(Error::ErrAssociationClosed(reason)),
+            },
         }
     }
 
@@ -290,14 +373,67 @@ impl Association {
     pub async fn close(&self) -> Result<()> {
         log::debug!("[{}] closing association..", self.name);
 
+        // record the reason before the read loop notices the closed conn
+        self.association_internal
+            .lock()
+            .await
+            .set_close_reason(CloseReason::Local);
+
         let _ = self.net_conn.close().await;
 
-        let mut ai = self.association_internal.lock().await;
-        ai.close().await
+        let result = {
+            let mut ai = self.association_internal.lock().await;
+            ai.close().await
+        };
+
+        // the loops stop once the association is closed
+        let cancelled = self.tasks.close(CLOSE_LOOPS_TIMEOUT).await;
+        if cancelled > 0 {
+            log::debug!("[{}] cancelled {} loops on close", self.name, cancelled);
+        }
+
+        result
+    }
+
+    /// abort sends an ABORT with a User-Initiated Abort cause carrying `reason` to the peer and
+    /// closes the association, without waiting for outstanding data to be acknowledged.
+    pub async fn abort(&self, reason: &str) -> Result<()> {
+        log::debug!("[{}] aborting association: {}", self.name, reason);
+
+        let raw = {
+            let mut ai = self.association_internal.lock().await;
+            ai.set_close_reason(CloseReason::Local);
+            ai.create_packet(vec![Box::new(ChunkAbort {
+                error_causes: vec![ErrorCause {
+                    code: USER_INITIATED_ABORT,
+                    raw: Bytes::copy_from_slice(reason.as_bytes()),
+                }],
+            })])
+            .marshal()?
+        };
+        if let Err(err) = self.net_conn.send(&raw).await {
+            log::warn!("[{}] failed to send ABORT: {}", self.name, err);
+        }
+
+        self.close().await
+    }
+
+    /// close_reason returns why the association was closed, None while it is open. An
+    /// association failing its handshake returns the reason as `Error::ErrAssociationClosed`
+    /// instead.
+    pub async fn close_reason(&self) -> Option<CloseReason> {
+        let ai = self.association_internal.lock().await;
+        ai.close_reason
     }
 
     async fn new(config: Config, is_client: bool) -> Result<(Self, mpsc::Receiver<Option<Error>>)> {
         let net_conn = Arc::clone(&config.net_conn);
+        let max_retransmissions = config.max_retransmissions;
+        let max_init_retransmissions = if max_retransmissions == 0 {
+            MAX_INIT_RETRANS
+        } else {
+            max_retransmissions
+        };
 
         let (awake_write_loop_ch_tx, awake_write_loop_ch_rx) = mpsc::channel(1);
         let (accept_ch_tx, accept_ch_rx) = mpsc::channel(ACCEPT_CH_SIZE);
@@ -322,11 +458,15 @@ impl Association {
         let max_message_size = Arc::clone(&ai.max_message_size);
         let inflight_queue_length = Arc::clone(&ai.inflight_queue_length);
         let will_send_shutdown = Arc::clone(&ai.will_send_shutdown);
+        let association_buffer = Arc::clone(&ai.association_buffer);
+        let stats = Arc::clone(&ai.stats);
+        let num_outbound_streams = Arc::clone(&ai.my_max_num_outbound_streams);
+        let num_inbound_streams = Arc::clone(&ai.my_max_num_inbound_streams);
 
         let mut init = ChunkInit {
             initial_tsn: ai.my_next_tsn,
-            num_outbound_streams: ai.my_max_num_outbound_streams,
-            num_inbound_streams: ai.my_max_num_inbound_streams,
+            num_outbound_streams: num_outbound_streams.load(Ordering::SeqCst),
+            num_inbound_streams: num_inbound_streams.load(Ordering::SeqCst),
             initiate_tag: ai.my_verification_tag,
             advertised_receiver_window_credit: ai.max_receive_buffer_size,
             ..Default::default()
@@ -346,6 +486,7 @@ impl Association {
         let association_internal1 = Arc::clone(&association_internal);
         let association_internal2 = Arc::clone(&association_internal);
 
+        let tasks = TaskGroup::new();
         {
             let association_internal3 = Arc::clone(&association_internal);
 
@@ -353,35 +494,47 @@ impl Association {
             ai.t1init = Some(RtxTimer::new(
                 Arc::downgrade(&association_internal3),
                 RtxTimerId::T1Init,
-                MAX_INIT_RETRANS,
+                max_init_retransmissions,
+                ai.clock.clone(),
+                tasks.clone(),
             ));
             ai.t1cookie = Some(RtxTimer::new(
                 Arc::downgrade(&association_internal3),
                 RtxTimerId::T1Cookie,
-                MAX_INIT_RETRANS,
+                max_init_retransmissions,
+                ai.clock.clone(),
+                tasks.clone(),
             ));
             ai.t2shutdown = Some(RtxTimer::new(
                 Arc::downgrade(&association_internal3),
                 RtxTimerId::T2Shutdown,
                 NO_MAX_RETRANS,
+                ai.clock.clone(),
+                tasks.clone(),
             )); // retransmit forever
             ai.t3rtx = Some(RtxTimer::new(
                 Arc::downgrade(&association_internal3),
                 RtxTimerId::T3RTX,
-                NO_MAX_RETRANS,
-            )); // retransmit forever
+                max_retransmissions,
+                ai.clock.clone(),
+                tasks.clone(),
+            )); // retransmit forever by default
             ai.treconfig = Some(RtxTimer::new(
                 Arc::downgrade(&association_internal3),
                 RtxTimerId::Reconfig,
                 NO_MAX_RETRANS,
+                ai.clock.clone(),
+                tasks.clone(),
             )); // retransmit forever
             ai.ack_timer = Some(AckTimer::new(
                 Arc::downgrade(&association_internal3),
                 ACK_INTERVAL,
+                ai.clock.clone(),
+                tasks.clone(),
             ));
         }
 
-        tokio::spawn(async move {
+        tasks.spawn(async move {
             Association::read_loop(
                 name1,
                 bytes_received1,
@@ -392,7 +545,7 @@ impl Association {
             .await;
         });
 
-        tokio::spawn(async move {
+        tasks.spawn(async move {
             Association::write_loop(
                 name2,
                 bytes_sent2,
@@ -428,6 +581,12 @@ impl Association {
                 net_conn,
                 bytes_received,
                 bytes_sent,
+                association_buffer,
+                stats,
+                num_outbound_streams,
+                num_inbound_streams,
+                add_streams_mu: Mutex::new(()),
+                tasks,
                 association_internal,
             },
             handshake_completed_ch_rx,
@@ -456,6 +615,8 @@ impl Association {
                         }
                         Err(err) => {
                             log::warn!("[{}] failed to read packets on net_conn: {}", name, err);
+                            let mut ai = association_internal.lock().await;
+                            ai.set_close_reason(CloseReason::ConnectionLost);
                             break;
                         }
                     }
@@ -474,6 +635,8 @@ impl Association {
                 let mut ai = association_internal.lock().await;
                 if let Err(err) = ai.handle_inbound(&inbound).await {
                     log::warn!("[{}] failed to handle_inbound: {:?}", name, err);
+                    // An ABORT already recorded its own reason
+                    ai.set_close_reason(CloseReason::ProtocolViolation);
                     done = true;
                 }
             }
@@ -511,6 +674,8 @@ impl Association {
                 log::debug!("[{}] sending {} bytes", name, raw.len());
                 if let Err(err) = net_conn.send(raw).await {
                     log::warn!("[{}] failed to write packets on net_conn: {}", name, err);
+                    let mut ai = association_internal.lock().await;
+                    ai.set_close_reason(CloseReason::ConnectionLost);
                     ok = false;
                     break;
                 } else {
@@ -553,6 +718,107 @@ impl Association {
         self.bytes_received.load(Ordering::SeqCst)
     }
 
+    /// fast_retransmissions returns the number of DATA chunks retransmitted after being reported
+    /// missing by the peer's SACKs.
+    pub fn fast_retransmissions(&self) -> u64 {
+        self.stats.get_num_fast_retrans()
+    }
+
+    /// fast_recoveries returns the number of times Fast Recovery was entered, each reducing
+    /// cwnd once for all the losses of a window.
+    pub fn fast_recoveries(&self) -> u64 {
+        self.stats.get_num_fast_recoveries()
+    }
+
+    /// timeout_retransmissions returns the number of DATA chunks retransmitted after the
+    /// T3-rtx timer expired.
+    pub fn timeout_retransmissions(&self) -> u64 {
+        self.stats.get_num_timeout_retrans()
+    }
+
+    /// t3_timeouts returns the number of times the T3-rtx timer expired.
+    pub fn t3_timeouts(&self) -> u64 {
+        self.stats.get_num_t3timeouts()
+    }
+
+    /// duplicate_tsns_received returns the number of DATA chunks received with a TSN that was
+    /// already received.
+    pub fn duplicate_tsns_received(&self) -> u64 {
+        self.stats.get_num_dup_tsns()
+    }
+
+    /// out_of_window_datas_received returns the number of DATA chunks dropped for a TSN too far
+    /// ahead of the cumulative TSN.
+    pub fn out_of_window_datas_received(&self) -> u64 {
+        self.stats.get_num_out_of_window_datas()
+    }
+
+    /// stale_sacks_received returns the number of SACKs dropped for a cumulative TSN ack behind
+    /// the one of an earlier SACK.
+    pub fn stale_sacks_received(&self) -> u64 {
+        self.stats.get_num_stale_sacks()
+    }
+
+    /// buffered_amount returns the number of bytes of outgoing data currently buffered by all
+    /// streams of the association.
+    pub fn buffered_amount(&self) -> usize {
+        self.association_buffer.buffered_amount()
+    }
+
+    /// buffered_amount_low_threshold returns the number of bytes of outgoing data buffered by
+    /// all streams that is considered "low." Defaults to 0.
+    pub fn buffered_amount_low_threshold(&self) -> usize {
+        self.association_buffer.buffered_amount_low_threshold()
+    }
+
+    /// set_buffered_amount_low_threshold is used to update the threshold.
+    /// See buffered_amount_low_threshold().
+    pub fn set_buffered_amount_low_threshold(&self, th: usize) {
+        self.association_buffer
+            .set_buffered_amount_low_threshold(th);
+    }
+
+    /// on_buffered_amount_low sets the callback handler which would be called when the number of
+    /// bytes of outgoing data buffered by all streams drops to or below the threshold.
+    pub fn on_buffered_amount_low(&self, f: OnBufferedAmountLowFn) {
+        self.association_buffer.on_buffered_amount_low(f);
+    }
+
+    /// max_outbound_streams returns the number of streams this endpoint may send on, the
+    /// smallest of the outbound streams it advertised and the inbound streams of the peer,
+    /// raised by `add_outbound_streams`. Streams can only be opened with a lower identifier.
+    pub fn max_outbound_streams(&self) -> u16 {
+        self.num_outbound_streams.load(Ordering::SeqCst)
+    }
+
+    /// max_inbound_streams returns the number of streams the peer may send on.
+    pub fn max_inbound_streams(&self) -> u16 {
+        self.num_inbound_streams.load(Ordering::SeqCst)
+    }
+
+    /// add_outbound_streams asks the peer to accept `count` more outbound streams with a
+    /// RE-CONFIG chunk (RFC 6525), and to add inbound streams up to the same number if needed,
+    /// and waits for its answer. Once it resolves, streams
+    /// can be opened up to the new `max_outbound_streams`. Fails with `ErrAddStreamsRefused` if
+    /// the peer refuses.
+    pub async fn add_outbound_streams(&self, count: u16) -> Result<()> {
+        let _add_streams = self.add_streams_mu.lock().await;
+
+        let done = {
+            let mut ai = self.association_internal.lock().await;
+            ai.add_outbound_streams(count).await?
+        };
+
+        match done.await {
+            Ok(result) => result,
+            // the association was closed before the peer answered
+            Err(_) => match self.close_reason().await {
+                Some(reason) => Err(Error::ErrAssociationClosed(reason)),
+                None => Err(Error::ErrAddStreamsStateNotEstablished),
+            },
+        }
+    }
+
     /// open_stream opens a stream
     pub async fn open_stream(
         &self,
@@ -0,0 +1,132 @@
+#[cfg(test)]
+mod clock_test;
+
+use std::fmt;
+use std::future::Future;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Clock is the source of time of an association. Retransmission and delayed ack timers sleep on
+/// it and round trip times and packet lifetimes are measured with it.
+///
+/// [`TokioClock`] is used unless the association `Config` sets one. [`ManualClock`] only moves
+/// when told to, which makes timer driven behavior deterministic and fast to test.
+pub trait Clock: Send + Sync {
+    /// now returns the current time.
+    fn now(&self) -> Instant;
+
+    /// sleep_until returns a future that completes once `now()` reaches the deadline.
+    fn sleep_until(&self, deadline: Instant) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+}
+
+/// TokioClock reads time from the tokio runtime, so it also follows `tokio::time::pause`.
+#[derive(Default, Debug, Copy, Clone)]
+pub struct TokioClock;
+
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        tokio::time::Instant::now().into_std()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(tokio::time::sleep_until(tokio::time::Instant::from_std(
+            deadline,
+        )))
+    }
+}
+
+/// SharedClock is the clock of an association shared with its timers, [`TokioClock`] by default.
+#[derive(Clone)]
+pub(crate) struct SharedClock(pub(crate) Arc<dyn Clock>);
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        SharedClock(Arc::new(TokioClock))
+    }
+}
+
+impl Deref for SharedClock {
+    type Target = dyn Clock;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl fmt::Debug for SharedClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedClock")
+    }
+}
+
+/// ManualClock is a clock that stands still until it is advanced with [`ManualClock::advance`].
+///
+/// Advancing the clock wakes the sleepers whose deadline has passed, they run once the runtime
+/// schedules them. On a current thread runtime, yielding with `tokio::task::yield_now` lets them
+/// run before the test goes on.
+pub struct ManualClock {
+    start: Instant,
+    elapsed: Mutex<watch::Sender<Duration>>,
+    // keeps the channel open while nobody sleeps
+    _elapsed_rx: watch::Receiver<Duration>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManualClock {
+    /// new creates a clock standing at the current time.
+    pub fn new() -> Self {
+        let (elapsed_tx, elapsed_rx) = watch::channel(Duration::ZERO);
+        ManualClock {
+            start: Instant::now(),
+            elapsed: Mutex::new(elapsed_tx),
+            _elapsed_rx: elapsed_rx,
+        }
+    }
+
+    /// advance moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let elapsed_tx = self.elapsed.lock().unwrap();
+        let elapsed = *elapsed_tx.borrow() + duration;
+        let _ = elapsed_tx.send(elapsed);
+    }
+
+    /// elapsed returns how far the clock has been advanced since it was created.
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap().borrow()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        let wait = deadline.saturating_duration_since(self.start);
+        let mut elapsed_rx = self.elapsed.lock().unwrap().subscribe();
+        Box::pin(async move {
+            while *elapsed_rx.borrow_and_update() < wait {
+                if elapsed_rx.changed().await.is_err() {
+                    // The clock is gone, it will never get there
+                    std::future::pending::<()>().await;
+                }
+            }
+        })
+    }
+}
+
+impl fmt::Debug for ManualClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ManualClock")
+            .field("elapsed", &self.elapsed())
+            .finish()
+    }
+}
@@ -0,0 +1,37 @@
+use super::*;
+
+#[tokio::test]
+async fn test_manual_clock_sleep_until() {
+    let clock = Arc::new(ManualClock::new());
+    let start = clock.now();
+    assert_eq!(start, clock.now(), "the clock must stand still");
+
+    let sleep = clock.sleep_until(start + Duration::from_millis(100));
+    let woken = tokio::spawn(sleep);
+
+    clock.advance(Duration::from_millis(99));
+    tokio::task::yield_now().await;
+    assert!(!woken.is_finished(), "must sleep until the deadline");
+
+    clock.advance(Duration::from_millis(1));
+    tokio::time::timeout(Duration::from_secs(1), woken)
+        .await
+        .expect("must wake at the deadline")
+        .unwrap();
+
+    assert_eq!(Duration::from_millis(100), clock.elapsed());
+    assert_eq!(start + Duration::from_millis(100), clock.now());
+
+    // Deadlines in the past complete right away
+    clock.sleep_until(start).await;
+}
+
+#[tokio::test]
+async fn test_tokio_clock_follows_paused_time() {
+    tokio::time::pause();
+    let clock = TokioClock;
+    let start = clock.now();
+
+    clock.sleep_until(start + Duration::from_secs(60)).await;
+    assert!(clock.now() >= start + Duration::from_secs(60));
+}
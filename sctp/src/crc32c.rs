@@ -0,0 +1,173 @@
+//! CRC32c (Castagnoli) checksum of SCTP packets, see RFC 4960 Appendix B.
+//!
+//! The checksum is computed with the SSE 4.2 (x86_64) or ARMv8 CRC (aarch64) instructions when
+//! the CPU supports them, which is detected at runtime, and with the table based implementation
+//! of the crc crate otherwise.
+
+use crc::{Crc, CRC_32_ISCSI};
+
+static CASTAGNOLI: Crc<u32> = Crc::<u32>::new(&CRC_32_ISCSI);
+
+/// checksum returns the CRC32c of the concatenation of parts.
+pub fn checksum(parts: &[&[u8]]) -> u32 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("sse4.2") {
+            // Safety: the CPU supports the instructions used by update
+            return !parts
+                .iter()
+                .fold(!0, |crc, part| unsafe { sse42::update(crc, part) });
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("crc") {
+            // Safety: the CPU supports the instructions used by update
+            return !parts
+                .iter()
+                .fold(!0, |crc, part| unsafe { armv8::update(crc, part) });
+        }
+    }
+
+    checksum_table(parts)
+}
+
+/// checksum_table returns the CRC32c of the concatenation of parts, computed with the table
+/// based implementation even if the CPU has CRC32c instructions.
+pub fn checksum_table(parts: &[&[u8]]) -> u32 {
+    let mut digest = CASTAGNOLI.digest();
+    for part in parts {
+        digest.update(part);
+    }
+    digest.finalize()
+}
+
+/// is_accelerated returns true if checksum uses the CRC32c instructions of the CPU.
+pub fn is_accelerated() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        is_x86_feature_detected!("sse4.2")
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        std::arch::is_aarch64_feature_detected!("crc")
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        false
+    }
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+#[inline(always)]
+fn read_u64_le(chunk: &[u8]) -> u64 {
+    let mut b = [0u8; 8];
+    b.copy_from_slice(chunk);
+    u64::from_le_bytes(b)
+}
+
+#[cfg(target_arch = "x86_64")]
+mod sse42 {
+    use std::arch::x86_64::{_mm_crc32_u64, _mm_crc32_u8};
+
+    /// update continues the CRC32c register crc, without the initial and final inversion, over
+    /// data. The caller must check that the CPU supports SSE 4.2.
+    #[target_feature(enable = "sse4.2")]
+    pub(super) unsafe fn update(crc: u32, data: &[u8]) -> u32 {
+        let mut crc = crc as u64;
+        let mut chunks = data.chunks_exact(8);
+        for chunk in &mut chunks {
+            crc = _mm_crc32_u64(crc, super::read_u64_le(chunk));
+        }
+
+        let mut crc = crc as u32;
+        for b in chunks.remainder() {
+            crc = _mm_crc32_u8(crc, *b);
+        }
+        crc
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod armv8 {
+    use std::arch::asm;
+
+    /// update continues the CRC32c register crc, without the initial and final inversion, over
+    /// data. The caller must check that the CPU supports the CRC extension.
+    pub(super) unsafe fn update(mut crc: u32, data: &[u8]) -> u32 {
+        let mut chunks = data.chunks_exact(8);
+        for chunk in &mut chunks {
+            asm!(
+                ".arch_extension crc",
+                "crc32cx {crc:w}, {crc:w}, {v:x}",
+                crc = inout(reg) crc,
+                v = in(reg) super::read_u64_le(chunk),
+                options(pure, nomem, nostack),
+            );
+        }
+
+        for b in chunks.remainder() {
+            asm!(
+                ".arch_extension crc",
+                "crc32cb {crc:w}, {crc:w}, {v:w}",
+                crc = inout(reg) crc,
+                v = in(reg) *b as u32,
+                options(pure, nomem, nostack),
+            );
+        }
+        crc
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_checksum_check_value() {
+        // The check value of the CRC-32/ISCSI catalogue entry
+        assert_eq!(checksum(&[b"123456789"]), 0xe306_9283);
+        assert_eq!(checksum_table(&[b"123456789"]), 0xe306_9283);
+        assert_eq!(checksum(&[b"1234", b"", b"56789"]), 0xe306_9283);
+        assert_eq!(checksum(&[]), checksum_table(&[]));
+    }
+
+    #[test]
+    fn test_checksum_matches_table() {
+        let mut rng = rand::thread_rng();
+        let mut buf = vec![0u8; 2048];
+        rng.fill(&mut buf[..]);
+
+        // Unaligned starts and lengths, split into parts at random points
+        for len in 0..=300 {
+            for offset in 0..8 {
+                let data = &buf[offset..offset + len];
+                assert_eq!(
+                    checksum(&[data]),
+                    checksum_table(&[data]),
+                    "len {} offset {}",
+                    len,
+                    offset
+                );
+
+                let split = if len > 0 { rng.gen_range(0..=len) } else { 0 };
+                assert_eq!(
+                    checksum(&[&data[..split], &data[split..]]),
+                    checksum_table(&[data]),
+                    "len {} offset {} split {}",
+                    len,
+                    offset,
+                    split
+                );
+            }
+        }
+
+        for _ in 0..100 {
+            let len = rng.gen_range(0..=buf.len());
+            let data = &buf[..len];
+            assert_eq!(checksum(&[data]), checksum_table(&[data]), "len {}", len);
+        }
+    }
+}
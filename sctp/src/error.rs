@@ -1,3 +1,5 @@
+use crate::association::CloseReason;
+
 use std::io;
 use thiserror::Error;
 
@@ -78,6 +80,8 @@ pub enum Error {
     ErrSsnResetRequestParamTooShort,
     #[error("reconfig response parameter too short")]
     ErrReconfigRespParamTooShort,
+    #[error("add streams request parameter too short")]
+    ErrAddStreamsRequestParamTooShort,
     #[error("invalid algorithm type")]
     ErrInvalidAlgorithmType,
 
@@ -166,6 +170,8 @@ pub enum Error {
     ErrAssociationInitFailed,
     #[error("association handshake closed")]
     ErrAssociationHandshakeClosed,
+    #[error("association closed: {0}")]
+    ErrAssociationClosed(CloseReason),
     #[error("silently discard")]
     ErrSilentlyDiscard,
     #[error("the init not stored to send")]
@@ -188,6 +194,12 @@ pub enum Error {
     ErrStreamAlreadyExist,
     #[error("Failed to create a stream with identifier")]
     ErrStreamCreateFailed,
+    #[error("stream identifier exceeds the number of outbound streams")]
+    ErrStreamIdentifierOutOfRange,
+    #[error("the peer refused to add outbound streams")]
+    ErrAddStreamsRefused,
+    #[error("adding streams in non-Established state")]
+    ErrAddStreamsStateNotEstablished,
     #[error("unable to be popped from inflight queue TSN")]
     ErrInflightQueueTsnPop,
     #[error("requested non-existent TSN")]
@@ -209,6 +221,8 @@ pub enum Error {
     ErrOutboundPacketTooLarge,
     #[error("Stream closed")]
     ErrStreamClosed,
+    #[error("association buffered amount exceeds the limit")]
+    ErrAssociationBufferFull,
     #[error("Short buffer to be filled")]
     ErrShortBuffer,
     #[error("Io EOF")]
@@ -231,6 +245,9 @@ impl From<Error> for io::Error {
             e @ Error::ErrStreamClosed => {
                 io::Error::new(io::ErrorKind::ConnectionAborted, e.to_string())
             }
+            e @ Error::ErrAssociationBufferFull => {
+                io::Error::new(io::ErrorKind::WouldBlock, e.to_string())
+            }
             e => io::Error::new(io::ErrorKind::Other, e.to_string()),
         }
     }
@@ -3,6 +3,8 @@
 
 pub mod association;
 pub mod chunk;
+pub mod clock;
+pub mod crc32c;
 mod error;
 pub mod error_cause;
 pub mod packet;
@@ -15,12 +15,12 @@ use crate::chunk::chunk_shutdown::ChunkShutdown;
 use crate::chunk::chunk_shutdown_ack::ChunkShutdownAck;
 use crate::chunk::chunk_shutdown_complete::ChunkShutdownComplete;
 use crate::chunk::chunk_type::*;
+use crate::crc32c;
 use crate::error::{Error, Result};
 use crate::util::*;
 
 use crate::chunk::chunk_unknown::ChunkUnknown;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
-use crc::{Crc, CRC_32_ISCSI};
 use std::fmt;
 
 ///Packet represents an SCTP packet, defined in https://tools.ietf.org/html/rfc4960#section-3
@@ -168,12 +168,7 @@ impl Packet {
         }
         let raw = raw.freeze();
 
-        let hasher = Crc::<u32>::new(&CRC_32_ISCSI);
-        let mut digest = hasher.digest();
-        digest.update(writer);
-        digest.update(&FOUR_ZEROES);
-        digest.update(&raw[..]);
-        let checksum = digest.finalize();
+        let checksum = crc32c::checksum(&[&writer[..], &FOUR_ZEROES[..], &raw[..]]);
 
         // Checksum is already in BigEndian
         // Using LittleEndian stops it from being flipped
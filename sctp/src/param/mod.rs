@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod param_test;
 
+pub(crate) mod param_add_streams_request;
 pub(crate) mod param_chunk_list;
 pub(crate) mod param_forward_tsn_supported;
 pub(crate) mod param_header;
@@ -17,7 +18,8 @@ pub(crate) mod param_unrecognized;
 
 use crate::error::{Error, Result};
 use crate::param::{
-    param_chunk_list::ParamChunkList, param_forward_tsn_supported::ParamForwardTsnSupported,
+    param_add_streams_request::ParamAddStreamsRequest, param_chunk_list::ParamChunkList,
+    param_forward_tsn_supported::ParamForwardTsnSupported,
     param_heartbeat_info::ParamHeartbeatInfo,
     param_outgoing_reset_request::ParamOutgoingResetRequest, param_random::ParamRandom,
     param_reconfig_response::ParamReconfigResponse,
@@ -71,6 +73,9 @@ pub(crate) fn build_param(raw_param: &Bytes) -> Result<Box<dyn Param + Send + Sy
         ParamType::HeartbeatInfo => Ok(Box::new(ParamHeartbeatInfo::unmarshal(raw_param)?)),
         ParamType::OutSsnResetReq => Ok(Box::new(ParamOutgoingResetRequest::unmarshal(raw_param)?)),
         ParamType::ReconfigResp => Ok(Box::new(ParamReconfigResponse::unmarshal(raw_param)?)),
+        ParamType::AddOutStreamsReq | ParamType::AddIncStreamsReq => {
+            Ok(Box::new(ParamAddStreamsRequest::unmarshal(raw_param)?))
+        }
         _ => {
             // According to RFC https://datatracker.ietf.org/doc/html/rfc4960#section-3.2.1
             let stop_processing = ((raw_type >> 15) & 0x01) == 0;
@@ -0,0 +1,99 @@
+use super::{param_header::*, param_type::*, *};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+pub(crate) const PARAM_ADD_STREAMS_REQUEST_LENGTH: usize = 8;
+
+///This parameter is used by the sender to request that the number of
+///outgoing (type 17) or incoming (type 18) streams be increased.
+/// 0                   1                   2                   3
+/// 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+///+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///|   Parameter Type = 17 or 18   |      Parameter Length = 12    |
+///+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///|          Re-configuration Request Sequence Number             |
+///+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///|      Number of new streams    |         Reserved              |
+///+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub(crate) struct ParamAddStreamsRequest {
+    /// incoming is true for an Add Incoming Streams Request, which asks the receiver to add
+    /// outgoing streams, and false for an Add Outgoing Streams Request.
+    pub(crate) incoming: bool,
+    /// reconfig_request_sequence_number is used to identify the request, see
+    /// [`super::param_outgoing_reset_request::ParamOutgoingResetRequest`].
+    pub(crate) reconfig_request_sequence_number: u32,
+    /// The number of streams to add in the direction of the request.
+    pub(crate) number_of_new_streams: u16,
+}
+
+impl fmt::Display for ParamAddStreamsRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.header(),
+            self.reconfig_request_sequence_number,
+            self.number_of_new_streams
+        )
+    }
+}
+
+impl Param for ParamAddStreamsRequest {
+    fn header(&self) -> ParamHeader {
+        ParamHeader {
+            typ: if self.incoming {
+                ParamType::AddIncStreamsReq
+            } else {
+                ParamType::AddOutStreamsReq
+            },
+            value_length: self.value_length() as u16,
+        }
+    }
+
+    fn unmarshal(raw: &Bytes) -> Result<Self> {
+        let header = ParamHeader::unmarshal(raw)?;
+
+        let incoming = match header.typ {
+            ParamType::AddOutStreamsReq => false,
+            ParamType::AddIncStreamsReq => true,
+            _ => return Err(Error::ErrParamterType),
+        };
+
+        // validity of value_length is checked in ParamHeader::unmarshal
+        if header.value_length() < PARAM_ADD_STREAMS_REQUEST_LENGTH {
+            return Err(Error::ErrAddStreamsRequestParamTooShort);
+        }
+
+        let reader =
+            &mut raw.slice(PARAM_HEADER_LENGTH..PARAM_HEADER_LENGTH + header.value_length());
+        let reconfig_request_sequence_number = reader.get_u32();
+        let number_of_new_streams = reader.get_u16();
+
+        Ok(ParamAddStreamsRequest {
+            incoming,
+            reconfig_request_sequence_number,
+            number_of_new_streams,
+        })
+    }
+
+    fn marshal_to(&self, buf: &mut BytesMut) -> Result<usize> {
+        self.header().marshal_to(buf)?;
+        buf.put_u32(self.reconfig_request_sequence_number);
+        buf.put_u16(self.number_of_new_streams);
+        buf.put_u16(0);
+        Ok(buf.len())
+    }
+
+    fn value_length(&self) -> usize {
+        PARAM_ADD_STREAMS_REQUEST_LENGTH
+    }
+
+    fn clone_to(&self) -> Box<dyn Param + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + Sync) {
+        self
+    }
+}
@@ -237,6 +237,71 @@ fn test_reconfig_result_stringer() -> Result<()> {
     Ok(())
 }
 
+///////////////////////////////////////////////////////////////////
+//param_add_streams_request_test
+///////////////////////////////////////////////////////////////////
+use super::param_add_streams_request::*;
+
+static CHUNK_RECONFIG_ADD_OUTGOING_STREAMS: Bytes =
+    Bytes::from_static(&[0x0, 0x11, 0x0, 0xc, 0x0, 0x0, 0x0, 0x7, 0x0, 0x10, 0x0, 0x0]);
+static CHUNK_RECONFIG_ADD_INCOMING_STREAMS: Bytes =
+    Bytes::from_static(&[0x0, 0x12, 0x0, 0xc, 0x0, 0x0, 0x0, 0x8, 0x1, 0x0, 0x0, 0x0]);
+
+#[test]
+fn test_param_add_streams_request_success() -> Result<()> {
+    let tests = vec![
+        (
+            CHUNK_RECONFIG_ADD_OUTGOING_STREAMS.clone(),
+            ParamAddStreamsRequest {
+                incoming: false,
+                reconfig_request_sequence_number: 7,
+                number_of_new_streams: 16,
+            },
+        ),
+        (
+            CHUNK_RECONFIG_ADD_INCOMING_STREAMS.clone(),
+            ParamAddStreamsRequest {
+                incoming: true,
+                reconfig_request_sequence_number: 8,
+                number_of_new_streams: 256,
+            },
+        ),
+    ];
+
+    for (binary, parsed) in tests {
+        let actual = ParamAddStreamsRequest::unmarshal(&binary)?;
+        assert_eq!(parsed, actual);
+        let b = actual.marshal()?;
+        assert_eq!(binary, b);
+
+        let built = build_param(&binary)?;
+        assert_eq!(
+            Some(&parsed),
+            built.as_any().downcast_ref::<ParamAddStreamsRequest>()
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_param_add_streams_request_failure() -> Result<()> {
+    let tests = vec![
+        (
+            "param too short",
+            Bytes::from_static(&[0x0, 0x11, 0x0, 0x8, 0x0, 0x0, 0x0, 0x7]),
+        ),
+        ("wrong param type", CHUNK_RECONFIG_RESPONCE.clone()),
+    ];
+
+    for (name, binary) in tests {
+        let result = ParamAddStreamsRequest::unmarshal(&binary);
+        assert!(result.is_err(), "expected unmarshal: {} to fail.", name);
+    }
+
+    Ok(())
+}
+
 ///////////////////////////////////////////////////////////////////
 //param_test
 ///////////////////////////////////////////////////////////////////
@@ -34,10 +34,6 @@ impl PayloadQueue {
         });
     }
 
-    pub(crate) fn can_push(&self, p: &ChunkPayloadData, cumulative_tsn: u32) -> bool {
-        !(self.chunk_map.contains_key(&p.tsn) || sna32lte(p.tsn, cumulative_tsn))
-    }
-
     pub(crate) fn push_no_check(&mut self, p: ChunkPayloadData) {
         self.n_bytes += p.user_data.len();
         self.sorted.push(p.tsn);
@@ -80,6 +76,16 @@ impl PayloadQueue {
         None
     }
 
+    /// pop_until drops the chunks up to and including the given TSN.
+    pub(crate) fn pop_until(&mut self, tsn: u32) {
+        while let Some(&first) = self.sorted.first() {
+            if sna32gt(first, tsn) {
+                break;
+            }
+            self.pop(first);
+        }
+    }
+
     /// get returns reference to chunkPayloadData with the given TSN value.
     pub(crate) fn get(&self, tsn: u32) -> Option<&ChunkPayloadData> {
         self.chunk_map.get(&tsn)
@@ -88,6 +94,12 @@ impl PayloadQueue {
         self.chunk_map.get_mut(&tsn)
     }
 
+    /// push_duplicate records a TSN that was received again, to be retrieved with
+    /// pop_duplicates.
+    pub(crate) fn push_duplicate(&mut self, tsn: u32) {
+        self.dup_tsn.push(tsn);
+    }
+
     /// popDuplicates returns an array of TSN values that were found duplicate.
     pub(crate) fn pop_duplicates(&mut self) -> Vec<u32> {
         self.dup_tsn.drain(..).collect()
@@ -101,11 +113,7 @@ impl PayloadQueue {
         let mut b = GapAckBlock::default();
         let mut gap_ack_blocks = vec![];
         for (i, tsn) in self.sorted.iter().enumerate() {
-            let diff = if *tsn >= cumulative_tsn {
-                (*tsn - cumulative_tsn) as u16
-            } else {
-                0
-            };
+            let diff = tsn.wrapping_sub(cumulative_tsn) as u16;
 
             if i == 0 {
                 b.start = diff;
@@ -1,26 +1,128 @@
 use std::{
-    collections::VecDeque,
-    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
-use util::sync::RwLock;
+use util::sync::Mutex;
 
 use crate::chunk::chunk_payload_data::ChunkPayloadData;
+use crate::stream::DEFAULT_STREAM_PRIORITY;
 
 /// Basic queue for either ordered or unordered chunks.
 pub(crate) type PendingBaseQueue = VecDeque<ChunkPayloadData>;
 
-// TODO: benchmark performance between multiple Atomic+Mutex vs one Mutex<PendingQueueInternal>
+/// The chunks of all streams sharing a priority.
+#[derive(Debug, Default)]
+struct PriorityQueue {
+    unordered_queue: PendingBaseQueue,
+    ordered_queue: PendingBaseQueue,
+    /// Bytes popped so far, scaled by the inverse of the priority
+    virtual_time: u64,
+}
+
+impl PriorityQueue {
+    fn queue(&self, unordered: bool) -> &PendingBaseQueue {
+        if unordered {
+            &self.unordered_queue
+        } else {
+            &self.ordered_queue
+        }
+    }
+
+    fn queue_mut(&mut self, unordered: bool) -> &mut PendingBaseQueue {
+        if unordered {
+            &mut self.unordered_queue
+        } else {
+            &mut self.ordered_queue
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.unordered_queue.is_empty() && self.ordered_queue.is_empty()
+    }
+}
 
-/// A queue for both ordered and unordered chunks.
+#[derive(Debug, Default)]
+struct PendingQueueInternal {
+    stream_priorities: HashMap<u16, u16>,
+    queues: BTreeMap<u16, PriorityQueue>,
+    /// Priority and queue of the message being sent. Its remaining fragments go first.
+    selected: Option<(u16, bool)>,
+    /// Priority and queue of the chunk returned by the last peek
+    peeked: Option<(u16, bool)>,
+}
+
+impl PendingQueueInternal {
+    fn priority_queue_mut(&mut self, stream_identifier: u16) -> &mut PriorityQueue {
+        let priority = self
+            .stream_priorities
+            .get(&stream_identifier)
+            .copied()
+            .unwrap_or(DEFAULT_STREAM_PRIORITY);
+
+        if !self.queues.contains_key(&priority) {
+            // A queue which becomes busy starts with the lowest virtual time of the busy queues
+            // so it gets no credit for the time it was idle.
+            let virtual_time = self
+                .queues
+                .values()
+                .map(|q| q.virtual_time)
+                .min()
+                .unwrap_or_default();
+            self.queues.insert(
+                priority,
+                PriorityQueue {
+                    virtual_time,
+                    ..Default::default()
+                },
+            );
+        }
+
+        self.queues
+            .get_mut(&priority)
+            .expect("queue to exist because of the above insert")
+    }
+
+    /// next returns the priority and queue to send from, unless a fragmented message is being
+    /// sent. Priorities are scheduled by weighted fair queueing: the busy priority with the
+    /// lowest virtual time wins, the higher priority on ties, and unordered chunks go before
+    /// ordered chunks of the same priority.
+    fn next(&self) -> Option<(u16, bool)> {
+        let mut next: Option<(u16, &PriorityQueue)> = None;
+        for (priority, q) in self.queues.iter().rev() {
+            if q.is_empty() {
+                continue;
+            }
+            match next {
+                Some((_, n)) if n.virtual_time <= q.virtual_time => {}
+                _ => next = Some((*priority, q)),
+            }
+        }
+
+        next.map(|(priority, q)| (priority, !q.unordered_queue.is_empty()))
+    }
+
+    fn pop_from(&mut self, priority: u16, unordered: bool) -> Option<ChunkPayloadData> {
+        let q = self.queues.get_mut(&priority)?;
+        let popped = q.queue_mut(unordered).pop_front();
+        if let Some(p) = &popped {
+            q.virtual_time +=
+                p.user_data.len() as u64 * DEFAULT_STREAM_PRIORITY as u64 / priority.max(1) as u64;
+        }
+        if q.is_empty() {
+            self.queues.remove(&priority);
+        }
+        popped
+    }
+}
+
+/// A queue for both ordered and unordered chunks. Chunks of streams with a higher priority, see
+/// [`PendingQueue::set_stream_priority`], get a larger share of the sending capacity.
 #[derive(Debug, Default)]
 pub(crate) struct PendingQueue {
-    unordered_queue: RwLock<PendingBaseQueue>,
-    ordered_queue: RwLock<PendingBaseQueue>,
+    internal: Mutex<PendingQueueInternal>,
     queue_len: AtomicUsize,
     n_bytes: AtomicUsize,
-    selected: AtomicBool,
-    unordered_is_selected: AtomicBool,
 }
 
 impl PendingQueue {
@@ -28,16 +130,26 @@ impl PendingQueue {
         PendingQueue::default()
     }
 
+    /// set_stream_priority sets the priority of the chunks pushed for the stream from now on.
+    pub(crate) fn set_stream_priority(&self, stream_identifier: u16, priority: u16) {
+        let mut internal = self.internal.lock();
+        if priority == DEFAULT_STREAM_PRIORITY {
+            internal.stream_priorities.remove(&stream_identifier);
+        } else {
+            internal
+                .stream_priorities
+                .insert(stream_identifier, priority);
+        }
+    }
+
     /// Appends a chunk to the back of the pending queue.
     pub(crate) fn push(&self, c: ChunkPayloadData) {
         let user_data_len = c.user_data.len();
 
-        if c.unordered {
-            let mut unordered_queue = self.unordered_queue.write();
-            unordered_queue.push_back(c);
-        } else {
-            let mut ordered_queue = self.ordered_queue.write();
-            ordered_queue.push_back(c);
+        {
+            let mut internal = self.internal.lock();
+            let q = internal.priority_queue_mut(c.stream_identifier);
+            q.queue_mut(c.unordered).push_back(c);
         }
 
         self.n_bytes.fetch_add(user_data_len, Ordering::SeqCst);
@@ -57,21 +169,22 @@ impl PendingQueue {
         let total_user_data_len = chunks.iter().fold(0, |acc, c| acc + c.user_data.len());
         let chunks_len = chunks.len();
 
-        let unordered = chunks
+        let first = chunks
             .first()
-            .expect("chunks to not be empty because of the above check")
-            .unordered;
-        if unordered {
-            let mut unordered_queue = self.unordered_queue.write();
+            .expect("chunks to not be empty because of the above check");
+        let (unordered, stream_identifier) = (first.unordered, first.stream_identifier);
+        {
+            let mut internal = self.internal.lock();
+            let q = internal
+                .priority_queue_mut(stream_identifier)
+                .queue_mut(unordered);
             for c in chunks {
-                assert!(c.unordered, "expected all chunks to be unordered");
-                unordered_queue.push_back(c);
-            }
-        } else {
-            let mut ordered_queue = self.ordered_queue.write();
-            for c in chunks {
-                assert!(!c.unordered, "expected all chunks to be ordered");
-                ordered_queue.push_back(c);
+                if unordered {
+                    assert!(c.unordered, "expected all chunks to be unordered");
+                } else {
+                    assert!(!c.unordered, "expected all chunks to be ordered");
+                }
+                q.push_back(c);
             }
         }
 
@@ -81,45 +194,35 @@ impl PendingQueue {
     }
 
     pub(crate) fn peek(&self) -> Option<ChunkPayloadData> {
-        if self.selected.load(Ordering::SeqCst) {
-            if self.unordered_is_selected.load(Ordering::SeqCst) {
-                let unordered_queue = self.unordered_queue.read();
-                return unordered_queue.get(0).cloned();
-            } else {
-                let ordered_queue = self.ordered_queue.read();
-                return ordered_queue.get(0).cloned();
-            }
-        }
+        let mut internal = self.internal.lock();
 
-        let c = {
-            let unordered_queue = self.unordered_queue.read();
-            unordered_queue.get(0).cloned()
+        let next = match internal.selected {
+            Some(selected) => Some(selected),
+            None => internal.next(),
         };
+        internal.peeked = next;
 
-        if c.is_some() {
-            return c;
-        }
-
-        let ordered_queue = self.ordered_queue.read();
-        ordered_queue.get(0).cloned()
+        let (priority, unordered) = next?;
+        internal
+            .queues
+            .get(&priority)
+            .and_then(|q| q.queue(unordered).front().cloned())
     }
 
+    /// Pops the chunk returned by the last peek. `beginning_fragment` and `unordered` are the
+    /// flags of that chunk.
     pub(crate) fn pop(
         &self,
         beginning_fragment: bool,
         unordered: bool,
     ) -> Option<ChunkPayloadData> {
-        let popped = if self.selected.load(Ordering::SeqCst) {
-            let popped = if self.unordered_is_selected.load(Ordering::SeqCst) {
-                let mut unordered_queue = self.unordered_queue.write();
-                unordered_queue.pop_front()
-            } else {
-                let mut ordered_queue = self.ordered_queue.write();
-                ordered_queue.pop_front()
-            };
+        let mut internal = self.internal.lock();
+
+        let popped = if let Some((priority, selected_unordered)) = internal.selected {
+            let popped = internal.pop_from(priority, selected_unordered);
             if let Some(p) = &popped {
                 if p.ending_fragment {
-                    self.selected.store(false, Ordering::SeqCst);
+                    internal.selected = None;
                 }
             }
             popped
@@ -127,31 +230,27 @@ impl PendingQueue {
             if !beginning_fragment {
                 return None;
             }
-            if unordered {
-                let popped = {
-                    let mut unordered_queue = self.unordered_queue.write();
-                    unordered_queue.pop_front()
-                };
-                if let Some(p) = &popped {
-                    if !p.ending_fragment {
-                        self.selected.store(true, Ordering::SeqCst);
-                        self.unordered_is_selected.store(true, Ordering::SeqCst);
-                    }
+
+            // Chunks pushed since the peek may have changed the scheduling decision
+            let priority = match internal.peeked.take() {
+                Some((priority, peeked_unordered)) if peeked_unordered == unordered => {
+                    Some(priority)
                 }
-                popped
-            } else {
-                let popped = {
-                    let mut ordered_queue = self.ordered_queue.write();
-                    ordered_queue.pop_front()
-                };
-                if let Some(p) = &popped {
-                    if !p.ending_fragment {
-                        self.selected.store(true, Ordering::SeqCst);
-                        self.unordered_is_selected.store(false, Ordering::SeqCst);
-                    }
+                _ => internal
+                    .queues
+                    .iter()
+                    .rev()
+                    .find(|(_, q)| !q.queue(unordered).is_empty())
+                    .map(|(priority, _)| *priority),
+            }?;
+
+            let popped = internal.pop_from(priority, unordered);
+            if let Some(p) = &popped {
+                if !p.ending_fragment {
+                    internal.selected = Some((priority, unordered));
                 }
-                popped
             }
+            popped
         };
 
         if let Some(p) = &popped {
@@ -439,6 +439,98 @@ async fn test_pending_queue_append() -> Result<()> {
     Ok(())
 }
 
+fn pop_stream_identifiers(pq: &PendingQueue, n: usize) -> Vec<u16> {
+    let mut sis = vec![];
+    for _ in 0..n {
+        let c = pq.peek().expect("peek error");
+        let (beginning_fragment, unordered) = (c.beginning_fragment, c.unordered);
+        let c = pq
+            .pop(beginning_fragment, unordered)
+            .expect("should not error");
+        sis.push(c.stream_identifier);
+    }
+    sis
+}
+
+#[test]
+fn test_pending_queue_priority_overtakes_backlog() -> Result<()> {
+    let pq = PendingQueue::new();
+    pq.set_stream_priority(2, 1024);
+
+    for i in 0..10 {
+        pq.push(ChunkPayloadData {
+            stream_identifier: 1,
+            ..make_data_chunk(i, false, NO_FRAGMENT)
+        });
+    }
+    assert_eq!(vec![1, 1], pop_stream_identifiers(&pq, 2));
+
+    pq.push(ChunkPayloadData {
+        stream_identifier: 2,
+        ..make_data_chunk(10, false, NO_FRAGMENT)
+    });
+    assert_eq!(vec![2, 1], pop_stream_identifiers(&pq, 2));
+    assert_eq!(7, pq.len(), "len mismatch");
+
+    Ok(())
+}
+
+#[test]
+fn test_pending_queue_priority_weighted_share() -> Result<()> {
+    let pq = PendingQueue::new();
+    pq.set_stream_priority(1, 256);
+    pq.set_stream_priority(2, 512);
+
+    for i in 0..30 {
+        pq.push(ChunkPayloadData {
+            stream_identifier: 1,
+            ..make_data_chunk(i, false, NO_FRAGMENT)
+        });
+        pq.push(ChunkPayloadData {
+            stream_identifier: 2,
+            ..make_data_chunk(i, false, NO_FRAGMENT)
+        });
+    }
+
+    // the stream with twice the priority gets twice the share, without starving the other one
+    let sis = pop_stream_identifiers(&pq, 30);
+    assert_eq!(10, sis.iter().filter(|si| **si == 1).count());
+    assert_eq!(20, sis.iter().filter(|si| **si == 2).count());
+
+    Ok(())
+}
+
+#[test]
+fn test_pending_queue_priority_keeps_fragments_together() -> Result<()> {
+    let pq = PendingQueue::new();
+    pq.set_stream_priority(2, 1024);
+
+    pq.append(vec![
+        ChunkPayloadData {
+            stream_identifier: 1,
+            ..make_data_chunk(0, false, FRAG_BEGIN)
+        },
+        ChunkPayloadData {
+            stream_identifier: 1,
+            ..make_data_chunk(1, false, FRAG_MIDDLE)
+        },
+        ChunkPayloadData {
+            stream_identifier: 1,
+            ..make_data_chunk(2, false, FRAG_END)
+        },
+    ]);
+    assert_eq!(vec![1], pop_stream_identifiers(&pq, 1));
+
+    pq.push(ChunkPayloadData {
+        stream_identifier: 2,
+        ..make_data_chunk(3, false, NO_FRAGMENT)
+    });
+    assert_eq!(vec![1, 1, 2], pop_stream_identifiers(&pq, 3));
+    assert!(pq.is_empty(), "should be empty");
+
+    Ok(())
+}
+
 ///////////////////////////////////////////////////////////////////
 //reassembly_queue_test
 ///////////////////////////////////////////////////////////////////
@@ -989,3 +1081,112 @@ fn test_chunk_set_incomplete_chunk_set_no_contiguous_tsn() -> Result<()> {
     );
     Ok(())
 }
+
+#[test]
+fn test_reassembly_queue_end_flow() -> Result<()> {
+    let mut rq = ReassemblyQueue::new(0);
+
+    let org_ppi = PayloadProtocolIdentifier::Binary;
+
+    for (tsn, ssn, data) in [(10, 0u16, "ABC"), (12, 2u16, "GHI")] {
+        let complete = rq.push(ChunkPayloadData {
+            payload_type: org_ppi,
+            beginning_fragment: true,
+            ending_fragment: true,
+            tsn,
+            stream_sequence_number: ssn,
+            user_data: Bytes::from(data),
+            ..Default::default()
+        });
+        assert!(complete, "chunk set should be complete");
+    }
+    // SSN 1 was abandoned after its first fragment
+    let complete = rq.push(ChunkPayloadData {
+        payload_type: org_ppi,
+        beginning_fragment: true,
+        tsn: 11,
+        stream_sequence_number: 1,
+        user_data: Bytes::from_static(b"DEF"),
+        ..Default::default()
+    });
+    assert!(!complete, "chunk set should not be complete yet");
+    assert_eq!(9, rq.get_num_bytes(), "num bytes mismatch");
+
+    rq.end_flow();
+    assert_eq!(6, rq.get_num_bytes(), "num bytes mismatch");
+
+    // The new flow starts over at SSN 0 and is read after the ended one
+    let complete = rq.push(ChunkPayloadData {
+        payload_type: org_ppi,
+        beginning_fragment: true,
+        ending_fragment: true,
+        tsn: 13,
+        stream_sequence_number: 0,
+        user_data: Bytes::from_static(b"JKL"),
+        ..Default::default()
+    });
+    assert!(complete, "chunk set should be complete");
+
+    let mut buf = vec![0u8; 16];
+    for expected in [&b"ABC"[..], b"GHI", b"", b"JKL"] {
+        assert!(rq.is_readable(), "should be readable");
+        let (n, _) = rq.read(&mut buf)?;
+        assert_eq!(expected, &buf[..n]);
+    }
+    assert!(!rq.is_readable(), "should not be readable");
+    assert_eq!(0, rq.get_num_bytes(), "num bytes mismatch");
+
+    Ok(())
+}
+
+#[test]
+fn test_reassembly_queue_discard_flow() -> Result<()> {
+    let mut rq = ReassemblyQueue::new(0);
+
+    let complete = rq.push(ChunkPayloadData {
+        payload_type: PayloadProtocolIdentifier::Binary,
+        beginning_fragment: true,
+        ending_fragment: true,
+        tsn: 10,
+        stream_sequence_number: 5,
+        user_data: Bytes::from_static(b"ABC"),
+        ..Default::default()
+    });
+    assert!(complete, "chunk set should be complete");
+
+    rq.discard_flow();
+    assert!(!rq.is_readable(), "should not be readable");
+    assert_eq!(0, rq.get_num_bytes(), "num bytes mismatch");
+    assert_eq!(0, rq.next_ssn, "next ssn mismatch");
+
+    Ok(())
+}
+
+#[test]
+fn test_reassembly_queue_read_to_vec_grows_buffer() -> Result<()> {
+    let mut rq = ReassemblyQueue::new(0);
+
+    let org_ppi = PayloadProtocolIdentifier::Binary;
+
+    let chunk = ChunkPayloadData {
+        payload_type: org_ppi,
+        beginning_fragment: true,
+        ending_fragment: true,
+        tsn: 123,
+        stream_sequence_number: 0,
+        user_data: Bytes::from_static(b"0123456789"),
+        ..Default::default()
+    };
+
+    let complete = rq.push(chunk);
+    assert!(complete, "the set should be complete");
+
+    let mut buf = vec![0u8; 8];
+    let (n, ppi) = rq.read_to_vec(&mut buf)?;
+    assert_eq!(10, n, "should received 10 bytes");
+    assert_eq!(org_ppi, ppi, "should have valid ppi");
+    assert_eq!(&buf[..n], b"0123456789", "data should match");
+    assert_eq!(0, rq.get_num_bytes(), "num bytes mismatch");
+
+    Ok(())
+}
@@ -4,6 +4,7 @@ use crate::util::*;
 use crate::error::{Error, Result};
 
 use std::cmp::Ordering;
+use std::collections::VecDeque;
 
 fn sort_chunks_by_tsn(c: &mut [ChunkPayloadData]) {
     c.sort_by(|a, b| {
@@ -92,7 +93,7 @@ impl ChunkSet {
                 //   used by the receiver to reassemble the message.  This means that the
                 //   TSNs for each fragment of a fragmented user message MUST be strictly
                 //   sequential.
-                if c.tsn != last_tsn + 1 {
+                if c.tsn != last_tsn.wrapping_add(1) {
                     // mid or end fragment is missing
                     return false;
                 }
@@ -113,6 +114,10 @@ pub(crate) struct ReassemblyQueue {
     pub(crate) ordered: Vec<ChunkSet>,
     pub(crate) unordered: Vec<ChunkSet>,
     pub(crate) unordered_chunks: Vec<ChunkPayloadData>,
+    /// Complete messages of the incoming flows ended by a reset, `None` marks the end of a flow
+    pub(crate) ended_flows: VecDeque<Option<ChunkSet>>,
+    /// Set once nothing is received anymore, reads return EOF after the ended flows
+    pub(crate) closed: bool,
     pub(crate) n_bytes: usize,
 }
 
@@ -129,6 +134,8 @@ impl ReassemblyQueue {
             ordered: vec![],
             unordered: vec![],
             unordered_chunks: vec![],
+            ended_flows: VecDeque::new(),
+            closed: false,
             n_bytes: 0,
         }
     }
@@ -206,7 +213,7 @@ impl ReassemblyQueue {
             }
 
             // Check if contiguous in TSN
-            if c.tsn != last_tsn + 1 {
+            if c.tsn != last_tsn.wrapping_add(1) {
                 start_idx = -1;
                 continue;
             }
@@ -237,6 +244,10 @@ impl ReassemblyQueue {
     }
 
     pub(crate) fn is_readable(&self) -> bool {
+        if !self.ended_flows.is_empty() || self.closed {
+            return true;
+        }
+
         // Check unordered first
         if !self.unordered.is_empty() {
             // The chunk sets in r.unordered should all be complete.
@@ -254,8 +265,42 @@ impl ReassemblyQueue {
     }
 
     pub(crate) fn read(&mut self, buf: &mut [u8]) -> Result<(usize, PayloadProtocolIdentifier)> {
-        // Check unordered first
-        let cset = if !self.unordered.is_empty() {
+        match self.next_chunk_set()? {
+            Some(cset) => self.copy_chunk_set(cset, buf),
+            None => Ok((0, PayloadProtocolIdentifier::Unknown)),
+        }
+    }
+
+    /// read_to_vec reads like read, but grows buf to fit the message instead of failing with
+    /// ErrShortBuffer.
+    pub(crate) fn read_to_vec(
+        &mut self,
+        buf: &mut Vec<u8>,
+    ) -> Result<(usize, PayloadProtocolIdentifier)> {
+        match self.next_chunk_set()? {
+            Some(cset) => {
+                let len = cset.chunks.iter().map(|c| c.user_data.len()).sum();
+                if buf.len() < len {
+                    buf.resize(len, 0);
+                }
+                self.copy_chunk_set(cset, buf)
+            }
+            None => Ok((0, PayloadProtocolIdentifier::Unknown)),
+        }
+    }
+
+    /// next_chunk_set removes the next message to read, None once the stream ended.
+    fn next_chunk_set(&mut self) -> Result<Option<ChunkSet>> {
+        // The messages of ended flows go first
+        let cset = if let Some(cset) = self.ended_flows.pop_front() {
+            match cset {
+                Some(cset) => cset,
+                None => return Ok(None),
+            }
+        } else if self.closed {
+            return Ok(None);
+        } else if !self.unordered.is_empty() {
+            // Check unordered first
             self.unordered.remove(0)
         } else if !self.ordered.is_empty() {
             // Now, check ordered
@@ -274,6 +319,14 @@ impl ReassemblyQueue {
             return Err(Error::ErrTryAgain);
         };
 
+        Ok(Some(cset))
+    }
+
+    fn copy_chunk_set(
+        &mut self,
+        cset: ChunkSet,
+        buf: &mut [u8],
+    ) -> Result<(usize, PayloadProtocolIdentifier)> {
         // Concat all fragments into the buffer
         let mut n_written = 0;
         let mut err = None;
@@ -339,6 +392,49 @@ impl ReassemblyQueue {
         }
     }
 
+    /// end_flow ends the incoming flow when the peer reset the stream (RFC 6525 Sec 5.2.2). By
+    /// then all of its chunks were received, its complete messages are read before an EOF,
+    /// ordered ones in SSN order. Incomplete ones, i.e. abandoned ones, are dropped. The next flow
+    /// starts over at SSN 0.
+    pub(crate) fn end_flow(&mut self) {
+        let unordered = std::mem::take(&mut self.unordered);
+        self.ended_flows.extend(unordered.into_iter().map(Some));
+
+        let ordered = std::mem::take(&mut self.ordered);
+        let (complete, incomplete): (Vec<ChunkSet>, Vec<ChunkSet>) =
+            ordered.into_iter().partition(|s| s.is_complete());
+        self.ended_flows.extend(complete.into_iter().map(Some));
+        self.ended_flows.push_back(None);
+
+        let num_bytes = incomplete
+            .iter()
+            .flat_map(|s| s.chunks.iter())
+            .chain(self.unordered_chunks.iter())
+            .fold(0, |n, c| n + c.user_data.len());
+        self.subtract_num_bytes(num_bytes);
+        self.unordered_chunks.clear();
+
+        self.next_ssn = 0;
+    }
+
+    /// discard_flow drops the incoming flow without reading it, the next flow starts over at
+    /// SSN 0.
+    pub(crate) fn discard_flow(&mut self) {
+        let num_bytes = self
+            .ordered
+            .iter()
+            .chain(self.unordered.iter())
+            .flat_map(|s| s.chunks.iter())
+            .chain(self.unordered_chunks.iter())
+            .fold(0, |n, c| n + c.user_data.len());
+        self.subtract_num_bytes(num_bytes);
+        self.ordered.clear();
+        self.unordered.clear();
+        self.unordered_chunks.clear();
+
+        self.next_ssn = 0;
+    }
+
     pub(crate) fn subtract_num_bytes(&mut self, n_bytes: usize) {
         if self.n_bytes >= n_bytes {
             self.n_bytes -= n_bytes;
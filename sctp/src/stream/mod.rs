@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod stream_test;
 
+use crate::association::association_buffer::{AssociationBuffer, BufferFullPolicy};
 use crate::association::AssociationState;
 use crate::chunk::chunk_payload_data::{ChunkPayloadData, PayloadProtocolIdentifier};
 use crate::error::{Error, Result};
@@ -10,6 +11,7 @@ use crate::queue::reassembly_queue::ReassemblyQueue;
 use arc_swap::ArcSwapOption;
 use bytes::Bytes;
 use std::{
+    collections::VecDeque,
     fmt,
     future::Future,
     io,
@@ -17,12 +19,13 @@ use std::{
     pin::Pin,
     sync::atomic::{AtomicBool, AtomicU16, AtomicU32, AtomicU8, AtomicUsize, Ordering},
     sync::Arc,
-    task::{Context, Poll},
+    task::{Context, Poll, Waker},
 };
 use tokio::{
     io::{AsyncRead, AsyncWrite, ReadBuf},
     sync::{mpsc, Mutex, Notify},
 };
+use util::sync::Mutex as SyncMutex;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(C)]
@@ -62,9 +65,58 @@ impl From<u8> for ReliabilityType {
     }
 }
 
+/// DEFAULT_STREAM_PRIORITY is the priority of a stream whose priority was never set. It matches
+/// the "normal" priority of the data channel establishment protocol (RFC 8832).
+pub const DEFAULT_STREAM_PRIORITY: u16 = 256;
+
 pub type OnBufferedAmountLowFn =
     Box<dyn (FnMut() -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>) + Send + Sync>;
 
+/// register_waker adds `waker` to the waiter list unless it would wake the same task as a
+/// waker already registered.
+pub(crate) fn register_waker(wakers: &SyncMutex<Vec<Waker>>, waker: &Waker) {
+    let mut wakers = wakers.lock();
+    if !wakers.iter().any(|w| w.will_wake(waker)) {
+        wakers.push(waker.clone());
+    }
+}
+
+/// wake_all wakes and removes every waker of the waiter list.
+pub(crate) fn wake_all(wakers: &SyncMutex<Vec<Waker>>) {
+    let wakers = std::mem::take(&mut *wakers.lock());
+    for waker in wakers {
+        waker.wake();
+    }
+}
+
+/// Held is what is held back in the outgoing direction of a stream while a reset is in progress.
+#[derive(Debug)]
+pub(crate) enum Held {
+    /// The fragments of a message
+    Data(Vec<ChunkPayloadData>),
+    /// The empty chunk requesting a reset of the outgoing direction
+    Reset(ChunkPayloadData),
+    /// What follows is held until the peer reset its outgoing direction of an earlier incarnation
+    /// of the stream
+    IncomingReset,
+}
+
+/// OutgoingReset holds back the messages written while an outgoing SSN reset of the stream is in
+/// progress, so that they are only sent once the peer performed the reset.
+#[derive(Debug, Default)]
+pub(crate) struct OutgoingReset {
+    /// Set from sending a reset request until the peer responded
+    pub(crate) in_progress: bool,
+    /// In the order they were written
+    pub(crate) held: VecDeque<Held>,
+}
+
+impl OutgoingReset {
+    fn is_holding(&self) -> bool {
+        self.in_progress || !self.held.is_empty()
+    }
+}
+
 // TODO: benchmark performance between multiple Atomic+Mutex vs one Mutex<StreamInternal>
 
 /// Stream represents an SCTP stream
@@ -75,20 +127,32 @@ pub struct Stream {
     pub(crate) state: Arc<AtomicU8>,             // clone from association
     pub(crate) awake_write_loop_ch: Option<Arc<mpsc::Sender<()>>>,
     pub(crate) pending_queue: Arc<PendingQueue>,
+    pub(crate) association_buffer: Arc<AssociationBuffer>, // clone from association
 
     pub(crate) stream_identifier: u16,
     pub(crate) default_payload_type: AtomicU32, //PayloadProtocolIdentifier,
     pub(crate) reassembly_queue: Mutex<ReassemblyQueue>,
     pub(crate) sequence_number: AtomicU16,
     pub(crate) read_notifier: Notify,
+    pub(crate) read_wakers: SyncMutex<Vec<Waker>>,
+    pub(crate) write_wakers: SyncMutex<Vec<Waker>>,
     pub(crate) read_shutdown: AtomicBool,
     pub(crate) write_shutdown: AtomicBool,
     pub(crate) unordered: AtomicBool,
     pub(crate) reliability_type: AtomicU8, //ReliabilityType,
     pub(crate) reliability_value: AtomicU32,
+    pub(crate) priority: AtomicU16,
     pub(crate) buffered_amount: AtomicUsize,
     pub(crate) buffered_amount_low: AtomicUsize,
     pub(crate) on_buffered_amount_low: ArcSwapOption<Mutex<OnBufferedAmountLowFn>>,
+    pub(crate) outgoing_reset: SyncMutex<OutgoingReset>,
+    /// Set once both halves were shutdown and the reset request was queued
+    pub(crate) closing: AtomicBool,
+    /// Set once the peer reset its outgoing direction of the stream
+    pub(crate) incoming_reset: AtomicBool,
+    /// Resets of the peer still to come for earlier incarnations of a reopened stream, see
+    /// [`Stream::take_over`]
+    pub(crate) awaiting_incoming_resets: AtomicUsize,
     pub(crate) name: String,
 }
 
@@ -108,47 +172,100 @@ impl fmt::Debug for Stream {
             .field("unordered", &self.unordered)
             .field("reliability_type", &self.reliability_type)
             .field("reliability_value", &self.reliability_value)
+            .field("priority", &self.priority)
             .field("buffered_amount", &self.buffered_amount)
             .field("buffered_amount_low", &self.buffered_amount_low)
+            .field("outgoing_reset", &self.outgoing_reset)
+            .field("closing", &self.closing)
+            .field("incoming_reset", &self.incoming_reset)
+            .field("awaiting_incoming_resets", &self.awaiting_incoming_resets)
             .field("name", &self.name)
             .finish()
     }
 }
 
+/// StreamConfig holds the state a stream shares with its association.
+pub(crate) struct StreamConfig {
+    pub(crate) max_payload_size: u32,
+    pub(crate) max_message_size: Arc<AtomicU32>,
+    pub(crate) state: Arc<AtomicU8>,
+    pub(crate) awake_write_loop_ch: Option<Arc<mpsc::Sender<()>>>,
+    pub(crate) pending_queue: Arc<PendingQueue>,
+    pub(crate) association_buffer: Arc<AssociationBuffer>,
+}
+
 impl Stream {
-    pub(crate) fn new(
-        name: String,
-        stream_identifier: u16,
-        max_payload_size: u32,
-        max_message_size: Arc<AtomicU32>,
-        state: Arc<AtomicU8>,
-        awake_write_loop_ch: Option<Arc<mpsc::Sender<()>>>,
-        pending_queue: Arc<PendingQueue>,
-    ) -> Self {
+    pub(crate) fn new(name: String, stream_identifier: u16, config: StreamConfig) -> Self {
+        let StreamConfig {
+            max_payload_size,
+            max_message_size,
+            state,
+            awake_write_loop_ch,
+            pending_queue,
+            association_buffer,
+        } = config;
+
+        // the stream identifier may have been used by a previous stream
+        pending_queue.set_stream_priority(stream_identifier, DEFAULT_STREAM_PRIORITY);
+
         Stream {
             max_payload_size,
             max_message_size,
             state,
             awake_write_loop_ch,
             pending_queue,
+            association_buffer,
 
             stream_identifier,
             default_payload_type: AtomicU32::new(0), //PayloadProtocolIdentifier::Unknown,
             reassembly_queue: Mutex::new(ReassemblyQueue::new(stream_identifier)),
             sequence_number: AtomicU16::new(0),
             read_notifier: Notify::new(),
+            read_wakers: SyncMutex::new(vec![]),
+            write_wakers: SyncMutex::new(vec![]),
             read_shutdown: AtomicBool::new(false),
             write_shutdown: AtomicBool::new(false),
             unordered: AtomicBool::new(false),
             reliability_type: AtomicU8::new(0), //ReliabilityType::Reliable,
             reliability_value: AtomicU32::new(0),
+            priority: AtomicU16::new(DEFAULT_STREAM_PRIORITY),
             buffered_amount: AtomicUsize::new(0),
             buffered_amount_low: AtomicUsize::new(0),
             on_buffered_amount_low: ArcSwapOption::empty(),
+            outgoing_reset: SyncMutex::new(OutgoingReset::default()),
+            closing: AtomicBool::new(false),
+            incoming_reset: AtomicBool::new(false),
+            awaiting_incoming_resets: AtomicUsize::new(0),
             name,
         }
     }
 
+    /// take_over makes this stream the new incarnation of `old`, a stream with the same
+    /// identifier which was closed but whose resets may not have completed yet. The messages
+    /// written to this stream are held back until the peer performed the reset of `old` and
+    /// reset its own direction, so the new flows in both directions start over at SSN 0. Until
+    /// then, the messages of the peer for `old` are discarded.
+    pub(crate) fn take_over(&self, old: &Stream) {
+        let mut outgoing_reset = std::mem::take(&mut *old.outgoing_reset.lock());
+        let mut awaiting = old.awaiting_incoming_resets.load(Ordering::SeqCst);
+        if !old.incoming_reset.load(Ordering::SeqCst) {
+            outgoing_reset.held.push_back(Held::IncomingReset);
+            awaiting += 1;
+        }
+        *self.outgoing_reset.lock() = outgoing_reset;
+        self.awaiting_incoming_resets
+            .store(awaiting, Ordering::SeqCst);
+
+        // The bytes of `old` still in flight are released by the acknowledgements to this stream
+        self.buffered_amount.store(
+            old.buffered_amount.swap(0, Ordering::SeqCst),
+            Ordering::SeqCst,
+        );
+
+        old.shutdown_read_half();
+        old.shutdown_write_half();
+    }
+
     /// stream_identifier returns the Stream identifier associated to the stream.
     pub fn stream_identifier(&self) -> u16 {
         self.stream_identifier
@@ -160,6 +277,22 @@ impl Stream {
             .store(default_payload_type as u32, Ordering::SeqCst);
     }
 
+    /// priority returns the priority of this stream, [`DEFAULT_STREAM_PRIORITY`] unless set.
+    pub fn priority(&self) -> u16 {
+        self.priority.load(Ordering::SeqCst)
+    }
+
+    /// set_priority sets the priority of the data written to this stream from now on. Pending
+    /// data of the association is scheduled by weighted fair queueing between priorities, a
+    /// stream with twice the priority of another gets twice its share of the sending capacity.
+    /// Data already queued keeps the priority it was written with.
+    pub fn set_priority(&self, priority: u16) {
+        log::debug!("[{}] priority: {}", self.name, priority);
+        self.priority.store(priority, Ordering::SeqCst);
+        self.pending_queue
+            .set_stream_priority(self.stream_identifier, priority);
+    }
+
     /// set_reliability_params sets reliability parameters for this stream.
     pub fn set_reliability_params(&self, unordered: bool, rel_type: ReliabilityType, rel_val: u32) {
         log::debug!(
@@ -175,6 +308,16 @@ impl Stream {
         self.reliability_value.store(rel_val, Ordering::SeqCst);
     }
 
+    /// reliability_params returns the reliability parameters data is written with, in the order
+    /// taken by [`Stream::set_reliability_params`].
+    pub fn reliability_params(&self) -> (bool, ReliabilityType, u32) {
+        (
+            self.unordered.load(Ordering::SeqCst),
+            self.reliability_type.load(Ordering::SeqCst).into(),
+            self.reliability_value.load(Ordering::SeqCst),
+        )
+    }
+
     /// Reads a packet of len(p) bytes, dropping the Payload Protocol Identifier.
     ///
     /// Returns `Error::ErrShortBuffer` if `p` is too short.
@@ -189,6 +332,24 @@ impl Stream {
     /// Returns `Error::ErrShortBuffer` if `p` is too short.
     /// Returns `(0, PayloadProtocolIdentifier::Unknown)` if the reading half of this stream is shutdown or it (the stream) was reset.
     pub async fn read_sctp(&self, p: &mut [u8]) -> Result<(usize, PayloadProtocolIdentifier)> {
+        self.read_with(|reassembly_queue| reassembly_queue.read(p))
+            .await
+    }
+
+    /// Reads a packet like [`Stream::read_sctp`], but grows `p` to fit it instead of returning
+    /// `Error::ErrShortBuffer`.
+    pub async fn read_sctp_to_vec(
+        &self,
+        p: &mut Vec<u8>,
+    ) -> Result<(usize, PayloadProtocolIdentifier)> {
+        self.read_with(|reassembly_queue| reassembly_queue.read_to_vec(p))
+            .await
+    }
+
+    async fn read_with<F>(&self, mut read: F) -> Result<(usize, PayloadProtocolIdentifier)>
+    where
+        F: FnMut(&mut ReassemblyQueue) -> Result<(usize, PayloadProtocolIdentifier)>,
+    {
         loop {
             if self.read_shutdown.load(Ordering::SeqCst) {
                 return Ok((0, PayloadProtocolIdentifier::Unknown));
@@ -196,7 +357,7 @@ impl Stream {
 
             let result = {
                 let mut reassembly_queue = self.reassembly_queue.lock().await;
-                reassembly_queue.read(p)
+                read(&mut reassembly_queue)
             };
 
             match result {
@@ -209,7 +370,67 @@ impl Stream {
         }
     }
 
+    /// Attempts to read a packet of len(p) bytes and the associated Payload Protocol Identifier.
+    ///
+    /// Returns `Poll::Pending` if no packet is available yet, the current task is then woken
+    /// when one arrives or the reading half of this stream is shutdown. Otherwise behaves like
+    /// [`Stream::read_sctp`].
+    pub fn poll_read_sctp(
+        &self,
+        cx: &mut Context<'_>,
+        p: &mut [u8],
+    ) -> Poll<Result<(usize, PayloadProtocolIdentifier)>> {
+        if let Poll::Ready(result) = self.try_read_sctp(cx, p) {
+            return Poll::Ready(result);
+        }
+
+        register_waker(&self.read_wakers, cx.waker());
+
+        // Check again, a chunk may have become readable before the waker was registered.
+        self.try_read_sctp(cx, p)
+    }
+
+    fn try_read_sctp(
+        &self,
+        cx: &mut Context<'_>,
+        p: &mut [u8],
+    ) -> Poll<Result<(usize, PayloadProtocolIdentifier)>> {
+        if self.read_shutdown.load(Ordering::SeqCst) {
+            return Poll::Ready(Ok((0, PayloadProtocolIdentifier::Unknown)));
+        }
+
+        let mut reassembly_queue = match self.reassembly_queue.try_lock() {
+            Ok(reassembly_queue) => reassembly_queue,
+            Err(_) => {
+                // The lock is never held across an await point, so retry right away.
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+        };
+
+        match reassembly_queue.read(p) {
+            result @ (Ok(_) | Err(Error::ErrShortBuffer)) => Poll::Ready(result),
+            Err(_) => Poll::Pending,
+        }
+    }
+
+    /// notify_readable wakes up a pending reader, either [`Stream::read_sctp`] or
+    /// [`Stream::poll_read_sctp`].
+    fn notify_readable(&self) {
+        self.read_notifier.notify_one();
+        wake_all(&self.read_wakers);
+    }
+
     pub(crate) async fn handle_data(&self, pd: ChunkPayloadData) {
+        if self.awaiting_incoming_resets.load(Ordering::SeqCst) > 0 {
+            log::debug!(
+                "[{}] discarding ssn={} sent before the reset of the stream",
+                self.name,
+                pd.stream_sequence_number
+            );
+            return;
+        }
+
         let readable = {
             let mut reassembly_queue = self.reassembly_queue.lock().await;
             if reassembly_queue.push(pd) {
@@ -223,11 +444,62 @@ impl Stream {
 
         if readable {
             log::debug!("[{}] readNotifier.signal()", self.name);
-            self.read_notifier.notify_one();
+            self.notify_readable();
             log::debug!("[{}] readNotifier.signal() done", self.name);
         }
     }
 
+    /// handle_incoming_reset performs the reset of the incoming direction requested by the peer.
+    /// The reader gets the rest of the flow followed by an EOF, unless the reset is for an
+    /// earlier incarnation of the stream.
+    pub(crate) async fn handle_incoming_reset(&self) {
+        if self.awaiting_incoming_resets.load(Ordering::SeqCst) > 0 {
+            self.reassembly_queue.lock().await.discard_flow();
+            self.awaiting_incoming_resets.fetch_sub(1, Ordering::SeqCst);
+            {
+                let mut outgoing_reset = self.outgoing_reset.lock();
+                if let Some(i) = outgoing_reset
+                    .held
+                    .iter()
+                    .position(|h| matches!(h, Held::IncomingReset))
+                {
+                    outgoing_reset.held.remove(i);
+                }
+            }
+            self.release_held();
+            return;
+        }
+
+        self.incoming_reset.store(true, Ordering::SeqCst);
+        self.reassembly_queue.lock().await.end_flow();
+        self.notify_readable();
+    }
+
+    /// handle_outgoing_reset_done is called once the peer responded to the reset request of the
+    /// outgoing direction, the messages held back meanwhile are sent.
+    pub(crate) fn handle_outgoing_reset_done(&self) {
+        self.outgoing_reset.lock().in_progress = false;
+        self.release_held();
+    }
+
+    /// is_reset returns whether the stream was closed and both of its directions reset, it
+    /// isn't used anymore.
+    pub(crate) fn is_reset(&self) -> bool {
+        self.closing.load(Ordering::SeqCst)
+            && self.incoming_reset.load(Ordering::SeqCst)
+            && self.awaiting_incoming_resets.load(Ordering::SeqCst) == 0
+            && !self.outgoing_reset.lock().in_progress
+    }
+
+    /// close_after_reset lets the reader read what is left of the flow before EOF, after both
+    /// directions were reset.
+    pub(crate) async fn close_after_reset(&self) {
+        self.reassembly_queue.lock().await.closed = true;
+        self.notify_readable();
+        self.shutdown_write_half();
+        self.release_association_buffer();
+    }
+
     pub(crate) async fn handle_forward_tsn_for_ordered(&self, ssn: u16) {
         if self.unordered.load(Ordering::SeqCst) {
             return; // unordered chunks are handled by handleForwardUnordered method
@@ -243,7 +515,7 @@ impl Stream {
 
         // Notify the reader asynchronously if there's a data chunk to read.
         if readable {
-            self.read_notifier.notify_one();
+            self.notify_readable();
         }
     }
 
@@ -262,21 +534,68 @@ impl Stream {
 
         // Notify the reader asynchronously if there's a data chunk to read.
         if readable {
-            self.read_notifier.notify_one();
+            self.notify_readable();
         }
     }
 
     /// Writes `p` to the DTLS connection with the default Payload Protocol Identifier.
     ///
     /// Returns an error if the write half of this stream is shutdown or `p` is too large.
-    pub fn write(&self, p: &Bytes) -> Result<usize> {
+    /// If `p` doesn't fit into the association's `max_association_buffered_amount`, waits for
+    /// room with `BufferFullPolicy::Block` and returns `Error::ErrAssociationBufferFull`
+    /// otherwise.
+    pub async fn write(&self, p: &Bytes) -> Result<usize> {
         self.write_sctp(p, self.default_payload_type.load(Ordering::SeqCst).into())
+            .await
     }
 
     /// Writes `p` to the DTLS connection with the given Payload Protocol Identifier.
     ///
     /// Returns an error if the write half of this stream is shutdown or `p` is too large.
-    pub fn write_sctp(&self, p: &Bytes, ppi: PayloadProtocolIdentifier) -> Result<usize> {
+    /// If `p` doesn't fit into the association's `max_association_buffered_amount`, waits for
+    /// room with `BufferFullPolicy::Block` and returns `Error::ErrAssociationBufferFull`
+    /// otherwise.
+    pub async fn write_sctp(&self, p: &Bytes, ppi: PayloadProtocolIdentifier) -> Result<usize> {
+        WriteSctp {
+            stream: self,
+            p,
+            ppi,
+        }
+        .await
+    }
+
+    /// Attempts to write `p` with the given Payload Protocol Identifier.
+    ///
+    /// Returns `Poll::Pending` if `p` doesn't fit into the association's
+    /// `max_association_buffered_amount` with `BufferFullPolicy::Block`, the current task is
+    /// then woken when buffered data is acknowledged or the writing half of this stream is
+    /// shutdown. Otherwise behaves like [`Stream::write_sctp`].
+    pub fn poll_write_sctp(
+        &self,
+        cx: &mut Context<'_>,
+        p: &Bytes,
+        ppi: PayloadProtocolIdentifier,
+    ) -> Poll<Result<usize>> {
+        loop {
+            match self.try_write_sctp(p, ppi) {
+                Err(Error::ErrAssociationBufferFull)
+                    if self.association_buffer.policy() == BufferFullPolicy::Block =>
+                {
+                    register_waker(&self.association_buffer.write_wakers, cx.waker());
+
+                    // Check again, room may have been released before the waker was registered.
+                    if self.association_buffer.is_full(p.len())
+                        && !self.write_shutdown.load(Ordering::SeqCst)
+                    {
+                        return Poll::Pending;
+                    }
+                }
+                result => return Poll::Ready(result),
+            }
+        }
+    }
+
+    fn try_write_sctp(&self, p: &Bytes, ppi: PayloadProtocolIdentifier) -> Result<usize> {
         if self.write_shutdown.load(Ordering::SeqCst) {
             return Err(Error::ErrStreamClosed);
         }
@@ -294,8 +613,13 @@ impl Stream {
             _ => {}
         };
 
+        self.association_buffer.reserve(p.len())?;
+
         let chunks = self.packetize(p, ppi);
-        self.send_payload_data(chunks)?;
+        if let Err(err) = self.send_payload_data(chunks) {
+            self.association_buffer.release(p.len());
+            return Err(err);
+        }
 
         Ok(p.len())
     }
@@ -355,6 +679,29 @@ impl Stream {
         chunks
     }
 
+    /// Resets the outgoing direction of this stream without closing it, so that it can be reused
+    /// for a new flow of messages (RFC 6525 Sec 5.1.2).
+    ///
+    /// The messages written so far are sent first. Later messages start over at SSN 0 and are
+    /// held back until the peer performed the reset, its reader reads them after an EOF ending
+    /// the previous flow. It must not be called concurrently with writes to the stream.
+    pub fn reset_sequence(&self) -> Result<()> {
+        if self.write_shutdown.load(Ordering::SeqCst) {
+            return Err(Error::ErrStreamClosed);
+        }
+
+        self.send_reset_request(self.stream_identifier)?;
+        self.sequence_number.store(0, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    /// is_incoming_reset returns whether the peer reset its outgoing direction of this stream,
+    /// ending the flow read from it.
+    pub fn is_incoming_reset(&self) -> bool {
+        self.incoming_reset.load(Ordering::SeqCst)
+    }
+
     /// Closes both read and write halves of this stream.
     ///
     /// Use [`Stream::shutdown`] instead.
@@ -370,18 +717,20 @@ impl Stream {
     ///
     /// Resets the stream when both halves of this stream are shutdown.
     pub async fn shutdown(&self, how: Shutdown) -> Result<()> {
+        self.shutdown_halves(how)
+    }
+
+    fn shutdown_halves(&self, how: Shutdown) -> Result<()> {
         if self.read_shutdown.load(Ordering::SeqCst) && self.write_shutdown.load(Ordering::SeqCst) {
             return Ok(());
         }
 
         if how == Shutdown::Write || how == Shutdown::Both {
-            self.write_shutdown.store(true, Ordering::SeqCst);
+            self.shutdown_write_half();
         }
 
-        if (how == Shutdown::Read || how == Shutdown::Both)
-            && !self.read_shutdown.swap(true, Ordering::SeqCst)
-        {
-            self.read_notifier.notify_waiters();
+        if how == Shutdown::Read || how == Shutdown::Both {
+            self.shutdown_read_half();
         }
 
         if how == Shutdown::Both
@@ -391,11 +740,34 @@ impl Stream {
             // Reset the stream
             // https://tools.ietf.org/html/rfc6525
             self.send_reset_request(self.stream_identifier)?;
+            self.closing.store(true, Ordering::SeqCst);
         }
 
         Ok(())
     }
 
+    /// shutdown_read_half makes all pending and future reads return 0.
+    pub(crate) fn shutdown_read_half(&self) {
+        if !self.read_shutdown.swap(true, Ordering::SeqCst) {
+            self.read_notifier.notify_waiters();
+            wake_all(&self.read_wakers);
+        }
+    }
+
+    /// shutdown_write_half makes all pending and future writes fail.
+    pub(crate) fn shutdown_write_half(&self) {
+        if !self.write_shutdown.swap(true, Ordering::SeqCst) {
+            wake_all(&self.write_wakers);
+            // Writers of this stream may be waiting for room in the association buffer.
+            wake_all(&self.association_buffer.write_wakers);
+        }
+    }
+
+    /// max_message_size returns the largest message `write` accepts, as set on the association.
+    pub fn max_message_size(&self) -> u32 {
+        self.max_message_size.load(Ordering::SeqCst)
+    }
+
     /// buffered_amount returns the number of bytes of data currently queued to be sent over this stream.
     pub fn buffered_amount(&self) -> usize {
         self.buffered_amount.load(Ordering::SeqCst)
@@ -444,6 +816,10 @@ impl Stream {
             from_amount - n_bytes_released as usize
         };
 
+        wake_all(&self.write_wakers);
+
+        let association_buffer_low = self.association_buffer.release(from_amount - new_amount);
+
         let buffered_amount_low = self.buffered_amount_low.load(Ordering::SeqCst);
 
         log::trace!(
@@ -460,6 +836,17 @@ impl Stream {
                 f().await;
             }
         }
+
+        if association_buffer_low {
+            self.association_buffer.fire_on_buffered_amount_low().await;
+        }
+    }
+
+    /// release_association_buffer gives back the association buffer space still held by
+    /// this stream, whose remaining data will never be acknowledged to it.
+    pub(crate) fn release_association_buffer(&self) {
+        self.association_buffer
+            .release(self.buffered_amount.swap(0, Ordering::SeqCst));
     }
 
     /// get_num_bytes_in_reassembly_queue returns the number of bytes of data currently queued to
@@ -488,13 +875,44 @@ impl Stream {
             return Err(Error::ErrPayloadDataStateNotExist);
         }
 
-        // NOTE: append is used here instead of push in order to prevent chunks interlacing.
-        self.pending_queue.append(chunks);
+        {
+            let mut outgoing_reset = self.outgoing_reset.lock();
+            if outgoing_reset.is_holding() {
+                outgoing_reset.held.push_back(Held::Data(chunks));
+                return Ok(());
+            }
+
+            // NOTE: append is used here instead of push in order to prevent chunks interlacing.
+            self.pending_queue.append(chunks);
+        }
 
         self.awake_write_loop();
         Ok(())
     }
 
+    /// release_held sends what is held back, up to the next reset to wait for.
+    fn release_held(&self) {
+        {
+            let mut outgoing_reset = self.outgoing_reset.lock();
+            while !outgoing_reset.in_progress {
+                match outgoing_reset.held.pop_front() {
+                    Some(Held::Data(chunks)) => self.pending_queue.append(chunks),
+                    Some(Held::Reset(c)) => {
+                        outgoing_reset.in_progress = true;
+                        self.pending_queue.push(c);
+                    }
+                    Some(Held::IncomingReset) => {
+                        outgoing_reset.held.push_front(Held::IncomingReset);
+                        break;
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        self.awake_write_loop();
+    }
+
     fn send_reset_request(&self, stream_identifier: u16) -> Result<()> {
         let state = self.get_state();
         if state != AssociationState::Established {
@@ -511,74 +929,80 @@ impl Stream {
             ..Default::default()
         };
 
-        self.pending_queue.push(c);
+        {
+            let mut outgoing_reset = self.outgoing_reset.lock();
+            if outgoing_reset.is_holding() {
+                outgoing_reset.held.push_back(Held::Reset(c));
+                return Ok(());
+            }
+
+            outgoing_reset.in_progress = true;
+            self.pending_queue.push(c);
+        }
 
         self.awake_write_loop();
         Ok(())
     }
 }
 
-/// Default capacity of the temporary read buffer used by [`PollStream`].
-const DEFAULT_READ_BUF_SIZE: usize = 8192;
-
-/// State of the read `Future` in [`PollStream`].
-enum ReadFut {
-    /// Nothing in progress.
-    Idle,
-    /// Reading data from the underlying stream.
-    Reading(Pin<Box<dyn Future<Output = Result<Vec<u8>>> + Send>>),
-    /// Finished reading, but there's unread data in the temporary buffer.
-    RemainingData(Vec<u8>),
+/// WriteSctp is the future of [`Stream::write_sctp`].
+struct WriteSctp<'a> {
+    stream: &'a Stream,
+    p: &'a Bytes,
+    ppi: PayloadProtocolIdentifier,
 }
 
-impl ReadFut {
-    /// Gets a mutable reference to the future stored inside `Reading(future)`.
-    ///
-    /// # Panics
-    ///
-    /// Panics if `ReadFut` variant is not `Reading`.
-    fn get_reading_mut(&mut self) -> &mut Pin<Box<dyn Future<Output = Result<Vec<u8>>> + Send>> {
-        match self {
-            ReadFut::Reading(ref mut fut) => fut,
-            _ => panic!("expected ReadFut to be Reading"),
-        }
+impl Future for WriteSctp<'_> {
+    type Output = Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.stream.poll_write_sctp(cx, self.p, self.ppi)
     }
 }
 
+/// Default capacity of the read buffer used by [`StreamIo`].
+const DEFAULT_READ_BUF_SIZE: usize = 8192;
+
 /// A wrapper around around [`Stream`], which implements [`AsyncRead`] and
 /// [`AsyncWrite`].
 ///
-/// Both `poll_read` and `poll_write` calls allocate temporary buffers, which results in an
-/// additional overhead.
-pub struct PollStream {
+/// Readiness is driven by wakers registered on the stream, so polling does not allocate. Packets
+/// are read into a buffer allocated once, unless the caller's buffer can hold
+/// [`StreamIo::set_read_buf_capacity`] bytes, in which case they are read into it directly.
+pub struct StreamIo {
     stream: Arc<Stream>,
 
-    read_fut: ReadFut,
-    write_fut: Option<Pin<Box<dyn Future<Output = Result<usize>> + Send>>>,
-    shutdown_fut: Option<Pin<Box<dyn Future<Output = Result<()>> + Send>>>,
-
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    read_len: usize,
     read_buf_cap: usize,
+
+    write_buffer_limit: usize,
 }
 
-impl PollStream {
-    /// Constructs a new `PollStream`.
+/// Former name of [`StreamIo`], kept for compatibility.
+pub type PollStream = StreamIo;
+
+impl StreamIo {
+    /// Constructs a new `StreamIo`.
     ///
     /// # Examples
     ///
     /// ```
-    /// use webrtc_sctp::stream::{Stream, PollStream};
+    /// use webrtc_sctp::stream::{Stream, StreamIo};
     /// use std::sync::Arc;
     ///
     /// let stream = Arc::new(Stream::default());
-    /// let poll_stream = PollStream::new(stream);
+    /// let stream_io = StreamIo::new(stream);
     /// ```
     pub fn new(stream: Arc<Stream>) -> Self {
         Self {
             stream,
-            read_fut: ReadFut::Idle,
-            write_fut: None,
-            shutdown_fut: None,
+            read_buf: vec![],
+            read_pos: 0,
+            read_len: 0,
             read_buf_cap: DEFAULT_READ_BUF_SIZE,
+            write_buffer_limit: usize::MAX,
         }
     }
 
@@ -618,13 +1042,19 @@ impl PollStream {
         reassembly_queue.get_num_bytes()
     }
 
-    /// Set the capacity of the temporary read buffer (default: 8192).
+    /// Set the capacity of the read buffer (default: 8192). Packets larger than it can't be read.
     pub fn set_read_buf_capacity(&mut self, capacity: usize) {
         self.read_buf_cap = capacity
     }
+
+    /// Set the amount of buffered outgoing data above which `poll_write` returns
+    /// `Poll::Pending` until the peer acknowledges enough of it (default: no limit).
+    pub fn set_write_buffer_limit(&mut self, limit: usize) {
+        self.write_buffer_limit = limit
+    }
 }
 
-impl AsyncRead for PollStream {
+impl AsyncRead for StreamIo {
     fn poll_read(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -634,143 +1064,107 @@ impl AsyncRead for PollStream {
             return Poll::Ready(Ok(()));
         }
 
-        let fut = match self.read_fut {
-            ReadFut::Idle => {
-                // read into a temporary buffer because `buf` has an unonymous lifetime, which can
-                // be shorter than the lifetime of `read_fut`.
-                let stream = self.stream.clone();
-                let mut temp_buf = vec![0; self.read_buf_cap];
-                self.read_fut = ReadFut::Reading(Box::pin(async move {
-                    stream.read(temp_buf.as_mut_slice()).await.map(|n| {
-                        temp_buf.truncate(n);
-                        temp_buf
-                    })
-                }));
-                self.read_fut.get_reading_mut()
-            }
-            ReadFut::Reading(ref mut fut) => fut,
-            ReadFut::RemainingData(ref mut data) => {
-                let remaining = buf.remaining();
-                let len = std::cmp::min(data.len(), remaining);
-                buf.put_slice(&data[..len]);
-                if data.len() > remaining {
-                    // ReadFut remains to be RemainingData
-                    data.drain(0..len);
-                } else {
-                    self.read_fut = ReadFut::Idle;
-                }
-                return Poll::Ready(Ok(()));
-            }
-        };
+        let this = &mut *self;
 
-        loop {
-            match fut.as_mut().poll(cx) {
-                Poll::Pending => return Poll::Pending,
-                // retry immediately upon empty data or incomplete chunks
-                // since there's no way to setup a waker.
-                Poll::Ready(Err(Error::ErrTryAgain)) => {}
-                // EOF has been reached => don't touch buf and just return Ok
-                Poll::Ready(Err(Error::ErrEof)) => {
-                    self.read_fut = ReadFut::Idle;
-                    return Poll::Ready(Ok(()));
-                }
-                Poll::Ready(Err(e)) => {
-                    self.read_fut = ReadFut::Idle;
-                    return Poll::Ready(Err(e.into()));
-                }
-                Poll::Ready(Ok(mut temp_buf)) => {
-                    let remaining = buf.remaining();
-                    let len = std::cmp::min(temp_buf.len(), remaining);
-                    buf.put_slice(&temp_buf[..len]);
-                    if temp_buf.len() > remaining {
-                        temp_buf.drain(0..len);
-                        self.read_fut = ReadFut::RemainingData(temp_buf);
-                    } else {
-                        self.read_fut = ReadFut::Idle;
-                    }
-                    return Poll::Ready(Ok(()));
+        // Hand out what's left of the previous packet first.
+        if this.read_pos < this.read_len {
+            let len = std::cmp::min(this.read_len - this.read_pos, buf.remaining());
+            buf.put_slice(&this.read_buf[this.read_pos..this.read_pos + len]);
+            this.read_pos += len;
+            return Poll::Ready(Ok(()));
+        }
+
+        if buf.remaining() >= this.read_buf_cap {
+            let unfilled = buf.initialize_unfilled_to(this.read_buf_cap);
+            return match this.stream.poll_read_sctp(cx, unfilled) {
+                Poll::Pending => Poll::Pending,
+                Poll::Ready(Ok((n, _))) => {
+                    buf.advance(n);
+                    Poll::Ready(Ok(()))
                 }
+                Poll::Ready(Err(e)) => Poll::Ready(Err(e.into())),
+            };
+        }
+
+        if this.read_buf.len() != this.read_buf_cap {
+            this.read_buf.resize(this.read_buf_cap, 0);
+        }
+        match this.stream.poll_read_sctp(cx, &mut this.read_buf) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok((n, _))) => {
+                let len = std::cmp::min(n, buf.remaining());
+                buf.put_slice(&this.read_buf[..len]);
+                this.read_pos = len;
+                this.read_len = n;
+                Poll::Ready(Ok(()))
             }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e.into())),
         }
     }
 }
 
-impl AsyncWrite for PollStream {
+impl AsyncWrite for StreamIo {
     fn poll_write(
         self: Pin<&mut Self>,
-        _cx: &mut Context<'_>,
+        cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        let bytes = Bytes::copy_from_slice(buf);
-        match self.stream.write(&bytes) {
-            Ok(n) => Poll::Ready(Ok(n)),
-            Err(e) => Poll::Ready(Err(e.into())),
-        }
-    }
+        let stream = &self.stream;
+        let is_blocked = || {
+            stream.buffered_amount() >= self.write_buffer_limit
+                && !stream.write_shutdown.load(Ordering::SeqCst)
+        };
+        if is_blocked() {
+            register_waker(&stream.write_wakers, cx.waker());
 
-    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        match self.write_fut.as_mut() {
-            Some(fut) => match fut.as_mut().poll(cx) {
-                Poll::Pending => Poll::Pending,
-                Poll::Ready(Err(e)) => {
-                    self.write_fut = None;
-                    Poll::Ready(Err(e.into()))
-                }
-                Poll::Ready(Ok(_)) => {
-                    self.write_fut = None;
-                    Poll::Ready(Ok(()))
-                }
-            },
-            None => Poll::Ready(Ok(())),
+            // Check again, the buffer may have been released before the waker was registered.
+            if is_blocked() {
+                return Poll::Pending;
+            }
         }
-    }
 
-    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        match self.as_mut().poll_flush(cx) {
-            Poll::Pending => return Poll::Pending,
-            Poll::Ready(_) => {}
+        let bytes = Bytes::copy_from_slice(buf);
+        let ppi = stream.default_payload_type.load(Ordering::SeqCst).into();
+        match stream.poll_write_sctp(cx, &bytes, ppi) {
+            Poll::Ready(Ok(n)) => Poll::Ready(Ok(n)),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e.into())),
+            Poll::Pending => Poll::Pending,
         }
+    }
 
-        let fut = match self.shutdown_fut.as_mut() {
-            Some(fut) => fut,
-            None => {
-                let stream = self.stream.clone();
-                self.shutdown_fut.get_or_insert(Box::pin(async move {
-                    stream.shutdown(Shutdown::Write).await
-                }))
-            }
-        };
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // Writes are handed to the association right away, there's nothing to flush.
+        Poll::Ready(Ok(()))
+    }
 
-        match fut.as_mut().poll(cx) {
-            Poll::Pending => Poll::Pending,
-            Poll::Ready(Err(e)) => {
-                self.shutdown_fut = None;
-                Poll::Ready(Err(e.into()))
-            }
-            Poll::Ready(Ok(_)) => {
-                self.shutdown_fut = None;
-                Poll::Ready(Ok(()))
-            }
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.stream.shutdown_halves(Shutdown::Write) {
+            Ok(()) => Poll::Ready(Ok(())),
+            Err(e) => Poll::Ready(Err(e.into())),
         }
     }
 }
 
-impl Clone for PollStream {
-    fn clone(&self) -> PollStream {
-        PollStream::new(self.clone_inner())
+impl Clone for StreamIo {
+    fn clone(&self) -> StreamIo {
+        let mut stream_io = StreamIo::new(self.clone_inner());
+        stream_io.read_buf_cap = self.read_buf_cap;
+        stream_io.write_buffer_limit = self.write_buffer_limit;
+        stream_io
     }
 }
 
-impl fmt::Debug for PollStream {
+impl fmt::Debug for StreamIo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("PollStream")
+        f.debug_struct("StreamIo")
             .field("stream", &self.stream)
             .field("read_buf_cap", &self.read_buf_cap)
+            .field("write_buffer_limit", &self.write_buffer_limit)
             .finish()
     }
 }
 
-impl AsRef<Stream> for PollStream {
+impl AsRef<Stream> for StreamIo {
     fn as_ref(&self) -> &Stream {
         &self.stream
     }
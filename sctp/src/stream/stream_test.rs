@@ -73,15 +73,7 @@ async fn test_stream_amount_on_buffered_amount_low() -> Result<()> {
 
 #[tokio::test]
 async fn test_stream() -> std::result::Result<(), io::Error> {
-    let s = Stream::new(
-        "test_poll_stream".to_owned(),
-        0,
-        4096,
-        Arc::new(AtomicU32::new(4096)),
-        Arc::new(AtomicU8::new(AssociationState::Established as u8)),
-        None,
-        Arc::new(PendingQueue::new()),
-    );
+    let s = Stream::new("test_poll_stream".to_owned(), 0, new_test_stream_config());
 
     // getters
     assert_eq!(0, s.stream_identifier());
@@ -94,10 +86,12 @@ async fn test_stream() -> std::result::Result<(), io::Error> {
     s.set_reliability_params(true, ReliabilityType::Reliable, 0);
 
     // write
-    let n = s.write(&Bytes::from("Hello "))?;
+    let n = s.write(&Bytes::from("Hello ")).await?;
     assert_eq!(6, n);
     assert_eq!(6, s.buffered_amount());
-    let n = s.write_sctp(&Bytes::from("world"), PayloadProtocolIdentifier::Binary)?;
+    let n = s
+        .write_sctp(&Bytes::from("world"), PayloadProtocolIdentifier::Binary)
+        .await?;
     assert_eq!(5, n);
     assert_eq!(11, s.buffered_amount());
 
@@ -120,7 +114,7 @@ async fn test_stream() -> std::result::Result<(), io::Error> {
     // shutdown write
     s.shutdown(Shutdown::Write).await?;
     // write must fail
-    assert!(s.write(&Bytes::from("error")).is_err());
+    assert!(s.write(&Bytes::from("error")).await.is_err());
     // read should continue working
     s.handle_data(ChunkPayloadData {
         unordered: true,
@@ -148,11 +142,7 @@ async fn test_poll_stream() -> std::result::Result<(), io::Error> {
     let s = Arc::new(Stream::new(
         "test_poll_stream".to_owned(),
         0,
-        4096,
-        Arc::new(AtomicU32::new(4096)),
-        Arc::new(AtomicU8::new(AssociationState::Established as u8)),
-        None,
-        Arc::new(PendingQueue::new()),
+        new_test_stream_config(),
     ));
     let mut poll_stream = PollStream::new(s.clone());
 
@@ -209,3 +199,158 @@ async fn test_poll_stream() -> std::result::Result<(), io::Error> {
 
     Ok(())
 }
+
+fn new_test_stream(name: &str) -> Arc<Stream> {
+    Arc::new(Stream::new(name.to_owned(), 0, new_test_stream_config()))
+}
+
+fn new_test_stream_config() -> StreamConfig {
+    StreamConfig {
+        max_payload_size: 4096,
+        max_message_size: Arc::new(AtomicU32::new(4096)),
+        state: Arc::new(AtomicU8::new(AssociationState::Established as u8)),
+        awake_write_loop_ch: None,
+        pending_queue: Arc::new(PendingQueue::new()),
+        association_buffer: Arc::new(AssociationBuffer::default()),
+    }
+}
+
+fn new_test_chunk(ssn: u16, user_data: Bytes) -> ChunkPayloadData {
+    ChunkPayloadData {
+        beginning_fragment: true,
+        ending_fragment: true,
+        stream_sequence_number: ssn,
+        user_data,
+        payload_type: PayloadProtocolIdentifier::Binary,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_stream_io_large_packet_is_split_across_reads() -> std::result::Result<(), io::Error> {
+    let s = new_test_stream("test_stream_io_large_packet_is_split_across_reads");
+    let mut stream_io = StreamIo::new(s.clone());
+
+    s.handle_data(new_test_chunk(
+        0,
+        Bytes::from_static(&[0, 1, 2, 3, 4, 5, 6]),
+    ))
+    .await;
+
+    let mut buf = [0; 4];
+    assert_eq!(4, stream_io.read(&mut buf).await?);
+    assert_eq!(buf, [0, 1, 2, 3]);
+    assert_eq!(3, stream_io.read(&mut buf).await?);
+    assert_eq!(buf[..3], [4, 5, 6]);
+
+    // A buffer of at least the read buffer capacity is filled directly.
+    stream_io.set_read_buf_capacity(8);
+    s.handle_data(new_test_chunk(1, Bytes::from_static(&[7, 8, 9])))
+        .await;
+    let mut buf = [0; 16];
+    assert_eq!(3, stream_io.read(&mut buf).await?);
+    assert_eq!(buf[..3], [7, 8, 9]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stream_io_pending_read_is_woken() -> std::result::Result<(), io::Error> {
+    let s = new_test_stream("test_stream_io_pending_read_is_woken");
+    let mut stream_io = StreamIo::new(s.clone());
+
+    let mut reader = tokio::spawn(async move {
+        let mut buf = [0; 3];
+        let n = stream_io.read(&mut buf).await?;
+        io::Result::Ok(buf[..n].to_vec())
+    });
+
+    assert!(
+        tokio::time::timeout(std::time::Duration::from_millis(10), &mut reader)
+            .await
+            .is_err(),
+        "reader should be pending"
+    );
+
+    s.handle_data(new_test_chunk(0, Bytes::from_static(&[1, 2, 3])))
+        .await;
+    assert_eq!(reader.await??, vec![1, 2, 3]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stream_io_pending_read_is_woken_on_shutdown() -> std::result::Result<(), io::Error> {
+    let s = new_test_stream("test_stream_io_pending_read_is_woken_on_shutdown");
+    let mut stream_io = StreamIo::new(s.clone());
+
+    let reader = tokio::spawn(async move {
+        let mut buf = [0; 3];
+        stream_io.read(&mut buf).await
+    });
+
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    s.shutdown(Shutdown::Read).await?;
+    assert_eq!(0, reader.await??);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stream_io_write_buffer_limit() -> std::result::Result<(), io::Error> {
+    let s = new_test_stream("test_stream_io_write_buffer_limit");
+    let mut stream_io = StreamIo::new(s.clone());
+    stream_io.set_write_buffer_limit(4);
+
+    assert_eq!(4, stream_io.write(&[0, 1, 2, 3]).await?);
+    assert_eq!(4, s.buffered_amount());
+
+    let mut writer = tokio::spawn(async move { stream_io.write(&[4, 5]).await });
+
+    assert!(
+        tokio::time::timeout(std::time::Duration::from_millis(10), &mut writer)
+            .await
+            .is_err(),
+        "writer should be pending"
+    );
+
+    // The peer acknowledged part of the data.
+    s.on_buffer_released(2).await;
+    assert_eq!(2, writer.await??);
+    assert_eq!(4, s.buffered_amount());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_stream_io_concurrent_handle_data_and_read() -> std::result::Result<(), io::Error> {
+    const N: u16 = 2000;
+
+    let s = new_test_stream("test_stream_io_concurrent_handle_data_and_read");
+    let mut stream_io = StreamIo::new(s.clone());
+
+    let reader = tokio::spawn(async move {
+        let mut buf = [0; 2];
+        for i in 0..N {
+            let n = stream_io.read(&mut buf).await?;
+            assert_eq!(2, n);
+            assert_eq!(i.to_be_bytes(), buf);
+        }
+        io::Result::Ok(())
+    });
+
+    for i in 0..N {
+        s.handle_data(new_test_chunk(i, Bytes::copy_from_slice(&i.to_be_bytes())))
+            .await;
+        if i % 16 == 0 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    tokio::time::timeout(std::time::Duration::from_secs(10), reader)
+        .await
+        .expect("reader missed a wakeup")??;
+    assert!(s.read_wakers.lock().len() <= 1);
+
+    Ok(())
+}
@@ -1,7 +1,10 @@
+use crate::clock::SharedClock;
 use async_trait::async_trait;
+use std::fmt;
 use std::sync::Weak;
 use tokio::sync::{mpsc, Mutex};
 use tokio::time::Duration;
+use util::sync::TaskGroup;
 
 pub(crate) const ACK_INTERVAL: Duration = Duration::from_millis(200);
 
@@ -12,20 +15,38 @@ pub(crate) trait AckTimerObserver {
 }
 
 /// ackTimer provides the retnransmission timer conforms with RFC 4960 Sec 6.3.1
-#[derive(Default, Debug)]
 pub(crate) struct AckTimer<T: 'static + AckTimerObserver + Send> {
     pub(crate) timeout_observer: Weak<Mutex<T>>,
     pub(crate) interval: Duration,
     pub(crate) close_tx: Option<mpsc::Sender<()>>,
+    pub(crate) clock: SharedClock,
+    /// The tasks of the association, which the timer runs on
+    pub(crate) tasks: TaskGroup,
+}
+
+impl<T: 'static + AckTimerObserver + Send> fmt::Debug for AckTimer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AckTimer")
+            .field("interval", &self.interval)
+            .field("running", &self.is_running())
+            .finish()
+    }
 }
 
 impl<T: 'static + AckTimerObserver + Send> AckTimer<T> {
     /// newAckTimer creates a new acknowledgement timer used to enable delayed ack.
-    pub(crate) fn new(timeout_observer: Weak<Mutex<T>>, interval: Duration) -> Self {
+    pub(crate) fn new(
+        timeout_observer: Weak<Mutex<T>>,
+        interval: Duration,
+        clock: SharedClock,
+        tasks: TaskGroup,
+    ) -> Self {
         AckTimer {
             timeout_observer,
             interval,
             close_tx: None,
+            clock,
+            tasks,
         }
     }
 
@@ -39,9 +60,9 @@ impl<T: 'static + AckTimerObserver + Send> AckTimer<T> {
         let (close_tx, mut close_rx) = mpsc::channel(1);
         let interval = self.interval;
         let timeout_observer = self.timeout_observer.clone();
+        let timer = self.clock.sleep_until(self.clock.now() + interval);
 
-        tokio::spawn(async move {
-            let timer = tokio::time::sleep(interval);
+        self.tasks.spawn(async move {
             tokio::pin!(timer);
 
             tokio::select! {
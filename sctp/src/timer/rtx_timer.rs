@@ -1,8 +1,11 @@
 use crate::association::RtxTimerId;
+use crate::clock::SharedClock;
 use async_trait::async_trait;
+use std::fmt;
 use std::sync::{Arc, Weak};
 use tokio::sync::{mpsc, Mutex};
 use tokio::time::Duration;
+use util::sync::TaskGroup;
 
 pub(crate) const RTO_INITIAL: u64 = 3000; // msec
 pub(crate) const RTO_MIN: u64 = 1000; // msec
@@ -105,12 +108,23 @@ pub(crate) trait RtxTimerObserver {
 }
 
 /// rtxTimer provides the retnransmission timer conforms with RFC 4960 Sec 6.3.1
-#[derive(Default, Debug)]
 pub(crate) struct RtxTimer<T: 'static + RtxTimerObserver + Send> {
     pub(crate) timeout_observer: Weak<Mutex<T>>,
     pub(crate) id: RtxTimerId,
     pub(crate) max_retrans: usize,
     pub(crate) close_tx: Arc<Mutex<Option<mpsc::Sender<()>>>>,
+    pub(crate) clock: SharedClock,
+    /// The tasks of the association, which the timer runs on
+    pub(crate) tasks: TaskGroup,
+}
+
+impl<T: 'static + RtxTimerObserver + Send> fmt::Debug for RtxTimer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RtxTimer")
+            .field("id", &self.id)
+            .field("max_retrans", &self.max_retrans)
+            .finish()
+    }
 }
 
 impl<T: 'static + RtxTimerObserver + Send> RtxTimer<T> {
@@ -121,12 +135,16 @@ impl<T: 'static + RtxTimerObserver + Send> RtxTimer<T> {
         timeout_observer: Weak<Mutex<T>>,
         id: RtxTimerId,
         max_retrans: usize,
+        clock: SharedClock,
+        tasks: TaskGroup,
     ) -> Self {
         RtxTimer {
             timeout_observer,
             id,
             max_retrans,
             close_tx: Arc::new(Mutex::new(None)),
+            clock,
+            tasks,
         }
     }
 
@@ -153,13 +171,18 @@ impl<T: 'static + RtxTimerObserver + Send> RtxTimer<T> {
         let max_retrans = self.max_retrans;
         let close_tx = Arc::clone(&self.close_tx);
         let timeout_observer = self.timeout_observer.clone();
+        let clock = self.clock.clone();
+        let mut deadline = clock.now();
 
-        tokio::spawn(async move {
+        self.tasks.spawn(async move {
             let mut n_rtos = 0;
 
             loop {
+                // The timer restarts when it expires, the time spent in the observer doesn't
+                // delay the next expiry.
                 let interval = calculate_next_timeout(rto, n_rtos);
-                let timer = tokio::time::sleep(Duration::from_millis(interval));
+                deadline += Duration::from_millis(interval);
+                let timer = clock.sleep_until(deadline);
                 tokio::pin!(timer);
 
                 tokio::select! {
@@ -1,9 +1,22 @@
 use async_trait::async_trait;
 use tokio::sync::Mutex;
-use tokio::time::{sleep, Duration};
+use tokio::time::Duration;
 
+use crate::clock::{Clock, ManualClock, SharedClock};
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
+use util::sync::TaskGroup;
+
+/// advance moves the clock forward in steps of a millisecond, letting the timers woken by each
+/// step run before the next one.
+async fn advance(clock: &ManualClock, millis: u64) {
+    for _ in 0..millis {
+        clock.advance(Duration::from_millis(1));
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+    }
+}
 
 ///////////////////////////////////////////////////////////////////
 //ack_timer_test
@@ -29,10 +42,16 @@ mod test_ack_timer {
 
     #[tokio::test]
     async fn test_ack_timer_start_and_stop() -> Result<()> {
+        let clock = Arc::new(ManualClock::new());
         let ncbs = Arc::new(AtomicU32::new(0));
         let obs = Arc::new(Mutex::new(TestAckTimerObserver { ncbs: ncbs.clone() }));
 
-        let mut rt = AckTimer::new(Arc::downgrade(&obs), ACK_INTERVAL);
+        let mut rt = AckTimer::new(
+            Arc::downgrade(&obs),
+            ACK_INTERVAL,
+            SharedClock(clock.clone()),
+            TaskGroup::new(),
+        );
 
         // should start ok
         let ok = rt.start();
@@ -43,8 +62,8 @@ mod test_ack_timer {
         rt.stop();
         assert!(!rt.is_running(), "should not be running");
 
-        // Sleep more than 200msec of interval to test if it never times out
-        sleep(ACK_INTERVAL + Duration::from_millis(50)).await;
+        // Advance more than 200msec of interval to test if it never times out
+        advance(&clock, ACK_INTERVAL.as_millis() as u64 + 50).await;
 
         assert_eq!(
             0,
@@ -64,6 +83,31 @@ mod test_ack_timer {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_ack_timer_times_out_after_interval() -> Result<()> {
+        let clock = Arc::new(ManualClock::new());
+        let ncbs = Arc::new(AtomicU32::new(0));
+        let obs = Arc::new(Mutex::new(TestAckTimerObserver { ncbs: ncbs.clone() }));
+
+        let mut rt = AckTimer::new(
+            Arc::downgrade(&obs),
+            ACK_INTERVAL,
+            SharedClock(clock.clone()),
+            TaskGroup::new(),
+        );
+        assert!(rt.start(), "start() should succeed");
+
+        advance(&clock, ACK_INTERVAL.as_millis() as u64 - 1).await;
+        assert_eq!(0, ncbs.load(Ordering::SeqCst), "should not be timed out");
+
+        advance(&clock, 1).await;
+        assert_eq!(1, ncbs.load(Ordering::SeqCst), "should be timed out");
+
+        rt.stop();
+
+        Ok(())
+    }
 }
 
 ///////////////////////////////////////////////////////////////////
@@ -156,21 +200,20 @@ mod test_rto_manager {
     }
 }
 
-//TODO: remove this conditional test
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
 mod test_rtx_timer {
     use super::*;
     use crate::association::RtxTimerId;
     use crate::error::Result;
 
-    use std::time::SystemTime;
+    use std::time::Instant;
     use tokio::sync::mpsc;
 
     struct TestTimerObserver {
         ncbs: Arc<AtomicU32>,
         timer_id: RtxTimerId,
-        done_tx: Option<mpsc::Sender<SystemTime>>,
+        done_tx: Option<mpsc::Sender<Instant>>,
         max_rtos: usize,
+        clock: Arc<ManualClock>,
     }
 
     impl Default for TestTimerObserver {
@@ -180,6 +223,7 @@ mod test_rtx_timer {
                 timer_id: RtxTimerId::T1Init,
                 done_tx: None,
                 max_rtos: 0,
+                clock: Arc::new(ManualClock::new()),
             }
         }
     }
@@ -191,12 +235,11 @@ mod test_rtx_timer {
             // 30 : 1 (30)
             // 60 : 2 (90)
             // 120: 3 (210)
-            // 240: 4 (550) <== expected in 650 msec
+            // 240: 4 (450) <== expected in 650 msec
             assert_eq!(self.timer_id, timer_id, "unexpected timer ID: {}", timer_id);
             if (self.max_rtos > 0 && n_rtos == self.max_rtos) || self.max_rtos == usize::MAX {
                 if let Some(done) = &self.done_tx {
-                    let elapsed = SystemTime::now();
-                    let _ = done.send(elapsed).await;
+                    let _ = done.send(self.clock.now()).await;
                 }
             }
         }
@@ -205,35 +248,47 @@ mod test_rtx_timer {
             if self.max_rtos == 0 {
                 if let Some(done) = &self.done_tx {
                     assert_eq!(self.timer_id, timer_id, "unexpted timer ID: {}", timer_id);
-                    let elapsed = SystemTime::now();
-                    //t.Logf("onRtxFailure: elapsed=%.03f\n", elapsed)
-                    let _ = done.send(elapsed).await;
+                    let _ = done.send(self.clock.now()).await;
                 }
             } else {
-                assert!(false, "timer should not fail");
+                panic!("timer should not fail");
             }
         }
     }
 
-    #[tokio::test]
-    async fn test_rtx_timer_callback_interval() -> Result<()> {
-        let timer_id = RtxTimerId::T1Init;
-        let ncbs = Arc::new(AtomicU32::new(0));
+    fn new_timer(
+        clock: &Arc<ManualClock>,
+        timer_id: RtxTimerId,
+        max_retrans: usize,
+    ) -> (Arc<Mutex<TestTimerObserver>>, RtxTimer<TestTimerObserver>) {
         let obs = Arc::new(Mutex::new(TestTimerObserver {
-            ncbs: ncbs.clone(),
             timer_id,
+            clock: Arc::clone(clock),
             ..Default::default()
         }));
-        let rt = RtxTimer::new(Arc::downgrade(&obs), timer_id, PATH_MAX_RETRANS);
+        let rt = RtxTimer::new(
+            Arc::downgrade(&obs),
+            timer_id,
+            max_retrans,
+            SharedClock(clock.clone()),
+            TaskGroup::new(),
+        );
+        (obs, rt)
+    }
+
+    #[tokio::test]
+    async fn test_rtx_timer_callback_interval() -> Result<()> {
+        let clock = Arc::new(ManualClock::new());
+        let (obs, rt) = new_timer(&clock, RtxTimerId::T1Init, PATH_MAX_RETRANS);
+        let ncbs = obs.lock().await.ncbs.clone();
 
         assert!(!rt.is_running().await, "should not be running");
 
-        // since := time.Now()
         let ok = rt.start(30).await;
         assert!(ok, "should be true");
         assert!(rt.is_running().await, "should be running");
 
-        sleep(Duration::from_millis(650)).await;
+        advance(&clock, 650).await;
         rt.stop().await;
         assert!(!rt.is_running().await, "should not be running");
 
@@ -244,14 +299,9 @@ mod test_rtx_timer {
 
     #[tokio::test]
     async fn test_rtx_timer_last_start_wins() -> Result<()> {
-        let timer_id = RtxTimerId::T3RTX;
-        let ncbs = Arc::new(AtomicU32::new(0));
-        let obs = Arc::new(Mutex::new(TestTimerObserver {
-            ncbs: ncbs.clone(),
-            timer_id,
-            ..Default::default()
-        }));
-        let rt = RtxTimer::new(Arc::downgrade(&obs), timer_id, PATH_MAX_RETRANS);
+        let clock = Arc::new(ManualClock::new());
+        let (obs, rt) = new_timer(&clock, RtxTimerId::T3RTX, PATH_MAX_RETRANS);
+        let ncbs = obs.lock().await.ncbs.clone();
 
         let interval = 30;
         let ok = rt.start(interval).await;
@@ -261,7 +311,7 @@ mod test_rtx_timer {
         let ok = rt.start(interval * 99).await; // should ignored
         assert!(!ok, "should be ignored");
 
-        sleep(Duration::from_millis((interval * 3) / 2)).await;
+        advance(&clock, (interval * 3) / 2).await;
         rt.stop().await;
 
         assert!(!rt.is_running().await, "should not be running");
@@ -272,21 +322,16 @@ mod test_rtx_timer {
 
     #[tokio::test]
     async fn test_rtx_timer_stop_right_after_start() -> Result<()> {
-        let timer_id = RtxTimerId::T3RTX;
-        let ncbs = Arc::new(AtomicU32::new(0));
-        let obs = Arc::new(Mutex::new(TestTimerObserver {
-            ncbs: ncbs.clone(),
-            timer_id,
-            ..Default::default()
-        }));
-        let rt = RtxTimer::new(Arc::downgrade(&obs), timer_id, PATH_MAX_RETRANS);
+        let clock = Arc::new(ManualClock::new());
+        let (obs, rt) = new_timer(&clock, RtxTimerId::T3RTX, PATH_MAX_RETRANS);
+        let ncbs = obs.lock().await.ncbs.clone();
 
         let interval = 30;
         let ok = rt.start(interval).await;
         assert!(ok, "should be accepted");
         rt.stop().await;
 
-        sleep(Duration::from_millis((interval * 3) / 2)).await;
+        advance(&clock, (interval * 3) / 2).await;
         rt.stop().await;
 
         assert!(!rt.is_running().await, "should not be running");
@@ -297,14 +342,9 @@ mod test_rtx_timer {
 
     #[tokio::test]
     async fn test_rtx_timer_start_stop_then_start() -> Result<()> {
-        let timer_id = RtxTimerId::T1Cookie;
-        let ncbs = Arc::new(AtomicU32::new(0));
-        let obs = Arc::new(Mutex::new(TestTimerObserver {
-            ncbs: ncbs.clone(),
-            timer_id,
-            ..Default::default()
-        }));
-        let rt = RtxTimer::new(Arc::downgrade(&obs), timer_id, PATH_MAX_RETRANS);
+        let clock = Arc::new(ManualClock::new());
+        let (obs, rt) = new_timer(&clock, RtxTimerId::T1Cookie, PATH_MAX_RETRANS);
+        let ncbs = obs.lock().await.ncbs.clone();
 
         let interval = 30;
         let ok = rt.start(interval).await;
@@ -315,7 +355,7 @@ mod test_rtx_timer {
         assert!(ok, "should be accepted");
         assert!(rt.is_running().await, "should be running");
 
-        sleep(Duration::from_millis((interval * 3) / 2)).await;
+        advance(&clock, (interval * 3) / 2).await;
         rt.stop().await;
 
         assert!(!rt.is_running().await, "should NOT be running");
@@ -326,14 +366,9 @@ mod test_rtx_timer {
 
     #[tokio::test]
     async fn test_rtx_timer_start_and_stop_in_atight_loop() -> Result<()> {
-        let timer_id = RtxTimerId::T2Shutdown;
-        let ncbs = Arc::new(AtomicU32::new(0));
-        let obs = Arc::new(Mutex::new(TestTimerObserver {
-            ncbs: ncbs.clone(),
-            timer_id,
-            ..Default::default()
-        }));
-        let rt = RtxTimer::new(Arc::downgrade(&obs), timer_id, PATH_MAX_RETRANS);
+        let clock = Arc::new(ManualClock::new());
+        let (obs, rt) = new_timer(&clock, RtxTimerId::T2Shutdown, PATH_MAX_RETRANS);
+        let ncbs = obs.lock().await.ncbs.clone();
 
         for _ in 0..1000 {
             let ok = rt.start(30).await;
@@ -343,6 +378,7 @@ mod test_rtx_timer {
             assert!(!rt.is_running().await, "should NOT be running");
         }
 
+        advance(&clock, 30).await;
         assert_eq!(0, ncbs.load(Ordering::SeqCst), "no callback should be made");
 
         Ok(())
@@ -352,17 +388,12 @@ mod test_rtx_timer {
     async fn test_rtx_timer_should_stop_after_rtx_failure() -> Result<()> {
         let (done_tx, mut done_rx) = mpsc::channel(1);
 
-        let timer_id = RtxTimerId::Reconfig;
-        let ncbs = Arc::new(AtomicU32::new(0));
-        let obs = Arc::new(Mutex::new(TestTimerObserver {
-            ncbs: ncbs.clone(),
-            timer_id,
-            done_tx: Some(done_tx),
-            ..Default::default()
-        }));
+        let clock = Arc::new(ManualClock::new());
+        let (obs, rt) = new_timer(&clock, RtxTimerId::Reconfig, PATH_MAX_RETRANS);
+        let ncbs = obs.lock().await.ncbs.clone();
+        obs.lock().await.done_tx = Some(done_tx);
 
-        let since = SystemTime::now();
-        let rt = RtxTimer::new(Arc::downgrade(&obs), timer_id, PATH_MAX_RETRANS);
+        let since = clock.now();
 
         // RTO(msec) Total(msec)
         //  10          10    1st RTO
@@ -377,22 +408,14 @@ mod test_rtx_timer {
         assert!(ok, "should be accepted");
         assert!(rt.is_running().await, "should be running");
 
-        let elapsed = done_rx.recv().await;
+        advance(&clock, 629).await;
+        assert!(done_rx.try_recv().is_err(), "must not fail before 630 msec");
+        advance(&clock, 1).await;
+        let elapsed = done_rx.try_recv().expect("must fail at 630 msec");
 
         assert!(!rt.is_running().await, "should not be running");
         assert_eq!(5, ncbs.load(Ordering::SeqCst), "should be called 5 times");
-
-        if let Some(elapsed) = elapsed {
-            let diff = elapsed.duration_since(since).unwrap();
-            assert!(
-                diff > Duration::from_millis(600),
-                "must have taken more than 600 msec"
-            );
-            assert!(
-                diff < Duration::from_millis(700),
-                "must fail in less than 700 msec"
-            );
-        }
+        assert_eq!(Duration::from_millis(630), elapsed.duration_since(since));
 
         Ok(())
     }
@@ -401,19 +424,16 @@ mod test_rtx_timer {
     async fn test_rtx_timer_should_not_stop_if_max_retrans_is_zero() -> Result<()> {
         let (done_tx, mut done_rx) = mpsc::channel(1);
 
-        let timer_id = RtxTimerId::Reconfig;
-        let max_rtos = 6;
-        let ncbs = Arc::new(AtomicU32::new(0));
-        let obs = Arc::new(Mutex::new(TestTimerObserver {
-            ncbs: ncbs.clone(),
-            timer_id,
-            done_tx: Some(done_tx),
-            max_rtos,
-            ..Default::default()
-        }));
+        let clock = Arc::new(ManualClock::new());
+        let (obs, rt) = new_timer(&clock, RtxTimerId::Reconfig, 0);
+        let ncbs = obs.lock().await.ncbs.clone();
+        {
+            let mut obs = obs.lock().await;
+            obs.done_tx = Some(done_tx);
+            obs.max_rtos = 6;
+        }
 
-        let since = SystemTime::now();
-        let rt = RtxTimer::new(Arc::downgrade(&obs), timer_id, 0);
+        let since = clock.now();
 
         // RTO(msec) Total(msec)
         //  10          10    1st RTO
@@ -428,22 +448,12 @@ mod test_rtx_timer {
         assert!(ok, "should be accepted");
         assert!(rt.is_running().await, "should be running");
 
-        let elapsed = done_rx.recv().await;
+        advance(&clock, 630).await;
+        let elapsed = done_rx.try_recv().expect("6th RTO must occur at 630 msec");
 
         assert!(rt.is_running().await, "should still be running");
         assert_eq!(6, ncbs.load(Ordering::SeqCst), "should be called 6 times");
-
-        if let Some(elapsed) = elapsed {
-            let diff = elapsed.duration_since(since).unwrap();
-            assert!(
-                diff > Duration::from_millis(600),
-                "must have taken more than 600 msec"
-            );
-            assert!(
-                diff < Duration::from_millis(700),
-                "must fail in less than 700 msec"
-            );
-        }
+        assert_eq!(Duration::from_millis(630), elapsed.duration_since(since));
 
         rt.stop().await;
 
@@ -454,14 +464,13 @@ mod test_rtx_timer {
     async fn test_rtx_timer_stop_timer_that_is_not_running_is_noop() -> Result<()> {
         let (done_tx, mut done_rx) = mpsc::channel(1);
 
-        let timer_id = RtxTimerId::Reconfig;
-        let obs = Arc::new(Mutex::new(TestTimerObserver {
-            timer_id,
-            done_tx: Some(done_tx),
-            max_rtos: usize::MAX,
-            ..Default::default()
-        }));
-        let rt = RtxTimer::new(Arc::downgrade(&obs), timer_id, PATH_MAX_RETRANS);
+        let clock = Arc::new(ManualClock::new());
+        let (obs, rt) = new_timer(&clock, RtxTimerId::Reconfig, PATH_MAX_RETRANS);
+        {
+            let mut obs = obs.lock().await;
+            obs.done_tx = Some(done_tx);
+            obs.max_rtos = usize::MAX;
+        }
 
         for _ in 0..10 {
             rt.stop().await;
@@ -471,7 +480,8 @@ mod test_rtx_timer {
         assert!(ok, "should be accepted");
         assert!(rt.is_running().await, "must be running");
 
-        let _ = done_rx.recv().await;
+        advance(&clock, 20).await;
+        assert!(done_rx.try_recv().is_ok(), "RTO should occur");
         rt.stop().await;
         assert!(!rt.is_running().await, "must be false");
 
@@ -480,14 +490,9 @@ mod test_rtx_timer {
 
     #[tokio::test]
     async fn test_rtx_timer_closed_timer_wont_start() -> Result<()> {
-        let timer_id = RtxTimerId::Reconfig;
-        let ncbs = Arc::new(AtomicU32::new(0));
-        let obs = Arc::new(Mutex::new(TestTimerObserver {
-            ncbs: ncbs.clone(),
-            timer_id,
-            ..Default::default()
-        }));
-        let rt = RtxTimer::new(Arc::downgrade(&obs), timer_id, PATH_MAX_RETRANS);
+        let clock = Arc::new(ManualClock::new());
+        let (obs, rt) = new_timer(&clock, RtxTimerId::Reconfig, PATH_MAX_RETRANS);
+        let ncbs = obs.lock().await.ncbs.clone();
 
         let ok = rt.start(20).await;
         assert!(ok, "should be accepted");
@@ -500,7 +505,7 @@ mod test_rtx_timer {
         //assert!(!ok, "should not start");
         assert!(!rt.is_running().await, "must not be running");
 
-        sleep(Duration::from_millis(100)).await;
+        advance(&clock, 100).await;
         assert_eq!(0, ncbs.load(Ordering::SeqCst), "RTO should not occur");
 
         Ok(())
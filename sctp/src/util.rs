@@ -1,5 +1,6 @@
+use crate::crc32c;
+
 use bytes::Bytes;
-use crc::{Crc, CRC_32_ISCSI};
 
 const PADDING_MULTIPLE: usize = 4;
 
@@ -11,14 +12,10 @@ pub(crate) fn get_padding_size(len: usize) -> usize {
 /// We need to use it for the checksum and don't want to allocate/clear each time.
 pub(crate) static FOUR_ZEROES: Bytes = Bytes::from_static(&[0, 0, 0, 0]);
 
-/// Fastest way to do a crc32 without allocating.
+/// generate_packet_checksum computes the CRC32c of a packet without allocating, as if its
+/// checksum field was zero.
 pub(crate) fn generate_packet_checksum(raw: &Bytes) -> u32 {
-    let hasher = Crc::<u32>::new(&CRC_32_ISCSI);
-    let mut digest = hasher.digest();
-    digest.update(&raw[0..8]);
-    digest.update(&FOUR_ZEROES[..]);
-    digest.update(&raw[12..]);
-    digest.finalize()
+    crc32c::checksum(&[&raw[0..8], &FOUR_ZEROES[..], &raw[12..]])
 }
 
 /// Serial Number Arithmetic (RFC 1982)
@@ -72,6 +69,19 @@ pub(crate) fn sna16eq(i1: u16, i2: u16) -> bool {
     i1 == i2
 }
 
+/// unwrap_tsn extends tsn to the 64 bit TSN closest to the extended TSN reference, so that
+/// extended TSNs keep increasing when the 32 bit TSNs wrap around. TSNs before extended TSN 0
+/// are extended to 0.
+#[inline]
+pub(crate) fn unwrap_tsn(reference: u64, tsn: u32) -> u64 {
+    let diff = tsn.wrapping_sub(reference as u32) as i32;
+    if diff >= 0 {
+        reference + diff as u64
+    } else {
+        reference.saturating_sub(diff.unsigned_abs() as u64)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::error::Result;
@@ -332,4 +342,25 @@ mod test {
 
         Ok(())
     }
+    #[test]
+    fn test_unwrap_tsn() {
+        const WRAP: u64 = 1 << 32;
+
+        for reference in [0, 5, WRAP - 1, WRAP, 3 * WRAP + 7, 5 * WRAP - 3] {
+            for diff in [0i64, 1, -1, 1000, -1000, (1 << 31) - 1, -(1 << 31)] {
+                let want = reference as i64 + diff;
+                if want < 0 {
+                    assert_eq!(unwrap_tsn(reference, want as u32), 0);
+                    continue;
+                }
+                assert_eq!(
+                    unwrap_tsn(reference, want as u32),
+                    want as u64,
+                    "reference={} diff={}",
+                    reference,
+                    diff
+                );
+            }
+        }
+    }
 }
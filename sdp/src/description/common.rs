@@ -1,5 +1,8 @@
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
 
+use crate::lexer::END_LINE;
+
 /// Information describes the "i=" field which provides textual information
 /// about the session.
 pub type Information = String;
@@ -92,3 +95,107 @@ impl Attribute {
         self.key.as_str() == "candidate"
     }
 }
+
+/// OriginalLine keeps a line of an unmarshaled description as it was received, so that marshal
+/// reproduces it byte for byte as long as the value parsed from it is not modified.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct OriginalLine {
+    /// The line as marshal builds it from the parsed value, without line ending.
+    pub(crate) canonical: String,
+    /// The line as it was received, including its line ending.
+    pub(crate) raw: String,
+}
+
+impl OriginalLine {
+    pub(crate) fn new(key: &str, value: &str, raw_value: &str) -> Self {
+        OriginalLine {
+            canonical: key.to_owned() + value,
+            raw: key.to_owned() + raw_value,
+        }
+    }
+}
+
+/// OriginalLines are the lines a description, or a media description, was unmarshaled from. They
+/// are empty for descriptions built in code and after
+/// [`canonicalize`](crate::SessionDescription::canonicalize).
+#[derive(Debug, Default, Clone)]
+pub struct OriginalLines(pub(crate) Vec<OriginalLine>);
+
+impl OriginalLines {
+    pub(crate) fn push(&mut self, line: OriginalLine) {
+        self.0.push(line);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// marshal_lines joins the lines of a description, or of a media description, built from its
+/// current values. Lines still equal to a line it was unmarshaled from are written as received
+/// and in their original place. Attributes take the places of the original attributes in their
+/// current order. Other lines are written after the line preceding them.
+pub(crate) fn marshal_lines(original: &OriginalLines, lines: &[String]) -> String {
+    let original = &original.0;
+    let mut unused: HashMap<&str, VecDeque<usize>> = HashMap::new();
+    for (i, o) in original.iter().enumerate() {
+        unused.entry(o.canonical.as_str()).or_default().push_back(i);
+    }
+    let matched: Vec<Option<usize>> = lines
+        .iter()
+        .map(|line| unused.get_mut(line.as_str()).and_then(|u| u.pop_front()))
+        .collect();
+
+    // The line written in place of each original line, and the reverse
+    let mut places: Vec<Option<usize>> = vec![None; original.len()];
+    let mut line_places: Vec<Option<usize>> = vec![None; lines.len()];
+    let mut attribute_places = {
+        let mut attribute_places: Vec<usize> = matched
+            .iter()
+            .zip(lines)
+            .filter_map(|(m, line)| m.filter(|_| line.starts_with("a=")))
+            .collect();
+        attribute_places.sort_unstable();
+        attribute_places.into_iter()
+    };
+    for (i, (m, line)) in matched.iter().zip(lines).enumerate() {
+        if let Some(o) = m {
+            let place = if line.starts_with("a=") {
+                attribute_places.next()
+            } else {
+                Some(*o)
+            };
+            if let Some(place) = place {
+                places[place] = Some(i);
+                line_places[i] = Some(place);
+            }
+        }
+    }
+
+    // Lines written after each original line, the first entry is for the start
+    let mut inserted: Vec<Vec<usize>> = vec![vec![]; original.len() + 1];
+    let mut after = 0;
+    for (i, line_place) in line_places.iter().enumerate() {
+        match line_place {
+            Some(place) => after = place + 1,
+            None => inserted[after].push(i),
+        }
+    }
+
+    let mut result = String::new();
+    for (after, inserted) in inserted.iter().enumerate() {
+        if after > 0 {
+            if let Some(o) = places[after - 1].and_then(|i| matched[i]) {
+                result += original[o].raw.as_str();
+                if !original[o].raw.ends_with('\n') {
+                    result += END_LINE;
+                }
+            }
+        }
+        for i in inserted {
+            result += lines[*i].as_str();
+            result += END_LINE;
+        }
+    }
+    result
+}
@@ -147,6 +147,7 @@ fn test_marshal() -> Result<()> {
                 }],
                 encryption_key: Some("prompt".to_string()),
                 attributes: vec![Attribute::new("sendrecv".to_string(), None)],
+                ..Default::default()
             },
             MediaDescription {
                 media_name: MediaName {
@@ -166,8 +167,10 @@ fn test_marshal() -> Result<()> {
                     "rtpmap".to_string(),
                     Some("99 h263-1998/90000".to_string()),
                 )],
+                ..Default::default()
             },
         ],
+        ..Default::default()
     };
 
     let actual = sd.marshal();
@@ -534,7 +537,16 @@ fn test_round_trip() -> Result<()> {
     for (name, sdp_str, expected) in tests {
         let mut reader = Cursor::new(sdp_str.as_bytes());
         let sdp = SessionDescription::unmarshal(&mut reader);
-        if let Ok(sdp) = sdp {
+        if let Ok(mut sdp) = sdp {
+            // Only empty lines are dropped
+            let actual = sdp.marshal();
+            let lines: String = sdp_str
+                .split_inclusive('\n')
+                .filter(|line| !line.trim().is_empty())
+                .collect();
+            assert_eq!(actual, lines, "{}\n{}", name, sdp_str);
+
+            sdp.canonicalize();
             let actual = sdp.marshal();
             if let Some(expected) = expected {
                 assert_eq!(actual.as_str(), expected, "{}\n{}", name, sdp_str);
@@ -552,7 +564,9 @@ fn test_round_trip() -> Result<()> {
 #[test]
 fn test_unmarshal_repeat_times() -> Result<()> {
     let mut reader = Cursor::new(REPEAT_TIMES_SDP.as_bytes());
-    let sdp = SessionDescription::unmarshal(&mut reader)?;
+    let mut sdp = SessionDescription::unmarshal(&mut reader)?;
+    assert_eq!(sdp.marshal(), REPEAT_TIMES_SDP);
+    sdp.canonicalize();
     let actual = sdp.marshal();
     assert_eq!(actual.as_str(), REPEAT_TIMES_SDPEXPECTED);
     Ok(())
@@ -573,7 +587,9 @@ fn test_unmarshal_repeat_times_overflow() -> Result<()> {
 #[test]
 fn test_unmarshal_time_zones() -> Result<()> {
     let mut reader = Cursor::new(TIME_ZONES_SDP.as_bytes());
-    let sdp = SessionDescription::unmarshal(&mut reader)?;
+    let mut sdp = SessionDescription::unmarshal(&mut reader)?;
+    assert_eq!(sdp.marshal(), TIME_ZONES_SDP);
+    sdp.canonicalize();
     let actual = sdp.marshal();
     assert_eq!(actual.as_str(), TIME_ZONES_SDPEXPECTED);
     Ok(())
@@ -592,3 +608,233 @@ fn test_unmarshal_non_nil_address() -> Result<()> {
     }
     Ok(())
 }
+
+const CHROME_OFFER_SDP: &str = "v=0\r\n\
+o=- 4611731400430051336 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+a=group:BUNDLE 0 1 2\r\n\
+a=extmap-allow-mixed\r\n\
+a=msid-semantic: WMS 9ad1c1e2-3a3c-4c0d-8d8e-27a1ec7e0a8b\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 111 63 9 0 8 13 110 126\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=rtcp:9 IN IP4 0.0.0.0\r\n\
+a=ice-ufrag:EsAw\r\n\
+a=ice-pwd:bP+XJMM09aR8AiX1jdukzR6Y\r\n\
+a=ice-options:trickle\r\n\
+a=fingerprint:sha-256 DA:7B:57:DC:28:CE:04:4F:31:79:85:C4:31:67:EB:27:58:29:ED:77:2A:0D:24:AE:ED:88:20:BA:1B:FD:31:3B\r\n\
+a=setup:actpass\r\n\
+a=mid:0\r\n\
+a=extmap:1 urn:ietf:params:rtp-hdrext:ssrc-audio-level\r\n\
+a=extmap:2 http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time\r\n\
+a=sendrecv\r\n\
+a=msid:9ad1c1e2-3a3c-4c0d-8d8e-27a1ec7e0a8b 0c3bd0b0-9c1f-4d7b-a3c6-a8b2a9f2f4a1\r\n\
+a=rtcp-mux\r\n\
+a=rtpmap:111 opus/48000/2\r\n\
+a=rtcp-fb:111 transport-cc\r\n\
+a=fmtp:111 minptime=10;useinbandfec=1\r\n\
+a=rtpmap:63 red/48000/2\r\n\
+a=fmtp:63 111/111\r\n\
+a=rtpmap:9 G722/8000\r\n\
+a=rtpmap:0 PCMU/8000\r\n\
+a=rtpmap:8 PCMA/8000\r\n\
+a=rtpmap:13 CN/8000\r\n\
+a=rtpmap:110 telephone-event/48000\r\n\
+a=rtpmap:126 telephone-event/8000\r\n\
+a=ssrc:3870452381 cname:Y3wPTlVq1Dc4hj7l\r\n\
+a=ssrc:3870452381 msid:9ad1c1e2-3a3c-4c0d-8d8e-27a1ec7e0a8b 0c3bd0b0-9c1f-4d7b-a3c6-a8b2a9f2f4a1\r\n\
+m=video 9 UDP/TLS/RTP/SAVPF 96 97 45\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=rtcp:9 IN IP4 0.0.0.0\r\n\
+a=ice-ufrag:EsAw\r\n\
+a=ice-pwd:bP+XJMM09aR8AiX1jdukzR6Y\r\n\
+a=ice-options:trickle\r\n\
+a=fingerprint:sha-256 DA:7B:57:DC:28:CE:04:4F:31:79:85:C4:31:67:EB:27:58:29:ED:77:2A:0D:24:AE:ED:88:20:BA:1B:FD:31:3B\r\n\
+a=setup:actpass\r\n\
+a=mid:1\r\n\
+a=extmap:14 urn:ietf:params:rtp-hdrext:toffset\r\n\
+a=extmap:13 urn:3gpp:video-orientation\r\n\
+a=sendrecv\r\n\
+a=rtcp-mux\r\n\
+a=rtcp-rsize\r\n\
+a=rtpmap:96 VP8/90000\r\n\
+a=rtcp-fb:96 goog-remb\r\n\
+a=rtcp-fb:96 transport-cc\r\n\
+a=rtcp-fb:96 ccm fir\r\n\
+a=rtcp-fb:96 nack\r\n\
+a=rtcp-fb:96 nack pli\r\n\
+a=rtpmap:97 rtx/90000\r\n\
+a=fmtp:97 apt=96\r\n\
+a=rtpmap:45 AV1/90000\r\n\
+a=fmtp:45 level-idx=5;profile=0;tier=0\r\n\
+a=rid:h send\r\n\
+a=rid:l send\r\n\
+a=simulcast:send h;l\r\n\
+m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=ice-ufrag:EsAw\r\n\
+a=ice-pwd:bP+XJMM09aR8AiX1jdukzR6Y\r\n\
+a=ice-options:trickle\r\n\
+a=fingerprint:sha-256 DA:7B:57:DC:28:CE:04:4F:31:79:85:C4:31:67:EB:27:58:29:ED:77:2A:0D:24:AE:ED:88:20:BA:1B:FD:31:3B\r\n\
+a=setup:actpass\r\n\
+a=mid:2\r\n\
+a=sctp-port:5000\r\n\
+a=max-message-size:262144\r\n";
+
+const FIREFOX_ANSWER_SDP: &str = "v=0\r\n\
+o=mozilla...THIS_IS_SDPARTA-99.0 5133456322870283446 0 IN IP4 0.0.0.0\r\n\
+s=-\r\n\
+t=0 0\r\n\
+a=fingerprint:sha-256 5A:1C:7F:31:6F:93:67:2B:C1:8A:38:8C:C4:48:E3:A1:6B:7F:4F:1A:30:E5:21:71:1D:E7:0A:2B:62:9C:37:5D\r\n\
+a=group:BUNDLE 0 1\r\n\
+a=ice-options:trickle\r\n\
+a=msid-semantic:WMS *\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 109 9 0 8 101\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=sendrecv\r\n\
+a=extmap:1 urn:ietf:params:rtp-hdrext:ssrc-audio-level\r\n\
+a=extmap:2/recvonly urn:ietf:params:rtp-hdrext:csrc-audio-level\r\n\
+a=fmtp:109 maxplaybackrate=48000;stereo=1;useinbandfec=1\r\n\
+a=fmtp:101 0-15\r\n\
+a=ice-pwd:8b1a8a5c5e7f6d4c3b2a1f0e9d8c7b6a\r\n\
+a=ice-ufrag:c0a8f1e2\r\n\
+a=mid:0\r\n\
+a=msid:{5e3a1b2c-7d4e-4f60-8a9b-0c1d2e3f4a5b} {6f4b2c3d-8e5f-4071-9bac-1d2e3f4a5b6c}\r\n\
+a=rtcp-mux\r\n\
+a=rtpmap:109 opus/48000/2\r\n\
+a=rtpmap:9 G722/8000/1\r\n\
+a=rtpmap:0 PCMU/8000\r\n\
+a=rtpmap:8 PCMA/8000\r\n\
+a=rtpmap:101 telephone-event/8000\r\n\
+a=setup:active\r\n\
+a=ssrc:2655508255 cname:{735484ea-4f6c-f74a-bd66-7425f8476c2e}\r\n\
+m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=sendrecv\r\n\
+a=ice-pwd:8b1a8a5c5e7f6d4c3b2a1f0e9d8c7b6a\r\n\
+a=ice-ufrag:c0a8f1e2\r\n\
+a=mid:1\r\n\
+a=setup:active\r\n\
+a=sctp-port:5000\r\n\
+a=max-message-size:1073741823\r\n";
+
+// A gateway answer with LF line endings, unknown attributes, values with trailing whitespace,
+// repeat times in units and media lines in non-spec order.
+const GATEWAY_ANSWER_SDP: &str = "v=0\n\
+o=gw-media  1678901234 1678901235 IN IP4 198.51.100.7\n\
+s=Gateway Session\n\
+u=http://gw.example.com\n\
+t=0 0\n\
+r=7d 1h 0 25h\n\
+a=X-nat:0\n\
+a=tool:gw 4.2 \n\
+m=audio 40000 RTP/AVP 0 101\n\
+a=rtpmap:0 PCMU/8000\n\
+a=x-custom-flag\n\
+b=TIAS:64000\n\
+c=IN IP4 198.51.100.7\n\
+a=rtpmap:101 telephone-event/8000\n\
+a=fmtp:101 0-16\n\
+a=ptime:20\n\
+a=sendrecv\n\
+m=video 40002 RTP/AVPF 96\n\
+i=camera\n\
+b=AS:2000\n\
+a=rtpmap:96 H264/90000\n\
+a=fmtp:96 profile-level-id=42e01f;packetization-mode=1\n\
+a=rtcp-fb:96 nack pli\n\
+a=x-gw-quality: high\n";
+
+const CORPUS: [(&str, &str); 3] = [
+    ("ChromeOffer", CHROME_OFFER_SDP),
+    ("FirefoxAnswer", FIREFOX_ANSWER_SDP),
+    ("GatewayAnswer", GATEWAY_ANSWER_SDP),
+];
+
+#[test]
+fn test_round_trip_corpus() -> Result<()> {
+    for (name, sdp_str) in CORPUS {
+        let mut reader = Cursor::new(sdp_str.as_bytes());
+        let sdp = SessionDescription::unmarshal(&mut reader)?;
+        assert_eq!(sdp.marshal(), sdp_str, "{}", name);
+
+        // A canonical description goes through unchanged as well
+        let mut canonical = sdp.clone();
+        canonical.canonicalize();
+        let canonical = canonical.marshal();
+        let mut reader = Cursor::new(canonical.as_bytes());
+        let sdp = SessionDescription::unmarshal(&mut reader)?;
+        assert_eq!(sdp.marshal(), canonical, "{}", name);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_round_trip_corpus_modified() -> Result<()> {
+    for (name, sdp_str) in CORPUS {
+        let mut reader = Cursor::new(sdp_str.as_bytes());
+        let mut sdp = SessionDescription::unmarshal(&mut reader)?;
+
+        // Bump the session version, add a candidate after the first attribute of the first media
+        // description and drop the last attribute of the last one
+        sdp.origin.session_version += 1;
+        sdp.media_descriptions[0].attributes.insert(
+            1,
+            Attribute::new(
+                ATTR_KEY_CANDIDATE.to_owned(),
+                Some("1 1 udp 2122260223 192.0.2.1 50000 typ host".to_owned()),
+            ),
+        );
+        if let Some(last) = sdp.media_descriptions.last_mut() {
+            last.attributes.pop();
+        }
+
+        // Only the modified lines differ, new lines end with CRLF
+        let mut expected: Vec<String> = sdp_str
+            .split_inclusive('\n')
+            .map(|line| line.to_owned())
+            .collect();
+        expected[1] = format!("o={}\r\n", sdp.origin);
+        let first_media = expected
+            .iter()
+            .position(|line| line.starts_with("m="))
+            .expect("a media description");
+        let first_attribute = first_media
+            + expected[first_media..]
+                .iter()
+                .position(|line| line.starts_with("a="))
+                .expect("a media attribute");
+        expected.insert(
+            first_attribute + 1,
+            "a=candidate:1 1 udp 2122260223 192.0.2.1 50000 typ host\r\n".to_owned(),
+        );
+        expected.pop();
+
+        assert_eq!(sdp.marshal(), expected.concat(), "{}", name);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_marshal_reordered_attributes() -> Result<()> {
+    let mut reader = Cursor::new(MEDIA_DESCRIPTION_OUT_OF_ORDER_SDP.as_bytes());
+    let mut sdp = SessionDescription::unmarshal(&mut reader)?;
+    sdp.media_descriptions[1].attributes.swap(0, 1);
+
+    // The attributes follow their new order, the c= and i= lines stay after them
+    let expected = "v=0\r\n\
+o=jdoe 2890844526 2890842807 IN IP4 10.47.16.5\r\n\
+s=SDP Seminar\r\n\
+t=2873397496 2873404696\r\n\
+m=video 51372 RTP/AVP 99\r\n\
+m=audio 54400 RTP/SAVPF 0 96\r\n\
+a=candidate:0 1 UDP 2113667327 203.0.113.1 54400 typ host\r\n\
+a=rtpmap:99 h263-1998/90000\r\n\
+c=IN IP4 203.0.113.1\r\n\
+i=Vivamus a posuere nisl\r\n";
+    assert_eq!(sdp.marshal(), expected);
+
+    Ok(())
+}
@@ -4,6 +4,7 @@ use url::Url;
 
 use crate::description::common::*;
 use crate::extmap::*;
+use crate::lexer::key_value_build;
 
 /// Constants for extmap key
 pub const EXT_MAP_VALUE_TRANSPORT_CC_KEY: isize = 3;
@@ -56,6 +57,9 @@ pub struct MediaDescription {
     ///
     /// <https://tools.ietf.org/html/rfc4566#section-5.12>
     pub attributes: Vec<Attribute>,
+
+    /// The lines this media description was unmarshaled from.
+    pub original_lines: OriginalLines,
 }
 
 impl MediaDescription {
@@ -100,7 +104,29 @@ impl MediaDescription {
             bandwidth: vec![],
             encryption_key: None,
             attributes: vec![],
+            original_lines: OriginalLines::default(),
+        }
+    }
+
+    /// lines returns the lines of the media description in the order of
+    /// <https://tools.ietf.org/html/rfc4566#section-5>, without line endings.
+    pub(crate) fn lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+
+        key_value_build(&mut lines, "m=", Some(&self.media_name.to_string()));
+        key_value_build(&mut lines, "i=", self.media_title.as_ref());
+        if let Some(connection_information) = &self.connection_information {
+            key_value_build(&mut lines, "c=", Some(&connection_information.to_string()));
+        }
+        for bandwidth in &self.bandwidth {
+            key_value_build(&mut lines, "b=", Some(&bandwidth.to_string()));
         }
+        key_value_build(&mut lines, "k=", self.encryption_key.as_ref());
+        for attribute in &self.attributes {
+            key_value_build(&mut lines, "a=", Some(&attribute.to_string()));
+        }
+
+        lines
     }
 
     /// with_property_attribute adds a property attribute 'a=key' to the media description
@@ -22,6 +22,8 @@ pub const ATTR_KEY_MSID_SEMANTIC: &str = "msid-semantic";
 pub const ATTR_KEY_CONNECTION_SETUP: &str = "setup";
 pub const ATTR_KEY_MID: &str = "mid";
 pub const ATTR_KEY_ICELITE: &str = "ice-lite";
+pub const ATTR_KEY_ICE_OPTIONS: &str = "ice-options";
+pub const ATTR_KEY_CRYPTEX: &str = "cryptex";
 pub const ATTR_KEY_RTCPMUX: &str = "rtcp-mux";
 pub const ATTR_KEY_RTCPRSIZE: &str = "rtcp-rsize";
 pub const ATTR_KEY_INACTIVE: &str = "inactive";
@@ -29,6 +31,7 @@ pub const ATTR_KEY_RECV_ONLY: &str = "recvonly";
 pub const ATTR_KEY_SEND_ONLY: &str = "sendonly";
 pub const ATTR_KEY_SEND_RECV: &str = "sendrecv";
 pub const ATTR_KEY_EXT_MAP: &str = "extmap";
+pub const ATTR_KEY_MAX_MESSAGE_SIZE: &str = "max-message-size";
 
 /// Constants for semantic tokens used in JSEP
 pub const SEMANTIC_TOKEN_LIP_SYNCHRONIZATION: &str = "LS";
@@ -231,6 +234,9 @@ pub struct SessionDescription {
 
     /// <https://tools.ietf.org/html/rfc4566#section-5.14>
     pub media_descriptions: Vec<MediaDescription>,
+
+    /// The session-level lines this description was unmarshaled from.
+    pub original_lines: OriginalLines,
 }
 
 /// Reset cleans the SessionDescription, and sets all fields back to their default values
@@ -272,6 +278,7 @@ impl SessionDescription {
             encryption_key: None,
             attributes: vec![], // TODO: implement trickle ICE
             media_descriptions: vec![],
+            original_lines: OriginalLines::default(),
         };
 
         if identity {
@@ -397,62 +404,70 @@ impl SessionDescription {
     ///    b=* (zero or more bandwidth information lines)
     ///    k=* (encryption key)
     ///    a=* (zero or more media attribute lines)
+    ///
+    /// Lines which are unchanged since unmarshal are written as they were received, see
+    /// [`SessionDescription::canonicalize`].
     pub fn marshal(&self) -> String {
-        let mut result = String::new();
+        let mut result = marshal_lines(&self.original_lines, &self.lines());
+        for media_description in &self.media_descriptions {
+            result += marshal_lines(
+                &media_description.original_lines,
+                &media_description.lines(),
+            )
+            .as_str();
+        }
 
-        result += key_value_build("v=", Some(&self.version.to_string())).as_str();
-        result += key_value_build("o=", Some(&self.origin.to_string())).as_str();
-        result += key_value_build("s=", Some(&self.session_name)).as_str();
+        result
+    }
 
-        result += key_value_build("i=", self.session_information.as_ref()).as_str();
+    /// canonicalize drops the text this description was unmarshaled from, so that marshal builds
+    /// every line from its value. Unknown attributes are kept, but line endings, whitespace, units
+    /// and the order of lines are normalized.
+    pub fn canonicalize(&mut self) {
+        self.original_lines.clear();
+        for media_description in &mut self.media_descriptions {
+            media_description.original_lines.clear();
+        }
+    }
+
+    /// lines returns the session-level lines in the order of
+    /// <https://tools.ietf.org/html/rfc4566#section-5>, without line endings.
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+
+        key_value_build(&mut lines, "v=", Some(&self.version.to_string()));
+        key_value_build(&mut lines, "o=", Some(&self.origin.to_string()));
+        key_value_build(&mut lines, "s=", Some(&self.session_name));
+
+        key_value_build(&mut lines, "i=", self.session_information.as_ref());
 
         if let Some(uri) = &self.uri {
-            result += key_value_build("u=", Some(&format!("{}", uri))).as_str();
+            key_value_build(&mut lines, "u=", Some(&format!("{}", uri)));
         }
-        result += key_value_build("e=", self.email_address.as_ref()).as_str();
-        result += key_value_build("p=", self.phone_number.as_ref()).as_str();
+        key_value_build(&mut lines, "e=", self.email_address.as_ref());
+        key_value_build(&mut lines, "p=", self.phone_number.as_ref());
         if let Some(connection_information) = &self.connection_information {
-            result += key_value_build("c=", Some(&connection_information.to_string())).as_str();
+            key_value_build(&mut lines, "c=", Some(&connection_information.to_string()));
         }
 
         for bandwidth in &self.bandwidth {
-            result += key_value_build("b=", Some(&bandwidth.to_string())).as_str();
+            key_value_build(&mut lines, "b=", Some(&bandwidth.to_string()));
         }
         for time_description in &self.time_descriptions {
-            result += key_value_build("t=", Some(&time_description.timing.to_string())).as_str();
+            key_value_build(&mut lines, "t=", Some(&time_description.timing.to_string()));
             for repeat_time in &time_description.repeat_times {
-                result += key_value_build("r=", Some(&repeat_time.to_string())).as_str();
+                key_value_build(&mut lines, "r=", Some(&repeat_time.to_string()));
             }
         }
         if !self.time_zones.is_empty() {
-            let mut time_zones = vec![];
-            for time_zone in &self.time_zones {
-                time_zones.push(time_zone.to_string());
-            }
-            result += key_value_build("z=", Some(&time_zones.join(" "))).as_str();
+            key_value_build(&mut lines, "z=", Some(&time_zones_value(&self.time_zones)));
         }
-        result += key_value_build("k=", self.encryption_key.as_ref()).as_str();
+        key_value_build(&mut lines, "k=", self.encryption_key.as_ref());
         for attribute in &self.attributes {
-            result += key_value_build("a=", Some(&attribute.to_string())).as_str();
+            key_value_build(&mut lines, "a=", Some(&attribute.to_string()));
         }
 
-        for media_description in &self.media_descriptions {
-            result +=
-                key_value_build("m=", Some(&media_description.media_name.to_string())).as_str();
-            result += key_value_build("i=", media_description.media_title.as_ref()).as_str();
-            if let Some(connection_information) = &media_description.connection_information {
-                result += key_value_build("c=", Some(&connection_information.to_string())).as_str();
-            }
-            for bandwidth in &media_description.bandwidth {
-                result += key_value_build("b=", Some(&bandwidth.to_string())).as_str();
-            }
-            result += key_value_build("k=", media_description.encryption_key.as_ref()).as_str();
-            for attribute in &media_description.attributes {
-                result += key_value_build("a=", Some(&attribute.to_string())).as_str();
-            }
-        }
-
-        result
+        lines
     }
 
     /// Unmarshal is the primary function that deserializes the session description
@@ -550,6 +565,7 @@ impl SessionDescription {
                 encryption_key: None,
                 attributes: vec![],
                 media_descriptions: vec![],
+                original_lines: OriginalLines::default(),
             },
             reader,
         };
@@ -892,7 +908,7 @@ fn s16<'a, R: io::BufRead + io::Seek>(lexer: &mut Lexer<'a, R>) -> Result<Option
 fn unmarshal_protocol_version<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
 
     let version = value.parse::<u32>()?;
 
@@ -901,6 +917,10 @@ fn unmarshal_protocol_version<'a, R: io::BufRead + io::Seek>(
     if version != 0 {
         return Err(Error::SdpInvalidSyntax(value));
     }
+    lexer
+        .desc
+        .original_lines
+        .push(OriginalLine::new("v=", &version.to_string(), &raw));
 
     Ok(Some(StateFn { f: s2 }))
 }
@@ -908,7 +928,7 @@ fn unmarshal_protocol_version<'a, R: io::BufRead + io::Seek>(
 fn unmarshal_origin<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
 
     let fields: Vec<&str> = value.split_whitespace().collect();
     if fields.len() != 6 {
@@ -942,6 +962,11 @@ fn unmarshal_origin<'a, R: io::BufRead + io::Seek>(
         address_type: fields[4].to_owned(),
         unicast_address: fields[5].to_owned(),
     };
+    let origin = lexer.desc.origin.to_string();
+    lexer
+        .desc
+        .original_lines
+        .push(OriginalLine::new("o=", &origin, &raw));
 
     Ok(Some(StateFn { f: s3 }))
 }
@@ -949,7 +974,11 @@ fn unmarshal_origin<'a, R: io::BufRead + io::Seek>(
 fn unmarshal_session_name<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
+    lexer
+        .desc
+        .original_lines
+        .push(OriginalLine::new("s=", &value, &raw));
     lexer.desc.session_name = value;
     Ok(Some(StateFn { f: s4 }))
 }
@@ -957,7 +986,11 @@ fn unmarshal_session_name<'a, R: io::BufRead + io::Seek>(
 fn unmarshal_session_information<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
+    lexer
+        .desc
+        .original_lines
+        .push(OriginalLine::new("i=", &value, &raw));
     lexer.desc.session_information = Some(value);
     Ok(Some(StateFn { f: s7 }))
 }
@@ -965,15 +998,24 @@ fn unmarshal_session_information<'a, R: io::BufRead + io::Seek>(
 fn unmarshal_uri<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
-    lexer.desc.uri = Some(Url::parse(&value)?);
+    let (value, raw) = read_value(lexer.reader)?;
+    let uri = Url::parse(&value)?;
+    lexer
+        .desc
+        .original_lines
+        .push(OriginalLine::new("u=", uri.as_str(), &raw));
+    lexer.desc.uri = Some(uri);
     Ok(Some(StateFn { f: s10 }))
 }
 
 fn unmarshal_email<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
+    lexer
+        .desc
+        .original_lines
+        .push(OriginalLine::new("e=", &value, &raw));
     lexer.desc.email_address = Some(value);
     Ok(Some(StateFn { f: s6 }))
 }
@@ -981,7 +1023,11 @@ fn unmarshal_email<'a, R: io::BufRead + io::Seek>(
 fn unmarshal_phone<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
+    lexer
+        .desc
+        .original_lines
+        .push(OriginalLine::new("p=", &value, &raw));
     lexer.desc.phone_number = Some(value);
     Ok(Some(StateFn { f: s8 }))
 }
@@ -989,8 +1035,16 @@ fn unmarshal_phone<'a, R: io::BufRead + io::Seek>(
 fn unmarshal_session_connection_information<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
-    lexer.desc.connection_information = unmarshal_connection_information(&value)?;
+    let (value, raw) = read_value(lexer.reader)?;
+    let connection_information = unmarshal_connection_information(&value)?;
+    lexer.desc.original_lines.push(OriginalLine::new(
+        "c=",
+        &connection_information
+            .as_ref()
+            .map_or(String::new(), |c| c.to_string()),
+        &raw,
+    ));
+    lexer.desc.connection_information = connection_information;
     Ok(Some(StateFn { f: s5 }))
 }
 
@@ -1034,8 +1088,13 @@ fn unmarshal_connection_information(value: &str) -> Result<Option<ConnectionInfo
 fn unmarshal_session_bandwidth<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
-    lexer.desc.bandwidth.push(unmarshal_bandwidth(&value)?);
+    let (value, raw) = read_value(lexer.reader)?;
+    let bandwidth = unmarshal_bandwidth(&value)?;
+    lexer
+        .desc
+        .original_lines
+        .push(OriginalLine::new("b=", &bandwidth.to_string(), &raw));
+    lexer.desc.bandwidth.push(bandwidth);
     Ok(Some(StateFn { f: s5 }))
 }
 
@@ -1051,7 +1110,9 @@ fn unmarshal_bandwidth(value: &str) -> Result<Bandwidth> {
     } else {
         // Set according to currently registered with IANA
         // https://tools.ietf.org/html/rfc4566#section-5.8
-        let i = index_of(parts[0], &["CT", "AS"]);
+        // https://tools.ietf.org/html/rfc3556#section-2
+        // https://tools.ietf.org/html/rfc3890#section-6.2
+        let i = index_of(parts[0], &["CT", "AS", "RS", "RR", "TIAS"]);
         if i == -1 {
             return Err(Error::SdpInvalidValue(parts[0].to_owned()));
         }
@@ -1069,7 +1130,7 @@ fn unmarshal_bandwidth(value: &str) -> Result<Bandwidth> {
 fn unmarshal_timing<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
 
     let fields: Vec<&str> = value.split_whitespace().collect();
     if fields.len() < 2 {
@@ -1079,11 +1140,16 @@ fn unmarshal_timing<'a, R: io::BufRead + io::Seek>(
     let start_time = fields[0].parse::<u64>()?;
     let stop_time = fields[1].parse::<u64>()?;
 
+    let timing = Timing {
+        start_time,
+        stop_time,
+    };
+    lexer
+        .desc
+        .original_lines
+        .push(OriginalLine::new("t=", &timing.to_string(), &raw));
     lexer.desc.time_descriptions.push(TimeDescription {
-        timing: Timing {
-            start_time,
-            stop_time,
-        },
+        timing,
         repeat_times: vec![],
     });
 
@@ -1093,7 +1159,7 @@ fn unmarshal_timing<'a, R: io::BufRead + io::Seek>(
 fn unmarshal_repeat_times<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
 
     let fields: Vec<&str> = value.split_whitespace().collect();
     if fields.len() < 3 {
@@ -1108,11 +1174,16 @@ fn unmarshal_repeat_times<'a, R: io::BufRead + io::Seek>(
             let offset = parse_time_units(field)?;
             offsets.push(offset);
         }
-        latest_time_desc.repeat_times.push(RepeatTime {
+        let repeat_time = RepeatTime {
             interval,
             duration,
             offsets,
-        });
+        };
+        lexer
+            .desc
+            .original_lines
+            .push(OriginalLine::new("r=", &repeat_time.to_string(), &raw));
+        latest_time_desc.repeat_times.push(repeat_time);
 
         Ok(Some(StateFn { f: s9 }))
     } else {
@@ -1123,7 +1194,7 @@ fn unmarshal_repeat_times<'a, R: io::BufRead + io::Seek>(
 fn unmarshal_time_zones<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
 
     // These fields are transimitted in pairs
     // z=<adjustment time> <offset> <adjustment time> <offset> ....
@@ -1133,15 +1204,22 @@ fn unmarshal_time_zones<'a, R: io::BufRead + io::Seek>(
         return Err(Error::SdpInvalidSyntax(format!("`t={}`", value)));
     }
 
+    let mut time_zones = vec![];
     for i in (0..fields.len()).step_by(2) {
         let adjustment_time = fields[i].parse::<u64>()?;
         let offset = parse_time_units(fields[i + 1])?;
 
-        lexer.desc.time_zones.push(TimeZone {
+        time_zones.push(TimeZone {
             adjustment_time,
             offset,
         });
     }
+    lexer.desc.original_lines.push(OriginalLine::new(
+        "z=",
+        &time_zones_value(&time_zones),
+        &raw,
+    ));
+    lexer.desc.time_zones.extend(time_zones);
 
     Ok(Some(StateFn { f: s13 }))
 }
@@ -1149,7 +1227,11 @@ fn unmarshal_time_zones<'a, R: io::BufRead + io::Seek>(
 fn unmarshal_session_encryption_key<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
+    lexer
+        .desc
+        .original_lines
+        .push(OriginalLine::new("k=", &value, &raw));
     lexer.desc.encryption_key = Some(value);
     Ok(Some(StateFn { f: s11 }))
 }
@@ -1157,7 +1239,7 @@ fn unmarshal_session_encryption_key<'a, R: io::BufRead + io::Seek>(
 fn unmarshal_session_attribute<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
 
     let fields: Vec<&str> = value.splitn(2, ':').collect();
     let attribute = if fields.len() == 2 {
@@ -1171,6 +1253,10 @@ fn unmarshal_session_attribute<'a, R: io::BufRead + io::Seek>(
             value: None,
         }
     };
+    lexer
+        .desc
+        .original_lines
+        .push(OriginalLine::new("a=", &attribute.to_string(), &raw));
     lexer.desc.attributes.push(attribute);
 
     Ok(Some(StateFn { f: s11 }))
@@ -1179,7 +1265,7 @@ fn unmarshal_session_attribute<'a, R: io::BufRead + io::Seek>(
 fn unmarshal_media_description<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
 
     let fields: Vec<&str> = value.split_whitespace().collect();
     if fields.len() < 4 {
@@ -1229,21 +1315,25 @@ fn unmarshal_media_description<'a, R: io::BufRead + io::Seek>(
         formats.push(field.to_string());
     }
 
-    lexer.desc.media_descriptions.push(MediaDescription {
-        media_name: MediaName {
-            media: fields[0].to_owned(),
-            port: RangedPort {
-                value: port_value,
-                range: port_range,
-            },
-            protos,
-            formats,
+    let media_name = MediaName {
+        media: fields[0].to_owned(),
+        port: RangedPort {
+            value: port_value,
+            range: port_range,
         },
+        protos,
+        formats,
+    };
+    let mut original_lines = OriginalLines::default();
+    original_lines.push(OriginalLine::new("m=", &media_name.to_string(), &raw));
+    lexer.desc.media_descriptions.push(MediaDescription {
+        media_name,
         media_title: None,
         connection_information: None,
         bandwidth: vec![],
         encryption_key: None,
         attributes: vec![],
+        original_lines,
     });
 
     Ok(Some(StateFn { f: s12 }))
@@ -1252,9 +1342,12 @@ fn unmarshal_media_description<'a, R: io::BufRead + io::Seek>(
 fn unmarshal_media_title<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
 
     if let Some(latest_media_desc) = lexer.desc.media_descriptions.last_mut() {
+        latest_media_desc
+            .original_lines
+            .push(OriginalLine::new("i=", &value, &raw));
         latest_media_desc.media_title = Some(value);
         Ok(Some(StateFn { f: s16 }))
     } else {
@@ -1265,10 +1358,18 @@ fn unmarshal_media_title<'a, R: io::BufRead + io::Seek>(
 fn unmarshal_media_connection_information<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
 
     if let Some(latest_media_desc) = lexer.desc.media_descriptions.last_mut() {
-        latest_media_desc.connection_information = unmarshal_connection_information(&value)?;
+        let connection_information = unmarshal_connection_information(&value)?;
+        latest_media_desc.original_lines.push(OriginalLine::new(
+            "c=",
+            &connection_information
+                .as_ref()
+                .map_or(String::new(), |c| c.to_string()),
+            &raw,
+        ));
+        latest_media_desc.connection_information = connection_information;
         Ok(Some(StateFn { f: s15 }))
     } else {
         Err(Error::SdpEmptyTimeDescription)
@@ -1278,10 +1379,15 @@ fn unmarshal_media_connection_information<'a, R: io::BufRead + io::Seek>(
 fn unmarshal_media_bandwidth<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
 
     if let Some(latest_media_desc) = lexer.desc.media_descriptions.last_mut() {
         let bandwidth = unmarshal_bandwidth(&value)?;
+        latest_media_desc.original_lines.push(OriginalLine::new(
+            "b=",
+            &bandwidth.to_string(),
+            &raw,
+        ));
         latest_media_desc.bandwidth.push(bandwidth);
         Ok(Some(StateFn { f: s15 }))
     } else {
@@ -1292,9 +1398,12 @@ fn unmarshal_media_bandwidth<'a, R: io::BufRead + io::Seek>(
 fn unmarshal_media_encryption_key<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
 
     if let Some(latest_media_desc) = lexer.desc.media_descriptions.last_mut() {
+        latest_media_desc
+            .original_lines
+            .push(OriginalLine::new("k=", &value, &raw));
         latest_media_desc.encryption_key = Some(value);
         Ok(Some(StateFn { f: s14 }))
     } else {
@@ -1305,7 +1414,7 @@ fn unmarshal_media_encryption_key<'a, R: io::BufRead + io::Seek>(
 fn unmarshal_media_attribute<'a, R: io::BufRead + io::Seek>(
     lexer: &mut Lexer<'a, R>,
 ) -> Result<Option<StateFn<'a, R>>> {
-    let (value, _) = read_value(lexer.reader)?;
+    let (value, raw) = read_value(lexer.reader)?;
 
     let fields: Vec<&str> = value.splitn(2, ':').collect();
     let attribute = if fields.len() == 2 {
@@ -1321,6 +1430,11 @@ fn unmarshal_media_attribute<'a, R: io::BufRead + io::Seek>(
     };
 
     if let Some(latest_media_desc) = lexer.desc.media_descriptions.last_mut() {
+        latest_media_desc.original_lines.push(OriginalLine::new(
+            "a=",
+            &attribute.to_string(),
+            &raw,
+        ));
         latest_media_desc.attributes.push(attribute);
         Ok(Some(StateFn { f: s14 }))
     } else {
@@ -1328,6 +1442,14 @@ fn unmarshal_media_attribute<'a, R: io::BufRead + io::Seek>(
     }
 }
 
+fn time_zones_value(time_zones: &[TimeZone]) -> String {
+    time_zones
+        .iter()
+        .map(|time_zone| time_zone.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
 fn parse_time_units(value: &str) -> Result<i64> {
     // Some time offsets in the protocol can be provided with a shorthand
     // notation. This code ensures to convert it to NTP timestamp format.
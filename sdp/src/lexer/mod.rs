@@ -42,10 +42,12 @@ pub fn read_type<R: io::BufRead + io::Seek>(reader: &mut R) -> Result<(Vec<u8>,
     }
 }
 
-pub fn read_value<R: io::BufRead + io::Seek>(reader: &mut R) -> Result<(String, usize)> {
-    let mut value = String::new();
-    let num_bytes = reader.read_line(&mut value)?;
-    Ok((value.trim().to_string(), num_bytes))
+/// read_value reads the rest of the line. It returns the value without surrounding whitespace
+/// and the line as it was read, including the line ending.
+pub fn read_value<R: io::BufRead + io::Seek>(reader: &mut R) -> Result<(String, String)> {
+    let mut raw = String::new();
+    reader.read_line(&mut raw)?;
+    Ok((raw.trim().to_string(), raw))
 }
 
 pub fn index_of(element: &str, data: &[&str]) -> i32 {
@@ -57,10 +59,8 @@ pub fn index_of(element: &str, data: &[&str]) -> i32 {
     -1
 }
 
-pub fn key_value_build(key: &str, value: Option<&String>) -> String {
+pub fn key_value_build(lines: &mut Vec<String>, key: &str, value: Option<&String>) {
     if let Some(val) = value {
-        format!("{}{}{}", key, val, END_LINE)
-    } else {
-        "".to_string()
+        lines.push(format!("{}{}", key, val));
     }
 }
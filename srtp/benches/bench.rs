@@ -0,0 +1,76 @@
+use bytes::BytesMut;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use webrtc_srtp::{Context, ProtectionProfile};
+
+const RTP_HEADER: [u8; 12] = [
+    0x80, 0x0f, 0x12, 0x34, 0xde, 0xca, 0xfb, 0xad, 0xca, 0xfe, 0xba, 0xbe,
+];
+const RTP_PAYLOAD_SIZE: usize = 1000;
+
+fn new_context(profile: ProtectionProfile) -> Context {
+    Context::new(
+        &vec![0x01; profile.key_len()],
+        &vec![0x02; profile.salt_len()],
+        profile,
+        None,
+        None,
+    )
+    .unwrap()
+}
+
+fn benchmark_context(c: &mut Criterion) {
+    let mut rtp = RTP_HEADER.to_vec();
+    rtp.extend_from_slice(&[0xab; RTP_PAYLOAD_SIZE]);
+
+    for (name, profile) in [
+        (
+            "AES_CM_128_HMAC_SHA1_80",
+            ProtectionProfile::Aes128CmHmacSha1_80,
+        ),
+        ("AEAD_AES_128_GCM", ProtectionProfile::AeadAes128Gcm),
+    ] {
+        ///////////////////////////////////////////////////////////////////////////////////////////
+        let mut encrypt = new_context(profile);
+        c.bench_function(&format!("Benchmark Context {} EncryptRTP", name), |b| {
+            b.iter(|| encrypt.encrypt_rtp(&rtp).unwrap());
+        });
+
+        let srtp = new_context(profile).encrypt_rtp(&rtp).unwrap();
+        let mut decrypt = new_context(profile);
+        c.bench_function(&format!("Benchmark Context {} DecryptRTP", name), |b| {
+            b.iter(|| decrypt.decrypt_rtp(&srtp).unwrap());
+        });
+
+        let mut encrypt = new_context(profile);
+        c.bench_function(
+            &format!("Benchmark Context {} EncryptRTP in place", name),
+            |b| {
+                b.iter_batched_ref(
+                    || {
+                        let mut packet = BytesMut::with_capacity(srtp.len());
+                        packet.extend_from_slice(&rtp);
+                        packet
+                    },
+                    |packet| encrypt.encrypt_rtp_in_place(packet).unwrap(),
+                    BatchSize::SmallInput,
+                );
+            },
+        );
+
+        let mut decrypt = new_context(profile);
+        c.bench_function(
+            &format!("Benchmark Context {} DecryptRTP in place", name),
+            |b| {
+                b.iter_batched_ref(
+                    || BytesMut::from(&srtp[..]),
+                    |packet| decrypt.decrypt_rtp_in_place(packet).unwrap(),
+                    BatchSize::SmallInput,
+                );
+            },
+        );
+    }
+}
+
+criterion_group!(benches, benchmark_context);
+criterion_main!(benches);
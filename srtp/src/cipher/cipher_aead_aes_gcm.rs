@@ -1,12 +1,13 @@
 use aes_gcm::{
-    aead::{generic_array::GenericArray, Aead, NewAead, Payload},
-    Aes128Gcm, Nonce,
+    aead::{generic_array::GenericArray, Aead, AeadInPlace, NewAead, Payload},
+    Aes128Gcm, Nonce, Tag,
 };
 use byteorder::{BigEndian, ByteOrder};
 use bytes::{Bytes, BytesMut};
 
 use super::Cipher;
 use crate::{
+    cryptex,
     error::{Error, Result},
     key_derivation::*,
 };
@@ -29,34 +30,79 @@ impl Cipher for CipherAeadAesGcm {
         CIPHER_AEAD_AES_GCM_AUTH_TAG_LEN
     }
 
-    fn encrypt_rtp(
+    fn encrypt_rtp_in_place(
         &mut self,
-        payload: &[u8],
+        packet: &mut BytesMut,
         header: &rtp::header::Header,
         roc: u32,
-    ) -> Result<Bytes> {
-        // Grow the given buffer to fit the output.
-        let mut writer =
-            BytesMut::with_capacity(header.marshal_size() + payload.len() + self.auth_tag_len());
+    ) -> Result<()> {
+        let nonce = self.rtp_initialization_vector(header, roc);
+        let payload_offset = header.marshal_size();
 
-        let data = header.marshal()?;
-        writer.extend(data);
+        // The header is the additional data.
+        let (aad, payload) = packet.split_at_mut(payload_offset);
+        let tag =
+            self.srtp_cipher
+                .encrypt_in_place_detached(Nonce::from_slice(&nonce), aad, payload)?;
+
+        packet.extend_from_slice(&tag);
+        Ok(())
+    }
+
+    fn decrypt_rtp_in_place(
+        &mut self,
+        packet: &mut BytesMut,
+        header: &rtp::header::Header,
+        roc: u32,
+    ) -> Result<()> {
+        let payload_offset = header.marshal_size();
+        if packet.len() < payload_offset + self.auth_tag_len() {
+            return Err(Error::ErrFailedToVerifyAuthTag);
+        }
 
         let nonce = self.rtp_initialization_vector(header, roc);
+        let tag_offset = packet.len() - self.auth_tag_len();
+        let (packet_without_tag, tag) = packet.split_at_mut(tag_offset);
+        let (aad, payload) = packet_without_tag.split_at_mut(payload_offset);
+        self.srtp_cipher.decrypt_in_place_detached(
+            Nonce::from_slice(&nonce),
+            aad,
+            payload,
+            Tag::from_slice(tag),
+        )?;
 
+        packet.truncate(tag_offset);
+        Ok(())
+    }
+
+    fn encrypt_rtp_cryptex(
+        &mut self,
+        packet: &[u8],
+        header: &rtp::header::Header,
+        roc: u32,
+    ) -> Result<Bytes> {
+        let mut plaintext = packet.to_vec();
+        let encrypted_offset = cryptex::to_encryption_layout(&mut plaintext);
+
+        // The fixed header and the extension header are the additional data.
+        let nonce = self.rtp_initialization_vector(header, roc);
         let encrypted = self.srtp_cipher.encrypt(
             Nonce::from_slice(&nonce),
             Payload {
-                msg: payload,
-                aad: &writer,
+                msg: &plaintext[encrypted_offset..],
+                aad: &plaintext[..encrypted_offset],
             },
         )?;
 
+        let mut writer = BytesMut::with_capacity(encrypted_offset + encrypted.len());
+        writer.extend_from_slice(&plaintext[..encrypted_offset]);
         writer.extend(encrypted);
+        cryptex::from_encryption_layout(&mut writer);
+
         Ok(writer.freeze())
     }
 
-    fn decrypt_rtp(
+    fn decrypt_rtp_cryptex(
         &mut self,
         ciphertext: &[u8],
         header: &rtp::header::Header,
@@ -66,64 +112,71 @@ impl Cipher for CipherAeadAesGcm {
             return Err(Error::ErrFailedToVerifyAuthTag);
         }
 
+        let mut ciphertext = ciphertext.to_vec();
+        let encrypted_offset = cryptex::to_encryption_layout(&mut ciphertext);
+
         let nonce = self.rtp_initialization_vector(header, roc);
-        let payload_offset = header.marshal_size();
         let decrypted_msg: Vec<u8> = self.srtp_cipher.decrypt(
             Nonce::from_slice(&nonce),
             Payload {
-                msg: &ciphertext[payload_offset..],
-                aad: &ciphertext[..payload_offset],
+                msg: &ciphertext[encrypted_offset..],
+                aad: &ciphertext[..encrypted_offset],
             },
         )?;
 
-        let mut writer = BytesMut::with_capacity(payload_offset + decrypted_msg.len());
-        writer.extend_from_slice(&ciphertext[..payload_offset]);
+        let mut writer = BytesMut::with_capacity(encrypted_offset + decrypted_msg.len());
+        writer.extend_from_slice(&ciphertext[..encrypted_offset]);
         writer.extend(decrypted_msg);
+        cryptex::from_encryption_layout(&mut writer);
+        cryptex::restore_profile(&mut writer);
 
         Ok(writer.freeze())
     }
 
-    fn encrypt_rtcp(&mut self, decrypted: &[u8], srtcp_index: usize, ssrc: u32) -> Result<Bytes> {
+    fn encrypt_rtcp_in_place(
+        &mut self,
+        packet: &mut BytesMut,
+        srtcp_index: usize,
+        ssrc: u32,
+    ) -> Result<()> {
         let iv = self.rtcp_initialization_vector(srtcp_index, ssrc);
-        let aad = self.rtcp_additional_authenticated_data(decrypted, srtcp_index);
+        let aad = self.rtcp_additional_authenticated_data(packet, srtcp_index);
 
-        let encrypted_data = self.srtcp_cipher.encrypt(
+        let tag = self.srtcp_cipher.encrypt_in_place_detached(
             Nonce::from_slice(&iv),
-            Payload {
-                msg: &decrypted[8..],
-                aad: &aad,
-            },
+            &aad,
+            &mut packet[8..],
         )?;
 
-        let mut writer = BytesMut::with_capacity(encrypted_data.len() + aad.len());
-        writer.extend_from_slice(&decrypted[..8]);
-        writer.extend(encrypted_data);
-        writer.extend_from_slice(&aad[8..]);
-
-        Ok(writer.freeze())
+        packet.extend_from_slice(&tag);
+        packet.extend_from_slice(&aad[8..]);
+        Ok(())
     }
 
-    fn decrypt_rtcp(&mut self, encrypted: &[u8], srtcp_index: usize, ssrc: u32) -> Result<Bytes> {
-        if encrypted.len() < self.auth_tag_len() + SRTCP_INDEX_SIZE {
+    fn decrypt_rtcp_in_place(
+        &mut self,
+        packet: &mut BytesMut,
+        srtcp_index: usize,
+        ssrc: u32,
+    ) -> Result<()> {
+        if packet.len() < 8 + self.auth_tag_len() + SRTCP_INDEX_SIZE {
             return Err(Error::ErrFailedToVerifyAuthTag);
         }
 
         let nonce = self.rtcp_initialization_vector(srtcp_index, ssrc);
-        let aad = self.rtcp_additional_authenticated_data(encrypted, srtcp_index);
+        let aad = self.rtcp_additional_authenticated_data(packet, srtcp_index);
 
-        let decrypted_data = self.srtcp_cipher.decrypt(
+        let tag_offset = packet.len() - SRTCP_INDEX_SIZE - self.auth_tag_len();
+        let (packet_without_tag, tag) = packet.split_at_mut(tag_offset);
+        self.srtcp_cipher.decrypt_in_place_detached(
             Nonce::from_slice(&nonce),
-            Payload {
-                msg: &encrypted[8..(encrypted.len() - SRTCP_INDEX_SIZE)],
-                aad: &aad,
-            },
+            &aad,
+            &mut packet_without_tag[8..],
+            Tag::from_slice(&tag[..self.auth_tag_len()]),
         )?;
 
-        let mut writer = BytesMut::with_capacity(8 + decrypted_data.len());
-        writer.extend_from_slice(&encrypted[..8]);
-        writer.extend(decrypted_data);
-
-        Ok(writer.freeze())
+        packet.truncate(tag_offset);
+        Ok(())
     }
 
     fn get_rtcp_index(&self, input: &[u8]) -> usize {
@@ -1,12 +1,12 @@
 use super::Cipher;
 use crate::error::Result;
-use crate::{error::Error, key_derivation::*, protection_profile::*};
+use crate::{cryptex, error::Error, key_derivation::*, protection_profile::*};
 use util::marshal::*;
 
 use aes::cipher::generic_array::GenericArray;
 use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
 use bytes::{BufMut, Bytes, BytesMut};
-use ctr::cipher::{NewCipher, StreamCipher, StreamCipherSeek};
+use ctr::cipher::{NewCipher, StreamCipher};
 use hmac::{Hmac, Mac, NewMac};
 use sha1::Sha1;
 use std::io::BufWriter;
@@ -129,6 +129,30 @@ impl CipherAesCmHmacSha1 {
         Ok(code_bytes[0..self.auth_tag_len()].to_vec())
     }
 
+    fn srtp_keystream(&self, header: &rtp::header::Header, roc: u32) -> Result<Aes128Ctr> {
+        let counter = generate_counter(
+            header.sequence_number,
+            roc,
+            header.ssrc,
+            &self.srtp_session_salt,
+        )?;
+        let key = GenericArray::from_slice(&self.srtp_session_key);
+        let nonce = GenericArray::from_slice(&counter);
+        Ok(Aes128Ctr::new(key, nonce))
+    }
+
+    fn srtcp_keystream(&self, srtcp_index: usize, ssrc: u32) -> Result<Aes128Ctr> {
+        let counter = generate_counter(
+            (srtcp_index & 0xFFFF) as u16,
+            (srtcp_index >> 16) as u32,
+            ssrc,
+            &self.srtcp_session_salt,
+        )?;
+        let key = GenericArray::from_slice(&self.srtcp_session_key);
+        let nonce = GenericArray::from_slice(&counter);
+        Ok(Aes128Ctr::new(key, nonce))
+    }
+
     /// https://tools.ietf.org/html/rfc3711#section-4.2
     ///
     /// The pre-defined authentication transform for SRTP is HMAC-SHA1
@@ -164,43 +188,79 @@ impl Cipher for CipherAesCmHmacSha1 {
             as usize
     }
 
-    fn encrypt_rtp(
+    fn encrypt_rtp_in_place(
         &mut self,
-        payload: &[u8],
+        packet: &mut BytesMut,
         header: &rtp::header::Header,
         roc: u32,
-    ) -> Result<Bytes> {
-        let mut writer =
-            BytesMut::with_capacity(header.marshal_size() + payload.len() + self.auth_tag_len());
+    ) -> Result<()> {
+        // Encrypt the payload, the header stays unencrypted.
+        let mut stream = self.srtp_keystream(header, roc)?;
+        let payload_offset = header.marshal_size();
+        stream.apply_keystream(&mut packet[payload_offset..]);
 
-        // Copy the header unencrypted.
-        let data = header.marshal()?;
-        writer.extend(data);
+        // Generate the auth tag.
+        let auth_tag = self.generate_srtp_auth_tag(packet, roc)?;
+        packet.extend(auth_tag);
 
-        // Write the plaintext header to the destination buffer.
-        writer.extend_from_slice(payload);
+        Ok(())
+    }
 
-        // Encrypt the payload
-        let counter = generate_counter(
-            header.sequence_number,
-            roc,
-            header.ssrc,
-            &self.srtp_session_salt,
-        )?;
-        let key = GenericArray::from_slice(&self.srtp_session_key);
-        let nonce = GenericArray::from_slice(&counter);
-        let mut stream = Aes128Ctr::new(key, nonce);
+    fn decrypt_rtp_in_place(
+        &mut self,
+        packet: &mut BytesMut,
+        header: &rtp::header::Header,
+        roc: u32,
+    ) -> Result<()> {
+        if packet.len() < self.auth_tag_len() {
+            return Err(Error::SrtpTooSmall(packet.len(), self.auth_tag_len()));
+        }
+
+        // Split the auth tag and the cipher text into two parts.
+        let tag_offset = packet.len() - self.auth_tag_len();
+        let (cipher_text, actual_tag) = packet.split_at(tag_offset);
+
+        // Generate the auth tag we expect to see from the ciphertext.
+        let expected_tag = self.generate_srtp_auth_tag(cipher_text, roc)?;
+
+        // See if the auth tag actually matches.
+        // We use a constant time comparison to prevent timing attacks.
+        if actual_tag.ct_eq(&expected_tag).unwrap_u8() != 1 {
+            return Err(Error::RtpFailedToVerifyAuthTag);
+        }
+        packet.truncate(tag_offset);
+
+        // Decrypt the ciphertext for the payload.
+        let mut stream = self.srtp_keystream(header, roc)?;
         let payload_offset = header.marshal_size();
-        stream.apply_keystream(&mut writer[payload_offset..]);
+        stream.apply_keystream(&mut packet[payload_offset..]);
 
-        // Generate the auth tag.
+        Ok(())
+    }
+
+    fn encrypt_rtp_cryptex(
+        &mut self,
+        packet: &[u8],
+        header: &rtp::header::Header,
+        roc: u32,
+    ) -> Result<Bytes> {
+        let mut writer = BytesMut::with_capacity(packet.len() + self.auth_tag_len());
+        writer.extend_from_slice(packet);
+
+        // Encrypt the CSRCs, the extension data and the payload
+        let mut stream = self.srtp_keystream(header, roc)?;
+        let encrypted_offset = cryptex::to_encryption_layout(&mut writer);
+        stream.apply_keystream(&mut writer[encrypted_offset..]);
+        cryptex::from_encryption_layout(&mut writer);
+
+        // The auth tag covers the packet as sent.
         let auth_tag = self.generate_srtp_auth_tag(&writer, roc)?;
         writer.extend(auth_tag);
 
         Ok(writer.freeze())
     }
 
-    fn decrypt_rtp(
+    fn decrypt_rtp_cryptex(
         &mut self,
         encrypted: &[u8],
         header: &rtp::header::Header,
@@ -210,97 +270,72 @@ impl Cipher for CipherAesCmHmacSha1 {
             return Err(Error::SrtpTooSmall(encrypted.len(), self.auth_tag_len()));
         }
 
-        let mut writer = BytesMut::with_capacity(encrypted.len() - self.auth_tag_len());
-
-        // Split the auth tag and the cipher text into two parts.
         let actual_tag = &encrypted[encrypted.len() - self.auth_tag_len()..];
         let cipher_text = &encrypted[..encrypted.len() - self.auth_tag_len()];
 
-        // Generate the auth tag we expect to see from the ciphertext.
         let expected_tag = self.generate_srtp_auth_tag(cipher_text, roc)?;
-
-        // See if the auth tag actually matches.
-        // We use a constant time comparison to prevent timing attacks.
         if actual_tag.ct_eq(&expected_tag).unwrap_u8() != 1 {
             return Err(Error::RtpFailedToVerifyAuthTag);
         }
 
-        // Write cipher_text to the destination buffer.
+        let mut writer = BytesMut::with_capacity(cipher_text.len());
         writer.extend_from_slice(cipher_text);
 
-        // Decrypt the ciphertext for the payload.
-        let counter = generate_counter(
-            header.sequence_number,
-            roc,
-            header.ssrc,
-            &self.srtp_session_salt,
-        )?;
-
-        let key = GenericArray::from_slice(&self.srtp_session_key);
-        let nonce = GenericArray::from_slice(&counter);
-        let mut stream = Aes128Ctr::new(key, nonce);
-        let payload_offset = header.marshal_size();
-        stream.seek(0);
-        stream.apply_keystream(&mut writer[payload_offset..]);
+        let mut stream = self.srtp_keystream(header, roc)?;
+        let encrypted_offset = cryptex::to_encryption_layout(&mut writer);
+        stream.apply_keystream(&mut writer[encrypted_offset..]);
+        cryptex::from_encryption_layout(&mut writer);
+        cryptex::restore_profile(&mut writer);
 
         Ok(writer.freeze())
     }
 
-    fn encrypt_rtcp(&mut self, decrypted: &[u8], srtcp_index: usize, ssrc: u32) -> Result<Bytes> {
-        let mut writer =
-            BytesMut::with_capacity(decrypted.len() + SRTCP_INDEX_SIZE + self.auth_tag_len());
-
-        // Write the decrypted to the destination buffer.
-        writer.extend_from_slice(decrypted);
-
+    fn encrypt_rtcp_in_place(
+        &mut self,
+        packet: &mut BytesMut,
+        srtcp_index: usize,
+        ssrc: u32,
+    ) -> Result<()> {
         // Encrypt everything after header
-        let counter = generate_counter(
-            (srtcp_index & 0xFFFF) as u16,
-            (srtcp_index >> 16) as u32,
-            ssrc,
-            &self.srtcp_session_salt,
-        )?;
-
-        let key = GenericArray::from_slice(&self.srtcp_session_key);
-        let nonce = GenericArray::from_slice(&counter);
-        let mut stream = Aes128Ctr::new(key, nonce);
-
+        let mut stream = self.srtcp_keystream(srtcp_index, ssrc)?;
         stream.apply_keystream(
-            &mut writer[rtcp::header::HEADER_LENGTH + rtcp::header::SSRC_LENGTH..],
+            &mut packet[rtcp::header::HEADER_LENGTH + rtcp::header::SSRC_LENGTH..],
         );
 
         // Add SRTCP index and set Encryption bit
-        writer.put_u32(srtcp_index as u32 | (1u32 << 31));
+        packet.put_u32(srtcp_index as u32 | (1u32 << 31));
 
         // Generate the auth tag.
-        let auth_tag = self.generate_srtcp_auth_tag(&writer);
-        writer.extend(auth_tag);
+        let auth_tag = self.generate_srtcp_auth_tag(packet);
+        packet.extend(auth_tag);
 
-        Ok(writer.freeze())
+        Ok(())
     }
 
-    fn decrypt_rtcp(&mut self, encrypted: &[u8], srtcp_index: usize, ssrc: u32) -> Result<Bytes> {
-        if encrypted.len() < self.auth_tag_len() + SRTCP_INDEX_SIZE {
+    fn decrypt_rtcp_in_place(
+        &mut self,
+        packet: &mut BytesMut,
+        srtcp_index: usize,
+        ssrc: u32,
+    ) -> Result<()> {
+        if packet.len() < self.auth_tag_len() + SRTCP_INDEX_SIZE {
             return Err(Error::SrtcpTooSmall(
-                encrypted.len(),
+                packet.len(),
                 self.auth_tag_len() + SRTCP_INDEX_SIZE,
             ));
         }
 
-        let tail_offset = encrypted.len() - (self.auth_tag_len() + SRTCP_INDEX_SIZE);
-
-        let mut writer = BytesMut::with_capacity(tail_offset);
-
-        writer.extend_from_slice(&encrypted[0..tail_offset]);
+        let tail_offset = packet.len() - (self.auth_tag_len() + SRTCP_INDEX_SIZE);
 
-        let is_encrypted = encrypted[tail_offset] >> 7;
+        let is_encrypted = packet[tail_offset] >> 7;
         if is_encrypted == 0 {
-            return Ok(writer.freeze());
+            packet.truncate(tail_offset);
+            return Ok(());
         }
 
         // Split the auth tag and the cipher text into two parts.
-        let actual_tag = &encrypted[encrypted.len() - self.auth_tag_len()..];
-        let cipher_text = &encrypted[..encrypted.len() - self.auth_tag_len()];
+        let tag_offset = packet.len() - self.auth_tag_len();
+        let (cipher_text, actual_tag) = packet.split_at(tag_offset);
 
         // Generate the auth tag we expect to see from the ciphertext.
         let expected_tag = self.generate_srtcp_auth_tag(cipher_text);
@@ -310,23 +345,13 @@ impl Cipher for CipherAesCmHmacSha1 {
         if actual_tag.ct_eq(&expected_tag).unwrap_u8() != 1 {
             return Err(Error::RtcpFailedToVerifyAuthTag);
         }
+        packet.truncate(tail_offset);
 
-        let counter = generate_counter(
-            (srtcp_index & 0xFFFF) as u16,
-            (srtcp_index >> 16) as u32,
-            ssrc,
-            &self.srtcp_session_salt,
-        )?;
-
-        let key = GenericArray::from_slice(&self.srtcp_session_key);
-        let nonce = GenericArray::from_slice(&counter);
-        let mut stream = Aes128Ctr::new(key, nonce);
-
-        stream.seek(0);
+        let mut stream = self.srtcp_keystream(srtcp_index, ssrc)?;
         stream.apply_keystream(
-            &mut writer[rtcp::header::HEADER_LENGTH + rtcp::header::SSRC_LENGTH..],
+            &mut packet[rtcp::header::HEADER_LENGTH + rtcp::header::SSRC_LENGTH..],
         );
 
-        Ok(writer.freeze())
+        Ok(())
     }
 }
@@ -1,9 +1,11 @@
 pub mod cipher_aead_aes_gcm;
 pub mod cipher_aes_cm_hmac_sha1;
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
+use util::marshal::*;
 
 use crate::error::Result;
+use crate::key_derivation::SRTCP_INDEX_SIZE;
 
 ///NOTE: Auth tag and AEAD auth tag are placed at the different position in SRTCP
 ///
@@ -37,25 +39,100 @@ pub(crate) trait Cipher {
     /// Retrieved RTCP index.
     fn get_rtcp_index(&self, input: &[u8]) -> usize;
 
+    /// Encrypt RTP packet in place, the packet holds the marshalled header followed by the
+    /// payload. The payload is encrypted where it is and the auth tag is appended.
+    fn encrypt_rtp_in_place(
+        &mut self,
+        packet: &mut BytesMut,
+        header: &rtp::header::Header,
+        roc: u32,
+    ) -> Result<()>;
+
+    /// Decrypt RTP packet in place, the auth tag is checked and removed.
+    fn decrypt_rtp_in_place(
+        &mut self,
+        packet: &mut BytesMut,
+        header: &rtp::header::Header,
+        roc: u32,
+    ) -> Result<()>;
+
     /// Encrypt RTP payload.
     fn encrypt_rtp(
         &mut self,
         payload: &[u8],
         header: &rtp::header::Header,
         roc: u32,
-    ) -> Result<Bytes>;
+    ) -> Result<Bytes> {
+        let mut writer =
+            BytesMut::with_capacity(header.marshal_size() + payload.len() + self.auth_tag_len());
+        writer.extend(header.marshal()?);
+        writer.extend_from_slice(payload);
+
+        self.encrypt_rtp_in_place(&mut writer, header, roc)?;
+        Ok(writer.freeze())
+    }
 
     /// Decrypt RTP payload.
     fn decrypt_rtp(
         &mut self,
-        payload: &[u8],
+        encrypted: &[u8],
+        header: &rtp::header::Header,
+        roc: u32,
+    ) -> Result<Bytes> {
+        let mut writer = BytesMut::from(encrypted);
+        self.decrypt_rtp_in_place(&mut writer, header, roc)?;
+        Ok(writer.freeze())
+    }
+
+    /// Encrypt RTP packet with Cryptex, the CSRCs and the header extension data are encrypted
+    /// as well. The packet is the plaintext as returned by `cryptex::marshal_cryptex`.
+    fn encrypt_rtp_cryptex(
+        &mut self,
+        packet: &[u8],
+        header: &rtp::header::Header,
+        roc: u32,
+    ) -> Result<Bytes>;
+
+    /// Decrypt RTP packet with Cryptex, the extension profile of the decrypted packet is
+    /// restored.
+    fn decrypt_rtp_cryptex(
+        &mut self,
+        encrypted: &[u8],
         header: &rtp::header::Header,
         roc: u32,
     ) -> Result<Bytes>;
 
+    /// Encrypt RTCP packet in place, everything after the sender SSRC is encrypted where
+    /// it is and the SRTCP index and the auth tag are appended.
+    fn encrypt_rtcp_in_place(
+        &mut self,
+        packet: &mut BytesMut,
+        srtcp_index: usize,
+        ssrc: u32,
+    ) -> Result<()>;
+
+    /// Decrypt RTCP packet in place, the SRTCP index and the auth tag are removed.
+    fn decrypt_rtcp_in_place(
+        &mut self,
+        packet: &mut BytesMut,
+        srtcp_index: usize,
+        ssrc: u32,
+    ) -> Result<()>;
+
     /// Encrypt RTCP payload.
-    fn encrypt_rtcp(&mut self, payload: &[u8], srtcp_index: usize, ssrc: u32) -> Result<Bytes>;
+    fn encrypt_rtcp(&mut self, decrypted: &[u8], srtcp_index: usize, ssrc: u32) -> Result<Bytes> {
+        let mut writer =
+            BytesMut::with_capacity(decrypted.len() + SRTCP_INDEX_SIZE + self.auth_tag_len());
+        writer.extend_from_slice(decrypted);
+
+        self.encrypt_rtcp_in_place(&mut writer, srtcp_index, ssrc)?;
+        Ok(writer.freeze())
+    }
 
     /// Decrypt RTCP payload.
-    fn decrypt_rtcp(&mut self, payload: &[u8], srtcp_index: usize, ssrc: u32) -> Result<Bytes>;
+    fn decrypt_rtcp(&mut self, encrypted: &[u8], srtcp_index: usize, ssrc: u32) -> Result<Bytes> {
+        let mut writer = BytesMut::from(encrypted);
+        self.decrypt_rtcp_in_place(&mut writer, srtcp_index, ssrc)?;
+        Ok(writer.freeze())
+    }
 }
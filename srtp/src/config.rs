@@ -30,6 +30,10 @@ pub struct Config {
 
     pub local_rtcp_options: Option<ContextOption>,
     pub remote_rtcp_options: Option<ContextOption>,
+
+    /// Encrypt the CSRCs and header extensions of RTP packets with Cryptex, see
+    /// `Context::set_cryptex`.
+    pub cryptex: bool,
 }
 
 impl Config {
@@ -1,7 +1,7 @@
 use super::*;
 use crate::key_derivation::*;
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use lazy_static::lazy_static;
 
 const CIPHER_CONTEXT_ALGO: ProtectionProfile = ProtectionProfile::Aes128CmHmacSha1_80;
@@ -61,6 +61,41 @@ fn test_context_index() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_context_remove_ssrc() -> Result<()> {
+    let key_len = CIPHER_CONTEXT_ALGO.key_len();
+    let salt_len = CIPHER_CONTEXT_ALGO.salt_len();
+
+    let mut c = Context::new(
+        &vec![0; key_len],
+        &vec![0; salt_len],
+        CIPHER_CONTEXT_ALGO,
+        None,
+        None,
+    )?;
+
+    c.set_roc(123, 100);
+    c.set_index(456, 100);
+    assert_eq!(c.srtp_ssrcs(), vec![123]);
+    assert_eq!(c.srtcp_ssrcs(), vec![456]);
+
+    assert!(c.remove_srtp_ssrc(123));
+    assert!(!c.remove_srtp_ssrc(123), "SSRC must only be removed once");
+    assert!(
+        c.get_roc(123).is_none(),
+        "ROC must be dropped with the SSRC"
+    );
+
+    assert!(c.remove_srtcp_ssrc(456));
+    assert!(!c.remove_srtcp_ssrc(456), "SSRC must only be removed once");
+    assert!(
+        c.get_index(456).is_none(),
+        "Index must be dropped with the SSRC"
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_key_len() -> Result<()> {
     let key_len = CIPHER_CONTEXT_ALGO.key_len();
@@ -113,7 +148,7 @@ fn test_valid_packet_counter() -> Result<()> {
         0xcf, 0x90, 0x1e, 0xa5, 0xda, 0xd3, 0x2c, 0x15, 0x00, 0xa2, 0x24, 0xae, 0xae, 0xaf, 0x00,
         0x00,
     ];
-    let counter = generate_counter(32846, s.rollover_counter, s.ssrc, &srtp_session_salt)?;
+    let counter = generate_counter(32846, s.rollover_counter(), s.ssrc, &srtp_session_salt)?;
     assert_eq!(
         counter, expected_counter,
         "Session Key {:?} does not match expected {:?}",
@@ -305,3 +340,125 @@ fn test_decrypt_rtcp() {
 
     assert_eq!(gotten_decrypted_rtcp_packet, *DECRYPTED_RTCP_PACKET)
 }
+
+#[test]
+fn test_context_without_runtime() {
+    fn assert_send<T: Send>() {}
+    assert_send::<Context>();
+
+    for profile in [
+        ProtectionProfile::Aes128CmHmacSha1_80,
+        ProtectionProfile::AeadAes128Gcm,
+    ] {
+        let new_context = || {
+            Context::new(
+                &vec![0x01; profile.key_len()],
+                &vec![0x02; profile.salt_len()],
+                profile,
+                None,
+                None,
+            )
+            .expect("Error creating srtp context")
+        };
+        let mut encrypt = new_context();
+        let decrypt = new_context();
+
+        let encrypted_rtp = encrypt
+            .encrypt_rtp(&DECRYPTED_RTP_PACKET)
+            .expect("Error encrypting rtp payload");
+        let encrypted_rtcp = encrypt
+            .encrypt_rtcp(&DECRYPTED_RTCP_PACKET)
+            .expect("Error encrypting rtcp payload");
+
+        // The decrypting context is moved to a plain thread, no runtime involved
+        let (decrypted_rtp, decrypted_rtcp) = std::thread::spawn(move || {
+            let mut decrypt = decrypt;
+            (
+                decrypt.decrypt_rtp(&encrypted_rtp),
+                decrypt.decrypt_rtcp(&encrypted_rtcp),
+            )
+        })
+        .join()
+        .expect("decrypting thread panicked");
+
+        assert_eq!(
+            decrypted_rtp.expect("Error decrypting rtp payload"),
+            *DECRYPTED_RTP_PACKET
+        );
+        assert_eq!(
+            decrypted_rtcp.expect("Error decrypting rtcp payload"),
+            *DECRYPTED_RTCP_PACKET
+        );
+    }
+}
+
+#[test]
+fn test_context_in_place() {
+    for profile in [
+        ProtectionProfile::Aes128CmHmacSha1_80,
+        ProtectionProfile::AeadAes128Gcm,
+    ] {
+        let new_context = || {
+            Context::new(
+                &vec![0x01; profile.key_len()],
+                &vec![0x02; profile.salt_len()],
+                profile,
+                None,
+                None,
+            )
+            .expect("Error creating srtp context")
+        };
+        let mut encrypt = new_context();
+        let mut encrypt_in_place = new_context();
+        let mut decrypt_in_place = new_context();
+
+        let expected_rtp = encrypt
+            .encrypt_rtp(&DECRYPTED_RTP_PACKET)
+            .expect("Error encrypting rtp payload");
+        let mut rtp = BytesMut::with_capacity(expected_rtp.len());
+        rtp.extend_from_slice(&DECRYPTED_RTP_PACKET);
+        encrypt_in_place
+            .encrypt_rtp_in_place(&mut rtp)
+            .expect("Error encrypting rtp payload in place");
+        assert_eq!(rtp, expected_rtp, "{:?} in place RTP encryption", profile);
+
+        // A packet that fails to authenticate is left as it was
+        let mut tampered = rtp.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 0xff;
+        let result = decrypt_in_place.decrypt_rtp_in_place(&mut tampered);
+        assert!(result.is_err(), "{:?} accepted a tampered packet", profile);
+        assert_eq!(tampered[..last], rtp[..last]);
+
+        decrypt_in_place
+            .decrypt_rtp_in_place(&mut rtp)
+            .expect("Error decrypting rtp payload in place");
+        assert_eq!(
+            rtp, *DECRYPTED_RTP_PACKET,
+            "{:?} in place RTP decryption",
+            profile
+        );
+
+        let expected_rtcp = encrypt
+            .encrypt_rtcp(&DECRYPTED_RTCP_PACKET)
+            .expect("Error encrypting rtcp payload");
+        let mut rtcp = BytesMut::from(&DECRYPTED_RTCP_PACKET[..]);
+        encrypt_in_place
+            .encrypt_rtcp_in_place(&mut rtcp)
+            .expect("Error encrypting rtcp payload in place");
+        assert_eq!(
+            rtcp, expected_rtcp,
+            "{:?} in place RTCP encryption",
+            profile
+        );
+
+        decrypt_in_place
+            .decrypt_rtcp_in_place(&mut rtcp)
+            .expect("Error decrypting rtcp payload in place");
+        assert_eq!(
+            rtcp, *DECRYPTED_RTCP_PACKET,
+            "{:?} in place RTCP decryption",
+            profile
+        );
+    }
+}
@@ -0,0 +1,453 @@
+use super::*;
+use crate::key_derivation::*;
+use util::marshal::*;
+
+use aes_gcm::{
+    aead::{generic_array::GenericArray, Aead, NewAead, Payload},
+    Aes128Gcm, Nonce,
+};
+use bytes::{Bytes, BytesMut};
+use rtp::header::{Extension, Header};
+
+const MASTER_KEY: [u8; 16] = [
+    0xE1, 0xF9, 0x7A, 0x0D, 0x3E, 0x01, 0x8B, 0xE0, 0xD6, 0x4F, 0xA3, 0x2C, 0x06, 0xDE, 0x41, 0x39,
+];
+const MASTER_SALT: [u8; 14] = [
+    0x0E, 0xC6, 0x75, 0xAD, 0x49, 0x8A, 0xFE, 0xEB, 0xB6, 0x96, 0x0B, 0x3A, 0xAB, 0xE6,
+];
+const GCM_MASTER_SALT: [u8; 12] = [
+    0x51, 0x75, 0x69, 0x64, 0x20, 0x70, 0x72, 0x6f, 0x20, 0x71, 0x75, 0x6f,
+];
+
+const PROFILES: [ProtectionProfile; 2] = [
+    ProtectionProfile::Aes128CmHmacSha1_80,
+    ProtectionProfile::AeadAes128Gcm,
+];
+
+fn build_context(profile: ProtectionProfile, cryptex: bool) -> Result<Context> {
+    let master_salt: &[u8] = match profile {
+        ProtectionProfile::Aes128CmHmacSha1_80 => &MASTER_SALT,
+        ProtectionProfile::AeadAes128Gcm => &GCM_MASTER_SALT,
+    };
+    let mut context = Context::new(&MASTER_KEY, master_salt, profile, None, None)?;
+    context.set_cryptex(cryptex);
+    Ok(context)
+}
+
+fn build_header(csrc: Vec<u32>, extension_profile: u16, extensions: Vec<Extension>) -> Header {
+    Header {
+        version: 2,
+        payload_type: 0x0f,
+        sequence_number: 0x1234,
+        timestamp: 0xdecafbad,
+        ssrc: 0xcafebabe,
+        csrc,
+        extension: !extensions.is_empty(),
+        extension_profile: if extensions.is_empty() {
+            0
+        } else {
+            extension_profile
+        },
+        extensions,
+        ..Default::default()
+    }
+}
+
+fn one_byte_extensions() -> Vec<Extension> {
+    vec![
+        // audio level, voice activity and -42 dBov
+        Extension {
+            id: 1,
+            payload: Bytes::from_static(&[0xaa]),
+        },
+        Extension {
+            id: 2,
+            payload: Bytes::from_static(&[0x01, 0x02, 0x03]),
+        },
+    ]
+}
+
+fn two_byte_extensions() -> Vec<Extension> {
+    vec![
+        Extension {
+            id: 1,
+            payload: Bytes::from_static(&[0xaa]),
+        },
+        Extension {
+            id: 17,
+            payload: Bytes::from_static(&[0x01, 0x02, 0x03, 0x04, 0x05]),
+        },
+    ]
+}
+
+const PAYLOAD: [u8; 8] = [0xab; 8];
+
+fn marshal(header: &Header) -> Result<Bytes> {
+    let mut packet = BytesMut::new();
+    packet.extend(header.marshal()?);
+    packet.extend_from_slice(&PAYLOAD);
+    Ok(packet.freeze())
+}
+
+fn csrc_end(header: &Header) -> usize {
+    12 + 4 * header.csrc.len()
+}
+
+#[test]
+fn test_cryptex_encrypts_csrcs_and_extensions() -> Result<()> {
+    for profile in PROFILES {
+        for (extension_profile, extensions, cryptex_profile) in [
+            (0xBEDE, one_byte_extensions(), [0xC0, 0xDE]),
+            (0x1000, two_byte_extensions(), [0xC2, 0xDE]),
+        ] {
+            for csrc in [vec![], vec![0x0001e240, 0x0000b26e, 0x00000000]] {
+                let header = build_header(csrc, extension_profile, extensions.clone());
+                let plaintext = marshal(&header)?;
+                let ext_start = csrc_end(&header);
+                let ext_end = header.marshal_size();
+
+                let mut encrypt = build_context(profile, true)?;
+                let mut decrypt = build_context(profile, true)?;
+                let encrypted = encrypt.encrypt_rtp(&plaintext)?;
+
+                assert_eq!(
+                    &encrypted[..12],
+                    &plaintext[..12],
+                    "{:?}: fixed header",
+                    profile
+                );
+                assert_eq!(
+                    &encrypted[ext_start..ext_start + 2],
+                    &cryptex_profile,
+                    "{:?}: cryptex profile",
+                    profile
+                );
+                assert_eq!(
+                    &encrypted[ext_start + 2..ext_start + 4],
+                    &plaintext[ext_start + 2..ext_start + 4],
+                    "{:?}: extension length",
+                    profile
+                );
+                if !header.csrc.is_empty() {
+                    assert_ne!(
+                        &encrypted[12..ext_start],
+                        &plaintext[12..ext_start],
+                        "{:?}: csrcs should be encrypted",
+                        profile
+                    );
+                }
+                assert_ne!(
+                    &encrypted[ext_start + 4..ext_end],
+                    &plaintext[ext_start + 4..ext_end],
+                    "{:?}: extension data should be encrypted",
+                    profile
+                );
+
+                let decrypted = decrypt.decrypt_rtp(&encrypted)?;
+                assert_eq!(decrypted, plaintext, "{:?}: should round trip", profile);
+                let decrypted_header = Header::unmarshal(&mut decrypted.clone())?;
+                assert_eq!(decrypted_header.extensions, extensions);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_cryptex_csrcs_without_extension() -> Result<()> {
+    for profile in PROFILES {
+        let header = build_header(vec![0x0001e240, 0x0000b26e], 0, vec![]);
+        let plaintext = marshal(&header)?;
+
+        let mut encrypt = build_context(profile, true)?;
+        let mut decrypt = build_context(profile, true)?;
+        let encrypted = encrypt.encrypt_rtp(&plaintext)?;
+
+        // An empty extension is added
+        assert_eq!(
+            encrypted[0],
+            plaintext[0] | 0x10,
+            "{:?}: extension bit",
+            profile
+        );
+        assert_eq!(
+            &encrypted[1..12],
+            &plaintext[1..12],
+            "{:?}: fixed header",
+            profile
+        );
+        assert_ne!(
+            &encrypted[12..20],
+            &plaintext[12..20],
+            "{:?}: csrcs",
+            profile
+        );
+        assert_eq!(
+            &encrypted[20..24],
+            &[0xC0, 0xDE, 0, 0],
+            "{:?}: extension",
+            profile
+        );
+
+        let decrypted = decrypt.decrypt_rtp(&encrypted)?;
+        let decrypted_header = Header::unmarshal(&mut decrypted.clone())?;
+        assert!(
+            decrypted_header.extension,
+            "{:?}: extension is kept",
+            profile
+        );
+        assert_eq!(decrypted_header.extension_profile, 0xBEDE);
+        assert!(decrypted_header.extensions.is_empty());
+        assert_eq!(decrypted_header.csrc, header.csrc);
+        assert_eq!(&decrypted[24..], &PAYLOAD, "{:?}: payload", profile);
+    }
+
+    Ok(())
+}
+
+// The keystream of AES-CM runs over the CSRCs, the extension data and the payload as if they were
+// contiguous. A packet with only a payload of zeroes gives the keystream away.
+#[test]
+fn test_cryptex_aes_cm_keystream() -> Result<()> {
+    let profile = ProtectionProfile::Aes128CmHmacSha1_80;
+    let header = build_header(vec![0x0001e240, 0x0000b26e], 0xBEDE, one_byte_extensions());
+    let plaintext = marshal(&header)?;
+    let ext_start = csrc_end(&header);
+    let ext_end = header.marshal_size();
+
+    let encrypted = build_context(profile, true)?.encrypt_rtp(&plaintext)?;
+
+    let zeroes_header = build_header(vec![], 0, vec![]);
+    let mut zeroes = BytesMut::new();
+    zeroes.extend(zeroes_header.marshal()?);
+    zeroes.extend_from_slice(&vec![0u8; plaintext.len()]);
+    let keystream = build_context(profile, false)?.encrypt_rtp(&zeroes)?;
+    let keystream = &keystream[12..];
+
+    let mut expected = plaintext[12..ext_start].to_vec();
+    expected.extend_from_slice(&plaintext[ext_start + 4..]);
+    for (b, k) in expected.iter_mut().zip(keystream) {
+        *b ^= k;
+    }
+
+    let csrc_len = ext_start - 12;
+    assert_eq!(&encrypted[12..ext_start], &expected[..csrc_len], "csrcs");
+    assert_eq!(
+        &encrypted[ext_start + 4..ext_end],
+        &expected[csrc_len..csrc_len + ext_end - ext_start - 4],
+        "extension data"
+    );
+    assert_eq!(
+        &encrypted[ext_end..plaintext.len()],
+        &expected[csrc_len + ext_end - ext_start - 4..],
+        "payload"
+    );
+
+    Ok(())
+}
+
+// With AEAD, the fixed header and the extension header are the additional data, and the CSRCs,
+// the extension data and the payload are the plaintext.
+#[test]
+fn test_cryptex_aead_layout() -> Result<()> {
+    let profile = ProtectionProfile::AeadAes128Gcm;
+    let header = build_header(vec![0x0001e240, 0x0000b26e], 0xBEDE, one_byte_extensions());
+    let plaintext = marshal(&header)?;
+    let ext_start = csrc_end(&header);
+
+    let encrypted = build_context(profile, true)?.encrypt_rtp(&plaintext)?;
+
+    let session_key = aes_cm_key_derivation(
+        LABEL_SRTP_ENCRYPTION,
+        &MASTER_KEY,
+        &GCM_MASTER_SALT,
+        0,
+        MASTER_KEY.len(),
+    )?;
+    let session_salt = aes_cm_key_derivation(
+        LABEL_SRTP_SALT,
+        &MASTER_KEY,
+        &GCM_MASTER_SALT,
+        0,
+        MASTER_KEY.len(),
+    )?;
+    let mut iv = vec![0u8; 12];
+    iv[2..6].copy_from_slice(&header.ssrc.to_be_bytes());
+    iv[10..12].copy_from_slice(&header.sequence_number.to_be_bytes());
+    for (v, s) in iv.iter_mut().zip(&session_salt) {
+        *v ^= s;
+    }
+
+    let mut aad = plaintext[..12].to_vec();
+    aad.extend_from_slice(&[0xC0, 0xDE]);
+    aad.extend_from_slice(&plaintext[ext_start + 2..ext_start + 4]);
+    let mut msg = plaintext[12..ext_start].to_vec();
+    msg.extend_from_slice(&plaintext[ext_start + 4..]);
+
+    let cipher = Aes128Gcm::new(GenericArray::from_slice(&session_key));
+    let expected = cipher.encrypt(
+        Nonce::from_slice(&iv),
+        Payload {
+            msg: &msg,
+            aad: &aad,
+        },
+    )?;
+
+    let csrc_len = ext_start - 12;
+    assert_eq!(encrypted.len(), 16 + expected.len(), "length");
+    assert_eq!(&encrypted[12..ext_start], &expected[..csrc_len], "csrcs");
+    assert_eq!(
+        &encrypted[ext_start + 4..],
+        &expected[csrc_len..],
+        "extension data, payload and tag"
+    );
+    assert_eq!(&encrypted[ext_start..ext_start + 2], &[0xC0, 0xDE]);
+
+    Ok(())
+}
+
+#[test]
+fn test_cryptex_authenticates_csrcs_and_extensions() -> Result<()> {
+    for profile in PROFILES {
+        let header = build_header(vec![0x0001e240], 0xBEDE, one_byte_extensions());
+        let plaintext = marshal(&header)?;
+        let ext_start = csrc_end(&header);
+
+        for offset in [12, ext_start + 2, ext_start + 4] {
+            let encrypted = build_context(profile, true)?.encrypt_rtp(&plaintext)?;
+            let mut tampered = encrypted.to_vec();
+            tampered[offset] ^= 0x01;
+
+            let mut decrypt = build_context(profile, true)?;
+            assert!(
+                decrypt.decrypt_rtp(&tampered).is_err(),
+                "{:?}: byte {} should be authenticated",
+                profile,
+                offset
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_cryptex_non_cryptex_peer() -> Result<()> {
+    for profile in PROFILES {
+        // Packets of a peer not using Cryptex are decrypted as usual
+        let header = build_header(vec![0x0001e240], 0xBEDE, one_byte_extensions());
+        let plaintext = marshal(&header)?;
+        let encrypted = build_context(profile, false)?.encrypt_rtp(&plaintext)?;
+        let decrypted = build_context(profile, true)?.decrypt_rtp(&encrypted)?;
+        assert_eq!(decrypted, plaintext, "{:?}: should decrypt", profile);
+
+        // Cryptex doesn't change packets without CSRCs and extensions
+        let header = build_header(vec![], 0, vec![]);
+        let plaintext = marshal(&header)?;
+        let with_cryptex = build_context(profile, true)?.encrypt_rtp(&plaintext)?;
+        let without_cryptex = build_context(profile, false)?.encrypt_rtp(&plaintext)?;
+        assert_eq!(with_cryptex, without_cryptex, "{:?}: should match", profile);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_cryptex_unsupported_extension_profile() -> Result<()> {
+    let header = build_header(
+        vec![],
+        0x1234,
+        vec![Extension {
+            id: 0,
+            payload: Bytes::from_static(&[1, 2, 3, 4]),
+        }],
+    );
+    let plaintext = marshal(&header)?;
+
+    let result =
+        build_context(ProtectionProfile::Aes128CmHmacSha1_80, true)?.encrypt_rtp(&plaintext);
+    assert_eq!(
+        result,
+        Err(Error::ErrCryptexUnsupportedExtensionProfile(0x1234))
+    );
+
+    Ok(())
+}
+
+fn from_hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+// The test vectors of RFC 9335 Appendix A: (plaintext, SRTP packet)
+const RFC9335_AES_CM_VECTORS: [(&str, &str); 4] = [
+    // A.1.1, one-byte header extension
+    (
+        "900f1235decafbadcafebabebede000151000200abababababababababababababababab",
+        "900f1235decafbadcafebabec0de0001eb92365251c3e036f8de27e9c27ee3e0b4651d9fbc4218a70244522f34a5",
+    ),
+    // A.1.2, two-byte header extension
+    (
+        "900f1236decafbadcafebabe1000000105020002abababababababababababababababab",
+        "900f1236decafbadcafebabec2de00014ed9cc4e6a712b3096c5ca77339d4204ce0d77396cab69585fbce38194a5",
+    ),
+    // A.1.3, one-byte header extension and CSRCs
+    (
+        "920f1238decafbadcafebabe0001e2400000b26ebede000151000200abababababababababababababababab",
+        "920f1238decafbadcafebabe8bb6e12b5cff16ddc0de000192838c8c09e58393e1de3a9a74734d6745671338c3acf11da2df8423bee0",
+    ),
+    // A.1.4, two-byte header extension and CSRCs
+    (
+        "920f1239decafbadcafebabe0001e2400000b26e1000000105020002abababababababababababababababab",
+        "920f1239decafbadcafebabef70e513eb90b9b25c2de0001bbed4848faa644665f3d7f34125914e9f4d0ae923c6f479b95a0f7b53133",
+    ),
+];
+
+#[test]
+fn test_cryptex_rfc9335_aes_cm_vectors() -> Result<()> {
+    for (i, (plaintext, srtp)) in RFC9335_AES_CM_VECTORS.iter().enumerate() {
+        let (plaintext, srtp) = (from_hex(plaintext), from_hex(srtp));
+
+        let mut encrypt = build_context(ProtectionProfile::Aes128CmHmacSha1_80, true)?;
+        let encrypted = encrypt.encrypt_rtp(&plaintext)?;
+        assert_eq!(&encrypted[..], &srtp[..], "vector {}: encrypt", i);
+
+        let mut decrypt = build_context(ProtectionProfile::Aes128CmHmacSha1_80, true)?;
+        let decrypted = decrypt.decrypt_rtp(&srtp)?;
+        assert_eq!(&decrypted[..], &plaintext[..], "vector {}: decrypt", i);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_cryptex_rfc9335_aead_vector() -> Result<()> {
+    let master_key = from_hex("000102030405060708090a0b0c0d0e0f");
+    let master_salt = from_hex("a0a1a2a3a4a5a6a7a8a9aaab");
+    let plaintext =
+        from_hex("900f1235decafbadcafebabebede000151000200abababababababababababababababab");
+    let srtp = from_hex(
+        "900f1235decafbadcafebabec0de000139972dc9572c4d99e8fc355de743fb2e94f9d8ff54e72f4193bbc5c74ffab0fa9fa0fbeb",
+    );
+
+    let new_context = || -> Result<Context> {
+        let mut context = Context::new(
+            &master_key,
+            &master_salt,
+            ProtectionProfile::AeadAes128Gcm,
+            None,
+            None,
+        )?;
+        context.set_cryptex(true);
+        Ok(context)
+    };
+    let encrypted = new_context()?.encrypt_rtp(&plaintext)?;
+    assert_eq!(&encrypted[..], &srtp[..], "encrypt");
+    let decrypted = new_context()?.decrypt_rtp(&srtp)?;
+    assert_eq!(&decrypted[..], &plaintext[..], "decrypt");
+
+    Ok(())
+}
@@ -1,6 +1,8 @@
 #[cfg(test)]
 mod context_test;
 #[cfg(test)]
+mod cryptex_test;
+#[cfg(test)]
 mod srtcp_test;
 #[cfg(test)]
 mod srtp_test;
@@ -11,21 +13,18 @@ use crate::{
     option::*, protection_profile::*,
 };
 
+use rtp::seqnum::ExtendedSeqnum;
 use std::collections::HashMap;
 use util::replay_detector::*;
 
 pub mod srtcp;
 pub mod srtp;
 
-const MAX_ROC_DISORDER: u16 = 100;
-
 /// Encrypt/Decrypt state for a single SRTP SSRC
 #[derive(Default)]
 pub(crate) struct SrtpSsrcState {
     ssrc: u32,
-    rollover_counter: u32,
-    rollover_has_processed: bool,
-    last_sequence_number: u16,
+    seqnum: ExtendedSeqnum,
     replay_detector: Option<Box<dyn ReplayDetector + Send + 'static>>,
 }
 
@@ -38,67 +37,55 @@ pub(crate) struct SrtcpSsrcState {
 }
 
 impl SrtpSsrcState {
-    pub fn next_rollover_count(&self, sequence_number: u16) -> u32 {
-        let mut roc = self.rollover_counter;
-
-        if !self.rollover_has_processed {
-        } else if sequence_number == 0 {
-            // We exactly hit the rollover count
-
-            // Only update rolloverCounter if lastSequenceNumber is greater then MAX_ROCDISORDER
-            // otherwise we already incremented for disorder
-            if self.last_sequence_number > MAX_ROC_DISORDER {
-                roc += 1;
-            }
-        } else if self.last_sequence_number < MAX_ROC_DISORDER
-            && sequence_number > (MAX_SEQUENCE_NUMBER - MAX_ROC_DISORDER)
-        {
-            // Our last sequence number incremented because we crossed 0, but then our current number was within MAX_ROCDISORDER of the max
-            // So we fell behind, drop to account for jitter
-            roc -= 1;
-        } else if sequence_number < MAX_ROC_DISORDER
-            && self.last_sequence_number > (MAX_SEQUENCE_NUMBER - MAX_ROC_DISORDER)
-        {
-            // our current is within a MAX_ROCDISORDER of 0
-            // and our last sequence number was a high sequence number, increment to account for jitter
-            roc += 1;
-        }
+    /// next_index returns the index of the packet with sequence_number, see RFC 3711 Section
+    /// 3.3.1, without recording it.
+    pub fn next_index(&self, sequence_number: u16) -> u64 {
+        // A sequence number that would precede the first one across the wraparound gets the
+        // rollover counter 0.
+        self.seqnum
+            .extend(sequence_number)
+            .unwrap_or(sequence_number as u64)
+    }
 
-        roc
+    pub fn next_rollover_count(&self, sequence_number: u16) -> u32 {
+        (self.next_index(sequence_number) >> 16) as u32
     }
 
-    /// https://tools.ietf.org/html/rfc3550#appendix-A.1
+    /// update_rollover_count records the sequence number of a packet that was processed.
     pub fn update_rollover_count(&mut self, sequence_number: u16) {
-        if !self.rollover_has_processed {
-            self.rollover_has_processed = true;
-        } else if sequence_number == 0 {
-            // We exactly hit the rollover count
-
-            // Only update rolloverCounter if lastSequenceNumber is greater then MAX_ROCDISORDER
-            // otherwise we already incremented for disorder
-            if self.last_sequence_number > MAX_ROC_DISORDER {
-                self.rollover_counter += 1;
-            }
-        } else if self.last_sequence_number < MAX_ROC_DISORDER
-            && sequence_number > (MAX_SEQUENCE_NUMBER - MAX_ROC_DISORDER)
-        {
-            // Our last sequence number incremented because we crossed 0, but then our current number was within MAX_ROCDISORDER of the max
-            // So we fell behind, drop to account for jitter
-            self.rollover_counter -= 1;
-        } else if sequence_number < MAX_ROC_DISORDER
-            && self.last_sequence_number > (MAX_SEQUENCE_NUMBER - MAX_ROC_DISORDER)
-        {
-            // our current is within a MAX_ROCDISORDER of 0
-            // and our last sequence number was a high sequence number, increment to account for jitter
-            self.rollover_counter += 1;
-        }
-        self.last_sequence_number = sequence_number;
+        self.seqnum.update(sequence_number);
+    }
+
+    pub fn rollover_counter(&self) -> u32 {
+        self.seqnum.roll_over_count() as u32
     }
 }
 
 /// Context represents a SRTP cryptographic context
 /// Context can only be used for one-way operations
 /// it must either used ONLY for encryption or ONLY for decryption
+///
+/// Context is synchronous and does not need an async runtime, so it can be
+/// driven directly by transports that have their own packet pump instead of
+/// going through [`Session`](crate::session::Session).
+///
+/// ```
+/// use webrtc_srtp::{Context, ProtectionProfile};
+///
+/// let profile = ProtectionProfile::Aes128CmHmacSha1_80;
+/// let master_key = vec![0u8; profile.key_len()];
+/// let master_salt = vec![0u8; profile.salt_len()];
+///
+/// let mut encrypt = Context::new(&master_key, &master_salt, profile, None, None).unwrap();
+/// let mut decrypt = Context::new(&master_key, &master_salt, profile, None, None).unwrap();
+///
+/// let rtp = [
+///     0x80, 0x0f, 0x12, 0x34, 0xde, 0xca, 0xfb, 0xad, 0xca, 0xfe, 0xba, 0xbe, 0xab, 0xab, 0xab,
+///     0xab,
+/// ];
+/// let srtp = encrypt.encrypt_rtp(&rtp).unwrap();
+/// assert_eq!(&decrypt.decrypt_rtp(&srtp).unwrap()[..], &rtp[..]);
+/// ```
 pub struct Context {
     cipher: Box<dyn Cipher + Send>,
 
@@ -107,6 +94,8 @@ pub struct Context {
 
     new_srtp_replay_detector: ContextOption,
     new_srtcp_replay_detector: ContextOption,
+
+    cryptex: bool,
 }
 
 impl Context {
@@ -155,6 +144,7 @@ impl Context {
             srtcp_ssrc_states: HashMap::new(),
             new_srtp_replay_detector: srtp_ctx_opt,
             new_srtcp_replay_detector: srtcp_ctx_opt,
+            cryptex: false,
         })
     }
 
@@ -180,26 +170,64 @@ impl Context {
     }
 
     /// roc returns SRTP rollover counter value of specified SSRC.
-    fn get_roc(&self, ssrc: u32) -> Option<u32> {
-        self.srtp_ssrc_states.get(&ssrc).map(|s| s.rollover_counter)
+    pub fn get_roc(&self, ssrc: u32) -> Option<u32> {
+        self.srtp_ssrc_states
+            .get(&ssrc)
+            .map(|s| s.rollover_counter())
     }
 
     /// set_roc sets SRTP rollover counter value of specified SSRC.
-    fn set_roc(&mut self, ssrc: u32, roc: u32) {
+    pub fn set_roc(&mut self, ssrc: u32, roc: u32) {
         if let Some(s) = self.get_srtp_ssrc_state(ssrc) {
-            s.rollover_counter = roc;
+            s.seqnum.set_roll_over_count(roc as u64);
         }
     }
 
     /// index returns SRTCP index value of specified SSRC.
-    fn get_index(&self, ssrc: u32) -> Option<usize> {
+    pub fn get_index(&self, ssrc: u32) -> Option<usize> {
         self.srtcp_ssrc_states.get(&ssrc).map(|s| s.srtcp_index)
     }
 
     /// set_index sets SRTCP index value of specified SSRC.
-    fn set_index(&mut self, ssrc: u32, index: usize) {
+    pub fn set_index(&mut self, ssrc: u32, index: usize) {
         if let Some(s) = self.get_srtcp_ssrc_state(ssrc) {
             s.srtcp_index = index;
         }
     }
+
+    /// set_cryptex enables or disables Cryptex, see RFC 9335. When enabled, the CSRCs and the
+    /// header extension of encrypted RTP packets are encrypted along with the payload, and
+    /// received packets with a Cryptex extension profile are decrypted likewise. Received packets
+    /// without one are still decrypted as usual. Cryptex must only be used when negotiated with
+    /// the remote, e.g. with the `a=cryptex` SDP attribute.
+    pub fn set_cryptex(&mut self, enabled: bool) {
+        self.cryptex = enabled;
+    }
+
+    /// cryptex returns true if Cryptex is enabled.
+    pub fn cryptex(&self) -> bool {
+        self.cryptex
+    }
+
+    /// srtp_ssrcs returns the SSRCs that currently have SRTP state.
+    pub fn srtp_ssrcs(&self) -> Vec<u32> {
+        self.srtp_ssrc_states.keys().copied().collect()
+    }
+
+    /// srtcp_ssrcs returns the SSRCs that currently have SRTCP state.
+    pub fn srtcp_ssrcs(&self) -> Vec<u32> {
+        self.srtcp_ssrc_states.keys().copied().collect()
+    }
+
+    /// remove_srtp_ssrc drops the rollover counter and replay state of specified SSRC,
+    /// returns true if the SSRC was known. State is created again on the next packet.
+    pub fn remove_srtp_ssrc(&mut self, ssrc: u32) -> bool {
+        self.srtp_ssrc_states.remove(&ssrc).is_some()
+    }
+
+    /// remove_srtcp_ssrc drops the SRTCP index and replay state of specified SSRC,
+    /// returns true if the SSRC was known. State is created again on the next packet.
+    pub fn remove_srtcp_ssrc(&mut self, ssrc: u32) -> bool {
+        self.srtcp_ssrc_states.remove(&ssrc).is_some()
+    }
 }
@@ -2,63 +2,96 @@ use super::*;
 use crate::error::Result;
 use util::marshal::*;
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 
 impl Context {
-    /// DecryptRTCP decrypts a RTCP packet with an encrypted payload
-    pub fn decrypt_rtcp(&mut self, encrypted: &[u8]) -> Result<Bytes> {
+    /// srtcp_decrypt_index checks the packet against the replay detector of its SSRC and
+    /// returns its SRTCP index and SSRC.
+    fn srtcp_decrypt_index(&mut self, encrypted: &[u8]) -> Result<(usize, u32)> {
         let mut buf = encrypted;
         rtcp::header::Header::unmarshal(&mut buf)?;
 
         let index = self.cipher.get_rtcp_index(encrypted);
         let ssrc = u32::from_be_bytes([encrypted[4], encrypted[5], encrypted[6], encrypted[7]]);
 
-        {
-            if let Some(state) = self.get_srtcp_ssrc_state(ssrc) {
-                if let Some(replay_detector) = &mut state.replay_detector {
-                    if !replay_detector.check(index as u64) {
-                        return Err(Error::SrtcpSsrcDuplicated(ssrc, index));
-                    }
+        if let Some(state) = self.get_srtcp_ssrc_state(ssrc) {
+            if let Some(replay_detector) = &mut state.replay_detector {
+                if !replay_detector.check(index as u64) {
+                    return Err(Error::SrtcpSsrcDuplicated(ssrc, index));
                 }
-            } else {
-                return Err(Error::SsrcMissingFromSrtcp(ssrc));
             }
+        } else {
+            return Err(Error::SsrcMissingFromSrtcp(ssrc));
         }
 
-        let dst = self.cipher.decrypt_rtcp(encrypted, index, ssrc)?;
+        Ok((index, ssrc))
+    }
 
-        {
-            if let Some(state) = self.get_srtcp_ssrc_state(ssrc) {
-                if let Some(replay_detector) = &mut state.replay_detector {
-                    replay_detector.accept();
-                }
+    /// srtcp_decrypted records a packet that was decrypted and authenticated.
+    fn srtcp_decrypted(&mut self, ssrc: u32) {
+        if let Some(state) = self.get_srtcp_ssrc_state(ssrc) {
+            if let Some(replay_detector) = &mut state.replay_detector {
+                replay_detector.accept();
             }
         }
-
-        Ok(dst)
     }
 
-    /// EncryptRTCP marshals and encrypts an RTCP packet, writing to the dst buffer provided.
-    /// If the dst buffer does not have the capacity to hold `len(plaintext) + 14` bytes, a new one will be allocated and returned.
-    pub fn encrypt_rtcp(&mut self, decrypted: &[u8]) -> Result<Bytes> {
+    /// srtcp_encrypt_index returns the next SRTCP index of the packet and its SSRC.
+    fn srtcp_encrypt_index(&mut self, decrypted: &[u8]) -> Result<(usize, u32)> {
         let mut buf = decrypted;
         rtcp::header::Header::unmarshal(&mut buf)?;
 
         let ssrc = u32::from_be_bytes([decrypted[4], decrypted[5], decrypted[6], decrypted[7]]);
 
-        let index;
-        {
-            if let Some(state) = self.get_srtcp_ssrc_state(ssrc) {
-                state.srtcp_index += 1;
-                if state.srtcp_index > MAX_SRTCP_INDEX {
-                    state.srtcp_index = 0;
-                }
-                index = state.srtcp_index;
-            } else {
-                return Err(Error::SsrcMissingFromSrtcp(ssrc));
+        if let Some(state) = self.get_srtcp_ssrc_state(ssrc) {
+            state.srtcp_index += 1;
+            if state.srtcp_index > MAX_SRTCP_INDEX {
+                state.srtcp_index = 0;
             }
+            Ok((state.srtcp_index, ssrc))
+        } else {
+            Err(Error::SsrcMissingFromSrtcp(ssrc))
         }
+    }
+
+    /// DecryptRTCP decrypts a RTCP packet with an encrypted payload
+    pub fn decrypt_rtcp(&mut self, encrypted: &[u8]) -> Result<Bytes> {
+        let (index, ssrc) = self.srtcp_decrypt_index(encrypted)?;
+
+        let dst = self.cipher.decrypt_rtcp(encrypted, index, ssrc)?;
+
+        self.srtcp_decrypted(ssrc);
+
+        Ok(dst)
+    }
+
+    /// decrypt_rtcp_in_place decrypts the SRTCP packet in `packet` and leaves the RTCP packet
+    /// in it, the SRTCP index and the auth tag are removed. The packet is left untouched if it
+    /// fails to authenticate.
+    pub fn decrypt_rtcp_in_place(&mut self, packet: &mut BytesMut) -> Result<()> {
+        let (index, ssrc) = self.srtcp_decrypt_index(packet)?;
+
+        self.cipher.decrypt_rtcp_in_place(packet, index, ssrc)?;
+
+        self.srtcp_decrypted(ssrc);
+
+        Ok(())
+    }
+
+    /// EncryptRTCP marshals and encrypts an RTCP packet, writing to the dst buffer provided.
+    /// If the dst buffer does not have the capacity to hold `len(plaintext) + 14` bytes, a new one will be allocated and returned.
+    pub fn encrypt_rtcp(&mut self, decrypted: &[u8]) -> Result<Bytes> {
+        let (index, ssrc) = self.srtcp_encrypt_index(decrypted)?;
 
         self.cipher.encrypt_rtcp(decrypted, index, ssrc)
     }
+
+    /// encrypt_rtcp_in_place encrypts the RTCP packet in `packet` and leaves the SRTCP packet
+    /// in it, the SRTCP index and the auth tag are appended. Reserve room for them to avoid
+    /// growing the buffer.
+    pub fn encrypt_rtcp_in_place(&mut self, packet: &mut BytesMut) -> Result<()> {
+        let (index, ssrc) = self.srtcp_encrypt_index(packet)?;
+
+        self.cipher.encrypt_rtcp_in_place(packet, index, ssrc)
+    }
 }
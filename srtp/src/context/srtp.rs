@@ -1,42 +1,69 @@
 use super::*;
+use crate::cryptex;
 use crate::error::Result;
 use util::marshal::*;
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 
 impl Context {
+    /// srtp_decrypt_roc checks the packet against the replay detector of its SSRC and returns
+    /// the rollover counter to decrypt it with.
+    fn srtp_decrypt_roc(&mut self, header: &rtp::header::Header) -> Result<u32> {
+        if let Some(state) = self.get_srtp_ssrc_state(header.ssrc) {
+            let index = state.next_index(header.sequence_number);
+            if let Some(replay_detector) = &mut state.replay_detector {
+                if !replay_detector.check(index) {
+                    return Err(Error::SrtpSsrcDuplicated(
+                        header.ssrc,
+                        header.sequence_number,
+                    ));
+                }
+            }
+
+            Ok((index >> 16) as u32)
+        } else {
+            Err(Error::SsrcMissingFromSrtp(header.ssrc))
+        }
+    }
+
+    /// srtp_decrypted records a packet that was decrypted and authenticated.
+    fn srtp_decrypted(&mut self, header: &rtp::header::Header) {
+        if let Some(state) = self.get_srtp_ssrc_state(header.ssrc) {
+            if let Some(replay_detector) = &mut state.replay_detector {
+                replay_detector.accept();
+            }
+            state.update_rollover_count(header.sequence_number);
+        }
+    }
+
+    fn srtp_encrypt_roc(&mut self, header: &rtp::header::Header) -> Result<u32> {
+        if let Some(state) = self.get_srtp_ssrc_state(header.ssrc) {
+            Ok(state.next_rollover_count(header.sequence_number))
+        } else {
+            Err(Error::SsrcMissingFromSrtp(header.ssrc))
+        }
+    }
+
+    fn srtp_encrypted(&mut self, header: &rtp::header::Header) {
+        if let Some(state) = self.get_srtp_ssrc_state(header.ssrc) {
+            state.update_rollover_count(header.sequence_number);
+        }
+    }
+
     pub fn decrypt_rtp_with_header(
         &mut self,
         encrypted: &[u8],
         header: &rtp::header::Header,
     ) -> Result<Bytes> {
-        let roc;
-        {
-            if let Some(state) = self.get_srtp_ssrc_state(header.ssrc) {
-                if let Some(replay_detector) = &mut state.replay_detector {
-                    if !replay_detector.check(header.sequence_number as u64) {
-                        return Err(Error::SrtpSsrcDuplicated(
-                            header.ssrc,
-                            header.sequence_number,
-                        ));
-                    }
-                }
+        let roc = self.srtp_decrypt_roc(header)?;
 
-                roc = state.next_rollover_count(header.sequence_number);
-            } else {
-                return Err(Error::SsrcMissingFromSrtp(header.ssrc));
-            }
-        }
+        let dst = if self.cryptex && cryptex::is_cryptex(header) {
+            self.cipher.decrypt_rtp_cryptex(encrypted, header, roc)?
+        } else {
+            self.cipher.decrypt_rtp(encrypted, header, roc)?
+        };
 
-        let dst = self.cipher.decrypt_rtp(encrypted, header, roc)?;
-        {
-            if let Some(state) = self.get_srtp_ssrc_state(header.ssrc) {
-                if let Some(replay_detector) = &mut state.replay_detector {
-                    replay_detector.accept();
-                }
-                state.update_rollover_count(header.sequence_number);
-            }
-        }
+        self.srtp_decrypted(header);
 
         Ok(dst)
     }
@@ -48,29 +75,46 @@ impl Context {
         self.decrypt_rtp_with_header(encrypted, &header)
     }
 
+    /// decrypt_rtp_in_place decrypts the SRTP packet in `packet` and leaves the RTP packet in
+    /// it, the payload is decrypted where it is and the auth tag is removed. The packet is
+    /// left untouched if it fails to authenticate. Cryptex packets go through an intermediate
+    /// buffer.
+    pub fn decrypt_rtp_in_place(&mut self, packet: &mut BytesMut) -> Result<()> {
+        let header = {
+            let mut buf = &packet[..];
+            rtp::header::Header::unmarshal(&mut buf)?
+        };
+        let roc = self.srtp_decrypt_roc(&header)?;
+
+        if self.cryptex && cryptex::is_cryptex(&header) {
+            let decrypted = self.cipher.decrypt_rtp_cryptex(packet, &header, roc)?;
+            packet.clear();
+            packet.extend_from_slice(&decrypted);
+        } else {
+            self.cipher.decrypt_rtp_in_place(packet, &header, roc)?;
+        }
+
+        self.srtp_decrypted(&header);
+
+        Ok(())
+    }
+
     pub fn encrypt_rtp_with_header(
         &mut self,
         plaintext: &[u8],
         header: &rtp::header::Header,
     ) -> Result<Bytes> {
-        let roc;
-        {
-            if let Some(state) = self.get_srtp_ssrc_state(header.ssrc) {
-                roc = state.next_rollover_count(header.sequence_number);
-            } else {
-                return Err(Error::SsrcMissingFromSrtp(header.ssrc));
-            }
-        }
+        let roc = self.srtp_encrypt_roc(header)?;
 
-        let dst = self
-            .cipher
-            .encrypt_rtp(&plaintext[header.marshal_size()..], header, roc)?;
+        let payload = &plaintext[header.marshal_size()..];
+        let dst = if self.cryptex && cryptex::needs_cryptex(header) {
+            let packet = cryptex::marshal_cryptex(header, payload)?;
+            self.cipher.encrypt_rtp_cryptex(&packet, header, roc)?
+        } else {
+            self.cipher.encrypt_rtp(payload, header, roc)?
+        };
 
-        {
-            if let Some(state) = self.get_srtp_ssrc_state(header.ssrc) {
-                state.update_rollover_count(header.sequence_number);
-            }
-        }
+        self.srtp_encrypted(header);
 
         Ok(dst)
     }
@@ -82,4 +126,29 @@ impl Context {
         let header = rtp::header::Header::unmarshal(&mut buf)?;
         self.encrypt_rtp_with_header(plaintext, &header)
     }
+
+    /// encrypt_rtp_in_place encrypts the RTP packet in `packet` and leaves the SRTP packet in
+    /// it, the payload is encrypted where it is and the auth tag is appended. Reserve room
+    /// for the auth tag to avoid growing the buffer. Cryptex packets go through an
+    /// intermediate buffer.
+    pub fn encrypt_rtp_in_place(&mut self, packet: &mut BytesMut) -> Result<()> {
+        let header = {
+            let mut buf = &packet[..];
+            rtp::header::Header::unmarshal(&mut buf)?
+        };
+        let roc = self.srtp_encrypt_roc(&header)?;
+
+        if self.cryptex && cryptex::needs_cryptex(&header) {
+            let plaintext = cryptex::marshal_cryptex(&header, &packet[header.marshal_size()..])?;
+            let encrypted = self.cipher.encrypt_rtp_cryptex(&plaintext, &header, roc)?;
+            packet.clear();
+            packet.extend_from_slice(&encrypted);
+        } else {
+            self.cipher.encrypt_rtp_in_place(packet, &header, roc)?;
+        }
+
+        self.srtp_encrypted(&header);
+
+        Ok(())
+    }
 }
@@ -167,6 +167,51 @@ fn test_rtp_lifecyle() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_rtp_replay_protection_across_rollover() -> Result<()> {
+    let mut encrypt_context = build_test_context()?;
+    let mut decrypt_context = build_test_context()?;
+    decrypt_context.new_srtp_replay_detector = srtp_replay_protection(64);
+
+    // 65500..=65535 with rollover counter 0, then 0..40 with rollover counter 1.
+    let mut encrypted = vec![];
+    for i in 0..76u16 {
+        let decrypted_pkt = rtp::packet::Packet {
+            header: rtp::header::Header {
+                sequence_number: 65500u16.wrapping_add(i),
+                ..Default::default()
+            },
+            payload: RTP_TEST_CASE_DECRYPTED.clone(),
+        };
+        let decrypted_raw = decrypted_pkt.marshal()?;
+        encrypted.push((
+            decrypted_raw.clone(),
+            encrypt_context.encrypt_rtp(&decrypted_raw)?,
+        ));
+    }
+    assert_eq!(encrypt_context.get_roc(0), Some(1));
+
+    // Reorder the packets around the wraparound.
+    encrypted.swap(34, 37);
+    for (decrypted_raw, encrypted_raw) in &encrypted {
+        assert_eq!(&decrypt_context.decrypt_rtp(encrypted_raw)?, decrypted_raw);
+    }
+    assert_eq!(decrypt_context.get_roc(0), Some(1));
+
+    // Replays from both sides of the wraparound are detected.
+    for (_, encrypted_raw) in &encrypted[30..45] {
+        assert!(
+            matches!(
+                decrypt_context.decrypt_rtp(encrypted_raw),
+                Err(Error::SrtpSsrcDuplicated(_, _))
+            ),
+            "replay wasn't detected"
+        );
+    }
+
+    Ok(())
+}
+
 //TODO: BenchmarkEncryptRTP
 //TODO: BenchmarkEncryptRTPInPlace
 //TODO: BenchmarkDecryptRTP
@@ -0,0 +1,97 @@
+//! Cryptex, encrypted RTP header extensions and CSRCs.
+//!
+//! https://www.rfc-editor.org/rfc/rfc9335
+//!
+//! The "defined by profile" field of the header extension tells a Cryptex packet apart, 0xC0DE
+//! instead of 0xBEDE for one-byte extensions and 0xC2DE instead of 0x1000 for two-byte
+//! extensions. The CSRCs, the extension data and the payload are encrypted as if they were
+//! contiguous, so the 4 byte extension header is moved in front of the CSRCs while encrypting:
+//!
+//! > | fixed header | CSRCs | ext header | ext data | payload |      wire
+//! > | fixed header | ext header | CSRCs | ext data | payload |      encryption
+//! >  |-------------------------|        AEAD additional data
+//! >                              |---------------------------|  encrypted
+
+use crate::error::{Error, Result};
+use util::marshal::*;
+
+use bytes::{BufMut, BytesMut};
+use rtp::header::{
+    Header, CSRC_LENGTH, CSRC_OFFSET, EXTENSION_PROFILE_ONE_BYTE, EXTENSION_PROFILE_TWO_BYTE,
+};
+
+pub(crate) const CRYPTEX_PROFILE_ONE_BYTE: u16 = 0xC0DE;
+pub(crate) const CRYPTEX_PROFILE_TWO_BYTE: u16 = 0xC2DE;
+
+const EXTENSION_BIT: u8 = 0x10;
+const EXTENSION_HEADER_LENGTH: usize = 4;
+
+/// is_cryptex returns true if the header extension of header has a Cryptex profile.
+pub(crate) fn is_cryptex(header: &Header) -> bool {
+    header.extension
+        && (header.extension_profile == CRYPTEX_PROFILE_ONE_BYTE
+            || header.extension_profile == CRYPTEX_PROFILE_TWO_BYTE)
+}
+
+/// needs_cryptex returns true if header has something Cryptex encrypts besides the payload.
+pub(crate) fn needs_cryptex(header: &Header) -> bool {
+    header.extension || !header.csrc.is_empty()
+}
+
+/// marshal_cryptex returns the plaintext packet of header and payload as sent with Cryptex: the
+/// extension profile is replaced by its Cryptex counterpart, and an empty extension is added to
+/// packets with CSRCs but no extension.
+pub(crate) fn marshal_cryptex(header: &Header, payload: &[u8]) -> Result<BytesMut> {
+    let header_len = header.marshal_size();
+    let mut packet = BytesMut::with_capacity(header_len + EXTENSION_HEADER_LENGTH + payload.len());
+    packet.extend(header.marshal()?);
+
+    let profile_offset = CSRC_OFFSET + header.csrc.len() * CSRC_LENGTH;
+    if header.extension {
+        let profile = match header.extension_profile {
+            EXTENSION_PROFILE_ONE_BYTE => CRYPTEX_PROFILE_ONE_BYTE,
+            EXTENSION_PROFILE_TWO_BYTE => CRYPTEX_PROFILE_TWO_BYTE,
+            profile => return Err(Error::ErrCryptexUnsupportedExtensionProfile(profile)),
+        };
+        packet[profile_offset..profile_offset + 2].copy_from_slice(&profile.to_be_bytes());
+        packet.extend_from_slice(payload);
+    } else {
+        packet[0] |= EXTENSION_BIT;
+        let tail = packet.split_off(profile_offset);
+        packet.put_u16(CRYPTEX_PROFILE_ONE_BYTE);
+        packet.put_u16(0);
+        packet.extend(tail);
+        packet.extend_from_slice(payload);
+    }
+
+    Ok(packet)
+}
+
+/// restore_profile replaces the Cryptex profile of the decrypted packet by the original one.
+pub(crate) fn restore_profile(packet: &mut [u8]) {
+    let profile_offset = csrc_end(packet);
+    let profile = match u16::from_be_bytes([packet[profile_offset], packet[profile_offset + 1]]) {
+        CRYPTEX_PROFILE_ONE_BYTE => EXTENSION_PROFILE_ONE_BYTE,
+        CRYPTEX_PROFILE_TWO_BYTE => EXTENSION_PROFILE_TWO_BYTE,
+        _ => return,
+    };
+    packet[profile_offset..profile_offset + 2].copy_from_slice(&profile.to_be_bytes());
+}
+
+/// to_encryption_layout moves the extension header of a packet with a header extension in
+/// front of its CSRCs, and returns the offset of the encrypted part.
+pub(crate) fn to_encryption_layout(packet: &mut [u8]) -> usize {
+    let end = csrc_end(packet);
+    packet[CSRC_OFFSET..end + EXTENSION_HEADER_LENGTH].rotate_right(EXTENSION_HEADER_LENGTH);
+    CSRC_OFFSET + EXTENSION_HEADER_LENGTH
+}
+
+/// from_encryption_layout moves the extension header back behind the CSRCs.
+pub(crate) fn from_encryption_layout(packet: &mut [u8]) {
+    let end = csrc_end(packet);
+    packet[CSRC_OFFSET..end + EXTENSION_HEADER_LENGTH].rotate_left(EXTENSION_HEADER_LENGTH);
+}
+
+fn csrc_end(packet: &[u8]) -> usize {
+    CSRC_OFFSET + (packet[0] & rtp::header::CC_MASK) as usize * CSRC_LENGTH
+}
@@ -1,5 +1,6 @@
 use std::io;
 use thiserror::Error;
+#[cfg(feature = "tokio")]
 use tokio::sync::mpsc::error::SendError as MpscSendError;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -76,6 +77,8 @@ pub enum Error {
     InvalidRtpStream,
     #[error("this stream is not a RTCPStream")]
     InvalidRtcpStream,
+    #[error("header extension profile {0:#06x} can't be encrypted with Cryptex")]
+    ErrCryptexUnsupportedExtensionProfile(u16),
 
     #[error("{0}")]
     Io(#[source] IoError),
@@ -112,6 +115,7 @@ impl From<io::Error> for Error {
 }
 
 // Because Tokio SendError is parameterized, we sadly lose the backtrace.
+#[cfg(feature = "tokio")]
 impl<T> From<MpscSendError<T>> for Error {
     fn from(e: MpscSendError<T>) -> Self {
         Error::MpscSend(e.to_string())
@@ -1,14 +1,27 @@
+//! A pure Rust implementation of SRTP.
+//!
+//! [`Session`](session::Session) reads and writes SRTP/SRTCP over a `Conn` on a tokio runtime.
+//! Transports that already have their own packet pump can use [`Context`] directly,
+//! it protects and unprotects packets synchronously without a runtime, in place or into a new
+//! buffer. `Session` and `Stream` are behind the default `tokio` feature, the crate builds
+//! without tokio when it is disabled.
+
 #![warn(rust_2018_idioms)]
 #![allow(dead_code)]
 
 mod cipher;
 pub mod config;
 pub mod context;
+mod cryptex;
 mod error;
 mod key_derivation;
 pub mod option;
 pub mod protection_profile;
+#[cfg(feature = "tokio")]
 pub mod session;
+#[cfg(feature = "tokio")]
 pub mod stream;
 
+pub use context::Context;
 pub use error::Error;
+pub use protection_profile::ProtectionProfile;
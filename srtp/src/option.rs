@@ -1,20 +1,45 @@
+use rtp::seqnum::SeqnumWindow;
 use util::replay_detector::*;
 
 pub type ContextOption = Box<dyn (Fn() -> Box<dyn ReplayDetector + Send + 'static>) + Send + Sync>;
 
-pub(crate) const MAX_SEQUENCE_NUMBER: u16 = 65535;
 pub(crate) const MAX_SRTCP_INDEX: usize = 0x7FFFFFFF;
 
-/// srtp_replay_protection sets SRTP replay protection window size.
+/// srtp_replay_protection sets SRTP replay protection window size, rounded up to a multiple of
+/// 64. SRTP replay detectors check the index of the packets, their sequence number extended
+/// with the rollover counter.
 pub fn srtp_replay_protection(window_size: usize) -> ContextOption {
     Box::new(move || -> Box<dyn ReplayDetector + Send> {
-        Box::new(WrappedSlidingWindowDetector::new(
-            window_size,
-            MAX_SEQUENCE_NUMBER as u64,
-        ))
+        Box::new(SrtpReplayDetector {
+            window: SeqnumWindow::new(window_size),
+            index: None,
+        })
     })
 }
 
+/// SrtpReplayDetector detects replayed SRTP packets by their index.
+struct SrtpReplayDetector {
+    window: SeqnumWindow,
+    index: Option<u64>,
+}
+
+impl ReplayDetector for SrtpReplayDetector {
+    fn check(&mut self, index: u64) -> bool {
+        self.index = None;
+        if self.window.is_too_old(index) || self.window.contains(index) {
+            return false;
+        }
+        self.index = Some(index);
+        true
+    }
+
+    fn accept(&mut self) {
+        if let Some(index) = self.index.take() {
+            self.window.insert(index);
+        }
+    }
+}
+
 /// Sets SRTCP replay protection window size.
 pub fn srtcp_replay_protection(window_size: usize) -> ContextOption {
     Box::new(move || -> Box<dyn ReplayDetector + Send> {
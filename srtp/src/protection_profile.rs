@@ -13,20 +13,23 @@ impl Default for ProtectionProfile {
 }
 
 impl ProtectionProfile {
-    pub(crate) fn key_len(&self) -> usize {
+    /// key_len returns the length of the master key.
+    pub fn key_len(&self) -> usize {
         match *self {
             ProtectionProfile::Aes128CmHmacSha1_80 | ProtectionProfile::AeadAes128Gcm => 16,
         }
     }
 
-    pub(crate) fn salt_len(&self) -> usize {
+    /// salt_len returns the length of the master salt.
+    pub fn salt_len(&self) -> usize {
         match *self {
             ProtectionProfile::Aes128CmHmacSha1_80 => 14,
             ProtectionProfile::AeadAes128Gcm => 12,
         }
     }
 
-    pub(crate) fn auth_tag_len(&self) -> usize {
+    /// auth_tag_len returns the length of the authentication tag appended to each packet.
+    pub fn auth_tag_len(&self) -> usize {
         match *self {
             ProtectionProfile::Aes128CmHmacSha1_80 => 10, //CIPHER_AES_CM_HMAC_SHA1AUTH_TAG_LEN,
             ProtectionProfile::AeadAes128Gcm => 16,       //CIPHER_AEAD_AES_GCM_AUTH_TAG_LEN,
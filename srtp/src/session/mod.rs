@@ -10,7 +10,7 @@ use crate::{
     option::*,
     stream::*,
 };
-use util::{conn::Conn, marshal::*};
+use util::{conn::Conn, marshal::*, sync::TaskGroup};
 
 use bytes::Bytes;
 use std::collections::HashSet;
@@ -20,9 +20,12 @@ use std::{
     sync::Arc,
 };
 use tokio::sync::{mpsc, Mutex};
+use tokio::time::Duration;
 
 const DEFAULT_SESSION_SRTP_REPLAY_PROTECTION_WINDOW: usize = 64;
 const DEFAULT_SESSION_SRTCP_REPLAY_PROTECTION_WINDOW: usize = 64;
+/// max time closing a session waits for its read loop to stop
+const CLOSE_READ_LOOP_TIMEOUT: Duration = Duration::from_secs(1);
 
 /// Session implements io.ReadWriteCloser and provides a bi-directional SRTP session
 /// SRTP itself does not have a design like this, but it is common in most applications
@@ -36,6 +39,7 @@ pub struct Session {
     close_session_tx: mpsc::Sender<()>,
     pub(crate) udp_tx: Arc<dyn Conn + Send + Sync>,
     is_rtp: bool,
+    tasks: TaskGroup,
 }
 
 impl Session {
@@ -44,7 +48,7 @@ impl Session {
         config: Config,
         is_rtp: bool,
     ) -> Result<Self> {
-        let local_context = Context::new(
+        let mut local_context = Context::new(
             &config.keys.local_master_key,
             &config.keys.local_master_salt,
             config.profile,
@@ -72,6 +76,9 @@ impl Session {
             },
         )?;
 
+        local_context.set_cryptex(config.cryptex);
+        remote_context.set_cryptex(config.cryptex);
+
         let streams_map = Arc::new(Mutex::new(HashMap::new()));
         let (mut new_stream_tx, new_stream_rx) = mpsc::channel(8);
         let (close_stream_tx, mut close_stream_rx) = mpsc::channel(8);
@@ -81,7 +88,8 @@ impl Session {
         let cloned_streams_map = Arc::clone(&streams_map);
         let cloned_close_stream_tx = close_stream_tx.clone();
 
-        tokio::spawn(async move {
+        let tasks = TaskGroup::new();
+        tasks.spawn(async move {
             let mut buf = vec![0u8; 8192];
 
             loop {
@@ -118,6 +126,7 @@ impl Session {
             close_session_tx,
             udp_tx,
             is_rtp,
+            tasks,
         })
     }
 
@@ -225,6 +234,7 @@ impl Session {
 
     pub async fn close(&self) -> Result<()> {
         self.close_session_tx.send(()).await?;
+        self.tasks.close(CLOSE_READ_LOOP_TIMEOUT).await;
 
         Ok(())
     }
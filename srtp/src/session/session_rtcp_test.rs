@@ -36,6 +36,8 @@ async fn build_session_srtcp_pair() -> Result<(Session, Session)> {
 
         local_rtcp_options: None,
         remote_rtcp_options: None,
+
+        cryptex: false,
     };
 
     let cb = Config {
@@ -62,6 +64,8 @@ async fn build_session_srtcp_pair() -> Result<(Session, Session)> {
 
         local_rtcp_options: None,
         remote_rtcp_options: None,
+
+        cryptex: false,
     };
 
     let sa = Session::new(Arc::new(ua), ca, false).await?;
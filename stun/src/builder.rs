@@ -0,0 +1,179 @@
+#[cfg(test)]
+mod builder_test;
+
+use crate::agent::TransactionId;
+use crate::attributes::*;
+use crate::error::*;
+use crate::error_code::ErrorCode;
+use crate::fingerprint::FINGERPRINT;
+use crate::integrity::MessageIntegrity;
+use crate::message::*;
+use crate::textattrs::TextAttribute;
+use crate::xoraddr::XorMappedAddress;
+
+use std::net::SocketAddr;
+
+/// MessageBuilder builds a [`Message`] attribute by attribute, without collecting
+/// `Box<dyn Setter>`s first.
+///
+/// Attributes are written in the order they are added. MESSAGE-INTEGRITY may only be
+/// followed by FINGERPRINT and nothing may follow FINGERPRINT, violations are returned by
+/// [`MessageBuilder::build`] as `Error::ErrAttributeAfterIntegrity`,
+/// `Error::ErrFingerprintBeforeIntegrity` or `Error::ErrAttributeAfterFingerprint`.
+///
+/// ```
+/// use stun::integrity::MessageIntegrity;
+/// use stun::message::Message;
+///
+/// let msg = Message::binding_request()
+///     .with_username("remote:local")
+///     .with_priority(0x6e7f_1eff)
+///     .with_integrity(MessageIntegrity::new_short_term_integrity("pwd".to_owned()))
+///     .with_fingerprint()
+///     .build()
+///     .unwrap();
+/// ```
+pub struct MessageBuilder {
+    msg: Message,
+    err: Option<Error>,
+}
+
+impl MessageBuilder {
+    /// new starts a message of type `typ` with a random transaction id.
+    pub fn new(typ: MessageType) -> Self {
+        let mut msg = Message::new();
+        msg.write_header();
+        msg.set_type(typ);
+        msg.transaction_id = TransactionId::new();
+        msg.write_transaction_id();
+
+        MessageBuilder { msg, err: None }
+    }
+
+    /// with_transaction_id replaces the random transaction id, e.g. with the one of the request
+    /// a response is built for.
+    pub fn with_transaction_id(self, transaction_id: TransactionId) -> Self {
+        self.with(transaction_id)
+    }
+
+    /// with_username adds a USERNAME attribute.
+    pub fn with_username(self, username: impl Into<String>) -> Self {
+        self.with(TextAttribute::new(ATTR_USERNAME, username.into()))
+    }
+
+    /// with_software adds a SOFTWARE attribute.
+    pub fn with_software(self, software: impl Into<String>) -> Self {
+        self.with(TextAttribute::new(ATTR_SOFTWARE, software.into()))
+    }
+
+    /// with_realm adds a REALM attribute.
+    pub fn with_realm(self, realm: impl Into<String>) -> Self {
+        self.with(TextAttribute::new(ATTR_REALM, realm.into()))
+    }
+
+    /// with_nonce adds a NONCE attribute.
+    pub fn with_nonce(self, nonce: impl Into<String>) -> Self {
+        self.with(TextAttribute::new(ATTR_NONCE, nonce.into()))
+    }
+
+    /// with_priority adds the PRIORITY attribute of ICE connectivity checks.
+    pub fn with_priority(self, priority: u32) -> Self {
+        self.with(RawAttribute {
+            typ: ATTR_PRIORITY,
+            length: 4,
+            value: priority.to_be_bytes().to_vec(),
+        })
+    }
+
+    /// with_xor_mapped_address adds a XOR-MAPPED-ADDRESS attribute.
+    pub fn with_xor_mapped_address(self, addr: SocketAddr) -> Self {
+        self.with(XorMappedAddress {
+            ip: addr.ip(),
+            port: addr.port(),
+        })
+    }
+
+    /// with_error_code adds an ERROR-CODE attribute with the default reason of `code`.
+    pub fn with_error_code(self, code: ErrorCode) -> Self {
+        self.with(code)
+    }
+
+    /// with_integrity adds a MESSAGE-INTEGRITY attribute, only FINGERPRINT may be added after it.
+    pub fn with_integrity(self, integrity: MessageIntegrity) -> Self {
+        self.with(integrity)
+    }
+
+    /// with_fingerprint adds a FINGERPRINT attribute, it must be the last one.
+    pub fn with_fingerprint(self) -> Self {
+        self.with(FINGERPRINT)
+    }
+
+    /// with adds any other attribute through its Setter.
+    pub fn with<S: Setter>(mut self, setter: S) -> Self {
+        if self.err.is_some() {
+            return self;
+        }
+
+        let had_integrity = self.msg.contains(ATTR_MESSAGE_INTEGRITY);
+        let had_fingerprint = self.msg.contains(ATTR_FINGERPRINT);
+        let attributes = self.msg.attributes.0.len();
+
+        if let Err(err) = setter.add_to(&mut self.msg) {
+            self.err = Some(err);
+            return self;
+        }
+
+        let added = &self.msg.attributes.0[attributes..];
+        self.err = if added.is_empty() {
+            None
+        } else if had_fingerprint && added.iter().any(|a| a.typ == ATTR_MESSAGE_INTEGRITY) {
+            Some(Error::ErrFingerprintBeforeIntegrity)
+        } else if had_fingerprint {
+            Some(Error::ErrAttributeAfterFingerprint)
+        } else if had_integrity && added.iter().any(|a| a.typ != ATTR_FINGERPRINT) {
+            Some(Error::ErrAttributeAfterIntegrity)
+        } else {
+            None
+        };
+
+        self
+    }
+
+    /// build returns the message, or the first error met while adding attributes.
+    pub fn build(self) -> Result<Message> {
+        match self.err {
+            Some(err) => Err(err),
+            None => Ok(self.msg),
+        }
+    }
+}
+
+impl Message {
+    /// builder starts a [`MessageBuilder`] for a message of type `typ`.
+    pub fn builder(typ: MessageType) -> MessageBuilder {
+        MessageBuilder::new(typ)
+    }
+
+    /// binding_request starts a [`MessageBuilder`] for a Binding request.
+    pub fn binding_request() -> MessageBuilder {
+        MessageBuilder::new(BINDING_REQUEST)
+    }
+
+    /// binding_success starts a [`MessageBuilder`] for a Binding success response.
+    pub fn binding_success() -> MessageBuilder {
+        MessageBuilder::new(BINDING_SUCCESS)
+    }
+
+    /// binding_error starts a [`MessageBuilder`] for a Binding error response.
+    pub fn binding_error() -> MessageBuilder {
+        MessageBuilder::new(BINDING_ERROR)
+    }
+
+    /// get_attr decodes the attribute read by the Getter `T`, e.g.
+    /// `msg.get_attr::<XorMappedAddress>()`.
+    pub fn get_attr<T: Getter + Default>(&self) -> Result<T> {
+        let mut attr = T::default();
+        attr.get_from(self)?;
+        Ok(attr)
+    }
+}
@@ -0,0 +1,131 @@
+use super::*;
+use crate::error_code::ErrorCodeAttribute;
+use crate::fingerprint::FINGERPRINT;
+use crate::textattrs::Username;
+
+#[test]
+fn test_builder_binding_request() -> Result<()> {
+    let integrity = MessageIntegrity::new_short_term_integrity("pwd".to_owned());
+    let msg = Message::binding_request()
+        .with_software("software")
+        .with_username("remote:local")
+        .with_priority(0x6e7f_1eff)
+        .with_integrity(integrity.clone())
+        .with_fingerprint()
+        .build()?;
+
+    assert_eq!(msg.typ, BINDING_REQUEST);
+    let types: Vec<AttrType> = msg.attributes.0.iter().map(|a| a.typ).collect();
+    assert_eq!(
+        types,
+        vec![
+            ATTR_SOFTWARE,
+            ATTR_USERNAME,
+            ATTR_PRIORITY,
+            ATTR_MESSAGE_INTEGRITY,
+            ATTR_FINGERPRINT
+        ]
+    );
+    assert_eq!(msg.get(ATTR_PRIORITY)?, vec![0x6e, 0x7f, 0x1e, 0xff]);
+
+    let mut decoded = Message::new();
+    decoded.unmarshal_binary(&msg.raw)?;
+    assert_eq!(decoded.transaction_id, msg.transaction_id);
+    integrity.check(&mut decoded)?;
+    FINGERPRINT.check(&decoded)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_builder_matches_build() -> Result<()> {
+    let transaction_id = TransactionId::new();
+    let integrity = MessageIntegrity::new_short_term_integrity("pwd".to_owned());
+
+    let mut expected = Message::new();
+    expected.build(&[
+        Box::new(BINDING_SUCCESS),
+        Box::new(transaction_id),
+        Box::new(Username::new(ATTR_USERNAME, "user".to_owned())),
+        Box::new(integrity.clone()),
+        Box::new(FINGERPRINT),
+    ])?;
+
+    let msg = Message::binding_success()
+        .with_transaction_id(transaction_id)
+        .with_username("user")
+        .with_integrity(integrity)
+        .with_fingerprint()
+        .build()?;
+
+    assert_eq!(msg.raw, expected.raw);
+
+    Ok(())
+}
+
+#[test]
+fn test_builder_get_attr() -> Result<()> {
+    let addr: SocketAddr = "213.141.156.236:21254".parse().unwrap();
+    let msg = Message::binding_success()
+        .with_xor_mapped_address(addr)
+        .build()?;
+
+    let xor_addr = msg.get_attr::<XorMappedAddress>()?;
+    assert_eq!(xor_addr.ip, addr.ip());
+    assert_eq!(xor_addr.port, addr.port());
+
+    let result = msg.get_attr::<ErrorCodeAttribute>();
+    assert_eq!(result.err(), Some(Error::ErrAttributeNotFound));
+
+    Ok(())
+}
+
+#[test]
+fn test_builder_ordering_violations() {
+    let integrity = || MessageIntegrity::new_short_term_integrity("pwd".to_owned());
+
+    let result = Message::binding_request()
+        .with_integrity(integrity())
+        .with_username("remote:local")
+        .build();
+    assert_eq!(result.err(), Some(Error::ErrAttributeAfterIntegrity));
+
+    let result = Message::binding_request()
+        .with_fingerprint()
+        .with_priority(1)
+        .build();
+    assert_eq!(result.err(), Some(Error::ErrAttributeAfterFingerprint));
+
+    let result = Message::binding_request()
+        .with_fingerprint()
+        .with_integrity(integrity())
+        .build();
+    assert_eq!(result.err(), Some(Error::ErrFingerprintBeforeIntegrity));
+
+    let result = Message::binding_request()
+        .with_fingerprint()
+        .with_fingerprint()
+        .build();
+    assert_eq!(result.err(), Some(Error::ErrAttributeAfterFingerprint));
+
+    // The first violation is reported, later attributes are ignored.
+    let result = Message::binding_request()
+        .with_integrity(integrity())
+        .with_software("software")
+        .with_fingerprint()
+        .with_priority(1)
+        .build();
+    assert_eq!(result.err(), Some(Error::ErrAttributeAfterIntegrity));
+}
+
+#[test]
+fn test_builder_transaction_id_after_fingerprint() -> Result<()> {
+    let transaction_id = TransactionId::new();
+    let msg = Message::binding_request()
+        .with_fingerprint()
+        .with_transaction_id(transaction_id)
+        .build()?;
+    assert_eq!(msg.transaction_id, transaction_id);
+
+    Ok(())
+}
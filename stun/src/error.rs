@@ -39,6 +39,10 @@ pub enum Error {
     ErrFingerprintMismatch,
     #[error("FINGERPRINT before MESSAGE-INTEGRITY attribute")]
     ErrFingerprintBeforeIntegrity,
+    #[error("attribute after MESSAGE-INTEGRITY attribute")]
+    ErrAttributeAfterIntegrity,
+    #[error("attribute after FINGERPRINT attribute")]
+    ErrAttributeAfterFingerprint,
     #[error("bad UNKNOWN-ATTRIBUTES size")]
     ErrBadUnknownAttrsSize,
     #[error("invalid length of IP value")]
@@ -4,6 +4,7 @@ use crate::relay::relay_static::*;
 use crate::server::{config::*, *};
 
 use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::net::UdpSocket;
 use tokio::time::Duration;
 
@@ -187,3 +188,310 @@ async fn test_client_nonce_expiration() -> Result<()> {
 
     Ok(())
 }
+
+// RequestCounter wraps a UdpSocket and counts the requests of a method
+// written to it.
+struct RequestCounter {
+    conn: UdpSocket,
+    method: Method,
+    count: AtomicUsize,
+}
+
+impl RequestCounter {
+    fn count(&self, buf: &[u8]) -> bool {
+        if !is_message(buf) {
+            return false;
+        }
+        let mut msg = Message::new();
+        msg.raw = buf.to_vec();
+        msg.decode().is_ok() && msg.typ == MessageType::new(self.method, CLASS_REQUEST)
+    }
+}
+
+#[async_trait]
+impl Conn for RequestCounter {
+    async fn connect(&self, addr: SocketAddr) -> std::result::Result<(), util::Error> {
+        self.conn.connect(addr).await?;
+        Ok(())
+    }
+
+    async fn recv(&self, buf: &mut [u8]) -> std::result::Result<usize, util::Error> {
+        Ok(self.conn.recv(buf).await?)
+    }
+
+    async fn recv_from(
+        &self,
+        buf: &mut [u8],
+    ) -> std::result::Result<(usize, SocketAddr), util::Error> {
+        Ok(self.conn.recv_from(buf).await?)
+    }
+
+    async fn send(&self, buf: &[u8]) -> std::result::Result<usize, util::Error> {
+        Ok(self.conn.send(buf).await?)
+    }
+
+    async fn send_to(
+        &self,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> std::result::Result<usize, util::Error> {
+        if self.count(buf) {
+            self.count.fetch_add(1, Ordering::SeqCst);
+        }
+        Ok(self.conn.send_to(buf, target).await?)
+    }
+
+    fn local_addr(&self) -> std::result::Result<SocketAddr, util::Error> {
+        Ok(self.conn.local_addr()?)
+    }
+
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    async fn close(&self) -> std::result::Result<(), util::Error> {
+        Ok(())
+    }
+}
+
+async fn create_test_server_and_counting_client(
+    method: Method,
+) -> Result<(Server, Client, Arc<RequestCounter>)> {
+    let conn = Arc::new(UdpSocket::bind("0.0.0.0:0").await?);
+    let server_port = conn.local_addr()?.port();
+
+    let server = Server::new(ServerConfig {
+        conn_configs: vec![ConnConfig {
+            conn,
+            relay_addr_generator: Box::new(RelayAddressGeneratorStatic {
+                relay_address: IpAddr::from_str("127.0.0.1")?,
+                address: "0.0.0.0".to_owned(),
+                net: Arc::new(Net::new(None)),
+            }),
+        }],
+        realm: "webrtc.rs".to_owned(),
+        auth_handler: Arc::new(TestAuthHandler {}),
+        channel_bind_timeout: Duration::from_secs(0),
+    })
+    .await?;
+
+    let counter = Arc::new(RequestCounter {
+        conn: UdpSocket::bind("0.0.0.0:0").await?,
+        method,
+        count: AtomicUsize::new(0),
+    });
+
+    let client = Client::new(ClientConfig {
+        stun_serv_addr: format!("127.0.0.1:{}", server_port),
+        turn_serv_addr: format!("127.0.0.1:{}", server_port),
+        username: "foo".to_owned(),
+        password: "pass".to_owned(),
+        realm: String::new(),
+        software: String::new(),
+        rto_in_ms: 0,
+        conn: Arc::clone(&counter) as Arc<dyn Conn + Send + Sync>,
+        vnet: None,
+    })
+    .await?;
+
+    client.listen().await?;
+
+    Ok((server, client, counter))
+}
+
+#[tokio::test]
+async fn test_client_create_permissions_batched() -> Result<()> {
+    let (server, client, counter) =
+        create_test_server_and_counting_client(METHOD_CREATE_PERMISSION).await?;
+
+    let allocation = client.allocate().await?;
+
+    let peers: Vec<SocketAddr> = (0..5)
+        .map(|i| SocketAddr::from_str(&format!("127.0.0.1:{}", 8080 + i)))
+        .collect::<std::result::Result<_, _>>()?;
+
+    let requests = allocation.create_permissions(&peers).await;
+    assert_eq!(requests.len(), peers.len());
+    for (request, peer) in requests.iter().zip(peers.iter()) {
+        assert_eq!(request.addr(), *peer);
+        request.ready().await?;
+    }
+    assert_eq!(
+        counter.count.load(Ordering::SeqCst),
+        1,
+        "expected a single CreatePermission request for all peers"
+    );
+
+    // Installed permissions are reused without another request.
+    let requests = allocation.create_permissions(&peers[..2]).await;
+    for request in &requests {
+        request.ready().await?;
+    }
+    allocation.send_to(&[0x00], peers[0]).await?;
+    assert_eq!(counter.count.load(Ordering::SeqCst), 1);
+
+    client.close().await?;
+    server.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_client_create_permissions_partial_failure() -> Result<()> {
+    let (server, client, _counter) =
+        create_test_server_and_counting_client(METHOD_CREATE_PERMISSION).await?;
+
+    let allocation = client.allocate().await?;
+
+    let mut peers: Vec<SocketAddr> = (0..4)
+        .map(|i| SocketAddr::from_str(&format!("127.0.0.1:{}", 8080 + i)))
+        .collect::<std::result::Result<_, _>>()?;
+    // The relayed address is IPv4, so the server refuses an IPv6 peer.
+    let refused = SocketAddr::from_str("[::1]:8080")?;
+    peers.insert(2, refused);
+
+    let requests = allocation.create_permissions(&peers).await;
+    for request in &requests {
+        let result = request.ready().await;
+        if request.addr() == refused {
+            assert!(
+                matches!(result, Err(Error::ErrCreatePermissionFailed(_))),
+                "expected the IPv6 peer to be refused, got {:?}",
+                result
+            );
+        } else {
+            assert!(result.is_ok(), "expected {} to be ready", request.addr());
+        }
+    }
+
+    client.close().await?;
+    server.close().await?;
+
+    Ok(())
+}
+
+// wait_for_allocations_deleted waits for the server to handle the Refresh that closed
+// the allocations, which isn't waited for by the client.
+async fn wait_for_allocations_deleted(server: &Server) -> Result<()> {
+    for _ in 0..100 {
+        if server.get_allocations_info(None).await?.is_empty() {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    Err(Error::Other("allocations not deleted".to_owned()))
+}
+
+#[tokio::test]
+async fn test_client_allocate_reuses_cached_nonce() -> Result<()> {
+    let (server, client, counter) = create_test_server_and_counting_client(METHOD_ALLOCATE).await?;
+
+    // The first allocation is challenged for the realm and nonce.
+    let allocation = client.allocate().await?;
+    let first = counter.count.swap(0, Ordering::SeqCst);
+    assert_eq!(
+        first, 2,
+        "expected a challenged and an authenticated Allocate"
+    );
+    allocation.close().await?;
+    drop(allocation);
+    wait_for_allocations_deleted(&server).await?;
+
+    // The second one reuses them right away.
+    let allocation = client.allocate().await?;
+    let second = counter.count.swap(0, Ordering::SeqCst);
+    assert!(
+        second < first,
+        "expected fewer Allocate requests, got {} after {}",
+        second,
+        first
+    );
+    assert_eq!(second, 1);
+    assert_eq!(
+        client.credential_cache_stats().await,
+        CredentialCacheStats { hits: 1, misses: 1 }
+    );
+
+    allocation.close().await?;
+    client.close().await?;
+    server.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_client_allocate_retries_stale_nonce() -> Result<()> {
+    let (server, client, counter) = create_test_server_and_counting_client(METHOD_ALLOCATE).await?;
+
+    let allocation = client.allocate().await?;
+    allocation.close().await?;
+    drop(allocation);
+    wait_for_allocations_deleted(&server).await?;
+
+    // Forget the cached nonce on the server, the next Allocate gets a 438 for it.
+    {
+        let mut nonces = server.nonces.lock().await;
+        nonces.clear();
+    }
+    counter.count.store(0, Ordering::SeqCst);
+
+    let allocation = client.allocate().await?;
+    assert_eq!(
+        counter.count.load(Ordering::SeqCst),
+        2,
+        "expected the stale nonce to be retried with the fresh one"
+    );
+
+    // The fresh nonce has been cached.
+    allocation.close().await?;
+    drop(allocation);
+    wait_for_allocations_deleted(&server).await?;
+    counter.count.store(0, Ordering::SeqCst);
+    let allocation = client.allocate().await?;
+    assert_eq!(counter.count.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        client.credential_cache_stats().await,
+        CredentialCacheStats { hits: 2, misses: 1 }
+    );
+
+    allocation.close().await?;
+    client.close().await?;
+    server.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_client_permission_stale_nonce_updates_cache() -> Result<()> {
+    let (server, client, counter) = create_test_server_and_counting_client(METHOD_ALLOCATE).await?;
+
+    let allocation = client.allocate().await?;
+
+    // Forget the nonce on the server, the CreatePermission gets a 438 and is retried.
+    {
+        let mut nonces = server.nonces.lock().await;
+        nonces.clear();
+    }
+    allocation
+        .send_to(&[0x00], SocketAddr::from_str("127.0.0.1:8080")?)
+        .await?;
+
+    allocation.close().await?;
+    drop(allocation);
+    wait_for_allocations_deleted(&server).await?;
+
+    // The next allocation starts out with the fresh nonce.
+    counter.count.store(0, Ordering::SeqCst);
+    let allocation = client.allocate().await?;
+    assert_eq!(
+        counter.count.load(Ordering::SeqCst),
+        1,
+        "expected the nonce refreshed by the CreatePermission to be cached"
+    );
+
+    allocation.close().await?;
+    client.close().await?;
+    server.close().await?;
+
+    Ok(())
+}
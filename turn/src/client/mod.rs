@@ -15,6 +15,7 @@ use binding::*;
 use relay_conn::*;
 use transaction::*;
 
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -34,6 +35,8 @@ use async_trait::async_trait;
 const DEFAULT_RTO_IN_MS: u16 = 200;
 const MAX_DATA_BUFFER_SIZE: usize = u16::MAX as usize; // message size limit for Chromium
 const MAX_READ_QUEUE_SIZE: usize = 1024;
+// an Allocate is sent at most this often: challenged, stale nonce, authenticated
+const MAX_ALLOCATE_ATTEMPTS: usize = 3;
 
 //              interval [msec]
 // 0: 0 ms      +500
@@ -58,6 +61,21 @@ pub struct ClientConfig {
     pub vnet: Option<Arc<Net>>,
 }
 
+/// CredentialCacheStats counts how often an allocation could reuse the realm and nonce
+/// cached for its TURN server, saving the unauthenticated Allocate round trip.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CredentialCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+// Credentials are the realm and nonce a TURN server challenged the client with
+#[derive(Clone)]
+struct Credentials {
+    realm: Realm,
+    nonce: Nonce,
+}
+
 struct ClientInternal {
     conn: Arc<dyn Conn + Send + Sync>,
     stun_serv_addr: String,
@@ -71,6 +89,8 @@ struct ClientInternal {
     binding_mgr: Arc<Mutex<BindingManager>>,
     rto_in_ms: u16,
     read_ch_tx: Arc<Mutex<Option<mpsc::Sender<InboundData>>>>,
+    credentials: HashMap<String, Credentials>,
+    credential_cache_stats: CredentialCacheStats,
 }
 
 #[async_trait]
@@ -103,6 +123,54 @@ impl RelayConnObserver for ClientInternal {
         to: &str,
         ignore_result: bool,
     ) -> Result<TransactionResult> {
+        let result_ch_rx = self.send_transaction(msg, to, ignore_result).await?;
+
+        // If dontWait is true, get the transaction going and return immediately
+        if ignore_result {
+            return Ok(TransactionResult::default());
+        }
+
+        // wait_for_result waits for the transaction result
+        if let Some(mut result_ch_rx) = result_ch_rx {
+            match result_ch_rx.recv().await {
+                Some(tr) => Ok(tr),
+                None => Err(Error::ErrTransactionClosed),
+            }
+        } else {
+            Err(Error::ErrWaitForResultOnNonResultTransaction)
+        }
+    }
+
+    // start_transaction starts a STUN transaction without waiting for its result
+    async fn start_transaction(
+        &mut self,
+        msg: &Message,
+        to: &str,
+    ) -> Result<mpsc::Receiver<TransactionResult>> {
+        self.send_transaction(msg, to, false)
+            .await?
+            .ok_or(Error::ErrWaitForResultOnNonResultTransaction)
+    }
+
+    // set_nonce replaces the nonce cached for the TURN server
+    fn set_nonce(&mut self, nonce: Nonce) {
+        self.credentials.insert(
+            self.turn_serv_addr.clone(),
+            Credentials {
+                realm: self.realm.clone(),
+                nonce,
+            },
+        );
+    }
+}
+
+impl ClientInternal {
+    async fn send_transaction(
+        &mut self,
+        msg: &Message,
+        to: &str,
+        ignore_result: bool,
+    ) -> Result<Option<mpsc::Receiver<TransactionResult>>> {
         let tr_key = base64::encode(msg.transaction_id.0);
 
         let mut tr = Transaction::new(TransactionConfig {
@@ -133,24 +201,9 @@ impl RelayConnObserver for ClientInternal {
             }
         }
 
-        // If dontWait is true, get the transaction going and return immediately
-        if ignore_result {
-            return Ok(TransactionResult::default());
-        }
-
-        // wait_for_result waits for the transaction result
-        if let Some(mut result_ch_rx) = result_ch_rx {
-            match result_ch_rx.recv().await {
-                Some(tr) => Ok(tr),
-                None => Err(Error::ErrTransactionClosed),
-            }
-        } else {
-            Err(Error::ErrWaitForResultOnNonResultTransaction)
-        }
+        Ok(result_ch_rx)
     }
-}
 
-impl ClientInternal {
     // new returns a new Client instance. listeningAddress is the address and port to listen on, default "0.0.0.0:0"
     async fn new(config: ClientConfig) -> Result<Self> {
         let net = if let Some(vnet) = config.vnet {
@@ -203,6 +256,8 @@ impl ClientInternal {
             },
             integrity: MessageIntegrity::new_short_term_integrity(String::new()),
             read_ch_tx: Arc::new(Mutex::new(None)),
+            credentials: HashMap::new(),
+            credential_cache_stats: CredentialCacheStats::default(),
         })
     }
 
@@ -480,71 +535,112 @@ impl ClientInternal {
         bm.find_by_number(ch_num).map(|b| b.addr)
     }
 
+    // set_realm sets the realm and the long-term credentials of it
+    fn set_realm(&mut self, realm: Realm) {
+        self.realm = realm;
+        self.integrity = MessageIntegrity::new_long_term_integrity(
+            self.username.text.clone(),
+            self.realm.text.clone(),
+            self.password.clone(),
+        );
+    }
+
     // Allocate sends a TURN allocation request to the given transport address
     async fn allocate(&mut self) -> Result<RelayConnConfig> {
         {
             let read_ch_tx = self.read_ch_tx.lock().await;
             log::debug!("allocate check: read_ch_tx_opt = {}", read_ch_tx.is_some());
-            if read_ch_tx.is_some() {
+            // The RelayConn of a previous allocation has been dropped once nothing receives
+            if read_ch_tx.as_ref().map_or(false, |tx| !tx.is_closed()) {
                 return Err(Error::ErrOneAllocateOnly);
             }
         }
 
-        let mut msg = Message::new();
-        msg.build(&[
-            Box::new(TransactionId::new()),
-            Box::new(MessageType::new(METHOD_ALLOCATE, CLASS_REQUEST)),
-            Box::new(RequestedTransport {
-                protocol: PROTO_UDP,
-            }),
-            Box::new(FINGERPRINT),
-        ])?;
-
-        log::debug!("client.Allocate call PerformTransaction 1");
-        let tr_res = self
-            .perform_transaction(&msg, &self.turn_serv_addr.clone(), false)
-            .await?;
-        let res = tr_res.msg;
+        let turn_serv_addr = self.turn_serv_addr.clone();
 
-        // Anonymous allocate failed, trying to authenticate.
-        let nonce = Nonce::get_from_as(&res, ATTR_NONCE)?;
-        self.realm = Realm::get_from_as(&res, ATTR_REALM)?;
+        // With the realm and nonce cached, skip the unauthenticated Allocate
+        let mut nonce = match self.credentials.get(&turn_serv_addr).cloned() {
+            Some(credentials) => {
+                self.credential_cache_stats.hits += 1;
+                self.set_realm(credentials.realm);
+                Some(credentials.nonce)
+            }
+            None => {
+                self.credential_cache_stats.misses += 1;
+                None
+            }
+        };
 
-        self.integrity = MessageIntegrity::new_long_term_integrity(
-            self.username.text.clone(),
-            self.realm.text.clone(),
-            self.password.clone(),
-        );
+        let mut attempt = 0;
+        let res = loop {
+            attempt += 1;
 
-        // Trying to authorize.
-        msg.build(&[
-            Box::new(TransactionId::new()),
-            Box::new(MessageType::new(METHOD_ALLOCATE, CLASS_REQUEST)),
-            Box::new(RequestedTransport {
-                protocol: PROTO_UDP,
-            }),
-            Box::new(self.username.clone()),
-            Box::new(self.realm.clone()),
-            Box::new(nonce.clone()),
-            Box::new(self.integrity.clone()),
-            Box::new(FINGERPRINT),
-        ])?;
-
-        log::debug!("client.Allocate call PerformTransaction 2");
-        let tr_res = self
-            .perform_transaction(&msg, &self.turn_serv_addr.clone(), false)
-            .await?;
-        let res = tr_res.msg;
+            let mut msg = Message::new();
+            if let Some(nonce) = &nonce {
+                msg.build(&[
+                    Box::new(TransactionId::new()),
+                    Box::new(MessageType::new(METHOD_ALLOCATE, CLASS_REQUEST)),
+                    Box::new(RequestedTransport {
+                        protocol: PROTO_UDP,
+                    }),
+                    Box::new(self.username.clone()),
+                    Box::new(self.realm.clone()),
+                    Box::new(nonce.clone()),
+                    Box::new(self.integrity.clone()),
+                    Box::new(FINGERPRINT),
+                ])?;
+            } else {
+                msg.build(&[
+                    Box::new(TransactionId::new()),
+                    Box::new(MessageType::new(METHOD_ALLOCATE, CLASS_REQUEST)),
+                    Box::new(RequestedTransport {
+                        protocol: PROTO_UDP,
+                    }),
+                    Box::new(FINGERPRINT),
+                ])?;
+            }
+
+            log::debug!("client.Allocate call PerformTransaction {}", attempt);
+            let tr_res = self
+                .perform_transaction(&msg, &turn_serv_addr, false)
+                .await?;
+            let res = tr_res.msg;
+
+            if res.typ.class != CLASS_ERROR_RESPONSE {
+                break res;
+            }
 
-        if res.typ.class == CLASS_ERROR_RESPONSE {
             let mut code = ErrorCodeAttribute::default();
-            let result = code.get_from(&res);
-            if result.is_err() {
+            if code.get_from(&res).is_err() {
                 return Err(Error::Other(format!("{}", res.typ)));
-            } else {
+            }
+
+            let challenged = code.code == CODE_UNAUTHORIZED || code.code == CODE_STALE_NONCE;
+            if !challenged || attempt == MAX_ALLOCATE_ATTEMPTS {
                 return Err(Error::Other(format!("{} (error {})", res.typ, code)));
             }
-        }
+
+            // Challenged, or the cached nonce went stale: retry with the fresh one.
+            let fresh_nonce = Nonce::get_from_as(&res, ATTR_NONCE)?;
+            if let Ok(realm) = Realm::get_from_as(&res, ATTR_REALM) {
+                self.set_realm(realm);
+            }
+            log::debug!("allocate: error {}, got new nonce", code.code.0);
+            self.credentials.insert(
+                turn_serv_addr.clone(),
+                Credentials {
+                    realm: self.realm.clone(),
+                    nonce: fresh_nonce.clone(),
+                },
+            );
+            nonce = Some(fresh_nonce);
+        };
+
+        // An anonymous allocation still needs a nonce for the requests of its RelayConn.
+        let nonce = match nonce {
+            Some(nonce) => nonce,
+            None => Nonce::get_from_as(&res, ATTR_NONCE)?,
+        };
 
         // Getting relayed addresses from response.
         let mut relayed = RelayedAddress::default();
@@ -592,7 +688,9 @@ impl Client {
         ci.listen().await
     }
 
-    pub async fn allocate(&self) -> Result<impl Conn> {
+    pub async fn allocate(
+        &self,
+    ) -> Result<RelayConn<impl RelayConnObserver + Send + Sync + 'static>> {
         let config = {
             let mut ci = self.client_internal.lock().await;
             ci.allocate().await?
@@ -601,6 +699,13 @@ impl Client {
         Ok(RelayConn::new(Arc::clone(&self.client_internal), config).await)
     }
 
+    /// credential_cache_stats returns how often allocations reused the cached realm and nonce
+    /// of the TURN server.
+    pub async fn credential_cache_stats(&self) -> CredentialCacheStats {
+        let ci = self.client_internal.lock().await;
+        ci.credential_cache_stats
+    }
+
     pub async fn close(&self) -> Result<()> {
         let mut ci = self.client_internal.lock().await;
         ci.close().await;
@@ -1,12 +1,18 @@
+use crate::error::{Error, Result};
+
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::Arc;
+use tokio::sync::Notify;
+use util::sync::Mutex as SyncMutex;
 
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub(crate) enum PermState {
     Idle = 0,
     Permitted = 1,
+    Requested = 2,
+    Failed = 3,
 }
 
 impl Default for PermState {
@@ -19,6 +25,8 @@ impl From<u8> for PermState {
     fn from(v: u8) -> Self {
         match v {
             0 => PermState::Idle,
+            2 => PermState::Requested,
+            3 => PermState::Failed,
             _ => PermState::Permitted,
         }
     }
@@ -27,16 +35,65 @@ impl From<u8> for PermState {
 #[derive(Default)]
 pub(crate) struct Permission {
     st: AtomicU8, //PermState,
+    failure: SyncMutex<Option<String>>,
+    settled: Notify,
 }
 
 impl Permission {
     pub(crate) fn set_state(&self, state: PermState) {
         self.st.store(state as u8, Ordering::SeqCst);
+        if state != PermState::Requested {
+            self.settled.notify_waiters();
+        }
     }
 
     pub(crate) fn state(&self) -> PermState {
         self.st.load(Ordering::SeqCst).into()
     }
+
+    pub(crate) fn fail(&self, err: &Error) {
+        *self.failure.lock() = Some(err.to_string());
+        self.set_state(PermState::Failed);
+    }
+
+    // wait blocks while the permission is being requested
+    pub(crate) async fn wait(&self) -> Result<()> {
+        loop {
+            let settled = self.settled.notified();
+            match self.state() {
+                PermState::Requested => settled.await,
+                PermState::Permitted => return Ok(()),
+                PermState::Idle => {
+                    return Err(Error::ErrCreatePermissionFailed("not requested".to_owned()))
+                }
+                PermState::Failed => {
+                    return Err(Error::ErrCreatePermissionFailed(
+                        self.failure.lock().clone().unwrap_or_default(),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// PermissionRequest tracks the permission for a peer requested with
+/// `RelayConn::create_permissions`.
+pub struct PermissionRequest {
+    pub(crate) addr: SocketAddr,
+    pub(crate) perm: Arc<Permission>,
+}
+
+impl PermissionRequest {
+    /// addr returns the peer address the permission was requested for.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// ready waits until the TURN server installed the permission, it fails with
+    /// `Error::ErrCreatePermissionFailed` if the server refused it.
+    pub async fn ready(&self) -> Result<()> {
+        self.perm.wait().await
+    }
 }
 
 // Thread-safe Permission map
@@ -26,6 +26,7 @@ use tokio::sync::{mpsc, Mutex};
 use tokio::time::{Duration, Instant};
 
 use async_trait::async_trait;
+use futures::future;
 
 const PERM_REFRESH_INTERVAL: Duration = Duration::from_secs(120);
 const MAX_RETRY_ATTEMPTS: u16 = 3;
@@ -48,6 +49,17 @@ pub trait RelayConnObserver {
         to: &str,
         ignore_result: bool,
     ) -> Result<TransactionResult, Error>;
+    // start_transaction starts a STUN transaction like perform_transaction, but returns
+    // the channel its result is delivered on instead of waiting for it, so that several
+    // transactions can be in flight at once.
+    async fn start_transaction(
+        &mut self,
+        msg: &Message,
+        to: &str,
+    ) -> Result<mpsc::Receiver<TransactionResult>, Error>;
+    // set_nonce is called with the fresh nonce a stale nonce was replaced with, so that
+    // later allocations to the same TURN server start out with it.
+    fn set_nonce(&mut self, _nonce: Nonce) {}
 }
 
 // RelayConnConfig is a set of configuration params use by NewUDPConn
@@ -104,6 +116,15 @@ impl<T: 'static + RelayConnObserver + Send + Sync> RelayConn<T> {
 
         c
     }
+
+    /// create_permissions installs permissions for the IP addresses of `addrs` on the TURN
+    /// server. The addresses without a permission yet are requested with a single
+    /// CreatePermission request, and each returned request resolves once the permission of
+    /// its address is installed. When the server refuses the request, the addresses are
+    /// requested one by one, so a refused peer doesn't fail the others.
+    pub async fn create_permissions(&self, addrs: &[SocketAddr]) -> Vec<PermissionRequest> {
+        RelayConnInternal::request_permissions(&self.relay_conn, addrs).await
+    }
 }
 
 #[async_trait]
@@ -159,6 +180,15 @@ impl<T: RelayConnObserver + Send + Sync> Conn for RelayConn<T> {
     // see SetDeadline and SetWriteDeadline.
     // On packet-oriented connections, write timeouts are rare.
     async fn send_to(&self, p: &[u8], addr: SocketAddr) -> Result<usize, util::Error> {
+        // This would block, per destination IP (, or perm), until the permission is
+        // installed, which guarantees the order of packets (within the same perm). Sends
+        // to other destinations don't wait for it.
+        for request in RelayConnInternal::request_permissions(&self.relay_conn, &[addr]).await {
+            if let Err(err) = request.ready().await {
+                return Err(io::Error::new(io::ErrorKind::Other, err.to_string()).into());
+            }
+        }
+
         let mut relay_conn = self.relay_conn.lock().await;
         match relay_conn.send_to(p, addr).await {
             Ok(n) => Ok(n),
@@ -210,26 +240,6 @@ impl<T: RelayConnObserver + Send + Sync> RelayConnInternal<T> {
     // see SetDeadline and SetWriteDeadline.
     // On packet-oriented connections, write timeouts are rare.
     async fn send_to(&mut self, p: &[u8], addr: SocketAddr) -> Result<usize, Error> {
-        // check if we have a permission for the destination IP addr
-        let perm = if let Some(perm) = self.perm_map.find(&addr) {
-            Arc::clone(perm)
-        } else {
-            let perm = Arc::new(Permission::default());
-            self.perm_map.insert(&addr, Arc::clone(&perm));
-            perm
-        };
-
-        let mut result = Ok(());
-        for _ in 0..MAX_RETRY_ATTEMPTS {
-            result = self.create_perm(&perm, addr).await;
-            if let Err(err) = &result {
-                if Error::ErrTryAgain != *err {
-                    break;
-                }
-            }
-        }
-        result?;
-
         let number = {
             let (bind_st, bind_at, bind_number, bind_addr) = {
                 let mut binding_mgr = self.binding_mgr.lock().await;
@@ -356,23 +366,137 @@ impl<T: RelayConnObserver + Send + Sync> RelayConnInternal<T> {
         self.send_channel_data(p, number).await
     }
 
-    // This func-block would block, per destination IP (, or perm), until
-    // the perm state becomes "requested". Purpose of this is to guarantee
-    // the order of packets (within the same perm).
-    // Note that CreatePermission transaction may not be complete before
-    // all the data transmission. This is done assuming that the request
-    // will be mostly likely successful and we can tolerate some loss of
-    // UDP packet (or reorder), inorder to minimize the latency in most cases.
-    async fn create_perm(&mut self, perm: &Arc<Permission>, addr: SocketAddr) -> Result<(), Error> {
-        if perm.state() == PermState::Idle {
-            // punch a hole! (this would block a bit..)
-            if let Err(err) = self.create_permissions(&[addr]).await {
-                self.perm_map.delete(&addr);
-                return Err(err);
+    // request_permissions returns the permissions for addrs, and starts a single
+    // CreatePermission transaction for the ones which weren't requested yet.
+    async fn request_permissions(
+        rc: &Arc<Mutex<Self>>,
+        addrs: &[SocketAddr],
+    ) -> Vec<PermissionRequest> {
+        let mut requests = vec![];
+        let mut batch = vec![];
+        {
+            let mut rci = rc.lock().await;
+            for addr in addrs {
+                let perm = if let Some(perm) = rci.perm_map.find(addr) {
+                    Arc::clone(perm)
+                } else {
+                    let perm = Arc::new(Permission::default());
+                    rci.perm_map.insert(addr, Arc::clone(&perm));
+                    perm
+                };
+
+                if perm.state() == PermState::Idle {
+                    perm.set_state(PermState::Requested);
+                    batch.push((*addr, Arc::clone(&perm)));
+                }
+                requests.push(PermissionRequest { addr: *addr, perm });
             }
-            perm.set_state(PermState::Permitted);
         }
-        Ok(())
+
+        if !batch.is_empty() {
+            let rc = Arc::clone(rc);
+            tokio::spawn(async move {
+                RelayConnInternal::complete_permissions(rc, batch).await;
+            });
+        }
+
+        requests
+    }
+
+    async fn complete_permissions(rc: Arc<Mutex<Self>>, batch: Vec<(SocketAddr, Arc<Permission>)>) {
+        let addrs: Vec<SocketAddr> = batch.iter().map(|(addr, _)| *addr).collect();
+        match RelayConnInternal::permission_transaction(&rc, &addrs).await {
+            Ok(()) => {
+                for (_, perm) in &batch {
+                    perm.set_state(PermState::Permitted);
+                }
+            }
+            Err(err) if batch.len() > 1 => {
+                // the server refuses the whole request if it refuses one of the peers
+                log::debug!(
+                    "CreatePermission for {} peers failed: {}, requesting them one by one",
+                    batch.len(),
+                    err
+                );
+                let rc = &rc;
+                future::join_all(batch.iter().map(|(addr, perm)| async move {
+                    let result = RelayConnInternal::permission_transaction(rc, &[*addr]).await;
+                    RelayConnInternal::settle_permission(rc, addr, perm, result).await;
+                }))
+                .await;
+            }
+            Err(err) => {
+                let (addr, perm) = &batch[0];
+                RelayConnInternal::settle_permission(&rc, addr, perm, Err(err)).await;
+            }
+        }
+    }
+
+    async fn settle_permission(
+        rc: &Arc<Mutex<Self>>,
+        addr: &SocketAddr,
+        perm: &Arc<Permission>,
+        result: Result<(), Error>,
+    ) {
+        match result {
+            Ok(()) => perm.set_state(PermState::Permitted),
+            Err(err) => {
+                log::warn!("CreatePermission for {} failed: {}", addr, err);
+                {
+                    // the next send requests it again
+                    let mut rci = rc.lock().await;
+                    if rci
+                        .perm_map
+                        .find(addr)
+                        .map_or(false, |p| Arc::ptr_eq(p, perm))
+                    {
+                        rci.perm_map.delete(addr);
+                    }
+                }
+                perm.fail(&err);
+            }
+        }
+    }
+
+    // permission_transaction performs a CreatePermission transaction for addrs without
+    // holding the lock while waiting for the response, retrying on stale nonces.
+    async fn permission_transaction(
+        rc: &Arc<Mutex<Self>>,
+        addrs: &[SocketAddr],
+    ) -> Result<(), Error> {
+        let mut result = Ok(());
+        for _ in 0..MAX_RETRY_ATTEMPTS {
+            result = async {
+                let mut result_ch_rx = {
+                    let rci = rc.lock().await;
+                    let msg = rci.create_permission_request(addrs).await?;
+                    let mut obs = rci.obs.lock().await;
+                    let turn_server_addr = obs.turn_server_addr();
+                    obs.start_transaction(&msg, &turn_server_addr).await?
+                };
+
+                let tr_res = result_ch_rx
+                    .recv()
+                    .await
+                    .ok_or(Error::ErrTransactionClosed)?;
+                if let Some(err) = tr_res.err {
+                    return Err(err);
+                }
+
+                let mut rci = rc.lock().await;
+                rci.handle_create_permission_response(&tr_res.msg).await
+            }
+            .await;
+
+            if let Err(err) = &result {
+                if Error::ErrTryAgain != *err {
+                    break;
+                }
+            } else {
+                break;
+            }
+        }
+        result
     }
 
     async fn send_channel_data(&self, data: &[u8], ch_num: u16) -> Result<usize, Error> {
@@ -387,30 +511,32 @@ impl<T: RelayConnObserver + Send + Sync> RelayConnInternal<T> {
         Ok(obs.write_to(&ch_data.raw, &obs.turn_server_addr()).await?)
     }
 
-    async fn create_permissions(&mut self, addrs: &[SocketAddr]) -> Result<(), Error> {
-        let res = {
-            let msg = {
-                let obs = self.obs.lock().await;
-                let mut setters: Vec<Box<dyn Setter>> = vec![
-                    Box::new(TransactionId::new()),
-                    Box::new(MessageType::new(METHOD_CREATE_PERMISSION, CLASS_REQUEST)),
-                ];
+    // create_permission_request builds one CreatePermission request for all addrs
+    async fn create_permission_request(&self, addrs: &[SocketAddr]) -> Result<Message, Error> {
+        let obs = self.obs.lock().await;
+        let mut setters: Vec<Box<dyn Setter>> = vec![
+            Box::new(TransactionId::new()),
+            Box::new(MessageType::new(METHOD_CREATE_PERMISSION, CLASS_REQUEST)),
+        ];
 
-                for addr in addrs {
-                    setters.push(Box::new(socket_addr2peer_address(addr)));
-                }
+        for addr in addrs {
+            setters.push(Box::new(socket_addr2peer_address(addr)));
+        }
 
-                setters.push(Box::new(obs.username()));
-                setters.push(Box::new(obs.realm()));
-                setters.push(Box::new(self.nonce.clone()));
-                setters.push(Box::new(self.integrity.clone()));
-                setters.push(Box::new(FINGERPRINT));
+        setters.push(Box::new(obs.username()));
+        setters.push(Box::new(obs.realm()));
+        setters.push(Box::new(self.nonce.clone()));
+        setters.push(Box::new(self.integrity.clone()));
+        setters.push(Box::new(FINGERPRINT));
 
-                let mut msg = Message::new();
-                msg.build(&setters)?;
-                msg
-            };
+        let mut msg = Message::new();
+        msg.build(&setters)?;
+        Ok(msg)
+    }
 
+    async fn create_permissions(&mut self, addrs: &[SocketAddr]) -> Result<(), Error> {
+        let msg = self.create_permission_request(addrs).await?;
+        let res = {
             let mut obs = self.obs.lock().await;
             let turn_server_addr = obs.turn_server_addr();
 
@@ -422,13 +548,17 @@ impl<T: RelayConnObserver + Send + Sync> RelayConnInternal<T> {
             tr_res.msg
         };
 
+        self.handle_create_permission_response(&res).await
+    }
+
+    async fn handle_create_permission_response(&mut self, res: &Message) -> Result<(), Error> {
         if res.typ.class == CLASS_ERROR_RESPONSE {
             let mut code = ErrorCodeAttribute::default();
-            let result = code.get_from(&res);
+            let result = code.get_from(res);
             if result.is_err() {
                 return Err(Error::Other(format!("{}", res.typ)));
             } else if code.code == CODE_STALE_NONCE {
-                self.set_nonce_from_msg(&res);
+                self.set_nonce_from_msg(res).await;
                 return Err(Error::ErrTryAgain);
             } else {
                 return Err(Error::Other(format!("{} (error {})", res.typ, code)));
@@ -438,10 +568,11 @@ impl<T: RelayConnObserver + Send + Sync> RelayConnInternal<T> {
         Ok(())
     }
 
-    pub fn set_nonce_from_msg(&mut self, msg: &Message) {
-        // Update nonce
+    pub async fn set_nonce_from_msg(&mut self, msg: &Message) {
+        // Update nonce, and the one cached for the next allocations
         match Nonce::get_from_as(msg, ATTR_NONCE) {
             Ok(nonce) => {
+                self.obs.lock().await.set_nonce(nonce.clone());
                 self.nonce = nonce;
                 log::debug!("refresh allocation: 438, got new nonce.");
             }
@@ -498,7 +629,7 @@ impl<T: RelayConnObserver + Send + Sync> RelayConnInternal<T> {
             if result.is_err() {
                 return Err(Error::Other(format!("{}", res.typ)));
             } else if code.code == CODE_STALE_NONCE {
-                self.set_nonce_from_msg(&res);
+                self.set_nonce_from_msg(&res).await;
                 return Err(Error::ErrTryAgain);
             } else {
                 return Ok(());
@@ -35,6 +35,14 @@ impl RelayConnObserver for DummyRelayConnObserver {
     ) -> Result<TransactionResult> {
         Err(Error::ErrFakeErr)
     }
+
+    async fn start_transaction(
+        &mut self,
+        _msg: &Message,
+        _to: &str,
+    ) -> Result<mpsc::Receiver<TransactionResult>> {
+        Err(Error::ErrFakeErr)
+    }
 }
 
 #[tokio::test]
@@ -146,6 +146,8 @@ pub enum Error {
     ErrNoAllocationFound,
     #[error("unable to handle send-indication, no permission added")]
     ErrNoPermission,
+    #[error("failed to create permission: {0}")]
+    ErrCreatePermissionFailed(String),
     #[error("packet write smaller than packet")]
     ErrShortWrite,
     #[error("no such channel bind")]
@@ -32,6 +32,7 @@ pub struct Server {
     realm: String,
     channel_bind_timeout: Duration,
     pub(crate) nonces: Arc<Mutex<HashMap<String, Instant>>>,
+    pub(crate) rotated_nonces: Arc<Mutex<HashMap<String, Instant>>>,
     command_tx: Mutex<Option<broadcast::Sender<Command>>>,
 }
 
@@ -46,6 +47,7 @@ impl Server {
             realm: config.realm,
             channel_bind_timeout: config.channel_bind_timeout,
             nonces: Arc::new(Mutex::new(HashMap::new())),
+            rotated_nonces: Arc::new(Mutex::new(HashMap::new())),
             command_tx: Mutex::new(Some(command_tx.clone())),
         };
 
@@ -55,6 +57,7 @@ impl Server {
 
         for p in config.conn_configs.into_iter() {
             let nonces = Arc::clone(&s.nonces);
+            let rotated_nonces = Arc::clone(&s.rotated_nonces);
             let auth_handler = Arc::clone(&s.auth_handler);
             let realm = s.realm.clone();
             let channel_bind_timeout = s.channel_bind_timeout;
@@ -68,6 +71,7 @@ impl Server {
                 conn,
                 allocation_manager,
                 nonces,
+                rotated_nonces,
                 auth_handler,
                 realm,
                 channel_bind_timeout,
@@ -139,10 +143,12 @@ impl Server {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn read_loop(
         conn: Arc<dyn Conn + Send + Sync>,
         allocation_manager: Arc<Manager>,
         nonces: Arc<Mutex<HashMap<String, Instant>>>,
+        rotated_nonces: Arc<Mutex<HashMap<String, Instant>>>,
         auth_handler: Arc<dyn AuthHandler + Send + Sync>,
         realm: String,
         channel_bind_timeout: Duration,
@@ -203,6 +209,7 @@ impl Server {
                 buff: buf[..n].to_vec(),
                 allocation_manager: Arc::clone(&allocation_manager),
                 nonces: Arc::clone(&nonces),
+                rotated_nonces: Arc::clone(&rotated_nonces),
                 auth_handler: Arc::clone(&auth_handler),
                 realm: realm.clone(),
                 channel_bind_timeout,
@@ -45,6 +45,9 @@ use md5::{Digest, Md5};
 pub(crate) const MAXIMUM_ALLOCATION_LIFETIME: Duration = Duration::from_secs(3600); // https://tools.ietf.org/html/rfc5766#section-6.2 defines 3600 seconds recommendation
 pub(crate) const NONCE_LIFETIME: Duration = Duration::from_secs(3600); // https://tools.ietf.org/html/rfc5766#section-4
 
+// A rotated nonce is still accepted this long, so requests in flight don't all get 438 at rotation
+pub(crate) const NONCE_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
 // Request contains all the state needed to process a single incoming datagram
 pub struct Request {
     // Current Request State
@@ -55,6 +58,8 @@ pub struct Request {
     // Server State
     pub allocation_manager: Arc<Manager>,
     pub nonces: Arc<Mutex<HashMap<String, Instant>>>,
+    // expired nonces, by the time they were replaced with a fresh one
+    pub rotated_nonces: Arc<Mutex<HashMap<String, Instant>>>,
 
     // User Configuration
     pub auth_handler: Arc<dyn AuthHandler + Send + Sync>,
@@ -75,6 +80,7 @@ impl Request {
             buff: vec![],
             allocation_manager,
             nonces: Arc::new(Mutex::new(HashMap::new())),
+            rotated_nonces: Arc::new(Mutex::new(HashMap::new())),
             auth_handler,
             realm: String::new(),
             channel_bind_timeout: Duration::from_secs(0),
@@ -169,20 +175,32 @@ impl Request {
         let to_be_deleted = {
             // Assert Nonce exists and is not expired
             let mut nonces = self.nonces.lock().await;
+            let mut rotated_nonces = self.rotated_nonces.lock().await;
+            let now = Instant::now();
 
-            let to_be_deleted = if let Some(nonce_creation_time) = nonces.get(&nonce_attr.text) {
-                Instant::now()
-                    .checked_duration_since(*nonce_creation_time)
+            if let Some(nonce_creation_time) = nonces.get(&nonce_attr.text).copied() {
+                let expired = now
+                    .checked_duration_since(nonce_creation_time)
                     .unwrap_or_else(|| Duration::from_secs(0))
-                    >= NONCE_LIFETIME
-            } else {
-                true
-            };
-
-            if to_be_deleted {
-                nonces.remove(&nonce_attr.text);
+                    >= NONCE_LIFETIME;
+                if expired {
+                    // The expired nonce is rotated, its grace period starts when it expired
+                    nonces.remove(&nonce_attr.text);
+                    rotated_nonces.insert(
+                        nonce_attr.text.clone(),
+                        nonce_creation_time + NONCE_LIFETIME,
+                    );
+                }
             }
-            to_be_deleted
+
+            rotated_nonces.retain(|_, rotated_at| {
+                now.checked_duration_since(*rotated_at)
+                    .unwrap_or_else(|| Duration::from_secs(0))
+                    < NONCE_GRACE_PERIOD
+            });
+
+            // The previous nonce is accepted during the grace period after its rotation
+            !nonces.contains_key(&nonce_attr.text) && !rotated_nonces.contains_key(&nonce_attr.text)
         };
 
         if to_be_deleted {
@@ -614,36 +632,54 @@ impl Request {
                 log::debug!("no MessageIntegrity");
                 return Ok(());
             };
-            let mut add_count = 0;
+            let mut peer_addrs = vec![];
+            for attr in &m.attributes.0 {
+                if attr.typ != ATTR_XOR_PEER_ADDRESS {
+                    continue;
+                }
+
+                // get_from only finds the first XOR-PEER-ADDRESS, decode each one on its own
+                let mut single = Message::new();
+                single.transaction_id = m.transaction_id;
+                single.add(ATTR_XOR_PEER_ADDRESS, &attr.value);
 
+                let mut peer_address = PeerAddress::default();
+                if peer_address.get_from(&single).is_err() {
+                    peer_addrs.clear();
+                    break;
+                }
+                peer_addrs.push(SocketAddr::new(peer_address.ip, peer_address.port));
+            }
+
+            // RFC 6156 section 4.2: no permission is installed if a peer address
+            // doesn't match the family of the relayed transport address
+            if peer_addrs
+                .iter()
+                .any(|peer_addr| peer_addr.is_ipv4() != a.relay_addr.is_ipv4())
             {
-                for attr in &m.attributes.0 {
-                    if attr.typ != ATTR_XOR_PEER_ADDRESS {
-                        continue;
-                    }
+                let msg = build_msg(
+                    m.transaction_id,
+                    MessageType::new(METHOD_CREATE_PERMISSION, CLASS_ERROR_RESPONSE),
+                    vec![
+                        Box::new(ErrorCodeAttribute {
+                            code: CODE_PEER_ADDR_FAMILY_MISMATCH,
+                            reason: vec![],
+                        }),
+                        Box::new(message_integrity),
+                    ],
+                )?;
 
-                    let mut peer_address = PeerAddress::default();
-                    if peer_address.get_from(m).is_err() {
-                        add_count = 0;
-                        break;
-                    }
+                return build_and_send(&self.conn, self.src_addr, msg).await;
+            }
 
-                    log::debug!(
-                        "adding permission for {}",
-                        format!("{}:{}", peer_address.ip, peer_address.port)
-                    );
+            for peer_addr in &peer_addrs {
+                log::debug!("adding permission for {}", peer_addr);
 
-                    a.add_permission(Permission::new(SocketAddr::new(
-                        peer_address.ip,
-                        peer_address.port,
-                    )))
-                    .await;
-                    add_count += 1;
-                }
+                a.add_permission(Permission::new(*peer_addr)).await;
             }
 
             let mut resp_class = CLASS_SUCCESS_RESPONSE;
-            if add_count == 0 {
+            if peer_addrs.is_empty() {
                 resp_class = CLASS_ERROR_RESPONSE;
             }
 
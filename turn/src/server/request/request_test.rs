@@ -117,3 +117,49 @@ async fn test_allocation_lifetime_deletion_zero_lifetime() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(start_paused = true)]
+async fn test_rotated_nonce_grace_period() -> Result<()> {
+    let l = Arc::new(UdpSocket::bind("0.0.0.0:0").await?);
+
+    let allocation_manager = Arc::new(Manager::new(ManagerConfig {
+        relay_addr_generator: Box::new(RelayAddressGeneratorNone {
+            address: "0.0.0.0".to_owned(),
+            net: Arc::new(Net::new(None)),
+        }),
+    }));
+
+    let socket = SocketAddr::new(IpAddr::from_str("127.0.0.1")?, 5000);
+
+    let mut r = Request::new(l, socket, allocation_manager, Arc::new(TestAuthHandler {}));
+
+    {
+        let mut nonces = r.nonces.lock().await;
+        nonces.insert(STATIC_KEY.to_owned(), Instant::now());
+    }
+
+    let mut m = Message::new();
+    MessageIntegrity(STATIC_KEY.as_bytes().to_vec()).add_to(&mut m)?;
+    Nonce::new(ATTR_NONCE, STATIC_KEY.to_owned()).add_to(&mut m)?;
+    Realm::new(ATTR_REALM, STATIC_KEY.to_owned()).add_to(&mut m)?;
+    Username::new(ATTR_USERNAME, STATIC_KEY.to_owned()).add_to(&mut m)?;
+
+    assert!(r.authenticate_request(&m, METHOD_REFRESH).await?.is_some());
+
+    // The expired nonce is rotated, and still accepted from requests in flight during the grace
+    // period from the start,
+    tokio::time::advance(NONCE_LIFETIME).await;
+    assert!(r.authenticate_request(&m, METHOD_REFRESH).await?.is_some());
+    assert!(!r.nonces.lock().await.contains_key(STATIC_KEY));
+    assert!(r.rotated_nonces.lock().await.contains_key(STATIC_KEY));
+
+    tokio::time::advance(NONCE_GRACE_PERIOD / 2).await;
+    assert!(r.authenticate_request(&m, METHOD_REFRESH).await?.is_some());
+
+    // but no longer after it.
+    tokio::time::advance(NONCE_GRACE_PERIOD).await;
+    assert!(r.authenticate_request(&m, METHOD_REFRESH).await?.is_none());
+    assert!(r.rotated_nonces.lock().await.is_empty());
+
+    Ok(())
+}
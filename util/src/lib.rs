@@ -88,3 +88,6 @@ pub use error::{Error, Result};
 
 #[cfg(feature = "sync")]
 pub mod sync;
+
+#[cfg(feature = "serde")]
+pub mod serde_hex;
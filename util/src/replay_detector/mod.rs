@@ -35,6 +35,18 @@ impl SlidingWindowDetector {
             mask: FixedBigInt::new(window_size),
         }
     }
+
+    /// accepted returns the sequence numbers accepted within the window, in increasing order.
+    /// Accepting them in this order with a new detector restores the window, e.g. to carry it
+    /// over to another connection.
+    pub fn accepted(&self) -> Vec<u64> {
+        let n = (self.window_size as u64).min(self.latest_seq.saturating_add(1));
+        (0..n as usize)
+            .rev()
+            .filter(|&i| self.mask.bit(i) != 0)
+            .map(|i| self.latest_seq - i as u64)
+            .collect()
+    }
 }
 
 impl ReplayDetector for SlidingWindowDetector {
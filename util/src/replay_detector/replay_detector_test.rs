@@ -275,3 +275,27 @@ fn test_replay_detector() {
         }
     }
 }
+
+#[test]
+fn test_sliding_window_detector_accepted() {
+    let mut det = SlidingWindowDetector::new(8, 0xFFFF);
+    assert!(det.accepted().is_empty());
+
+    for seq in [3u64, 1, 10, 5, 9, 12].iter() {
+        if det.check(*seq) {
+            det.accept();
+        }
+    }
+    // 3 and 1 fell out of the window of 8
+    assert_eq!(det.accepted(), vec![5, 9, 10, 12]);
+
+    // A detector restored from the accepted sequence numbers rejects the same ones
+    let mut restored = SlidingWindowDetector::new(8, 0xFFFF);
+    for seq in det.accepted() {
+        assert!(restored.check(seq));
+        restored.accept();
+    }
+    for seq in 0..20 {
+        assert_eq!(restored.check(seq), det.check(seq), "seq {}", seq);
+    }
+}
@@ -0,0 +1,114 @@
+//! Serde helpers for byte payloads, to be used with `#[serde(with = "util::serde_hex")]`.
+//!
+//! Human readable formats, e.g. JSON, get the bytes as a lower case hex string, the others get
+//! them as bytes. Both forms, as well as a sequence of numbers, are accepted when deserializing.
+
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserializer, Serializer};
+
+use std::fmt;
+use std::marker::PhantomData;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// encode returns the lower case hex string of data
+pub fn encode(data: &[u8]) -> String {
+    let mut s = String::with_capacity(data.len() * 2);
+    for b in data {
+        s.push(HEX_DIGITS[(b >> 4) as usize] as char);
+        s.push(HEX_DIGITS[(b & 0xf) as usize] as char);
+    }
+    s
+}
+
+/// decode parses a hex string, in lower or upper case, to bytes
+pub fn decode(s: &str) -> Option<Vec<u8>> {
+    fn digit(c: u8) -> Option<u8> {
+        match c {
+            b'0'..=b'9' => Some(c - b'0'),
+            b'a'..=b'f' => Some(c - b'a' + 10),
+            b'A'..=b'F' => Some(c - b'A' + 10),
+            _ => None,
+        }
+    }
+
+    let s = s.as_bytes();
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    s.chunks_exact(2)
+        .map(|pair| Some((digit(pair[0])? << 4) | digit(pair[1])?))
+        .collect()
+}
+
+pub fn serialize<T, S>(data: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: AsRef<[u8]>,
+    S: Serializer,
+{
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&encode(data.as_ref()))
+    } else {
+        serializer.serialize_bytes(data.as_ref())
+    }
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: From<Vec<u8>>,
+    D: Deserializer<'de>,
+{
+    struct HexVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: From<Vec<u8>>> Visitor<'de> for HexVisitor<T> {
+        type Value = T;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a hex string or bytes")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+            decode(v)
+                .map(T::from)
+                .ok_or_else(|| E::invalid_value(de::Unexpected::Str(v), &self))
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<T, E> {
+            Ok(T::from(v.to_vec()))
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<T, E> {
+            Ok(T::from(v))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<T, A::Error> {
+            let mut v = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(b) = seq.next_element()? {
+                v.push(b);
+            }
+            Ok(T::from(v))
+        }
+    }
+
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_str(HexVisitor(PhantomData))
+    } else {
+        deserializer.deserialize_bytes(HexVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hex_encode_decode() {
+        assert_eq!(encode(&[]), "");
+        assert_eq!(encode(&[0x00, 0x7f, 0xab, 0xff]), "007fabff");
+        assert_eq!(decode("007fabff"), Some(vec![0x00, 0x7f, 0xab, 0xff]));
+        assert_eq!(decode("007FABFF"), Some(vec![0x00, 0x7f, 0xab, 0xff]));
+        assert_eq!(decode(""), Some(vec![]));
+        assert_eq!(decode("abc"), None);
+        assert_eq!(decode("zz"), None);
+    }
+}
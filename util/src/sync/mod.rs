@@ -1,3 +1,11 @@
+#[cfg(all(test, feature = "tokio"))]
+mod sync_test;
+
+#[cfg(feature = "tokio")]
+mod task_group;
+#[cfg(feature = "tokio")]
+pub use task_group::TaskGroup;
+
 use std::{ops, sync};
 
 /// A synchronous mutual exclusion primitive useful for protecting shared data.
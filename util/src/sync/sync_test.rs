@@ -0,0 +1,101 @@
+use super::*;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio::time::{sleep, timeout, Duration};
+
+#[tokio::test]
+async fn test_task_group_wait() {
+    let tasks = TaskGroup::new();
+    assert!(tasks.is_empty());
+
+    let (done_tx, done_rx) = watch::channel(false);
+    let finished = Arc::new(AtomicUsize::new(0));
+    for _ in 0..4 {
+        let mut done_rx = done_rx.clone();
+        let finished = Arc::clone(&finished);
+        tasks.spawn(async move {
+            while !*done_rx.borrow_and_update() {
+                let _ = done_rx.changed().await;
+            }
+            finished.fetch_add(1, Ordering::SeqCst);
+        });
+    }
+    assert_eq!(tasks.len(), 4);
+
+    assert!(
+        timeout(Duration::from_millis(50), tasks.wait())
+            .await
+            .is_err(),
+        "wait returned while tasks are running"
+    );
+
+    done_tx.send(true).unwrap();
+    assert_eq!(tasks.close(Duration::from_secs(5)).await, 0);
+    assert!(tasks.is_empty());
+    assert_eq!(finished.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn test_task_group_close_cancels() {
+    let tasks = TaskGroup::new();
+
+    let finished = Arc::new(AtomicUsize::new(0));
+    for i in 0..3u64 {
+        let finished = Arc::clone(&finished);
+        tasks.spawn(async move {
+            sleep(Duration::from_millis(10 + i * 3600 * 1000)).await;
+            finished.fetch_add(1, Ordering::SeqCst);
+        });
+    }
+
+    // The task sleeping 10ms finishes, the others are cancelled
+    let cancelled = timeout(
+        Duration::from_secs(5),
+        tasks.close(Duration::from_millis(200)),
+    )
+    .await
+    .expect("close didn't return");
+    assert_eq!(cancelled, 2);
+    assert!(tasks.is_empty());
+    assert!(tasks.is_cancelled());
+    assert_eq!(finished.load(Ordering::SeqCst), 1);
+
+    // Tasks spawned after the group was closed don't run
+    let finished2 = Arc::clone(&finished);
+    tasks.spawn(async move {
+        finished2.fetch_add(1, Ordering::SeqCst);
+    });
+    tasks.wait().await;
+    assert_eq!(finished.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_task_group_close_from_own_task() {
+    let tasks = TaskGroup::new();
+
+    tasks.spawn(async {
+        sleep(Duration::from_secs(3600)).await;
+    });
+
+    // A task closing its own group doesn't wait for, nor cancel, itself
+    let (result_tx, result_rx) = tokio::sync::oneshot::channel();
+    let tasks2 = tasks.clone();
+    tasks.spawn(async move {
+        let cancelled = tasks2.close(Duration::from_millis(50)).await;
+        assert_eq!(tasks2.len(), 1);
+        let _ = result_tx.send(cancelled);
+    });
+
+    let cancelled = timeout(Duration::from_secs(5), result_rx)
+        .await
+        .expect("close from a task of the group didn't return")
+        .unwrap();
+    assert_eq!(cancelled, 1);
+
+    timeout(Duration::from_secs(5), tasks.wait())
+        .await
+        .expect("tasks didn't finish");
+    assert!(tasks.is_empty());
+}
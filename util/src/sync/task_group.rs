@@ -0,0 +1,173 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio::time::Duration;
+
+static NEXT_GROUP_ID: AtomicU64 = AtomicU64::new(1);
+
+tokio::task_local! {
+    /// The group and task ids of a task spawned by a TaskGroup
+    static CURRENT_TASK: (u64, u64);
+}
+
+/// TaskGroup tracks the tasks spawned on behalf of an owner, e.g. a connection, so that closing
+/// the owner can wait for all of them to finish and cancel the ones that don't finish in time.
+///
+/// A task of the group which waits for the group itself, e.g. a callback closing the owner, is
+/// neither waited for nor cancelled by that wait.
+#[derive(Clone)]
+pub struct TaskGroup {
+    inner: Arc<TaskGroupInner>,
+}
+
+struct TaskGroupInner {
+    id: u64,
+    next_task_id: AtomicU64,
+    running: watch::Sender<usize>,
+    cancel: watch::Sender<Cancel>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Cancel {
+    None,
+    /// All tasks are cancelled, except the task that cancelled them
+    All {
+        except: Option<u64>,
+    },
+}
+
+/// RunningGuard counts a task of the group as running until it is dropped
+struct RunningGuard(Arc<TaskGroupInner>);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.0.running.send_modify(|n| *n -= 1);
+    }
+}
+
+impl Default for TaskGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaskGroup {
+    pub fn new() -> Self {
+        let (running, _) = watch::channel(0);
+        let (cancel, _) = watch::channel(Cancel::None);
+
+        TaskGroup {
+            inner: Arc::new(TaskGroupInner {
+                id: NEXT_GROUP_ID.fetch_add(1, Ordering::SeqCst),
+                next_task_id: AtomicU64::new(1),
+                running,
+                cancel,
+            }),
+        }
+    }
+
+    /// spawn runs future on a new task of the group, discarding its output. Once the group is
+    /// cancelled, the future is dropped at its next await point, or right away if the group
+    /// already is cancelled.
+    pub fn spawn<F>(&self, future: F)
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.inner.running.send_modify(|n| *n += 1);
+        let guard = RunningGuard(Arc::clone(&self.inner));
+
+        let group_id = self.inner.id;
+        let task_id = self.inner.next_task_id.fetch_add(1, Ordering::SeqCst);
+        let mut cancel_rx = self.inner.cancel.subscribe();
+        tokio::spawn(CURRENT_TASK.scope((group_id, task_id), async move {
+            let _guard = guard;
+            tokio::select! {
+                biased;
+                _ = TaskGroup::cancelled(&mut cancel_rx, task_id) => {
+                    log::trace!("task {} of group {} cancelled", task_id, group_id);
+                }
+                _ = future => {}
+            }
+        }));
+    }
+
+    /// len returns the number of running tasks
+    pub fn len(&self) -> usize {
+        *self.inner.running.borrow()
+    }
+
+    /// is_empty returns true if no task is running
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// is_cancelled returns true once the group has been cancelled or closed
+    pub fn is_cancelled(&self) -> bool {
+        *self.inner.cancel.borrow() != Cancel::None
+    }
+
+    /// cancel cancels all running tasks and the tasks spawned later on.
+    pub fn cancel(&self) {
+        let except = self.current_task();
+        self.inner.cancel.send_modify(|cancel| {
+            if *cancel == Cancel::None {
+                *cancel = Cancel::All { except };
+            }
+        });
+    }
+
+    /// wait waits until all tasks have finished.
+    pub async fn wait(&self) {
+        let own = self.current_task().is_some() as usize;
+        let mut running = self.inner.running.subscribe();
+        while *running.borrow_and_update() > own {
+            if running.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// close waits up to timeout for the running tasks to finish, cancels the ones left and
+    /// waits for them to stop. It returns the number of tasks that had to be cancelled.
+    pub async fn close(&self, timeout: Duration) -> usize {
+        let cancelled = match tokio::time::timeout(timeout, self.wait()).await {
+            Ok(()) => 0,
+            Err(_) => self
+                .len()
+                .saturating_sub(self.current_task().is_some() as usize),
+        };
+        self.cancel();
+        self.wait().await;
+
+        cancelled
+    }
+
+    /// current_task returns the id of the calling task if it belongs to this group
+    fn current_task(&self) -> Option<u64> {
+        CURRENT_TASK
+            .try_with(|(group_id, task_id)| {
+                if *group_id == self.inner.id {
+                    Some(*task_id)
+                } else {
+                    None
+                }
+            })
+            .ok()
+            .flatten()
+    }
+
+    async fn cancelled(cancel_rx: &mut watch::Receiver<Cancel>, task_id: u64) {
+        loop {
+            if let Cancel::All { except } = *cancel_rx.borrow_and_update() {
+                if except != Some(task_id) {
+                    return;
+                }
+            }
+            if cancel_rx.changed().await.is_err() {
+                return std::future::pending().await;
+            }
+        }
+    }
+}
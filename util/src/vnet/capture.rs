@@ -0,0 +1,284 @@
+#[cfg(test)]
+mod capture_test;
+
+use crate::sync::Mutex as SyncMutex;
+use crate::vnet::chunk::Chunk;
+use crate::vnet::net::UDP_STR;
+use crate::vnet::router::ChunkFilterFn;
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4; // microsecond timestamps
+const PCAP_SNAPLEN: u32 = 65535;
+const PCAP_LINKTYPE_RAW: u32 = 101; // packets start with an IPv4 or IPv6 header
+
+const IP_PROTOCOL_UDP: u8 = 17;
+
+/// CapturedPacket is a copy of a chunk seen by a capture hook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedPacket {
+    /// when the chunk was forwarded by the router, or sent or received by the net
+    pub timestamp: SystemTime,
+    /// "udp" or "tcp"
+    pub network: String,
+    pub source_addr: SocketAddr,
+    pub destination_addr: SocketAddr,
+    /// the TTL left when the chunk was captured
+    pub ttl: u8,
+    pub payload: Vec<u8>,
+}
+
+impl CapturedPacket {
+    pub(crate) fn new(c: &(dyn Chunk + Send + Sync)) -> Self {
+        CapturedPacket {
+            timestamp: SystemTime::now(),
+            network: c.network(),
+            source_addr: c.source_addr(),
+            destination_addr: c.destination_addr(),
+            ttl: c.ttl(),
+            payload: c.user_data(),
+        }
+    }
+}
+
+/// CaptureHandlerFn is called with every chunk a capture hook sees. It runs while the router or
+/// net holds its lock, so it must neither block nor call back into the virtual network.
+pub type CaptureHandlerFn = Box<dyn Fn(&CapturedPacket) + Send + Sync>;
+
+struct CaptureHook {
+    filter: Option<ChunkFilterFn>,
+    handler: CaptureHandlerFn,
+}
+
+/// CaptureHooks are the capture hooks of a router or net. They cost a length check per chunk
+/// while none is added.
+#[derive(Default)]
+pub(crate) struct CaptureHooks {
+    hooks: Vec<CaptureHook>,
+}
+
+impl CaptureHooks {
+    pub(crate) fn add(&mut self, filter: Option<ChunkFilterFn>, handler: CaptureHandlerFn) {
+        self.hooks.push(CaptureHook { filter, handler });
+    }
+
+    pub(crate) fn capture(&self, c: &(dyn Chunk + Send + Sync)) {
+        if self.hooks.is_empty() {
+            return;
+        }
+
+        let mut packet = None;
+        for hook in &self.hooks {
+            if let Some(filter) = &hook.filter {
+                if !filter(c) {
+                    continue;
+                }
+            }
+            let packet = packet.get_or_insert_with(|| CapturedPacket::new(c));
+            (hook.handler)(packet);
+        }
+    }
+}
+
+/// Capture keeps the latest packets handed to its handler in a ring buffer, e.g. to assert
+/// what crossed a link in tests, or to write them to a pcap file for offline inspection.
+pub struct Capture {
+    capacity: usize,
+    packets: SyncMutex<VecDeque<CapturedPacket>>,
+    overwritten: AtomicU64,
+}
+
+impl Capture {
+    /// new creates a capture keeping at most capacity packets, older packets are overwritten.
+    pub fn new(capacity: usize) -> Arc<Self> {
+        Arc::new(Capture {
+            capacity,
+            packets: SyncMutex::new(VecDeque::with_capacity(capacity)),
+            overwritten: AtomicU64::new(0),
+        })
+    }
+
+    /// handler returns a CaptureHandlerFn storing the packets in this capture, to be added with
+    /// `Router::add_capture_handler` or `Net::add_capture_handler`.
+    pub fn handler(self: &Arc<Self>) -> CaptureHandlerFn {
+        let capture = Arc::clone(self);
+        Box::new(move |packet: &CapturedPacket| capture.push(packet.clone()))
+    }
+
+    fn push(&self, packet: CapturedPacket) {
+        if self.capacity == 0 {
+            self.overwritten.fetch_add(1, Ordering::SeqCst);
+            return;
+        }
+
+        let mut packets = self.packets.lock();
+        if packets.len() == self.capacity {
+            packets.pop_front();
+            self.overwritten.fetch_add(1, Ordering::SeqCst);
+        }
+        packets.push_back(packet);
+    }
+
+    /// packets returns the captured packets, oldest first.
+    pub fn packets(&self) -> Vec<CapturedPacket> {
+        self.packets.lock().iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.packets.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.lock().is_empty()
+    }
+
+    /// overwritten returns the number of packets dropped because the capture was full.
+    pub fn overwritten(&self) -> u64 {
+        self.overwritten.load(Ordering::SeqCst)
+    }
+
+    /// clear drops the captured packets.
+    pub fn clear(&self) {
+        self.packets.lock().clear();
+    }
+
+    /// write_pcap writes the captured packets in the pcap format with raw IP link type. UDP
+    /// packets get synthesized IP and UDP headers, packets of other networks are left out.
+    pub fn write_pcap<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let packets = self.packets();
+
+        w.write_all(&PCAP_MAGIC.to_le_bytes())?;
+        w.write_all(&2u16.to_le_bytes())?; // version major
+        w.write_all(&4u16.to_le_bytes())?; // version minor
+        w.write_all(&0i32.to_le_bytes())?; // thiszone
+        w.write_all(&0u32.to_le_bytes())?; // sigfigs
+        w.write_all(&PCAP_SNAPLEN.to_le_bytes())?;
+        w.write_all(&PCAP_LINKTYPE_RAW.to_le_bytes())?;
+
+        for packet in &packets {
+            let data = match ip_udp_packet(packet) {
+                Some(data) => data,
+                None => continue,
+            };
+            let ts = packet
+                .timestamp
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            let orig_len = data.len() as u32;
+            let incl_len = std::cmp::min(orig_len, PCAP_SNAPLEN);
+
+            w.write_all(&(ts.as_secs() as u32).to_le_bytes())?;
+            w.write_all(&ts.subsec_micros().to_le_bytes())?;
+            w.write_all(&incl_len.to_le_bytes())?;
+            w.write_all(&orig_len.to_le_bytes())?;
+            w.write_all(&data[..incl_len as usize])?;
+        }
+
+        Ok(())
+    }
+}
+
+// ip_udp_packet returns the IP packet carrying packet, None if it isn't UDP.
+pub(crate) fn ip_udp_packet(packet: &CapturedPacket) -> Option<Vec<u8>> {
+    if packet.network != UDP_STR {
+        return None;
+    }
+
+    let udp_len = 8 + packet.payload.len();
+    if udp_len > u16::MAX as usize {
+        return None;
+    }
+    let mut udp = Vec::with_capacity(udp_len);
+    udp.extend_from_slice(&packet.source_addr.port().to_be_bytes());
+    udp.extend_from_slice(&packet.destination_addr.port().to_be_bytes());
+    udp.extend_from_slice(&(udp_len as u16).to_be_bytes());
+    udp.extend_from_slice(&[0, 0]); // checksum
+    udp.extend_from_slice(&packet.payload);
+
+    let mut ip = match (packet.source_addr.ip(), packet.destination_addr.ip()) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            let total_len = 20 + udp_len;
+            if total_len > u16::MAX as usize {
+                return None;
+            }
+
+            let mut ip = Vec::with_capacity(total_len);
+            ip.push(0x45); // version 4, 5 words of header
+            ip.push(0); // DSCP, ECN
+            ip.extend_from_slice(&(total_len as u16).to_be_bytes());
+            ip.extend_from_slice(&[0, 0, 0x40, 0]); // identification, don't fragment
+            ip.push(packet.ttl);
+            ip.push(IP_PROTOCOL_UDP);
+            ip.extend_from_slice(&[0, 0]); // header checksum
+            ip.extend_from_slice(&src.octets());
+            ip.extend_from_slice(&dst.octets());
+            let checksum = internet_checksum(0, &ip);
+            ip[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+            let mut pseudo_header = Vec::with_capacity(12);
+            pseudo_header.extend_from_slice(&src.octets());
+            pseudo_header.extend_from_slice(&dst.octets());
+            pseudo_header.extend_from_slice(&[0, IP_PROTOCOL_UDP]);
+            pseudo_header.extend_from_slice(&(udp_len as u16).to_be_bytes());
+            set_udp_checksum(&mut udp, &pseudo_header);
+
+            ip
+        }
+        (IpAddr::V6(src), IpAddr::V6(dst)) => {
+            let mut ip = Vec::with_capacity(40 + udp_len);
+            ip.extend_from_slice(&[0x60, 0, 0, 0]); // version 6, traffic class, flow label
+            ip.extend_from_slice(&(udp_len as u16).to_be_bytes());
+            ip.push(IP_PROTOCOL_UDP);
+            ip.push(packet.ttl); // hop limit
+            ip.extend_from_slice(&src.octets());
+            ip.extend_from_slice(&dst.octets());
+
+            let mut pseudo_header = Vec::with_capacity(40);
+            pseudo_header.extend_from_slice(&src.octets());
+            pseudo_header.extend_from_slice(&dst.octets());
+            pseudo_header.extend_from_slice(&(udp_len as u32).to_be_bytes());
+            pseudo_header.extend_from_slice(&[0, 0, 0, IP_PROTOCOL_UDP]);
+            set_udp_checksum(&mut udp, &pseudo_header);
+
+            ip
+        }
+        _ => return None,
+    };
+
+    ip.extend_from_slice(&udp);
+    Some(ip)
+}
+
+fn set_udp_checksum(udp: &mut [u8], pseudo_header: &[u8]) {
+    let sum = ones_complement_sum(ones_complement_sum(0, pseudo_header), udp);
+    let checksum = match !sum {
+        0 => 0xffff, // zero means no checksum in UDP
+        checksum => checksum,
+    };
+    udp[6..8].copy_from_slice(&checksum.to_be_bytes());
+}
+
+pub(crate) fn internet_checksum(initial: u16, data: &[u8]) -> u16 {
+    !ones_complement_sum(initial, data)
+}
+
+fn ones_complement_sum(initial: u16, data: &[u8]) -> u16 {
+    let mut sum = initial as u32;
+    for word in data.chunks(2) {
+        let word = if word.len() == 2 {
+            u16::from_be_bytes([word[0], word[1]])
+        } else {
+            u16::from_be_bytes([word[0], 0])
+        };
+        sum += word as u32;
+    }
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum as u16
+}
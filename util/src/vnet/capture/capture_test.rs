@@ -0,0 +1,134 @@
+use super::*;
+use crate::vnet::chunk::{ChunkUdp, DEFAULT_TTL};
+
+use std::str::FromStr;
+use std::time::Duration;
+
+fn new_packet(src: &str, dst: &str, payload: &[u8]) -> CapturedPacket {
+    CapturedPacket {
+        timestamp: UNIX_EPOCH + Duration::from_micros(1_500_000_250),
+        network: UDP_STR.to_owned(),
+        source_addr: SocketAddr::from_str(src).unwrap(),
+        destination_addr: SocketAddr::from_str(dst).unwrap(),
+        ttl: 63,
+        payload: payload.to_vec(),
+    }
+}
+
+#[test]
+fn test_capture_hooks_filter() {
+    let all = Capture::new(10);
+    let to_port_5678 = Capture::new(10);
+
+    let mut hooks = CaptureHooks::default();
+    hooks.add(None, all.handler());
+    hooks.add(
+        Some(Box::new(|c: &(dyn Chunk + Send + Sync)| {
+            c.destination_addr().port() == 5678
+        })),
+        to_port_5678.handler(),
+    );
+
+    for dst in ["1.2.3.4:5678", "1.2.3.4:9999", "1.2.3.5:5678"] {
+        let mut c = ChunkUdp::new(
+            SocketAddr::from_str("192.168.0.2:1234").unwrap(),
+            SocketAddr::from_str(dst).unwrap(),
+        );
+        c.user_data = b"Hello".to_vec();
+        hooks.capture(&c);
+    }
+
+    assert_eq!(3, all.len(), "should match");
+    let packets = to_port_5678.packets();
+    assert_eq!(2, packets.len(), "should match");
+    assert_eq!("1.2.3.4:5678", packets[0].destination_addr.to_string());
+    assert_eq!("1.2.3.5:5678", packets[1].destination_addr.to_string());
+    assert_eq!("192.168.0.2:1234", packets[0].source_addr.to_string());
+    assert_eq!(UDP_STR, packets[0].network);
+    assert_eq!(DEFAULT_TTL, packets[0].ttl);
+    assert_eq!(b"Hello".to_vec(), packets[0].payload);
+}
+
+#[test]
+fn test_capture_ring_buffer() {
+    let capture = Capture::new(3);
+    assert!(capture.is_empty(), "should be empty");
+
+    let handler = capture.handler();
+    for i in 0..5u8 {
+        handler(&new_packet("10.0.0.1:1000", "10.0.0.2:2000", &[i]));
+    }
+
+    let payloads: Vec<Vec<u8>> = capture.packets().into_iter().map(|p| p.payload).collect();
+    assert_eq!(
+        vec![vec![2], vec![3], vec![4]],
+        payloads,
+        "should keep the latest"
+    );
+    assert_eq!(2, capture.overwritten(), "should match");
+
+    capture.clear();
+    assert!(capture.is_empty(), "should be empty");
+    assert_eq!(2, capture.overwritten(), "should match");
+}
+
+#[test]
+fn test_capture_write_pcap() -> io::Result<()> {
+    let capture = Capture::new(10);
+    let handler = capture.handler();
+    handler(&new_packet("10.0.0.1:1000", "10.0.0.2:2000", b"abc"));
+    handler(&CapturedPacket {
+        network: "tcp".to_owned(),
+        ..new_packet("10.0.0.1:1000", "10.0.0.2:2000", b"left out")
+    });
+    handler(&new_packet("[fe80::1]:1000", "[fe80::2]:2000", b"abcd"));
+
+    let mut pcap = vec![];
+    capture.write_pcap(&mut pcap)?;
+
+    // global header
+    assert_eq!(&[0xd4, 0xc3, 0xb2, 0xa1], &pcap[0..4], "magic");
+    assert_eq!(&[2, 0, 4, 0], &pcap[4..8], "version");
+    assert_eq!(&101u32.to_le_bytes(), &pcap[20..24], "link type");
+
+    // IPv4 record
+    let record = &pcap[24..];
+    assert_eq!(&1500u32.to_le_bytes(), &record[0..4], "seconds");
+    assert_eq!(&250u32.to_le_bytes(), &record[4..8], "microseconds");
+    assert_eq!(&31u32.to_le_bytes(), &record[8..12], "captured length");
+    assert_eq!(&31u32.to_le_bytes(), &record[12..16], "length");
+    let ip = &record[16..47];
+    assert_eq!(0x45, ip[0], "version and header length");
+    assert_eq!(&[0, 31], &ip[2..4], "total length");
+    assert_eq!(63, ip[8], "ttl");
+    assert_eq!(IP_PROTOCOL_UDP, ip[9], "protocol");
+    assert_eq!(0, internet_checksum(0, &ip[..20]), "header checksum");
+    assert_eq!(&[10, 0, 0, 1, 10, 0, 0, 2], &ip[12..20], "addresses");
+    let udp = &ip[20..];
+    assert_eq!(
+        &[0x03, 0xe8, 0x07, 0xd0, 0, 11],
+        &udp[..6],
+        "ports and length"
+    );
+    let mut pseudo_header = ip[12..20].to_vec();
+    pseudo_header.extend_from_slice(&[0, IP_PROTOCOL_UDP, 0, 11]);
+    assert_eq!(
+        0,
+        internet_checksum(!internet_checksum(0, &pseudo_header), udp),
+        "udp checksum"
+    );
+    assert_eq!(b"abc", &udp[8..], "payload");
+
+    // the TCP packet is left out, IPv6 record follows
+    let record = &record[47..];
+    assert_eq!(&52u32.to_le_bytes(), &record[8..12], "captured length");
+    let ip = &record[16..];
+    assert_eq!(52, ip.len(), "should be the last record");
+    assert_eq!(0x60, ip[0], "version");
+    assert_eq!(&[0, 12], &ip[4..6], "payload length");
+    assert_eq!(IP_PROTOCOL_UDP, ip[6], "next header");
+    assert_eq!(63, ip[7], "hop limit");
+    assert_eq!(b"abcd", &ip[48..], "payload");
+
+    Ok(())
+}
@@ -11,6 +11,9 @@ use std::str::FromStr;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::SystemTime;
 
+/// DEFAULT_TTL is the TTL of chunks sent by sockets whose TTL wasn't changed.
+pub const DEFAULT_TTL: u8 = 64;
+
 lazy_static! {
     static ref TAG_CTR: AtomicU64 = AtomicU64::new(0);
 }
@@ -38,15 +41,23 @@ fn assign_chunk_tag() -> String {
     base36(n)
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
-pub(crate) struct TcpFlag(pub(crate) u8);
+/// TcpFlag are the control bits of a TCP chunk.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct TcpFlag(pub(crate) u8);
 
-pub(crate) const TCP_FLAG_ZERO: TcpFlag = TcpFlag(0x00);
-pub(crate) const TCP_FLAG_FIN: TcpFlag = TcpFlag(0x01);
-pub(crate) const TCP_FLAG_SYN: TcpFlag = TcpFlag(0x02);
-pub(crate) const TCP_FLAG_RST: TcpFlag = TcpFlag(0x04);
-pub(crate) const TCP_FLAG_PSH: TcpFlag = TcpFlag(0x08);
-pub(crate) const TCP_FLAG_ACK: TcpFlag = TcpFlag(0x10);
+pub const TCP_FLAG_ZERO: TcpFlag = TcpFlag(0x00);
+pub const TCP_FLAG_FIN: TcpFlag = TcpFlag(0x01);
+pub const TCP_FLAG_SYN: TcpFlag = TcpFlag(0x02);
+pub const TCP_FLAG_RST: TcpFlag = TcpFlag(0x04);
+pub const TCP_FLAG_PSH: TcpFlag = TcpFlag(0x08);
+pub const TCP_FLAG_ACK: TcpFlag = TcpFlag(0x10);
+
+impl TcpFlag {
+    /// contains returns true if all the bits of other are set.
+    pub fn contains(self, other: TcpFlag) -> bool {
+        self & other == other
+    }
+}
 
 impl BitOr for TcpFlag {
     type Output = Self;
@@ -97,12 +108,17 @@ pub trait Chunk: fmt::Display + fmt::Debug {
     fn get_destination_ip(&self) -> IpAddr; // used by router
     fn set_source_addr(&mut self, address: &str) -> Result<()>; // used by nat
     fn set_destination_addr(&mut self, address: &str) -> Result<()>; // used by nat
+    fn ttl(&self) -> u8; // decremented by routers
+    fn set_ttl(&mut self, ttl: u8);
 
     fn source_addr(&self) -> SocketAddr;
     fn destination_addr(&self) -> SocketAddr;
     fn user_data(&self) -> Vec<u8>;
     fn tag(&self) -> String;
     fn network(&self) -> String; // returns "udp" or "tcp"
+    fn tcp_flags(&self) -> TcpFlag {
+        TCP_FLAG_ZERO // control bits, only TCP chunks have them
+    }
     fn clone_to(&self) -> Box<dyn Chunk + Send + Sync>;
 }
 
@@ -111,6 +127,7 @@ pub(crate) struct ChunkIp {
     pub(crate) timestamp: SystemTime,
     pub(crate) source_ip: IpAddr,
     pub(crate) destination_ip: IpAddr,
+    pub(crate) ttl: u8,
     pub(crate) tag: String,
 }
 
@@ -197,6 +214,7 @@ impl Chunk for ChunkUdp {
                 timestamp: self.chunk_ip.timestamp,
                 source_ip: self.chunk_ip.source_ip,
                 destination_ip: self.chunk_ip.destination_ip,
+                ttl: self.chunk_ip.ttl,
                 tag: self.chunk_ip.tag.clone(),
             },
             source_port: self.source_port,
@@ -222,6 +240,14 @@ impl Chunk for ChunkUdp {
         self.destination_port = addr.port();
         Ok(())
     }
+
+    fn ttl(&self) -> u8 {
+        self.chunk_ip.ttl
+    }
+
+    fn set_ttl(&mut self, ttl: u8) {
+        self.chunk_ip.ttl = ttl;
+    }
 }
 
 impl ChunkUdp {
@@ -231,6 +257,7 @@ impl ChunkUdp {
                 timestamp: SystemTime::now(),
                 source_ip: src_addr.ip(),
                 destination_ip: dst_addr.ip(),
+                ttl: DEFAULT_TTL,
                 tag: assign_chunk_tag(),
             },
             source_port: src_addr.port(),
@@ -242,13 +269,13 @@ impl ChunkUdp {
 
 #[derive(PartialEq, Debug)]
 pub(crate) struct ChunkTcp {
-    chunk_ip: ChunkIp,
-    source_port: u16,
-    destination_port: u16,
-    flags: TcpFlag, // control bits
-    user_data: Vec<u8>, // only with PSH flag
-                    // seq             :u32,  // always starts with 0
-                    // ack             :u32,  // always starts with 0
+    pub(crate) chunk_ip: ChunkIp,
+    pub(crate) source_port: u16,
+    pub(crate) destination_port: u16,
+    pub(crate) flags: TcpFlag, // control bits
+    pub(crate) user_data: Vec<u8>, // only with PSH flag
+                               // seq             :u32,  // always starts with 0
+                               // ack             :u32,  // always starts with 0
 }
 
 impl fmt::Display for ChunkTcp {
@@ -304,6 +331,7 @@ impl Chunk for ChunkTcp {
                 timestamp: self.chunk_ip.timestamp,
                 source_ip: self.chunk_ip.source_ip,
                 destination_ip: self.chunk_ip.destination_ip,
+                ttl: self.chunk_ip.ttl,
                 tag: self.chunk_ip.tag.clone(),
             },
             source_port: self.source_port,
@@ -314,7 +342,11 @@ impl Chunk for ChunkTcp {
     }
 
     fn network(&self) -> String {
-        "tcp".to_owned()
+        TCP_STR.to_owned()
+    }
+
+    fn tcp_flags(&self) -> TcpFlag {
+        self.flags
     }
 
     fn set_source_addr(&mut self, address: &str) -> Result<()> {
@@ -330,6 +362,14 @@ impl Chunk for ChunkTcp {
         self.destination_port = addr.port();
         Ok(())
     }
+
+    fn ttl(&self) -> u8 {
+        self.chunk_ip.ttl
+    }
+
+    fn set_ttl(&mut self, ttl: u8) {
+        self.chunk_ip.ttl = ttl;
+    }
 }
 
 impl ChunkTcp {
@@ -339,6 +379,7 @@ impl ChunkTcp {
                 timestamp: SystemTime::now(),
                 source_ip: src_addr.ip(),
                 destination_ip: dst_addr.ip(),
+                ttl: DEFAULT_TTL,
                 tag: assign_chunk_tag(),
             },
             source_port: src_addr.port(),
@@ -5,12 +5,13 @@ use crate::conn::Conn;
 use crate::error::*;
 use crate::sync::RwLock;
 use crate::vnet::chunk::{Chunk, ChunkUdp};
+use crate::vnet::net::{SocketOptions, SocketStats};
 
 use std::net::{IpAddr, SocketAddr};
 use tokio::sync::{mpsc, Mutex};
 
 use async_trait::async_trait;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
 use std::sync::Arc;
 
 const MAX_READ_QUEUE_SIZE: usize = 1024;
@@ -34,6 +35,10 @@ pub(crate) struct UdpConn {
     read_ch_rx: Mutex<mpsc::Receiver<Box<dyn Chunk + Send + Sync>>>,
     closed: AtomicBool,
     obs: Arc<Mutex<dyn ConnObserver + Send + Sync>>,
+    ttl: AtomicU8,
+    recv_buffer_size: AtomicUsize,
+    recv_buffered: AtomicUsize,
+    recv_buffer_drops: AtomicU64,
 }
 
 impl UdpConn {
@@ -51,12 +56,58 @@ impl UdpConn {
             read_ch_rx: Mutex::new(read_ch_rx),
             closed: AtomicBool::new(false),
             obs,
+            ttl: AtomicU8::new(SocketOptions::default().ttl),
+            recv_buffer_size: AtomicUsize::new(0),
+            recv_buffered: AtomicUsize::new(0),
+            recv_buffer_drops: AtomicU64::new(0),
         }
     }
 
     pub(crate) fn get_inbound_ch(&self) -> Arc<Mutex<Option<ChunkChTx>>> {
         Arc::clone(&self.read_ch_tx)
     }
+
+    pub(crate) fn set_options(&self, options: SocketOptions) {
+        self.ttl.store(options.ttl, Ordering::SeqCst);
+        self.recv_buffer_size
+            .store(options.recv_buffer_size, Ordering::SeqCst);
+    }
+
+    pub(crate) fn stats(&self) -> SocketStats {
+        SocketStats {
+            recv_buffered: self.recv_buffered.load(Ordering::SeqCst),
+            recv_buffer_drops: self.recv_buffer_drops.load(Ordering::SeqCst),
+        }
+    }
+
+    /// on_inbound_chunk queues a chunk received by this connection. With a receive buffer size
+    /// set, the chunk is dropped if its payload doesn't fit into the receive buffer.
+    pub(crate) async fn on_inbound_chunk(&self, c: Box<dyn Chunk + Send + Sync>) {
+        let recv_buffer_size = self.recv_buffer_size.load(Ordering::SeqCst);
+        if recv_buffer_size == 0 {
+            let ch_tx = self.read_ch_tx.lock().await;
+            if let Some(tx) = &*ch_tx {
+                let _ = tx.send(c).await;
+            }
+            return;
+        }
+
+        let n = c.user_data().len();
+        let buffered = self.recv_buffered.fetch_add(n, Ordering::SeqCst);
+        let queued = if buffered + n > recv_buffer_size {
+            false
+        } else {
+            let ch_tx = self.read_ch_tx.lock().await;
+            match &*ch_tx {
+                Some(tx) => tx.try_send(c).is_ok(),
+                None => false,
+            }
+        };
+        if !queued {
+            self.recv_buffered.fetch_sub(n, Ordering::SeqCst);
+            self.recv_buffer_drops.fetch_add(1, Ordering::SeqCst);
+        }
+    }
 }
 
 #[async_trait]
@@ -83,6 +134,11 @@ impl Conn for UdpConn {
         let rem_addr = *self.rem_addr.read();
         while let Some(chunk) = read_ch.recv().await {
             let user_data = chunk.user_data();
+            let _ =
+                self.recv_buffered
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |buffered| {
+                        Some(buffered.saturating_sub(user_data.len()))
+                    });
             let n = std::cmp::min(buf.len(), user_data.len());
             buf[..n].copy_from_slice(&user_data[..n]);
             let addr = chunk.source_addr();
@@ -122,6 +178,7 @@ impl Conn for UdpConn {
         let src_addr = SocketAddr::new(src_ip, self.loc_addr.port());
 
         let mut chunk = ChunkUdp::new(src_addr, target);
+        chunk.chunk_ip.ttl = self.ttl.load(Ordering::SeqCst);
         chunk.user_data = buf.to_vec();
         {
             let c: Box<dyn Chunk + Send + Sync> = Box::new(chunk);
@@ -1,3 +1,4 @@
+pub mod capture;
 pub mod chunk;
 pub(crate) mod chunk_queue;
 pub(crate) mod conn;
@@ -7,3 +8,4 @@ pub mod nat;
 pub mod net;
 pub(crate) mod resolver;
 pub mod router;
+pub mod tcp;
@@ -3,7 +3,7 @@ mod nat_test;
 
 use crate::error::*;
 use crate::vnet::chunk::Chunk;
-use crate::vnet::net::UDP_STR;
+use crate::vnet::net::{TCP_STR, UDP_STR};
 
 use std::collections::{HashMap, HashSet};
 use std::net::IpAddr;
@@ -62,11 +62,63 @@ pub struct NatType {
     pub mode: NatMode,
     pub mapping_behavior: EndpointDependencyType,
     pub filtering_behavior: EndpointDependencyType,
-    pub hair_pining: bool,       // Not implemented yet
+    // hair_pining lets hosts behind the NAT reach each other through their mapped addresses
+    pub hair_pining: bool,
     pub port_preservation: bool, // Not implemented yet
     pub mapping_life_time: Duration,
 }
 
+impl NatType {
+    // full_cone returns a NAT with endpoint independent mapping and filtering: once a
+    // mapping exists, anyone can reach the host through it.
+    pub fn full_cone() -> Self {
+        NatType {
+            mapping_behavior: EndpointDependencyType::EndpointIndependent,
+            filtering_behavior: EndpointDependencyType::EndpointIndependent,
+            ..Default::default()
+        }
+    }
+
+    // restricted_cone returns a NAT with endpoint independent mapping that only lets in
+    // packets from addresses the host has sent to.
+    pub fn restricted_cone() -> Self {
+        NatType {
+            mapping_behavior: EndpointDependencyType::EndpointIndependent,
+            filtering_behavior: EndpointDependencyType::EndpointAddrDependent,
+            ..Default::default()
+        }
+    }
+
+    // port_restricted_cone returns a NAT with endpoint independent mapping that only lets in
+    // packets from addresses and ports the host has sent to.
+    pub fn port_restricted_cone() -> Self {
+        NatType {
+            mapping_behavior: EndpointDependencyType::EndpointIndependent,
+            filtering_behavior: EndpointDependencyType::EndpointAddrPortDependent,
+            ..Default::default()
+        }
+    }
+
+    // symmetric returns a NAT that allocates a new mapping for every remote address and port,
+    // so a server reflexive address learned from a STUN server is useless for other peers.
+    pub fn symmetric() -> Self {
+        NatType {
+            mapping_behavior: EndpointDependencyType::EndpointAddrPortDependent,
+            filtering_behavior: EndpointDependencyType::EndpointAddrPortDependent,
+            ..Default::default()
+        }
+    }
+}
+
+// NatMapping is a snapshot of a NAT binding, see Router::nat_mappings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NatMapping {
+    pub proto: String,       // "udp" or "tcp"
+    pub local: String,       // "<local-ip>:<local-port>"
+    pub mapped: String,      // "<mapped-ip>:<mapped-port>"
+    pub expires: SystemTime, // time to expire
+}
+
 #[derive(Default, Debug, Clone)]
 pub(crate) struct NatConfig {
     pub(crate) name: String,
@@ -145,6 +197,10 @@ impl NetworkAddressTranslator {
         })
     }
 
+    pub(crate) fn is_mapped_ip(&self, ip: &IpAddr) -> bool {
+        self.mapped_ips.contains(ip)
+    }
+
     pub(crate) fn get_paired_mapped_ip(&self, loc_ip: &IpAddr) -> Option<&IpAddr> {
         for (i, ip) in self.local_ips.iter().enumerate() {
             if ip == loc_ip {
@@ -169,7 +225,7 @@ impl NetworkAddressTranslator {
     ) -> Result<Option<Box<dyn Chunk + Send + Sync>>> {
         let mut to = from.clone_to();
 
-        if from.network() == UDP_STR {
+        if from.network() == UDP_STR || from.network() == TCP_STR {
             if self.nat_type.mode == NatMode::Nat1To1 {
                 // 1:1 NAT behavior
                 let src_addr = from.source_addr();
@@ -205,7 +261,7 @@ impl NetworkAddressTranslator {
                     }
                 };
 
-                let o_key = format!("udp:{}:{}", from.source_addr(), bound);
+                let o_key = format!("{}:{}:{}", from.network(), from.source_addr(), bound);
                 let name = self.name.clone();
 
                 let m_mapped = if let Some(m) = self.find_outbound_mapping(&o_key).await {
@@ -232,7 +288,7 @@ impl NetworkAddressTranslator {
 
                     let m = if let Some(mapped_ips_first) = self.mapped_ips.first() {
                         Mapping {
-                            proto: "udp".to_owned(),
+                            proto: from.network(),
                             local: from.source_addr().to_string(),
                             bound,
                             mapped: format!("{}:{}", mapped_ips_first, mapped_port),
@@ -250,7 +306,7 @@ impl NetworkAddressTranslator {
                         outbound_map.insert(o_key.clone(), Arc::new(m.clone()));
                     }
 
-                    let i_key = format!("udp:{}", m.mapped);
+                    let i_key = format!("{}:{}", m.proto, m.mapped);
 
                     log::debug!(
                         "[{}] created a new NAT binding oKey={} i_key={}",
@@ -300,7 +356,7 @@ impl NetworkAddressTranslator {
     ) -> Result<Option<Box<dyn Chunk + Send + Sync>>> {
         let mut to = from.clone_to();
 
-        if from.network() == UDP_STR {
+        if from.network() == UDP_STR || from.network() == TCP_STR {
             if self.nat_type.mode == NatMode::Nat1To1 {
                 // 1:1 NAT behavior
                 let dst_addr = from.destination_addr();
@@ -326,7 +382,7 @@ impl NetworkAddressTranslator {
                     }
                 };
 
-                let i_key = format!("udp:{}", from.destination_addr());
+                let i_key = format!("{}:{}", from.network(), from.destination_addr());
                 if let Some(m) = self.find_inbound_mapping(&i_key).await {
                     {
                         let filters = m.filters.lock().await;
@@ -452,6 +508,43 @@ impl NetworkAddressTranslator {
         inbound_map.get(i_key).map(Arc::clone)
     }
 
+    // mappings removes expired mappings and returns the remaining ones
+    pub(crate) async fn mappings(&self) -> Vec<NatMapping> {
+        let now = SystemTime::now();
+        let mut mappings = vec![];
+        let mut expired = vec![];
+        {
+            let inbound_map = self.inbound_map.lock().await;
+            for m in inbound_map.values() {
+                let expires = *m.expires.lock().await;
+                if now.duration_since(expires).is_ok() {
+                    expired.push((
+                        NetworkAddressTranslator::get_inbound_map_key(m),
+                        NetworkAddressTranslator::get_outbound_map_key(m),
+                    ));
+                } else {
+                    mappings.push(NatMapping {
+                        proto: m.proto.clone(),
+                        local: m.local.clone(),
+                        mapped: m.mapped.clone(),
+                        expires,
+                    });
+                }
+            }
+        }
+
+        if !expired.is_empty() {
+            let mut inbound_map = self.inbound_map.lock().await;
+            let mut outbound_map = self.outbound_map.lock().await;
+            for (in_key, out_key) in expired {
+                inbound_map.remove(&in_key);
+                outbound_map.remove(&out_key);
+            }
+        }
+
+        mappings
+    }
+
     // caller must hold the mutex
     fn get_outbound_map_key(m: &Mapping) -> String {
         format!("{}:{}:{}", m.proto, m.local, m.bound)
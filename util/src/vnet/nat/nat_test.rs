@@ -37,6 +37,45 @@ fn test_nat_type_default() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_nat_type_presets() -> Result<()> {
+    let tests = vec![
+        (
+            "full cone",
+            NatType::full_cone(),
+            EndpointDependencyType::EndpointIndependent,
+            EndpointDependencyType::EndpointIndependent,
+        ),
+        (
+            "restricted cone",
+            NatType::restricted_cone(),
+            EndpointDependencyType::EndpointIndependent,
+            EndpointDependencyType::EndpointAddrDependent,
+        ),
+        (
+            "port restricted cone",
+            NatType::port_restricted_cone(),
+            EndpointDependencyType::EndpointIndependent,
+            EndpointDependencyType::EndpointAddrPortDependent,
+        ),
+        (
+            "symmetric",
+            NatType::symmetric(),
+            EndpointDependencyType::EndpointAddrPortDependent,
+            EndpointDependencyType::EndpointAddrPortDependent,
+        ),
+    ];
+
+    for (name, nat_type, mapping_behavior, filtering_behavior) in tests {
+        assert_eq!(NatMode::Normal, nat_type.mode, "{}", name);
+        assert_eq!(mapping_behavior, nat_type.mapping_behavior, "{}", name);
+        assert_eq!(filtering_behavior, nat_type.filtering_behavior, "{}", name);
+        assert!(!nat_type.hair_pining, "{}", name);
+    }
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_nat_mapping_behavior_full_cone_nat() -> Result<()> {
     let nat = NetworkAddressTranslator::new(NatConfig {
@@ -635,3 +674,43 @@ async fn test_nat1to1_bahavior_failure() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_nat_mappings_report_expiry() -> Result<()> {
+    let nat = NetworkAddressTranslator::new(NatConfig {
+        nat_type: NatType {
+            mapping_life_time: Duration::from_millis(100),
+            ..NatType::port_restricted_cone()
+        },
+        mapped_ips: vec![IpAddr::from_str(DEMO_IP)?],
+        ..Default::default()
+    })?;
+
+    let src = SocketAddr::from_str("192.168.0.2:1234")?;
+    let dst = SocketAddr::from_str("5.6.7.8:5678")?;
+
+    let before = SystemTime::now();
+    let oec = nat
+        .translate_outbound(&ChunkUdp::new(src, dst))
+        .await?
+        .unwrap();
+
+    let mappings = nat.mappings().await;
+    assert_eq!(1, mappings.len(), "should match");
+    assert_eq!(UDP_STR, mappings[0].proto);
+    assert_eq!(src.to_string(), mappings[0].local);
+    assert_eq!(oec.source_addr().to_string(), mappings[0].mapped);
+    assert!(
+        mappings[0].expires >= before.add(Duration::from_millis(100)),
+        "should expire after the mapping life time"
+    );
+
+    // sleep long enough for the mapping to expire
+    tokio::time::sleep(Duration::from_millis(125)).await;
+
+    assert!(nat.mappings().await.is_empty(), "should have expired");
+    assert_eq!(0, nat.outbound_map_len().await, "should match");
+    assert_eq!(0, nat.inbound_map_len().await, "should match");
+
+    Ok(())
+}
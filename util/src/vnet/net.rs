@@ -4,9 +4,11 @@ mod net_test;
 use super::conn_map::*;
 use super::interface::*;
 use crate::error::*;
-use crate::vnet::chunk::Chunk;
+use crate::vnet::capture::{CaptureHandlerFn, CaptureHooks};
+use crate::vnet::chunk::{Chunk, DEFAULT_TTL};
 use crate::vnet::conn::{ConnObserver, UdpConn};
 use crate::vnet::router::*;
+use crate::vnet::tcp::{self, TcpConnMap, TcpListener, TcpStream};
 use crate::{conn, ifaces, Conn};
 
 use async_trait::async_trait;
@@ -21,6 +23,7 @@ use tokio::sync::Mutex;
 
 pub(crate) const LO0_STR: &str = "lo0";
 pub(crate) const UDP_STR: &str = "udp";
+pub(crate) const TCP_STR: &str = "tcp";
 
 lazy_static! {
     pub static ref MAC_ADDR_COUNTER: AtomicU64 = AtomicU64::new(0xBEEFED910200);
@@ -40,6 +43,8 @@ pub(crate) struct VNetInternal {
     pub(crate) interfaces: Vec<Interface>,         // read-only
     pub(crate) router: Option<Arc<Mutex<Router>>>, // read-only
     pub(crate) udp_conns: UdpConnMap,              // read-only
+    pub(crate) tcp_conns: Arc<TcpConnMap>,         // read-only
+    pub(crate) capture_hooks: CaptureHooks,        // requires mutex [x]
 }
 
 impl VNetInternal {
@@ -51,13 +56,17 @@ impl VNetInternal {
 #[async_trait]
 impl ConnObserver for VNetInternal {
     async fn write(&self, c: Box<dyn Chunk + Send + Sync>) -> Result<()> {
+        self.capture_hooks.capture(&*c);
+
         if c.network() == UDP_STR && c.get_destination_ip().is_loopback() {
             if let Some(conn) = self.udp_conns.find(&c.destination_addr()).await {
-                let read_ch_tx = conn.get_inbound_ch();
-                let ch_tx = read_ch_tx.lock().await;
-                if let Some(tx) = &*ch_tx {
-                    let _ = tx.send(c).await;
-                }
+                conn.on_inbound_chunk(c).await;
+            }
+            return Ok(());
+        }
+        if c.network() == TCP_STR && c.get_destination_ip().is_loopback() {
+            if let Some(rst) = self.tcp_conns.on_inbound_chunk(c) {
+                self.tcp_conns.on_inbound_chunk(rst);
             }
             return Ok(());
         }
@@ -161,11 +170,19 @@ impl Nic for VNet {
         if c.network() == UDP_STR {
             let vi = self.vi.lock().await;
             if let Some(conn) = vi.udp_conns.find(&c.destination_addr()).await {
-                let read_ch_tx = conn.get_inbound_ch();
-                let ch_tx = read_ch_tx.lock().await;
-                if let Some(tx) = &*ch_tx {
-                    let _ = tx.send(c).await;
-                }
+                vi.capture_hooks.capture(&*c);
+                conn.on_inbound_chunk(c).await;
+            }
+        } else if c.network() == TCP_STR {
+            let vi = self.vi.lock().await;
+            vi.capture_hooks.capture(&*c);
+            if let Some(rst) = vi.tcp_conns.on_inbound_chunk(c) {
+                // the router waits for this call, so the RST is sent by another task
+                let vi = Arc::clone(&self.vi);
+                tokio::spawn(async move {
+                    let vi = vi.lock().await;
+                    let _ = vi.write(rst).await;
+                });
             }
         }
     }
@@ -350,6 +367,31 @@ impl VNet {
         Ok(conn)
     }
 
+    pub(crate) async fn set_socket_options(
+        &self,
+        local_addr: SocketAddr,
+        options: SocketOptions,
+    ) -> Result<()> {
+        let vi = self.vi.lock().await;
+        let conn = vi
+            .udp_conns
+            .find(&local_addr)
+            .await
+            .ok_or(Error::ErrNotFound)?;
+        conn.set_options(options);
+        Ok(())
+    }
+
+    pub(crate) async fn socket_stats(&self, local_addr: SocketAddr) -> Result<SocketStats> {
+        let vi = self.vi.lock().await;
+        let conn = vi
+            .udp_conns
+            .find(&local_addr)
+            .await
+            .ok_or(Error::ErrNotFound)?;
+        Ok(conn.stats())
+    }
+
     pub(crate) async fn dail(
         &self,
         use_ipv4: bool,
@@ -379,6 +421,46 @@ impl VNet {
 
         Ok(conn)
     }
+
+    pub(crate) async fn listen_tcp(&self, mut local_addr: SocketAddr) -> Result<TcpListener> {
+        if !self.has_ipaddr(local_addr.ip()) {
+            return Err(Error::ErrCantAssignRequestedAddr);
+        }
+
+        let vi = self.vi.lock().await;
+        if local_addr.port() == 0 {
+            local_addr.set_port(tcp::pick_port(&vi.tcp_conns, local_addr.ip())?);
+        } else if vi.tcp_conns.is_in_use(local_addr) {
+            return Err(Error::ErrAddressAlreadyInUse);
+        }
+
+        let incoming = vi.tcp_conns.add_listener(local_addr);
+        Ok(TcpListener::new(
+            local_addr,
+            incoming,
+            Arc::clone(&self.vi),
+            Arc::clone(&vi.tcp_conns),
+        ))
+    }
+
+    // tcp_local_addr returns the local address to connect from to remote_addr, with a free port.
+    pub(crate) async fn tcp_local_addr(
+        &self,
+        local_ip: IpAddr,
+        remote_addr: SocketAddr,
+    ) -> Result<(SocketAddr, Arc<TcpConnMap>)> {
+        if !local_ip.is_unspecified() && !self.has_ipaddr(local_ip) {
+            return Err(Error::ErrCantAssignRequestedAddr);
+        }
+
+        let vi = self.vi.lock().await;
+        let src_ip = vi
+            .determine_source_ip(local_ip, remote_addr.ip())
+            .ok_or(Error::ErrLocAddr)?;
+        let port = tcp::pick_port(&vi.tcp_conns, src_ip)?;
+
+        Ok((SocketAddr::new(src_ip, port), Arc::clone(&vi.tcp_conns)))
+    }
 }
 
 // NetConfig is a bag of configuration parameters passed to NewNet().
@@ -393,6 +475,35 @@ pub struct NetConfig {
     pub static_ip: String,
 }
 
+// SocketOptions are the emulated options of a UDP socket of a virtual network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketOptions {
+    // TTL of the chunks sent by the socket. Each router to router hop decrements it, and the
+    // chunk is dropped once it would reach 0.
+    pub ttl: u8,
+    // Receive buffer size in bytes of payload. Received chunks which don't fit are dropped and
+    // counted in SocketStats::recv_buffer_drops. 0 means unlimited, the default.
+    pub recv_buffer_size: usize,
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        SocketOptions {
+            ttl: DEFAULT_TTL,
+            recv_buffer_size: 0,
+        }
+    }
+}
+
+// SocketStats are the counters of a UDP socket of a virtual network.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SocketStats {
+    // bytes of payload received but not read yet
+    pub recv_buffered: usize,
+    // number of chunks dropped because the receive buffer was full
+    pub recv_buffer_drops: u64,
+}
+
 // Net represents a local network stack euivalent to a set of layers from NIC
 // up to the transport (UDP / TCP) layer.
 pub enum Net {
@@ -438,6 +549,8 @@ impl Net {
                     interfaces: vec![lo0, eth0],
                     router: None,
                     udp_conns: UdpConnMap::new(),
+                    tcp_conns: Arc::new(TcpConnMap::default()),
+                    capture_hooks: CaptureHooks::default(),
                 })),
             };
 
@@ -555,6 +668,85 @@ impl Net {
         }
     }
 
+    // ListenTcp returns a listener accepting the TCP connections of this virtual network to
+    // addr. A port 0 picks a free port, see TcpListener::local_addr.
+    pub async fn listen_tcp(&self, addr: SocketAddr) -> Result<TcpListener> {
+        match self {
+            Net::VNet(vnet) => {
+                let net = vnet.lock().await;
+                net.listen_tcp(addr).await
+            }
+            Net::Ifs(_) => Err(Error::ErrVnetDisabled),
+        }
+    }
+
+    // DialTcp connects to remote_addr over a TCP connection of this virtual network, from
+    // local_ip unless it's unspecified.
+    pub async fn dial_tcp(&self, local_ip: IpAddr, remote_addr: SocketAddr) -> Result<TcpStream> {
+        match self {
+            Net::VNet(vnet) => {
+                // the net must be unlocked while connecting, it receives the SYN-ACK
+                let (vi, (local_addr, tcp_conns)) = {
+                    let net = vnet.lock().await;
+                    (
+                        Arc::clone(&net.vi),
+                        net.tcp_local_addr(local_ip, remote_addr).await?,
+                    )
+                };
+                tcp::dial(vi, tcp_conns, local_addr, remote_addr).await
+            }
+            Net::Ifs(_) => Err(Error::ErrVnetDisabled),
+        }
+    }
+
+    // SetSocketOptions sets the options of the socket of this virtual network bound to
+    // local_addr, as returned by its local_addr(). Set them before the socket receives, the
+    // receive buffer doesn't account for chunks queued before.
+    pub async fn set_socket_options(
+        &self,
+        local_addr: SocketAddr,
+        options: SocketOptions,
+    ) -> Result<()> {
+        match self {
+            Net::VNet(vnet) => {
+                let net = vnet.lock().await;
+                net.set_socket_options(local_addr, options).await
+            }
+            Net::Ifs(_) => Err(Error::ErrVnetDisabled),
+        }
+    }
+
+    // SocketStats returns the counters of the socket of this virtual network bound to
+    // local_addr.
+    pub async fn socket_stats(&self, local_addr: SocketAddr) -> Result<SocketStats> {
+        match self {
+            Net::VNet(vnet) => {
+                let net = vnet.lock().await;
+                net.socket_stats(local_addr).await
+            }
+            Net::Ifs(_) => Err(Error::ErrVnetDisabled),
+        }
+    }
+
+    // AddCaptureHandler adds a handler called with every chunk sent by the sockets of this
+    // virtual network, and every chunk received by one of them, for which filter returns true
+    // if given.
+    pub async fn add_capture_handler(
+        &self,
+        filter: Option<ChunkFilterFn>,
+        handler: CaptureHandlerFn,
+    ) -> Result<()> {
+        match self {
+            Net::VNet(vnet) => {
+                let net = vnet.lock().await;
+                let mut vi = net.vi.lock().await;
+                vi.capture_hooks.add(filter, handler);
+                Ok(())
+            }
+            Net::Ifs(_) => Err(Error::ErrVnetDisabled),
+        }
+    }
+
     pub fn get_nic(&self) -> Result<Arc<Mutex<dyn Nic + Send + Sync>>> {
         match self {
             Net::VNet(vnet) => Ok(Arc::clone(vnet) as Arc<Mutex<dyn Nic + Send + Sync>>),
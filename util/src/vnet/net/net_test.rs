@@ -1,4 +1,5 @@
 use super::*;
+use crate::vnet::capture::Capture;
 use crate::vnet::chunk::ChunkUdp;
 
 use tokio::sync::{broadcast, mpsc};
@@ -913,3 +914,65 @@ async fn test_net_virtual_two_ips_on_a_nic() -> Result<()> {
     log::debug!("main recv done_ch_rx");
     Ok(())
 }
+
+#[tokio::test]
+async fn test_net_virtual_socket_options() -> Result<()> {
+    let nw = Net::new(Some(NetConfig::default()));
+    let capture = Capture::new(10);
+    nw.add_capture_handler(None, capture.handler()).await?;
+
+    let conn = nw.bind(SocketAddr::from_str("127.0.0.1:0")?).await?;
+    let laddr = conn.local_addr()?;
+    nw.set_socket_options(
+        laddr,
+        SocketOptions {
+            ttl: 5,
+            recv_buffer_size: 10,
+        },
+    )
+    .await?;
+
+    // the third chunk doesn't fit into the 10 bytes of receive buffer
+    for _ in 0..3 {
+        conn.send_to(b"1234", laddr).await?;
+    }
+    assert_eq!(
+        SocketStats {
+            recv_buffered: 8,
+            recv_buffer_drops: 1,
+        },
+        nw.socket_stats(laddr).await?,
+        "should match"
+    );
+
+    let mut buf = vec![0u8; 1500];
+    let (n, _) = conn.recv_from(&mut buf).await?;
+    assert_eq!(4, n, "should match");
+    assert_eq!(
+        4,
+        nw.socket_stats(laddr).await?.recv_buffered,
+        "should match"
+    );
+
+    // the chunks are captured when sent, including the one dropped by the receiver
+    let packets = capture.packets();
+    assert_eq!(3, packets.len(), "should match");
+    for packet in &packets {
+        assert_eq!(5, packet.ttl, "should match");
+        assert_eq!(laddr, packet.destination_addr, "should match");
+    }
+
+    let unbound = SocketAddr::from_str("127.0.0.1:1")?;
+    assert!(nw.socket_stats(unbound).await.is_err(), "should fail");
+
+    let native = Net::new(None);
+    assert!(
+        native
+            .set_socket_options(laddr, SocketOptions::default())
+            .await
+            .is_err(),
+        "should fail"
+    );
+
+    Ok(())
+}
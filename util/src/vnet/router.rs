@@ -2,6 +2,7 @@
 mod router_test;
 
 use crate::error::*;
+use crate::vnet::capture::*;
 use crate::vnet::chunk::*;
 use crate::vnet::chunk_queue::*;
 use crate::vnet::interface::*;
@@ -81,6 +82,7 @@ pub struct RouterInternal {
     pub(crate) nat: NetworkAddressTranslator,      // read-only
     pub(crate) nics: HashMap<String, Arc<Mutex<dyn Nic + Send + Sync>>>, // read-only
     pub(crate) chunk_filters: Vec<ChunkFilterFn>,  // requires mutex [x]
+    pub(crate) capture_hooks: CaptureHooks,        // requires mutex [x]
     pub(crate) last_id: u8, // requires mutex [x], used to assign the last digit of IPv4 address
 }
 
@@ -126,7 +128,12 @@ impl Nic for Router {
         Err(Error::ErrNotFound)
     }
 
-    async fn on_inbound_chunk(&self, c: Box<dyn Chunk + Send + Sync>) {
+    async fn on_inbound_chunk(&self, mut c: Box<dyn Chunk + Send + Sync>) {
+        // the chunk crossed the link from the parent router
+        if !decrement_ttl(&self.name, &mut *c) {
+            return;
+        }
+
         let from_parent: Box<dyn Chunk + Send + Sync> = {
             let router_internal = self.router_internal.lock().await;
             match router_internal.nat.translate_inbound(&*c).await {
@@ -407,6 +414,24 @@ impl Router {
         router_internal.chunk_filters.push(filter);
     }
 
+    // AddCaptureHandler adds a handler called with every chunk forwarded by this router, i.e.
+    // which passed the chunk filters, and for which filter returns true if given.
+    pub async fn add_capture_handler(
+        &self,
+        filter: Option<ChunkFilterFn>,
+        handler: CaptureHandlerFn,
+    ) {
+        let mut router_internal = self.router_internal.lock().await;
+        router_internal.capture_hooks.add(filter, handler);
+    }
+
+    // NatMappings returns the NAT bindings of this router that have not expired yet. Expired
+    // bindings are removed from the mapping table by this call.
+    pub async fn nat_mappings(&self) -> Vec<NatMapping> {
+        let router_internal = self.router_internal.lock().await;
+        router_internal.nat.mappings().await
+    }
+
     pub(crate) async fn push(&self, mut c: Box<dyn Chunk + Send + Sync>) {
         log::debug!("[{}] route {}", self.name, c);
         if self.done.is_some() {
@@ -472,7 +497,7 @@ impl Router {
                 break; // no more chunk in the queue
             }
 
-            if let Some(c) = queue.pop().await {
+            if let Some(mut c) = queue.pop().await {
                 let ri = router_internal.lock().await;
                 let mut blocked = false;
                 for filter in &ri.chunk_filters {
@@ -484,6 +509,7 @@ impl Router {
                 if blocked {
                     continue; // discard
                 }
+                ri.capture_hooks.capture(&*c);
 
                 let dst_ip = c.get_destination_ip();
 
@@ -499,10 +525,39 @@ impl Router {
                         // NIC not found. drop it.
                         log::debug!("[{}] {} unreachable", name, c);
                     }
+                } else if ri.parent.is_some() && ri.nat.is_mapped_ip(&dst_ip) {
+                    // the destination is one of our own mapped addresses (hairpinning)
+                    if !ri.nat.nat_type.hair_pining {
+                        log::debug!("[{}] drop {} as hairpinning is disabled", name, c);
+                        continue;
+                    }
+                    if !decrement_ttl(name, &mut *c) {
+                        continue;
+                    }
+
+                    if let Some(to_self) = ri.nat.translate_outbound(&*c).await? {
+                        match ri.nat.translate_inbound(&*to_self).await {
+                            Ok(Some(back)) => {
+                                let dst_ip = back.get_destination_ip();
+                                if let Some(nic) = ri.nics.get(&dst_ip.to_string()) {
+                                    let ni = nic.lock().await;
+                                    ni.on_inbound_chunk(back).await;
+                                } else {
+                                    log::debug!("[{}] {} unreachable", name, back);
+                                }
+                            }
+                            Ok(None) => {}
+                            Err(err) => log::debug!("[{}] {}", name, err),
+                        }
+                    }
                 } else {
                     // the destination is outside of this subnet
                     // is this WAN?
                     if let Some(parent) = &ri.parent {
+                        if !decrement_ttl(name, &mut *c) {
+                            continue;
+                        }
+
                         // Pass it to the parent via NAT
                         if let Some(to_parent) = ri.nat.translate_outbound(&*c).await? {
                             // call to parent router mutex unlock mutex
@@ -523,6 +578,18 @@ impl Router {
     }
 }
 
+// decrement_ttl counts a hop of the chunk from one router to another. It returns false if the
+// chunk must be dropped as its TTL expired.
+fn decrement_ttl(name: &str, c: &mut (dyn Chunk + Send + Sync)) -> bool {
+    let ttl = c.ttl();
+    if ttl <= 1 {
+        log::debug!("[{}] drop {} as its TTL expired", name, c);
+        return false;
+    }
+    c.set_ttl(ttl - 1);
+    true
+}
+
 impl RouterInternal {
     // caller must hold the mutex
     pub(crate) async fn add_nic(&mut self, nic: Arc<Mutex<dyn Nic + Send + Sync>>) -> Result<()> {
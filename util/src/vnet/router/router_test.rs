@@ -809,3 +809,305 @@ async fn test_router_failures_add_router() -> Result<()> {
 
     Ok(())
 }
+
+async fn run_router_nat_hairpinning(hair_pining: bool) -> Result<()> {
+    let wan = Arc::new(Mutex::new(Router::new(RouterConfig {
+        cidr: "1.2.3.0/24".to_owned(),
+        ..Default::default()
+    })?));
+    let lan = Arc::new(Mutex::new(Router::new(RouterConfig {
+        cidr: "192.168.0.0/24".to_owned(),
+        nat_type: Some(NatType {
+            hair_pining,
+            ..NatType::full_cone()
+        }),
+        ..Default::default()
+    })?));
+
+    let mut nics = vec![];
+    let mut ips = vec![];
+    for r in [&wan, &lan, &lan] {
+        let nic = Arc::new(Mutex::new(DummyNic {
+            net: Net::new(Some(NetConfig::default())),
+            ..Default::default()
+        }));
+        {
+            let n = Arc::clone(&nic) as Arc<Mutex<dyn Nic + Send + Sync>>;
+            let mut w = r.lock().await;
+            w.add_net(n).await?;
+        }
+        {
+            let n = nic.lock().await;
+            n.set_router(Arc::clone(r)).await?;
+        }
+        {
+            let n = Arc::clone(&nic) as Arc<Mutex<dyn Nic + Send + Sync>>;
+            ips.push(get_ipaddr(&n).await?);
+        }
+        nics.push(nic);
+    }
+
+    {
+        let mut w = wan.lock().await;
+        w.add_router(Arc::clone(&lan)).await?;
+    }
+    {
+        let l = lan.lock().await;
+        l.set_router(Arc::clone(&wan)).await?;
+    }
+    {
+        let mut w = wan.lock().await;
+        w.start().await?;
+    }
+
+    // the second LAN host creates a NAT binding by sending to the WAN host
+    {
+        let c = Box::new(ChunkUdp::new(
+            SocketAddr::new(ips[2], 1234),
+            SocketAddr::new(ips[0], 5678),
+        ));
+        let l = lan.lock().await;
+        l.push(c).await;
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let mapped = {
+        let l = lan.lock().await;
+        let mappings = l.nat_mappings().await;
+        assert_eq!(1, mappings.len(), "should match");
+        mappings[0].mapped.clone()
+    };
+
+    // the first LAN host sends to the mapped address of the second one
+    {
+        let c = Box::new(ChunkUdp::new(
+            SocketAddr::new(ips[1], 1234),
+            SocketAddr::from_str(&mapped)?,
+        ));
+        let l = lan.lock().await;
+        l.push(c).await;
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    {
+        let n = nics[0].lock().await;
+        assert_eq!(1, n.cbs0.load(Ordering::SeqCst), "WAN host should match");
+    }
+    {
+        let n = nics[2].lock().await;
+        assert_eq!(
+            if hair_pining { 1 } else { 0 },
+            n.cbs0.load(Ordering::SeqCst),
+            "hairpinned chunk should match"
+        );
+    }
+
+    {
+        let mut w = wan.lock().await;
+        w.stop().await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_router_nat_hairpinning_enabled() -> Result<()> {
+    run_router_nat_hairpinning(true).await
+}
+
+#[tokio::test]
+async fn test_router_nat_hairpinning_disabled() -> Result<()> {
+    run_router_nat_hairpinning(false).await
+}
+
+// add_net_to connects a new virtual network to the router and returns its IP address.
+async fn add_net_to(r: &Arc<Mutex<Router>>) -> Result<(Net, IpAddr)> {
+    let net = Net::new(Some(NetConfig::default()));
+    let nic = net.get_nic()?;
+    {
+        let mut w = r.lock().await;
+        w.add_net(Arc::clone(&nic)).await?;
+    }
+    {
+        let n = nic.lock().await;
+        n.set_router(Arc::clone(r)).await?;
+    }
+    let ip = get_ipaddr(&nic).await?;
+    Ok((net, ip))
+}
+
+#[tokio::test]
+async fn test_router_capture_paced_sender() -> Result<()> {
+    const NUM_PACKETS: usize = 10;
+    const INTERVAL: Duration = Duration::from_millis(20);
+
+    let wan = Arc::new(Mutex::new(Router::new(RouterConfig {
+        cidr: "1.2.3.0/24".to_owned(),
+        ..Default::default()
+    })?));
+    let (net1, ip1) = add_net_to(&wan).await?;
+    let (net2, ip2) = add_net_to(&wan).await?;
+
+    let media = Capture::new(100);
+    {
+        let mut w = wan.lock().await;
+        w.add_capture_handler(
+            Some(Box::new(|c: &(dyn Chunk + Send + Sync)| {
+                c.destination_addr().port() == 5678
+            })),
+            media.handler(),
+        )
+        .await;
+        w.start().await?;
+    }
+
+    let conn1 = net1.bind(SocketAddr::new(ip1, 1234)).await?;
+    let conn2 = net2.bind(SocketAddr::new(ip2, 5678)).await?;
+    let conn3 = net2.bind(SocketAddr::new(ip2, 9999)).await?;
+
+    // a paced sender, and unpaced traffic left out by the capture filter
+    for i in 0..NUM_PACKETS {
+        conn1.send_to(&[i as u8], conn2.local_addr()?).await?;
+        conn1.send_to(b"noise", conn3.local_addr()?).await?;
+        tokio::time::sleep(INTERVAL).await;
+    }
+
+    let mut buf = vec![0u8; 1500];
+    for i in 0..NUM_PACKETS {
+        let n = tokio::time::timeout(Duration::from_secs(1), conn2.recv(&mut buf))
+            .await
+            .expect("should receive")?;
+        assert_eq!(&[i as u8], &buf[..n], "should match");
+    }
+
+    let packets = media.packets();
+    assert_eq!(NUM_PACKETS, packets.len(), "should match");
+    for (i, packet) in packets.iter().enumerate() {
+        assert_eq!(vec![i as u8], packet.payload, "should be in order");
+        assert_eq!(SocketAddr::new(ip1, 1234), packet.source_addr);
+    }
+    for pair in packets.windows(2) {
+        let spacing = pair[1]
+            .timestamp
+            .duration_since(pair[0].timestamp)
+            .unwrap_or_default();
+        assert!(
+            spacing + MARGIN / 2 >= INTERVAL,
+            "packets spaced by {:?}, should be at least {:?}",
+            spacing,
+            INTERVAL
+        );
+    }
+    let span = packets[NUM_PACKETS - 1]
+        .timestamp
+        .duration_since(packets[0].timestamp)
+        .unwrap_or_default();
+    assert!(
+        span >= INTERVAL * (NUM_PACKETS as u32 - 1),
+        "span {:?} should match",
+        span
+    );
+
+    {
+        let mut w = wan.lock().await;
+        w.stop().await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_router_ttl() -> Result<()> {
+    let wan = Arc::new(Mutex::new(Router::new(RouterConfig {
+        cidr: "1.2.3.0/24".to_owned(),
+        ..Default::default()
+    })?));
+    let lan = Arc::new(Mutex::new(Router::new(RouterConfig {
+        cidr: "192.168.0.0/24".to_owned(),
+        nat_type: Some(NatType::full_cone()),
+        ..Default::default()
+    })?));
+    let (wan_net, wan_ip) = add_net_to(&wan).await?;
+    let (lan_net, lan_ip) = add_net_to(&lan).await?;
+    {
+        let mut w = wan.lock().await;
+        w.add_router(Arc::clone(&lan)).await?;
+    }
+    {
+        let l = lan.lock().await;
+        l.set_router(Arc::clone(&wan)).await?;
+    }
+
+    let capture = Capture::new(10);
+    {
+        let mut w = wan.lock().await;
+        w.add_capture_handler(None, capture.handler()).await;
+        w.start().await?;
+    }
+
+    let wan_conn = wan_net.bind(SocketAddr::new(wan_ip, 5678)).await?;
+    let lan_conn = lan_net.bind(SocketAddr::new(lan_ip, 1234)).await?;
+    let mut buf = vec![0u8; 1500];
+
+    // leaving the LAN is a hop, a TTL of 1 expires
+    for ttl in [1, 2] {
+        lan_net
+            .set_socket_options(
+                lan_conn.local_addr()?,
+                SocketOptions {
+                    ttl,
+                    ..Default::default()
+                },
+            )
+            .await?;
+        lan_conn.send_to(&[ttl], wan_conn.local_addr()?).await?;
+    }
+    let n = tokio::time::timeout(Duration::from_secs(1), wan_conn.recv(&mut buf))
+        .await
+        .expect("should receive")?;
+    assert_eq!(
+        &[2],
+        &buf[..n],
+        "only the chunk with a TTL of 2 should arrive"
+    );
+
+    let packets = capture.packets();
+    assert_eq!(1, packets.len(), "should match");
+    assert_eq!(1, packets[0].ttl, "should be decremented");
+
+    // entering the LAN is a hop too, the reply needs a TTL of 2 as well
+    let mapped = packets[0].source_addr;
+    for ttl in [1, 2] {
+        wan_net
+            .set_socket_options(
+                wan_conn.local_addr()?,
+                SocketOptions {
+                    ttl,
+                    ..Default::default()
+                },
+            )
+            .await?;
+        wan_conn.send_to(&[ttl], mapped).await?;
+    }
+    let n = tokio::time::timeout(Duration::from_secs(1), lan_conn.recv(&mut buf))
+        .await
+        .expect("should receive")?;
+    assert_eq!(
+        &[2],
+        &buf[..n],
+        "only the chunk with a TTL of 2 should arrive"
+    );
+    assert!(
+        tokio::time::timeout(Duration::from_millis(50), lan_conn.recv(&mut buf))
+            .await
+            .is_err(),
+        "should receive nothing else"
+    );
+
+    {
+        let mut w = wan.lock().await;
+        w.stop().await?;
+    }
+
+    Ok(())
+}
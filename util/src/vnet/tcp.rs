@@ -0,0 +1,374 @@
+#[cfg(test)]
+mod tcp_test;
+
+use crate::error::*;
+use crate::sync::Mutex as SyncMutex;
+use crate::vnet::chunk::*;
+use crate::vnet::conn::ConnObserver;
+use crate::vnet::net::VNetInternal;
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, ReadBuf};
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::Duration;
+
+/// How long dialing waits for the SYN-ACK of the remote.
+const DIAL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The number of connections received and not accepted yet, more SYNs are dropped.
+const ACCEPT_QUEUE_SIZE: usize = 64;
+
+/// The number of bytes a stream buffers in each direction.
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The largest payload of a chunk.
+const MAX_SEGMENT_SIZE: usize = 1400;
+
+type ChunkTx = mpsc::UnboundedSender<Box<dyn Chunk + Send + Sync>>;
+
+/// A connection received by a listener: its local and remote addresses.
+type Incoming = (SocketAddr, SocketAddr);
+
+/// TcpConnMap dispatches the TCP chunks received by a virtual network to its streams and
+/// listeners.
+#[derive(Default)]
+pub(crate) struct TcpConnMap {
+    listeners: SyncMutex<HashMap<SocketAddr, mpsc::Sender<Incoming>>>,
+    // keyed by local and remote address
+    streams: SyncMutex<HashMap<(SocketAddr, SocketAddr), ChunkTx>>,
+}
+
+impl TcpConnMap {
+    /// on_inbound_chunk passes the chunk to its stream, or queues a SYN for the listener of its
+    /// destination. It returns the RST to send back if no one takes the chunk.
+    pub(crate) fn on_inbound_chunk(
+        &self,
+        c: Box<dyn Chunk + Send + Sync>,
+    ) -> Option<Box<dyn Chunk + Send + Sync>> {
+        let (local, remote) = (c.destination_addr(), c.source_addr());
+        let flags = c.tcp_flags();
+
+        let stream = self.streams.lock().get(&(local, remote)).cloned();
+        let c = match stream {
+            Some(stream) => match stream.send(c) {
+                Ok(()) => return None,
+                // the stream just ended
+                Err(err) => err.0,
+            },
+            None => {
+                if flags == TCP_FLAG_SYN {
+                    if let Some(listener) = self.find_listener(local) {
+                        // a SYN beyond the accept queue is dropped, like by a full backlog
+                        let _ = listener.try_send((local, remote));
+                        return None;
+                    }
+                }
+                c
+            }
+        };
+
+        if flags.contains(TCP_FLAG_RST) {
+            return None;
+        }
+        log::debug!("reset {}, no TCP connection or listener", c);
+        Some(Box::new(ChunkTcp::new(
+            local,
+            remote,
+            TCP_FLAG_RST | TCP_FLAG_ACK,
+        )))
+    }
+
+    fn find_listener(&self, addr: SocketAddr) -> Option<mpsc::Sender<Incoming>> {
+        let listeners = self.listeners.lock();
+        listeners
+            .iter()
+            .find(|(laddr, _)| {
+                laddr.port() == addr.port()
+                    && (laddr.ip().is_unspecified() || laddr.ip() == addr.ip())
+            })
+            .map(|(_, listener)| listener.clone())
+    }
+
+    /// is_in_use returns true if a stream or a listener uses addr as its local address.
+    pub(crate) fn is_in_use(&self, addr: SocketAddr) -> bool {
+        let conflicts = |laddr: &SocketAddr| {
+            laddr.port() == addr.port()
+                && (addr.ip().is_unspecified()
+                    || laddr.ip().is_unspecified()
+                    || laddr.ip() == addr.ip())
+        };
+        self.listeners.lock().keys().any(conflicts)
+            || self
+                .streams
+                .lock()
+                .keys()
+                .any(|(laddr, _)| conflicts(laddr))
+    }
+
+    fn add_stream(
+        &self,
+        local: SocketAddr,
+        remote: SocketAddr,
+    ) -> Option<mpsc::UnboundedReceiver<Box<dyn Chunk + Send + Sync>>> {
+        let mut streams = self.streams.lock();
+        if streams.contains_key(&(local, remote)) {
+            return None;
+        }
+        let (tx, rx) = mpsc::unbounded_channel();
+        streams.insert((local, remote), tx);
+        Some(rx)
+    }
+
+    fn remove_stream(&self, local: SocketAddr, remote: SocketAddr) {
+        self.streams.lock().remove(&(local, remote));
+    }
+
+    pub(crate) fn add_listener(&self, addr: SocketAddr) -> mpsc::Receiver<Incoming> {
+        let (tx, rx) = mpsc::channel(ACCEPT_QUEUE_SIZE);
+        self.listeners.lock().insert(addr, tx);
+        rx
+    }
+}
+
+/// TcpStream is a TCP connection of a virtual network. Chunks aren't retransmitted, so a chunk
+/// filter dropping TCP chunks, or a router queue overflowing, breaks the stream.
+pub struct TcpStream {
+    io: DuplexStream,
+    local_addr: SocketAddr,
+    peer_addr: SocketAddr,
+}
+
+impl TcpStream {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+}
+
+impl AsyncRead for TcpStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TcpStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
+/// TcpListener accepts the TCP connections of a virtual network to its address.
+pub struct TcpListener {
+    local_addr: SocketAddr,
+    incoming: Mutex<mpsc::Receiver<Incoming>>,
+    vi: Arc<Mutex<VNetInternal>>,
+    tcp_conns: Arc<TcpConnMap>,
+}
+
+impl TcpListener {
+    pub(crate) fn new(
+        local_addr: SocketAddr,
+        incoming: mpsc::Receiver<Incoming>,
+        vi: Arc<Mutex<VNetInternal>>,
+        tcp_conns: Arc<TcpConnMap>,
+    ) -> Self {
+        TcpListener {
+            local_addr,
+            incoming: Mutex::new(incoming),
+            vi,
+            tcp_conns,
+        }
+    }
+
+    /// accept returns the next connection and its remote address.
+    pub async fn accept(&self) -> Result<(TcpStream, SocketAddr)> {
+        let mut incoming = self.incoming.lock().await;
+        loop {
+            let (local, remote) = incoming.recv().await.ok_or(Error::ErrClosedListener)?;
+            // a retransmitted SYN of a connection already accepted
+            let inbound = match self.tcp_conns.add_stream(local, remote) {
+                Some(inbound) => inbound,
+                None => continue,
+            };
+
+            let segments = Segments {
+                local,
+                remote,
+                vi: Arc::clone(&self.vi),
+            };
+            if let Err(err) = segments.send(TCP_FLAG_SYN | TCP_FLAG_ACK, vec![]).await {
+                log::debug!(
+                    "failed to accept the TCP connection from {}: {}",
+                    remote,
+                    err
+                );
+                self.tcp_conns.remove_stream(local, remote);
+                continue;
+            }
+
+            let stream = start_stream(segments, inbound, Arc::clone(&self.tcp_conns));
+            return Ok((stream, remote));
+        }
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for TcpListener {
+    fn drop(&mut self) {
+        self.tcp_conns.listeners.lock().remove(&self.local_addr);
+    }
+}
+
+/// dial connects from local to remote.
+pub(crate) async fn dial(
+    vi: Arc<Mutex<VNetInternal>>,
+    tcp_conns: Arc<TcpConnMap>,
+    local: SocketAddr,
+    remote: SocketAddr,
+) -> Result<TcpStream> {
+    let mut inbound = tcp_conns
+        .add_stream(local, remote)
+        .ok_or(Error::ErrAddressAlreadyInUse)?;
+    let segments = Segments { local, remote, vi };
+
+    let result = async {
+        segments.send(TCP_FLAG_SYN, vec![]).await?;
+        match tokio::time::timeout(DIAL_TIMEOUT, inbound.recv()).await {
+            Ok(Some(c)) if c.tcp_flags().contains(TCP_FLAG_SYN | TCP_FLAG_ACK) => Ok(()),
+            Ok(_) => {
+                Err(io::Error::new(io::ErrorKind::ConnectionRefused, "connection refused").into())
+            }
+            Err(_) => Err(Error::ErrTimeout),
+        }
+    }
+    .await;
+    if let Err(err) = result {
+        tcp_conns.remove_stream(local, remote);
+        return Err(err);
+    }
+
+    Ok(start_stream(segments, inbound, tcp_conns))
+}
+
+/// The local and remote address of a stream and the net sending its chunks.
+struct Segments {
+    local: SocketAddr,
+    remote: SocketAddr,
+    vi: Arc<Mutex<VNetInternal>>,
+}
+
+impl Segments {
+    async fn send(&self, flags: TcpFlag, user_data: Vec<u8>) -> Result<()> {
+        let mut chunk = ChunkTcp::new(self.local, self.remote, flags);
+        chunk.user_data = user_data;
+        let vi = self.vi.lock().await;
+        vi.write(Box::new(chunk)).await
+    }
+}
+
+/// start_stream returns the stream whose chunks are sent with segments and received from
+/// inbound, until both sides sent a FIN or one a RST.
+fn start_stream(
+    segments: Segments,
+    mut inbound: mpsc::UnboundedReceiver<Box<dyn Chunk + Send + Sync>>,
+    tcp_conns: Arc<TcpConnMap>,
+) -> TcpStream {
+    let (io, internal) = tokio::io::duplex(STREAM_BUFFER_SIZE);
+    let stream = TcpStream {
+        io,
+        local_addr: segments.local,
+        peer_addr: segments.remote,
+    };
+
+    tokio::spawn(async move {
+        let (mut reader, mut writer) = tokio::io::split(internal);
+        let mut buf = vec![0u8; MAX_SEGMENT_SIZE];
+        let (mut fin_sent, mut fin_received) = (false, false);
+        while !fin_sent || !fin_received {
+            tokio::select! {
+                result = reader.read(&mut buf), if !fin_sent => {
+                    let sent = match result {
+                        Ok(n) if n > 0 => {
+                            segments.send(TCP_FLAG_PSH | TCP_FLAG_ACK, buf[..n].to_vec()).await
+                        }
+                        // shut down or dropped
+                        _ => {
+                            fin_sent = true;
+                            segments.send(TCP_FLAG_FIN | TCP_FLAG_ACK, vec![]).await
+                        }
+                    };
+                    if let Err(err) = sent {
+                        log::debug!("TCP connection with {} failed: {}", segments.remote, err);
+                        break;
+                    }
+                }
+                c = inbound.recv(), if !fin_received => {
+                    let c = match c {
+                        Some(c) => c,
+                        None => break,
+                    };
+                    let flags = c.tcp_flags();
+                    if flags.contains(TCP_FLAG_RST) {
+                        log::debug!("TCP connection with {} was reset", segments.remote);
+                        break;
+                    }
+                    let user_data = c.user_data();
+                    // the data of a dropped stream is discarded
+                    if !user_data.is_empty() {
+                        let _ = writer.write_all(&user_data).await;
+                    }
+                    if flags.contains(TCP_FLAG_FIN) {
+                        fin_received = true;
+                        let _ = writer.shutdown().await;
+                    }
+                }
+            }
+        }
+
+        tcp_conns.remove_stream(segments.local, segments.remote);
+    });
+
+    stream
+}
+
+/// pick_port returns a free port of ip between 5000 and 5999.
+pub(crate) fn pick_port(tcp_conns: &TcpConnMap, ip: IpAddr) -> Result<u16> {
+    const START: u16 = 5000;
+    const SPACE: u16 = 1000;
+    let offset = rand::random::<u16>() % SPACE;
+    for i in 0..SPACE {
+        let port = ((offset + i) % SPACE) + START;
+        if !tcp_conns.is_in_use(SocketAddr::new(ip, port)) {
+            return Ok(port);
+        }
+    }
+
+    Err(Error::ErrPortSpaceExhausted)
+}
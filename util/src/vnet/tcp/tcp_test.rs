@@ -0,0 +1,158 @@
+use super::*;
+use crate::vnet::nat::NatType;
+use crate::vnet::net::{Net, NetConfig};
+use crate::vnet::router::{Nic, Router, RouterConfig};
+
+async fn add_net_to(r: &Arc<Mutex<Router>>) -> Result<(Net, IpAddr)> {
+    let net = Net::new(Some(NetConfig::default()));
+    let nic = net.get_nic()?;
+    {
+        let mut w = r.lock().await;
+        w.add_net(Arc::clone(&nic)).await?;
+    }
+    let n = nic.lock().await;
+    n.set_router(Arc::clone(r)).await?;
+    let eth0 = n.get_interface("eth0").await.ok_or(Error::ErrNoInterface)?;
+    let ip = eth0
+        .addrs()
+        .first()
+        .ok_or(Error::ErrNoAddressAssigned)?
+        .addr();
+    drop(n);
+    Ok((net, ip))
+}
+
+#[tokio::test]
+async fn test_tcp_stream_through_nat() -> Result<()> {
+    let wan = Arc::new(Mutex::new(Router::new(RouterConfig {
+        cidr: "1.2.3.0/24".to_owned(),
+        ..Default::default()
+    })?));
+    let lan = Arc::new(Mutex::new(Router::new(RouterConfig {
+        cidr: "192.168.0.0/24".to_owned(),
+        nat_type: Some(NatType::symmetric()),
+        ..Default::default()
+    })?));
+    let (wan_net, wan_ip) = add_net_to(&wan).await?;
+    let (lan_net, _) = add_net_to(&lan).await?;
+    {
+        let mut w = wan.lock().await;
+        w.add_router(Arc::clone(&lan)).await?;
+    }
+    {
+        let l = lan.lock().await;
+        l.set_router(Arc::clone(&wan)).await?;
+    }
+    {
+        let mut w = wan.lock().await;
+        w.start().await?;
+    }
+
+    let listener = wan_net.listen_tcp(SocketAddr::new(wan_ip, 3478)).await?;
+    let (accepted, client) = tokio::join!(
+        listener.accept(),
+        lan_net.dial_tcp(IpAddr::from([0, 0, 0, 0]), listener.local_addr())
+    );
+    let (mut server, remote) = accepted?;
+    let mut client = client?;
+    assert_eq!(client.peer_addr(), listener.local_addr(), "should match");
+
+    let mappings = {
+        let l = lan.lock().await;
+        l.nat_mappings().await
+    };
+    assert_eq!(1, mappings.len(), "the SYN should create a NAT binding");
+    assert_eq!("tcp", mappings[0].proto, "should match");
+    assert_eq!(
+        remote.to_string(),
+        mappings[0].mapped,
+        "the server should see the mapped address"
+    );
+
+    // larger than a chunk, so it's split and reassembled in order
+    let sent: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+    client.write_all(&sent).await?;
+    let mut received = vec![0u8; sent.len()];
+    server.read_exact(&mut received).await?;
+    assert_eq!(sent, received, "should match");
+
+    server.write_all(b"pong").await?;
+    let mut buf = [0u8; 4];
+    client.read_exact(&mut buf).await?;
+    assert_eq!(b"pong", &buf, "should match");
+
+    // a shutdown is read as the end of the stream
+    client.shutdown().await?;
+    assert_eq!(0, server.read(&mut buf).await?, "should read EOF");
+
+    {
+        let mut w = wan.lock().await;
+        w.stop().await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tcp_dial_refused() -> Result<()> {
+    let wan = Arc::new(Mutex::new(Router::new(RouterConfig {
+        cidr: "1.2.3.0/24".to_owned(),
+        ..Default::default()
+    })?));
+    let (net1, _) = add_net_to(&wan).await?;
+    let (_net2, ip2) = add_net_to(&wan).await?;
+    {
+        let mut w = wan.lock().await;
+        w.start().await?;
+    }
+
+    // reset right away rather than timing out
+    let result = tokio::time::timeout(
+        Duration::from_secs(1),
+        net1.dial_tcp(IpAddr::from([0, 0, 0, 0]), SocketAddr::new(ip2, 3478)),
+    )
+    .await
+    .expect("should be refused before the timeout");
+    match result {
+        Err(Error::Io(err)) => assert_eq!(io::ErrorKind::ConnectionRefused, err.0.kind()),
+        Err(err) => panic!("unexpected error {}", err),
+        Ok(_) => panic!("should be refused"),
+    }
+
+    {
+        let mut w = wan.lock().await;
+        w.stop().await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tcp_loopback() -> Result<()> {
+    let net = Net::new(Some(NetConfig::default()));
+    let lo = IpAddr::from([127, 0, 0, 1]);
+
+    let listener = net.listen_tcp(SocketAddr::new(lo, 0)).await?;
+    assert_ne!(0, listener.local_addr().port(), "should pick a port");
+    assert!(
+        net.listen_tcp(listener.local_addr()).await.is_err(),
+        "the address should be in use"
+    );
+
+    let (accepted, client) =
+        tokio::join!(listener.accept(), net.dial_tcp(lo, listener.local_addr()));
+    let (mut server, remote) = accepted?;
+    let mut client = client?;
+    assert_eq!(client.local_addr(), remote, "should match");
+
+    client.write_all(b"ping").await?;
+    let mut buf = [0u8; 4];
+    server.read_exact(&mut buf).await?;
+    assert_eq!(b"ping", &buf, "should match");
+
+    // dropping a stream ends it
+    drop(server);
+    assert_eq!(0, client.read(&mut buf).await?, "should read EOF");
+
+    Ok(())
+}
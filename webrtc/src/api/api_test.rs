@@ -1,4 +1,25 @@
 use super::*;
+use crate::data_channel::data_channel_message::DataChannelMessage;
+use crate::dtls_transport::dtls_parameters::DTLSParameters;
+use crate::ice_transport::ice_candidate::RTCIceCandidate;
+use crate::ice_transport::ice_parameters::RTCIceParameters;
+use crate::ice_transport::ice_role::RTCIceRole;
+use crate::rtp_transceiver::rtp_codec::{
+    RTCRtpCodecCapability, RTCRtpHeaderExtensionParameters, RTCRtpParameters,
+};
+use crate::rtp_transceiver::{
+    RTCRtpCodingParameters, RTCRtpReceiveParameters, RTCRtpSendParameters,
+};
+use crate::sctp_transport::sctp_transport_capabilities::SCTPTransportCapabilities;
+use crate::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
+
+use bytes::Bytes;
+use rtp::extension::video_orientation_extension::{
+    CameraDirection, VideoOrientationExtension, VideoRotation,
+};
+use rtp::extension::HeaderExtension;
+use tokio::sync::mpsc;
+use tokio::time::Duration;
 
 #[test]
 fn test_new_api() -> Result<()> {
@@ -17,10 +38,348 @@ fn test_new_api() -> Result<()> {
         "Failed to set settings engine"
     );
     assert_eq!(
-        api.media_engine.audio_codecs.is_empty(),
+        api.media_engine.load().audio_codecs.is_empty(),
         false,
         "Failed to set media engine"
     );
 
     Ok(())
 }
+
+fn h264_codec() -> RTCRtpCodecParameters {
+    RTCRtpCodecParameters {
+        capability: RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_H264.to_owned(),
+            clock_rate: 90000,
+            channels: 0,
+            sdp_fmtp_line: "level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e01f"
+                .to_owned(),
+            rtcp_feedback: vec![],
+        },
+        payload_type: 102,
+        ..Default::default()
+    }
+}
+
+async fn offer_has_codec(pc: &RTCPeerConnection, mime_type: &str) -> Result<bool> {
+    if pc.get_transceivers().await.is_empty() {
+        pc.add_transceiver_from_kind(RTPCodecType::Video, &[])
+            .await?;
+    }
+    let offer = pc.create_offer(None).await?;
+    let encoding_name = mime_type.split('/').nth(1).unwrap_or_default();
+    Ok(offer
+        .sdp
+        .lines()
+        .any(|l| l.starts_with("a=rtpmap:") && l.contains(&format!(" {}/", encoding_name))))
+}
+
+#[tokio::test]
+async fn test_api_register_codec_after_build() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_codec(
+        RTCRtpCodecParameters {
+            capability: RTCRtpCodecCapability {
+                mime_type: MIME_TYPE_VP8.to_owned(),
+                clock_rate: 90000,
+                channels: 0,
+                sdp_fmtp_line: "".to_owned(),
+                rtcp_feedback: vec![],
+            },
+            payload_type: 96,
+            ..Default::default()
+        },
+        RTPCodecType::Video,
+    )?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let pc_before = api.new_peer_connection(RTCConfiguration::default()).await?;
+
+    api.register_codec(h264_codec(), RTPCodecType::Video)?;
+    assert_eq!(
+        api.register_codec(h264_codec(), RTPCodecType::Unspecified),
+        Err(Error::ErrUnknownType)
+    );
+    assert_eq!(api.registered_codecs(RTPCodecType::Video).len(), 2);
+
+    let pc_after = api.new_peer_connection(RTCConfiguration::default()).await?;
+
+    assert!(!offer_has_codec(&pc_before, MIME_TYPE_H264).await?);
+    assert!(offer_has_codec(&pc_before, MIME_TYPE_VP8).await?);
+    assert!(offer_has_codec(&pc_after, MIME_TYPE_H264).await?);
+    assert!(offer_has_codec(&pc_after, MIME_TYPE_VP8).await?);
+
+    // Removing the codec again doesn't affect the PeerConnection that already has it
+    assert_eq!(api.unregister_codec("video/h264"), 1);
+    assert_eq!(api.unregister_codec(MIME_TYPE_H264), 0);
+    let pc_removed = api.new_peer_connection(RTCConfiguration::default()).await?;
+    assert!(!offer_has_codec(&pc_removed, MIME_TYPE_H264).await?);
+    assert!(offer_has_codec(&pc_after, MIME_TYPE_H264).await?);
+
+    for pc in [pc_before, pc_after, pc_removed].iter() {
+        pc.close().await?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_api_register_codec_concurrently() -> Result<()> {
+    let api = Arc::new(APIBuilder::new().build());
+
+    let writers: Vec<_> = (0..4u8)
+        .map(|w| {
+            let api = Arc::clone(&api);
+            std::thread::spawn(move || {
+                for i in 0..16u8 {
+                    let mut codec = h264_codec();
+                    codec.payload_type = 32 + w * 16 + i;
+                    api.register_codec(codec, RTPCodecType::Video).unwrap();
+                }
+            })
+        })
+        .collect();
+    let reader = {
+        let api = Arc::clone(&api);
+        std::thread::spawn(move || {
+            let mut last = 0;
+            for _ in 0..1000 {
+                // Registrations are never lost or torn, so a snapshot only ever grows
+                let len = api.registered_codecs(RTPCodecType::Video).len();
+                assert!(len >= last);
+                last = len;
+            }
+        })
+    };
+
+    for w in writers {
+        w.join().unwrap();
+    }
+    reader.join().unwrap();
+
+    assert_eq!(api.registered_codecs(RTPCodecType::Video).len(), 64);
+    assert_eq!(api.unregister_codec(MIME_TYPE_H264), 64);
+    assert!(api.registered_codecs(RTPCodecType::Video).is_empty());
+
+    Ok(())
+}
+
+/// OrtcStack is one side of a connection built from the ORTC transports alone, without any
+/// SessionDescription.
+struct OrtcStack {
+    gatherer: Arc<RTCIceGatherer>,
+    ice: Arc<RTCIceTransport>,
+    dtls: Arc<RTCDtlsTransport>,
+    sctp: Arc<RTCSctpTransport>,
+}
+
+/// OrtcSignal holds what one side of an ORTC connection tells the other out of band.
+struct OrtcSignal {
+    ice_candidates: Vec<RTCIceCandidate>,
+    ice_parameters: RTCIceParameters,
+    dtls_parameters: DTLSParameters,
+    sctp_capabilities: SCTPTransportCapabilities,
+}
+
+impl OrtcStack {
+    fn new(api: &API) -> Result<Self> {
+        let gatherer = Arc::new(api.new_ice_gatherer(RTCIceGatherOptions::default())?);
+        let ice = Arc::new(api.new_ice_transport(Arc::clone(&gatherer)));
+        let dtls = Arc::new(api.new_dtls_transport(Arc::clone(&ice), vec![])?);
+        let sctp = Arc::new(api.new_sctp_transport(Arc::clone(&dtls))?);
+
+        Ok(OrtcStack {
+            gatherer,
+            ice,
+            dtls,
+            sctp,
+        })
+    }
+
+    async fn signal(&self) -> Result<OrtcSignal> {
+        let (gathered_tx, mut gathered_rx) = mpsc::channel::<()>(1);
+        self.gatherer
+            .on_local_candidate(Box::new(move |c: Option<RTCIceCandidate>| {
+                let gathered_tx = gathered_tx.clone();
+                Box::pin(async move {
+                    if c.is_none() {
+                        let _ = gathered_tx.send(()).await;
+                    }
+                })
+            }));
+        self.gatherer.gather().await?;
+        let _ = gathered_rx.recv().await;
+
+        Ok(OrtcSignal {
+            ice_candidates: self.gatherer.get_local_candidates().await?,
+            ice_parameters: self.gatherer.get_local_parameters().await?,
+            dtls_parameters: self.dtls.get_local_parameters()?,
+            sctp_capabilities: self.sctp.get_capabilities(),
+        })
+    }
+
+    async fn start(&self, remote: &OrtcSignal, role: RTCIceRole) -> Result<()> {
+        self.ice
+            .set_remote_candidates(&remote.ice_candidates)
+            .await?;
+        self.ice.start(&remote.ice_parameters, Some(role)).await?;
+        self.dtls.start(remote.dtls_parameters.clone()).await?;
+        self.sctp.start(remote.sctp_capabilities).await
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.sctp.stop().await?;
+        self.dtls.stop().await?;
+        self.ice.stop().await
+    }
+}
+
+#[tokio::test]
+async fn test_ortc_media_and_data_channel() -> Result<()> {
+    const SSRC: u32 = 0x1234_5678;
+    const PAYLOAD_TYPE: u8 = 111;
+    const ORIENTATION_ID: u8 = 7;
+
+    // The MediaEngine has no codecs, everything is given explicitly.
+    let api = APIBuilder::new().build();
+    let (a, b) = (OrtcStack::new(&api)?, OrtcStack::new(&api)?);
+
+    let (message_tx, mut message_rx) = mpsc::channel(1);
+    b.sctp
+        .on_data_channel(Box::new(move |d: Arc<RTCDataChannel>| {
+            let message_tx = message_tx.clone();
+            Box::pin(async move {
+                d.on_message(Box::new(move |msg: DataChannelMessage| {
+                    let message_tx = message_tx.clone();
+                    Box::pin(async move {
+                        let _ = message_tx.send(msg.data).await;
+                    })
+                }));
+            })
+        }));
+
+    let (signal_a, signal_b) = (a.signal().await?, b.signal().await?);
+    let (started_a, started_b) = tokio::join!(
+        a.start(&signal_b, RTCIceRole::Controlling),
+        b.start(&signal_a, RTCIceRole::Controlled)
+    );
+    started_a?;
+    started_b?;
+
+    let rtp_parameters = RTCRtpParameters {
+        header_extensions: vec![RTCRtpHeaderExtensionParameters {
+            uri: sdp::extmap::VIDEO_ORIENTATION_URI.to_owned(),
+            id: ORIENTATION_ID as isize,
+        }],
+        codecs: vec![RTCRtpCodecParameters {
+            capability: RTCRtpCodecCapability {
+                mime_type: MIME_TYPE_VP8.to_owned(),
+                clock_rate: 90000,
+                ..Default::default()
+            },
+            payload_type: PAYLOAD_TYPE,
+            ..Default::default()
+        }],
+    };
+    let encodings = vec![RTCRtpCodingParameters {
+        ssrc: SSRC,
+        payload_type: PAYLOAD_TYPE,
+        ..Default::default()
+    }];
+
+    let track = Arc::new(TrackLocalStaticRTP::new(
+        rtp_parameters.codecs[0].capability.clone(),
+        "video".to_owned(),
+        "ortc".to_owned(),
+    ));
+    let sender = api
+        .new_rtp_sender(
+            Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>,
+            Arc::clone(&a.dtls),
+            api.new_interceptor("a")?,
+        )
+        .await;
+    sender
+        .send(&RTCRtpSendParameters {
+            rtp_parameters: rtp_parameters.clone(),
+            encodings: encodings.clone(),
+        })
+        .await?;
+
+    let receiver = api.new_rtp_receiver(
+        RTPCodecType::Video,
+        Arc::clone(&b.dtls),
+        api.new_interceptor("b")?,
+    );
+    receiver
+        .receive(&RTCRtpReceiveParameters {
+            rtp_parameters,
+            encodings,
+        })
+        .await?;
+    let remote_track = receiver.track().await.expect("receiver has a track");
+
+    let writer = tokio::spawn(async move {
+        let extensions = [HeaderExtension::VideoOrientation(
+            VideoOrientationExtension {
+                direction: CameraDirection::Front,
+                flip: false,
+                rotation: VideoRotation::Degree90,
+            },
+        )];
+        for sequence_number in 0..500u16 {
+            let pkt = rtp::packet::Packet {
+                header: rtp::header::Header {
+                    version: 2,
+                    sequence_number,
+                    ..Default::default()
+                },
+                payload: Bytes::from_static(&[0x10, 0x01, 0x02, 0x03]),
+            };
+            if track
+                .write_rtp_with_extensions(&pkt, &extensions)
+                .await
+                .is_err()
+            {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    });
+
+    let (pkt, _) = tokio::time::timeout(Duration::from_secs(10), remote_track.read_rtp())
+        .await
+        .expect("no RTP received")?;
+    assert_eq!(pkt.header.ssrc, SSRC);
+    assert_eq!(pkt.header.payload_type, PAYLOAD_TYPE);
+    assert!(pkt.header.get_extension(ORIENTATION_ID).is_some());
+    assert_eq!(remote_track.ssrc(), SSRC);
+    assert_eq!(remote_track.payload_type(), PAYLOAD_TYPE);
+    assert_eq!(
+        remote_track.codec().await.capability.mime_type,
+        MIME_TYPE_VP8
+    );
+    writer.abort();
+
+    let channel = api
+        .new_data_channel(
+            Arc::clone(&a.sctp),
+            DataChannelParameters {
+                label: "ortc".to_owned(),
+                ..Default::default()
+            },
+        )
+        .await?;
+    channel.send(&Bytes::from_static(b"without SDP")).await?;
+    let message = tokio::time::timeout(Duration::from_secs(10), message_rx.recv())
+        .await
+        .expect("no message received");
+    assert_eq!(message, Some(Bytes::from_static(b"without SDP")));
+
+    sender.stop().await?;
+    receiver.stop().await?;
+    a.close().await?;
+    b.close().await?;
+
+    Ok(())
+}
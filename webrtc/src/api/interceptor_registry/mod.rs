@@ -4,11 +4,16 @@ mod interceptor_registry_test;
 use crate::api::media_engine::MediaEngine;
 use crate::error::Result;
 use crate::rtp_transceiver::rtp_codec::RTCRtpHeaderExtensionCapability;
-use crate::rtp_transceiver::{rtp_codec::RTPCodecType, RTCPFeedback, TYPE_RTCP_FB_TRANSPORT_CC};
+use crate::rtp_transceiver::{
+    rtp_codec::RTPCodecType, RTCPFeedback, TYPE_RTCP_FB_GOOG_REMB, TYPE_RTCP_FB_TRANSPORT_CC,
+};
 
+use interceptor::dependency_descriptor::DependencyDescriptorInterceptor;
 use interceptor::nack::{generator::Generator, responder::Responder};
 use interceptor::registry::Registry;
+use interceptor::remb::receiver::Receiver as RembReceiver;
 use interceptor::report::{receiver::ReceiverReport, sender::SenderReport};
+use interceptor::rtcp_scheduler::RtcpSchedulerConfig;
 use interceptor::twcc::{receiver::Receiver, sender::Sender};
 
 /// register_default_interceptors will register some useful interceptors.
@@ -36,6 +41,20 @@ pub fn configure_rtcp_reports(mut registry: Registry) -> Registry {
     registry
 }
 
+/// configure_rtcp_reports_with_scheduler will setup Sender and Receiver Reports whose interval
+/// follows the RFC 3550 timing rules for the given config. The config takes precedence over the one
+/// set with `SettingEngine::set_rtcp_session_bandwidth`.
+pub fn configure_rtcp_reports_with_scheduler(
+    mut registry: Registry,
+    config: RtcpSchedulerConfig,
+) -> Registry {
+    let receiver = Box::new(ReceiverReport::builder().with_rtcp_scheduler_config(config));
+    let sender = Box::new(SenderReport::builder().with_rtcp_scheduler_config(config));
+    registry.add(receiver);
+    registry.add(sender);
+    registry
+}
+
 /// configure_nack will setup everything necessary for handling generating/responding to nack messages.
 pub fn configure_nack(mut registry: Registry, media_engine: &mut MediaEngine) -> Registry {
     media_engine.register_feedback(
@@ -166,3 +185,50 @@ pub fn configure_twcc_receiver_only(
     registry.add(receiver);
     Ok(registry)
 }
+
+/// configure_dependency_descriptor will negotiate the AV1 Dependency Descriptor header extension
+/// for video and parse it on incoming packets. The parsed descriptor is returned with the packet
+/// attributes of `TrackRemote::read_rtp`, see `DependencyDescriptorInfo::from_attributes`.
+/// Outgoing descriptors are attached per packet with
+/// `TrackLocalStaticRTP::write_rtp_with_extensions`.
+pub fn configure_dependency_descriptor(
+    mut registry: Registry,
+    media_engine: &mut MediaEngine,
+) -> Result<Registry> {
+    media_engine.register_header_extension(
+        RTCRtpHeaderExtensionCapability {
+            uri: sdp::extmap::DEPENDENCY_DESCRIPTOR_URI.to_owned(),
+        },
+        RTPCodecType::Video,
+        None,
+    )?;
+
+    let interceptor = Box::new(DependencyDescriptorInterceptor::builder());
+    registry.add(interceptor);
+    Ok(registry)
+}
+
+/// configure_remb will negotiate REMB feedback and the abs-send-time header extension for video
+/// and send REMB packets carrying the bandwidth estimated from incoming video. The estimate comes
+/// from `interceptor::estimator::DelayLossEstimator`, add `RembReceiver::builder().with_estimator`
+/// instead to use another `Estimator`. REMB is not part of the default interceptors.
+pub fn configure_remb(mut registry: Registry, media_engine: &mut MediaEngine) -> Result<Registry> {
+    media_engine.register_feedback(
+        RTCPFeedback {
+            typ: TYPE_RTCP_FB_GOOG_REMB.to_owned(),
+            ..Default::default()
+        },
+        RTPCodecType::Video,
+    );
+    media_engine.register_header_extension(
+        RTCRtpHeaderExtensionCapability {
+            uri: sdp::extmap::ABS_SEND_TIME_URI.to_owned(),
+        },
+        RTPCodecType::Video,
+        None,
+    )?;
+
+    let receiver = Box::new(RembReceiver::builder());
+    registry.add(receiver);
+    Ok(registry)
+}
@@ -533,7 +533,11 @@ async fn test_media_engine_header_extension_direction() -> Result<()> {
         )?;
 
         let params = m
-            .get_rtp_parameters_by_kind(RTPCodecType::Audio, RTCRtpTransceiverDirection::Recvonly)
+            .get_rtp_parameters_by_kind(
+                RTPCodecType::Audio,
+                RTCRtpTransceiverDirection::Recvonly,
+                &[],
+            )
             .await;
 
         assert_eq!(1, params.header_extensions.len());
@@ -552,7 +556,11 @@ async fn test_media_engine_header_extension_direction() -> Result<()> {
         )?;
 
         let params = m
-            .get_rtp_parameters_by_kind(RTPCodecType::Audio, RTCRtpTransceiverDirection::Recvonly)
+            .get_rtp_parameters_by_kind(
+                RTPCodecType::Audio,
+                RTCRtpTransceiverDirection::Recvonly,
+                &[],
+            )
             .await;
 
         assert_eq!(1, params.header_extensions.len());
@@ -571,7 +579,11 @@ async fn test_media_engine_header_extension_direction() -> Result<()> {
         )?;
 
         let params = m
-            .get_rtp_parameters_by_kind(RTPCodecType::Audio, RTCRtpTransceiverDirection::Recvonly)
+            .get_rtp_parameters_by_kind(
+                RTPCodecType::Audio,
+                RTCRtpTransceiverDirection::Recvonly,
+                &[],
+            )
             .await;
 
         assert_eq!(0, params.header_extensions.len());
@@ -590,7 +602,11 @@ async fn test_media_engine_header_extension_direction() -> Result<()> {
         )?;
 
         let params = m
-            .get_rtp_parameters_by_kind(RTPCodecType::Audio, RTCRtpTransceiverDirection::Inactive)
+            .get_rtp_parameters_by_kind(
+                RTPCodecType::Audio,
+                RTCRtpTransceiverDirection::Inactive,
+                &[],
+            )
             .await;
 
         assert_eq!(1, params.header_extensions.len());
@@ -765,7 +781,11 @@ a=rtpmap:111 opus/48000/2
     assert!(!mid_video_enabled);
 
     let params = m
-        .get_rtp_parameters_by_kind(RTPCodecType::Video, RTCRtpTransceiverDirection::Sendonly)
+        .get_rtp_parameters_by_kind(
+            RTPCodecType::Video,
+            RTCRtpTransceiverDirection::Sendonly,
+            &[],
+        )
         .await;
     dbg!(&params);
 
@@ -780,3 +800,123 @@ a=rtpmap:111 opus/48000/2
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_media_engine_rtcp_feedback_negotiation() -> Result<()> {
+    const VP8_WITHOUT_NACK: &str = "v=0
+o=- 4596489990601351948 2 IN IP4 127.0.0.1
+s=-
+t=0 0
+m=video 60323 UDP/TLS/RTP/SAVPF 96
+a=rtpmap:96 VP8/90000
+a=rtcp-fb:96 nack pli
+a=rtcp-fb:96 transport-cc
+a=rtcp-fb:96 unknown-feedback
+";
+
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    m.register_feedback_for_codec(
+        RTCPFeedback {
+            typ: "transport-cc".to_owned(),
+            parameter: "".to_owned(),
+        },
+        MIME_TYPE_VP8,
+    );
+
+    let vp8 = m
+        .video_codecs
+        .iter()
+        .find(|c| c.capability.mime_type == MIME_TYPE_VP8)
+        .unwrap();
+    assert!(vp8
+        .capability
+        .rtcp_feedback
+        .iter()
+        .any(|fb| fb.typ == "transport-cc"));
+    let h264 = m
+        .video_codecs
+        .iter()
+        .find(|c| c.capability.mime_type == MIME_TYPE_H264)
+        .unwrap();
+    assert!(!h264
+        .capability
+        .rtcp_feedback
+        .iter()
+        .any(|fb| fb.typ == "transport-cc"));
+
+    let mut reader = Cursor::new(VP8_WITHOUT_NACK.as_bytes());
+    m.update_from_remote_description(&SessionDescription::unmarshal(&mut reader)?)
+        .await?;
+
+    // Only the feedback offered by both sides is kept.
+    let (vp8, _) = m.get_codec_by_payload(96).await?;
+    assert_eq!(
+        vp8.capability.rtcp_feedback,
+        vec![
+            RTCPFeedback {
+                typ: "nack".to_owned(),
+                parameter: "pli".to_owned(),
+            },
+            RTCPFeedback {
+                typ: "transport-cc".to_owned(),
+                parameter: "".to_owned(),
+            },
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_media_engine_unregister_codec() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    m.register_codec(
+        RTCRtpCodecParameters {
+            capability: RTCRtpCodecCapability {
+                mime_type: "video/rtx".to_owned(),
+                clock_rate: 90000,
+                channels: 0,
+                sdp_fmtp_line: "apt=96".to_owned(),
+                rtcp_feedback: vec![],
+            },
+            payload_type: 97,
+            ..Default::default()
+        },
+        RTPCodecType::Video,
+    )?;
+    m.register_codec(
+        RTCRtpCodecParameters {
+            capability: RTCRtpCodecCapability {
+                mime_type: "video/rtx".to_owned(),
+                clock_rate: 90000,
+                channels: 0,
+                sdp_fmtp_line: "apt=102".to_owned(),
+                rtcp_feedback: vec![],
+            },
+            payload_type: 103,
+            ..Default::default()
+        },
+        RTPCodecType::Video,
+    )?;
+
+    let h264_count = m
+        .registered_codecs(RTPCodecType::Video)
+        .iter()
+        .filter(|c| c.capability.mime_type == MIME_TYPE_H264)
+        .count();
+    assert!(h264_count > 0);
+
+    // The RTX codec repairing H264 goes along with it, the one repairing VP8 stays
+    assert_eq!(m.unregister_codec("VIDEO/h264"), h264_count + 1);
+    let video = m.registered_codecs(RTPCodecType::Video);
+    assert!(video
+        .iter()
+        .all(|c| c.capability.mime_type != MIME_TYPE_H264 && c.payload_type != 103));
+    assert!(video.iter().any(|c| c.payload_type == 97));
+    assert!(!m.registered_codecs(RTPCodecType::Audio).is_empty());
+    assert!(m.registered_codecs(RTPCodecType::Unspecified).is_empty());
+
+    Ok(())
+}
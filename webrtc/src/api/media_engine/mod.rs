@@ -8,8 +8,8 @@ use crate::peer_connection::sdp::{
 use crate::rtp_transceiver::fmtp;
 use crate::rtp_transceiver::rtp_codec::{
     codec_parameters_fuzzy_search, CodecMatch, RTCRtpCodecCapability, RTCRtpCodecParameters,
-    RTCRtpHeaderExtensionCapability, RTCRtpHeaderExtensionParameters, RTCRtpParameters,
-    RTPCodecType,
+    RTCRtpHeaderExtensionCapability, RTCRtpHeaderExtensionParameters,
+    RTCRtpHeaderExtensionToNegotiate, RTCRtpParameters, RTPCodecType,
 };
 use crate::rtp_transceiver::rtp_transceiver_direction::RTCRtpTransceiverDirection;
 use crate::rtp_transceiver::{PayloadType, RTCPFeedback};
@@ -17,11 +17,11 @@ use crate::stats::stats_collector::StatsCollector;
 use crate::stats::CodecStats;
 use crate::stats::StatsReportType::Codec;
 
+use sdp::description::media::MediaDescription;
 use sdp::description::session::SessionDescription;
 use std::collections::HashMap;
 use std::ops::Range;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::Mutex;
 
 /// MIME_TYPE_H264 H264 MIME type.
@@ -74,6 +74,27 @@ impl MediaEngineHeaderExtension {
             true
         }
     }
+
+    /// is_enabled_for reports whether the extension should be negotiated for a transceiver of the
+    /// given kind and direction. An entry in `to_negotiate` overrides the allowed direction the
+    /// extension was registered with.
+    fn is_enabled_for(
+        &self,
+        typ: RTPCodecType,
+        direction: RTCRtpTransceiverDirection,
+        to_negotiate: &[RTCRtpHeaderExtensionToNegotiate],
+    ) -> bool {
+        if !(self.is_audio && typ == RTPCodecType::Audio
+            || self.is_video && typ == RTPCodecType::Video)
+        {
+            return false;
+        }
+
+        match to_negotiate.iter().find(|e| e.uri == self.uri) {
+            Some(e) => !e.is_stopped(),
+            None => self.is_matching_direction(direction),
+        }
+    }
 }
 
 /// A MediaEngine defines the codecs supported by a PeerConnection, and the
@@ -317,13 +338,8 @@ impl MediaEngine {
         mut codec: RTCRtpCodecParameters,
         typ: RTPCodecType,
     ) -> Result<()> {
-        codec.stats_id = format!(
-            "RTPCodec-{}",
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_nanos()
-        );
+        // The payload type is unique per kind, so the id is stable across reports
+        codec.stats_id = format!("RTPCodec-{}-{}", typ, codec.payload_type);
         match typ {
             RTPCodecType::Audio => {
                 MediaEngine::add_codec(&mut self.audio_codecs, codec);
@@ -337,6 +353,40 @@ impl MediaEngine {
         }
     }
 
+    /// unregister_codec removes every codec with the given mime type from the MediaEngine, along
+    /// with the retransmission codecs pointing to them with `apt`. Returns the number of codecs
+    /// removed.
+    pub fn unregister_codec(&mut self, mime_type: &str) -> usize {
+        let mut removed = 0;
+        for codecs in [&mut self.video_codecs, &mut self.audio_codecs] {
+            let payload_types: Vec<String> = codecs
+                .iter()
+                .filter(|c| c.capability.mime_type.eq_ignore_ascii_case(mime_type))
+                .map(|c| c.payload_type.to_string())
+                .collect();
+            let before = codecs.len();
+            codecs.retain(|c| {
+                if c.capability.mime_type.eq_ignore_ascii_case(mime_type) {
+                    return false;
+                }
+                let fmtp = fmtp::parse(&c.capability.mime_type, &c.capability.sdp_fmtp_line);
+                !matches!(fmtp.parameter("apt"), Some(apt) if payload_types.contains(apt))
+            });
+            removed += before - codecs.len();
+        }
+        removed
+    }
+
+    /// registered_codecs returns the codecs of the given kind registered with the MediaEngine,
+    /// regardless of what has been negotiated.
+    pub fn registered_codecs(&self, typ: RTPCodecType) -> Vec<RTCRtpCodecParameters> {
+        match typ {
+            RTPCodecType::Audio => self.audio_codecs.clone(),
+            RTPCodecType::Video => self.video_codecs.clone(),
+            _ => vec![],
+        }
+    }
+
     /// Adds a header extension to the MediaEngine
     /// To determine the negotiated value use [`get_header_extension_id`] after signaling is complete.
     ///
@@ -404,6 +454,22 @@ impl MediaEngine {
         }
     }
 
+    /// register_feedback_for_codec adds feedback mechanism to the already registered codecs of
+    /// the given mime type only, e.g. to offer transport-cc for VP8 but not for H264.
+    pub fn register_feedback_for_codec(&mut self, feedback: RTCPFeedback, mime_type: &str) {
+        for c in self
+            .video_codecs
+            .iter_mut()
+            .chain(self.audio_codecs.iter_mut())
+        {
+            if c.capability.mime_type.eq_ignore_ascii_case(mime_type)
+                && !c.capability.rtcp_feedback.contains(&feedback)
+            {
+                c.capability.rtcp_feedback.push(feedback.clone());
+            }
+        }
+    }
+
     /// get_header_extension_id returns the negotiated ID for a header extension.
     /// If the Header Extension isn't enabled ok will be false
     pub(crate) async fn get_header_extension_id(
@@ -424,6 +490,16 @@ impl MediaEngine {
         (0, false, false)
     }
 
+    /// has_header_extension returns true if a header extension with the given uri has been
+    /// registered for the given kind.
+    pub(crate) fn has_header_extension(&self, uri: &str, typ: RTPCodecType) -> bool {
+        self.header_extensions.iter().any(|e| {
+            e.uri == uri
+                && (e.is_audio && typ == RTPCodecType::Audio
+                    || e.is_video && typ == RTPCodecType::Video)
+        })
+    }
+
     /// clone_to copies any user modifiable state of the MediaEngine
     /// all internal state is reset
     pub(crate) fn clone_to(&self) -> Self {
@@ -527,6 +603,22 @@ impl MediaEngine {
         Ok(match_type)
     }
 
+    /// Keep only the feedback of a remote codec which we offer for the matching local codec, the
+    /// result being what both sides agreed to send and receive.
+    fn negotiate_rtcp_feedback(&self, remote_codec: &mut RTCRtpCodecParameters, typ: RTPCodecType) {
+        let codecs = if typ == RTPCodecType::Audio {
+            &self.audio_codecs
+        } else {
+            &self.video_codecs
+        };
+
+        let (local_codec, _) = codec_parameters_fuzzy_search(remote_codec, codecs);
+        remote_codec
+            .capability
+            .rtcp_feedback
+            .retain(|feedback| local_codec.capability.rtcp_feedback.contains(feedback));
+    }
+
     /// Look up a header extension and enable if it exists
     pub(crate) async fn update_header_extension(
         &self,
@@ -607,34 +699,19 @@ impl MediaEngine {
                 self.negotiated_video.store(true, Ordering::SeqCst);
                 RTPCodecType::Video
             } else {
+                // Codecs are only negotiated from the first media section of a kind, but header
+                // extensions can be enabled per transceiver, so pick up the ones of the others too.
+                self.update_header_extensions_from_media_description(media)
+                    .await?;
                 continue;
             };
 
-            let codecs = codecs_from_media_description(media)?;
-
-            let mut exact_matches = vec![]; //make([]RTPCodecParameters, 0, len(codecs))
-            let mut partial_matches = vec![]; //make([]RTPCodecParameters, 0, len(codecs))
-
-            for codec in codecs {
-                let match_type =
-                    self.match_remote_codec(&codec, typ, &exact_matches, &partial_matches)?;
-
-                if match_type == CodecMatch::Exact {
-                    exact_matches.push(codec);
-                } else if match_type == CodecMatch::Partial {
-                    partial_matches.push(codec);
-                }
-            }
-
-            // use exact matches when they exist, otherwise fall back to partial
-            if !exact_matches.is_empty() {
-                self.push_codecs(exact_matches, typ).await;
-            } else if !partial_matches.is_empty() {
-                self.push_codecs(partial_matches, typ).await;
-            } else {
+            let codecs = self.negotiate_codecs(media, typ)?;
+            if codecs.is_empty() {
                 // no match, not negotiated
                 continue;
             }
+            self.push_codecs(codecs, typ).await;
 
             let extensions = rtp_extensions_from_media_description(media)?;
 
@@ -646,6 +723,60 @@ impl MediaEngine {
         Ok(())
     }
 
+    /// negotiate_codecs returns the codecs of a remote media description that match a registered
+    /// codec, with the payload types of the remote and the feedback both sides agreed on.
+    pub(crate) fn negotiate_codecs(
+        &self,
+        media: &MediaDescription,
+        typ: RTPCodecType,
+    ) -> Result<Vec<RTCRtpCodecParameters>> {
+        let codecs = codecs_from_media_description(media)?;
+
+        let mut exact_matches = vec![]; //make([]RTPCodecParameters, 0, len(codecs))
+        let mut partial_matches = vec![]; //make([]RTPCodecParameters, 0, len(codecs))
+
+        for mut codec in codecs {
+            let match_type =
+                self.match_remote_codec(&codec, typ, &exact_matches, &partial_matches)?;
+            if match_type != CodecMatch::None {
+                self.negotiate_rtcp_feedback(&mut codec, typ);
+            }
+
+            if match_type == CodecMatch::Exact {
+                exact_matches.push(codec);
+            } else if match_type == CodecMatch::Partial {
+                partial_matches.push(codec);
+            }
+        }
+
+        // use exact matches when they exist, otherwise fall back to partial
+        if !exact_matches.is_empty() {
+            Ok(exact_matches)
+        } else {
+            Ok(partial_matches)
+        }
+    }
+
+    /// Enable the header extensions of a media section whose kind already has negotiated codecs
+    async fn update_header_extensions_from_media_description(
+        &self,
+        media: &MediaDescription,
+    ) -> Result<()> {
+        let typ = match media.media_name.media.to_lowercase().as_str() {
+            "audio" if !self.negotiated_audio_codecs.lock().await.is_empty() => RTPCodecType::Audio,
+            "video" if !self.negotiated_video_codecs.lock().await.is_empty() => RTPCodecType::Video,
+            _ => return Ok(()),
+        };
+
+        let extensions = rtp_extensions_from_media_description(media)?;
+
+        for (extension, id) in extensions {
+            self.update_header_extension(id, &extension, typ).await?;
+        }
+
+        Ok(())
+    }
+
     pub(crate) async fn get_codecs_by_kind(&self, typ: RTPCodecType) -> Vec<RTCRtpCodecParameters> {
         if typ == RTPCodecType::Video {
             if self.negotiated_video.load(Ordering::SeqCst) {
@@ -666,22 +797,28 @@ impl MediaEngine {
         }
     }
 
+    /// is_negotiated returns true once a remote description has been applied for the given kind.
+    pub(crate) fn is_negotiated(&self, typ: RTPCodecType) -> bool {
+        self.negotiated_video.load(Ordering::SeqCst) && typ == RTPCodecType::Video
+            || self.negotiated_audio.load(Ordering::SeqCst) && typ == RTPCodecType::Audio
+    }
+
+    /// get_rtp_parameters_by_kind returns the parameters for a transceiver of the given kind and
+    /// direction. `header_extensions_to_negotiate` holds the per-transceiver header extension
+    /// choices made with `set_header_extensions_to_negotiate`; pass an empty slice to use the
+    /// MediaEngine defaults.
     pub(crate) async fn get_rtp_parameters_by_kind(
         &self,
         typ: RTPCodecType,
         direction: RTCRtpTransceiverDirection,
+        header_extensions_to_negotiate: &[RTCRtpHeaderExtensionToNegotiate],
     ) -> RTCRtpParameters {
         let mut header_extensions = vec![];
 
-        if self.negotiated_video.load(Ordering::SeqCst) && typ == RTPCodecType::Video
-            || self.negotiated_audio.load(Ordering::SeqCst) && typ == RTPCodecType::Audio
-        {
+        if self.is_negotiated(typ) {
             let negotiated_header_extensions = self.negotiated_header_extensions.lock().await;
             for (id, e) in &*negotiated_header_extensions {
-                if e.is_matching_direction(direction)
-                    && (e.is_audio && typ == RTPCodecType::Audio
-                        || e.is_video && typ == RTPCodecType::Video)
-                {
+                if e.is_enabled_for(typ, direction, header_extensions_to_negotiate) {
                     header_extensions.push(RTCRtpHeaderExtensionParameters {
                         id: *id,
                         uri: e.uri.clone(),
@@ -693,11 +830,7 @@ impl MediaEngine {
             let mut negotiated_header_extensions = self.negotiated_header_extensions.lock().await;
 
             for local_extension in &self.header_extensions {
-                let relevant = local_extension.is_matching_direction(direction)
-                    && (local_extension.is_audio && typ == RTPCodecType::Audio
-                        || local_extension.is_video && typ == RTPCodecType::Video);
-
-                if !relevant {
+                if !local_extension.is_enabled_for(typ, direction, header_extensions_to_negotiate) {
                     continue;
                 }
 
@@ -20,12 +20,15 @@ use crate::error::{Error, Result};
 use crate::peer_connection::configuration::RTCConfiguration;
 use crate::peer_connection::RTCPeerConnection;
 use crate::rtp_transceiver::rtp_codec::RTPCodecType;
+use crate::rtp_transceiver::rtp_codec::{RTCRtpCodecParameters, RTCRtpHeaderExtensionCapability};
 use crate::rtp_transceiver::rtp_receiver::RTCRtpReceiver;
 use crate::rtp_transceiver::rtp_sender::RTCRtpSender;
 use crate::sctp_transport::RTCSctpTransport;
 use crate::track::track_local::TrackLocal;
+use interceptor::video_orientation::VideoOrientationInterceptor;
 use interceptor::{registry::Registry, Interceptor};
 
+use arc_swap::ArcSwap;
 use rcgen::KeyPair;
 use std::sync::Arc;
 use std::time::SystemTime;
@@ -36,7 +39,9 @@ use std::time::SystemTime;
 /// may be phased out in the future.
 pub struct API {
     pub(crate) setting_engine: Arc<SettingEngine>,
-    pub(crate) media_engine: Arc<MediaEngine>,
+    /// Codecs registered at runtime replace the MediaEngine, each PeerConnection takes a snapshot
+    /// of the current one when it is created.
+    pub(crate) media_engine: ArcSwap<MediaEngine>,
     pub(crate) interceptor_registry: Registry,
 }
 
@@ -49,6 +54,43 @@ impl API {
         RTCPeerConnection::new(self, configuration).await
     }
 
+    /// register_codec adds a codec to the MediaEngine of the API after it has been built, e.g.
+    /// once a hardware encoder turned out to be available. Only PeerConnections created
+    /// afterwards offer the codec, existing ones keep the codecs they were created with.
+    pub fn register_codec(&self, codec: RTCRtpCodecParameters, typ: RTPCodecType) -> Result<()> {
+        if typ != RTPCodecType::Audio && typ != RTPCodecType::Video {
+            return Err(Error::ErrUnknownType);
+        }
+
+        self.media_engine.rcu(|media_engine| {
+            let mut media_engine = media_engine.clone_to();
+            // The kind was checked above, so the registration can't fail
+            let _ = media_engine.register_codec(codec.clone(), typ);
+            media_engine
+        });
+
+        Ok(())
+    }
+
+    /// unregister_codec removes the codecs with the given mime type from the MediaEngine of the
+    /// API, see [`MediaEngine::unregister_codec`]. Like [`API::register_codec`] it only affects
+    /// PeerConnections created afterwards. Returns the number of codecs removed.
+    pub fn unregister_codec(&self, mime_type: &str) -> usize {
+        let mut removed = 0;
+        self.media_engine.rcu(|media_engine| {
+            let mut media_engine = media_engine.clone_to();
+            removed = media_engine.unregister_codec(mime_type);
+            media_engine
+        });
+        removed
+    }
+
+    /// registered_codecs returns the codecs of the given kind new PeerConnections are created
+    /// with.
+    pub fn registered_codecs(&self, typ: RTPCodecType) -> Vec<RTCRtpCodecParameters> {
+        self.media_engine.load().registered_codecs(typ)
+    }
+
     /// new_ice_gatherer creates a new ice gatherer.
     /// This constructor is part of the ORTC API. It is not
     /// meant to be used together with the basic WebRTC API.
@@ -129,12 +171,22 @@ impl API {
             return Err(Error::ErrStringSizeLimit);
         }
 
-        let d = RTCDataChannel::new(params, Arc::clone(&self.setting_engine));
+        let d = RTCDataChannel::new(
+            params,
+            sctp_transport.next_data_channel_stats_id(),
+            Arc::clone(&self.setting_engine),
+        );
         d.open(sctp_transport).await?;
 
         Ok(d)
     }
 
+    /// new_interceptor builds the interceptors registered with the API, for the RTPSenders and
+    /// RTPReceivers of a DTLSTransport constructed with the ORTC API.
+    pub fn new_interceptor(&self, id: &str) -> Result<Arc<dyn Interceptor + Send + Sync>> {
+        Ok(self.interceptor_registry.build(id)?)
+    }
+
     /// new_rtp_receiver constructs a new RTPReceiver
     pub fn new_rtp_receiver(
         &self,
@@ -146,8 +198,9 @@ impl API {
             self.setting_engine.get_receive_mtu(),
             kind,
             transport,
-            Arc::clone(&self.media_engine),
+            self.media_engine.load_full(),
             interceptor,
+            self.setting_engine.timeout.track_inactivity_timeout,
         )
     }
 
@@ -162,7 +215,7 @@ impl API {
             self.setting_engine.get_receive_mtu(),
             track,
             transport,
-            Arc::clone(&self.media_engine),
+            self.media_engine.load_full(),
             interceptor,
             false,
         )
@@ -173,7 +226,7 @@ impl API {
 #[derive(Default)]
 pub struct APIBuilder {
     setting_engine: Option<Arc<SettingEngine>>,
-    media_engine: Option<Arc<MediaEngine>>,
+    media_engine: Option<MediaEngine>,
     interceptor_registry: Option<Registry>,
 }
 
@@ -183,24 +236,39 @@ impl APIBuilder {
     }
 
     pub fn build(mut self) -> API {
+        let setting_engine = if let Some(setting_engine) = self.setting_engine.take() {
+            setting_engine
+        } else {
+            Arc::new(SettingEngine::default())
+        };
+        let mut media_engine = self.media_engine.take().unwrap_or_default();
+        let mut interceptor_registry = self.interceptor_registry.take().unwrap_or_default();
+
+        // Opt-in, see SettingEngine::set_video_orientation
+        if setting_engine.video_orientation {
+            match media_engine.register_header_extension(
+                RTCRtpHeaderExtensionCapability {
+                    uri: sdp::extmap::VIDEO_ORIENTATION_URI.to_owned(),
+                },
+                RTPCodecType::Video,
+                None,
+            ) {
+                Ok(()) => {
+                    interceptor_registry.add(Box::new(VideoOrientationInterceptor::builder()))
+                }
+                Err(err) => log::warn!("failed to enable the video orientation extension: {}", err),
+            }
+        }
+
+        // See SettingEngine::set_rtcp_session_bandwidth
+        if let Some(config) = setting_engine.rtcp_scheduler_config() {
+            interceptor_registry.set_rtcp_scheduler_config(config);
+        }
+
         API {
-            setting_engine: if let Some(setting_engine) = self.setting_engine.take() {
-                setting_engine
-            } else {
-                Arc::new(SettingEngine::default())
-            },
-            media_engine: if let Some(media_engine) = self.media_engine.take() {
-                media_engine
-            } else {
-                Arc::new(MediaEngine::default())
-            },
-            interceptor_registry: if let Some(interceptor_registry) =
-                self.interceptor_registry.take()
-            {
-                interceptor_registry
-            } else {
-                Registry::new()
-            },
+            setting_engine,
+            media_engine: ArcSwap::from_pointee(media_engine),
+            interceptor_registry,
         }
     }
 
@@ -212,9 +280,10 @@ impl APIBuilder {
     }
 
     /// WithMediaEngine allows providing a MediaEngine to the API.
-    /// Settings can be changed after passing the engine to an API.
+    /// Codecs can still be changed after passing the engine to an API, see
+    /// [`API::register_codec`].
     pub fn with_media_engine(mut self, media_engine: MediaEngine) -> Self {
-        self.media_engine = Some(Arc::new(media_engine));
+        self.media_engine = Some(media_engine);
         self
     }
 
@@ -4,10 +4,14 @@ mod setting_engine_test;
 use crate::dtls_transport::dtls_role::DTLSRole;
 use crate::ice_transport::ice_candidate_type::RTCIceCandidateType;
 use dtls::extension::extension_use_srtp::SrtpProtectionProfile;
-use ice::agent::agent_config::{InterfaceFilterFn, IpFilterFn};
+use ice::agent::agent_config::{InterfaceFilterFn, IpFilterFn, NetworkCostFn};
 use ice::mdns::MulticastDnsMode;
 use ice::network_type::NetworkType;
+use ice::resolver::Resolver;
 use ice::udp_network::UDPNetwork;
+use interceptor::dump::DumpBuilder;
+use interceptor::rtcp_scheduler::RtcpSchedulerConfig;
+use interceptor::SharedTimeSource;
 
 use crate::error::{Error, Result};
 
@@ -30,14 +34,26 @@ pub struct Timeout {
     pub ice_srflx_acceptance_min_wait: Option<Duration>,
     pub ice_prflx_acceptance_min_wait: Option<Duration>,
     pub ice_relay_acceptance_min_wait: Option<Duration>,
+    pub ice_dns_timeout: Option<Duration>,
+    pub track_inactivity_timeout: Option<Duration>,
+    pub dtls_handshake_timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    pub simulcast_rtcp_probe_timeout: Option<Duration>,
 }
 
 #[derive(Default, Clone)]
 pub struct Candidates {
     pub ice_lite: bool,
+    pub ice_renomination: bool,
+    pub ice_renomination_hold_time: Option<Duration>,
+    pub ice_prefer_low_cost: bool,
     pub ice_network_types: Vec<NetworkType>,
+    pub ice_tcp_active_candidates: bool,
+    pub ice_turn_over_tcp: bool,
+    pub ice_resolver: Option<Arc<dyn Resolver + Send + Sync>>,
     pub interface_filter: Arc<Option<InterfaceFilterFn>>,
     pub ip_filter: Arc<Option<IpFilterFn>>,
+    pub network_cost: Arc<Option<NetworkCostFn>>,
     pub nat_1to1_ips: Vec<String>,
     pub nat_1to1_ip_candidate_type: RTCIceCandidateType,
     pub multicast_dns_mode: MulticastDnsMode,
@@ -77,6 +93,14 @@ pub struct SettingEngine {
     pub(crate) srtp_protection_profiles: Vec<SrtpProtectionProfile>,
     pub(crate) receive_mtu: usize,
     pub(crate) mid_generator: Option<Arc<dyn Fn(isize) -> String + Send + Sync>>,
+    pub(crate) rtcp_session_bandwidth: Option<u32>,
+    pub(crate) sctp_max_retransmissions: usize,
+    pub(crate) sctp_num_streams: (u16, u16),
+    pub(crate) data_channel_pending_message_limit: usize,
+    pub(crate) video_orientation: bool,
+    pub(crate) cryptex: bool,
+    pub(crate) packet_dump: Option<Arc<DumpBuilder>>,
+    pub(crate) stats_time_source: Option<SharedTimeSource>,
 }
 
 impl SettingEngine {
@@ -116,6 +140,39 @@ impl SettingEngine {
         self.timeout.ice_keepalive_interval = keep_alive_interval;
     }
 
+    /// set_track_inactivity_timeout sets how long a remote track may go without receiving
+    /// RTP before it is muted. The track is unmuted when packets arrive again. With
+    /// simulcast the track is only muted once every layer is inactive. Default is None,
+    /// tracks are then only muted when the remote stops sending through renegotiation.
+    pub fn set_track_inactivity_timeout(&mut self, t: Option<Duration>) {
+        self.timeout.track_inactivity_timeout = t;
+    }
+
+    /// set_dtls_handshake_timeout sets how long the DTLS handshake may take once ICE connected.
+    /// The DTLS transport and the PeerConnection then fail with
+    /// `RTCConnectionError::DtlsHandshakeTimeout`. An ICE restart during the handshake restarts
+    /// the wait. Default is None, the handshake is retransmitted until the PeerConnection is closed.
+    pub fn set_dtls_handshake_timeout(&mut self, t: Option<Duration>) {
+        self.timeout.dtls_handshake_timeout = t;
+    }
+
+    /// set_connect_timeout sets how long a PeerConnection may take to connect once it has both
+    /// descriptions and started ICE. It then fails with `RTCConnectionError::ConnectTimeout`. An
+    /// ICE restart before the PeerConnection connected restarts the wait. Default is None.
+    pub fn set_connect_timeout(&mut self, t: Option<Duration>) {
+        self.timeout.connect_timeout = t;
+    }
+
+    /// set_simulcast_rtcp_probe_timeout sets how long an incoming SSRC that no media section
+    /// declares is probed for its MID and RID. Its first RTP packets are read for the header
+    /// extensions, and its RTCP for SDES items telling them, which browsers send with their
+    /// reports, about once a second. `on_track` fires for the SSRC only once they are known, so
+    /// a layer whose packets carry no header extensions is delayed until its first SDES
+    /// arrives, and dropped after the timeout. Default is 5 seconds.
+    pub fn set_simulcast_rtcp_probe_timeout(&mut self, t: Duration) {
+        self.timeout.simulcast_rtcp_probe_timeout = Some(t);
+    }
+
     /// set_host_acceptance_min_wait sets the icehost_acceptance_min_wait
     pub fn set_host_acceptance_min_wait(&mut self, t: Option<Duration>) {
         self.timeout.ice_host_acceptance_min_wait = t;
@@ -148,12 +205,61 @@ impl SettingEngine {
         self.candidates.ice_lite = lite;
     }
 
+    /// set_ice_renomination allows the controlling ice agent to nominate a better candidate pair
+    /// after a pair was selected. It is advertised with a=ice-options and only used if the remote
+    /// peer advertises renomination as well.
+    pub fn set_ice_renomination(&mut self, renomination: bool) {
+        self.candidates.ice_renomination = renomination;
+    }
+
+    /// set_ice_renomination_hold_time sets how long a candidate pair has to stay better than the
+    /// selected pair before it is renominated. Defaults to 0.
+    pub fn set_ice_renomination_hold_time(&mut self, hold_time: Duration) {
+        self.candidates.ice_renomination_hold_time = Some(hold_time);
+    }
+
+    /// set_ice_prefer_low_cost makes the ice agent prefer candidate pairs with a lower network
+    /// cost over pairs of the same priority, e.g. a Wi-Fi path over a cellular one. The cost
+    /// of local host candidates is set with set_network_cost, the one of remote candidates is
+    /// signaled with their network-cost extension.
+    pub fn set_ice_prefer_low_cost(&mut self, prefer_low_cost: bool) {
+        self.candidates.ice_prefer_low_cost = prefer_low_cost;
+    }
+
     /// set_network_types configures what types of candidate networks are supported
     /// during local and server reflexive gathering.
     pub fn set_network_types(&mut self, candidate_types: Vec<NetworkType>) {
         self.candidates.ice_network_types = candidate_types;
     }
 
+    /// set_ice_tcp_active_candidates enables gathering active TCP candidates, which connect to
+    /// the passive TCP candidates of the remote peer, besides the passive ones. TCP candidates
+    /// are only gathered if the network types set with set_network_types include a TCP type.
+    /// DTLS and SCTP run over TCP pairs unchanged, packets are framed as in RFC 4571.
+    pub fn set_ice_tcp_active_candidates(&mut self, active: bool) {
+        self.candidates.ice_tcp_active_candidates = active;
+    }
+
+    /// set_ice_turn_over_tcp allows gathering relay candidates from the `turn:` URLs of
+    /// RTCConfiguration with `transport=tcp`, reaching the TURN server over TCP. `turns:` URLs
+    /// (TURN over TLS) aren't supported yet, they fire on_ice_candidate_error during gathering.
+    pub fn set_ice_turn_over_tcp(&mut self, turn_over_tcp: bool) {
+        self.candidates.ice_turn_over_tcp = turn_over_tcp;
+    }
+
+    /// set_ice_resolver sets the resolver looking up the host names of the STUN and TURN
+    /// servers, instead of the one of the system or of the virtual network.
+    pub fn set_ice_resolver(&mut self, resolver: Arc<dyn Resolver + Send + Sync>) {
+        self.candidates.ice_resolver = Some(resolver);
+    }
+
+    /// set_ice_dns_timeout sets how long looking up the host name of a STUN or TURN server may
+    /// take, 5 seconds by default. Servers which can't be resolved in time are reported to
+    /// `RTCPeerConnection::on_ice_candidate_error`, without delaying the candidates of others.
+    pub fn set_ice_dns_timeout(&mut self, timeout: Duration) {
+        self.timeout.ice_dns_timeout = Some(timeout);
+    }
+
     /// set_interface_filter sets the filtering functions when gathering ICE candidates
     /// This can be used to exclude certain network interfaces from ICE. Which may be
     /// useful if you know a certain interface will never succeed, or if you wish to reduce
@@ -170,6 +276,13 @@ impl SettingEngine {
         self.candidates.ip_filter = Arc::new(Some(filter));
     }
 
+    /// set_network_cost sets the function returning the network cost of the host candidates
+    /// gathered on an interface, given its name and the IP of the candidate. The cost is signaled
+    /// to the remote peer as the network-cost extension of the candidates.
+    pub fn set_network_cost(&mut self, network_cost: NetworkCostFn) {
+        self.candidates.network_cost = Arc::new(Some(network_cost));
+    }
+
     /// set_nat_1to1_ips sets a list of external IP addresses of 1:1 (D)NAT
     /// and a candidate type for which the external IP address is used.
     /// This is useful when you are host a server using Pion on an AWS EC2 instance
@@ -301,6 +414,8 @@ impl SettingEngine {
     /// disable_media_engine_copy stops the MediaEngine from being copied. This allows a user to modify
     /// the MediaEngine after the PeerConnection has been constructed. This is useful if you wish to
     /// modify codecs after signaling. Make sure not to share MediaEngines between PeerConnections.
+    /// The PeerConnection shares the MediaEngine of the API as it was when it was created, codecs
+    /// registered later with `API::register_codec` go to a new MediaEngine.
     pub fn disable_media_engine_copy(&mut self, is_disabled: bool) {
         self.disable_media_engine_copy = is_disabled;
     }
@@ -324,4 +439,81 @@ impl SettingEngine {
     pub fn set_mid_generator(&mut self, f: impl Fn(isize) -> String + Send + Sync + 'static) {
         self.mid_generator = Some(Arc::new(f));
     }
+
+    /// set_rtcp_session_bandwidth sets the session bandwidth, in bits per second, the RTCP
+    /// interval is derived from. RTCP is allowed 5% of it, see RFC 3550 section 6.2.
+    /// `APIBuilder::build` applies it to the interceptors of the registry which schedule RTCP,
+    /// i.e. the sender and receiver reports and the NACK generator of
+    /// `register_default_interceptors`, unless they were configured with their own timing.
+    pub fn set_rtcp_session_bandwidth(&mut self, session_bandwidth: u32) {
+        self.rtcp_session_bandwidth = Some(session_bandwidth);
+    }
+
+    /// set_sctp_max_retransmissions sets how many times in a row the SCTP handshake or
+    /// unacknowledged data channel messages are retransmitted before the association is given
+    /// up, failing the SCTP transport and its data channels with
+    /// `sctp::association::CloseReason::HandshakeTimeout` or `MaxRetransmissionsExceeded`.
+    /// Default is 0, the handshake is then retransmitted 8 times and messages until ICE notices
+    /// that the connection is lost.
+    pub fn set_sctp_max_retransmissions(&mut self, max_retransmissions: usize) {
+        self.sctp_max_retransmissions = max_retransmissions;
+    }
+
+    /// set_sctp_num_streams sets the number of outbound and inbound SCTP streams advertised when
+    /// the association is set up, 0 means 65535, the default. Opening a data channel with an id
+    /// beyond the streams negotiated asks the peer for more streams and opens the channel once it
+    /// agreed, or fails it with `sctp::Error::ErrAddStreamsRefused`.
+    pub fn set_sctp_num_streams(&mut self, outbound: u16, inbound: u16) {
+        self.sctp_num_streams = (outbound, inbound);
+    }
+
+    /// set_data_channel_pending_message_limit sets how many messages a data channel keeps while
+    /// it has no on_message handler, to deliver them once the handler is set. Further messages are
+    /// dropped with a warning. 0 means 1024, the default.
+    pub fn set_data_channel_pending_message_limit(&mut self, limit: usize) {
+        self.data_channel_pending_message_limit = limit;
+    }
+
+    /// set_video_orientation enables the negotiation of the video orientation (CVO) header
+    /// extension, `urn:3gpp:video-orientation`, which mobile senders use to signal the rotation of
+    /// the video instead of re-encoding it. It is off by default. When enabled, `APIBuilder::build`
+    /// registers the extension with the MediaEngine and an interceptor parsing it, the most recent
+    /// orientation received is then available in the attributes of `TrackRemote::read_rtp`, see
+    /// `interceptor::video_orientation::video_orientation_from_attributes`. `Sample::orientation`
+    /// is sent by `TrackLocalStaticSample::write_sample`.
+    pub fn set_video_orientation(&mut self, enabled: bool) {
+        self.video_orientation = enabled;
+    }
+
+    /// set_cryptex enables Cryptex, RFC 9335, which encrypts the CSRCs and header extensions of
+    /// RTP packets, e.g. the audio level, along with the payload. It is off by default. When
+    /// enabled, descriptions carry `a=cryptex`, answers only if the offer does, and Cryptex is used
+    /// if both the local and the remote description signal it when the connection is established.
+    /// Renegotiation doesn't turn it on or off. Packets without Cryptex are still accepted.
+    pub fn set_cryptex(&mut self, enabled: bool) {
+        self.cryptex = enabled;
+    }
+
+    /// set_packet_dump dumps the RTP and RTCP packets of every PeerConnection to the file or
+    /// writer of builder, unencrypted, for offline analysis. Use "{id}" in the path of
+    /// `DumpBuilder::with_file` to give each PeerConnection its own file, it is replaced by
+    /// `RTCPeerConnection::get_stats_id`. Otherwise the packets of all the PeerConnections are
+    /// written to the same dump.
+    pub fn set_packet_dump(&mut self, builder: DumpBuilder) {
+        self.packet_dump = Some(Arc::new(builder));
+    }
+
+    /// set_stats_time_source sets the wall clock used to stamp the reports of
+    /// `RTCPeerConnection::get_stats`, the system clock by default. The monotonic timestamp of
+    /// the reports doesn't depend on it.
+    pub fn set_stats_time_source(&mut self, time_source: SharedTimeSource) {
+        self.stats_time_source = Some(time_source);
+    }
+
+    /// rtcp_scheduler_config returns the RTCP timing configuration derived from the session
+    /// bandwidth, if one was set.
+    pub fn rtcp_scheduler_config(&self) -> Option<RtcpSchedulerConfig> {
+        self.rtcp_session_bandwidth
+            .map(RtcpSchedulerConfig::with_session_bandwidth)
+    }
 }
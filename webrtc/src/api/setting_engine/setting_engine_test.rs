@@ -1,9 +1,16 @@
 use super::*;
 use crate::api::media_engine::MediaEngine;
+use crate::api::media_engine::MIME_TYPE_VP8;
 use crate::api::APIBuilder;
 use crate::peer_connection::peer_connection_test::*;
-use crate::rtp_transceiver::rtp_codec::RTPCodecType;
+use crate::rtp_transceiver::rtp_codec::{RTCRtpCodecCapability, RTPCodecType};
+use crate::rtp_transceiver::rtp_receiver::RTCRtpReceiver;
+use crate::track::track_local::track_local_static_sample::TrackLocalStaticSample;
+use crate::track::track_remote::TrackRemote;
+use bytes::Bytes;
+use interceptor::dump::DumpFormat;
 use std::sync::atomic::Ordering;
+use tokio::sync::mpsc;
 
 #[test]
 fn test_set_connection_timeout() -> Result<()> {
@@ -124,6 +131,20 @@ fn test_set_replay_protection() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_set_rtcp_session_bandwidth() -> Result<()> {
+    let mut s = SettingEngine::default();
+    assert!(s.rtcp_scheduler_config().is_none());
+
+    s.set_rtcp_session_bandwidth(1_000_000);
+    let config = s
+        .rtcp_scheduler_config()
+        .expect("RTCP scheduler config should be set");
+    assert_eq!(config.session_bandwidth, 1_000_000);
+
+    Ok(())
+}
+
 /*TODO:#[test] fn test_setting_engine_set_ice_tcp_mux() ->Result<()> {
 
     listener, err := net.ListenTCP("tcp", &net.TCPAddr{})
@@ -167,9 +188,14 @@ async fn test_setting_engine_set_disable_media_engine_copy() -> Result<()> {
         signal_pair(&mut offerer, &mut answerer).await?;
 
         // Assert that the MediaEngine the user created isn't modified
-        assert!(!api.media_engine.negotiated_video.load(Ordering::SeqCst));
+        assert!(!api
+            .media_engine
+            .load()
+            .negotiated_video
+            .load(Ordering::SeqCst));
         {
-            let negotiated_video_codecs = api.media_engine.negotiated_video_codecs.lock().await;
+            let media_engine = api.media_engine.load();
+            let negotiated_video_codecs = media_engine.negotiated_video_codecs.lock().await;
             assert!(negotiated_video_codecs.is_empty());
         }
 
@@ -250,9 +276,14 @@ async fn test_setting_engine_set_disable_media_engine_copy() -> Result<()> {
         signal_pair(&mut offerer, &mut answerer).await?;
 
         // Assert that the user MediaEngine was modified, so no copy happened
-        assert!(api.media_engine.negotiated_video.load(Ordering::SeqCst));
+        assert!(api
+            .media_engine
+            .load()
+            .negotiated_video
+            .load(Ordering::SeqCst));
         {
-            let negotiated_video_codecs = api.media_engine.negotiated_video_codecs.lock().await;
+            let media_engine = api.media_engine.load();
+            let negotiated_video_codecs = media_engine.negotiated_video_codecs.lock().await;
             assert!(!negotiated_video_codecs.is_empty());
         }
 
@@ -281,3 +312,91 @@ async fn test_setting_engine_set_disable_media_engine_copy() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_set_packet_dump() -> Result<()> {
+    let template = std::env::temp_dir()
+        .join(format!("webrtc-dump-{}-{{id}}.pcap", std::process::id()))
+        .to_string_lossy()
+        .into_owned();
+
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let mut s = SettingEngine::default();
+    s.set_packet_dump(
+        DumpBuilder::with_file(template.clone())
+            .with_format(DumpFormat::Pcap)
+            .with_rtcp(false),
+    );
+    let api = APIBuilder::new()
+        .with_media_engine(m)
+        .with_setting_engine(s)
+        .build();
+
+    let (mut offerer, mut answerer) = new_pair(&api).await?;
+
+    let track = Arc::new(TrackLocalStaticSample::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    offerer.add_track(track.clone()).await?;
+
+    let (done_tx, done_rx) = mpsc::channel(1);
+    answerer.on_track(Box::new(
+        move |track: Option<Arc<TrackRemote>>, _: Option<Arc<RTCRtpReceiver>>| {
+            let done_tx = done_tx.clone();
+            Box::pin(async move {
+                if let Some(track) = track {
+                    if track.read_rtp().await.is_ok() {
+                        let _ = done_tx.send(()).await;
+                    }
+                }
+            })
+        },
+    ));
+
+    signal_pair(&mut offerer, &mut answerer).await?;
+    send_video_until_done(
+        done_rx,
+        vec![track],
+        Bytes::from_static(&[0xDE, 0xAD, 0xBE, 0xEF]),
+        None,
+    )
+    .await;
+
+    let offerer_path = template.replace("{id}", offerer.get_stats_id());
+    let answerer_path = template.replace("{id}", answerer.get_stats_id());
+    close_pair_now(&offerer, &answerer).await;
+
+    // Each PeerConnection has its own dump, with the VP8 packets it sent or received
+    for (path, src) in [
+        (&offerer_path, [10, 0, 0, 1]),
+        (&answerer_path, [10, 0, 0, 2]),
+    ]
+    .iter()
+    {
+        let b = std::fs::read(path).unwrap();
+        let _ = std::fs::remove_file(path);
+
+        assert_eq!(&b[..4], &0xa1b2_c3d4u32.to_le_bytes());
+        let mut rest = &b[24..];
+        let mut packets = 0;
+        while !rest.is_empty() {
+            let len = u32::from_le_bytes([rest[8], rest[9], rest[10], rest[11]]) as usize;
+            let ip = &rest[16..16 + len];
+            assert_eq!(&ip[12..16], src);
+            let rtp = &ip[28..];
+            assert_eq!(rtp[0] >> 6, 2);
+            assert!(rtp.ends_with(&[0xDE, 0xAD, 0xBE, 0xEF]));
+            packets += 1;
+            rest = &rest[16 + len..];
+        }
+        assert!(packets > 0, "no packet dumped in {}", path);
+    }
+
+    Ok(())
+}
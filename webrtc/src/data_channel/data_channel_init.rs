@@ -1,3 +1,5 @@
+use crate::data_channel::data_channel_priority::RTCPriorityType;
+
 /// DataChannelConfig can be used to configure properties of the underlying
 /// channel such as data reliability.
 #[derive(Default, Debug, Clone)]
@@ -26,4 +28,14 @@ pub struct RTCDataChannelInit {
     /// to negotiate the channel and create an DataChannel with the same id
     /// at the other peer.
     pub negotiated: Option<u16>,
+
+    /// priority describes the priority of this channel relative to the other channels of
+    /// the peer connection. The default value of None uses `RTCPriorityType::Low`.
+    pub priority: Option<RTCPriorityType>,
+
+    /// chunked splits binary messages into fragments of at most 16 KiB which the remote
+    /// reassembles, for messages larger than what older endpoints and gateways accept. Both
+    /// peers must use this crate. The default value of None sends each message as a single SCTP
+    /// message, limited by the max message size of the remote.
+    pub chunked: Option<bool>,
 }
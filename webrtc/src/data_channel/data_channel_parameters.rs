@@ -6,7 +6,13 @@ pub struct DataChannelParameters {
     pub label: String,
     pub protocol: String,
     pub ordered: bool,
-    pub max_packet_life_time: u16,
-    pub max_retransmits: u16,
+    /// None unless partial reliability is limited by time
+    pub max_packet_life_time: Option<u16>,
+    /// None unless partial reliability is limited by retransmissions, Some(0) never retransmits
+    pub max_retransmits: Option<u16>,
     pub negotiated: Option<u16>,
+    /// The priority sent in the DATA_CHANNEL_OPEN message
+    pub priority: u16,
+    /// Whether binary messages are split into fragments reassembled by the remote
+    pub chunked: bool,
 }
@@ -0,0 +1,156 @@
+use data::message::message_channel_open::{
+    CHANNEL_PRIORITY_BELOW_NORMAL, CHANNEL_PRIORITY_EXTRA_HIGH, CHANNEL_PRIORITY_HIGH,
+    CHANNEL_PRIORITY_NORMAL,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// PriorityType indicates the relative priority of a data channel, see
+/// https://w3c.github.io/webrtc-priority/#rtc-priority-type. Data of a higher priority channel
+/// gets a larger share of the sending capacity of the SCTP association.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RTCPriorityType {
+    #[serde(rename = "unspecified")]
+    Unspecified = 0,
+
+    /// PriorityTypeVeryLow maps to the DCEP priority 128 and covers the values up to 128.
+    #[serde(rename = "very-low")]
+    VeryLow,
+
+    /// PriorityTypeLow maps to the DCEP priority 256 and covers the values up to 256. It is
+    /// the default priority of a data channel.
+    #[serde(rename = "low")]
+    Low,
+
+    /// PriorityTypeMedium maps to the DCEP priority 512 and covers the values up to 512.
+    #[serde(rename = "medium")]
+    Medium,
+
+    /// PriorityTypeHigh maps to the DCEP priority 1024 and covers the values above 512.
+    #[serde(rename = "high")]
+    High,
+}
+
+impl Default for RTCPriorityType {
+    fn default() -> Self {
+        RTCPriorityType::Unspecified
+    }
+}
+
+const PRIORITY_TYPE_VERY_LOW_STR: &str = "very-low";
+const PRIORITY_TYPE_LOW_STR: &str = "low";
+const PRIORITY_TYPE_MEDIUM_STR: &str = "medium";
+const PRIORITY_TYPE_HIGH_STR: &str = "high";
+
+impl RTCPriorityType {
+    /// dcep_priority returns the priority sent in the DATA_CHANNEL_OPEN message, see
+    /// https://www.rfc-editor.org/rfc/rfc8831#section-6.4
+    pub(crate) fn dcep_priority(&self) -> u16 {
+        match *self {
+            RTCPriorityType::VeryLow => CHANNEL_PRIORITY_BELOW_NORMAL,
+            RTCPriorityType::Medium => CHANNEL_PRIORITY_HIGH,
+            RTCPriorityType::High => CHANNEL_PRIORITY_EXTRA_HIGH,
+            RTCPriorityType::Low | RTCPriorityType::Unspecified => CHANNEL_PRIORITY_NORMAL,
+        }
+    }
+}
+
+/// Maps a DCEP priority to the bucket containing it.
+impl From<u16> for RTCPriorityType {
+    fn from(v: u16) -> Self {
+        if v <= CHANNEL_PRIORITY_BELOW_NORMAL {
+            RTCPriorityType::VeryLow
+        } else if v <= CHANNEL_PRIORITY_NORMAL {
+            RTCPriorityType::Low
+        } else if v <= CHANNEL_PRIORITY_HIGH {
+            RTCPriorityType::Medium
+        } else {
+            RTCPriorityType::High
+        }
+    }
+}
+
+impl From<&str> for RTCPriorityType {
+    fn from(raw: &str) -> Self {
+        match raw {
+            PRIORITY_TYPE_VERY_LOW_STR => RTCPriorityType::VeryLow,
+            PRIORITY_TYPE_LOW_STR => RTCPriorityType::Low,
+            PRIORITY_TYPE_MEDIUM_STR => RTCPriorityType::Medium,
+            PRIORITY_TYPE_HIGH_STR => RTCPriorityType::High,
+            _ => RTCPriorityType::Unspecified,
+        }
+    }
+}
+
+impl fmt::Display for RTCPriorityType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match *self {
+            RTCPriorityType::VeryLow => PRIORITY_TYPE_VERY_LOW_STR,
+            RTCPriorityType::Low => PRIORITY_TYPE_LOW_STR,
+            RTCPriorityType::Medium => PRIORITY_TYPE_MEDIUM_STR,
+            RTCPriorityType::High => PRIORITY_TYPE_HIGH_STR,
+            RTCPriorityType::Unspecified => crate::UNSPECIFIED_STR,
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_priority_type_from_dcep_priority() {
+        let tests = vec![
+            (0, RTCPriorityType::VeryLow),
+            (128, RTCPriorityType::VeryLow),
+            (129, RTCPriorityType::Low),
+            (256, RTCPriorityType::Low),
+            (257, RTCPriorityType::Medium),
+            (512, RTCPriorityType::Medium),
+            (513, RTCPriorityType::High),
+            (1024, RTCPriorityType::High),
+            (u16::MAX, RTCPriorityType::High),
+        ];
+
+        for (priority, expected_priority_type) in tests {
+            assert_eq!(
+                expected_priority_type,
+                RTCPriorityType::from(priority),
+                "testCase: {}",
+                priority,
+            );
+        }
+    }
+
+    #[test]
+    fn test_priority_type_dcep_priority_round_trip() {
+        for priority_type in [
+            RTCPriorityType::VeryLow,
+            RTCPriorityType::Low,
+            RTCPriorityType::Medium,
+            RTCPriorityType::High,
+        ] {
+            assert_eq!(
+                priority_type,
+                RTCPriorityType::from(priority_type.dcep_priority())
+            );
+        }
+    }
+
+    #[test]
+    fn test_priority_type_string() {
+        let tests = vec![
+            (RTCPriorityType::Unspecified, crate::UNSPECIFIED_STR),
+            (RTCPriorityType::VeryLow, "very-low"),
+            (RTCPriorityType::Low, "low"),
+            (RTCPriorityType::Medium, "medium"),
+            (RTCPriorityType::High, "high"),
+        ];
+
+        for (priority_type, expected_string) in tests {
+            assert_eq!(expected_string, priority_type.to_string());
+            assert_eq!(priority_type, RTCPriorityType::from(expected_string));
+        }
+    }
+}
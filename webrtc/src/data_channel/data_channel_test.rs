@@ -2,6 +2,7 @@ use super::*;
 use crate::api::media_engine::MediaEngine;
 use crate::api::{APIBuilder, API};
 use crate::data_channel::data_channel_init::RTCDataChannelInit;
+use crate::data_channel::data_channel_priority::RTCPriorityType;
 use crate::peer_connection::peer_connection_test::*;
 use crate::peer_connection::RTCPeerConnection;
 
@@ -20,8 +21,11 @@ use crate::ice_transport::RTCIceTransport;
 use crate::peer_connection::configuration::RTCConfiguration;
 use crate::sctp_transport::sctp_transport_capabilities::SCTPTransportCapabilities;
 use regex::Regex;
+use std::collections::HashMap;
 use tokio::sync::mpsc;
 use tokio::time::Duration;
+use tokio::time::Instant;
+use util::vnet::chunk::Chunk;
 use waitgroup::WaitGroup;
 
 // EXPECTED_LABEL represents the label of the data channel we are trying to test.
@@ -353,6 +357,44 @@ async fn test_data_channel_close() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_data_channel_label_and_protocol_length() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (offer_pc, answer_pc) = new_pair(&api).await?;
+
+    offer_pc.create_data_channel("", None).await?;
+    offer_pc
+        .create_data_channel(
+            &"l".repeat(65535),
+            Some(RTCDataChannelInit {
+                protocol: Some("p".repeat(65535)),
+                ..Default::default()
+            }),
+        )
+        .await?;
+
+    let result = offer_pc.create_data_channel(&"l".repeat(65536), None).await;
+    assert!(matches!(result, Err(Error::ErrStringSizeLimit)));
+
+    let result = offer_pc
+        .create_data_channel(
+            EXPECTED_LABEL,
+            Some(RTCDataChannelInit {
+                protocol: Some("p".repeat(65536)),
+                ..Default::default()
+            }),
+        )
+        .await;
+    assert!(matches!(result, Err(Error::ErrProtocolTooLarge)));
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_data_channel_parameters_max_packet_life_time_exchange() -> Result<()> {
     let mut m = MediaEngine::default();
@@ -378,7 +420,7 @@ async fn test_data_channel_parameters_max_packet_life_time_exchange() -> Result<
     );
     assert_eq!(
         dc.max_packet_lifetime(),
-        max_packet_life_time,
+        Some(max_packet_life_time),
         "should match"
     );
 
@@ -395,7 +437,7 @@ async fn test_data_channel_parameters_max_packet_life_time_exchange() -> Result<
         );
         assert_eq!(
             d.max_packet_lifetime(),
-            max_packet_life_time,
+            Some(max_packet_life_time),
             "should match"
         );
         let done_tx2 = Arc::clone(&done_tx);
@@ -429,7 +471,7 @@ async fn test_data_channel_parameters_max_retransmits_exchange() -> Result<()> {
 
     // Check if parameters are correctly set
     assert!(!dc.ordered(), "Ordered should be set to false");
-    assert_eq!(dc.max_retransmits(), max_retransmits, "should match");
+    assert_eq!(dc.max_retransmits(), Some(max_retransmits), "should match");
 
     let done_tx = Arc::new(Mutex::new(Some(done_tx)));
     answer_pc.on_data_channel(Box::new(move |d: Arc<RTCDataChannel>| {
@@ -441,7 +483,7 @@ async fn test_data_channel_parameters_max_retransmits_exchange() -> Result<()> {
 
         // Check if parameters are correctly set
         assert!(!d.ordered(), "Ordered should be set to false");
-        assert_eq!(max_retransmits, d.max_retransmits(), "should match");
+        assert_eq!(Some(max_retransmits), d.max_retransmits(), "should match");
         let done_tx2 = Arc::clone(&done_tx);
         Box::pin(async move {
             let mut done = done_tx2.lock().await;
@@ -502,6 +544,245 @@ async fn test_data_channel_parameters_protocol_exchange() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_data_channel_parameters_priority_exchange() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let options = RTCDataChannelInit {
+        priority: Some(RTCPriorityType::High),
+        ..Default::default()
+    };
+
+    let (mut offer_pc, mut answer_pc, dc, done_tx, done_rx) =
+        set_up_data_channel_parameters_test(&api, Some(options)).await?;
+
+    // Check if parameters are correctly set
+    assert_eq!(
+        RTCPriorityType::High,
+        dc.priority(),
+        "Priority should match DataChannelConfig"
+    );
+
+    let done_tx = Arc::new(Mutex::new(Some(done_tx)));
+    answer_pc.on_data_channel(Box::new(move |d: Arc<RTCDataChannel>| {
+        // Make sure this is the data channel we were looking for. (Not the one
+        // created in signalPair).
+        if d.label() != EXPECTED_LABEL {
+            return Box::pin(async {});
+        }
+        // Check if parameters are correctly set
+        assert_eq!(
+            RTCPriorityType::High,
+            d.priority(),
+            "Priority should match what channel creator declared"
+        );
+
+        let done_tx2 = Arc::clone(&done_tx);
+        Box::pin(async move {
+            let mut done = done_tx2.lock().await;
+            done.take();
+        })
+    }));
+
+    close_reliability_param_test(&mut offer_pc, &mut answer_pc, done_rx).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_data_channel_priority_overtakes_bulk_backlog() -> Result<()> {
+    const BULK_MESSAGES: usize = 200;
+
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut offer_pc, mut answer_pc) = new_pair(&api).await?;
+
+    let (ready_tx, mut ready_rx) = mpsc::channel::<()>(2);
+    let (urgent_tx, mut urgent_rx) = mpsc::channel::<usize>(1);
+    let (done_tx, mut done_rx) = mpsc::channel::<()>(1);
+    let bulk_received = Arc::new(AtomicUsize::new(0));
+    answer_pc.on_data_channel(Box::new(move |d: Arc<RTCDataChannel>| {
+        let bulk_received = Arc::clone(&bulk_received);
+        let urgent_tx = urgent_tx.clone();
+        let done_tx = done_tx.clone();
+        let ready_tx = ready_tx.clone();
+        Box::pin(async move {
+            match d.label() {
+                "bulk" => d.on_message(Box::new(move |_: DataChannelMessage| {
+                    let received = bulk_received.fetch_add(1, Ordering::SeqCst) + 1;
+                    let done_tx = done_tx.clone();
+                    Box::pin(async move {
+                        if received == BULK_MESSAGES {
+                            let _ = done_tx.send(()).await;
+                        }
+                    })
+                })),
+                "urgent" => d.on_message(Box::new(move |_: DataChannelMessage| {
+                    let received = bulk_received.load(Ordering::SeqCst);
+                    let urgent_tx = urgent_tx.clone();
+                    Box::pin(async move {
+                        let _ = urgent_tx.send(received).await;
+                    })
+                })),
+                _ => return,
+            }
+            let _ = ready_tx.send(()).await;
+        })
+    }));
+
+    let bulk = offer_pc.create_data_channel("bulk", None).await?;
+    let urgent = offer_pc
+        .create_data_channel(
+            "urgent",
+            Some(RTCDataChannelInit {
+                priority: Some(RTCPriorityType::High),
+                ..Default::default()
+            }),
+        )
+        .await?;
+
+    signal_pair(&mut offer_pc, &mut answer_pc).await?;
+
+    for _ in 0..2 {
+        tokio::time::timeout(Duration::from_secs(5), ready_rx.recv())
+            .await
+            .expect("data channels should open");
+    }
+
+    let payload = Bytes::from(vec![0u8; 1024]);
+    for _ in 0..BULK_MESSAGES {
+        bulk.send(&payload).await?;
+    }
+    urgent.send(&Bytes::from_static(b"urgent")).await?;
+
+    let bulk_before_urgent = tokio::time::timeout(Duration::from_secs(5), urgent_rx.recv())
+        .await
+        .expect("urgent message should arrive")
+        .unwrap_or_default();
+    assert!(
+        bulk_before_urgent < BULK_MESSAGES / 2,
+        "urgent message should overtake the bulk backlog, {} bulk messages arrived first",
+        bulk_before_urgent
+    );
+
+    tokio::time::timeout(Duration::from_secs(5), done_rx.recv())
+        .await
+        .expect("bulk messages should arrive");
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_data_channel_unreliable_unordered_over_lossy_network() -> Result<()> {
+    const MESSAGES: u32 = 100;
+    const DTLS_APPLICATION_DATA: u8 = 23;
+
+    let (mut offer_pc, mut answer_pc, wan) = create_vnet_pair().await?;
+
+    let options = RTCDataChannelInit {
+        ordered: Some(false),
+        max_retransmits: Some(0),
+        negotiated: Some(0),
+        ..Default::default()
+    };
+    let offer_dc = offer_pc
+        .create_data_channel(EXPECTED_LABEL, Some(options.clone()))
+        .await?;
+    let answer_dc = answer_pc
+        .create_data_channel(EXPECTED_LABEL, Some(options))
+        .await?;
+    assert_eq!(Some(0), offer_dc.max_retransmits());
+
+    let (open_tx, mut open_rx) = mpsc::channel(2);
+    for dc in [&offer_dc, &answer_dc] {
+        let open_tx = open_tx.clone();
+        dc.on_open(Box::new(move || {
+            Box::pin(async move {
+                let _ = open_tx.send(()).await;
+            })
+        }));
+    }
+
+    let (message_tx, mut message_rx) = mpsc::channel(MESSAGES as usize);
+    answer_dc.on_message(Box::new(move |msg: DataChannelMessage| {
+        let received_at = Instant::now();
+        let message_tx = message_tx.clone();
+        Box::pin(async move {
+            let seq = u32::from_be_bytes([msg.data[0], msg.data[1], msg.data[2], msg.data[3]]);
+            let _ = message_tx.send((seq, received_at)).await;
+        })
+    }));
+
+    signal_pair(&mut offer_pc, &mut answer_pc).await?;
+
+    for _ in 0..2 {
+        tokio::time::timeout(Duration::from_secs(5), open_rx.recv())
+            .await
+            .expect("data channels should open");
+    }
+    for dc in [&offer_dc, &answer_dc] {
+        assert!(!dc.is_ordered().await, "should be unordered once open");
+        assert_eq!((ReliabilityType::Rexmit, 0), dc.reliability().await);
+    }
+
+    // Lose every fourth SCTP packet of the offerer
+    {
+        let sent = AtomicUsize::new(0);
+        let w = wan.lock().await;
+        w.add_chunk_filter(Box::new(move |c: &(dyn Chunk + Send + Sync)| {
+            c.source_addr().ip().to_string() != "1.2.3.4"
+                || c.user_data().first() != Some(&DTLS_APPLICATION_DATA)
+                || sent.fetch_add(1, Ordering::SeqCst) % 4 != 3
+        }))
+        .await;
+    }
+
+    let mut sent_at = vec![];
+    for seq in 0..MESSAGES {
+        sent_at.push(Instant::now());
+        offer_dc
+            .send(&Bytes::copy_from_slice(&seq.to_be_bytes()))
+            .await?;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let mut received = vec![];
+    while let Ok((seq, received_at)) = message_rx.try_recv() {
+        received.push(seq);
+
+        let latency = received_at - sent_at[seq as usize];
+        assert!(
+            latency < Duration::from_millis(250),
+            "message {} took {:?}, it must not wait for a retransmission",
+            seq,
+            latency
+        );
+    }
+
+    assert!(
+        !received.is_empty() && received.len() < MESSAGES as usize,
+        "lost messages must be skipped, received {} of {}",
+        received.len(),
+        MESSAGES
+    );
+    assert!(
+        received.windows(2).all(|w| w[0] < w[1]),
+        "lost messages must not be retransmitted later: {:?}",
+        received
+    );
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_data_channel_parameters_negotiated_exchange() -> Result<()> {
     let mut m = MediaEngine::default();
@@ -754,7 +1035,7 @@ async fn test_data_channel_parameters_go() -> Result<()> {
         // Check if parameters are correctly set
         assert!(dc.ordered(), "Ordered should be set to true");
         assert_eq!(
-            max_packet_life_time,
+            Some(max_packet_life_time),
             dc.max_packet_lifetime(),
             "should match"
         );
@@ -770,7 +1051,7 @@ async fn test_data_channel_parameters_go() -> Result<()> {
             // Check if parameters are correctly set
             assert!(d.ordered, "Ordered should be set to true");
             assert_eq!(
-                max_packet_life_time,
+                Some(max_packet_life_time),
                 d.max_packet_lifetime(),
                 "should match"
             );
@@ -1276,6 +1557,105 @@ async fn test_data_channel_non_standard_session_description() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_data_channel_chunked_and_remote_max_message_size() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (offer_pc, answer_pc) = new_pair(&api).await?;
+
+    let chunked = offer_pc
+        .create_data_channel(
+            EXPECTED_LABEL,
+            Some(RTCDataChannelInit {
+                protocol: Some("json".to_owned()),
+                chunked: Some(true),
+                ..Default::default()
+            }),
+        )
+        .await?;
+    let plain = offer_pc.create_data_channel("plain", None).await?;
+    assert!(chunked.chunked());
+    assert!(!plain.chunked());
+
+    let (open_tx, mut open_rx) = mpsc::channel::<()>(2);
+    for dc in [&chunked, &plain] {
+        let open_tx = open_tx.clone();
+        dc.on_open(Box::new(move || {
+            Box::pin(async move {
+                let _ = open_tx.send(()).await;
+            })
+        }));
+    }
+
+    let (message_tx, mut message_rx) = mpsc::channel::<Bytes>(1);
+    answer_pc.on_data_channel(Box::new(move |d: Arc<RTCDataChannel>| {
+        if d.label() != EXPECTED_LABEL {
+            return Box::pin(async {});
+        }
+        assert!(d.chunked(), "the remote should reassemble chunked messages");
+        assert_eq!(d.protocol(), "json");
+
+        let message_tx = message_tx.clone();
+        Box::pin(async move {
+            d.on_message(Box::new(move |msg: DataChannelMessage| {
+                let message_tx = message_tx.clone();
+                Box::pin(async move {
+                    let _ = message_tx.send(msg.data).await;
+                })
+            }));
+        })
+    }));
+
+    let offer = offer_pc.create_offer(None).await?;
+    let mut offer_gathering_complete = offer_pc.gathering_complete_promise().await;
+    offer_pc.set_local_description(offer).await?;
+    let _ = offer_gathering_complete.recv().await;
+    answer_pc
+        .set_remote_description(offer_pc.local_description().await.unwrap())
+        .await?;
+
+    let answer = answer_pc.create_answer(None).await?;
+    let mut answer_gathering_complete = answer_pc.gathering_complete_promise().await;
+    answer_pc.set_local_description(answer).await?;
+    let _ = answer_gathering_complete.recv().await;
+
+    // The answerer only accepts messages of up to 16 KiB
+    let mut answer = answer_pc.local_description().await.unwrap();
+    answer.sdp = answer.sdp.replace(
+        "a=sctp-port:5000\r\n",
+        "a=sctp-port:5000\r\na=max-message-size:16384\r\n",
+    );
+    assert!(answer.sdp.contains("a=max-message-size:16384"));
+    offer_pc.set_remote_description(answer).await?;
+
+    for _ in 0..2 {
+        open_rx.recv().await;
+    }
+
+    plain.send(&Bytes::from(vec![0u8; 16384])).await?;
+    let result = plain.send(&Bytes::from(vec![0u8; 16385])).await;
+    assert_eq!(
+        result,
+        Err(Error::Data(data::Error::Sctp(
+            sctp::Error::ErrOutboundPacketTooLarge
+        )))
+    );
+
+    let message: Bytes = (0..100_000)
+        .map(|i| (i % 251) as u8)
+        .collect::<Vec<u8>>()
+        .into();
+    let n = chunked.send(&message).await?;
+    assert_eq!(n, message.len());
+    assert_eq!(message_rx.recv().await, Some(message));
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
+
 struct TestOrtcStack {
     //api      *API
     gatherer: Arc<RTCIceGatherer>,
@@ -1515,3 +1895,247 @@ async fn test_data_channel_ortc_e2e() -> Result<()> {
 
     Ok(())
 }
+
+/// create_reused_data_channel creates the negotiated channel for `round` on stream id 50,
+/// forwarding its messages and its closing to the test.
+async fn create_reused_data_channel(
+    pc: &RTCPeerConnection,
+    round: usize,
+    msg_tx: mpsc::UnboundedSender<(usize, Bytes)>,
+    close_tx: mpsc::UnboundedSender<usize>,
+) -> Result<Arc<RTCDataChannel>> {
+    let dc = pc
+        .create_data_channel(
+            "reused",
+            Some(RTCDataChannelInit {
+                negotiated: Some(50),
+                ..Default::default()
+            }),
+        )
+        .await?;
+    dc.on_message(Box::new(move |msg: DataChannelMessage| {
+        let _ = msg_tx.send((round, msg.data));
+        Box::pin(async {})
+    }));
+    dc.on_close(Box::new(move || {
+        let _ = close_tx.send(round);
+        Box::pin(async {})
+    }));
+    Ok(dc)
+}
+
+#[tokio::test]
+async fn test_data_channel_id_reused_right_after_close() -> Result<()> {
+    const ROUNDS: usize = 50;
+    const MSGS: usize = 20;
+
+    let api = APIBuilder::new().build();
+    let (mut pca, mut pcb) = new_pair(&api).await?;
+
+    let (msg_tx, mut msg_rx) = mpsc::unbounded_channel();
+    let (close_tx, mut close_rx) = mpsc::unbounded_channel();
+    let (a_close_tx, _a_close_rx) = mpsc::unbounded_channel();
+    let (a_msg_tx, _a_msg_rx) = mpsc::unbounded_channel();
+
+    let mut dca = create_reused_data_channel(&pca, 0, a_msg_tx.clone(), a_close_tx.clone()).await?;
+    let mut dcb = create_reused_data_channel(&pcb, 0, msg_tx.clone(), close_tx.clone()).await?;
+
+    signal_pair(&mut pca, &mut pcb).await?;
+
+    for round in 0..ROUNDS {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while dca.ready_state() != RTCDataChannelState::Open {
+            assert!(Instant::now() < deadline, "round {} did not open", round);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        for i in 0..MSGS {
+            dca.send(&Bytes::from(format!("{}:{}", round, i))).await?;
+        }
+
+        // The id is reused right away, before the stream was reset
+        dca.close().await?;
+        if round + 1 < ROUNDS {
+            dca = create_reused_data_channel(&pca, round + 1, a_msg_tx.clone(), a_close_tx.clone())
+                .await?;
+        }
+
+        let closed = tokio::time::timeout(Duration::from_secs(5), close_rx.recv())
+            .await
+            .expect("timed out waiting for the remote channel to close");
+        assert_eq!(closed, Some(round));
+
+        let mut msgs = vec![];
+        while let Ok((channel_round, data)) = msg_rx.try_recv() {
+            assert_eq!(channel_round, round, "delivered to another channel");
+            msgs.push(data);
+        }
+        let expected: Vec<Bytes> = (0..MSGS)
+            .map(|i| Bytes::from(format!("{}:{}", round, i)))
+            .collect();
+        assert_eq!(msgs, expected, "round {}", round);
+
+        if round + 1 < ROUNDS {
+            dcb = create_reused_data_channel(&pcb, round + 1, msg_tx.clone(), close_tx.clone())
+                .await?;
+        }
+    }
+    drop(dcb);
+
+    close_pair_now(&pca, &pcb).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_data_channel_open_beyond_initial_sctp_streams() -> Result<()> {
+    const NUM_CHANNELS: usize = 10;
+
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let mut s = SettingEngine::default();
+    s.set_sctp_num_streams(4, 4);
+    let api = APIBuilder::new()
+        .with_media_engine(m)
+        .with_setting_engine(s)
+        .build();
+
+    let (mut offer_pc, mut answer_pc) = new_pair(&api).await?;
+
+    // The answerer echoes every message, on the stream of the channel
+    answer_pc.on_data_channel(Box::new(move |d: Arc<RTCDataChannel>| {
+        Box::pin(async move {
+            let d2 = Arc::clone(&d);
+            d.on_message(Box::new(move |msg: DataChannelMessage| {
+                let d3 = Arc::clone(&d2);
+                Box::pin(async move {
+                    let result = d3.send(&msg.data).await;
+                    assert!(result.is_ok(), "Failed to echo on data channel");
+                })
+            }));
+        })
+    }));
+
+    let (echo_tx, mut echo_rx) = mpsc::channel::<String>(NUM_CHANNELS);
+    for i in 0..NUM_CHANNELS {
+        let label = format!("channel-{}", i);
+        let dc = offer_pc.create_data_channel(&label, None).await?;
+
+        let dc2 = Arc::clone(&dc);
+        dc.on_open(Box::new(move || {
+            let dc3 = Arc::clone(&dc2);
+            Box::pin(async move {
+                let result = dc3.send_text(dc3.label().to_owned()).await;
+                assert!(result.is_ok(), "Failed to send string on data channel");
+            })
+        }));
+        let echo_tx = echo_tx.clone();
+        dc.on_message(Box::new(move |msg: DataChannelMessage| {
+            let echo_tx = echo_tx.clone();
+            Box::pin(async move {
+                let _ = echo_tx
+                    .send(String::from_utf8(msg.data.to_vec()).unwrap())
+                    .await;
+            })
+        }));
+    }
+
+    signal_pair(&mut offer_pc, &mut answer_pc).await?;
+
+    let mut echoed = std::collections::HashSet::new();
+    while echoed.len() < NUM_CHANNELS {
+        let label = tokio::time::timeout(Duration::from_secs(10), echo_rx.recv())
+            .await
+            .expect("every channel should open and be echoed")
+            .unwrap();
+        echoed.insert(label);
+    }
+
+    // The initial channel of signal_pair and 10 more didn't fit in 4 streams
+    let association = offer_pc.sctp().association().await.unwrap();
+    assert!(association.max_outbound_streams() > 4);
+    assert!(association.max_inbound_streams() > 4);
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_data_channel_first_message_not_lost() -> Result<()> {
+    const NUM_CHANNELS: usize = 500;
+
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut offer_pc, mut answer_pc) = new_pair(&api).await?;
+
+    // The answerer only sets on_message a while after the channel opened, when the offerer
+    // has already sent its messages
+    let (msg_tx, mut msg_rx) = mpsc::channel::<(String, String)>(2 * NUM_CHANNELS);
+    answer_pc.on_data_channel(Box::new(move |d: Arc<RTCDataChannel>| {
+        if !d.label().starts_with("channel-") {
+            return Box::pin(async {});
+        }
+        let msg_tx = msg_tx.clone();
+        Box::pin(async move {
+            let d2 = Arc::clone(&d);
+            d.on_open(Box::new(move || {
+                Box::pin(async move {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+
+                    let label = d2.label().to_owned();
+                    d2.on_message(Box::new(move |msg: DataChannelMessage| {
+                        let msg_tx = msg_tx.clone();
+                        let label = label.clone();
+                        Box::pin(async move {
+                            let data = String::from_utf8(msg.data.to_vec()).unwrap();
+                            let _ = msg_tx.send((label, data)).await;
+                        })
+                    }));
+                })
+            }));
+        })
+    }));
+
+    signal_pair(&mut offer_pc, &mut answer_pc).await?;
+
+    for i in 0..NUM_CHANNELS {
+        let dc = offer_pc
+            .create_data_channel(&format!("channel-{}", i), None)
+            .await?;
+        let dc2 = Arc::clone(&dc);
+        dc.on_open(Box::new(move || {
+            Box::pin(async move {
+                for data in ["#1", "#2"] {
+                    let result = dc2.send_text(data.to_owned()).await;
+                    assert!(result.is_ok(), "Failed to send string on data channel");
+                }
+            })
+        }));
+    }
+
+    let mut received = HashMap::<String, Vec<String>>::new();
+    for _ in 0..2 * NUM_CHANNELS {
+        let (label, data) = tokio::time::timeout(Duration::from_secs(30), msg_rx.recv())
+            .await
+            .expect("every message should be delivered")
+            .unwrap();
+        received.entry(label).or_default().push(data);
+    }
+
+    assert_eq!(received.len(), NUM_CHANNELS);
+    for (label, messages) in received {
+        assert_eq!(
+            messages,
+            vec!["#1", "#2"],
+            "{} should get both in order",
+            label
+        );
+    }
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
@@ -4,32 +4,37 @@ mod data_channel_test;
 pub mod data_channel_init;
 pub mod data_channel_message;
 pub mod data_channel_parameters;
+pub mod data_channel_priority;
 pub mod data_channel_state;
 
 use data_channel_message::*;
 use data_channel_parameters::*;
+use data_channel_priority::RTCPriorityType;
 
 use arc_swap::ArcSwapOption;
 use bytes::Bytes;
 use std::{
+    collections::VecDeque,
     future::Future,
     pin::Pin,
     sync::{
         atomic::{AtomicBool, AtomicU16, AtomicU8, AtomicUsize, Ordering},
         Arc, Weak,
     },
-    time::SystemTime,
 };
 
 use data::message::message_channel_open::ChannelType;
-use sctp::stream::OnBufferedAmountLowFn;
+use sctp::association::{Association, CloseReason};
+use sctp::stream::{OnBufferedAmountLowFn, ReliabilityType};
 use tokio::sync::{Mutex, Notify};
 use util::sync::Mutex as SyncMutex;
+use util::sync::TaskGroup;
 
 use data_channel_state::RTCDataChannelState;
 
 use crate::api::setting_engine::SettingEngine;
 use crate::error::{Error, OnErrorHdlrFn, Result};
+use crate::peer_connection::TASKS_CLOSE_TIMEOUT;
 use crate::sctp_transport::RTCSctpTransport;
 use crate::stats::stats_collector::StatsCollector;
 use crate::stats::{DataChannelStats, StatsReportType};
@@ -37,6 +42,14 @@ use crate::stats::{DataChannelStats, StatsReportType};
 /// message size limit for Chromium
 const DATA_CHANNEL_BUFFER_SIZE: u16 = u16::MAX;
 
+/// Messages kept by default until an on_message handler is set, see
+/// `SettingEngine::set_data_channel_pending_message_limit`
+const DEFAULT_PENDING_MESSAGE_LIMIT: usize = 1024;
+
+/// Chunked channels reassemble binary messages up to this size
+const CHUNKED_DATA_CHANNEL_BUFFER_SIZE: usize =
+    data::data_channel::fragment::MAX_CHUNKED_MESSAGE_SIZE;
+
 pub type OnMessageHdlrFn = Box<
     dyn (FnMut(DataChannelMessage) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>)
         + Send
@@ -57,14 +70,17 @@ pub struct RTCDataChannel {
     pub(crate) stats_id: String,
     pub(crate) label: String,
     pub(crate) ordered: bool,
-    pub(crate) max_packet_lifetime: u16,
-    pub(crate) max_retransmits: u16,
+    pub(crate) max_packet_lifetime: Option<u16>,
+    pub(crate) max_retransmits: Option<u16>,
     pub(crate) protocol: String,
     pub(crate) negotiated: bool,
+    pub(crate) priority: u16,
+    pub(crate) chunked: bool,
     pub(crate) id: AtomicU16,
     pub(crate) ready_state: Arc<AtomicU8>, // DataChannelState
     pub(crate) buffered_amount_low_threshold: AtomicUsize,
     pub(crate) detach_called: Arc<AtomicBool>,
+    pub(crate) close_reason: Arc<SyncMutex<Option<CloseReason>>>,
 
     // The binaryType represents attribute MUST, on getting, return the value to
     // which it was last set. On setting, if the new value is either the string
@@ -74,6 +90,8 @@ pub struct RTCDataChannel {
     // "blob". This attribute controls how binary data is exposed to scripts.
     // binaryType                 string
     pub(crate) on_message_handler: Arc<ArcSwapOption<Mutex<OnMessageHdlrFn>>>,
+    // wakes the read loop up to deliver the messages received before on_message was set
+    pub(crate) on_message_set: Arc<Notify>,
     pub(crate) on_open_handler: SyncMutex<Option<OnOpenHdlrFn>>,
     pub(crate) on_close_handler: Arc<ArcSwapOption<Mutex<OnCloseHdlrFn>>>,
     pub(crate) on_error_handler: Arc<ArcSwapOption<Mutex<OnErrorHdlrFn>>>,
@@ -84,6 +102,8 @@ pub struct RTCDataChannel {
     pub(crate) data_channel: Mutex<Option<Arc<data::data_channel::DataChannel>>>,
 
     pub(crate) notify_tx: Arc<Notify>,
+    // the read loop and the event handlers run by it, waited for on close
+    pub(crate) tasks: TaskGroup,
 
     // A reference to the associated api object used by this datachannel
     pub(crate) setting_engine: Arc<SettingEngine>,
@@ -91,20 +111,29 @@ pub struct RTCDataChannel {
 
 impl RTCDataChannel {
     // create the DataChannel object before the networking is set up.
-    pub(crate) fn new(params: DataChannelParameters, setting_engine: Arc<SettingEngine>) -> Self {
+    //
+    // stats_id comes from RTCSctpTransport::next_data_channel_stats_id, so it doesn't change
+    // between two calls of get_stats.
+    pub(crate) fn new(
+        params: DataChannelParameters,
+        stats_id: String,
+        setting_engine: Arc<SettingEngine>,
+    ) -> Self {
         // the id value if non-negotiated doesn't matter, since it will be overwritten
         // on opening
         let id = params.negotiated.unwrap_or(0);
         RTCDataChannel {
-            stats_id: format!(
-                "DataChannel-{}",
-                SystemTime::now()
-                    .duration_since(SystemTime::UNIX_EPOCH)
-                    .map_or(0, |d| d.as_nanos())
-            ),
+            stats_id,
             label: params.label,
             protocol: params.protocol,
             negotiated: params.negotiated.is_some(),
+            // 0 is sent by implementations which don't support priorities
+            priority: if params.priority == 0 {
+                RTCPriorityType::Low.dcep_priority()
+            } else {
+                params.priority
+            },
+            chunked: params.chunked,
             id: AtomicU16::new(id),
             ordered: params.ordered,
             max_packet_lifetime: params.max_packet_life_time,
@@ -131,39 +160,16 @@ impl RTCDataChannel {
                 }
             }
 
-            let channel_type;
-            let reliability_parameter;
-
-            if self.max_packet_lifetime == 0 && self.max_retransmits == 0 {
-                reliability_parameter = 0u32;
-                if self.ordered {
-                    channel_type = ChannelType::Reliable;
-                } else {
-                    channel_type = ChannelType::ReliableUnordered;
-                }
-            } else if self.max_retransmits != 0 {
-                reliability_parameter = self.max_retransmits as u32;
-                if self.ordered {
-                    channel_type = ChannelType::PartialReliableRexmit;
-                } else {
-                    channel_type = ChannelType::PartialReliableRexmitUnordered;
-                }
-            } else {
-                reliability_parameter = self.max_packet_lifetime as u32;
-                if self.ordered {
-                    channel_type = ChannelType::PartialReliableTimed;
-                } else {
-                    channel_type = ChannelType::PartialReliableTimedUnordered;
-                }
-            }
+            let (channel_type, reliability_parameter) = self.channel_type();
 
             let cfg = data::data_channel::Config {
                 channel_type,
-                priority: data::message::message_channel_open::CHANNEL_PRIORITY_NORMAL,
+                priority: self.priority,
                 reliability_parameter,
                 label: self.label.clone(),
                 protocol: self.protocol.clone(),
                 negotiated: self.negotiated,
+                chunked: self.chunked,
             };
 
             if !self.negotiated {
@@ -176,6 +182,10 @@ impl RTCDataChannel {
                     Ordering::SeqCst,
                 );
             }
+            // The channel opens once the peer agreed to the streams it needs
+            sctp_transport
+                .ensure_outbound_stream(&association, self.id())
+                .await?;
 
             let dc = data::data_channel::DataChannel::dial(&association, self.id(), cfg).await?;
 
@@ -190,7 +200,8 @@ impl RTCDataChannel {
                 }
             }
 
-            self.handle_open(Arc::new(dc)).await;
+            self.handle_open(Arc::new(dc), Arc::downgrade(&association))
+                .await;
 
             Ok(())
         } else {
@@ -198,6 +209,30 @@ impl RTCDataChannel {
         }
     }
 
+    /// channel_type returns the DCEP channel type and reliability parameter of the
+    /// configuration. A max_retransmits of 0 is partially reliable, it never retransmits.
+    fn channel_type(&self) -> (ChannelType, u32) {
+        match (self.max_retransmits, self.max_packet_lifetime, self.ordered) {
+            (Some(max_retransmits), _, true) => {
+                (ChannelType::PartialReliableRexmit, max_retransmits as u32)
+            }
+            (Some(max_retransmits), _, false) => (
+                ChannelType::PartialReliableRexmitUnordered,
+                max_retransmits as u32,
+            ),
+            (None, Some(max_packet_lifetime), true) => (
+                ChannelType::PartialReliableTimed,
+                max_packet_lifetime as u32,
+            ),
+            (None, Some(max_packet_lifetime), false) => (
+                ChannelType::PartialReliableTimedUnordered,
+                max_packet_lifetime as u32,
+            ),
+            (None, None, true) => (ChannelType::Reliable, 0),
+            (None, None, false) => (ChannelType::ReliableUnordered, 0),
+        }
+    }
+
     /// transport returns the SCTPTransport instance the DataChannel is sending over.
     pub async fn transport(&self) -> Option<Weak<RTCSctpTransport>> {
         let sctp_transport = self.sctp_transport.lock().await;
@@ -222,7 +257,7 @@ impl RTCDataChannel {
 
         let detach_data_channels = self.setting_engine.detach.data_channels;
         let detach_called = Arc::clone(&self.detach_called);
-        tokio::spawn(async move {
+        self.tasks.spawn(async move {
             if let Some(f) = on_open_handler {
                 f().await;
 
@@ -239,19 +274,26 @@ impl RTCDataChannel {
     }
 
     /// on_close sets an event handler which is invoked when
-    /// the underlying data transport has been closed.
+    /// the underlying data transport has been closed. close_reason tells
+    /// whether it closed along with the SCTP association.
     pub fn on_close(&self, f: OnCloseHdlrFn) {
         self.on_close_handler.store(Some(Arc::new(Mutex::new(f))));
     }
 
     /// on_message sets an event handler which is invoked on a binary
     /// message arrival over the sctp transport from a remote peer.
-    /// OnMessage can currently receive messages up to 16384 bytes
-    /// in size. Check out the detach API if you want to use larger
-    /// message sizes. Note that browser support for larger messages
-    /// is also limited.
+    /// OnMessage can currently receive messages up to 65535 bytes
+    /// in size, or 16 MiB on chunked channels. Check out the detach
+    /// API if you want to use larger message sizes. Note that browser
+    /// support for larger messages is also limited.
+    ///
+    /// Messages are delivered one at a time in the order they were read. The messages
+    /// received while no handler is set, e.g. before on_message is called from the
+    /// on_data_channel or on_open handler, are kept and delivered to the handler first, up to
+    /// `SettingEngine::set_data_channel_pending_message_limit`.
     pub fn on_message(&self, f: OnMessageHdlrFn) {
         self.on_message_handler.store(Some(Arc::new(Mutex::new(f))));
+        self.on_message_set.notify_one();
     }
 
     async fn do_message(&self, msg: DataChannelMessage) {
@@ -261,7 +303,11 @@ impl RTCDataChannel {
         }
     }
 
-    pub(crate) async fn handle_open(&self, dc: Arc<data::data_channel::DataChannel>) {
+    pub(crate) async fn handle_open(
+        &self,
+        dc: Arc<data::data_channel::DataChannel>,
+        association: Weak<Association>,
+    ) {
         {
             let mut data_channel = self.data_channel.lock().await;
             *data_channel = Some(Arc::clone(&dc));
@@ -272,16 +318,29 @@ impl RTCDataChannel {
 
         if !self.setting_engine.detach.data_channels {
             let ready_state = Arc::clone(&self.ready_state);
+            let close_reason = Arc::clone(&self.close_reason);
             let on_message_handler = Arc::clone(&self.on_message_handler);
+            let on_message_set = Arc::clone(&self.on_message_set);
             let on_close_handler = Arc::clone(&self.on_close_handler);
             let on_error_handler = Arc::clone(&self.on_error_handler);
             let notify_rx = self.notify_tx.clone();
-            tokio::spawn(async move {
+            let tasks = self.tasks.clone();
+            let pending_message_limit = match self.setting_engine.data_channel_pending_message_limit
+            {
+                0 => DEFAULT_PENDING_MESSAGE_LIMIT,
+                limit => limit,
+            };
+            self.tasks.spawn(async move {
                 RTCDataChannel::read_loop(
                     notify_rx,
+                    tasks,
                     dc,
+                    association,
                     ready_state,
+                    close_reason,
                     on_message_handler,
+                    on_message_set,
+                    pending_message_limit,
                     on_close_handler,
                     on_error_handler,
                 )
@@ -296,50 +355,81 @@ impl RTCDataChannel {
         self.on_error_handler.store(Some(Arc::new(Mutex::new(f))));
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn read_loop(
         notify_rx: Arc<Notify>,
+        tasks: TaskGroup,
         data_channel: Arc<data::data_channel::DataChannel>,
+        association: Weak<Association>,
         ready_state: Arc<AtomicU8>,
+        close_reason: Arc<SyncMutex<Option<CloseReason>>>,
         on_message_handler: Arc<ArcSwapOption<Mutex<OnMessageHdlrFn>>>,
+        on_message_set: Arc<Notify>,
+        pending_message_limit: usize,
         on_close_handler: Arc<ArcSwapOption<Mutex<OnCloseHdlrFn>>>,
         on_error_handler: Arc<ArcSwapOption<Mutex<OnErrorHdlrFn>>>,
     ) {
-        let mut buffer = vec![0u8; DATA_CHANNEL_BUFFER_SIZE as usize];
+        let buffer_size = if data_channel.config.chunked {
+            CHUNKED_DATA_CHANNEL_BUFFER_SIZE
+        } else {
+            DATA_CHANNEL_BUFFER_SIZE as usize
+        };
+        let mut buffer = vec![0u8; buffer_size];
+        // messages read while no on_message handler was set
+        let mut pending = VecDeque::new();
+        let mut dropped = 0u64;
         loop {
+            if !pending.is_empty() {
+                if let Some(handler) = &*on_message_handler.load() {
+                    let mut f = handler.lock().await;
+                    while let Some(msg) = pending.pop_front() {
+                        f(msg).await;
+                    }
+                }
+            }
+
             let (n, is_string) = tokio::select! {
                 _ = notify_rx.notified() => break,
+                _ = on_message_set.notified(), if !pending.is_empty() => continue,
                 result = data_channel.read_data_channel(&mut buffer) => {
                     match result{
+                        Ok((n, is_string)) if n > 0 => (n, is_string),
                         // EOF (`data_channel` was either closed or the underlying stream got
-                        // reset by the remote) => close and run `on_close` handler.
-                        Ok((0, _)) =>
-                        {
-                            ready_state.store(RTCDataChannelState::Closed as u8, Ordering::SeqCst);
-
-                            let on_close_handler2 = Arc::clone(&on_close_handler);
-                            tokio::spawn(async move {
-                                if let Some(handler) = &*on_close_handler2.load() {
-                                    let mut f = handler.lock().await;
-                                    f().await;
+                        // reset by the remote) => close and run `on_close` handler. Errors
+                        // run `on_error` first.
+                        result => {
+                            let mut err = result.err().map(Error::from);
+
+                            // A stream closed along with its association fails with the
+                            // reason, unless this end closed it or the peer shut it down.
+                            let reason = match association.upgrade() {
+                                Some(association) => association.close_reason().await,
+                                None => None,
+                            };
+                            match reason {
+                                Some(CloseReason::Local) | None => {}
+                                Some(reason) => {
+                                    *close_reason.lock() = Some(reason);
+                                    if reason != CloseReason::ShutdownReceived {
+                                        err = Some(sctp::Error::ErrAssociationClosed(reason).into());
+                                    }
                                 }
-                            });
+                            }
 
-                            break;
-                        }
-                        Ok((n, is_string)) => (n, is_string),
-                        Err(err) => {
                             ready_state.store(RTCDataChannelState::Closed as u8, Ordering::SeqCst);
 
-                            let on_error_handler2 = Arc::clone(&on_error_handler);
-                            tokio::spawn(async move {
-                                if let Some(handler) = &*on_error_handler2.load() {
-                                    let mut f = handler.lock().await;
-                                    f(err.into()).await;
-                                }
-                            });
+                            if let Some(err) = err {
+                                let on_error_handler2 = Arc::clone(&on_error_handler);
+                                tasks.spawn(async move {
+                                    if let Some(handler) = &*on_error_handler2.load() {
+                                        let mut f = handler.lock().await;
+                                        f(err).await;
+                                    }
+                                });
+                            }
 
                             let on_close_handler2 = Arc::clone(&on_close_handler);
-                            tokio::spawn(async move {
+                            tasks.spawn(async move {
                                 if let Some(handler) = &*on_close_handler2.load() {
                                     let mut f = handler.lock().await;
                                     f().await;
@@ -352,18 +442,35 @@ impl RTCDataChannel {
                 }
             };
 
+            let msg = DataChannelMessage {
+                is_string,
+                data: Bytes::from(buffer[..n].to_vec()),
+            };
             if let Some(handler) = &*on_message_handler.load() {
                 let mut f = handler.lock().await;
-                f(DataChannelMessage {
-                    is_string,
-                    data: Bytes::from(buffer[..n].to_vec()),
-                })
-                .await;
+                // the handler may have been set after the pending messages were checked
+                while let Some(msg) = pending.pop_front() {
+                    f(msg).await;
+                }
+                f(msg).await;
+            } else if pending.len() < pending_message_limit {
+                pending.push_back(msg);
+            } else {
+                if dropped == 0 {
+                    log::warn!(
+                        "data channel {} has no on_message handler and {} pending messages, dropping messages",
+                        data_channel.config.label,
+                        pending.len()
+                    );
+                }
+                dropped += 1;
             }
         }
     }
 
-    /// send sends the binary message to the DataChannel peer
+    /// send sends the binary message to the DataChannel peer. Messages larger than the max
+    /// message size of the remote fail with `sctp::Error::ErrOutboundPacketTooLarge` unless the
+    /// channel is chunked.
     pub async fn send(&self, data: &Bytes) -> Result<usize> {
         self.ensure_open()?;
 
@@ -422,18 +529,26 @@ impl RTCDataChannel {
     /// the DataChannel object was created by this peer or the remote peer.
     pub async fn close(&self) -> Result<()> {
         if self.ready_state() == RTCDataChannelState::Closed {
+            // the event handlers of a channel closed by the peer may still be running
+            self.tasks.close(TASKS_CLOSE_TIMEOUT).await;
             return Ok(());
         }
 
         self.set_ready_state(RTCDataChannelState::Closing);
         self.notify_tx.notify_waiters();
 
-        let data_channel = self.data_channel.lock().await;
-        if let Some(dc) = &*data_channel {
-            Ok(dc.close().await?)
-        } else {
-            Ok(())
-        }
+        let result = {
+            let data_channel = self.data_channel.lock().await;
+            if let Some(dc) = &*data_channel {
+                dc.close().await
+            } else {
+                Ok(())
+            }
+        };
+
+        self.tasks.close(TASKS_CLOSE_TIMEOUT).await;
+
+        Ok(result?)
     }
 
     /// label represents a label that can be used to distinguish this
@@ -450,23 +565,73 @@ impl RTCDataChannel {
     }
 
     /// max_packet_lifetime represents the length of the time window (msec) during
-    /// which transmissions and retransmissions may occur in unreliable mode.
-    pub fn max_packet_lifetime(&self) -> u16 {
+    /// which transmissions and retransmissions may occur in unreliable mode, None
+    /// unless set.
+    pub fn max_packet_lifetime(&self) -> Option<u16> {
         self.max_packet_lifetime
     }
 
     /// max_retransmits represents the maximum number of retransmissions that are
-    /// attempted in unreliable mode.
-    pub fn max_retransmits(&self) -> u16 {
+    /// attempted in unreliable mode, None unless set.
+    pub fn max_retransmits(&self) -> Option<u16> {
         self.max_retransmits
     }
 
+    /// is_ordered returns whether messages are currently sent with ordered delivery. A
+    /// channel which is not negotiated sends ordered until the remote acknowledged it, as
+    /// required by RFC 8832, even if out-of-order delivery is allowed. Before the channel is
+    /// opened, `ordered` is returned.
+    pub async fn is_ordered(&self) -> bool {
+        let data_channel = self.data_channel.lock().await;
+        match &*data_channel {
+            Some(dc) => dc.is_ordered(),
+            None => self.ordered,
+        }
+    }
+
+    /// reliability returns the partial reliability policy messages are currently sent with and
+    /// its parameter, the max number of retransmissions or the max packet lifetime. Before the
+    /// channel is opened, the configured policy is returned.
+    pub async fn reliability(&self) -> (ReliabilityType, u32) {
+        let data_channel = self.data_channel.lock().await;
+        match &*data_channel {
+            Some(dc) => dc.reliability(),
+            None => match self.channel_type() {
+                (ChannelType::Reliable | ChannelType::ReliableUnordered, _) => {
+                    (ReliabilityType::Reliable, 0)
+                }
+                (
+                    ChannelType::PartialReliableRexmit
+                    | ChannelType::PartialReliableRexmitUnordered,
+                    max_retransmits,
+                ) => (ReliabilityType::Rexmit, max_retransmits),
+                (
+                    ChannelType::PartialReliableTimed | ChannelType::PartialReliableTimedUnordered,
+                    max_packet_lifetime,
+                ) => (ReliabilityType::Timed, max_packet_lifetime),
+            },
+        }
+    }
+
     /// protocol represents the name of the sub-protocol used with this
     /// DataChannel.
     pub fn protocol(&self) -> &str {
         self.protocol.as_str()
     }
 
+    /// priority represents the priority of this DataChannel, either set with
+    /// `RTCDataChannelInit::priority` or received from the remote when it opened the channel.
+    pub fn priority(&self) -> RTCPriorityType {
+        RTCPriorityType::from(self.priority)
+    }
+
+    /// chunked represents whether binary messages are split into fragments reassembled by the
+    /// remote, either set with `RTCDataChannelInit::chunked` or announced by the remote when it
+    /// opened the channel.
+    pub fn chunked(&self) -> bool {
+        self.chunked
+    }
+
     /// negotiated represents whether this DataChannel was negotiated by the
     /// application (true), or not (false).
     pub fn negotiated(&self) -> bool {
@@ -488,6 +653,14 @@ impl RTCDataChannel {
         self.ready_state.load(Ordering::SeqCst).into()
     }
 
+    /// close_reason returns why the SCTP association carrying the channel was
+    /// closed by the peer or the network, once the channel is closed. None if
+    /// the channel or the association was closed by this end, or only the
+    /// channel was closed by the peer.
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        *self.close_reason.lock()
+    }
+
     /// buffered_amount represents the number of bytes of application data
     /// (UTF-8 text and binary data) that have been queued using send(). Even
     /// though the data transmission can occur in parallel, the returned value
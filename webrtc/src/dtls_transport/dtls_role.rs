@@ -55,6 +55,23 @@ impl fmt::Display for DTLSRole {
     }
 }
 
+/// connection_role_from_sdp returns the value of the first setup attribute of the
+/// SessionDescription, or ConnectionRole::Unspecified if there is none.
+pub(crate) fn connection_role_from_sdp(session_description: &SessionDescription) -> ConnectionRole {
+    for media_section in &session_description.media_descriptions {
+        for attribute in &media_section.attributes {
+            if attribute.key == "setup" {
+                return match attribute.value.as_deref() {
+                    Some(value) => ConnectionRole::from(value),
+                    None => ConnectionRole::Unspecified,
+                };
+            }
+        }
+    }
+
+    ConnectionRole::Unspecified
+}
+
 /// Iterate a SessionDescription from a remote to determine if an explicit
 /// role can been determined from it. The decision is made from the first role we we parse.
 /// If no role can be found we return DTLSRoleAuto
@@ -89,6 +106,15 @@ impl DTLSRole {
             _ => ConnectionRole::Unspecified,
         }
     }
+
+    /// inverse returns the role of the remote peer when we take this role.
+    pub(crate) fn inverse(self) -> DTLSRole {
+        match self {
+            DTLSRole::Client => DTLSRole::Server,
+            DTLSRole::Server => DTLSRole::Client,
+            role => role,
+        }
+    }
 }
 
 #[cfg(test)]
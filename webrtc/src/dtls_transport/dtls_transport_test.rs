@@ -8,6 +8,8 @@ use crate::peer_connection::peer_connection_state::RTCPeerConnectionState;
 use crate::peer_connection::peer_connection_test::{
     close_pair_now, new_pair, signal_pair, until_connection_state,
 };
+use crate::peer_connection::RTCPeerConnection;
+use crate::stats::{StatsReport, StatsReportType};
 use ice::mdns::MulticastDnsMode;
 use ice::network_type::NetworkType;
 use regex::Regex;
@@ -205,3 +207,284 @@ async fn test_peer_connection_dtls_role_setting_engine_client() -> Result<()> {
 
     run_test(DTLSRole::Client).await
 }
+
+#[tokio::test]
+async fn test_dtls_transport_stats() -> Result<()> {
+    let new_setting_engine = |role: Option<DTLSRole>| -> Result<SettingEngine> {
+        let mut s = SettingEngine::default();
+        if let Some(role) = role {
+            s.set_answering_dtls_role(role)?;
+        }
+        s.set_srtp_protection_profiles(vec![SrtpProtectionProfile::Srtp_Aead_Aes_128_Gcm]);
+        s.set_ice_multicast_dns_mode(MulticastDnsMode::Disabled);
+        s.set_network_types(vec![NetworkType::Udp4]);
+        Ok(s)
+    };
+
+    let mut offer_pc = APIBuilder::new()
+        .with_setting_engine(new_setting_engine(None)?)
+        .build()
+        .new_peer_connection(RTCConfiguration::default())
+        .await?;
+    let mut answer_pc = APIBuilder::new()
+        .with_setting_engine(new_setting_engine(Some(DTLSRole::Server))?)
+        .build()
+        .new_peer_connection(RTCConfiguration::default())
+        .await?;
+
+    signal_pair(&mut offer_pc, &mut answer_pc).await?;
+
+    let wg = WaitGroup::new();
+    until_connection_state(&mut offer_pc, &wg, RTCPeerConnectionState::Connected).await;
+    until_connection_state(&mut answer_pc, &wg, RTCPeerConnectionState::Connected).await;
+    wg.wait().await;
+
+    fn transport_stats(report: &StatsReport) -> &ICETransportStats {
+        match report.reports.get("ice_transport") {
+            Some(StatsReportType::Transport(stats)) => stats,
+            Some(_) => panic!("found the wrong type"),
+            None => panic!("missing transport stats"),
+        }
+    }
+    let offer_report = offer_pc.get_stats().await;
+    let offer_stats = transport_stats(&offer_report);
+    let answer_report = answer_pc.get_stats().await;
+    let answer_stats = transport_stats(&answer_report);
+
+    // The answerer forced setup:passive, the offerer acts as the DTLS client.
+    assert_eq!(offer_stats.dtls_role.as_deref(), Some("client"));
+    assert_eq!(answer_stats.dtls_role.as_deref(), Some("server"));
+
+    for stats in [offer_stats, answer_stats] {
+        assert_eq!(stats.tls_version.as_deref(), Some("FEFD"));
+        assert_eq!(stats.srtp_cipher.as_deref(), Some("AEAD_AES_128_GCM"));
+        assert!(stats
+            .dtls_cipher
+            .as_deref()
+            .map_or(false, |cipher| cipher.starts_with("TLS_")));
+        assert!(stats.dtls_handshake_duration.unwrap_or_default() > 0.0);
+        assert!(!stats
+            .selected_candidate_pair_id
+            .as_deref()
+            .unwrap_or_default()
+            .is_empty());
+    }
+    assert_eq!(offer_stats.dtls_cipher, answer_stats.dtls_cipher);
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
+
+async fn new_dtls_role_pc(answering_dtls_role: Option<DTLSRole>) -> Result<RTCPeerConnection> {
+    let mut s = SettingEngine::default();
+    if let Some(role) = answering_dtls_role {
+        s.set_answering_dtls_role(role)?;
+    }
+    s.set_ice_multicast_dns_mode(MulticastDnsMode::Disabled);
+    s.set_network_types(vec![NetworkType::Udp4]);
+
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+
+    APIBuilder::new()
+        .with_setting_engine(s)
+        .with_media_engine(m)
+        .build()
+        .new_peer_connection(RTCConfiguration::default())
+        .await
+}
+
+fn replace_setup(sdp: &str, setup: Option<&str>) -> String {
+    let re = Regex::new(r"a=setup:[a-z]+\r\n").unwrap();
+    let replacement = match setup {
+        Some(setup) => format!("a=setup:{}\r\n", setup),
+        None => String::new(),
+    };
+    re.replace_all(sdp, replacement.as_str()).to_string()
+}
+
+#[tokio::test]
+async fn test_dtls_role_offer_answer_setup() -> Result<()> {
+    struct Test {
+        name: &'static str,
+        answering_dtls_role: Option<DTLSRole>,
+        offer_setup: &'static str,
+        answer_setup: Option<Option<&'static str>>,
+        expected_answer_setup: Option<&'static str>,
+        expected_offer_error: Option<Error>,
+        expected_answer_error: Option<Error>,
+        expected_roles: (DTLSRole, DTLSRole),
+    }
+
+    let tests = vec![
+        Test {
+            name: "actpass offer, default answer",
+            answering_dtls_role: None,
+            offer_setup: "actpass",
+            answer_setup: None,
+            expected_answer_setup: Some("active"),
+            expected_offer_error: None,
+            expected_answer_error: None,
+            expected_roles: (DTLSRole::Server, DTLSRole::Client),
+        },
+        Test {
+            name: "actpass offer, passive answer",
+            answering_dtls_role: Some(DTLSRole::Server),
+            offer_setup: "actpass",
+            answer_setup: None,
+            expected_answer_setup: Some("passive"),
+            expected_offer_error: None,
+            expected_answer_error: None,
+            expected_roles: (DTLSRole::Client, DTLSRole::Server),
+        },
+        Test {
+            name: "passive offer overrides the answering role",
+            answering_dtls_role: Some(DTLSRole::Server),
+            offer_setup: "passive",
+            answer_setup: None,
+            expected_answer_setup: Some("active"),
+            expected_offer_error: None,
+            expected_answer_error: None,
+            expected_roles: (DTLSRole::Server, DTLSRole::Client),
+        },
+        Test {
+            name: "active offer is rejected",
+            answering_dtls_role: None,
+            offer_setup: "active",
+            answer_setup: None,
+            expected_answer_setup: None,
+            expected_offer_error: Some(Error::ErrPeerConnRemoteOfferSetupActive),
+            expected_answer_error: None,
+            expected_roles: (DTLSRole::Unspecified, DTLSRole::Unspecified),
+        },
+        Test {
+            name: "actpass answer is rejected",
+            answering_dtls_role: None,
+            offer_setup: "actpass",
+            answer_setup: Some(Some("actpass")),
+            expected_answer_setup: Some("active"),
+            expected_offer_error: None,
+            expected_answer_error: Some(Error::ErrPeerConnRemoteAnswerSetupActpass),
+            expected_roles: (DTLSRole::Unspecified, DTLSRole::Client),
+        },
+        Test {
+            name: "answer without setup is active",
+            answering_dtls_role: None,
+            offer_setup: "actpass",
+            answer_setup: Some(None),
+            expected_answer_setup: Some("active"),
+            expected_offer_error: None,
+            expected_answer_error: None,
+            expected_roles: (DTLSRole::Server, DTLSRole::Client),
+        },
+    ];
+
+    for test in tests {
+        let offer_pc = new_dtls_role_pc(None).await?;
+        let answer_pc = new_dtls_role_pc(test.answering_dtls_role).await?;
+
+        let _ = offer_pc.create_data_channel("data", None).await?;
+        let offer = offer_pc.create_offer(None).await?;
+        assert!(offer.sdp.contains("a=setup:actpass"), "{}", test.name);
+        offer_pc.set_local_description(offer.clone()).await?;
+
+        let mut offer = offer;
+        offer.sdp = replace_setup(&offer.sdp, Some(test.offer_setup));
+        let result = answer_pc.set_remote_description(offer).await;
+        if let Some(err) = test.expected_offer_error {
+            assert_eq!(result, Err(err), "{}", test.name);
+        } else {
+            assert!(result.is_ok(), "{}: {:?}", test.name, result);
+
+            let mut answer = answer_pc.create_answer(None).await?;
+            if let Some(setup) = test.expected_answer_setup {
+                assert!(
+                    answer.sdp.contains(&format!("a=setup:{}\r\n", setup)),
+                    "{}",
+                    test.name
+                );
+            }
+            answer_pc.set_local_description(answer.clone()).await?;
+
+            if let Some(setup) = test.answer_setup {
+                answer.sdp = replace_setup(&answer.sdp, setup);
+            }
+            let result = offer_pc.set_remote_description(answer).await;
+            if let Some(err) = test.expected_answer_error {
+                assert_eq!(result, Err(err), "{}", test.name);
+            } else {
+                assert!(result.is_ok(), "{}: {:?}", test.name, result);
+            }
+        }
+
+        assert_eq!(
+            offer_pc.sctp().transport().role().await,
+            test.expected_roles.0,
+            "{}: offerer role",
+            test.name
+        );
+        assert_eq!(
+            answer_pc.sctp().transport().role().await,
+            test.expected_roles.1,
+            "{}: answerer role",
+            test.name
+        );
+
+        close_pair_now(&offer_pc, &answer_pc).await;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dtls_role_kept_on_renegotiation() -> Result<()> {
+    let mut offer_pc = new_dtls_role_pc(None).await?;
+    let mut answer_pc = new_dtls_role_pc(None).await?;
+
+    signal_pair(&mut offer_pc, &mut answer_pc).await?;
+
+    let wg = WaitGroup::new();
+    until_connection_state(&mut offer_pc, &wg, RTCPeerConnectionState::Connected).await;
+    until_connection_state(&mut answer_pc, &wg, RTCPeerConnectionState::Connected).await;
+    wg.wait().await;
+
+    let offer_transport = offer_pc.sctp().transport();
+    let answer_transport = answer_pc.sctp().transport();
+    assert_eq!(offer_transport.role().await, DTLSRole::Server);
+    assert_eq!(answer_transport.role().await, DTLSRole::Client);
+
+    // A renegotiation answer claiming passive must not flip the roles
+    let offer = offer_pc.create_offer(None).await?;
+    offer_pc.set_local_description(offer.clone()).await?;
+    answer_pc.set_remote_description(offer).await?;
+    let mut answer = answer_pc.create_answer(None).await?;
+    assert!(answer.sdp.contains("a=setup:active\r\n"));
+    answer_pc.set_local_description(answer.clone()).await?;
+    answer.sdp = replace_setup(&answer.sdp, Some("passive"));
+    offer_pc.set_remote_description(answer).await?;
+
+    assert_eq!(offer_transport.role().await, DTLSRole::Server);
+    assert_eq!(answer_transport.role().await, DTLSRole::Client);
+
+    // When the roles of the offer/answer exchange swap, the new answerer keeps being passive
+    let offer = answer_pc.create_offer(None).await?;
+    assert!(offer.sdp.contains("a=setup:actpass\r\n"));
+    answer_pc.set_local_description(offer.clone()).await?;
+    offer_pc.set_remote_description(offer).await?;
+    let answer = offer_pc.create_answer(None).await?;
+    assert!(answer.sdp.contains("a=setup:passive\r\n"));
+    offer_pc.set_local_description(answer.clone()).await?;
+    answer_pc.set_remote_description(answer).await?;
+
+    assert_eq!(offer_transport.role().await, DTLSRole::Server);
+    assert_eq!(answer_transport.role().await, DTLSRole::Client);
+    assert_eq!(
+        offer_pc.connection_state(),
+        RTCPeerConnectionState::Connected
+    );
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
@@ -6,16 +6,19 @@ use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::Arc;
 
 use bytes::Bytes;
+use dtls::cipher_suite::CipherSuiteId;
 use dtls::config::ClientAuthType;
 use dtls::conn::DTLSConn;
 use dtls::extension::extension_use_srtp::SrtpProtectionProfile;
+use dtls::record_layer::record_layer_header::PROTOCOL_VERSION1_2;
 use interceptor::stream_info::StreamInfo;
-use interceptor::{Interceptor, RTCPReader, RTPReader};
+use interceptor::{Interceptor, RTCPReader};
 use sha2::{Digest, Sha256};
 use srtp::protection_profile::ProtectionProfile;
 use srtp::session::Session;
 use srtp::stream::Stream;
 use tokio::sync::{mpsc, Mutex};
+use tokio::time::{Duration, Instant};
 use util::Conn;
 
 use dtls_role::*;
@@ -30,8 +33,13 @@ use crate::ice_transport::RTCIceTransport;
 use crate::mux::endpoint::Endpoint;
 use crate::mux::mux_func::{match_dtls, match_srtcp, match_srtp, MatchFunc};
 use crate::peer_connection::certificate::RTCCertificate;
+use crate::peer_connection::deadline::Deadline;
+use crate::rtp_transceiver::rtp_receiver::RepairableStream;
 use crate::rtp_transceiver::SSRC;
 use crate::stats::stats_collector::StatsCollector;
+use crate::stats::ICETransportStats;
+use crate::stats::StatsReportType::Transport;
+use crate::track::TrackStream;
 
 #[cfg(test)]
 mod dtls_transport_test;
@@ -68,6 +76,9 @@ pub struct RTCDtlsTransport {
     pub(crate) remote_certificate: Mutex<Bytes>,
     pub(crate) state: AtomicU8, //DTLSTransportState,
     pub(crate) srtp_protection_profile: Mutex<ProtectionProfile>,
+    pub(crate) negotiated_role: Mutex<DTLSRole>,
+    pub(crate) cipher_suite: Mutex<Option<CipherSuiteId>>,
+    pub(crate) handshake_duration: Mutex<Option<Duration>>,
     pub(crate) on_state_change_handler: ArcSwapOption<Mutex<OnDTLSTransportStateChangeHdlrFn>>,
     pub(crate) conn: Mutex<Option<Arc<DTLSConn>>>,
 
@@ -83,6 +94,11 @@ pub struct RTCDtlsTransport {
     pub(crate) srtp_ready_rx: Mutex<Option<mpsc::Receiver<()>>>,
 
     pub(crate) dtls_matcher: Option<MatchFunc>,
+
+    pub(crate) handshake_deadline: Deadline,
+
+    // whether Cryptex was negotiated, read when the SRTP session starts
+    pub(crate) cryptex: AtomicBool,
 }
 
 impl RTCDtlsTransport {
@@ -181,6 +197,7 @@ impl RTCDtlsTransport {
 
         let mut srtp_config = srtp::config::Config {
             profile,
+            cryptex: self.cryptex.load(Ordering::SeqCst),
             ..Default::default()
         };
 
@@ -281,7 +298,14 @@ impl RTCDtlsTransport {
         srtcp_session.clone()
     }
 
-    pub(crate) async fn role(&self) -> DTLSRole {
+    /// role returns the DTLS role of this transport. It is resolved once the remote
+    /// description has been applied, or start was called, and is DTLSRole::Unspecified
+    /// before. Renegotiation never changes it.
+    pub async fn role(&self) -> DTLSRole {
+        *self.negotiated_role.lock().await
+    }
+
+    async fn resolve_role(&self) -> DTLSRole {
         // If remote has an explicit role use the inverse
         {
             let remote_parameters = self.remote_parameters.lock().await;
@@ -311,6 +335,36 @@ impl RTCDtlsTransport {
         for cert in &self.certificates {
             cert.collect_stats(collector).await;
         }
+
+        if let Some(mut stats) = self.ice_transport.transport_stats().await {
+            self.fill_transport_stats(&mut stats).await;
+            collector.insert(stats.id.clone(), Transport(stats));
+        }
+    }
+
+    /// fill_transport_stats adds the parameters negotiated by the DTLS handshake to the
+    /// transport stats, they are left unset until the handshake has completed.
+    async fn fill_transport_stats(&self, stats: &mut ICETransportStats) {
+        let handshake_duration = { *self.handshake_duration.lock().await };
+        let handshake_duration = match handshake_duration {
+            Some(handshake_duration) => handshake_duration,
+            None => return,
+        };
+
+        stats.tls_version = Some(format!(
+            "{:02X}{:02X}",
+            PROTOCOL_VERSION1_2.major, PROTOCOL_VERSION1_2.minor
+        ));
+        stats.dtls_cipher = { *self.cipher_suite.lock().await }.map(|id| id.to_string());
+        stats.srtp_cipher = Some(
+            match *self.srtp_protection_profile.lock().await {
+                ProtectionProfile::Aes128CmHmacSha1_80 => "AES_CM_128_HMAC_SHA1_80",
+                ProtectionProfile::AeadAes128Gcm => "AEAD_AES_128_GCM",
+            }
+            .to_owned(),
+        );
+        stats.dtls_role = Some(self.negotiated_role.lock().await.to_string());
+        stats.dtls_handshake_duration = Some(handshake_duration.as_secs_f64());
     }
 
     async fn prepare_transport(
@@ -336,6 +390,12 @@ impl RTCDtlsTransport {
             *rp = remote_parameters;
         }
 
+        let role = self.resolve_role().await;
+        {
+            let mut negotiated_role = self.negotiated_role.lock().await;
+            *negotiated_role = role;
+        }
+
         let certificate = if let Some(cert) = self.certificates.first() {
             cert.dtls_certificate.clone()
         } else {
@@ -344,7 +404,7 @@ impl RTCDtlsTransport {
         self.state_change(RTCDtlsTransportState::Connecting).await;
 
         Ok((
-            self.role().await,
+            role,
             dtls::config::Config {
                 certificates: vec![certificate],
                 srtp_protection_profiles: if !self
@@ -376,23 +436,42 @@ impl RTCDtlsTransport {
 
             // Connect as DTLS Client/Server, function is blocking and we
             // must not hold the DTLSTransport lock
-            if role == DTLSRole::Client {
-                dtls::conn::DTLSConn::new(
-                    dtls_endpoint as Arc<dyn Conn + Send + Sync>,
-                    dtls_config,
-                    true,
-                    None,
-                )
-                .await
-            } else {
-                dtls::conn::DTLSConn::new(
-                    dtls_endpoint as Arc<dyn Conn + Send + Sync>,
-                    dtls_config,
-                    false,
-                    None,
-                )
-                .await
+            let handshake_start = Instant::now();
+            let handshake = dtls::conn::DTLSConn::new(
+                Arc::clone(&dtls_endpoint) as Arc<dyn Conn + Send + Sync>,
+                dtls_config,
+                role == DTLSRole::Client,
+                None,
+            );
+            let dtls_conn_result = match self.setting_engine.timeout.dtls_handshake_timeout {
+                Some(timeout) => {
+                    self.handshake_deadline.set(Some(Instant::now() + timeout));
+                    let result = tokio::select! {
+                        result = handshake => Some(result),
+                        true = self.handshake_deadline.wait() => None,
+                    };
+                    self.handshake_deadline.set(None);
+
+                    match result {
+                        Some(result) => result,
+                        None => {
+                            if let Err(err) = dtls_endpoint.close().await {
+                                log::warn!("Failed to close DTLS endpoint: {}", err);
+                            }
+                            self.state_change(RTCDtlsTransportState::Failed).await;
+                            return Err(Error::ErrDtlsHandshakeTimeout);
+                        }
+                    }
+                }
+                None => handshake.await,
+            };
+
+            if dtls_conn_result.is_ok() {
+                let mut handshake_duration = self.handshake_duration.lock().await;
+                *handshake_duration = Some(handshake_start.elapsed());
             }
+
+            dtls_conn_result
         } else {
             Err(dtls::Error::Other(
                 "ice_transport.new_endpoint failed".to_owned(),
@@ -428,6 +507,11 @@ impl RTCDtlsTransport {
             };
         }
 
+        {
+            let mut cipher_suite = self.cipher_suite.lock().await;
+            *cipher_suite = dtls_conn.selected_cipher_suite().await;
+        }
+
         // Check the fingerprint if a certificate was exchanged
         let remote_certs = &dtls_conn.connection_state().await.peer_certificates;
         if remote_certs.is_empty() {
@@ -467,6 +551,16 @@ impl RTCDtlsTransport {
         self.start_srtp().await
     }
 
+    /// extend_handshake_deadline restarts the wait for a pending DTLS handshake, e.g. when ICE
+    /// restarts, so that the time spent reconnecting doesn't count.
+    pub(crate) fn extend_handshake_deadline(&self) {
+        if let Some(timeout) = self.setting_engine.timeout.dtls_handshake_timeout {
+            if self.handshake_deadline.get().is_some() {
+                self.handshake_deadline.set(Some(Instant::now() + timeout));
+            }
+        }
+    }
+
     /// stops and closes the DTLSTransport object.
     pub async fn stop(&self) -> Result<()> {
         // Try closing everything and collect the errors
@@ -576,26 +670,24 @@ impl RTCDtlsTransport {
         simulcast_streams.remove(&ssrc);
     }
 
+    /// streams_for_ssrc opens the SRTP and SRTCP streams of ssrc and binds the interceptors to
+    /// them. The RTP interceptors also read the packets sent to the `repaired_tx` of the
+    /// returned stream, the packets recovered from its repair stream.
     pub(crate) async fn streams_for_ssrc(
         &self,
         ssrc: SSRC,
         stream_info: &StreamInfo,
         interceptor: &Arc<dyn Interceptor + Send + Sync>,
-    ) -> Result<(
-        Option<Arc<srtp::stream::Stream>>,
-        Option<Arc<dyn RTPReader + Send + Sync>>,
-        Option<Arc<srtp::stream::Stream>>,
-        Option<Arc<dyn RTCPReader + Send + Sync>>,
-    )> {
+    ) -> Result<TrackStream> {
         let srtp_session = self
             .get_srtp_session()
             .await
             .ok_or(Error::ErrDtlsTransportNotStarted)?;
         //log::debug!("streams_for_ssrc: srtp_session.listen ssrc={}", ssrc);
         let rtp_read_stream = srtp_session.open(ssrc).await;
-        let rtp_stream_reader = Arc::clone(&rtp_read_stream) as Arc<dyn RTPReader + Send + Sync>;
+        let (rtp_stream_reader, repaired_tx) = RepairableStream::new(Arc::clone(&rtp_read_stream));
         let rtp_interceptor = interceptor
-            .bind_remote_stream(stream_info, rtp_stream_reader)
+            .bind_remote_stream(stream_info, Arc::new(rtp_stream_reader))
             .await;
 
         let srtcp_session = self
@@ -607,11 +699,13 @@ impl RTCDtlsTransport {
         let rtcp_stream_reader = Arc::clone(&rtcp_read_stream) as Arc<dyn RTCPReader + Send + Sync>;
         let rtcp_interceptor = interceptor.bind_rtcp_reader(rtcp_stream_reader).await;
 
-        Ok((
-            Some(rtp_read_stream),
-            Some(rtp_interceptor),
-            Some(rtcp_read_stream),
-            Some(rtcp_interceptor),
-        ))
+        Ok(TrackStream {
+            stream_info: Some(stream_info.clone()),
+            rtp_read_stream: Some(rtp_read_stream),
+            rtp_interceptor: Some(rtp_interceptor),
+            rtcp_read_stream: Some(rtcp_read_stream),
+            rtcp_interceptor: Some(rtcp_interceptor),
+            repaired_tx: Some(repaired_tx),
+        })
     }
 }
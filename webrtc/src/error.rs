@@ -81,6 +81,11 @@ pub enum Error {
     #[error("ice candidate pool size cannot be modified")]
     ErrModifyingICECandidatePoolSize,
 
+    /// ErrModifyingICEServers indicates that an attempt to modify
+    /// ICEServers was made after PeerConnection has been initialized.
+    #[error("ice servers cannot be modified")]
+    ErrModifyingICEServers,
+
     /// ErrStringSizeLimit indicates that the character size limit of string is
     /// exceeded. The limit is hardcoded to 65535 according to specifications.
     #[error("data channel label exceeds size limit")]
@@ -233,6 +238,8 @@ pub enum Error {
     ErrFailedToStartSRTCP,
     #[error("attempted to start DTLSTransport that is not in new state")]
     ErrInvalidDTLSStart,
+    #[error("the DTLS handshake timed out")]
+    ErrDtlsHandshakeTimeout,
     #[error("peer didn't provide certificate via DTLS")]
     ErrNoRemoteCertificate,
     #[error("identity provider is not implemented")]
@@ -273,6 +280,16 @@ pub enum Error {
     ErrPeerConnSDPTypeInvalidValueSetLocalDescription,
     #[error("remoteDescription contained media section without mid value")]
     ErrPeerConnRemoteDescriptionWithoutMidValue,
+    #[error(
+        "answer has no media section for the {kind} media section with mid {mid} of the offer"
+    )]
+    ErrPeerConnAnswerMissingMediaSection { mid: String, kind: String },
+    #[error("remote media section with mid {mid} is {remote_kind}, but {local_kind} locally")]
+    ErrPeerConnRemoteMediaSectionKindMismatch {
+        mid: String,
+        remote_kind: String,
+        local_kind: String,
+    },
     #[error("remoteDescription has not been set yet")]
     ErrPeerConnRemoteDescriptionNil,
     #[error("single media section has an explicit SSRC")]
@@ -301,6 +318,14 @@ pub enum Error {
     ErrPeerConnWriteRTCPOpenWriteStream,
     #[error("cannot find transceiver with mid")]
     ErrPeerConnTranscieverMidNil,
+    #[error("too many ICE candidates queued before the remote description was set")]
+    ErrPeerConnPendingCandidatesFull,
+    #[error("ICE candidate does not match a media section of the remote description")]
+    ErrPeerConnCandidateMediaSectionNotFound,
+    #[error("remote offer must not use setup:active")]
+    ErrPeerConnRemoteOfferSetupActive,
+    #[error("remote answer must use setup:active or setup:passive, not setup:actpass")]
+    ErrPeerConnRemoteAnswerSetupActpass,
     #[error("DTLSTransport must not be nil")]
     ErrRTPReceiverDTLSTransportNil,
     #[error("Receive has already been called")]
@@ -332,6 +357,10 @@ pub enum Error {
     ErrRTPTransceiverSetSendingInvalidState,
     #[error("unsupported codec type by this transceiver")]
     ErrRTPTransceiverCodecUnsupported,
+    #[error("unsupported header extension by this transceiver")]
+    ErrRTPTransceiverHeaderExtensionUnsupported,
+    #[error("header extension direction must not be unspecified")]
+    ErrRTPTransceiverHeaderExtensionDirectionInvalid,
     #[error("DTLS not established")]
     ErrSCTPTransportDTLS,
     #[error("add_transceiver_sdp() called with 0 transceivers")]
@@ -375,6 +404,8 @@ pub enum Error {
 
     #[error("not long enough to be a RTP Packet")]
     ErrRTPTooShort,
+    #[error("RTP packet has an unsupported version")]
+    ErrRTPUnsupportedVersion,
 
     #[error("{0}")]
     Util(#[from] util::Error),
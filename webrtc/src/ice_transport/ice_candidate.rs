@@ -7,6 +7,7 @@ use ice::candidate::candidate_peer_reflexive::CandidatePeerReflexiveConfig;
 use ice::candidate::candidate_relay::CandidateRelayConfig;
 use ice::candidate::candidate_server_reflexive::CandidateServerReflexiveConfig;
 use ice::candidate::Candidate;
+use ice::tcp_type::TcpType;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{Error, Result};
@@ -27,6 +28,12 @@ pub struct RTCIceCandidate {
     pub related_address: String,
     pub related_port: u16,
     pub tcp_type: String,
+    /// The network-id extension of the candidate, 0 if it has none
+    #[serde(default)]
+    pub network_id: u16,
+    /// The network-cost extension of the candidate, 0 if it has none
+    #[serde(default)]
+    pub network_cost: u16,
 }
 
 /// Conversion for ice_candidates
@@ -58,6 +65,8 @@ impl From<&Arc<dyn Candidate + Send + Sync>> for RTCIceCandidate {
             tcp_type: c.tcp_type().to_string(),
             related_address,
             related_port,
+            network_id: c.network_id(),
+            network_cost: c.network_cost(),
         }
     }
 }
@@ -74,12 +83,13 @@ impl RTCIceCandidate {
                         address: self.address.clone(),
                         port: self.port,
                         component: self.component,
-                        //tcp_type: ice.NewTCPType(c.TCPType),
                         foundation: self.foundation.clone(),
                         priority: self.priority,
+                        network_id: self.network_id,
+                        network_cost: self.network_cost,
                         ..Default::default()
                     },
-                    ..Default::default()
+                    tcp_type: TcpType::from(self.tcp_type.as_str()),
                 };
                 config.new_candidate_host()?
             }
@@ -93,6 +103,8 @@ impl RTCIceCandidate {
                         component: self.component,
                         foundation: self.foundation.clone(),
                         priority: self.priority,
+                        network_id: self.network_id,
+                        network_cost: self.network_cost,
                         ..Default::default()
                     },
                     rel_addr: self.related_address.clone(),
@@ -110,6 +122,8 @@ impl RTCIceCandidate {
                         component: self.component,
                         foundation: self.foundation.clone(),
                         priority: self.priority,
+                        network_id: self.network_id,
+                        network_cost: self.network_cost,
                         ..Default::default()
                     },
                     rel_addr: self.related_address.clone(),
@@ -127,6 +141,8 @@ impl RTCIceCandidate {
                         component: self.component,
                         foundation: self.foundation.clone(),
                         priority: self.priority,
+                        network_id: self.network_id,
+                        network_cost: self.network_cost,
                         ..Default::default()
                     },
                     rel_addr: self.related_address.clone(),
@@ -216,4 +232,34 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_ice_candidate_to_json_tcp_type() -> Result<()> {
+        let tests = vec![
+            (
+                "candidate:1052353102 1 tcp 1671430143 192.168.0.196 9 typ host tcptype active",
+                "active",
+            ),
+            (
+                "candidate:1052353102 1 tcp 1679818751 192.168.0.196 50000 typ host tcptype passive",
+                "passive",
+            ),
+            (
+                "candidate:4273957277 1 udp 2130706431 10.0.75.1 53634 typ host",
+                "unspecified",
+            ),
+        ];
+
+        for (raw, tcp_type) in tests {
+            let c: Arc<dyn Candidate + Send + Sync> = Arc::new(
+                ice::candidate::candidate_base::unmarshal_candidate(&raw["candidate:".len()..])?,
+            );
+            let candidate = RTCIceCandidate::from(&c);
+            assert_eq!(candidate.tcp_type, tcp_type);
+
+            assert_eq!(candidate.to_json()?.candidate, raw);
+        }
+
+        Ok(())
+    }
 }
@@ -1,6 +1,11 @@
 use crate::ice_transport::ice_candidate::*;
 
+use ice::agent::agent_stats::SelectedCandidatePairChange;
+use ice::candidate::CandidatePair;
 use std::fmt;
+use std::time::SystemTime;
+
+pub use ice::agent::agent_stats::SelectedCandidatePairChangeReason as RTCIceCandidatePairChangeReason;
 
 /// ICECandidatePair represents an ICE Candidate pair
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
@@ -31,4 +36,51 @@ impl RTCIceCandidatePair {
             remote,
         }
     }
+
+    /// local returns the local candidate of the pair.
+    pub fn local(&self) -> &RTCIceCandidate {
+        &self.local
+    }
+
+    /// remote returns the remote candidate of the pair.
+    pub fn remote(&self) -> &RTCIceCandidate {
+        &self.remote
+    }
+}
+
+impl From<&CandidatePair> for RTCIceCandidatePair {
+    fn from(pair: &CandidatePair) -> Self {
+        RTCIceCandidatePair::new(
+            RTCIceCandidate::from(&pair.local),
+            RTCIceCandidate::from(&pair.remote),
+        )
+    }
+}
+
+/// RTCIceCandidatePairChange describes a change of the selected ICE candidate pair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RTCIceCandidatePairChange {
+    /// The pair selected before, None for the initial nomination. After an ICE restart it is
+    /// the pair selected before the restart.
+    pub previous: Option<RTCIceCandidatePair>,
+    /// The pair selected now.
+    pub current: RTCIceCandidatePair,
+    /// Why the selected pair changed.
+    pub reason: RTCIceCandidatePairChangeReason,
+    /// When the selected pair changed.
+    pub timestamp: SystemTime,
+}
+
+impl From<&SelectedCandidatePairChange> for RTCIceCandidatePairChange {
+    fn from(change: &SelectedCandidatePairChange) -> Self {
+        RTCIceCandidatePairChange {
+            previous: change
+                .previous
+                .as_ref()
+                .map(|pair| RTCIceCandidatePair::from(pair.as_ref())),
+            current: RTCIceCandidatePair::from(change.current.as_ref()),
+            reason: change.reason,
+            timestamp: change.timestamp,
+        }
+    }
 }
@@ -10,6 +10,7 @@ use crate::stats::stats_collector::StatsCollector;
 use crate::stats::SourceStatsType::*;
 use crate::stats::{ICECandidatePairStats, StatsReportType};
 
+use ice::agent::agent_gather::CandidateError;
 use ice::agent::Agent;
 use ice::candidate::{Candidate, CandidateType};
 use ice::url::Url;
@@ -20,7 +21,8 @@ use std::future::Future;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify};
+use util::sync::Mutex as SyncMutex;
 
 /// ICEGatherOptions provides options relating to the gathering of ICE candidates.
 #[derive(Default, Debug, Clone)]
@@ -41,6 +43,23 @@ pub type OnICEGathererStateChangeHdlrFn = Box<
         + Sync,
 >;
 
+/// Describes why no candidate could be gathered from an ICE server, like the
+/// RTCPeerConnectionIceErrorEvent.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct RTCIceCandidateError {
+    /// The URL of the STUN or TURN server.
+    pub url: String,
+    /// 701 if the server couldn't be resolved or reached.
+    pub error_code: u16,
+    pub error_text: String,
+}
+
+pub type OnICECandidateErrorHdlrFn = Box<
+    dyn (FnMut(RTCIceCandidateError) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>)
+        + Send
+        + Sync,
+>;
+
 pub type OnGatheringCompleteHdlrFn =
     Box<dyn (FnMut() -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>) + Send + Sync>;
 
@@ -51,7 +70,7 @@ pub type OnGatheringCompleteHdlrFn =
 #[derive(Default)]
 pub struct RTCIceGatherer {
     pub(crate) validated_servers: Vec<Url>,
-    pub(crate) gather_policy: RTCIceTransportPolicy,
+    pub(crate) gather_policy: SyncMutex<RTCIceTransportPolicy>,
     pub(crate) setting_engine: Arc<SettingEngine>,
 
     pub(crate) state: Arc<AtomicU8>, //ICEGathererState,
@@ -59,9 +78,13 @@ pub struct RTCIceGatherer {
 
     pub(crate) on_local_candidate_handler: Arc<ArcSwapOption<Mutex<OnLocalCandidateHdlrFn>>>,
     pub(crate) on_state_change_handler: Arc<ArcSwapOption<Mutex<OnICEGathererStateChangeHdlrFn>>>,
+    pub(crate) on_candidate_error_handler: Arc<ArcSwapOption<Mutex<OnICECandidateErrorHdlrFn>>>,
 
     // Used for gathering_complete_promise
     pub(crate) on_gathering_complete_handler: Arc<ArcSwapOption<Mutex<OnGatheringCompleteHdlrFn>>>,
+
+    // Used for wait_gathering_complete, doesn't replace the handler above
+    pub(crate) gathering_complete: Arc<Notify>,
 }
 
 impl RTCIceGatherer {
@@ -71,7 +94,7 @@ impl RTCIceGatherer {
         setting_engine: Arc<SettingEngine>,
     ) -> Self {
         RTCIceGatherer {
-            gather_policy,
+            gather_policy: SyncMutex::new(gather_policy),
             validated_servers,
             setting_engine,
             state: Arc::new(AtomicU8::new(RTCIceGathererState::New as u8)),
@@ -90,12 +113,7 @@ impl RTCIceGatherer {
             return Ok(());
         }
 
-        let mut candidate_types = vec![];
-        if self.setting_engine.candidates.ice_lite {
-            candidate_types.push(ice::candidate::CandidateType::Host);
-        } else if self.gather_policy == RTCIceTransportPolicy::Relay {
-            candidate_types.push(ice::candidate::CandidateType::Relay);
-        }
+        let (candidate_types, remote_candidate_types) = self.candidate_types();
 
         let nat_1to1_cand_type = match self.setting_engine.candidates.nat_1to1_ip_candidate_type {
             RTCIceCandidateType::Host => CandidateType::Host,
@@ -114,17 +132,26 @@ impl RTCIceGatherer {
         let mut config = ice::agent::agent_config::AgentConfig {
             udp_network: self.setting_engine.udp_network.clone(),
             lite: self.setting_engine.candidates.ice_lite,
+            renomination: self.setting_engine.candidates.ice_renomination,
+            renomination_hold_time: self.setting_engine.candidates.ice_renomination_hold_time,
+            prefer_low_cost: self.setting_engine.candidates.ice_prefer_low_cost,
+            tcp_active_candidates: self.setting_engine.candidates.ice_tcp_active_candidates,
+            turn_over_tcp: self.setting_engine.candidates.ice_turn_over_tcp,
             urls: self.validated_servers.clone(),
             disconnected_timeout: self.setting_engine.timeout.ice_disconnected_timeout,
             failed_timeout: self.setting_engine.timeout.ice_failed_timeout,
             keepalive_interval: self.setting_engine.timeout.ice_keepalive_interval,
             candidate_types,
+            remote_candidate_types,
             host_acceptance_min_wait: self.setting_engine.timeout.ice_host_acceptance_min_wait,
             srflx_acceptance_min_wait: self.setting_engine.timeout.ice_srflx_acceptance_min_wait,
             prflx_acceptance_min_wait: self.setting_engine.timeout.ice_prflx_acceptance_min_wait,
             relay_acceptance_min_wait: self.setting_engine.timeout.ice_relay_acceptance_min_wait,
+            resolver: self.setting_engine.candidates.ice_resolver.clone(),
+            dns_timeout: self.setting_engine.timeout.ice_dns_timeout,
             interface_filter: self.setting_engine.candidates.interface_filter.clone(),
             ip_filter: self.setting_engine.candidates.ip_filter.clone(),
+            network_cost: self.setting_engine.candidates.network_cost.clone(),
             nat_1to1_ips: self.setting_engine.candidates.nat_1to1_ips.clone(),
             nat_1to1_ip_candidate_type: nat_1to1_cand_type,
             net: self.setting_engine.vnet.clone(),
@@ -136,7 +163,6 @@ impl RTCIceGatherer {
                 .clone(),
             local_ufrag: self.setting_engine.candidates.username_fragment.clone(),
             local_pwd: self.setting_engine.candidates.password.clone(),
-            //TODO: TCPMux:                 self.setting_engine.iceTCPMux,
             //TODO: ProxyDialer:            self.setting_engine.iceProxyDialer,
             ..Default::default()
         };
@@ -155,6 +181,33 @@ impl RTCIceGatherer {
         Ok(())
     }
 
+    /// Returns the local and remote candidate types the agent may use under the gather policy.
+    fn candidate_types(&self) -> (Vec<CandidateType>, Vec<CandidateType>) {
+        let mut candidate_types = vec![];
+        let mut remote_candidate_types = vec![];
+        if self.setting_engine.candidates.ice_lite {
+            candidate_types.push(CandidateType::Host);
+        } else if *self.gather_policy.lock() == RTCIceTransportPolicy::Relay {
+            candidate_types.push(CandidateType::Relay);
+            // Checks towards non-relay remote candidates could leak our addresses.
+            remote_candidate_types.push(CandidateType::Relay);
+        }
+        (candidate_types, remote_candidate_types)
+    }
+
+    /// Changes the gather policy. An existing agent keeps using the previous one until
+    /// apply_gather_policy is called when restarting it.
+    pub(crate) fn set_gather_policy(&self, gather_policy: RTCIceTransportPolicy) {
+        *self.gather_policy.lock() = gather_policy;
+    }
+
+    /// Makes the agent gather and accept candidates according to the current gather policy.
+    pub(crate) fn apply_gather_policy(&self, agent: &Agent) -> Result<()> {
+        let (candidate_types, remote_candidate_types) = self.candidate_types();
+        agent.set_candidate_types(candidate_types, remote_candidate_types)?;
+        Ok(())
+    }
+
     /// Gather ICE candidates.
     pub async fn gather(&self) -> Result<()> {
         self.create_agent().await?;
@@ -165,6 +218,7 @@ impl RTCIceGatherer {
             let on_local_candidate_handler = Arc::clone(&self.on_local_candidate_handler);
             let on_state_change_handler = Arc::clone(&self.on_state_change_handler);
             let on_gathering_complete_handler = Arc::clone(&self.on_gathering_complete_handler);
+            let gathering_complete = Arc::clone(&self.gathering_complete);
 
             agent.on_candidate(Box::new(
                 move |candidate: Option<Arc<dyn Candidate + Send + Sync>>| {
@@ -173,6 +227,7 @@ impl RTCIceGatherer {
                     let on_state_change_handler_clone = Arc::clone(&on_state_change_handler);
                     let on_gathering_complete_handler_clone =
                         Arc::clone(&on_gathering_complete_handler);
+                    let gathering_complete_clone = Arc::clone(&gathering_complete);
 
                     Box::pin(async move {
                         if let Some(cand) = candidate {
@@ -183,6 +238,7 @@ impl RTCIceGatherer {
                         } else {
                             state_clone
                                 .store(RTCIceGathererState::Complete as u8, Ordering::SeqCst);
+                            gathering_complete_clone.notify_waiters();
 
                             if let Some(handler) = &*on_state_change_handler_clone.load() {
                                 let mut f = handler.lock().await;
@@ -203,15 +259,43 @@ impl RTCIceGatherer {
                 },
             ));
 
+            let on_candidate_error_handler = Arc::clone(&self.on_candidate_error_handler);
+            agent.on_candidate_error(Box::new(move |err: CandidateError| {
+                let on_candidate_error_handler_clone = Arc::clone(&on_candidate_error_handler);
+                Box::pin(async move {
+                    if let Some(handler) = &*on_candidate_error_handler_clone.load() {
+                        let mut f = handler.lock().await;
+                        f(RTCIceCandidateError {
+                            url: err.url.to_string(),
+                            error_code: err.error_code,
+                            error_text: err.error_text,
+                        })
+                        .await;
+                    }
+                })
+            }));
+
             agent.gather_candidates()?;
         }
 
         Ok(())
     }
 
+    /// wait_gathering_complete returns once gathering completes or the gatherer is closed, right
+    /// away if that already happened.
+    pub(crate) async fn wait_gathering_complete(&self) {
+        // Created before the state is checked so a completion in between isn't missed.
+        let notified = self.gathering_complete.notified();
+        match self.state() {
+            RTCIceGathererState::Complete | RTCIceGathererState::Closed => {}
+            _ => notified.await,
+        }
+    }
+
     /// Close prunes all local candidates, and closes the ports.
     pub async fn close(&self) -> Result<()> {
         self.set_state(RTCIceGathererState::Closed).await;
+        self.gathering_complete.notify_waiters();
 
         let agent = {
             let mut agent_opt = self.agent.lock().await;
@@ -268,6 +352,13 @@ impl RTCIceGatherer {
             .store(Some(Arc::new(Mutex::new(f))));
     }
 
+    /// on_candidate_error sets an event handler which fires when no candidate could be gathered
+    /// from a STUN or TURN server.
+    pub fn on_candidate_error(&self, f: OnICECandidateErrorHdlrFn) {
+        self.on_candidate_error_handler
+            .store(Some(Arc::new(Mutex::new(f))));
+    }
+
     /// on_gathering_complete sets an event handler which fires any time the ICEGatherer changes
     pub fn on_gathering_complete(&self, f: OnGatheringCompleteHdlrFn) {
         self.on_gathering_complete_handler
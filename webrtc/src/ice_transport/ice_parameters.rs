@@ -1,10 +1,24 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
 /// ICEParameters includes the ICE username fragment
 /// and password and other ICE-related parameters.
-#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// The password is left out of the Debug output so that the parameters can be logged.
+#[derive(Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RTCIceParameters {
     pub username_fragment: String,
     pub password: String,
     pub ice_lite: bool,
 }
+
+impl fmt::Debug for RTCIceParameters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RTCIceParameters")
+            .field("username_fragment", &self.username_fragment)
+            .field("password", &"<redacted>")
+            .field("ice_lite", &self.ice_lite)
+            .finish()
+    }
+}
@@ -1,12 +1,22 @@
 use super::*;
 use crate::api::media_engine::MediaEngine;
+use crate::api::setting_engine::SettingEngine;
 use crate::api::APIBuilder;
+use crate::data_channel::data_channel_message::DataChannelMessage;
+use crate::data_channel::RTCDataChannel;
 use crate::error::Result;
+use crate::ice_transport::ice_candidate_pair::RTCIceCandidatePairChangeReason;
 use crate::ice_transport::ice_connection_state::RTCIceConnectionState;
+use crate::ice_transport::ice_protocol::RTCIceProtocol;
+use crate::peer_connection::configuration::RTCConfiguration;
+use crate::peer_connection::offer_answer_options::RTCOfferOptions;
 use crate::peer_connection::peer_connection_state::RTCPeerConnectionState;
 use crate::peer_connection::peer_connection_test::{
     close_pair_now, new_pair, signal_pair, until_connection_state,
 };
+use crate::stats::StatsReportType;
+use bytes::Bytes;
+use ice::network_type::NetworkType;
 use std::sync::atomic::AtomicU32;
 use tokio::time::Duration;
 use waitgroup::WaitGroup;
@@ -38,11 +48,19 @@ async fn test_ice_transport_on_selected_candidate_pair_change() -> Result<()> {
         .sctp()
         .transport()
         .ice_transport()
-        .on_selected_candidate_pair_change(Box::new(move |_: RTCIceCandidatePair| {
+        .on_selected_candidate_pair_change(Box::new(move |_: RTCIceCandidatePairChange| {
             sender_called_candidate_change2.store(1, Ordering::SeqCst);
             Box::pin(async {})
         }));
 
+    let (answer_changes_tx, mut answer_changes_rx) = mpsc::unbounded_channel();
+    pc_answer.on_selected_candidate_pair_change(Box::new(
+        move |change: RTCIceCandidatePairChange| {
+            let _ = answer_changes_tx.send(change);
+            Box::pin(async {})
+        },
+    ));
+
     signal_pair(&mut pc_offer, &mut pc_answer).await?;
 
     let _ = ice_complete_rx.recv().await;
@@ -52,6 +70,25 @@ async fn test_ice_transport_on_selected_candidate_pair_change() -> Result<()> {
         "Sender ICETransport OnSelectedCandidateChange was never called"
     );
 
+    let change = answer_changes_rx
+        .try_recv()
+        .expect("the peer connection handler should be called");
+    assert_eq!(
+        change.reason,
+        RTCIceCandidatePairChangeReason::InitialNomination
+    );
+    assert!(change.previous.is_none());
+    let dtls_transport = pc_answer.sctp().transport();
+    let ice_transport = dtls_transport.ice_transport();
+    assert_eq!(
+        ice_transport.get_selected_candidate_pair().await,
+        Some(change.current.clone())
+    );
+    assert_eq!(
+        ice_transport.selected_candidate_pair_changes().await,
+        vec![change]
+    );
+
     close_pair_now(&pc_offer, &pc_answer).await;
 
     Ok(())
@@ -119,3 +156,205 @@ async fn test_ice_transport_get_selected_candidate_pair() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_ice_transport_tcp_only_peer() -> Result<()> {
+    // vnet has no TCP, the peers connect over the real network. The answerer gathers TCP
+    // candidates only, so the offerer can only reach it over TCP.
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let mut s = SettingEngine::default();
+    s.set_network_types(vec![NetworkType::Udp4, NetworkType::Tcp4]);
+    let offer_api = APIBuilder::new()
+        .with_media_engine(m)
+        .with_setting_engine(s)
+        .build();
+
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let mut s = SettingEngine::default();
+    s.set_network_types(vec![NetworkType::Tcp4]);
+    s.set_ice_tcp_active_candidates(true);
+    let answer_api = APIBuilder::new()
+        .with_media_engine(m)
+        .with_setting_engine(s)
+        .build();
+
+    let mut pc_offer = offer_api
+        .new_peer_connection(RTCConfiguration::default())
+        .await?;
+    let mut pc_answer = answer_api
+        .new_peer_connection(RTCConfiguration::default())
+        .await?;
+
+    let dc = pc_offer.create_data_channel("tcp", None).await?;
+    let (message_tx, mut message_rx) = mpsc::channel::<Bytes>(1);
+    dc.on_message(Box::new(move |msg: DataChannelMessage| {
+        let message_tx = message_tx.clone();
+        Box::pin(async move {
+            let _ = message_tx.send(msg.data).await;
+        })
+    }));
+    pc_answer.on_data_channel(Box::new(move |d: Arc<RTCDataChannel>| {
+        if d.label() != "tcp" {
+            return Box::pin(async {});
+        }
+        Box::pin(async move {
+            let d2 = Arc::clone(&d);
+            d.on_open(Box::new(move || {
+                Box::pin(async move {
+                    let result = d2.send_text("over tcp".to_owned()).await;
+                    assert!(result.is_ok(), "Failed to send string on data channel");
+                })
+            }));
+        })
+    }));
+
+    signal_pair(&mut pc_offer, &mut pc_answer).await?;
+
+    let message = tokio::time::timeout(Duration::from_secs(10), message_rx.recv())
+        .await
+        .expect("timed out waiting for the message sent over TCP");
+    assert_eq!(message, Some(Bytes::from_static(b"over tcp")));
+
+    let answer = pc_answer.local_description().await.unwrap();
+    let candidates: Vec<&str> = answer
+        .sdp
+        .lines()
+        .filter(|l| l.starts_with("a=candidate:"))
+        .collect();
+    assert!(!candidates.is_empty());
+    assert!(candidates.iter().all(|c| c.contains(" tcp ")));
+    assert!(candidates.iter().any(|c| c.contains(" tcptype active")));
+
+    for pc in [&pc_offer, &pc_answer].iter() {
+        let pair = pc
+            .sctp()
+            .transport()
+            .ice_transport()
+            .get_selected_candidate_pair()
+            .await
+            .unwrap();
+        assert_eq!(pair.local().protocol, RTCIceProtocol::Tcp);
+        assert_eq!(pair.remote().protocol, RTCIceProtocol::Tcp);
+
+        let nominated_over_tcp = pc.get_stats().await.reports.values().any(|r| match r {
+            StatsReportType::CandidatePair(stats) => {
+                stats.nominated && stats.network_type == NetworkType::Tcp4
+            }
+            _ => false,
+        });
+        assert!(nominated_over_tcp);
+    }
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+fn sdp_attribute<'a>(sdp: &'a str, name: &str) -> Option<&'a str> {
+    let prefix = format!("a={name}:");
+    sdp.lines()
+        .find_map(|line| line.strip_prefix(prefix.as_str()))
+}
+
+#[tokio::test]
+async fn test_ice_transport_local_and_remote_parameters() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut offerer, mut answerer) = new_pair(&api).await?;
+
+    let peer_connection_connected = WaitGroup::new();
+    until_connection_state(
+        &mut offerer,
+        &peer_connection_connected,
+        RTCPeerConnectionState::Connected,
+    )
+    .await;
+    until_connection_state(
+        &mut answerer,
+        &peer_connection_connected,
+        RTCPeerConnectionState::Connected,
+    )
+    .await;
+
+    let offerer_dtls_transport = offerer.sctp().transport();
+    let offerer_transport = offerer_dtls_transport.ice_transport();
+    let answerer_dtls_transport = answerer.sctp().transport();
+    let answerer_transport = answerer_dtls_transport.ice_transport();
+
+    // Available before the offer is created
+    let offerer_params = offerer_transport.local_parameters_with_secret().await?;
+    assert!(!offerer_params.username_fragment.is_empty());
+    assert!(!offerer_params.password.is_empty());
+    assert!(offerer_transport
+        .remote_parameters_with_secret()
+        .await
+        .is_none());
+    assert!(!format!("{offerer_params:?}").contains(&offerer_params.password));
+
+    signal_pair(&mut offerer, &mut answerer).await?;
+    peer_connection_connected.wait().await;
+
+    let offer = offerer.local_description().await.unwrap();
+    assert_eq!(
+        sdp_attribute(&offer.sdp, "ice-ufrag"),
+        Some(offerer_params.username_fragment.as_str())
+    );
+    assert_eq!(
+        sdp_attribute(&offer.sdp, "ice-pwd"),
+        Some(offerer_params.password.as_str())
+    );
+
+    let answerer_params = answerer_transport.local_parameters_with_secret().await?;
+    let answer = answerer.local_description().await.unwrap();
+    assert_eq!(
+        sdp_attribute(&answer.sdp, "ice-ufrag"),
+        Some(answerer_params.username_fragment.as_str())
+    );
+    assert_eq!(
+        answerer_transport.remote_parameters_with_secret().await,
+        Some(offerer_params.clone())
+    );
+    assert_eq!(
+        offerer_transport.remote_parameters_with_secret().await,
+        Some(answerer_params)
+    );
+
+    let (changed_tx, mut changed_rx) = mpsc::channel(1);
+    offerer_transport.on_local_parameters_change(Box::new(move |params: RTCIceParameters| {
+        let changed_tx = changed_tx.clone();
+        Box::pin(async move {
+            let _ = changed_tx.try_send(params);
+        })
+    }));
+
+    let restart_offer = offerer
+        .create_offer(Some(RTCOfferOptions {
+            ice_restart: true,
+            ..Default::default()
+        }))
+        .await?;
+
+    let restarted_params = offerer_transport.local_parameters_with_secret().await?;
+    assert_ne!(
+        restarted_params.username_fragment,
+        offerer_params.username_fragment
+    );
+    assert_ne!(restarted_params.password, offerer_params.password);
+    assert_eq!(changed_rx.try_recv().ok(), Some(restarted_params.clone()));
+    assert_eq!(
+        sdp_attribute(&restart_offer.sdp, "ice-ufrag"),
+        Some(restarted_params.username_fragment.as_str())
+    );
+    assert_eq!(
+        sdp_attribute(&restart_offer.sdp, "ice-pwd"),
+        Some(restarted_params.password.as_str())
+    );
+
+    close_pair_now(&offerer, &answerer).await;
+
+    Ok(())
+}
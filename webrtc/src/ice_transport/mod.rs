@@ -2,15 +2,18 @@ use std::future::Future;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use arc_swap::ArcSwapOption;
+use ice::agent::agent_stats::SelectedCandidatePairChange;
 use ice::candidate::Candidate;
+use ice::options::IceOptions;
 use ice::state::ConnectionState;
 use tokio::sync::{mpsc, Mutex};
 use util::Conn;
 
 use ice_candidate::RTCIceCandidate;
-use ice_candidate_pair::RTCIceCandidatePair;
+use ice_candidate_pair::{RTCIceCandidatePair, RTCIceCandidatePairChange};
 use ice_gatherer::RTCIceGatherer;
 use ice_role::RTCIceRole;
 
@@ -20,9 +23,7 @@ use crate::ice_transport::ice_transport_state::RTCIceTransportState;
 use crate::mux::endpoint::Endpoint;
 use crate::mux::mux_func::MatchFunc;
 use crate::mux::{Config, Mux};
-use crate::stats::stats_collector::StatsCollector;
 use crate::stats::ICETransportStats;
-use crate::stats::StatsReportType::Transport;
 
 #[cfg(test)]
 mod ice_transport_test;
@@ -47,8 +48,14 @@ pub type OnConnectionStateChangeHdlrFn = Box<
         + Sync,
 >;
 
+pub type OnLocalParametersChangeHdlrFn = Box<
+    dyn (FnMut(RTCIceParameters) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>)
+        + Send
+        + Sync,
+>;
+
 pub type OnSelectedCandidatePairChangeHdlrFn = Box<
-    dyn (FnMut(RTCIceCandidatePair) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>)
+    dyn (FnMut(RTCIceCandidatePairChange) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>)
         + Send
         + Sync,
 >;
@@ -59,6 +66,7 @@ struct ICETransportInternal {
     conn: Option<Arc<dyn Conn + Send + Sync>>, //AgentConn
     mux: Option<Mux>,
     cancel_tx: Option<mpsc::Sender<()>>,
+    remote_ice_lite: bool,
 }
 
 /// ICETransport allows an application access to information about the ICE
@@ -69,6 +77,7 @@ pub struct RTCIceTransport {
     on_connection_state_change_handler: Arc<ArcSwapOption<Mutex<OnConnectionStateChangeHdlrFn>>>,
     on_selected_candidate_pair_change_handler:
         Arc<ArcSwapOption<Mutex<OnSelectedCandidatePairChangeHdlrFn>>>,
+    on_local_parameters_change_handler: ArcSwapOption<Mutex<OnLocalParametersChangeHdlrFn>>,
     state: Arc<AtomicU8>, // ICETransportState
     internal: Mutex<ICETransportInternal>,
 }
@@ -96,6 +105,58 @@ impl RTCIceTransport {
         None
     }
 
+    /// selected_candidate_pair_changes returns the latest changes of the selected candidate
+    /// pair, oldest first.
+    pub async fn selected_candidate_pair_changes(&self) -> Vec<RTCIceCandidatePairChange> {
+        match self.gatherer.get_agent().await {
+            Some(agent) => agent
+                .get_selected_candidate_pair_changes()
+                .iter()
+                .map(RTCIceCandidatePairChange::from)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// current_round_trip_time returns the latest round trip time measured by the STUN
+    /// connectivity and consent checks on the selected candidate pair, if any.
+    pub async fn current_round_trip_time(&self) -> Option<Duration> {
+        let agent = self.gatherer.get_agent().await?;
+        agent
+            .get_selected_candidate_pair()?
+            .current_round_trip_time()
+    }
+
+    /// local_parameters_with_secret returns the username fragment and password the local ICE
+    /// agent authenticates connectivity checks with, the ones signaled in the local description.
+    /// They are available as soon as the gatherer created its agent, so before an offer
+    /// is created, and change with each ICE restart, see [`RTCIceTransport::on_local_parameters_change`].
+    ///
+    /// The password authenticates the session, it should not be logged or sent anywhere but to the peer.
+    pub async fn local_parameters_with_secret(&self) -> Result<RTCIceParameters> {
+        self.gatherer.get_local_parameters().await
+    }
+
+    /// remote_parameters_with_secret returns the username fragment and password of the remote
+    /// ICE agent, the ones of the latest remote description. None is returned before they are
+    /// known.
+    ///
+    /// The password authenticates the session, it should not be logged.
+    pub async fn remote_parameters_with_secret(&self) -> Option<RTCIceParameters> {
+        let agent = self.gatherer.get_agent().await?;
+        let (username_fragment, password) = agent.get_remote_user_credentials().await;
+        if username_fragment.is_empty() {
+            return None;
+        }
+
+        let ice_lite = self.internal.lock().await.remote_ice_lite;
+        Some(RTCIceParameters {
+            username_fragment,
+            password,
+            ice_lite,
+        })
+    }
+
     /// Start incoming connectivity checks based on its configured role.
     pub async fn start(&self, params: &RTCIceParameters, role: Option<RTCIceRole>) -> Result<()> {
         if self.state() != RTCIceTransportState::New {
@@ -125,18 +186,16 @@ impl RTCIceTransport {
             let on_selected_candidate_pair_change_handler =
                 Arc::clone(&self.on_selected_candidate_pair_change_handler);
             agent.on_selected_candidate_pair_change(Box::new(
-                move |local: &Arc<dyn Candidate + Send + Sync>,
-                      remote: &Arc<dyn Candidate + Send + Sync>| {
+                move |change: &SelectedCandidatePairChange| {
                     let on_selected_candidate_pair_change_handler_clone =
                         Arc::clone(&on_selected_candidate_pair_change_handler);
-                    let local = RTCIceCandidate::from(local);
-                    let remote = RTCIceCandidate::from(remote);
+                    let change = RTCIceCandidatePairChange::from(change);
                     Box::pin(async move {
                         if let Some(handler) =
                             &*on_selected_candidate_pair_change_handler_clone.load()
                         {
                             let mut f = handler.lock().await;
-                            f(RTCIceCandidatePair::new(local, remote)).await;
+                            f(change).await;
                         }
                     })
                 },
@@ -153,6 +212,7 @@ impl RTCIceTransport {
                 let mut internal = self.internal.lock().await;
                 internal.role = role;
                 internal.cancel_tx = Some(cancel_tx);
+                internal.remote_ice_lite = params.ice_lite;
             }
 
             let conn: Arc<dyn Conn + Send + Sync> = match role {
@@ -200,6 +260,7 @@ impl RTCIceTransport {
     /// so for now lets keep it private so we don't cause ORTC users to depend on non-standard APIs
     pub(crate) async fn restart(&self) -> Result<()> {
         if let Some(agent) = self.gatherer.get_agent().await {
+            self.gatherer.apply_gather_policy(&agent)?;
             agent
                 .restart(
                     self.gatherer
@@ -213,6 +274,13 @@ impl RTCIceTransport {
         } else {
             return Err(Error::ErrICEAgentNotExist);
         }
+
+        if let Some(handler) = &*self.on_local_parameters_change_handler.load() {
+            let params = self.gatherer.get_local_parameters().await?;
+            let mut f = handler.lock().await;
+            f(params).await;
+        }
+
         self.gatherer.gather().await
     }
 
@@ -242,12 +310,19 @@ impl RTCIceTransport {
     }
 
     /// on_selected_candidate_pair_change sets a handler that is invoked when a new
-    /// ICE candidate pair is selected
+    /// ICE candidate pair is selected, with the previous pair and the reason of the change.
     pub fn on_selected_candidate_pair_change(&self, f: OnSelectedCandidatePairChangeHdlrFn) {
         self.on_selected_candidate_pair_change_handler
             .store(Some(Arc::new(Mutex::new(f))));
     }
 
+    /// on_local_parameters_change sets a handler that is fired with the new local parameters
+    /// when an ICE restart changed them.
+    pub fn on_local_parameters_change(&self, f: OnLocalParametersChangeHdlrFn) {
+        self.on_local_parameters_change_handler
+            .store(Some(Arc::new(Mutex::new(f))));
+    }
+
     /// on_connection_state_change sets a handler that is fired when the ICE
     /// connection state changes.
     pub fn on_connection_state_change(&self, f: OnConnectionStateChangeHdlrFn) {
@@ -287,6 +362,8 @@ impl RTCIceTransport {
             if let Some(r) = remote_candidate {
                 let c: Arc<dyn Candidate + Send + Sync> = Arc::new(r.to_ice()?);
                 agent.add_remote_candidate(&c)?;
+            } else {
+                agent.set_remote_end_of_candidates();
             }
 
             Ok(())
@@ -295,6 +372,18 @@ impl RTCIceTransport {
         }
     }
 
+    /// set_remote_options sets the ICE options signaled in the remote description.
+    pub(crate) async fn set_remote_options(&self, options: IceOptions) -> Result<()> {
+        self.ensure_gatherer().await?;
+
+        if let Some(agent) = self.gatherer.get_agent().await {
+            agent.set_remote_options(options);
+            Ok(())
+        } else {
+            Err(Error::ErrICEAgentNotExist)
+        }
+    }
+
     /// State returns the current ice transport state.
     pub fn state(&self) -> RTCIceTransportState {
         RTCIceTransportState::from(self.state.load(Ordering::SeqCst))
@@ -321,12 +410,11 @@ impl RTCIceTransport {
         }
     }
 
-    pub(crate) async fn collect_stats(&self, collector: &StatsCollector) {
-        if let Some(agent) = self.gatherer.get_agent().await {
-            let stats = ICETransportStats::new("ice_transport".to_string(), agent);
-
-            collector.insert("ice_transport".to_string(), Transport(stats));
-        }
+    /// transport_stats returns the transport stats of the ICE agent, the DTLS transport
+    /// completes them with the negotiated DTLS parameters.
+    pub(crate) async fn transport_stats(&self) -> Option<ICETransportStats> {
+        let agent = self.gatherer.get_agent().await?;
+        Some(ICETransportStats::new("ice_transport".to_string(), agent))
     }
 
     pub(crate) async fn have_remote_credentials_change(
@@ -23,6 +23,7 @@ pub mod dtls_transport;
 pub mod error;
 pub mod ice_transport;
 pub mod mux;
+pub mod negotiation;
 pub mod peer_connection;
 pub mod rtp_transceiver;
 pub mod sctp_transport;
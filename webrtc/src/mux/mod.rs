@@ -7,11 +7,13 @@ pub mod mux_func;
 use crate::error::Result;
 use crate::mux::endpoint::Endpoint;
 use crate::mux::mux_func::MatchFunc;
+use crate::peer_connection::TASKS_CLOSE_TIMEOUT;
 
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::{mpsc, Mutex};
+use util::sync::TaskGroup;
 use util::{Buffer, Conn};
 
 /// mux multiplexes packets on a single socket (RFC7983)
@@ -34,6 +36,7 @@ pub struct Mux {
     endpoints: Arc<Mutex<HashMap<usize, Arc<Endpoint>>>>,
     buffer_size: usize,
     closed_ch_tx: Option<mpsc::Sender<()>>,
+    tasks: TaskGroup,
 }
 
 impl Mux {
@@ -45,12 +48,13 @@ impl Mux {
             endpoints: Arc::new(Mutex::new(HashMap::new())),
             buffer_size: config.buffer_size,
             closed_ch_tx: Some(closed_ch_tx),
+            tasks: TaskGroup::new(),
         };
 
         let buffer_size = m.buffer_size;
         let next_conn = Arc::clone(&m.next_conn);
         let endpoints = Arc::clone(&m.endpoints);
-        tokio::spawn(async move {
+        m.tasks.spawn(async move {
             Mux::read_loop(buffer_size, next_conn, closed_ch_rx, endpoints).await;
         });
 
@@ -88,8 +92,12 @@ impl Mux {
     pub async fn close(&mut self) {
         self.closed_ch_tx.take();
 
-        let mut endpoints = self.endpoints.lock().await;
-        endpoints.clear();
+        {
+            let mut endpoints = self.endpoints.lock().await;
+            endpoints.clear();
+        }
+
+        self.tasks.close(TASKS_CLOSE_TIMEOUT).await;
     }
 
     async fn read_loop(
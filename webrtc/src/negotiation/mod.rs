@@ -0,0 +1,226 @@
+#[cfg(test)]
+mod negotiation_test;
+
+use crate::data_channel::data_channel_init::RTCDataChannelInit;
+use crate::data_channel::RTCDataChannel;
+use crate::error::Result;
+use crate::ice_transport::ice_candidate::{RTCIceCandidate, RTCIceCandidateInit};
+use crate::peer_connection::sdp::sdp_type::RTCSdpType;
+use crate::peer_connection::sdp::session_description::RTCSessionDescription;
+use crate::peer_connection::signaling_state::RTCSignalingState;
+use crate::peer_connection::RTCPeerConnection;
+use crate::rtp_transceiver::rtp_sender::RTCRtpSender;
+use crate::track::track_local::TrackLocal;
+
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+/// Signal is a message exchanged by two PerfectNegotiators over their SignalingChannel.
+#[derive(Debug, Clone)]
+pub enum Signal {
+    Description(Box<RTCSessionDescription>),
+    Candidate(RTCIceCandidateInit),
+}
+
+/// SignalingChannel carries the signals of a PerfectNegotiator to the remote one. Signals must
+/// be received in the order the remote sent them.
+#[async_trait]
+pub trait SignalingChannel {
+    /// send delivers signal to the remote negotiator.
+    async fn send(&self, signal: Signal) -> Result<()>;
+
+    /// recv returns the next signal of the remote negotiator, or None once the channel is closed.
+    async fn recv(&self) -> Option<Signal>;
+}
+
+/// PerfectNegotiator negotiates a peer connection with the "perfect negotiation" pattern of
+/// the WebRTC specification: both sides create offers whenever negotiation is needed, and when
+/// their offers collide, the polite side rolls its offer back and answers the remote one, while
+/// the impolite side ignores the remote offer. The two sides of a connection must disagree on
+/// being polite.
+///
+/// The negotiator takes over the on_negotiation_needed and on_ice_candidate handlers of the
+/// peer connection.
+pub struct PerfectNegotiator {
+    pc: Arc<RTCPeerConnection>,
+    polite: bool,
+    receive_loop: JoinHandle<()>,
+}
+
+struct Negotiation {
+    signaling: Arc<dyn SignalingChannel + Send + Sync>,
+    polite: bool,
+    /// Set while the candidates of an ignored remote offer may arrive.
+    ignore_offer: AtomicBool,
+    /// Serializes making offers, handling remote descriptions and sending candidates, so that
+    /// candidates are never sent before the description they belong to.
+    op_lock: Mutex<()>,
+}
+
+impl PerfectNegotiator {
+    /// new starts negotiating pc with the remote negotiator reached through signaling.
+    pub fn new(
+        pc: Arc<RTCPeerConnection>,
+        signaling: Arc<dyn SignalingChannel + Send + Sync>,
+        polite: bool,
+    ) -> Self {
+        let negotiation = Arc::new(Negotiation {
+            signaling,
+            polite,
+            ignore_offer: AtomicBool::new(false),
+            op_lock: Mutex::new(()),
+        });
+
+        let (pc_weak, negotiation2) = (Arc::downgrade(&pc), Arc::clone(&negotiation));
+        pc.on_negotiation_needed(Box::new(move || {
+            let (pc_weak, negotiation) = (Weak::clone(&pc_weak), Arc::clone(&negotiation2));
+            // The handler runs on the operations of the peer connection, which making the
+            // offer needs, so it must not wait for it.
+            tokio::spawn(async move {
+                if let Some(pc) = pc_weak.upgrade() {
+                    if let Err(err) = negotiation.make_offer(&pc).await {
+                        log::warn!("failed to make an offer: {}", err);
+                    }
+                }
+            });
+            Box::pin(async {})
+        }));
+
+        let negotiation2 = Arc::clone(&negotiation);
+        pc.on_ice_candidate(Box::new(move |candidate: Option<RTCIceCandidate>| {
+            let negotiation = Arc::clone(&negotiation2);
+            tokio::spawn(async move {
+                if let Some(candidate) = candidate {
+                    if let Err(err) = negotiation.send_candidate(&candidate).await {
+                        log::warn!("failed to send a candidate: {}", err);
+                    }
+                }
+            });
+            Box::pin(async {})
+        }));
+
+        let pc2 = Arc::clone(&pc);
+        let receive_loop = tokio::spawn(async move {
+            while let Some(signal) = negotiation.signaling.recv().await {
+                if let Err(err) = negotiation.handle_signal(&pc2, signal).await {
+                    log::warn!("failed to handle a signal: {}", err);
+                }
+            }
+        });
+
+        PerfectNegotiator {
+            pc,
+            polite,
+            receive_loop,
+        }
+    }
+
+    /// peer_connection returns the negotiated peer connection.
+    pub fn peer_connection(&self) -> &Arc<RTCPeerConnection> {
+        &self.pc
+    }
+
+    /// is_polite returns whether this side gives way when offers collide.
+    pub fn is_polite(&self) -> bool {
+        self.polite
+    }
+
+    /// add_track adds track to the peer connection, which is then renegotiated.
+    pub async fn add_track(
+        &self,
+        track: Arc<dyn TrackLocal + Send + Sync>,
+    ) -> Result<Arc<RTCRtpSender>> {
+        self.pc.add_track(track).await
+    }
+
+    /// create_data_channel creates a data channel on the peer connection, which is renegotiated
+    /// if it had no data channel yet.
+    pub async fn create_data_channel(
+        &self,
+        label: &str,
+        options: Option<RTCDataChannelInit>,
+    ) -> Result<Arc<RTCDataChannel>> {
+        self.pc.create_data_channel(label, options).await
+    }
+
+    /// close stops handling the signals of the remote negotiator and closes the peer connection.
+    pub async fn close(&self) -> Result<()> {
+        self.receive_loop.abort();
+        self.pc.close().await
+    }
+}
+
+impl Drop for PerfectNegotiator {
+    fn drop(&mut self) {
+        self.receive_loop.abort();
+    }
+}
+
+impl Negotiation {
+    async fn make_offer(&self, pc: &RTCPeerConnection) -> Result<()> {
+        let _op = self.op_lock.lock().await;
+        // A remote offer came first, negotiation is needed again once it's answered.
+        if pc.signaling_state() != RTCSignalingState::Stable {
+            return Ok(());
+        }
+
+        let offer = pc.create_offer(None).await?;
+        pc.set_local_description(offer).await?;
+        self.send_local_description(pc).await
+    }
+
+    async fn send_candidate(&self, candidate: &RTCIceCandidate) -> Result<()> {
+        let candidate = candidate.to_json()?;
+        let _op = self.op_lock.lock().await;
+        self.signaling.send(Signal::Candidate(candidate)).await
+    }
+
+    async fn send_local_description(&self, pc: &RTCPeerConnection) -> Result<()> {
+        if let Some(desc) = pc.local_description().await {
+            self.signaling
+                .send(Signal::Description(Box::new(desc)))
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn handle_signal(&self, pc: &RTCPeerConnection, signal: Signal) -> Result<()> {
+        match signal {
+            Signal::Description(desc) => {
+                let _op = self.op_lock.lock().await;
+
+                let is_offer = desc.sdp_type == RTCSdpType::Offer;
+                let offer_collision = is_offer && pc.signaling_state() != RTCSignalingState::Stable;
+                let ignore_offer = !self.polite && offer_collision;
+                self.ignore_offer.store(ignore_offer, Ordering::SeqCst);
+                if ignore_offer {
+                    return Ok(());
+                }
+
+                if offer_collision {
+                    pc.set_local_description(RTCSessionDescription {
+                        sdp_type: RTCSdpType::Rollback,
+                        ..Default::default()
+                    })
+                    .await?;
+                }
+                pc.set_remote_description(*desc).await?;
+
+                if is_offer {
+                    let answer = pc.create_answer(None).await?;
+                    pc.set_local_description(answer).await?;
+                    self.send_local_description(pc).await?;
+                }
+                Ok(())
+            }
+            Signal::Candidate(candidate) => match pc.add_ice_candidate(candidate).await {
+                // The candidates of an ignored offer fail to be added.
+                Err(_) if self.ignore_offer.load(Ordering::SeqCst) => Ok(()),
+                result => result,
+            },
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use super::*;
+
+use crate::api::media_engine::{MediaEngine, MIME_TYPE_OPUS, MIME_TYPE_VP8};
+use crate::api::APIBuilder;
+use crate::peer_connection::configuration::RTCConfiguration;
+use crate::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
+use crate::track::track_local::track_local_static_sample::TrackLocalStaticSample;
+use std::sync::atomic::AtomicUsize;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+
+/// How long a signal takes from one side to the other, long enough for offers sent at the
+/// same time to collide.
+const SIGNALING_DELAY: Duration = Duration::from_millis(20);
+
+/// MemorySignaling is one end of an in-memory signaling channel which counts colliding offers.
+struct MemorySignaling {
+    tx: mpsc::UnboundedSender<Signal>,
+    rx: Mutex<mpsc::UnboundedReceiver<Signal>>,
+    /// Offers sent to this end which it hasn't received yet.
+    incoming_offers: Arc<AtomicUsize>,
+    /// Offers sent by this end which the other end hasn't received yet.
+    outgoing_offers: Arc<AtomicUsize>,
+    glares: Arc<AtomicUsize>,
+}
+
+fn memory_signaling_pair(
+    glares: &Arc<AtomicUsize>,
+) -> (Arc<MemorySignaling>, Arc<MemorySignaling>) {
+    let (tx_a, rx_b) = mpsc::unbounded_channel();
+    let (tx_b, rx_a) = mpsc::unbounded_channel();
+    let (offers_to_a, offers_to_b) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+
+    let a = MemorySignaling {
+        tx: tx_a,
+        rx: Mutex::new(rx_a),
+        incoming_offers: Arc::clone(&offers_to_a),
+        outgoing_offers: Arc::clone(&offers_to_b),
+        glares: Arc::clone(glares),
+    };
+    let b = MemorySignaling {
+        tx: tx_b,
+        rx: Mutex::new(rx_b),
+        incoming_offers: offers_to_b,
+        outgoing_offers: offers_to_a,
+        glares: Arc::clone(glares),
+    };
+    (Arc::new(a), Arc::new(b))
+}
+
+fn is_offer(signal: &Signal) -> bool {
+    matches!(signal, Signal::Description(desc) if desc.sdp_type == RTCSdpType::Offer)
+}
+
+#[async_trait]
+impl SignalingChannel for MemorySignaling {
+    async fn send(&self, signal: Signal) -> Result<()> {
+        if is_offer(&signal) {
+            if self.incoming_offers.load(Ordering::SeqCst) > 0 {
+                self.glares.fetch_add(1, Ordering::SeqCst);
+            }
+            self.outgoing_offers.fetch_add(1, Ordering::SeqCst);
+        }
+        let _ = self.tx.send(signal);
+        Ok(())
+    }
+
+    async fn recv(&self) -> Option<Signal> {
+        let signal = {
+            let mut rx = self.rx.lock().await;
+            rx.recv().await?
+        };
+        tokio::time::sleep(SIGNALING_DELAY).await;
+        if is_offer(&signal) {
+            self.incoming_offers.fetch_sub(1, Ordering::SeqCst);
+        }
+        Some(signal)
+    }
+}
+
+async fn new_negotiator(
+    signaling: Arc<MemorySignaling>,
+    polite: bool,
+) -> Result<PerfectNegotiator> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+    let pc = api.new_peer_connection(RTCConfiguration::default()).await?;
+
+    Ok(PerfectNegotiator::new(Arc::new(pc), signaling, polite))
+}
+
+fn new_track(mime_type: &str, id: &str) -> Arc<dyn TrackLocal + Send + Sync> {
+    Arc::new(TrackLocalStaticSample::new(
+        RTCRtpCodecCapability {
+            mime_type: mime_type.to_owned(),
+            ..Default::default()
+        },
+        id.to_owned(),
+        "webrtc-rs".to_owned(),
+    ))
+}
+
+/// without_candidates drops the candidates, which are added to local descriptions as they are
+/// gathered.
+fn without_candidates(sdp: &str) -> Vec<&str> {
+    sdp.lines()
+        .filter(|line| !line.starts_with("a=candidate:") && *line != "a=end-of-candidates")
+        .collect()
+}
+
+/// converged returns whether both sides are stable with the same descriptions, which carry all
+/// their tracks.
+async fn converged(
+    a: &PerfectNegotiator,
+    b: &PerfectNegotiator,
+    track_ids: &[(String, String)],
+) -> bool {
+    for (x, y) in [(a, b), (b, a)] {
+        if x.peer_connection().signaling_state() != RTCSignalingState::Stable {
+            return false;
+        }
+        let local = x.peer_connection().current_local_description().await;
+        let remote = y.peer_connection().current_remote_description().await;
+        match (local, remote) {
+            (Some(local), Some(remote))
+                if without_candidates(&local.sdp) == without_candidates(&remote.sdp) => {}
+            _ => return false,
+        }
+    }
+
+    let a_sdp = a.peer_connection().current_local_description().await;
+    let b_sdp = b.peer_connection().current_local_description().await;
+    let (a_sdp, b_sdp) = match (a_sdp, b_sdp) {
+        (Some(a_sdp), Some(b_sdp)) => (a_sdp.sdp, b_sdp.sdp),
+        _ => return false,
+    };
+    track_ids
+        .iter()
+        .all(|(a_id, b_id)| a_sdp.contains(a_id.as_str()) && b_sdp.contains(b_id.as_str()))
+}
+
+#[tokio::test]
+async fn test_perfect_negotiation_glare() -> Result<()> {
+    const ROUNDS: usize = 6;
+
+    let glares = Arc::new(AtomicUsize::new(0));
+    let (signaling_a, signaling_b) = memory_signaling_pair(&glares);
+    let a = new_negotiator(signaling_a, false).await?;
+    let b = new_negotiator(signaling_b, true).await?;
+    assert!(!a.is_polite());
+    assert!(b.is_polite());
+
+    let mut track_ids = vec![];
+    for round in 0..ROUNDS {
+        let glares_before = glares.load(Ordering::SeqCst);
+
+        if round == 0 {
+            let (dc_a, dc_b) = tokio::join!(
+                a.create_data_channel("a", None),
+                b.create_data_channel("b", None)
+            );
+            dc_a?;
+            dc_b?;
+        } else {
+            let mime_type = if round % 2 == 0 {
+                MIME_TYPE_VP8
+            } else {
+                MIME_TYPE_OPUS
+            };
+            let ids = (format!("track-a-{}", round), format!("track-b-{}", round));
+            let (sender_a, sender_b) = tokio::join!(
+                a.add_track(new_track(mime_type, &ids.0)),
+                b.add_track(new_track(mime_type, &ids.1))
+            );
+            sender_a?;
+            sender_b?;
+            track_ids.push(ids);
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !converged(&a, &b, &track_ids).await {
+            assert!(
+                Instant::now() < deadline,
+                "round {} didn't converge, signaling states {} and {}",
+                round,
+                a.peer_connection().signaling_state(),
+                b.peer_connection().signaling_state()
+            );
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(
+            glares.load(Ordering::SeqCst) > glares_before,
+            "the offers of round {} didn't collide",
+            round
+        );
+    }
+
+    a.close().await?;
+    b.close().await?;
+
+    Ok(())
+}
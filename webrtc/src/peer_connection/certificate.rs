@@ -4,7 +4,7 @@ use ring::signature::{EcdsaKeyPair, Ed25519KeyPair, RsaKeyPair};
 use sha2::{Digest, Sha256};
 
 use std::ops::Add;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime};
 
 use crate::dtls_transport::dtls_fingerprint::RTCDtlsFingerprint;
 use crate::error::{Error, Result};
@@ -83,13 +83,14 @@ impl RTCCertificate {
             not_after.into()
         };
 
+        let dtls_certificate = dtls::crypto::Certificate {
+            certificate: vec![rustls::Certificate(x509_cert.serialize_der()?)],
+            private_key,
+        };
         Ok(Self {
-            dtls_certificate: dtls::crypto::Certificate {
-                certificate: vec![rustls::Certificate(x509_cert.serialize_der()?)],
-                private_key,
-            },
+            stats_id: gen_stats_id(&dtls_certificate),
+            dtls_certificate,
             expires,
-            stats_id: gen_stats_id(),
         })
     }
 
@@ -147,14 +148,13 @@ impl RTCCertificate {
     /// Use this method when you have a persistent certificate (i.e. you don't want to generate a
     /// new one for each DTLS connection).
     ///
-    /// NOTE: ID used for statistics will be different as it's neither derived from the given
-    /// certificate nor persisted along it when using [`serialize_pem`].
+    /// The ID used for statistics is derived from the fingerprint of the certificate, so it's the
+    /// same for every [`RTCCertificate`] built from it.
     pub fn from_existing(dtls_certificate: dtls::crypto::Certificate, expires: SystemTime) -> Self {
         Self {
+            stats_id: gen_stats_id(&dtls_certificate),
             dtls_certificate,
             expires,
-            // TODO: figure out if it needs to be persisted
-            stats_id: gen_stats_id(),
         }
     }
 
@@ -189,14 +189,9 @@ impl RTCCertificate {
         let mut fingerprints = Vec::new();
 
         for c in &self.dtls_certificate.certificate {
-            let mut h = Sha256::new();
-            h.update(c.as_ref());
-            let hashed = h.finalize();
-            let values: Vec<String> = hashed.iter().map(|x| format! {"{:02x}", x}).collect();
-
             fingerprints.push(RTCDtlsFingerprint {
                 algorithm: "sha-256".to_owned(),
-                value: values.join(":"),
+                value: sha256_fingerprint(c.as_ref()).join(":"),
             });
         }
 
@@ -214,14 +209,21 @@ impl RTCCertificate {
     }
 }
 
-fn gen_stats_id() -> String {
-    format!(
-        "certificate-{}",
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos() as u64
-    )
+fn sha256_fingerprint(der: &[u8]) -> Vec<String> {
+    let mut h = Sha256::new();
+    h.update(der);
+    let hashed = h.finalize();
+    hashed.iter().map(|x| format! {"{:02x}", x}).collect()
+}
+
+/// gen_stats_id derives the stats id from the SHA-256 fingerprint of the first certificate.
+fn gen_stats_id(dtls_certificate: &dtls::crypto::Certificate) -> String {
+    let fingerprint = dtls_certificate
+        .certificate
+        .first()
+        .map(|c| sha256_fingerprint(c.as_ref()).concat())
+        .unwrap_or_default();
+    format!("certificate-{}", fingerprint)
 }
 
 #[cfg(test)]
@@ -277,6 +279,24 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_certificate_stats_id_is_derived_from_certificate() -> Result<()> {
+        let kp = KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?;
+        let cert = RTCCertificate::from_key_pair(kp)?;
+
+        let existing = RTCCertificate::from_existing(cert.dtls_certificate.clone(), cert.expires);
+        assert_eq!(existing.stats_id, cert.stats_id);
+
+        let fingerprint = cert.get_fingerprints().remove(0).value.replace(':', "");
+        assert_eq!(cert.stats_id, format!("certificate-{}", fingerprint));
+
+        let kp = KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?;
+        let other = RTCCertificate::from_key_pair(kp)?;
+        assert_ne!(other.stats_id, cert.stats_id);
+
+        Ok(())
+    }
+
     #[cfg(feature = "pem")]
     #[test]
     fn test_certificate_serialize_pem_and_from_pem() -> Result<()> {
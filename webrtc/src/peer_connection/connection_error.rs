@@ -0,0 +1,30 @@
+use std::fmt;
+
+/// RTCConnectionError tells why a PeerConnection failed, see `RTCPeerConnection::connection_error`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RTCConnectionError {
+    /// IceFailed means ICE found no working candidate pair, or lost connectivity for too long.
+    IceFailed,
+
+    /// DtlsFailed means the DTLS handshake failed, or the remote certificate was refused.
+    DtlsFailed(String),
+
+    /// DtlsHandshakeTimeout means the DTLS handshake did not complete within the duration set
+    /// with `SettingEngine::set_dtls_handshake_timeout` once ICE connected.
+    DtlsHandshakeTimeout,
+
+    /// ConnectTimeout means the PeerConnection did not connect within the duration set
+    /// with `SettingEngine::set_connect_timeout`.
+    ConnectTimeout,
+}
+
+impl fmt::Display for RTCConnectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RTCConnectionError::IceFailed => write!(f, "ICE failed"),
+            RTCConnectionError::DtlsFailed(err) => write!(f, "DTLS failed: {}", err),
+            RTCConnectionError::DtlsHandshakeTimeout => write!(f, "DTLS handshake timed out"),
+            RTCConnectionError::ConnectTimeout => write!(f, "connecting timed out"),
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+/// Deadline is a point in time which can be moved or cleared while it is waited for.
+#[derive(Debug)]
+pub(crate) struct Deadline {
+    tx: watch::Sender<Option<Instant>>,
+}
+
+impl Default for Deadline {
+    fn default() -> Self {
+        let (tx, _) = watch::channel(None);
+        Deadline { tx }
+    }
+}
+
+impl Deadline {
+    /// set moves the deadline, None clears it. It returns the previous deadline.
+    pub(crate) fn set(&self, deadline: Option<Instant>) -> Option<Instant> {
+        self.tx.send_replace(deadline)
+    }
+
+    /// get returns the deadline, None if it is cleared.
+    pub(crate) fn get(&self) -> Option<Instant> {
+        *self.tx.borrow()
+    }
+
+    /// wait returns true once the deadline has passed, or false as soon as it is cleared.
+    pub(crate) async fn wait(&self) -> bool {
+        let mut rx = self.tx.subscribe();
+        loop {
+            let deadline = match *rx.borrow_and_update() {
+                Some(deadline) => deadline,
+                None => return false,
+            };
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => return true,
+                _ = rx.changed() => {}
+            }
+        }
+    }
+}
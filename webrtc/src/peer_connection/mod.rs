@@ -3,6 +3,8 @@ pub(crate) mod peer_connection_test;
 
 pub mod certificate;
 pub mod configuration;
+pub mod connection_error;
+pub(crate) mod deadline;
 pub mod offer_answer_options;
 pub(crate) mod operation;
 mod peer_connection_internal;
@@ -16,12 +18,13 @@ use crate::api::setting_engine::SettingEngine;
 use crate::api::API;
 use crate::data_channel::data_channel_init::RTCDataChannelInit;
 use crate::data_channel::data_channel_parameters::DataChannelParameters;
+use crate::data_channel::data_channel_priority::RTCPriorityType;
 use crate::data_channel::data_channel_state::RTCDataChannelState;
 use crate::data_channel::RTCDataChannel;
 use crate::dtls_transport::dtls_fingerprint::RTCDtlsFingerprint;
 use crate::dtls_transport::dtls_parameters::DTLSParameters;
 use crate::dtls_transport::dtls_role::{
-    DTLSRole, DEFAULT_DTLS_ROLE_ANSWER, DEFAULT_DTLS_ROLE_OFFER,
+    connection_role_from_sdp, DTLSRole, DEFAULT_DTLS_ROLE_ANSWER, DEFAULT_DTLS_ROLE_OFFER,
 };
 use crate::dtls_transport::dtls_transport_state::RTCDtlsTransportState;
 use crate::dtls_transport::RTCDtlsTransport;
@@ -30,22 +33,29 @@ use crate::ice_transport::ice_candidate::{RTCIceCandidate, RTCIceCandidateInit};
 use crate::ice_transport::ice_connection_state::RTCIceConnectionState;
 use crate::ice_transport::ice_gatherer::RTCIceGatherOptions;
 use crate::ice_transport::ice_gatherer::{
-    OnGatheringCompleteHdlrFn, OnICEGathererStateChangeHdlrFn, OnLocalCandidateHdlrFn,
-    RTCIceGatherer,
+    OnGatheringCompleteHdlrFn, OnICECandidateErrorHdlrFn, OnICEGathererStateChangeHdlrFn,
+    OnLocalCandidateHdlrFn, RTCIceGatherer,
 };
 use crate::ice_transport::ice_gatherer_state::RTCIceGathererState;
 use crate::ice_transport::ice_gathering_state::RTCIceGatheringState;
 use crate::ice_transport::ice_parameters::RTCIceParameters;
 use crate::ice_transport::ice_role::RTCIceRole;
 use crate::ice_transport::ice_transport_state::RTCIceTransportState;
-use crate::ice_transport::RTCIceTransport;
+use crate::ice_transport::{OnSelectedCandidatePairChangeHdlrFn, RTCIceTransport};
 use crate::peer_connection::certificate::RTCCertificate;
 use crate::peer_connection::configuration::RTCConfiguration;
-use crate::peer_connection::offer_answer_options::{RTCAnswerOptions, RTCOfferOptions};
+use crate::peer_connection::connection_error::RTCConnectionError;
+use crate::peer_connection::deadline::Deadline;
+use crate::peer_connection::offer_answer_options::{
+    RTCAnswerOptions, RTCIcePrepareOptions, RTCIceTrickleMode, RTCOfferOptions,
+};
 use crate::peer_connection::operation::{Operation, Operations};
 use crate::peer_connection::peer_connection_state::{
     NegotiationNeededState, RTCPeerConnectionState,
 };
+use crate::peer_connection::policy::bundle_policy::RTCBundlePolicy;
+use crate::peer_connection::policy::ice_transport_policy::RTCIceTransportPolicy;
+use crate::peer_connection::policy::rtcp_mux_policy::RTCRtcpMuxPolicy;
 use crate::peer_connection::sdp::sdp_type::RTCSdpType;
 use crate::peer_connection::sdp::session_description::RTCSessionDescription;
 use crate::peer_connection::sdp::*;
@@ -57,7 +67,8 @@ use crate::rtp_transceiver::rtp_receiver::RTCRtpReceiver;
 use crate::rtp_transceiver::rtp_sender::RTCRtpSender;
 use crate::rtp_transceiver::rtp_transceiver_direction::RTCRtpTransceiverDirection;
 use crate::rtp_transceiver::{
-    find_by_mid, handle_unknown_rtp_packet, satisfy_type_and_direction, RTCRtpTransceiver,
+    find_by_mid, handle_unknown_rtcp_packet, handle_unknown_rtp_packet, satisfy_type_and_direction,
+    RTCRtpTransceiver,
 };
 use crate::rtp_transceiver::{RTCRtpTransceiverInit, SSRC};
 use crate::sctp_transport::sctp_transport_capabilities::SCTPTransportCapabilities;
@@ -74,27 +85,44 @@ use ::sdp::description::session::*;
 use ::sdp::util::ConnectionRole;
 use arc_swap::ArcSwapOption;
 use async_trait::async_trait;
-use interceptor::{stats, Attributes, Interceptor, RTCPWriter};
+use interceptor::chain::Chain;
+use interceptor::{stats, Attributes, Interceptor, InterceptorBuilder, RTCPWriter};
 use peer_connection_internal::*;
 use rand::{thread_rng, Rng};
 use rcgen::KeyPair;
 use srtp::stream::Stream;
+use std::collections::HashSet;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::{mpsc, Mutex};
+use tokio::time::Instant;
+use util::sync::{Mutex as SyncMutex, TaskGroup};
 
 /// SIMULCAST_PROBE_COUNT is the amount of RTP Packets
 /// that handleUndeclaredSSRC will read and try to dispatch from
 /// mid and rid values
 pub(crate) const SIMULCAST_PROBE_COUNT: usize = 10;
 
+/// SIMULCAST_RTCP_PROBE_TIMEOUT is how long we probe an unknown SSRC for its MID and RID
+/// unless set with `SettingEngine::set_simulcast_rtcp_probe_timeout`. It bounds the wait for
+/// an RTCP SDES to tell them when its RTP Packets don't carry them
+pub(crate) const SIMULCAST_RTCP_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// SIMULCAST_MAX_PROBE_ROUTINES is how many active routines can be used to probe
 /// If the total amount of incoming SSRCes exceeds this new requests will be ignored
 pub(crate) const SIMULCAST_MAX_PROBE_ROUTINES: u64 = 25;
 
+/// MAX_PENDING_REMOTE_CANDIDATES is how many remote ICE candidates add_ice_candidate
+/// queues while the remote description is not set yet
+pub(crate) const MAX_PENDING_REMOTE_CANDIDATES: usize = 256;
+
+/// TASKS_CLOSE_TIMEOUT is how long closing waits for the tasks of a component
+/// to stop before they are cancelled
+pub(crate) const TASKS_CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
 pub(crate) const MEDIA_SECTION_APPLICATION: &str = "application";
 
 const RUNES_ALPHA: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
@@ -183,9 +211,12 @@ struct NegotiationNeededParams {
 /// browser, or to another endpoint implementing the required protocols.
 pub struct RTCPeerConnection {
     stats_id: String,
+    /// Origin of the monotonic timestamps of the stats reports
+    created_at: Instant,
+    last_stats_timestamp: SyncMutex<Duration>,
     idp_login_url: Option<String>,
 
-    configuration: RTCConfiguration,
+    configuration: Mutex<RTCConfiguration>,
 
     interceptor_rtcp_writer: Arc<dyn RTCPWriter + Send + Sync>,
 
@@ -221,12 +252,28 @@ impl RTCPeerConnection {
     pub(crate) async fn new(api: &API, mut configuration: RTCConfiguration) -> Result<Self> {
         RTCPeerConnection::init_configuration(&mut configuration)?;
 
+        let stats_id = format!(
+            "PeerConnection-{}",
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        );
+
         let (interceptor, stats_interceptor): (Arc<dyn Interceptor + Send + Sync>, _) = {
-            let mut chain = api.interceptor_registry.build_chain("")?;
+            let chain = api.interceptor_registry.build_chain("")?;
             let stats_interceptor = stats::make_stats_interceptor("");
-            chain.add(stats_interceptor.clone());
+            // The stats interceptor goes first, closest to the transport, so it also accounts for
+            // the packets the other interceptors send on their own, e.g. NACK retransmissions,
+            // sender reports and feedback. The packet dump sees them as well.
+            let mut interceptors: Vec<Arc<dyn Interceptor + Send + Sync>> =
+                vec![stats_interceptor.clone()];
+            if let Some(packet_dump) = &api.setting_engine.packet_dump {
+                interceptors.push(packet_dump.build(&stats_id)?);
+            }
+            interceptors.push(Arc::new(chain));
 
-            (Arc::new(chain), stats_interceptor)
+            (Arc::new(Chain::new(interceptors)), stats_interceptor)
         };
 
         let weak_interceptor = Arc::downgrade(&interceptor);
@@ -240,17 +287,13 @@ impl RTCPeerConnection {
         // Some variables defined explicitly despite their implicit zero values to
         // allow better readability to understand what is happening.
         Ok(RTCPeerConnection {
-            stats_id: format!(
-                "PeerConnection-{}",
-                SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_nanos()
-            ),
+            stats_id,
+            created_at: Instant::now(),
+            last_stats_timestamp: SyncMutex::new(Duration::ZERO),
             interceptor,
             interceptor_rtcp_writer,
             internal,
-            configuration,
+            configuration: Mutex::new(configuration),
             idp_login_url: None,
         })
     }
@@ -303,6 +346,10 @@ impl RTCPeerConnection {
 
     /// on_data_channel sets an event handler which is invoked when a data
     /// channel message arrives from a remote peer.
+    ///
+    /// The channel doesn't read messages until the handler returns, so an on_message handler
+    /// set by it gets every message from the first one on. Messages read before on_message is
+    /// set otherwise are kept and delivered first, see `RTCDataChannel::on_message`.
     pub fn on_data_channel(&self, f: OnDataChannelHdlrFn) {
         self.internal
             .on_data_channel_handler
@@ -572,6 +619,13 @@ impl RTCPeerConnection {
         self.internal.ice_gatherer.on_local_candidate(f)
     }
 
+    /// on_ice_candidate_error sets an event handler which is invoked when no candidate could be
+    /// gathered from one of the ICE servers, e.g. because its host name can't be resolved or
+    /// none of its addresses answers.
+    pub fn on_ice_candidate_error(&self, f: OnICECandidateErrorHdlrFn) {
+        self.internal.ice_gatherer.on_candidate_error(f)
+    }
+
     /// on_ice_gathering_state_change sets an event handler which is invoked when the
     /// ICE candidate gathering state has changed.
     pub fn on_ice_gathering_state_change(&self, f: OnICEGathererStateChangeHdlrFn) {
@@ -588,13 +642,14 @@ impl RTCPeerConnection {
 
     async fn do_track(
         on_track_handler: Arc<ArcSwapOption<Mutex<OnTrackHdlrFn>>>,
+        tasks: &TaskGroup,
         t: Option<Arc<TrackRemote>>,
         r: Option<Arc<RTCRtpReceiver>>,
     ) {
         log::debug!("got new track: {:?}", t);
 
         if t.is_some() {
-            tokio::spawn(async move {
+            tasks.spawn(async move {
                 if let Some(handler) = &*on_track_handler.load() {
                     let mut f = handler.lock().await;
                     f(t, r).await;
@@ -627,6 +682,14 @@ impl RTCPeerConnection {
         }
     }
 
+    /// on_selected_candidate_pair_change sets an event handler which is called when the
+    /// selected ICE candidate pair changes, see [`RTCIceTransport::on_selected_candidate_pair_change`].
+    pub fn on_selected_candidate_pair_change(&self, f: OnSelectedCandidatePairChangeHdlrFn) {
+        self.internal
+            .ice_transport
+            .on_selected_candidate_pair_change(f);
+    }
+
     /// on_peer_connection_state_change sets an event handler which is called
     /// when the PeerConnectionState has changed
     pub fn on_peer_connection_state_change(&self, f: OnPeerConnectionStateChangeHdlrFn) {
@@ -645,86 +708,146 @@ impl RTCPeerConnection {
         }
     }
 
-    /*TODO: // set_configuration updates the configuration of this PeerConnection object.
-    pub async fn set_configuration(&mut self, configuration: Configuration) -> Result<()> {
-        //nolint:gocognit
+    /// set_configuration updates the configuration of this PeerConnection object.
+    /// A changed ice_transport_policy takes effect at the next ICE restart, changing
+    /// any of the other settings is rejected.
+    /// <https://www.w3.org/TR/webrtc/#dom-rtcpeerconnection-setconfiguration>
+    pub async fn set_configuration(&self, configuration: RTCConfiguration) -> Result<()> {
         // https://www.w3.org/TR/webrtc/#dom-rtcpeerconnection-setconfiguration (step #2)
         if self.internal.is_closed.load(Ordering::SeqCst) {
-            return Err(Error::ErrConnectionClosed.into());
+            return Err(Error::ErrConnectionClosed);
         }
 
+        let mut current = self.configuration.lock().await;
+
         // https://www.w3.org/TR/webrtc/#set-the-configuration (step #3)
-        if !configuration.peer_identity.is_empty() {
-            if configuration.peer_identity != self.configuration.peer_identity {
-                return Err(Error::ErrModifyingPeerIdentity.into());
-            }
-            self.configuration.peer_identity = configuration.peer_identity;
+        if !configuration.peer_identity.is_empty()
+            && configuration.peer_identity != current.peer_identity
+        {
+            return Err(Error::ErrModifyingPeerIdentity);
         }
 
         // https://www.w3.org/TR/webrtc/#set-the-configuration (step #4)
-        if !configuration.certificates.is_empty() {
-            if configuration.certificates.len() != self.configuration.certificates.len() {
-                return Err(Error::ErrModifyingCertificates.into());
-            }
-
-            self.configuration.certificates = configuration.certificates;
+        if !configuration.certificates.is_empty()
+            && configuration.certificates != current.certificates
+        {
+            return Err(Error::ErrModifyingCertificates);
         }
 
         // https://www.w3.org/TR/webrtc/#set-the-configuration (step #5)
-        if configuration.bundle_policy != BundlePolicy::Unspecified {
-            if configuration.bundle_policy != self.configuration.bundle_policy {
-                return Err(Error::ErrModifyingBundlePolicy.into());
-            }
-            self.configuration.bundle_policy = configuration.bundle_policy;
+        if configuration.bundle_policy != RTCBundlePolicy::Unspecified
+            && configuration.bundle_policy != current.bundle_policy
+        {
+            return Err(Error::ErrModifyingBundlePolicy);
         }
 
         // https://www.w3.org/TR/webrtc/#set-the-configuration (step #6)
-        if configuration.rtcp_mux_policy != RTCPMuxPolicy::Unspecified {
-            if configuration.rtcp_mux_policy != self.configuration.rtcp_mux_policy {
-                return Err(Error::ErrModifyingRTCPMuxPolicy.into());
-            }
-            self.configuration.rtcp_mux_policy = configuration.rtcp_mux_policy;
+        if configuration.rtcp_mux_policy != RTCRtcpMuxPolicy::Unspecified
+            && configuration.rtcp_mux_policy != current.rtcp_mux_policy
+        {
+            return Err(Error::ErrModifyingRTCPMuxPolicy);
         }
 
         // https://www.w3.org/TR/webrtc/#set-the-configuration (step #7)
-        if configuration.ice_candidate_pool_size != 0 {
-            if self.configuration.ice_candidate_pool_size != configuration.ice_candidate_pool_size
-                && self.local_description().await.is_some()
-            {
-                return Err(Error::ErrModifyingICECandidatePoolSize.into());
-            }
-            self.configuration.ice_candidate_pool_size = configuration.ice_candidate_pool_size;
+        if configuration.ice_candidate_pool_size != 0
+            && configuration.ice_candidate_pool_size != current.ice_candidate_pool_size
+        {
+            return Err(Error::ErrModifyingICECandidatePoolSize);
         }
 
-        // https://www.w3.org/TR/webrtc/#set-the-configuration (step #8)
-        if configuration.ice_transport_policy != ICETransportPolicy::Unspecified {
-            self.configuration.ice_transport_policy = configuration.ice_transport_policy
+        // https://www.w3.org/TR/webrtc/#set-the-configuration (step #11)
+        // The agent keeps the servers it was created with, so they cannot be swapped yet.
+        if !configuration.ice_servers.is_empty() && configuration.ice_servers != current.ice_servers
+        {
+            return Err(Error::ErrModifyingICEServers);
         }
 
-        // https://www.w3.org/TR/webrtc/#set-the-configuration (step #11)
-        if !configuration.ice_servers.is_empty() {
-            // https://www.w3.org/TR/webrtc/#set-the-configuration (step #11.3)
-            for server in &configuration.ice_servers {
-                server.validate()?;
-            }
-            self.configuration.ice_servers = configuration.ice_servers
+        // https://www.w3.org/TR/webrtc/#set-the-configuration (step #8)
+        if configuration.ice_transport_policy != RTCIceTransportPolicy::Unspecified {
+            current.ice_transport_policy = configuration.ice_transport_policy;
+            self.internal
+                .ice_gatherer
+                .set_gather_policy(configuration.ice_transport_policy);
         }
+
         Ok(())
-    }*/
+    }
 
     /// get_configuration returns a Configuration object representing the current
     /// configuration of this PeerConnection object. The returned object is a
     /// copy and direct mutation on it will not take affect until set_configuration
     /// has been called with Configuration passed as its only argument.
     /// <https://www.w3.org/TR/webrtc/#dom-rtcpeerconnection-getconfiguration>
-    pub fn get_configuration(&self) -> &RTCConfiguration {
-        &self.configuration
+    pub async fn get_configuration(&self) -> RTCConfiguration {
+        let configuration = self.configuration.lock().await;
+        configuration.clone()
     }
 
     pub fn get_stats_id(&self) -> &str {
         self.stats_id.as_str()
     }
 
+    /// prepare_ice starts gathering ICE candidates, including server reflexive candidates and
+    /// TURN allocations, before any session description exists. Without it gathering only starts
+    /// once set_local_description is called. The options select the transport policy to gather
+    /// with and whether to wait for gathering to complete, see RTCIcePrepareOptions.
+    ///
+    /// The ICE credentials are fixed when gathering starts. create_offer, create_answer and
+    /// set_local_description reuse them along with every candidate gathered so far, nothing is
+    /// gathered again. Once gathering completes, an offer created afterwards carries all
+    /// candidates, so the remote peer can start connectivity checks right away.
+    pub async fn prepare_ice(&self, options: Option<RTCIcePrepareOptions>) -> Result<()> {
+        if self.internal.is_closed.load(Ordering::SeqCst) {
+            return Err(Error::ErrConnectionClosed);
+        }
+
+        let options = options.unwrap_or_default();
+        if options.ice_transport_policy != RTCIceTransportPolicy::Unspecified {
+            let mut current = self.configuration.lock().await;
+            current.ice_transport_policy = options.ice_transport_policy;
+            self.internal
+                .ice_gatherer
+                .set_gather_policy(options.ice_transport_policy);
+        }
+
+        if self.internal.ice_gatherer.state() == RTCIceGathererState::New {
+            self.internal.ice_gatherer.gather().await?;
+        }
+
+        if !options.wait_gathering_complete {
+            return Ok(());
+        }
+
+        let gathering_complete = self.internal.ice_gatherer.wait_gathering_complete();
+        if let Some(timeout) = options.ice_gathering_timeout {
+            if tokio::time::timeout(timeout, gathering_complete)
+                .await
+                .is_err()
+            {
+                log::warn!(
+                    "ICE gathering didn't complete within {:?}, the description lacks the remaining candidates",
+                    timeout
+                );
+            }
+        } else {
+            gathering_complete.await;
+        }
+
+        Ok(())
+    }
+
+    /// gather_all_candidates starts gathering like prepare_ice and waits until it completes, so
+    /// the description created next carries every local candidate and a=end-of-candidates. Once
+    /// timeout elapses the description gets the candidates gathered so far instead.
+    async fn gather_all_candidates(&self, timeout: Option<Duration>) -> Result<()> {
+        self.prepare_ice(Some(RTCIcePrepareOptions {
+            wait_gathering_complete: true,
+            ice_gathering_timeout: timeout,
+            ..Default::default()
+        }))
+        .await
+    }
+
     /// create_offer starts the PeerConnection and generates the localDescription
     /// <https://w3c.github.io/webrtc-pc/#dom-rtcpeerconnection-createoffer>
     pub async fn create_offer(
@@ -738,9 +861,19 @@ impl RTCPeerConnection {
             return Err(Error::ErrConnectionClosed);
         }
 
+        let ice_trickle = options
+            .as_ref()
+            .map_or(true, |o| o.ice_trickle_mode != RTCIceTrickleMode::Disabled);
+
         if let Some(options) = options {
             if options.ice_restart {
                 self.internal.ice_transport.restart().await?;
+                self.internal.ice_restarted().await;
+            }
+
+            if options.ice_trickle_mode != RTCIceTrickleMode::Full {
+                self.gather_all_candidates(options.ice_gathering_timeout)
+                    .await?;
             }
         }
 
@@ -819,7 +952,7 @@ impl RTCPeerConnection {
 
             let mut d = if current_remote_description_is_none {
                 self.internal
-                    .generate_unmatched_sdp(current_transceivers, use_identity)
+                    .generate_unmatched_sdp(current_transceivers, use_identity, ice_trickle)
                     .await?
             } else {
                 self.internal
@@ -828,6 +961,7 @@ impl RTCPeerConnection {
                         use_identity,
                         true, /*includeUnmatched */
                         DEFAULT_DTLS_ROLE_OFFER.to_connection_role(),
+                        ice_trickle,
                     )
                     .await?
             };
@@ -870,15 +1004,20 @@ impl RTCPeerConnection {
         >,
         is_closed: &Arc<AtomicBool>,
         peer_connection_state: &Arc<AtomicU8>,
+        connection_error: &SyncMutex<Option<RTCConnectionError>>,
+        connect_deadline: &Deadline,
         ice_connection_state: RTCIceConnectionState,
         dtls_transport_state: RTCDtlsTransportState,
     ) {
+        let failed = connection_error.lock().is_some();
+
         let connection_state =
             // The RTCPeerConnection object's [[IsClosed]] slot is true.
             if is_closed.load(Ordering::SeqCst) {
                 RTCPeerConnectionState::Closed
-            } else if ice_connection_state == RTCIceConnectionState::Failed || dtls_transport_state == RTCDtlsTransportState::Failed {
-                // Any of the RTCIceTransports or RTCDtlsTransports are in a "failed" state.
+            } else if failed || ice_connection_state == RTCIceConnectionState::Failed || dtls_transport_state == RTCDtlsTransportState::Failed {
+                // Any of the RTCIceTransports or RTCDtlsTransports are in a "failed" state,
+                // or the peer connection gave up connecting.
                 RTCPeerConnectionState::Failed
             } else if ice_connection_state == RTCIceConnectionState::Disconnected {
                 // Any of the RTCIceTransports or RTCDtlsTransports are in the "disconnected"
@@ -896,6 +1035,11 @@ impl RTCPeerConnection {
                 RTCPeerConnectionState::New
             };
 
+        if connection_state == RTCPeerConnectionState::Connected {
+            // Only an ICE restart waits for the peer connection to connect again
+            connect_deadline.set(None);
+        }
+
         if peer_connection_state.load(Ordering::SeqCst) == connection_state as u8 {
             return;
         }
@@ -913,7 +1057,7 @@ impl RTCPeerConnection {
     /// create_answer starts the PeerConnection and generates the localDescription
     pub async fn create_answer(
         &self,
-        _options: Option<RTCAnswerOptions>,
+        options: Option<RTCAnswerOptions>,
     ) -> Result<RTCSessionDescription> {
         let use_identity = self.idp_login_url.is_some();
         if self.remote_description().await.is_none() {
@@ -928,15 +1072,29 @@ impl RTCPeerConnection {
             return Err(Error::ErrIncorrectSignalingState);
         }
 
+        // The role was resolved from the remote offer, or by the initial negotiation
         let mut connection_role = self
             .internal
-            .setting_engine
-            .answering_dtls_role
+            .dtls_transport
+            .role()
+            .await
             .to_connection_role();
         if connection_role == ConnectionRole::Unspecified {
             connection_role = DEFAULT_DTLS_ROLE_ANSWER.to_connection_role();
         }
 
+        let ice_trickle = options
+            .as_ref()
+            .map_or(true, |o| o.ice_trickle_mode != RTCIceTrickleMode::Disabled);
+
+        // The answerer of a half trickle exchange may trickle
+        if let Some(options) = options {
+            if options.ice_trickle_mode == RTCIceTrickleMode::Disabled {
+                self.gather_all_candidates(options.ice_gathering_timeout)
+                    .await?;
+            }
+        }
+
         let local_transceivers = self.get_transceivers().await;
         let mut d = self
             .internal
@@ -945,6 +1103,7 @@ impl RTCPeerConnection {
                 use_identity,
                 false, /*includeUnmatched */
                 connection_role,
+                ice_trickle,
             )
             .await?;
 
@@ -1185,6 +1344,7 @@ impl RTCPeerConnection {
                     .signaling_state
                     .store(next_state as u8, Ordering::SeqCst);
                 if self.signaling_state() == RTCSignalingState::Stable {
+                    self.internal.negotiate_cryptex().await;
                     self.internal
                         .is_negotiation_needed
                         .store(false, Ordering::SeqCst);
@@ -1197,12 +1357,17 @@ impl RTCPeerConnection {
         }
     }
 
-    /// set_local_description sets the SessionDescription of the local peer
+    /// set_local_description sets the SessionDescription of the local peer. A description of
+    /// type rollback, whose sdp is ignored, rolls back a local offer which wasn't answered yet.
     pub async fn set_local_description(&self, mut desc: RTCSessionDescription) -> Result<()> {
         if self.internal.is_closed.load(Ordering::SeqCst) {
             return Err(Error::ErrConnectionClosed);
         }
 
+        if desc.sdp_type == RTCSdpType::Rollback {
+            return self.rollback_local_offer().await;
+        }
+
         let have_local_description = {
             let current_local_description = self.internal.current_local_description.lock().await;
             current_local_description.is_some()
@@ -1294,6 +1459,40 @@ impl RTCPeerConnection {
         }
     }
 
+    /// rollback_local_offer returns to the stable state from have-local-offer (JSEP 4.1.8.2).
+    /// Transceivers which got their mid from the rolled back offer lose it again, so that they
+    /// can be matched with the media sections of a remote offer or be offered later.
+    async fn rollback_local_offer(&self) -> Result<()> {
+        if self.signaling_state() == RTCSignalingState::HaveLocalOffer {
+            let mut negotiated_mids = HashSet::new();
+            if let Some(parsed) = self
+                .current_local_description()
+                .await
+                .and_then(|d| d.parsed)
+            {
+                for media in &parsed.media_descriptions {
+                    if let Some(mid) = get_mid_value(media) {
+                        negotiated_mids.insert(mid.to_owned());
+                    }
+                }
+            }
+
+            for t in self.get_transceivers().await {
+                let mid = t.mid().await;
+                if !mid.is_empty() && !negotiated_mids.contains(&mid) {
+                    t.unset_mid().await;
+                }
+            }
+        }
+
+        let rollback = RTCSessionDescription {
+            sdp_type: RTCSdpType::Rollback,
+            ..Default::default()
+        };
+        self.set_description(&rollback, StateChangeOp::SetLocal)
+            .await
+    }
+
     /// local_description returns PendingLocalDescription if it is not null and
     /// otherwise it returns CurrentLocalDescription. This property is used to
     /// determine if set_local_description has already been called.
@@ -1317,9 +1516,28 @@ impl RTCPeerConnection {
         };
 
         desc.parsed = Some(desc.unmarshal()?);
+        self.match_remote_media_sections(&mut desc).await?;
+
+        // The DTLS role is settled by the initial offer/answer exchange, setup attributes
+        // of later descriptions are ignored.
+        let dtls_role = if is_renegotation {
+            None
+        } else {
+            Some(self.resolve_dtls_role(&desc)?)
+        };
+
+        // Hold the candidate queue from publishing the description until it is drained,
+        // so add_ice_candidate can't add a candidate ahead of the queued ones.
+        let mut pending_remote_candidates = self.internal.pending_remote_candidates.lock().await;
+
         self.set_description(&desc, StateChangeOp::SetRemote)
             .await?;
 
+        if let Some(dtls_role) = dtls_role {
+            let mut negotiated_role = self.internal.dtls_transport.negotiated_role.lock().await;
+            *negotiated_role = dtls_role;
+        }
+
         if let Some(parsed) = &desc.parsed {
             self.internal
                 .media_engine
@@ -1376,6 +1594,10 @@ impl RTCPeerConnection {
                                 Arc::clone(&self.internal.dtls_transport),
                                 Arc::clone(&self.internal.media_engine),
                                 Arc::clone(&self.interceptor),
+                                self.internal
+                                    .setting_engine
+                                    .timeout
+                                    .track_inactivity_timeout,
                             ));
 
                             let local_direction =
@@ -1435,6 +1657,9 @@ impl RTCPeerConnection {
                         }
 
                         if let Some(t) = find_by_mid(mid_value, &mut local_transceivers).await {
+                            if t.kind() != kind {
+                                continue;
+                            }
                             let previous_direction = t.current_direction();
 
                             // 4.5.9.2.9
@@ -1459,6 +1684,8 @@ impl RTCPeerConnection {
                 }
             }
 
+            self.internal.update_negotiated_codecs(parsed).await?;
+
             let (remote_ufrag, remote_pwd, candidates) = extract_ice_details(parsed).await?;
 
             if is_renegotation
@@ -1471,6 +1698,7 @@ impl RTCPeerConnection {
                 // An ICE Restart only happens implicitly for a set_remote_description of type offer
                 if !we_offer {
                     self.internal.ice_transport.restart().await?;
+                    self.internal.ice_restarted().await;
                 }
 
                 self.internal
@@ -1479,12 +1707,29 @@ impl RTCPeerConnection {
                     .await?;
             }
 
+            let (ice_options, end_of_candidates) = extract_ice_options(parsed);
+            self.internal
+                .ice_transport
+                .set_remote_options(ice_options)
+                .await?;
+
             for candidate in candidates {
                 self.internal
                     .ice_transport
                     .add_remote_candidate(Some(candidate))
                     .await?;
             }
+            if end_of_candidates {
+                self.internal
+                    .ice_transport
+                    .add_remote_candidate(None)
+                    .await?;
+            }
+
+            self.internal
+                .add_pending_remote_candidates(&mut pending_remote_candidates)
+                .await;
+            drop(pending_remote_candidates);
 
             if is_renegotation {
                 if we_offer {
@@ -1541,7 +1786,7 @@ impl RTCPeerConnection {
             //log::trace!("start_transports: parsed={:?}", parsed);
 
             let pci = Arc::clone(&self.internal);
-            let dtls_role = DTLSRole::from(parsed);
+            let dtls_role = self.internal.dtls_transport.role().await.inverse();
             let remote_desc = Arc::new(desc);
             self.internal
                 .ops
@@ -1576,6 +1821,89 @@ impl RTCPeerConnection {
         Ok(())
     }
 
+    /// match_remote_media_sections checks that the media sections of the remote description
+    /// keep the kind of the local ones with the same mid. An answer must answer every media
+    /// section of our offer, and its sections without a mid are matched to the ones of the
+    /// offer by kind and order.
+    async fn match_remote_media_sections(&self, desc: &mut RTCSessionDescription) -> Result<()> {
+        let parsed = match desc.parsed.as_mut() {
+            Some(parsed) => parsed,
+            None => return Ok(()),
+        };
+
+        match desc.sdp_type {
+            RTCSdpType::Answer | RTCSdpType::Pranswer => {
+                let offer = {
+                    let pending_local_description =
+                        self.internal.pending_local_description.lock().await;
+                    pending_local_description
+                        .as_ref()
+                        .filter(|d| d.sdp_type == RTCSdpType::Offer)
+                        .and_then(|d| d.parsed.clone())
+                };
+                if let Some(offer) = offer {
+                    match_answer_media_sections(&offer, parsed)?;
+                }
+            }
+            RTCSdpType::Offer => {
+                for t in self.get_transceivers().await {
+                    let mid = t.mid().await;
+                    if mid.is_empty() {
+                        continue;
+                    }
+                    let media = parsed
+                        .media_descriptions
+                        .iter()
+                        .find(|m| get_mid_value(m) == Some(&mid));
+                    if let Some(media) = media {
+                        if RTPCodecType::from(media.media_name.media.as_str()) != t.kind() {
+                            return Err(Error::ErrPeerConnRemoteMediaSectionKindMismatch {
+                                mid,
+                                remote_kind: media.media_name.media.clone(),
+                                local_kind: t.kind().to_string(),
+                            });
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// resolve_dtls_role determines the DTLS role we take from the setup attribute of the
+    /// remote description of the initial offer/answer exchange.
+    /// <https://www.rfc-editor.org/rfc/rfc8842#section-5>
+    fn resolve_dtls_role(&self, desc: &RTCSessionDescription) -> Result<DTLSRole> {
+        let remote_role = match &desc.parsed {
+            Some(parsed) => connection_role_from_sdp(parsed),
+            None => return Ok(DTLSRole::Unspecified),
+        };
+
+        match desc.sdp_type {
+            RTCSdpType::Offer => match remote_role {
+                // The offerer MUST use setup:actpass, an offerer insisting on passive
+                // (e.g. an ice-lite SFU) gets an active answerer.
+                ConnectionRole::Active => Err(Error::ErrPeerConnRemoteOfferSetupActive),
+                ConnectionRole::Passive => Ok(DTLSRole::Client),
+                _ => match self.internal.setting_engine.answering_dtls_role {
+                    DTLSRole::Client => Ok(DTLSRole::Client),
+                    DTLSRole::Server => Ok(DTLSRole::Server),
+                    _ => Ok(DEFAULT_DTLS_ROLE_ANSWER),
+                },
+            },
+            RTCSdpType::Answer | RTCSdpType::Pranswer => match remote_role {
+                ConnectionRole::Active => Ok(DTLSRole::Server),
+                ConnectionRole::Passive => Ok(DTLSRole::Client),
+                ConnectionRole::Actpass => Err(Error::ErrPeerConnRemoteAnswerSetupActpass),
+                // Without a setup attribute the answerer is active (RFC 4145)
+                _ => Ok(DTLSRole::Server),
+            },
+            _ => Ok(DTLSRole::Unspecified),
+        }
+    }
+
     /// start_rtp_senders starts all outbound RTP streams
     pub(crate) async fn start_rtp_senders(&self) -> Result<()> {
         let current_transceivers = self.internal.rtp_transceivers.lock().await;
@@ -1599,30 +1927,29 @@ impl RTCPeerConnection {
     }
 
     /// add_ice_candidate accepts an ICE candidate string and adds it
-    /// to the existing set of candidates.
+    /// to the existing set of candidates. Candidates received before the
+    /// remote description are queued and added once set_remote_description
+    /// succeeds.
     pub async fn add_ice_candidate(&self, candidate: RTCIceCandidateInit) -> Result<()> {
-        if self.remote_description().await.is_none() {
-            return Err(Error::ErrNoRemoteDescription);
+        if self.internal.is_closed.load(Ordering::SeqCst) {
+            return Err(Error::ErrConnectionClosed);
         }
 
-        let candidate_value = match candidate.candidate.strip_prefix("candidate:") {
-            Some(s) => s,
-            None => candidate.candidate.as_str(),
-        };
-
-        let ice_candidate = if !candidate_value.is_empty() {
-            let candidate: Arc<dyn Candidate + Send + Sync> =
-                Arc::new(unmarshal_candidate(candidate_value)?);
+        // Hold the queue while adding so candidates are applied in the order
+        // they were received, even if the remote description is being set.
+        let mut pending_remote_candidates = self.internal.pending_remote_candidates.lock().await;
+        if self.remote_description().await.is_none() {
+            // Reject malformed candidates right away rather than when the queue is drained
+            unmarshal_ice_candidate(&candidate)?;
 
-            Some(RTCIceCandidate::from(&candidate))
-        } else {
-            None
-        };
+            if pending_remote_candidates.len() >= MAX_PENDING_REMOTE_CANDIDATES {
+                return Err(Error::ErrPeerConnPendingCandidatesFull);
+            }
+            pending_remote_candidates.push(candidate);
+            return Ok(());
+        }
 
-        self.internal
-            .ice_transport
-            .add_remote_candidate(ice_candidate)
-            .await
+        self.internal.add_remote_candidate(&candidate).await
     }
 
     /// ice_connection_state returns the ICE connection state of the
@@ -1809,9 +2136,15 @@ impl RTCPeerConnection {
             return Err(Error::ErrConnectionClosed);
         }
 
+        // https://w3c.github.io/webrtc-pc/#peer-to-peer-data-api (Step #4)
+        if label.len() > 65535 {
+            return Err(Error::ErrStringSizeLimit);
+        }
+
         let mut params = DataChannelParameters {
             label: label.to_owned(),
             ordered: true,
+            priority: RTCPriorityType::Low.dcep_priority(),
             ..Default::default()
         };
 
@@ -1825,14 +2158,10 @@ impl RTCPeerConnection {
             }
 
             // https://w3c.github.io/webrtc-pc/#peer-to-peer-data-api (Step #7)
-            if let Some(max_packet_life_time) = options.max_packet_life_time {
-                params.max_packet_life_time = max_packet_life_time;
-            }
+            params.max_packet_life_time = options.max_packet_life_time;
 
             // https://w3c.github.io/webrtc-pc/#peer-to-peer-data-api (Step #8)
-            if let Some(max_retransmits) = options.max_retransmits {
-                params.max_retransmits = max_retransmits;
-            }
+            params.max_retransmits = options.max_retransmits;
 
             // https://w3c.github.io/webrtc-pc/#peer-to-peer-data-api (Step #10)
             if let Some(protocol) = options.protocol {
@@ -1846,15 +2175,25 @@ impl RTCPeerConnection {
 
             // https://w3c.github.io/webrtc-pc/#peer-to-peer-data-api (Step #12)
             params.negotiated = options.negotiated;
+
+            // https://w3c.github.io/webrtc-priority/#rtcdatachannel-extensions
+            if let Some(priority) = options.priority {
+                params.priority = priority.dcep_priority();
+            }
+
+            if let Some(chunked) = options.chunked {
+                params.chunked = chunked;
+            }
         }
 
         let d = Arc::new(RTCDataChannel::new(
             params,
+            self.internal.sctp_transport.next_data_channel_stats_id(),
             Arc::clone(&self.internal.setting_engine),
         ));
 
         // https://w3c.github.io/webrtc-pc/#peer-to-peer-data-api (Step #16)
-        if d.max_packet_lifetime != 0 && d.max_retransmits != 0 {
+        if d.max_packet_lifetime.is_some() && d.max_retransmits.is_some() {
             return Err(Error::ErrRetransmitsOrPacketLifeTime);
         }
 
@@ -1951,28 +2290,58 @@ impl RTCPeerConnection {
             close_errs.push(Error::new(format!("dtls_transport: {}", err)));
         }
 
+        {
+            let mut pending_remote_candidates =
+                self.internal.pending_remote_candidates.lock().await;
+            if !pending_remote_candidates.is_empty() {
+                log::debug!(
+                    "discarding {} remote ICE candidates queued without a remote description",
+                    pending_remote_candidates.len()
+                );
+                pending_remote_candidates.clear();
+            }
+        }
+
         // https://www.w3.org/TR/webrtc/#dom-rtcpeerconnection-close (step #8, #9, #10)
         if let Err(err) = self.internal.ice_transport.stop().await {
             close_errs.push(Error::new(format!("dtls_transport: {}", err)));
         }
 
         // https://www.w3.org/TR/webrtc/#dom-rtcpeerconnection-close (step #11)
-        RTCPeerConnection::update_connection_state(
-            &self.internal.on_peer_connection_state_change_handler,
-            &self.internal.is_closed,
-            &self.internal.peer_connection_state,
-            self.ice_connection_state(),
-            self.internal.dtls_transport.state(),
-        )
-        .await;
+        self.internal.update_connection_state().await;
 
         if let Err(err) = self.internal.ops.close().await {
             close_errs.push(Error::new(format!("ops: {}", err)));
         }
 
+        // The tasks of the peer connection stop once everything above is closed
+        self.internal.connect_deadline.set(None);
+        let cancelled = self.internal.tasks.close(TASKS_CLOSE_TIMEOUT).await;
+        if cancelled > 0 {
+            log::debug!("cancelled {} tasks which didn't stop on close", cancelled);
+        }
+        self.internal.done_tx.send_modify(|done| *done = true);
+
         flatten_errs(close_errs)
     }
 
+    /// done returns a future which resolves once the peer connection is closed and all of its
+    /// tasks have stopped, that is once its sockets and other resources are released. close
+    /// waits for this as well, done is for waiting on a close happening elsewhere, e.g. in an
+    /// event handler.
+    pub fn done(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut done_rx = self.internal.done_tx.subscribe();
+        let tasks = self.internal.tasks.clone();
+        async move {
+            while !*done_rx.borrow_and_update() {
+                if done_rx.changed().await.is_err() {
+                    break;
+                }
+            }
+            tasks.wait().await;
+        }
+    }
+
     /// CurrentLocalDescription represents the local description that was
     /// successfully negotiated the last time the PeerConnection transitioned
     /// into the stable state plus any local candidates that have been generated
@@ -2043,11 +2412,49 @@ impl RTCPeerConnection {
             .into()
     }
 
+    /// connection_error returns why the PeerConnection failed, None if it didn't. It is set
+    /// before on_peer_connection_state_change reports the failed state, and cleared by an ICE
+    /// restart which can recover from it.
+    pub fn connection_error(&self) -> Option<RTCConnectionError> {
+        self.internal.connection_error.lock().clone()
+    }
+
+    /// get_stats gathers the statistics of the connection and of all the objects it owns.
+    ///
+    /// Calling it again yields the same ids for the objects which still exist, and a strictly
+    /// greater [`StatsReport::monotonic_timestamp`], even if the system clock was set backwards
+    /// in between.
     pub async fn get_stats(&self) -> StatsReport {
-        self.internal
+        let collector = self
+            .internal
             .get_stats(self.get_stats_id().to_owned())
-            .await
-            .into()
+            .await;
+        let timestamp = match &self.internal.setting_engine.stats_time_source {
+            Some(time_source) => time_source.now(),
+            None => SystemTime::now(),
+        };
+
+        StatsReport::new(collector, timestamp, self.next_stats_timestamp())
+    }
+
+    /// next_stats_timestamp returns the time elapsed since the creation of the connection, or
+    /// slightly more than the previous one if the clock didn't advance.
+    fn next_stats_timestamp(&self) -> Duration {
+        let elapsed = self.created_at.elapsed();
+        let mut last = self.last_stats_timestamp.lock();
+        *last = if elapsed > *last {
+            elapsed
+        } else {
+            *last + Duration::from_micros(1)
+        };
+        *last
+    }
+
+    /// current_rtt returns the latest round trip time measured on the selected candidate pair.
+    /// It is kept up to date by the STUN consent checks, see
+    /// [`RTCIceTransport::current_round_trip_time`].
+    pub async fn current_rtt(&self) -> Option<Duration> {
+        self.internal.ice_transport.current_round_trip_time().await
     }
 
     /// sctp returns the SCTPTransport for this PeerConnection
@@ -1,3 +1,31 @@
+use crate::peer_connection::policy::ice_transport_policy::RTCIceTransportPolicy;
+
+use std::time::Duration;
+
+/// RTCIceTrickleMode selects whether local ICE candidates are trickled, that is signaled one by
+/// one through on_ice_candidate, or carried by the session description.
+/// See <https://www.rfc-editor.org/rfc/rfc8838#section-3>.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum RTCIceTrickleMode {
+    /// Full means the description carries only the candidates gathered so far, the rest is
+    /// trickled. This is the default.
+    Full,
+
+    /// Half means the offer waits for gathering to complete and carries all candidates, while
+    /// the answerer may trickle. An answer created with Half behaves like Full.
+    Half,
+
+    /// Disabled means descriptions wait for gathering to complete and carry all candidates
+    /// followed by a=end-of-candidates, for signaling that can't transport trickled candidates.
+    Disabled,
+}
+
+impl Default for RTCIceTrickleMode {
+    fn default() -> Self {
+        RTCIceTrickleMode::Full
+    }
+}
+
 /// AnswerOptions structure describes the options used to control the answer
 /// creation process.
 #[derive(Default, Debug, PartialEq, Eq, Copy, Clone)]
@@ -5,6 +33,15 @@ pub struct RTCAnswerOptions {
     /// voice_activity_detection allows the application to provide information
     /// about whether it wishes voice detection feature to be enabled or disabled.
     pub voice_activity_detection: bool,
+
+    /// ice_trickle_mode selects whether the answer waits for ICE gathering to complete,
+    /// see RTCIceTrickleMode.
+    pub ice_trickle_mode: RTCIceTrickleMode,
+
+    /// ice_gathering_timeout bounds the wait for ICE gathering when the answer waits for it.
+    /// Once it elapses the answer carries the candidates gathered so far, without
+    /// a=end-of-candidates. None waits until gathering completes.
+    pub ice_gathering_timeout: Option<Duration>,
 }
 
 /// OfferOptions structure describes the options used to control the offer
@@ -19,4 +56,31 @@ pub struct RTCOfferOptions {
     /// When this value is true, the generated description will have ICE
     /// credentials that are different from the current credentials
     pub ice_restart: bool,
+
+    /// ice_trickle_mode selects whether the offer waits for ICE gathering to complete,
+    /// see RTCIceTrickleMode.
+    pub ice_trickle_mode: RTCIceTrickleMode,
+
+    /// ice_gathering_timeout bounds the wait for ICE gathering when the offer waits for it.
+    /// Once it elapses the offer carries the candidates gathered so far, without
+    /// a=end-of-candidates. None waits until gathering completes.
+    pub ice_gathering_timeout: Option<Duration>,
+}
+
+/// IcePrepareOptions structure describes the options used to control the ICE candidate
+/// gathering started ahead of the descriptions with prepare_ice
+#[derive(Default, Debug, PartialEq, Eq, Copy, Clone)]
+pub struct RTCIcePrepareOptions {
+    /// ice_transport_policy replaces the policy of the configuration before gathering starts,
+    /// e.g. Relay to only gather TURN allocations ahead. Unspecified keeps the configured policy.
+    /// Once gathering started, a new policy only applies from the next ICE restart.
+    pub ice_transport_policy: RTCIceTransportPolicy,
+
+    /// wait_gathering_complete makes prepare_ice return only once gathering completes, so the
+    /// description created next carries every local candidate.
+    pub wait_gathering_complete: bool,
+
+    /// ice_gathering_timeout bounds the wait for ICE gathering when prepare_ice waits for it.
+    /// None waits until gathering completes.
+    pub ice_gathering_timeout: Option<Duration>,
 }
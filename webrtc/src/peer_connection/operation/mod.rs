@@ -7,9 +7,11 @@ use std::pin::Pin;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::mpsc;
+use util::sync::TaskGroup;
 use waitgroup::WaitGroup;
 
 use crate::error::Result;
+use crate::peer_connection::TASKS_CLOSE_TIMEOUT;
 
 /// Operation is a function
 pub struct Operation(
@@ -41,6 +43,7 @@ pub(crate) struct Operations {
     length: Arc<AtomicUsize>,
     ops_tx: Option<Arc<mpsc::UnboundedSender<Operation>>>,
     close_tx: Option<mpsc::Sender<()>>,
+    tasks: TaskGroup,
 }
 
 impl Operations {
@@ -51,7 +54,8 @@ impl Operations {
         let l = Arc::clone(&length);
         let ops_tx = Arc::new(ops_tx);
         let ops_tx2 = Arc::clone(&ops_tx);
-        tokio::spawn(async move {
+        let tasks = TaskGroup::new();
+        tasks.spawn(async move {
             Operations::start(l, ops_tx, ops_rx, close_rx).await;
         });
 
@@ -59,6 +63,7 @@ impl Operations {
             length,
             ops_tx: Some(ops_tx2),
             close_tx: Some(close_tx),
+            tasks,
         }
     }
 
@@ -133,6 +138,7 @@ impl Operations {
         if let Some(close_tx) = &self.close_tx {
             close_tx.send(()).await?;
         }
+        self.tasks.close(TASKS_CLOSE_TIMEOUT).await;
         Ok(())
     }
 }
@@ -7,11 +7,12 @@ use crate::stats::{
     InboundRTPStats, OutboundRTPStats, RTCStatsType, RemoteInboundRTPStats, RemoteOutboundRTPStats,
     StatsReportType,
 };
-use crate::track::TrackStream;
 use crate::{SDES_REPAIR_RTP_STREAM_ID_URI, SDP_ATTRIBUTE_RID};
 use arc_swap::ArcSwapOption;
+use ice::options::IceOptions;
 use std::sync::atomic::AtomicIsize;
 use std::sync::Weak;
+use tokio::sync::watch;
 
 pub(crate) struct PeerConnectionInternal {
     /// a value containing the last known greater mid value
@@ -25,6 +26,10 @@ pub(crate) struct PeerConnectionInternal {
 
     pub(super) on_negotiation_needed_handler: Arc<ArcSwapOption<Mutex<OnNegotiationNeededHdlrFn>>>,
     pub(super) is_closed: Arc<AtomicBool>,
+    /// the tasks spawned by the peer connection, waited for on close
+    pub(super) tasks: TaskGroup,
+    /// set once close released all resources of the peer connection
+    pub(super) done_tx: watch::Sender<bool>,
 
     /// ops is an operations queue which will ensure the enqueued actions are
     /// executed in order. It is used for asynchronously, but serially processing
@@ -40,6 +45,10 @@ pub(crate) struct PeerConnectionInternal {
         Arc<ArcSwapOption<Mutex<OnPeerConnectionStateChangeHdlrFn>>>,
     pub(super) peer_connection_state: Arc<AtomicU8>,
     pub(super) ice_connection_state: Arc<AtomicU8>,
+    /// why the peer connection failed, set before it changes to the failed state
+    pub(super) connection_error: Arc<SyncMutex<Option<RTCConnectionError>>>,
+    /// the point in time the peer connection has to be connected by, cleared once connected
+    pub(super) connect_deadline: Arc<Deadline>,
 
     pub(super) sctp_transport: Arc<RTCSctpTransport>,
     pub(super) rtp_transceivers: Arc<Mutex<Vec<Arc<RTCRtpTransceiver>>>>,
@@ -57,6 +66,8 @@ pub(crate) struct PeerConnectionInternal {
     pub(super) current_remote_description: Arc<Mutex<Option<RTCSessionDescription>>>,
     pub(super) pending_local_description: Arc<Mutex<Option<RTCSessionDescription>>>,
     pub(super) pending_remote_description: Arc<Mutex<Option<RTCSessionDescription>>>,
+    /// remote ICE candidates received before the remote description, in arrival order
+    pub(super) pending_remote_candidates: Mutex<Vec<RTCIceCandidateInit>>,
 
     // A reference to the associated API state used by this connection
     pub(super) setting_engine: Arc<SettingEngine>,
@@ -81,12 +92,16 @@ impl PeerConnectionInternal {
             on_negotiation_needed_handler: Arc::new(ArcSwapOption::empty()),
             ops: Arc::new(Operations::new()),
             is_closed: Arc::new(AtomicBool::new(false)),
+            tasks: TaskGroup::new(),
+            done_tx: watch::channel(false).0,
             is_negotiation_needed: Arc::new(AtomicBool::new(false)),
             negotiation_needed_state: Arc::new(AtomicU8::new(NegotiationNeededState::Empty as u8)),
             signaling_state: Arc::new(AtomicU8::new(RTCSignalingState::Stable as u8)),
             ice_transport: Arc::new(Default::default()),
             dtls_transport: Arc::new(Default::default()),
             ice_connection_state: Arc::new(AtomicU8::new(RTCIceConnectionState::New as u8)),
+            connection_error: Arc::new(SyncMutex::new(None)),
+            connect_deadline: Arc::new(Deadline::default()),
             sctp_transport: Arc::new(Default::default()),
             rtp_transceivers: Arc::new(Default::default()),
             on_track_handler: Arc::new(ArcSwapOption::empty()),
@@ -101,14 +116,15 @@ impl PeerConnectionInternal {
 
             setting_engine: Arc::clone(&api.setting_engine),
             media_engine: if !api.setting_engine.disable_media_engine_copy {
-                Arc::new(api.media_engine.clone_to())
+                Arc::new(api.media_engine.load().clone_to())
             } else {
-                Arc::clone(&api.media_engine)
+                api.media_engine.load_full()
             },
             interceptor,
             stats_interceptor,
             on_peer_connection_state_change_handler: Arc::new(ArcSwapOption::empty()),
             pending_remote_description: Arc::new(Default::default()),
+            pending_remote_candidates: Mutex::new(vec![]),
         };
 
         // Create the ice gatherer
@@ -118,7 +134,7 @@ impl PeerConnectionInternal {
         })?);
 
         // Create the ice transport
-        pc.ice_transport = pc.create_ice_transport(api).await;
+        pc.ice_transport = Arc::new(api.new_ice_transport(Arc::clone(&pc.ice_gatherer)));
 
         // Create the DTLS transport
         let certificates = configuration.certificates.drain(..).collect();
@@ -141,7 +157,10 @@ impl PeerConnectionInternal {
                 })
             }));
 
-        Ok((Arc::new(pc), configuration))
+        let pc = Arc::new(pc);
+        pc.handle_ice_connection_state_change();
+
+        Ok((pc, configuration))
     }
 
     pub(super) async fn start_rtp(
@@ -214,6 +233,7 @@ impl PeerConnectionInternal {
                         Arc::clone(&self.dtls_transport),
                         Arc::clone(&self.media_engine),
                         interceptor,
+                        self.setting_engine.timeout.track_inactivity_timeout,
                     ));
                     t.set_receiver(Some(receiver)).await;
                 }
@@ -224,7 +244,7 @@ impl PeerConnectionInternal {
             .await?;
         if let Some(parsed) = &remote_desc.parsed {
             if have_application_media_section(parsed) {
-                self.start_sctp().await;
+                self.start_sctp(get_max_message_size(parsed)).await;
             }
         }
 
@@ -236,7 +256,7 @@ impl PeerConnectionInternal {
         let dtls_transport = Arc::clone(&self.dtls_transport);
         let is_closed = Arc::clone(&self.is_closed);
         let pci = Arc::clone(self);
-        tokio::spawn(async move {
+        self.tasks.spawn(async move {
             let simulcast_routine_count = Arc::new(AtomicU64::new(0));
             loop {
                 let srtp_session = match dtls_transport.get_srtp_session().await {
@@ -273,7 +293,7 @@ impl PeerConnectionInternal {
                 let dtls_transport2 = Arc::clone(&dtls_transport);
                 let simulcast_routine_count2 = Arc::clone(&simulcast_routine_count);
                 let pci2 = Arc::clone(&pci);
-                tokio::spawn(async move {
+                pci.tasks.spawn(async move {
                     let ssrc = stream.get_ssrc();
 
                     dtls_transport2
@@ -294,7 +314,7 @@ impl PeerConnectionInternal {
         });
 
         let dtls_transport = Arc::clone(&self.dtls_transport);
-        tokio::spawn(async move {
+        self.tasks.spawn(async move {
             loop {
                 let srtcp_session = match dtls_transport.get_srtcp_session().await {
                     Some(s) => s,
@@ -366,6 +386,7 @@ impl PeerConnectionInternal {
                         incoming_track,
                         receiver,
                         Arc::clone(&self.on_track_handler),
+                        &self.tasks,
                     )
                     .await;
                     track_handled = true;
@@ -381,13 +402,11 @@ impl PeerConnectionInternal {
     }
 
     /// Start SCTP subsystem
-    async fn start_sctp(&self) {
+    async fn start_sctp(&self, max_message_size: u32) {
         // Start sctp
         if let Err(err) = self
             .sctp_transport
-            .start(SCTPTransportCapabilities {
-                max_message_size: 0,
-            })
+            .start(SCTPTransportCapabilities { max_message_size })
             .await
         {
             log::warn!("Failed to start SCTP: {}", err);
@@ -461,6 +480,7 @@ impl PeerConnectionInternal {
                     Arc::clone(&self.dtls_transport),
                     Arc::clone(&self.media_engine),
                     interceptor,
+                    self.setting_engine.timeout.track_inactivity_timeout,
                 ));
 
                 RTCRtpTransceiver::new(
@@ -500,6 +520,7 @@ impl PeerConnectionInternal {
                     Arc::clone(&self.dtls_transport),
                     Arc::clone(&self.media_engine),
                     Arc::clone(&interceptor),
+                    self.setting_engine.timeout.track_inactivity_timeout,
                 )));
                 let s = Some(Arc::new(
                     RTCRtpSender::new(
@@ -600,6 +621,51 @@ impl PeerConnectionInternal {
         }
     }
 
+    /// add_remote_candidate validates a candidate against the remote description
+    /// and hands it to the ICE transport.
+    pub(super) async fn add_remote_candidate(&self, candidate: &RTCIceCandidateInit) -> Result<()> {
+        let remote_description = match self.remote_description().await {
+            Some(remote_description) => remote_description,
+            None => return Err(Error::ErrNoRemoteDescription),
+        };
+
+        if let Some(parsed) = &remote_description.parsed {
+            let found = match (&candidate.sdp_mid, candidate.sdp_mline_index) {
+                (Some(mid), _) if !mid.is_empty() => parsed
+                    .media_descriptions
+                    .iter()
+                    .any(|media| get_mid_value(media) == Some(mid)),
+                (None, Some(index)) => (index as usize) < parsed.media_descriptions.len(),
+                _ => true,
+            };
+            if !found {
+                return Err(Error::ErrPeerConnCandidateMediaSectionNotFound);
+            }
+        }
+
+        let ice_candidate = unmarshal_ice_candidate(candidate)?;
+        self.ice_transport.add_remote_candidate(ice_candidate).await
+    }
+
+    /// add_pending_remote_candidates adds the candidates queued by add_ice_candidate
+    /// before the remote description was set. Candidates that fail are logged and skipped.
+    /// The caller holds the pending_remote_candidates lock while the remote
+    /// description is published and passes the guarded queue in.
+    pub(super) async fn add_pending_remote_candidates(
+        &self,
+        pending_remote_candidates: &mut Vec<RTCIceCandidateInit>,
+    ) {
+        for candidate in pending_remote_candidates.drain(..) {
+            if let Err(err) = self.add_remote_candidate(&candidate).await {
+                log::warn!(
+                    "failed to add queued remote ICE candidate {}: {}",
+                    candidate.candidate,
+                    err
+                );
+            }
+        }
+    }
+
     pub(super) fn set_gather_complete_handler(&self, f: OnGatheringCompleteHdlrFn) {
         self.ice_gatherer.on_gathering_complete(f);
     }
@@ -614,6 +680,8 @@ impl PeerConnectionInternal {
         fingerprint: String,
         fingerprint_hash: String,
     ) {
+        self.arm_connect_deadline();
+
         // Start the ice transport
         if let Err(err) = self
             .ice_transport
@@ -642,16 +710,111 @@ impl PeerConnectionInternal {
                 }],
             })
             .await;
+        if let Err(err) = &result {
+            let mut connection_error = self.connection_error.lock();
+            if connection_error.is_none() {
+                *connection_error = Some(match err {
+                    Error::ErrDtlsHandshakeTimeout => RTCConnectionError::DtlsHandshakeTimeout,
+                    err => RTCConnectionError::DtlsFailed(err.to_string()),
+                });
+            }
+        }
+        self.update_connection_state().await;
+        if let Err(err) = result {
+            log::warn!("Failed to start manager dtls: {}", err);
+        }
+    }
+
+    pub(super) async fn update_connection_state(&self) {
         RTCPeerConnection::update_connection_state(
             &self.on_peer_connection_state_change_handler,
             &self.is_closed,
             &self.peer_connection_state,
+            &self.connection_error,
+            &self.connect_deadline,
             self.ice_connection_state.load(Ordering::SeqCst).into(),
             self.dtls_transport.state(),
         )
         .await;
-        if let Err(err) = result {
-            log::warn!("Failed to start manager dtls: {}", err);
+    }
+
+    /// arm_connect_deadline (re)starts the wait for the peer connection to connect, if
+    /// SettingEngine::set_connect_timeout was set.
+    fn arm_connect_deadline(self: &Arc<Self>) {
+        let timeout = match self.setting_engine.timeout.connect_timeout {
+            Some(timeout) => timeout,
+            None => return,
+        };
+        if self
+            .connect_deadline
+            .set(Some(Instant::now() + timeout))
+            .is_some()
+        {
+            // the running watcher picks up the new deadline
+            return;
+        }
+
+        let pci = Arc::clone(self);
+        self.tasks.spawn(async move {
+            if !pci.connect_deadline.wait().await {
+                return;
+            }
+            pci.connect_deadline.set(None);
+
+            let state: RTCPeerConnectionState =
+                pci.peer_connection_state.load(Ordering::SeqCst).into();
+            if state != RTCPeerConnectionState::Connected && !pci.is_closed.load(Ordering::SeqCst) {
+                log::warn!("peer connection didn't connect within {:?}", timeout);
+                pci.fail(RTCConnectionError::ConnectTimeout).await;
+            }
+        });
+    }
+
+    /// fail records err, unless an earlier error is recorded, and moves the peer connection to
+    /// the failed state.
+    async fn fail(&self, err: RTCConnectionError) {
+        {
+            let mut connection_error = self.connection_error.lock();
+            if connection_error.is_none() {
+                *connection_error = Some(err);
+            }
+        }
+        self.update_connection_state().await;
+    }
+
+    /// ice_restarted clears the errors an ICE restart can recover from and restarts the
+    /// connect and DTLS handshake deadlines.
+    pub(super) async fn ice_restarted(self: &Arc<Self>) {
+        let recovered = {
+            let mut connection_error = self.connection_error.lock();
+            match *connection_error {
+                Some(RTCConnectionError::IceFailed) | Some(RTCConnectionError::ConnectTimeout) => {
+                    *connection_error = None;
+                    true
+                }
+                _ => false,
+            }
+        };
+
+        let state: RTCPeerConnectionState =
+            self.peer_connection_state.load(Ordering::SeqCst).into();
+        if state != RTCPeerConnectionState::Connected {
+            self.arm_connect_deadline();
+        }
+        self.dtls_transport.extend_handshake_deadline();
+
+        if recovered {
+            self.update_connection_state().await;
+        }
+    }
+
+    /// ice_options returns the ICE options advertised in local descriptions, ice_trickle is
+    /// false if the description is meant to carry all local candidates.
+    fn ice_options(&self, ice_trickle: bool) -> IceOptions {
+        IceOptions {
+            trickle: ice_trickle,
+            renomination: self.setting_engine.candidates.ice_renomination,
+            ice2: false,
         }
     }
 
@@ -661,6 +824,7 @@ impl PeerConnectionInternal {
         &self,
         local_transceivers: Vec<Arc<RTCRtpTransceiver>>,
         use_identity: bool,
+        ice_trickle: bool,
     ) -> Result<SessionDescription> {
         let d = SessionDescription::new_jsep_session_description(use_identity);
 
@@ -713,6 +877,8 @@ impl PeerConnectionInternal {
             is_icelite: self.setting_engine.candidates.ice_lite,
             connection_role: DEFAULT_DTLS_ROLE_OFFER.to_connection_role(),
             ice_gathering_state: self.ice_gathering_state(),
+            ice_options: self.ice_options(ice_trickle),
+            cryptex: self.setting_engine.cryptex,
         };
         populate_sdp(
             d,
@@ -734,6 +900,7 @@ impl PeerConnectionInternal {
         use_identity: bool,
         include_unmatched: bool,
         connection_role: ConnectionRole,
+        ice_trickle: bool,
     ) -> Result<SessionDescription> {
         let d = SessionDescription::new_jsep_session_description(use_identity);
 
@@ -741,6 +908,10 @@ impl PeerConnectionInternal {
         let candidates = self.ice_gatherer.get_local_candidates().await?;
 
         let remote_description = self.remote_description().await;
+        let remote_cryptex = remote_description
+            .as_ref()
+            .and_then(|r| r.parsed.as_ref())
+            .map_or(false, has_cryptex);
         let mut media_sections = vec![];
         let mut already_have_application_media_section = false;
         if let Some(remote_description) = remote_description.as_ref() {
@@ -833,6 +1004,9 @@ impl PeerConnectionInternal {
             is_icelite: self.setting_engine.candidates.ice_lite,
             connection_role,
             ice_gathering_state: self.ice_gathering_state(),
+            ice_options: self.ice_options(ice_trickle),
+            // answers only signal Cryptex if the offer does
+            cryptex: self.setting_engine.cryptex && (include_unmatched || remote_cryptex),
         };
         populate_sdp(
             d,
@@ -846,6 +1020,31 @@ impl PeerConnectionInternal {
         .await
     }
 
+    /// negotiate_cryptex records whether the current local and remote descriptions both signal
+    /// Cryptex, for the SRTP session to start. It is called whenever the signaling state gets
+    /// stable, an established SRTP session keeps what it started with.
+    pub(super) async fn negotiate_cryptex(&self) {
+        let local_cryptex = {
+            let current_local_description = self.current_local_description.lock().await;
+            current_local_description
+                .as_ref()
+                .and_then(|d| d.parsed.as_ref())
+                .map_or(false, has_cryptex)
+        };
+        let remote_cryptex = {
+            let current_remote_description = self.current_remote_description.lock().await;
+            current_remote_description
+                .as_ref()
+                .and_then(|d| d.parsed.as_ref())
+                .map_or(false, has_cryptex)
+        };
+
+        self.dtls_transport.cryptex.store(
+            self.setting_engine.cryptex && local_cryptex && remote_cryptex,
+            Ordering::SeqCst,
+        );
+    }
+
     pub(super) fn ice_gathering_state(&self) -> RTCIceGatheringState {
         match self.ice_gatherer.state() {
             RTCIceGathererState::New => RTCIceGatheringState::New,
@@ -911,6 +1110,7 @@ impl PeerConnectionInternal {
                 &incoming,
                 receiver,
                 Arc::clone(&self.on_track_handler),
+                &self.tasks,
             )
             .await;
         }
@@ -981,32 +1181,73 @@ impl PeerConnectionInternal {
                         params.codecs[0].capability.clone(),
                         &params.header_extensions,
                     );
-                    let (rtp_read_stream, rtp_interceptor, rtcp_read_stream, rtcp_interceptor) =
-                        self.dtls_transport
-                            .streams_for_ssrc(ssrc, &stream_info, &icpr)
-                            .await?;
+                    let track_stream = self
+                        .dtls_transport
+                        .streams_for_ssrc(ssrc, &stream_info, &icpr)
+                        .await?;
 
+                    // Without header extensions, the SDES items of the RTCP tell the MID and RID
                     let a = Attributes::new();
-                    for _ in 0..=SIMULCAST_PROBE_COUNT {
-                        if mid.is_empty() || (rid.is_empty() && rsid.is_empty()) {
-                            if let Some(icpr) = &rtp_interceptor {
-                                let (n, _) = icpr.read(&mut buf, &a).await?;
+                    let mut rtcp_buf = vec![0u8; self.setting_engine.get_receive_mtu()];
+                    let mut probes = 0;
+                    let probe_timeout = tokio::time::sleep(
+                        self.setting_engine
+                            .timeout
+                            .simulcast_rtcp_probe_timeout
+                            .unwrap_or(SIMULCAST_RTCP_PROBE_TIMEOUT),
+                    );
+                    tokio::pin!(probe_timeout);
+                    while mid.is_empty() || (rid.is_empty() && rsid.is_empty()) {
+                        let (rtp_icpr, rtcp_icpr) = match (
+                            &track_stream.rtp_interceptor,
+                            &track_stream.rtcp_interceptor,
+                        ) {
+                            (Some(rtp_icpr), Some(rtcp_icpr)) => (rtp_icpr, rtcp_icpr),
+                            _ => return Err(Error::ErrInterceptorNotBind),
+                        };
+
+                        let probe_rtp = probes < SIMULCAST_PROBE_COUNT;
+                        let (m, r, rs) = tokio::select! {
+                            result = rtp_icpr.read(&mut buf, &a), if probe_rtp => {
+                                probes += 1;
+                                let (n, _) = result?;
                                 let (m, r, rs, _) = handle_unknown_rtp_packet(
                                     &buf[..n],
                                     mid_extension_id as u8,
                                     sid_extension_id as u8,
                                     rsid_extension_id as u8,
                                 )?;
-                                mid = m;
-                                rid = r;
-                                rsid = rs;
-
-                                continue;
-                            } else {
-                                return Err(Error::ErrInterceptorNotBind);
+                                (m, r, rs)
+                            }
+                            result = rtcp_icpr.read(&mut rtcp_buf, &a) => {
+                                let (n, _) = result?;
+                                match handle_unknown_rtcp_packet(&rtcp_buf[..n], ssrc) {
+                                    Ok(ids) => ids,
+                                    Err(err) => {
+                                        log::debug!(
+                                            "Failed to read RTCP of ssrc({}): {}",
+                                            ssrc,
+                                            err
+                                        );
+                                        continue;
+                                    }
+                                }
                             }
+                            _ = &mut probe_timeout => break,
+                        };
+
+                        if !m.is_empty() {
+                            mid = m;
                         }
+                        if !r.is_empty() {
+                            rid = r;
+                        }
+                        if !rs.is_empty() {
+                            rsid = rs;
+                        }
+                    }
 
+                    if !mid.is_empty() && (!rid.is_empty() || !rsid.is_empty()) {
                         let transceivers = self.rtp_transceivers.lock().await;
                         for t in &*transceivers {
                             if t.mid().await != mid || t.receiver().await.is_none() {
@@ -1015,37 +1256,15 @@ impl PeerConnectionInternal {
 
                             if let Some(receiver) = t.receiver().await {
                                 if !rsid.is_empty() {
-                                    return receiver
-                                        .receive_for_rtx(
-                                            0,
-                                            rsid,
-                                            TrackStream {
-                                                stream_info: Some(stream_info.clone()),
-                                                rtp_read_stream,
-                                                rtp_interceptor,
-                                                rtcp_read_stream,
-                                                rtcp_interceptor,
-                                            },
-                                        )
-                                        .await;
+                                    return receiver.receive_for_rtx(0, rsid, track_stream).await;
                                 }
 
-                                let track = receiver
-                                    .receive_for_rid(
-                                        rid,
-                                        params,
-                                        TrackStream {
-                                            stream_info: Some(stream_info.clone()),
-                                            rtp_read_stream,
-                                            rtp_interceptor,
-                                            rtcp_read_stream,
-                                            rtcp_interceptor,
-                                        },
-                                    )
-                                    .await?;
+                                let track =
+                                    receiver.receive_for_rid(rid, params, track_stream).await?;
 
                                 RTCPeerConnection::do_track(
                                     Arc::clone(&self.on_track_handler),
+                                    &self.tasks,
                                     Some(track),
                                     Some(receiver.clone()),
                                 )
@@ -1055,10 +1274,10 @@ impl PeerConnectionInternal {
                         }
                     }
 
-                    if let Some(rtp_read_stream) = rtp_read_stream {
+                    if let Some(rtp_read_stream) = track_stream.rtp_read_stream {
                         let _ = rtp_read_stream.close().await;
                     }
-                    if let Some(rtcp_read_stream) = rtcp_read_stream {
+                    if let Some(rtcp_read_stream) = track_stream.rtcp_read_stream {
                         let _ = rtcp_read_stream.close().await;
                     }
                     icpr.unbind_remote_stream(&stream_info).await;
@@ -1077,6 +1296,7 @@ impl PeerConnectionInternal {
         incoming: &TrackDetails,
         receiver: Arc<RTCRtpReceiver>,
         on_track_handler: Arc<ArcSwapOption<Mutex<OnTrackHdlrFn>>>,
+        tasks: &TaskGroup,
     ) {
         receiver.start(incoming).await;
         for t in receiver.tracks().await {
@@ -1086,7 +1306,8 @@ impl PeerConnectionInternal {
 
             let receiver2 = Arc::clone(&receiver);
             let on_track_handler2 = Arc::clone(&on_track_handler);
-            tokio::spawn(async move {
+            let tasks2 = tasks.clone();
+            tasks.spawn(async move {
                 if let Some(track) = receiver2.track().await {
                     let mut b = vec![0u8; receive_mtu];
                     let n = match track.peek(&mut b).await {
@@ -1112,6 +1333,7 @@ impl PeerConnectionInternal {
 
                     RTCPeerConnection::do_track(
                         on_track_handler2,
+                        &tasks2,
                         receiver2.track().await,
                         Some(receiver2),
                     )
@@ -1121,61 +1343,91 @@ impl PeerConnectionInternal {
         }
     }
 
-    pub(super) async fn create_ice_transport(&self, api: &API) -> Arc<RTCIceTransport> {
-        let ice_transport = Arc::new(api.new_ice_transport(Arc::clone(&self.ice_gatherer)));
+    /// handle_ice_connection_state_change updates the ICE and peer connection states when the
+    /// state of the ICE transport changes. The peer connection state is derived from the DTLS
+    /// transport of the peer connection at that time, so it has to be set up first.
+    fn handle_ice_connection_state_change(self: &Arc<Self>) {
+        let pci = Arc::downgrade(self);
+        self.ice_transport.on_connection_state_change(Box::new(
+            move |state: RTCIceTransportState| {
+                let cs = match state {
+                    RTCIceTransportState::New => RTCIceConnectionState::New,
+                    RTCIceTransportState::Checking => RTCIceConnectionState::Checking,
+                    RTCIceTransportState::Connected => RTCIceConnectionState::Connected,
+                    RTCIceTransportState::Completed => RTCIceConnectionState::Completed,
+                    RTCIceTransportState::Failed => RTCIceConnectionState::Failed,
+                    RTCIceTransportState::Disconnected => RTCIceConnectionState::Disconnected,
+                    RTCIceTransportState::Closed => RTCIceConnectionState::Closed,
+                    _ => {
+                        log::warn!("on_connection_state_change: unhandled ICE state: {}", state);
+                        return Box::pin(async {});
+                    }
+                };
 
-        let ice_connection_state = Arc::clone(&self.ice_connection_state);
-        let peer_connection_state = Arc::clone(&self.peer_connection_state);
-        let is_closed = Arc::clone(&self.is_closed);
-        let dtls_transport = Arc::clone(&self.dtls_transport);
-        let on_ice_connection_state_change_handler =
-            Arc::clone(&self.on_ice_connection_state_change_handler);
-        let on_peer_connection_state_change_handler =
-            Arc::clone(&self.on_peer_connection_state_change_handler);
-
-        ice_transport.on_connection_state_change(Box::new(move |state: RTCIceTransportState| {
-            let cs = match state {
-                RTCIceTransportState::New => RTCIceConnectionState::New,
-                RTCIceTransportState::Checking => RTCIceConnectionState::Checking,
-                RTCIceTransportState::Connected => RTCIceConnectionState::Connected,
-                RTCIceTransportState::Completed => RTCIceConnectionState::Completed,
-                RTCIceTransportState::Failed => RTCIceConnectionState::Failed,
-                RTCIceTransportState::Disconnected => RTCIceConnectionState::Disconnected,
-                RTCIceTransportState::Closed => RTCIceConnectionState::Closed,
-                _ => {
-                    log::warn!("on_connection_state_change: unhandled ICE state: {}", state);
-                    return Box::pin(async {});
-                }
+                let pci = pci.clone();
+                Box::pin(async move {
+                    let pci = match pci.upgrade() {
+                        Some(pci) => pci,
+                        None => return,
+                    };
+
+                    RTCPeerConnection::do_ice_connection_state_change(
+                        &pci.on_ice_connection_state_change_handler,
+                        &pci.ice_connection_state,
+                        cs,
+                    )
+                    .await;
+
+                    if cs == RTCIceConnectionState::Failed {
+                        // Only an ICE restart clears it, see ice_restarted
+                        let mut connection_error = pci.connection_error.lock();
+                        if connection_error.is_none() {
+                            *connection_error = Some(RTCConnectionError::IceFailed);
+                        }
+                    }
+
+                    RTCPeerConnection::update_connection_state(
+                        &pci.on_peer_connection_state_change_handler,
+                        &pci.is_closed,
+                        &pci.peer_connection_state,
+                        &pci.connection_error,
+                        &pci.connect_deadline,
+                        cs,
+                        pci.dtls_transport.state(),
+                    )
+                    .await;
+                })
+            },
+        ));
+    }
+
+    /// update_negotiated_codecs sets the codecs of each media section of the remote description
+    /// on the receiver of its transceiver, so they are known before any media arrives.
+    pub(super) async fn update_negotiated_codecs(&self, desc: &SessionDescription) -> Result<()> {
+        let rtp_transceivers = { self.rtp_transceivers.lock().await.clone() };
+        for media in &desc.media_descriptions {
+            let mid = match get_mid_value(media) {
+                Some(mid) if !mid.is_empty() => mid,
+                _ => continue,
             };
+            let kind = RTPCodecType::from(media.media_name.media.as_str());
+            if kind == RTPCodecType::Unspecified {
+                continue;
+            }
 
-            let ice_connection_state2 = Arc::clone(&ice_connection_state);
-            let on_ice_connection_state_change_handler2 =
-                Arc::clone(&on_ice_connection_state_change_handler);
-            let on_peer_connection_state_change_handler2 =
-                Arc::clone(&on_peer_connection_state_change_handler);
-            let is_closed2 = Arc::clone(&is_closed);
-            let dtls_transport_state = dtls_transport.state();
-            let peer_connection_state2 = Arc::clone(&peer_connection_state);
-            Box::pin(async move {
-                RTCPeerConnection::do_ice_connection_state_change(
-                    &on_ice_connection_state_change_handler2,
-                    &ice_connection_state2,
-                    cs,
-                )
-                .await;
-
-                RTCPeerConnection::update_connection_state(
-                    &on_peer_connection_state_change_handler2,
-                    &is_closed2,
-                    &peer_connection_state2,
-                    cs,
-                    dtls_transport_state,
-                )
-                .await;
-            })
-        }));
+            for t in &rtp_transceivers {
+                if t.mid().await != *mid {
+                    continue;
+                }
+                if let Some(receiver) = t.receiver().await {
+                    let codecs = self.media_engine.negotiate_codecs(media, kind)?;
+                    receiver.set_negotiated_codecs(codecs).await;
+                }
+                break;
+            }
+        }
 
-        ice_transport
+        Ok(())
     }
 
     /// has_local_description_changed returns whether local media (rtp_transceivers) has changed
@@ -1200,7 +1452,6 @@ impl PeerConnectionInternal {
 
         tokio::join!(
             self.ice_gatherer.collect_stats(&collector),
-            self.ice_transport.collect_stats(&collector),
             self.sctp_transport.collect_stats(&collector, stats_id),
             self.dtls_transport.collect_stats(&collector),
             self.media_engine.collect_stats(&collector),
@@ -1352,6 +1603,7 @@ impl PeerConnectionInternal {
             mid: String,
             rid: Option<String>,
             kind: &'static str,
+            media_source_id: String,
         }
         let mut track_infos = vec![];
         for transeiver in transceivers {
@@ -1376,13 +1628,18 @@ impl PeerConnectionInternal {
                 RTPCodecType::Video => "video",
             };
 
-            track_infos.push(TrackInfo {
-                track_id,
-                ssrc: sender.ssrc,
-                mid: mid.clone(),
-                rid: None,
-                kind,
-            });
+            // One entry per encoding, they all share the media source of the track.
+            let media_source_id = format!("RTCMediaSource{}_{}", capitalize(kind), track_id);
+            for encoding in sender.get_parameters().await.encodings {
+                track_infos.push(TrackInfo {
+                    track_id: track_id.clone(),
+                    ssrc: encoding.ssrc,
+                    mid: mid.clone(),
+                    rid: (!encoding.rid.is_empty()).then(|| encoding.rid),
+                    kind,
+                    media_source_id: media_source_id.clone(),
+                });
+            }
         }
 
         let stream_stats = self
@@ -1405,6 +1662,8 @@ impl PeerConnectionInternal {
                 packets_sent,
                 bytes_sent,
                 header_bytes_sent,
+                retransmitted_packets_sent,
+                retransmitted_bytes_sent,
                 nack_count,
                 remote_inbound_packets_received,
                 remote_inbound_packets_lost,
@@ -1416,6 +1675,8 @@ impl PeerConnectionInternal {
                 stats.packets_sent(),
                 stats.payload_bytes_sent(),
                 stats.header_bytes_sent(),
+                stats.retransmitted_packets_sent(),
+                stats.retransmitted_bytes_sent(),
                 stats.nacks_received(),
                 stats.remote_packets_received(),
                 stats.remote_total_lost(),
@@ -1431,6 +1692,7 @@ impl PeerConnectionInternal {
                 rid,
                 kind,
                 track_id: track_identifier,
+                media_source_id,
             } = info;
 
             collector.insert(
@@ -1444,9 +1706,14 @@ impl PeerConnectionInternal {
                     kind,
                     packets_sent,
                     mid,
+                    media_source_id,
                     rid,
                     header_bytes_sent,
                     bytes_sent,
+                    retransmitted_packets_sent,
+                    retransmitted_bytes_sent,
+                    quality_limitation_reason: (kind == "video").then(|| "none"),
+                    quality_limitation_resolution_changes: (kind == "video").then(|| 0),
                     nack_count,
 
                     fir_count: (info.kind == "video").then(|| stats.firs_received()),
@@ -1511,3 +1778,22 @@ fn capitalize(s: &str) -> String {
 
     result
 }
+
+/// unmarshal_ice_candidate parses the candidate attribute of an RTCIceCandidateInit,
+/// an empty candidate signals the end of candidates.
+pub(super) fn unmarshal_ice_candidate(
+    candidate: &RTCIceCandidateInit,
+) -> Result<Option<RTCIceCandidate>> {
+    let candidate_value = match candidate.candidate.strip_prefix("candidate:") {
+        Some(s) => s,
+        None => candidate.candidate.as_str(),
+    };
+
+    if candidate_value.is_empty() {
+        return Ok(None);
+    }
+
+    let candidate: Arc<dyn Candidate + Send + Sync> =
+        Arc::new(unmarshal_candidate(candidate_value)?);
+    Ok(Some(RTCIceCandidate::from(&candidate)))
+}
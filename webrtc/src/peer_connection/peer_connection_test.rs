@@ -1,19 +1,64 @@
 use super::*;
 
-use crate::api::media_engine::MIME_TYPE_VP8;
+use crate::api::interceptor_registry::configure_nack;
+use crate::api::media_engine::{MIME_TYPE_OPUS, MIME_TYPE_VP8};
 use crate::api::APIBuilder;
-use crate::ice_transport::ice_candidate_pair::RTCIceCandidatePair;
+use crate::ice_transport::ice_candidate_pair::RTCIceCandidatePairChange;
+use crate::ice_transport::ice_candidate_type::RTCIceCandidateType;
+use crate::ice_transport::ice_gatherer::RTCIceCandidateError;
+use crate::ice_transport::ice_server::RTCIceServer;
 use crate::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
+use crate::rtp_transceiver::RTCPFeedback;
 use crate::stats::StatsReportType;
 use bytes::Bytes;
+use ice::options::IceOptions;
+use interceptor::mock::mock_time::MockTime;
+use interceptor::nack::responder::Responder;
+use interceptor::registry::Registry;
+use interceptor::InterceptorBuilder;
 use media::Sample;
-use std::sync::atomic::AtomicU32;
-use tokio::time::Duration;
+use rtcp::source_description::{
+    SdesType, SourceDescription, SourceDescriptionChunk, SourceDescriptionItem,
+};
+use rtp::extension::audio_level_extension::AudioLevelExtension;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, AtomicUsize};
+use tokio::time::{Duration, Instant};
+use util::vnet::capture::Capture;
+use util::vnet::chunk::Chunk;
+use util::vnet::nat::NatType;
 use util::vnet::net::{Net, NetConfig};
-use util::vnet::router::{Router, RouterConfig};
+use util::vnet::router::{Nic, Router, RouterConfig};
 use waitgroup::WaitGroup;
 
 pub(crate) async fn create_vnet_pair(
+) -> Result<(RTCPeerConnection, RTCPeerConnection, Arc<Mutex<Router>>)> {
+    create_vnet_pair_with_settings(|_| {}).await
+}
+
+/// create_vnet_pair_with_settings is create_vnet_pair with `configure` applied to the setting
+/// engines of both peer connections.
+pub(crate) async fn create_vnet_pair_with_settings(
+    configure: impl Fn(&mut SettingEngine),
+) -> Result<(RTCPeerConnection, RTCPeerConnection, Arc<Mutex<Router>>)> {
+    create_vnet_pair_with_api(|mut setting_engine, _| {
+        configure(&mut setting_engine);
+
+        let mut media_engine = MediaEngine::default();
+        media_engine.register_default_codecs()?;
+        Ok(APIBuilder::new()
+            .with_setting_engine(setting_engine)
+            .with_media_engine(media_engine)
+            .build())
+    })
+    .await
+}
+
+/// create_vnet_pair_with_api is create_vnet_pair with the APIs of the peer connections built by
+/// `new_api`, which is given the vnet setting engine and whether the API is for the offerer.
+pub(crate) async fn create_vnet_pair_with_api(
+    new_api: impl Fn(SettingEngine, bool) -> Result<API>,
 ) -> Result<(RTCPeerConnection, RTCPeerConnection, Arc<Mutex<Router>>)> {
     // Create a root router
     let wan = Arc::new(Mutex::new(Router::new(RouterConfig {
@@ -77,21 +122,11 @@ pub(crate) async fn create_vnet_pair(
         w.start().await?;
     }
 
-    let mut offer_media_engine = MediaEngine::default();
-    offer_media_engine.register_default_codecs()?;
-    let offer_peer_connection = APIBuilder::new()
-        .with_setting_engine(offer_setting_engine)
-        .with_media_engine(offer_media_engine)
-        .build()
+    let offer_peer_connection = new_api(offer_setting_engine, true)?
         .new_peer_connection(RTCConfiguration::default())
         .await?;
 
-    let mut answer_media_engine = MediaEngine::default();
-    answer_media_engine.register_default_codecs()?;
-    let answer_peer_connection = APIBuilder::new()
-        .with_setting_engine(answer_setting_engine)
-        .with_media_engine(answer_media_engine)
-        .build()
+    let answer_peer_connection = new_api(answer_setting_engine, false)?
         .new_peer_connection(RTCConfiguration::default())
         .await?;
 
@@ -258,6 +293,61 @@ pub(crate) async fn until_connection_state(
     }));
 }
 
+#[tokio::test]
+async fn test_get_stats_ids_and_timestamps() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let clock = Arc::new(MockTime::default());
+    let start = SystemTime::now();
+    clock.set_now(start);
+    let mut s = SettingEngine::default();
+    s.set_stats_time_source(clock.clone());
+    let api = APIBuilder::new()
+        .with_media_engine(m)
+        .with_setting_engine(s)
+        .build();
+
+    let pc = api.new_peer_connection(RTCConfiguration::default()).await?;
+    pc.create_data_channel("first", None).await?;
+    pc.create_data_channel("second", None).await?;
+
+    let ids = |report: &StatsReport| {
+        let mut ids: Vec<String> = report.reports.keys().cloned().collect();
+        ids.sort();
+        ids
+    };
+
+    let first = pc.get_stats().await;
+    assert_eq!(first.timestamp, start);
+    assert!(first.monotonic_timestamp > Duration::ZERO);
+    assert!(first.reports.contains_key("DataChannel-0"));
+    assert!(first.reports.contains_key("DataChannel-1"));
+    assert!(first.reports.contains_key("RTPCodec-video-96"));
+
+    // The wall clock goes backwards, the monotonic timestamp still increases
+    clock.set_now(start - Duration::from_secs(3600));
+    let second = pc.get_stats().await;
+    assert_eq!(second.timestamp, start - Duration::from_secs(3600));
+    assert!(second.monotonic_timestamp > first.monotonic_timestamp);
+    assert_eq!(ids(&second), ids(&first));
+
+    // and it advances with the time elapsed, whatever the wall clock says
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let third = pc.get_stats().await;
+    assert_eq!(third.timestamp, start - Duration::from_secs(3600));
+    assert!(
+        third.monotonic_timestamp >= second.monotonic_timestamp + Duration::from_millis(50),
+        "{:?} after {:?}",
+        third.monotonic_timestamp,
+        second.monotonic_timestamp
+    );
+    assert_eq!(ids(&third), ids(&first));
+
+    pc.close().await?;
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_get_stats() -> Result<()> {
     let mut m = MediaEngine::default();
@@ -285,7 +375,7 @@ async fn test_get_stats() -> Result<()> {
         .sctp()
         .transport()
         .ice_transport()
-        .on_selected_candidate_pair_change(Box::new(move |_: RTCIceCandidatePair| {
+        .on_selected_candidate_pair_change(Box::new(move |_: RTCIceCandidatePairChange| {
             sender_called_candidate_change2.store(1, Ordering::SeqCst);
             Box::pin(async {})
         }));
@@ -346,10 +436,29 @@ async fn test_get_stats() -> Result<()> {
         Some(StatsReportType::Transport(ice_transport_stats)) => {
             assert!(ice_transport_stats.bytes_received > 0);
             assert!(ice_transport_stats.bytes_sent > 0);
+            let changes = &ice_transport_stats.selected_candidate_pair_changes;
+            assert_eq!(changes.len(), 1);
+            assert_eq!(changes[0].reason, "initial-nomination");
+            assert_eq!(
+                Some(&changes[0].candidate_pair_id),
+                ice_transport_stats.selected_candidate_pair_id.as_ref()
+            );
         }
         Some(_other) => panic!("found the wrong type"),
         None => panic!("missed it"),
     }
+    let selected_pair_stats = offer_stats
+        .reports
+        .values()
+        .find_map(|v| match v {
+            StatsReportType::CandidatePair(d) if d.nominated => Some(d),
+            _ => None,
+        })
+        .expect("Should have produced a stat for the selected candidate pair");
+    assert!(selected_pair_stats.requests_sent > 0);
+    assert!(selected_pair_stats.responses_received > 0);
+    assert!(selected_pair_stats.current_round_trip_time > 0.0);
+    assert!(pc_offer.current_rtt().await.is_some());
     let outbound_stats = offer_stats
         .reports
         .values()
@@ -381,3 +490,1792 @@ async fn test_get_stats() -> Result<()> {
 
     Ok(())
 }
+
+/// SharedResponder hands out the same NACK responder, so a test can look at its counters.
+struct SharedResponder(Arc<Responder>);
+
+impl InterceptorBuilder for SharedResponder {
+    fn build(
+        &self,
+        _id: &str,
+    ) -> std::result::Result<Arc<dyn Interceptor + Send + Sync>, interceptor::Error> {
+        Ok(Arc::clone(&self.0) as Arc<dyn Interceptor + Send + Sync>)
+    }
+}
+
+#[tokio::test]
+async fn test_get_stats_nack_retransmissions() -> Result<()> {
+    let responder = Responder::builder().build_responder();
+    let responder2 = Arc::clone(&responder);
+    let (mut pc_offer, mut pc_answer, wan) =
+        create_vnet_pair_with_api(move |setting_engine, is_offer| {
+            let mut media_engine = MediaEngine::default();
+            media_engine.register_default_codecs()?;
+            let mut registry = Registry::new();
+            if is_offer {
+                media_engine.register_feedback(
+                    RTCPFeedback {
+                        typ: "nack".to_owned(),
+                        parameter: "".to_owned(),
+                    },
+                    RTPCodecType::Video,
+                );
+                registry.add(Box::new(SharedResponder(Arc::clone(&responder2))));
+            } else {
+                registry = configure_nack(registry, &mut media_engine);
+            }
+
+            Ok(APIBuilder::new()
+                .with_setting_engine(setting_engine)
+                .with_media_engine(media_engine)
+                .with_interceptor_registry(registry)
+                .build())
+        })
+        .await?;
+
+    let track = Arc::new(TrackLocalStaticSample::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    let sender = pc_offer.add_track(track.clone()).await?;
+    // NACKs only reach the responder while the RTCP of the sender is read
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 1500];
+        while sender.read(&mut buf).await.is_ok() {}
+    });
+
+    let (connected_tx, mut connected_rx) = mpsc::channel::<()>(1);
+    pc_answer.on_track(Box::new(
+        move |track: Option<Arc<TrackRemote>>, _: Option<Arc<RTCRtpReceiver>>| {
+            if let Some(track) = track {
+                let connected_tx = connected_tx.clone();
+                tokio::spawn(async move {
+                    let _ = connected_tx.send(()).await;
+                    while track.read_rtp().await.is_ok() {}
+                });
+            }
+            Box::pin(async {})
+        },
+    ));
+
+    signal_pair(&mut pc_offer, &mut pc_answer).await?;
+
+    let write_samples = |count: usize| {
+        let track = Arc::clone(&track);
+        async move {
+            for _ in 0..count {
+                track
+                    .write_sample(&Sample {
+                        data: Bytes::from_static(b"\xDE\xAD\xBE\xEF\xAA"),
+                        duration: Duration::from_millis(20),
+                        ..Default::default()
+                    })
+                    .await?;
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            Result::<()>::Ok(())
+        }
+    };
+    while connected_rx.try_recv().is_err() {
+        write_samples(1).await?;
+    }
+
+    // Lose every fifth RTP packet of the offerer while the losses are on
+    let lossy = Arc::new(AtomicBool::new(true));
+    {
+        let lossy = Arc::clone(&lossy);
+        let sent = AtomicUsize::new(0);
+        let w = wan.lock().await;
+        w.add_chunk_filter(Box::new(move |c: &(dyn Chunk + Send + Sync)| {
+            let data = c.user_data();
+            let is_rtp =
+                data.len() > 1 && (128..192).contains(&data[0]) && !(192..224).contains(&data[1]);
+            !lossy.load(Ordering::SeqCst)
+                || c.source_addr().ip().to_string() != "1.2.3.4"
+                || !is_rtp
+                || sent.fetch_add(1, Ordering::SeqCst) % 5 != 4
+        }))
+        .await;
+    }
+    write_samples(50).await?;
+    lossy.store(false, Ordering::SeqCst);
+    // Let the outstanding NACKs be answered
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let offer_stats = pc_offer.get_stats().await;
+    let outbound_stats = offer_stats
+        .reports
+        .values()
+        .find_map(|v| match v {
+            StatsReportType::OutboundRTP(d) => Some(d),
+            _ => None,
+        })
+        .expect("Should have produced an RTP Outbound stat");
+    let (retransmitted_packets, retransmitted_bytes) = responder
+        .retransmissions(outbound_stats.ssrc)
+        .await
+        .expect("the responder should know the stream");
+    assert!(outbound_stats.nack_count > 0);
+    assert!(outbound_stats.retransmitted_packets_sent > 0);
+    assert_eq!(
+        outbound_stats.retransmitted_packets_sent,
+        retransmitted_packets
+    );
+    assert_eq!(outbound_stats.retransmitted_bytes_sent, retransmitted_bytes);
+    assert_eq!(outbound_stats.quality_limitation_reason, Some("none"));
+    assert_eq!(
+        outbound_stats.media_source_id,
+        format!("RTCMediaSourceVideo_{}", track.id())
+    );
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+/// gather_candidates sets the local description and collects the trickled
+/// candidates until gathering completes.
+async fn gather_candidates(
+    pc: &RTCPeerConnection,
+    desc: RTCSessionDescription,
+) -> Result<Vec<RTCIceCandidateInit>> {
+    let (candidate_tx, mut candidate_rx) = mpsc::unbounded_channel();
+    pc.on_ice_candidate(Box::new(move |candidate: Option<RTCIceCandidate>| {
+        let _ = candidate_tx.send(candidate);
+        Box::pin(async {})
+    }));
+
+    pc.set_local_description(desc).await?;
+
+    let mut candidates = vec![];
+    while let Some(Some(candidate)) = candidate_rx.recv().await {
+        candidates.push(candidate.to_json()?);
+    }
+
+    Ok(candidates)
+}
+
+#[tokio::test]
+async fn test_add_ice_candidate_before_remote_description() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut pc_offer, mut pc_answer) = new_pair(&api).await?;
+
+    let wg = WaitGroup::new();
+    until_connection_state(&mut pc_offer, &wg, RTCPeerConnectionState::Connected).await;
+    until_connection_state(&mut pc_answer, &wg, RTCPeerConnectionState::Connected).await;
+
+    pc_offer
+        .create_data_channel("initial_data_channel", None)
+        .await?;
+
+    // The descriptions are signaled without candidates, every candidate is
+    // trickled and delivered to the remote peer before its description.
+    let offer = pc_offer.create_offer(None).await?;
+    let offer_candidates = gather_candidates(&pc_offer, offer.clone()).await?;
+    assert!(!offer_candidates.is_empty());
+    for candidate in offer_candidates {
+        pc_answer.add_ice_candidate(candidate).await?;
+    }
+    pc_answer.set_remote_description(offer).await?;
+    assert!(pc_answer
+        .internal
+        .pending_remote_candidates
+        .lock()
+        .await
+        .is_empty());
+
+    let answer = pc_answer.create_answer(None).await?;
+    let answer_candidates = gather_candidates(&pc_answer, answer.clone()).await?;
+    assert!(!answer_candidates.is_empty());
+    for candidate in answer_candidates {
+        pc_offer.add_ice_candidate(candidate).await?;
+    }
+    pc_offer.set_remote_description(answer).await?;
+    assert!(pc_offer
+        .internal
+        .pending_remote_candidates
+        .lock()
+        .await
+        .is_empty());
+
+    tokio::time::timeout(Duration::from_secs(10), wg.wait())
+        .await
+        .expect("peer connections should connect with queued candidates");
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_add_ice_candidate_pending_queue() -> Result<()> {
+    let api = APIBuilder::new().build();
+    let pc = api.new_peer_connection(RTCConfiguration::default()).await?;
+
+    let candidate = RTCIceCandidateInit {
+        candidate: "candidate:1 1 udp 2130706431 192.168.1.1 53165 typ host".to_owned(),
+        ..Default::default()
+    };
+
+    let result = pc
+        .add_ice_candidate(RTCIceCandidateInit {
+            candidate: "candidate:invalid".to_owned(),
+            ..Default::default()
+        })
+        .await;
+    assert!(result.is_err(), "malformed candidates must not be queued");
+
+    for _ in 0..MAX_PENDING_REMOTE_CANDIDATES {
+        pc.add_ice_candidate(candidate.clone()).await?;
+    }
+    let result = pc.add_ice_candidate(candidate.clone()).await;
+    assert!(matches!(
+        result,
+        Err(Error::ErrPeerConnPendingCandidatesFull)
+    ));
+
+    pc.close().await?;
+    assert!(pc
+        .internal
+        .pending_remote_candidates
+        .lock()
+        .await
+        .is_empty());
+
+    let result = pc.add_ice_candidate(candidate).await;
+    assert!(matches!(result, Err(Error::ErrConnectionClosed)));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_add_ice_candidate_unknown_media_section() -> Result<()> {
+    let api = APIBuilder::new().build();
+    let (pc_offer, pc_answer) = new_pair(&api).await?;
+
+    pc_offer
+        .create_data_channel("initial_data_channel", None)
+        .await?;
+    let offer = pc_offer.create_offer(None).await?;
+    pc_answer.set_remote_description(offer).await?;
+
+    let candidate = "candidate:1 1 udp 2130706431 192.168.1.1 53165 typ host".to_owned();
+
+    pc_answer
+        .add_ice_candidate(RTCIceCandidateInit {
+            candidate: candidate.clone(),
+            sdp_mid: Some("0".to_owned()),
+            ..Default::default()
+        })
+        .await?;
+
+    let result = pc_answer
+        .add_ice_candidate(RTCIceCandidateInit {
+            candidate: candidate.clone(),
+            sdp_mid: Some("unknown".to_owned()),
+            ..Default::default()
+        })
+        .await;
+    assert!(matches!(
+        result,
+        Err(Error::ErrPeerConnCandidateMediaSectionNotFound)
+    ));
+
+    let result = pc_answer
+        .add_ice_candidate(RTCIceCandidateInit {
+            candidate,
+            sdp_mid: None,
+            sdp_mline_index: Some(1),
+            ..Default::default()
+        })
+        .await;
+    assert!(matches!(
+        result,
+        Err(Error::ErrPeerConnCandidateMediaSectionNotFound)
+    ));
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+struct RejectAuthHandler;
+
+impl turn::auth::AuthHandler for RejectAuthHandler {
+    fn auth_handle(
+        &self,
+        username: &str,
+        _realm: &str,
+        _src_addr: SocketAddr,
+    ) -> std::result::Result<Vec<u8>, turn::Error> {
+        Err(turn::Error::Other(format!("unknown user {}", username)))
+    }
+}
+
+const TURN_USERNAME: &str = "webrtc-rs";
+const TURN_PASSWORD: &str = "secret";
+
+struct StaticAuthHandler;
+
+impl turn::auth::AuthHandler for StaticAuthHandler {
+    fn auth_handle(
+        &self,
+        username: &str,
+        realm: &str,
+        _src_addr: SocketAddr,
+    ) -> std::result::Result<Vec<u8>, turn::Error> {
+        if username != TURN_USERNAME {
+            return Err(turn::Error::Other(format!("unknown user {}", username)));
+        }
+        Ok(turn::auth::generate_auth_key(
+            username,
+            realm,
+            TURN_PASSWORD,
+        ))
+    }
+}
+
+/// create_stun_vnet_pair creates two peer connections behind full cone NATs of separate LANs,
+/// configured with a STUN server on the WAN. Every packet crossing the WAN is delayed by
+/// `wan_delay`.
+async fn create_stun_vnet_pair(
+    wan_delay: Duration,
+) -> Result<(
+    RTCPeerConnection,
+    RTCPeerConnection,
+    Arc<Mutex<Router>>,
+    turn::server::Server,
+)> {
+    create_turn_vnet_pair(wan_delay, false).await
+}
+
+/// create_turn_vnet_pair creates the peer connections of create_stun_vnet_pair, configured
+/// with a TURN server on the WAN as well if `turn` is set.
+async fn create_turn_vnet_pair(
+    wan_delay: Duration,
+    turn: bool,
+) -> Result<(
+    RTCPeerConnection,
+    RTCPeerConnection,
+    Arc<Mutex<Router>>,
+    turn::server::Server,
+)> {
+    let wan = Arc::new(Mutex::new(Router::new(RouterConfig {
+        cidr: "0.0.0.0/0".to_owned(),
+        min_delay: wan_delay,
+        ..Default::default()
+    })?));
+
+    let stun_vnet = Arc::new(Net::new(Some(NetConfig {
+        static_ips: vec!["1.2.3.4".to_owned()],
+        ..Default::default()
+    })));
+    let nic = stun_vnet.get_nic()?;
+    {
+        let mut w = wan.lock().await;
+        w.add_net(Arc::clone(&nic)).await?;
+    }
+    {
+        let n = nic.lock().await;
+        n.set_router(Arc::clone(&wan)).await?;
+    }
+
+    let mut peer_connections = vec![];
+    for (global_ip, local_ip) in [("27.1.1.1", "192.168.0.1"), ("28.1.1.1", "10.2.0.1")] {
+        let lan = Arc::new(Mutex::new(Router::new(RouterConfig {
+            static_ips: vec![global_ip.to_owned()],
+            cidr: format!("{local_ip}/24"),
+            nat_type: Some(NatType::full_cone()),
+            ..Default::default()
+        })?));
+        {
+            let mut w = wan.lock().await;
+            w.add_router(Arc::clone(&lan)).await?;
+        }
+        {
+            let l = lan.lock().await;
+            l.set_router(Arc::clone(&wan)).await?;
+        }
+
+        let vnet = Arc::new(Net::new(Some(NetConfig {
+            static_ips: vec![local_ip.to_owned()],
+            ..Default::default()
+        })));
+        let nic = vnet.get_nic()?;
+        {
+            let mut l = lan.lock().await;
+            l.add_net(Arc::clone(&nic)).await?;
+        }
+        {
+            let n = nic.lock().await;
+            n.set_router(Arc::clone(&lan)).await?;
+        }
+
+        let mut setting_engine = SettingEngine::default();
+        setting_engine.set_vnet(Some(vnet));
+        setting_engine.set_ice_timeouts(
+            Some(Duration::from_secs(1)),
+            Some(Duration::from_secs(1)),
+            Some(Duration::from_millis(200)),
+        );
+        if turn {
+            // Relay pairs must be nominatable before the failed timeout
+            setting_engine.set_relay_acceptance_min_wait(Some(Duration::from_millis(0)));
+        }
+
+        let mut media_engine = MediaEngine::default();
+        media_engine.register_default_codecs()?;
+        let peer_connection = APIBuilder::new()
+            .with_setting_engine(setting_engine)
+            .with_media_engine(media_engine)
+            .build()
+            .new_peer_connection(RTCConfiguration {
+                ice_servers: vec![RTCIceServer {
+                    urls: if turn {
+                        vec![
+                            "stun:1.2.3.4:3478".to_owned(),
+                            "turn:1.2.3.4:3478?transport=udp".to_owned(),
+                        ]
+                    } else {
+                        vec!["stun:1.2.3.4:3478".to_owned()]
+                    },
+                    username: TURN_USERNAME.to_owned(),
+                    credential: TURN_PASSWORD.to_owned(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })
+            .await?;
+        peer_connections.push(peer_connection);
+    }
+
+    {
+        let mut w = wan.lock().await;
+        w.start().await?;
+    }
+
+    let conn = stun_vnet
+        .bind(SocketAddr::from_str("1.2.3.4:3478").unwrap())
+        .await?;
+    let server = turn::server::Server::new(turn::server::config::ServerConfig {
+        conn_configs: vec![turn::server::config::ConnConfig {
+            conn,
+            relay_addr_generator: Box::new(
+                turn::relay::relay_static::RelayAddressGeneratorStatic {
+                    relay_address: IpAddr::from_str("1.2.3.4").unwrap(),
+                    address: "0.0.0.0".to_owned(),
+                    net: stun_vnet,
+                },
+            ),
+        }],
+        realm: "webrtc.rs".to_owned(),
+        auth_handler: if turn {
+            Arc::new(StaticAuthHandler)
+        } else {
+            Arc::new(RejectAuthHandler)
+        },
+        channel_bind_timeout: Duration::from_secs(0),
+    })
+    .await
+    .map_err(|err| Error::new(err.to_string()))?;
+
+    let pc_answer = peer_connections.pop().unwrap();
+    let pc_offer = peer_connections.pop().unwrap();
+
+    Ok((pc_offer, pc_answer, wan, server))
+}
+
+#[tokio::test]
+async fn test_prepare_ice() -> Result<()> {
+    const WAN_DELAY: Duration = Duration::from_millis(100);
+
+    let (mut pc_offer, mut pc_answer, wan, server) = create_stun_vnet_pair(WAN_DELAY).await?;
+
+    let wg = WaitGroup::new();
+    until_connection_state(&mut pc_offer, &wg, RTCPeerConnectionState::Connected).await;
+    until_connection_state(&mut pc_answer, &wg, RTCPeerConnectionState::Connected).await;
+
+    // Only the offerer gathers ahead
+    pc_offer
+        .prepare_ice(Some(RTCIcePrepareOptions {
+            wait_gathering_complete: true,
+            ..Default::default()
+        }))
+        .await?;
+    pc_offer
+        .create_data_channel("initial_data_channel", None)
+        .await?;
+
+    let start = Instant::now();
+    let offer = pc_offer.create_offer(None).await?;
+    pc_offer.set_local_description(offer.clone()).await?;
+    let prepared = start.elapsed();
+    assert!(offer.sdp.contains("typ srflx"), "{}", offer.sdp);
+    assert!(offer.sdp.contains("a=end-of-candidates"), "{}", offer.sdp);
+
+    // The answerer has to wait for its STUN transaction before its answer is complete
+    pc_answer.set_remote_description(offer).await?;
+    let start = Instant::now();
+    let answer = pc_answer.create_answer(None).await?;
+    assert!(!answer.sdp.contains("typ srflx"), "{}", answer.sdp);
+    let mut answer_gathering_complete = pc_answer.gathering_complete_promise().await;
+    pc_answer.set_local_description(answer).await?;
+    let _ = answer_gathering_complete.recv().await;
+    let unprepared = start.elapsed();
+    let answer = pc_answer
+        .local_description()
+        .await
+        .ok_or(Error::new("non local description".to_owned()))?;
+    assert!(answer.sdp.contains("typ srflx"), "{}", answer.sdp);
+
+    assert!(
+        prepared < WAN_DELAY && unprepared >= WAN_DELAY,
+        "prepared offer took {:?}, unprepared answer took {:?}",
+        prepared,
+        unprepared
+    );
+
+    // Host candidates of the other LAN are unreachable, the pre-gathered server reflexive
+    // candidate of the offerer must be usable with the credentials from its offer.
+    pc_offer.set_remote_description(answer).await?;
+    tokio::time::timeout(Duration::from_secs(10), wg.wait())
+        .await
+        .expect("peer connections didn't connect");
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+    server
+        .close()
+        .await
+        .map_err(|err| Error::new(err.to_string()))?;
+    {
+        let mut w = wan.lock().await;
+        w.stop().await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_prepare_ice_turn() -> Result<()> {
+    const WAN_DELAY: Duration = Duration::from_millis(100);
+
+    let (mut pc_offer, mut pc_answer, wan, server) = create_turn_vnet_pair(WAN_DELAY, true).await?;
+
+    let wg = WaitGroup::new();
+    until_connection_state(&mut pc_offer, &wg, RTCPeerConnectionState::Connected).await;
+    until_connection_state(&mut pc_answer, &wg, RTCPeerConnectionState::Connected).await;
+
+    // The offerer allocates its relay ahead and only uses relay candidates
+    let start = Instant::now();
+    pc_offer
+        .prepare_ice(Some(RTCIcePrepareOptions {
+            ice_transport_policy: RTCIceTransportPolicy::Relay,
+            wait_gathering_complete: true,
+            ice_gathering_timeout: Some(Duration::from_secs(5)),
+        }))
+        .await?;
+    let gathering = start.elapsed();
+    // An allocation takes two round trips, the first one is rejected for authentication
+    assert!(gathering >= 2 * WAN_DELAY, "gathering took {:?}", gathering);
+    assert_eq!(
+        pc_offer.get_configuration().await.ice_transport_policy,
+        RTCIceTransportPolicy::Relay
+    );
+    pc_offer
+        .create_data_channel("initial_data_channel", None)
+        .await?;
+
+    let start = Instant::now();
+    let offer = pc_offer.create_offer(None).await?;
+    pc_offer.set_local_description(offer.clone()).await?;
+    let prepared = start.elapsed();
+    assert!(prepared < WAN_DELAY, "prepared offer took {:?}", prepared);
+    assert!(offer.sdp.contains("typ relay"), "{}", offer.sdp);
+    assert!(!offer.sdp.contains("typ host"), "{}", offer.sdp);
+    assert!(offer.sdp.contains("a=end-of-candidates"), "{}", offer.sdp);
+
+    pc_answer.set_remote_description(offer).await?;
+    let answer = pc_answer.create_answer(None).await?;
+    let mut answer_gathering_complete = pc_answer.gathering_complete_promise().await;
+    pc_answer.set_local_description(answer).await?;
+    let _ = answer_gathering_complete.recv().await;
+    let answer = pc_answer
+        .local_description()
+        .await
+        .ok_or(Error::new("non local description".to_owned()))?;
+    assert!(answer.sdp.contains("typ relay"), "{}", answer.sdp);
+
+    // The allocation made ahead relays the checks with the credentials of the offer
+    pc_offer.set_remote_description(answer).await?;
+    tokio::time::timeout(Duration::from_secs(10), wg.wait())
+        .await
+        .expect("peer connections didn't connect");
+
+    let pair = pc_offer
+        .internal
+        .ice_transport
+        .get_selected_candidate_pair()
+        .await
+        .expect("no selected candidate pair");
+    assert_eq!(pair.local().typ, RTCIceCandidateType::Relay);
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+    server
+        .close()
+        .await
+        .map_err(|err| Error::new(err.to_string()))?;
+    {
+        let mut w = wan.lock().await;
+        w.stop().await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_non_trickle_ice() -> Result<()> {
+    let (mut pc_offer, mut pc_answer, wan, server) =
+        create_stun_vnet_pair(Duration::from_millis(20)).await?;
+
+    let wg = WaitGroup::new();
+    until_connection_state(&mut pc_offer, &wg, RTCPeerConnectionState::Connected).await;
+    until_connection_state(&mut pc_answer, &wg, RTCPeerConnectionState::Connected).await;
+
+    pc_offer
+        .create_data_channel("initial_data_channel", None)
+        .await?;
+
+    // Candidates are only exchanged through the descriptions, add_ice_candidate is never called
+    let offer = pc_offer
+        .create_offer(Some(RTCOfferOptions {
+            ice_trickle_mode: RTCIceTrickleMode::Disabled,
+            ..Default::default()
+        }))
+        .await?;
+    assert!(offer.sdp.contains("typ srflx"), "{}", offer.sdp);
+    assert!(offer.sdp.contains("a=end-of-candidates"), "{}", offer.sdp);
+    assert!(!offer.sdp.contains("a=ice-options"), "{}", offer.sdp);
+    pc_offer.set_local_description(offer.clone()).await?;
+    pc_answer.set_remote_description(offer).await?;
+
+    // The offerer doesn't trickle, so the answerer doesn't expect more candidates
+    let agent = pc_answer.internal.ice_transport.gatherer.get_agent().await;
+    assert!(!agent.unwrap().get_remote_options().trickle);
+
+    let answer = pc_answer
+        .create_answer(Some(RTCAnswerOptions {
+            ice_trickle_mode: RTCIceTrickleMode::Disabled,
+            ..Default::default()
+        }))
+        .await?;
+    assert!(answer.sdp.contains("typ srflx"), "{}", answer.sdp);
+    assert!(answer.sdp.contains("a=end-of-candidates"), "{}", answer.sdp);
+    pc_answer.set_local_description(answer.clone()).await?;
+    pc_offer.set_remote_description(answer).await?;
+
+    tokio::time::timeout(Duration::from_secs(10), wg.wait())
+        .await
+        .expect("peer connections didn't connect");
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+    server
+        .close()
+        .await
+        .map_err(|err| Error::new(err.to_string()))?;
+    {
+        let mut w = wan.lock().await;
+        w.stop().await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_non_trickle_ice_gathering_timeout() -> Result<()> {
+    let (pc_offer, pc_answer, wan, server) =
+        create_stun_vnet_pair(Duration::from_millis(500)).await?;
+
+    pc_offer
+        .create_data_channel("initial_data_channel", None)
+        .await?;
+
+    // The STUN transaction can't complete in time, the offer gets the host candidates only
+    let start = Instant::now();
+    let offer = pc_offer
+        .create_offer(Some(RTCOfferOptions {
+            ice_trickle_mode: RTCIceTrickleMode::Half,
+            ice_gathering_timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        }))
+        .await?;
+    assert!(start.elapsed() < Duration::from_millis(500));
+    assert!(offer.sdp.contains("typ host"), "{}", offer.sdp);
+    assert!(!offer.sdp.contains("typ srflx"), "{}", offer.sdp);
+    assert!(!offer.sdp.contains("a=end-of-candidates"), "{}", offer.sdp);
+    assert_eq!(
+        pc_offer.ice_gathering_state(),
+        RTCIceGatheringState::Gathering
+    );
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+    server
+        .close()
+        .await
+        .map_err(|err| Error::new(err.to_string()))?;
+    {
+        let mut w = wan.lock().await;
+        w.stop().await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ice_options_negotiation() -> Result<()> {
+    let new_peer_connection = |renomination: bool| async move {
+        let mut m = MediaEngine::default();
+        m.register_default_codecs()?;
+        let mut s = SettingEngine::default();
+        s.set_ice_renomination(renomination);
+        let api = APIBuilder::new()
+            .with_media_engine(m)
+            .with_setting_engine(s)
+            .build();
+        api.new_peer_connection(RTCConfiguration::default()).await
+    };
+
+    let pc_offer = new_peer_connection(true).await?;
+    let pc_answer = new_peer_connection(false).await?;
+
+    pc_offer
+        .create_data_channel("initial_data_channel", None)
+        .await?;
+
+    let offer = pc_offer.create_offer(None).await?;
+    assert!(
+        offer.sdp.contains("a=ice-options:trickle renomination"),
+        "{}",
+        offer.sdp
+    );
+    pc_offer.set_local_description(offer.clone()).await?;
+    pc_answer.set_remote_description(offer).await?;
+
+    let answer = pc_answer.create_answer(None).await?;
+    assert!(
+        answer.sdp.contains("a=ice-options:trickle\r\n"),
+        "{}",
+        answer.sdp
+    );
+    pc_answer.set_local_description(answer.clone()).await?;
+    pc_offer.set_remote_description(answer).await?;
+
+    // Renomination is only used by the offerer, the answerer doesn't support it
+    let offer_agent = pc_offer.internal.ice_transport.gatherer.get_agent().await;
+    let offer_agent = offer_agent.unwrap();
+    assert!(offer_agent.get_local_options().renomination);
+    assert_eq!(
+        offer_agent.get_remote_options(),
+        IceOptions {
+            trickle: true,
+            renomination: false,
+            ice2: false,
+        }
+    );
+
+    let answer_agent = pc_answer.internal.ice_transport.gatherer.get_agent().await;
+    let answer_agent = answer_agent.unwrap();
+    assert!(!answer_agent.get_local_options().renomination);
+    assert!(answer_agent.get_remote_options().renomination);
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_set_configuration_ice_transport_policy() -> Result<()> {
+    let (pc_offer, pc_answer, wan) = create_vnet_pair().await?;
+
+    pc_offer
+        .create_data_channel("initial_data_channel", None)
+        .await?;
+    let non_trickle = || {
+        Some(RTCOfferOptions {
+            ice_trickle_mode: RTCIceTrickleMode::Disabled,
+            ..Default::default()
+        })
+    };
+    let offer = pc_offer.create_offer(non_trickle()).await?;
+    assert!(offer.sdp.contains("typ host"), "{}", offer.sdp);
+    pc_offer.set_local_description(offer).await?;
+
+    assert_eq!(
+        pc_offer
+            .set_configuration(RTCConfiguration {
+                bundle_policy: RTCBundlePolicy::MaxCompat,
+                ..Default::default()
+            })
+            .await
+            .expect_err("the bundle policy cannot change"),
+        Error::ErrModifyingBundlePolicy
+    );
+    pc_offer
+        .set_configuration(RTCConfiguration {
+            ice_transport_policy: RTCIceTransportPolicy::Relay,
+            ..Default::default()
+        })
+        .await?;
+    assert_eq!(
+        pc_offer.get_configuration().await.ice_transport_policy,
+        RTCIceTransportPolicy::Relay
+    );
+
+    // The gathered candidates stay in use until the next restart.
+    let offer = pc_offer.create_offer(non_trickle()).await?;
+    assert!(offer.sdp.contains("typ host"), "{}", offer.sdp);
+
+    // Without TURN servers there is nothing left to gather under the relay policy.
+    let offer = pc_offer
+        .create_offer(Some(RTCOfferOptions {
+            ice_restart: true,
+            ice_trickle_mode: RTCIceTrickleMode::Disabled,
+            ..Default::default()
+        }))
+        .await?;
+    assert!(!offer.sdp.contains("a=candidate"), "{}", offer.sdp);
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+    {
+        let mut w = wan.lock().await;
+        w.stop().await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_close_releases_tasks() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    for i in 0..100 {
+        let (mut pc_offer, mut pc_answer) = new_pair(&api).await?;
+
+        let wg = WaitGroup::new();
+        until_connection_state(&mut pc_offer, &wg, RTCPeerConnectionState::Connected).await;
+        until_connection_state(&mut pc_answer, &wg, RTCPeerConnectionState::Connected).await;
+
+        signal_pair(&mut pc_offer, &mut pc_answer).await?;
+        tokio::time::timeout(Duration::from_secs(10), wg.wait())
+            .await
+            .expect("peer connections didn't connect");
+
+        let done_offer = pc_offer.done();
+        let done_answer = pc_answer.done();
+        let tasks = [
+            pc_offer.internal.tasks.clone(),
+            pc_answer.internal.tasks.clone(),
+        ];
+        let internals = [
+            Arc::downgrade(&pc_offer.internal),
+            Arc::downgrade(&pc_answer.internal),
+        ];
+        close_pair_now(&pc_offer, &pc_answer).await;
+
+        // close already waited for the tasks, done resolves right away
+        tokio::time::timeout(Duration::from_millis(100), async {
+            done_offer.await;
+            done_answer.await;
+        })
+        .await
+        .expect("done didn't resolve after close");
+        assert!(
+            tasks.iter().all(|t| t.is_empty()),
+            "iteration {}: tasks still running after close",
+            i
+        );
+
+        drop(pc_offer);
+        drop(pc_answer);
+
+        // The tasks spawned by the peer connection hold on to its internals, so they are only
+        // released once those tasks are gone. Only tasks woken by close to exit, like SCTP
+        // timers, may still be alive, they are gone the next time they are polled.
+        let released = tokio::time::timeout(Duration::from_secs(1), async {
+            while internals.iter().any(|w| w.upgrade().is_some()) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await;
+        assert!(
+            released.is_ok(),
+            "iteration {}: peer connection still referenced after close",
+            i
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_peer_connection_simulcast_layers_from_rtcp_sdes() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    for uri in [
+        ::sdp::extmap::SDES_MID_URI,
+        ::sdp::extmap::SDES_RTP_STREAM_ID_URI,
+    ] {
+        m.register_header_extension(
+            RTCRtpHeaderExtensionCapability {
+                uri: uri.to_owned(),
+            },
+            RTPCodecType::Video,
+            None,
+        )?;
+    }
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (pc_offer, pc_answer) = new_pair(&api).await?;
+
+    let track = Arc::new(TrackLocalStaticSample::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    pc_offer
+        .add_track(track as Arc<dyn TrackLocal + Send + Sync>)
+        .await?;
+
+    let (rid_tx, mut rid_rx) = mpsc::channel(2);
+    pc_answer.on_track(Box::new(
+        move |track: Option<Arc<TrackRemote>>, _: Option<Arc<RTCRtpReceiver>>| {
+            if let Some(track) = track {
+                let _ = rid_tx.try_send(track.rid().to_owned());
+            }
+            Box::pin(async {})
+        },
+    ));
+
+    let offer = pc_offer.create_offer(None).await?;
+    let mut offer_gathering_complete = pc_offer.gathering_complete_promise().await;
+    pc_offer.set_local_description(offer).await?;
+    let _ = offer_gathering_complete.recv().await;
+
+    // Announce two layers by RID only, their SSRCs are not declared
+    let offer = pc_offer.local_description().await.unwrap();
+    let mut sdp: String = offer
+        .sdp
+        .split("\r\n")
+        .filter(|line| !line.is_empty() && !line.starts_with("a=ssrc"))
+        .map(|line| format!("{}\r\n", line))
+        .collect();
+    sdp += "a=rid:a send\r\na=rid:b send\r\na=simulcast:send a;b\r\n";
+    pc_answer
+        .set_remote_description(RTCSessionDescription::offer(sdp)?)
+        .await?;
+
+    let answer = pc_answer.create_answer(None).await?;
+    let mut answer_gathering_complete = pc_answer.gathering_complete_promise().await;
+    pc_answer.set_local_description(answer).await?;
+    let _ = answer_gathering_complete.recv().await;
+    pc_offer
+        .set_remote_description(pc_answer.local_description().await.unwrap())
+        .await?;
+
+    // The RTP packets carry no header extensions, only the SDES tells MID and RID
+    let srtp_session = loop {
+        if let Some(s) = pc_offer.sctp().transport().get_srtp_session().await {
+            break s;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    };
+    let layers = [(1000u32, "a"), (2000u32, "b")];
+    let mut rids = vec![];
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut sequence_number = 0u16;
+    while rids.len() < layers.len() {
+        assert!(Instant::now() < deadline, "layers identified: {:?}", rids);
+
+        sequence_number = sequence_number.wrapping_add(1);
+        for (ssrc, rid) in layers {
+            srtp_session
+                .write_rtp(&rtp::packet::Packet {
+                    header: rtp::header::Header {
+                        version: 2,
+                        payload_type: 96,
+                        sequence_number,
+                        ssrc,
+                        ..Default::default()
+                    },
+                    payload: Bytes::from_static(&[0x10, 0x00, 0x00, 0x00]),
+                })
+                .await?;
+
+            if sequence_number % 5 == 1 {
+                pc_offer
+                    .write_rtcp(&[Box::new(SourceDescription {
+                        chunks: vec![SourceDescriptionChunk {
+                            source: ssrc,
+                            items: vec![
+                                SourceDescriptionItem {
+                                    sdes_type: SdesType::SdesMid,
+                                    text: Bytes::from_static(b"0"),
+                                },
+                                SourceDescriptionItem {
+                                    sdes_type: SdesType::SdesRtpStreamId,
+                                    text: Bytes::copy_from_slice(rid.as_bytes()),
+                                },
+                            ],
+                        }],
+                    })])
+                    .await?;
+            }
+        }
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        while let Ok(rid) = rid_rx.try_recv() {
+            rids.push(rid);
+        }
+    }
+
+    rids.sort();
+    assert_eq!(rids, vec!["a".to_owned(), "b".to_owned()]);
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+/// new_dtls_server_pair returns signaled peer connections which connect over ICE but both take
+/// the DTLS server role, so the DTLS handshake never starts. `configure` is applied to the setting
+/// engine of the offerer.
+async fn new_dtls_server_pair(
+    configure: impl Fn(&mut SettingEngine),
+) -> Result<(RTCPeerConnection, RTCPeerConnection)> {
+    let new_api = |setting_engine: SettingEngine| -> Result<API> {
+        let mut m = MediaEngine::default();
+        m.register_default_codecs()?;
+        Ok(APIBuilder::new()
+            .with_setting_engine(setting_engine)
+            .with_media_engine(m)
+            .build())
+    };
+
+    let mut offer_setting_engine = SettingEngine::default();
+    configure(&mut offer_setting_engine);
+    let mut answer_setting_engine = SettingEngine::default();
+    answer_setting_engine.set_answering_dtls_role(DTLSRole::Server)?;
+
+    let pc_offer = new_api(offer_setting_engine)?
+        .new_peer_connection(RTCConfiguration::default())
+        .await?;
+    let pc_answer = new_api(answer_setting_engine)?
+        .new_peer_connection(RTCConfiguration::default())
+        .await?;
+
+    pc_offer
+        .create_data_channel("initial_data_channel", None)
+        .await?;
+    let offer = pc_offer.create_offer(None).await?;
+    let mut offer_gathering_complete = pc_offer.gathering_complete_promise().await;
+    pc_offer.set_local_description(offer).await?;
+    let _ = offer_gathering_complete.recv().await;
+    pc_answer
+        .set_remote_description(pc_offer.local_description().await.unwrap())
+        .await?;
+
+    let answer = pc_answer.create_answer(None).await?;
+    let mut answer_gathering_complete = pc_answer.gathering_complete_promise().await;
+    pc_answer.set_local_description(answer).await?;
+    let _ = answer_gathering_complete.recv().await;
+
+    // Tell the offerer the answerer is the DTLS client, which it isn't
+    let answer = pc_answer.local_description().await.unwrap();
+    assert!(answer.sdp.contains("a=setup:passive"));
+    pc_offer
+        .set_remote_description(RTCSessionDescription::answer(
+            answer.sdp.replace("a=setup:passive", "a=setup:active"),
+        )?)
+        .await?;
+
+    Ok((pc_offer, pc_answer))
+}
+
+/// until_failed resolves with the connection error the peer connection reported when it
+/// changed to the failed state.
+async fn until_failed(
+    pc: &RTCPeerConnection,
+) -> tokio::sync::oneshot::Receiver<Option<RTCConnectionError>> {
+    let (failed_tx, failed_rx) = tokio::sync::oneshot::channel();
+    let failed_tx = Arc::new(SyncMutex::new(Some(failed_tx)));
+    let internal = Arc::clone(&pc.internal);
+    pc.on_peer_connection_state_change(Box::new(move |state: RTCPeerConnectionState| {
+        if state == RTCPeerConnectionState::Failed {
+            if let Some(failed_tx) = failed_tx.lock().take() {
+                let _ = failed_tx.send(internal.connection_error.lock().clone());
+            }
+        }
+        Box::pin(async {})
+    }));
+    failed_rx
+}
+
+#[tokio::test]
+async fn test_peer_connection_dtls_handshake_timeout() -> Result<()> {
+    let (pc_offer, pc_answer) =
+        new_dtls_server_pair(|s| s.set_dtls_handshake_timeout(Some(Duration::from_millis(500))))
+            .await?;
+    let failed = until_failed(&pc_offer).await;
+
+    let connection_error = tokio::time::timeout(Duration::from_secs(10), failed)
+        .await
+        .expect("peer connection didn't fail")
+        .unwrap();
+    assert_eq!(
+        connection_error,
+        Some(RTCConnectionError::DtlsHandshakeTimeout)
+    );
+    assert_eq!(pc_offer.connection_state(), RTCPeerConnectionState::Failed);
+    assert_eq!(
+        pc_offer.connection_error(),
+        Some(RTCConnectionError::DtlsHandshakeTimeout)
+    );
+    assert_eq!(
+        pc_offer.sctp().transport().state(),
+        RTCDtlsTransportState::Failed
+    );
+    assert_eq!(pc_answer.connection_error(), None);
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_peer_connection_connect_timeout() -> Result<()> {
+    let connect_timeout = Duration::from_millis(500);
+    let started = Instant::now();
+    let (pc_offer, pc_answer) =
+        new_dtls_server_pair(|s| s.set_connect_timeout(Some(connect_timeout))).await?;
+    // The transports are started in the background
+    let deadline = tokio::time::timeout(Duration::from_secs(1), async {
+        loop {
+            if let Some(deadline) = pc_offer.internal.connect_deadline.get() {
+                break deadline;
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await
+    .expect("starting the transports should arm the connect deadline");
+    // Armed with the configured timeout while the pair was signaled
+    assert!(deadline >= started + connect_timeout);
+    assert!(deadline <= Instant::now() + connect_timeout);
+    let failed = until_failed(&pc_offer).await;
+
+    let connection_error = tokio::time::timeout(Duration::from_secs(10), failed)
+        .await
+        .expect("peer connection didn't fail")
+        .unwrap();
+    let failed_at = Instant::now();
+    assert_eq!(connection_error, Some(RTCConnectionError::ConnectTimeout));
+    // The failure fires at the deadline, the configured time after the transports started
+    assert!(
+        failed_at >= deadline && failed_at < deadline + Duration::from_millis(250),
+        "failed {:?} after the deadline",
+        failed_at.saturating_duration_since(deadline)
+    );
+    assert_eq!(pc_offer.connection_state(), RTCPeerConnectionState::Failed);
+    assert_eq!(
+        pc_offer.connection_error(),
+        Some(RTCConnectionError::ConnectTimeout)
+    );
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_peer_connection_connect_timeout_ice_restart() -> Result<()> {
+    let connect_timeout = Duration::from_secs(1);
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let mut s = SettingEngine::default();
+    s.set_connect_timeout(Some(connect_timeout));
+    let api = APIBuilder::new()
+        .with_media_engine(m)
+        .with_setting_engine(s)
+        .build();
+    let (mut pc_offer, mut pc_answer) = new_pair(&api).await?;
+
+    let (state_tx, mut state_rx) = mpsc::unbounded_channel();
+    pc_offer.on_peer_connection_state_change(Box::new(move |state: RTCPeerConnectionState| {
+        let _ = state_tx.send(state);
+        Box::pin(async {})
+    }));
+
+    signal_pair(&mut pc_offer, &mut pc_answer).await?;
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while state_rx.recv().await != Some(RTCPeerConnectionState::Connected) {}
+    })
+    .await
+    .expect("peer connections didn't connect");
+    assert_eq!(pc_offer.internal.connect_deadline.get(), None);
+
+    // Restart ICE on the connected pair, then outlast the deadline armed on the first connect
+    restart_ice(&pc_offer, &pc_answer).await?;
+    assert_eq!(pc_offer.internal.connect_deadline.get(), None);
+
+    tokio::time::sleep(connect_timeout * 2).await;
+    while let Ok(state) = state_rx.try_recv() {
+        assert_ne!(state, RTCPeerConnectionState::Failed);
+    }
+    assert_eq!(pc_offer.connection_error(), None);
+    assert_eq!(
+        pc_offer.ice_connection_state(),
+        RTCIceConnectionState::Connected
+    );
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+/// restart_ice renegotiates the pair with an ICE restart offered by pc_offer.
+async fn restart_ice(pc_offer: &RTCPeerConnection, pc_answer: &RTCPeerConnection) -> Result<()> {
+    let offer = pc_offer
+        .create_offer(Some(RTCOfferOptions {
+            ice_restart: true,
+            ..Default::default()
+        }))
+        .await?;
+    let mut offer_gathering_complete = pc_offer.gathering_complete_promise().await;
+    pc_offer.set_local_description(offer).await?;
+    let _ = offer_gathering_complete.recv().await;
+    pc_answer
+        .set_remote_description(pc_offer.local_description().await.unwrap())
+        .await?;
+    let answer = pc_answer.create_answer(None).await?;
+    let mut answer_gathering_complete = pc_answer.gathering_complete_promise().await;
+    pc_answer.set_local_description(answer).await?;
+    let _ = answer_gathering_complete.recv().await;
+    pc_offer
+        .set_remote_description(pc_answer.local_description().await.unwrap())
+        .await?;
+
+    Ok(())
+}
+
+/// until_state waits for the state changes reported on state_rx to reach want.
+async fn until_state(
+    state_rx: &mut mpsc::UnboundedReceiver<RTCPeerConnectionState>,
+    want: RTCPeerConnectionState,
+) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while state_rx.recv().await != Some(want) {}
+    })
+    .await
+    .unwrap_or_else(|_| panic!("peer connection didn't become {}", want));
+}
+
+#[tokio::test]
+async fn test_peer_connection_ice_restart_reconnects() -> Result<()> {
+    let connect_timeout = Duration::from_secs(2);
+    for lost_state in [
+        RTCPeerConnectionState::Disconnected,
+        RTCPeerConnectionState::Failed,
+    ] {
+        let (mut pc_offer, mut pc_answer, wan) =
+            create_vnet_pair_with_settings(|s| s.set_connect_timeout(Some(connect_timeout)))
+                .await?;
+
+        let blocked = Arc::new(AtomicBool::new(false));
+        {
+            let blocked = Arc::clone(&blocked);
+            let w = wan.lock().await;
+            w.add_chunk_filter(Box::new(move |_: &(dyn Chunk + Send + Sync)| {
+                !blocked.load(Ordering::SeqCst)
+            }))
+            .await;
+        }
+
+        let (state_tx, mut state_rx) = mpsc::unbounded_channel();
+        pc_offer.on_peer_connection_state_change(Box::new(move |state: RTCPeerConnectionState| {
+            let _ = state_tx.send(state);
+            Box::pin(async {})
+        }));
+        signal_pair(&mut pc_offer, &mut pc_answer).await?;
+        until_state(&mut state_rx, RTCPeerConnectionState::Connected).await;
+
+        // Lose the network until the peer connection noticed
+        blocked.store(true, Ordering::SeqCst);
+        until_state(&mut state_rx, lost_state).await;
+        blocked.store(false, Ordering::SeqCst);
+
+        restart_ice(&pc_offer, &pc_answer).await?;
+        until_state(&mut state_rx, RTCPeerConnectionState::Connected).await;
+        assert_eq!(
+            pc_offer.internal.connect_deadline.get(),
+            None,
+            "{}: reconnecting should clear the connect deadline",
+            lost_state
+        );
+
+        // The deadline armed by the restart must not fire once reconnected
+        tokio::time::sleep(connect_timeout + Duration::from_millis(500)).await;
+        while let Ok(state) = state_rx.try_recv() {
+            assert_ne!(
+                state,
+                RTCPeerConnectionState::Failed,
+                "{}: failed after reconnecting",
+                lost_state
+            );
+        }
+        assert_eq!(pc_offer.connection_error(), None, "{}", lost_state);
+        assert_eq!(
+            pc_offer.connection_state(),
+            RTCPeerConnectionState::Connected,
+            "{}",
+            lost_state
+        );
+
+        close_pair_now(&pc_offer, &pc_answer).await;
+    }
+
+    Ok(())
+}
+
+/// run_cryptex_pair sends audio with an audio level between a vnet pair with Cryptex enabled as
+/// given, and returns the answer and the extension profiles of the RTP packets on the wire.
+async fn run_cryptex_pair(offer_cryptex: bool, answer_cryptex: bool) -> Result<(String, Vec<u16>)> {
+    let (mut pc_offer, mut pc_answer, wan) =
+        create_vnet_pair_with_api(move |mut setting_engine, is_offer| {
+            setting_engine.set_cryptex(if is_offer {
+                offer_cryptex
+            } else {
+                answer_cryptex
+            });
+
+            let mut media_engine = MediaEngine::default();
+            media_engine.register_default_codecs()?;
+            media_engine.register_header_extension(
+                RTCRtpHeaderExtensionCapability {
+                    uri: ::sdp::extmap::AUDIO_LEVEL_URI.to_owned(),
+                },
+                RTPCodecType::Audio,
+                None,
+            )?;
+            Ok(APIBuilder::new()
+                .with_setting_engine(setting_engine)
+                .with_media_engine(media_engine)
+                .build())
+        })
+        .await?;
+
+    let capture = Capture::new(1000);
+    {
+        let w = wan.lock().await;
+        w.add_capture_handler(
+            Some(Box::new(|c: &(dyn Chunk + Send + Sync)| {
+                c.source_addr().ip().to_string() == "1.2.3.4"
+            })),
+            capture.handler(),
+        )
+        .await;
+    }
+
+    let track = Arc::new(TrackLocalStaticSample::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_OPUS.to_owned(),
+            ..Default::default()
+        },
+        "audio".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    pc_offer.add_track(track.clone()).await?;
+
+    let (extensions_tx, mut extensions_rx) = mpsc::channel::<Vec<Bytes>>(1);
+    pc_answer.on_track(Box::new(
+        move |track: Option<Arc<TrackRemote>>, _: Option<Arc<RTCRtpReceiver>>| {
+            if let Some(track) = track {
+                let extensions_tx = extensions_tx.clone();
+                tokio::spawn(async move {
+                    while let Ok((packet, _)) = track.read_rtp().await {
+                        let extensions = packet
+                            .header
+                            .extensions
+                            .iter()
+                            .map(|e| e.payload.clone())
+                            .collect();
+                        let _ = extensions_tx.try_send(extensions);
+                    }
+                });
+            }
+            Box::pin(async {})
+        },
+    ));
+
+    signal_pair(&mut pc_offer, &mut pc_answer).await?;
+    let answer = pc_answer.local_description().await.unwrap().sdp;
+
+    // voice activity and -42 dBov, 0xaa on the wire
+    let audio_level = AudioLevelExtension {
+        level: 42,
+        voice: true,
+    };
+    let extensions = loop {
+        track
+            .sample_writer()
+            .with_audio_level(audio_level)
+            .write_sample(&Sample {
+                data: Bytes::from_static(b"\xDE\xAD\xBE\xEF"),
+                duration: Duration::from_millis(20),
+                ..Default::default()
+            })
+            .await?;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        if let Ok(extensions) = extensions_rx.try_recv() {
+            break extensions;
+        }
+    };
+    assert!(
+        extensions.contains(&Bytes::from_static(&[0xaa])),
+        "the audio level should be readable: {:?}",
+        extensions
+    );
+
+    let profiles = capture
+        .packets()
+        .iter()
+        .map(|p| &p.payload)
+        .filter(|data| {
+            data.len() > 16
+                && (128..192).contains(&data[0])
+                && !(192..224).contains(&data[1])
+                && data[0] & 0x10 != 0
+        })
+        .map(|data| {
+            let profile_offset = 12 + 4 * (data[0] & 0x0f) as usize;
+            u16::from_be_bytes([data[profile_offset], data[profile_offset + 1]])
+        })
+        .collect();
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok((answer, profiles))
+}
+
+#[tokio::test]
+async fn test_peer_connection_cryptex() -> Result<()> {
+    let (answer, profiles) = run_cryptex_pair(true, true).await?;
+    assert!(answer.contains("a=cryptex\r\n"), "{}", answer);
+    assert!(!profiles.is_empty(), "should capture RTP packets");
+    assert!(
+        profiles.iter().all(|profile| *profile == 0xC0DE),
+        "extensions should be encrypted: {:x?}",
+        profiles
+    );
+
+    // The answerer doesn't support Cryptex, the offerer falls back
+    let (answer, profiles) = run_cryptex_pair(true, false).await?;
+    assert!(!answer.contains("a=cryptex"), "{}", answer);
+    assert!(!profiles.is_empty(), "should capture RTP packets");
+    assert!(
+        profiles.iter().all(|profile| *profile == 0xBEDE),
+        "extensions shouldn't be encrypted: {:x?}",
+        profiles
+    );
+
+    // Not offered, not answered
+    let (answer, _) = run_cryptex_pair(false, true).await?;
+    assert!(!answer.contains("a=cryptex"), "{}", answer);
+
+    Ok(())
+}
+
+/// HangingResolver never answers.
+struct HangingResolver;
+
+#[async_trait::async_trait]
+impl ice::resolver::Resolver for HangingResolver {
+    async fn lookup_ip(&self, _host: &str) -> std::result::Result<Vec<IpAddr>, ice::Error> {
+        std::future::pending().await
+    }
+}
+
+#[tokio::test]
+async fn test_peer_connection_on_ice_candidate_error() -> Result<()> {
+    let mut setting_engine = SettingEngine::default();
+    setting_engine.set_ice_resolver(Arc::new(HangingResolver));
+    setting_engine.set_ice_dns_timeout(Duration::from_millis(100));
+    setting_engine.set_ice_multicast_dns_mode(ice::mdns::MulticastDnsMode::Disabled);
+
+    let mut media_engine = MediaEngine::default();
+    media_engine.register_default_codecs()?;
+    let pc = APIBuilder::new()
+        .with_setting_engine(setting_engine)
+        .with_media_engine(media_engine)
+        .build()
+        .new_peer_connection(RTCConfiguration {
+            ice_servers: vec![RTCIceServer {
+                urls: vec!["stun:stun.example.org:3478".to_owned()],
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+        .await?;
+
+    let (error_tx, mut error_rx) = mpsc::unbounded_channel();
+    pc.on_ice_candidate_error(Box::new(move |err: RTCIceCandidateError| {
+        let _ = error_tx.send(err);
+        Box::pin(async {})
+    }));
+
+    pc.create_data_channel("data", None).await?;
+    let offer = pc.create_offer(None).await?;
+    pc.set_local_description(offer).await?;
+
+    let err = tokio::time::timeout(Duration::from_secs(5), error_rx.recv())
+        .await
+        .expect("the ICE server should be reported")
+        .unwrap();
+    assert_eq!(
+        err,
+        RTCIceCandidateError {
+            url: "stun:stun.example.org:3478".to_owned(),
+            error_code: 701,
+            error_text: "DNS lookup timed out".to_owned(),
+        }
+    );
+
+    pc.close().await?;
+
+    Ok(())
+}
+
+/// new_audio_video_offer creates an offerer with an audio and a video transceiver in the
+/// have-local-offer state, and the answer to its offer split into the session part and the
+/// media sections. The audio section of the answer is doctored to be sendrecv, so that the
+/// transceiver it is associated with is told apart by its current direction.
+async fn new_audio_video_offer() -> Result<(RTCPeerConnection, String, Vec<String>)> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+    let (pc_offer, pc_answer) = new_pair(&api).await?;
+
+    pc_offer
+        .add_transceiver_from_kind(RTPCodecType::Audio, &[])
+        .await?;
+    pc_offer
+        .add_transceiver_from_kind(RTPCodecType::Video, &[])
+        .await?;
+    let offer = pc_offer.create_offer(None).await?;
+    pc_offer.set_local_description(offer.clone()).await?;
+    pc_answer.set_remote_description(offer).await?;
+    let answer = pc_answer.create_answer(None).await?;
+    pc_answer.close().await?;
+
+    let mut sections: Vec<String> = answer
+        .sdp
+        .split("\r\nm=")
+        .map(|section| format!("m={}\r\n", section.trim_end_matches("\r\n")))
+        .collect();
+    let session = sections.remove(0).trim_start_matches("m=").to_owned();
+    assert_eq!(sections.len(), 2);
+    assert!(sections[0].starts_with("m=audio") && sections[1].starts_with("m=video"));
+    sections[0] = sections[0].replace("a=recvonly", "a=sendrecv");
+
+    Ok((pc_offer, session, sections))
+}
+
+fn doctored_answer(session: &str, sections: &[String]) -> RTCSessionDescription {
+    RTCSessionDescription {
+        sdp_type: RTCSdpType::Answer,
+        sdp: format!("{}{}", session, sections.concat()),
+        ..Default::default()
+    }
+}
+
+/// assert_answer_associated asserts that the audio and video transceivers of pc_offer are
+/// associated with the audio and video sections of the answer.
+async fn assert_answer_associated(pc_offer: &RTCPeerConnection) {
+    assert_eq!(pc_offer.signaling_state(), RTCSignalingState::Stable);
+    for t in pc_offer.get_transceivers().await {
+        let (mid, want_direction) = match t.kind() {
+            RTPCodecType::Audio => ("0", RTCRtpTransceiverDirection::Sendrecv),
+            _ => ("1", RTCRtpTransceiverDirection::Sendonly),
+        };
+        assert_eq!(t.mid().await, mid);
+        assert_eq!(
+            t.current_direction(),
+            want_direction,
+            "{} transceiver",
+            t.kind()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_set_remote_description_answer_reordered_media_sections() -> Result<()> {
+    // Sections matched by mid.
+    let (pc_offer, session, mut sections) = new_audio_video_offer().await?;
+    sections.swap(0, 1);
+    pc_offer
+        .set_remote_description(doctored_answer(&session, &sections))
+        .await?;
+    assert_answer_associated(&pc_offer).await;
+    pc_offer.close().await?;
+
+    // Sections without mid matched by kind and order.
+    let (pc_offer, session, sections) = new_audio_video_offer().await?;
+    let mut sections: Vec<String> = sections
+        .iter()
+        .map(|section| {
+            section
+                .lines()
+                .filter(|line| !line.starts_with("a=mid:"))
+                .map(|line| format!("{}\r\n", line))
+                .collect()
+        })
+        .collect();
+    sections.swap(0, 1);
+    pc_offer
+        .set_remote_description(doctored_answer(&session, &sections))
+        .await?;
+    assert_answer_associated(&pc_offer).await;
+    pc_offer.close().await?;
+
+    // Sections that weren't offered are ignored.
+    let (pc_offer, session, mut sections) = new_audio_video_offer().await?;
+    sections.insert(
+        0,
+        "m=video 9 UDP/TLS/RTP/SAVPF 96\r\nc=IN IP4 0.0.0.0\r\na=mid:9\r\na=sendrecv\r\na=rtpmap:96 VP8/90000\r\n"
+            .to_owned(),
+    );
+    sections.push("m=text 9 UDP/TLS/RTP/SAVPF 98\r\nc=IN IP4 0.0.0.0\r\na=recvonly\r\n".to_owned());
+    pc_offer
+        .set_remote_description(doctored_answer(&session, &sections))
+        .await?;
+    assert_answer_associated(&pc_offer).await;
+    pc_offer.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_set_remote_description_answer_invalid_media_sections() -> Result<()> {
+    // A missing section.
+    let (pc_offer, session, sections) = new_audio_video_offer().await?;
+    let result = pc_offer
+        .set_remote_description(doctored_answer(&session, &sections[..1]))
+        .await;
+    assert!(
+        matches!(
+            &result,
+            Err(Error::ErrPeerConnAnswerMissingMediaSection { mid, kind })
+                if mid == "1" && kind == "video"
+        ),
+        "{:?}",
+        result
+    );
+    assert_eq!(
+        pc_offer.signaling_state(),
+        RTCSignalingState::HaveLocalOffer
+    );
+
+    // The offer can still be answered.
+    pc_offer
+        .set_remote_description(doctored_answer(&session, &sections))
+        .await?;
+    assert_answer_associated(&pc_offer).await;
+    pc_offer.close().await?;
+
+    // Sections which swapped their mids.
+    let (pc_offer, session, sections) = new_audio_video_offer().await?;
+    let sections = vec![
+        sections[0].replace("a=mid:0", "a=mid:1"),
+        sections[1].replace("a=mid:1", "a=mid:0"),
+    ];
+    let result = pc_offer
+        .set_remote_description(doctored_answer(&session, &sections))
+        .await;
+    assert!(
+        matches!(
+            &result,
+            Err(Error::ErrPeerConnRemoteMediaSectionKindMismatch { mid, remote_kind, local_kind })
+                if mid == "1" && remote_kind == "audio" && local_kind == "video"
+        ),
+        "{:?}",
+        result
+    );
+    assert_eq!(
+        pc_offer.signaling_state(),
+        RTCSignalingState::HaveLocalOffer
+    );
+    for t in pc_offer.get_transceivers().await {
+        assert_eq!(
+            t.current_direction(),
+            RTCRtpTransceiverDirection::Unspecified
+        );
+    }
+    pc_offer.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_set_remote_description_offer_media_kind_mismatch() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+    let (pc_offer, pc_answer) = new_pair(&api).await?;
+
+    pc_offer
+        .add_transceiver_from_kind(RTPCodecType::Audio, &[])
+        .await?;
+    pc_offer
+        .add_transceiver_from_kind(RTPCodecType::Video, &[])
+        .await?;
+    let offer = pc_offer.create_offer(None).await?;
+    pc_answer.set_remote_description(offer.clone()).await?;
+    let answer = pc_answer.create_answer(None).await?;
+    pc_answer.set_local_description(answer).await?;
+
+    // A new offer may not change the kind of a media section.
+    let reoffer = RTCSessionDescription {
+        sdp_type: RTCSdpType::Offer,
+        sdp: offer
+            .sdp
+            .replace("a=mid:0", "a=mid:x")
+            .replace("a=mid:1", "a=mid:0")
+            .replace("a=mid:x", "a=mid:1"),
+        ..Default::default()
+    };
+    let result = pc_answer.set_remote_description(reoffer).await;
+    assert!(
+        matches!(
+            &result,
+            Err(Error::ErrPeerConnRemoteMediaSectionKindMismatch { mid, remote_kind, local_kind })
+                if mid == "0" && remote_kind == "video" && local_kind == "audio"
+        ),
+        "{:?}",
+        result
+    );
+    assert_eq!(pc_answer.signaling_state(), RTCSignalingState::Stable);
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
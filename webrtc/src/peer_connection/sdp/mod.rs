@@ -22,7 +22,8 @@ use crate::peer_connection::MEDIA_SECTION_APPLICATION;
 use crate::SDP_ATTRIBUTE_RID;
 use ice::candidate::candidate_base::unmarshal_candidate;
 use ice::candidate::Candidate;
-use sdp::description::common::{Address, ConnectionInformation};
+use ice::options::IceOptions;
+use sdp::description::common::{Address, Attribute, ConnectionInformation};
 use sdp::description::media::{MediaDescription, MediaName, RangedPort};
 use sdp::description::session::*;
 use sdp::extmap::ExtMap;
@@ -327,6 +328,7 @@ pub(crate) async fn add_data_media_section(
         bandwidth: vec![],
         encryption_key: None,
         attributes: vec![],
+        ..Default::default()
     }
     .with_value_attribute(
         ATTR_KEY_CONNECTION_SETUP.to_owned(),
@@ -450,13 +452,15 @@ pub(crate) async fn add_transceiver_sdp(
         );
 
         for feedback in &codec.capability.rtcp_feedback {
-            media = media.with_value_attribute(
-                "rtcp-fb".to_owned(),
+            let value = if feedback.parameter.is_empty() {
+                format!("{} {}", codec.payload_type, feedback.typ)
+            } else {
                 format!(
                     "{} {} {}",
                     codec.payload_type, feedback.typ, feedback.parameter
-                ),
-            );
+                )
+            };
+            media = media.with_value_attribute("rtcp-fb".to_owned(), value);
         }
     }
     if codecs.is_empty() {
@@ -498,12 +502,17 @@ pub(crate) async fn add_transceiver_sdp(
             bandwidth: vec![],
             encryption_key: None,
             attributes: vec![],
+            ..Default::default()
         });
         return Ok((d, false));
     }
 
     let parameters = media_engine
-        .get_rtp_parameters_by_kind(t.kind, t.direction())
+        .get_rtp_parameters_by_kind(
+            t.kind,
+            t.direction(),
+            &t.header_extensions_to_negotiate().await,
+        )
         .await;
     for rtp_extension in &parameters.header_extensions {
         let ext_url = Url::parse(rtp_extension.uri.as_str())?;
@@ -533,7 +542,7 @@ pub(crate) async fn add_transceiver_sdp(
         if let Some(sender) = mt.sender().await {
             if let Some(track) = sender.track().await {
                 media = media.with_media_source(
-                    sender.ssrc,
+                    sender.ssrc(),
                     track.stream_id().to_owned(), /* cname */
                     track.stream_id().to_owned(), /* streamLabel */
                     track.id().to_owned(),
@@ -647,6 +656,8 @@ pub(crate) struct PopulateSdpParams {
     pub(crate) is_icelite: bool,
     pub(crate) connection_role: ConnectionRole,
     pub(crate) ice_gathering_state: RTCIceGatheringState,
+    pub(crate) ice_options: IceOptions,
+    pub(crate) cryptex: bool,
 }
 
 /// populate_sdp serializes a PeerConnections state into an SDP
@@ -732,6 +743,19 @@ pub(crate) async fn populate_sdp(
         d = d.with_value_attribute(ATTR_KEY_ICELITE.to_owned(), ATTR_KEY_ICELITE.to_owned());
     }
 
+    if !params.ice_options.is_empty() {
+        // RFC 8839 S5.6
+        d = d.with_value_attribute(
+            ATTR_KEY_ICE_OPTIONS.to_owned(),
+            params.ice_options.to_string(),
+        );
+    }
+
+    if params.cryptex {
+        // RFC 9335 S6
+        d = d.with_property_attribute(ATTR_KEY_CRYPTEX.to_owned());
+    }
+
     Ok(d.with_value_attribute(ATTR_KEY_GROUP.to_owned(), bundle_value))
 }
 
@@ -754,6 +778,74 @@ pub(crate) fn get_peer_direction(media: &MediaDescription) -> RTCRtpTransceiverD
     RTCRtpTransceiverDirection::Unspecified
 }
 
+/// match_answer_media_sections matches the media sections of answer with the ones of offer,
+/// which it answers. Sections are matched by mid, and answer sections without a mid are given
+/// the one of the first unmatched offer section of the same kind. Answer sections which match
+/// no offer section are ignored. Fails if an offer section is left unanswered, or if a section
+/// changes kind.
+pub(crate) fn match_answer_media_sections(
+    offer: &SessionDescription,
+    answer: &mut SessionDescription,
+) -> Result<()> {
+    let offered: Vec<(&String, &String)> = offer
+        .media_descriptions
+        .iter()
+        .filter_map(|m| get_mid_value(m).map(|mid| (mid, &m.media_name.media)))
+        .collect();
+    let mut answered = vec![false; offered.len()];
+
+    for media in &answer.media_descriptions {
+        let mid = match get_mid_value(media) {
+            Some(mid) => mid,
+            None => continue,
+        };
+        match offered.iter().position(|(offer_mid, _)| *offer_mid == mid) {
+            Some(i) if *offered[i].1 != media.media_name.media => {
+                return Err(Error::ErrPeerConnRemoteMediaSectionKindMismatch {
+                    mid: mid.to_owned(),
+                    remote_kind: media.media_name.media.clone(),
+                    local_kind: offered[i].1.to_owned(),
+                });
+            }
+            Some(i) => answered[i] = true,
+            None => log::warn!(
+                "ignoring {} media section with mid {} of the answer, which wasn't offered",
+                media.media_name.media,
+                mid
+            ),
+        }
+    }
+
+    for media in &mut answer.media_descriptions {
+        if get_mid_value(media).is_some() {
+            continue;
+        }
+        let i =
+            (0..offered.len()).find(|&i| !answered[i] && *offered[i].1 == media.media_name.media);
+        if let Some(i) = i {
+            answered[i] = true;
+            media.attributes.push(Attribute::new(
+                ATTR_KEY_MID.to_owned(),
+                Some(offered[i].0.to_owned()),
+            ));
+        } else {
+            log::warn!(
+                "ignoring {} media section without mid of the answer, which wasn't offered",
+                media.media_name.media
+            );
+        }
+    }
+
+    if let Some(i) = answered.iter().position(|answered| !answered) {
+        return Err(Error::ErrPeerConnAnswerMissingMediaSection {
+            mid: offered[i].0.to_owned(),
+            kind: offered[i].1.to_owned(),
+        });
+    }
+
+    Ok(())
+}
+
 pub(crate) fn extract_fingerprint(desc: &SessionDescription) -> Result<(String, String)> {
     let mut fingerprints = vec![];
 
@@ -785,6 +877,45 @@ pub(crate) fn extract_fingerprint(desc: &SessionDescription) -> Result<(String,
     Ok((parts[1].to_owned(), parts[0].to_owned()))
 }
 
+/// has_cryptex returns true if desc signals Cryptex, RFC 9335, with a session level a=cryptex or
+/// one in each of its audio and video sections. Cryptex applies to the whole SRTP session, which
+/// is shared by the bundled sections, so it isn't used when only some of the sections signal it.
+pub(crate) fn has_cryptex(desc: &SessionDescription) -> bool {
+    if desc.attributes.iter().any(|a| a.key == ATTR_KEY_CRYPTEX) {
+        return true;
+    }
+
+    let mut media_sections = desc
+        .media_descriptions
+        .iter()
+        .filter(|m| m.media_name.media != MEDIA_SECTION_APPLICATION)
+        .peekable();
+    media_sections.peek().is_some()
+        && media_sections.all(|m| m.attribute(ATTR_KEY_CRYPTEX).is_some())
+}
+
+/// extract_ice_options returns the ICE options of the session and media level a=ice-options
+/// attributes, and whether the remote signaled a=end-of-candidates.
+pub(crate) fn extract_ice_options(desc: &SessionDescription) -> (IceOptions, bool) {
+    let mut tags = vec![];
+    if let Some(value) = desc.attribute(ATTR_KEY_ICE_OPTIONS) {
+        tags.push(value.as_str());
+    }
+
+    let mut end_of_candidates = desc
+        .attributes
+        .iter()
+        .any(|a| a.key == ATTR_KEY_END_OF_CANDIDATES);
+    for m in &desc.media_descriptions {
+        if let Some(value) = m.attribute(ATTR_KEY_ICE_OPTIONS).and_then(|o| o) {
+            tags.push(value);
+        }
+        end_of_candidates = end_of_candidates || m.attribute(ATTR_KEY_END_OF_CANDIDATES).is_some();
+    }
+
+    (IceOptions::from(tags.join(" ").as_str()), end_of_candidates)
+}
+
 pub(crate) async fn extract_ice_details(
     desc: &SessionDescription,
 ) -> Result<(String, String, Vec<RTCIceCandidate>)> {
@@ -810,13 +941,27 @@ pub(crate) async fn extract_ice_details(
         for a in &m.attributes {
             if a.is_ice_candidate() {
                 if let Some(value) = &a.value {
-                    let c: Arc<dyn Candidate + Send + Sync> = Arc::new(unmarshal_candidate(value)?);
-                    let candidate = RTCIceCandidate::from(&c);
-                    candidates.push(candidate);
+                    let c: Arc<dyn Candidate + Send + Sync> = match unmarshal_candidate(value) {
+                        Ok(c) => Arc::new(c),
+                        Err(err) => {
+                            log::warn!("skipping remote ICE candidate {}: {}", value, err);
+                            continue;
+                        }
+                    };
+
+                    // Sections sharing a BUNDLE transport repeat the same candidates
+                    if candidates
+                        .iter()
+                        .any(|other: &Arc<dyn Candidate + Send + Sync>| other.equal(&*c))
+                    {
+                        continue;
+                    }
+                    candidates.push(c);
                 }
             }
         }
     }
+    let candidates = candidates.iter().map(RTCIceCandidate::from).collect();
 
     if remote_ufrags.is_empty() {
         return Err(Error::ErrSessionDescriptionMissingIceUfrag);
@@ -849,6 +994,19 @@ pub(crate) fn have_application_media_section(desc: &SessionDescription) -> bool
     false
 }
 
+/// get_max_message_size returns the max message size of the remote from the `a=max-message-size`
+/// attribute of its application media section. An absent attribute returns 0, which keeps the
+/// default of 65536 bytes, and `a=max-message-size:0`, which allows any size, returns u32::MAX.
+pub(crate) fn get_max_message_size(desc: &SessionDescription) -> u32 {
+    desc.media_descriptions
+        .iter()
+        .filter(|m| m.media_name.media == MEDIA_SECTION_APPLICATION)
+        .find_map(|m| m.attribute(ATTR_KEY_MAX_MESSAGE_SIZE).and_then(|o| o))
+        .and_then(|size| size.trim().parse::<u32>().ok())
+        .map(|size| if size == 0 { u32::MAX } else { size })
+        .unwrap_or(0)
+}
+
 pub(crate) fn get_by_mid<'a, 'b>(
     search_mid: &'a str,
     desc: &'b session_description::RTCSessionDescription,
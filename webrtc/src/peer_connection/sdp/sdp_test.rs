@@ -252,9 +252,87 @@ async fn test_extract_ice_details() -> Result<()> {
         }
     }
 
+    //"Candidates repeated across bundled sections or malformed are skipped"
+    {
+        let section = |candidates: &[&str]| {
+            let mut attributes = vec![
+                Attribute {
+                    key: "ice-ufrag".to_owned(),
+                    value: Some(DEFAULT_UFRAG.to_owned()),
+                },
+                Attribute {
+                    key: "ice-pwd".to_owned(),
+                    value: Some(DEFAULT_PWD.to_owned()),
+                },
+            ];
+            for c in candidates {
+                attributes.push(Attribute {
+                    key: "candidate".to_owned(),
+                    value: Some((*c).to_owned()),
+                });
+            }
+            MediaDescription {
+                attributes,
+                ..Default::default()
+            }
+        };
+
+        let host = "1 1 udp 2130706431 10.0.0.1 50000 typ host";
+        let srflx = "2 1 udp 1694498815 203.0.113.1 60000 typ srflx raddr 10.0.0.1 rport 50000";
+        let s = SessionDescription {
+            media_descriptions: vec![section(&[host, "1 1 udp"]), section(&[host, srflx])],
+            ..Default::default()
+        };
+
+        let (_, _, candidates) = extract_ice_details(&s).await?;
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].address, "10.0.0.1");
+        assert_eq!(candidates[1].address, "203.0.113.1");
+    }
+
     Ok(())
 }
 
+#[test]
+fn test_extract_ice_options() {
+    let attribute = |key: &str, value: Option<&str>| Attribute {
+        key: key.to_owned(),
+        value: value.map(|v| v.to_owned()),
+    };
+
+    //"No ice-options"
+    {
+        let s = SessionDescription::default();
+        assert_eq!(extract_ice_options(&s), (IceOptions::default(), false));
+    }
+
+    //"Session and media level ice-options are merged"
+    {
+        let s = SessionDescription {
+            attributes: vec![attribute(ATTR_KEY_ICE_OPTIONS, Some("trickle"))],
+            media_descriptions: vec![MediaDescription {
+                attributes: vec![
+                    attribute(ATTR_KEY_ICE_OPTIONS, Some("renomination")),
+                    attribute(ATTR_KEY_END_OF_CANDIDATES, None),
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            extract_ice_options(&s),
+            (
+                IceOptions {
+                    trickle: true,
+                    renomination: true,
+                    ice2: false,
+                },
+                true
+            )
+        );
+    }
+}
+
 #[test]
 fn test_track_details_from_sdp() -> Result<()> {
     //"Tracks unknown, audio and video with RTX"
@@ -542,6 +620,8 @@ async fn fingerprint_test(
         is_icelite: false,
         connection_role: ConnectionRole::Active,
         ice_gathering_state: RTCIceGatheringState::New,
+        ice_options: IceOptions::default(),
+        cryptex: false,
     };
 
     let s = populate_sdp(
@@ -585,9 +665,10 @@ async fn test_media_description_fingerprints() -> Result<()> {
                     RTCRtpTransceiverDirection::Inactive,
                     RTPCodecType::Video,
                     api.media_engine
+                        .load()
                         .get_codecs_by_kind(RTPCodecType::Video)
                         .await,
-                    Arc::clone(&api.media_engine),
+                    api.media_engine.load_full(),
                     None,
                 )
                 .await,
@@ -603,9 +684,10 @@ async fn test_media_description_fingerprints() -> Result<()> {
                     RTCRtpTransceiverDirection::Inactive,
                     RTPCodecType::Audio,
                     api.media_engine
+                        .load()
                         .get_codecs_by_kind(RTPCodecType::Audio)
                         .await,
-                    Arc::clone(&api.media_engine),
+                    api.media_engine.load_full(),
                     None,
                 )
                 .await,
@@ -634,7 +716,7 @@ async fn test_media_description_fingerprints() -> Result<()> {
                     api.setting_engine.get_receive_mtu(),
                     track,
                     Arc::new(RTCDtlsTransport::default()),
-                    Arc::clone(&api.media_engine),
+                    api.media_engine.load_full(),
                     Arc::clone(&interceptor),
                     false,
                 )
@@ -645,10 +727,10 @@ async fn test_media_description_fingerprints() -> Result<()> {
     }
 
     //"Per-Media Description Fingerprints",
-    fingerprint_test(&certificate, &api.media_engine, &media, true, 3).await?;
+    fingerprint_test(&certificate, &api.media_engine.load(), &media, true, 3).await?;
 
     //"Per-Session Description Fingerprints",
-    fingerprint_test(&certificate, &api.media_engine, &media, false, 1).await?;
+    fingerprint_test(&certificate, &api.media_engine.load(), &media, false, 1).await?;
 
     Ok(())
 }
@@ -690,6 +772,8 @@ async fn test_populate_sdp() -> Result<()> {
             is_icelite: se.candidates.ice_lite,
             connection_role: DEFAULT_DTLS_ROLE_OFFER.to_connection_role(),
             ice_gathering_state: RTCIceGatheringState::Complete,
+            ice_options: IceOptions::default(),
+            cryptex: false,
         };
         let offer_sdp = populate_sdp(
             d,
@@ -771,6 +855,8 @@ async fn test_populate_sdp() -> Result<()> {
             is_icelite: se.candidates.ice_lite,
             connection_role: DEFAULT_DTLS_ROLE_OFFER.to_connection_role(),
             ice_gathering_state: RTCIceGatheringState::Complete,
+            ice_options: IceOptions::default(),
+            cryptex: false,
         };
         let offer_sdp = populate_sdp(
             d,
@@ -874,6 +960,8 @@ async fn test_populate_sdp_reject() -> Result<()> {
         is_icelite: se.candidates.ice_lite,
         connection_role: DEFAULT_DTLS_ROLE_OFFER.to_connection_role(),
         ice_gathering_state: RTCIceGatheringState::Complete,
+        ice_options: IceOptions::default(),
+        cryptex: false,
     };
     let offer_sdp = populate_sdp(
         d,
@@ -1060,3 +1148,46 @@ fn test_rtp_extensions_from_media_description() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_get_max_message_size() -> Result<()> {
+    let parse = |attribute: &str| -> Result<u32> {
+        let sdp = format!(
+            "v=0\r\no=- 0 0 IN IP4 0.0.0.0\r\ns=-\r\nt=0 0\r\n\
+             m=audio 9 UDP/TLS/RTP/SAVPF 111\r\na=max-message-size:1\r\n\
+             m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\na=sctp-port:5000\r\n{}",
+            attribute
+        );
+        let desc = SessionDescription::unmarshal(&mut std::io::Cursor::new(sdp.as_bytes()))?;
+        Ok(get_max_message_size(&desc))
+    };
+
+    assert_eq!(parse("")?, 0);
+    assert_eq!(parse("a=max-message-size:262144\r\n")?, 262144);
+    assert_eq!(parse("a=max-message-size:0\r\n")?, u32::MAX);
+    assert_eq!(parse("a=max-message-size:invalid\r\n")?, 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_has_cryptex() -> Result<()> {
+    let parse = |session: &str, audio: &str, video: &str| -> Result<bool> {
+        let sdp = format!(
+            "v=0\r\no=- 0 0 IN IP4 0.0.0.0\r\ns=-\r\nt=0 0\r\n{}\
+             m=audio 9 UDP/TLS/RTP/SAVPF 111\r\n{}\
+             m=video 9 UDP/TLS/RTP/SAVPF 96\r\n{}\
+             m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\na=sctp-port:5000\r\n",
+            session, audio, video
+        );
+        let desc = SessionDescription::unmarshal(&mut std::io::Cursor::new(sdp.as_bytes()))?;
+        Ok(has_cryptex(&desc))
+    };
+
+    assert!(!parse("", "", "")?);
+    assert!(parse("a=cryptex\r\n", "", "")?);
+    assert!(parse("", "a=cryptex\r\n", "a=cryptex\r\n")?);
+    assert!(!parse("", "a=cryptex\r\n", "")?);
+
+    Ok(())
+}
@@ -150,6 +150,13 @@ pub(crate) fn check_next_signaling_state(
             }
         }
         RTCSignalingState::HaveLocalOffer => {
+            // have-local-offer->SetLocal(rollback)->stable
+            if op == StateChangeOp::SetLocal
+                && sdp_type == RTCSdpType::Rollback
+                && next == RTCSignalingState::Stable
+            {
+                return Ok(next);
+            }
             if op == StateChangeOp::SetRemote {
                 match sdp_type {
                     // have-local-offer->SetRemote(answer)->stable
@@ -342,6 +349,26 @@ mod test {
                     applying: RTCSdpType::Pranswer,
                 }),
             ),
+            (
+                "have-local-offer->SetLocal(rollback)->stable",
+                RTCSignalingState::HaveLocalOffer,
+                RTCSignalingState::Stable,
+                StateChangeOp::SetLocal,
+                RTCSdpType::Rollback,
+                None,
+            ),
+            (
+                "(invalid) have-remote-offer->SetLocal(rollback)->stable",
+                RTCSignalingState::HaveRemoteOffer,
+                RTCSignalingState::Stable,
+                StateChangeOp::SetLocal,
+                RTCSdpType::Rollback,
+                Some(Error::ErrSignalingStateProposedTransitionInvalid {
+                    from: RTCSignalingState::HaveRemoteOffer,
+                    is_local: true,
+                    applying: RTCSdpType::Rollback,
+                }),
+            ),
             (
                 "(invalid) stable->SetRemote(rollback)->have-local-offer",
                 RTCSignalingState::Stable,
@@ -8,6 +8,7 @@ use crate::rtp_transceiver::rtp_receiver::{RTCRtpReceiver, RTPReceiverInternal};
 use crate::rtp_transceiver::rtp_sender::RTCRtpSender;
 use crate::rtp_transceiver::rtp_transceiver_direction::RTCRtpTransceiverDirection;
 use crate::track::track_local::TrackLocal;
+use crate::track::track_remote::TrackRemote;
 
 use interceptor::{
     stream_info::{RTPHeaderExtension, StreamInfo},
@@ -15,6 +16,7 @@ use interceptor::{
 };
 
 use log::trace;
+use rtcp::source_description::{SdesType, SourceDescription};
 use serde::{Deserialize, Serialize};
 use std::future::Future;
 use std::pin::Pin;
@@ -112,6 +114,9 @@ pub type RTCRtpEncodingParameters = RTCRtpCodingParameters;
 /// RTPReceiveParameters contains the RTP stack settings used by receivers
 #[derive(Debug)]
 pub struct RTCRtpReceiveParameters {
+    /// The codecs and header extensions to receive with. Without SDP, e.g. with the ORTC API,
+    /// they must match the sender's. When empty, the receiver uses the negotiated ones.
+    pub rtp_parameters: RTCRtpParameters,
     pub encodings: Vec<RTCRtpDecodingParameters>,
 }
 
@@ -163,6 +168,7 @@ pub(crate) fn create_stream_info(
         channels: codec.channels,
         sdp_fmtp_line: codec.sdp_fmtp_line,
         rtcp_feedback: feedbacks,
+        ..Default::default()
     }
 }
 
@@ -179,6 +185,7 @@ pub struct RTCRtpTransceiver {
     current_direction: AtomicU8, //RTPTransceiverDirection
 
     codecs: Arc<Mutex<Vec<RTCRtpCodecParameters>>>, // User provided codecs via set_codec_preferences
+    header_extensions_to_negotiate: Arc<Mutex<Vec<RTCRtpHeaderExtensionToNegotiate>>>, // User provided via set_header_extensions_to_negotiate
 
     pub(crate) stopped: AtomicBool,
     pub(crate) kind: RTPCodecType,
@@ -207,6 +214,7 @@ impl RTCRtpTransceiver {
             current_direction: AtomicU8::new(RTCRtpTransceiverDirection::Unspecified as u8),
 
             codecs: Arc::new(Mutex::new(codecs)),
+            header_extensions_to_negotiate: Arc::new(Mutex::new(vec![])),
             stopped: AtomicBool::new(false),
             kind,
             media_engine,
@@ -237,6 +245,57 @@ impl RTCRtpTransceiver {
         Ok(())
     }
 
+    /// set_header_extensions_to_negotiate selects, per header extension registered on the
+    /// MediaEngine, whether it is offered and answered for this transceiver. Extensions that are
+    /// not listed keep the behaviour they were registered with. The choices take effect with the
+    /// next negotiation.
+    pub async fn set_header_extensions_to_negotiate(
+        &self,
+        extensions: Vec<RTCRtpHeaderExtensionToNegotiate>,
+    ) -> Result<()> {
+        for extension in &extensions {
+            if extension.direction == RTCRtpTransceiverDirection::Unspecified {
+                return Err(Error::ErrRTPTransceiverHeaderExtensionDirectionInvalid);
+            }
+            if !self
+                .media_engine
+                .has_header_extension(&extension.uri, self.kind)
+            {
+                return Err(Error::ErrRTPTransceiverHeaderExtensionUnsupported);
+            }
+        }
+
+        {
+            let mut h = self.header_extensions_to_negotiate.lock().await;
+            *h = extensions;
+        }
+        Ok(())
+    }
+
+    /// header_extensions_to_negotiate returns the choices made with
+    /// set_header_extensions_to_negotiate
+    pub async fn header_extensions_to_negotiate(&self) -> Vec<RTCRtpHeaderExtensionToNegotiate> {
+        let h = self.header_extensions_to_negotiate.lock().await;
+        h.clone()
+    }
+
+    /// get_negotiated_header_extensions returns the header extensions, with their negotiated ids,
+    /// in use by this transceiver. It is empty until a remote description has been applied.
+    pub async fn get_negotiated_header_extensions(&self) -> Vec<RTCRtpHeaderExtensionParameters> {
+        if !self.media_engine.is_negotiated(self.kind) {
+            return vec![];
+        }
+
+        self.media_engine
+            .get_rtp_parameters_by_kind(
+                self.kind,
+                self.direction(),
+                &self.header_extensions_to_negotiate().await,
+            )
+            .await
+            .header_extensions
+    }
+
     /// Codecs returns list of supported codecs
     pub(crate) async fn get_codecs(&self) -> Vec<RTCRtpCodecParameters> {
         let mut codecs = self.codecs.lock().await;
@@ -280,17 +339,32 @@ impl RTCRtpTransceiver {
         receiver.clone()
     }
 
+    /// select_layer surfaces the simulcast layer with the given rid through the primary track of
+    /// the receiver, see `RTCRtpReceiver::select_layer`.
+    pub async fn select_layer(&self, rid: &str) -> Result<Arc<TrackRemote>> {
+        match self.receiver().await {
+            Some(receiver) => receiver.select_layer(rid).await,
+            None => Err(Error::ErrRTPReceiverNil),
+        }
+    }
+
     pub(crate) async fn set_receiver(&self, r: Option<Arc<RTCRtpReceiver>>) {
         if let Some(receiver) = &r {
             receiver
                 .set_transceiver_codecs(Some(Arc::clone(&self.codecs)))
                 .await;
+            receiver
+                .set_transceiver_header_extensions(Some(Arc::clone(
+                    &self.header_extensions_to_negotiate,
+                )))
+                .await;
         }
 
         {
             let mut receiver = self.receiver.lock().await;
             if let Some(prev_receiver) = &*receiver {
                 prev_receiver.set_transceiver_codecs(None).await;
+                prev_receiver.set_transceiver_header_extensions(None).await;
             }
 
             *receiver = r;
@@ -308,6 +382,12 @@ impl RTCRtpTransceiver {
         Ok(())
     }
 
+    /// unset_mid clears the mid, when the offer it was assigned by is rolled back.
+    pub(crate) async fn unset_mid(&self) {
+        let mut m = self.mid.lock().await;
+        m.clear();
+    }
+
     /// mid gets the Transceiver's mid value. When not already set, this value will be set in CreateOffer or create_answer.
     pub async fn mid(&self) -> String {
         let mid = self.mid.lock().await;
@@ -538,12 +618,12 @@ pub(crate) fn handle_unknown_rtp_packet(
     let mut reader = buf;
     let rp = rtp::packet::Packet::unmarshal(&mut reader)?;
 
+    let payload_type = rp.header.payload_type;
+
     if !rp.header.extension {
-        return Ok((String::new(), String::new(), String::new(), 0));
+        return Ok((String::new(), String::new(), String::new(), payload_type));
     }
 
-    let payload_type = rp.header.payload_type;
-
     let mid = if let Some(payload) = rp.header.get_extension(mid_extension_id) {
         String::from_utf8(payload.to_vec())?
     } else {
@@ -564,3 +644,35 @@ pub(crate) fn handle_unknown_rtp_packet(
 
     Ok((mid, rid, srid, payload_type))
 }
+
+/// handle_unknown_rtcp_packet consumes a batch of RTCP Packets and returns the MID, RtpStreamId
+/// and RepairedRtpStreamId their SDES items carry for the ssrc. It is used to demux an unknown
+/// SSRC whose RTP Packets arrive without header extensions.
+pub(crate) fn handle_unknown_rtcp_packet(
+    buf: &[u8],
+    ssrc: SSRC,
+) -> Result<(String, String, String)> {
+    let mut reader = buf;
+    let pkts = rtcp::packet::unmarshal(&mut reader)?;
+
+    let (mut mid, mut rid, mut srid) = (String::new(), String::new(), String::new());
+    for pkt in &pkts {
+        let sdes = match pkt.as_any().downcast_ref::<SourceDescription>() {
+            Some(sdes) => sdes,
+            None => continue,
+        };
+        for chunk in sdes.chunks.iter().filter(|c| c.source == ssrc) {
+            for item in &chunk.items {
+                let text = String::from_utf8(item.text.to_vec())?;
+                match item.sdes_type {
+                    SdesType::SdesMid => mid = text,
+                    SdesType::SdesRtpStreamId => rid = text,
+                    SdesType::SdesRepairedRtpStreamId => srid = text,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    Ok((mid, rid, srid))
+}
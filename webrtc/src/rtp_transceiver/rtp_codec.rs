@@ -98,6 +98,23 @@ pub struct RTCRtpHeaderExtensionCapability {
     pub uri: String,
 }
 
+/// RTCRtpHeaderExtensionToNegotiate selects whether a header extension registered on the
+/// [`MediaEngine`](crate::api::media_engine::MediaEngine) is negotiated for a single transceiver.
+/// A direction of [`RTCRtpTransceiverDirection::Inactive`] stops the extension, any other
+/// direction keeps it.
+/// <https://w3c.github.io/webrtc-extensions/#rtp-header-extension-control>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RTCRtpHeaderExtensionToNegotiate {
+    pub uri: String,
+    pub direction: RTCRtpTransceiverDirection,
+}
+
+impl RTCRtpHeaderExtensionToNegotiate {
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.direction == RTCRtpTransceiverDirection::Inactive
+    }
+}
+
 /// RTPHeaderExtensionParameter represents a negotiated RFC5285 RTP header extension.
 /// <https://w3c.github.io/webrtc-pc/#dictionary-rtcrtpheaderextensionparameters-members>
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
@@ -0,0 +1,320 @@
+use crate::api::media_engine::{MIME_TYPE_AV1, MIME_TYPE_H264, MIME_TYPE_VP8, MIME_TYPE_VP9};
+use crate::dtls_transport::RTCDtlsTransport;
+use crate::rtp_transceiver::rtp_codec::RTPCodecType;
+use crate::rtp_transceiver::SSRC;
+use crate::track::track_remote::TrackRemote;
+
+use bytes::Bytes;
+use interceptor::Attributes;
+use rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use rtp::seqnum;
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::time::{Duration, Instant};
+use util::sync::Mutex as SyncMutex;
+use util::sync::TaskGroup;
+use util::{Marshal, Unmarshal};
+
+/// How many packets of each layer are queued for the primary track
+const LAYER_QUEUE_SIZE: usize = 256;
+
+/// A keyframe of the selected layer is requested again this often until it arrives
+const KEYFRAME_REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// is_keyframe tells whether the RTP payload of a codec starts a keyframe, the point where a
+/// decoder can start decoding. Payloads of codecs it can't tell for, such as audio codecs, are
+/// all considered keyframes.
+pub fn is_keyframe(mime_type: &str, payload: &Bytes) -> bool {
+    if mime_type.eq_ignore_ascii_case(MIME_TYPE_VP8) {
+        rtp::codecs::vp8::is_keyframe(payload)
+    } else if mime_type.eq_ignore_ascii_case(MIME_TYPE_VP9) {
+        rtp::codecs::vp9::is_keyframe(payload)
+    } else if mime_type.eq_ignore_ascii_case(MIME_TYPE_H264) {
+        rtp::codecs::h264::is_keyframe(payload)
+    } else if mime_type.eq_ignore_ascii_case(MIME_TYPE_AV1) {
+        // N: the packet starts a new coded video sequence
+        payload.first().map_or(false, |b| b & 0x08 != 0)
+    } else {
+        true
+    }
+}
+
+/// patch_header writes the payload type, sequence number, timestamp and SSRC of header into
+/// raw, the marshaled RTP packet header was unmarshaled from.
+pub(crate) fn patch_header(raw: &mut [u8], header: &rtp::header::Header) {
+    raw[1] = (raw[1] & 0x80) | (header.payload_type & 0x7F);
+    raw[2..4].copy_from_slice(&header.sequence_number.to_be_bytes());
+    raw[4..8].copy_from_slice(&header.timestamp.to_be_bytes());
+    raw[8..12].copy_from_slice(&header.ssrc.to_be_bytes());
+}
+
+/// Forwarded is the newest packet forwarded to the primary track
+#[derive(Debug)]
+struct Forwarded {
+    sequence_number: u16,
+    timestamp: u32,
+    at: Instant,
+}
+
+/// LayerSwitcher picks the packets of the simulcast layers that make up the primary track of a
+/// receiver, and rewrites them so the primary track looks like a single stream across switches.
+///
+/// A switch happens on the first keyframe of the newly selected layer, until then the packets of
+/// the previous layer keep being forwarded. Sequence numbers continue where the previous layer
+/// left off and timestamps advance by the time elapsed since its last packet.
+///
+/// Layers are identified by their rid, or by their SSRC for streams without one.
+#[derive(Default, Debug)]
+pub(crate) struct LayerSwitcher<K = String> {
+    selected: K,
+    current: Option<K>,
+    ssrc: SSRC,
+    sequence_number_offset: u16,
+    timestamp_offset: u32,
+    last: Option<Forwarded>,
+}
+
+impl<K: PartialEq> LayerSwitcher<K> {
+    pub(crate) fn select<Q>(&mut self, layer: &Q)
+    where
+        Q: ToOwned<Owned = K> + ?Sized,
+    {
+        self.selected = layer.to_owned();
+    }
+
+    /// follow selects the layer unless it's already selected or forwarded, so the newest of
+    /// the streams identified by the same key takes over once it starts with a keyframe.
+    pub(crate) fn follow<Q>(&mut self, layer: &Q)
+    where
+        K: Borrow<Q>,
+        Q: PartialEq + ToOwned<Owned = K> + ?Sized,
+    {
+        if self.selected.borrow() != layer && self.current() != Some(layer) {
+            self.select(layer);
+        }
+    }
+
+    /// current returns the layer forwarded, None until the first keyframe arrived.
+    pub(crate) fn current<Q>(&self) -> Option<&Q>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+    {
+        self.current.as_ref().map(Borrow::borrow)
+    }
+
+    /// is_switching returns true until a keyframe of the selected layer arrived.
+    pub(crate) fn is_switching(&self) -> bool {
+        self.current.as_ref() != Some(&self.selected)
+    }
+
+    /// forward rewrites the header of a packet received on the given layer into the header of
+    /// a packet of the primary track. It returns false for packets that aren't part of the
+    /// primary track.
+    pub(crate) fn forward<Q>(
+        &mut self,
+        layer: &Q,
+        header: &mut rtp::header::Header,
+        keyframe: bool,
+        clock_rate: u32,
+        now: Instant,
+    ) -> bool
+    where
+        K: Borrow<Q>,
+        Q: PartialEq + ToOwned<Owned = K> + ?Sized,
+    {
+        if self.selected.borrow() == layer && self.is_switching() {
+            if !keyframe {
+                return false;
+            }
+
+            match &self.last {
+                Some(last) => {
+                    let elapsed = now.saturating_duration_since(last.at);
+                    let ticks = ((elapsed.as_secs_f64() * clock_rate as f64) as u32).max(1);
+                    self.sequence_number_offset = last
+                        .sequence_number
+                        .wrapping_add(1)
+                        .wrapping_sub(header.sequence_number);
+                    self.timestamp_offset = last
+                        .timestamp
+                        .wrapping_add(ticks)
+                        .wrapping_sub(header.timestamp);
+                }
+                None => self.ssrc = header.ssrc,
+            }
+            self.current = Some(layer.to_owned());
+        }
+
+        if self.current() != Some(layer) {
+            return false;
+        }
+
+        header.ssrc = self.ssrc;
+        header.sequence_number = header
+            .sequence_number
+            .wrapping_add(self.sequence_number_offset);
+        header.timestamp = header.timestamp.wrapping_add(self.timestamp_offset);
+
+        let is_newest = self.last.as_ref().map_or(true, |last| {
+            seqnum::is_newer(header.sequence_number, last.sequence_number)
+        });
+        if is_newest {
+            self.last = Some(Forwarded {
+                sequence_number: header.sequence_number,
+                timestamp: header.timestamp,
+                at: now,
+            });
+        }
+
+        true
+    }
+}
+
+/// LayerSelector feeds the primary track of a simulcast receiver with the layer selected by
+/// `RTCRtpReceiver::select_layer`. It subscribes to every layer, so the tracks of the layers
+/// remain readable on their own.
+pub(crate) struct LayerSelector {
+    primary: Arc<TrackRemote>,
+    transport: Arc<RTCDtlsTransport>,
+    switcher: SyncMutex<LayerSwitcher>,
+    // tids of the layer tracks subscribed to
+    attached: SyncMutex<HashSet<usize>>,
+    last_keyframe_request: SyncMutex<Option<Instant>>,
+}
+
+impl LayerSelector {
+    pub(crate) fn new(primary: Arc<TrackRemote>, transport: Arc<RTCDtlsTransport>) -> Self {
+        LayerSelector {
+            primary,
+            transport,
+            switcher: SyncMutex::new(LayerSwitcher::default()),
+            attached: SyncMutex::new(HashSet::new()),
+            last_keyframe_request: SyncMutex::new(None),
+        }
+    }
+
+    pub(crate) fn primary(&self) -> Arc<TrackRemote> {
+        Arc::clone(&self.primary)
+    }
+
+    pub(crate) fn current_layer(&self) -> Option<String> {
+        self.switcher.lock().current().map(str::to_owned)
+    }
+
+    /// select switches the primary track to the layer once a keyframe of it arrives, and
+    /// requests one.
+    pub(crate) async fn select(&self, layer: &TrackRemote) {
+        let switching = {
+            let mut switcher = self.switcher.lock();
+            switcher.select(layer.rid());
+            switcher.is_switching()
+        };
+
+        if switching {
+            *self.last_keyframe_request.lock() = None;
+            self.request_keyframe(layer.ssrc()).await;
+        }
+    }
+
+    /// attach starts feeding the packets of the layer into the selection, unless it already is.
+    pub(crate) async fn attach(self: &Arc<Self>, layer: Arc<TrackRemote>, tasks: &TaskGroup) {
+        if !self.attached.lock().insert(layer.tid()) {
+            return;
+        }
+
+        let mut subscriber = layer.subscribe(LAYER_QUEUE_SIZE).await;
+        let selector = Arc::downgrade(self);
+        tasks.spawn(async move {
+            let mut b = vec![0u8; crate::RECEIVE_MTU];
+            while let Ok((n, attributes)) = subscriber.read(&mut b).await {
+                let selector = match selector.upgrade() {
+                    Some(selector) => selector,
+                    None => return,
+                };
+                selector
+                    .on_packet(&layer, Bytes::copy_from_slice(&b[..n]), attributes)
+                    .await;
+            }
+        });
+    }
+
+    async fn on_packet(&self, layer: &TrackRemote, data: Bytes, attributes: Attributes) {
+        let mut buf = &data[..];
+        let mut pkt = match rtp::packet::Packet::unmarshal(&mut buf) {
+            Ok(pkt) => pkt,
+            Err(_) => return,
+        };
+        if self.primary.check_and_update_track(&data).await.is_err() {
+            return;
+        }
+
+        let codec = self.primary.codec().await;
+        let keyframe = self.primary.kind() != RTPCodecType::Video
+            || is_keyframe(&codec.capability.mime_type, &pkt.payload);
+
+        let (forward, awaiting_keyframe) = {
+            let mut switcher = self.switcher.lock();
+            let forward = switcher.forward(
+                layer.rid(),
+                &mut pkt.header,
+                keyframe,
+                codec.capability.clock_rate,
+                Instant::now(),
+            );
+            let awaiting_keyframe = switcher.is_switching() && switcher.selected == layer.rid();
+            (forward, awaiting_keyframe)
+        };
+
+        if awaiting_keyframe {
+            self.request_keyframe(layer.ssrc()).await;
+        }
+        if forward {
+            if self.primary.ssrc() == 0 {
+                self.primary.set_ssrc(pkt.header.ssrc);
+            }
+            if let Ok(data) = pkt.marshal() {
+                self.primary.deliver(data, &attributes);
+            }
+        }
+    }
+
+    /// request_keyframe sends a PLI for the layer, at most once per KEYFRAME_REQUEST_INTERVAL.
+    /// The SSRC of a layer isn't known before its first packet, the request is sent then.
+    async fn request_keyframe(&self, media_ssrc: SSRC) {
+        if media_ssrc == 0 {
+            return;
+        }
+        {
+            let mut last_keyframe_request = self.last_keyframe_request.lock();
+            let now = Instant::now();
+            if let Some(last) = *last_keyframe_request {
+                if now.saturating_duration_since(last) < KEYFRAME_REQUEST_INTERVAL {
+                    return;
+                }
+            }
+            *last_keyframe_request = Some(now);
+        }
+
+        if let Err(err) = self
+            .transport
+            .write_rtcp(&[Box::new(PictureLossIndication {
+                sender_ssrc: 0,
+                media_ssrc,
+            })])
+            .await
+        {
+            log::warn!(
+                "failed to request a keyframe for SSRC {}: {}",
+                media_ssrc,
+                err
+            );
+        }
+    }
+
+    /// close ends the primary track, its reads fail once the packets queued are read.
+    pub(crate) fn close(&self) {
+        self.primary.close_subscribers();
+    }
+}
@@ -1,28 +1,47 @@
 #[cfg(test)]
 mod rtp_receiver_test;
 
+pub mod layer_selector;
+pub mod packet_tap;
+
 use crate::api::media_engine::MediaEngine;
 use crate::dtls_transport::RTCDtlsTransport;
 use crate::error::{flatten_errs, Error, Result};
 use crate::peer_connection::sdp::TrackDetails;
+use crate::peer_connection::TASKS_CLOSE_TIMEOUT;
+use crate::rtp_transceiver::fmtp;
 use crate::rtp_transceiver::rtp_codec::{
-    codec_parameters_fuzzy_search, CodecMatch, RTCRtpCodecCapability, RTCRtpCodecParameters,
-    RTCRtpParameters, RTPCodecType,
+    codec_parameters_fuzzy_search, CodecMatch, RTCRtpCodecParameters,
+    RTCRtpHeaderExtensionToNegotiate, RTCRtpParameters, RTPCodecType,
 };
 use crate::rtp_transceiver::rtp_transceiver_direction::RTCRtpTransceiverDirection;
 use crate::rtp_transceiver::{
-    create_stream_info, RTCRtpDecodingParameters, RTCRtpReceiveParameters, SSRC,
+    create_stream_info, PayloadType, RTCRtpDecodingParameters, RTCRtpReceiveParameters, SSRC,
 };
 use crate::track::track_remote::TrackRemote;
 use crate::track::{TrackStream, TrackStreams};
+use layer_selector::LayerSelector;
+use packet_tap::{PacketTap, PacketTaps, RecoverySource, TapInfo};
 
+use async_trait::async_trait;
+use bytes::Bytes;
+use interceptor::nack::ATTR_RETRANSMISSION;
 use interceptor::stream_info::RTPHeaderExtension;
-use interceptor::{Attributes, Interceptor};
+use interceptor::{Attributes, Interceptor, RTPReader};
 use log::trace;
+use std::collections::HashMap;
 use std::fmt;
 
 use std::sync::Arc;
-use tokio::sync::{watch, Mutex, RwLock};
+use std::time::SystemTime;
+use tokio::sync::{mpsc, watch, Mutex, RwLock};
+use tokio::time::{Duration, Instant};
+use util::sync::Mutex as SyncMutex;
+use util::sync::TaskGroup;
+use util::{Marshal, Unmarshal};
+
+/// How many packets recovered from the repair stream are kept until the track reads them
+const REPAIRED_QUEUE_SIZE: usize = 64;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
@@ -142,6 +161,67 @@ impl State {
     }
 }
 
+/// RepairedPacket is a packet recovered from a retransmission, rewritten to the SSRC, payload
+/// type and sequence number of the original
+pub(crate) struct RepairedPacket {
+    data: Bytes,
+    attributes: Attributes,
+}
+
+/// RepairableStream reads the SRTP stream of a media stream along with the packets recovered
+/// from its repair stream, so that the interceptors bound to the media stream see both.
+/// Recovered packets are read with the `ATTR_RETRANSMISSION` attribute.
+pub(crate) struct RepairableStream {
+    stream: Arc<srtp::stream::Stream>,
+    repaired_rx: Mutex<mpsc::Receiver<RepairedPacket>>,
+}
+
+impl RepairableStream {
+    /// new returns the reader of stream and the sender of the packets recovered for it.
+    pub(crate) fn new(stream: Arc<srtp::stream::Stream>) -> (Self, mpsc::Sender<RepairedPacket>) {
+        let (repaired_tx, repaired_rx) = mpsc::channel(REPAIRED_QUEUE_SIZE);
+        (
+            RepairableStream {
+                stream,
+                repaired_rx: Mutex::new(repaired_rx),
+            },
+            repaired_tx,
+        )
+    }
+}
+
+#[async_trait]
+impl RTPReader for RepairableStream {
+    async fn read(
+        &self,
+        buf: &mut [u8],
+        a: &Attributes,
+    ) -> std::result::Result<(usize, Attributes), interceptor::Error> {
+        let mut repaired_rx = self.repaired_rx.lock().await;
+        tokio::select! {
+            result = self.stream.read(buf) => Ok((result?, a.clone())),
+            Some(repaired) = repaired_rx.recv() => {
+                let n = repaired.data.len();
+                if buf.len() < n {
+                    return Err(interceptor::Error::ErrShortBuffer);
+                }
+                buf[..n].copy_from_slice(&repaired.data);
+
+                let mut attributes = repaired.attributes;
+                attributes.insert(ATTR_RETRANSMISSION, 1);
+                Ok((n, attributes))
+            }
+        }
+    }
+}
+
+/// Packet arrival bookkeeping of a single track (simulcast layer) of a receiver
+struct TrackActivity {
+    last_packet: Instant,
+    has_received: bool,
+    inactive: bool,
+}
+
 pub struct RTPReceiverInternal {
     pub(crate) kind: RTPCodecType,
 
@@ -152,6 +232,25 @@ pub struct RTPReceiverInternal {
     tracks: RwLock<Vec<TrackStreams>>,
 
     transceiver_codecs: Mutex<Option<Arc<Mutex<Vec<RTCRtpCodecParameters>>>>>,
+    transceiver_header_extensions: Mutex<Option<Arc<Mutex<Vec<RTCRtpHeaderExtensionToNegotiate>>>>>,
+
+    // Codecs of the media section of the remote description, with the remote's payload types
+    negotiated_codecs: Mutex<Vec<RTCRtpCodecParameters>>,
+    // Codecs and header extensions given to receive, which take precedence over the negotiated
+    // ones when set
+    receive_parameters: Mutex<Option<RTCRtpParameters>>,
+
+    // Tracks are muted after this long without RTP, keyed by track tid
+    inactivity_timeout: Option<Duration>,
+    track_activity: SyncMutex<HashMap<usize, TrackActivity>>,
+
+    packet_taps: PacketTaps,
+
+    // Feeds the primary track with the simulcast layer selected by select_layer
+    layer_selector: SyncMutex<Option<Arc<LayerSelector>>>,
+
+    // the repair stream readers and track fan outs, waited for on stop
+    pub(crate) tasks: TaskGroup,
 
     transport: Arc<RTCDtlsTransport>,
     media_engine: Arc<MediaEngine>,
@@ -258,12 +357,14 @@ impl RTPReceiverInternal {
 
         //log::debug!("read_rtp enter tracks tid {}", tid);
         let mut rtp_interceptor = None;
+        let mut rid = String::new();
         //let mut ssrc = 0;
         {
             let tracks = self.tracks.read().await;
             for t in &*tracks {
                 if t.track.tid() == tid {
                     rtp_interceptor = t.stream.rtp_interceptor.clone();
+                    rid = t.track.rid().to_owned();
                     //ssrc = t.track.ssrc();
                     break;
                 }
@@ -283,6 +384,14 @@ impl RTPReceiverInternal {
             //);
             let mut current_state = *state_watch_rx.borrow();
             loop {
+                let inactivity_deadline = self.inactivity_deadline(tid);
+                let inactivity_timer = async {
+                    match inactivity_deadline {
+                        Some(deadline) => tokio::time::sleep_until(deadline).await,
+                        None => std::future::pending().await,
+                    }
+                };
+
                 tokio::select! {
                     _ = state_watch_rx.changed() => {
                         let new_state = *state_watch_rx.borrow();
@@ -299,8 +408,17 @@ impl RTPReceiverInternal {
                             trace!("Dropping {} read bytes received while RTPReceiver was paused", result.0);
                             continue;
                         }
+                        self.packet_taps.deliver(&b[..result.0], || TapInfo {
+                            arrival_time: SystemTime::now(),
+                            recovery_source: RecoverySource::from_attributes(&result.1),
+                            rid: rid.clone(),
+                        });
+                        self.on_rtp_received(tid).await;
                         return Ok(result);
                     }
+                    _ = inactivity_timer => {
+                        self.on_rtp_inactive(tid).await;
+                    }
                 }
             }
         } else {
@@ -309,22 +427,157 @@ impl RTPReceiverInternal {
         }
     }
 
+    /// repaired_tx returns the sender of the packets recovered for the media stream of the
+    /// track tid, if the stream is known yet.
+    async fn repaired_tx(&self, tid: usize) -> Option<mpsc::Sender<RepairedPacket>> {
+        let tracks = self.tracks.read().await;
+        tracks
+            .iter()
+            .find(|t| t.track.tid() == tid)
+            .and_then(|t| t.stream.repaired_tx.clone())
+    }
+
+    /// inactivity_deadline returns when the track is considered inactive if no RTP
+    /// arrives, or None if it already is or inactivity detection is disabled.
+    fn inactivity_deadline(&self, tid: usize) -> Option<Instant> {
+        let timeout = self.inactivity_timeout?;
+
+        let mut track_activity = self.track_activity.lock();
+        let activity = track_activity.entry(tid).or_insert_with(|| TrackActivity {
+            last_packet: Instant::now(),
+            has_received: false,
+            inactive: false,
+        });
+
+        if activity.inactive {
+            None
+        } else {
+            Some(activity.last_packet + timeout)
+        }
+    }
+
+    async fn on_rtp_received(&self, tid: usize) {
+        let update_muted = {
+            let mut track_activity = self.track_activity.lock();
+            let activity = track_activity.entry(tid).or_insert_with(|| TrackActivity {
+                last_packet: Instant::now(),
+                has_received: false,
+                inactive: false,
+            });
+
+            let update_muted = activity.inactive || !activity.has_received;
+            activity.last_packet = Instant::now();
+            activity.has_received = true;
+            activity.inactive = false;
+            update_muted
+        };
+
+        if update_muted {
+            self.update_muted().await;
+        }
+    }
+
+    async fn on_rtp_inactive(&self, tid: usize) {
+        {
+            let mut track_activity = self.track_activity.lock();
+            if let Some(activity) = track_activity.get_mut(&tid) {
+                trace!(
+                    "Track {} received no RTP within {:?}",
+                    tid,
+                    self.inactivity_timeout
+                );
+                activity.inactive = true;
+            }
+        }
+
+        self.update_muted().await;
+    }
+
+    /// update_muted mutes the tracks while the receiver is paused or every track
+    /// being read has gone inactive, so a single simulcast layer stopping doesn't
+    /// mute the others.
+    pub(crate) async fn update_muted(&self) {
+        let muted = self.current_state() != State::Started || {
+            let track_activity = self.track_activity.lock();
+            !track_activity.is_empty() && track_activity.values().all(|a| a.inactive)
+        };
+
+        let primary = self.layer_selector.lock().as_ref().map(|s| s.primary());
+        if let Some(primary) = primary {
+            primary.set_muted(muted).await;
+        }
+
+        let tracks = self.tracks.read().await;
+        for t in &*tracks {
+            // TODO: If we introduce futures as a direct dependency this and other futures could be
+            // ran concurrently with [`join_all`](https://docs.rs/futures/0.3.21/futures/future/fn.join_all.html)
+            t.track.set_muted(muted).await;
+        }
+    }
+
     async fn get_parameters(&self) -> RTCRtpParameters {
+        if let Some(parameters) = &*self.receive_parameters.lock().await {
+            return parameters.clone();
+        }
+
+        let header_extensions_to_negotiate = {
+            let transceiver_header_extensions = self.transceiver_header_extensions.lock().await;
+            if let Some(header_extensions) = &*transceiver_header_extensions {
+                header_extensions.lock().await.clone()
+            } else {
+                vec![]
+            }
+        };
+
         let mut parameters = self
             .media_engine
-            .get_rtp_parameters_by_kind(self.kind, RTCRtpTransceiverDirection::Recvonly)
+            .get_rtp_parameters_by_kind(
+                self.kind,
+                RTCRtpTransceiverDirection::Recvonly,
+                &header_extensions_to_negotiate,
+            )
             .await;
 
-        let transceiver_codecs = self.transceiver_codecs.lock().await;
-        if let Some(codecs) = &*transceiver_codecs {
-            let mut c = codecs.lock().await;
-            parameters.codecs =
-                RTPReceiverInternal::get_codecs(&mut c, self.kind, &self.media_engine).await;
+        let negotiated_codecs = self.negotiated_codecs.lock().await;
+        if !negotiated_codecs.is_empty() {
+            parameters.codecs = negotiated_codecs.clone();
+        } else {
+            let transceiver_codecs = self.transceiver_codecs.lock().await;
+            if let Some(codecs) = &*transceiver_codecs {
+                let mut c = codecs.lock().await;
+                parameters.codecs =
+                    RTPReceiverInternal::get_codecs(&mut c, self.kind, &self.media_engine).await;
+            }
         }
 
         parameters
     }
 
+    /// get_parameters_by_payload_type returns the parameters of the negotiated codec with the
+    /// payload type, or None if the codecs weren't negotiated for this receiver. Codecs given to
+    /// receive count as negotiated.
+    pub(crate) async fn get_parameters_by_payload_type(
+        &self,
+        payload_type: PayloadType,
+    ) -> Option<RTCRtpParameters> {
+        let codec = {
+            let receive_parameters = self.receive_parameters.lock().await;
+            let negotiated_codecs = self.negotiated_codecs.lock().await;
+            let codecs = match &*receive_parameters {
+                Some(parameters) => &parameters.codecs,
+                None => &*negotiated_codecs,
+            };
+            codecs
+                .iter()
+                .find(|c| c.payload_type == payload_type)
+                .cloned()?
+        };
+
+        let mut parameters = self.get_parameters().await;
+        parameters.codecs = vec![codec];
+        Some(parameters)
+    }
+
     pub(crate) async fn get_codecs(
         codecs: &mut [RTCRtpCodecParameters],
         kind: RTPCodecType,
@@ -408,6 +661,7 @@ impl RTCRtpReceiver {
         transport: Arc<RTCDtlsTransport>,
         media_engine: Arc<MediaEngine>,
         interceptor: Arc<dyn Interceptor + Send + Sync>,
+        inactivity_timeout: Option<Duration>,
     ) -> Self {
         let (state_tx, state_rx) = watch::channel(State::Unstarted);
 
@@ -428,6 +682,16 @@ impl RTCRtpReceiver {
                 state_rx,
 
                 transceiver_codecs: Mutex::new(None),
+                transceiver_header_extensions: Mutex::new(None),
+                negotiated_codecs: Mutex::new(vec![]),
+                receive_parameters: Mutex::new(None),
+
+                inactivity_timeout,
+                track_activity: SyncMutex::new(HashMap::new()),
+
+                packet_taps: PacketTaps::default(),
+                layer_selector: SyncMutex::new(None),
+                tasks: TaskGroup::new(),
             }),
         }
     }
@@ -444,6 +708,15 @@ impl RTCRtpReceiver {
         *transceiver_codecs = codecs;
     }
 
+    pub(crate) async fn set_transceiver_header_extensions(
+        &self,
+        header_extensions: Option<Arc<Mutex<Vec<RTCRtpHeaderExtensionToNegotiate>>>>,
+    ) {
+        let mut transceiver_header_extensions =
+            self.internal.transceiver_header_extensions.lock().await;
+        *transceiver_header_extensions = header_extensions;
+    }
+
     /// transport returns the currently-configured *DTLSTransport or nil
     /// if one has not yet been configured
     pub fn transport(&self) -> Arc<RTCDtlsTransport> {
@@ -451,11 +724,26 @@ impl RTCRtpReceiver {
     }
 
     /// get_parameters describes the current configuration for the encoding and
-    /// transmission of media on the receiver's track.
+    /// transmission of media on the receiver's track. Once a remote description was applied, the
+    /// codecs are the ones negotiated for the media section of the receiver.
     pub async fn get_parameters(&self) -> RTCRtpParameters {
         self.internal.get_parameters().await
     }
 
+    /// set_negotiated_codecs updates the codecs negotiated for the receiver from a remote
+    /// description, and the codec of its tracks if the one they receive is no longer negotiated.
+    pub(crate) async fn set_negotiated_codecs(&self, codecs: Vec<RTCRtpCodecParameters>) {
+        {
+            let mut negotiated_codecs = self.internal.negotiated_codecs.lock().await;
+            *negotiated_codecs = codecs;
+        }
+
+        let parameters = self.internal.get_parameters().await;
+        for track in self.tracks().await {
+            track.set_negotiated_parameters(&parameters).await;
+        }
+    }
+
     /// SetRTPParameters applies provided RTPParameters the RTPReceiver's tracks.
     /// This method is part of the ORTC API. It is not
     /// meant to be used together with the basic WebRTC API.
@@ -482,8 +770,13 @@ impl RTCRtpReceiver {
         }
     }
 
-    /// track returns the RtpTransceiver TrackRemote
+    /// track returns the RtpTransceiver TrackRemote. For a simulcast receiver it's the primary
+    /// track once a layer was selected with `select_layer`, None before.
     pub async fn track(&self) -> Option<Arc<TrackRemote>> {
+        if let Some(selector) = &*self.internal.layer_selector.lock() {
+            return Some(selector.primary());
+        }
+
         let tracks = self.internal.tracks.read().await;
         if tracks.len() != 1 {
             None
@@ -499,7 +792,77 @@ impl RTCRtpReceiver {
         tracks.iter().map(|t| Arc::clone(&t.track)).collect()
     }
 
-    /// receive initialize the track and starts all the transports
+    /// select_layer surfaces the simulcast layer with the given rid through the primary track of
+    /// the receiver, which is returned and also returned by `track` from then on.
+    ///
+    /// The primary track switches to a newly selected layer on its first keyframe, which is
+    /// requested with a PLI, and keeps delivering the previous layer until then. Its sequence
+    /// numbers and timestamps continue across switches, so decoders reading it don't reset. The
+    /// tracks of the layers, returned by `tracks`, remain readable on their own.
+    pub async fn select_layer(&self, rid: &str) -> Result<Arc<TrackRemote>> {
+        let (layer, receiving) = {
+            let tracks = self.internal.tracks.read().await;
+            let layer = tracks
+                .iter()
+                .find(|t| t.track.rid() == rid)
+                .map(|t| Arc::clone(&t.track))
+                .ok_or(Error::ErrRTPReceiverForRIDTrackStreamNotFound)?;
+            let receiving: Vec<Arc<TrackRemote>> = tracks
+                .iter()
+                .filter(|t| t.stream.rtp_interceptor.is_some())
+                .map(|t| Arc::clone(&t.track))
+                .collect();
+            (layer, receiving)
+        };
+
+        let existing = self.internal.layer_selector.lock().clone();
+        let selector = match existing {
+            Some(selector) => selector,
+            None => {
+                let primary = Arc::new(TrackRemote::new(
+                    self.receive_mtu,
+                    self.kind,
+                    0,
+                    String::new(),
+                    Arc::downgrade(&self.internal),
+                    Arc::clone(&self.internal.media_engine),
+                    Arc::clone(&self.internal.interceptor),
+                ));
+                primary.serve_deliveries().await;
+                primary.set_id(layer.id().await).await;
+                primary.set_stream_id(layer.stream_id().await).await;
+                primary
+                    .set_negotiated_parameters(&self.get_parameters().await)
+                    .await;
+
+                let selector = Arc::new(LayerSelector::new(primary, Arc::clone(&self.transport)));
+                let mut layer_selector = self.internal.layer_selector.lock();
+                Arc::clone(layer_selector.get_or_insert(selector))
+            }
+        };
+
+        for track in receiving {
+            selector.attach(track, &self.internal.tasks).await;
+        }
+        selector.select(&layer).await;
+        self.internal.update_muted().await;
+
+        Ok(selector.primary())
+    }
+
+    /// selected_layer returns the rid of the simulcast layer the primary track delivers, None
+    /// until the first keyframe of the layer chosen with `select_layer` arrived.
+    pub fn selected_layer(&self) -> Option<String> {
+        self.internal
+            .layer_selector
+            .lock()
+            .as_ref()
+            .and_then(|s| s.current_layer())
+    }
+
+    /// receive initialize the track and starts all the transports. The codecs and header
+    /// extensions of the parameters are used instead of negotiated ones if they hold any codec,
+    /// and the payload type of an encoding selects the codec its stream starts with.
     pub async fn receive(&self, parameters: &RTCRtpReceiveParameters) -> Result<()> {
         let receiver = Arc::downgrade(&self.internal);
 
@@ -509,6 +872,12 @@ impl RTCRtpReceiver {
         }
         self.internal.start()?;
 
+        let explicit = !parameters.rtp_parameters.codecs.is_empty();
+        if explicit {
+            let mut receive_parameters = self.internal.receive_parameters.lock().await;
+            *receive_parameters = Some(parameters.rtp_parameters.clone());
+        }
+
         let (global_params, interceptor, media_engine) = {
             (
                 self.internal.get_parameters().await,
@@ -517,63 +886,49 @@ impl RTCRtpReceiver {
             )
         };
 
-        let codec = if let Some(codec) = global_params.codecs.first() {
-            codec.capability.clone()
-        } else {
-            RTCRtpCodecCapability::default()
-        };
+        let negotiated = explicit || !self.internal.negotiated_codecs.lock().await.is_empty();
 
         for encoding in &parameters.encodings {
-            let (stream_info, rtp_read_stream, rtp_interceptor, rtcp_read_stream, rtcp_interceptor) =
-                if encoding.ssrc != 0 {
-                    let stream_info = create_stream_info(
-                        "".to_owned(),
-                        encoding.ssrc,
-                        0,
-                        codec.clone(),
-                        &global_params.header_extensions,
-                    );
-                    let (rtp_read_stream, rtp_interceptor, rtcp_read_stream, rtcp_interceptor) =
-                        self.transport
-                            .streams_for_ssrc(encoding.ssrc, &stream_info, &interceptor)
-                            .await?;
-
-                    (
-                        Some(stream_info),
-                        rtp_read_stream,
-                        rtp_interceptor,
-                        rtcp_read_stream,
-                        rtcp_interceptor,
-                    )
-                } else {
-                    (None, None, None, None, None)
-                };
+            let codec = global_params
+                .codecs
+                .iter()
+                .find(|c| encoding.payload_type != 0 && c.payload_type == encoding.payload_type)
+                .or_else(|| global_params.codecs.first())
+                .map(|c| c.capability.clone())
+                .unwrap_or_default();
+
+            let stream = if encoding.ssrc != 0 {
+                let stream_info = create_stream_info(
+                    "".to_owned(),
+                    encoding.ssrc,
+                    encoding.payload_type,
+                    codec.clone(),
+                    &global_params.header_extensions,
+                );
+                self.transport
+                    .streams_for_ssrc(encoding.ssrc, &stream_info, &interceptor)
+                    .await?
+            } else {
+                TrackStream::default()
+            };
+
+            let track = Arc::new(TrackRemote::new(
+                self.receive_mtu,
+                self.kind,
+                encoding.ssrc,
+                encoding.rid.clone(),
+                receiver.clone(),
+                Arc::clone(&media_engine),
+                Arc::clone(&interceptor),
+            ));
+            if negotiated {
+                track.set_negotiated_parameters(&global_params).await;
+            }
 
             let t = TrackStreams {
-                track: Arc::new(TrackRemote::new(
-                    self.receive_mtu,
-                    self.kind,
-                    encoding.ssrc,
-                    encoding.rid.clone(),
-                    receiver.clone(),
-                    Arc::clone(&media_engine),
-                    Arc::clone(&interceptor),
-                )),
-                stream: TrackStream {
-                    stream_info,
-                    rtp_read_stream,
-                    rtp_interceptor,
-                    rtcp_read_stream,
-                    rtcp_interceptor,
-                },
-
-                repair_stream: TrackStream {
-                    stream_info: None,
-                    rtp_read_stream: None,
-                    rtp_interceptor: None,
-                    rtcp_read_stream: None,
-                    rtcp_interceptor: None,
-                },
+                track,
+                stream,
+                repair_stream: TrackStream::default(),
             };
 
             {
@@ -590,23 +945,13 @@ impl RTCRtpReceiver {
                     codec.clone(),
                     &global_params.header_extensions,
                 );
-                let (rtp_read_stream, rtp_interceptor, rtcp_read_stream, rtcp_interceptor) = self
+                let repair_stream = self
                     .transport
                     .streams_for_ssrc(rtx_ssrc, &stream_info, &interceptor)
                     .await?;
 
-                self.receive_for_rtx(
-                    rtx_ssrc,
-                    "".to_owned(),
-                    TrackStream {
-                        stream_info: Some(stream_info),
-                        rtp_read_stream,
-                        rtp_interceptor,
-                        rtcp_read_stream,
-                        rtcp_interceptor,
-                    },
-                )
-                .await?;
+                self.receive_for_rtx(rtx_ssrc, "".to_owned(), repair_stream)
+                    .await?;
             }
         }
 
@@ -663,22 +1008,23 @@ impl RTCRtpReceiver {
             encoding.rtx.ssrc = incoming.repair_ssrc;
         }
 
-        if let Err(err) = self.receive(&RTCRtpReceiveParameters { encodings }).await {
+        let parameters = RTCRtpReceiveParameters {
+            rtp_parameters: RTCRtpParameters::default(),
+            encodings,
+        };
+        if let Err(err) = self.receive(&parameters).await {
             log::warn!("RTPReceiver Receive failed {}", err);
             return;
         }
 
         // set track id and label early so they can be set as new track information
         // is received from the SDP.
-        let is_unpaused = self.current_state() == State::Started;
         for track_remote in &self.tracks().await {
             track_remote.set_id(incoming.id.clone()).await;
             track_remote.set_stream_id(incoming.stream_id.clone()).await;
-
-            if is_unpaused {
-                track_remote.fire_onunmute().await;
-            }
         }
+
+        self.internal.update_muted().await;
     }
 
     /// Stop irreversibly stops the RTPReceiver
@@ -731,6 +1077,11 @@ impl RTCRtpReceiver {
             }
         }
 
+        self.internal.tasks.close(TASKS_CLOSE_TIMEOUT).await;
+        if let Some(selector) = &*self.internal.layer_selector.lock() {
+            selector.close();
+        }
+
         flatten_errs(errs)
     }
 
@@ -739,6 +1090,13 @@ impl RTCRtpReceiver {
         self.internal.read_rtp(b, tid).await
     }
 
+    /// add_packet_tap returns a [`PacketTap`] receiving a copy of every RTP packet delivered to
+    /// the tracks of this receiver, including packets recovered from retransmissions. Up to
+    /// capacity packets are queued for the tap, further packets are dropped until it catches up.
+    pub fn add_packet_tap(&self, capacity: usize) -> PacketTap {
+        self.internal.packet_taps.add(capacity)
+    }
+
     /// receive_for_rid is the sibling of Receive expect for RIDs instead of SSRCs
     /// It populates all the internal state for the given RID
     pub(crate) async fn receive_for_rid(
@@ -747,27 +1105,39 @@ impl RTCRtpReceiver {
         params: RTCRtpParameters,
         stream: TrackStream,
     ) -> Result<Arc<TrackRemote>> {
-        let mut tracks = self.internal.tracks.write().await;
-        for t in &mut *tracks {
-            if t.track.rid() == rid {
-                t.track.set_kind(self.kind);
-                if let Some(codec) = params.codecs.first() {
-                    t.track.set_codec(codec.clone()).await;
-                }
-                t.track.set_params(params.clone()).await;
-                t.track
-                    .set_ssrc(stream.stream_info.as_ref().map_or(0, |s| s.ssrc));
-                t.stream = stream;
-                return Ok(Arc::clone(&t.track));
+        let track = {
+            let mut tracks = self.internal.tracks.write().await;
+            let t = tracks
+                .iter_mut()
+                .find(|t| t.track.rid() == rid)
+                .ok_or(Error::ErrRTPReceiverForRIDTrackStreamNotFound)?;
+
+            t.track.set_kind(self.kind);
+            if let Some(codec) = params.codecs.first() {
+                t.track.set_codec(codec.clone()).await;
             }
+            t.track.set_params(params.clone()).await;
+            t.track
+                .set_ssrc(stream.stream_info.as_ref().map_or(0, |s| s.ssrc));
+            t.stream = stream;
+            Arc::clone(&t.track)
+        };
+
+        // a layer starting after one was selected is part of the selection as well
+        let selector = self.internal.layer_selector.lock().clone();
+        if let Some(selector) = selector {
+            selector
+                .attach(Arc::clone(&track), &self.internal.tasks)
+                .await;
         }
 
-        Err(Error::ErrRTPReceiverForRIDTrackStreamNotFound)
+        Ok(track)
     }
 
     /// receiveForRtx starts a routine that processes the repair stream
-    /// These packets aren't exposed to the user yet, but we need to process them for
-    /// TWCC
+    /// Retransmitted packets are restored to the packets they repair and read through the
+    /// interceptors of the media stream, packets without payload, such as padding for probing,
+    /// are only processed for TWCC
     pub(crate) async fn receive_for_rtx(
         &self,
         ssrc: SSRC,
@@ -782,14 +1152,70 @@ impl RTCRtpReceiver {
 
                 let receive_mtu = self.receive_mtu;
                 let track = t.clone();
-                tokio::spawn(async move {
+                let internal = Arc::downgrade(&self.internal);
+                let media_engine = Arc::clone(&self.internal.media_engine);
+                self.internal.tasks.spawn(async move {
                     let a = Attributes::new();
                     let mut b = vec![0u8; receive_mtu];
+                    // The RTX payload type of the stream and its associated payload type
+                    let mut apt: Option<(PayloadType, PayloadType)> = None;
+                    // The media stream of a simulcast layer may start after its repair stream
+                    let mut repaired_tx = track.stream.repaired_tx.clone();
                     while let Some(repair_rtp_interceptor) = &track.repair_stream.rtp_interceptor {
                         //TODO: cancel repair_rtp_interceptor.read gracefully
                         //println!("repair_rtp_interceptor read begin with ssrc={}", ssrc);
-                        if repair_rtp_interceptor.read(&mut b, &a).await.is_err() {
-                            break;
+                        let (n, attributes) = match repair_rtp_interceptor.read(&mut b, &a).await {
+                            Ok(result) => result,
+                            Err(_) => break,
+                        };
+
+                        let mut buf = &b[..n];
+                        let pkt = match rtp::packet::Packet::unmarshal(&mut buf) {
+                            Ok(pkt) => pkt,
+                            Err(_) => continue,
+                        };
+
+                        let payload_type = match apt {
+                            Some((rtx_payload_type, payload_type))
+                                if rtx_payload_type == pkt.header.payload_type =>
+                            {
+                                payload_type
+                            }
+                            _ => match Self::associated_payload_type(
+                                &media_engine,
+                                pkt.header.payload_type,
+                            )
+                            .await
+                            {
+                                Some(payload_type) => {
+                                    apt = Some((pkt.header.payload_type, payload_type));
+                                    payload_type
+                                }
+                                // the payload type of the track if it's unknown
+                                None => track.track.payload_type(),
+                            },
+                        };
+
+                        let data = match Self::repair(pkt, payload_type, track.track.ssrc()) {
+                            Some(data) => data,
+                            None => continue,
+                        };
+
+                        if repaired_tx.is_none() {
+                            repaired_tx = match internal.upgrade() {
+                                Some(internal) => internal.repaired_tx(track.track.tid()).await,
+                                None => break,
+                            };
+                        }
+                        let sent = repaired_tx
+                            .as_ref()
+                            .map(|tx| tx.try_send(RepairedPacket { data, attributes }).is_ok())
+                            .unwrap_or(false);
+                        if !sent {
+                            trace!(
+                                "Dropping packet repaired for ssrc {}, the track isn't read",
+                                track.track.ssrc()
+                            );
                         }
                     }
                 });
@@ -801,6 +1227,42 @@ impl RTCRtpReceiver {
         Err(Error::ErrRTPReceiverForRIDTrackStreamNotFound)
     }
 
+    /// associated_payload_type returns the payload type the RTX codec with payload type
+    /// rtx_payload_type is associated with by its apt parameter.
+    async fn associated_payload_type(
+        media_engine: &MediaEngine,
+        rtx_payload_type: PayloadType,
+    ) -> Option<PayloadType> {
+        let (codec, _) = media_engine
+            .get_codec_by_payload(rtx_payload_type)
+            .await
+            .ok()?;
+        fmtp::parse(&codec.capability.mime_type, &codec.capability.sdp_fmtp_line)
+            .parameter("apt")
+            .and_then(|apt| apt.parse().ok())
+    }
+
+    /// repair restores the packet the retransmission pkt repairs (RFC 4588 section 4). It
+    /// returns None for retransmissions without an original sequence number, e.g. padding for
+    /// probing.
+    fn repair(
+        mut pkt: rtp::packet::Packet,
+        payload_type: PayloadType,
+        ssrc: SSRC,
+    ) -> Option<Bytes> {
+        if pkt.payload.len() < 2 {
+            return None;
+        }
+
+        pkt.header.sequence_number = u16::from_be_bytes([pkt.payload[0], pkt.payload[1]]);
+        pkt.header.ssrc = ssrc;
+        pkt.header.payload_type = payload_type;
+        pkt.header.padding = false;
+        pkt.payload = pkt.payload.slice(2..);
+
+        pkt.marshal().ok()
+    }
+
     // State
 
     pub(crate) fn current_state(&self) -> State {
@@ -814,13 +1276,7 @@ impl RTCRtpReceiver {
             return Ok(());
         }
 
-        let streams = self.internal.tracks.read().await;
-
-        for stream in streams.iter() {
-            // TODO: If we introduce futures as a direct dependency this and other futures could be
-            // ran concurrently with [`join_all`](https://docs.rs/futures/0.3.21/futures/future/fn.join_all.html)
-            stream.track.fire_onmute().await;
-        }
+        self.internal.update_muted().await;
 
         Ok(())
     }
@@ -832,13 +1288,9 @@ impl RTCRtpReceiver {
             return Ok(());
         }
 
-        let streams = self.internal.tracks.read().await;
-
-        for stream in streams.iter() {
-            // TODO: If we introduce futures as a direct dependency this and other futures could be
-            // ran concurrently with [`join_all`](https://docs.rs/futures/0.3.21/futures/future/fn.join_all.html)
-            stream.track.fire_onunmute().await;
-        }
+        // Inactivity timers start over now that the remote is expected to send again
+        self.internal.track_activity.lock().clear();
+        self.internal.update_muted().await;
 
         Ok(())
     }
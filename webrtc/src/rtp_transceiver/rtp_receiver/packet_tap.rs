@@ -0,0 +1,114 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use interceptor::nack::ATTR_RETRANSMISSION;
+use interceptor::Attributes;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use util::sync::Mutex as SyncMutex;
+use util::Unmarshal;
+
+/// RecoverySource tells how a packet seen by a [`PacketTap`] reached the receiver
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RecoverySource {
+    /// The packet was received on the media stream
+    Direct,
+    /// The packet was recovered from a retransmission on the RTX repair stream (RFC 4588)
+    Rtx,
+}
+
+impl RecoverySource {
+    /// from_attributes returns the recovery source of a packet read with attributes.
+    pub(crate) fn from_attributes(attributes: &Attributes) -> Self {
+        if attributes.contains_key(&ATTR_RETRANSMISSION) {
+            RecoverySource::Rtx
+        } else {
+            RecoverySource::Direct
+        }
+    }
+}
+
+/// TapInfo describes a packet seen by a [`PacketTap`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TapInfo {
+    /// When the packet was read through the interceptors of the receiver
+    pub arrival_time: SystemTime,
+    pub recovery_source: RecoverySource,
+    /// The RID of the simulcast layer the packet belongs to, empty without simulcast
+    pub rid: String,
+}
+
+/// PacketTap receives a copy of every RTP packet delivered to the tracks of an
+/// [`RTCRtpReceiver`](super::RTCRtpReceiver), after interceptors and recovery. It doesn't consume
+/// the packets, the tracks still read all of them.
+///
+/// Packets are queued up to the capacity the tap was added with. A tap that falls behind never
+/// blocks the tracks, packets that don't fit into the queue are dropped and counted instead.
+/// The tap is removed once it is dropped.
+pub struct PacketTap {
+    rx: mpsc::Receiver<(rtp::packet::Packet, TapInfo)>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl PacketTap {
+    /// recv returns the next packet, or None once the receiver is gone.
+    pub async fn recv(&mut self) -> Option<(rtp::packet::Packet, TapInfo)> {
+        self.rx.recv().await
+    }
+
+    /// dropped returns how many packets were dropped because the queue of this tap was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::SeqCst)
+    }
+}
+
+struct TapSender {
+    tx: mpsc::Sender<(rtp::packet::Packet, TapInfo)>,
+    dropped: Arc<AtomicU64>,
+}
+
+/// PacketTaps are the taps added to a receiver
+#[derive(Default)]
+pub(crate) struct PacketTaps {
+    taps: SyncMutex<Vec<TapSender>>,
+}
+
+impl PacketTaps {
+    pub(crate) fn add(&self, capacity: usize) -> PacketTap {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        let dropped = Arc::new(AtomicU64::new(0));
+
+        let mut taps = self.taps.lock();
+        taps.push(TapSender {
+            tx,
+            dropped: Arc::clone(&dropped),
+        });
+
+        PacketTap { rx, dropped }
+    }
+
+    /// deliver queues a copy of the packet in b to every tap without waiting for any of them.
+    pub(crate) fn deliver(&self, b: &[u8], info: impl FnOnce() -> TapInfo) {
+        let mut taps = self.taps.lock();
+        if taps.is_empty() {
+            return;
+        }
+
+        let mut buf = b;
+        let pkt = match rtp::packet::Packet::unmarshal(&mut buf) {
+            Ok(pkt) => pkt,
+            Err(_) => return,
+        };
+        let info = info();
+
+        taps.retain(|tap| match tap.tx.try_send((pkt.clone(), info.clone())) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                tap.dropped.fetch_add(1, Ordering::SeqCst);
+                true
+            }
+            Err(TrySendError::Closed(_)) => false,
+        });
+    }
+}
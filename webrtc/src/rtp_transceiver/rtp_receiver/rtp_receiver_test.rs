@@ -1,16 +1,24 @@
 use super::*;
-use crate::api::media_engine::{MIME_TYPE_OPUS, MIME_TYPE_VP8};
+use crate::api::media_engine::{MIME_TYPE_H264, MIME_TYPE_OPUS, MIME_TYPE_VP8, MIME_TYPE_VP9};
+use crate::api::setting_engine::SettingEngine;
+use crate::api::APIBuilder;
 use crate::error::Result;
 use crate::peer_connection::peer_connection_state::RTCPeerConnectionState;
 use crate::peer_connection::peer_connection_test::{
-    close_pair_now, create_vnet_pair, signal_pair, until_connection_state,
+    close_pair_now, create_vnet_pair, new_pair, signal_pair, until_connection_state,
 };
-use crate::rtp_transceiver::rtp_codec::RTCRtpHeaderExtensionParameters;
+use crate::peer_connection::sdp::session_description::RTCSessionDescription;
+use crate::peer_connection::RTCPeerConnection;
+use crate::rtp_transceiver::rtp_codec::{RTCRtpCodecCapability, RTCRtpHeaderExtensionParameters};
+use crate::rtp_transceiver::rtp_receiver::packet_tap::RecoverySource;
+use crate::rtp_transceiver::rtp_transceiver_direction::RTCRtpTransceiverDirection;
 use crate::rtp_transceiver::RTCPFeedback;
+use crate::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
 use crate::track::track_local::track_local_static_sample::TrackLocalStaticSample;
-use crate::track::track_local::TrackLocal;
+use crate::track::track_local::{TrackLocal, TrackLocalWriter};
 use bytes::Bytes;
 use media::Sample;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::mpsc;
 use tokio::time::Duration;
 use waitgroup::WaitGroup;
@@ -240,3 +248,556 @@ async fn test_rtp_receiver_set_read_deadline() -> Result<()> {
 
     Ok(())
 }
+
+enum MuteEvent {
+    Mute,
+    Unmute,
+}
+
+/// start_muted_test_pair connects a sender with a single video track to a receiver
+/// that reports the remote track and its onmute/onunmute events. Samples are written
+/// every 20ms while `sending` is set.
+async fn start_muted_test_pair(
+    inactivity_timeout: Option<Duration>,
+    sending: Arc<AtomicBool>,
+) -> Result<(
+    RTCPeerConnection,
+    RTCPeerConnection,
+    Arc<TrackRemote>,
+    mpsc::Receiver<MuteEvent>,
+)> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let mut s = SettingEngine::default();
+    s.set_track_inactivity_timeout(inactivity_timeout);
+    let api = APIBuilder::new()
+        .with_media_engine(m)
+        .with_setting_engine(s)
+        .build();
+
+    let (mut sender, mut receiver) = new_pair(&api).await?;
+
+    let track = Arc::new(TrackLocalStaticSample::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    sender
+        .add_track(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>)
+        .await?;
+
+    let (track_tx, mut track_rx) = mpsc::channel::<Arc<TrackRemote>>(1);
+    let (mute_tx, mute_rx) = mpsc::channel::<MuteEvent>(8);
+    receiver.on_track(Box::new(
+        move |track_remote: Option<Arc<TrackRemote>>, _: Option<Arc<RTCRtpReceiver>>| {
+            let track_tx = track_tx.clone();
+            let mute_tx = mute_tx.clone();
+            Box::pin(async move {
+                let track_remote = match track_remote {
+                    Some(t) => t,
+                    None => return,
+                };
+
+                let mute_tx2 = mute_tx.clone();
+                track_remote
+                    .onmute(move || {
+                        let mute_tx = mute_tx.clone();
+                        Box::pin(async move {
+                            let _ = mute_tx.send(MuteEvent::Mute).await;
+                        })
+                    })
+                    .await;
+                track_remote
+                    .onunmute(move || {
+                        let mute_tx = mute_tx2.clone();
+                        Box::pin(async move {
+                            let _ = mute_tx.send(MuteEvent::Unmute).await;
+                        })
+                    })
+                    .await;
+
+                let reader = Arc::clone(&track_remote);
+                tokio::spawn(async move { while reader.read_rtp().await.is_ok() {} });
+
+                let _ = track_tx.send(track_remote).await;
+            })
+        },
+    ));
+
+    signal_pair(&mut sender, &mut receiver).await?;
+
+    tokio::spawn(async move {
+        loop {
+            if sending.load(Ordering::SeqCst)
+                && track
+                    .write_sample(&Sample {
+                        data: Bytes::from_static(&[0xAA]),
+                        duration: Duration::from_millis(20),
+                        ..Default::default()
+                    })
+                    .await
+                    .is_err()
+            {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    });
+
+    let track_remote = tokio::time::timeout(Duration::from_secs(10), track_rx.recv())
+        .await
+        .map_err(|_| Error::new("timed out waiting for remote track".to_owned()))?
+        .ok_or_else(|| Error::new("no remote track".to_owned()))?;
+
+    Ok((sender, receiver, track_remote, mute_rx))
+}
+
+async fn expect_mute_event(mute_rx: &mut mpsc::Receiver<MuteEvent>, mute: bool) {
+    let event = tokio::time::timeout(Duration::from_secs(5), mute_rx.recv())
+        .await
+        .expect("timed out waiting for mute event");
+    match event {
+        Some(MuteEvent::Mute) => assert!(mute, "unexpected onmute"),
+        Some(MuteEvent::Unmute) => assert!(!mute, "unexpected onunmute"),
+        None => panic!("mute event channel closed"),
+    }
+}
+
+#[tokio::test]
+async fn test_track_remote_muted_on_inactivity() -> Result<()> {
+    let sending = Arc::new(AtomicBool::new(true));
+    let (sender, receiver, track_remote, mut mute_rx) =
+        start_muted_test_pair(Some(Duration::from_millis(300)), Arc::clone(&sending)).await?;
+
+    assert!(!track_remote.muted());
+
+    // No RTP for longer than the inactivity timeout mutes the track
+    sending.store(false, Ordering::SeqCst);
+    expect_mute_event(&mut mute_rx, true).await;
+    assert!(track_remote.muted());
+
+    // and the first packet after that unmutes it again
+    sending.store(true, Ordering::SeqCst);
+    expect_mute_event(&mut mute_rx, false).await;
+    assert!(!track_remote.muted());
+
+    close_pair_now(&sender, &receiver).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_track_remote_muted_on_direction_change() -> Result<()> {
+    let sending = Arc::new(AtomicBool::new(true));
+    let (sender, receiver, track_remote, mut mute_rx) =
+        start_muted_test_pair(None, Arc::clone(&sending)).await?;
+
+    assert!(!track_remote.muted());
+
+    // Renegotiating the transceiver to inactive mutes the remote track
+    for transceiver in sender.get_transceivers().await {
+        transceiver
+            .set_direction(RTCRtpTransceiverDirection::Inactive)
+            .await;
+    }
+
+    let offer = sender.create_offer(None).await?;
+    sender.set_local_description(offer.clone()).await?;
+    receiver.set_remote_description(offer).await?;
+    let answer = receiver.create_answer(None).await?;
+    receiver.set_local_description(answer.clone()).await?;
+    sender.set_remote_description(answer).await?;
+
+    expect_mute_event(&mut mute_rx, true).await;
+    assert!(track_remote.muted());
+
+    close_pair_now(&sender, &receiver).await;
+
+    Ok(())
+}
+
+/// rtp_packet builds a packet of the track written in test_packet_tap_recovery
+fn rtp_packet(sequence_number: u16) -> rtp::packet::Packet {
+    rtp::packet::Packet {
+        header: rtp::header::Header {
+            version: 2,
+            sequence_number,
+            timestamp: sequence_number as u32 * 3000,
+            ..Default::default()
+        },
+        payload: Bytes::from_static(&[0xAA]),
+    }
+}
+
+/// next_tapped returns the next packet of the tap, failing the test if none arrives.
+async fn next_tapped(tap: &mut PacketTap) -> (rtp::packet::Packet, TapInfo) {
+    tokio::time::timeout(Duration::from_secs(5), tap.recv())
+        .await
+        .expect("timed out waiting for tapped packet")
+        .expect("packet tap closed")
+}
+
+#[tokio::test]
+async fn test_packet_tap_recovery() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut sender, mut receiver) = new_pair(&api).await?;
+
+    let track = Arc::new(TrackLocalStaticRTP::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    let rtp_sender = sender
+        .add_track(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>)
+        .await?;
+    let ssrc = rtp_sender.get_parameters().await.encodings[0].ssrc;
+    let rtx_ssrc = ssrc.wrapping_add(1);
+
+    let (track_tx, mut track_rx) = mpsc::channel(1);
+    receiver.on_track(Box::new(
+        move |track_remote: Option<Arc<TrackRemote>>, rtp_receiver: Option<Arc<RTCRtpReceiver>>| {
+            let track_tx = track_tx.clone();
+            Box::pin(async move {
+                if let (Some(track_remote), Some(rtp_receiver)) = (track_remote, rtp_receiver) {
+                    let _ = track_tx.send((track_remote, rtp_receiver)).await;
+                }
+            })
+        },
+    ));
+
+    // Declare the RTX repair stream of the track, the sender doesn't retransmit on its own
+    let wg = WaitGroup::new();
+    until_connection_state(&mut sender, &wg, RTCPeerConnectionState::Connected).await;
+    until_connection_state(&mut receiver, &wg, RTCPeerConnectionState::Connected).await;
+
+    let offer = sender.create_offer(None).await?;
+    let mut gathering_complete = sender.gathering_complete_promise().await;
+    sender.set_local_description(offer).await?;
+    let _ = gathering_complete.recv().await;
+    let offer = sender.local_description().await.unwrap();
+    let fid = format!("a=ssrc-group:FID {} {}\r\na=ssrc:{}", ssrc, rtx_ssrc, ssrc);
+    let sdp = offer.sdp.replacen(&format!("a=ssrc:{}", ssrc), &fid, 1);
+    receiver
+        .set_remote_description(RTCSessionDescription::offer(sdp)?)
+        .await?;
+
+    let answer = receiver.create_answer(None).await?;
+    let mut gathering_complete = receiver.gathering_complete_promise().await;
+    receiver.set_local_description(answer).await?;
+    let _ = gathering_complete.recv().await;
+    sender
+        .set_remote_description(receiver.local_description().await.unwrap())
+        .await?;
+
+    wg.wait().await;
+
+    let mut sequence_number = 0;
+    let (track_remote, rtp_receiver) = loop {
+        track.write_rtp(&rtp_packet(sequence_number)).await?;
+        sequence_number += 1;
+
+        tokio::select! {
+            result = track_rx.recv() => break result.unwrap(),
+            _ = tokio::time::sleep(Duration::from_millis(20)) => {}
+        }
+    };
+
+    let mut taps = vec![
+        rtp_receiver.add_packet_tap(16),
+        rtp_receiver.add_packet_tap(16),
+    ];
+
+    let (read_tx, mut read_rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        while let Ok((pkt, _)) = track_remote.read_rtp().await {
+            let _ = read_tx.send(pkt.header.sequence_number).await;
+        }
+    });
+
+    // The packet after the direct one is lost and recovered from a retransmission
+    let direct = sequence_number;
+    let lost = sequence_number + 1;
+    track.write_rtp(&rtp_packet(direct)).await?;
+
+    let mut rtx_packet = rtp_packet(lost);
+    rtx_packet.header.ssrc = rtx_ssrc;
+    rtx_packet.header.payload_type = 97;
+    rtx_packet.header.sequence_number = 0;
+    let mut payload = lost.to_be_bytes().to_vec();
+    payload.extend_from_slice(&rtx_packet.payload);
+    rtx_packet.payload = Bytes::from(payload);
+    let srtp_session = rtp_sender.transport().get_srtp_session().await.unwrap();
+    srtp_session.write_rtp(&rtx_packet).await?;
+
+    for tap in &mut taps {
+        let mut seen = HashMap::new();
+        while seen.len() < 2 {
+            let (pkt, info) = next_tapped(tap).await;
+            if [direct, lost].contains(&pkt.header.sequence_number) {
+                assert_eq!(pkt.header.ssrc, ssrc);
+                assert_eq!(info.rid, "");
+                seen.insert(pkt.header.sequence_number, info.recovery_source);
+            }
+        }
+        assert_eq!(seen[&direct], RecoverySource::Direct);
+        // Rtx is only reported for packets read through the interceptors of the media stream
+        assert_eq!(seen[&lost], RecoverySource::Rtx);
+        assert_eq!(tap.dropped(), 0);
+    }
+
+    // The track still reads every packet the taps saw
+    let mut read = vec![];
+    while ![direct, lost].iter().all(|n| read.contains(n)) {
+        let sequence_number = tokio::time::timeout(Duration::from_secs(5), read_rx.recv())
+            .await
+            .expect("timed out waiting for track read")
+            .unwrap();
+        read.push(sequence_number);
+    }
+
+    close_pair_now(&sender, &receiver).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_negotiated_codec_before_media() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut sender, mut receiver) = new_pair(&api).await?;
+
+    let track = Arc::new(TrackLocalStaticRTP::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    sender
+        .add_track(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>)
+        .await?;
+
+    let wg = WaitGroup::new();
+    until_connection_state(&mut receiver, &wg, RTCPeerConnectionState::Connected).await;
+
+    signal_pair(&mut sender, &mut receiver).await?;
+
+    // The receiver knows the negotiated codecs as soon as the remote description is applied
+    let rtp_receiver = receiver.get_transceivers().await[0]
+        .receiver()
+        .await
+        .unwrap();
+    let parameters = rtp_receiver.get_parameters().await;
+    assert_eq!(parameters.codecs[0].capability.mime_type, MIME_TYPE_VP8);
+    assert!(parameters
+        .codecs
+        .iter()
+        .any(|c| c.capability.mime_type == MIME_TYPE_VP9));
+
+    // and its track once it exists, without any packet having been written
+    wg.wait().await;
+    let track_remote = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Some(t) = rtp_receiver.track().await {
+                return t;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("timed out waiting for the remote track");
+    let codec = track_remote.codec().await;
+    assert_eq!(codec.capability.mime_type, MIME_TYPE_VP8);
+    assert_eq!(codec.capability.clock_rate, 90000);
+    assert_eq!(track_remote.payload_type(), codec.payload_type);
+
+    // Renegotiating to VP9 only switches both
+    let vp9 = RTCRtpCodecParameters {
+        capability: RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP9.to_owned(),
+            clock_rate: 90000,
+            sdp_fmtp_line: "profile-id=0".to_owned(),
+            ..Default::default()
+        },
+        payload_type: 98,
+        ..Default::default()
+    };
+    sender.get_transceivers().await[0]
+        .set_codec_preferences(vec![vp9])
+        .await?;
+    let offer = sender.create_offer(None).await?;
+    sender.set_local_description(offer.clone()).await?;
+    receiver.set_remote_description(offer).await?;
+
+    let parameters = rtp_receiver.get_parameters().await;
+    assert!(!parameters.codecs.is_empty());
+    assert!(parameters
+        .codecs
+        .iter()
+        .all(|c| c.capability.mime_type == MIME_TYPE_VP9));
+    let codec = track_remote.codec().await;
+    assert_eq!(codec.capability.mime_type, MIME_TYPE_VP9);
+    assert_eq!(codec.capability.sdp_fmtp_line, "profile-id=0");
+    assert_eq!(track_remote.payload_type(), 98);
+
+    close_pair_now(&sender, &receiver).await;
+
+    Ok(())
+}
+
+/// vp8_layer_packet builds a packet of a synthetic VP8 simulcast layer, a frame per packet
+fn vp8_layer_packet(ssrc: SSRC, frame: u16, keyframe: bool) -> rtp::packet::Packet {
+    rtp::packet::Packet {
+        header: rtp::header::Header {
+            version: 2,
+            ssrc,
+            sequence_number: (ssrc as u16).wrapping_mul(7919).wrapping_add(frame),
+            timestamp: ssrc.wrapping_mul(104_729).wrapping_add(frame as u32 * 3000),
+            ..Default::default()
+        },
+        // S bit set, partition 0, P bit of the frame header cleared for keyframes
+        payload: Bytes::from(vec![0x10, if keyframe { 0x00 } else { 0x01 }, 0x00, 0x00]),
+    }
+}
+
+#[test]
+fn test_layer_switcher_switches_on_keyframes() {
+    use layer_selector::{is_keyframe, LayerSwitcher};
+
+    // every layer sends a keyframe every 10 frames, at a different offset, and its packets
+    // arrive a few milliseconds after those of the previous layer
+    let layers = [("q", 1000u32, 3u16), ("h", 2000, 6), ("f", 3000, 9)];
+    let mut switcher = LayerSwitcher::default();
+    let start = Instant::now();
+
+    let mut forwarded: Vec<(&str, rtp::packet::Packet, bool, Instant)> = vec![];
+    for frame in 0..100u16 {
+        match frame {
+            0 => switcher.select("q"),
+            25 => switcher.select("h"),
+            50 => switcher.select("f"),
+            75 => switcher.select("q"),
+            _ => {}
+        }
+
+        for (i, &(rid, ssrc, keyframe_offset)) in layers.iter().enumerate() {
+            let mut pkt = vp8_layer_packet(ssrc, frame, frame % 10 == keyframe_offset);
+            let keyframe = is_keyframe(MIME_TYPE_VP8, &pkt.payload);
+            let now = start + Duration::from_millis(frame as u64 * 33 + i as u64 * 5);
+            if switcher.forward(rid, &mut pkt.header, keyframe, 90000, now) {
+                forwarded.push((rid, pkt, keyframe, now));
+            }
+        }
+    }
+
+    assert_eq!(switcher.current(), Some("q"));
+    let rids: Vec<&str> = forwarded.iter().map(|(rid, _, _, _)| *rid).collect();
+    let mut switches = rids.clone();
+    switches.dedup();
+    assert_eq!(switches, vec!["q", "h", "f", "q"]);
+
+    // the first layer is forwarded as is
+    assert_eq!(forwarded[0].1, vp8_layer_packet(1000, 3, true));
+    for (i, (rid, pkt, keyframe, at)) in forwarded.iter().enumerate() {
+        assert_eq!(pkt.header.ssrc, 1000);
+        if i == 0 {
+            continue;
+        }
+
+        let (previous_rid, previous, _, previous_at) = &forwarded[i - 1];
+        assert_eq!(
+            pkt.header.sequence_number,
+            previous.header.sequence_number.wrapping_add(1),
+            "sequence numbers must continue across switches"
+        );
+        assert!(
+            pkt.header.timestamp.wrapping_sub(previous.header.timestamp) as i32 > 0,
+            "timestamps must advance across switches"
+        );
+        if rid != previous_rid {
+            assert!(keyframe, "a switch to {} must start on a keyframe", rid);
+            let elapsed = at.duration_since(*previous_at);
+            assert_eq!(
+                pkt.header.timestamp.wrapping_sub(previous.header.timestamp),
+                elapsed.as_millis() as u32 * 90,
+                "timestamps advance by the time elapsed since the last packet"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_is_keyframe() {
+    use layer_selector::is_keyframe;
+
+    let cases: Vec<(&str, &[u8], bool)> = vec![
+        (MIME_TYPE_VP8, &[0x10, 0x00, 0x9d, 0x01], true),
+        (MIME_TYPE_VP8, &[0x10, 0x01, 0x9d, 0x01], false),
+        // not the first partition of the frame
+        (MIME_TYPE_VP8, &[0x00, 0x00, 0x9d, 0x01], false),
+        (MIME_TYPE_VP8, &[0x10], false),
+        // B set, P cleared
+        (MIME_TYPE_VP9, &[0x08, 0xAA], true),
+        (MIME_TYPE_VP9, &[0x48, 0xAA], false),
+        (MIME_TYPE_VP9, &[0x00, 0xAA], false),
+        // STAP-A with an SPS
+        (
+            MIME_TYPE_H264,
+            &[0x78, 0x00, 0x01, 0x09, 0x00, 0x01, 0x67],
+            true,
+        ),
+        (MIME_TYPE_H264, &[0x78, 0x00, 0x01, 0x09], false),
+        // first and middle fragments of an IDR
+        (MIME_TYPE_H264, &[0x7C, 0x85, 0xAA], true),
+        (MIME_TYPE_H264, &[0x7C, 0x05, 0xAA], false),
+        (MIME_TYPE_H264, &[0x65, 0xAA], true),
+        (MIME_TYPE_H264, &[0x41, 0xAA], false),
+        (MIME_TYPE_H264, &[], false),
+        (MIME_TYPE_OPUS, &[0xAA], true),
+    ];
+
+    for (mime_type, payload, expected) in cases {
+        assert_eq!(
+            is_keyframe(mime_type, &Bytes::copy_from_slice(payload)),
+            expected,
+            "{} {:02x?}",
+            mime_type,
+            payload
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_select_layer_unknown_rid() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (sender, receiver) = new_pair(&api).await?;
+    let transceiver = receiver
+        .add_transceiver_from_kind(RTPCodecType::Video, &[])
+        .await?;
+
+    assert_eq!(
+        transceiver.select_layer("f").await.err(),
+        Some(Error::ErrRTPReceiverForRIDTrackStreamNotFound)
+    );
+    let rtp_receiver = transceiver.receiver().await.unwrap();
+    assert!(rtp_receiver.track().await.is_none());
+    assert_eq!(rtp_receiver.selected_layer(), None);
+
+    close_pair_now(&sender, &receiver).await;
+    Ok(())
+}
@@ -4,13 +4,18 @@ mod rtp_sender_test;
 use crate::api::media_engine::MediaEngine;
 use crate::dtls_transport::RTCDtlsTransport;
 use crate::error::{Error, Result};
-use crate::rtp_transceiver::rtp_codec::{RTCRtpCodecParameters, RTPCodecType};
+use crate::rtp_transceiver::rtp_codec::{
+    RTCRtpCodecParameters, RTCRtpHeaderExtensionToNegotiate, RTPCodecType,
+};
 use crate::rtp_transceiver::rtp_transceiver_direction::RTCRtpTransceiverDirection;
 use crate::rtp_transceiver::srtp_writer_future::SrtpWriterFuture;
 use crate::rtp_transceiver::{
     create_stream_info, PayloadType, RTCRtpEncodingParameters, RTCRtpSendParameters,
     RTCRtpTransceiver, SSRC,
 };
+use crate::track::track_local::track_local_static_sample::{
+    KeyframeRequestReason, TrackLocalStaticSample,
+};
 use crate::track::track_local::{
     InterceptorToTrackLocalWriter, TrackLocal, TrackLocalContext, TrackLocalWriter,
 };
@@ -18,7 +23,9 @@ use crate::track::track_local::{
 use ice::rand::generate_crypto_random_string;
 use interceptor::stream_info::StreamInfo;
 use interceptor::{Attributes, Interceptor, RTCPReader, RTPWriter};
-use std::sync::atomic::{AtomicBool, Ordering};
+use rtcp::payload_feedbacks::full_intra_request::FullIntraRequest;
+use rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::{Arc, Weak};
 use tokio::sync::{mpsc, Mutex, Notify};
 
@@ -87,7 +94,7 @@ pub struct RTCRtpSender {
     pub(crate) transport: Arc<RTCDtlsTransport>,
 
     pub(crate) payload_type: PayloadType,
-    pub(crate) ssrc: SSRC,
+    ssrc: AtomicU32,
     receive_mtu: usize,
 
     /// a transceiver sender since we can just check the
@@ -152,7 +159,7 @@ impl RTCRtpSender {
 
         let srtp_stream = Arc::new(SrtpWriterFuture {
             closed: AtomicBool::new(false),
-            ssrc,
+            ssrc: AtomicU32::new(ssrc),
             rtp_sender: Arc::downgrade(&internal),
             rtp_transport: Arc::clone(&transport),
             rtcp_read_stream: Mutex::new(None),
@@ -177,7 +184,7 @@ impl RTCRtpSender {
             transport,
 
             payload_type: 0,
-            ssrc,
+            ssrc: AtomicU32::new(ssrc),
             receive_mtu,
 
             negotiated: AtomicBool::new(false),
@@ -201,6 +208,11 @@ impl RTCRtpSender {
         }
     }
 
+    /// ssrc returns the SSRC the sender sends with, which `send` may change.
+    pub(crate) fn ssrc(&self) -> SSRC {
+        self.ssrc.load(Ordering::SeqCst)
+    }
+
     pub(crate) fn is_negotiated(&self) -> bool {
         self.negotiated.load(Ordering::SeqCst)
     }
@@ -246,10 +258,14 @@ impl RTCRtpSender {
             RTCRtpSendParameters {
                 rtp_parameters: self
                     .media_engine
-                    .get_rtp_parameters_by_kind(kind, RTCRtpTransceiverDirection::Sendonly)
+                    .get_rtp_parameters_by_kind(
+                        kind,
+                        RTCRtpTransceiverDirection::Sendonly,
+                        &self.header_extensions_to_negotiate().await,
+                    )
                     .await,
                 encodings: vec![RTCRtpEncodingParameters {
-                    ssrc: self.ssrc,
+                    ssrc: self.ssrc(),
                     payload_type: self.payload_type,
                     ..Default::default()
                 }],
@@ -273,6 +289,20 @@ impl RTCRtpSender {
         send_parameters
     }
 
+    /// header_extensions_to_negotiate returns the header extension choices of the transceiver
+    /// this sender belongs to, if any
+    async fn header_extensions_to_negotiate(&self) -> Vec<RTCRtpHeaderExtensionToNegotiate> {
+        let t = {
+            let tr = self.rtp_transceiver.lock().await;
+            tr.as_ref().and_then(|t| t.upgrade())
+        };
+        if let Some(t) = t {
+            t.header_extensions_to_negotiate().await
+        } else {
+            vec![]
+        }
+    }
+
     /// track returns the RTCRtpTransceiver track, or nil
     pub async fn track(&self) -> Option<Arc<dyn TrackLocal + Send + Sync>> {
         let track = self.track.lock().await;
@@ -328,7 +358,11 @@ impl RTCRtpSender {
                 id: context.id.clone(),
                 params: self
                     .media_engine
-                    .get_rtp_parameters_by_kind(t.kind(), RTCRtpTransceiverDirection::Sendonly)
+                    .get_rtp_parameters_by_kind(
+                        t.kind(),
+                        RTCRtpTransceiverDirection::Sendonly,
+                        &self.header_extensions_to_negotiate().await,
+                    )
                     .await,
                 ssrc: context.ssrc,
                 write_stream: context.write_stream.clone(),
@@ -368,28 +402,67 @@ impl RTCRtpSender {
     }
 
     /// send Attempts to set the parameters controlling the sending of media.
+    ///
+    /// A sender without transceiver, as constructed with the ORTC API, sends with the codecs and
+    /// header extensions of the parameters if they hold any codec, limited to the codec with the
+    /// payload type of the encoding if it has one. The sender then sends with the SSRC of the
+    /// encoding.
     pub async fn send(&self, parameters: &RTCRtpSendParameters) -> Result<()> {
         if self.has_sent().await {
             return Err(Error::ErrRTPSenderSendAlreadyCalled);
         }
 
+        let encoding = &parameters.encodings[0];
+        let transceiver = {
+            let tr = self.rtp_transceiver.lock().await;
+            tr.as_ref().map(|t| t.upgrade())
+        };
+        let has_transceiver = transceiver.is_some();
+        let mid = match transceiver.flatten() {
+            Some(t) => t.mid().await,
+            None => String::new(),
+        };
+        let explicit_params = if !has_transceiver && !parameters.rtp_parameters.codecs.is_empty() {
+            let mut params = parameters.rtp_parameters.clone();
+            if encoding.payload_type != 0 {
+                params
+                    .codecs
+                    .retain(|c| c.payload_type == encoding.payload_type);
+                if params.codecs.is_empty() {
+                    return Err(Error::ErrCodecNotFound);
+                }
+            }
+            Some(params)
+        } else {
+            None
+        };
+
+        self.ssrc.store(encoding.ssrc, Ordering::SeqCst);
+        self.srtp_stream.ssrc.store(encoding.ssrc, Ordering::SeqCst);
+
         let write_stream = Arc::new(InterceptorToTrackLocalWriter::new(self.paused.clone()));
         let (context, stream_info) = {
             let track = self.track.lock().await;
+            let params = match explicit_params {
+                Some(params) => params,
+                None => {
+                    self.media_engine
+                        .get_rtp_parameters_by_kind(
+                            if let Some(t) = &*track {
+                                t.kind()
+                            } else {
+                                RTPCodecType::default()
+                            },
+                            RTCRtpTransceiverDirection::Sendonly,
+                            &self.header_extensions_to_negotiate().await,
+                        )
+                        .await
+                }
+            };
             let mut context = TrackLocalContext {
                 id: self.id.clone(),
-                params: self
-                    .media_engine
-                    .get_rtp_parameters_by_kind(
-                        if let Some(t) = &*track {
-                            t.kind()
-                        } else {
-                            RTPCodecType::default()
-                        },
-                        RTCRtpTransceiverDirection::Sendonly,
-                    )
-                    .await,
-                ssrc: parameters.encodings[0].ssrc,
+                params,
+                ssrc: encoding.ssrc,
                 write_stream: Some(
                     Arc::clone(&write_stream) as Arc<dyn TrackLocalWriter + Send + Sync>
                 ),
@@ -404,13 +477,16 @@ impl RTCRtpSender {
             let payload_type = codec.payload_type;
             let capability = codec.capability.clone();
             context.params.codecs = vec![codec];
-            let stream_info = create_stream_info(
+            let mut stream_info = create_stream_info(
                 self.id.clone(),
-                parameters.encodings[0].ssrc,
+                encoding.ssrc,
                 payload_type,
                 capability,
                 &parameters.rtp_parameters.header_extensions,
             );
+            // sent in the SDES of the sender reports
+            stream_info.mid = mid;
+            stream_info.rid = encoding.rid.clone();
 
             (context, stream_info)
         };
@@ -465,15 +541,71 @@ impl RTCRtpSender {
     }
 
     /// read reads incoming RTCP for this RTPReceiver
+    ///
+    /// Keyframe requests read are passed on to a TrackLocalStaticSample, see
+    /// `TrackLocalStaticSample::on_keyframe_request`.
     pub async fn read(&self, b: &mut [u8]) -> Result<(usize, Attributes)> {
-        self.internal.read(b).await
+        let (n, attributes) = self.internal.read(b).await?;
+
+        if let Some(track) = self.keyframe_request_track().await {
+            let mut buf = &b[..n];
+            if let Ok(pkts) = rtcp::packet::unmarshal(&mut buf) {
+                self.handle_keyframe_requests(&track, &pkts).await;
+            }
+        }
+
+        Ok((n, attributes))
     }
 
     /// read_rtcp is a convenience method that wraps Read and unmarshals for you.
     pub async fn read_rtcp(
         &self,
     ) -> Result<(Vec<Box<dyn rtcp::packet::Packet + Send + Sync>>, Attributes)> {
-        self.internal.read_rtcp(self.receive_mtu).await
+        let (pkts, attributes) = self.internal.read_rtcp(self.receive_mtu).await?;
+
+        if let Some(track) = self.keyframe_request_track().await {
+            self.handle_keyframe_requests(&track, &pkts).await;
+        }
+
+        Ok((pkts, attributes))
+    }
+
+    /// keyframe_request_track returns the track if it takes keyframe requests.
+    async fn keyframe_request_track(&self) -> Option<Arc<dyn TrackLocal + Send + Sync>> {
+        let track = self.track.lock().await;
+        track
+            .as_ref()
+            .filter(|t| t.as_any().is::<TrackLocalStaticSample>())
+            .cloned()
+    }
+
+    /// handle_keyframe_requests passes a PLI or FIR for the SSRC of the sender on to the track.
+    async fn handle_keyframe_requests(
+        &self,
+        track: &Arc<dyn TrackLocal + Send + Sync>,
+        pkts: &[Box<dyn rtcp::packet::Packet + Send + Sync>],
+    ) {
+        let track = match track.as_any().downcast_ref::<TrackLocalStaticSample>() {
+            Some(track) => track,
+            None => return,
+        };
+
+        for pkt in pkts {
+            let pkt = pkt.as_any();
+            let reason = if let Some(pli) = pkt.downcast_ref::<PictureLossIndication>() {
+                (pli.media_ssrc == self.ssrc()).then(|| KeyframeRequestReason::PictureLoss)
+            } else if let Some(fir) = pkt.downcast_ref::<FullIntraRequest>() {
+                fir.fir
+                    .iter()
+                    .any(|entry| entry.ssrc == self.ssrc())
+                    .then(|| KeyframeRequestReason::FullIntraRequest)
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                track.request_keyframe(reason).await;
+            }
+        }
     }
 
     /// has_sent tells if data has been ever sent for this instance
@@ -1,18 +1,26 @@
 use super::*;
+use crate::api::interceptor_registry::register_default_interceptors;
 use crate::api::media_engine::{MIME_TYPE_H264, MIME_TYPE_OPUS, MIME_TYPE_VP8, MIME_TYPE_VP9};
 use crate::api::setting_engine::SettingEngine;
-use crate::api::APIBuilder;
+use crate::api::{APIBuilder, API};
 use crate::error::Result;
+use crate::peer_connection::configuration::RTCConfiguration;
 use crate::peer_connection::peer_connection_state::RTCPeerConnectionState;
 use crate::peer_connection::peer_connection_test::{
     close_pair_now, create_vnet_pair, new_pair, send_video_until_done, signal_pair,
     until_connection_state,
 };
+use crate::peer_connection::sdp::session_description::RTCSessionDescription;
 use crate::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
 use crate::rtp_transceiver::rtp_receiver::RTCRtpReceiver;
+use crate::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
 use crate::track::track_local::track_local_static_sample::TrackLocalStaticSample;
 use crate::track::track_remote::TrackRemote;
 use bytes::Bytes;
+use interceptor::registry::Registry;
+use regex::Regex;
+use rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use rtcp::transport_feedbacks::transport_layer_nack::TransportLayerNack;
 use std::sync::atomic::AtomicU64;
 use tokio::time::Duration;
 use waitgroup::WaitGroup;
@@ -143,7 +151,7 @@ async fn test_rtp_sender_get_parameters() -> Result<()> {
         let parameters = sender.get_parameters().await;
         assert_ne!(0, parameters.rtp_parameters.codecs.len());
         assert_eq!(1, parameters.encodings.len());
-        assert_eq!(sender.ssrc, parameters.encodings[0].ssrc);
+        assert_eq!(sender.ssrc(), parameters.encodings[0].ssrc);
     } else {
         assert!(false);
     }
@@ -345,3 +353,133 @@ async fn test_rtp_sender_replace_track_invalid_codec_change() -> Result<()> {
     close_pair_now(&sender, &receiver).await;
     Ok(())
 }
+
+#[tokio::test]
+async fn test_rtp_sender_rtcp_feedback_negotiation() -> Result<()> {
+    let new_api = || -> Result<API> {
+        let mut m = MediaEngine::default();
+        m.register_default_codecs()?;
+        let registry = register_default_interceptors(Registry::new(), &mut m)?;
+
+        Ok(APIBuilder::new()
+            .with_media_engine(m)
+            .with_interceptor_registry(registry)
+            .build())
+    };
+
+    let pc_offer = new_api()?
+        .new_peer_connection(RTCConfiguration::default())
+        .await?;
+    let pc_answer = new_api()?
+        .new_peer_connection(RTCConfiguration::default())
+        .await?;
+
+    let track = Arc::new(TrackLocalStaticRTP::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    let rtp_sender = pc_offer
+        .add_track(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>)
+        .await?;
+
+    let (track_tx, mut track_rx) = mpsc::channel(1);
+    pc_answer.on_track(Box::new(
+        move |track: Option<Arc<TrackRemote>>, _: Option<Arc<RTCRtpReceiver>>| {
+            let track_tx = track_tx.clone();
+            Box::pin(async move {
+                if let Some(track) = track {
+                    let _ = track_tx.send(track).await;
+                }
+            })
+        },
+    ));
+
+    // The answerer receives an offer without generic NACK, as if the offering browser didn't
+    // support it, and must not send NACKs for the stream.
+    let offer = pc_offer.create_offer(None).await?;
+    let mut gathering_complete = pc_offer.gathering_complete_promise().await;
+    pc_offer.set_local_description(offer).await?;
+    let _ = gathering_complete.recv().await;
+    let offer = pc_offer.local_description().await.unwrap();
+    let stripped = Regex::new(r"a=rtcp-fb:\d+ nack\r\n")
+        .unwrap()
+        .replace_all(&offer.sdp, "")
+        .into_owned();
+    assert_ne!(stripped, offer.sdp);
+    pc_answer
+        .set_remote_description(RTCSessionDescription::offer(stripped)?)
+        .await?;
+
+    let answer = pc_answer.create_answer(None).await?;
+    let mut gathering_complete = pc_answer.gathering_complete_promise().await;
+    pc_answer.set_local_description(answer).await?;
+    let _ = gathering_complete.recv().await;
+    let answer = pc_answer.local_description().await.unwrap();
+    assert!(!answer.sdp.contains(" nack\r\n"));
+    assert!(answer.sdp.contains(" nack pli\r\n"));
+    pc_offer.set_remote_description(answer).await?;
+
+    // Send packets with gaps in the sequence numbers until the remote track shows up.
+    let mut sequence_number = 0u16;
+    let remote_track = loop {
+        let received = tokio::time::timeout(Duration::from_millis(20), track_rx.recv()).await;
+        if let Ok(remote_track) = received {
+            break remote_track.unwrap();
+        }
+
+        track
+            .write_rtp(&rtp::packet::Packet {
+                header: rtp::header::Header {
+                    version: 2,
+                    sequence_number,
+                    ..Default::default()
+                },
+                payload: Bytes::from_static(&[0xAA]),
+            })
+            .await?;
+        sequence_number = sequence_number.wrapping_add(3);
+    };
+
+    let feedback = remote_track.codec().await.capability.rtcp_feedback;
+    assert!(!feedback
+        .iter()
+        .any(|fb| fb.typ == "nack" && fb.parameter.is_empty()));
+    assert!(feedback
+        .iter()
+        .any(|fb| fb.typ == "nack" && fb.parameter == "pli"));
+
+    pc_answer
+        .write_rtcp(&[Box::new(PictureLossIndication {
+            sender_ssrc: 0,
+            media_ssrc: remote_track.ssrc(),
+        })])
+        .await?;
+
+    let mut seen_pli = false;
+    let deadline = tokio::time::sleep(Duration::from_secs(1));
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            _ = deadline.as_mut() => break,
+            result = rtp_sender.read_rtcp() => {
+                let (packets, _) = result?;
+                for p in packets {
+                    assert!(
+                        p.as_any().downcast_ref::<TransportLayerNack>().is_none(),
+                        "received a NACK which wasn't negotiated"
+                    );
+                    seen_pli |= p.as_any().downcast_ref::<PictureLossIndication>().is_some();
+                }
+            }
+        }
+    }
+    assert!(seen_pli, "PLI should still be delivered");
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use std::sync::atomic::AtomicUsize;
 
 use super::*;
+use crate::api::interceptor_registry::configure_twcc;
 use crate::api::media_engine::{MIME_TYPE_OPUS, MIME_TYPE_VP8, MIME_TYPE_VP9};
 use crate::api::APIBuilder;
 use crate::peer_connection::configuration::RTCConfiguration;
@@ -25,7 +26,7 @@ async fn test_rtp_transceiver_set_codec_preferences() -> Result<()> {
         RTCRtpTransceiverDirection::Unspecified,
         RTPCodecType::Video,
         media_video_codecs.clone(),
-        Arc::clone(&api.media_engine),
+        api.media_engine.load_full(),
         None,
     )
     .await;
@@ -199,6 +200,123 @@ async fn test_rtp_transceiver_set_codec_preferences_payload_type() -> Result<()>
     Ok(())
 }
 
+#[tokio::test]
+async fn test_rtp_transceiver_set_header_extensions_to_negotiate() -> Result<()> {
+    let new_api = || -> Result<crate::api::API> {
+        let mut m = MediaEngine::default();
+        m.register_default_codecs()?;
+        let registry = configure_twcc(interceptor::registry::Registry::new(), &mut m)?;
+        Ok(APIBuilder::new()
+            .with_media_engine(m)
+            .with_interceptor_registry(registry)
+            .build())
+    };
+    let has_twcc_extmap = |m: &sdp::description::media::MediaDescription| {
+        m.attributes.iter().any(|a| {
+            a.key == "extmap"
+                && a.value
+                    .as_deref()
+                    .map_or(false, |v| v.contains(sdp::extmap::TRANSPORT_CC_URI))
+        })
+    };
+
+    let offer_pc = new_api()?
+        .new_peer_connection(RTCConfiguration::default())
+        .await?;
+    let answer_pc = new_api()?
+        .new_peer_connection(RTCConfiguration::default())
+        .await?;
+
+    let without_twcc = offer_pc
+        .add_transceiver_from_kind(RTPCodecType::Video, &[])
+        .await?;
+    let with_twcc = offer_pc
+        .add_transceiver_from_kind(RTPCodecType::Video, &[])
+        .await?;
+
+    // Only extensions registered on the MediaEngine for the transceiver kind may be chosen
+    assert_eq!(
+        Err(Error::ErrRTPTransceiverHeaderExtensionUnsupported),
+        without_twcc
+            .set_header_extensions_to_negotiate(vec![RTCRtpHeaderExtensionToNegotiate {
+                uri: sdp::extmap::ABS_SEND_TIME_URI.to_owned(),
+                direction: RTCRtpTransceiverDirection::Sendrecv,
+            }])
+            .await
+    );
+    assert_eq!(
+        Err(Error::ErrRTPTransceiverHeaderExtensionDirectionInvalid),
+        without_twcc
+            .set_header_extensions_to_negotiate(vec![RTCRtpHeaderExtensionToNegotiate {
+                uri: sdp::extmap::TRANSPORT_CC_URI.to_owned(),
+                direction: RTCRtpTransceiverDirection::Unspecified,
+            }])
+            .await
+    );
+
+    without_twcc
+        .set_header_extensions_to_negotiate(vec![RTCRtpHeaderExtensionToNegotiate {
+            uri: sdp::extmap::TRANSPORT_CC_URI.to_owned(),
+            direction: RTCRtpTransceiverDirection::Inactive,
+        }])
+        .await?;
+
+    let offer = offer_pc.create_offer(None).await?;
+    let parsed = offer.unmarshal()?;
+    assert!(!has_twcc_extmap(&parsed.media_descriptions[0]));
+    assert!(has_twcc_extmap(&parsed.media_descriptions[1]));
+
+    offer_pc.set_local_description(offer.clone()).await?;
+    answer_pc.set_remote_description(offer).await?;
+    let answer = answer_pc.create_answer(None).await?;
+    answer_pc.set_local_description(answer.clone()).await?;
+    offer_pc.set_remote_description(answer).await?;
+
+    let twcc = |extensions: Vec<RTCRtpHeaderExtensionParameters>| {
+        extensions
+            .into_iter()
+            .find(|e| e.uri == sdp::extmap::TRANSPORT_CC_URI)
+    };
+    assert!(twcc(without_twcc.get_negotiated_header_extensions().await).is_none());
+    let twcc_id = twcc(with_twcc.get_negotiated_header_extensions().await)
+        .expect("transport-cc should be negotiated on the second transceiver")
+        .id;
+
+    // The sender parameters are what the interceptors see when the stream is bound
+    let sender_params = |t: &Arc<RTCRtpTransceiver>| {
+        let t = Arc::clone(t);
+        async move {
+            let sender = t.sender().await.unwrap();
+            sender
+                .get_parameters()
+                .await
+                .rtp_parameters
+                .header_extensions
+        }
+    };
+    assert!(twcc(sender_params(&without_twcc).await).is_none());
+    assert_eq!(
+        Some(twcc_id),
+        twcc(sender_params(&with_twcc).await).map(|e| e.id)
+    );
+
+    // Re-enabling the extension keeps its id across renegotiation
+    without_twcc
+        .set_header_extensions_to_negotiate(vec![])
+        .await?;
+    let offer = offer_pc.create_offer(None).await?;
+    let parsed = offer.unmarshal()?;
+    assert!(has_twcc_extmap(&parsed.media_descriptions[0]));
+    assert_eq!(
+        Some(twcc_id),
+        twcc(without_twcc.get_negotiated_header_extensions().await).map(|e| e.id)
+    );
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_rtp_transceiver_direction_change() -> Result<()> {
     let (offer_pc, answer_pc, _) = create_vnet_pair().await?;
@@ -1,7 +1,6 @@
 use crate::dtls_transport::RTCDtlsTransport;
 use crate::error::{Error, Result};
 use crate::rtp_transceiver::rtp_sender::RTPSenderInternal;
-use crate::rtp_transceiver::SSRC;
 
 use srtp::session::Session;
 use srtp::stream::Stream;
@@ -9,7 +8,7 @@ use srtp::stream::Stream;
 use async_trait::async_trait;
 use bytes::Bytes;
 use interceptor::{Attributes, RTCPReader, RTPWriter};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::{Arc, Weak};
 use tokio::sync::Mutex;
 
@@ -17,7 +16,7 @@ use tokio::sync::Mutex;
 /// the SRTP Session is available
 pub(crate) struct SrtpWriterFuture {
     pub(crate) closed: AtomicBool,
-    pub(crate) ssrc: SSRC,
+    pub(crate) ssrc: AtomicU32,
     pub(crate) rtp_sender: Weak<RTPSenderInternal>,
     pub(crate) rtp_transport: Arc<RTCDtlsTransport>,
     pub(crate) rtcp_read_stream: Mutex<Option<Arc<Stream>>>, // atomic.Value // *
@@ -59,7 +58,7 @@ impl SrtpWriterFuture {
         }
 
         if let Some(srtcp_session) = self.rtp_transport.get_srtcp_session().await {
-            let rtcp_read_stream = srtcp_session.open(self.ssrc).await;
+            let rtcp_read_stream = srtcp_session.open(self.ssrc.load(Ordering::SeqCst)).await;
             let mut stream = self.rtcp_read_stream.lock().await;
             *stream = Some(rtcp_read_stream);
         }
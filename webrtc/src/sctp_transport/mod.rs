@@ -13,13 +13,14 @@ use crate::data_channel::RTCDataChannel;
 use crate::dtls_transport::dtls_role::DTLSRole;
 use crate::dtls_transport::*;
 use crate::error::*;
+use crate::peer_connection::TASKS_CLOSE_TIMEOUT;
 use crate::sctp_transport::sctp_transport_capabilities::SCTPTransportCapabilities;
 use crate::stats::stats_collector::StatsCollector;
 use crate::stats::StatsReportType::{PeerConnection, SCTPTransport};
 use crate::stats::{ICETransportStats, PeerConnectionStats};
 
 use data::message::message_channel_open::ChannelType;
-use sctp::association::Association;
+use sctp::association::{Association, CloseReason};
 
 use crate::data_channel::data_channel_parameters::DataChannelParameters;
 
@@ -31,6 +32,8 @@ use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
 use std::sync::Arc;
 use tokio::sync::{Mutex, Notify};
+use util::sync::Mutex as SyncMutex;
+use util::sync::TaskGroup;
 use util::Conn;
 
 const SCTP_MAX_CHANNELS: u16 = u16::MAX;
@@ -47,15 +50,65 @@ pub type OnDataChannelOpenedHdlrFn = Box<
         + Sync,
 >;
 
+pub type OnSctpTransportStateChangeHdlrFn = Box<
+    dyn (FnMut(RTCSctpTransportState) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>)
+        + Send
+        + Sync,
+>;
+
+/// The state and handlers updated once the association closes.
+#[derive(Default, Clone)]
+struct CloseNotifier {
+    state: Arc<AtomicU8>, // RTCSctpTransportState
+    close_reason: Arc<SyncMutex<Option<CloseReason>>>,
+    on_state_change_handler: Arc<ArcSwapOption<Mutex<OnSctpTransportStateChangeHdlrFn>>>,
+    on_error_handler: Arc<ArcSwapOption<Mutex<OnErrorHdlrFn>>>,
+}
+
+impl CloseNotifier {
+    async fn set_state(&self, state: RTCSctpTransportState) {
+        if self.state.swap(state as u8, Ordering::SeqCst) == state as u8 {
+            return;
+        }
+
+        if let Some(handler) = &*self.on_state_change_handler.load() {
+            let mut f = handler.lock().await;
+            f(state).await;
+        }
+    }
+
+    /// association_closed records why the association closed and reports it, a graceful
+    /// shutdown by the peer isn't reported as an error.
+    async fn association_closed(&self, reason: CloseReason) {
+        log::debug!("SCTP association closed: {}", reason);
+        {
+            let mut close_reason = self.close_reason.lock();
+            if close_reason.is_none() {
+                *close_reason = Some(reason);
+            }
+        }
+
+        self.set_state(RTCSctpTransportState::Closed).await;
+
+        if reason != CloseReason::ShutdownReceived {
+            if let Some(handler) = &*self.on_error_handler.load() {
+                let mut f = handler.lock().await;
+                f(sctp::Error::ErrAssociationClosed(reason).into()).await;
+            }
+        }
+    }
+}
+
 struct AcceptDataChannelParams {
     notify_rx: Arc<Notify>,
     sctp_association: Arc<Association>,
     data_channels: Arc<Mutex<Vec<Arc<RTCDataChannel>>>>,
-    on_error_handler: Arc<ArcSwapOption<Mutex<OnErrorHdlrFn>>>,
+    close_notifier: CloseNotifier,
     on_data_channel_handler: Arc<ArcSwapOption<Mutex<OnDataChannelHdlrFn>>>,
     on_data_channel_opened_handler: Arc<ArcSwapOption<Mutex<OnDataChannelOpenedHdlrFn>>>,
     data_channels_opened: Arc<AtomicU32>,
     data_channels_accepted: Arc<AtomicU32>,
+    data_channel_stats_ids: Arc<AtomicU32>,
     setting_engine: Arc<SettingEngine>,
 }
 
@@ -64,8 +117,8 @@ struct AcceptDataChannelParams {
 pub struct RTCSctpTransport {
     pub(crate) dtls_transport: Arc<RTCDtlsTransport>,
 
-    // State represents the current state of the SCTP transport.
-    state: AtomicU8, // RTCSctpTransportState
+    // State, close reason and the handlers reporting them.
+    close_notifier: CloseNotifier,
 
     // SCTPTransportState doesn't have an enum to distinguish between New/Connecting
     // so we need a dedicated field
@@ -81,7 +134,6 @@ pub struct RTCSctpTransport {
 
     sctp_association: Mutex<Option<Arc<Association>>>,
 
-    on_error_handler: Arc<ArcSwapOption<Mutex<OnErrorHdlrFn>>>,
     on_data_channel_handler: Arc<ArcSwapOption<Mutex<OnDataChannelHdlrFn>>>,
     on_data_channel_opened_handler: Arc<ArcSwapOption<Mutex<OnDataChannelOpenedHdlrFn>>>,
 
@@ -90,8 +142,13 @@ pub struct RTCSctpTransport {
     pub(crate) data_channels_opened: Arc<AtomicU32>,
     pub(crate) data_channels_requested: Arc<AtomicU32>,
     data_channels_accepted: Arc<AtomicU32>,
+    /// Counter the stats ids of the data channels are derived from
+    data_channel_stats_ids: Arc<AtomicU32>,
+    /// Held while asking the peer for more streams, so concurrent channels ask once
+    add_streams_mu: Mutex<()>,
 
     notify_tx: Arc<Notify>,
+    tasks: TaskGroup,
 
     setting_engine: Arc<SettingEngine>,
 }
@@ -103,12 +160,14 @@ impl RTCSctpTransport {
     ) -> Self {
         RTCSctpTransport {
             dtls_transport,
-            state: AtomicU8::new(RTCSctpTransportState::Connecting as u8),
+            close_notifier: CloseNotifier {
+                state: Arc::new(AtomicU8::new(RTCSctpTransportState::Connecting as u8)),
+                ..Default::default()
+            },
             is_started: AtomicBool::new(false),
             max_message_size: RTCSctpTransport::calc_message_size(65536, 65536),
             max_channels: SCTP_MAX_CHANNELS,
             sctp_association: Mutex::new(None),
-            on_error_handler: Arc::new(ArcSwapOption::empty()),
             on_data_channel_handler: Arc::new(ArcSwapOption::empty()),
             on_data_channel_opened_handler: Arc::new(ArcSwapOption::empty()),
 
@@ -116,13 +175,21 @@ impl RTCSctpTransport {
             data_channels_opened: Arc::new(AtomicU32::new(0)),
             data_channels_requested: Arc::new(AtomicU32::new(0)),
             data_channels_accepted: Arc::new(AtomicU32::new(0)),
+            data_channel_stats_ids: Arc::new(AtomicU32::new(0)),
+            add_streams_mu: Mutex::new(()),
 
             notify_tx: Arc::new(Notify::new()),
+            tasks: TaskGroup::new(),
 
             setting_engine,
         }
     }
 
+    /// next_data_channel_stats_id returns the stats id of a new data channel of this transport.
+    pub(crate) fn next_data_channel_stats_id(&self) -> String {
+        data_channel_stats_id(&self.data_channel_stats_ids)
+    }
+
     /// transport returns the DTLSTransport instance the SCTPTransport is sending over.
     pub fn transport(&self) -> Arc<RTCDtlsTransport> {
         Arc::clone(&self.dtls_transport)
@@ -138,7 +205,10 @@ impl RTCSctpTransport {
     /// Start the SCTPTransport. Since both local and remote parties must mutually
     /// create an SCTPTransport, SCTP SO (Simultaneous Open) is used to establish
     /// a connection over SCTP.
-    pub async fn start(&self, _remote_caps: SCTPTransportCapabilities) -> Result<()> {
+    ///
+    /// The max message size of `remote_caps` limits the messages sent, 0 keeps the default of
+    /// 65536 bytes.
+    pub async fn start(&self, remote_caps: SCTPTransportCapabilities) -> Result<()> {
         if self.is_started.load(Ordering::SeqCst) {
             return Ok(());
         }
@@ -152,7 +222,7 @@ impl RTCSctpTransport {
                         // It seems like notify_tx is only notified on Stop so perhaps this check
                         // is redundant.
                         // TODO: Consider renaming notify_tx to shutdown_tx.
-                        if self.state.load(Ordering::SeqCst) == RTCSctpTransportState::Closed as u8 {
+                        if self.state() == RTCSctpTransportState::Closed {
                             return Err(Error::ErrSCTPTransportDTLS);
                         }
                     },
@@ -161,31 +231,50 @@ impl RTCSctpTransport {
                         max_receive_buffer_size: 0,
                         max_message_size: 0,
                         name: String::new(),
+                        max_association_buffered_amount: 0,
+                        buffer_full_policy: Default::default(),
+                        max_retransmissions: self.setting_engine.sctp_max_retransmissions,
+                        num_outbound_streams: self.setting_engine.sctp_num_streams.0,
+                        num_inbound_streams: self.setting_engine.sctp_num_streams.1,
+                        clock: None,
                     }) => {
-                        break Arc::new(association?);
+                        match association {
+                            Ok(association) => break Arc::new(association),
+                            Err(err) => {
+                                if let sctp::Error::ErrAssociationClosed(reason) = err {
+                                    self.close_notifier.association_closed(reason).await;
+                                }
+                                return Err(err.into());
+                            }
+                        }
                     }
                 };
             };
+            if remote_caps.max_message_size != 0 {
+                sctp_association.set_max_message_size(remote_caps.max_message_size);
+            }
 
             {
                 let mut sa = self.sctp_association.lock().await;
                 *sa = Some(Arc::clone(&sctp_association));
             }
-            self.state
-                .store(RTCSctpTransportState::Connected as u8, Ordering::SeqCst);
+            self.close_notifier
+                .set_state(RTCSctpTransportState::Connected)
+                .await;
 
             let param = AcceptDataChannelParams {
                 notify_rx: self.notify_tx.clone(),
                 sctp_association,
                 data_channels: Arc::clone(&self.data_channels),
-                on_error_handler: Arc::clone(&self.on_error_handler),
+                close_notifier: self.close_notifier.clone(),
                 on_data_channel_handler: Arc::clone(&self.on_data_channel_handler),
                 on_data_channel_opened_handler: Arc::clone(&self.on_data_channel_opened_handler),
                 data_channels_opened: Arc::clone(&self.data_channels_opened),
                 data_channels_accepted: Arc::clone(&self.data_channels_accepted),
+                data_channel_stats_ids: Arc::clone(&self.data_channel_stats_ids),
                 setting_engine: Arc::clone(&self.setting_engine),
             };
-            tokio::spawn(async move {
+            self.tasks.spawn(async move {
                 RTCSctpTransport::accept_data_channels(param).await;
             });
 
@@ -204,10 +293,12 @@ impl RTCSctpTransport {
             }
         }
 
-        self.state
-            .store(RTCSctpTransportState::Closed as u8, Ordering::SeqCst);
+        self.close_notifier
+            .set_state(RTCSctpTransportState::Closed)
+            .await;
 
         self.notify_tx.notify_waiters();
+        self.tasks.close(TASKS_CLOSE_TIMEOUT).await;
 
         Ok(())
     }
@@ -233,11 +324,22 @@ impl RTCSctpTransport {
                     match result {
                         Ok(dc) => dc,
                         Err(err) => {
-                            if data::Error::ErrStreamClosed == err {
-                                log::error!("Failed to accept data channel: {}", err);
-                                if let Some(handler) = &*param.on_error_handler.load() {
-                                    let mut f = handler.lock().await;
-                                    f(err.into()).await;
+                            match param.sctp_association.close_reason().await {
+                                // stopped by stop()
+                                Some(CloseReason::Local) => {}
+                                Some(reason) => {
+                                    param.close_notifier.association_closed(reason).await;
+                                }
+                                None => {
+                                    if data::Error::ErrStreamClosed == err {
+                                        log::error!("Failed to accept data channel: {}", err);
+                                        if let Some(handler) =
+                                            &*param.close_notifier.on_error_handler.load()
+                                        {
+                                            let mut f = handler.lock().await;
+                                            f(err.into()).await;
+                                        }
+                                    }
                                 }
                             }
                             break;
@@ -246,9 +348,9 @@ impl RTCSctpTransport {
                 }
             };
 
-            let mut max_retransmits = 0;
-            let mut max_packet_lifetime = 0;
-            let val = dc.config.reliability_parameter as u16;
+            let mut max_retransmits = None;
+            let mut max_packet_lifetime = None;
+            let val = Some(dc.config.reliability_parameter as u16);
             let ordered;
 
             match dc.config.channel_type {
@@ -289,7 +391,10 @@ impl RTCSctpTransport {
                     ordered,
                     max_packet_life_time: max_packet_lifetime,
                     max_retransmits,
+                    priority: dc.config.priority,
+                    chunked: dc.config.chunked,
                 },
+                data_channel_stats_id(&param.data_channel_stats_ids),
                 Arc::clone(&param.setting_engine),
             ));
 
@@ -303,7 +408,9 @@ impl RTCSctpTransport {
                 dcs.push(Arc::clone(&rtc_dc));
             }
 
-            rtc_dc.handle_open(Arc::new(dc)).await;
+            rtc_dc
+                .handle_open(Arc::new(dc), Arc::downgrade(&param.sctp_association))
+                .await;
 
             if let Some(handler) = &*param.on_data_channel_opened_handler.load() {
                 let mut f = handler.lock().await;
@@ -314,9 +421,28 @@ impl RTCSctpTransport {
     }
 
     /// on_error sets an event handler which is invoked when
-    /// the SCTP connection error occurs.
+    /// the SCTP connection error occurs. An association closed by anything but
+    /// this end or a graceful shutdown of the peer is reported as
+    /// `sctp::Error::ErrAssociationClosed` with the reason.
     pub fn on_error(&self, f: OnErrorHdlrFn) {
-        self.on_error_handler.store(Some(Arc::new(Mutex::new(f))));
+        self.close_notifier
+            .on_error_handler
+            .store(Some(Arc::new(Mutex::new(f))));
+    }
+
+    /// on_state_change sets an event handler which is invoked when the
+    /// state of the SCTP transport changes. Once it is closed, close_reason
+    /// tells why.
+    pub fn on_state_change(&self, f: OnSctpTransportStateChangeHdlrFn) {
+        self.close_notifier
+            .on_state_change_handler
+            .store(Some(Arc::new(Mutex::new(f))));
+    }
+
+    /// close_reason returns why the SCTP association was closed by the peer or
+    /// the network, None while it is open or if it was stopped locally.
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        *self.close_notifier.close_reason.lock()
     }
 
     /// on_data_channel sets an event handler which is invoked when a data
@@ -356,7 +482,7 @@ impl RTCSctpTransport {
 
     /// state returns the current state of the SCTPTransport
     pub fn state(&self) -> RTCSctpTransportState {
-        self.state.load(Ordering::SeqCst).into()
+        self.close_notifier.state.load(Ordering::SeqCst).into()
     }
 
     pub(crate) async fn collect_stats(
@@ -409,7 +535,13 @@ impl RTCSctpTransport {
         {
             let data_channels = self.data_channels.lock().await;
             for dc in &*data_channels {
-                ids_map.insert(dc.id());
+                // The ids of closed channels are reused, their streams are reset meanwhile
+                match dc.ready_state() {
+                    RTCDataChannelState::Closing | RTCDataChannelState::Closed => {}
+                    _ => {
+                        ids_map.insert(dc.id());
+                    }
+                }
             }
         }
 
@@ -425,6 +557,32 @@ impl RTCSctpTransport {
         Err(Error::ErrMaxDataChannelID)
     }
 
+    /// ensure_outbound_stream asks the peer for more outbound streams when `id` is beyond the
+    /// ones negotiated, and returns once the peer added them. The streams are at least doubled,
+    /// so opening many channels takes few round trips.
+    pub(crate) async fn ensure_outbound_stream(
+        &self,
+        association: &Association,
+        id: u16,
+    ) -> Result<()> {
+        let _add_streams = self.add_streams_mu.lock().await;
+
+        let num_streams = association.max_outbound_streams();
+        if id < num_streams {
+            return Ok(());
+        }
+        let count = std::cmp::max(id - num_streams + 1, num_streams);
+        let count = std::cmp::min(count, u16::MAX - num_streams);
+        log::debug!(
+            "data channel id {} exceeds the {} SCTP streams, adding {}",
+            id,
+            num_streams,
+            count
+        );
+
+        Ok(association.add_outbound_streams(count).await?)
+    }
+
     pub(crate) async fn association(&self) -> Option<Arc<Association>> {
         let sctp_association = self.sctp_association.lock().await;
         sctp_association.clone()
@@ -442,3 +600,9 @@ impl RTCSctpTransport {
         self.data_channels_requested.load(Ordering::SeqCst)
     }
 }
+
+/// data_channel_stats_id numbers the data channels of a connection in creation order, so their
+/// stats ids are the same in every report.
+fn data_channel_stats_id(counter: &AtomicU32) -> String {
+    format!("DataChannel-{}", counter.fetch_add(1, Ordering::SeqCst))
+}
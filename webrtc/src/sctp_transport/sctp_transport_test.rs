@@ -1,5 +1,12 @@
 use super::*;
+use crate::peer_connection::peer_connection_test::{
+    close_pair_now, create_vnet_pair, create_vnet_pair_with_settings, signal_pair,
+};
+use crate::peer_connection::RTCPeerConnection;
 use std::sync::atomic::AtomicU16;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use util::vnet::chunk::Chunk;
 
 #[tokio::test]
 async fn test_generate_data_channel_id() -> Result<()> {
@@ -40,3 +47,203 @@ async fn test_generate_data_channel_id() -> Result<()> {
 
     Ok(())
 }
+
+/// DTLS records of this content type carry the SCTP packets
+const DTLS_APPLICATION_DATA: u8 = 23;
+
+const CLOSE_REASON_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// watch_sctp_transport forwards the errors and closed state of `sctp_transport`.
+fn watch_sctp_transport(
+    sctp_transport: &RTCSctpTransport,
+) -> (mpsc::Receiver<Error>, mpsc::Receiver<()>) {
+    let (error_tx, error_rx) = mpsc::channel(8);
+    sctp_transport.on_error(Box::new(move |err: Error| {
+        let error_tx = error_tx.clone();
+        Box::pin(async move {
+            let _ = error_tx.send(err).await;
+        })
+    }));
+
+    let (closed_tx, closed_rx) = mpsc::channel(8);
+    sctp_transport.on_state_change(Box::new(move |state: RTCSctpTransportState| {
+        let closed_tx = closed_tx.clone();
+        Box::pin(async move {
+            if state == RTCSctpTransportState::Closed {
+                let _ = closed_tx.send(()).await;
+            }
+        })
+    }));
+
+    (error_rx, closed_rx)
+}
+
+/// watch_data_channel forwards the errors and the close of `dc`.
+fn watch_data_channel(dc: &RTCDataChannel) -> (mpsc::Receiver<Error>, mpsc::Receiver<()>) {
+    let (error_tx, error_rx) = mpsc::channel(8);
+    dc.on_error(Box::new(move |err: Error| {
+        let error_tx = error_tx.clone();
+        Box::pin(async move {
+            let _ = error_tx.send(err).await;
+        })
+    }));
+
+    let (close_tx, close_rx) = mpsc::channel(8);
+    dc.on_close(Box::new(move || {
+        let close_tx = close_tx.clone();
+        Box::pin(async move {
+            let _ = close_tx.send(()).await;
+        })
+    }));
+
+    (error_rx, close_rx)
+}
+
+async fn recv_within<T>(rx: &mut mpsc::Receiver<T>, what: &str) -> T {
+    match tokio::time::timeout(CLOSE_REASON_TIMEOUT, rx.recv()).await {
+        Ok(Some(v)) => v,
+        _ => panic!("timed out waiting for {}", what),
+    }
+}
+
+/// open_data_channel signals the pair and returns the channel opened by the offerer once it is
+/// open.
+async fn open_data_channel(
+    offer_pc: &mut RTCPeerConnection,
+    answer_pc: &mut RTCPeerConnection,
+) -> Result<Arc<RTCDataChannel>> {
+    let dc = offer_pc.create_data_channel("data", None).await?;
+    let (open_tx, mut open_rx) = mpsc::channel(1);
+    dc.on_open(Box::new(move || {
+        Box::pin(async move {
+            let _ = open_tx.send(()).await;
+        })
+    }));
+
+    signal_pair(offer_pc, answer_pc).await?;
+    recv_within(&mut open_rx, "the data channel to open").await;
+
+    Ok(dc)
+}
+
+fn association_closed(reason: CloseReason) -> Error {
+    Error::Sctp(sctp::Error::ErrAssociationClosed(reason))
+}
+
+#[tokio::test]
+async fn test_sctp_transport_close_reason_peer_abort() -> Result<()> {
+    let (mut offer_pc, mut answer_pc, _wan) = create_vnet_pair().await?;
+
+    let (answer_dc_tx, mut answer_dc_rx) = mpsc::channel(1);
+    answer_pc.on_data_channel(Box::new(move |dc: Arc<RTCDataChannel>| {
+        let answer_dc_tx = answer_dc_tx.clone();
+        Box::pin(async move {
+            if dc.label() == "data" {
+                let (errors, closes) = watch_data_channel(&dc);
+                let _ = answer_dc_tx.send((dc, errors, closes)).await;
+            }
+        })
+    }));
+    let (mut transport_errors, mut transport_closed) = watch_sctp_transport(&answer_pc.sctp());
+
+    open_data_channel(&mut offer_pc, &mut answer_pc).await?;
+    let (answer_dc, mut dc_errors, mut dc_closes) =
+        recv_within(&mut answer_dc_rx, "the remote data channel").await;
+
+    offer_pc
+        .sctp()
+        .association()
+        .await
+        .expect("association must be established")
+        .abort("test")
+        .await?;
+
+    // 12 is the User-Initiated Abort cause code
+    let reason = CloseReason::PeerAbort { cause: Some(12) };
+    assert_eq!(
+        association_closed(reason),
+        recv_within(&mut transport_errors, "the SCTP transport error").await
+    );
+    recv_within(&mut transport_closed, "the SCTP transport to close").await;
+    assert_eq!(Some(reason), answer_pc.sctp().close_reason());
+
+    assert_eq!(
+        association_closed(reason),
+        recv_within(&mut dc_errors, "the data channel error").await
+    );
+    recv_within(&mut dc_closes, "the data channel to close").await;
+    assert_eq!(Some(reason), answer_dc.close_reason());
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sctp_transport_close_reason_max_retransmissions() -> Result<()> {
+    let (mut offer_pc, mut answer_pc, wan) =
+        create_vnet_pair_with_settings(|s| s.set_sctp_max_retransmissions(1)).await?;
+
+    let dc = open_data_channel(&mut offer_pc, &mut answer_pc).await?;
+    let (mut dc_errors, mut dc_closes) = watch_data_channel(&dc);
+    let (mut transport_errors, mut transport_closed) = watch_sctp_transport(&offer_pc.sctp());
+
+    // Lose every SCTP packet of the offerer, ICE keeps working
+    {
+        let w = wan.lock().await;
+        w.add_chunk_filter(Box::new(|c: &(dyn Chunk + Send + Sync)| {
+            c.source_addr().ip().to_string() != "1.2.3.4"
+                || c.user_data().first() != Some(&DTLS_APPLICATION_DATA)
+        }))
+        .await;
+    }
+    dc.send_text("lost".to_owned()).await?;
+
+    let reason = CloseReason::MaxRetransmissionsExceeded;
+    assert_eq!(
+        association_closed(reason),
+        recv_within(&mut dc_errors, "the data channel error").await
+    );
+    recv_within(&mut dc_closes, "the data channel to close").await;
+    assert_eq!(Some(reason), dc.close_reason());
+
+    assert_eq!(
+        association_closed(reason),
+        recv_within(&mut transport_errors, "the SCTP transport error").await
+    );
+    recv_within(&mut transport_closed, "the SCTP transport to close").await;
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sctp_transport_close_reason_handshake_timeout() -> Result<()> {
+    let (mut offer_pc, mut answer_pc, wan) =
+        create_vnet_pair_with_settings(|s| s.set_sctp_max_retransmissions(1)).await?;
+
+    // Let ICE and DTLS connect, but blackhole the SCTP handshake
+    {
+        let w = wan.lock().await;
+        w.add_chunk_filter(Box::new(|c: &(dyn Chunk + Send + Sync)| {
+            c.user_data().first() != Some(&DTLS_APPLICATION_DATA)
+        }))
+        .await;
+    }
+    let (mut transport_errors, mut transport_closed) = watch_sctp_transport(&offer_pc.sctp());
+
+    signal_pair(&mut offer_pc, &mut answer_pc).await?;
+
+    let reason = CloseReason::HandshakeTimeout;
+    assert_eq!(
+        association_closed(reason),
+        recv_within(&mut transport_errors, "the SCTP transport error").await
+    );
+    recv_within(&mut transport_closed, "the SCTP transport to close").await;
+    assert_eq!(Some(reason), offer_pc.sctp().close_reason());
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
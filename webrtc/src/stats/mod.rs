@@ -6,7 +6,7 @@ use crate::rtp_transceiver::rtp_codec::RTCRtpCodecParameters;
 use crate::rtp_transceiver::{PayloadType, SSRC};
 use crate::sctp_transport::RTCSctpTransport;
 
-use ice::agent::agent_stats::{CandidatePairStats, CandidateStats};
+use ice::agent::agent_stats::{CandidatePairStats, CandidateStats, SelectedCandidatePairChange};
 use ice::agent::Agent;
 use ice::candidate::{CandidatePairState, CandidateType};
 use ice::network_type::NetworkType;
@@ -15,9 +15,12 @@ use stats_collector::StatsCollector;
 use serde::{Serialize, Serializer};
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use tokio::time::Instant;
 
+#[cfg(test)]
+mod stats_test;
+
 mod serialize;
 pub mod stats_collector;
 
@@ -113,15 +116,33 @@ impl Serialize for StatsReportType {
     }
 }
 
+/// StatsReport is the result of [`RTCPeerConnection::get_stats`](crate::peer_connection::RTCPeerConnection::get_stats).
+///
+/// The ids of the entries are derived from stable properties of the objects they describe, e.g.
+/// the kind and SSRC of a stream or the id of a candidate, so an object keeps its id in every
+/// report of the connection.
 #[derive(Debug)]
 pub struct StatsReport {
     pub reports: HashMap<String, StatsReportType>,
+    /// The wall clock time the report was gathered at, see
+    /// [`SettingEngine::set_stats_time_source`](crate::api::setting_engine::SettingEngine::set_stats_time_source).
+    pub timestamp: SystemTime,
+    /// The time the report was gathered at, relative to the creation of the connection. Unlike
+    /// `timestamp` it isn't affected by adjustments of the system clock, and it strictly
+    /// increases from one report of a connection to the next, so use it to compute rates.
+    pub monotonic_timestamp: Duration,
 }
 
-impl From<StatsCollector> for StatsReport {
-    fn from(collector: StatsCollector) -> Self {
+impl StatsReport {
+    pub(crate) fn new(
+        collector: StatsCollector,
+        timestamp: SystemTime,
+        monotonic_timestamp: Duration,
+    ) -> Self {
         StatsReport {
             reports: collector.into_reports(),
+            timestamp,
+            monotonic_timestamp,
         }
     }
 }
@@ -139,7 +160,7 @@ impl Serialize for StatsReport {
 #[serde(rename_all = "camelCase")]
 pub struct ICECandidatePairStats {
     // RTCStats
-    #[serde(with = "serialize::instant_to_epoch_seconds")]
+    #[serde(with = "serialize::instant_to_epoch")]
     pub timestamp: Instant,
     #[serde(rename = "type")]
     pub stats_type: RTCStatsType,
@@ -155,9 +176,9 @@ pub struct ICECandidatePairStats {
     pub packets_received: u32,
     pub bytes_sent: u64,
     pub bytes_received: u64,
-    #[serde(with = "serialize::instant_to_epoch_seconds")]
+    #[serde(with = "serialize::instant_to_epoch")]
     pub last_packet_sent_timestamp: Instant,
-    #[serde(with = "serialize::instant_to_epoch_seconds")]
+    #[serde(with = "serialize::instant_to_epoch")]
     pub last_packet_received_timestamp: Instant,
     pub total_round_trip_time: f64,
     pub current_round_trip_time: f64,
@@ -172,14 +193,21 @@ pub struct ICECandidatePairStats {
     // TODO: Add `bytesDiscardedOnSend`
 
     // Non-canon
+    /// Network of the local candidate, telling whether the pair runs over UDP or TCP.
+    pub network_type: NetworkType,
     pub circuit_breaker_trigger_count: u32,
-    #[serde(with = "serialize::instant_to_epoch_seconds")]
+    #[serde(with = "serialize::instant_to_epoch")]
     pub consent_expired_timestamp: Instant,
-    #[serde(with = "serialize::instant_to_epoch_seconds")]
+    #[serde(with = "serialize::instant_to_epoch")]
     pub first_request_timestamp: Instant,
-    #[serde(with = "serialize::instant_to_epoch_seconds")]
+    #[serde(with = "serialize::instant_to_epoch")]
     pub last_request_timestamp: Instant,
     pub retransmissions_sent: u64,
+    #[serde(
+        with = "serialize::system_time_to_epoch",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_keepalive_timestamp: Option<SystemTime>,
 }
 
 impl From<CandidatePairStats> for ICECandidatePairStats {
@@ -197,8 +225,10 @@ impl From<CandidatePairStats> for ICECandidatePairStats {
             id: format!("{}-{}", stats.local_candidate_id, stats.remote_candidate_id),
             last_packet_received_timestamp: stats.last_packet_received_timestamp,
             last_packet_sent_timestamp: stats.last_packet_sent_timestamp,
+            last_keepalive_timestamp: stats.last_keepalive_timestamp,
             last_request_timestamp: stats.last_request_timestamp,
             local_candidate_id: stats.local_candidate_id,
+            network_type: stats.network_type,
             nominated: stats.nominated,
             packets_received: stats.packets_received,
             packets_sent: stats.packets_sent,
@@ -220,7 +250,7 @@ impl From<CandidatePairStats> for ICECandidatePairStats {
 #[serde(rename_all = "camelCase")]
 pub struct ICECandidateStats {
     // RTCStats
-    #[serde(with = "serialize::instant_to_epoch_seconds")]
+    #[serde(with = "serialize::instant_to_epoch")]
     pub timestamp: Instant,
     #[serde(rename = "type")]
     pub stats_type: RTCStatsType,
@@ -229,6 +259,7 @@ pub struct ICECandidateStats {
     // RTCIceCandidateStats
     pub candidate_type: CandidateType,
     pub deleted: bool,
+    #[serde(rename = "address")]
     pub ip: String,
     pub network_type: NetworkType,
     pub port: u16,
@@ -259,7 +290,7 @@ impl ICECandidateStats {
 #[serde(rename_all = "camelCase")]
 pub struct ICETransportStats {
     // RTCStats
-    #[serde(with = "serialize::instant_to_epoch_seconds")]
+    #[serde(with = "serialize::instant_to_epoch")]
     pub timestamp: Instant,
     #[serde(rename = "type")]
     pub stats_type: RTCStatsType,
@@ -268,6 +299,60 @@ pub struct ICETransportStats {
     // Non-canon
     pub bytes_received: usize,
     pub bytes_sent: usize,
+    /// Remote candidates ignored because of the ICE transport policy.
+    pub filtered_remote_candidates: u64,
+
+    // RTCTransportStats
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected_candidate_pair_id: Option<String>,
+    /// Negotiated DTLS version as four upper case hexadecimal digits, e.g. "FEFD" for DTLS 1.2.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_version: Option<String>,
+    /// IANA name of the negotiated DTLS cipher suite.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dtls_cipher: Option<String>,
+    /// Name of the SRTP protection profile negotiated through the use_srtp extension.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub srtp_cipher: Option<String>,
+    /// Role, "client" or "server", the DTLS transport took in the handshake.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dtls_role: Option<String>,
+    /// Time in seconds the DTLS handshake took to complete (non-canon).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dtls_handshake_duration: Option<f64>,
+    /// The latest changes of the selected candidate pair, oldest first (non-canon).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub selected_candidate_pair_changes: Vec<SelectedCandidatePairChangeStats>,
+}
+
+/// A change of the selected candidate pair of an ICE transport (non-canon).
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectedCandidatePairChangeStats {
+    #[serde(with = "serialize::required_system_time_to_epoch")]
+    pub timestamp: SystemTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_candidate_pair_id: Option<String>,
+    pub candidate_pair_id: String,
+    /// One of "initial-nomination", "renomination", "consent-loss-failover" and "restart".
+    pub reason: String,
+}
+
+impl From<SelectedCandidatePairChange> for SelectedCandidatePairChangeStats {
+    fn from(change: SelectedCandidatePairChange) -> Self {
+        SelectedCandidatePairChangeStats {
+            timestamp: change.timestamp,
+            previous_candidate_pair_id: change
+                .previous
+                .map(|pair| format!("{}-{}", pair.local.id(), pair.remote.id())),
+            candidate_pair_id: format!(
+                "{}-{}",
+                change.current.local.id(),
+                change.current.remote.id()
+            ),
+            reason: change.reason.to_string(),
+        }
+    }
 }
 
 impl ICETransportStats {
@@ -276,6 +361,20 @@ impl ICETransportStats {
             id,
             bytes_received: agent.get_bytes_received(),
             bytes_sent: agent.get_bytes_sent(),
+            filtered_remote_candidates: agent.get_filtered_remote_candidates(),
+            selected_candidate_pair_id: agent
+                .get_selected_candidate_pair()
+                .map(|pair| format!("{}-{}", pair.local.id(), pair.remote.id())),
+            tls_version: None,
+            dtls_cipher: None,
+            srtp_cipher: None,
+            dtls_role: None,
+            dtls_handshake_duration: None,
+            selected_candidate_pair_changes: agent
+                .get_selected_candidate_pair_changes()
+                .into_iter()
+                .map(SelectedCandidatePairChangeStats::from)
+                .collect(),
             stats_type: RTCStatsType::Transport,
             timestamp: Instant::now(),
         }
@@ -286,7 +385,7 @@ impl ICETransportStats {
 #[serde(rename_all = "camelCase")]
 pub struct CertificateStats {
     // RTCStats
-    #[serde(with = "serialize::instant_to_epoch_seconds")]
+    #[serde(with = "serialize::instant_to_epoch")]
     pub timestamp: Instant,
     #[serde(rename = "type")]
     pub stats_type: RTCStatsType,
@@ -316,7 +415,7 @@ impl CertificateStats {
 #[serde(rename_all = "camelCase")]
 pub struct CodecStats {
     // RTCStats
-    #[serde(with = "serialize::instant_to_epoch_seconds")]
+    #[serde(with = "serialize::instant_to_epoch")]
     pub timestamp: Instant,
     #[serde(rename = "type")]
     pub stats_type: RTCStatsType,
@@ -350,7 +449,7 @@ impl From<&RTCRtpCodecParameters> for CodecStats {
 #[serde(rename_all = "camelCase")]
 pub struct DataChannelStats {
     // RTCStats
-    #[serde(with = "serialize::instant_to_epoch_seconds")]
+    #[serde(with = "serialize::instant_to_epoch")]
     pub timestamp: Instant,
     #[serde(rename = "type")]
     pub stats_type: RTCStatsType,
@@ -405,7 +504,7 @@ impl DataChannelStats {
 #[serde(rename_all = "camelCase")]
 pub struct PeerConnectionStats {
     // RTCStats
-    #[serde(with = "serialize::instant_to_epoch_seconds")]
+    #[serde(with = "serialize::instant_to_epoch")]
     pub timestamp: Instant,
     #[serde(rename = "type")]
     pub stats_type: RTCStatsType,
@@ -438,7 +537,7 @@ impl PeerConnectionStats {
 #[serde(rename_all = "camelCase")]
 pub struct InboundRTPStats {
     // RTCStats
-    #[serde(with = "serialize::instant_to_epoch_seconds")]
+    #[serde(with = "serialize::instant_to_epoch")]
     pub timestamp: Instant,
     #[serde(rename = "type")]
     pub stats_type: RTCStatsType,
@@ -464,6 +563,10 @@ pub struct InboundRTPStats {
     // NB: `framesDecoded`, `frameWidth`, frameHeight`, `framesPerSecond`, `qpSum`,
     // `totalDecodeTime`, `totalInterFrameDelay`, and `totalSquaredInterFrameDelay` are all decoder
     // specific values and can't be produced since we aren't decoding.
+    #[serde(
+        with = "serialize::system_time_to_epoch",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub last_packet_received_timestamp: Option<SystemTime>,
     pub header_bytes_received: u64,
     // TODO: `packetsDiscarded`. This value only makes sense if we have jitter buffer, which we
@@ -471,7 +574,9 @@ pub struct InboundRTPStats {
     // TODO: `fecPacketsReceived`, `fecPacketsDiscarded`
     pub bytes_received: u64,
     pub nack_count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub fir_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub pli_count: Option<u64>,
     // NB: `totalProcessingDelay`, `estimatedPlayoutTimestamp`, `jitterBufferDelay`,
     // `jitterBufferTargetDelay`, `jitterBufferEmittedCount`, `jitterBufferMinimumDelay`,
@@ -485,7 +590,7 @@ pub struct InboundRTPStats {
 #[serde(rename_all = "camelCase")]
 pub struct OutboundRTPStats {
     // RTCStats
-    #[serde(with = "serialize::instant_to_epoch_seconds")]
+    #[serde(with = "serialize::instant_to_epoch")]
     pub timestamp: Instant,
     #[serde(rename = "type")]
     pub stats_type: RTCStatsType,
@@ -505,20 +610,31 @@ pub struct OutboundRTPStats {
     // NB: non-canon in browsers this is available via `RTCMediaSourceStats` which we are unlikely to implement
     pub track_identifier: String,
     pub mid: String,
-    // TODO: `mediaSourceId` and `remoteId`
+    /// Shared by all the encodings of a simulcast sender.
+    pub media_source_id: String,
+    // TODO: `remoteId`
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub rid: Option<String>,
     pub header_bytes_sent: u64,
-    // TODO: `retransmittedPacketsSent` and `retransmittedPacketsSent`
+    pub retransmitted_packets_sent: u64,
+    pub retransmitted_bytes_sent: u64,
     // NB: `targetBitrate`, `totalEncodedBytesTarget`, `frameWidth` `frameHeight`, `framesPerSecond`, `framesSent`,
     // `hugeFramesSent`, `framesEncoded`, `keyFramesEncoded`, `qpSum`, and `totalEncodeTime` are
     // all encoder specific and can't be produced snce we aren't encoding.
     // TODO: `totalPacketSendDelay` time from `TrackLocalWriter::write_rtp` to being written to
-    // socket.
-
-    // NB: `qualityLimitationReason`, `qualityLimitationDurations`, and `qualityLimitationResolutionChanges` are all
-    // encoder specific and can't be produced since we aren't encoding.
+    // socket, it needs a pacer which we don't have.
+
+    // NB: We aren't encoding so we never limit the quality ourselves, `qualityLimitationReason` is
+    // always "none" and `qualityLimitationResolutionChanges` 0 for video. `qualityLimitationDurations`
+    // is omitted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality_limitation_reason: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality_limitation_resolution_changes: Option<u32>,
     pub nack_count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub fir_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub pli_count: Option<u64>,
     // NB: `encoderImplementation` is encoder specific and can't be produced since we aren't
     // encoding.
@@ -528,7 +644,7 @@ pub struct OutboundRTPStats {
 #[serde(rename_all = "camelCase")]
 pub struct RemoteInboundRTPStats {
     // RTCStats
-    #[serde(with = "serialize::instant_to_epoch_seconds")]
+    #[serde(with = "serialize::instant_to_epoch")]
     pub timestamp: Instant,
     #[serde(rename = "type")]
     pub stats_type: RTCStatsType,
@@ -549,6 +665,7 @@ pub struct RemoteInboundRTPStats {
 
     // RTCRemoteInboundRtpStreamStats
     pub local_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub round_trip_time: Option<f64>,
     pub total_round_trip_time: f64,
     pub fraction_lost: f64,
@@ -559,7 +676,7 @@ pub struct RemoteInboundRTPStats {
 #[serde(rename_all = "camelCase")]
 pub struct RemoteOutboundRTPStats {
     // RTCStats
-    #[serde(with = "serialize::instant_to_epoch_seconds")]
+    #[serde(with = "serialize::instant_to_epoch")]
     pub timestamp: Instant,
     #[serde(rename = "type")]
     pub stats_type: RTCStatsType,
@@ -578,6 +695,7 @@ pub struct RemoteOutboundRTPStats {
     // RTCRemoteOutboundRtpStreamStats
     pub local_id: String,
     // TODO: `remote_timestamp`
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub round_trip_time: Option<f64>,
     pub reports_sent: u64,
     pub total_round_trip_time: f64,
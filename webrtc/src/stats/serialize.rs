@@ -1,12 +1,22 @@
-pub mod instant_to_epoch_seconds {
-    // Serializes a `tokio::time::Instant` to an approximation of epoch time in the form
-    // of an `f64` where the integer portion is seconds and the decimal portion is milliseconds.
-    // For instance, `Monday, May 30, 2022 10:45:26.456 PM UTC` converts to `1653950726.456`.
-    //
+// Timestamps are serialized like the DOMHighResTimeStamp of the W3C stats, as an `f64` of
+// milliseconds since the epoch with a microsecond decimal portion. For instance,
+// `Monday, May 30, 2022 10:45:26.456 PM UTC` converts to `1653950726456.0`.
+
+use serde::{Serialize, Serializer};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn epoch_millis(time: SystemTime) -> f64 {
+    let epoch = time
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards");
+
+    epoch.as_micros() as f64 / 1000.0
+}
+
+pub mod instant_to_epoch {
     // Note that an `Instant` is not connected to real world time, so this conversion is
     // approximate.
-    use serde::{Serialize, Serializer};
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use super::*;
     use tokio::time::Instant;
 
     pub fn serialize<S>(instant: &Instant, serializer: S) -> Result<S::Ok, S::Error>
@@ -16,12 +26,29 @@ pub mod instant_to_epoch_seconds {
         let system_now = SystemTime::now();
         let instant_now = Instant::now();
         let approx = system_now - (instant_now - *instant);
-        let epoch = approx
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards");
 
-        let epoch_ms = epoch.as_millis() as f64 / 1000.0;
+        epoch_millis(approx).serialize(serializer)
+    }
+}
+
+pub mod system_time_to_epoch {
+    use super::*;
+
+    pub fn serialize<S>(time: &Option<SystemTime>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        time.map(epoch_millis).serialize(serializer)
+    }
+}
 
-        epoch_ms.serialize(serializer)
+pub mod required_system_time_to_epoch {
+    use super::*;
+
+    pub fn serialize<S>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        epoch_millis(*time).serialize(serializer)
     }
 }
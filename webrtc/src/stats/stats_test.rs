@@ -0,0 +1,309 @@
+use super::*;
+
+use serde_json::{json, Value};
+use std::time::{Duration, UNIX_EPOCH};
+
+fn new_report() -> StatsReport {
+    let now = Instant::now();
+    let keepalive = Some(UNIX_EPOCH + Duration::from_millis(1_653_950_726_456));
+
+    let reports = vec![
+        StatsReportType::CandidatePair(ICECandidatePairStats {
+            timestamp: now,
+            stats_type: RTCStatsType::CandidatePair,
+            id: "RTCIceCandidatePair_a-b".to_owned(),
+            local_candidate_id: "a".to_owned(),
+            remote_candidate_id: "b".to_owned(),
+            state: CandidatePairState::Succeeded,
+            nominated: true,
+            packets_sent: 1,
+            packets_received: 2,
+            bytes_sent: 3,
+            bytes_received: 4,
+            last_packet_sent_timestamp: now,
+            last_packet_received_timestamp: now,
+            total_round_trip_time: 0.5,
+            current_round_trip_time: 0.25,
+            available_outgoing_bitrate: 0.0,
+            available_incoming_bitrate: 0.0,
+            requests_received: 5,
+            requests_sent: 6,
+            responses_received: 7,
+            responses_sent: 8,
+            consent_requests_sent: 9,
+            network_type: NetworkType::Tcp4,
+            circuit_breaker_trigger_count: 0,
+            consent_expired_timestamp: now,
+            first_request_timestamp: now,
+            last_request_timestamp: now,
+            retransmissions_sent: 10,
+            last_keepalive_timestamp: keepalive,
+        }),
+        StatsReportType::LocalCandidate(ICECandidateStats {
+            timestamp: now,
+            stats_type: RTCStatsType::LocalCandidate,
+            id: "a".to_owned(),
+            candidate_type: CandidateType::Host,
+            deleted: false,
+            ip: "192.0.2.1".to_owned(),
+            network_type: NetworkType::Udp4,
+            port: 5000,
+            priority: 2130706431,
+            relay_protocol: String::new(),
+            url: String::new(),
+        }),
+        StatsReportType::Transport(ICETransportStats {
+            timestamp: now,
+            stats_type: RTCStatsType::Transport,
+            id: "ice_transport".to_owned(),
+            bytes_received: 11,
+            bytes_sent: 12,
+            filtered_remote_candidates: 0,
+            selected_candidate_pair_id: Some("RTCIceCandidatePair_a-b".to_owned()),
+            tls_version: Some("FEFD".to_owned()),
+            dtls_cipher: Some("TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256".to_owned()),
+            srtp_cipher: None,
+            dtls_role: Some("client".to_owned()),
+            dtls_handshake_duration: None,
+            selected_candidate_pair_changes: vec![SelectedCandidatePairChangeStats {
+                timestamp: UNIX_EPOCH + Duration::from_millis(1_653_950_726_456),
+                previous_candidate_pair_id: None,
+                candidate_pair_id: "a-b".to_owned(),
+                reason: "initial-nomination".to_owned(),
+            }],
+        }),
+        StatsReportType::Codec(CodecStats {
+            timestamp: now,
+            stats_type: RTCStatsType::Codec,
+            id: "RTCCodec_111".to_owned(),
+            payload_type: 111,
+            mime_type: "audio/opus".to_owned(),
+            channels: 2,
+            clock_rate: 48000,
+            sdp_fmtp_line: "minptime=10;useinbandfec=1".to_owned(),
+        }),
+        StatsReportType::DataChannel(DataChannelStats {
+            timestamp: now,
+            stats_type: RTCStatsType::DataChannel,
+            id: "RTCDataChannel_1".to_owned(),
+            bytes_received: 13,
+            bytes_sent: 14,
+            data_channel_identifier: 1,
+            label: "chat".to_owned(),
+            messages_received: 15,
+            messages_sent: 16,
+            protocol: String::new(),
+            state: RTCDataChannelState::Open,
+        }),
+        StatsReportType::InboundRTP(InboundRTPStats {
+            timestamp: now,
+            stats_type: RTCStatsType::InboundRTP,
+            id: "RTCInboundRTPVideoStream_1234".to_owned(),
+            ssrc: 1234,
+            kind: "video",
+            packets_received: 17,
+            track_identifier: "track".to_owned(),
+            mid: "0".to_owned(),
+            last_packet_received_timestamp: None,
+            header_bytes_received: 18,
+            bytes_received: 19,
+            nack_count: 20,
+            fir_count: None,
+            pli_count: Some(21),
+        }),
+        StatsReportType::RemoteInboundRTP(RemoteInboundRTPStats {
+            timestamp: now,
+            stats_type: RTCStatsType::RemoteInboundRTP,
+            id: "RTCRemoteInboundRTPVideoStream_5678".to_owned(),
+            ssrc: 5678,
+            kind: "video",
+            packets_received: 22,
+            packets_lost: 1,
+            local_id: "RTCOutboundRTPVideoStream_5678".to_owned(),
+            round_trip_time: Some(0.125),
+            total_round_trip_time: 0.375,
+            fraction_lost: 0.5,
+            round_trip_time_measurements: 3,
+        }),
+    ];
+
+    let reports = reports
+        .into_iter()
+        .map(|report| {
+            let id = match &report {
+                StatsReportType::CandidatePair(stats) => stats.id.clone(),
+                StatsReportType::LocalCandidate(stats) => stats.id.clone(),
+                StatsReportType::Transport(stats) => stats.id.clone(),
+                StatsReportType::Codec(stats) => stats.id.clone(),
+                StatsReportType::DataChannel(stats) => stats.id.clone(),
+                StatsReportType::InboundRTP(stats) => stats.id.clone(),
+                StatsReportType::RemoteInboundRTP(stats) => stats.id.clone(),
+                _ => unreachable!(),
+            };
+            (id, report)
+        })
+        .collect();
+
+    StatsReport {
+        reports,
+        timestamp: SystemTime::now(),
+        monotonic_timestamp: Duration::ZERO,
+    }
+}
+
+/// normalize_timestamps checks that the timestamps taken from an Instant are close to the current
+/// epoch time in milliseconds and replaces them by 0, so that the report can be compared.
+fn normalize_timestamps(value: &mut Value) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as f64;
+
+    for stats in value.as_object_mut().unwrap().values_mut() {
+        for (key, v) in stats.as_object_mut().unwrap().iter_mut() {
+            let instant = matches!(
+                key.as_str(),
+                "timestamp"
+                    | "lastPacketSentTimestamp"
+                    | "lastPacketReceivedTimestamp"
+                    | "consentExpiredTimestamp"
+                    | "firstRequestTimestamp"
+                    | "lastRequestTimestamp"
+            );
+            if instant {
+                let ms = v.as_f64().unwrap();
+                assert!((ms - now).abs() < 60_000.0, "{} {} is not now", key, ms);
+                *v = json!(0);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_stats_report_json() {
+    let report = new_report();
+    let mut value = serde_json::to_value(&report).unwrap();
+    normalize_timestamps(&mut value);
+
+    let golden = json!({
+        "RTCIceCandidatePair_a-b": {
+            "timestamp": 0,
+            "type": "candidate-pair",
+            "id": "RTCIceCandidatePair_a-b",
+            "localCandidateId": "a",
+            "remoteCandidateId": "b",
+            "state": "succeeded",
+            "nominated": true,
+            "packetsSent": 1,
+            "packetsReceived": 2,
+            "bytesSent": 3,
+            "bytesReceived": 4,
+            "lastPacketSentTimestamp": 0,
+            "lastPacketReceivedTimestamp": 0,
+            "totalRoundTripTime": 0.5,
+            "currentRoundTripTime": 0.25,
+            "availableOutgoingBitrate": 0.0,
+            "availableIncomingBitrate": 0.0,
+            "requestsReceived": 5,
+            "requestsSent": 6,
+            "responsesReceived": 7,
+            "responsesSent": 8,
+            "consentRequestsSent": 9,
+            "networkType": "tcp4",
+            "circuitBreakerTriggerCount": 0,
+            "consentExpiredTimestamp": 0,
+            "firstRequestTimestamp": 0,
+            "lastRequestTimestamp": 0,
+            "retransmissionsSent": 10,
+            "lastKeepaliveTimestamp": 1653950726456.0,
+        },
+        "a": {
+            "timestamp": 0,
+            "type": "local-candidate",
+            "id": "a",
+            "candidateType": "host",
+            "deleted": false,
+            "address": "192.0.2.1",
+            "networkType": "udp4",
+            "port": 5000,
+            "priority": 2130706431,
+            "relayProtocol": "",
+            "url": "",
+        },
+        "ice_transport": {
+            "timestamp": 0,
+            "type": "transport",
+            "id": "ice_transport",
+            "bytesReceived": 11,
+            "bytesSent": 12,
+            "filteredRemoteCandidates": 0,
+            "selectedCandidatePairId": "RTCIceCandidatePair_a-b",
+            "tlsVersion": "FEFD",
+            "dtlsCipher": "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+            "dtlsRole": "client",
+            "selectedCandidatePairChanges": [{
+                "timestamp": 1653950726456.0,
+                "candidatePairId": "a-b",
+                "reason": "initial-nomination",
+            }],
+        },
+        "RTCCodec_111": {
+            "timestamp": 0,
+            "type": "codec",
+            "id": "RTCCodec_111",
+            "payloadType": 111,
+            "mimeType": "audio/opus",
+            "channels": 2,
+            "clockRate": 48000,
+            "sdpFmtpLine": "minptime=10;useinbandfec=1",
+        },
+        "RTCDataChannel_1": {
+            "timestamp": 0,
+            "type": "data-channel",
+            "id": "RTCDataChannel_1",
+            "bytesReceived": 13,
+            "bytesSent": 14,
+            "dataChannelIdentifier": 1,
+            "label": "chat",
+            "messagesReceived": 15,
+            "messagesSent": 16,
+            "protocol": "",
+            "state": "open",
+        },
+        "RTCInboundRTPVideoStream_1234": {
+            "timestamp": 0,
+            "type": "inbound-rtp",
+            "id": "RTCInboundRTPVideoStream_1234",
+            "ssrc": 1234,
+            "kind": "video",
+            "packetsReceived": 17,
+            "trackIdentifier": "track",
+            "mid": "0",
+            "headerBytesReceived": 18,
+            "bytesReceived": 19,
+            "nackCount": 20,
+            "pliCount": 21,
+        },
+        "RTCRemoteInboundRTPVideoStream_5678": {
+            "timestamp": 0,
+            "type": "remote-inbound-rtp",
+            "id": "RTCRemoteInboundRTPVideoStream_5678",
+            "ssrc": 5678,
+            "kind": "video",
+            "packetsReceived": 22,
+            "packetsLost": 1,
+            "localId": "RTCOutboundRTPVideoStream_5678",
+            "roundTripTime": 0.125,
+            "totalRoundTripTime": 0.375,
+            "fractionLost": 0.5,
+            "roundTripTimeMeasurements": 3,
+        },
+    });
+
+    assert_eq!(
+        value,
+        golden,
+        "{}",
+        serde_json::to_string_pretty(&value).unwrap()
+    );
+}
@@ -1,22 +1,28 @@
+pub mod rtp_ingest;
 pub mod track_local;
 pub mod track_remote;
 
 use track_remote::*;
 
+use crate::rtp_transceiver::rtp_receiver::RepairedPacket;
+
 use interceptor::stream_info::StreamInfo;
 use interceptor::{RTCPReader, RTPReader};
 use std::sync::Arc;
+use tokio::sync::mpsc;
 
 pub(crate) const RTP_OUTBOUND_MTU: usize = 1200;
 pub(crate) const RTP_PAYLOAD_TYPE_BITMASK: u8 = 0x7F;
 
-#[derive(Clone)]
+#[derive(Default, Clone)]
 pub(crate) struct TrackStream {
     pub(crate) stream_info: Option<StreamInfo>,
     pub(crate) rtp_read_stream: Option<Arc<srtp::stream::Stream>>,
     pub(crate) rtp_interceptor: Option<Arc<dyn RTPReader + Send + Sync>>,
     pub(crate) rtcp_read_stream: Option<Arc<srtp::stream::Stream>>,
     pub(crate) rtcp_interceptor: Option<Arc<dyn RTCPReader + Send + Sync>>,
+    // Packets recovered from the repair stream, read through the interceptors of this stream
+    pub(crate) repaired_tx: Option<mpsc::Sender<RepairedPacket>>,
 }
 
 /// TrackStreams maintains a mapping of RTP/RTCP streams to a specific track
@@ -0,0 +1,183 @@
+#[cfg(test)]
+mod rtp_ingest_test;
+
+use crate::error::{Error, Result};
+use crate::rtp_transceiver::rtp_codec::{
+    RTCRtpCodecParameters, RTCRtpHeaderExtensionParameters, RTPCodecType,
+};
+use crate::rtp_transceiver::rtp_receiver::layer_selector::{
+    is_keyframe, patch_header, LayerSwitcher,
+};
+use crate::rtp_transceiver::{create_stream_info, SSRC};
+
+use bytes::{Bytes, BytesMut};
+use interceptor::stream_info::StreamInfo;
+use interceptor::{Attributes, Interceptor, RTCPWriter, RTCPWriterFn, RTPReader, RTPReaderFn};
+use media::io::sample_builder::SampleBuilder;
+use media::Sample;
+use rtp::packetizer::Depacketizer;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::time::Instant;
+use util::sync::Mutex as SyncMutex;
+use util::Unmarshal;
+
+/// RtpIngestConfig is the configuration of a RtpIngest
+#[derive(Default)]
+pub struct RtpIngestConfig {
+    /// The codec of the ingested packets, its payload type is set on all of them.
+    pub codec: RTCRtpCodecParameters,
+    /// The header extensions carried by the packets, for the interceptors that use them.
+    pub header_extensions: Vec<RTCRtpHeaderExtensionParameters>,
+    /// The SSRC the packets are ingested with, a random one if 0.
+    pub ssrc: SSRC,
+    /// The number of packets a sample waits for the missing ones, see `SampleBuilder::new`.
+    pub max_late: u16,
+    /// The interceptors the packets go through, e.g. built from an `interceptor::registry::Registry`.
+    pub interceptor: Option<Arc<dyn Interceptor + Send + Sync>>,
+    /// Where the RTCP generated by the interceptors, such as NACKs and receiver reports, is
+    /// written to. It's discarded if not set.
+    pub rtcp_writer: Option<Arc<dyn RTCPWriter + Send + Sync>>,
+}
+
+/// RtpIngest builds Samples out of RTP packets received out of band, e.g. from a cascaded SFU
+/// over QUIC or read from a recording, without a PeerConnection. The packets go through the
+/// configured interceptors, as the packets of a remote track do, and then a SampleBuilder.
+///
+/// The upstream SSRC is replaced with the SSRC of the ingest, so it can't collide with the
+/// local streams. When packets of a new upstream SSRC arrive, they take over once they start
+/// with a keyframe, and their sequence numbers and timestamps continue the ones ingested.
+pub struct RtpIngest<T: Depacketizer> {
+    kind: RTPCodecType,
+    codec: RTCRtpCodecParameters,
+    ssrc: SSRC,
+    stream_info: StreamInfo,
+    interceptor: Option<Arc<dyn Interceptor + Send + Sync>>,
+    rtp_reader: Arc<dyn RTPReader + Send + Sync>,
+    // packets pushed, waiting to be read through the interceptors
+    pending: Arc<SyncMutex<VecDeque<Bytes>>>,
+    switcher: LayerSwitcher<SSRC>,
+    builder: SampleBuilder<T>,
+}
+
+impl<T: Depacketizer> RtpIngest<T> {
+    pub async fn new(config: RtpIngestConfig, depacketizer: T) -> Self {
+        let ssrc = if config.ssrc != 0 {
+            config.ssrc
+        } else {
+            rand::random::<u32>()
+        };
+        let kind = if config.codec.capability.mime_type.starts_with("audio/") {
+            RTPCodecType::Audio
+        } else if config.codec.capability.mime_type.starts_with("video/") {
+            RTPCodecType::Video
+        } else {
+            RTPCodecType::Unspecified
+        };
+        let stream_info = create_stream_info(
+            String::new(),
+            ssrc,
+            config.codec.payload_type,
+            config.codec.capability.clone(),
+            &config.header_extensions,
+        );
+
+        let pending = Arc::new(SyncMutex::new(VecDeque::<Bytes>::new()));
+        let source: Arc<dyn RTPReader + Send + Sync> = {
+            let pending = Arc::clone(&pending);
+            Arc::new(RTPReaderFn(Box::new(
+                move |buf: &mut [u8], _: &Attributes| {
+                    let result = match pending.lock().pop_front() {
+                        Some(pkt) if pkt.len() <= buf.len() => {
+                            buf[..pkt.len()].copy_from_slice(&pkt);
+                            Ok((pkt.len(), Attributes::new()))
+                        }
+                        Some(_) => Err(interceptor::Error::ErrShortBuffer),
+                        None => Err(interceptor::Error::ErrIoEOF),
+                    };
+                    Box::pin(async move { result })
+                },
+            )))
+        };
+
+        let rtp_reader = if let Some(interceptor) = &config.interceptor {
+            let rtcp_writer = config.rtcp_writer.unwrap_or_else(|| {
+                Arc::new(RTCPWriterFn(Box::new(|_, _| Box::pin(async { Ok(0) }))))
+            });
+            interceptor.bind_rtcp_writer(rtcp_writer).await;
+            interceptor.bind_remote_stream(&stream_info, source).await
+        } else {
+            source
+        };
+
+        let sample_rate = config.codec.capability.clock_rate;
+        RtpIngest {
+            kind,
+            codec: config.codec,
+            ssrc,
+            stream_info,
+            interceptor: config.interceptor,
+            rtp_reader,
+            pending,
+            switcher: LayerSwitcher::default(),
+            builder: SampleBuilder::new(config.max_late, depacketizer, sample_rate),
+        }
+    }
+
+    /// ssrc returns the SSRC the packets are ingested with
+    pub fn ssrc(&self) -> SSRC {
+        self.ssrc
+    }
+
+    /// push ingests a marshaled RTP packet, the samples completed by it are returned by `pop`.
+    pub async fn push(&mut self, buf: &[u8]) -> Result<()> {
+        let mut data = BytesMut::from(buf);
+        let mut header = {
+            let pkt = rtp::packet::Packet::unmarshal(&mut &data[..])?;
+            if pkt.header.version != 2 {
+                return Err(Error::ErrRTPUnsupportedVersion);
+            }
+
+            let keyframe = self.kind != RTPCodecType::Video
+                || is_keyframe(&self.codec.capability.mime_type, &pkt.payload);
+            let mut header = pkt.header;
+            let upstream = header.ssrc;
+            self.switcher.follow(&upstream);
+            if !self.switcher.forward(
+                &upstream,
+                &mut header,
+                keyframe,
+                self.codec.capability.clock_rate,
+                Instant::now(),
+            ) {
+                return Ok(());
+            }
+            header
+        };
+        header.ssrc = self.ssrc;
+        header.payload_type = self.codec.payload_type;
+        patch_header(&mut data, &header);
+
+        let mut b = vec![0u8; data.len()];
+        self.pending.lock().push_back(data.freeze());
+        let (n, _) = self.rtp_reader.read(&mut b, &Attributes::new()).await?;
+
+        let pkt = rtp::packet::Packet::unmarshal(&mut &b[..n])?;
+        self.builder.push(pkt);
+        Ok(())
+    }
+
+    /// pop returns the next sample built, if any.
+    pub fn pop(&mut self) -> Option<Sample> {
+        self.builder.pop()
+    }
+
+    /// close unbinds the stream from the interceptors and closes them.
+    pub async fn close(&self) -> Result<()> {
+        if let Some(interceptor) = &self.interceptor {
+            interceptor.unbind_remote_stream(&self.stream_info).await;
+            interceptor.close().await?;
+        }
+        Ok(())
+    }
+}
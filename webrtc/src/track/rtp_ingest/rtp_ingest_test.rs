@@ -0,0 +1,247 @@
+use super::*;
+use crate::api::interceptor_registry::register_default_interceptors;
+use crate::api::media_engine::{MediaEngine, MIME_TYPE_VP8};
+use crate::api::APIBuilder;
+use crate::peer_connection::peer_connection_test::{close_pair_now, new_pair, signal_pair};
+use crate::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
+use crate::rtp_transceiver::rtp_receiver::RTCRtpReceiver;
+use crate::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
+use crate::track::track_local::TrackLocal;
+use crate::track::track_remote::TrackRemote;
+
+use interceptor::registry::Registry;
+use rtp::codecs::vp8::{Vp8Packet, Vp8Payloader};
+use rtp::packetizer::Payloader;
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+use util::Marshal;
+
+const FRAMES: usize = 12;
+const SEQUENCE_NUMBER_BASE: u16 = 65500;
+
+fn vp8_codec() -> RTCRtpCodecParameters {
+    RTCRtpCodecParameters {
+        capability: RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            clock_rate: 90000,
+            ..Default::default()
+        },
+        payload_type: 96,
+        ..Default::default()
+    }
+}
+
+/// frame returns a VP8 frame, a keyframe every fifth one, spanning a few packets.
+fn frame(i: usize) -> Bytes {
+    let mut data = vec![i as u8; 2500 + i * 10];
+    data[0] = if i % 5 == 0 { 0x00 } else { 0x01 };
+    Bytes::from(data)
+}
+
+/// record packetizes the frames as an upstream stream would send them.
+fn record(ssrc: SSRC, sequence_number: u16, timestamp: u32, frames: &[Bytes]) -> Vec<Bytes> {
+    let mut payloader = Vp8Payloader::default();
+    let mut sequence_number = sequence_number;
+    let mut packets = vec![];
+    for (i, frame) in frames.iter().enumerate() {
+        let payloads = payloader.payload(1100, frame).unwrap();
+        let last = payloads.len() - 1;
+        for (j, payload) in payloads.into_iter().enumerate() {
+            let pkt = rtp::packet::Packet {
+                header: rtp::header::Header {
+                    version: 2,
+                    marker: j == last,
+                    payload_type: 111,
+                    sequence_number,
+                    timestamp: timestamp.wrapping_add(i as u32 * 3000),
+                    ssrc,
+                    ..Default::default()
+                },
+                payload,
+            };
+            packets.push(pkt.marshal().unwrap());
+            sequence_number = sequence_number.wrapping_add(1);
+        }
+    }
+    packets
+}
+
+async fn ingest(packets: &[Bytes], interceptor: bool) -> Result<Vec<Sample>> {
+    let interceptor = if interceptor {
+        let mut m = MediaEngine::default();
+        m.register_default_codecs()?;
+        let registry = register_default_interceptors(Registry::new(), &mut m)?;
+        Some(registry.build("")?)
+    } else {
+        None
+    };
+
+    let mut ingest = RtpIngest::new(
+        RtpIngestConfig {
+            codec: vp8_codec(),
+            max_late: 10,
+            interceptor,
+            ..Default::default()
+        },
+        Vp8Packet::default(),
+    )
+    .await;
+
+    let mut samples = vec![];
+    for pkt in packets {
+        ingest.push(pkt).await?;
+        while let Some(sample) = ingest.pop() {
+            samples.push(sample);
+        }
+    }
+    ingest.close().await?;
+
+    Ok(samples)
+}
+
+#[tokio::test]
+async fn test_rtp_ingest_matches_peer_connection() -> Result<()> {
+    let frames: Vec<Bytes> = (0..FRAMES).map(frame).collect();
+    let packets = record(0x1234_5678, SEQUENCE_NUMBER_BASE, 1000, &frames);
+
+    let ingested = ingest(&packets, true).await?;
+    // The last frame waits for the one after it
+    assert_eq!(ingested.len(), FRAMES - 1);
+    for (sample, frame) in ingested.iter().zip(frames.iter()) {
+        assert_eq!(&sample.data, frame);
+    }
+
+    // The same packets sent to a PeerConnection, and built into samples from its track
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+    let (mut pc_offer, mut pc_answer) = new_pair(&api).await?;
+
+    let track = Arc::new(TrackLocalStaticRTP::new(
+        vp8_codec().capability,
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    pc_offer
+        .add_track(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>)
+        .await?;
+
+    let (received_tx, mut received_rx) = mpsc::channel::<()>(1);
+    let (samples_tx, mut samples_rx) = mpsc::channel::<Sample>(FRAMES);
+    pc_answer.on_track(Box::new(
+        move |track: Option<Arc<TrackRemote>>, _: Option<Arc<RTCRtpReceiver>>| {
+            let (received_tx, samples_tx) = (received_tx.clone(), samples_tx.clone());
+            Box::pin(async move {
+                let track = match track {
+                    Some(track) => track,
+                    None => return,
+                };
+                tokio::spawn(async move {
+                    let mut builder = SampleBuilder::new(10, Vp8Packet::default(), 90000);
+                    while let Ok((p, _)) = track.read_rtp().await {
+                        let recorded =
+                            p.header.sequence_number.wrapping_sub(SEQUENCE_NUMBER_BASE) < 1000;
+                        if !recorded {
+                            let _ = received_tx.try_send(());
+                            continue;
+                        }
+                        builder.push(p);
+                        while let Some(sample) = builder.pop() {
+                            let _ = samples_tx.send(sample).await;
+                        }
+                    }
+                });
+            })
+        },
+    ));
+
+    signal_pair(&mut pc_offer, &mut pc_answer).await?;
+
+    // Warm up with keyframes preceding the recording, until the track is received
+    for warm_up in 0..500 {
+        let pkt = rtp::packet::Packet {
+            header: rtp::header::Header {
+                version: 2,
+                marker: true,
+                sequence_number: SEQUENCE_NUMBER_BASE.wrapping_sub(1000) + warm_up,
+                timestamp: 1000 - 90,
+                ssrc: 0x1234_5678,
+                ..Default::default()
+            },
+            payload: Bytes::from_static(&[0x10, 0x00, 0xFF, 0xFF]),
+        };
+        track.write_rtp_raw(&pkt.marshal()?).await?;
+        tokio::select! {
+            _ = received_rx.recv() => break,
+            _ = tokio::time::sleep(Duration::from_millis(20)) => {}
+        }
+    }
+
+    for pkt in &packets {
+        track.write_rtp_raw(pkt).await?;
+    }
+
+    for expected in &ingested {
+        let sample = tokio::time::timeout(Duration::from_secs(5), samples_rx.recv())
+            .await
+            .expect("timed out waiting for the samples of the PeerConnection")
+            .unwrap();
+        assert_eq!(sample.data, expected.data);
+        assert_eq!(sample.packet_timestamp, expected.packet_timestamp);
+        assert_eq!(sample.duration, expected.duration);
+    }
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rtp_ingest_upstream_ssrc_change() -> Result<()> {
+    let frames: Vec<Bytes> = (0..FRAMES).map(frame).collect();
+    let mut packets = record(0x1111_1111, 100, 1000, &frames[..4]);
+    // The upstream restarts with another SSRC and clock, starting with a delta frame that
+    // can't be decoded on its own.
+    packets.extend(record(0x2222_2222, 40000, 7_000_000, &frames[4..8]));
+
+    let samples = ingest(&packets, false).await?;
+
+    let data: Vec<&Bytes> = samples.iter().map(|s| &s.data).collect();
+    let expected: Vec<&Bytes> = frames[..4].iter().chain(frames[5..7].iter()).collect();
+    assert_eq!(data, expected);
+
+    // The timestamps continue across the SSRCs
+    for pair in samples.windows(2) {
+        let delta = pair[1]
+            .packet_timestamp
+            .wrapping_sub(pair[0].packet_timestamp);
+        assert!(delta > 0 && delta < 90000, "unexpected delta {}", delta);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rtp_ingest_invalid_packet() -> Result<()> {
+    let mut ingest = RtpIngest::new(
+        RtpIngestConfig {
+            codec: vp8_codec(),
+            ssrc: 5000,
+            ..Default::default()
+        },
+        Vp8Packet::default(),
+    )
+    .await;
+    assert_eq!(ingest.ssrc(), 5000);
+
+    assert!(ingest.push(&[0x80, 0x60]).await.is_err());
+
+    let mut pkt = record(1, 1, 1, &[frame(0)])[0].to_vec();
+    pkt[0] &= 0x3F;
+    assert!(matches!(
+        ingest.push(&pkt).await,
+        Err(Error::ErrRTPUnsupportedVersion)
+    ));
+
+    Ok(())
+}
@@ -3,8 +3,13 @@ use std::collections::HashMap;
 use super::*;
 
 use crate::error::flatten_errs;
-use bytes::BytesMut;
+use crate::rtp_transceiver::rtp_receiver::layer_selector::{
+    is_keyframe, patch_header, LayerSwitcher,
+};
+use bytes::{Bytes, BytesMut};
 use tokio::sync::Mutex;
+use tokio::time::Instant;
+use util::sync::Mutex as SyncMutex;
 use util::{Marshal, MarshalSize};
 
 /// TrackLocalStaticRTP  is a TrackLocal that has a pre-set codec and accepts RTP Packets.
@@ -15,6 +20,8 @@ pub struct TrackLocalStaticRTP {
     codec: RTCRtpCodecCapability,
     id: String,
     stream_id: String,
+    // the upstream streams of the packets written with write_rtp_raw
+    raw_switcher: SyncMutex<LayerSwitcher<SSRC>>,
 }
 
 impl TrackLocalStaticRTP {
@@ -25,6 +32,7 @@ impl TrackLocalStaticRTP {
             bindings: Mutex::new(vec![]),
             id,
             stream_id,
+            raw_switcher: SyncMutex::new(LayerSwitcher::default()),
         }
     }
 
@@ -59,22 +67,100 @@ impl TrackLocalStaticRTP {
     ///
     /// Extensions that are already configured on the packet are overwritten by extensions in
     /// `extensions`.
+    ///
+    /// Only the SSRC and payload type are rewritten per binding, the CSRC list of the packet is
+    /// forwarded as is, so mixed packets keep their contributing sources.
+    ///
+    /// Per packet metadata such as the AV1 Dependency Descriptor is attached by passing
+    /// `HeaderExtension::DependencyDescriptor`, it's only sent if the extension was negotiated.
     pub async fn write_rtp_with_extensions(
         &self,
         p: &rtp::packet::Packet,
         extensions: &[rtp::extension::HeaderExtension],
     ) -> Result<usize> {
-        let mut n = 0;
-        let mut write_errs = vec![];
-        let mut pkt = p.clone();
+        self.write_rtp_with_extension_iter(p, extensions).await
+    }
+
+    /// write_rtp_raw writes a marshaled RTP packet received out of band, e.g. from a cascaded
+    /// SFU, to the TrackLocalStaticRTP. The packet is validated and parsed once, then each
+    /// binding gets a copy of it with its SSRC and payload type patched in place, the rest of
+    /// the packet is sent as received.
+    ///
+    /// The upstream SSRC is never sent as is, so it can't collide with the SSRCs of the local
+    /// streams. When packets of a new upstream SSRC arrive, e.g. after the upstream restarted,
+    /// they take over once they start with a keyframe, and their sequence numbers and
+    /// timestamps continue the ones already sent. Packets that aren't forwarded return 0.
+    pub async fn write_rtp_raw(&self, buf: &[u8]) -> Result<usize> {
+        let raw = Bytes::copy_from_slice(buf);
+        let mut pkt = rtp::packet::Packet::unmarshal(&mut raw.clone())?;
+        if pkt.header.version != 2 {
+            return Err(Error::ErrRTPUnsupportedVersion);
+        }
+
+        let keyframe =
+            self.kind() != RTPCodecType::Video || is_keyframe(&self.codec.mime_type, &pkt.payload);
+        {
+            let mut switcher = self.raw_switcher.lock();
+            let ssrc = pkt.header.ssrc;
+            switcher.follow(&ssrc);
+            if !switcher.forward(
+                &ssrc,
+                &mut pkt.header,
+                keyframe,
+                self.codec.clock_rate,
+                Instant::now(),
+            ) {
+                return Ok(0);
+            }
+        }
 
         let bindings = {
             let bindings = self.bindings.lock().await;
             bindings.clone()
         };
+
+        let mut n = 0;
+        let mut write_errs = vec![];
+        for b in bindings.into_iter() {
+            if b.is_sender_paused() {
+                continue;
+            }
+            pkt.header.ssrc = b.ssrc;
+            pkt.header.payload_type = b.payload_type;
+
+            let mut data = BytesMut::from(&raw[..]);
+            patch_header(&mut data, &pkt.header);
+
+            if let Some(write_stream) = &b.write_stream {
+                match write_stream.write(&data).await {
+                    Ok(m) => {
+                        n += m;
+                    }
+                    Err(err) => {
+                        write_errs.push(err);
+                    }
+                }
+            } else {
+                write_errs.push(Error::new("track binding has none write_stream".to_owned()));
+            }
+        }
+
+        flatten_errs(write_errs)?;
+        Ok(n)
+    }
+
+    pub(crate) async fn write_rtp_with_extension_iter<'a>(
+        &self,
+        p: &rtp::packet::Packet,
+        extensions: impl IntoIterator<Item = &'a rtp::extension::HeaderExtension>,
+    ) -> Result<usize> {
+        let mut n = 0;
+        let mut write_errs = vec![];
+        let mut pkt = p.clone();
+
         // Prepare the extensions data
         let extension_data: HashMap<_, _> = extensions
-            .iter()
+            .into_iter()
             .flat_map(|extension| {
                 let buf = {
                     let mut buf = BytesMut::with_capacity(extension.marshal_size());
@@ -91,6 +177,11 @@ impl TrackLocalStaticRTP {
             })
             .collect();
 
+        let bindings = {
+            let bindings = self.bindings.lock().await;
+            bindings.clone()
+        };
+
         for b in bindings.into_iter() {
             if b.is_sender_paused() {
                 // See caveat in function doc.
@@ -2,10 +2,49 @@ use super::track_local_static_rtp::TrackLocalStaticRTP;
 use super::*;
 use crate::error::flatten_errs;
 
+use crate::rtp_transceiver::rtp_receiver::layer_selector::is_keyframe;
 use crate::track::RTP_OUTBOUND_MTU;
+use arc_swap::ArcSwapOption;
 use log::warn;
 use media::Sample;
+use rtp::extension::HeaderExtension;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::SystemTime;
 use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
+use util::sync::Mutex as SyncMutex;
+
+/// Keyframe requests of the remote peer arriving within this long of the previous one are
+/// dropped by default
+const DEFAULT_KEYFRAME_REQUEST_WINDOW: Duration = Duration::from_millis(500);
+
+/// KeyframeRequestReason tells why the producer of a TrackLocalStaticSample is asked for a
+/// keyframe.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyframeRequestReason {
+    /// The remote peer sent a Picture Loss Indication
+    PictureLoss,
+    /// The remote peer sent a Full Intra Request
+    FullIntraRequest,
+    /// No keyframe was written for the periodic keyframe interval
+    Periodic,
+}
+
+pub type OnKeyframeRequestHdlrFn = Box<
+    dyn (FnMut(KeyframeRequestReason) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>)
+        + Send
+        + Sync,
+>;
+
+#[derive(Debug)]
+struct KeyframeRequests {
+    window: Duration,
+    periodic_interval: Option<Duration>,
+    last_request: Option<Instant>,
+    // The last keyframe written or requested, periodic requests are timed from it
+    last_keyframe: Option<Instant>,
+}
 
 #[derive(Debug, Clone)]
 struct TrackLocalStaticSampleInternal {
@@ -13,14 +52,55 @@ struct TrackLocalStaticSampleInternal {
     sequencer: Option<Box<dyn rtp::sequence::Sequencer + Send + Sync>>,
     clock_rate: f64,
     did_warn_about_wonky_pause: bool,
+    // RTP timestamps of the last sample written and of the one after it, once packetized
+    last_timestamp: Option<u32>,
+    next_timestamp: Option<u32>,
+    // Capture time and RTP timestamp capture times of samples are mapped from
+    clock_epoch: Option<(SystemTime, u32)>,
+}
+
+impl TrackLocalStaticSampleInternal {
+    /// timestamp_at maps a capture time onto the RTP clock. Capture times from before the
+    /// previous sample, e.g. after the wall clock jumped back, continue from the duration of the
+    /// previous sample and become the new epoch, so the RTP timestamp never decreases.
+    fn timestamp_at(&mut self, capture_time: SystemTime, next_timestamp: u32) -> u32 {
+        if let Some((epoch_time, epoch_timestamp)) = self.clock_epoch {
+            if let Ok(elapsed) = capture_time.duration_since(epoch_time) {
+                let samples = (elapsed.as_secs_f64() * self.clock_rate).round() as u64;
+                let timestamp = epoch_timestamp.wrapping_add(samples as u32);
+                let after_last = self
+                    .last_timestamp
+                    .map_or(true, |last| timestamp.wrapping_sub(last) as i32 >= 0);
+                if after_last {
+                    return timestamp;
+                }
+            }
+        }
+
+        self.clock_epoch = Some((capture_time, next_timestamp));
+        next_timestamp
+    }
 }
 
 /// TrackLocalStaticSample is a TrackLocal that has a pre-set codec and accepts Samples.
 /// If you wish to send a RTP Packet use TrackLocalStaticRTP
-#[derive(Debug)]
 pub struct TrackLocalStaticSample {
     rtp_track: TrackLocalStaticRTP,
     internal: Mutex<TrackLocalStaticSampleInternal>,
+    keyframe_requests: SyncMutex<KeyframeRequests>,
+    keyframe_pending: AtomicBool,
+    on_keyframe_request_handler: ArcSwapOption<Mutex<OnKeyframeRequestHdlrFn>>,
+}
+
+impl fmt::Debug for TrackLocalStaticSample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrackLocalStaticSample")
+            .field("rtp_track", &self.rtp_track)
+            .field("internal", &self.internal)
+            .field("keyframe_requests", &self.keyframe_requests)
+            .field("keyframe_pending", &self.keyframe_pending)
+            .finish()
+    }
 }
 
 impl TrackLocalStaticSample {
@@ -35,7 +115,18 @@ impl TrackLocalStaticSample {
                 sequencer: None,
                 clock_rate: 0.0f64,
                 did_warn_about_wonky_pause: false,
+                last_timestamp: None,
+                next_timestamp: None,
+                clock_epoch: None,
+            }),
+            keyframe_requests: SyncMutex::new(KeyframeRequests {
+                window: DEFAULT_KEYFRAME_REQUEST_WINDOW,
+                periodic_interval: None,
+                last_request: None,
+                last_keyframe: None,
             }),
+            keyframe_pending: AtomicBool::new(false),
+            on_keyframe_request_handler: ArcSwapOption::empty(),
         }
     }
 
@@ -44,10 +135,101 @@ impl TrackLocalStaticSample {
         self.rtp_track.codec()
     }
 
+    /// on_keyframe_request sets an event handler which is invoked when the producer of the samples
+    /// should write a keyframe: when the remote peer sent a Picture Loss Indication or a Full
+    /// Intra Request for the track, or when no keyframe was written for the periodic keyframe
+    /// interval.
+    ///
+    /// Requests of the remote peer are read from the RTCP of the RTPSender, which has to be read
+    /// with `RTCRtpSender::read` or `RTCRtpSender::read_rtcp` as for the interceptors. Requests
+    /// arriving within the keyframe request window of the previous one are dropped, see
+    /// [`TrackLocalStaticSample::set_keyframe_request_window`].
+    pub fn on_keyframe_request(&self, f: OnKeyframeRequestHdlrFn) {
+        self.on_keyframe_request_handler
+            .store(Some(Arc::new(Mutex::new(f))));
+    }
+
+    /// set_keyframe_request_window sets how long keyframe requests of the remote peer are dropped
+    /// after one was passed on, so that a burst of them, e.g. a PLI from every receiver of a
+    /// forwarded stream, results in a single keyframe. Defaults to 500ms.
+    pub fn set_keyframe_request_window(&self, window: Duration) {
+        self.keyframe_requests.lock().window = window;
+    }
+
+    /// set_periodic_keyframe_interval sets the interval after which a keyframe is requested if
+    /// none was written nor requested, for remote peers which never send keyframe requests.
+    /// Disabled by default.
+    ///
+    /// The interval is checked whenever a sample is written, the request is made after writing
+    /// it.
+    pub fn set_periodic_keyframe_interval(&self, interval: Option<Duration>) {
+        self.keyframe_requests.lock().periodic_interval = interval;
+    }
+
+    /// force_keyframe_pending returns true from a keyframe request until a sample starting with a
+    /// keyframe of the codec of the track is written. The producer of the samples can check it
+    /// before encoding each one instead of handling [`TrackLocalStaticSample::on_keyframe_request`].
+    pub fn force_keyframe_pending(&self) -> bool {
+        self.keyframe_pending.load(Ordering::SeqCst)
+    }
+
+    /// request_keyframe passes a keyframe request on to the producer of the samples, unless a
+    /// request of the remote peer arrives within the keyframe request window of the previous one.
+    pub(crate) async fn request_keyframe(&self, reason: KeyframeRequestReason) {
+        {
+            let now = Instant::now();
+            let mut keyframe_requests = self.keyframe_requests.lock();
+            if let Some(last_request) = keyframe_requests.last_request {
+                if reason != KeyframeRequestReason::Periodic
+                    && now.duration_since(last_request) < keyframe_requests.window
+                {
+                    return;
+                }
+            }
+            keyframe_requests.last_request = Some(now);
+            keyframe_requests.last_keyframe = Some(now);
+        }
+        self.keyframe_pending.store(true, Ordering::SeqCst);
+
+        if let Some(handler) = &*self.on_keyframe_request_handler.load() {
+            let mut f = handler.lock().await;
+            f(reason).await;
+        }
+    }
+
+    /// written_sample updates the keyframe state after a sample was written and returns true if a
+    /// periodic keyframe request is due.
+    fn written_sample(&self, keyframe: bool) -> bool {
+        let now = Instant::now();
+        let mut keyframe_requests = self.keyframe_requests.lock();
+        if keyframe {
+            self.keyframe_pending.store(false, Ordering::SeqCst);
+            keyframe_requests.last_keyframe = Some(now);
+            return false;
+        }
+
+        let interval = match keyframe_requests.periodic_interval {
+            Some(interval) if !self.keyframe_pending.load(Ordering::SeqCst) => interval,
+            _ => return false,
+        };
+        match keyframe_requests.last_keyframe {
+            Some(last_keyframe) => now.duration_since(last_keyframe) >= interval,
+            None => {
+                keyframe_requests.last_keyframe = Some(now);
+                false
+            }
+        }
+    }
+
     /// write_sample writes a Sample to the TrackLocalStaticSample
     /// If one PeerConnection fails the packets will still be sent to
     /// all PeerConnections. The error message will contain the ID of the failed
     /// PeerConnections so you can remove them
+    ///
+    /// The `orientation` of the sample is sent on its last packet if the video orientation
+    /// extension was negotiated, see `SettingEngine::set_video_orientation`.
+    ///
+    /// A sample starting with a keyframe clears [`TrackLocalStaticSample::force_keyframe_pending`].
     pub async fn write_sample(&self, sample: &Sample) -> Result<()> {
         self.write_sample_with_extensions(sample, &[]).await
     }
@@ -61,6 +243,34 @@ impl TrackLocalStaticSample {
         &self,
         sample: &Sample,
         extensions: &[rtp::extension::HeaderExtension],
+    ) -> Result<()> {
+        self.write_sample_internal(sample, None, extensions).await
+    }
+
+    /// write_sample_with_timestamp writes a Sample captured at `capture_time`.
+    ///
+    /// The RTP timestamp is derived from the capture time on the media clock, relative to the
+    /// first sample written with a capture time, instead of from the durations of the samples
+    /// written before. Samples delivered in bursts, e.g. by a file reader, are thereby still
+    /// timestamped evenly. A capture time before the one of the previous sample is treated as a
+    /// clock jump: the timestamp follows from the previous sample's duration instead and never
+    /// decreases.
+    ///
+    /// See [`TrackLocalStaticSample::write_sample`] for further details.
+    pub async fn write_sample_with_timestamp(
+        &self,
+        sample: &Sample,
+        capture_time: SystemTime,
+    ) -> Result<()> {
+        self.write_sample_internal(sample, Some(capture_time), &[])
+            .await
+    }
+
+    async fn write_sample_internal(
+        &self,
+        sample: &Sample,
+        capture_time: Option<SystemTime>,
+        extensions: &[rtp::extension::HeaderExtension],
     ) -> Result<()> {
         let mut internal = self.internal.lock().await;
 
@@ -112,27 +322,77 @@ impl TrackLocalStaticSample {
         }
 
         let clock_rate = internal.clock_rate;
+        let samples = (sample.duration.as_secs_f64() * clock_rate) as u32;
+        let mut skipped_samples = samples.wrapping_mul(sample.prev_dropped_packets as u32);
+
+        // Until a first sample was packetized the timestamp the packetizer gives it is unknown,
+        // it then becomes the epoch
+        if let (Some(capture_time), Some(next_timestamp)) = (capture_time, internal.next_timestamp)
+        {
+            let next_timestamp = next_timestamp.wrapping_add(skipped_samples);
+            let timestamp = internal.timestamp_at(capture_time, next_timestamp);
+            skipped_samples = skipped_samples.wrapping_add(timestamp.wrapping_sub(next_timestamp));
+        }
 
         let packets = if let Some(packetizer) = &mut internal.packetizer {
-            let samples = (sample.duration.as_secs_f64() * clock_rate) as u32;
-            if sample.prev_dropped_packets > 0 {
-                packetizer.skip_samples(samples * sample.prev_dropped_packets as u32);
+            if skipped_samples != 0 {
+                packetizer.skip_samples(skipped_samples);
             }
             packetizer.packetize(&sample.data, samples).await?
         } else {
             vec![]
         };
 
+        let keyframe = packets
+            .first()
+            .map(|p| is_keyframe(&self.rtp_track.codec().mime_type, &p.payload));
+
+        if let Some(p) = packets.first() {
+            let timestamp = p.header.timestamp;
+            internal.last_timestamp = Some(timestamp);
+            internal.next_timestamp = Some(timestamp.wrapping_add(samples));
+            if let (Some(capture_time), None) = (capture_time, internal.clock_epoch) {
+                internal.clock_epoch = Some((capture_time, timestamp));
+            }
+        } else if let Some(next_timestamp) = internal.next_timestamp {
+            // The packetizer advances its timestamp for empty samples as well
+            internal.next_timestamp = Some(
+                next_timestamp
+                    .wrapping_add(skipped_samples)
+                    .wrapping_add(samples),
+            );
+        }
+
+        // The orientation is sent on the last packet of the sample, unless the caller attached
+        // one to all of them
+        let orientation = sample
+            .orientation
+            .filter(|_| {
+                !extensions
+                    .iter()
+                    .any(|e| matches!(e, HeaderExtension::VideoOrientation(_)))
+            })
+            .map(HeaderExtension::VideoOrientation);
+
         let mut write_errs = vec![];
-        for p in packets {
+        let last = packets.len().saturating_sub(1);
+        for (i, p) in packets.into_iter().enumerate() {
+            let orientation = orientation.as_ref().filter(|_| i == last);
             if let Err(err) = self
                 .rtp_track
-                .write_rtp_with_extensions(&p, extensions)
+                .write_rtp_with_extension_iter(&p, extensions.iter().chain(orientation))
                 .await
             {
                 write_errs.push(err);
             }
         }
+        drop(internal);
+
+        if let Some(keyframe) = keyframe {
+            if self.written_sample(keyframe) {
+                self.request_keyframe(KeyframeRequestReason::Periodic).await;
+            }
+        }
 
         flatten_errs(write_errs)
     }
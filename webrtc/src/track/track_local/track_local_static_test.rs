@@ -1,5 +1,7 @@
 use super::{track_local_static_rtp::*, track_local_static_sample::*, *};
-use crate::api::media_engine::{MediaEngine, MIME_TYPE_VP8};
+use crate::api::interceptor_registry::configure_dependency_descriptor;
+use crate::api::media_engine::{MediaEngine, MIME_TYPE_OPUS, MIME_TYPE_VP8};
+use crate::api::setting_engine::SettingEngine;
 use crate::api::APIBuilder;
 use crate::peer_connection::configuration::RTCConfiguration;
 use crate::peer_connection::peer_connection_test::*;
@@ -7,8 +9,25 @@ use crate::rtp_transceiver::rtp_receiver::RTCRtpReceiver;
 use crate::track::track_remote::TrackRemote;
 
 use bytes::Bytes;
+use interceptor::dependency_descriptor::DependencyDescriptorInfo;
+use interceptor::registry::Registry;
+use interceptor::video_orientation::video_orientation_from_attributes;
+use media::io::sample_builder::SampleBuilder;
+use media::Sample;
+use rtcp::payload_feedbacks::full_intra_request::{FirEntry, FullIntraRequest};
+use rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use rtp::extension::dependency_descriptor_extension::{
+    DecodeTargetIndication, DependencyDescriptorExtension, FrameDependencyStructure,
+    FrameDependencyTemplate,
+};
+use rtp::extension::video_orientation_extension::{
+    CameraDirection, VideoOrientationExtension, VideoRotation,
+};
+use rtp::extension::HeaderExtension;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use tokio::sync::{mpsc, Mutex};
+use util::Marshal;
 
 // If a remote doesn't support a Codec used by a `TrackLocalStatic`
 // an error should be returned to the user
@@ -409,6 +428,161 @@ async fn test_track_local_static_binding_non_blocking() -> Result<()> {
     Ok(())
 }
 
+// Assert that CSRCs set on the written packet reach the remote side
+#[tokio::test]
+async fn test_track_local_static_rtp_keeps_csrc() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut pc_offer, mut pc_answer) = new_pair(&api).await?;
+
+    let track = Arc::new(TrackLocalStaticRTP::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    pc_offer
+        .add_track(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>)
+        .await?;
+
+    let (csrc_tx, mut csrc_rx) = mpsc::channel::<Vec<u32>>(1);
+    pc_answer.on_track(Box::new(
+        move |track: Option<Arc<TrackRemote>>, _: Option<Arc<RTCRtpReceiver>>| {
+            let csrc_tx = csrc_tx.clone();
+            Box::pin(async move {
+                if let Some(track) = track {
+                    tokio::spawn(async move {
+                        if let Ok((pkt, _)) = track.read_rtp().await {
+                            let _ = csrc_tx.send(pkt.header.csrc).await;
+                        }
+                    });
+                }
+            })
+        },
+    ));
+
+    signal_pair(&mut pc_offer, &mut pc_answer).await?;
+
+    let pkt = rtp::packet::Packet {
+        header: rtp::header::Header {
+            version: 2,
+            csrc: vec![0x1111, 0x2222, 0x3333],
+            ..Default::default()
+        },
+        payload: Bytes::from_static(&[0x00]),
+    };
+
+    let csrc = loop {
+        track.write_rtp(&pkt).await?;
+        tokio::select! {
+            csrc = csrc_rx.recv() => break csrc,
+            _ = tokio::time::sleep(std::time::Duration::from_millis(20)) => {}
+        }
+    };
+    assert_eq!(csrc, Some(vec![0x1111, 0x2222, 0x3333]));
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_track_local_static_rtp_dependency_descriptor() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let registry = configure_dependency_descriptor(Registry::new(), &mut m)?;
+    let api = APIBuilder::new()
+        .with_media_engine(m)
+        .with_interceptor_registry(registry)
+        .build();
+
+    let (mut pc_offer, mut pc_answer) = new_pair(&api).await?;
+
+    let track = Arc::new(TrackLocalStaticRTP::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    pc_offer
+        .add_track(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>)
+        .await?;
+
+    let (dd_tx, mut dd_rx) = mpsc::channel::<Option<DependencyDescriptorInfo>>(1);
+    pc_answer.on_track(Box::new(
+        move |track: Option<Arc<TrackRemote>>, _: Option<Arc<RTCRtpReceiver>>| {
+            let dd_tx = dd_tx.clone();
+            Box::pin(async move {
+                if let Some(track) = track {
+                    tokio::spawn(async move {
+                        if let Ok((_, attributes)) = track.read_rtp().await {
+                            let _ = dd_tx
+                                .send(DependencyDescriptorInfo::from_attributes(&attributes))
+                                .await;
+                        }
+                    });
+                }
+            })
+        },
+    ));
+
+    signal_pair(&mut pc_offer, &mut pc_answer).await?;
+
+    let pkt = rtp::packet::Packet {
+        header: rtp::header::Header {
+            version: 2,
+            ..Default::default()
+        },
+        payload: Bytes::from_static(&[0x00]),
+    };
+    let dd = DependencyDescriptorExtension {
+        start_of_frame: true,
+        end_of_frame: true,
+        frame_number: 7,
+        attached_structure: Some(FrameDependencyStructure {
+            decode_target_count: 1,
+            templates: vec![FrameDependencyTemplate {
+                decode_target_indications: vec![DecodeTargetIndication::Switch],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let info = loop {
+        track
+            .write_rtp_with_extensions(&pkt, &[HeaderExtension::DependencyDescriptor(dd.clone())])
+            .await?;
+        tokio::select! {
+            info = dd_rx.recv() => break info,
+            _ = tokio::time::sleep(std::time::Duration::from_millis(20)) => {}
+        }
+    };
+    assert_eq!(
+        info,
+        Some(Some(DependencyDescriptorInfo {
+            start_of_frame: true,
+            end_of_frame: true,
+            frame_number: 7,
+            template_id: 0,
+            spatial_id: Some(0),
+            temporal_id: Some(0),
+            active_decode_targets: Some(0b1),
+        }))
+    );
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
 /*
 //TODO: func BenchmarkTrackLocalWrite(b *testing.B) {
     offerPC, answerPC, err := newPair()
@@ -435,3 +609,610 @@ async fn test_track_local_static_binding_non_blocking() -> Result<()> {
     }
 }
 */
+
+/// RecordingWriter keeps the packets written to a track binding
+#[derive(Debug, Default)]
+struct RecordingWriter {
+    packets: Mutex<Vec<rtp::packet::Packet>>,
+}
+
+#[async_trait]
+impl TrackLocalWriter for RecordingWriter {
+    async fn write_rtp(&self, p: &rtp::packet::Packet) -> Result<usize> {
+        self.packets.lock().await.push(p.clone());
+        Ok(p.payload.len())
+    }
+
+    async fn write(&self, mut b: &[u8]) -> Result<usize> {
+        let p = rtp::packet::Packet::unmarshal(&mut b)?;
+        self.write_rtp(&p).await
+    }
+}
+
+#[tokio::test]
+async fn test_track_local_static_sample_capture_timestamps() -> Result<()> {
+    let codec = RTCRtpCodecCapability {
+        mime_type: MIME_TYPE_VP8.to_owned(),
+        clock_rate: 90000,
+        ..Default::default()
+    };
+    let track =
+        TrackLocalStaticSample::new(codec.clone(), "video".to_owned(), "webrtc-rs".to_owned());
+
+    let writer = Arc::new(RecordingWriter::default());
+    track
+        .bind(&TrackLocalContext {
+            id: "binding".to_owned(),
+            params: RTCRtpParameters {
+                codecs: vec![RTCRtpCodecParameters {
+                    capability: codec,
+                    payload_type: 96,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            ssrc: 1234,
+            write_stream: Some(Arc::clone(&writer) as Arc<dyn TrackLocalWriter + Send + Sync>),
+            ..Default::default()
+        })
+        .await?;
+
+    // 30 fps frames delivered in bursts of five, with durations that don't match the frame rate
+    let start = SystemTime::now();
+    let frame_interval = Duration::from_secs(1) / 30;
+    for i in 0..10 {
+        let sample = Sample {
+            data: Bytes::from_static(&[0xAA]),
+            duration: Duration::from_millis(if i % 5 == 4 { 100 } else { 1 }),
+            ..Default::default()
+        };
+        track
+            .write_sample_with_timestamp(&sample, start + frame_interval * i)
+            .await?;
+        if i % 5 == 4 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    // The wall clock jumps back by a second: the timestamp continues from the last duration
+    let sample = Sample {
+        data: Bytes::from_static(&[0xAA]),
+        duration: Duration::from_secs(1) / 30,
+        ..Default::default()
+    };
+    track
+        .write_sample_with_timestamp(&sample, start + frame_interval * 9 - Duration::from_secs(1))
+        .await?;
+    // and frames after it are spaced by their capture time again
+    track
+        .write_sample_with_timestamp(
+            &sample,
+            start + frame_interval * 10 - Duration::from_secs(1),
+        )
+        .await?;
+
+    let packets = writer.packets.lock().await;
+    let timestamps: Vec<u32> = packets.iter().map(|p| p.header.timestamp).collect();
+    assert_eq!(timestamps.len(), 12);
+    for (i, pair) in timestamps.windows(2).enumerate() {
+        let step = pair[1].wrapping_sub(pair[0]);
+        match i {
+            // The duration of the frame before the jump, 100ms
+            9 => assert_eq!(step, 9000, "timestamps {:?}", timestamps),
+            _ => assert_eq!(step, 3000, "timestamps {:?}", timestamps),
+        }
+    }
+
+    Ok(())
+}
+
+fn orientations() -> Vec<VideoOrientationExtension> {
+    [
+        VideoRotation::Degree0,
+        VideoRotation::Degree90,
+        VideoRotation::Degree180,
+        VideoRotation::Degree270,
+    ]
+    .iter()
+    .enumerate()
+    .map(|(i, rotation)| VideoOrientationExtension {
+        direction: CameraDirection::Back,
+        flip: i % 2 == 1,
+        rotation: *rotation,
+    })
+    .collect()
+}
+
+#[tokio::test]
+async fn test_track_local_static_sample_video_orientation() -> Result<()> {
+    let codec = RTCRtpCodecCapability {
+        mime_type: MIME_TYPE_VP8.to_owned(),
+        clock_rate: 90000,
+        ..Default::default()
+    };
+    let track =
+        TrackLocalStaticSample::new(codec.clone(), "video".to_owned(), "webrtc-rs".to_owned());
+
+    let writer = Arc::new(RecordingWriter::default());
+    track
+        .bind(&TrackLocalContext {
+            id: "binding".to_owned(),
+            params: RTCRtpParameters {
+                header_extensions: vec![RTCRtpHeaderExtensionParameters {
+                    uri: sdp::extmap::VIDEO_ORIENTATION_URI.to_owned(),
+                    id: 7,
+                }],
+                codecs: vec![RTCRtpCodecParameters {
+                    capability: codec,
+                    payload_type: 96,
+                    ..Default::default()
+                }],
+            },
+            ssrc: 1234,
+            write_stream: Some(Arc::clone(&writer) as Arc<dyn TrackLocalWriter + Send + Sync>),
+            ..Default::default()
+        })
+        .await?;
+
+    // Frames larger than the MTU, only their last packet carries the orientation
+    for orientation in orientations() {
+        track
+            .write_sample(&Sample {
+                data: Bytes::from(vec![0xAA; 3000]),
+                duration: Duration::from_secs(1) / 30,
+                orientation: Some(orientation),
+                ..Default::default()
+            })
+            .await?;
+    }
+    track
+        .write_sample(&Sample {
+            data: Bytes::from(vec![0xAA; 3000]),
+            duration: Duration::from_secs(1) / 30,
+            ..Default::default()
+        })
+        .await?;
+
+    let packets = writer.packets.lock().await;
+    let mut sent = vec![];
+    for (i, p) in packets.iter().enumerate() {
+        let last_of_frame = packets
+            .get(i + 1)
+            .map_or(true, |next| next.header.timestamp != p.header.timestamp);
+        match p.header.get_extension(7) {
+            Some(mut ext) => {
+                assert!(last_of_frame, "orientation sent before the end of a frame");
+                sent.push(VideoOrientationExtension::unmarshal(&mut ext)?);
+            }
+            None => assert!(!last_of_frame || sent.len() == 4),
+        }
+    }
+    assert_eq!(sent, orientations());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_video_orientation_received_with_samples() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let mut s = SettingEngine::default();
+    s.set_video_orientation(true);
+    let api = APIBuilder::new()
+        .with_media_engine(m)
+        .with_setting_engine(s)
+        .build();
+
+    let (mut pc_offer, mut pc_answer) = new_pair(&api).await?;
+
+    let track = Arc::new(TrackLocalStaticSample::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    pc_offer
+        .add_track(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>)
+        .await?;
+
+    // The orientation of every packet read and of every sample built from them, by frame
+    let (frames_tx, mut frames_rx) = mpsc::channel::<(u8, Option<VideoOrientationExtension>)>(16);
+    let (samples_tx, mut samples_rx) = mpsc::channel::<(u8, Option<VideoOrientationExtension>)>(16);
+    pc_answer.on_track(Box::new(
+        move |track: Option<Arc<TrackRemote>>, receiver: Option<Arc<RTCRtpReceiver>>| {
+            let (frames_tx, samples_tx) = (frames_tx.clone(), samples_tx.clone());
+            Box::pin(async move {
+                let (track, receiver) = match (track, receiver) {
+                    (Some(track), Some(receiver)) => (track, receiver),
+                    _ => return,
+                };
+                let id = receiver
+                    .get_parameters()
+                    .await
+                    .header_extensions
+                    .iter()
+                    .find(|ext| ext.uri == sdp::extmap::VIDEO_ORIENTATION_URI)
+                    .map(|ext| ext.id as u8)
+                    .expect("video orientation should be negotiated");
+
+                tokio::spawn(async move {
+                    let mut builder =
+                        SampleBuilder::new(10, rtp::codecs::vp8::Vp8Packet::default(), 90000)
+                            .with_video_orientation_extension_id(id);
+                    while let Ok((p, attributes)) = track.read_rtp().await {
+                        let frame = *p.payload.last().unwrap();
+                        let orientation = video_orientation_from_attributes(&attributes);
+                        let _ = frames_tx.send((frame, orientation)).await;
+
+                        builder.push(p);
+                        while let Some(sample) = builder.pop() {
+                            let _ = samples_tx
+                                .send((*sample.data.last().unwrap(), sample.orientation))
+                                .await;
+                        }
+                    }
+                });
+            })
+        },
+    ));
+
+    signal_pair(&mut pc_offer, &mut pc_answer).await?;
+
+    // Write the first frame until the track is received, then the rotating ones
+    let write_frame = |frame: u8, orientation: Option<VideoOrientationExtension>| {
+        let track = Arc::clone(&track);
+        async move {
+            track
+                .write_sample(&Sample {
+                    data: Bytes::from(vec![frame]),
+                    duration: Duration::from_secs(1) / 30,
+                    orientation,
+                    ..Default::default()
+                })
+                .await
+        }
+    };
+    let orientations = orientations();
+    let first = loop {
+        write_frame(0, Some(orientations[0])).await?;
+        tokio::select! {
+            frame = frames_rx.recv() => break frame,
+            _ = tokio::time::sleep(Duration::from_millis(20)) => {}
+        }
+    };
+    assert_eq!(first, Some((0, Some(orientations[0]))));
+    for (i, orientation) in orientations.iter().enumerate().skip(1) {
+        write_frame(i as u8, Some(*orientation)).await?;
+    }
+    // Frames without orientation keep the most recent one
+    write_frame(4, None).await?;
+    write_frame(5, None).await?;
+
+    let mut frames = vec![];
+    while frames.len() < 5 {
+        let (frame, orientation) = frames_rx.recv().await.unwrap();
+        if frame != 0 {
+            frames.push((frame, orientation));
+        }
+    }
+    let expected = vec![
+        (1, Some(orientations[1])),
+        (2, Some(orientations[2])),
+        (3, Some(orientations[3])),
+        (4, Some(orientations[3])),
+        (5, Some(orientations[3])),
+    ];
+    assert_eq!(frames, expected);
+
+    // A sample is built once the first packet of the next frame arrived
+    let mut samples = vec![];
+    while samples.len() < 4 {
+        let (frame, orientation) = samples_rx.recv().await.unwrap();
+        if frame != 0 {
+            samples.push((frame, orientation));
+        }
+    }
+    assert_eq!(samples, expected[..4]);
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_track_local_static_rtp_write_rtp_raw_invalid() -> Result<()> {
+    let track = TrackLocalStaticRTP::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    );
+
+    assert!(track.write_rtp_raw(&[0x80, 0x60, 0x00]).await.is_err());
+
+    let mut raw = rtp::packet::Packet {
+        header: rtp::header::Header {
+            version: 1,
+            ..Default::default()
+        },
+        payload: Bytes::from_static(&[0x10, 0x00, 0x00, 0x00]),
+    }
+    .marshal()?
+    .to_vec();
+    assert!(matches!(
+        track.write_rtp_raw(&raw).await,
+        Err(Error::ErrRTPUnsupportedVersion)
+    ));
+
+    // Without bindings, a valid packet isn't sent anywhere
+    raw[0] = (raw[0] & 0x3F) | 0x80;
+    assert_eq!(track.write_rtp_raw(&raw).await?, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_track_local_static_rtp_write_rtp_raw_patches_bindings() -> Result<()> {
+    let codec = RTCRtpCodecCapability {
+        mime_type: MIME_TYPE_OPUS.to_owned(),
+        clock_rate: 48000,
+        ..Default::default()
+    };
+    let track = TrackLocalStaticRTP::new(codec.clone(), "audio".to_owned(), "webrtc-rs".to_owned());
+
+    let mut writers = vec![];
+    for (ssrc, payload_type) in [(1234, 111), (5678, 96)] {
+        let writer = Arc::new(RecordingWriter::default());
+        track
+            .bind(&TrackLocalContext {
+                id: format!("binding-{}", ssrc),
+                params: RTCRtpParameters {
+                    codecs: vec![RTCRtpCodecParameters {
+                        capability: codec.clone(),
+                        payload_type,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                ssrc,
+                write_stream: Some(Arc::clone(&writer) as Arc<dyn TrackLocalWriter + Send + Sync>),
+                ..Default::default()
+            })
+            .await?;
+        writers.push((ssrc, payload_type, writer));
+    }
+
+    let mut header = rtp::header::Header {
+        version: 2,
+        marker: true,
+        payload_type: 100,
+        sequence_number: 4000,
+        timestamp: 160_000,
+        ssrc: 42,
+        csrc: vec![7, 8],
+        ..Default::default()
+    };
+    header.set_extension(1, Bytes::from_static(&[0x30]))?;
+    let raw = rtp::packet::Packet {
+        header,
+        payload: Bytes::from_static(&[0xAA, 0xBB]),
+    }
+    .marshal()?;
+
+    assert_eq!(track.write_rtp_raw(&raw).await?, 4);
+
+    // Only the SSRC and payload type differ between the bindings
+    for (ssrc, payload_type, writer) in writers {
+        let packets = writer.packets.lock().await;
+        assert_eq!(packets.len(), 1);
+        let pkt = &packets[0];
+        assert_eq!(pkt.header.ssrc, ssrc);
+        assert_eq!(pkt.header.payload_type, payload_type);
+        assert!(pkt.header.marker);
+        assert_eq!(pkt.header.sequence_number, 4000);
+        assert_eq!(pkt.header.timestamp, 160_000);
+        assert_eq!(pkt.header.csrc, vec![7, 8]);
+        assert_eq!(
+            pkt.header.get_extension(1),
+            Some(Bytes::from_static(&[0x30]))
+        );
+        assert_eq!(pkt.payload, Bytes::from_static(&[0xAA, 0xBB]));
+    }
+
+    Ok(())
+}
+
+fn vp8_frame(keyframe: bool) -> Sample {
+    Sample {
+        // The P bit of the frame tag is cleared in keyframes
+        data: Bytes::from(vec![if keyframe { 0x00 } else { 0x01 }, 0xAA]),
+        duration: Duration::from_secs(1) / 30,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_track_local_static_sample_keyframe_requests() -> Result<()> {
+    let codec = RTCRtpCodecCapability {
+        mime_type: MIME_TYPE_VP8.to_owned(),
+        clock_rate: 90000,
+        ..Default::default()
+    };
+    let track =
+        TrackLocalStaticSample::new(codec.clone(), "video".to_owned(), "webrtc-rs".to_owned());
+    track
+        .bind(&TrackLocalContext {
+            id: "binding".to_owned(),
+            params: RTCRtpParameters {
+                codecs: vec![RTCRtpCodecParameters {
+                    capability: codec,
+                    payload_type: 96,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            ssrc: 1234,
+            write_stream: Some(
+                Arc::new(RecordingWriter::default()) as Arc<dyn TrackLocalWriter + Send + Sync>
+            ),
+            ..Default::default()
+        })
+        .await?;
+
+    let requests = Arc::new(Mutex::new(vec![]));
+    let requests2 = Arc::clone(&requests);
+    track.on_keyframe_request(Box::new(move |reason: KeyframeRequestReason| {
+        let requests2 = Arc::clone(&requests2);
+        Box::pin(async move {
+            requests2.lock().await.push(reason);
+        })
+    }));
+    track.set_keyframe_request_window(Duration::from_millis(100));
+
+    // A burst of requests is passed on once
+    for _ in 0..5 {
+        track
+            .request_keyframe(KeyframeRequestReason::PictureLoss)
+            .await;
+    }
+    assert_eq!(
+        *requests.lock().await,
+        vec![KeyframeRequestReason::PictureLoss]
+    );
+
+    // The request stays pending until a keyframe is written
+    assert!(track.force_keyframe_pending());
+    track.write_sample(&vp8_frame(false)).await?;
+    assert!(track.force_keyframe_pending());
+    track.write_sample(&vp8_frame(true)).await?;
+    assert!(!track.force_keyframe_pending());
+
+    tokio::time::sleep(Duration::from_millis(120)).await;
+    track
+        .request_keyframe(KeyframeRequestReason::FullIntraRequest)
+        .await;
+    assert!(track.force_keyframe_pending());
+    track.write_sample(&vp8_frame(true)).await?;
+
+    // Without keyframes a periodic request is made once, until a keyframe is written
+    track.set_periodic_keyframe_interval(Some(Duration::from_millis(100)));
+    for _ in 0..15 {
+        track.write_sample(&vp8_frame(false)).await?;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(
+        *requests.lock().await,
+        vec![
+            KeyframeRequestReason::PictureLoss,
+            KeyframeRequestReason::FullIntraRequest,
+            KeyframeRequestReason::Periodic,
+        ]
+    );
+    assert!(track.force_keyframe_pending());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_track_local_static_sample_keyframe_requests_from_pli() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut pc_offer, mut pc_answer) = new_pair(&api).await?;
+
+    let track = Arc::new(TrackLocalStaticSample::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    let window = Duration::from_millis(200);
+    track.set_keyframe_request_window(window);
+    let (requests_tx, mut requests_rx) = mpsc::unbounded_channel();
+    track.on_keyframe_request(Box::new(move |reason: KeyframeRequestReason| {
+        let _ = requests_tx.send((reason, tokio::time::Instant::now()));
+        Box::pin(async {})
+    }));
+
+    let sender = pc_offer
+        .add_track(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>)
+        .await?;
+    tokio::spawn(async move { while sender.read_rtcp().await.is_ok() {} });
+
+    let (ssrc_tx, mut ssrc_rx) = mpsc::channel(1);
+    pc_answer.on_track(Box::new(
+        move |track: Option<Arc<TrackRemote>>, _: Option<Arc<RTCRtpReceiver>>| {
+            if let Some(track) = track {
+                let _ = ssrc_tx.try_send(track.ssrc());
+            }
+            Box::pin(async {})
+        },
+    ));
+
+    signal_pair(&mut pc_offer, &mut pc_answer).await?;
+
+    let ssrc = loop {
+        track.write_sample(&vp8_frame(true)).await?;
+        tokio::select! {
+            ssrc = ssrc_rx.recv() => break ssrc.unwrap(),
+            _ = tokio::time::sleep(Duration::from_millis(20)) => {}
+        }
+    };
+
+    // A PLI every 10ms, and FIRs for another stream
+    let started = tokio::time::Instant::now();
+    for _ in 0..70 {
+        pc_answer
+            .write_rtcp(&[
+                Box::new(PictureLossIndication {
+                    sender_ssrc: 0,
+                    media_ssrc: ssrc,
+                }),
+                Box::new(FullIntraRequest {
+                    sender_ssrc: 0,
+                    media_ssrc: ssrc,
+                    fir: vec![FirEntry {
+                        ssrc: ssrc.wrapping_add(1),
+                        sequence_number: 0,
+                    }],
+                }),
+            ])
+            .await?;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    // The loop takes longer than 700ms on a loaded machine, leaving room for more windows
+    let max_requests = (started.elapsed().as_millis() / window.as_millis()) as usize + 1;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mut requests = vec![];
+    while let Ok(request) = requests_rx.try_recv() {
+        requests.push(request);
+    }
+    assert!(
+        (2..=max_requests).contains(&requests.len()),
+        "{} keyframe requests",
+        requests.len()
+    );
+    for (i, (reason, at)) in requests.iter().enumerate() {
+        assert_eq!(*reason, KeyframeRequestReason::PictureLoss);
+        if i > 0 {
+            let since_previous = at.duration_since(requests[i - 1].1);
+            assert!(
+                since_previous + Duration::from_millis(10) >= window,
+                "keyframe requests {:?} apart",
+                since_previous
+            );
+        }
+    }
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
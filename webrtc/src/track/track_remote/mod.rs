@@ -1,3 +1,8 @@
+#[cfg(test)]
+mod track_remote_test;
+
+pub mod track_remote_subscriber;
+
 use crate::api::media_engine::MediaEngine;
 use crate::error::{Error, Result};
 use crate::rtp_transceiver::rtp_codec::{RTCRtpCodecParameters, RTCRtpParameters, RTPCodecType};
@@ -7,15 +12,20 @@ use crate::RECEIVE_MTU;
 use crate::rtp_transceiver::rtp_receiver::RTPReceiverInternal;
 
 use crate::track::RTP_PAYLOAD_TYPE_BITMASK;
+use track_remote_subscriber::{TrackRemoteSubscriber, TrackRemoteSubscribers};
+
 use bytes::{Bytes, BytesMut};
 use interceptor::{Attributes, Interceptor};
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::atomic::{AtomicU32, AtomicU8, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, AtomicUsize, Ordering};
 use std::sync::{Arc, Weak};
 use tokio::sync::Mutex;
 use util::Unmarshal;
 
+/// Packets queued for the reads of the track itself once it has subscribers
+const TRACK_REMOTE_READ_QUEUE_SIZE: usize = 1024;
+
 lazy_static! {
     static ref TRACK_REMOTE_UNIQUE_ID: AtomicUsize = AtomicUsize::new(0);
 }
@@ -54,9 +64,16 @@ pub struct TrackRemote {
     interceptor: Arc<dyn Interceptor + Send + Sync>,
 
     handlers: Mutex<Handlers>,
+    muted: AtomicBool,
 
     receiver: Option<Weak<RTPReceiverInternal>>,
     internal: Mutex<TrackRemoteInternal>,
+
+    // Once subscribe was called, packets are read from the receiver by a single task
+    // and the reads of the track itself go through one of the subscribers
+    subscribers: TrackRemoteSubscribers,
+    subscribed: AtomicBool,
+    read_subscriber: Mutex<Option<TrackRemoteSubscriber>>,
 }
 
 impl std::fmt::Debug for TrackRemote {
@@ -99,8 +116,13 @@ impl TrackRemote {
             media_engine,
             interceptor,
             handlers: Default::default(),
+            muted: AtomicBool::new(true),
 
             internal: Default::default(),
+
+            subscribers: Default::default(),
+            subscribed: AtomicBool::new(false),
+            read_subscriber: Default::default(),
         }
     }
 
@@ -171,7 +193,9 @@ impl TrackRemote {
         self.stream_id().await + " " + self.id().await.as_str()
     }
 
-    /// codec gets the Codec of the track
+    /// codec gets the Codec of the track. It's known once a remote description is applied: the
+    /// preferred negotiated codec at first, then the codec of the packets received. The codecs
+    /// negotiated for the track are available from `RTCRtpReceiver::get_parameters`.
     pub async fn codec(&self) -> RTCRtpCodecParameters {
         let codec = self.codec.lock().await;
         codec.clone()
@@ -192,6 +216,14 @@ impl TrackRemote {
         *p = params;
     }
 
+    /// muted returns true while the track doesn't receive media, either because the
+    /// remote stopped sending through renegotiation or no RTP arrived within the
+    /// SettingEngine's track inactivity timeout. Tracks start muted.
+    pub fn muted(&self) -> bool {
+        self.muted.load(Ordering::SeqCst)
+    }
+
+    /// onmute sets a handler that is called when the track becomes muted
     pub async fn onmute<F>(&self, handler: F)
     where
         F: FnMut() -> Pin<Box<dyn Future<Output = ()> + Send + 'static>> + Send + 'static + Sync,
@@ -200,6 +232,7 @@ impl TrackRemote {
         handlers.on_mute = Some(Box::new(handler));
     }
 
+    /// onunmute sets a handler that is called when the track is unmuted
     pub async fn onunmute<F>(&self, handler: F)
     where
         F: FnMut() -> Pin<Box<dyn Future<Output = ()> + Send + 'static>> + Send + 'static + Sync,
@@ -208,8 +241,103 @@ impl TrackRemote {
         handlers.on_unmute = Some(Box::new(handler));
     }
 
+    /// subscribe returns a [`TrackRemoteSubscriber`] reading every packet of this track from now
+    /// on, independent of the other readers of the track. Without subscribers, concurrent reads of
+    /// the track each get some of the packets.
+    ///
+    /// Up to capacity packets are queued for the subscriber, further packets are dropped until it
+    /// catches up. Once the track has subscribers, `read` and `read_rtp` of the track are served
+    /// the same way, from a queue that can't slow down the subscribers either.
+    pub async fn subscribe(self: &Arc<Self>, capacity: usize) -> TrackRemoteSubscriber {
+        let subscriber = self.subscribers.add(capacity);
+
+        if !self.subscribed.swap(true, Ordering::SeqCst) {
+            {
+                let mut read_subscriber = self.read_subscriber.lock().await;
+                *read_subscriber = Some(self.subscribers.add(TRACK_REMOTE_READ_QUEUE_SIZE));
+            }
+
+            let track = Arc::downgrade(self);
+            let fan_out = async move {
+                TrackRemote::fan_out(track).await;
+            };
+            // the receiver waits for the fan out when it stops
+            match self.receiver.as_ref().and_then(|r| r.upgrade()) {
+                Some(receiver) => receiver.tasks.spawn(fan_out),
+                None => {
+                    tokio::spawn(fan_out);
+                }
+            }
+        }
+
+        subscriber
+    }
+
+    /// serve_deliveries makes the reads of the track and of its subscribers get the packets
+    /// passed to `deliver` instead of the packets of the receiver.
+    pub(crate) async fn serve_deliveries(&self) {
+        self.subscribed.store(true, Ordering::SeqCst);
+        let mut read_subscriber = self.read_subscriber.lock().await;
+        *read_subscriber = Some(self.subscribers.add(TRACK_REMOTE_READ_QUEUE_SIZE));
+    }
+
+    pub(crate) fn deliver(&self, data: Bytes, attributes: &Attributes) {
+        self.subscribers.deliver(data, attributes);
+    }
+
+    pub(crate) fn close_subscribers(&self) {
+        self.subscribers.close();
+    }
+
+    /// fan_out reads the packets of the track from the receiver and delivers them to the
+    /// subscribers until the receiver stops.
+    async fn fan_out(track: Weak<TrackRemote>) {
+        loop {
+            let track = match track.upgrade() {
+                Some(track) => track,
+                None => return,
+            };
+
+            let mut b = vec![0u8; track.receive_mtu];
+            let (n, attributes) = match track.read_from_receiver(&mut b).await {
+                Ok(result) => result,
+                Err(err) => {
+                    log::debug!(
+                        "track {} stops delivering to subscribers: {}",
+                        track.tid,
+                        err
+                    );
+                    track.subscribers.close();
+                    return;
+                }
+            };
+            if let Err(err) = track.check_and_update_track(&b[..n]).await {
+                log::warn!("track {} dropping packet: {}", track.tid, err);
+                continue;
+            }
+
+            b.truncate(n);
+            track.subscribers.deliver(Bytes::from(b), &attributes);
+        }
+    }
+
     /// Read reads data from the track.
     pub async fn read(&self, b: &mut [u8]) -> Result<(usize, Attributes)> {
+        {
+            let mut read_subscriber = self.read_subscriber.lock().await;
+            if let Some(read_subscriber) = &mut *read_subscriber {
+                return read_subscriber.read(b).await;
+            }
+        }
+
+        let (n, attributes) = self.read_from_receiver(b).await?;
+        self.check_and_update_track(&b[..n]).await?;
+        Ok((n, attributes))
+    }
+
+    /// read_from_receiver reads the next packet from the receiver, or the packet
+    /// determine_payload_type peeked at.
+    async fn read_from_receiver(&self, b: &mut [u8]) -> Result<(usize, Attributes)> {
         let (peeked, peeked_attributes) = {
             let mut internal = self.internal.lock().await;
             (internal.peeked.take(), internal.peeked_attributes.take())
@@ -220,22 +348,15 @@ impl TrackRemote {
             // released the lock.  Deal with it.
             let n = std::cmp::min(b.len(), data.len());
             b[..n].copy_from_slice(&data[..n]);
-            self.check_and_update_track(&b[..n]).await?;
             Ok((n, attributes))
+        } else if let Some(receiver) = &self.receiver {
+            if let Some(receiver) = receiver.upgrade() {
+                receiver.read_rtp(b, self.tid).await
+            } else {
+                Err(Error::ErrRTPReceiverNil)
+            }
         } else {
-            let (n, attributes) = {
-                if let Some(receiver) = &self.receiver {
-                    if let Some(receiver) = receiver.upgrade() {
-                        receiver.read_rtp(b, self.tid).await?
-                    } else {
-                        return Err(Error::ErrRTPReceiverNil);
-                    }
-                } else {
-                    return Err(Error::ErrRTPReceiverNil);
-                }
-            };
-            self.check_and_update_track(&b[..n]).await?;
-            Ok((n, attributes))
+            Err(Error::ErrRTPReceiverNil)
         }
     }
 
@@ -248,10 +369,18 @@ impl TrackRemote {
 
         let payload_type = b[1] & RTP_PAYLOAD_TYPE_BITMASK;
         if payload_type != self.payload_type() {
-            let p = self
-                .media_engine
-                .get_rtp_parameters_by_payload_type(payload_type)
-                .await?;
+            let negotiated = match self.receiver.as_ref().and_then(|r| r.upgrade()) {
+                Some(receiver) => receiver.get_parameters_by_payload_type(payload_type).await,
+                None => None,
+            };
+            let p = match negotiated {
+                Some(p) => p,
+                None => {
+                    self.media_engine
+                        .get_rtp_parameters_by_payload_type(payload_type)
+                        .await?
+                }
+            };
 
             if let Some(receiver) = &self.receiver {
                 if let Some(receiver) = receiver.upgrade() {
@@ -276,6 +405,31 @@ impl TrackRemote {
         Ok(())
     }
 
+    /// set_negotiated_parameters sets the codec of the track from the codecs negotiated for its
+    /// receiver, before any packet arrived or after renegotiation. The codec of the packets
+    /// received so far is kept while it's still negotiated, otherwise the preferred codec is
+    /// assumed until a packet with another payload type arrives.
+    pub(crate) async fn set_negotiated_parameters(&self, parameters: &RTCRtpParameters) {
+        let codec = match parameters
+            .codecs
+            .iter()
+            .find(|c| c.payload_type == self.payload_type())
+            .or_else(|| parameters.codecs.first())
+        {
+            Some(codec) => codec.clone(),
+            None => return,
+        };
+
+        self.payload_type
+            .store(codec.payload_type, Ordering::SeqCst);
+        self.set_params(RTCRtpParameters {
+            header_extensions: parameters.header_extensions.clone(),
+            codecs: vec![codec.clone()],
+        })
+        .await;
+        self.set_codec(codec).await;
+    }
+
     /// read_rtp is a convenience method that wraps Read and unmarshals for you.
     pub async fn read_rtp(&self) -> Result<(rtp::packet::Packet, Attributes)> {
         let mut b = vec![0u8; self.receive_mtu];
@@ -302,7 +456,8 @@ impl TrackRemote {
 
     /// peek is like Read, but it doesn't discard the packet read
     pub(crate) async fn peek(&self, b: &mut [u8]) -> Result<(usize, Attributes)> {
-        let (n, a) = self.read(b).await?;
+        let (n, a) = self.read_from_receiver(b).await?;
+        self.check_and_update_track(&b[..n]).await?;
 
         // this might overwrite data if somebody peeked between the Read
         // and us getting the lock.  Oh well, we'll just drop a packet in
@@ -317,6 +472,19 @@ impl TrackRemote {
         Ok((n, a))
     }
 
+    /// set_muted updates the muted state and fires onmute/onunmute when it changes
+    pub(crate) async fn set_muted(&self, muted: bool) {
+        if self.muted.swap(muted, Ordering::SeqCst) == muted {
+            return;
+        }
+
+        if muted {
+            self.fire_onmute().await;
+        } else {
+            self.fire_onunmute().await;
+        }
+    }
+
     pub(crate) async fn fire_onmute(&self) {
         let mut handlers = self.handlers.lock().await;
 
@@ -0,0 +1,97 @@
+use crate::error::{Error, Result};
+
+use bytes::Bytes;
+use interceptor::Attributes;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use util::sync::Mutex as SyncMutex;
+use util::Unmarshal;
+
+/// TrackRemoteSubscriber is an independent stream of the RTP packets of a
+/// [`TrackRemote`](super::TrackRemote), returned by `TrackRemote::subscribe`.
+///
+/// Every subscriber of a track reads every packet received after it subscribed. Packets are
+/// queued up to the capacity the subscriber was created with, a subscriber that falls behind
+/// never slows down the others, packets that don't fit into its queue are dropped and counted
+/// instead. The subscriber is removed once it is dropped.
+pub struct TrackRemoteSubscriber {
+    rx: mpsc::Receiver<(Bytes, Attributes)>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl TrackRemoteSubscriber {
+    /// read reads the next packet of the track into b. It fails with `Error::ErrClosedPipe`
+    /// once the track stopped receiving.
+    pub async fn read(&mut self, b: &mut [u8]) -> Result<(usize, Attributes)> {
+        let (data, attributes) = self.rx.recv().await.ok_or(Error::ErrClosedPipe)?;
+
+        let n = std::cmp::min(b.len(), data.len());
+        b[..n].copy_from_slice(&data[..n]);
+        Ok((n, attributes))
+    }
+
+    /// read_rtp is a convenience method that wraps Read and unmarshals for you.
+    /// The packets can be pushed into a `media::io::sample_builder::SampleBuilder`
+    /// to consume the track sample by sample.
+    pub async fn read_rtp(&mut self) -> Result<(rtp::packet::Packet, Attributes)> {
+        let (data, attributes) = self.rx.recv().await.ok_or(Error::ErrClosedPipe)?;
+
+        let mut buf = &data[..];
+        let pkt = rtp::packet::Packet::unmarshal(&mut buf)?;
+        Ok((pkt, attributes))
+    }
+
+    /// dropped returns how many packets were dropped because the queue of this subscriber was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::SeqCst)
+    }
+}
+
+struct SubscriberSender {
+    tx: mpsc::Sender<(Bytes, Attributes)>,
+    dropped: Arc<AtomicU64>,
+}
+
+/// TrackRemoteSubscribers are the subscribers of a track
+#[derive(Default)]
+pub(crate) struct TrackRemoteSubscribers {
+    subscribers: SyncMutex<Vec<SubscriberSender>>,
+}
+
+impl TrackRemoteSubscribers {
+    pub(crate) fn add(&self, capacity: usize) -> TrackRemoteSubscriber {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        let dropped = Arc::new(AtomicU64::new(0));
+
+        let mut subscribers = self.subscribers.lock();
+        subscribers.push(SubscriberSender {
+            tx,
+            dropped: Arc::clone(&dropped),
+        });
+
+        TrackRemoteSubscriber { rx, dropped }
+    }
+
+    /// deliver queues the packet to every subscriber without waiting for any of them.
+    pub(crate) fn deliver(&self, data: Bytes, attributes: &Attributes) {
+        let mut subscribers = self.subscribers.lock();
+        subscribers.retain(|subscriber| {
+            match subscriber.tx.try_send((data.clone(), attributes.clone())) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    subscriber.dropped.fetch_add(1, Ordering::SeqCst);
+                    true
+                }
+                Err(TrySendError::Closed(_)) => false,
+            }
+        });
+    }
+
+    /// close removes all subscribers, they read the packets still queued and then fail.
+    pub(crate) fn close(&self) {
+        let mut subscribers = self.subscribers.lock();
+        subscribers.clear();
+    }
+}
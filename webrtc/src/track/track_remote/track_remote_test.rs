@@ -0,0 +1,131 @@
+use super::*;
+use crate::api::media_engine::MIME_TYPE_VP8;
+use crate::api::APIBuilder;
+use crate::peer_connection::peer_connection_test::{close_pair_now, new_pair, signal_pair};
+use crate::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
+use crate::rtp_transceiver::rtp_receiver::RTCRtpReceiver;
+use crate::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
+use crate::track::track_local::{TrackLocal, TrackLocalWriter};
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+
+/// rtp_packet builds a packet of the track written in the subscriber tests
+fn rtp_packet(sequence_number: u16) -> rtp::packet::Packet {
+    rtp::packet::Packet {
+        header: rtp::header::Header {
+            version: 2,
+            sequence_number,
+            timestamp: sequence_number as u32 * 3000,
+            ..Default::default()
+        },
+        payload: Bytes::from_static(&[0xAA]),
+    }
+}
+
+#[tokio::test]
+async fn test_track_remote_subscribers() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut sender, mut receiver) = new_pair(&api).await?;
+
+    let track = Arc::new(TrackLocalStaticRTP::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    sender
+        .add_track(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>)
+        .await?;
+
+    let (track_tx, mut track_rx) = mpsc::channel(1);
+    receiver.on_track(Box::new(
+        move |track_remote: Option<Arc<TrackRemote>>, _: Option<Arc<RTCRtpReceiver>>| {
+            let track_tx = track_tx.clone();
+            Box::pin(async move {
+                if let Some(track_remote) = track_remote {
+                    let _ = track_tx.send(track_remote).await;
+                }
+            })
+        },
+    ));
+
+    signal_pair(&mut sender, &mut receiver).await?;
+
+    let mut sequence_number = 0;
+    let track_remote = loop {
+        track.write_rtp(&rtp_packet(sequence_number)).await?;
+        sequence_number += 1;
+
+        tokio::select! {
+            result = track_rx.recv() => break result.unwrap(),
+            _ = tokio::time::sleep(Duration::from_millis(20)) => {}
+        }
+    };
+
+    let mut fast = track_remote.subscribe(256).await;
+    let mut slow = track_remote.subscribe(4).await;
+
+    let first = sequence_number;
+    let count = 50;
+    for i in 0..count {
+        track.write_rtp(&rtp_packet(first + i)).await?;
+        tokio::time::sleep(Duration::from_millis(2)).await;
+    }
+
+    // The fast subscriber reads every packet written after it subscribed, in order
+    let mut read = vec![];
+    while read.len() < count as usize {
+        let (pkt, _) = tokio::time::timeout(Duration::from_secs(5), fast.read_rtp())
+            .await
+            .expect("timed out waiting for subscriber read")?;
+        if pkt.header.sequence_number >= first {
+            assert_eq!(pkt.payload, Bytes::from_static(&[0xAA]));
+            read.push(pkt.header.sequence_number);
+        }
+    }
+    assert_eq!(read, (first..first + count).collect::<Vec<u16>>());
+    assert_eq!(fast.dropped(), 0);
+
+    // while the slow one, which didn't read at all, only kept what fits into its queue
+    assert!(slow.dropped() > 0, "slow subscriber didn't drop packets");
+    let mut queued = 0;
+    while tokio::time::timeout(Duration::from_millis(100), slow.read_rtp())
+        .await
+        .is_ok()
+    {
+        queued += 1;
+    }
+    assert!(queued <= 4, "slow subscriber read {} packets", queued);
+
+    // The track itself still reads packets once it has subscribers
+    track.write_rtp(&rtp_packet(first + count)).await?;
+    loop {
+        let (pkt, _) = tokio::time::timeout(Duration::from_secs(5), track_remote.read_rtp())
+            .await
+            .expect("timed out waiting for track read")?;
+        if pkt.header.sequence_number == first + count {
+            break;
+        }
+    }
+
+    close_pair_now(&sender, &receiver).await;
+
+    // Subscribers fail once the receiver stopped
+    let result = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Err(err) = fast.read_rtp().await {
+                return err;
+            }
+        }
+    })
+    .await
+    .expect("subscriber didn't stop with the receiver");
+    assert_eq!(result, Error::ErrClosedPipe);
+
+    Ok(())
+}